@@ -1,12 +1,20 @@
+import 'dart:convert';
+
 import 'package:dartz/dartz.dart';
+import 'package:appflowy_backend/ffi.dart';
 import 'package:appflowy_backend/dispatch/dispatch.dart';
 
 import 'package:appflowy_backend/protobuf/flowy-folder/view.pb.dart';
 import 'package:appflowy_backend/protobuf/flowy-error/errors.pb.dart';
 import 'package:appflowy_backend/protobuf/flowy-document/entities.pb.dart';
 
+/// Decodes the json of the document from its utf8 bytes without building the
+/// string of the whole document first.
+final _jsonUtf8 = json.fuse(utf8);
+
 class DocumentService {
-  Future<Either<DocumentSnapshotPB, FlowyError>> openDocument({
+  /// Returns the decoded json of the document.
+  Future<Either<Map<String, dynamic>, FlowyError>> openDocument({
     required ViewPB view,
   }) async {
     await FolderEventSetLatestView(ViewIdPB(value: view.id)).send();
@@ -22,16 +30,42 @@ class DocumentService {
     //     break;
     // }
 
-    return DocumentEventGetDocument(payload).send();
+    final result = await DocumentEventGetDocument(payload).send();
+    return result.fold<Future<Either<Map<String, dynamic>, FlowyError>>>(
+      (snapshot) => _decodeSnapshot(snapshot),
+      (error) async => right(error),
+    );
+  }
+
+  Future<Either<Map<String, dynamic>, FlowyError>> _decodeSnapshot(
+    DocumentSnapshotPB snapshot,
+  ) async {
+    if (!snapshot.hasReader()) {
+      return left(jsonDecode(snapshot.snapshot) as Map<String, dynamic>);
+    }
+    // The large documents come with a reader instead of the snapshot.
+    try {
+      final bytes = await readDocumentChunks(
+        snapshot.reader.handle.toInt(),
+        snapshot.reader.len.toInt(),
+      );
+      return left(_jsonUtf8.decode(bytes) as Map<String, dynamic>);
+    } catch (e) {
+      return right(FlowyError.create()..msg = e.toString());
+    }
   }
 
+  /// Set [stripAllAttributes] to paste without formatting, the attributes of
+  /// the [operations] are dropped before they're applied.
   Future<Either<Unit, FlowyError>> applyEdit({
     required String docId,
     required String operations,
+    bool stripAllAttributes = false,
   }) {
     final payload = EditPayloadPB.create()
       ..docId = docId
-      ..operations = operations;
+      ..operations = operations
+      ..stripAllAttributes = stripAllAttributes;
     return DocumentEventApplyEdit(payload).send();
   }
 
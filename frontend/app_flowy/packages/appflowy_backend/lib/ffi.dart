@@ -2,6 +2,8 @@
 
 import 'dart:ffi';
 import 'dart:io';
+import 'dart:isolate';
+import 'dart:typed_data';
 // ignore: import_of_legacy_library_into_null_safe
 import 'package:ffi/ffi.dart' as ffi;
 import 'package:flutter/foundation.dart' as Foundation;
@@ -107,6 +109,117 @@ typedef _set_stream_port_Dart = int Function(
   int port,
 );
 
+/// C function `ack_notifications`.
+int ack_notifications(int count) {
+  return _ack_notifications(count);
+}
+
+final _ack_notifications_Dart _ack_notifications =
+    _dart_ffi_lib.lookupFunction<_ack_notifications_C, _ack_notifications_Dart>(
+        'ack_notifications');
+
+typedef _ack_notifications_C = Int32 Function(
+  Int64 count,
+);
+typedef _ack_notifications_Dart = int Function(
+  int count,
+);
+
+/// C function `advance_event_generation`.
+int advance_event_generation(int generation) {
+  return _advance_event_generation(generation);
+}
+
+final _advance_event_generation_Dart _advance_event_generation =
+    _dart_ffi_lib.lookupFunction<_advance_event_generation_C,
+        _advance_event_generation_Dart>('advance_event_generation');
+
+typedef _advance_event_generation_C = Int32 Function(
+  Int64 generation,
+);
+typedef _advance_event_generation_Dart = int Function(
+  int generation,
+);
+
+/// C function `appflowy_doc_read_chunk`.
+int appflowy_doc_read_chunk(
+  int handle,
+  Pointer<Uint8> buf,
+  int len,
+) {
+  return _appflowy_doc_read_chunk(handle, buf, len);
+}
+
+final _appflowy_doc_read_chunk_Dart _appflowy_doc_read_chunk =
+    _dart_ffi_lib.lookupFunction<_appflowy_doc_read_chunk_C,
+        _appflowy_doc_read_chunk_Dart>('appflowy_doc_read_chunk');
+typedef _appflowy_doc_read_chunk_C = Int64 Function(
+  Int64 handle,
+  Pointer<Uint8> buf,
+  Uint64 len,
+);
+typedef _appflowy_doc_read_chunk_Dart = int Function(
+  int handle,
+  Pointer<Uint8> buf,
+  int len,
+);
+
+/// C function `appflowy_doc_close_reader`.
+int appflowy_doc_close_reader(
+  int handle,
+) {
+  return _appflowy_doc_close_reader(handle);
+}
+
+final _appflowy_doc_close_reader_Dart _appflowy_doc_close_reader =
+    _dart_ffi_lib.lookupFunction<_appflowy_doc_close_reader_C,
+        _appflowy_doc_close_reader_Dart>('appflowy_doc_close_reader');
+typedef _appflowy_doc_close_reader_C = Int32 Function(
+  Int64 handle,
+);
+typedef _appflowy_doc_close_reader_Dart = int Function(
+  int handle,
+);
+
+/// Reads the `len` bytes of the document reader with the `handle` to the end.
+/// The reader is read on a background isolate, the UI isn't blocked by a large
+/// document. Throws if the reader fails, the reader is closed then.
+Future<Uint8List> readDocumentChunks(int handle, int len) async {
+  final bytes = await Foundation.compute(_readDocumentChunks, [handle, len]);
+  return bytes.materialize().asUint8List();
+}
+
+/// The chunks are read into one native buffer, its bytes are copied once into
+/// the data that is moved to the calling isolate.
+TransferableTypedData _readDocumentChunks(List<int> args) {
+  const chunkSize = 64 * 1024;
+  final handle = args[0];
+  final len = args[1];
+  final buf = ffi.calloc<Uint8>(len + chunkSize);
+  var offset = 0;
+  try {
+    while (true) {
+      final written =
+          appflowy_doc_read_chunk(handle, buf.elementAt(offset), chunkSize);
+      if (written < 0) {
+        appflowy_doc_close_reader(handle);
+        throw StateError('Read the document reader $handle failed');
+      }
+      if (written == 0) {
+        break;
+      }
+      offset += written;
+      if (offset > len) {
+        appflowy_doc_close_reader(handle);
+        throw StateError('The document reader $handle is longer than $len');
+      }
+    }
+    return TransferableTypedData.fromList([buf.asTypedList(offset)]);
+  } finally {
+    ffi.calloc.free(buf);
+  }
+}
+
 /// C function `link_me_please`.
 void link_me_please() {
   _link_me_please();
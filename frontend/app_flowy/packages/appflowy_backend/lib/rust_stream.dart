@@ -3,6 +3,7 @@ import 'dart:async';
 import 'dart:typed_data';
 import 'dart:ffi';
 import 'package:appflowy_backend/log.dart';
+import 'ffi.dart' as ffi;
 import 'protobuf/dart-notify/subject.pb.dart';
 
 typedef ObserverCallback = void Function(SubscribeObject observable);
@@ -36,6 +37,8 @@ class RustStreamReceiver {
   }
 
   void streamCallback(Uint8List bytes) {
+    // The queued notifications are only posted as fast as they're acknowledged.
+    ffi.ack_notifications(1);
     try {
       final observable = SubscribeObject.fromBuffer(bytes);
       _observableController.add(observable);
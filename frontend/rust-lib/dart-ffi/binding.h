@@ -9,6 +9,14 @@ void async_event(int64_t port, const uint8_t *input, uintptr_t len);
 
 const uint8_t *sync_event(const uint8_t *input, uintptr_t len);
 
+int32_t advance_event_generation(int64_t generation);
+
 int32_t set_stream_port(int64_t port);
 
+int32_t ack_notifications(int64_t count);
+
+int64_t appflowy_doc_read_chunk(int64_t handle, uint8_t *buf, uintptr_t len);
+
+int32_t appflowy_doc_close_reader(int64_t handle);
+
 void link_me_please(void);
\ No newline at end of file
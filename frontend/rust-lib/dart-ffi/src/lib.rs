@@ -42,12 +42,15 @@ pub extern "C" fn async_event(port: i64, input: *const u8, len: usize) {
         port
     );
 
-    let dispatcher = match FLOWY_SDK.read().as_ref() {
+    let (dispatcher, request) = match FLOWY_SDK.read().as_ref() {
         None => {
             log::error!("sdk not init yet.");
             return;
         }
-        Some(e) => e.event_dispatcher.clone(),
+        Some(e) => (
+            e.event_dispatcher.clone(),
+            request.capability_token(e.ffi_capability_token.clone()),
+        ),
     };
     let _ = AFPluginDispatcher::async_send_with_callback(dispatcher, request, move |resp: AFPluginEventResponse| {
         log::trace!("[FFI]: Post data to dart through {} port", port);
@@ -60,12 +63,15 @@ pub extern "C" fn sync_event(input: *const u8, len: usize) -> *const u8 {
     let request: AFPluginRequest = FFIRequest::from_u8_pointer(input, len).into();
     log::trace!("[FFI]: {} Sync Event: {:?}", &request.id, &request.event,);
 
-    let dispatcher = match FLOWY_SDK.read().as_ref() {
+    let (dispatcher, request) = match FLOWY_SDK.read().as_ref() {
         None => {
             log::error!("sdk not init yet.");
             return forget_rust(Vec::default());
         }
-        Some(e) => e.event_dispatcher.clone(),
+        Some(e) => (
+            e.event_dispatcher.clone(),
+            request.capability_token(e.ffi_capability_token.clone()),
+        ),
     };
     let _response = AFPluginDispatcher::sync_send(dispatcher, request);
 
@@ -75,12 +81,75 @@ pub extern "C" fn sync_event(input: *const u8, len: usize) -> *const u8 {
     forget_rust(result)
 }
 
+/// Called when the events sent before are stale, e.g. the user left the view they were sent
+/// for. The events of an older generation that are still held until their module is ready are
+/// dropped, see `FFIRequest::generation`.
+#[no_mangle]
+pub extern "C" fn advance_event_generation(generation: i64) -> i32 {
+    match FLOWY_SDK.read().as_ref() {
+        None => -1,
+        Some(sdk) => {
+            sdk.event_dispatcher.advance_generation(generation.max(0) as u64);
+            0
+        }
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn set_stream_port(port: i64) -> i32 {
     dart_notify::dart::DartStreamSender::set_port(port);
     0
 }
 
+/// Called once the Flutter side received `count` notifications of the stream, the queued
+/// notifications are posted as fast as they're acknowledged.
+#[no_mangle]
+pub extern "C" fn ack_notifications(count: i64) -> i32 {
+    dart_notify::dart::DartStreamSender::ack(count.max(0) as usize);
+    0
+}
+
+/// Copies the next `len` bytes of the json of the document opened with the `handle` into
+/// `buf`, see `DocumentEvent::OpenDocumentReader`. Returns the number of the copied bytes, 0
+/// once the json was read to the end, which closes the reader, or -1 if there is no reader
+/// with the `handle`, e.g. it wasn't read for `DocumentConfig::document_reader_timeout`.
+#[no_mangle]
+pub extern "C" fn appflowy_doc_read_chunk(handle: i64, buf: *mut u8, len: usize) -> i64 {
+    if buf.is_null() {
+        return -1;
+    }
+    let document_manager = match FLOWY_SDK.read().as_ref() {
+        None => {
+            log::error!("sdk not init yet.");
+            return -1;
+        }
+        Some(sdk) => sdk.document_manager.clone(),
+    };
+    let buf = unsafe { std::slice::from_raw_parts_mut(buf, len) };
+    match document_manager.read_document_chunk(handle, buf) {
+        Ok(written) => written as i64,
+        Err(e) => {
+            log::error!("[FFI]: Read the document chunk failed: {:?}", e);
+            -1
+        }
+    }
+}
+
+/// Closes the reader before it was read to the end.
+#[no_mangle]
+pub extern "C" fn appflowy_doc_close_reader(handle: i64) -> i32 {
+    match FLOWY_SDK.read().as_ref() {
+        None => -1,
+        Some(sdk) => {
+            if sdk.document_manager.close_document_reader(handle) {
+                0
+            } else {
+                -1
+            }
+        }
+    }
+}
+
 #[inline(never)]
 #[no_mangle]
 pub extern "C" fn link_me_please() {}
@@ -10,6 +10,10 @@ pub struct FFIRequest {
 
     #[pb(index = 2)]
     pub(crate) payload: Vec<u8>,
+
+    /// The generation the event was sent for, see `advance_event_generation`.
+    #[pb(index = 3, one_of)]
+    pub(crate) generation: Option<i64>,
 }
 
 impl FFIRequest {
@@ -23,6 +27,10 @@ impl FFIRequest {
 
 impl std::convert::From<FFIRequest> for AFPluginRequest {
     fn from(ffi_request: FFIRequest) -> Self {
-        AFPluginRequest::new(ffi_request.event).payload(ffi_request.payload)
+        let request = AFPluginRequest::new(ffi_request.event).payload(ffi_request.payload);
+        match ffi_request.generation {
+            None => request,
+            Some(generation) => request.generation(generation.max(0) as u64),
+        }
     }
 }
@@ -1,42 +1,135 @@
 use crate::entities::SubscribeObject;
+use crate::queue::{NotificationQueue, NotificationQueueStats};
+#[cfg(feature = "dart")]
 use bytes::Bytes;
 use lazy_static::lazy_static;
-use std::{convert::TryInto, sync::RwLock};
+use std::collections::HashMap;
+#[cfg(feature = "dart")]
+use std::convert::TryInto;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
 
 lazy_static! {
     static ref DART_STREAM_SENDER: RwLock<DartStreamSender> = RwLock::new(DartStreamSender::new());
+    static ref NOTIFICATION_QUEUES: RwLock<HashMap<String, RegisteredQueue>> = RwLock::new(HashMap::new());
+    static ref IN_FLIGHT: InFlight = InFlight::default();
 }
 
-pub struct DartStreamSender {
+/// The number of the posted notifications that the Flutter side hasn't acknowledged yet, past
+/// which the queues stop posting. The isolate's port queue is unbounded, so the notifications
+/// wait in the queues, where they're combined, until the Flutter side catches up.
+pub const MAX_IN_FLIGHT: usize = 64;
+
+/// Receives the posted notifications instead of the isolate, see `DartStreamSender::set_sink`.
+pub type NotificationSink = Arc<dyn Fn(SubscribeObject) + Send + Sync>;
+
+enum PostTarget {
     #[allow(dead_code)]
-    isolate: Option<allo_isolate::Isolate>,
+    Isolate(allo_isolate::Isolate),
+    Sink(NotificationSink),
+}
+
+struct RegisteredQueue {
+    queue: Arc<NotificationQueue>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct InFlight {
+    count: Mutex<usize>,
+    acked: Condvar,
+}
+
+impl InFlight {
+    fn add(&self) {
+        *self.count.lock().unwrap() += 1;
+    }
+
+    fn ack(&self, n: usize) {
+        let mut count = self.count.lock().unwrap();
+        *count = count.saturating_sub(n);
+        self.acked.notify_all();
+    }
+
+    fn reset(&self) {
+        *self.count.lock().unwrap() = 0;
+        self.acked.notify_all();
+    }
+
+    /// Waits until another notification can be posted. Returns false if the `queue` is closed
+    /// while waiting.
+    fn wait_for_room(&self, queue: &NotificationQueue) -> bool {
+        let mut count = self.count.lock().unwrap();
+        while *count >= MAX_IN_FLIGHT {
+            if queue.is_closed() {
+                return false;
+            }
+            count = self.acked.wait_timeout(count, Duration::from_millis(100)).unwrap().0;
+        }
+        true
+    }
+}
+
+pub struct DartStreamSender {
+    target: Option<PostTarget>,
+}
+
+/// Unregisters the queue when dropped, see `DartStreamSender::register_queue`.
+pub struct NotificationQueueRegistration {
+    source: String,
+}
+
+impl Drop for NotificationQueueRegistration {
+    fn drop(&mut self) {
+        DartStreamSender::unregister_queue(&self.source);
+    }
 }
 
 impl DartStreamSender {
     fn new() -> Self {
-        Self { isolate: None }
+        Self { target: None }
     }
 
     fn inner_set_port(&mut self, port: i64) {
         log::info!("Setup rust to flutter stream with port {}", port);
-        self.isolate = Some(allo_isolate::Isolate::new(port));
+        self.target = Some(PostTarget::Isolate(allo_isolate::Isolate::new(port)));
     }
 
-    #[allow(dead_code)]
     fn inner_post(&self, observable_subject: SubscribeObject) -> Result<(), String> {
-        match self.isolate {
-            Some(ref isolate) => {
+        match self.target {
+            Some(PostTarget::Sink(ref sink)) => {
+                IN_FLIGHT.add();
+                sink(observable_subject);
+                Ok(())
+            }
+            #[cfg(feature = "dart")]
+            Some(PostTarget::Isolate(ref isolate)) => {
                 let bytes: Bytes = observable_subject.try_into().unwrap();
-                isolate.post(bytes.to_vec());
+                // Counted before posting, the Flutter side may acknowledge it right away.
+                IN_FLIGHT.add();
+                if !isolate.post(bytes.to_vec()) {
+                    IN_FLIGHT.ack(1);
+                    return Err("Post to the isolate failed".to_owned());
+                }
                 Ok(())
             }
+            #[cfg(not(feature = "dart"))]
+            Some(PostTarget::Isolate(_)) => Ok(()),
+            #[cfg(feature = "dart")]
             None => Err("Isolate is not set".to_owned()),
+            #[cfg(not(feature = "dart"))]
+            None => Ok(()),
         }
     }
 
     pub fn set_port(port: i64) {
         match DART_STREAM_SENDER.write() {
-            Ok(mut stream) => stream.inner_set_port(port),
+            Ok(mut stream) => {
+                stream.inner_set_port(port);
+                // The notifications posted to the previous isolate won't be acknowledged.
+                IN_FLIGHT.reset();
+            }
             Err(e) => {
                 let msg = format!("Get rust to flutter stream lock fail. {:?}", e);
                 log::error!("{:?}", msg);
@@ -44,14 +137,119 @@ impl DartStreamSender {
         }
     }
 
-    pub fn post(_observable_subject: SubscribeObject) -> Result<(), String> {
-        #[cfg(feature = "dart")]
+    /// Posts the notifications to the `sink` instead of the isolate, for the hosts that don't
+    /// run Flutter. The sink acknowledges the notifications with `ack` like the Flutter side.
+    pub fn set_sink(sink: NotificationSink) {
+        match DART_STREAM_SENDER.write() {
+            Ok(mut stream) => {
+                stream.target = Some(PostTarget::Sink(sink));
+                IN_FLIGHT.reset();
+            }
+            Err(e) => log::error!("Get rust to flutter stream lock fail. {:?}", e),
+        }
+    }
+
+    /// Called by the Flutter side once it received `count` notifications. The queues only post
+    /// while fewer than `MAX_IN_FLIGHT` notifications are unacknowledged.
+    pub fn ack(count: usize) {
+        IN_FLIGHT.ack(count);
+    }
+
+    /// Posts the notifications of the queue's source through the queue from now on. They're
+    /// posted in the background, one at a time, as fast as the Flutter side acknowledges them.
+    /// Returns None if a queue of the source is already registered, the queue is ignored
+    /// then. Otherwise the queue stays registered until the returned registration is dropped.
+    pub fn register_queue(queue: Arc<NotificationQueue>) -> Option<NotificationQueueRegistration> {
+        let mut queues = match NOTIFICATION_QUEUES.write() {
+            Ok(queues) => queues,
+            Err(e) => {
+                log::error!("Get notification queues lock fail. {:?}", e);
+                return None;
+            }
+        };
+        if queues.contains_key(queue.source()) {
+            return None;
+        }
+        let source = queue.source().to_owned();
+        let thread_queue = queue.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("notify-{}", source))
+            .spawn(move || {
+                while IN_FLIGHT.wait_for_room(&thread_queue) {
+                    let subject = match thread_queue.pop_wait() {
+                        None => break,
+                        Some(subject) => subject,
+                    };
+                    if let Err(e) = Self::post_now(subject) {
+                        log::error!("Send observable subject failed: {}", e);
+                    }
+                }
+            });
+        match spawned {
+            Ok(thread) => {
+                queues.insert(
+                    source.clone(),
+                    RegisteredQueue {
+                        queue,
+                        thread: Some(thread),
+                    },
+                );
+                Some(NotificationQueueRegistration { source })
+            }
+            Err(e) => {
+                log::error!("Spawn the notification queue failed: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// Stops the thread of the queue and posts the notifications that were still queued. The
+    /// notifications of the source are posted right away from now on.
+    fn unregister_queue(source: &str) {
+        let registered = match NOTIFICATION_QUEUES.write() {
+            Ok(mut queues) => queues.remove(source),
+            Err(e) => {
+                log::error!("Get notification queues lock fail. {:?}", e);
+                return;
+            }
+        };
+        if let Some(mut registered) = registered {
+            let subjects = registered.queue.close();
+            if let Some(thread) = registered.thread.take() {
+                let _ = thread.join();
+            }
+            for subject in subjects {
+                if let Err(e) = Self::post_now(subject) {
+                    log::error!("Send observable subject failed: {}", e);
+                }
+            }
+        }
+    }
+
+    pub fn queue_stats(source: &str) -> Option<NotificationQueueStats> {
+        let queues = NOTIFICATION_QUEUES.read().ok()?;
+        queues.get(source).map(|registered| registered.queue.stats())
+    }
+
+    pub fn post(observable_subject: SubscribeObject) -> Result<(), String> {
+        let queue = NOTIFICATION_QUEUES.read().ok().and_then(|queues| {
+            queues
+                .get(&observable_subject.source)
+                .map(|registered| registered.queue.clone())
+        });
+        match queue {
+            None => Self::post_now(observable_subject),
+            Some(queue) => {
+                queue.push(observable_subject);
+                Ok(())
+            }
+        }
+    }
+
+    fn post_now(observable_subject: SubscribeObject) -> Result<(), String> {
         match DART_STREAM_SENDER.read() {
-            Ok(stream) => stream.inner_post(_observable_subject),
+            Ok(stream) => stream.inner_post(observable_subject),
             Err(e) => Err(format!("Get rust to flutter stream lock fail. {:?}", e)),
         }
-
-        #[cfg(not(feature = "dart"))]
-        Ok(())
     }
 }
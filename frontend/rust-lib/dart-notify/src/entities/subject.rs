@@ -45,3 +45,14 @@ impl std::default::Default for SubscribeObject {
         }
     }
 }
+
+/// The payload of the notification that tells the notifications dropped by the queue of the
+/// source, see `NotificationQueue`. The UI should refetch the objects of the dropped types.
+#[derive(Debug, Default, Clone, ProtoBuf)]
+pub struct NotificationsDroppedPB {
+    #[pb(index = 1)]
+    pub count: i64,
+
+    #[pb(index = 2)]
+    pub types: Vec<i32>,
+}
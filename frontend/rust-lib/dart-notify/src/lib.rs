@@ -3,6 +3,7 @@ use bytes::Bytes;
 pub mod dart;
 pub mod entities;
 mod protobuf;
+pub mod queue;
 
 use crate::{dart::DartStreamSender, entities::SubscribeObject};
 use lib_dispatch::prelude::ToBytes;
@@ -53,20 +54,22 @@ impl DartNotifyBuilder {
         self
     }
 
-    pub fn send(self) {
+    pub fn build(self) -> SubscribeObject {
         let payload = self.payload.map(|bytes| bytes.to_vec());
 
         let error = self.error.map(|bytes| bytes.to_vec());
 
-        let subject = SubscribeObject {
+        SubscribeObject {
             source: self.source,
             ty: self.ty,
             id: self.id,
             payload,
             error,
-        };
+        }
+    }
 
-        match DartStreamSender::post(subject) {
+    pub fn send(self) {
+        match DartStreamSender::post(self.build()) {
             Ok(_) => {}
             Err(error) => log::error!("Send observable subject failed: {}", error),
         }
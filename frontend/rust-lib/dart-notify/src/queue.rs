@@ -0,0 +1,235 @@
+use crate::entities::{NotificationsDroppedPB, SubscribeObject};
+use bytes::Bytes;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::convert::TryInto;
+use std::sync::{Condvar, Mutex};
+
+/// Combines two notifications of the same type and id into one, or returns None if they can't
+/// be combined.
+pub type MergeNotification = fn(&SubscribeObject, &SubscribeObject) -> Option<SubscribeObject>;
+
+/// How a queued notification is combined with the ones of the same type that come after it.
+#[derive(Clone, Copy)]
+pub enum CoalesceRule {
+    /// Never dropped or combined. The notifications with an error are always kept.
+    Keep,
+    /// The state of the object, only the latest notification of each id is kept.
+    Latest,
+    /// Combined with the notification of the same id if it's right behind it in the queue,
+    /// e.g. the consecutive changes of a document.
+    Merge(MergeNotification),
+    /// Dropped when the queue is full.
+    Drop,
+}
+
+pub struct NotificationQueueConfig {
+    /// The number of the queued notifications. The oldest notification that isn't kept by
+    /// its rule is dropped to make room for a new one.
+    pub capacity: usize,
+    /// The rules by the notification type, the types without a rule are `CoalesceRule::Drop`.
+    pub rules: HashMap<i32, CoalesceRule>,
+    /// The type of the notification that tells the dropped notifications, see `NotificationsDroppedPB`.
+    pub dropped_ty: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NotificationQueueStats {
+    /// The notifications that wait to be posted.
+    pub depth: usize,
+    pub peak_depth: usize,
+    /// The notifications that were dropped since the queue was created.
+    pub num_of_dropped: usize,
+}
+
+enum QueueEntry {
+    Notification(SubscribeObject),
+    /// Stands for the notifications dropped since it was queued, see `QueueState::dropped`.
+    Dropped,
+}
+
+#[derive(Default)]
+struct QueueState {
+    entries: VecDeque<QueueEntry>,
+    /// The number and the types of the dropped notifications that the queued `Dropped` entry
+    /// tells. There's at most one `Dropped` entry, the notifications dropped while it's
+    /// queued are added to it.
+    dropped: Option<(usize, BTreeSet<i32>)>,
+    peak_depth: usize,
+    num_of_dropped: usize,
+    /// Set by `close`, the notifications aren't queued anymore.
+    closed: bool,
+}
+
+impl QueueState {
+    fn depth(&self) -> usize {
+        self.entries.len() - self.dropped.is_some() as usize
+    }
+}
+
+/// Holds the notifications of a source until they're posted to the Flutter side. While the
+/// isolate is busy, the queue stays bounded by combining the notifications according to the
+/// rules of their types and dropping the ones that can be dropped. The UI is told about the
+/// dropped notifications with a single notification of `dropped_ty`, so it can refetch.
+pub struct NotificationQueue {
+    source: String,
+    config: NotificationQueueConfig,
+    state: Mutex<QueueState>,
+    not_empty: Condvar,
+}
+
+impl NotificationQueue {
+    pub fn new(source: &str, config: NotificationQueueConfig) -> Self {
+        Self {
+            source: source.to_owned(),
+            config,
+            state: Mutex::new(QueueState::default()),
+            not_empty: Condvar::new(),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn push(&self, subject: SubscribeObject) {
+        let rule = self.rule_of(&subject);
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return;
+        }
+        match rule {
+            CoalesceRule::Latest => {
+                let queued = state.entries.iter_mut().find_map(|entry| match entry {
+                    QueueEntry::Notification(queued) if queued.ty == subject.ty && queued.id == subject.id => {
+                        Some(queued)
+                    }
+                    _ => None,
+                });
+                if let Some(queued) = queued {
+                    *queued = subject;
+                    return;
+                }
+            }
+            CoalesceRule::Merge(merge) => {
+                if let Some(QueueEntry::Notification(queued)) = state.entries.back_mut() {
+                    if queued.ty == subject.ty && queued.id == subject.id {
+                        if let Some(merged) = merge(queued, &subject) {
+                            *queued = merged;
+                            return;
+                        }
+                    }
+                }
+            }
+            CoalesceRule::Keep | CoalesceRule::Drop => {}
+        }
+
+        state.entries.push_back(QueueEntry::Notification(subject));
+        while state.depth() > self.config.capacity {
+            if !self.drop_oldest(&mut state) {
+                break;
+            }
+        }
+        state.peak_depth = state.peak_depth.max(state.depth());
+        self.not_empty.notify_one();
+    }
+
+    /// Returns the next notification to post, or None if the queue is empty.
+    pub fn pop(&self) -> Option<SubscribeObject> {
+        let mut state = self.state.lock().unwrap();
+        self.pop_entry(&mut state)
+    }
+
+    /// Waits until there's a notification to post. Returns None once the queue is closed.
+    pub fn pop_wait(&self) -> Option<SubscribeObject> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.closed {
+                return None;
+            }
+            if let Some(subject) = self.pop_entry(&mut state) {
+                return Some(subject);
+            }
+            state = self.not_empty.wait(state).unwrap();
+        }
+    }
+
+    /// Stops queueing the notifications and wakes up the `pop_wait`. Returns the notifications
+    /// that were still queued.
+    pub fn close(&self) -> Vec<SubscribeObject> {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        let subjects = std::iter::from_fn(|| self.pop_entry(&mut state)).collect();
+        self.not_empty.notify_all();
+        subjects
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    pub fn stats(&self) -> NotificationQueueStats {
+        let state = self.state.lock().unwrap();
+        NotificationQueueStats {
+            depth: state.depth(),
+            peak_depth: state.peak_depth,
+            num_of_dropped: state.num_of_dropped,
+        }
+    }
+
+    fn rule_of(&self, subject: &SubscribeObject) -> CoalesceRule {
+        if subject.error.is_some() {
+            return CoalesceRule::Keep;
+        }
+        self.config
+            .rules
+            .get(&subject.ty)
+            .copied()
+            .unwrap_or(CoalesceRule::Drop)
+    }
+
+    /// Drops the oldest notification that isn't kept by its rule. Returns false if all the
+    /// queued notifications are kept, the queue grows past its capacity then.
+    fn drop_oldest(&self, state: &mut QueueState) -> bool {
+        let index = state.entries.iter().position(|entry| match entry {
+            QueueEntry::Notification(subject) => !matches!(self.rule_of(subject), CoalesceRule::Keep),
+            QueueEntry::Dropped => false,
+        });
+        let subject = match index.and_then(|index| state.entries.remove(index)) {
+            Some(QueueEntry::Notification(subject)) => subject,
+            _ => return false,
+        };
+        state.num_of_dropped += 1;
+        match state.dropped.as_mut() {
+            Some((count, types)) => {
+                *count += 1;
+                types.insert(subject.ty);
+            }
+            None => {
+                state.dropped = Some((1, BTreeSet::from([subject.ty])));
+                state.entries.push_back(QueueEntry::Dropped);
+            }
+        }
+        true
+    }
+
+    fn pop_entry(&self, state: &mut QueueState) -> Option<SubscribeObject> {
+        match state.entries.pop_front()? {
+            QueueEntry::Notification(subject) => Some(subject),
+            QueueEntry::Dropped => {
+                let (count, types) = state.dropped.take().unwrap_or_default();
+                let dropped = NotificationsDroppedPB {
+                    count: count as i64,
+                    types: types.into_iter().collect(),
+                };
+                let payload: Option<Bytes> = dropped.try_into().ok();
+                Some(SubscribeObject {
+                    source: self.source.clone(),
+                    ty: self.config.dropped_ty,
+                    id: "".to_owned(),
+                    payload: payload.map(|bytes| bytes.to_vec()),
+                    error: None,
+                })
+            }
+        }
+    }
+}
@@ -42,6 +42,8 @@ impl DocumentDepsResolver {
         ));
         let receiver = Arc::new(DocumentWSMessageReceiverImpl(manager.clone()));
         ws_conn.add_ws_message_receiver(receiver).unwrap();
+        manager.schedule_backups();
+        manager.schedule_attachment_reconcile();
 
         manager
     }
@@ -1,7 +1,7 @@
 use bytes::Bytes;
 use flowy_database::ConnectionPool;
 
-use flowy_document::DocumentManager;
+use flowy_document::{DocumentManager, DocumentScopeResolver};
 use flowy_folder::entities::{ViewDataFormatPB, ViewLayoutTypePB, ViewPB};
 use flowy_folder::manager::{ViewDataProcessor, ViewDataProcessorMap};
 use flowy_folder::{
@@ -26,7 +26,11 @@ use lib_infra::future::{BoxResultFuture, FutureResult};
 use lib_ws::{WSChannel, WSMessageReceiver, WebSocketRawMessage};
 use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::{convert::TryInto, sync::Arc};
+use std::path::PathBuf;
+use std::{
+    convert::TryInto,
+    sync::{Arc, Weak},
+};
 
 pub struct FolderDepsResolver();
 impl FolderDepsResolver {
@@ -57,12 +61,64 @@ impl FolderDepsResolver {
             }
         }
 
+        // The folder keeps the document manager through its view processors, so the resolver
+        // only keeps a weak reference to the folder.
+        let scope_resolver = Arc::new(DocumentScopeResolverImpl(Arc::downgrade(&folder_manager)));
+        text_block_manager.set_scope_resolver(scope_resolver).await;
+
         let receiver = Arc::new(FolderWSMessageReceiverImpl(folder_manager.clone()));
         ws_conn.add_ws_message_receiver(receiver).unwrap();
         folder_manager
     }
 }
 
+struct DocumentScopeResolverImpl(Weak<FolderManager>);
+impl DocumentScopeResolver for DocumentScopeResolverImpl {
+    fn workspace_doc_ids(&self, workspace_id: &str) -> FutureResult<Vec<String>, FlowyError> {
+        let folder_manager = self.0.upgrade();
+        let workspace_id = workspace_id.to_owned();
+        FutureResult::new(async move {
+            match folder_manager {
+                None => Err(FlowyError::internal().context("The folder is dropped")),
+                Some(folder_manager) => folder_manager.workspace_document_ids(&workspace_id).await,
+            }
+        })
+    }
+
+    fn app_doc_ids(&self, app_id: &str) -> FutureResult<Vec<String>, FlowyError> {
+        let folder_manager = self.0.upgrade();
+        let app_id = app_id.to_owned();
+        FutureResult::new(async move {
+            match folder_manager {
+                None => Err(FlowyError::internal().context("The folder is dropped")),
+                Some(folder_manager) => folder_manager.app_document_ids(&app_id).await,
+            }
+        })
+    }
+
+    fn is_scratch_view(&self, view_id: &str) -> FutureResult<bool, FlowyError> {
+        let folder_manager = self.0.upgrade();
+        let view_id = view_id.to_owned();
+        FutureResult::new(async move {
+            match folder_manager {
+                None => Err(FlowyError::internal().context("The folder is dropped")),
+                Some(folder_manager) => folder_manager.is_scratch_view(&view_id).await,
+            }
+        })
+    }
+
+    fn set_view_scratch(&self, view_id: &str, is_scratch: bool) -> FutureResult<(), FlowyError> {
+        let folder_manager = self.0.upgrade();
+        let view_id = view_id.to_owned();
+        FutureResult::new(async move {
+            match folder_manager {
+                None => Err(FlowyError::internal().context("The folder is dropped")),
+                Some(folder_manager) => folder_manager.set_view_scratch(&view_id, is_scratch).await,
+            }
+        })
+    }
+}
+
 fn make_view_data_processor(
     document_manager: Arc<DocumentManager>,
     grid_manager: Arc<GridManager>,
@@ -214,6 +270,32 @@ impl ViewDataProcessor for DocumentViewDataProcessor {
     fn data_types(&self) -> Vec<ViewDataFormatPB> {
         vec![ViewDataFormatPB::DeltaFormat, ViewDataFormatPB::TreeFormat]
     }
+
+    fn set_view_scratch(&self, view_id: &str, is_scratch: bool) -> FutureResult<(), FlowyError> {
+        self.0.set_scratch_document(view_id, is_scratch);
+        FutureResult::new(async { Ok(()) })
+    }
+
+    fn is_view_untouched(&self, view_id: &str) -> FutureResult<bool, FlowyError> {
+        let manager = self.0.clone();
+        let view_id = view_id.to_string();
+        FutureResult::new(async move { manager.is_untouched_document(&view_id).await })
+    }
+
+    fn import_view(&self, path: &str) -> FutureResult<String, FlowyError> {
+        let manager = self.0.clone();
+        let path = PathBuf::from(path);
+        FutureResult::new(async move { manager.import_doc_portable(&path).await })
+    }
+
+    fn merge_local_database(&self, dir: &str) -> FutureResult<HashMap<String, String>, FlowyError> {
+        let manager = self.0.clone();
+        let dir = dir.to_owned();
+        FutureResult::new(async move {
+            let summary = manager.merge_local_database(&dir).await?;
+            Ok(summary.doc_ids.into_iter().collect())
+        })
+    }
 }
 
 struct GridViewDataProcessor(Arc<GridManager>);
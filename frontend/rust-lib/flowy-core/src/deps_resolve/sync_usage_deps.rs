@@ -0,0 +1,43 @@
+use crate::FlowyError;
+use flowy_database::ConnectionPool;
+use flowy_folder::manager::FolderManager;
+use flowy_net::sync_usage::{SyncUsageManager, SyncUsageUser};
+use flowy_net::ws::connection::FlowyWebSocketConnect;
+use flowy_user::services::UserSession;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The interval of saving the bytes counted by the websocket to the database.
+const SYNC_USAGE_FLUSH_INTERVAL_IN_SECS: u64 = 60;
+
+pub struct SyncUsageDepsResolver();
+impl SyncUsageDepsResolver {
+    pub fn resolve(
+        ws_conn: Arc<FlowyWebSocketConnect>,
+        user_session: Arc<UserSession>,
+        folder_manager: Arc<FolderManager>,
+    ) -> Arc<SyncUsageManager> {
+        let user = Arc::new(SyncUsageUserImpl {
+            user_session,
+            folder_manager,
+        });
+        let manager = Arc::new(SyncUsageManager::new(ws_conn, user));
+        manager.run(Duration::from_secs(SYNC_USAGE_FLUSH_INTERVAL_IN_SECS));
+        manager
+    }
+}
+
+struct SyncUsageUserImpl {
+    user_session: Arc<UserSession>,
+    folder_manager: Arc<FolderManager>,
+}
+
+impl SyncUsageUser for SyncUsageUserImpl {
+    fn workspace_id(&self) -> Result<String, FlowyError> {
+        self.folder_manager.current_workspace_id()
+    }
+
+    fn db_pool(&self) -> Result<Arc<ConnectionPool>, FlowyError> {
+        self.user_session.db_pool()
+    }
+}
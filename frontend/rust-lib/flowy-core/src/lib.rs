@@ -11,10 +11,13 @@ use flowy_folder::{errors::FlowyError, manager::FolderManager};
 use flowy_grid::manager::GridManager;
 use flowy_net::ClientServerConfiguration;
 use flowy_net::{
-    entities::NetworkType,
+    entities::{NetworkType, PowerType},
     local_server::LocalServer,
+    sync_usage::SyncUsageManager,
     ws::connection::{listen_on_websocket, FlowyWebSocketConnect},
 };
+pub use flowy_revision::Executor;
+use flowy_revision::PowerState;
 use flowy_task::{TaskDispatcher, TaskRunner};
 use flowy_user::services::{notifier::UserStatus, UserSession, UserSessionConfig};
 use lib_dispatch::prelude::*;
@@ -33,6 +36,15 @@ use tokio::sync::{broadcast, RwLock};
 
 static INIT_LOG: AtomicBool = AtomicBool::new(false);
 
+/// The capabilities without the maintenance events. The FFI is granted all the capabilities by
+/// default, a host that doesn't expose the maintenance events to its UI passes these to
+/// `FlowySDKConfig::with_ffi_capabilities`.
+pub const RESTRICTED_FFI_CAPABILITIES: [AFPluginCapability; 3] = [
+    AFPluginCapability::Read,
+    AFPluginCapability::Write,
+    AFPluginCapability::Export,
+];
+
 #[derive(Clone)]
 pub struct FlowySDKConfig {
     /// Different `FlowySDK` instance should have different name
@@ -42,6 +54,8 @@ pub struct FlowySDKConfig {
     log_filter: String,
     server_config: ClientServerConfiguration,
     pub document: DocumentConfig,
+    /// The capabilities granted to the requests that come through the FFI, all of them by default.
+    ffi_capabilities: Vec<AFPluginCapability>,
 }
 
 impl fmt::Debug for FlowySDKConfig {
@@ -50,6 +64,7 @@ impl fmt::Debug for FlowySDKConfig {
             .field("root", &self.root)
             .field("server-config", &self.server_config)
             .field("document-config", &self.document)
+            .field("ffi-capabilities", &self.ffi_capabilities)
             .finish()
     }
 }
@@ -62,14 +77,31 @@ impl FlowySDKConfig {
             log_filter: crate_log_filter("info".to_owned()),
             server_config,
             document: DocumentConfig::default(),
+            ffi_capabilities: AFPluginCapability::ALL.to_vec(),
         }
     }
 
+    /// Restricts the capabilities of the FFI, e.g. to the `RESTRICTED_FFI_CAPABILITIES`.
+    pub fn with_ffi_capabilities(mut self, capabilities: &[AFPluginCapability]) -> Self {
+        self.ffi_capabilities = capabilities.to_vec();
+        self
+    }
+
     pub fn with_document_version(mut self, version: DocumentVersionPB) -> Self {
         self.document.version = version;
         self
     }
 
+    pub fn with_redacted_document_logs(mut self, redact_logs: bool) -> Self {
+        self.document.redact_logs = redact_logs;
+        self
+    }
+
+    pub fn with_document_executor(mut self, executor: Executor) -> Self {
+        self.document.executor = executor;
+        self
+    }
+
     pub fn log_filter(mut self, level: &str) -> Self {
         self.log_filter = crate_log_filter(level.to_owned());
         self
@@ -109,10 +141,15 @@ pub struct FlowySDK {
     pub document_manager: Arc<DocumentManager>,
     pub folder_manager: Arc<FolderManager>,
     pub grid_manager: Arc<GridManager>,
+    pub sync_usage_manager: Arc<SyncUsageManager>,
     pub event_dispatcher: Arc<AFPluginDispatcher>,
     pub ws_conn: Arc<FlowyWebSocketConnect>,
     pub local_server: Option<Arc<LocalServer>>,
     pub task_dispatcher: Arc<RwLock<TaskDispatcher>>,
+    /// Grants all the capabilities, the host attaches it to the requests it sends in-process.
+    pub capability_token: AFPluginCapabilityToken,
+    /// Grants the `FlowySDKConfig::ffi_capabilities`, attached to the requests of the FFI.
+    pub ffi_capability_token: AFPluginCapabilityToken,
 }
 
 impl FlowySDK {
@@ -126,41 +163,46 @@ impl FlowySDK {
         runtime.spawn(TaskRunner::run(task_dispatcher.clone()));
 
         let (local_server, ws_conn) = mk_local_server(&config.server_config);
-        let (user_session, document_manager, folder_manager, local_server, grid_manager) = runtime.block_on(async {
-            let user_session = mk_user_session(&config, &local_server, &config.server_config);
-            let document_manager = DocumentDepsResolver::resolve(
-                local_server.clone(),
-                ws_conn.clone(),
-                user_session.clone(),
-                &config.server_config,
-                &config.document,
-            );
-
-            let grid_manager =
-                GridDepsResolver::resolve(ws_conn.clone(), user_session.clone(), task_dispatcher.clone()).await;
-
-            let folder_manager = FolderDepsResolver::resolve(
-                local_server.clone(),
-                user_session.clone(),
-                &config.server_config,
-                &ws_conn,
-                &document_manager,
-                &grid_manager,
-            )
-            .await;
-
-            if let Some(local_server) = local_server.as_ref() {
-                local_server.run();
-            }
-            ws_conn.init().await;
-            (
-                user_session,
-                document_manager,
-                folder_manager,
-                local_server,
-                grid_manager,
-            )
-        });
+        let (user_session, document_manager, folder_manager, local_server, grid_manager, sync_usage_manager) = runtime
+            .block_on(async {
+                let user_session = mk_user_session(&config, &local_server, &config.server_config);
+                let document_manager = DocumentDepsResolver::resolve(
+                    local_server.clone(),
+                    ws_conn.clone(),
+                    user_session.clone(),
+                    &config.server_config,
+                    &config.document,
+                );
+
+                let grid_manager =
+                    GridDepsResolver::resolve(ws_conn.clone(), user_session.clone(), task_dispatcher.clone()).await;
+
+                let folder_manager = FolderDepsResolver::resolve(
+                    local_server.clone(),
+                    user_session.clone(),
+                    &config.server_config,
+                    &ws_conn,
+                    &document_manager,
+                    &grid_manager,
+                )
+                .await;
+
+                let sync_usage_manager =
+                    SyncUsageDepsResolver::resolve(ws_conn.clone(), user_session.clone(), folder_manager.clone());
+
+                if let Some(local_server) = local_server.as_ref() {
+                    local_server.run();
+                }
+                ws_conn.init().await;
+                (
+                    user_session,
+                    document_manager,
+                    folder_manager,
+                    local_server,
+                    grid_manager,
+                    sync_usage_manager,
+                )
+            });
 
         let event_dispatcher = Arc::new(AFPluginDispatcher::construct(runtime, || {
             make_plugins(
@@ -169,9 +211,13 @@ impl FlowySDK {
                 &grid_manager,
                 &user_session,
                 &document_manager,
+                &sync_usage_manager,
             )
         }));
 
+        let capability_token = event_dispatcher.mint_capability_token(&AFPluginCapability::ALL);
+        let ffi_capability_token = event_dispatcher.mint_capability_token(&config.ffi_capabilities);
+
         _start_listening(
             &config,
             &event_dispatcher,
@@ -188,10 +234,13 @@ impl FlowySDK {
             document_manager,
             folder_manager,
             grid_manager,
+            sync_usage_manager,
             event_dispatcher,
             ws_conn,
             local_server,
             task_dispatcher,
+            capability_token,
+            ffi_capability_token,
         }
     }
 
@@ -211,12 +260,14 @@ fn _start_listening(
 ) {
     let subscribe_user_status = user_session.notifier.subscribe_user_status();
     let subscribe_network_type = ws_conn.subscribe_network_ty();
+    let subscribe_power_type = ws_conn.subscribe_power_ty();
     let folder_manager = folder_manager.clone();
     let grid_manager = grid_manager.clone();
     let cloned_folder_manager = folder_manager.clone();
     let ws_conn = ws_conn.clone();
     let user_session = user_session.clone();
     let document_manager = document_manager.clone();
+    let cloned_document_manager = document_manager.clone();
     let config = config.clone();
 
     event_dispatcher.spawn(async move {
@@ -236,6 +287,10 @@ fn _start_listening(
     event_dispatcher.spawn(async move {
         _listen_network_status(subscribe_network_type, cloned_folder_manager).await;
     });
+
+    event_dispatcher.spawn(async move {
+        _listen_power_status(subscribe_power_type, cloned_document_manager).await;
+    });
 }
 
 fn mk_local_server(
@@ -308,7 +363,11 @@ async fn _listen_user_status(
 
         match result().await {
             Ok(_) => {}
-            Err(e) => tracing::error!("{}", e),
+            Err(e) => {
+                tracing::error!("{}", e);
+                // The documents may not be initialized, their held events fail instead.
+                document_manager.open_startup_gate();
+            }
         }
     }
 }
@@ -319,6 +378,18 @@ async fn _listen_network_status(mut subscribe: broadcast::Receiver<NetworkType>,
     }
 }
 
+/// The documents are written to disk less often while the device is on battery.
+async fn _listen_power_status(mut subscribe: broadcast::Receiver<PowerType>, document_manager: Arc<DocumentManager>) {
+    while let Ok(new_type) = subscribe.recv().await {
+        let power_state = match new_type {
+            PowerType::UnknownPowerType => PowerState::Unknown,
+            PowerType::OnBattery => PowerState::OnBattery,
+            PowerType::PluggedIn => PowerState::PluggedIn,
+        };
+        document_manager.set_power_state(power_state);
+    }
+}
+
 fn init_kv(root: &str) {
     match flowy_database::kv::KV::init(root) {
         Ok(_) => {}
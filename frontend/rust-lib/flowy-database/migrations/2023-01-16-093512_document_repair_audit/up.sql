@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE document_repair_audit (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    object_id TEXT NOT NULL DEFAULT '',
+    rev_id BIGINT NOT NULL DEFAULT 0,
+    issue TEXT NOT NULL DEFAULT '',
+    outcome TEXT NOT NULL DEFAULT '',
+    detail TEXT NOT NULL DEFAULT '',
+    timestamp BIGINT NOT NULL DEFAULT 0
+);
\ No newline at end of file
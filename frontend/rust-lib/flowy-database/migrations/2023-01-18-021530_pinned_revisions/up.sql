@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE pinned_revisions (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    object_id TEXT NOT NULL DEFAULT '',
+    rev_id BIGINT NOT NULL DEFAULT 0,
+    UNIQUE(object_id, rev_id)
+);
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE document_snippet (
+    id TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL DEFAULT '',
+    data BLOB NOT NULL DEFAULT (x''),
+    create_time BIGINT NOT NULL DEFAULT 0
+);
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE document_chunk (
+    doc_id TEXT NOT NULL DEFAULT '',
+    chunk_index INTEGER NOT NULL DEFAULT 0,
+    data TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (doc_id, chunk_index)
+);
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE document_export_stamp (
+    doc_id TEXT NOT NULL PRIMARY KEY DEFAULT '',
+    exporter_version BIGINT NOT NULL DEFAULT 0,
+    rev_id BIGINT NOT NULL DEFAULT 0
+);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE pinned_revisions DROP COLUMN tag;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE pinned_revisions ADD COLUMN tag TEXT NOT NULL DEFAULT '';
\ No newline at end of file
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE sync_usage (
+    workspace_id TEXT NOT NULL,
+    day TEXT NOT NULL,
+    revision_sent BIGINT NOT NULL DEFAULT 0,
+    revision_received BIGINT NOT NULL DEFAULT 0,
+    presence_sent BIGINT NOT NULL DEFAULT 0,
+    presence_received BIGINT NOT NULL DEFAULT 0,
+    control_sent BIGINT NOT NULL DEFAULT 0,
+    control_received BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (workspace_id, day)
+);
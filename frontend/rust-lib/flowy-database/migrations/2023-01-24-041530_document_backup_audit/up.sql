@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE document_backup_audit (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL DEFAULT '',
+    path TEXT NOT NULL DEFAULT '',
+    outcome TEXT NOT NULL DEFAULT '',
+    detail TEXT NOT NULL DEFAULT '',
+    timestamp BIGINT NOT NULL DEFAULT 0
+);
\ No newline at end of file
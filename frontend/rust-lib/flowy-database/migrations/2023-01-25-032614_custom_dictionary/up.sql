@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE custom_dictionary (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    workspace_id TEXT NOT NULL DEFAULT '',
+    word TEXT NOT NULL DEFAULT '',
+    UNIQUE(workspace_id, word)
+);
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE rev_payload (
+    hash TEXT NOT NULL PRIMARY KEY,
+    data BLOB NOT NULL DEFAULT (x''),
+    ty INTEGER NOT NULL DEFAULT 0,
+    ref_count BIGINT NOT NULL DEFAULT 0
+);
\ No newline at end of file
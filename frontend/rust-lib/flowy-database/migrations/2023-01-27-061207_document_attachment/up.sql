@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE document_attachment (
+    id TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL DEFAULT '',
+    data BLOB NOT NULL DEFAULT (x''),
+    update_time BIGINT NOT NULL DEFAULT 0,
+    orphaned_time BIGINT NOT NULL DEFAULT 0
+);
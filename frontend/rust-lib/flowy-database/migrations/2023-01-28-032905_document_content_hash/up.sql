@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE document_content_hash (
+    doc_id TEXT NOT NULL PRIMARY KEY DEFAULT '',
+    rev_id BIGINT NOT NULL DEFAULT 0,
+    hash TEXT NOT NULL DEFAULT ''
+);
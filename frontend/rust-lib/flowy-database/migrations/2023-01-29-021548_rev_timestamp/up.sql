@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE rev_timestamp (
+    object_id TEXT NOT NULL DEFAULT '',
+    rev_id BIGINT NOT NULL DEFAULT 0,
+    create_time BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (object_id, rev_id)
+);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE document_merge (
+    source TEXT NOT NULL DEFAULT '',
+    source_doc_id TEXT NOT NULL DEFAULT '',
+    doc_id TEXT NOT NULL DEFAULT '',
+    merged BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (source, source_doc_id)
+);
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE rev_timestamp ADD COLUMN expires_at BIGINT;
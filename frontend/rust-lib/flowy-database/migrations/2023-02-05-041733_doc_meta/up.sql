@@ -0,0 +1,5 @@
+-- Your SQL goes here
+CREATE TABLE doc_meta (
+    doc_id TEXT NOT NULL PRIMARY KEY DEFAULT '',
+    preferences TEXT NOT NULL DEFAULT '{}'
+);
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE rev_dead_letter (
+    object_id TEXT NOT NULL DEFAULT '',
+    rev_id BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (object_id, rev_id)
+);
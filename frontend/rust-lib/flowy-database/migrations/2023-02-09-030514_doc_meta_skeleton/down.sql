@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE doc_meta DROP COLUMN skeleton;
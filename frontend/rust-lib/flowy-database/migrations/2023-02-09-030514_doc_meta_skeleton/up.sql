@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE doc_meta ADD COLUMN skeleton TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE document_attachment ADD COLUMN digest TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE rev_id_map (
+    object_id TEXT NOT NULL DEFAULT '',
+    rev_id BIGINT NOT NULL DEFAULT 0,
+    canonical_rev_id BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (object_id, rev_id)
+);
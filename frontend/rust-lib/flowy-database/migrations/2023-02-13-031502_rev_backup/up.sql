@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE rev_backup (
+    object_id TEXT NOT NULL DEFAULT '',
+    rev_id BIGINT NOT NULL DEFAULT 0,
+    base_rev_id BIGINT NOT NULL DEFAULT 0,
+    data BLOB NOT NULL DEFAULT (x''),
+    md5 TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (object_id, rev_id)
+);
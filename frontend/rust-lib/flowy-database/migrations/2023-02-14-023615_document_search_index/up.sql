@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE document_search_index (
+    doc_id TEXT NOT NULL PRIMARY KEY DEFAULT '',
+    rev_id BIGINT NOT NULL DEFAULT 0,
+    text TEXT NOT NULL DEFAULT ''
+);
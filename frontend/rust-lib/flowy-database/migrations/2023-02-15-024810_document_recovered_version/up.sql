@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE document_recovered_version (
+    object_id TEXT NOT NULL DEFAULT '',
+    rev_id BIGINT NOT NULL DEFAULT 0,
+    name TEXT NOT NULL DEFAULT '',
+    timestamp BIGINT NOT NULL DEFAULT 0,
+    data BLOB NOT NULL DEFAULT (x''),
+    PRIMARY KEY (object_id, rev_id)
+);
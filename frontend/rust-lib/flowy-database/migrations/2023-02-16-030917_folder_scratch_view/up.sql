@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE folder_scratch_view (
+    view_id TEXT NOT NULL PRIMARY KEY,
+    app_id TEXT NOT NULL DEFAULT '',
+    create_time BIGINT NOT NULL DEFAULT 0,
+    data TEXT NOT NULL DEFAULT '',
+    trash TEXT NOT NULL DEFAULT ''
+);
@@ -9,6 +9,9 @@ use crate::sqlite::PoolConfig;
 pub use crate::sqlite::{ConnectionPool, DBConnection, Database};
 
 pub mod schema;
+pub mod table_migration;
+#[cfg(feature = "flowy_unit_test")]
+pub mod test_util;
 
 #[macro_use]
 pub mod macros;
@@ -30,11 +33,15 @@ pub mod prelude {
 
 embed_migrations!("../flowy-database/migrations/");
 pub const DB_NAME: &str = "flowy-database.db";
+/// The database that replaces the `DB_NAME` database the next time it's opened by `init`, e.g.
+/// the restored backup. It can't replace the database while its connections are open.
+pub const STAGED_DB_NAME: &str = "flowy-database.db.staged";
 
 pub fn init(storage_path: &str) -> Result<Database, io::Error> {
     if !Path::new(storage_path).exists() {
         std::fs::create_dir_all(storage_path)?;
     }
+    swap_in_staged_database(storage_path)?;
     let pool_config = PoolConfig::default();
     let database = Database::new(storage_path, DB_NAME, pool_config).map_err(as_io_error)?;
     let conn = database.get_connection().map_err(as_io_error)?;
@@ -42,6 +49,25 @@ pub fn init(storage_path: &str) -> Result<Database, io::Error> {
     Ok(database)
 }
 
+fn swap_in_staged_database(storage_path: &str) -> Result<(), io::Error> {
+    let staged_path = Path::new(storage_path).join(STAGED_DB_NAME);
+    if !staged_path.exists() {
+        return Ok(());
+    }
+    let db_path = Path::new(storage_path).join(DB_NAME);
+    // The journal files belong to the replaced database, SQLite would apply them to the staged one.
+    for suffix in &["-wal", "-shm", "-journal"] {
+        let mut journal_path = db_path.clone().into_os_string();
+        journal_path.push(suffix);
+        let journal_path = Path::new(&journal_path);
+        if journal_path.exists() {
+            std::fs::remove_file(journal_path)?;
+        }
+    }
+    tracing::info!("Replace the database with {:?}", staged_path);
+    std::fs::rename(&staged_path, &db_path)
+}
+
 fn as_io_error<E>(e: E) -> io::Error
 where
     E: Into<crate::sqlite::Error> + Debug,
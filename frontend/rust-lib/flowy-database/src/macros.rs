@@ -184,6 +184,7 @@ macro_rules! impl_rev_state_map {
                 match value {
                     0 => $target::Sync,
                     1 => $target::Ack,
+                    2 => $target::Resolved,
                     o => {
                         tracing::error!("Unsupported rev state {}, fallback to RevState::Local", o);
                         $target::Sync
@@ -197,6 +198,7 @@ macro_rules! impl_rev_state_map {
                 match s {
                     $target::Sync => RevisionState::Sync,
                     $target::Ack => RevisionState::Ack,
+                    $target::Resolved => RevisionState::Resolved,
                 }
             }
         }
@@ -206,6 +208,7 @@ macro_rules! impl_rev_state_map {
                 match s {
                     RevisionState::Sync => $target::Sync,
                     RevisionState::Ack => $target::Ack,
+                    RevisionState::Resolved => $target::Resolved,
                 }
             }
         }
@@ -15,6 +15,99 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    custom_dictionary (id) {
+        id -> Integer,
+        workspace_id -> Text,
+        word -> Text,
+    }
+}
+
+diesel::table! {
+    doc_meta (doc_id) {
+        doc_id -> Text,
+        preferences -> Text,
+        skeleton -> Text,
+    }
+}
+
+diesel::table! {
+    document_attachment (id) {
+        id -> Text,
+        name -> Text,
+        data -> Binary,
+        update_time -> BigInt,
+        orphaned_time -> BigInt,
+        digest -> Text,
+    }
+}
+
+diesel::table! {
+    document_backup_audit (id) {
+        id -> Integer,
+        kind -> Text,
+        path -> Text,
+        outcome -> Text,
+        detail -> Text,
+        timestamp -> BigInt,
+    }
+}
+
+diesel::table! {
+    document_chunk (doc_id, chunk_index) {
+        doc_id -> Text,
+        chunk_index -> Integer,
+        data -> Text,
+    }
+}
+
+diesel::table! {
+    document_content_hash (doc_id) {
+        doc_id -> Text,
+        rev_id -> BigInt,
+        hash -> Text,
+    }
+}
+
+diesel::table! {
+    document_export_stamp (doc_id) {
+        doc_id -> Text,
+        exporter_version -> BigInt,
+        rev_id -> BigInt,
+    }
+}
+
+diesel::table! {
+    document_merge (source, source_doc_id) {
+        source -> Text,
+        source_doc_id -> Text,
+        doc_id -> Text,
+        merged -> Bool,
+    }
+}
+
+diesel::table! {
+    document_repair_audit (id) {
+        id -> Integer,
+        object_id -> Text,
+        rev_id -> BigInt,
+        issue -> Text,
+        outcome -> Text,
+        detail -> Text,
+        timestamp -> BigInt,
+    }
+}
+
+diesel::table! {
+    document_recovered_version (object_id, rev_id) {
+        object_id -> Text,
+        rev_id -> BigInt,
+        name -> Text,
+        timestamp -> BigInt,
+        data -> Binary,
+    }
+}
+
 diesel::table! {
     document_rev_snapshot (snapshot_id) {
         snapshot_id -> Text,
@@ -37,6 +130,23 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    document_search_index (doc_id) {
+        doc_id -> Text,
+        rev_id -> BigInt,
+        text -> Text,
+    }
+}
+
+diesel::table! {
+    document_snippet (id) {
+        id -> Text,
+        name -> Text,
+        data -> Binary,
+        create_time -> BigInt,
+    }
+}
+
 diesel::table! {
     folder_rev_snapshot (snapshot_id) {
         snapshot_id -> Text,
@@ -48,6 +158,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    folder_scratch_view (view_id) {
+        view_id -> Text,
+        app_id -> Text,
+        create_time -> BigInt,
+        data -> Text,
+        trash -> Text,
+    }
+}
+
 diesel::table! {
     grid_block_index_table (row_id) {
         row_id -> Text,
@@ -106,6 +226,49 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    pinned_revisions (id) {
+        id -> Integer,
+        object_id -> Text,
+        rev_id -> BigInt,
+        tag -> Text,
+    }
+}
+
+diesel::table! {
+    rev_backup (object_id, rev_id) {
+        object_id -> Text,
+        rev_id -> BigInt,
+        base_rev_id -> BigInt,
+        data -> Binary,
+        md5 -> Text,
+    }
+}
+
+diesel::table! {
+    rev_dead_letter (object_id, rev_id) {
+        object_id -> Text,
+        rev_id -> BigInt,
+    }
+}
+
+diesel::table! {
+    rev_id_map (object_id, rev_id) {
+        object_id -> Text,
+        rev_id -> BigInt,
+        canonical_rev_id -> BigInt,
+    }
+}
+
+diesel::table! {
+    rev_payload (hash) {
+        hash -> Text,
+        data -> Binary,
+        ty -> Integer,
+        ref_count -> BigInt,
+    }
+}
+
 diesel::table! {
     rev_snapshot (id) {
         id -> Integer,
@@ -127,6 +290,28 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    rev_timestamp (object_id, rev_id) {
+        object_id -> Text,
+        rev_id -> BigInt,
+        create_time -> BigInt,
+        expires_at -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    sync_usage (workspace_id, day) {
+        workspace_id -> Text,
+        day -> Text,
+        revision_sent -> BigInt,
+        revision_received -> BigInt,
+        presence_sent -> BigInt,
+        presence_received -> BigInt,
+        control_sent -> BigInt,
+        control_received -> BigInt,
+    }
+}
+
 diesel::table! {
     trash_table (id) {
         id -> Text,
@@ -179,17 +364,37 @@ diesel::table! {
 
 diesel::allow_tables_to_appear_in_same_query!(
     app_table,
+    custom_dictionary,
+    doc_meta,
+    document_attachment,
+    document_backup_audit,
+    document_chunk,
+    document_content_hash,
+    document_export_stamp,
+    document_merge,
+    document_recovered_version,
+    document_repair_audit,
     document_rev_snapshot,
     document_rev_table,
+    document_search_index,
+    document_snippet,
     folder_rev_snapshot,
+    folder_scratch_view,
     grid_block_index_table,
     grid_meta_rev_table,
     grid_rev_snapshot,
     grid_rev_table,
     grid_view_rev_table,
     kv_table,
+    pinned_revisions,
+    rev_backup,
+    rev_dead_letter,
+    rev_id_map,
+    rev_payload,
     rev_snapshot,
     rev_table,
+    rev_timestamp,
+    sync_usage,
     trash_table,
     user_table,
     view_table,
@@ -0,0 +1,180 @@
+use diesel::sql_types::{BigInt, Text};
+use diesel::{sql_query, RunQueryDsl, SqliteConnection};
+
+const CHECKPOINT_TABLE: &str = "table_migration_checkpoint";
+
+/// Rebuilds the `table` with a new schema without locking the database for the whole migration.
+///
+/// The rows are copied into a new table in batches, each batch in its own transaction. When all
+/// the rows are copied, the old table gets dropped and the new table takes its name. The progress
+/// is recorded in the `table_migration_checkpoint` table within the same transaction as the
+/// batch, so a migration that got interrupted continues from the last copied row.
+///
+/// The `table` must have an `id INTEGER PRIMARY KEY` column, it is used as the cursor of the copy.
+/// Rows that get updated or deleted after they were copied are not tracked, so the migration
+/// must run to completion before anything else reads or writes the table.
+#[derive(Clone, Debug)]
+pub struct CopyTableMigration {
+    /// Identifies the migration in the checkpoint table.
+    pub name: String,
+    pub table: String,
+    /// The column definitions of the new table, e.g. `id INTEGER NOT NULL PRIMARY KEY, ...`
+    pub columns: String,
+    /// Pairs of the new table's column and the expression that computes it from the old row.
+    pub copy_columns: Vec<(String, String)>,
+    /// Statements that run after the new table takes the old table's name, e.g. creating indexes.
+    pub after_swap: Vec<String>,
+    pub batch_size: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MigrationProgress {
+    pub name: String,
+    pub copied: i64,
+    pub total: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MigrationStep {
+    /// A batch of rows was copied into the new table.
+    Copied(MigrationProgress),
+    /// All the rows were copied and the new table replaced the old one.
+    Swapped,
+    /// The migration was completed before.
+    Done,
+}
+
+#[derive(QueryableByName)]
+struct I64Row {
+    #[sql_type = "BigInt"]
+    value: i64,
+}
+
+#[derive(QueryableByName)]
+struct CheckpointRow {
+    #[sql_type = "BigInt"]
+    last_id: i64,
+    #[sql_type = "BigInt"]
+    done: i64,
+}
+
+impl CopyTableMigration {
+    pub fn is_done(&self, conn: &SqliteConnection) -> Result<bool, crate::Error> {
+        Ok(self.read_checkpoint(conn)?.map(|row| row.done != 0).unwrap_or(false))
+    }
+
+    /// Runs the next step of the migration. Call it until it returns `Swapped` or `Done`.
+    pub fn step(&self, conn: &SqliteConnection) -> Result<MigrationStep, crate::Error> {
+        if self.is_done(conn)? {
+            return Ok(MigrationStep::Done);
+        }
+
+        conn.immediate_transaction::<_, crate::Error, _>(|| {
+            self.prepare(conn)?;
+            let last_id = self.read_checkpoint(conn)?.map(|row| row.last_id).unwrap_or(i64::MIN);
+            let (target_columns, source_columns): (Vec<String>, Vec<String>) =
+                self.copy_columns.iter().cloned().unzip();
+            let copy_sql = format!(
+                "INSERT INTO {} ({}) SELECT {} FROM {} WHERE id > ? ORDER BY id LIMIT ?",
+                self.new_table(),
+                target_columns.join(", "),
+                source_columns.join(", "),
+                self.table
+            );
+            let copied = sql_query(copy_sql)
+                .bind::<BigInt, _>(last_id)
+                .bind::<BigInt, _>(self.batch_size)
+                .execute(conn)?;
+
+            if copied == 0 {
+                self.swap(conn)?;
+                return Ok(MigrationStep::Swapped);
+            }
+
+            let last_id = select_i64(conn, &format!("SELECT MAX(id) AS value FROM {}", self.new_table()))?;
+            sql_query(format!("UPDATE {} SET last_id = ? WHERE name = ?", CHECKPOINT_TABLE))
+                .bind::<BigInt, _>(last_id)
+                .bind::<Text, _>(&self.name)
+                .execute(conn)?;
+
+            Ok(MigrationStep::Copied(MigrationProgress {
+                name: self.name.clone(),
+                copied: select_i64(conn, &format!("SELECT COUNT(*) AS value FROM {}", self.new_table()))?,
+                total: select_i64(conn, &format!("SELECT COUNT(*) AS value FROM {}", self.table))?,
+            }))
+        })
+    }
+
+    /// Drops the rows copied so far. The old table is untouched until the swap, so the migration
+    /// can be rolled back at any time before it's done.
+    pub fn rollback(&self, conn: &SqliteConnection) -> Result<(), crate::Error> {
+        if self.is_done(conn)? {
+            tracing::warn!("Can't rollback the migration: {}, it's done", self.name);
+            return Ok(());
+        }
+
+        conn.immediate_transaction::<_, crate::Error, _>(|| {
+            sql_query(format!("DROP TABLE IF EXISTS {}", self.new_table())).execute(conn)?;
+            sql_query(format!("DELETE FROM {} WHERE name = ?", CHECKPOINT_TABLE))
+                .bind::<Text, _>(&self.name)
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    fn new_table(&self) -> String {
+        format!("{}_migration", self.table)
+    }
+
+    fn prepare(&self, conn: &SqliteConnection) -> Result<(), crate::Error> {
+        create_checkpoint_table(conn)?;
+        sql_query(format!(
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            self.new_table(),
+            self.columns
+        ))
+        .execute(conn)?;
+        sql_query(format!(
+            "INSERT OR IGNORE INTO {} (name, last_id, done) VALUES (?, ?, 0)",
+            CHECKPOINT_TABLE
+        ))
+        .bind::<Text, _>(&self.name)
+        .bind::<BigInt, _>(i64::MIN)
+        .execute(conn)?;
+        Ok(())
+    }
+
+    fn swap(&self, conn: &SqliteConnection) -> Result<(), crate::Error> {
+        sql_query(format!("DROP TABLE {}", self.table)).execute(conn)?;
+        sql_query(format!("ALTER TABLE {} RENAME TO {}", self.new_table(), self.table)).execute(conn)?;
+        for statement in &self.after_swap {
+            sql_query(statement).execute(conn)?;
+        }
+        sql_query(format!("UPDATE {} SET done = 1 WHERE name = ?", CHECKPOINT_TABLE))
+            .bind::<Text, _>(&self.name)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    fn read_checkpoint(&self, conn: &SqliteConnection) -> Result<Option<CheckpointRow>, crate::Error> {
+        create_checkpoint_table(conn)?;
+        let mut rows = sql_query(format!("SELECT last_id, done FROM {} WHERE name = ?", CHECKPOINT_TABLE))
+            .bind::<Text, _>(&self.name)
+            .load::<CheckpointRow>(conn)?;
+        Ok(rows.pop())
+    }
+}
+
+fn create_checkpoint_table(conn: &SqliteConnection) -> Result<(), crate::Error> {
+    sql_query(format!(
+        "CREATE TABLE IF NOT EXISTS {} (name TEXT NOT NULL PRIMARY KEY, last_id BIGINT NOT NULL, done INTEGER NOT NULL DEFAULT 0)",
+        CHECKPOINT_TABLE
+    ))
+    .execute(conn)?;
+    Ok(())
+}
+
+fn select_i64(conn: &SqliteConnection, sql: &str) -> Result<i64, crate::Error> {
+    let mut rows = sql_query(sql).load::<I64Row>(conn)?;
+    Ok(rows.pop().map(|row| row.value).unwrap_or(0))
+}
@@ -0,0 +1,58 @@
+use crate::Database;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The database of a test in its own temp directory. The directory is removed when it's dropped.
+pub struct TempDatabase {
+    database: Option<Database>,
+    dir: PathBuf,
+}
+
+impl TempDatabase {
+    /// Creates the database with the migrations applied. The `name` only makes the directory
+    /// easier to find while the test runs.
+    pub fn new(name: &str) -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir = std::env::temp_dir().join(format!(
+            "flowy_{}_{}_{}_{}",
+            name,
+            std::process::id(),
+            nanos,
+            NEXT_ID.fetch_add(1, Ordering::SeqCst)
+        ));
+        let database = crate::init(dir.to_str().unwrap()).unwrap();
+        Self {
+            database: Some(database),
+            dir,
+        }
+    }
+
+    pub fn dir(&self) -> &str {
+        self.dir.to_str().unwrap()
+    }
+
+    /// Closes the database and opens it again, like the app does when it restarts.
+    pub fn reopen(&mut self) {
+        self.database.take();
+        self.database = Some(crate::init(self.dir()).unwrap());
+    }
+}
+
+impl Deref for TempDatabase {
+    type Target = Database;
+
+    fn deref(&self) -> &Self::Target {
+        self.database.as_ref().unwrap()
+    }
+}
+
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        self.database.take();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,147 @@
+#[macro_use]
+extern crate diesel;
+
+use diesel::sql_types::BigInt;
+use diesel::{sql_query, RunQueryDsl, SqliteConnection};
+use flowy_database::table_migration::{CopyTableMigration, MigrationProgress, MigrationStep};
+use flowy_database::test_util::TempDatabase;
+
+#[test]
+fn table_migration_copy_in_batches_test() {
+    let database = TempDatabase::new("table_migration");
+    let conn = database.get_connection().unwrap();
+    insert_document_revisions(&conn, 25);
+
+    let migration = created_at_migration();
+    let mut steps = vec![];
+    loop {
+        let step = migration.step(&conn).unwrap();
+        steps.push(step.clone());
+        if step == MigrationStep::Swapped {
+            break;
+        }
+    }
+
+    assert_eq!(
+        steps,
+        vec![
+            MigrationStep::Copied(progress(10, 25)),
+            MigrationStep::Copied(progress(20, 25)),
+            MigrationStep::Copied(progress(25, 25)),
+            MigrationStep::Swapped,
+        ]
+    );
+    assert_eq!(
+        number_of_rows(&conn, "SELECT COUNT(*) AS value FROM document_rev_table"),
+        25
+    );
+    assert_eq!(
+        number_of_rows(
+            &conn,
+            "SELECT COUNT(*) AS value FROM document_rev_table WHERE created_at = 0"
+        ),
+        25
+    );
+    assert_eq!(migration.step(&conn).unwrap(), MigrationStep::Done);
+}
+
+#[test]
+fn table_migration_resume_after_interrupt_test() {
+    let mut database = TempDatabase::new("table_migration");
+    {
+        let conn = database.get_connection().unwrap();
+        insert_document_revisions(&conn, 25);
+
+        // The app gets killed after copying two batches.
+        let migration = created_at_migration();
+        assert_eq!(migration.step(&conn).unwrap(), MigrationStep::Copied(progress(10, 25)));
+        assert_eq!(migration.step(&conn).unwrap(), MigrationStep::Copied(progress(20, 25)));
+    }
+
+    database.reopen();
+    let conn = database.get_connection().unwrap();
+    let migration = created_at_migration();
+    assert!(!migration.is_done(&conn).unwrap());
+    assert_eq!(migration.step(&conn).unwrap(), MigrationStep::Copied(progress(25, 25)));
+    assert_eq!(migration.step(&conn).unwrap(), MigrationStep::Swapped);
+    assert_eq!(
+        number_of_rows(&conn, "SELECT COUNT(*) AS value FROM document_rev_table"),
+        25
+    );
+    assert_eq!(
+        number_of_rows(&conn, "SELECT COUNT(DISTINCT rev_id) AS value FROM document_rev_table"),
+        25
+    );
+}
+
+#[test]
+fn table_migration_rollback_test() {
+    let database = TempDatabase::new("table_migration");
+    let conn = database.get_connection().unwrap();
+    insert_document_revisions(&conn, 15);
+
+    let migration = created_at_migration();
+    assert_eq!(migration.step(&conn).unwrap(), MigrationStep::Copied(progress(10, 15)));
+    migration.rollback(&conn).unwrap();
+    assert!(!migration.is_done(&conn).unwrap());
+
+    // The old table is untouched and the migration starts over.
+    assert_eq!(
+        number_of_rows(&conn, "SELECT COUNT(*) AS value FROM document_rev_table"),
+        15
+    );
+    assert_eq!(migration.step(&conn).unwrap(), MigrationStep::Copied(progress(10, 15)));
+}
+
+fn created_at_migration() -> CopyTableMigration {
+    let columns = ["id", "document_id", "base_rev_id", "rev_id", "data", "state"];
+    let mut copy_columns = columns
+        .iter()
+        .map(|column| (column.to_string(), column.to_string()))
+        .collect::<Vec<(String, String)>>();
+    copy_columns.push(("created_at".to_owned(), "0".to_owned()));
+
+    CopyTableMigration {
+        name: "document_rev_table_created_at".to_owned(),
+        table: "document_rev_table".to_owned(),
+        columns: "id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT, \
+                  document_id TEXT NOT NULL DEFAULT '', \
+                  base_rev_id BIGINT NOT NULL DEFAULT 0, \
+                  rev_id BIGINT NOT NULL DEFAULT 0, \
+                  data BLOB NOT NULL DEFAULT (x''), \
+                  state INTEGER NOT NULL DEFAULT 0, \
+                  created_at BIGINT NOT NULL DEFAULT 0"
+            .to_owned(),
+        copy_columns,
+        after_swap: vec![],
+        batch_size: 10,
+    }
+}
+
+fn progress(copied: i64, total: i64) -> MigrationProgress {
+    MigrationProgress {
+        name: "document_rev_table_created_at".to_owned(),
+        copied,
+        total,
+    }
+}
+
+fn insert_document_revisions(conn: &SqliteConnection, num: i64) {
+    for rev_id in 1..=num {
+        sql_query("INSERT INTO document_rev_table (document_id, base_rev_id, rev_id, state) VALUES ('doc', ?, ?, 0)")
+            .bind::<BigInt, _>(rev_id - 1)
+            .bind::<BigInt, _>(rev_id)
+            .execute(conn)
+            .unwrap();
+    }
+}
+
+#[derive(QueryableByName)]
+struct CountRow {
+    #[sql_type = "BigInt"]
+    value: i64,
+}
+
+fn number_of_rows(conn: &SqliteConnection, sql: &str) -> i64 {
+    sql_query(sql).load::<CountRow>(conn).unwrap().pop().unwrap().value
+}
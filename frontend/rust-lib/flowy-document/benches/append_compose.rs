@@ -0,0 +1,48 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use flowy_http_model::revision::Revision;
+use flowy_sync::util::make_operations_from_revisions;
+use lib_ot::core::{AttributeHashMap, OperationTransform};
+use lib_ot::text_delta::{DeltaTextOperationBuilder, DeltaTextOperations};
+
+/// The number of the appends of the journal-like document.
+const NUM_OF_APPENDS: usize = 1000;
+
+fn append_revisions() -> Vec<Revision> {
+    let mut len = 0;
+    (1..=NUM_OF_APPENDS as i64)
+        .map(|rev_id| {
+            let s = format!("line {}\n", rev_id);
+            let operations = DeltaTextOperationBuilder::new().retain(len).insert(&s).build();
+            len += s.len();
+            Revision::new("doc", rev_id - 1, rev_id, operations.json_bytes(), "")
+        })
+        .collect()
+}
+
+/// Composes the appends one by one like any other edit, which rebuilds the document each time.
+fn compose_revisions(revisions: &[Revision]) -> DeltaTextOperations {
+    let mut composed = DeltaTextOperations::new();
+    for revision in revisions {
+        let operations = DeltaTextOperations::from_bytes(&revision.bytes).unwrap();
+        composed = composed.compose(&operations).unwrap();
+    }
+    composed
+}
+
+fn append_compose_benchmark(c: &mut Criterion) {
+    let revisions = append_revisions();
+    assert_eq!(
+        make_operations_from_revisions::<AttributeHashMap>(revisions.clone()).unwrap(),
+        compose_revisions(&revisions)
+    );
+
+    let mut group = c.benchmark_group("compose 1000 appends");
+    group.bench_function("compose_append", |b| {
+        b.iter(|| make_operations_from_revisions::<AttributeHashMap>(black_box(revisions.clone())).unwrap())
+    });
+    group.bench_function("compose", |b| b.iter(|| compose_revisions(black_box(&revisions))));
+    group.finish();
+}
+
+criterion_group!(benches, append_compose_benchmark);
+criterion_main!(benches);
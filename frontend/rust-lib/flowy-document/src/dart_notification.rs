@@ -0,0 +1,308 @@
+use crate::entities::DocumentChangePB;
+use bytes::Bytes;
+use dart_notify::dart::{DartStreamSender, NotificationQueueRegistration};
+use dart_notify::entities::SubscribeObject;
+use dart_notify::queue::{CoalesceRule, NotificationQueue, NotificationQueueConfig, NotificationQueueStats};
+use dart_notify::DartNotifyBuilder;
+use flowy_derive::ProtoBuf_Enum;
+use lib_ot::core::OperationTransform;
+use lib_ot::text_delta::DeltaTextOperations;
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::sync::Arc;
+const OBSERVABLE_CATEGORY: &str = "Document";
+
+/// The notifications of the documents that wait for the Flutter side. The older ones are
+/// combined or dropped past it, see `document_notification_queue_config`.
+const NOTIFICATION_QUEUE_CAPACITY: usize = 1000;
+
+#[derive(ProtoBuf_Enum, Debug)]
+pub(crate) enum DocumentNotification {
+    Unknown = 0,
+    DidUpdateTableMigration = 1,
+    DidCompleteStartup = 2,
+    DidRefreshDocument = 3,
+    SyncLoopDetected = 4,
+    DidReceiveRemoteChange = 5,
+    DidUpdateReexportProgress = 6,
+    DidFailBackup = 7,
+    DidBecomeReady = 8,
+    DidUpdateCustomDictionary = 9,
+    /// Sent with `NotificationsDroppedPB` when the queue of the notifications was full. The UI
+    /// should refetch the documents whose notifications of these types it missed.
+    NotificationsDropped = 10,
+    /// Sent with `DocPreferencesPB` to the document whose preferences changed.
+    DidUpdateDocPreferences = 11,
+    /// Sent with `DocumentSnapshotPB` when the document that `open_latest_available` returned
+    /// as loading is opened, or with the error if it fails to open.
+    DidOpenDocument = 12,
+    /// Sent when `restore_from_backup` staged a backup. The documents stay closed until the
+    /// restart that swaps the restored database in.
+    DidStageRestore = 13,
+    /// Sent to the attachment when its data or name was downloaded in the background, see
+    /// `DocumentManager::reconcile_attachments`.
+    DidDownloadAttachment = 14,
+    /// Sent to the document whose snapshot couldn't be confirmed when it was opened. The snapshot
+    /// was kept as the "recovered backup" version and the document was built from its revisions.
+    DidRecoverSnapshot = 15,
+}
+
+impl std::default::Default for DocumentNotification {
+    fn default() -> Self {
+        DocumentNotification::Unknown
+    }
+}
+
+impl std::convert::From<DocumentNotification> for i32 {
+    fn from(notification: DocumentNotification) -> Self {
+        notification as i32
+    }
+}
+
+#[tracing::instrument(level = "trace")]
+pub(crate) fn send_anonymous_dart_notification(ty: DocumentNotification) -> DartNotifyBuilder {
+    DartNotifyBuilder::new("", ty, OBSERVABLE_CATEGORY)
+}
+
+#[tracing::instrument(level = "trace")]
+pub(crate) fn send_dart_notification(id: &str, ty: DocumentNotification) -> DartNotifyBuilder {
+    DartNotifyBuilder::new(id, ty, OBSERVABLE_CATEGORY)
+}
+
+/// Bounds the notifications of the documents that wait while the Flutter isolate is busy.
+pub(crate) fn register_document_notification_queue() -> Option<NotificationQueueRegistration> {
+    let queue = NotificationQueue::new(OBSERVABLE_CATEGORY, document_notification_queue_config());
+    DartStreamSender::register_queue(Arc::new(queue))
+}
+
+pub(crate) fn document_notification_queue_stats() -> NotificationQueueStats {
+    DartStreamSender::queue_stats(OBSERVABLE_CATEGORY).unwrap_or_default()
+}
+
+/// The sync state, the progress and the startup report only matter in their latest version,
+/// the remote changes of a document are composed into one, and the failures are never dropped.
+pub(crate) fn document_notification_queue_config() -> NotificationQueueConfig {
+    let mut rules = HashMap::new();
+    for ty in [
+        DocumentNotification::SyncLoopDetected,
+        DocumentNotification::DidFailBackup,
+        DocumentNotification::DidOpenDocument,
+        DocumentNotification::DidRecoverSnapshot,
+    ] {
+        rules.insert(ty.into(), CoalesceRule::Keep);
+    }
+    for ty in [
+        DocumentNotification::DidUpdateTableMigration,
+        DocumentNotification::DidCompleteStartup,
+        DocumentNotification::DidRefreshDocument,
+        DocumentNotification::DidUpdateReexportProgress,
+        DocumentNotification::DidUpdateCustomDictionary,
+        DocumentNotification::DidUpdateDocPreferences,
+    ] {
+        rules.insert(ty.into(), CoalesceRule::Latest);
+    }
+    rules.insert(
+        DocumentNotification::DidReceiveRemoteChange.into(),
+        CoalesceRule::Merge(merge_document_changes),
+    );
+    NotificationQueueConfig {
+        capacity: NOTIFICATION_QUEUE_CAPACITY,
+        rules,
+        dropped_ty: DocumentNotification::NotificationsDropped.into(),
+    }
+}
+
+/// Composes the operations of two consecutive `DocumentChangePB` of a document. The selection
+/// of the later one is kept, it was already moved along with both changes.
+fn merge_document_changes(first: &SubscribeObject, second: &SubscribeObject) -> Option<SubscribeObject> {
+    let decode = |subject: &SubscribeObject| {
+        let bytes = Bytes::from(subject.payload.clone()?);
+        DocumentChangePB::try_from(bytes).ok()
+    };
+    let first_change = decode(first)?;
+    let second_change = decode(second)?;
+    let operations = DeltaTextOperations::from_json(&first_change.operations)
+        .ok()?
+        .compose(&DeltaTextOperations::from_json(&second_change.operations).ok()?)
+        .ok()?;
+    let change = DocumentChangePB {
+        doc_id: second_change.doc_id,
+        operations: operations.json_str(),
+        selection: second_change.selection,
+    };
+    let payload: Bytes = change.try_into().ok()?;
+    Some(SubscribeObject {
+        payload: Some(payload.to_vec()),
+        ..second.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::DocumentStartupReportPB;
+    use dart_notify::dart::MAX_IN_FLIGHT;
+    use dart_notify::entities::NotificationsDroppedPB;
+    use dart_notify::queue::NotificationQueue;
+    use std::sync::{mpsc, Mutex};
+    use std::time::Duration;
+
+    fn queue() -> NotificationQueue {
+        NotificationQueue::new(OBSERVABLE_CATEGORY, document_notification_queue_config())
+    }
+
+    fn remote_change(doc_id: &str, operations: &str) -> SubscribeObject {
+        send_dart_notification(doc_id, DocumentNotification::DidReceiveRemoteChange)
+            .payload(DocumentChangePB {
+                doc_id: doc_id.to_owned(),
+                operations: operations.to_owned(),
+                selection: vec![],
+            })
+            .build()
+    }
+
+    fn decode_change(subject: &SubscribeObject) -> DocumentChangePB {
+        DocumentChangePB::try_from(Bytes::from(subject.payload.clone().unwrap())).unwrap()
+    }
+
+    // Nothing is popped while flooding, as if the Flutter isolate stalled.
+    fn drain(queue: &NotificationQueue) -> Vec<SubscribeObject> {
+        std::iter::from_fn(|| queue.pop()).collect()
+    }
+
+    #[test]
+    fn notification_queue_stays_bounded_with_a_stalled_consumer() {
+        let queue = queue();
+        for i in 0..NOTIFICATION_QUEUE_CAPACITY * 3 {
+            queue.push(send_dart_notification(&format!("doc_{}", i), DocumentNotification::DidBecomeReady).build());
+        }
+        let stats = queue.stats();
+        assert_eq!(stats.depth, NOTIFICATION_QUEUE_CAPACITY);
+        assert_eq!(stats.peak_depth, NOTIFICATION_QUEUE_CAPACITY);
+        assert_eq!(stats.num_of_dropped, NOTIFICATION_QUEUE_CAPACITY * 2);
+
+        // The dropped notifications are told by a single marker, it's queued when the first
+        // one is dropped.
+        let subjects = drain(&queue);
+        let markers = subjects
+            .iter()
+            .filter(|subject| subject.ty == i32::from(DocumentNotification::NotificationsDropped))
+            .collect::<Vec<_>>();
+        assert_eq!(markers.len(), 1);
+        let dropped = NotificationsDroppedPB::try_from(Bytes::from(markers[0].payload.clone().unwrap())).unwrap();
+        assert_eq!(dropped.count, (NOTIFICATION_QUEUE_CAPACITY * 2) as i64);
+        assert_eq!(dropped.types, vec![i32::from(DocumentNotification::DidBecomeReady)]);
+        assert_eq!(subjects.len(), NOTIFICATION_QUEUE_CAPACITY + 1);
+        assert_eq!(
+            subjects.last().unwrap().id,
+            format!("doc_{}", NOTIFICATION_QUEUE_CAPACITY * 3 - 1)
+        );
+        assert_eq!(queue.stats().depth, 0);
+    }
+
+    #[test]
+    fn notification_queue_keeps_the_latest_state_of_each_document() {
+        let queue = queue();
+        for i in 0..NOTIFICATION_QUEUE_CAPACITY * 2 {
+            let report = DocumentStartupReportPB {
+                num_of_documents: i as i64,
+                ..Default::default()
+            };
+            queue.push(
+                send_dart_notification(&format!("doc_{}", i % 2), DocumentNotification::DidCompleteStartup)
+                    .payload(report)
+                    .build(),
+            );
+        }
+        let subjects = drain(&queue);
+        assert_eq!(subjects.len(), 2);
+        assert_eq!(queue.stats().num_of_dropped, 0);
+        let last = DocumentStartupReportPB::try_from(Bytes::from(subjects[1].payload.clone().unwrap())).unwrap();
+        assert_eq!(subjects[1].id, "doc_1");
+        assert_eq!(last.num_of_documents, (NOTIFICATION_QUEUE_CAPACITY * 2 - 1) as i64);
+    }
+
+    #[test]
+    fn notification_queue_merges_consecutive_remote_changes() {
+        let queue = queue();
+        queue.push(remote_change("doc_1", r#"[{"insert":"a"}]"#));
+        queue.push(remote_change("doc_1", r#"[{"retain":1},{"insert":"b"}]"#));
+        queue.push(remote_change("doc_2", r#"[{"insert":"c"}]"#));
+        queue.push(remote_change("doc_1", r#"[{"insert":"d"}]"#));
+
+        let subjects = drain(&queue);
+        assert_eq!(subjects.len(), 3);
+        let merged = decode_change(&subjects[0]);
+        let operations = DeltaTextOperations::from_json(&merged.operations).unwrap();
+        assert_eq!(operations.content().unwrap(), "ab");
+        assert_eq!(decode_change(&subjects[2]).operations, r#"[{"insert":"d"}]"#);
+    }
+
+    #[test]
+    fn notification_queue_posts_as_fast_as_the_consumer_acks() {
+        const SOURCE: &str = "NotificationQueueTest";
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        DartStreamSender::set_sink(Arc::new(move |subject: SubscribeObject| {
+            if subject.source == SOURCE {
+                let _ = sender.lock().unwrap().send(subject);
+            } else {
+                DartStreamSender::ack(1);
+            }
+        }));
+        let queue = Arc::new(NotificationQueue::new(SOURCE, document_notification_queue_config()));
+        let registration = DartStreamSender::register_queue(queue.clone()).unwrap();
+        let num_of_notifications = NOTIFICATION_QUEUE_CAPACITY * 3;
+        for i in 0..num_of_notifications {
+            DartNotifyBuilder::new(&format!("doc_{}", i), DocumentNotification::DidBecomeReady, SOURCE).send();
+        }
+
+        // Nothing is acknowledged, so only the first notifications are posted and the rest wait
+        // in the queue.
+        let received = std::iter::from_fn(|| receiver.recv_timeout(Duration::from_millis(500)).ok()).count();
+        assert_eq!(received, MAX_IN_FLIGHT);
+        assert!(queue.stats().depth <= NOTIFICATION_QUEUE_CAPACITY);
+
+        // The rest is posted as the consumer acknowledges them.
+        DartStreamSender::ack(MAX_IN_FLIGHT);
+        let mut subjects = vec![];
+        while let Ok(subject) = receiver.recv_timeout(Duration::from_secs(5)) {
+            DartStreamSender::ack(1);
+            let is_last = subject.id == format!("doc_{}", num_of_notifications - 1);
+            subjects.push(subject);
+            if is_last {
+                break;
+            }
+        }
+        assert!(subjects
+            .iter()
+            .any(|subject| subject.ty == i32::from(DocumentNotification::NotificationsDropped)));
+        assert_eq!(subjects.last().unwrap().id, format!("doc_{}", num_of_notifications - 1));
+
+        // Dropping the registration stops the thread, the notifications are posted right away.
+        drop(registration);
+        assert!(queue.is_closed());
+        assert!(DartStreamSender::queue_stats(SOURCE).is_none());
+        DartNotifyBuilder::new("doc", DocumentNotification::DidBecomeReady, SOURCE).send();
+        assert_eq!(receiver.try_recv().unwrap().id, "doc");
+    }
+
+    #[test]
+    fn notification_queue_never_drops_errors() {
+        let queue = queue();
+        for i in 0..NOTIFICATION_QUEUE_CAPACITY {
+            queue.push(
+                send_dart_notification(&format!("doc_{}", i), DocumentNotification::DidBecomeReady)
+                    .error(DocumentStartupReportPB::default())
+                    .build(),
+            );
+        }
+        for i in 0..NOTIFICATION_QUEUE_CAPACITY {
+            queue.push(send_dart_notification(&format!("doc_{}", i), DocumentNotification::DidBecomeReady).build());
+        }
+        let subjects = drain(&queue);
+        let num_of_errors = subjects.iter().filter(|subject| subject.error.is_some()).count();
+        assert_eq!(num_of_errors, NOTIFICATION_QUEUE_CAPACITY);
+        assert_eq!(queue.stats().num_of_dropped, NOTIFICATION_QUEUE_CAPACITY);
+    }
+}
@@ -1,14 +1,15 @@
 use crate::editor::document::Document;
 use bytes::Bytes;
-use flowy_error::FlowyResult;
+use flowy_error::{FlowyError, FlowyResult};
 use lib_ot::core::{
     AttributeHashMap, Body, Changeset, Extension, NodeData, NodeId, NodeOperation, NodeTree, NodeTreeContext, Path,
     Selection, Transaction,
 };
-use lib_ot::text_delta::DeltaTextOperations;
+use lib_ot::text_delta::{without_archived, DeltaTextOperations};
 use serde::de::{self, MapAccess, Unexpected, Visitor};
 use serde::ser::{SerializeMap, SerializeSeq};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
 use std::fmt;
 
 impl Serialize for Document {
@@ -253,6 +254,33 @@ impl DocumentNode {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Returns the node and its children without their archived text, see `without_archived`.
+    pub fn without_archived(self) -> Self {
+        DocumentNode {
+            delta: without_archived(&self.delta),
+            children: self.children.into_iter().map(DocumentNode::without_archived).collect(),
+            ..self
+        }
+    }
+}
+
+/// Removes the archived text from the `content` returned by `Document::get_content`.
+pub fn content_without_archived(content: &str) -> FlowyResult<String> {
+    let mut value: Value = serde_json::from_str(content).map_err(|err| FlowyError::serde().context(err))?;
+    if let Some(document) = value.get_mut("document") {
+        let nodes = match document {
+            Value::Array(nodes) => nodes.iter_mut().collect::<Vec<_>>(),
+            node => vec![node],
+        };
+        for node in nodes.into_iter().filter(|node| node.is_object()) {
+            let document_node: DocumentNode =
+                serde_json::from_value(node.take()).map_err(|err| FlowyError::serde().context(err))?;
+            *node = serde_json::to_value(document_node.without_archived())
+                .map_err(|err| FlowyError::serde().context(err))?;
+        }
+    }
+    serde_json::to_string(&value).map_err(|err| FlowyError::serde().context(err))
 }
 
 impl std::convert::From<NodeData> for DocumentNode {
@@ -323,7 +351,7 @@ impl<'a> Serialize for DocumentContentSerializer<'a> {
 #[cfg(test)]
 mod tests {
     use crate::editor::document::Document;
-    use crate::editor::document_serde::DocumentTransaction;
+    use crate::editor::document_serde::{content_without_archived, DocumentTransaction};
     use crate::editor::initial_read_me;
 
     #[test]
@@ -364,6 +392,15 @@ mod tests {
         let _ = serde_json::from_str::<DocumentTransaction>(json).unwrap();
     }
 
+    #[test]
+    fn content_without_archived_test() {
+        let content = r#"{"document":{"type":"editor","children":[{"type":"text","delta":[{"insert":"abc"},{"insert":" def","attributes":{"archived":true}}]},{"type":"text","delta":[{"insert":"ghi","attributes":{"archived":true}}]}]}}"#;
+        assert_eq!(
+            content_without_archived(content).unwrap(),
+            r#"{"document":{"type":"editor","children":[{"type":"text","delta":[{"insert":"abc"}]},{"type":"text"}]}}"#
+        );
+    }
+
     #[test]
     fn document_serde_test() {
         let document: Document = serde_json::from_str(EXAMPLE_DOCUMENT).unwrap();
@@ -2,7 +2,7 @@ use crate::editor::document::{Document, DocumentRevisionSerde};
 use crate::editor::document_serde::DocumentTransaction;
 use crate::editor::make_transaction_from_revisions;
 use crate::editor::queue::{Command, CommandSender, DocumentQueue};
-use crate::{DocumentEditor, DocumentUser};
+use crate::{DocumentEditor, DocumentPause, DocumentUser};
 use bytes::Bytes;
 use flowy_database::ConnectionPool;
 use flowy_error::{internal_error, FlowyError, FlowyResult};
@@ -44,6 +44,10 @@ impl AppFlowyDocumentEditor {
         Ok(editor)
     }
 
+    pub fn rev_manager(&self) -> Arc<RevisionManager<Arc<ConnectionPool>>> {
+        self.rev_manager.clone()
+    }
+
     pub async fn apply_transaction(&self, transaction: Transaction) -> FlowyResult<()> {
         let (ret, rx) = oneshot::channel::<FlowyResult<()>>();
         let _ = self
@@ -82,8 +86,9 @@ fn spawn_edit_queue(
     document: Document,
 ) -> CommandSender {
     let (sender, receiver) = mpsc::channel(1000);
+    let executor = rev_manager.executor().clone();
     let queue = DocumentQueue::new(user, rev_manager, document, receiver);
-    tokio::spawn(queue.run());
+    executor.spawn(queue.run());
     sender
 }
 
@@ -120,6 +125,21 @@ impl DocumentEditor for Arc<AppFlowyDocumentEditor> {
         })
     }
 
+    async fn flush(&self) -> FlowyResult<()> {
+        self.rev_manager.flush().await
+    }
+
+    async fn pause(&self) -> FlowyResult<DocumentPause> {
+        let (resume, resume_rx) = oneshot::channel();
+        let (ret, rx) = oneshot::channel::<FlowyResult<()>>();
+        let _ = self
+            .command_sender
+            .send(Command::Pause { resume: resume_rx, ret })
+            .await;
+        rx.await.map_err(internal_error)??;
+        Ok(DocumentPause::new(resume))
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
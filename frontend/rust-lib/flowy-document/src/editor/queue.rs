@@ -70,6 +70,10 @@ impl DocumentQueue {
                 let content = self.document.read().await.get_content(pretty)?;
                 let _ = ret.send(Ok(content));
             }
+            Command::Pause { resume, ret } => {
+                let _ = ret.send(Ok(()));
+                let _ = resume.await;
+            }
         }
         Ok(())
     }
@@ -87,6 +91,17 @@ pub(crate) type CommandReceiver = Receiver<Command>;
 pub(crate) type Ret<T> = oneshot::Sender<Result<T, FlowyError>>;
 
 pub enum Command {
-    ComposeTransaction { transaction: Transaction, ret: Ret<()> },
-    GetDocumentContent { pretty: bool, ret: Ret<String> },
+    ComposeTransaction {
+        transaction: Transaction,
+        ret: Ret<()>,
+    },
+    GetDocumentContent {
+        pretty: bool,
+        ret: Ret<String>,
+    },
+    /// Holds the queue until the `resume` is sent or dropped, see `DocumentPause`.
+    Pause {
+        resume: oneshot::Receiver<()>,
+        ret: Ret<()>,
+    },
 }
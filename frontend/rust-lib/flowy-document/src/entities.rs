@@ -1,5 +1,20 @@
-use crate::errors::ErrorCode;
+use crate::errors::{ErrorCode, FlowyError};
+use crate::services::rev_sqlite::PayloadDedupeSummary;
+use crate::services::{
+    DocumentContentHash, DocumentMeta, DocumentPreview, DocumentStartupReport, FindReplaceDocPreview,
+    FindReplaceDocReport, FindReplaceOutcome, FindReplacePreview, FindReplaceQuery, FindReplaceReport,
+    FindReplaceScope, FindReplaceSkip, InvalidRevision, MaintenanceReport, MaintenanceStatus, MaintenanceTask,
+    MaintenanceTaskReport, MigrationOutcome, PayloadIssue, RepairAuditEntry, RepairOutcome, Snippet, StartupPhase,
+    StoragePath, StoragePathKind,
+};
+use crate::ReexportSummary;
+use dart_notify::queue::NotificationQueueStats;
+use flowy_database::table_migration::MigrationProgress;
 use flowy_derive::{ProtoBuf, ProtoBuf_Enum};
+use flowy_sync::util::TracedTransform;
+use lib_ot::core::{AttributeHashMap, Interval};
+use lib_ot::text_delta::{DeltaTextOperations, FindOptions, SearchSnippet};
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
 
 #[derive(PartialEq, Eq, Debug, ProtoBuf_Enum, Clone)]
@@ -37,6 +52,11 @@ pub struct EditPayloadPB {
     // Encode in JSON format
     #[pb(index = 2)]
     pub operations: String,
+
+    /// Drops the inline attributes of the operations before they're applied, e.g. for pasting
+    /// without formatting. The headers and lists of the lines are kept. See `strip_all_attributes`.
+    #[pb(index = 3)]
+    pub strip_all_attributes: bool,
 }
 
 #[derive(Default)]
@@ -45,6 +65,8 @@ pub struct EditParams {
 
     // Encode in JSON format
     pub operations: String,
+
+    pub strip_all_attributes: bool,
 }
 
 impl TryInto<EditParams> for EditPayloadPB {
@@ -53,6 +75,7 @@ impl TryInto<EditParams> for EditPayloadPB {
         Ok(EditParams {
             doc_id: self.doc_id,
             operations: self.operations,
+            strip_all_attributes: self.strip_all_attributes,
         })
     }
 }
@@ -62,9 +85,187 @@ pub struct DocumentSnapshotPB {
     #[pb(index = 1)]
     pub doc_id: String,
 
-    /// Encode in JSON format
+    /// Encode in JSON format. Empty unless the `DocumentFieldPB::Full` was requested.
     #[pb(index = 2)]
     pub snapshot: String,
+
+    #[pb(index = 3, one_of)]
+    pub preview: Option<DocumentPreviewPB>,
+
+    #[pb(index = 4, one_of)]
+    pub meta: Option<DocumentMetaPB>,
+
+    /// True if the document is still being read, see `DocumentEvent::OpenLatestAvailable`. The
+    /// `meta` is set if it's known.
+    #[pb(index = 5)]
+    pub loading: bool,
+
+    /// Set instead of the `snapshot` if the `Full` document is larger than the
+    /// `DocumentConfig::streamed_open_threshold`, its json is read with `appflowy_doc_read_chunk`.
+    #[pb(index = 6, one_of)]
+    pub reader: Option<DocumentReaderPB>,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct OpenDocumentReaderPayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+}
+
+/// A reader of the json of a document, see `appflowy_doc_read_chunk`.
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct DocumentReaderPB {
+    #[pb(index = 1)]
+    pub handle: i64,
+
+    /// The number of bytes of the json.
+    #[pb(index = 2)]
+    pub len: i64,
+}
+
+/// The part of the document that the open event returns. The previews of the documents in a
+/// list only need the beginning of their text, the `Full` document may be megabytes.
+#[derive(PartialEq, Eq, Debug, ProtoBuf_Enum, Clone, Copy)]
+pub enum DocumentFieldPB {
+    Full = 0,
+    /// The first `DEFAULT_PREVIEW_LEN` graphemes of the visible text.
+    Preview = 1,
+    /// The counts and the revision ids only, see `DocumentMetaPB`.
+    Meta = 2,
+}
+
+impl std::default::Default for DocumentFieldPB {
+    fn default() -> Self {
+        Self::Full
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct DocumentPreviewPB {
+    #[pb(index = 1)]
+    pub text: String,
+
+    #[pb(index = 2)]
+    pub truncated: bool,
+}
+
+impl std::convert::From<DocumentPreview> for DocumentPreviewPB {
+    fn from(preview: DocumentPreview) -> Self {
+        Self {
+            text: preview.text,
+            truncated: preview.truncated,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct DocumentMetaPB {
+    #[pb(index = 1)]
+    pub rev_id: i64,
+
+    #[pb(index = 2, one_of)]
+    pub next_sync_rev_id: Option<i64>,
+
+    #[pb(index = 3)]
+    pub num_of_chars: i64,
+
+    #[pb(index = 4)]
+    pub num_of_words: i64,
+
+    /// The JSON value of each preference, see `DocPreferencesPB`.
+    #[pb(index = 5)]
+    pub preferences: HashMap<String, String>,
+}
+
+impl std::convert::From<DocumentMeta> for DocumentMetaPB {
+    fn from(meta: DocumentMeta) -> Self {
+        Self {
+            rev_id: meta.rev_id,
+            next_sync_rev_id: meta.next_sync_rev_id,
+            num_of_chars: meta.num_of_chars as i64,
+            num_of_words: meta.num_of_words as i64,
+            preferences: meta.preferences.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct RedlinePayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    #[pb(index = 2)]
+    pub from_rev_id: i64,
+
+    #[pb(index = 3)]
+    pub to_rev_id: i64,
+}
+
+/// The changes between two versions of a document. The `operations` is the json of a delta
+/// that consists of the text of both versions marked with the `insertion`, `deletion` or
+/// `format_changed` attribute. It's read-only, it can't be applied to the document.
+#[derive(Default, ProtoBuf)]
+pub struct RedlinePB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    #[pb(index = 2)]
+    pub operations: String,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct ExplainTransformPayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    #[pb(index = 2)]
+    pub local_rev_id: i64,
+
+    #[pb(index = 3)]
+    pub remote_rev_id: i64,
+}
+
+/// The replay of the transform of two revisions of a document, see `DocumentManager::explain_transform`.
+/// The deltas are the json of the operations, nothing is applied to the document.
+#[derive(Default, ProtoBuf)]
+pub struct TransformExplanationPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    #[pb(index = 2)]
+    pub local: String,
+
+    #[pb(index = 3)]
+    pub remote: String,
+
+    #[pb(index = 4)]
+    pub local_first: TracedTransformPB,
+
+    #[pb(index = 5)]
+    pub remote_first: TracedTransformPB,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct TracedTransformPB {
+    #[pb(index = 1)]
+    pub left_prime: String,
+
+    #[pb(index = 2)]
+    pub right_prime: String,
+
+    /// One line for each decision of the transform, in the order they were made.
+    #[pb(index = 3)]
+    pub steps: Vec<String>,
+}
+
+impl std::convert::From<TracedTransform<AttributeHashMap>> for TracedTransformPB {
+    fn from(transform: TracedTransform<AttributeHashMap>) -> Self {
+        Self {
+            left_prime: transform.left_prime.json_str(),
+            right_prime: transform.right_prime.json_str(),
+            steps: transform.steps.iter().map(|step| step.to_string()).collect(),
+        }
+    }
 }
 
 #[derive(Default, ProtoBuf)]
@@ -77,6 +278,10 @@ pub struct ExportPayloadPB {
 
     #[pb(index = 3)]
     pub document_version: DocumentVersionPB,
+
+    /// Exports the archived text too, it's left out by default.
+    #[pb(index = 4)]
+    pub include_archived: bool,
 }
 
 #[derive(PartialEq, Eq, Debug, ProtoBuf_Enum, Clone)]
@@ -102,6 +307,9 @@ pub struct OpenDocumentContextPB {
 
     #[pb(index = 2)]
     pub document_version: DocumentVersionPB,
+
+    #[pb(index = 3)]
+    pub fields: DocumentFieldPB,
 }
 
 #[derive(Default, Debug)]
@@ -109,6 +317,7 @@ pub struct ExportParams {
     pub view_id: String,
     pub export_type: ExportType,
     pub document_version: DocumentVersionPB,
+    pub include_archived: bool,
 }
 
 impl TryInto<ExportParams> for ExportPayloadPB {
@@ -118,6 +327,7 @@ impl TryInto<ExportParams> for ExportPayloadPB {
             view_id: self.view_id,
             export_type: self.export_type,
             document_version: self.document_version,
+            include_archived: self.include_archived,
         })
     }
 }
@@ -130,3 +340,1076 @@ pub struct ExportDataPB {
     #[pb(index = 2)]
     pub export_type: ExportType,
 }
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct TableMigrationProgressPB {
+    #[pb(index = 1)]
+    pub name: String,
+
+    #[pb(index = 2)]
+    pub copied: i64,
+
+    #[pb(index = 3)]
+    pub total: i64,
+}
+
+impl std::convert::From<MigrationProgress> for TableMigrationProgressPB {
+    fn from(progress: MigrationProgress) -> Self {
+        Self {
+            name: progress.name,
+            copied: progress.copied,
+            total: progress.total,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct DocumentStartupReportPB {
+    #[pb(index = 1)]
+    pub migrations: Vec<MigrationOutcomePB>,
+
+    #[pb(index = 2)]
+    pub num_of_documents: i64,
+
+    #[pb(index = 3)]
+    pub num_of_unsynced_revisions: i64,
+
+    #[pb(index = 4)]
+    pub warnings: Vec<String>,
+
+    #[pb(index = 5)]
+    pub phases: Vec<StartupPhasePB>,
+
+    #[pb(index = 6)]
+    pub total_duration_in_ms: i64,
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct MigrationOutcomePB {
+    #[pb(index = 1)]
+    pub name: String,
+
+    #[pb(index = 2)]
+    pub ran: bool,
+
+    #[pb(index = 3, one_of)]
+    pub error: Option<String>,
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct StartupPhasePB {
+    #[pb(index = 1)]
+    pub name: String,
+
+    #[pb(index = 2)]
+    pub duration_in_ms: i64,
+
+    #[pb(index = 3, one_of)]
+    pub error: Option<String>,
+}
+
+impl std::convert::From<DocumentStartupReport> for DocumentStartupReportPB {
+    fn from(report: DocumentStartupReport) -> Self {
+        let total_duration_in_ms = report.total_duration().as_millis() as i64;
+        Self {
+            migrations: report.migrations.into_iter().map(MigrationOutcomePB::from).collect(),
+            num_of_documents: report.num_of_documents as i64,
+            num_of_unsynced_revisions: report.num_of_unsynced_revisions,
+            warnings: report.warnings,
+            phases: report.phases.into_iter().map(StartupPhasePB::from).collect(),
+            total_duration_in_ms,
+        }
+    }
+}
+
+impl std::convert::From<MigrationOutcome> for MigrationOutcomePB {
+    fn from(outcome: MigrationOutcome) -> Self {
+        Self {
+            name: outcome.name,
+            ran: outcome.ran,
+            error: outcome.error,
+        }
+    }
+}
+
+impl std::convert::From<StartupPhase> for StartupPhasePB {
+    fn from(phase: StartupPhase) -> Self {
+        Self {
+            name: phase.name,
+            duration_in_ms: phase.duration.as_millis() as i64,
+            error: phase.error,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, ProtoBuf_Enum, Clone)]
+pub enum StoragePathKindPB {
+    Database = 0,
+    WriteAheadLog = 1,
+    SharedMemory = 2,
+    Journal = 3,
+}
+
+impl Default for StoragePathKindPB {
+    fn default() -> Self {
+        StoragePathKindPB::Database
+    }
+}
+
+impl std::convert::From<StoragePathKind> for StoragePathKindPB {
+    fn from(kind: StoragePathKind) -> Self {
+        match kind {
+            StoragePathKind::Database => StoragePathKindPB::Database,
+            StoragePathKind::WriteAheadLog => StoragePathKindPB::WriteAheadLog,
+            StoragePathKind::SharedMemory => StoragePathKindPB::SharedMemory,
+            StoragePathKind::Journal => StoragePathKindPB::Journal,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct StoragePathPB {
+    #[pb(index = 1)]
+    pub kind: StoragePathKindPB,
+
+    #[pb(index = 2)]
+    pub path: String,
+
+    #[pb(index = 3)]
+    pub safe_to_copy_while_running: bool,
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct StoragePathsPB {
+    #[pb(index = 1)]
+    pub items: Vec<StoragePathPB>,
+}
+
+impl std::convert::From<StoragePath> for StoragePathPB {
+    fn from(path: StoragePath) -> Self {
+        Self {
+            kind: path.kind.into(),
+            path: path.path.to_string_lossy().into_owned(),
+            safe_to_copy_while_running: path.safe_to_copy_while_running,
+        }
+    }
+}
+
+impl std::convert::From<Vec<StoragePath>> for StoragePathsPB {
+    fn from(paths: Vec<StoragePath>) -> Self {
+        Self {
+            items: paths.into_iter().map(StoragePathPB::from).collect(),
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct ValidateDocsPayloadPB {
+    /// Recovers or quarantines the invalid revisions after validating them.
+    #[pb(index = 1)]
+    pub repair: bool,
+}
+
+#[derive(PartialEq, Eq, Debug, ProtoBuf_Enum, Clone)]
+pub enum PayloadIssuePB {
+    InvalidUtf8 = 0,
+    UndecodableJson = 1,
+}
+
+impl Default for PayloadIssuePB {
+    fn default() -> Self {
+        PayloadIssuePB::InvalidUtf8
+    }
+}
+
+impl std::convert::From<PayloadIssue> for PayloadIssuePB {
+    fn from(issue: PayloadIssue) -> Self {
+        match issue {
+            PayloadIssue::InvalidUtf8 => PayloadIssuePB::InvalidUtf8,
+            PayloadIssue::UndecodableJson => PayloadIssuePB::UndecodableJson,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, ProtoBuf_Enum, Clone)]
+pub enum RepairOutcomePB {
+    Recovered = 0,
+    Quarantined = 1,
+}
+
+impl Default for RepairOutcomePB {
+    fn default() -> Self {
+        RepairOutcomePB::Recovered
+    }
+}
+
+impl std::convert::From<RepairOutcome> for RepairOutcomePB {
+    fn from(outcome: RepairOutcome) -> Self {
+        match outcome {
+            RepairOutcome::Recovered => RepairOutcomePB::Recovered,
+            RepairOutcome::Quarantined => RepairOutcomePB::Quarantined,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct InvalidRevisionPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    #[pb(index = 2)]
+    pub rev_id: i64,
+
+    #[pb(index = 3)]
+    pub issue: PayloadIssuePB,
+
+    #[pb(index = 4)]
+    pub error: String,
+}
+
+impl std::convert::From<InvalidRevision> for InvalidRevisionPB {
+    fn from(revision: InvalidRevision) -> Self {
+        Self {
+            doc_id: revision.doc_id,
+            rev_id: revision.rev_id,
+            issue: revision.issue.into(),
+            error: revision.error,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct RepairAuditEntryPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    #[pb(index = 2)]
+    pub rev_id: i64,
+
+    #[pb(index = 3)]
+    pub issue: PayloadIssuePB,
+
+    #[pb(index = 4)]
+    pub outcome: RepairOutcomePB,
+
+    #[pb(index = 5)]
+    pub detail: String,
+
+    #[pb(index = 6)]
+    pub timestamp: i64,
+}
+
+impl std::convert::From<RepairAuditEntry> for RepairAuditEntryPB {
+    fn from(entry: RepairAuditEntry) -> Self {
+        Self {
+            doc_id: entry.doc_id,
+            rev_id: entry.rev_id,
+            issue: entry.issue.into(),
+            outcome: entry.outcome.into(),
+            detail: entry.detail,
+            timestamp: entry.timestamp,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct DocumentIntegrityPB {
+    #[pb(index = 1)]
+    pub invalid_revisions: Vec<InvalidRevisionPB>,
+
+    /// Empty unless the repair was requested.
+    #[pb(index = 2)]
+    pub repairs: Vec<RepairAuditEntryPB>,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct ResumeSyncPayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    /// Replaces the document with the server's document instead of sending its revisions.
+    #[pb(index = 2)]
+    pub force_reset: bool,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct SyncHealthPayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct SyncHealthPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    /// The local revisions that the server didn't ack yet.
+    #[pb(index = 2)]
+    pub unsynced_revisions: i64,
+
+    /// The revisions pushed by the server that aren't applied yet.
+    #[pb(index = 3)]
+    pub remote_backlog: i64,
+
+    /// The rev_ids of the revisions that were given up pushing, see `ResumeSync`.
+    #[pb(index = 4)]
+    pub dead_letters: Vec<i64>,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct FocusDocumentPayloadPB {
+    /// The focused document, empty when no document is focused.
+    #[pb(index = 1)]
+    pub doc_id: String,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct ContentHashPayloadPB {
+    #[pb(index = 1)]
+    pub doc_ids: Vec<String>,
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct DocumentContentHashPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    /// The revision that the hash was computed at.
+    #[pb(index = 2)]
+    pub rev_id: i64,
+
+    /// The md5 of the canonical content, see `content_hash`.
+    #[pb(index = 3)]
+    pub hash: String,
+}
+
+impl std::convert::From<DocumentContentHash> for DocumentContentHashPB {
+    fn from(content_hash: DocumentContentHash) -> Self {
+        Self {
+            doc_id: content_hash.doc_id,
+            rev_id: content_hash.rev_id,
+            hash: content_hash.hash,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct RepeatedDocumentContentHashPB {
+    #[pb(index = 1)]
+    pub items: Vec<DocumentContentHashPB>,
+}
+
+impl std::convert::From<Vec<DocumentContentHash>> for RepeatedDocumentContentHashPB {
+    fn from(content_hashes: Vec<DocumentContentHash>) -> Self {
+        Self {
+            items: content_hashes.into_iter().map(DocumentContentHashPB::from).collect(),
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct ExportPortablePayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    /// The file to write, see `PORTABLE_DOCUMENT_EXTENSION`.
+    #[pb(index = 2)]
+    pub path: String,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct RestoreBackupPayloadPB {
+    /// The folder of the backup, e.g. one of the folders under the `backups` folder of the user.
+    #[pb(index = 1)]
+    pub path: String,
+
+    /// Restores the backup even if there are unsynced revisions, they're lost.
+    #[pb(index = 2)]
+    pub force: bool,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct SaveSnippetPayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    /// The start of the selection, in utf16 code units.
+    #[pb(index = 2)]
+    pub start: i64,
+
+    /// The end of the selection, exclusive.
+    #[pb(index = 3)]
+    pub end: i64,
+
+    #[pb(index = 4)]
+    pub name: String,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct InsertSnippetPayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    #[pb(index = 2)]
+    pub index: i64,
+
+    #[pb(index = 3)]
+    pub snippet_id: String,
+}
+
+/// The number of characters of the preview that is shown in the snippet picker.
+pub const SNIPPET_PREVIEW_LEN: usize = 80;
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct SnippetPB {
+    #[pb(index = 1)]
+    pub id: String,
+
+    #[pb(index = 2)]
+    pub name: String,
+
+    /// The plain text of the snippet, cut to `SNIPPET_PREVIEW_LEN` characters.
+    #[pb(index = 3)]
+    pub preview: String,
+
+    /// The json of the delta that is inserted.
+    #[pb(index = 4)]
+    pub operations: String,
+
+    #[pb(index = 5)]
+    pub create_time: i64,
+}
+
+impl std::convert::From<Snippet> for SnippetPB {
+    fn from(snippet: Snippet) -> Self {
+        Self {
+            preview: snippet.preview(SNIPPET_PREVIEW_LEN),
+            operations: snippet.operations.json_str(),
+            id: snippet.id,
+            name: snippet.name,
+            create_time: snippet.create_time,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct RepeatedSnippetPB {
+    #[pb(index = 1)]
+    pub items: Vec<SnippetPB>,
+}
+
+impl TryInto<Snippet> for SnippetPB {
+    type Error = FlowyError;
+    fn try_into(self) -> Result<Snippet, Self::Error> {
+        let operations = DeltaTextOperations::from_json(&self.operations)
+            .map_err(|e| FlowyError::invalid_data().context(format!("The snippet {} is invalid: {:?}", self.id, e)))?;
+        Ok(Snippet {
+            id: self.id,
+            name: self.name,
+            operations,
+            create_time: self.create_time,
+        })
+    }
+}
+
+impl std::convert::From<Vec<Snippet>> for RepeatedSnippetPB {
+    fn from(snippets: Vec<Snippet>) -> Self {
+        Self {
+            items: snippets.into_iter().map(SnippetPB::from).collect(),
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone, PartialEq, Eq)]
+pub struct SelectionRangePB {
+    /// The start of the range, in utf16 code units.
+    #[pb(index = 1)]
+    pub start: i64,
+
+    /// The end of the range, exclusive. It equals to the start if the range is the cursor.
+    #[pb(index = 2)]
+    pub end: i64,
+}
+
+impl std::convert::From<Interval> for SelectionRangePB {
+    fn from(interval: Interval) -> Self {
+        Self {
+            start: interval.start as i64,
+            end: interval.end as i64,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct UpdateSelectionPayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    /// The ranges of a multi-range selection are transformed independently.
+    #[pb(index = 2)]
+    pub ranges: Vec<SelectionRangePB>,
+}
+
+/// Sent with `DidReceiveRemoteChange` after the remote operations are applied to the document.
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct DocumentChangePB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    /// The json of the operations that were composed into the document.
+    #[pb(index = 2)]
+    pub operations: String,
+
+    /// The selection of the last `UpdateSelection` moved along with the text.
+    #[pb(index = 3)]
+    pub selection: Vec<SelectionRangePB>,
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct ReexportSummaryPB {
+    #[pb(index = 1)]
+    pub num_of_regenerated: i64,
+
+    #[pb(index = 2)]
+    pub num_of_skipped: i64,
+
+    #[pb(index = 3)]
+    pub num_of_failed: i64,
+
+    #[pb(index = 4)]
+    pub cancelled: bool,
+}
+
+impl std::convert::From<ReexportSummary> for ReexportSummaryPB {
+    fn from(summary: ReexportSummary) -> Self {
+        Self {
+            num_of_regenerated: summary.num_of_regenerated as i64,
+            num_of_skipped: summary.num_of_skipped as i64,
+            num_of_failed: summary.num_of_failed as i64,
+            cancelled: summary.cancelled,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct PayloadDedupeSummaryPB {
+    #[pb(index = 1)]
+    pub num_of_revisions: i64,
+
+    #[pb(index = 2)]
+    pub num_of_payloads: i64,
+}
+
+impl std::convert::From<PayloadDedupeSummary> for PayloadDedupeSummaryPB {
+    fn from(summary: PayloadDedupeSummary) -> Self {
+        Self {
+            num_of_revisions: summary.num_of_revisions as i64,
+            num_of_payloads: summary.num_of_payloads as i64,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct NotificationQueueStatsPB {
+    /// The notifications that wait for the Flutter side.
+    #[pb(index = 1)]
+    pub depth: i64,
+
+    #[pb(index = 2)]
+    pub peak_depth: i64,
+
+    #[pb(index = 3)]
+    pub num_of_dropped: i64,
+}
+
+impl std::convert::From<NotificationQueueStats> for NotificationQueueStatsPB {
+    fn from(stats: NotificationQueueStats) -> Self {
+        Self {
+            depth: stats.depth as i64,
+            peak_depth: stats.peak_depth as i64,
+            num_of_dropped: stats.num_of_dropped as i64,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct DictionaryWordPayloadPB {
+    #[pb(index = 1)]
+    pub workspace_id: String,
+
+    #[pb(index = 2)]
+    pub word: String,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct CustomDictionaryIdPB {
+    #[pb(index = 1)]
+    pub workspace_id: String,
+}
+
+/// The words of the workspace's custom dictionary. It's also sent with the
+/// `DidUpdateCustomDictionary` notification after the remote changes update the dictionary.
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct CustomDictionaryPB {
+    #[pb(index = 1)]
+    pub workspace_id: String,
+
+    /// The words in alphabetical order.
+    #[pb(index = 2)]
+    pub words: Vec<String>,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct RecoverTextPayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct RecoveredTextPB {
+    /// The file that keeps the recovered text, see `RECOVERED_MARKER`.
+    #[pb(index = 1)]
+    pub path: String,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct DocPreferencesIdPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct DocPreferencePayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    #[pb(index = 2)]
+    pub key: String,
+
+    /// Encode in JSON format, e.g. `14` for the font size or `true` for the focus mode.
+    #[pb(index = 3)]
+    pub value: String,
+}
+
+/// The preferences of a document, e.g. its font size, its width or whether it's in focus mode.
+/// It's also sent with the `DidUpdateDocPreferences` notification after they change.
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct DocPreferencesPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    /// The JSON value of each preference.
+    #[pb(index = 2)]
+    pub preferences: HashMap<String, String>,
+}
+
+impl DocPreferencesPB {
+    pub fn new(doc_id: &str, preferences: &BTreeMap<String, String>) -> Self {
+        Self {
+            doc_id: doc_id.to_owned(),
+            preferences: preferences
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct RunMaintenancePayloadPB {
+    /// The bits of `MaintenanceTasks`, e.g. `MaintenanceTasks::ALL`.
+    #[pb(index = 1)]
+    pub tasks: i32,
+
+    #[pb(index = 2)]
+    pub dry_run: bool,
+}
+
+#[derive(PartialEq, Eq, Debug, ProtoBuf_Enum, Clone)]
+pub enum MaintenanceTaskPB {
+    Flush = 0,
+    Compaction = 1,
+    Purge = 2,
+    BlobGc = 3,
+    Vacuum = 4,
+    SnapshotRefresh = 5,
+}
+
+impl Default for MaintenanceTaskPB {
+    fn default() -> Self {
+        MaintenanceTaskPB::Flush
+    }
+}
+
+impl std::convert::From<MaintenanceTask> for MaintenanceTaskPB {
+    fn from(task: MaintenanceTask) -> Self {
+        match task {
+            MaintenanceTask::Flush => MaintenanceTaskPB::Flush,
+            MaintenanceTask::Compaction => MaintenanceTaskPB::Compaction,
+            MaintenanceTask::Purge => MaintenanceTaskPB::Purge,
+            MaintenanceTask::BlobGc => MaintenanceTaskPB::BlobGc,
+            MaintenanceTask::Vacuum => MaintenanceTaskPB::Vacuum,
+            MaintenanceTask::SnapshotRefresh => MaintenanceTaskPB::SnapshotRefresh,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, ProtoBuf_Enum, Clone)]
+pub enum MaintenanceStatusPB {
+    Done = 0,
+    NoDryRun = 1,
+    Skipped = 2,
+    Failed = 3,
+}
+
+impl Default for MaintenanceStatusPB {
+    fn default() -> Self {
+        MaintenanceStatusPB::Done
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct MaintenanceTaskReportPB {
+    #[pb(index = 1)]
+    pub task: MaintenanceTaskPB,
+
+    #[pb(index = 2)]
+    pub status: MaintenanceStatusPB,
+
+    /// The error of the failed task, or the failed task that the skipped task depends on.
+    #[pb(index = 3, one_of)]
+    pub reason: Option<String>,
+
+    #[pb(index = 4)]
+    pub duration_in_ms: i64,
+
+    #[pb(index = 5)]
+    pub reclaimed_bytes: i64,
+}
+
+impl std::convert::From<MaintenanceTaskReport> for MaintenanceTaskReportPB {
+    fn from(report: MaintenanceTaskReport) -> Self {
+        let (status, reason) = match report.status {
+            MaintenanceStatus::Done => (MaintenanceStatusPB::Done, None),
+            MaintenanceStatus::NoDryRun => (MaintenanceStatusPB::NoDryRun, None),
+            MaintenanceStatus::Skipped(dependency) => (MaintenanceStatusPB::Skipped, Some(format!("{:?}", dependency))),
+            MaintenanceStatus::Failed(error) => (MaintenanceStatusPB::Failed, Some(error)),
+        };
+        Self {
+            task: report.task.into(),
+            status,
+            reason,
+            duration_in_ms: report.duration.as_millis() as i64,
+            reclaimed_bytes: report.reclaimed_bytes as i64,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct MaintenanceReportPB {
+    #[pb(index = 1)]
+    pub dry_run: bool,
+
+    /// In the order the tasks ran.
+    #[pb(index = 2)]
+    pub tasks: Vec<MaintenanceTaskReportPB>,
+
+    #[pb(index = 3)]
+    pub total_duration_in_ms: i64,
+
+    #[pb(index = 4)]
+    pub total_reclaimed_bytes: i64,
+}
+
+impl std::convert::From<MaintenanceReport> for MaintenanceReportPB {
+    fn from(report: MaintenanceReport) -> Self {
+        let total_duration_in_ms = report.total_duration().as_millis() as i64;
+        let total_reclaimed_bytes = report.total_reclaimed_bytes() as i64;
+        Self {
+            dry_run: report.dry_run,
+            tasks: report.tasks.into_iter().map(MaintenanceTaskReportPB::from).collect(),
+            total_duration_in_ms,
+            total_reclaimed_bytes,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct FindReplacePayloadPB {
+    /// Looks into every document saved on this device if it's true, otherwise into the
+    /// `doc_ids`.
+    #[pb(index = 1)]
+    pub all_documents: bool,
+
+    #[pb(index = 2)]
+    pub doc_ids: Vec<String>,
+
+    #[pb(index = 3)]
+    pub pattern: String,
+
+    #[pb(index = 4)]
+    pub replacement: String,
+
+    #[pb(index = 5)]
+    pub case_sensitive: bool,
+
+    #[pb(index = 6)]
+    pub whole_word: bool,
+
+    /// Looks into the documents of the workspace if it's set and `all_documents` is false.
+    #[pb(index = 7)]
+    pub workspace_id: String,
+
+    /// Looks into the documents of the app if it's set and neither `all_documents` nor the
+    /// `workspace_id` is.
+    #[pb(index = 8)]
+    pub app_id: String,
+}
+
+impl FindReplacePayloadPB {
+    pub fn scope(&self) -> FindReplaceScope {
+        if self.all_documents {
+            FindReplaceScope::All
+        } else if !self.workspace_id.is_empty() {
+            FindReplaceScope::Workspace(self.workspace_id.clone())
+        } else if !self.app_id.is_empty() {
+            FindReplaceScope::App(self.app_id.clone())
+        } else {
+            FindReplaceScope::DocIds(self.doc_ids.clone())
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct FindReplaceSnippetPB {
+    #[pb(index = 1)]
+    pub range: SelectionRangePB,
+
+    #[pb(index = 2)]
+    pub text: String,
+}
+
+#[derive(PartialEq, Eq, Debug, ProtoBuf_Enum, Clone)]
+pub enum FindReplaceSkipPB {
+    NotSkipped = 0,
+    ReadOnly = 1,
+    Locked = 2,
+}
+
+impl Default for FindReplaceSkipPB {
+    fn default() -> Self {
+        FindReplaceSkipPB::NotSkipped
+    }
+}
+
+impl std::convert::From<Option<FindReplaceSkip>> for FindReplaceSkipPB {
+    fn from(skip: Option<FindReplaceSkip>) -> Self {
+        match skip {
+            None => FindReplaceSkipPB::NotSkipped,
+            Some(FindReplaceSkip::ReadOnly) => FindReplaceSkipPB::ReadOnly,
+            Some(FindReplaceSkip::Locked) => FindReplaceSkipPB::Locked,
+        }
+    }
+}
+
+impl std::convert::From<FindReplaceSkipPB> for Option<FindReplaceSkip> {
+    fn from(skip: FindReplaceSkipPB) -> Self {
+        match skip {
+            FindReplaceSkipPB::NotSkipped => None,
+            FindReplaceSkipPB::ReadOnly => Some(FindReplaceSkip::ReadOnly),
+            FindReplaceSkipPB::Locked => Some(FindReplaceSkip::Locked),
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct FindReplaceDocPreviewPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    #[pb(index = 2)]
+    pub content_hash: String,
+
+    #[pb(index = 3)]
+    pub replaced_hash: String,
+
+    #[pb(index = 4)]
+    pub snippets: Vec<FindReplaceSnippetPB>,
+
+    #[pb(index = 5)]
+    pub skip: FindReplaceSkipPB,
+}
+
+/// The preview of the replace. It's passed back as is to `ApplyFindReplace`.
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct FindReplacePreviewPB {
+    #[pb(index = 1)]
+    pub pattern: String,
+
+    #[pb(index = 2)]
+    pub replacement: String,
+
+    #[pb(index = 3)]
+    pub case_sensitive: bool,
+
+    #[pb(index = 4)]
+    pub whole_word: bool,
+
+    #[pb(index = 5)]
+    pub documents: Vec<FindReplaceDocPreviewPB>,
+
+    #[pb(index = 6)]
+    pub num_of_matches: i64,
+}
+
+impl std::convert::From<FindReplacePreview> for FindReplacePreviewPB {
+    fn from(preview: FindReplacePreview) -> Self {
+        let num_of_matches = preview.num_of_matches() as i64;
+        let documents = preview
+            .documents
+            .into_iter()
+            .map(|document| FindReplaceDocPreviewPB {
+                doc_id: document.doc_id,
+                content_hash: document.content_hash,
+                replaced_hash: document.replaced_hash,
+                snippets: document
+                    .snippets
+                    .into_iter()
+                    .map(|snippet| FindReplaceSnippetPB {
+                        range: snippet.interval.into(),
+                        text: snippet.text,
+                    })
+                    .collect(),
+                skip: document.skip.into(),
+            })
+            .collect();
+        Self {
+            pattern: preview.query.pattern,
+            replacement: preview.query.replacement,
+            case_sensitive: preview.query.options.case_sensitive,
+            whole_word: preview.query.options.whole_word,
+            documents,
+            num_of_matches,
+        }
+    }
+}
+
+impl std::convert::From<FindReplacePreviewPB> for FindReplacePreview {
+    fn from(pb: FindReplacePreviewPB) -> Self {
+        let documents = pb
+            .documents
+            .into_iter()
+            .map(|document| FindReplaceDocPreview {
+                doc_id: document.doc_id,
+                content_hash: document.content_hash,
+                replaced_hash: document.replaced_hash,
+                snippets: document
+                    .snippets
+                    .into_iter()
+                    .map(|snippet| SearchSnippet {
+                        interval: Interval::new(snippet.range.start as usize, snippet.range.end as usize),
+                        text: snippet.text,
+                    })
+                    .collect(),
+                skip: document.skip.into(),
+            })
+            .collect();
+        Self {
+            query: FindReplaceQuery {
+                pattern: pb.pattern,
+                replacement: pb.replacement,
+                options: FindOptions {
+                    case_sensitive: pb.case_sensitive,
+                    whole_word: pb.whole_word,
+                },
+            },
+            documents,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, ProtoBuf_Enum, Clone)]
+pub enum FindReplaceOutcomePB {
+    Replaced = 0,
+    AlreadyReplaced = 1,
+    Stale = 2,
+    Skipped = 3,
+    Failed = 4,
+}
+
+impl Default for FindReplaceOutcomePB {
+    fn default() -> Self {
+        FindReplaceOutcomePB::Replaced
+    }
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct FindReplaceDocReportPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    #[pb(index = 2)]
+    pub outcome: FindReplaceOutcomePB,
+
+    #[pb(index = 3)]
+    pub num_of_replaced: i64,
+
+    /// Why the document was skipped or the error it failed with.
+    #[pb(index = 4, one_of)]
+    pub reason: Option<String>,
+}
+
+impl std::convert::From<FindReplaceDocReport> for FindReplaceDocReportPB {
+    fn from(report: FindReplaceDocReport) -> Self {
+        let (outcome, num_of_replaced, reason) = match report.outcome {
+            FindReplaceOutcome::Replaced(n) => (FindReplaceOutcomePB::Replaced, n as i64, None),
+            FindReplaceOutcome::AlreadyReplaced => (FindReplaceOutcomePB::AlreadyReplaced, 0, None),
+            FindReplaceOutcome::Stale => (FindReplaceOutcomePB::Stale, 0, None),
+            FindReplaceOutcome::Skipped(skip) => (FindReplaceOutcomePB::Skipped, 0, Some(skip.reason().to_owned())),
+            FindReplaceOutcome::Failed(error) => (FindReplaceOutcomePB::Failed, 0, Some(error)),
+        };
+        Self {
+            doc_id: report.doc_id,
+            outcome,
+            num_of_replaced,
+            reason,
+        }
+    }
+}
+
+/// The outcome of each document of the preview, in the order of the preview.
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct FindReplaceReportPB {
+    #[pb(index = 1)]
+    pub items: Vec<FindReplaceDocReportPB>,
+
+    #[pb(index = 2)]
+    pub num_of_replaced: i64,
+}
+
+impl std::convert::From<FindReplaceReport> for FindReplaceReportPB {
+    fn from(report: FindReplaceReport) -> Self {
+        let num_of_replaced = report.num_of_replaced() as i64;
+        Self {
+            items: report.documents.into_iter().map(FindReplaceDocReportPB::from).collect(),
+            num_of_replaced,
+        }
+    }
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct ExportRevGraphPayloadPB {
+    #[pb(index = 1)]
+    pub doc_id: String,
+
+    /// Only the last `tail` revisions are drawn, all of them if it's not set.
+    #[pb(index = 2, one_of)]
+    pub tail: Option<i64>,
+}
+
+#[derive(Default, ProtoBuf, Debug, Clone)]
+pub struct RevGraphPB {
+    /// The Graphviz DOT digraph of the revision chain, see `DocumentManager::export_rev_graph`.
+    #[pb(index = 1)]
+    pub dot: String,
+}
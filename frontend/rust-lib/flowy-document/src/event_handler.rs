@@ -1,11 +1,26 @@
 use crate::entities::{
-    DocumentSnapshotPB, EditParams, EditPayloadPB, ExportDataPB, ExportParams, ExportPayloadPB, OpenDocumentContextPB,
+    ContentHashPayloadPB, CustomDictionaryIdPB, CustomDictionaryPB, DictionaryWordPayloadPB, DocPreferencePayloadPB,
+    DocPreferencesIdPB, DocPreferencesPB, DocumentFieldPB, DocumentIntegrityPB, DocumentMetaPB, DocumentReaderPB,
+    DocumentSnapshotPB, DocumentStartupReportPB, EditParams, EditPayloadPB, ExplainTransformPayloadPB, ExportDataPB,
+    ExportParams, ExportPayloadPB, ExportPortablePayloadPB, ExportRevGraphPayloadPB, FindReplacePayloadPB,
+    FindReplacePreviewPB, FindReplaceReportPB, FocusDocumentPayloadPB, InsertSnippetPayloadPB, MaintenanceReportPB,
+    NotificationQueueStatsPB, OpenDocumentContextPB, OpenDocumentReaderPayloadPB, PayloadDedupeSummaryPB,
+    RecoverTextPayloadPB, RecoveredTextPB, RedlinePB, RedlinePayloadPB, ReexportSummaryPB,
+    RepeatedDocumentContentHashPB, RepeatedSnippetPB, RestoreBackupPayloadPB, ResumeSyncPayloadPB, RevGraphPB,
+    RunMaintenancePayloadPB, SaveSnippetPayloadPB, SnippetPB, StoragePathsPB, SyncHealthPB, SyncHealthPayloadPB,
+    TransformExplanationPB, UpdateSelectionPayloadPB, ValidateDocsPayloadPB,
 };
-use crate::DocumentManager;
+use crate::services::{
+    AvailableDocument, DocumentContent, FindReplacePreview, FindReplaceQuery, MaintenanceTasks, Snippet,
+};
+use crate::{DocumentManager, DEFAULT_PREVIEW_LEN};
 use flowy_error::FlowyError;
+use lib_ot::core::Interval;
+use lib_ot::text_delta::FindOptions;
 
 use lib_dispatch::prelude::{data_result, AFPluginData, AFPluginState, DataResult};
 use std::convert::TryInto;
+use std::path::Path;
 use std::sync::Arc;
 
 pub(crate) async fn get_document_handler(
@@ -13,12 +28,30 @@ pub(crate) async fn get_document_handler(
     manager: AFPluginState<Arc<DocumentManager>>,
 ) -> DataResult<DocumentSnapshotPB, FlowyError> {
     let context: OpenDocumentContextPB = data.into_inner();
-    let editor = manager.open_document_editor(&context.document_id).await?;
-    let document_data = editor.export().await?;
-    data_result(DocumentSnapshotPB {
+    let mut snapshot = DocumentSnapshotPB {
         doc_id: context.document_id,
-        snapshot: document_data,
-    })
+        ..Default::default()
+    };
+    match context.fields {
+        DocumentFieldPB::Full => match manager.read_document_content(&snapshot.doc_id).await? {
+            DocumentContent::Inline(json) => snapshot.snapshot = json,
+            DocumentContent::Streamed { handle, len } => {
+                snapshot.reader = Some(DocumentReaderPB {
+                    handle,
+                    len: len as i64,
+                })
+            }
+        },
+        DocumentFieldPB::Preview => {
+            let preview = manager.document_preview(&snapshot.doc_id, DEFAULT_PREVIEW_LEN).await?;
+            snapshot.preview = Some(preview.into());
+        }
+        DocumentFieldPB::Meta => {
+            let meta = manager.document_meta(&snapshot.doc_id).await?;
+            snapshot.meta = Some(meta.into());
+        }
+    }
+    data_result(snapshot)
 }
 
 pub(crate) async fn apply_edit_handler(
@@ -36,10 +69,391 @@ pub(crate) async fn export_handler(
     manager: AFPluginState<Arc<DocumentManager>>,
 ) -> DataResult<ExportDataPB, FlowyError> {
     let params: ExportParams = data.into_inner().try_into()?;
-    let editor = manager.open_document_editor(&params.view_id).await?;
-    let document_data = editor.export().await?;
+    let document_data = manager
+        .export_document(&params.view_id, params.include_archived)
+        .await?;
     data_result(ExportDataPB {
         data: document_data,
         export_type: params.export_type,
     })
 }
+
+pub(crate) async fn get_startup_report_handler(
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<DocumentStartupReportPB, FlowyError> {
+    match manager.startup_report().await {
+        None => Err(FlowyError::record_not_found().context("The document module isn't initialized")),
+        Some(report) => data_result(report.into()),
+    }
+}
+
+pub(crate) async fn get_redline_handler(
+    data: AFPluginData<RedlinePayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<RedlinePB, FlowyError> {
+    let payload: RedlinePayloadPB = data.into_inner();
+    let redline = manager
+        .redline(&payload.doc_id, payload.from_rev_id, payload.to_rev_id)
+        .await?;
+    data_result(RedlinePB {
+        doc_id: payload.doc_id,
+        operations: redline.json_str(),
+    })
+}
+
+pub(crate) async fn explain_transform_handler(
+    data: AFPluginData<ExplainTransformPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<TransformExplanationPB, FlowyError> {
+    let payload: ExplainTransformPayloadPB = data.into_inner();
+    let explanation = manager
+        .explain_transform(&payload.doc_id, payload.local_rev_id, payload.remote_rev_id)
+        .await?;
+    data_result(TransformExplanationPB {
+        doc_id: payload.doc_id,
+        local: explanation.local.json_str(),
+        remote: explanation.remote.json_str(),
+        local_first: explanation.local_first.into(),
+        remote_first: explanation.remote_first.into(),
+    })
+}
+
+pub(crate) async fn query_storage_paths_handler(
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<StoragePathsPB, FlowyError> {
+    let paths = manager.storage_paths()?;
+    data_result(paths.into())
+}
+
+pub(crate) async fn validate_all_docs_handler(
+    data: AFPluginData<ValidateDocsPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<DocumentIntegrityPB, FlowyError> {
+    let payload: ValidateDocsPayloadPB = data.into_inner();
+    let invalid_revisions = manager.validate_all_documents()?;
+    let repairs = if payload.repair && !invalid_revisions.is_empty() {
+        manager.repair_all_documents()?
+    } else {
+        vec![]
+    };
+    data_result(DocumentIntegrityPB {
+        invalid_revisions: invalid_revisions.into_iter().map(|revision| revision.into()).collect(),
+        repairs: repairs.into_iter().map(|entry| entry.into()).collect(),
+    })
+}
+
+pub(crate) async fn dedupe_revision_payloads_handler(
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<PayloadDedupeSummaryPB, FlowyError> {
+    let summary = manager.dedupe_revision_payloads()?;
+    data_result(summary.into())
+}
+
+pub(crate) async fn get_notification_queue_stats_handler(
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<NotificationQueueStatsPB, FlowyError> {
+    data_result(manager.notification_queue_stats().into())
+}
+
+pub(crate) async fn save_selection_as_snippet_handler(
+    data: AFPluginData<SaveSnippetPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<SnippetPB, FlowyError> {
+    let payload: SaveSnippetPayloadPB = data.into_inner();
+    if payload.start < 0 || payload.end < payload.start {
+        return Err(
+            FlowyError::out_of_bounds().context(format!("Invalid selection: {}..{}", payload.start, payload.end))
+        );
+    }
+    let interval = Interval::new(payload.start as usize, payload.end as usize);
+    let snippet = manager
+        .save_selection_as_snippet(&payload.doc_id, interval, &payload.name)
+        .await?;
+    data_result(snippet.into())
+}
+
+pub(crate) async fn get_snippets_handler(
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<RepeatedSnippetPB, FlowyError> {
+    let snippets = manager.snippets()?;
+    data_result(snippets.into())
+}
+
+pub(crate) async fn import_snippets_handler(
+    data: AFPluginData<RepeatedSnippetPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<RepeatedSnippetPB, FlowyError> {
+    let snippets = data
+        .into_inner()
+        .items
+        .into_iter()
+        .map(|snippet| snippet.try_into())
+        .collect::<Result<Vec<Snippet>, FlowyError>>()?;
+    let _ = manager.import_snippets(&snippets)?;
+    data_result(manager.snippets()?.into())
+}
+
+pub(crate) async fn insert_snippet_handler(
+    data: AFPluginData<InsertSnippetPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> Result<(), FlowyError> {
+    let payload: InsertSnippetPayloadPB = data.into_inner();
+    if payload.index < 0 {
+        return Err(FlowyError::out_of_bounds().context(format!("Invalid index: {}", payload.index)));
+    }
+    manager
+        .insert_snippet(&payload.doc_id, payload.index as usize, &payload.snippet_id)
+        .await
+}
+
+pub(crate) async fn resume_sync_handler(
+    data: AFPluginData<ResumeSyncPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> Result<(), FlowyError> {
+    let payload: ResumeSyncPayloadPB = data.into_inner();
+    manager.resume_document_sync(&payload.doc_id, payload.force_reset).await
+}
+
+pub(crate) async fn update_selection_handler(
+    data: AFPluginData<UpdateSelectionPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> Result<(), FlowyError> {
+    let payload: UpdateSelectionPayloadPB = data.into_inner();
+    let mut selection = Vec::with_capacity(payload.ranges.len());
+    for range in payload.ranges {
+        if range.start < 0 || range.end < range.start {
+            return Err(
+                FlowyError::out_of_bounds().context(format!("Invalid selection: {}..{}", range.start, range.end))
+            );
+        }
+        selection.push(Interval::new(range.start as usize, range.end as usize));
+    }
+    manager.update_selection(&payload.doc_id, selection).await
+}
+
+pub(crate) async fn reexport_documents_handler(
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<ReexportSummaryPB, FlowyError> {
+    let summary = manager.reexport_documents().await?;
+    data_result(summary.into())
+}
+
+pub(crate) async fn cancel_reexport_handler(manager: AFPluginState<Arc<DocumentManager>>) -> Result<(), FlowyError> {
+    manager.cancel_reexport();
+    Ok(())
+}
+
+pub(crate) async fn export_portable_handler(
+    data: AFPluginData<ExportPortablePayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> Result<(), FlowyError> {
+    let payload: ExportPortablePayloadPB = data.into_inner();
+    manager
+        .export_doc_portable(&payload.doc_id, Path::new(&payload.path))
+        .await
+}
+
+pub(crate) async fn focus_document_handler(
+    data: AFPluginData<FocusDocumentPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> Result<(), FlowyError> {
+    let payload: FocusDocumentPayloadPB = data.into_inner();
+    let doc_id = Some(payload.doc_id).filter(|doc_id| !doc_id.is_empty());
+    manager.set_focused_document(doc_id.as_deref());
+    Ok(())
+}
+
+pub(crate) async fn get_content_hashes_handler(
+    data: AFPluginData<ContentHashPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<RepeatedDocumentContentHashPB, FlowyError> {
+    let payload: ContentHashPayloadPB = data.into_inner();
+    let content_hashes = manager.doc_content_hashes(&payload.doc_ids).await?;
+    data_result(content_hashes.into())
+}
+
+pub(crate) async fn recover_document_text_handler(
+    data: AFPluginData<RecoverTextPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<RecoveredTextPB, FlowyError> {
+    let payload: RecoverTextPayloadPB = data.into_inner();
+    let path = manager.recover_text(&payload.doc_id).await?;
+    data_result(RecoveredTextPB { path })
+}
+
+pub(crate) async fn restore_from_backup_handler(
+    data: AFPluginData<RestoreBackupPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> Result<(), FlowyError> {
+    let payload: RestoreBackupPayloadPB = data.into_inner();
+    manager.restore_from_backup(&payload.path, payload.force).await
+}
+
+pub(crate) async fn add_dictionary_word_handler(
+    data: AFPluginData<DictionaryWordPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> Result<(), FlowyError> {
+    let payload: DictionaryWordPayloadPB = data.into_inner();
+    manager
+        .add_custom_dictionary_word(&payload.workspace_id, &payload.word)
+        .await
+}
+
+pub(crate) async fn remove_dictionary_word_handler(
+    data: AFPluginData<DictionaryWordPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> Result<(), FlowyError> {
+    let payload: DictionaryWordPayloadPB = data.into_inner();
+    manager
+        .remove_custom_dictionary_word(&payload.workspace_id, &payload.word)
+        .await
+}
+
+pub(crate) async fn get_custom_dictionary_handler(
+    data: AFPluginData<CustomDictionaryIdPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<CustomDictionaryPB, FlowyError> {
+    let workspace_id = data.into_inner().workspace_id;
+    let words = manager.custom_dictionary(&workspace_id)?;
+    data_result(CustomDictionaryPB { workspace_id, words })
+}
+
+pub(crate) async fn sync_custom_dictionary_handler(
+    data: AFPluginData<CustomDictionaryIdPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<CustomDictionaryPB, FlowyError> {
+    let workspace_id = data.into_inner().workspace_id;
+    let words = manager.sync_custom_dictionary(&workspace_id).await?;
+    data_result(CustomDictionaryPB { workspace_id, words })
+}
+
+pub(crate) async fn import_custom_dictionary_handler(
+    data: AFPluginData<CustomDictionaryPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<CustomDictionaryPB, FlowyError> {
+    let payload: CustomDictionaryPB = data.into_inner();
+    let _ = manager
+        .import_custom_dictionary(&payload.workspace_id, &payload.words)
+        .await?;
+    let words = manager.custom_dictionary(&payload.workspace_id)?;
+    data_result(CustomDictionaryPB {
+        workspace_id: payload.workspace_id,
+        words,
+    })
+}
+
+pub(crate) async fn get_doc_preferences_handler(
+    data: AFPluginData<DocPreferencesIdPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<DocPreferencesPB, FlowyError> {
+    let doc_id = data.into_inner().doc_id;
+    let preferences = manager.doc_preferences(&doc_id)?;
+    data_result(DocPreferencesPB::new(&doc_id, &preferences))
+}
+
+pub(crate) async fn set_doc_preference_handler(
+    data: AFPluginData<DocPreferencePayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> Result<(), FlowyError> {
+    let payload: DocPreferencePayloadPB = data.into_inner();
+    manager
+        .set_doc_preference_json(&payload.doc_id, &payload.key, payload.value)
+        .await
+}
+
+pub(crate) async fn sync_doc_preferences_handler(
+    data: AFPluginData<DocPreferencesIdPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<DocPreferencesPB, FlowyError> {
+    let doc_id = data.into_inner().doc_id;
+    let preferences = manager.sync_doc_preferences(&doc_id).await?;
+    data_result(DocPreferencesPB::new(&doc_id, &preferences))
+}
+
+pub(crate) async fn run_maintenance_handler(
+    data: AFPluginData<RunMaintenancePayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<MaintenanceReportPB, FlowyError> {
+    let payload = data.into_inner();
+    let report = manager
+        .run_maintenance(MaintenanceTasks(payload.tasks as u32), payload.dry_run)
+        .await?;
+    data_result(report.into())
+}
+
+pub(crate) async fn open_latest_available_handler(
+    data: AFPluginData<OpenDocumentContextPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<DocumentSnapshotPB, FlowyError> {
+    let doc_id = data.into_inner().document_id;
+    let mut snapshot = DocumentSnapshotPB {
+        doc_id,
+        ..Default::default()
+    };
+    match manager.open_latest_available(&snapshot.doc_id).await? {
+        AvailableDocument::Ready(json) => snapshot.snapshot = json,
+        AvailableDocument::Loading(meta) => {
+            snapshot.loading = true;
+            snapshot.meta = meta.map(DocumentMetaPB::from);
+        }
+    }
+    data_result(snapshot)
+}
+
+pub(crate) async fn find_replace_handler(
+    data: AFPluginData<FindReplacePayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<FindReplacePreviewPB, FlowyError> {
+    let payload = data.into_inner();
+    let scope = payload.scope();
+    let query = FindReplaceQuery {
+        pattern: payload.pattern,
+        replacement: payload.replacement,
+        options: FindOptions {
+            case_sensitive: payload.case_sensitive,
+            whole_word: payload.whole_word,
+        },
+    };
+    let preview = manager.find_replace(scope, query).await?;
+    data_result(preview.into())
+}
+
+pub(crate) async fn apply_find_replace_handler(
+    data: AFPluginData<FindReplacePreviewPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<FindReplaceReportPB, FlowyError> {
+    let preview = FindReplacePreview::from(data.into_inner());
+    let report = manager.apply_find_replace(&preview).await?;
+    data_result(report.into())
+}
+
+pub(crate) async fn export_rev_graph_handler(
+    data: AFPluginData<ExportRevGraphPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<RevGraphPB, FlowyError> {
+    let payload = data.into_inner();
+    let tail = payload.tail.map(|tail| tail.max(0) as usize);
+    let dot = manager.export_rev_graph(&payload.doc_id, tail).await?;
+    data_result(RevGraphPB { dot })
+}
+
+pub(crate) async fn open_document_reader_handler(
+    data: AFPluginData<OpenDocumentReaderPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<DocumentReaderPB, FlowyError> {
+    let payload = data.into_inner();
+    let (handle, len) = manager.open_document_reader(&payload.doc_id).await?;
+    data_result(DocumentReaderPB {
+        handle,
+        len: len as i64,
+    })
+}
+
+pub(crate) async fn get_sync_health_handler(
+    data: AFPluginData<SyncHealthPayloadPB>,
+    manager: AFPluginState<Arc<DocumentManager>>,
+) -> DataResult<SyncHealthPB, FlowyError> {
+    let payload = data.into_inner();
+    let health = manager.sync_health(&payload.doc_id).await?;
+    data_result(health)
+}
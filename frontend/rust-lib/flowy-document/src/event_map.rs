@@ -1,17 +1,96 @@
 use crate::event_handler::*;
 use crate::DocumentManager;
 use flowy_derive::{Flowy_Event, ProtoBuf_Enum};
+use flowy_error::FlowyError;
 use lib_dispatch::prelude::AFPlugin;
+use lib_dispatch::prelude::AFPluginCapability::*;
 use std::sync::Arc;
 use strum_macros::Display;
 
 pub fn init(document_manager: Arc<DocumentManager>) -> AFPlugin {
-    let mut plugin = AFPlugin::new().name(env!("CARGO_PKG_NAME")).state(document_manager);
+    let startup_gate = document_manager.startup_gate();
+    let mut plugin = AFPlugin::new()
+        .name(env!("CARGO_PKG_NAME"))
+        .state(document_manager)
+        .permission_error::<FlowyError>()
+        .startup_gate(startup_gate)
+        .not_ready_error::<FlowyError>();
 
     plugin = plugin
-        .event(DocumentEvent::GetDocument, get_document_handler)
-        .event(DocumentEvent::ApplyEdit, apply_edit_handler)
-        .event(DocumentEvent::ExportDocument, export_handler);
+        .event_with_capability(DocumentEvent::GetDocument, Read, get_document_handler)
+        .event_with_capability(DocumentEvent::ApplyEdit, Write, apply_edit_handler)
+        .event_with_capability(DocumentEvent::ExportDocument, Read, export_handler)
+        .event_with_capability(DocumentEvent::GetStartupReport, Read, get_startup_report_handler)
+        .event_with_capability(DocumentEvent::GetRedline, Read, get_redline_handler)
+        .event_with_capability(
+            DocumentEvent::QueryStoragePaths,
+            Maintenance,
+            query_storage_paths_handler,
+        )
+        .event_with_capability(DocumentEvent::ValidateAllDocs, Maintenance, validate_all_docs_handler)
+        .event_with_capability(
+            DocumentEvent::SaveSelectionAsSnippet,
+            Write,
+            save_selection_as_snippet_handler,
+        )
+        .event_with_capability(DocumentEvent::GetSnippets, Read, get_snippets_handler)
+        .event_with_capability(DocumentEvent::InsertSnippet, Write, insert_snippet_handler)
+        .event_with_capability(DocumentEvent::ImportSnippets, Write, import_snippets_handler)
+        .event_with_capability(DocumentEvent::ResumeSync, Maintenance, resume_sync_handler)
+        .event_with_capability(DocumentEvent::UpdateSelection, Write, update_selection_handler)
+        .event_with_capability(DocumentEvent::ReexportDocuments, Export, reexport_documents_handler)
+        .event_with_capability(DocumentEvent::CancelReexport, Export, cancel_reexport_handler)
+        .event_with_capability(
+            DocumentEvent::RestoreFromBackup,
+            Maintenance,
+            restore_from_backup_handler,
+        )
+        .event_with_capability(DocumentEvent::AddDictionaryWord, Write, add_dictionary_word_handler)
+        .event_with_capability(
+            DocumentEvent::RemoveDictionaryWord,
+            Write,
+            remove_dictionary_word_handler,
+        )
+        .event_with_capability(DocumentEvent::GetCustomDictionary, Read, get_custom_dictionary_handler)
+        .event_with_capability(
+            DocumentEvent::SyncCustomDictionary,
+            Write,
+            sync_custom_dictionary_handler,
+        )
+        .event_with_capability(
+            DocumentEvent::ImportCustomDictionary,
+            Write,
+            import_custom_dictionary_handler,
+        )
+        .event_with_capability(DocumentEvent::ExplainTransform, Maintenance, explain_transform_handler)
+        .event_with_capability(
+            DocumentEvent::DedupeRevisionPayloads,
+            Maintenance,
+            dedupe_revision_payloads_handler,
+        )
+        .event_with_capability(
+            DocumentEvent::GetNotificationQueueStats,
+            Read,
+            get_notification_queue_stats_handler,
+        )
+        .event_with_capability(DocumentEvent::ExportPortable, Export, export_portable_handler)
+        .event_with_capability(DocumentEvent::FocusDocument, Read, focus_document_handler)
+        .event_with_capability(DocumentEvent::GetContentHashes, Read, get_content_hashes_handler)
+        .event_with_capability(
+            DocumentEvent::RecoverDocumentText,
+            Export,
+            recover_document_text_handler,
+        )
+        .event_with_capability(DocumentEvent::GetDocPreferences, Read, get_doc_preferences_handler)
+        .event_with_capability(DocumentEvent::SetDocPreference, Write, set_doc_preference_handler)
+        .event_with_capability(DocumentEvent::SyncDocPreferences, Write, sync_doc_preferences_handler)
+        .event_with_capability(DocumentEvent::RunMaintenance, Maintenance, run_maintenance_handler)
+        .event_with_capability(DocumentEvent::OpenLatestAvailable, Read, open_latest_available_handler)
+        .event_with_capability(DocumentEvent::FindReplace, Read, find_replace_handler)
+        .event_with_capability(DocumentEvent::ApplyFindReplace, Write, apply_find_replace_handler)
+        .event_with_capability(DocumentEvent::ExportRevGraph, Read, export_rev_graph_handler)
+        .event_with_capability(DocumentEvent::OpenDocumentReader, Read, open_document_reader_handler)
+        .event_with_capability(DocumentEvent::GetSyncHealth, Read, get_sync_health_handler);
 
     plugin
 }
@@ -27,4 +106,145 @@ pub enum DocumentEvent {
 
     #[event(input = "ExportPayloadPB", output = "ExportDataPB")]
     ExportDocument = 2,
+
+    #[event(output = "DocumentStartupReportPB")]
+    GetStartupReport = 3,
+
+    #[event(input = "RedlinePayloadPB", output = "RedlinePB")]
+    GetRedline = 4,
+
+    #[event(output = "StoragePathsPB")]
+    QueryStoragePaths = 5,
+
+    #[event(input = "ValidateDocsPayloadPB", output = "DocumentIntegrityPB")]
+    ValidateAllDocs = 6,
+
+    #[event(input = "SaveSnippetPayloadPB", output = "SnippetPB")]
+    SaveSelectionAsSnippet = 7,
+
+    #[event(output = "RepeatedSnippetPB")]
+    GetSnippets = 8,
+
+    #[event(input = "InsertSnippetPayloadPB")]
+    InsertSnippet = 9,
+
+    #[event(input = "ResumeSyncPayloadPB")]
+    ResumeSync = 10,
+
+    #[event(input = "UpdateSelectionPayloadPB")]
+    UpdateSelection = 11,
+
+    #[event(output = "ReexportSummaryPB")]
+    ReexportDocuments = 12,
+
+    #[event()]
+    CancelReexport = 13,
+
+    #[event(input = "RestoreBackupPayloadPB")]
+    RestoreFromBackup = 14,
+
+    #[event(input = "DictionaryWordPayloadPB")]
+    AddDictionaryWord = 15,
+
+    #[event(input = "DictionaryWordPayloadPB")]
+    RemoveDictionaryWord = 16,
+
+    /// Returns all the words of the custom dictionary, the editor calls it at startup.
+    #[event(input = "CustomDictionaryIdPB", output = "CustomDictionaryPB")]
+    GetCustomDictionary = 17,
+
+    #[event(input = "CustomDictionaryIdPB", output = "CustomDictionaryPB")]
+    SyncCustomDictionary = 18,
+
+    /// Replays the transform of two revisions of a document with a trace of each decision, to
+    /// debug a mangled merge. The document doesn't change.
+    #[event(input = "ExplainTransformPayloadPB", output = "TransformExplanationPB")]
+    ExplainTransform = 19,
+
+    /// Shares the identical payloads of the revisions saved before the payloads were shared, see
+    /// `DocumentConfig::share_revision_payloads`. It only needs to run once.
+    #[event(output = "PayloadDedupeSummaryPB")]
+    DedupeRevisionPayloads = 20,
+
+    /// Returns the depth of the queue of the document notifications, see `NotificationsDropped`.
+    #[event(output = "NotificationQueueStatsPB")]
+    GetNotificationQueueStats = 21,
+
+    /// Writes the document with its history and attachments into a single file that can be
+    /// imported by another user, see `FolderEvent::ImportPortableView`.
+    #[event(input = "ExportPortablePayloadPB")]
+    ExportPortable = 22,
+
+    /// Tells which document has the focus, its reads go before the background work.
+    #[event(input = "FocusDocumentPayloadPB")]
+    FocusDocument = 23,
+
+    /// Returns the content hash of each document, the documents that don't exist are left out.
+    /// The hash changes only when the text or the formatting changes.
+    #[event(input = "ContentHashPayloadPB", output = "RepeatedDocumentContentHashPB")]
+    GetContentHashes = 24,
+
+    /// Writes the inserted text of every revision of the document into a file and returns its
+    /// path. It's offered when the document fails to open with `ErrorCode::DocumentUnreadable`.
+    #[event(input = "RecoverTextPayloadPB", output = "RecoveredTextPB")]
+    RecoverDocumentText = 25,
+
+    /// Returns the preferences of the document, e.g. its font size. They're also returned by
+    /// the open event with `DocumentFieldPB::Meta`.
+    #[event(input = "DocPreferencesIdPB", output = "DocPreferencesPB")]
+    GetDocPreferences = 26,
+
+    /// Sets a preference of the document. It's synced to the other devices, the document's
+    /// revisions don't change.
+    #[event(input = "DocPreferencePayloadPB")]
+    SetDocPreference = 27,
+
+    #[event(input = "DocPreferencesIdPB", output = "DocPreferencesPB")]
+    SyncDocPreferences = 28,
+
+    /// Runs the selected maintenance tasks in a safe order and reports each one, see
+    /// `DocumentManager::run_maintenance`.
+    #[event(input = "RunMaintenancePayloadPB", output = "MaintenanceReportPB")]
+    RunMaintenance = 29,
+
+    /// Opens the document without waiting behind the background work, see
+    /// `DocumentManager::open_latest_available`. If the returned document is `loading`, it's
+    /// sent with the `DidOpenDocument` notification once it's opened.
+    #[event(input = "OpenDocumentContextPB", output = "DocumentSnapshotPB")]
+    OpenLatestAvailable = 30,
+
+    /// Returns the matches of the pattern in each document of the scope without replacing them,
+    /// see `DocumentManager::find_replace`.
+    #[event(input = "FindReplacePayloadPB", output = "FindReplacePreviewPB")]
+    FindReplace = 31,
+
+    /// Replaces the matches of the preview returned by `FindReplace`. The documents that changed
+    /// since the preview are reported as stale.
+    #[event(input = "FindReplacePreviewPB", output = "FindReplaceReportPB")]
+    ApplyFindReplace = 32,
+
+    /// Describes the revision chain of the document as a Graphviz DOT digraph, with its forks,
+    /// gaps and quarantined revisions highlighted. It only has the metadata of the revisions, so
+    /// it can be attached to a bug report.
+    #[event(input = "ExportRevGraphPayloadPB", output = "RevGraphPB")]
+    ExportRevGraph = 33,
+
+    /// Opens a reader of the json of the document for the embedders that read it by chunks with
+    /// `appflowy_doc_read_chunk`, whatever the size of the document.
+    #[event(input = "OpenDocumentReaderPayloadPB", output = "DocumentReaderPB")]
+    OpenDocumentReader = 34,
+
+    /// Adds the words of the dictionary returned by `GetCustomDictionary`, e.g. when an exported
+    /// workspace is imported. The words the dictionary already has are skipped.
+    #[event(input = "CustomDictionaryPB", output = "CustomDictionaryPB")]
+    ImportCustomDictionary = 35,
+
+    /// Returns the revisions of the opened document that wait to be synced in both directions.
+    #[event(input = "SyncHealthPayloadPB", output = "SyncHealthPB")]
+    GetSyncHealth = 36,
+
+    /// Saves the snippets returned by `GetSnippets`, e.g. when an exported workspace is
+    /// imported. The snippets with the same id are replaced. Returns all the snippets.
+    #[event(input = "RepeatedSnippetPB", output = "RepeatedSnippetPB")]
+    ImportSnippets = 37,
 }
@@ -0,0 +1,144 @@
+use flowy_error::FlowyResult;
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The version of the markdown exporter. Bump it whenever the output of the exporter changes,
+/// so `DocumentManager::reexport_documents` regenerates the exports of the older version.
+pub const MARKDOWN_EXPORTER_VERSION: i64 = 2;
+
+/// Receives the markdown of the documents, e.g. the mirror files or the export archive.
+pub trait DocumentExportTarget: Send + Sync {
+    /// Writes the markdown of the document, replacing the one written before.
+    fn write(&self, doc_id: &str, markdown: &str) -> FlowyResult<()>;
+}
+
+/// The directory of the [MarkdownMirror] under the user's directory.
+pub const MARKDOWN_MIRROR_DIR: &str = "markdown_mirror";
+
+/// Keeps the markdown of each document in the file `<doc_id>.md` of its directory. The
+/// markdown is written to a temporary file that is renamed over the old one, so the readers of
+/// the mirror never see a half-written file.
+pub struct MarkdownMirror {
+    dir: PathBuf,
+}
+
+impl MarkdownMirror {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn path_of(&self, doc_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.md", doc_id))
+    }
+}
+
+impl DocumentExportTarget for MarkdownMirror {
+    fn write(&self, doc_id: &str, markdown: &str) -> FlowyResult<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path_of(doc_id);
+        let temp_path = self.dir.join(format!("{}.md.tmp", doc_id));
+        std::fs::write(&temp_path, markdown)?;
+        std::fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+}
+
+/// The targets registered with the `DocumentConfig`. Each regenerated document is written to
+/// all of them, and to the [MarkdownMirror] of the user unless it's turned off.
+#[derive(Clone)]
+pub struct DocumentExportTargets {
+    targets: Vec<Arc<dyn DocumentExportTarget>>,
+    exporter_version: i64,
+    wrap_width: Option<usize>,
+    markdown_mirror: bool,
+}
+
+impl std::default::Default for DocumentExportTargets {
+    fn default() -> Self {
+        Self {
+            targets: vec![],
+            exporter_version: MARKDOWN_EXPORTER_VERSION,
+            wrap_width: None,
+            markdown_mirror: true,
+        }
+    }
+}
+
+impl Debug for DocumentExportTargets {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DocumentExportTargets")
+            .field("num_of_targets", &self.targets.len())
+            .field("exporter_version", &self.exporter_version)
+            .field("wrap_width", &self.wrap_width)
+            .field("markdown_mirror", &self.markdown_mirror)
+            .finish()
+    }
+}
+
+impl DocumentExportTargets {
+    pub fn with_target(mut self, target: Arc<dyn DocumentExportTarget>) -> Self {
+        self.targets.push(target);
+        self
+    }
+
+    /// Overrides the version that is stamped on the regenerated documents, which is the
+    /// `MARKDOWN_EXPORTER_VERSION` by default.
+    pub fn with_exporter_version(mut self, exporter_version: i64) -> Self {
+        self.exporter_version = exporter_version;
+        self
+    }
+
+    /// Hard-wraps the lines of the markdown that are longer than `wrap_width` chars, e.g. a
+    /// minified JSON pasted as one line. The lines are written verbatim by default.
+    pub fn with_wrap_width(mut self, wrap_width: Option<usize>) -> Self {
+        self.wrap_width = wrap_width;
+        self
+    }
+
+    /// Turns the [MarkdownMirror] of the user on or off, it's on by default.
+    pub fn with_markdown_mirror(mut self, markdown_mirror: bool) -> Self {
+        self.markdown_mirror = markdown_mirror;
+        self
+    }
+
+    /// Returns the targets with the [MarkdownMirror] under the `user_dir` if it's on.
+    pub(crate) fn with_user_targets(mut self, user_dir: &str) -> Self {
+        if self.markdown_mirror {
+            let dir = Path::new(user_dir).join(MARKDOWN_MIRROR_DIR);
+            self.targets.push(Arc::new(MarkdownMirror::new(dir)));
+        }
+        self
+    }
+
+    pub fn wrap_width(&self) -> Option<usize> {
+        self.wrap_width
+    }
+
+    pub fn exporter_version(&self) -> i64 {
+        self.exporter_version
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Writes the markdown to each target, stops at the first target that fails.
+    pub(crate) fn write(&self, doc_id: &str, markdown: &str) -> FlowyResult<()> {
+        for target in self.targets.iter() {
+            target.write(doc_id, markdown)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReexportSummary {
+    pub num_of_regenerated: usize,
+    /// The documents that were exported by the current exporter version since their last change.
+    pub num_of_skipped: usize,
+    pub num_of_failed: usize,
+    /// The re-export was cancelled before all the documents were checked. Running it again
+    /// continues with the documents that are not stamped yet.
+    pub cancelled: bool,
+}
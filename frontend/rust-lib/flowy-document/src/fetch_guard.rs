@@ -0,0 +1,176 @@
+use crate::{Attachment, AttachmentVersion, DocumentChunk, DocumentCloudService};
+use flowy_error::{FlowyError, FlowyResult};
+use flowy_http_model::document::{CreateDocumentParams, DocumentId, DocumentPayload, ResetDocumentParams};
+use lib_infra::future::FutureResult;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::OnceCell;
+
+/// Shares one `fetch_document` of a document between the calls that fetch it at the same time,
+/// e.g. when the UI hydrates the same document twice in a row. The server is hit once and every
+/// call receives the same result. The fetch that starts after the shared one completed hits the
+/// server again, the results aren't cached.
+///
+/// The hydrations of a document share one run the same way, so the fetched document is saved
+/// once instead of by each of them, see `DocumentManager::hydrate_document`.
+#[derive(Default)]
+pub struct DocumentFetchGuard {
+    fetches: SingleFlight<FlowyResult<Option<DocumentPayload>>>,
+    hydrations: SingleFlight<FlowyResult<()>>,
+}
+
+impl DocumentFetchGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the `cloud_service` whose `fetch_document` goes through the guard, its other
+    /// methods are passed through.
+    pub fn guard(self: &Arc<Self>, cloud_service: Arc<dyn DocumentCloudService>) -> Arc<dyn DocumentCloudService> {
+        Arc::new(GuardedDocumentCloudService {
+            guard: self.clone(),
+            cloud_service,
+        })
+    }
+
+    pub async fn fetch_document(
+        &self,
+        cloud_service: &Arc<dyn DocumentCloudService>,
+        token: &str,
+        doc_id: &str,
+    ) -> FlowyResult<Option<DocumentPayload>> {
+        self.fetches
+            .run(doc_id, || cloud_service.fetch_document(token, doc_id.to_owned().into()))
+            .await
+    }
+
+    pub(crate) async fn hydrate<F, Fut>(&self, doc_id: &str, hydrate: F) -> FlowyResult<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = FlowyResult<()>>,
+    {
+        self.hydrations.run(doc_id, hydrate).await
+    }
+
+    /// Returns the number of the documents being fetched or hydrated.
+    pub fn num_in_flight(&self) -> usize {
+        self.fetches.len() + self.hydrations.len()
+    }
+}
+
+type InFlightCells<T> = Mutex<HashMap<String, Arc<OnceCell<T>>>>;
+
+/// Runs one future per key for the calls made at the same time. If the call running the future
+/// is dropped, one of the waiting calls runs it instead.
+struct SingleFlight<T> {
+    in_flight: InFlightCells<T>,
+}
+
+impl<T> std::default::Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    async fn run<F, Fut>(&self, key: &str, f: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let cell = lock_cells(&self.in_flight)
+            .entry(key.to_owned())
+            .or_insert_with(|| Arc::new(OnceCell::new()))
+            .clone();
+        let entry = InFlightEntry {
+            in_flight: &self.in_flight,
+            key,
+            cell,
+        };
+        let result = entry.cell.get_or_init(f).await.clone();
+        drop(entry);
+        result
+    }
+
+    fn len(&self) -> usize {
+        lock_cells(&self.in_flight).len()
+    }
+}
+
+/// Removes the cell of a key once it's completed, or once no call waits on it anymore, e.g. all
+/// of them were cancelled. The next call starts a new run then.
+struct InFlightEntry<'a, T> {
+    in_flight: &'a InFlightCells<T>,
+    key: &'a str,
+    cell: Arc<OnceCell<T>>,
+}
+
+impl<'a, T> Drop for InFlightEntry<'a, T> {
+    fn drop(&mut self) {
+        let mut in_flight = lock_cells(self.in_flight);
+        let is_current = matches!(in_flight.get(self.key), Some(current) if Arc::ptr_eq(current, &self.cell));
+        // The map and this entry hold the last references if no other call waits on the cell.
+        if is_current && (self.cell.initialized() || Arc::strong_count(&self.cell) <= 2) {
+            in_flight.remove(self.key);
+        }
+    }
+}
+
+fn lock_cells<T>(in_flight: &InFlightCells<T>) -> MutexGuard<HashMap<String, Arc<OnceCell<T>>>> {
+    // The map stays valid if a call panicked while holding the lock.
+    in_flight.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+struct GuardedDocumentCloudService {
+    guard: Arc<DocumentFetchGuard>,
+    cloud_service: Arc<dyn DocumentCloudService>,
+}
+
+impl DocumentCloudService for GuardedDocumentCloudService {
+    fn create_document(&self, token: &str, params: CreateDocumentParams) -> FutureResult<(), FlowyError> {
+        self.cloud_service.create_document(token, params)
+    }
+
+    fn fetch_document(&self, token: &str, params: DocumentId) -> FutureResult<Option<DocumentPayload>, FlowyError> {
+        let guard = self.guard.clone();
+        let cloud_service = self.cloud_service.clone();
+        let token = token.to_owned();
+        FutureResult::new(async move { guard.fetch_document(&cloud_service, &token, &params.value).await })
+    }
+
+    fn update_document_content(&self, token: &str, params: ResetDocumentParams) -> FutureResult<(), FlowyError> {
+        self.cloud_service.update_document_content(token, params)
+    }
+
+    fn fetch_document_chunk(
+        &self,
+        token: &str,
+        params: DocumentId,
+        chunk_index: usize,
+    ) -> FutureResult<Option<DocumentChunk>, FlowyError> {
+        self.cloud_service.fetch_document_chunk(token, params, chunk_index)
+    }
+
+    fn fetch_document_rev_id(&self, token: &str, params: DocumentId) -> FutureResult<Option<i64>, FlowyError> {
+        self.cloud_service.fetch_document_rev_id(token, params)
+    }
+
+    fn fetch_attachment(&self, token: &str, attachment_id: &str) -> FutureResult<Option<Attachment>, FlowyError> {
+        self.cloud_service.fetch_attachment(token, attachment_id)
+    }
+
+    fn fetch_attachment_versions(
+        &self,
+        token: &str,
+        attachment_ids: Vec<String>,
+    ) -> FutureResult<Option<HashMap<String, AttachmentVersion>>, FlowyError> {
+        self.cloud_service.fetch_attachment_versions(token, attachment_ids)
+    }
+
+    fn upload_attachment(&self, token: &str, attachment: Attachment) -> FutureResult<(), FlowyError> {
+        self.cloud_service.upload_attachment(token, attachment)
+    }
+}
@@ -1,14 +1,36 @@
+mod dart_notification;
 pub mod entities;
 mod event_handler;
 pub mod event_map;
+mod export_target;
+mod fetch_guard;
 pub mod manager;
 
 pub mod editor;
 pub mod old_editor;
 pub mod protobuf;
+mod revision_guard;
+mod server_resolver;
 mod services;
 
+pub use export_target::*;
+pub use fetch_guard::*;
+pub use flowy_revision::FetchOverwritePolicy;
 pub use manager::*;
+pub use revision_guard::*;
+pub use server_resolver::*;
+pub use services::{
+    doc_preferences_doc_id, Attachment, AttachmentReconcileSummary, AttachmentStore, AttachmentVersion,
+    AvailableDocument, BackupAuditEntry, BackupKind, BackupOutcome, DatabaseMergeSummary, DocumentContent,
+    DocumentContentHash, DocumentMeta, DocumentPreview, FindReplaceDocPreview, FindReplaceDocReport,
+    FindReplaceOutcome, FindReplacePreview, FindReplaceQuery, FindReplaceReport, FindReplaceScope, FindReplaceSkip,
+    LazyDocument, MaintenanceReport, MaintenanceStatus, MaintenanceTask, MaintenanceTaskReport, MaintenanceTasks,
+    RevGraph, RevGraphAuthor, RevGraphNode, Snippet, DEFAULT_ATTACHMENT_GRACE_PERIOD, DEFAULT_DOCUMENT_READER_TIMEOUT,
+    DEFAULT_PREVIEW_LEN, DEFAULT_STREAMED_OPEN_THRESHOLD, DOCUMENT_JSON_CHUNK_SIZE, PORTABLE_DOCUMENT_EXTENSION,
+    PORTABLE_DOCUMENT_FORMAT_VERSION, RECOVERED_MARKER, RECOVERED_UNREADABLE,
+};
+#[cfg(feature = "flowy_unit_test")]
+pub use services::DocumentDatabaseMock;
 pub mod errors {
     pub use flowy_error::{internal_error, ErrorCode, FlowyError};
 }
@@ -18,6 +40,7 @@ pub const TEXT_BLOCK_SYNC_INTERVAL_IN_MILLIS: u64 = 1000;
 use crate::errors::FlowyError;
 use flowy_http_model::document::{CreateDocumentParams, DocumentId, DocumentPayload, ResetDocumentParams};
 use lib_infra::future::FutureResult;
+use std::collections::HashMap;
 
 pub trait DocumentCloudService: Send + Sync {
     fn create_document(&self, token: &str, params: CreateDocumentParams) -> FutureResult<(), FlowyError>;
@@ -25,4 +48,65 @@ pub trait DocumentCloudService: Send + Sync {
     fn fetch_document(&self, token: &str, params: DocumentId) -> FutureResult<Option<DocumentPayload>, FlowyError>;
 
     fn update_document_content(&self, token: &str, params: ResetDocumentParams) -> FutureResult<(), FlowyError>;
+
+    /// Returns the chunk at `chunk_index` of the document's content. The large documents are
+    /// fetched chunk by chunk, see `DocumentManager::hydrate_document`. Returns None if the server
+    /// doesn't stream the documents, the document is fetched by `fetch_document` then.
+    fn fetch_document_chunk(
+        &self,
+        _token: &str,
+        _params: DocumentId,
+        _chunk_index: usize,
+    ) -> FutureResult<Option<DocumentChunk>, FlowyError> {
+        FutureResult::new(async { Ok(None) })
+    }
+
+    /// Returns the rev_id of the document on the server without its content, None if the server
+    /// doesn't have the document. The server that can't tell the rev_id alone sends the whole
+    /// document.
+    fn fetch_document_rev_id(&self, token: &str, params: DocumentId) -> FutureResult<Option<i64>, FlowyError> {
+        let document = self.fetch_document(token, params);
+        FutureResult::new(async move { Ok(document.await?.map(|document| document.rev_id)) })
+    }
+
+    /// Returns the attachment that the documents refer to by `attachment_id`, see
+    /// `DocumentManager::reconcile_attachments`. Returns None if the server doesn't have it.
+    fn fetch_attachment(&self, _token: &str, _attachment_id: &str) -> FutureResult<Option<Attachment>, FlowyError> {
+        FutureResult::new(async { Ok(None) })
+    }
+
+    /// Returns the version of each attachment of `attachment_ids` that the server has, the
+    /// attachments it doesn't have are left out. Returns None if the server doesn't keep the
+    /// versions, only the missing attachments are downloaded then and nothing is uploaded.
+    fn fetch_attachment_versions(
+        &self,
+        _token: &str,
+        _attachment_ids: Vec<String>,
+    ) -> FutureResult<Option<HashMap<String, AttachmentVersion>>, FlowyError> {
+        FutureResult::new(async { Ok(None) })
+    }
+
+    /// Saves the attachment on the server unless the server's version is the same or newer,
+    /// see `AttachmentVersion`.
+    fn upload_attachment(&self, _token: &str, _attachment: Attachment) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+}
+
+/// A part of the delta of a document streamed from the server.
+#[derive(Debug, Clone)]
+pub struct DocumentChunk {
+    pub doc_id: String,
+
+    /// The json of the chunk's operations, which must all be inserts. The document's delta is
+    /// the concatenation of the chunks.
+    pub data: Vec<u8>,
+
+    /// The rev_id of the document the chunk is cut from. All the chunks of a document must
+    /// have the same rev_id, otherwise the document changed while it was fetched.
+    pub rev_id: i64,
+
+    pub base_rev_id: i64,
+
+    pub is_last: bool,
 }
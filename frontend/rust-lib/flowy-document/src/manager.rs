@@ -1,31 +1,96 @@
-use crate::editor::{initial_document_content, AppFlowyDocumentEditor, DocumentRevisionMergeable};
-use crate::entities::{DocumentVersionPB, EditParams};
+use crate::dart_notification::{
+    document_notification_queue_stats, register_document_notification_queue, send_anonymous_dart_notification,
+    send_dart_notification, DocumentNotification,
+};
+use crate::editor::{
+    content_without_archived, initial_document_content, AppFlowyDocumentEditor, DocumentRevisionMergeable,
+};
+use crate::entities::{DocumentSnapshotPB, DocumentStartupReportPB, ReexportSummaryPB};
+use crate::entities::{DocumentVersionPB, EditParams, SyncHealthPB};
 use crate::old_editor::editor::{DeltaDocumentEditor, DeltaDocumentRevisionMergeable};
 use crate::services::rev_sqlite::{
+    read_last_snapshot_rev_id, DeltaRevisionSql, DocumentRevisionSql, PayloadDedupeSummary,
     SQLiteDeltaDocumentRevisionPersistence, SQLiteDocumentRevisionPersistence,
     SQLiteDocumentRevisionSnapshotPersistence,
 };
-use crate::services::DocumentPersistence;
-use crate::{errors::FlowyError, DocumentCloudService};
+use crate::services::{
+    attach_local_links, backup_database, content_byte_range, count_unsynced_revisions, custom_dictionary_doc_id,
+    dictionary_word_lines, dictionary_words, doc_preference_lines, doc_preference_values, doc_preferences,
+    doc_preferences_doc_id, first_unsynced_rev_id, hydrate_document_in_chunks, incremental_backup_parent,
+    layer_backup_chain, lazy_document_meta, lazy_document_preview, list_backups, merge_database,
+    merge_dictionary_content, merge_doc_preferences_content, merge_with_server_revisions, preview_document,
+    query_storage_paths, read_attachment_references, read_backup_audit, read_database_pages,
+    read_last_backup_timestamp, read_only_skip, read_repair_audit, referenced_attachment_ids, resolve_backup_chain,
+    restore_blob_dirs, rotate_backups, stage_database, stage_document_chunks, vacuum_database, validate_backup,
+    validate_dictionary_word, validate_doc_preference, validate_incremental_backup, write_backup, write_backup_audit,
+    write_incremental_backup, write_recovered_text, Attachment, AttachmentObserver, AttachmentReconcileSummary,
+    AttachmentReferences, AttachmentStore, AttachmentTransfer, AttachmentTransfers, AvailableDocument,
+    BackupAuditEntry, BackupKind, ContentHashSql, ContentObserver, CustomDictionaryObserver, CustomDictionarySql,
+    DatabaseMergeSummary, DocMetaSql, DocPreference, DocPreferencesObserver, DocumentContent, DocumentContentHash,
+    DocumentMeta, DocumentPersistence, DocumentPreview, DocumentReaders, DocumentReexport, DocumentStartupReport,
+    FindReplaceDocPreview, FindReplaceDocReport, FindReplaceOutcome, FindReplacePreview, FindReplaceQuery,
+    FindReplaceReport, FindReplaceScope, FindReplaceSkip, InvalidRevision, LazyDocument, MaintenanceReport,
+    MaintenanceTask, MaintenanceTasks, PortableDocument, RepairAuditEntry, RevGraph, SearchIndexEntry, SearchIndexSql,
+    ServerDocument, Snippet, SnippetSql, StoragePath, BACKUPS_DIR, DEFAULT_ATTACHMENT_GRACE_PERIOD,
+    DEFAULT_DOCUMENT_READER_TIMEOUT, DEFAULT_STREAMED_OPEN_THRESHOLD, DOCUMENT_JSON_CHUNK_SIZE, RECOVERED_TEXT_DIR,
+};
+use crate::{
+    errors::FlowyError, DocumentCloudService, DocumentExportTargets, DocumentFetchGuard, DocumentServer,
+    DocumentServerResolver, DocumentServerTable, ReexportSummary, RevisionGuards, DEFAULT_DOCUMENT_ENDPOINT,
+};
 use bytes::Bytes;
+use dart_notify::dart::NotificationQueueRegistration;
+use dart_notify::queue::NotificationQueueStats;
+use dashmap::DashMap;
 use flowy_database::ConnectionPool;
-use flowy_error::FlowyResult;
+use flowy_error::{internal_error, FlowyResult};
 use flowy_http_model::util::md5;
+use flowy_http_model::ws_data::ClientRevisionWSData;
 use flowy_http_model::ws_data::ServerRevisionWSData;
 use flowy_http_model::{document::DocumentId, revision::Revision};
 use flowy_revision::{
-    PhantomSnapshotPersistence, RevisionCloudService, RevisionManager, RevisionPersistence,
-    RevisionPersistenceConfiguration, RevisionWebSocket,
+    composable_revisions, ComposeErrorObserver, ErrorReporter, Executor, FetchOverwritePolicy,
+    PhantomSnapshotPersistence, PowerState, PriorityScheduler, RevisionCloudService, RevisionManager,
+    RevisionMergeable, RevisionPersistence, RevisionPersistenceConfiguration, RevisionSnapshot, RevisionWebSocket,
+    SaveDebounceConfiguration, TaskPriority, WSStateReceiver,
 };
-use flowy_sync::client_document::initial_delta_document_content;
+use flowy_revision_persistence::RevisionState;
+use flowy_sync::client_document::{initial_delta_document_content, make_attribute_diff, make_redline};
+use flowy_sync::util::{
+    explain_transform, make_blame, make_operations_at_rev_ids, make_operations_from_revisions,
+    make_operations_within_budget, make_operations_without_revision, make_touched_ranges, ComposeResult,
+    TransformExplanation,
+};
+use futures_util::future::BoxFuture;
+use lib_dispatch::prelude::AFPluginStartupGate;
 use lib_infra::async_trait::async_trait;
-use lib_infra::future::FutureResult;
+use lib_infra::chunked::ChunkedBuffer;
+use lib_infra::future::{BoxResultFuture, FutureResult};
 use lib_infra::ref_map::{RefCountHashMap, RefCountValue};
+use lib_infra::util::timestamp;
+use lib_ot::core::{AttributeHashMap, Interval};
+use lib_ot::text_delta::{
+    content_hash, strip_all_attributes, without_archived, CustomAttributeSemantics, CustomAttributes,
+    DeltaTextOperations, DocumentAst,
+};
 use lib_ws::WSConnectState;
+use nanoid::nanoid;
+use serde::{de::DeserializeOwned, Serialize};
 use std::any::Any;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+#[cfg(feature = "sync")]
+use tokio::sync::OwnedRwLockWriteGuard;
+use tokio::sync::{broadcast, oneshot, Notify, RwLock};
+
+/// The number of revisions a scratch document keeps, the older revisions get merged into one.
+pub const SCRATCH_DOCUMENT_HISTORY_LIMIT: usize = 20;
 
 pub trait DocumentUser: Send + Sync {
     fn user_dir(&self) -> Result<String, FlowyError>;
@@ -33,145 +98,2499 @@ pub trait DocumentUser: Send + Sync {
     fn token(&self) -> Result<String, FlowyError>;
 }
 
-pub trait DocumentDatabase: Send + Sync {
-    fn db_pool(&self) -> Result<Arc<ConnectionPool>, FlowyError>;
-}
+pub trait DocumentDatabase: Send + Sync {
+    fn db_pool(&self) -> Result<Arc<ConnectionPool>, FlowyError>;
+}
+
+/// Resolves the workspaces and the apps of the folder to the ids of the delta documents of their
+/// views, see `FindReplaceScope`, and the documents to the scratch flag of their views.
+pub trait DocumentScopeResolver: Send + Sync {
+    fn workspace_doc_ids(&self, workspace_id: &str) -> FutureResult<Vec<String>, FlowyError>;
+
+    fn app_doc_ids(&self, app_id: &str) -> FutureResult<Vec<String>, FlowyError>;
+
+    /// Returns true if the view of the document is a scratch view.
+    fn is_scratch_view(&self, _view_id: &str) -> FutureResult<bool, FlowyError> {
+        FutureResult::new(async { Ok(false) })
+    }
+
+    fn set_view_scratch(&self, _view_id: &str, _is_scratch: bool) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+}
+
+#[async_trait]
+pub trait DocumentEditor: Send + Sync {
+    /// Called when the document get closed
+    async fn close(&self);
+
+    /// Exports the document content. The content is encoded in the corresponding
+    /// editor data format.
+    fn export(&self) -> FutureResult<String, FlowyError>;
+
+    /// Exports the same content as `export` into chunks of `chunk_size` bytes, so a large
+    /// document isn't copied into one huge buffer.
+    fn export_chunked(&self, chunk_size: usize) -> FutureResult<ChunkedBuffer, FlowyError> {
+        let export = self.export();
+        FutureResult::new(async move {
+            let mut buffer = ChunkedBuffer::new(chunk_size);
+            buffer.write_all(export.await?.as_bytes()).map_err(internal_error)?;
+            Ok(buffer)
+        })
+    }
+
+    /// Duplicate the document inner data into String
+    fn duplicate(&self) -> FutureResult<String, FlowyError>;
+
+    fn receive_ws_data(&self, data: ServerRevisionWSData) -> FutureResult<(), FlowyError>;
+
+    fn receive_ws_state(&self, state: &WSConnectState);
+
+    /// Receives the local operations made by the user input. The operations are encoded
+    /// in binary format.
+    fn compose_local_operations(&self, data: Bytes) -> FutureResult<(), FlowyError>;
+
+    /// Writes the revisions that are waiting for the deferred save to disk.
+    async fn flush(&self) -> FlowyResult<()>;
+
+    /// Stops the document from applying the edits and syncing its revisions until the returned
+    /// `DocumentPause` is dropped. The edits sent in the meantime wait in the queue.
+    async fn pause(&self) -> FlowyResult<DocumentPause>;
+
+    /// Returns the `Any` reference that can be used to downcast back to the original,
+    /// concrete type.
+    ///
+    /// The indirection through `as_any` is because using `downcast_ref`
+    /// on `Box<A>` *directly* only lets us downcast back to `&A` again. You can take a look at [this](https://stackoverflow.com/questions/33687447/how-to-get-a-reference-to-a-concrete-type-from-a-trait-object)
+    /// for more information.
+    ///
+    ///
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Returned by `DocumentEditor::pause`, dropping it resumes the document.
+#[derive(Default)]
+pub struct DocumentPause {
+    /// Resumes the edit queue when it's sent or dropped.
+    #[allow(dead_code)]
+    resume_edits: Option<oneshot::Sender<()>>,
+    /// Blocks the sync of the revisions, see `RevisionWebSocketManager::pause`.
+    #[cfg(feature = "sync")]
+    #[allow(dead_code)]
+    sync: Option<OwnedRwLockWriteGuard<()>>,
+}
+
+impl DocumentPause {
+    pub(crate) fn new(resume_edits: oneshot::Sender<()>) -> Self {
+        Self {
+            resume_edits: Some(resume_edits),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "sync")]
+    pub(crate) fn with_sync(mut self, sync: OwnedRwLockWriteGuard<()>) -> Self {
+        self.sync = Some(sync);
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DocumentConfig {
+    pub version: DocumentVersionPB,
+    /// Mask the text of the document's operations before writing them to the logs.
+    pub redact_logs: bool,
+    /// Runs the background tasks of the documents, e.g. the edit queues and the deferred saves.
+    /// The host application passes the handle of its own runtime to keep these tasks on it.
+    pub executor: Executor,
+    /// Runs the blocking tasks of the documents on the `executor`, the ones of the focused
+    /// document first, see `set_focused_document`. The re-export and the other work over all the
+    /// documents run as background tasks.
+    pub priority_scheduler: PriorityScheduler,
+    /// Opens the delta documents with their local revisions right away and fetches the latest
+    /// version from the server in the background. The document gets refreshed with the newer
+    /// version, see `fetch_overwrite_policy` for the documents with unsynced local revisions.
+    pub revalidate_on_open: bool,
+    /// Applies to the unsynced local revisions when the server's version replaces the document,
+    /// on revalidation, on hydration and when the server resets the document while syncing.
+    pub fetch_overwrite_policy: FetchOverwritePolicy,
+    /// Checks the local changes of the delta documents before they are saved.
+    pub revision_guards: RevisionGuards,
+    /// Decides when the local changes are written to disk, see `set_power_state`.
+    pub save_debounce: SaveDebounceConfiguration,
+    /// Converts the `\r\n` and `\r` line endings of the delta documents passed to
+    /// `create_document`, e.g. the imported ones, to `\n`.
+    pub normalize_line_endings: bool,
+    /// Receives the markdown of the delta documents regenerated by `reexport_documents`.
+    pub export_targets: DocumentExportTargets,
+    /// Backs up the documents on a schedule once `schedule_backups` is called. There are no
+    /// scheduled backups if it's None.
+    pub backup: Option<DocumentBackupConfiguration>,
+    /// The number of the document events that are held until `initialize` completes, the
+    /// events after them fail with `ErrorCode::ModuleNotReady`.
+    pub startup_queue_capacity: usize,
+    /// Saves the identical payloads of the delta documents' revisions once, e.g. the baselines
+    /// of the documents made from the same template. The revisions saved before keep their own
+    /// payloads until `dedupe_revision_payloads` is called.
+    pub share_revision_payloads: bool,
+    /// The attachments that no document refers to are deleted by `reconcile_attachments` once
+    /// they have been orphaned this long.
+    pub attachment_grace_period: Duration,
+    /// Shares one fetch of a document from the server between the calls that fetch it at the
+    /// same time, see `DocumentFetchGuard`.
+    pub single_flight_fetch: bool,
+    /// The documents whose json is larger than this many bytes are opened through a reader,
+    /// see `DocumentManager::read_document_content`. The smaller ones are returned in one buffer.
+    pub streamed_open_threshold: usize,
+    /// The readers of `open_document_reader` that aren't read for this long are dropped.
+    pub document_reader_timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct DocumentBackupConfiguration {
+    /// The time between two backups. The first backup is made one interval after the schedule
+    /// starts.
+    pub interval: Duration,
+    /// The oldest backups are removed once there are more than this many, see `rotate_backups`.
+    pub max_backups: usize,
+    /// The number of the incremental backups made after each full backup. An incremental backup
+    /// only keeps the documents changed since the previous backup. Every backup is a full backup
+    /// if it's 0.
+    pub max_incremental_backups: usize,
+    /// The directories of the host application that are copied into each backup along with the
+    /// database, e.g. the images of the documents.
+    pub blob_dirs: Vec<PathBuf>,
+}
+
+impl std::default::Default for DocumentBackupConfiguration {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(24 * 60 * 60),
+            max_backups: 7,
+            max_incremental_backups: 0,
+            blob_dirs: vec![],
+        }
+    }
+}
+
+impl std::default::Default for DocumentConfig {
+    fn default() -> Self {
+        Self {
+            version: DocumentVersionPB::V1,
+            redact_logs: false,
+            executor: Executor::default(),
+            priority_scheduler: PriorityScheduler::default(),
+            revalidate_on_open: false,
+            fetch_overwrite_policy: FetchOverwritePolicy::default(),
+            revision_guards: RevisionGuards::default(),
+            save_debounce: SaveDebounceConfiguration::default(),
+            normalize_line_endings: false,
+            export_targets: DocumentExportTargets::default(),
+            backup: None,
+            startup_queue_capacity: 64,
+            share_revision_payloads: false,
+            attachment_grace_period: DEFAULT_ATTACHMENT_GRACE_PERIOD,
+            single_flight_fetch: true,
+            streamed_open_threshold: DEFAULT_STREAMED_OPEN_THRESHOLD,
+            document_reader_timeout: DEFAULT_DOCUMENT_READER_TIMEOUT,
+        }
+    }
+}
+
+pub struct DocumentManager {
+    server_resolver: Arc<dyn DocumentServerResolver>,
+    listened_endpoints: Arc<RwLock<HashSet<String>>>,
+    editor_map: Arc<RwLock<RefCountHashMap<RefCountDocumentHandler>>>,
+    user: Arc<dyn DocumentUser>,
+    persistence: Arc<DocumentPersistence>,
+    startup_report: Arc<RwLock<Option<DocumentStartupReport>>>,
+    /// The documents that `open_latest_available` is opening in the background.
+    opening_documents: Arc<RwLock<HashSet<String>>>,
+    compose_error_observer: Option<Arc<dyn ComposeErrorObserver>>,
+    error_reporter: Option<Arc<dyn ErrorReporter>>,
+    reexport_cancelled: Arc<AtomicBool>,
+    /// Set once a backup is staged by `restore_from_backup`, no document is opened until the
+    /// next launch swaps the restored database in.
+    restore_staged: Arc<AtomicBool>,
+    /// Notified when the remote changes of a document refer to attachments, see
+    /// `schedule_attachment_reconcile`.
+    attachment_reconcile: Arc<Notify>,
+    attachment_transfers: AttachmentTransfers,
+    startup_gate: AFPluginStartupGate,
+    fetch_guard: Arc<DocumentFetchGuard>,
+    document_readers: Arc<DocumentReaders>,
+    /// Set by the folder once it's created, see `set_scope_resolver`.
+    scope_resolver: Arc<RwLock<Option<Arc<dyn DocumentScopeResolver>>>>,
+    /// The scratch flag of the documents, read from their views the first time they're opened.
+    scratch_documents: Arc<DashMap<String, bool>>,
+    /// The custom attributes of this manager's delta documents, see `register_custom_attribute`.
+    custom_attributes: CustomAttributes,
+    /// Unregisters the queue of the document notifications when the manager is dropped. None
+    /// if another manager registered it.
+    _notification_queue: Option<NotificationQueueRegistration>,
+    #[allow(dead_code)]
+    config: DocumentConfig,
+}
+
+impl DocumentManager {
+    pub fn new(
+        cloud_service: Arc<dyn DocumentCloudService>,
+        document_user: Arc<dyn DocumentUser>,
+        database: Arc<dyn DocumentDatabase>,
+        rev_web_socket: Arc<dyn RevisionWebSocket>,
+        config: DocumentConfig,
+    ) -> Self {
+        let default_server = DocumentServer::new(DEFAULT_DOCUMENT_ENDPOINT, cloud_service, rev_web_socket);
+        let notification_queue = register_document_notification_queue();
+        Self {
+            server_resolver: Arc::new(DocumentServerTable::new(default_server)),
+            listened_endpoints: Arc::new(RwLock::new(HashSet::new())),
+            editor_map: Arc::new(RwLock::new(RefCountHashMap::new())),
+            user: document_user,
+            persistence: Arc::new(DocumentPersistence::new(database)),
+            startup_report: Arc::new(RwLock::new(None)),
+            opening_documents: Arc::new(RwLock::new(HashSet::new())),
+            compose_error_observer: None,
+            error_reporter: None,
+            reexport_cancelled: Arc::new(AtomicBool::new(false)),
+            restore_staged: Arc::new(AtomicBool::new(false)),
+            attachment_reconcile: Arc::new(Notify::new()),
+            attachment_transfers: AttachmentTransfers::default(),
+            startup_gate: AFPluginStartupGate::new(config.startup_queue_capacity),
+            fetch_guard: Arc::new(DocumentFetchGuard::new()),
+            document_readers: Arc::new(DocumentReaders::new(config.document_reader_timeout)),
+            scope_resolver: Arc::new(RwLock::new(None)),
+            scratch_documents: Arc::new(DashMap::new()),
+            custom_attributes: CustomAttributes::default(),
+            _notification_queue: notification_queue,
+            config,
+        }
+    }
+
+    /// Replaces the default resolver, which sends every document to the server passed in `new`.
+    pub fn with_server_resolver(mut self, server_resolver: Arc<dyn DocumentServerResolver>) -> Self {
+        self.server_resolver = server_resolver;
+        self
+    }
+
+    /// Syncs the document with the `server` instead of the one it resolves to, e.g. a document
+    /// shared from another workspace that lives on a different server. The host sets it when the
+    /// shared document is accepted and after each launch, it isn't saved. The opened editor of the
+    /// document is closed, it syncs with the `server` once it's opened again.
+    pub async fn set_document_server(&self, doc_id: &str, server: DocumentServer) -> FlowyResult<()> {
+        self.server_resolver.set_server(doc_id, server.clone());
+        self.listen_server_if_need(&server).await;
+        self.close_document_editor(doc_id).await
+    }
+
+    /// Syncs the document with the server it resolved to before `set_document_server` again.
+    pub async fn remove_document_server(&self, doc_id: &str) -> FlowyResult<()> {
+        if self.server_resolver.remove_server(doc_id).is_some() {
+            self.close_document_editor(doc_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Sets the resolver of the workspaces and the apps of `FindReplaceScope`. The folder is
+    /// created after the documents, so it's set afterwards instead of passed to `new`.
+    pub async fn set_scope_resolver(&self, scope_resolver: Arc<dyn DocumentScopeResolver>) {
+        *self.scope_resolver.write().await = Some(scope_resolver);
+    }
+
+    /// Passes the revision that can't be deserialized or composed when a document is opened to
+    /// the `observer`. Only the delta documents can tell which revision failed.
+    pub fn with_compose_error_observer(mut self, observer: Arc<dyn ComposeErrorObserver>) -> Self {
+        self.compose_error_observer = Some(observer);
+        self
+    }
+
+    /// Passes the failures of the documents' background tasks, e.g. the deferred saves and the
+    /// web sockets, to the `error_reporter` besides logging them.
+    pub fn with_error_reporter(mut self, error_reporter: Arc<dyn ErrorReporter>) -> Self {
+        self.error_reporter = Some(error_reporter);
+        self
+    }
+
+    /// Registers `key` as an extra inline attribute of this manager's delta documents with the
+    /// given `semantics`. The exporter keeps them too, see
+    /// `encode_markdown_list_with_custom_attributes`. It must be called before any document is
+    /// opened, the opened documents wouldn't agree with each other on the values they accept
+    /// otherwise, it fails with `ErrorCode::AttributeRegistrationClosed` then.
+    pub async fn register_custom_attribute(&self, key: &str, semantics: CustomAttributeSemantics) -> FlowyResult<()> {
+        let mut opened_documents = self.editor_map.read().await.keys();
+        opened_documents.extend(self.opening_documents.read().await.iter().cloned());
+        if !opened_documents.is_empty() {
+            return Err(FlowyError::attribute_registration_closed().context(format!(
+                "Register the custom attribute {} after the documents {:?} are opened",
+                key, opened_documents
+            )));
+        }
+        self.custom_attributes
+            .register(key, semantics)
+            .map_err(|e| FlowyError::invalid_attribute().context(e))
+    }
+
+    /// Called immediately after the application launched with the user sign in/sign up.
+    #[tracing::instrument(level = "trace", skip_all, err)]
+    pub async fn initialize(&self, user_id: &str) -> FlowyResult<()> {
+        let (mut report, result) = self.persistence.initialize(user_id, &self.config.version);
+        let start = Instant::now();
+        for server in self.server_resolver.servers() {
+            self.listen_server_if_need(&server).await;
+        }
+        report.add_phase("listen_servers", start.elapsed(), None);
+
+        tracing::info!("[Document Startup]: {:?}", report);
+        send_anonymous_dart_notification(DocumentNotification::DidCompleteStartup)
+            .payload(DocumentStartupReportPB::from(report.clone()))
+            .send();
+        *self.startup_report.write().await = Some(report);
+        // The documents can still be opened after a failed phase, so the events are let through
+        // before its error is returned.
+        self.open_startup_gate();
+        result
+    }
+
+    /// Holds the document events until `initialize` completes, see `event_map::init`. The host
+    /// advances its generation to drop the held events that became stale, e.g. the ones of the
+    /// view that the user already left.
+    pub fn startup_gate(&self) -> AFPluginStartupGate {
+        self.startup_gate.clone()
+    }
+
+    /// Returns the depth of the queue of the document notifications and the number of the
+    /// notifications it dropped, see `document_notification_queue_config`.
+    pub fn notification_queue_stats(&self) -> NotificationQueueStats {
+        document_notification_queue_stats()
+    }
+
+    /// Returns the report of the last `initialize`, it's None before the user signs in.
+    pub async fn startup_report(&self) -> Option<DocumentStartupReport> {
+        self.startup_report.read().await.clone()
+    }
+
+    pub async fn initialize_with_new_user(&self, _user_id: &str, _token: &str) -> FlowyResult<()> {
+        self.open_startup_gate();
+        Ok(())
+    }
+
+    /// Replays the events held while initializing in the background, then lets the events
+    /// through and notifies the frontend that the documents are ready. The host calls it when
+    /// the documents won't be initialized, e.g. the sign in failed, so the held events fail
+    /// instead of waiting. Calling it again while the events are replayed does nothing.
+    pub fn open_startup_gate(&self) {
+        if self.startup_gate.is_ready() {
+            return;
+        }
+        let startup_gate = self.startup_gate.clone();
+        self.config.executor.spawn(async move {
+            if startup_gate.open().await {
+                send_anonymous_dart_notification(DocumentNotification::DidBecomeReady).send();
+            }
+        });
+    }
+
+    #[tracing::instrument(level = "trace", skip_all, fields(document_id), err)]
+    pub async fn open_document_editor<T: AsRef<str>>(
+        &self,
+        document_id: T,
+    ) -> Result<Arc<dyn DocumentEditor>, FlowyError> {
+        let document_id = document_id.as_ref();
+        tracing::Span::current().record("document_id", &document_id);
+        self.init_document_editor(document_id).await
+    }
+
+    /// Opens the document without waiting behind the background work on the disk, e.g. a
+    /// maintenance run. The document that is already opened is returned right away. Otherwise,
+    /// the document gets the focus, so its reads go before the background work, and is opened
+    /// in the background: it's sent with the `DidOpenDocument` notification, and the meta it had
+    /// when it was last closed is returned meanwhile for the UI to render its skeleton.
+    pub async fn open_latest_available(self: &Arc<Self>, doc_id: &str) -> FlowyResult<AvailableDocument> {
+        let opened_editor = self.editor_map.read().await.get(doc_id).map(|handler| handler.0);
+        if let Some(editor) = opened_editor {
+            return Ok(AvailableDocument::Ready(editor.export().await?));
+        }
+
+        self.set_focused_document(Some(doc_id));
+        // The saved meta is one row read by its key, it doesn't go behind the background work.
+        let meta = match self.closed_document_meta(doc_id) {
+            Ok(meta) => meta,
+            Err(e) => {
+                tracing::warn!("Read the meta of {} failed: {:?}", doc_id, e);
+                None
+            }
+        };
+        if !self.opening_documents.write().await.insert(doc_id.to_owned()) {
+            return Ok(AvailableDocument::Loading(meta));
+        }
+        let weak_manager = Arc::downgrade(self);
+        let doc_id = doc_id.to_owned();
+        self.config.executor.spawn(async move {
+            let manager = match weak_manager.upgrade() {
+                None => return,
+                Some(manager) => manager,
+            };
+            let result = match manager.open_document_editor(&doc_id).await {
+                Ok(editor) => editor.export().await,
+                Err(e) => Err(e),
+            };
+            manager.opening_documents.write().await.remove(&doc_id);
+
+            let notification = send_dart_notification(&doc_id, DocumentNotification::DidOpenDocument);
+            match result {
+                Ok(snapshot) => notification
+                    .payload(DocumentSnapshotPB {
+                        doc_id,
+                        snapshot,
+                        ..Default::default()
+                    })
+                    .send(),
+                Err(e) => {
+                    tracing::error!("Open the document {} failed: {:?}", doc_id, e);
+                    notification.error(e).send();
+                }
+            }
+        });
+        Ok(AvailableDocument::Loading(meta))
+    }
+
+    /// Returns the JSON of the document. The archived text is left out unless `include_archived`,
+    /// see `without_archived`. The node documents leave it out of the delta of each text node.
+    pub async fn export_document(&self, doc_id: &str, include_archived: bool) -> FlowyResult<String> {
+        let editor = self.open_document_editor(doc_id).await?;
+        let data = editor.export().await?;
+        if include_archived {
+            return Ok(data);
+        }
+        match self.config.version {
+            DocumentVersionPB::V0 => {
+                let operations = DeltaTextOperations::from_json(&data)?;
+                Ok(without_archived(&operations).json_str())
+            }
+            DocumentVersionPB::V1 => content_without_archived(&data),
+        }
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, editor_id), fields(editor_id), err)]
+    pub async fn close_document_editor<T: AsRef<str>>(&self, editor_id: T) -> Result<(), FlowyError> {
+        let editor_id = editor_id.as_ref();
+        tracing::Span::current().record("editor_id", &editor_id);
+        if let Some(editor) = self.opened_delta_document_editor(editor_id).await {
+            if let Err(e) = self.save_content_hash(editor_id, &editor).await {
+                tracing::error!("Save the content hash of {} failed: {:?}", editor_id, e);
+            }
+            if let Err(e) = self.save_closed_document_meta(editor_id, &editor).await {
+                tracing::error!("Save the meta of {} failed: {:?}", editor_id, e);
+            }
+            if let Err(e) = self.save_search_index_entry(editor_id, &editor).await {
+                tracing::error!("Save the search index of {} failed: {:?}", editor_id, e);
+            }
+        }
+        self.editor_map.write().await.remove(editor_id).await;
+        if self.config.priority_scheduler.focused().as_deref() == Some(editor_id) {
+            self.config.priority_scheduler.set_focused(None);
+        }
+        Ok(())
+    }
+
+    /// The Flutter side calls it when the focus moves to another document, or with None when no
+    /// document is focused. The reads of the focused document run before the background work,
+    /// e.g. the re-export, see `DocumentConfig::priority_scheduler`.
+    pub fn set_focused_document(&self, doc_id: Option<&str>) {
+        self.config.priority_scheduler.set_focused(doc_id);
+    }
+
+    /// The host application calls it when the device switches between battery and power. The
+    /// documents save less often on battery.
+    pub fn set_power_state(&self, power_state: PowerState) {
+        self.config.save_debounce.set_power_state(power_state);
+    }
+
+    /// Returns the latest rev_id of each document of the current user, see
+    /// `DocumentPersistence::read_all_heads`.
+    pub fn document_heads(&self) -> FlowyResult<HashMap<String, i64>> {
+        self.persistence.read_all_heads(&self.config.version)
+    }
+
+    /// Returns the files that hold the documents of the current user.
+    pub fn storage_paths(&self) -> FlowyResult<Vec<StoragePath>> {
+        let user_dir = self.user.user_dir()?;
+        let conn = self.persistence.database.db_pool()?.get()?;
+        query_storage_paths(&user_dir, &conn)
+    }
+
+    /// Copies the documents of the current user into `dir`. The opened documents are paused and
+    /// flushed first, so no edit or synced revision gets written during the copy, and no document
+    /// gets opened or closed until the copy is done.
+    pub async fn safe_backup_to(&self, dir: &str) -> FlowyResult<PathBuf> {
+        let editor_map = self.editor_map.read().await;
+        let mut pauses = Vec::with_capacity(editor_map.len());
+        for handler in editor_map.values() {
+            pauses.push(handler.0.pause().await?);
+            handler.0.flush().await?;
+        }
+        let conn = self.persistence.database.db_pool()?.get()?;
+        let path = backup_database(&conn, dir)?;
+        drop(pauses);
+        drop(editor_map);
+        Ok(path)
+    }
+
+    /// Checks that the stored revisions of the delta documents can be parsed. It reads each
+    /// revision on its own, so a corrupted revision is reported instead of failing the document.
+    pub fn validate_all_documents(&self) -> FlowyResult<Vec<InvalidRevision>> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        DeltaRevisionSql::validate_all(&conn)
+    }
+
+    /// Recovers or quarantines the revisions reported by `validate_all_documents`. Returns the
+    /// audit entries of the repairs, they're also saved and can be read by `repair_audit`.
+    pub fn repair_all_documents(&self) -> FlowyResult<Vec<RepairAuditEntry>> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        DeltaRevisionSql::repair_all(&conn)
+    }
+
+    /// Shares the payloads of the delta documents' revisions that were saved before
+    /// `share_revision_payloads` was turned on. It only needs to run once, the new revisions
+    /// share their payloads as they're saved.
+    pub fn dedupe_revision_payloads(&self) -> FlowyResult<PayloadDedupeSummary> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        DeltaRevisionSql::share_all_payloads(&conn)
+    }
+
+    /// Deletes the acked revisions of the document that don't change it and rebases the ones
+    /// after them, see `DeltaRevisionSql::collapse_noops`. The opened document is closed first,
+    /// it keeps its revisions in memory. Returns the number of the deleted revisions.
+    ///
+    /// Only the delta documents have the revisions that only retain, the node documents fail.
+    pub async fn collapse_noops(&self, doc_id: &str) -> FlowyResult<usize> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("Only the delta documents can collapse the no-op revisions"));
+        }
+        if let Some(editor) = self.opened_delta_document_editor(doc_id).await {
+            editor.flush().await?;
+            drop(editor);
+            self.close_document_editor(doc_id).await?;
+        }
+        let pool = self.persistence.database.db_pool()?;
+        let doc_id = doc_id.to_owned();
+        let scheduler = &self.config.priority_scheduler;
+        let priority = scheduler.priority_of(&doc_id);
+        scheduler
+            .spawn_blocking(&self.config.executor, priority, move || {
+                DeltaRevisionSql::collapse_noops(&doc_id, &*pool.get()?)
+            })
+            .await
+            .map_err(internal_error)?
+    }
+
+    pub fn repair_audit(&self) -> FlowyResult<Vec<RepairAuditEntry>> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        read_repair_audit(&conn)
+    }
+
+    /// The last resort for the delta document that can't be opened, see
+    /// `ErrorCode::DocumentUnreadable`. Writes the inserted text of all its revisions into a
+    /// new file under the `recovered` folder of the user and returns the path of the file. The
+    /// revisions are read as they are, see `DeltaRevisionSql::recover_text`, nothing is changed
+    /// in the database.
+    pub async fn recover_text(&self, doc_id: &str) -> FlowyResult<String> {
+        if let Some(editor) = self.opened_delta_document_editor(doc_id).await {
+            editor.flush().await?;
+        }
+        let text = {
+            let conn = self.persistence.database.db_pool()?.get()?;
+            DeltaRevisionSql::recover_text(doc_id, &conn)?
+        };
+        let recovered_dir = Path::new(&self.user.user_dir()?).join(RECOVERED_TEXT_DIR);
+        let path = write_recovered_text(&recovered_dir, doc_id, &text)?;
+        tracing::info!("Recovered the text of {} to {:?}", doc_id, path);
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    /// Returns the json of the document like `DocumentEditor::export` if it's at most
+    /// `streamed_open_threshold` bytes, otherwise returns the handle of a reader that is read by
+    /// chunks with `read_document_chunk`. The json is never copied into one buffer then.
+    pub async fn read_document_content(&self, doc_id: &str) -> FlowyResult<DocumentContent> {
+        let editor = self.open_document_editor(doc_id).await?;
+        let buffer = editor.export_chunked(DOCUMENT_JSON_CHUNK_SIZE).await?;
+        if buffer.len() <= self.config.streamed_open_threshold {
+            let json = String::from_utf8(buffer.to_vec()).map_err(internal_error)?;
+            return Ok(DocumentContent::Inline(json));
+        }
+        let len = buffer.len();
+        let handle = self.document_readers.insert(buffer.into_reader());
+        Ok(DocumentContent::Streamed { handle, len })
+    }
+
+    /// Returns the handle of a reader of the document's json whatever its size, e.g. for the
+    /// embedders that read it through `appflowy_doc_read_chunk`. Returns the length of the json
+    /// too.
+    pub async fn open_document_reader(&self, doc_id: &str) -> FlowyResult<(i64, usize)> {
+        let editor = self.open_document_editor(doc_id).await?;
+        let buffer = editor.export_chunked(DOCUMENT_JSON_CHUNK_SIZE).await?;
+        let len = buffer.len();
+        Ok((self.document_readers.insert(buffer.into_reader()), len))
+    }
+
+    /// Copies the next bytes of the reader into `buf`, returns the number of the copied bytes.
+    /// Returns 0 once the json was read to the end, the reader is closed then.
+    pub fn read_document_chunk(&self, handle: i64, buf: &mut [u8]) -> FlowyResult<usize> {
+        self.document_readers
+            .read_chunk(handle, buf)
+            .ok_or_else(|| FlowyError::record_not_found().context(format!("Unknown document reader: {}", handle)))
+    }
+
+    /// Drops the reader before it was read to the end. Returns false if there was no reader.
+    pub fn close_document_reader(&self, handle: i64) -> bool {
+        self.document_readers.remove(handle)
+    }
+
+    /// Describes the revision chain of the delta document as a Graphviz DOT digraph to debug
+    /// a forked history, see `RevGraph::to_dot`. Only the last `tail` revisions are drawn if it's
+    /// set. It only has the metadata of the revisions, none of their content.
+    pub async fn export_rev_graph(&self, doc_id: &str, tail: Option<usize>) -> FlowyResult<String> {
+        if let Some(editor) = self.opened_delta_document_editor(doc_id).await {
+            editor.flush().await?;
+        }
+        let conn = self.persistence.database.db_pool()?.get()?;
+        // Read in one transaction, so the links and the nodes are of the same chain.
+        let graph = conn.immediate_transaction::<_, FlowyError, _>(|| {
+            Ok(RevGraph {
+                doc_id: doc_id.to_owned(),
+                links: DeltaRevisionSql::read_rev_links(doc_id, &conn)?,
+                nodes: DeltaRevisionSql::read_rev_graph_nodes(doc_id, tail, &conn)?,
+                snapshot_rev_id: read_last_snapshot_rev_id(doc_id, &conn)?,
+            })
+        })?;
+        Ok(graph.to_dot())
+    }
+
+    /// Backs up the documents every `interval` of the `backup` configuration until the manager
+    /// is dropped. Does nothing if there is no `backup` configuration.
+    pub fn schedule_backups(self: &Arc<Self>) {
+        let configuration = match &self.config.backup {
+            None => return,
+            Some(configuration) => configuration.clone(),
+        };
+        // The first backup is due an `interval` after the last one, so the schedule isn't reset
+        // by every launch.
+        let start = tokio::time::Instant::now() + self.next_backup_delay(configuration.interval);
+        let weak_manager = Arc::downgrade(self);
+        self.config.executor.spawn(async move {
+            let mut ticker = tokio::time::interval_at(start, configuration.interval);
+            loop {
+                ticker.tick().await;
+                match weak_manager.upgrade() {
+                    None => break,
+                    Some(manager) => {
+                        if let Err(e) = manager.backup_now().await {
+                            tracing::error!("Scheduled backup failed: {:?}", e);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Returns the time until the next scheduled backup. It's right away if no backup succeeded
+    /// yet or the last one is more than an `interval` ago, e.g. the app wasn't running.
+    fn next_backup_delay(&self, interval: Duration) -> Duration {
+        let read_last = || -> FlowyResult<Option<i64>> {
+            let conn = self.persistence.database.db_pool()?.get()?;
+            read_last_backup_timestamp(&conn)
+        };
+        match read_last() {
+            Ok(Some(last)) => interval.saturating_sub(Duration::from_secs((timestamp() - last).max(0) as u64)),
+            Ok(None) => Duration::ZERO,
+            Err(e) => {
+                tracing::error!("Read the time of the last backup failed: {:?}", e);
+                interval
+            }
+        }
+    }
+
+    /// Writes a new backup of the documents of the current user under the `backups` folder of
+    /// the user, an incremental one unless the `max_incremental_backups` were made since the
+    /// last full backup. Then removes the oldest backups beyond the `max_backups`. The outcome
+    /// is saved in the backup audit, and a failure is also sent to the frontend.
+    pub async fn backup_now(&self) -> FlowyResult<PathBuf> {
+        let configuration = self.config.backup.clone().unwrap_or_default();
+        let backups_dir = Path::new(&self.user.user_dir()?).join(BACKUPS_DIR);
+        let result = self.write_rotated_backup(&backups_dir, &configuration).await;
+
+        let audit_path = result
+            .as_ref()
+            .map(|path| path.as_path())
+            .unwrap_or(backups_dir.as_path());
+        self.audit_backup(&BackupAuditEntry::new(BackupKind::Backup, audit_path, &result));
+        if let Err(e) = &result {
+            send_anonymous_dart_notification(DocumentNotification::DidFailBackup)
+                .error(e.clone())
+                .send();
+        }
+        result
+    }
+
+    async fn write_rotated_backup(
+        &self,
+        backups_dir: &Path,
+        configuration: &DocumentBackupConfiguration,
+    ) -> FlowyResult<PathBuf> {
+        self.flush_opened_editors().await?;
+        // The copy is taken from the database itself, so it doesn't hold the editors while the
+        // files are written.
+        let pool = self.persistence.database.db_pool()?;
+        let backups_dir = backups_dir.to_owned();
+        let configuration = configuration.clone();
+        self.run_maintenance_blocking(move || {
+            let conn = pool.get()?;
+            let path = match incremental_backup_parent(&backups_dir, configuration.max_incremental_backups)? {
+                None => write_backup(&conn, &backups_dir, &configuration.blob_dirs)?,
+                Some(parent) => write_incremental_backup(&conn, &backups_dir, &parent, &configuration.blob_dirs)?,
+            };
+            for removed in rotate_backups(&backups_dir, configuration.max_backups)? {
+                tracing::trace!("Remove the old backup: {:?}", removed);
+            }
+            Ok(path)
+        })
+        .await
+    }
+
+    /// Flushes the opened documents. The editors are only held to collect them, the documents
+    /// can be opened or closed during the flush.
+    async fn flush_opened_editors(&self) -> FlowyResult<()> {
+        let editors = self
+            .editor_map
+            .read()
+            .await
+            .values()
+            .into_iter()
+            .map(|handler| handler.0)
+            .collect::<Vec<_>>();
+        for editor in editors {
+            editor.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the backups under the `backups` folder of the current user, the oldest first.
+    pub fn backups(&self) -> FlowyResult<Vec<PathBuf>> {
+        let backups_dir = Path::new(&self.user.user_dir()?).join(BACKUPS_DIR);
+        list_backups(&backups_dir)
+    }
+
+    /// Validates the backup at `path` and stages its database, it replaces the current database
+    /// the next time the database is opened, i.e. on the next launch. The whole database of the
+    /// user is restored, not only the documents, and the other modules keep the current database
+    /// until then. The files of the blob directories kept by the backup are copied back right
+    /// away, the files added since the backup are left as they are. An incremental backup is
+    /// applied on top of its full backup and the incremental backups before it, the restore is
+    /// refused if any of them is missing.
+    ///
+    /// The edits made after the restore would be lost by the swap, so the opened documents are
+    /// closed and no document can be opened until the next launch. The `DidStageRestore`
+    /// notification is sent for the UI to ask for the restart.
+    ///
+    /// The revisions that aren't synced yet would be lost, so it's refused while there are any
+    /// unless `force` is true.
+    pub async fn restore_from_backup(&self, path: &str, force: bool) -> FlowyResult<()> {
+        let result = self.stage_backup(path, force).await;
+        self.audit_backup(&BackupAuditEntry::new(BackupKind::Restore, Path::new(path), &result));
+        let _ = result?;
+
+        self.restore_staged.store(true, Ordering::SeqCst);
+        let doc_ids = self.editor_map.read().await.keys();
+        for doc_id in doc_ids {
+            // Every reference to the document is closed.
+            while self.editor_map.read().await.get(&doc_id).is_some() {
+                self.close_document_editor(&doc_id).await?;
+            }
+        }
+        send_anonymous_dart_notification(DocumentNotification::DidStageRestore).send();
+        Ok(())
+    }
+
+    async fn stage_backup(&self, path: &str, force: bool) -> FlowyResult<PathBuf> {
+        let user_dir = self.user.user_dir()?;
+        // The opened documents are flushed, so their unsaved revisions are counted as unsynced.
+        self.flush_opened_editors().await?;
+        let pool = self.persistence.database.db_pool()?;
+        let path = PathBuf::from(path);
+        let blob_dirs = self.config.backup.clone().unwrap_or_default().blob_dirs;
+        self.run_maintenance_blocking(move || {
+            let conn = pool.get()?;
+            let chain = resolve_backup_chain(&path)?;
+            let db_path = validate_backup(&chain[0], &conn)?;
+            for backup_dir in &chain[1..] {
+                validate_incremental_backup(backup_dir)?;
+            }
+            if !force {
+                let num_of_unsynced = count_unsynced_revisions(&conn)?;
+                if num_of_unsynced > 0 {
+                    return Err(FlowyError::invalid_data().context(format!(
+                        "{} revisions aren't synced yet, the restore would lose them",
+                        num_of_unsynced
+                    )));
+                }
+            }
+            drop(conn);
+            let staged_path = if chain.len() == 1 {
+                stage_database(&db_path, &user_dir)?
+            } else {
+                let layered_path = layer_backup_chain(&db_path, &chain[1..], &user_dir)?;
+                let result = stage_database(&layered_path, &user_dir);
+                let _ = std::fs::remove_file(&layered_path);
+                result?
+            };
+            restore_blob_dirs(&chain, &blob_dirs)?;
+            Ok(staged_path)
+        })
+        .await
+    }
+
+    fn audit_backup(&self, entry: &BackupAuditEntry) {
+        let write_audit = || -> FlowyResult<()> {
+            let conn = self.persistence.database.db_pool()?.get()?;
+            write_backup_audit(entry, &conn)
+        };
+        if let Err(e) = write_audit() {
+            tracing::error!("Save the backup audit failed: {:?}", e);
+        }
+    }
+
+    pub fn backup_audit(&self) -> FlowyResult<Vec<BackupAuditEntry>> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        read_backup_audit(&conn)
+    }
+
+    /// Returns the snapshots of the document that the server couldn't confirm when it was
+    /// opened, with the names of the versions they're kept as. The `DidRecoverSnapshot`
+    /// notification is sent when one is kept.
+    pub fn recovered_versions(&self, doc_id: &str) -> FlowyResult<Vec<(String, RevisionSnapshot)>> {
+        let pool = self.persistence.database.db_pool()?;
+        self.make_rev_manager(doc_id, pool)?.read_demoted_snapshots()
+    }
+
+    /// Applies the operations to the document. The operations whose attributes are stripped, see
+    /// `EditPayloadPB::strip_all_attributes`, must be the operations of a delta document.
+    pub async fn apply_edit(&self, params: EditParams) -> FlowyResult<()> {
+        let editor = self.get_document_editor(&params.doc_id).await?;
+        let operations = if params.strip_all_attributes {
+            let operations = DeltaTextOperations::from_json(&params.operations)?;
+            strip_all_attributes(&operations).json_str()
+        } else {
+            params.operations
+        };
+        editor.compose_local_operations(Bytes::from(operations)).await?;
+        Ok(())
+    }
+
+    pub async fn create_document<T: AsRef<str>>(&self, doc_id: T, revisions: Vec<Revision>) -> FlowyResult<()> {
+        let doc_id = doc_id.as_ref().to_owned();
+        let revisions = if self.config.normalize_line_endings && self.config.version == DocumentVersionPB::V0 {
+            normalize_line_endings_of_revisions(&doc_id, revisions)?
+        } else {
+            revisions
+        };
+        let db_pool = self.persistence.database.db_pool()?;
+        // Maybe we could save the document to disk without creating the RevisionManager
+        let rev_manager = self.make_rev_manager(&doc_id, db_pool)?;
+        rev_manager.reset_object(revisions).await?;
+        Ok(())
+    }
+
+    pub async fn receive_ws_data(&self, data: Bytes) {
+        let result: Result<ServerRevisionWSData, serde_json::Error> = ServerRevisionWSData::try_from(data);
+        match result {
+            Ok(data) => match self.editor_map.read().await.get(&data.object_id) {
+                None => tracing::error!(
+                    "Can't find any source handler for {:?}-{:?}",
+                    data.object_id,
+                    data.payload
+                ),
+                Some(handler) => match handler.0.receive_ws_data(data).await {
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("{}", e),
+                },
+            },
+            Err(e) => {
+                tracing::error!("Document ws data parser failed: {:?}", e);
+            }
+        }
+    }
+
+    /// Marks the document as a scratch document. A scratch document never syncs to the server,
+    /// and only its last `SCRATCH_DOCUMENT_HISTORY_LIMIT` revisions are kept.
+    ///
+    /// The flag is saved with the view of the document by the folder, this only tells the manager
+    /// about it, e.g. before the view is created.
+    pub fn set_scratch_document(&self, doc_id: &str, is_scratch: bool) {
+        self.scratch_documents.insert(doc_id.to_owned(), is_scratch);
+    }
+
+    /// Returns true if the document is a scratch document. The flag is read from the view of the
+    /// document if the manager wasn't told about it yet.
+    pub async fn is_scratch_document(&self, doc_id: &str) -> FlowyResult<bool> {
+        if let Some(is_scratch) = self.scratch_documents.get(doc_id) {
+            return Ok(*is_scratch);
+        }
+        let scope_resolver = self.scope_resolver.read().await.clone();
+        let is_scratch = match scope_resolver {
+            None => false,
+            Some(scope_resolver) => scope_resolver.is_scratch_view(doc_id).await?,
+        };
+        self.scratch_documents.insert(doc_id.to_owned(), is_scratch);
+        Ok(is_scratch)
+    }
+
+    /// Converts the scratch document into a normal document. The current content of the document
+    /// is saved as its first revision, which syncs to the server like any other revision.
+    #[tracing::instrument(level = "trace", skip(self), err)]
+    pub async fn convert_scratch_document(&self, doc_id: &str) -> FlowyResult<()> {
+        if !self.is_scratch_document(doc_id).await? {
+            return Ok(());
+        }
+
+        if self.editor_map.read().await.get(doc_id).is_some() {
+            return Err(FlowyError::internal().context("Close the document before converting it"));
+        }
+
+        let pool = self.persistence.database.db_pool()?;
+        let revisions = self.make_rev_manager(doc_id, pool.clone())?.load_revisions().await?;
+        if let Some(scope_resolver) = self.scope_resolver.read().await.clone() {
+            scope_resolver.set_view_scratch(doc_id, false).await?;
+        }
+        self.set_scratch_document(doc_id, false);
+        if revisions.is_empty() {
+            return Ok(());
+        }
+
+        let bytes = match self.config.version {
+            DocumentVersionPB::V0 => DeltaDocumentRevisionMergeable().combine_revisions(revisions)?,
+            DocumentVersionPB::V1 => DocumentRevisionMergeable().combine_revisions(revisions)?,
+        };
+        let doc_md5 = md5(&bytes);
+        let revision = Revision::new(doc_id, 0, 1, bytes, doc_md5);
+        let rev_manager = self.make_rev_manager(doc_id, pool)?;
+        rev_manager.reset_object(vec![revision]).await?;
+        Ok(())
+    }
+
+    /// Returns true if the document only has the revision it was created with. An opened
+    /// document is never untouched, its revisions may not be written to disk yet.
+    pub async fn is_untouched_document(&self, doc_id: &str) -> FlowyResult<bool> {
+        if self.editor_map.read().await.get(doc_id).is_some() {
+            return Ok(false);
+        }
+        let pool = self.persistence.database.db_pool()?;
+        let rev_manager = self.make_rev_manager(doc_id, pool)?;
+        Ok(rev_manager.number_of_revisions_in_disk() <= 1)
+    }
+
+    /// Replaces the local revisions of the closed document with the server's document. The
+    /// document is streamed in chunks if the server supports it, so a large document is saved
+    /// without loading it into memory.
+    ///
+    /// The revisions that the server doesn't have yet are handled according to the
+    /// `fetch_overwrite_policy` of the `DocumentConfig`. The hydrations of a document made at
+    /// the same time share one if `single_flight_fetch` is on.
+    pub async fn hydrate_document(&self, doc_id: &str) -> FlowyResult<()> {
+        let hydrate = || self.hydrate_document_with_policy(doc_id, self.config.fetch_overwrite_policy, None);
+        if self.config.single_flight_fetch {
+            self.fetch_guard.hydrate(doc_id, hydrate).await
+        } else {
+            hydrate().await
+        }
+    }
+
+    /// The unsynced revisions start at `next_sync_rev_id` if it's given, otherwise they're the
+    /// revisions that aren't synced and are newer than the server's document.
+    #[tracing::instrument(level = "trace", skip(self), err)]
+    async fn hydrate_document_with_policy(
+        &self,
+        doc_id: &str,
+        policy: FetchOverwritePolicy,
+        next_sync_rev_id: Option<i64>,
+    ) -> FlowyResult<()> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("The hydration only supports the delta documents"));
+        }
+        if self.editor_map.read().await.get(doc_id).is_some() {
+            return Err(FlowyError::internal().context("Close the document before hydrating it"));
+        }
+
+        let token = self.user.token()?;
+        let user_id = self.user.user_id()?;
+        let server = self.cloud_service(doc_id);
+        let pool = self.persistence.database.db_pool()?;
+        let rev_manager = self.make_rev_manager(doc_id, pool.clone())?;
+        // The document is streamed if the server supports it, its rev_id tells which local
+        // revisions the server doesn't have. It's fetched whole otherwise.
+        let server_document = match stage_document_chunks(&server, &token, doc_id, &pool).await? {
+            Some(server_document) => server_document,
+            None => {
+                let cloud_service = DocumentRevisionCloudService { token, server };
+                ServerDocument::Fetched(cloud_service.fetch_object(&user_id, doc_id).await?)
+            }
+        };
+        let result = self
+            .apply_server_document(doc_id, &rev_manager, &server_document, policy, next_sync_rev_id, &pool)
+            .await;
+        server_document.clear(doc_id, &pool)?;
+        result
+    }
+
+    async fn apply_server_document(
+        &self,
+        doc_id: &str,
+        rev_manager: &RevisionManager<Arc<ConnectionPool>>,
+        server_document: &ServerDocument,
+        policy: FetchOverwritePolicy,
+        next_sync_rev_id: Option<i64>,
+        pool: &Arc<ConnectionPool>,
+    ) -> FlowyResult<()> {
+        let next_sync_rev_id = match policy {
+            FetchOverwritePolicy::Overwrite => None,
+            _ => match next_sync_rev_id {
+                Some(rev_id) => Some(rev_id),
+                None => first_unsynced_rev_id(&rev_manager.get_all_revision_records()?, server_document.rev_id()),
+            },
+        };
+        match next_sync_rev_id {
+            None => match server_document {
+                ServerDocument::Staged { .. } => {
+                    server_document.compose_revision(doc_id, policy != FetchOverwritePolicy::Overwrite, pool)
+                }
+                ServerDocument::Fetched(revisions) => rev_manager.reset_object(revisions.clone()).await,
+            },
+            Some(next_sync_rev_id) if policy == FetchOverwritePolicy::Preserve => {
+                Err(FlowyError::internal().context(format!(
+                    "The document:{} has the unsynced revision:{}, it's not replaced with the server's document",
+                    doc_id, next_sync_rev_id
+                )))
+            }
+            Some(next_sync_rev_id) => {
+                // The local changes are rebased onto the server's content, so the staged
+                // document is read whole.
+                let server_revisions = server_document.read_revisions(doc_id, pool)?;
+                let local_revisions = rev_manager.load_revisions().await?;
+                let revisions =
+                    merge_with_server_revisions(doc_id, local_revisions, next_sync_rev_id, server_revisions)?;
+                rev_manager.reset_object(revisions).await
+            }
+        }
+    }
+
+    /// Resumes syncing the document after a sync loop was detected, see `SyncLoopObserver`.
+    /// With `force_reset`, the document is closed and replaced with the server's document
+    /// instead, the revisions that weren't sent are rebased onto it.
+    pub async fn resume_document_sync(&self, doc_id: &str, force_reset: bool) -> FlowyResult<()> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        if force_reset {
+            // The revisions pushed by the server aren't applied while the loop is detected, so
+            // the unsynced revisions are the last ones. Their rev_ids may not be newer than the
+            // server's document after the loop, the sync sequence tells where they start.
+            let next_sync_rev_id = editor.rev_manager().next_sync_rev_id().await;
+            self.close_document_editor(doc_id).await?;
+            return self
+                .hydrate_document_with_policy(doc_id, FetchOverwritePolicy::Merge, next_sync_rev_id)
+                .await;
+        }
+        editor.resume_sync().await
+    }
+
+    /// Tells how far the opened document is behind or ahead of the server: its revisions that
+    /// aren't acked, the ones it gave up pushing and the server's revisions it didn't apply yet.
+    pub async fn sync_health(&self, doc_id: &str) -> FlowyResult<SyncHealthPB> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        let rev_manager = editor.rev_manager();
+        Ok(SyncHealthPB {
+            doc_id: doc_id.to_owned(),
+            unsynced_revisions: rev_manager.number_of_sync_revisions() as i64,
+            remote_backlog: editor.remote_backlog() as i64,
+            dead_letters: rev_manager.dead_letters().await,
+        })
+    }
+
+    /// Keeps the selection of the opened document, the `DidReceiveRemoteChange` notification
+    /// carries the selection transformed by the remote operations.
+    pub async fn update_selection(&self, doc_id: &str, selection: Vec<Interval>) -> FlowyResult<()> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        editor.update_selection(selection).await
+    }
+
+    /// Annotates the changes from the version `from_rev_id` to the version `to_rev_id` of the
+    /// document, see `make_redline`. Only the delta documents keep their versions as deltas.
+    /// The returned operations are for displaying only, never apply them to the document.
+    pub async fn redline(&self, doc_id: &str, from_rev_id: i64, to_rev_id: i64) -> FlowyResult<DeltaTextOperations> {
+        let (from, to) = self.versions_between(doc_id, from_rev_id, to_rev_id).await?;
+        let redline = make_redline(&from, &to)?;
+        Ok(redline)
+    }
+
+    /// Returns the formatting changes from the version `from_rev_id` to the version `to_rev_id`
+    /// of the document, the text edits are left out, see `make_attribute_diff`.
+    pub async fn attribute_diff(
+        &self,
+        doc_id: &str,
+        from_rev_id: i64,
+        to_rev_id: i64,
+    ) -> FlowyResult<DeltaTextOperations> {
+        let (from, to) = self.versions_between(doc_id, from_rev_id, to_rev_id).await?;
+        let attribute_diff = make_attribute_diff(&from, &to)?;
+        Ok(attribute_diff)
+    }
+
+    /// Composes the versions `from_rev_id` and `to_rev_id` of the delta document.
+    async fn versions_between(
+        &self,
+        doc_id: &str,
+        from_rev_id: i64,
+        to_rev_id: i64,
+    ) -> FlowyResult<(DeltaTextOperations, DeltaTextOperations)> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("Only the delta documents keep their versions as deltas"));
+        }
+        if from_rev_id > to_rev_id {
+            return Err(FlowyError::invalid_data().context(format!(
+                "The from_rev_id:{} is greater than the to_rev_id:{}",
+                from_rev_id, to_rev_id
+            )));
+        }
+
+        let pool = self.persistence.database.db_pool()?;
+        let revisions = self.make_rev_manager(doc_id, pool)?.load_revisions().await?;
+        let last_rev_id = revisions.last().map(|revision| revision.rev_id).unwrap_or(0);
+        if to_rev_id > last_rev_id {
+            return Err(FlowyError::record_not_found().context(format!(
+                "The document:{} doesn't have the revision:{}",
+                doc_id, to_rev_id
+            )));
+        }
+        let version_at = |rev_id: i64| {
+            let revisions = revisions
+                .iter()
+                .filter(|revision| revision.rev_id <= rev_id)
+                .cloned()
+                .collect::<Vec<Revision>>();
+            make_operations_from_revisions::<AttributeHashMap>(revisions)
+        };
+        Ok((version_at(from_rev_id)?, version_at(to_rev_id)?))
+    }
+
+    /// Returns the hash of the document's content with the rev_id it was computed at, see
+    /// `content_hash`. The opened document keeps its hash until a revision is applied to it,
+    /// the hash is computed again the next time it's read. The hash of a closed document is saved, it's computed again only if the document got
+    /// new revisions since.
+    pub async fn doc_content_hash(&self, doc_id: &str) -> FlowyResult<DocumentContentHash> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("Only the delta documents have a content hash"));
+        }
+        if let Some(editor) = self.opened_delta_document_editor(doc_id).await {
+            let (rev_id, hash) = editor.content_hash().await?;
+            return Ok(DocumentContentHash {
+                doc_id: doc_id.to_owned(),
+                rev_id,
+                hash,
+            });
+        }
+
+        let pool = self.persistence.database.db_pool()?;
+        let conn = pool.get()?;
+        let latest_rev_id = match ContentHashSql::read_latest_rev_id(doc_id, &conn)? {
+            None => {
+                return Err(FlowyError::record_not_found().context(format!("The document:{} has no revisions", doc_id)))
+            }
+            Some(rev_id) => rev_id,
+        };
+        if let Some(saved) = ContentHashSql::read(doc_id, &conn)? {
+            if saved.rev_id == latest_rev_id {
+                return Ok(saved);
+            }
+        }
+        drop(conn);
+
+        let computed = self.compute_content_hash(doc_id, latest_rev_id, pool.clone()).await?;
+        ContentHashSql::write(&computed, &*pool.get()?)?;
+        Ok(computed)
+    }
+
+    /// Same as `doc_content_hash` for each of the documents. The documents whose hash can't be
+    /// read, e.g. the ones that don't exist, are left out. The saved hashes and the latest
+    /// rev_ids of the closed documents are read at once, only the documents that changed since
+    /// their hash was saved are composed.
+    pub async fn doc_content_hashes(&self, doc_ids: &[String]) -> FlowyResult<Vec<DocumentContentHash>> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("Only the delta documents have a content hash"));
+        }
+        let mut content_hashes = Vec::with_capacity(doc_ids.len());
+        let mut closed_doc_ids = vec![];
+        for doc_id in doc_ids {
+            match self.opened_delta_document_editor(doc_id).await {
+                None => closed_doc_ids.push(doc_id.clone()),
+                Some(editor) => match editor.content_hash().await {
+                    Ok((rev_id, hash)) => content_hashes.push(DocumentContentHash {
+                        doc_id: doc_id.clone(),
+                        rev_id,
+                        hash,
+                    }),
+                    Err(e) => tracing::warn!("Read the content hash of {} failed: {:?}", doc_id, e),
+                },
+            }
+        }
+        if closed_doc_ids.is_empty() {
+            return Ok(content_hashes);
+        }
+
+        let pool = self.persistence.database.db_pool()?;
+        let (mut saved, latest_rev_ids) = {
+            let conn = pool.get()?;
+            (
+                ContentHashSql::read_all(&closed_doc_ids, &conn)?,
+                ContentHashSql::read_latest_rev_ids(&closed_doc_ids, &conn)?,
+            )
+        };
+        let mut computed = vec![];
+        for doc_id in closed_doc_ids {
+            let latest_rev_id = match latest_rev_ids.get(&doc_id) {
+                None => {
+                    tracing::warn!("Read the content hash of {} failed: it has no revisions", doc_id);
+                    continue;
+                }
+                Some(rev_id) => *rev_id,
+            };
+            match saved.remove(&doc_id) {
+                Some(content_hash) if content_hash.rev_id == latest_rev_id => content_hashes.push(content_hash),
+                _ => match self.compute_content_hash(&doc_id, latest_rev_id, pool.clone()).await {
+                    Ok(content_hash) => computed.push(content_hash),
+                    Err(e) => tracing::warn!("Read the content hash of {} failed: {:?}", doc_id, e),
+                },
+            }
+        }
+        if !computed.is_empty() {
+            ContentHashSql::write_all(&computed, &*pool.get()?)?;
+            content_hashes.extend(computed);
+        }
+        Ok(content_hashes)
+    }
+
+    /// Composes the revisions of the closed document to hash its content.
+    async fn compute_content_hash(
+        &self,
+        doc_id: &str,
+        latest_rev_id: i64,
+        pool: Arc<ConnectionPool>,
+    ) -> FlowyResult<DocumentContentHash> {
+        let revisions = self.make_rev_manager(doc_id, pool)?.load_revisions().await?;
+        let rev_id = revisions
+            .last()
+            .map(|revision| revision.rev_id)
+            .unwrap_or(latest_rev_id);
+        let operations = make_operations_from_revisions::<AttributeHashMap>(revisions)?;
+        Ok(DocumentContentHash {
+            doc_id: doc_id.to_owned(),
+            rev_id,
+            hash: content_hash(&operations),
+        })
+    }
+
+    /// Saves the hash of the opened document, the document doesn't need to be composed to read
+    /// its hash after it's closed.
+    async fn save_content_hash(&self, doc_id: &str, editor: &Arc<DeltaDocumentEditor>) -> FlowyResult<()> {
+        let (rev_id, hash) = editor.content_hash().await?;
+        let content_hash = DocumentContentHash {
+            doc_id: doc_id.to_owned(),
+            rev_id,
+            hash,
+        };
+        let conn = self.persistence.database.db_pool()?.get()?;
+        ContentHashSql::write(&content_hash, &conn)
+    }
+
+    /// Saves the searched text of the document that is being closed, see `search_documents`. The
+    /// revisions are flushed first, so the saved rev_id is the head on disk. The rev_id is read
+    /// before the text, so the entry is composed again if the document changes meanwhile.
+    async fn save_search_index_entry(&self, doc_id: &str, editor: &Arc<DeltaDocumentEditor>) -> FlowyResult<()> {
+        editor.flush().await?;
+        let (rev_id, _) = editor.content_hash().await?;
+        let operations = editor.operations().await?;
+        let entry = SearchIndexEntry::new(doc_id, rev_id, &operations);
+        let conn = self.persistence.database.db_pool()?.get()?;
+        SearchIndexSql::write(&entry, &conn)
+    }
+
+    /// Returns the ids of the delta documents whose text contains the `query`, the archived text
+    /// isn't searched. The closed documents are searched through the text saved in the search
+    /// index, only the ones that changed since their text was saved are composed and indexed
+    /// again. The opened documents are searched as they're edited.
+    pub async fn search_documents(&self, query: &str) -> FlowyResult<Vec<String>> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("Only the delta documents are indexed"));
+        }
+        if query.is_empty() {
+            return Err(FlowyError::invalid_data().context("The query to search is empty"));
+        }
+        let pool = self.persistence.database.db_pool()?;
+        let heads = DeltaRevisionSql::read_heads(&*pool.get()?)?;
+        let mut doc_ids = vec![];
+        let mut closed_heads = vec![];
+        for (doc_id, rev_id) in heads {
+            match self.opened_delta_document_editor(&doc_id).await {
+                None => closed_heads.push((doc_id, rev_id)),
+                Some(editor) => match editor.plain_text(false).await {
+                    Ok(text) if text.contains(query) => doc_ids.push(doc_id),
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Search {} failed: {:?}", doc_id, e),
+                },
+            }
+        }
+
+        let closed_doc_ids = closed_heads
+            .iter()
+            .map(|(doc_id, _)| doc_id.clone())
+            .collect::<Vec<String>>();
+        let mut saved = SearchIndexSql::read_all(&closed_doc_ids, &*pool.get()?)?;
+        let mut computed = vec![];
+        for (doc_id, latest_rev_id) in closed_heads {
+            let entry = match saved.remove(&doc_id) {
+                Some(entry) if entry.rev_id == latest_rev_id => entry,
+                _ => match self
+                    .compute_search_index_entry(&doc_id, latest_rev_id, pool.clone())
+                    .await
+                {
+                    Ok(entry) => {
+                        computed.push(entry.clone());
+                        entry
+                    }
+                    Err(e) => {
+                        tracing::warn!("Index {} for the search failed: {:?}", doc_id, e);
+                        continue;
+                    }
+                },
+            };
+            if entry.text.contains(query) {
+                doc_ids.push(doc_id);
+            }
+        }
+        if !computed.is_empty() {
+            SearchIndexSql::write_all(&computed, &*pool.get()?)?;
+        }
+        doc_ids.sort();
+        Ok(doc_ids)
+    }
+
+    /// Composes the revisions of the closed document to index its text, see `search_documents`.
+    async fn compute_search_index_entry(
+        &self,
+        doc_id: &str,
+        latest_rev_id: i64,
+        pool: Arc<ConnectionPool>,
+    ) -> FlowyResult<SearchIndexEntry> {
+        let revisions = self.make_rev_manager(doc_id, pool)?.load_revisions().await?;
+        let rev_id = revisions
+            .last()
+            .map(|revision| revision.rev_id)
+            .unwrap_or(latest_rev_id);
+        let operations = make_operations_from_revisions::<AttributeHashMap>(revisions)?;
+        Ok(SearchIndexEntry::new(doc_id, rev_id, &operations))
+    }
+
+    /// Saves the meta of the document that is being closed, so `open_latest_available` can
+    /// return it the next time the document is opened, even after a restart. The preferences
+    /// aren't part of it, they're read along with it.
+    async fn save_closed_document_meta(&self, doc_id: &str, editor: &Arc<DeltaDocumentEditor>) -> FlowyResult<()> {
+        let meta = editor.meta().await?;
+        let conn = self.persistence.database.db_pool()?.get()?;
+        DocMetaSql::write_skeleton(doc_id, &meta, &conn)
+    }
+
+    fn closed_document_meta(&self, doc_id: &str) -> FlowyResult<Option<DocumentMeta>> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        DocMetaSql::read_meta(doc_id, &conn)
+    }
+
+    /// Finds the `query` in the delta documents of the `scope` without changing them, pass the
+    /// preview to `apply_find_replace` to replace the matches. There is no index of the text of
+    /// the documents, so each document of the scope is read. The closed documents are composed
+    /// from their revisions and stay closed. The read-only documents, e.g. the folder, are
+    /// previewed but skipped by the apply.
+    pub async fn find_replace(
+        &self,
+        scope: FindReplaceScope,
+        query: FindReplaceQuery,
+    ) -> FlowyResult<FindReplacePreview> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("Only the delta documents can be replaced"));
+        }
+        if query.pattern.is_empty() {
+            return Err(FlowyError::invalid_data().context("The pattern to find is empty"));
+        }
+        let pool = self.persistence.database.db_pool()?;
+        let doc_ids = match scope {
+            // The folder, the custom dictionaries and the preferences aren't listed.
+            FindReplaceScope::All => DeltaRevisionSql::read_doc_ids(&*pool.get()?)?,
+            FindReplaceScope::Workspace(workspace_id) => {
+                self.scope_resolver().await?.workspace_doc_ids(&workspace_id).await?
+            }
+            FindReplaceScope::App(app_id) => self.scope_resolver().await?.app_doc_ids(&app_id).await?,
+            FindReplaceScope::DocIds(doc_ids) => doc_ids,
+        };
+
+        let mut documents = vec![];
+        for doc_id in doc_ids {
+            let preview = match self.opened_delta_document_editor(&doc_id).await {
+                Some(editor) => editor.find_replace_preview(&query).await,
+                None => self
+                    .read_closed_delta_operations(&doc_id, pool.clone())
+                    .await
+                    .map(|operations| preview_document(&doc_id, &operations, &query, None)),
+            };
+            match preview {
+                Ok(None) => {}
+                Ok(Some(mut preview)) => {
+                    preview.skip = self.find_replace_skip(&doc_id).await;
+                    documents.push(preview);
+                }
+                Err(e) => tracing::warn!("Find in the document:{} failed: {:?}", doc_id, e),
+            }
+        }
+        Ok(FindReplacePreview { query, documents })
+    }
+
+    /// Replaces the matches of the `preview` with one revision per document. The documents that
+    /// changed since the preview are reported as stale and left as they are. If the apply was
+    /// interrupted, it's run again with the same preview: the documents it already replaced are
+    /// told apart by their content hash, so they aren't replaced twice.
+    pub async fn apply_find_replace(&self, preview: &FindReplacePreview) -> FlowyResult<FindReplaceReport> {
+        let mut report = FindReplaceReport::default();
+        for document in preview.documents.iter() {
+            let outcome = match self.apply_find_replace_to_document(document, &preview.query).await {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    tracing::error!("Replace in the document:{} failed: {:?}", document.doc_id, e);
+                    FindReplaceOutcome::Failed(format!("{:?}", e))
+                }
+            };
+            report.documents.push(FindReplaceDocReport {
+                doc_id: document.doc_id.clone(),
+                outcome,
+            });
+        }
+        Ok(report)
+    }
+
+    async fn apply_find_replace_to_document(
+        &self,
+        document: &FindReplaceDocPreview,
+        query: &FindReplaceQuery,
+    ) -> FlowyResult<FindReplaceOutcome> {
+        let doc_id = document.doc_id.as_str();
+        if let Some(skip) = read_only_skip(doc_id) {
+            return Ok(FindReplaceOutcome::Skipped(skip));
+        }
+        let was_opened = self.opened_delta_document_editor(doc_id).await.is_some();
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        let outcome = if editor.is_sync_loop_detected() {
+            Ok(FindReplaceOutcome::Skipped(FindReplaceSkip::Locked))
+        } else {
+            replace_in_document(&editor, document, query).await
+        };
+        if !was_opened {
+            drop(editor);
+            let _ = self.close_document_editor(doc_id).await?;
+        }
+        outcome
+    }
+
+    async fn scope_resolver(&self) -> FlowyResult<Arc<dyn DocumentScopeResolver>> {
+        self.scope_resolver
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| FlowyError::internal().context("The workspaces and the apps can't be resolved"))
+    }
+
+    async fn find_replace_skip(&self, doc_id: &str) -> Option<FindReplaceSkip> {
+        if let Some(skip) = read_only_skip(doc_id) {
+            return Some(skip);
+        }
+        match self.opened_delta_document_editor(doc_id).await {
+            Some(editor) if editor.is_sync_loop_detected() => Some(FindReplaceSkip::Locked),
+            _ => None,
+        }
+    }
+
+    /// Composes the delta document from its revisions, the document isn't opened.
+    async fn read_closed_delta_operations(
+        &self,
+        doc_id: &str,
+        pool: Arc<ConnectionPool>,
+    ) -> FlowyResult<DeltaTextOperations> {
+        let revisions = self.make_rev_manager(doc_id, pool)?.load_revisions().await?;
+        let operations = make_operations_from_revisions::<AttributeHashMap>(revisions)?;
+        Ok(operations)
+    }
+
+    /// Tags the revision of the document, see `RevisionManager::tag_revision`.
+    pub async fn tag_revision(&self, doc_id: &str, rev_id: i64, tag: &str) -> FlowyResult<()> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        editor.rev_manager().tag_revision(rev_id, tag).await
+    }
+
+    /// Returns the tags of the document with the document at each tagged revision, in ascending
+    /// order of the rev_id. The versions are composed in one pass over the revisions, starting
+    /// from the last snapshot if it's not after the first tagged revision.
+    pub async fn tagged_versions(&self, doc_id: &str) -> FlowyResult<Vec<(String, DeltaTextOperations)>> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        let rev_manager = editor.rev_manager();
+        let tags = rev_manager.revision_tags()?;
+        let first_rev_id = match tags.first() {
+            None => return Ok(vec![]),
+            Some((_, rev_id)) => *rev_id,
+        };
+
+        // The revisions are loaded from the disk, including the ones waiting for the deferred save.
+        rev_manager.flush().await?;
+        let mut base = DeltaTextOperations::default();
+        let mut revisions = rev_manager.load_revisions().await?;
+        if let Some(snapshot) = rev_manager.read_snapshot(None).await? {
+            if snapshot.rev_id <= first_rev_id {
+                base = DeltaTextOperations::from_bytes(&snapshot.data)?;
+                revisions.retain(|revision| revision.rev_id > snapshot.rev_id);
+            }
+        }
+        let rev_ids = tags.iter().map(|(_, rev_id)| *rev_id).collect::<Vec<i64>>();
+        let versions = make_operations_at_rev_ids(base, revisions, &rev_ids)?;
+        Ok(tags.into_iter().map(|(tag, _)| tag).zip(versions).collect())
+    }
+
+    /// Returns the document whose regions are composed on demand with `LazyDocument::range`.
+    /// It starts from the last snapshot if there is one, the document is never composed as a
+    /// whole.
+    pub async fn lazy_document(&self, doc_id: &str) -> FlowyResult<LazyDocument> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("The document is not a delta document"));
+        }
+        // The document isn't opened for it, but the revisions of the opened one must be saved
+        // before they're read.
+        if let Some(editor) = self.opened_delta_document_editor(doc_id).await {
+            editor.rev_manager().flush().await?;
+        }
+        let pool = self.persistence.database.db_pool()?;
+        let rev_manager = self.make_rev_manager(doc_id, pool)?;
+        let mut base = DeltaTextOperations::default();
+        let mut revisions = rev_manager.load_revisions().await?;
+        if let Some(snapshot) = rev_manager.read_snapshot(None).await? {
+            base = DeltaTextOperations::from_bytes(&snapshot.data)?;
+            revisions.retain(|revision| revision.rev_id > snapshot.rev_id);
+        }
+        LazyDocument::new(base, revisions)
+    }
+
+    /// Returns the document as if its revision `rev_id` was removed from the history, the
+    /// revisions after it are kept. It's a preview, the document itself doesn't change.
+    pub async fn preview_without(&self, doc_id: &str, rev_id: i64) -> FlowyResult<DeltaTextOperations> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        let rev_manager = editor.rev_manager();
+        rev_manager.flush().await?;
+        let revisions = rev_manager.load_revisions().await?;
+        let operations = make_operations_without_revision(revisions, rev_id)?;
+        Ok(operations)
+    }
+
+    /// Replays the transform of the revisions `local_rev_id` and `remote_rev_id` in both
+    /// directions, see `explain_transform`. It fails if either revision has been compacted away.
+    pub async fn explain_transform(
+        &self,
+        doc_id: &str,
+        local_rev_id: i64,
+        remote_rev_id: i64,
+    ) -> FlowyResult<TransformExplanation<AttributeHashMap>> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        let rev_manager = editor.rev_manager();
+        rev_manager.flush().await?;
+        let revisions = rev_manager.load_revisions().await?;
+        let explanation = explain_transform(revisions, local_rev_id, remote_rev_id)?;
+        Ok(explanation)
+    }
+
+    /// Composes the revisions of the document for at most about the `budget`, see
+    /// `make_operations_within_budget`. Pass a partial result to `continue_compose_budgeted` to
+    /// compose the rest later.
+    pub async fn compose_document_budgeted(
+        &self,
+        doc_id: &str,
+        budget: Duration,
+    ) -> FlowyResult<ComposeResult<AttributeHashMap>> {
+        let revisions = self.flushed_revisions(doc_id).await?;
+        let result = make_operations_within_budget(DeltaTextOperations::default(), revisions, budget)?;
+        Ok(result)
+    }
+
+    /// Composes the revisions after `rev_id` onto the `operations`, the document at `rev_id`
+    /// returned by a partial `compose_document_budgeted`.
+    pub async fn continue_compose_budgeted(
+        &self,
+        doc_id: &str,
+        operations: DeltaTextOperations,
+        rev_id: i64,
+        budget: Duration,
+    ) -> FlowyResult<ComposeResult<AttributeHashMap>> {
+        let mut revisions = self.flushed_revisions(doc_id).await?;
+        revisions.retain(|revision| revision.rev_id > rev_id);
+        let result = make_operations_within_budget(operations, revisions, budget)?;
+        Ok(result)
+    }
+
+    /// Returns the revision that inserted each part of the document in the `interval`, see
+    /// `make_blame`.
+    pub async fn blame(&self, doc_id: &str, interval: Interval) -> FlowyResult<Vec<(Interval, i64)>> {
+        let revisions = self.flushed_revisions(doc_id).await?;
+        let blame = make_blame::<AttributeHashMap>(revisions, interval)?;
+        Ok(blame)
+    }
+
+    /// Returns the ranges of the current document that the revision `rev_id` inserted or
+    /// deleted, e.g. to mark them in the margin, see `make_touched_ranges`.
+    pub async fn touched_ranges(&self, doc_id: &str, rev_id: i64) -> FlowyResult<Vec<Interval>> {
+        let revisions = self.flushed_revisions(doc_id).await?;
+        let ranges = make_touched_ranges::<AttributeHashMap>(revisions, rev_id)?;
+        Ok(ranges)
+    }
+
+    /// Returns the current content of the delta document as a tree of blocks for the renderers
+    /// that don't read the delta.
+    pub async fn document_ast(&self, doc_id: &str) -> FlowyResult<DocumentAst> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        editor.document_ast().await
+    }
+
+    /// Returns the first `max_len` graphemes of the visible text of the delta document. The
+    /// document isn't copied or serialized, see `document_preview`. The document that isn't
+    /// opened stays closed, only its beginning is composed, see `lazy_document_preview`.
+    pub async fn document_preview(&self, doc_id: &str, max_len: usize) -> FlowyResult<DocumentPreview> {
+        if self.opened_delta_document_editor(doc_id).await.is_none() {
+            if let Some(closed_document) = self.read_closed_document(doc_id).await? {
+                return lazy_document_preview(&closed_document.document, max_len);
+            }
+        }
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        editor.preview(max_len).await
+    }
+
+    /// Returns the `byte_len` bytes of the document's text from `byte_start`, see
+    /// `content_byte_range`. The text includes the archived text, like the offsets of the
+    /// document's operations do.
+    pub async fn content_bytes(&self, doc_id: &str, byte_start: usize, byte_len: usize) -> FlowyResult<Vec<u8>> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        let content = editor.content().await?;
+        Ok(content_byte_range(&content, byte_start, byte_len)?.to_vec())
+    }
+
+    /// Returns the counts and the revision ids of the delta document. The document that isn't
+    /// opened stays closed and is never composed as a whole, see `lazy_document_meta`.
+    pub async fn document_meta(&self, doc_id: &str) -> FlowyResult<DocumentMeta> {
+        let closed_document = match self.opened_delta_document_editor(doc_id).await {
+            None => self.read_closed_document(doc_id).await?,
+            Some(_) => None,
+        };
+        let mut meta = match closed_document {
+            Some(closed_document) => lazy_document_meta(
+                &closed_document.document,
+                closed_document.rev_id,
+                closed_document.next_sync_rev_id,
+            )?,
+            None => self.get_delta_document_editor(doc_id).await?.meta().await?,
+        };
+        meta.preferences = self.doc_preferences(doc_id)?;
+        Ok(meta)
+    }
+
+    /// Reads the revisions of the delta document that isn't opened without composing them, see
+    /// `LazyDocument`. None if they aren't on the disk, the document is opened to fetch them.
+    async fn read_closed_document(&self, doc_id: &str) -> FlowyResult<Option<ClosedDocument>> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Ok(None);
+        }
+        let pool = self.persistence.database.db_pool()?;
+        let rev_manager = self.make_rev_manager(doc_id, pool)?;
+        let records = rev_manager.get_all_revision_records()?;
+        let rev_id = match records.iter().map(|record| record.revision.rev_id).max() {
+            None => return Ok(None),
+            Some(rev_id) => rev_id,
+        };
+        let next_sync_rev_id = records
+            .iter()
+            .filter(|record| record.state == RevisionState::Sync)
+            .map(|record| record.revision.rev_id)
+            .min();
+        let mut revisions = composable_revisions(&records);
+        revisions.sort_by_key(|revision| revision.rev_id);
+        let mut base = DeltaTextOperations::default();
+        if let Some(snapshot) = rev_manager.read_snapshot(None).await? {
+            base = DeltaTextOperations::from_bytes(&snapshot.data)?;
+            revisions.retain(|revision| revision.rev_id > snapshot.rev_id);
+        }
+        Ok(Some(ClosedDocument {
+            document: LazyDocument::new(base, revisions)?,
+            rev_id,
+            next_sync_rev_id,
+        }))
+    }
+
+    /// Saves the `interval` of the document as a snippet named `name`. The attributes of the
+    /// selected text are kept, so inserting the snippet restores its formatting.
+    pub async fn save_selection_as_snippet(
+        &self,
+        doc_id: &str,
+        interval: Interval,
+        name: &str,
+    ) -> FlowyResult<Snippet> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        let operations = editor.slice(interval).await?;
+        if operations.is_empty() {
+            return Err(FlowyError::invalid_data().context("The selection is empty"));
+        }
+        let snippet = Snippet::new(name, operations);
+        let conn = self.persistence.database.db_pool()?.get()?;
+        SnippetSql::save(&snippet, &conn)?;
+        Ok(snippet)
+    }
+
+    pub fn snippets(&self) -> FlowyResult<Vec<Snippet>> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        SnippetSql::read_all(&conn)
+    }
+
+    /// Inserts the snippet at `index` of the document. It's a local edit like typing, so it's
+    /// saved as a revision and synced to the server.
+    pub async fn insert_snippet(&self, doc_id: &str, index: usize, snippet_id: &str) -> FlowyResult<()> {
+        let snippet = {
+            let conn = self.persistence.database.db_pool()?.get()?;
+            SnippetSql::read(snippet_id, &conn)?
+        };
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        editor.insert_operations(index, snippet.operations).await
+    }
+
+    /// Moves the text from `index` to the end of the document into the new document
+    /// `new_doc_id`, e.g. to split the document at a heading. Either both documents change or
+    /// neither does: the removal and the new document are saved in one transaction.
+    pub async fn split_document(&self, doc_id: &str, index: usize, new_doc_id: &str) -> FlowyResult<()> {
+        if doc_id == new_doc_id {
+            return Err(FlowyError::invalid_data().context("Can't split the document into itself"));
+        }
+        let pool = self.persistence.database.db_pool()?;
+        if self.editor_map.read().await.get(new_doc_id).is_some()
+            || self.make_rev_manager(new_doc_id, pool)?.number_of_revisions_in_disk() > 0
+        {
+            return Err(FlowyError::invalid_data().context(format!("The document {} already exists", new_doc_id)));
+        }
+
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        if let Some(tail) = editor.split_off(index, new_doc_id).await? {
+            // Nothing was removed from the document, so only the new document is saved.
+            let revision = Revision::initial_revision(new_doc_id, Bytes::from(tail.json_bytes()));
+            self.create_document(new_doc_id, vec![revision]).await?;
+        }
+        Ok(())
+    }
+
+    /// Regenerates the exports of the delta documents that were exported by an older version of
+    /// the exporter or changed since their last export, see `DocumentExportTargets`. The markdown
+    /// mirror of the user is regenerated with the other targets unless it's turned off. The
+    /// progress is sent with the `DidUpdateReexportProgress` notification after each page of
+    /// documents. A cancelled re-export continues where it stopped when it's run again.
+    pub async fn reexport_documents(&self) -> FlowyResult<ReexportSummary> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("The re-export only supports the delta documents"));
+        }
+        self.reexport_cancelled.store(false, Ordering::SeqCst);
+        let reexport = DocumentReexport::new(
+            &self.user.user_id()?,
+            self.persistence.database.db_pool()?,
+            self.config
+                .export_targets
+                .clone()
+                .with_user_targets(&self.user.user_dir()?),
+            self.custom_attributes.clone(),
+            self.reexport_cancelled.clone(),
+        );
+        let summary = reexport
+            .run(&self.config.executor, &self.config.priority_scheduler, |summary| {
+                send_anonymous_dart_notification(DocumentNotification::DidUpdateReexportProgress)
+                    .payload(ReexportSummaryPB::from(summary.clone()))
+                    .send();
+            })
+            .await?;
+        tracing::info!("Re-export documents: {:?}", summary);
+        Ok(summary)
+    }
+
+    /// Stops the running re-export after the document that is being exported.
+    pub fn cancel_reexport(&self) {
+        self.reexport_cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Saves the snippets returned by `snippets` in one transaction, e.g. when an exported
+    /// workspace is imported. The snippets with the same id are replaced.
+    pub fn import_snippets(&self, snippets: &[Snippet]) -> FlowyResult<usize> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        SnippetSql::import(snippets, &conn)
+    }
+
+    /// Writes the delta document into a single file with its whole history, its tags, its
+    /// preferences and the attachments that any of its revisions refers to, see
+    /// `PortableDocument`. The attachments that aren't stored locally are left out.
+    pub async fn export_doc_portable(&self, doc_id: &str, path: &Path) -> FlowyResult<()> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        let rev_manager = editor.rev_manager();
+        rev_manager.flush().await?;
+        let revisions = rev_manager.load_revisions().await?;
+        let tags = rev_manager.revision_tags()?;
+        let rev_id = rev_manager.rev_id();
+        let store = self.attachment_store()?;
+        let pool = self.persistence.database.db_pool()?;
+        let doc_id = doc_id.to_owned();
+        let path = path.to_owned();
+        let scheduler = &self.config.priority_scheduler;
+        let priority = scheduler.priority_of(&doc_id);
+        scheduler
+            .spawn_blocking(&self.config.executor, priority, move || -> FlowyResult<()> {
+                let mut attachments = vec![];
+                for attachment_id in referenced_attachment_ids(&revisions)? {
+                    match store.read(&attachment_id)? {
+                        None => tracing::warn!(
+                            "The attachment {} of {} is missing, it's not exported",
+                            attachment_id,
+                            doc_id
+                        ),
+                        Some(attachment) => attachments.push(attachment),
+                    }
+                }
+                let preferences = DocMetaSql::read_preferences(&doc_id, &*pool.get()?)?;
+                PortableDocument::new(&doc_id, rev_id, revisions, tags, attachments, preferences).write_to(&path)
+            })
+            .await
+            .map_err(internal_error)?
+    }
+
+    /// Creates a new document from the file written by `export_doc_portable` and returns its id.
+    /// The history is saved under the new id, so the revisions sync as the ones of a new
+    /// document. The caller registers the view of the document, see `ViewDataProcessor`.
+    ///
+    /// The file of a newer format fails with `ErrorCode::UnsupportedFormatVersion`.
+    pub async fn import_doc_portable(&self, path: &Path) -> FlowyResult<String> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("The portable documents only support the delta documents"));
+        }
+        let store = self.attachment_store()?;
+        let pool = self.persistence.database.db_pool()?;
+        let doc_id = nanoid!(10);
+        let path = path.to_owned();
+        let (source_doc_id, num_of_revisions) = {
+            let doc_id = doc_id.clone();
+            self.config
+                .priority_scheduler
+                .spawn_blocking(
+                    &self.config.executor,
+                    TaskPriority::Background,
+                    move || -> FlowyResult<(String, usize)> {
+                        let document = PortableDocument::read_from(&path)?;
+                        if document.revisions.is_empty() {
+                            return Err(FlowyError::invalid_data().context(format!("{:?} has no revisions", path)));
+                        }
+                        for attachment in document.attachments.iter() {
+                            let _ = store.merge(&Attachment::from(attachment.clone()))?;
+                        }
+                        document.save_as(&doc_id, &*pool.get()?)?;
+                        Ok((document.meta.doc_id, document.revisions.len()))
+                    },
+                )
+                .await
+                .map_err(internal_error)??
+        };
+        tracing::info!(
+            "Imported {} as {} with {} revisions",
+            source_doc_id,
+            doc_id,
+            num_of_revisions
+        );
+        Ok(doc_id)
+    }
+
+    /// Copies the delta documents and their attachments from the database under `dir`, e.g. the
+    /// database of another account on this device, see `DatabaseMergeSummary`. The documents get
+    /// new ids, the caller moves their views with them, see `ViewDataProcessor`. The merge that
+    /// stopped midway continues where it stopped when it's run again with the same `dir`.
+    pub async fn merge_local_database(&self, dir: &str) -> FlowyResult<DatabaseMergeSummary> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("The merge only supports the delta documents"));
+        }
+        let dir = PathBuf::from(dir);
+        let user_dir = self.user.user_dir()?;
+        let pool = self.persistence.database.db_pool()?;
+        let summary = self
+            .config
+            .priority_scheduler
+            .spawn_blocking(&self.config.executor, TaskPriority::Background, move || {
+                merge_database(&dir, &user_dir, pool)
+            })
+            .await
+            .map_err(internal_error)??;
+        tracing::info!(
+            "Merged {} documents, {} failed: {:?}",
+            summary.num_of_merged,
+            summary.failed.len(),
+            summary.doc_ids
+        );
+        Ok(summary)
+    }
+
+    /// Returns the store of the attachments that the delta documents refer to by id.
+    pub fn attachment_store(&self) -> FlowyResult<AttachmentStore> {
+        Ok(AttachmentStore::new(self.persistence.database.db_pool()?))
+    }
+
+    /// Reconciles the attachments once, then again whenever the remote changes of a document
+    /// refer to attachments, until the manager is dropped. The transfers run in the background,
+    /// see `wait_for_attachment_transfers`.
+    pub fn schedule_attachment_reconcile(self: &Arc<Self>) {
+        if self.config.version != DocumentVersionPB::V0 {
+            return;
+        }
+        let weak_manager = Arc::downgrade(self);
+        let attachment_reconcile = self.attachment_reconcile.clone();
+        self.config.executor.spawn(async move {
+            loop {
+                match weak_manager.upgrade() {
+                    None => break,
+                    Some(manager) => {
+                        if let Err(e) = manager.reconcile_attachments().await {
+                            tracing::error!("Reconcile attachments failed: {:?}", e);
+                        }
+                    }
+                }
+                attachment_reconcile.notified().await;
+            }
+        });
+    }
+
+    /// Makes sure every attachment that a delta document refers to is the same on this device
+    /// and on the server, e.g. after the revisions of another device were synced.
+    ///
+    /// The links to the local files are moved to attachments first, so the documents written
+    /// before the attachments had ids sync their files too. Then each attachment is compared
+    /// with the server of the first document that refers to it: the missing or older ones are
+    /// downloaded and the ones the server doesn't have or has an older version of are uploaded,
+    /// in the background. The newer version wins on both sides, so a rename made on two devices
+    /// converges to the same name. A server that doesn't keep the versions only gets the missing
+    /// attachments downloaded.
+    ///
+    /// The attachments that no document refers to are deleted once they have been orphaned for
+    /// the `attachment_grace_period`, unless some document can't be read.
+    pub async fn reconcile_attachments(&self) -> FlowyResult<AttachmentReconcileSummary> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("The attachments only support the delta documents"));
+        }
+        // The references are read from the disk, so the deferred saves are written first.
+        let opened_documents = self.editor_map.read().await.keys();
+        for doc_id in opened_documents {
+            let editor = self.get_delta_document_editor(&doc_id).await?;
+            editor.rev_manager().flush().await?;
+        }
+
+        let mut references = self.load_attachment_references().await?;
+        let store = self.attachment_store()?;
+        let token = self.user.token()?;
+        let mut summary = AttachmentReconcileSummary::default();
+        for doc_id in std::mem::take(&mut references.linked_doc_ids) {
+            match self.attach_document_links(&doc_id, &store).await {
+                Ok(attachment_ids) => {
+                    summary.num_of_attached_links += attachment_ids.len();
+                    for attachment_id in attachment_ids {
+                        references.doc_id_by_attachment.insert(attachment_id, doc_id.clone());
+                    }
+                }
+                Err(e) => tracing::error!("Move the links of {} to attachments failed: {:?}", doc_id, e),
+            }
+        }
+        summary.num_of_referenced = references.doc_id_by_attachment.len();
 
-#[async_trait]
-pub trait DocumentEditor: Send + Sync {
-    /// Called when the document get closed
-    async fn close(&self);
+        let mut servers = HashMap::<String, (DocumentServer, Vec<String>)>::new();
+        for (attachment_id, doc_id) in references.doc_id_by_attachment.iter() {
+            let server = self.server_resolver.resolve(doc_id);
+            servers
+                .entry(server.endpoint.clone())
+                .or_insert_with(|| (server, vec![]))
+                .1
+                .push(attachment_id.clone());
+        }
+        for (server, attachment_ids) in servers.into_values() {
+            let transfers = self
+                .attachment_transfers_with_server(&server, &store, &token, attachment_ids, &mut summary)
+                .await?;
+            for (attachment_id, transfer) in transfers {
+                self.attachment_transfers.queue(
+                    &self.config.executor,
+                    store.clone(),
+                    server.cloud_service.clone(),
+                    token.clone(),
+                    attachment_id,
+                    transfer,
+                );
+            }
+        }
 
-    /// Exports the document content. The content is encoded in the corresponding
-    /// editor data format.
-    fn export(&self) -> FutureResult<String, FlowyError>;
+        if references.unreadable_doc_ids.is_empty() {
+            let referenced = references.doc_id_by_attachment.into_keys().collect();
+            summary.num_of_collected =
+                store.collect_orphans(&referenced, timestamp(), self.config.attachment_grace_period)?;
+        } else {
+            tracing::warn!(
+                "Skip collecting the orphaned attachments, can't read {:?}",
+                references.unreadable_doc_ids
+            );
+        }
+        tracing::info!("Reconcile attachments: {:?}", summary);
+        Ok(summary)
+    }
 
-    /// Duplicate the document inner data into String
-    fn duplicate(&self) -> FutureResult<String, FlowyError>;
+    /// Compares the `attachment_ids` with the versions of the `server`, see
+    /// `reconcile_attachments`. Returns the transfers to queue.
+    async fn attachment_transfers_with_server(
+        &self,
+        server: &DocumentServer,
+        store: &AttachmentStore,
+        token: &str,
+        attachment_ids: Vec<String>,
+        summary: &mut AttachmentReconcileSummary,
+    ) -> FlowyResult<Vec<(String, AttachmentTransfer)>> {
+        let server_versions = match server
+            .cloud_service
+            .fetch_attachment_versions(token, attachment_ids.clone())
+            .await
+        {
+            Ok(server_versions) => server_versions,
+            Err(e) => {
+                tracing::error!("Fetch the attachment versions of {} failed: {:?}", server.endpoint, e);
+                None
+            }
+        };
+        let mut transfers = vec![];
+        for attachment_id in attachment_ids {
+            let local_version = store.read_version(&attachment_id)?;
+            let transfer = match &server_versions {
+                // Without the versions, only the missing attachments are downloaded.
+                None => local_version.is_none().then(|| AttachmentTransfer::Download),
+                Some(server_versions) => match (local_version, server_versions.get(&attachment_id)) {
+                    (None, None) => {
+                        tracing::warn!(
+                            "Neither this device nor the server has the attachment {}",
+                            attachment_id
+                        );
+                        summary.missing.push(attachment_id);
+                        continue;
+                    }
+                    (None, Some(_)) => Some(AttachmentTransfer::Download),
+                    (Some(_), None) => Some(AttachmentTransfer::Upload),
+                    (Some(local), Some(remote)) if &local > remote => Some(AttachmentTransfer::Upload),
+                    (Some(local), Some(remote)) if &local < remote => Some(AttachmentTransfer::Download),
+                    (Some(_), Some(_)) => None,
+                },
+            };
+            match transfer {
+                None => {}
+                Some(AttachmentTransfer::Download) => {
+                    summary.downloads.push(attachment_id.clone());
+                    transfers.push((attachment_id, AttachmentTransfer::Download));
+                }
+                Some(AttachmentTransfer::Upload) => {
+                    summary.uploads.push(attachment_id.clone());
+                    transfers.push((attachment_id, AttachmentTransfer::Upload));
+                }
+            }
+        }
+        Ok(transfers)
+    }
 
-    fn receive_ws_data(&self, data: ServerRevisionWSData) -> FutureResult<(), FlowyError>;
+    /// Moves the links to the local files of the document to attachments, see
+    /// `attach_local_links`. Returns the ids of the new attachments, none if the document changed
+    /// while the files were read, the next reconciliation tries again.
+    async fn attach_document_links(&self, doc_id: &str, store: &AttachmentStore) -> FlowyResult<Vec<String>> {
+        let was_opened = self.opened_delta_document_editor(doc_id).await.is_some();
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        let result = async {
+            let (content_hash, operations) = editor.operations_with_hash().await?;
+            let store = store.clone();
+            let attached = self
+                .run_maintenance_blocking(move || attach_local_links(&operations, &store))
+                .await?;
+            match attached {
+                None => Ok(vec![]),
+                Some((changes, attachment_ids)) => match editor.compose_if_unchanged(&content_hash, changes).await? {
+                    Some(_) => Ok(attachment_ids),
+                    None => {
+                        tracing::warn!("{} changed while its links were moved to attachments", doc_id);
+                        Ok(vec![])
+                    }
+                },
+            }
+        }
+        .await;
+        if !was_opened {
+            drop(editor);
+            let _ = self.close_document_editor(doc_id).await?;
+        }
+        result
+    }
 
-    fn receive_ws_state(&self, state: &WSConnectState);
+    /// Waits until the attachments queued by `reconcile_attachments` are downloaded or uploaded.
+    pub async fn wait_for_attachment_transfers(&self) {
+        self.attachment_transfers.wait().await;
+    }
 
-    /// Receives the local operations made by the user input. The operations are encoded
-    /// in binary format.
-    fn compose_local_operations(&self, data: Bytes) -> FutureResult<(), FlowyError>;
+    async fn load_attachment_references(&self) -> FlowyResult<AttachmentReferences> {
+        let user_id = self.user.user_id()?;
+        let pool = self.persistence.database.db_pool()?;
+        self.config
+            .priority_scheduler
+            .spawn_blocking(&self.config.executor, TaskPriority::Background, move || {
+                read_attachment_references(&user_id, pool)
+            })
+            .await
+            .map_err(internal_error)?
+    }
 
-    /// Returns the `Any` reference that can be used to downcast back to the original,
-    /// concrete type.
-    ///
-    /// The indirection through `as_any` is because using `downcast_ref`
-    /// on `Box<A>` *directly* only lets us downcast back to `&A` again. You can take a look at [this](https://stackoverflow.com/questions/33687447/how-to-get-a-reference-to-a-concrete-type-from-a-trait-object)
-    /// for more information.
+    /// Runs the selected maintenance `tasks` in the order of `MaintenanceTask::ORDER` and
+    /// reports the outcome of each, with how long it took and the bytes it reclaimed. A failed
+    /// task doesn't stop the tasks after it unless they depend on it, see
+    /// `MaintenanceTask::dependency`. In a `dry_run`, the tasks that can estimate what they'd
+    /// reclaim only do that, and the others don't run at all.
     ///
-    ///
-    fn as_any(&self) -> &dyn Any;
-}
-
-#[derive(Clone, Debug)]
-pub struct DocumentConfig {
-    pub version: DocumentVersionPB,
-}
+    /// The tasks that rewrite the revisions go through every document, not only the opened
+    /// ones. A document can't be opened or closed while a task works on it, but it can be
+    /// between the documents, so the maintenance doesn't hold up the editing for its whole run.
+    pub async fn run_maintenance(&self, tasks: MaintenanceTasks, dry_run: bool) -> FlowyResult<MaintenanceReport> {
+        let mut report = MaintenanceReport::new(dry_run);
+        for task in tasks.tasks() {
+            if let Some(dependency) = report.blocking_dependency(task) {
+                report.skip(task, dependency);
+                continue;
+            }
+            let start = Instant::now();
+            let result = self.run_maintenance_task(task, dry_run).await;
+            report.add(task, start.elapsed(), result);
+        }
+        tracing::info!("Run maintenance: {:?}", report);
+        Ok(report)
+    }
 
-impl std::default::Default for DocumentConfig {
-    fn default() -> Self {
-        Self {
-            version: DocumentVersionPB::V1,
+    /// Returns the bytes of the database that the task reclaimed, or would reclaim in a dry run.
+    /// None if the task has no dry-run mode.
+    async fn run_maintenance_task(&self, task: MaintenanceTask, dry_run: bool) -> FlowyResult<Option<u64>> {
+        match task {
+            MaintenanceTask::Flush => {
+                if dry_run {
+                    return Ok(None);
+                }
+                let editors = self
+                    .editor_map
+                    .read()
+                    .await
+                    .values()
+                    .into_iter()
+                    .map(|handler| handler.0)
+                    .collect::<Vec<_>>();
+                for editor in editors {
+                    editor.flush().await?;
+                }
+                Ok(Some(0))
+            }
+            MaintenanceTask::Compaction => {
+                if dry_run {
+                    let mut rev_managers = vec![];
+                    for doc_id in self.maintained_doc_ids().await? {
+                        rev_managers.push(self.maintained_rev_manager(&doc_id).await?);
+                    }
+                    return self
+                        .run_maintenance_blocking(move || {
+                            let mut removed_bytes = 0;
+                            for rev_manager in rev_managers {
+                                removed_bytes += rev_manager.compaction_estimate()?.num_of_removed_bytes as u64;
+                            }
+                            Ok(Some(removed_bytes))
+                        })
+                        .await;
+                }
+                self.reclaim_each_document(|rev_manager| async move {
+                    let _ = rev_manager.compact_small_revisions().await?;
+                    Ok(())
+                })
+                .await
+            }
+            MaintenanceTask::Purge => {
+                if dry_run {
+                    return Ok(None);
+                }
+                self.reclaim_each_document(|rev_manager| async move {
+                    let _ = rev_manager.sweep_expired_revisions().await?;
+                    Ok(())
+                })
+                .await
+            }
+            MaintenanceTask::BlobGc => {
+                if self.config.version != DocumentVersionPB::V0 {
+                    return Err(FlowyError::internal().context("The attachments only support the delta documents"));
+                }
+                let references = self.load_attachment_references().await?;
+                if !references.unreadable_doc_ids.is_empty() {
+                    return Err(FlowyError::internal().context(format!(
+                        "Keep the orphaned attachments, can't read {:?}",
+                        references.unreadable_doc_ids
+                    )));
+                }
+                let referenced = references.doc_id_by_attachment.into_keys().collect();
+                let store = self.attachment_store()?;
+                let grace_period = self.config.attachment_grace_period;
+                let pool = self.persistence.database.db_pool()?;
+                self.run_maintenance_blocking(move || {
+                    if dry_run {
+                        return Ok(Some(store.collectable_orphan_bytes(
+                            &referenced,
+                            timestamp(),
+                            grace_period,
+                        )?));
+                    }
+                    let used_bytes = read_database_pages(&*pool.get()?)?.used_bytes();
+                    let num_of_collected = store.collect_orphans(&referenced, timestamp(), grace_period)?;
+                    tracing::trace!("[Maintenance]: collected {} attachments", num_of_collected);
+                    let reclaimed_bytes = used_bytes.saturating_sub(read_database_pages(&*pool.get()?)?.used_bytes());
+                    Ok(Some(reclaimed_bytes))
+                })
+                .await
+            }
+            MaintenanceTask::Vacuum => {
+                let pool = self.persistence.database.db_pool()?;
+                self.run_maintenance_blocking(move || {
+                    let conn = pool.get()?;
+                    let before = read_database_pages(&conn)?;
+                    if dry_run {
+                        return Ok(Some(before.free_bytes()));
+                    }
+                    vacuum_database(&conn)?;
+                    let after = read_database_pages(&conn)?;
+                    Ok(Some(before.file_bytes().saturating_sub(after.file_bytes())))
+                })
+                .await
+            }
+            MaintenanceTask::SnapshotRefresh => {
+                if dry_run {
+                    return Ok(None);
+                }
+                let _ = self
+                    .reclaim_each_document(|rev_manager| async move {
+                        rev_manager.generate_snapshot().await;
+                        Ok(())
+                    })
+                    .await?;
+                Ok(Some(0))
+            }
         }
     }
-}
 
-pub struct DocumentManager {
-    cloud_service: Arc<dyn DocumentCloudService>,
-    rev_web_socket: Arc<dyn RevisionWebSocket>,
-    editor_map: Arc<RwLock<RefCountHashMap<RefCountDocumentHandler>>>,
-    user: Arc<dyn DocumentUser>,
-    persistence: Arc<DocumentPersistence>,
-    #[allow(dead_code)]
-    config: DocumentConfig,
-}
+    /// Runs the `task` on the revisions of every document and returns the bytes of the database
+    /// it freed. The `editor_map` is only locked while the `task` works on one document.
+    async fn reclaim_each_document<F, Fut>(&self, task: F) -> FlowyResult<Option<u64>>
+    where
+        F: Fn(Arc<RevisionManager<Arc<ConnectionPool>>>) -> Fut,
+        Fut: Future<Output = FlowyResult<()>>,
+    {
+        let used_bytes = self.used_database_bytes().await?;
+        for doc_id in self.maintained_doc_ids().await? {
+            let editor_map = self.editor_map.read().await;
+            let rev_manager = match editor_map.get(&doc_id) {
+                Some(handler) => opened_rev_manager(&handler.0)?,
+                None => Arc::new(self.make_rev_manager(&doc_id, self.persistence.database.db_pool()?)?),
+            };
+            task(rev_manager).await?;
+            drop(editor_map);
+        }
+        Ok(Some(used_bytes.saturating_sub(self.used_database_bytes().await?)))
+    }
 
-impl DocumentManager {
-    pub fn new(
-        cloud_service: Arc<dyn DocumentCloudService>,
-        document_user: Arc<dyn DocumentUser>,
-        database: Arc<dyn DocumentDatabase>,
-        rev_web_socket: Arc<dyn RevisionWebSocket>,
-        config: DocumentConfig,
-    ) -> Self {
-        Self {
-            cloud_service,
-            rev_web_socket,
-            editor_map: Arc::new(RwLock::new(RefCountHashMap::new())),
-            user: document_user,
-            persistence: Arc::new(DocumentPersistence::new(database)),
-            config,
+    /// Returns the revision manager of the opened document, or one made for the maintenance if
+    /// the document is closed.
+    async fn maintained_rev_manager(&self, doc_id: &str) -> FlowyResult<Arc<RevisionManager<Arc<ConnectionPool>>>> {
+        match self.editor_map.read().await.get(doc_id) {
+            Some(handler) => opened_rev_manager(&handler.0),
+            None => Ok(Arc::new(
+                self.make_rev_manager(doc_id, self.persistence.database.db_pool()?)?,
+            )),
         }
     }
 
-    /// Called immediately after the application launched with the user sign in/sign up.
-    #[tracing::instrument(level = "trace", skip_all, err)]
-    pub async fn initialize(&self, user_id: &str) -> FlowyResult<()> {
-        self.persistence.initialize(user_id)?;
-        listen_ws_state_changed(self.rev_web_socket.clone(), self.editor_map.clone());
-        Ok(())
+    /// The ids of the documents whose revisions are in the database, the ones of the current
+    /// document version.
+    async fn maintained_doc_ids(&self) -> FlowyResult<Vec<String>> {
+        let pool = self.persistence.database.db_pool()?;
+        let version = self.config.version.clone();
+        self.run_maintenance_blocking(move || {
+            let conn = pool.get()?;
+            match version {
+                DocumentVersionPB::V0 => DeltaRevisionSql::read_doc_ids(&conn),
+                DocumentVersionPB::V1 => DocumentRevisionSql::read_doc_ids(&conn),
+            }
+        })
+        .await
     }
 
-    pub async fn initialize_with_new_user(&self, _user_id: &str, _token: &str) -> FlowyResult<()> {
-        Ok(())
+    async fn used_database_bytes(&self) -> FlowyResult<u64> {
+        let pool = self.persistence.database.db_pool()?;
+        self.run_maintenance_blocking(move || Ok(read_database_pages(&*pool.get()?)?.used_bytes()))
+            .await
     }
 
-    #[tracing::instrument(level = "trace", skip_all, fields(document_id), err)]
-    pub async fn open_document_editor<T: AsRef<str>>(
-        &self,
-        document_id: T,
-    ) -> Result<Arc<dyn DocumentEditor>, FlowyError> {
-        let document_id = document_id.as_ref();
-        tracing::Span::current().record("document_id", &document_id);
-        self.init_document_editor(document_id).await
+    /// Runs the work on the disk of the maintenance on a blocking thread, behind the reads of
+    /// the focused document.
+    async fn run_maintenance_blocking<F, T>(&self, f: F) -> FlowyResult<T>
+    where
+        F: FnOnce() -> FlowyResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.config
+            .priority_scheduler
+            .spawn_blocking(&self.config.executor, TaskPriority::Background, f)
+            .await
+            .map_err(internal_error)?
     }
 
-    #[tracing::instrument(level = "trace", skip(self, editor_id), fields(editor_id), err)]
-    pub async fn close_document_editor<T: AsRef<str>>(&self, editor_id: T) -> Result<(), FlowyError> {
-        let editor_id = editor_id.as_ref();
-        tracing::Span::current().record("editor_id", &editor_id);
-        self.editor_map.write().await.remove(editor_id).await;
+    /// Adds the `word` to the custom dictionary of the workspace. The dictionary is a document,
+    /// so the word is saved as a revision and synced to the other devices.
+    pub async fn add_custom_dictionary_word(&self, workspace_id: &str, word: &str) -> FlowyResult<()> {
+        let word = validate_dictionary_word(word)?;
+        let editor = self.custom_dictionary_editor(workspace_id).await?;
+        if !dictionary_words(&editor.content().await?).iter().any(|w| w == word) {
+            editor.insert(0, format!("{}\n", word)).await?;
+        }
+        let _ = self.save_custom_dictionary(workspace_id, &editor).await?;
         Ok(())
     }
 
-    pub async fn apply_edit(&self, params: EditParams) -> FlowyResult<()> {
-        let editor = self.get_document_editor(&params.doc_id).await?;
-        editor.compose_local_operations(Bytes::from(params.operations)).await?;
+    pub async fn remove_custom_dictionary_word(&self, workspace_id: &str, word: &str) -> FlowyResult<()> {
+        let word = validate_dictionary_word(word)?;
+        let editor = self.custom_dictionary_editor(workspace_id).await?;
+        for interval in dictionary_word_lines(&editor.content().await?, word) {
+            editor.delete(interval).await?;
+        }
+        let _ = self.save_custom_dictionary(workspace_id, &editor).await?;
         Ok(())
     }
 
-    pub async fn create_document<T: AsRef<str>>(&self, doc_id: T, revisions: Vec<Revision>) -> FlowyResult<()> {
-        let doc_id = doc_id.as_ref().to_owned();
-        let db_pool = self.persistence.database.db_pool()?;
-        // Maybe we could save the document to disk without creating the RevisionManager
-        let rev_manager = self.make_rev_manager(&doc_id, db_pool)?;
-        rev_manager.reset_object(revisions).await?;
-        Ok(())
+    /// Returns the words of the workspace's custom dictionary in alphabetical order. They're read
+    /// from the `custom_dictionary` table, the dictionary document isn't opened.
+    pub fn custom_dictionary(&self, workspace_id: &str) -> FlowyResult<Vec<String>> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        CustomDictionarySql::read_all(workspace_id, &conn)
     }
 
-    pub async fn receive_ws_data(&self, data: Bytes) {
-        let result: Result<ServerRevisionWSData, serde_json::Error> = ServerRevisionWSData::try_from(data);
-        match result {
-            Ok(data) => match self.editor_map.read().await.get(&data.object_id) {
-                None => tracing::error!(
-                    "Can't find any source handler for {:?}-{:?}",
-                    data.object_id,
-                    data.payload
-                ),
-                Some(handler) => match handler.0.receive_ws_data(data).await {
-                    Ok(_) => {}
-                    Err(e) => tracing::error!("{}", e),
-                },
-            },
-            Err(e) => {
-                tracing::error!("Document ws data parser failed: {:?}", e);
+    /// Merges the custom dictionary with the server's one and uploads the result, so the words
+    /// added on the other devices are added here too, see `merge_dictionary_content`. Returns
+    /// the words after the merge.
+    pub async fn sync_custom_dictionary(&self, workspace_id: &str) -> FlowyResult<Vec<String>> {
+        let editor = self.custom_dictionary_editor(workspace_id).await?;
+        let cloud_service = self.cloud_service(&editor.doc_id);
+        let _ = editor
+            .sync_content(&self.user.token()?, cloud_service, Some(merge_dictionary_content))
+            .await?;
+        self.save_custom_dictionary(workspace_id, &editor).await
+    }
+
+    /// Adds the `words` that the dictionary doesn't have, e.g. the words of the exported
+    /// workspace that is imported, see `custom_dictionary`. Returns the number of the added words.
+    pub async fn import_custom_dictionary(&self, workspace_id: &str, words: &[String]) -> FlowyResult<usize> {
+        let editor = self.custom_dictionary_editor(workspace_id).await?;
+        let existing_words = dictionary_words(&editor.content().await?);
+        let mut new_words = vec![];
+        for word in words {
+            let word = validate_dictionary_word(word)?;
+            if !existing_words.iter().any(|w| w == word) && !new_words.contains(&word) {
+                new_words.push(word);
             }
         }
+        if !new_words.is_empty() {
+            let lines = new_words.iter().map(|word| format!("{}\n", word)).collect::<String>();
+            editor.insert(0, lines).await?;
+        }
+        let _ = self.save_custom_dictionary(workspace_id, &editor).await?;
+        Ok(new_words.len())
+    }
+
+    /// Returns the preference `key` of the document, None if it isn't set. It's read from the
+    /// `doc_meta` table, the preferences aren't opened.
+    pub fn get_doc_preference<T: DeserializeOwned>(&self, doc_id: &str, key: &str) -> FlowyResult<Option<T>> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        match DocMetaSql::read_preferences(doc_id, &conn)?.get(key) {
+            None => Ok(None),
+            Some(preference) => Ok(Some(serde_json::from_str(&preference.value)?)),
+        }
+    }
+
+    /// Returns the JSON value of each preference of the document.
+    pub fn doc_preferences(&self, doc_id: &str) -> FlowyResult<BTreeMap<String, String>> {
+        let conn = self.persistence.database.db_pool()?.get()?;
+        let preferences = DocMetaSql::read_preferences(doc_id, &conn)?;
+        Ok(doc_preference_values(&preferences))
+    }
+
+    pub async fn set_doc_preference<T: Serialize>(&self, doc_id: &str, key: &str, value: &T) -> FlowyResult<()> {
+        let value = serde_json::to_string(value)?;
+        self.set_doc_preference_json(doc_id, key, value).await
+    }
+
+    /// Sets the preference `key` of the document to the JSON `value`. The preferences are a
+    /// document of their own, so the value is synced to the other devices and the document
+    /// itself gets no revision. The value set last wins over the ones set on the other devices.
+    /// Setting the value the key already has adds no revision.
+    pub async fn set_doc_preference_json(&self, doc_id: &str, key: &str, value: String) -> FlowyResult<()> {
+        let key = validate_doc_preference(key, &value)?;
+        let preferences_doc_id = doc_preferences_doc_id(doc_id);
+        let is_opened = self.editor_map.read().await.get(&preferences_doc_id).is_some();
+        let editor = self.doc_preferences_editor(doc_id).await?;
+        let result = match write_doc_preference(&editor, key, value).await {
+            Ok(()) => self.save_doc_preferences(doc_id, &editor).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if !is_opened {
+            self.editor_map.write().await.remove(&preferences_doc_id).await;
+        }
+        result
+    }
+
+    /// Merges the preferences of the document with the server's ones and uploads the result,
+    /// like `sync_custom_dictionary`. The latest entry of each key wins, see
+    /// `merge_doc_preferences_content`. Returns the JSON value of each preference after the merge.
+    pub async fn sync_doc_preferences(&self, doc_id: &str) -> FlowyResult<BTreeMap<String, String>> {
+        let token = self.user.token()?;
+        let preferences_doc_id = doc_preferences_doc_id(doc_id);
+        let is_opened = self.editor_map.read().await.get(&preferences_doc_id).is_some();
+        let editor = self.doc_preferences_editor(doc_id).await?;
+        let result = match editor
+            .sync_content(
+                &token,
+                self.cloud_service(&editor.doc_id),
+                Some(merge_doc_preferences_content),
+            )
+            .await
+        {
+            Ok(_) => self.save_doc_preferences(doc_id, &editor).await,
+            Err(e) => Err(e),
+        };
+        if !is_opened {
+            self.editor_map.write().await.remove(&preferences_doc_id).await;
+        }
+        result
     }
 
     pub fn initial_document_content(&self) -> String {
@@ -202,6 +2621,78 @@ impl DocumentManager {
         }
     }
 
+    /// Opens the custom dictionary of the workspace, it's created if the workspace doesn't have
+    /// one yet.
+    async fn custom_dictionary_editor(&self, workspace_id: &str) -> FlowyResult<Arc<DeltaDocumentEditor>> {
+        self.open_or_create_delta_document(&custom_dictionary_doc_id(workspace_id))
+            .await
+    }
+
+    /// Opens the preferences of the document, they're created if the document doesn't have any
+    /// yet.
+    async fn doc_preferences_editor(&self, doc_id: &str) -> FlowyResult<Arc<DeltaDocumentEditor>> {
+        self.open_or_create_delta_document(&doc_preferences_doc_id(doc_id))
+            .await
+    }
+
+    async fn open_or_create_delta_document(&self, doc_id: &str) -> FlowyResult<Arc<DeltaDocumentEditor>> {
+        if self.editor_map.read().await.get(doc_id).is_none() {
+            let pool = self.persistence.database.db_pool()?;
+            if self.make_rev_manager(doc_id, pool)?.number_of_revisions_in_disk() == 0 {
+                let revision = Revision::initial_revision(doc_id, Bytes::from(initial_delta_document_content()));
+                self.create_document(doc_id, vec![revision]).await?;
+            }
+            let _ = self.init_document_editor(doc_id).await?;
+        }
+        self.get_delta_document_editor(doc_id).await
+    }
+
+    async fn save_custom_dictionary(
+        &self,
+        workspace_id: &str,
+        editor: &Arc<DeltaDocumentEditor>,
+    ) -> FlowyResult<Vec<String>> {
+        let content = editor.content().await?;
+        let pool = self.persistence.database.db_pool()?;
+        let _ = CustomDictionaryObserver::new(workspace_id, pool).save(&content)?;
+        Ok(dictionary_words(&content))
+    }
+
+    async fn save_doc_preferences(
+        &self,
+        doc_id: &str,
+        editor: &Arc<DeltaDocumentEditor>,
+    ) -> FlowyResult<BTreeMap<String, String>> {
+        let content = editor.content().await?;
+        let pool = self.persistence.database.db_pool()?;
+        DocPreferencesObserver::new(doc_id, pool).save(&content)
+    }
+
+    /// Returns the revisions of the document, including the ones waiting for the deferred save.
+    async fn flushed_revisions(&self, doc_id: &str) -> FlowyResult<Vec<Revision>> {
+        let editor = self.get_delta_document_editor(doc_id).await?;
+        let rev_manager = editor.rev_manager();
+        rev_manager.flush().await?;
+        rev_manager.load_revisions().await
+    }
+
+    async fn get_delta_document_editor(&self, doc_id: &str) -> FlowyResult<Arc<DeltaDocumentEditor>> {
+        if self.config.version != DocumentVersionPB::V0 {
+            return Err(FlowyError::internal().context("The document is not a delta document"));
+        }
+        let editor = self.get_document_editor(doc_id).await?;
+        match editor.as_any().downcast_ref::<Arc<DeltaDocumentEditor>>() {
+            None => Err(FlowyError::internal().context("The editor is not a DeltaDocumentEditor")),
+            Some(editor) => Ok(editor.clone()),
+        }
+    }
+
+    /// Returns the editor of the delta document if it's opened, the document isn't opened here.
+    async fn opened_delta_document_editor(&self, doc_id: &str) -> Option<Arc<DeltaDocumentEditor>> {
+        let handler = self.editor_map.read().await.get(doc_id)?;
+        handler.as_any().downcast_ref::<Arc<DeltaDocumentEditor>>().cloned()
+    }
+
     /// Initializes a document editor with the doc_id
     ///
     /// # Arguments
@@ -213,20 +2704,61 @@ impl DocumentManager {
     ///
     #[tracing::instrument(level = "trace", skip(self), err)]
     pub async fn init_document_editor(&self, doc_id: &str) -> Result<Arc<dyn DocumentEditor>, FlowyError> {
+        if self.restore_staged.load(Ordering::SeqCst) {
+            return Err(FlowyError::module_not_ready()
+                .context("A backup is restored, the documents are opened again after the restart"));
+        }
+        self.persistence.check_table_migrations()?;
         let pool = self.persistence.database.db_pool()?;
         let user = self.user.clone();
         let token = self.user.token()?;
+        let server = self.server_resolver.resolve(doc_id);
+        self.listen_server_if_need(&server).await;
+        let is_scratch_document = self.is_scratch_document(doc_id).await?;
+        let web_socket: Arc<dyn RevisionWebSocket> = if is_scratch_document {
+            Arc::new(LocalOnlyWebSocket::new())
+        } else {
+            server.web_socket.clone()
+        };
         let cloud_service = Arc::new(DocumentRevisionCloudService {
             token,
-            server: self.cloud_service.clone(),
+            server: self.cloud_service(doc_id),
         });
 
         match self.config.version {
             DocumentVersionPB::V0 => {
+                // The document that isn't on the disk yet is streamed from the server if it
+                // supports it, instead of fetched whole when the revisions are loaded.
+                let is_on_disk = self
+                    .make_rev_manager(doc_id, pool.clone())?
+                    .number_of_revisions_in_disk()
+                    > 0;
+                if !is_scratch_document && !is_on_disk {
+                    let server = &cloud_service.server;
+                    if let Err(e) = hydrate_document_in_chunks(server, &cloud_service.token, doc_id, pool.clone()).await
+                    {
+                        tracing::warn!("Stream the document {} failed, fetch it whole: {:?}", doc_id, e);
+                    }
+                }
                 let rev_manager = self.make_delta_document_rev_manager(doc_id, pool.clone())?;
+                let content_observer = ContentObserver::from_doc_id(doc_id, pool.clone());
+                // The custom dictionaries and the preferences don't refer to attachments.
+                let attachment_observer = content_observer
+                    .is_none()
+                    .then(|| AttachmentObserver::new(self.attachment_reconcile.clone()));
                 let editor: Arc<dyn DocumentEditor> = Arc::new(
-                    DeltaDocumentEditor::new(doc_id, user, rev_manager, self.rev_web_socket.clone(), cloud_service)
-                        .await?,
+                    DeltaDocumentEditor::new(
+                        doc_id,
+                        user,
+                        rev_manager,
+                        web_socket,
+                        cloud_service,
+                        &self.config,
+                        &self.custom_attributes,
+                        content_observer,
+                        attachment_observer,
+                    )
+                    .await?,
                 );
                 self.editor_map
                     .write()
@@ -247,6 +2779,30 @@ impl DocumentManager {
         }
     }
 
+    /// Returns the cloud service of the server that the document resolves to. Its fetches of the
+    /// document go through the `fetch_guard` if `single_flight_fetch` is on.
+    fn cloud_service(&self, doc_id: &str) -> Arc<dyn DocumentCloudService> {
+        let cloud_service = self.server_resolver.resolve(doc_id).cloud_service;
+        if self.config.single_flight_fetch {
+            self.fetch_guard.guard(cloud_service)
+        } else {
+            cloud_service
+        }
+    }
+
+    /// Each endpoint has its own web socket connection, so its state changes are only
+    /// forwarded to the documents that resolve to that endpoint.
+    async fn listen_server_if_need(&self, server: &DocumentServer) {
+        if self.listened_endpoints.write().await.insert(server.endpoint.clone()) {
+            listen_ws_state_changed(
+                server.clone(),
+                self.server_resolver.clone(),
+                self.editor_map.clone(),
+                &self.config.executor,
+            );
+        }
+    }
+
     fn make_rev_manager(
         &self,
         doc_id: &str,
@@ -258,6 +2814,31 @@ impl DocumentManager {
         }
     }
 
+    fn rev_persistence_configuration(
+        &self,
+        doc_id: &str,
+        merge_threshold: usize,
+    ) -> FlowyResult<RevisionPersistenceConfiguration> {
+        let mut configuration = RevisionPersistenceConfiguration::new(merge_threshold, true)
+            .with_save_debounce(self.config.save_debounce.clone())
+            .with_executor(self.config.executor.clone())
+            .with_priority_scheduler(self.config.priority_scheduler.clone());
+        if let Some(error_reporter) = self.error_reporter.as_ref() {
+            configuration = configuration.with_error_reporter(error_reporter.clone());
+        }
+        // The flag is resolved by the async callers, e.g. `init_document_editor`, before they make
+        // the revision manager.
+        let is_scratch_document = self
+            .scratch_documents
+            .get(doc_id)
+            .map_or(false, |is_scratch| *is_scratch);
+        if is_scratch_document {
+            Ok(configuration.with_local_only(SCRATCH_DOCUMENT_HISTORY_LIMIT))
+        } else {
+            Ok(configuration)
+        }
+    }
+
     fn make_document_rev_manager(
         &self,
         doc_id: &str,
@@ -265,16 +2846,17 @@ impl DocumentManager {
     ) -> Result<RevisionManager<Arc<ConnectionPool>>, FlowyError> {
         let user_id = self.user.user_id()?;
         let disk_cache = SQLiteDocumentRevisionPersistence::new(&user_id, pool.clone());
-        let configuration = RevisionPersistenceConfiguration::new(200, true);
+        let configuration = self.rev_persistence_configuration(doc_id, 200)?;
         let rev_persistence = RevisionPersistence::new(&user_id, doc_id, disk_cache, configuration);
         let snapshot_persistence = SQLiteDocumentRevisionSnapshotPersistence::new(doc_id, pool);
-        Ok(RevisionManager::new(
+        let rev_manager = RevisionManager::new(
             &user_id,
             doc_id,
             rev_persistence,
             DocumentRevisionMergeable(),
             snapshot_persistence,
-        ))
+        );
+        Ok(self.observe_compose_error(rev_manager))
     }
 
     fn make_delta_document_rev_manager(
@@ -283,19 +2865,51 @@ impl DocumentManager {
         pool: Arc<ConnectionPool>,
     ) -> Result<RevisionManager<Arc<ConnectionPool>>, FlowyError> {
         let user_id = self.user.user_id()?;
-        let disk_cache = SQLiteDeltaDocumentRevisionPersistence::new(&user_id, pool);
-        let configuration = RevisionPersistenceConfiguration::new(100, true);
+        let disk_cache = SQLiteDeltaDocumentRevisionPersistence::new(&user_id, pool)
+            .with_shared_payloads(self.config.share_revision_payloads);
+        let configuration = self.rev_persistence_configuration(doc_id, 100)?;
         let rev_persistence = RevisionPersistence::new(&user_id, doc_id, disk_cache, configuration);
-        Ok(RevisionManager::new(
+        let rev_manager = RevisionManager::new(
             &user_id,
             doc_id,
             rev_persistence,
             DeltaDocumentRevisionMergeable(),
             PhantomSnapshotPersistence(),
-        ))
+        );
+        Ok(self.observe_compose_error(rev_manager))
+    }
+
+    fn observe_compose_error(
+        &self,
+        rev_manager: RevisionManager<Arc<ConnectionPool>>,
+    ) -> RevisionManager<Arc<ConnectionPool>> {
+        match &self.compose_error_observer {
+            None => rev_manager,
+            Some(observer) => rev_manager.with_compose_error_observer(observer.clone()),
+        }
     }
 }
 
+/// Composes the delta revisions into one revision whose line endings are `\n`. The retains of
+/// the later revisions would point to the wrong positions if each revision was normalized alone.
+fn normalize_line_endings_of_revisions(doc_id: &str, revisions: Vec<Revision>) -> FlowyResult<Vec<Revision>> {
+    let rev_id = match revisions.last() {
+        None => return Ok(revisions),
+        Some(revision) => revision.rev_id,
+    };
+    let operations: DeltaTextOperations = make_operations_from_revisions(revisions)?;
+    let bytes = operations.normalize_line_endings().json_bytes();
+    let doc_md5 = md5(&bytes);
+    Ok(vec![Revision::new(doc_id, 0, rev_id, bytes, doc_md5)])
+}
+
+/// The delta document that is read without opening it, see `read_closed_document`.
+struct ClosedDocument {
+    document: LazyDocument,
+    rev_id: i64,
+    next_sync_rev_id: Option<i64>,
+}
+
 struct DocumentRevisionCloudService {
     token: String,
     server: Arc<dyn DocumentCloudService>,
@@ -312,7 +2926,7 @@ impl RevisionCloudService for DocumentRevisionCloudService {
             match server.fetch_document(&token, params).await? {
                 None => Err(FlowyError::record_not_found().context("Remote doesn't have this document")),
                 Some(payload) => {
-                    let bytes = Bytes::from(payload.data.clone());
+                    let bytes = Bytes::from(payload.data);
                     let doc_md5 = md5(&bytes);
                     let revision = Revision::new(&payload.doc_id, payload.base_rev_id, payload.rev_id, bytes, doc_md5);
                     Ok(vec![revision])
@@ -320,6 +2934,83 @@ impl RevisionCloudService for DocumentRevisionCloudService {
             }
         })
     }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn fetch_object_rev_id(&self, _user_id: &str, object_id: &str) -> FutureResult<Option<i64>, FlowyError> {
+        let params: DocumentId = object_id.to_string().into();
+        let server = self.server.clone();
+        let token = self.token.clone();
+
+        FutureResult::new(async move { server.fetch_document_rev_id(&token, params).await })
+    }
+}
+
+/// Returns the editors of the delta documents, only they keep the history that the maintenance
+/// rewrites.
+async fn replace_in_document(
+    editor: &Arc<DeltaDocumentEditor>,
+    document: &FindReplaceDocPreview,
+    query: &FindReplaceQuery,
+) -> FlowyResult<FindReplaceOutcome> {
+    let (_, content_hash) = editor.content_hash().await?;
+    if content_hash == document.replaced_hash {
+        return Ok(FindReplaceOutcome::AlreadyReplaced);
+    }
+    if content_hash != document.content_hash {
+        return Ok(FindReplaceOutcome::Stale);
+    }
+    match editor.replace_if_unchanged(&document.content_hash, query).await? {
+        None => Ok(FindReplaceOutcome::Stale),
+        Some(num_of_replaced) => Ok(FindReplaceOutcome::Replaced(num_of_replaced)),
+    }
+}
+
+/// Replaces the entries of the preference `key` with one entry of the `value`, with one revision.
+/// Nothing is written if the key already has the `value`.
+async fn write_doc_preference(editor: &Arc<DeltaDocumentEditor>, key: &str, value: String) -> FlowyResult<()> {
+    loop {
+        let (content_hash, operations) = editor.operations_with_hash().await?;
+        let content = operations.content()?;
+        let lines = doc_preference_lines(&content, key);
+        // The clock of the device that set the current value may be ahead, the value set here
+        // must still win.
+        let mut updated_at = chrono::Utc::now().timestamp_millis();
+        if let Some(current) = doc_preferences(&content).get(key) {
+            if current.value == value && lines.len() == 1 {
+                return Ok(());
+            }
+            updated_at = updated_at.max(current.updated_at + 1);
+        }
+        let preference = DocPreference {
+            key: key.to_owned(),
+            value: value.clone(),
+            updated_at,
+        };
+
+        let mut changes = DeltaTextOperations::default();
+        changes.insert(&preference.line()?, AttributeHashMap::default());
+        let mut offset = 0;
+        for interval in lines.iter().rev() {
+            changes.retain(interval.start - offset, AttributeHashMap::default());
+            changes.delete(interval.size());
+            offset = interval.end;
+        }
+        changes.retain(operations.utf16_target_len - offset, AttributeHashMap::default());
+        // The entries may have changed since they were read, e.g. by the sync.
+        if editor.compose_if_unchanged(&content_hash, changes).await?.is_some() {
+            return Ok(());
+        }
+    }
+}
+
+fn opened_rev_manager(editor: &Arc<dyn DocumentEditor>) -> FlowyResult<Arc<RevisionManager<Arc<ConnectionPool>>>> {
+    if let Some(editor) = editor.as_any().downcast_ref::<Arc<DeltaDocumentEditor>>() {
+        return Ok(editor.rev_manager());
+    }
+    match editor.as_any().downcast_ref::<Arc<AppFlowyDocumentEditor>>() {
+        Some(editor) => Ok(editor.rev_manager()),
+        None => Err(FlowyError::internal().context("Unknown document editor")),
+    }
 }
 
 #[derive(Clone)]
@@ -340,17 +3031,48 @@ impl std::ops::Deref for RefCountDocumentHandler {
     }
 }
 
-#[tracing::instrument(level = "trace", skip(web_socket, handlers))]
+#[tracing::instrument(level = "trace", skip_all, fields(endpoint = %server.endpoint))]
 fn listen_ws_state_changed(
-    web_socket: Arc<dyn RevisionWebSocket>,
+    server: DocumentServer,
+    server_resolver: Arc<dyn DocumentServerResolver>,
     handlers: Arc<RwLock<RefCountHashMap<RefCountDocumentHandler>>>,
+    executor: &Executor,
 ) {
-    tokio::spawn(async move {
-        let mut notify = web_socket.subscribe_state_changed().await;
+    executor.spawn(async move {
+        let mut notify = server.web_socket.subscribe_state_changed().await;
         while let Ok(state) = notify.recv().await {
-            handlers.read().await.values().iter().for_each(|handler| {
-                handler.receive_ws_state(&state);
-            })
+            let handlers = handlers.read().await;
+            handlers
+                .keys()
+                .iter()
+                .filter(|doc_id| server_resolver.resolve(doc_id).endpoint == server.endpoint)
+                .flat_map(|doc_id| handlers.get(doc_id))
+                .for_each(|handler| {
+                    handler.receive_ws_state(&state);
+                })
         }
     });
 }
+
+/// The web socket of the scratch documents, which never sync to the server.
+struct LocalOnlyWebSocket {
+    state_sender: broadcast::Sender<WSConnectState>,
+}
+
+impl LocalOnlyWebSocket {
+    fn new() -> Self {
+        let (state_sender, _) = broadcast::channel(1);
+        Self { state_sender }
+    }
+}
+
+impl RevisionWebSocket for LocalOnlyWebSocket {
+    fn send(&self, _data: ClientRevisionWSData) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn subscribe_state_changed(&self) -> BoxFuture<WSStateReceiver> {
+        let receiver = self.state_sender.subscribe();
+        Box::pin(async move { receiver })
+    }
+}
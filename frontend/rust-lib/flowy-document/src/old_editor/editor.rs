@@ -1,8 +1,13 @@
 #![allow(unused_attributes)]
 #![allow(unused_attributes)]
 
-use crate::old_editor::queue::{EditDocumentQueue, EditorCommand, EditorCommandSender};
-use crate::{errors::FlowyError, DocumentEditor, DocumentUser};
+use crate::old_editor::queue::{ContentMerge, EditDocumentQueue, EditorCommand, EditorCommandSender};
+use crate::old_editor::revalidate::{spawn_revalidation, sync_document_content};
+use crate::services::{
+    preview_document, replacement_operations, AttachmentObserver, ContentObserver, DocumentMeta, DocumentPreview,
+    FindReplaceDocPreview, FindReplaceQuery,
+};
+use crate::{errors::FlowyError, DocumentCloudService, DocumentConfig, DocumentEditor, DocumentPause, DocumentUser};
 use bytes::Bytes;
 use flowy_database::ConnectionPool;
 use flowy_error::{internal_error, FlowyResult};
@@ -10,21 +15,34 @@ use flowy_http_model::document::DocumentPayload;
 use flowy_http_model::revision::Revision;
 use flowy_http_model::ws_data::ServerRevisionWSData;
 use flowy_revision::{
-    RevisionCloudService, RevisionManager, RevisionMergeable, RevisionObjectDeserializer, RevisionObjectSerializer,
-    RevisionWebSocket,
+    ComposeErrorObserver, RevisionCloudService, RevisionManager, RevisionMergeable, RevisionObjectDeserializer,
+    RevisionObjectSerializer, RevisionWebSocket,
+};
+use flowy_sync::client_document::attribute_histogram;
+use flowy_sync::errors::{CollaborateError, CollaborateResult};
+use flowy_sync::util::{
+    make_operations_from_revisions, make_operations_from_revisions_observed, make_rollback_operations,
 };
-use flowy_sync::{errors::CollaborateResult, util::make_operations_from_revisions};
 use lib_infra::async_trait::async_trait;
+use lib_infra::chunked::ChunkedBuffer;
 use lib_infra::future::FutureResult;
-use lib_ot::core::{AttributeEntry, AttributeHashMap};
+use lib_ot::core::{AttributeEntry, AttributeHashMap, AttributeValue, OperationTransform};
 use lib_ot::{
     core::{DeltaOperation, Interval},
-    text_delta::DeltaTextOperations,
+    text_delta::{
+        detect_script, plain_text, search_snippets, search_text, word_count, BuildInTextAttribute,
+        BuildInTextAttributeKey, CustomAttributes, DeltaTextOperations, DocumentAst, Script, SearchSnippet,
+    },
 };
 use lib_ws::WSConnectState;
 use std::any::Any;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// The number of the chars of the line that `search_snippets` keeps on each side of a match.
+pub const SEARCH_SNIPPET_RADIUS: usize = 40;
 
 pub struct DeltaDocumentEditor {
     pub doc_id: String,
@@ -32,42 +50,78 @@ pub struct DeltaDocumentEditor {
     rev_manager: Arc<RevisionManager<Arc<ConnectionPool>>>,
     #[cfg(feature = "sync")]
     ws_manager: Arc<flowy_revision::RevisionWebSocketManager>,
+    #[cfg(feature = "sync")]
+    conflict_controller: Arc<crate::old_editor::web_socket::DocumentConflictController>,
     edit_cmd_tx: EditorCommandSender,
+    revalidation: Option<JoinHandle<()>>,
 }
 
 impl DeltaDocumentEditor {
-    #[allow(unused_variables)]
+    #[allow(unused_variables, clippy::too_many_arguments)]
     pub(crate) async fn new(
         doc_id: &str,
         user: Arc<dyn DocumentUser>,
         mut rev_manager: RevisionManager<Arc<ConnectionPool>>,
         rev_web_socket: Arc<dyn RevisionWebSocket>,
         cloud_service: Arc<dyn RevisionCloudService>,
+        config: &DocumentConfig,
+        custom_attributes: &CustomAttributes,
+        content_observer: Option<ContentObserver>,
+        attachment_observer: Option<AttachmentObserver>,
     ) -> FlowyResult<Arc<Self>> {
+        // The UI offers to recover the text of the document that can't be composed, see
+        // `DocumentManager::recover_text`. The other errors, e.g. of the database, are kept.
         let document = rev_manager
-            .initialize::<DeltaDocumentRevisionSerde>(Some(cloud_service))
+            .initialize::<DeltaDocumentRevisionSerde>(Some(cloud_service.clone()))
             .await?;
-        let operations = DeltaTextOperations::from_bytes(&document.data)?;
+        let operations =
+            DeltaTextOperations::from_bytes(&document.data).map_err(|e| unreadable_document(doc_id, e.into()))?;
         let rev_manager = Arc::new(rev_manager);
         let doc_id = doc_id.to_string();
         let user_id = user.user_id()?;
 
-        let edit_cmd_tx = spawn_edit_queue(user, rev_manager.clone(), operations);
+        let edit_cmd_tx = spawn_edit_queue(
+            user,
+            rev_manager.clone(),
+            operations,
+            config,
+            custom_attributes.clone(),
+            content_observer,
+            attachment_observer,
+        );
         #[cfg(feature = "sync")]
-        let ws_manager = crate::old_editor::web_socket::make_document_ws_manager(
+        let (ws_manager, conflict_controller) = crate::old_editor::web_socket::make_document_ws_manager(
             doc_id.clone(),
             user_id.clone(),
             edit_cmd_tx.clone(),
             rev_manager.clone(),
             rev_web_socket,
+            config.fetch_overwrite_policy,
         )
         .await;
+        // Opens the document with its local revisions, the newer content of the server replaces
+        // it once it's fetched.
+        let revalidation = if config.revalidate_on_open {
+            Some(spawn_revalidation(
+                doc_id.clone(),
+                user_id,
+                edit_cmd_tx.clone(),
+                rev_manager.clone(),
+                cloud_service,
+                config.fetch_overwrite_policy,
+            ))
+        } else {
+            None
+        };
         let editor = Arc::new(Self {
             doc_id,
             rev_manager,
             #[cfg(feature = "sync")]
             ws_manager,
+            #[cfg(feature = "sync")]
+            conflict_controller,
             edit_cmd_tx,
+            revalidation,
         });
         Ok(editor)
     }
@@ -84,6 +138,15 @@ impl DeltaDocumentEditor {
         Ok(())
     }
 
+    /// Inserts the `operations` at `index` with their attributes, e.g. a snippet.
+    pub async fn insert_operations(&self, index: usize, operations: DeltaTextOperations) -> FlowyResult<()> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<()>>();
+        let msg = EditorCommand::InsertOperations { index, operations, ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        rx.await.map_err(internal_error)??;
+        Ok(())
+    }
+
     pub async fn delete(&self, interval: Interval) -> Result<(), FlowyError> {
         let (ret, rx) = oneshot::channel::<CollaborateResult<()>>();
         let msg = EditorCommand::Delete { interval, ret };
@@ -116,6 +179,53 @@ impl DeltaDocumentEditor {
         Ok(())
     }
 
+    /// Deletes the first `len` of the document, e.g. to clear everything above the cursor.
+    /// Returns the revision of the deletion.
+    pub async fn trim_leading(&self, len: usize) -> FlowyResult<Revision> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<i64>>();
+        let msg = EditorCommand::TrimLeading { len, ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        let rev_id = rx.await.map_err(internal_error)??;
+        self.rev_manager
+            .get_revision(rev_id)
+            .await
+            .ok_or_else(|| FlowyError::record_not_found().context(format!("Revision {} not found", rev_id)))
+    }
+
+    /// Removes the text from `index` to the end of the document in one revision, which is saved
+    /// with the first revision of the document `new_doc_id` in one transaction. The removed text
+    /// keeps its attributes and ends with a newline. Returns it instead if there was nothing to
+    /// remove, e.g. the split at the end of the document, the new document isn't created then.
+    pub async fn split_off(&self, index: usize, new_doc_id: &str) -> FlowyResult<Option<DeltaTextOperations>> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<Option<DeltaTextOperations>>>();
+        let msg = EditorCommand::SplitOff {
+            index,
+            new_doc_id: new_doc_id.to_owned(),
+            ret,
+        };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        let result = rx.await.map_err(internal_error)??;
+        Ok(result)
+    }
+
+    /// Collects the following changes into one undo entry until `end_undo_group` is called, so
+    /// an operation made of several steps can be undone at once.
+    pub async fn begin_undo_group(&self) -> FlowyResult<()> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<()>>();
+        let msg = EditorCommand::BeginUndoGroup { ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        rx.await.map_err(internal_error)??;
+        Ok(())
+    }
+
+    pub async fn end_undo_group(&self) -> FlowyResult<()> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<()>>();
+        let msg = EditorCommand::EndUndoGroup { ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        rx.await.map_err(internal_error)??;
+        Ok(())
+    }
+
     pub async fn can_undo(&self) -> bool {
         let (ret, rx) = oneshot::channel::<bool>();
         let msg = EditorCommand::CanUndo { ret };
@@ -145,11 +255,265 @@ impl DeltaDocumentEditor {
         rx.await.map_err(internal_error)??;
         Ok(())
     }
+
+    /// Returns the dominant script of the document, it can be used to pick the fonts and the
+    /// spellcheck language.
+    pub async fn detect_script(&self) -> FlowyResult<Script> {
+        let operations = self.operations().await?;
+        Ok(detect_script(&operations))
+    }
+
+    /// Returns the length of the text each attribute is applied to, e.g. to find the stray
+    /// formatting of the document.
+    pub async fn attribute_histogram(&self) -> FlowyResult<HashMap<String, usize>> {
+        let operations = self.operations().await?;
+        Ok(attribute_histogram(&operations))
+    }
+
+    /// Returns the operations of the document in the `interval` with their attributes, e.g.
+    /// the selection the user saves as a snippet.
+    pub async fn slice(&self, interval: Interval) -> FlowyResult<DeltaTextOperations> {
+        let operations = self.operations().await?;
+        Ok(operations.slice(interval))
+    }
+
+    /// Archives the text in the `interval`. It stays in the document and syncs like any other
+    /// text, but it's left out of the search, the word count and the export.
+    pub async fn archive_range(&self, interval: Interval) -> FlowyResult<()> {
+        self.format(interval, BuildInTextAttribute::Archived(true)).await
+    }
+
+    /// Brings back the archived text in the `interval` with its other attributes untouched.
+    pub async fn unarchive_range(&self, interval: Interval) -> FlowyResult<()> {
+        let attribute = AttributeEntry::new(BuildInTextAttributeKey::Archived.as_ref(), AttributeValue::none());
+        self.format(interval, attribute).await
+    }
+
+    /// Returns the text of the document, the archived text is skipped unless `include_archived`.
+    pub async fn plain_text(&self, include_archived: bool) -> FlowyResult<String> {
+        let operations = self.operations().await?;
+        Ok(plain_text(&operations, include_archived))
+    }
+
+    pub async fn word_count(&self, include_archived: bool) -> FlowyResult<usize> {
+        let operations = self.operations().await?;
+        Ok(word_count(&operations, include_archived))
+    }
+
+    /// Returns the utf16 ranges of the document that match the `query`.
+    pub async fn search(&self, query: &str, include_archived: bool) -> FlowyResult<Vec<Interval>> {
+        let operations = self.operations().await?;
+        Ok(search_text(&operations, query, include_archived))
+    }
+
+    /// Returns the document as a tree of blocks, see `DeltaTextOperations::to_document_ast`.
+    pub async fn document_ast(&self) -> FlowyResult<DocumentAst> {
+        let operations = self.operations().await?;
+        Ok(operations.to_document_ast())
+    }
+
+    /// Returns the first `max_len` graphemes of the visible text, see `document_preview`.
+    pub async fn preview(&self, max_len: usize) -> FlowyResult<DocumentPreview> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<DocumentPreview>>();
+        let msg = EditorCommand::GetPreview { max_len, ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        let preview = rx.await.map_err(internal_error)??;
+        Ok(preview)
+    }
+
+    /// Returns the counts and the revision ids of the document without its content.
+    pub async fn meta(&self) -> FlowyResult<DocumentMeta> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<DocumentMeta>>();
+        let msg = EditorCommand::GetMeta { ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        let meta = rx.await.map_err(internal_error)??;
+        Ok(meta)
+    }
+
+    /// Returns the rev_id of the document with the hash of its content, see `content_hash`.
+    pub async fn content_hash(&self) -> FlowyResult<(i64, String)> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<(i64, String)>>();
+        let msg = EditorCommand::GetContentHash { ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        let content_hash = rx.await.map_err(internal_error)??;
+        Ok(content_hash)
+    }
+
+    /// Same as `search`, but each match comes with the text around it to show in the results.
+    pub async fn search_snippets(&self, query: &str, include_archived: bool) -> FlowyResult<Vec<SearchSnippet>> {
+        let operations = self.operations().await?;
+        Ok(search_snippets(
+            &operations,
+            query,
+            include_archived,
+            SEARCH_SNIPPET_RADIUS,
+        ))
+    }
+
+    /// Keeps the selection of the UI, it's moved along with the text when the remote
+    /// operations are applied. Each range is in utf16 code units.
+    pub async fn update_selection(&self, selection: Vec<Interval>) -> FlowyResult<()> {
+        let (ret, rx) = oneshot::channel();
+        let msg = EditorCommand::UpdateSelection { selection, ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        rx.await.map_err(internal_error)??;
+        Ok(())
+    }
+
+    pub async fn selection(&self) -> FlowyResult<Vec<Interval>> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<Vec<Interval>>>();
+        let msg = EditorCommand::GetSelection { ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        let selection = rx.await.map_err(internal_error)??;
+        Ok(selection)
+    }
+
+    /// Returns true if the document stopped sending its revisions because it kept re-sending
+    /// the same revision back and forth with another client.
+    pub fn is_sync_loop_detected(&self) -> bool {
+        #[cfg(feature = "sync")]
+        {
+            self.conflict_controller.is_sync_loop_detected()
+        }
+        #[cfg(not(feature = "sync"))]
+        {
+            false
+        }
+    }
+
+    /// The number of the revisions pushed by the server that aren't applied yet, they're applied
+    /// a batch at a time so the local edits aren't held back behind them.
+    pub fn remote_backlog(&self) -> usize {
+        #[cfg(feature = "sync")]
+        {
+            self.ws_manager.remote_backlog()
+        }
+        #[cfg(not(feature = "sync"))]
+        {
+            0
+        }
+    }
+
+    /// Sends the revision that was held back by the sync loop and applies the revisions that
+    /// the server pushed meanwhile.
+    pub async fn resume_sync(&self) -> FlowyResult<()> {
+        #[cfg(feature = "sync")]
+        self.conflict_controller.resume_sync().await?;
+        Ok(())
+    }
+
+    /// Returns the plain text of the document.
+    pub(crate) async fn content(&self) -> FlowyResult<String> {
+        let operations = self.operations().await?;
+        Ok(operations.content()?)
+    }
+
+    /// Returns the matches of the `query` in the document, None if it has none.
+    pub(crate) async fn find_replace_preview(
+        &self,
+        query: &FindReplaceQuery,
+    ) -> FlowyResult<Option<FindReplaceDocPreview>> {
+        let operations = self.operations().await?;
+        Ok(preview_document(&self.doc_id, &operations, query, None))
+    }
+
+    /// Replaces every match of the `query` with one revision if the document still has the
+    /// `content_hash`. Returns the number of the replaced matches, None if the document changed.
+    pub(crate) async fn replace_if_unchanged(
+        &self,
+        content_hash: &str,
+        query: &FindReplaceQuery,
+    ) -> FlowyResult<Option<usize>> {
+        let operations = self.operations().await?;
+        // The document had matches when it had the `content_hash`, so it changed if there are
+        // none left.
+        let (num_of_matches, operations) = match replacement_operations(&operations, query) {
+            None => return Ok(None),
+            Some(replacement) => replacement,
+        };
+
+        let (ret, rx) = oneshot::channel::<CollaborateResult<Option<i64>>>();
+        let msg = EditorCommand::ComposeLocalOperationsIfUnchanged {
+            content_hash: content_hash.to_owned(),
+            operations,
+            ret,
+        };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        let rev_id = rx.await.map_err(internal_error)??;
+        Ok(rev_id.map(|_| num_of_matches))
+    }
+
+    /// Returns the hash of the content with the operations of the document. The document that
+    /// changes after the hash was taken isn't changed by `compose_if_unchanged`.
+    pub(crate) async fn operations_with_hash(&self) -> FlowyResult<(String, DeltaTextOperations)> {
+        let (_, content_hash) = self.content_hash().await?;
+        let operations = self.operations().await?;
+        Ok((content_hash, operations))
+    }
+
+    /// Returns a copy of the operations of the document.
+    pub(crate) async fn operations(&self) -> FlowyResult<DeltaTextOperations> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<DeltaTextOperations>>();
+        let msg = EditorCommand::GetOperations { ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        let operations = rx.await.map_err(internal_error)??;
+        Ok(operations)
+    }
+
+    /// Composes the local `operations` unless the document no longer has the `content_hash`.
+    /// Returns the rev_id of the new revision, None if the document changed.
+    pub(crate) async fn compose_if_unchanged(
+        &self,
+        content_hash: &str,
+        operations: DeltaTextOperations,
+    ) -> FlowyResult<Option<i64>> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<Option<i64>>>();
+        let msg = EditorCommand::ComposeLocalOperationsIfUnchanged {
+            content_hash: content_hash.to_owned(),
+            operations,
+            ret,
+        };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        let rev_id = rx.await.map_err(internal_error)??;
+        Ok(rev_id)
+    }
+
+    /// Merges the document with the content of the `cloud_service` and uploads it, see
+    /// `sync_document_content`. Returns true if the document got the server's changes.
+    pub(crate) async fn sync_content(
+        &self,
+        token: &str,
+        cloud_service: Arc<dyn DocumentCloudService>,
+        merge: Option<ContentMerge>,
+    ) -> FlowyResult<bool> {
+        sync_document_content(
+            &self.doc_id,
+            token,
+            self.edit_cmd_tx.clone(),
+            self.rev_manager.clone(),
+            cloud_service,
+            merge,
+        )
+        .await
+    }
+
+    /// Returns the operations that undo the last `n` revisions of the document. Nothing is
+    /// applied, compose the operations as the local operations to roll the document back.
+    pub async fn rollback_operations(&self, n: usize) -> FlowyResult<DeltaTextOperations> {
+        // The last revisions may still be waiting for the deferred save.
+        self.rev_manager.flush().await?;
+        let revisions = self.rev_manager.load_revisions().await?;
+        let operations = make_rollback_operations(revisions, n)?;
+        Ok(operations)
+    }
 }
 
 #[async_trait]
 impl DocumentEditor for Arc<DeltaDocumentEditor> {
     async fn close(&self) {
+        if let Some(revalidation) = &self.revalidation {
+            revalidation.abort();
+        }
         #[cfg(feature = "sync")]
         self.ws_manager.stop();
     }
@@ -165,6 +529,17 @@ impl DocumentEditor for Arc<DeltaDocumentEditor> {
         })
     }
 
+    fn export_chunked(&self, chunk_size: usize) -> FutureResult<ChunkedBuffer, FlowyError> {
+        let (ret, rx) = oneshot::channel::<CollaborateResult<ChunkedBuffer>>();
+        let msg = EditorCommand::GetOperationsChunked { chunk_size, ret };
+        let edit_cmd_tx = self.edit_cmd_tx.clone();
+        FutureResult::new(async move {
+            let _ = edit_cmd_tx.send(msg).await;
+            let buffer = rx.await.map_err(internal_error)??;
+            Ok(buffer)
+        })
+    }
+
     fn duplicate(&self) -> FutureResult<String, FlowyError> {
         self.export()
     }
@@ -199,6 +574,26 @@ impl DocumentEditor for Arc<DeltaDocumentEditor> {
         })
     }
 
+    async fn flush(&self) -> FlowyResult<()> {
+        self.rev_manager.flush().await
+    }
+
+    async fn pause(&self) -> FlowyResult<DocumentPause> {
+        // The sync gets paused first, it may be waiting on the edit queue to apply the server's
+        // revisions.
+        #[cfg(feature = "sync")]
+        let sync = self.ws_manager.pause().await;
+        let (resume, resume_rx) = oneshot::channel();
+        let (ret, rx) = oneshot::channel::<CollaborateResult<()>>();
+        let msg = EditorCommand::Pause { resume: resume_rx, ret };
+        let _ = self.edit_cmd_tx.send(msg).await;
+        rx.await.map_err(internal_error)??;
+        let pause = DocumentPause::new(resume);
+        #[cfg(feature = "sync")]
+        let pause = pause.with_sync(sync);
+        Ok(pause)
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -214,9 +609,24 @@ fn spawn_edit_queue(
     user: Arc<dyn DocumentUser>,
     rev_manager: Arc<RevisionManager<Arc<ConnectionPool>>>,
     delta: DeltaTextOperations,
+    config: &DocumentConfig,
+    custom_attributes: CustomAttributes,
+    content_observer: Option<ContentObserver>,
+    attachment_observer: Option<AttachmentObserver>,
 ) -> EditorCommandSender {
     let (sender, receiver) = mpsc::channel(1000);
-    let edit_queue = EditDocumentQueue::new(user, rev_manager, delta, receiver);
+    let executor = rev_manager.executor().clone();
+    let edit_queue = EditDocumentQueue::new(
+        user,
+        rev_manager,
+        delta,
+        config.redact_logs,
+        custom_attributes,
+        config.revision_guards.clone(),
+        content_observer,
+        attachment_observer,
+        receiver,
+    );
     // We can use tokio::task::spawn_local here by using tokio::spawn_blocking.
     // https://github.com/tokio-rs/tokio/issues/2095
     // tokio::task::spawn_blocking(move || {
@@ -226,18 +636,14 @@ fn spawn_edit_queue(
     //         local.run_until(edit_queue.run()).await;
     //     });
     // });
-    tokio::spawn(edit_queue.run());
+    executor.spawn(edit_queue.run());
     sender
 }
 
 #[cfg(feature = "flowy_unit_test")]
 impl DeltaDocumentEditor {
     pub async fn document_operations(&self) -> FlowyResult<DeltaTextOperations> {
-        let (ret, rx) = oneshot::channel::<CollaborateResult<DeltaTextOperations>>();
-        let msg = EditorCommand::GetOperations { ret };
-        let _ = self.edit_cmd_tx.send(msg).await;
-        let delta = rx.await.map_err(internal_error)??;
-        Ok(delta)
+        self.operations().await
     }
 
     pub fn rev_manager(&self) -> Arc<RevisionManager<Arc<ConnectionPool>>> {
@@ -245,20 +651,26 @@ impl DeltaDocumentEditor {
     }
 }
 
+/// Only the revisions that can't be composed or parsed make the document unreadable.
+fn unreadable_document(doc_id: &str, error: FlowyError) -> FlowyError {
+    FlowyError::document_unreadable().context(format!("Open the document:{} failed: {}", doc_id, error.msg))
+}
+
 pub struct DeltaDocumentRevisionSerde();
 impl RevisionObjectDeserializer for DeltaDocumentRevisionSerde {
     type Output = DocumentPayload;
 
     fn deserialize_revisions(object_id: &str, revisions: Vec<Revision>) -> FlowyResult<Self::Output> {
-        let (base_rev_id, rev_id) = revisions.last().unwrap().pair_rev_id();
-        let mut delta = make_operations_from_revisions(revisions)?;
-        correct_delta(&mut delta);
-
-        Result::<DocumentPayload, FlowyError>::Ok(DocumentPayload {
-            doc_id: object_id.to_owned(),
-            data: delta.json_bytes().to_vec(),
-            rev_id,
-            base_rev_id,
+        make_document_payload(object_id, revisions, |_, _| {})
+    }
+
+    fn deserialize_revisions_with_observer(
+        object_id: &str,
+        revisions: Vec<Revision>,
+        observer: &dyn ComposeErrorObserver,
+    ) -> FlowyResult<Self::Output> {
+        make_document_payload(object_id, revisions, |revision, error| {
+            observer.did_fail(revision.rev_id, &revision.bytes, &error.clone().into())
         })
     }
 
@@ -267,6 +679,23 @@ impl RevisionObjectDeserializer for DeltaDocumentRevisionSerde {
     }
 }
 
+fn make_document_payload<F>(object_id: &str, revisions: Vec<Revision>, on_error: F) -> FlowyResult<DocumentPayload>
+where
+    F: FnMut(&Revision, &CollaborateError),
+{
+    let (base_rev_id, rev_id) = revisions.last().unwrap().pair_rev_id();
+    let mut delta = make_operations_from_revisions_observed(revisions, on_error)
+        .map_err(|e| unreadable_document(object_id, e.into()))?;
+    correct_delta(&mut delta);
+
+    Result::<DocumentPayload, FlowyError>::Ok(DocumentPayload {
+        doc_id: object_id.to_owned(),
+        data: delta.json_bytes().to_vec(),
+        rev_id,
+        base_rev_id,
+    })
+}
+
 impl RevisionObjectSerializer for DeltaDocumentRevisionSerde {
     fn combine_revisions(revisions: Vec<Revision>) -> FlowyResult<Bytes> {
         let operations = make_operations_from_revisions::<AttributeHashMap>(revisions)?;
@@ -279,6 +708,24 @@ impl RevisionMergeable for DeltaDocumentRevisionMergeable {
     fn combine_revisions(&self, revisions: Vec<Revision>) -> FlowyResult<Bytes> {
         DeltaDocumentRevisionSerde::combine_revisions(revisions)
     }
+
+    fn combine_snapshot(&self, revisions: Vec<Revision>) -> FlowyResult<Bytes> {
+        let operations = make_operations_from_revisions::<AttributeHashMap>(revisions)?;
+        Ok(operations.normalized().json_bytes())
+    }
+
+    fn verify_revision(&self, base_revisions: Vec<Revision>, revision: &Revision) -> FlowyResult<()> {
+        let base = make_operations_from_revisions::<AttributeHashMap>(base_revisions)?;
+        let operations = DeltaTextOperations::from_bytes(&revision.bytes)?;
+        if operations.utf16_base_len != base.utf16_target_len {
+            return Err(FlowyError::invalid_data().context(format!(
+                "The revision {} expects a base of length {}, but its base {} has length {}",
+                revision.rev_id, operations.utf16_base_len, revision.base_rev_id, base.utf16_target_len
+            )));
+        }
+        let _ = base.compose(&operations)?;
+        Ok(())
+    }
 }
 
 // quill-editor requires the delta should end with '\n' and only contains the
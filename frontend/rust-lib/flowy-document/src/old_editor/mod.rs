@@ -1,4 +1,5 @@
 pub mod conflict;
 pub mod editor;
 pub mod queue;
+mod revalidate;
 mod web_socket;
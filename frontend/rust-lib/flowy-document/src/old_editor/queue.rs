@@ -1,18 +1,29 @@
+use crate::dart_notification::{send_dart_notification, DocumentNotification};
+use crate::entities::{DocumentChangePB, SelectionRangePB};
 use crate::old_editor::web_socket::DeltaDocumentResolveOperations;
-use crate::DocumentUser;
+use crate::services::{
+    document_meta, document_preview, rebase_unsynced_revisions, AttachmentObserver, ContentObserver, DocumentMeta,
+    DocumentPreview,
+};
+use crate::{DocumentUser, FetchOverwritePolicy, GuardDecision, RevisionGuards};
 use async_stream::stream;
+use bytes::Bytes;
 use flowy_database::ConnectionPool;
 use flowy_error::FlowyError;
-use flowy_revision::{RevisionMD5, RevisionManager, TransformOperations};
+use flowy_http_model::revision::Revision;
+use flowy_revision::{OperationsDeserializer, RevisionMD5, RevisionManager, TransformOperations};
+use flowy_revision_persistence::SyncRecord;
 use flowy_sync::{
-    client_document::{history::UndoResult, ClientDocument},
-    errors::CollaborateError,
+    client_document::{history::UndoResult, transform_selection, ClientDocument, DocumentCheckpoint},
+    errors::{CollaborateError, CollaborateResult},
+    util::{cal_diff, make_operations_from_revisions},
 };
 use futures::stream::StreamExt;
-use lib_ot::core::AttributeEntry;
+use lib_infra::chunked::ChunkedBuffer;
+use lib_ot::core::{AttributeEntry, AttributeHashMap};
 use lib_ot::{
     core::{Interval, OperationTransform},
-    text_delta::DeltaTextOperations,
+    text_delta::{content_hash, CustomAttributes, DeltaTextOperations},
 };
 use std::sync::Arc;
 use tokio::sync::mpsc::{Receiver, Sender};
@@ -25,21 +36,47 @@ pub(crate) struct EditDocumentQueue {
     #[allow(dead_code)]
     user: Arc<dyn DocumentUser>,
     rev_manager: Arc<RevisionManager<Arc<ConnectionPool>>>,
+    revision_guards: RevisionGuards,
+    /// The last selection of the UI, see `EditorCommand::UpdateSelection`.
+    selection: RwLock<Vec<Interval>>,
+    /// The `content_hash` of the document, None once the document changed. Hashing needs the
+    /// whole document, so it's computed again when it's read instead of on each change.
+    content_hash: RwLock<Option<String>>,
+    /// Set if the document is the custom dictionary of a workspace or the preferences of a
+    /// document.
+    content_observer: Option<ContentObserver>,
+    /// Set if the document can refer to attachments, the remote changes that do are reconciled.
+    attachment_observer: Option<AttachmentObserver>,
     receiver: Option<EditorCommandReceiver>,
 }
 
 impl EditDocumentQueue {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         user: Arc<dyn DocumentUser>,
         rev_manager: Arc<RevisionManager<Arc<ConnectionPool>>>,
         operations: DeltaTextOperations,
+        redact_logs: bool,
+        custom_attributes: CustomAttributes,
+        revision_guards: RevisionGuards,
+        content_observer: Option<ContentObserver>,
+        attachment_observer: Option<AttachmentObserver>,
         receiver: EditorCommandReceiver,
     ) -> Self {
-        let document = Arc::new(RwLock::new(ClientDocument::from_operations(operations)));
+        let content_hash = RwLock::new(None);
+        let mut document = ClientDocument::from_operations(operations);
+        document.set_redact_logs(redact_logs);
+        document.set_custom_attributes(custom_attributes);
+        let document = Arc::new(RwLock::new(document));
         Self {
             document,
             user,
             rev_manager,
+            revision_guards,
+            selection: RwLock::new(vec![]),
+            content_hash,
+            content_observer,
+            attachment_observer,
             receiver: Some(receiver),
         }
     }
@@ -69,26 +106,96 @@ impl EditDocumentQueue {
         match command {
             EditorCommand::ComposeLocalOperations { operations, ret } => {
                 let mut document = self.document.write().await;
+                if let Err(e) = document.validate_base(&operations) {
+                    let _ = ret.send(Err(e));
+                    return Ok(());
+                }
+                let checkpoint = document.checkpoint();
                 document.compose_operations(operations.clone())?;
-                let md5 = document.document_md5();
-                drop(document);
-                let _ = self.save_local_operations(operations, md5).await?;
-                let _ = ret.send(Ok(()));
+                let result = self
+                    .commit_local_operations(&mut document, checkpoint, operations)
+                    .await?;
+                let _ = ret.send(result.map(|_| ()));
+            }
+            EditorCommand::ComposeLocalOperationsIfUnchanged {
+                content_hash,
+                operations,
+                ret,
+            } => {
+                let mut document = self.document.write().await;
+                if self.current_content_hash(&document).await != content_hash {
+                    let _ = ret.send(Ok(None));
+                    return Ok(());
+                }
+                if let Err(e) = document.validate_base(&operations) {
+                    let _ = ret.send(Err(e));
+                    return Ok(());
+                }
+                let checkpoint = document.checkpoint();
+                document.compose_operations(operations.clone())?;
+                let result = self
+                    .commit_local_operations(&mut document, checkpoint, operations)
+                    .await?;
+                let _ = ret.send(result.map(Some));
             }
             EditorCommand::ComposeRemoteOperation { client_operations, ret } => {
                 let mut document = self.document.write().await;
-                document.compose_operations(client_operations.clone())?;
+                let client_operations = document.compose_remote_operations(client_operations)?;
                 let md5 = document.document_md5();
+                self.invalidate_content_hash().await;
+                self.did_receive_remote_change(&document, &client_operations);
                 drop(document);
+                let selection = {
+                    let mut selection = self.selection.write().await;
+                    *selection = transform_selection(&selection, &client_operations);
+                    selection.clone()
+                };
+                send_dart_notification(
+                    &self.rev_manager.object_id,
+                    DocumentNotification::DidReceiveRemoteChange,
+                )
+                .payload(DocumentChangePB {
+                    doc_id: self.rev_manager.object_id.clone(),
+                    operations: client_operations.json_str(),
+                    selection: selection.into_iter().map(SelectionRangePB::from).collect(),
+                })
+                .send();
                 let _ = ret.send(Ok(md5.into()));
             }
             EditorCommand::ResetOperations { operations, ret } => {
                 let mut document = self.document.write().await;
+                let operations = document.custom_attributes().sanitize_operations(&operations);
                 document.set_operations(operations);
                 let md5 = document.document_md5();
-                drop(document);
+                self.did_reset_operations(&document).await;
                 let _ = ret.send(Ok(md5.into()));
             }
+            EditorCommand::ApplyServerRevisions {
+                revisions,
+                policy,
+                only_if_newer,
+                ret,
+            } => {
+                let mut document = self.document.write().await;
+                let server_rev_id = revisions.last().map(|revision| revision.rev_id).unwrap_or(0);
+                if only_if_newer && server_rev_id <= self.rev_manager.rev_id() {
+                    let _ = ret.send(Ok(false));
+                    return Ok(());
+                }
+                let result = self
+                    .apply_server_revisions(&mut document, revisions, policy)
+                    .await
+                    .map_err(|e| CollaborateError::internal().context(e));
+                let _ = ret.send(result);
+            }
+            EditorCommand::MergeServerContent { revision, merge, ret } => {
+                let mut document = self.document.write().await;
+                let result = self
+                    .merge_server_content(&mut document, revision, merge)
+                    .await
+                    .map_err(|e| CollaborateError::internal().context(e));
+                let _ = ret.send(result);
+            }
             EditorCommand::TransformOperations { operations, ret } => {
                 let f = || async {
                     let read_guard = self.document.read().await;
@@ -113,17 +220,30 @@ impl EditDocumentQueue {
             }
             EditorCommand::Insert { index, data, ret } => {
                 let mut write_guard = self.document.write().await;
+                let checkpoint = write_guard.checkpoint();
                 let operations = write_guard.insert(index, data)?;
-                let md5 = write_guard.document_md5();
-                let _ = self.save_local_operations(operations, md5).await?;
-                let _ = ret.send(Ok(()));
+                let result = self
+                    .commit_local_operations(&mut write_guard, checkpoint, operations)
+                    .await?;
+                let _ = ret.send(result.map(|_| ()));
+            }
+            EditorCommand::InsertOperations { index, operations, ret } => {
+                let mut write_guard = self.document.write().await;
+                let checkpoint = write_guard.checkpoint();
+                let operations = write_guard.insert_operations(index, operations)?;
+                let result = self
+                    .commit_local_operations(&mut write_guard, checkpoint, operations)
+                    .await?;
+                let _ = ret.send(result.map(|_| ()));
             }
             EditorCommand::Delete { interval, ret } => {
                 let mut write_guard = self.document.write().await;
+                let checkpoint = write_guard.checkpoint();
                 let operations = write_guard.delete(interval)?;
-                let md5 = write_guard.document_md5();
-                let _ = self.save_local_operations(operations, md5).await?;
-                let _ = ret.send(Ok(()));
+                let result = self
+                    .commit_local_operations(&mut write_guard, checkpoint, operations)
+                    .await?;
+                let _ = ret.send(result.map(|_| ()));
             }
             EditorCommand::Format {
                 interval,
@@ -131,16 +251,63 @@ impl EditDocumentQueue {
                 ret,
             } => {
                 let mut write_guard = self.document.write().await;
+                let checkpoint = write_guard.checkpoint();
                 let operations = write_guard.format(interval, attribute)?;
-                let md5 = write_guard.document_md5();
-                let _ = self.save_local_operations(operations, md5).await?;
-                let _ = ret.send(Ok(()));
+                let result = self
+                    .commit_local_operations(&mut write_guard, checkpoint, operations)
+                    .await?;
+                let _ = ret.send(result.map(|_| ()));
             }
             EditorCommand::Replace { interval, data, ret } => {
                 let mut write_guard = self.document.write().await;
+                let checkpoint = write_guard.checkpoint();
                 let operations = write_guard.replace(interval, data)?;
-                let md5 = write_guard.document_md5();
-                let _ = self.save_local_operations(operations, md5).await?;
+                let result = self
+                    .commit_local_operations(&mut write_guard, checkpoint, operations)
+                    .await?;
+                let _ = ret.send(result.map(|_| ()));
+            }
+            EditorCommand::TrimLeading { len, ret } => {
+                let mut write_guard = self.document.write().await;
+                let checkpoint = write_guard.checkpoint();
+                let operations = write_guard.trim_leading(len)?;
+                let result = self
+                    .commit_local_operations(&mut write_guard, checkpoint, operations)
+                    .await?;
+                let _ = ret.send(result);
+            }
+            EditorCommand::SplitOff { index, new_doc_id, ret } => {
+                let mut write_guard = self.document.write().await;
+                let checkpoint = write_guard.checkpoint();
+                let rollback = write_guard.checkpoint();
+                let (operations, tail) = write_guard.split_off(index)?;
+                if operations.is_empty() {
+                    let _ = ret.send(Ok(Some(tail)));
+                } else {
+                    // The revision that removes the tail and the first revision of the new
+                    // document are saved in one transaction.
+                    let revision = Revision::initial_revision(&new_doc_id, Bytes::from(tail.json_bytes()));
+                    let records = vec![SyncRecord::new(revision)];
+                    match self
+                        .commit_local_operations_with_records(&mut write_guard, checkpoint, operations, records)
+                        .await
+                    {
+                        Ok(result) => {
+                            let _ = ret.send(result.map(|_| None));
+                        }
+                        Err(e) => {
+                            write_guard.restore(rollback);
+                            let _ = ret.send(Err(CollaborateError::internal().context(e)));
+                        }
+                    }
+                }
+            }
+            EditorCommand::BeginUndoGroup { ret } => {
+                self.document.write().await.begin_undo_group();
+                let _ = ret.send(Ok(()));
+            }
+            EditorCommand::EndUndoGroup { ret } => {
+                self.document.write().await.end_undo_group();
                 let _ = ret.send(Ok(()));
             }
             EditorCommand::CanUndo { ret } => {
@@ -151,30 +318,248 @@ impl EditDocumentQueue {
             }
             EditorCommand::Undo { ret } => {
                 let mut write_guard = self.document.write().await;
+                let checkpoint = write_guard.checkpoint();
                 let UndoResult { operations } = write_guard.undo()?;
-                let md5 = write_guard.document_md5();
-                let _ = self.save_local_operations(operations, md5).await?;
-                let _ = ret.send(Ok(()));
+                let result = self
+                    .commit_local_operations(&mut write_guard, checkpoint, operations)
+                    .await?;
+                let _ = ret.send(result.map(|_| ()));
             }
             EditorCommand::Redo { ret } => {
                 let mut write_guard = self.document.write().await;
+                let checkpoint = write_guard.checkpoint();
                 let UndoResult { operations } = write_guard.redo()?;
-                let md5 = write_guard.document_md5();
-                let _ = self.save_local_operations(operations, md5).await?;
-                let _ = ret.send(Ok(()));
+                let result = self
+                    .commit_local_operations(&mut write_guard, checkpoint, operations)
+                    .await?;
+                let _ = ret.send(result.map(|_| ()));
             }
             EditorCommand::GetOperationsString { ret } => {
                 let data = self.document.read().await.get_operations_json();
                 let _ = ret.send(Ok(data));
             }
+            EditorCommand::GetOperationsChunked { chunk_size, ret } => {
+                let mut buffer = ChunkedBuffer::new(chunk_size);
+                let result = serde_json::to_writer(&mut buffer, self.document.read().await.get_operations())
+                    .map(|_| buffer)
+                    .map_err(|e| CollaborateError::serde().context(e));
+                let _ = ret.send(result);
+            }
             EditorCommand::GetOperations { ret } => {
                 let operations = self.document.read().await.get_operations().clone();
                 let _ = ret.send(Ok(operations));
             }
+            EditorCommand::GetPreview { max_len, ret } => {
+                let preview = document_preview(self.document.read().await.get_operations(), max_len);
+                let _ = ret.send(Ok(preview));
+            }
+            EditorCommand::GetMeta { ret } => {
+                let next_sync_rev_id = self.rev_manager.next_sync_rev_id().await;
+                let document = self.document.read().await;
+                let meta = document_meta(document.get_operations(), self.rev_manager.rev_id(), next_sync_rev_id);
+                let _ = ret.send(Ok(meta));
+            }
+            EditorCommand::GetContentHash { ret } => {
+                let document = self.document.read().await;
+                let hash = self.current_content_hash(&document).await;
+                let _ = ret.send(Ok((self.rev_manager.rev_id(), hash)));
+            }
+            EditorCommand::UpdateSelection { selection, ret } => {
+                *self.selection.write().await = selection;
+                let _ = ret.send(Ok(()));
+            }
+            EditorCommand::GetSelection { ret } => {
+                let selection = self.selection.read().await.clone();
+                let _ = ret.send(Ok(selection));
+            }
+            EditorCommand::Pause { resume, ret } => {
+                let _ = ret.send(Ok(()));
+                // The dropped sender resumes the queue too.
+                let _ = resume.await;
+            }
         }
         Ok(())
     }
 
+    /// Saves the local change that was applied to the `document` since the `checkpoint` if the
+    /// revision guards allow it. The rejected change is discarded, and the redacted change is
+    /// applied in place of the original one. Returns the result that is sent back to the editor,
+    /// which is the rev_id of the saved revision if the change is not rejected.
+    async fn commit_local_operations(
+        &self,
+        document: &mut ClientDocument,
+        checkpoint: DocumentCheckpoint,
+        operations: DeltaTextOperations,
+    ) -> Result<CollaborateResult<i64>, FlowyError> {
+        self.commit_local_operations_with_records(document, checkpoint, operations, vec![])
+            .await
+    }
+
+    /// Same as `commit_local_operations`, but the revision is saved with the `records` of other
+    /// objects in one transaction if they're not empty.
+    async fn commit_local_operations_with_records(
+        &self,
+        document: &mut ClientDocument,
+        checkpoint: DocumentCheckpoint,
+        operations: DeltaTextOperations,
+        records: Vec<SyncRecord>,
+    ) -> Result<CollaborateResult<i64>, FlowyError> {
+        let decision = self
+            .revision_guards
+            .check(
+                &self.rev_manager.object_id,
+                checkpoint.operations(),
+                &operations,
+                self.rev_manager.executor(),
+            )
+            .await;
+        let operations = match decision {
+            GuardDecision::Allow => operations,
+            GuardDecision::Reject(reason) => {
+                document.restore(checkpoint);
+                return Ok(Err(CollaborateError::revision_rejected().context(reason)));
+            }
+            GuardDecision::Redact(redacted_operations) => {
+                document.restore(checkpoint);
+                document.compose_operations(redacted_operations.clone())?;
+                redacted_operations
+            }
+        };
+        self.invalidate_content_hash().await;
+        let md5 = document.document_md5();
+        let rev_id = if records.is_empty() {
+            self.save_local_operations(operations, md5).await?
+        } else {
+            self.rev_manager
+                .add_local_revision_with_records(operations.json_bytes(), md5, records)
+                .await?
+        };
+        Ok(Ok(rev_id))
+    }
+
+    /// Replaces the document with the server's `revisions`, the unsynced local revisions are
+    /// handled according to the `policy`. The document is locked until the revisions are saved,
+    /// so no local change slips in between. Returns true if the content of the document changed.
+    async fn apply_server_revisions(
+        &self,
+        document: &mut ClientDocument,
+        revisions: Vec<Revision>,
+        policy: FetchOverwritePolicy,
+    ) -> Result<bool, FlowyError> {
+        // The last revisions may still be waiting for the deferred save.
+        self.rev_manager.flush().await?;
+        let server_operations = DeltaDocumentResolveOperations::deserialize_revisions(revisions.clone())?.into_inner();
+        let server_operations = document.custom_attributes().sanitize_operations(&server_operations);
+        let md5 = document.document_md5();
+        match (self.rev_manager.next_sync_rev_id().await, policy) {
+            (Some(rev_id), FetchOverwritePolicy::Preserve) => {
+                tracing::warn!(
+                    "Keep document {} with the unsynced revision: {}, ignore the server's revisions",
+                    self.rev_manager.object_id,
+                    rev_id
+                );
+                return Ok(false);
+            }
+            (Some(rev_id), FetchOverwritePolicy::Merge) => {
+                let local_revisions = self.rev_manager.load_revisions().await?;
+                let rebased_operations = rebase_unsynced_revisions(local_revisions, rev_id, &server_operations)?;
+                let operations = server_operations.compose(&rebased_operations)?;
+                self.rev_manager.reset_object(revisions).await?;
+                document.set_operations(operations);
+                if !rebased_operations.is_noop() {
+                    self.save_local_operations(rebased_operations, document.document_md5())
+                        .await?;
+                }
+            }
+            (Some(_), FetchOverwritePolicy::Overwrite) | (None, _) => {
+                self.rev_manager.reset_object(revisions).await?;
+                document.set_operations(server_operations);
+            }
+        }
+        self.did_reset_operations(document).await;
+        Ok(document.document_md5() != md5)
+    }
+
+    /// Replaces the document with the server's `revision` and saves the content returned by
+    /// `merge` as a local revision on top of it. The content is merged from the content that was
+    /// synced last, the local content and the server's content. Returns true if the content of
+    /// the document changed.
+    async fn merge_server_content(
+        &self,
+        document: &mut ClientDocument,
+        revision: Revision,
+        merge: ContentMerge,
+    ) -> Result<bool, FlowyError> {
+        // The last revisions may still be waiting for the deferred save.
+        self.rev_manager.flush().await?;
+        let local_content = document.get_operations().content()?;
+        let base_content = match self.rev_manager.next_sync_rev_id().await {
+            None => local_content.clone(),
+            Some(rev_id) => {
+                let synced = self
+                    .rev_manager
+                    .load_revisions()
+                    .await?
+                    .into_iter()
+                    .filter(|revision| revision.rev_id < rev_id)
+                    .collect::<Vec<Revision>>();
+                let synced_operations: DeltaTextOperations = make_operations_from_revisions(synced)?;
+                synced_operations.content()?
+            }
+        };
+        let server_operations: DeltaTextOperations = make_operations_from_revisions(vec![revision.clone()])?;
+        let server_content = server_operations.content()?;
+        let merged_content = merge(&base_content, &local_content, &server_content);
+
+        self.rev_manager.reset_object(vec![revision]).await?;
+        document.set_operations(server_operations);
+        if let Some(operations) = cal_diff::<AttributeHashMap>(server_content, merged_content.clone()) {
+            document.compose_operations(operations.clone())?;
+            self.save_local_operations(operations, document.document_md5()).await?;
+        }
+        self.did_reset_operations(document).await;
+        Ok(merged_content != local_content)
+    }
+
+    /// Called after the document is replaced. The reset can't be transformed, the selection is
+    /// only kept inside the document.
+    async fn did_reset_operations(&self, document: &ClientDocument) {
+        self.invalidate_content_hash().await;
+        self.did_receive_remote_change(document, document.get_operations());
+        let len = document.get_operations().utf16_target_len;
+        self.selection.write().await.iter_mut().for_each(|range| {
+            range.start = range.start.min(len);
+            range.end = range.end.min(len);
+        });
+    }
+
+    async fn invalidate_content_hash(&self) {
+        *self.content_hash.write().await = None;
+    }
+
+    /// Returns the cached hash of the document, it's computed if the document changed since.
+    async fn current_content_hash(&self, document: &ClientDocument) -> String {
+        let mut cached = self.content_hash.write().await;
+        cached
+            .get_or_insert_with(|| content_hash(document.get_operations()))
+            .clone()
+    }
+
+    /// Saves the words of the custom dictionary or the preferences after the remote operations
+    /// are applied to them.
+    /// The `changes` are the remote operations, or the whole document when it was replaced.
+    fn did_receive_remote_change(&self, document: &ClientDocument, changes: &DeltaTextOperations) {
+        if let Some(observer) = self.attachment_observer.as_ref() {
+            observer.did_receive_remote_operations(changes);
+        }
+        if let Some(observer) = self.content_observer.as_ref() {
+            match document.get_operations().content() {
+                Ok(content) => observer.did_receive_remote_change(&content),
+                Err(e) => tracing::error!("Read the observed document failed: {:?}", e),
+            }
+        }
+    }
+
     async fn save_local_operations(&self, operations: DeltaTextOperations, md5: String) -> Result<i64, FlowyError> {
         let bytes = operations.json_bytes();
         let rev_id = self.rev_manager.add_local_revision(bytes, md5).await?;
@@ -186,12 +571,22 @@ pub type TextTransformOperations = TransformOperations<DeltaDocumentResolveOpera
 pub(crate) type EditorCommandSender = Sender<EditorCommand>;
 pub(crate) type EditorCommandReceiver = Receiver<EditorCommand>;
 pub(crate) type Ret<T> = oneshot::Sender<Result<T, CollaborateError>>;
+/// Merges the content that was synced last, the local content and the server's content, see
+/// `EditorCommand::MergeServerContent`.
+pub(crate) type ContentMerge = fn(&str, &str, &str) -> String;
 
 pub(crate) enum EditorCommand {
     ComposeLocalOperations {
         operations: DeltaTextOperations,
         ret: Ret<()>,
     },
+    /// Composes the operations only if the document still has the `content_hash`, returns the
+    /// rev_id of the saved revision or None if the document changed.
+    ComposeLocalOperationsIfUnchanged {
+        content_hash: String,
+        operations: DeltaTextOperations,
+        ret: Ret<Option<i64>>,
+    },
     ComposeRemoteOperation {
         client_operations: DeltaTextOperations,
         ret: Ret<RevisionMD5>,
@@ -200,6 +595,22 @@ pub(crate) enum EditorCommand {
         operations: DeltaTextOperations,
         ret: Ret<RevisionMD5>,
     },
+    /// Replaces the document and its revisions with the server's revisions, see
+    /// `FetchOverwritePolicy`. With `only_if_newer`, the revisions are ignored unless they're
+    /// newer than the document. Returns true if the content of the document changed.
+    ApplyServerRevisions {
+        revisions: Vec<Revision>,
+        policy: FetchOverwritePolicy,
+        only_if_newer: bool,
+        ret: Ret<bool>,
+    },
+    /// Replaces the document with the server's revision and saves the merged content on top of
+    /// it, see `ContentMerge`. Returns true if the content of the document changed.
+    MergeServerContent {
+        revision: Revision,
+        merge: ContentMerge,
+        ret: Ret<bool>,
+    },
     TransformOperations {
         operations: DeltaTextOperations,
         ret: Ret<TextTransformOperations>,
@@ -209,6 +620,11 @@ pub(crate) enum EditorCommand {
         data: String,
         ret: Ret<()>,
     },
+    InsertOperations {
+        index: usize,
+        operations: DeltaTextOperations,
+        ret: Ret<()>,
+    },
     Delete {
         interval: Interval,
         ret: Ret<()>,
@@ -223,6 +639,24 @@ pub(crate) enum EditorCommand {
         data: String,
         ret: Ret<()>,
     },
+    /// Deletes the first `len` of the document, returns the rev_id of the saved revision.
+    TrimLeading {
+        len: usize,
+        ret: Ret<i64>,
+    },
+    /// Removes the text from `index` to the end, returns the removed text and the operations
+    /// that put it back.
+    SplitOff {
+        index: usize,
+        new_doc_id: String,
+        ret: Ret<Option<DeltaTextOperations>>,
+    },
+    BeginUndoGroup {
+        ret: Ret<()>,
+    },
+    EndUndoGroup {
+        ret: Ret<()>,
+    },
     CanUndo {
         ret: oneshot::Sender<bool>,
     },
@@ -238,29 +672,74 @@ pub(crate) enum EditorCommand {
     GetOperationsString {
         ret: Ret<String>,
     },
-    #[allow(dead_code)]
+    /// Writes the same json as `GetOperationsString` into chunks of `chunk_size` bytes.
+    GetOperationsChunked {
+        chunk_size: usize,
+        ret: Ret<ChunkedBuffer>,
+    },
     GetOperations {
         ret: Ret<DeltaTextOperations>,
     },
+    /// Reads the preview from the document in place, the document isn't copied.
+    GetPreview {
+        max_len: usize,
+        ret: Ret<DocumentPreview>,
+    },
+    GetMeta {
+        ret: Ret<DocumentMeta>,
+    },
+    /// Returns the rev_id of the document with its `content_hash`.
+    GetContentHash {
+        ret: Ret<(i64, String)>,
+    },
+    /// Replaces the selection that is transformed by the remote operations.
+    UpdateSelection {
+        selection: Vec<Interval>,
+        ret: Ret<()>,
+    },
+    GetSelection {
+        ret: Ret<Vec<Interval>>,
+    },
+    /// Holds the queue after the commands sent before it until the `resume` is sent or dropped,
+    /// see `DocumentPause`.
+    Pause {
+        resume: oneshot::Receiver<()>,
+        ret: Ret<()>,
+    },
 }
 
 impl std::fmt::Debug for EditorCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let s = match self {
             EditorCommand::ComposeLocalOperations { .. } => "ComposeLocalOperations",
+            EditorCommand::ComposeLocalOperationsIfUnchanged { .. } => "ComposeLocalOperationsIfUnchanged",
             EditorCommand::ComposeRemoteOperation { .. } => "ComposeRemoteOperation",
             EditorCommand::ResetOperations { .. } => "ResetOperations",
+            EditorCommand::ApplyServerRevisions { .. } => "ApplyServerRevisions",
+            EditorCommand::MergeServerContent { .. } => "MergeServerContent",
             EditorCommand::TransformOperations { .. } => "TransformOperations",
             EditorCommand::Insert { .. } => "Insert",
+            EditorCommand::InsertOperations { .. } => "InsertOperations",
             EditorCommand::Delete { .. } => "Delete",
             EditorCommand::Format { .. } => "Format",
             EditorCommand::Replace { .. } => "Replace",
+            EditorCommand::TrimLeading { .. } => "TrimLeading",
+            EditorCommand::SplitOff { .. } => "SplitOff",
+            EditorCommand::BeginUndoGroup { .. } => "BeginUndoGroup",
+            EditorCommand::EndUndoGroup { .. } => "EndUndoGroup",
             EditorCommand::CanUndo { .. } => "CanUndo",
             EditorCommand::CanRedo { .. } => "CanRedo",
             EditorCommand::Undo { .. } => "Undo",
             EditorCommand::Redo { .. } => "Redo",
             EditorCommand::GetOperationsString { .. } => "StringifyOperations",
+            EditorCommand::GetOperationsChunked { .. } => "GetOperationsChunked",
             EditorCommand::GetOperations { .. } => "ReadOperations",
+            EditorCommand::GetPreview { .. } => "GetPreview",
+            EditorCommand::GetMeta { .. } => "GetMeta",
+            EditorCommand::GetContentHash { .. } => "GetContentHash",
+            EditorCommand::UpdateSelection { .. } => "UpdateSelection",
+            EditorCommand::GetSelection { .. } => "GetSelection",
+            EditorCommand::Pause { .. } => "Pause",
         };
         f.write_str(s)
     }
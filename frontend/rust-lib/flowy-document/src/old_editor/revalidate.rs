@@ -0,0 +1,136 @@
+use crate::dart_notification::{send_dart_notification, DocumentNotification};
+use crate::old_editor::queue::{ContentMerge, EditorCommand, EditorCommandSender};
+use crate::{DocumentCloudService, FetchOverwritePolicy};
+use bytes::Bytes;
+use flowy_database::ConnectionPool;
+use flowy_error::{internal_error, FlowyResult};
+use flowy_http_model::document::ResetDocumentParams;
+use flowy_http_model::revision::Revision;
+use flowy_http_model::util::md5;
+use flowy_revision::{RevisionCloudService, RevisionManager};
+use flowy_sync::errors::CollaborateResult;
+use flowy_sync::util::make_operations_from_revisions;
+use lib_ot::text_delta::DeltaTextOperations;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Reconciles the opened document with the server in the background, the document was opened
+/// with its local revisions. The returned task gets aborted when the document is closed.
+pub(crate) fn spawn_revalidation(
+    doc_id: String,
+    user_id: String,
+    edit_cmd_tx: EditorCommandSender,
+    rev_manager: Arc<RevisionManager<Arc<ConnectionPool>>>,
+    cloud_service: Arc<dyn RevisionCloudService>,
+    policy: FetchOverwritePolicy,
+) -> JoinHandle<()> {
+    let executor = rev_manager.executor().clone();
+    executor.spawn(async move {
+        match revalidate_document(&doc_id, &user_id, edit_cmd_tx, cloud_service, policy).await {
+            Ok(true) => send_dart_notification(&doc_id, DocumentNotification::DidRefreshDocument).send(),
+            Ok(false) => {}
+            Err(e) => tracing::error!("Revalidate document {} failed: {:?}", doc_id, e),
+        }
+    })
+}
+
+/// Returns true if the document was replaced with the newer content of the server.
+///
+/// The server only provides the latest content of the document, so it's applied the same way
+/// as the revisions pushed by the server that override the local document. The unsynced local
+/// revisions are handled according to the `policy`.
+async fn revalidate_document(
+    doc_id: &str,
+    user_id: &str,
+    edit_cmd_tx: EditorCommandSender,
+    cloud_service: Arc<dyn RevisionCloudService>,
+    policy: FetchOverwritePolicy,
+) -> FlowyResult<bool> {
+    // The server only provides the whole document, so it's fetched once and the edit queue
+    // tells whether it's newer than the opened document.
+    let revisions = cloud_service.fetch_object(user_id, doc_id).await?;
+    let server_rev_id = revisions.last().map(|revision| revision.rev_id).unwrap_or(0);
+    if !apply_server_revisions(&edit_cmd_tx, revisions, policy, true).await? {
+        return Ok(false);
+    }
+    tracing::trace!(
+        "Refresh document {} with the server's revision: {}",
+        doc_id,
+        server_rev_id
+    );
+    Ok(true)
+}
+
+/// Replaces the document with the server's `revisions` in one command of the edit queue, see
+/// `EditorCommand::ApplyServerRevisions`. Returns true if the content of the document changed.
+async fn apply_server_revisions(
+    edit_cmd_tx: &EditorCommandSender,
+    revisions: Vec<Revision>,
+    policy: FetchOverwritePolicy,
+    only_if_newer: bool,
+) -> FlowyResult<bool> {
+    let (ret, rx) = oneshot::channel::<CollaborateResult<bool>>();
+    let msg = EditorCommand::ApplyServerRevisions {
+        revisions,
+        policy,
+        only_if_newer,
+        ret,
+    };
+    edit_cmd_tx.send(msg).await.map_err(internal_error)?;
+    let is_changed = rx.await.map_err(internal_error)??;
+    Ok(is_changed)
+}
+
+/// Syncs the document with the content of the server without the web socket, e.g. the custom
+/// dictionary that is synced when the editor starts. The content is merged with `merge` if it's
+/// given, otherwise the unsynced local changes are rebased onto the server's document. Then the
+/// merged document is uploaded and the local revisions are marked as synced. Returns true if
+/// the server's changes were applied.
+pub(crate) async fn sync_document_content(
+    doc_id: &str,
+    token: &str,
+    edit_cmd_tx: EditorCommandSender,
+    rev_manager: Arc<RevisionManager<Arc<ConnectionPool>>>,
+    cloud_service: Arc<dyn DocumentCloudService>,
+    merge: Option<ContentMerge>,
+) -> FlowyResult<bool> {
+    rev_manager.flush().await?;
+    let mut is_changed = false;
+    if let Some(payload) = cloud_service.fetch_document(token, doc_id.to_owned().into()).await? {
+        let bytes = Bytes::from(payload.data);
+        let doc_md5 = md5(&bytes);
+        let server_revision = Revision::new(doc_id, payload.base_rev_id, payload.rev_id, bytes, doc_md5);
+        is_changed = match merge {
+            None => {
+                apply_server_revisions(&edit_cmd_tx, vec![server_revision], FetchOverwritePolicy::Merge, false).await?
+            }
+            Some(merge) => {
+                let (ret, rx) = oneshot::channel::<CollaborateResult<bool>>();
+                let msg = EditorCommand::MergeServerContent {
+                    revision: server_revision,
+                    merge,
+                    ret,
+                };
+                edit_cmd_tx.send(msg).await.map_err(internal_error)?;
+                rx.await.map_err(internal_error)??
+            }
+        };
+    }
+
+    if let Some(next_sync_rev_id) = rev_manager.next_sync_rev_id().await {
+        let rev_id = rev_manager.rev_id();
+        let operations: DeltaTextOperations = make_operations_from_revisions(rev_manager.load_revisions().await?)?;
+        let bytes = operations.json_bytes();
+        let doc_md5 = md5(&bytes);
+        let params = ResetDocumentParams {
+            doc_id: doc_id.to_owned(),
+            revisions: vec![Revision::new(doc_id, rev_id - 1, rev_id, bytes, doc_md5)],
+        };
+        cloud_service.update_document_content(token, params).await?;
+        for rev_id in next_sync_rev_id..=rev_id {
+            rev_manager.ack_revision(rev_id).await?;
+        }
+    }
+    Ok(is_changed)
+}
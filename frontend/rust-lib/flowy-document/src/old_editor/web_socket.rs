@@ -1,3 +1,4 @@
+use crate::dart_notification::{send_dart_notification, DocumentNotification};
 use crate::old_editor::queue::{EditorCommand, EditorCommandSender, TextTransformOperations};
 use crate::TEXT_BLOCK_SYNC_INTERVAL_IN_MILLIS;
 use bytes::Bytes;
@@ -46,12 +47,19 @@ pub(crate) async fn make_document_ws_manager(
     edit_cmd_tx: EditorCommandSender,
     rev_manager: Arc<RevisionManager<Arc<ConnectionPool>>>,
     rev_web_socket: Arc<dyn RevisionWebSocket>,
-) -> Arc<RevisionWebSocketManager> {
-    let ws_data_provider = Arc::new(WSDataProvider::new(&doc_id, Arc::new(rev_manager.clone())));
+    overwrite_policy: FetchOverwritePolicy,
+) -> (Arc<RevisionWebSocketManager>, Arc<DocumentConflictController>) {
+    let executor = rev_manager.executor().clone();
+    let error_reporter = rev_manager.error_reporter();
+    let ws_data_provider =
+        Arc::new(WSDataProvider::new(&doc_id, Arc::new(rev_manager.clone())).with_session(WSSession::default()));
     let resolver = Arc::new(DocumentConflictResolver { edit_cmd_tx });
-    let conflict_controller =
-        DocumentConflictController::new(&user_id, resolver, Arc::new(ws_data_provider.clone()), rev_manager);
-    let ws_data_stream = Arc::new(DocumentRevisionWSDataStream::new(conflict_controller));
+    let conflict_controller = Arc::new(
+        DocumentConflictController::new(&user_id, resolver, Arc::new(ws_data_provider.clone()), rev_manager)
+            .with_sync_loop_observer(Arc::new(DocumentSyncLoopObserver()))
+            .with_overwrite_policy(overwrite_policy),
+    );
+    let ws_data_stream = Arc::new(DocumentRevisionWSDataStream::new(conflict_controller.clone()));
     let ws_data_sink = Arc::new(DocumentWSDataSink(ws_data_provider));
     let ping_duration = Duration::from_millis(TEXT_BLOCK_SYNC_INTERVAL_IN_MILLIS);
     let ws_manager = Arc::new(RevisionWebSocketManager::new(
@@ -61,14 +69,29 @@ pub(crate) async fn make_document_ws_manager(
         ws_data_sink,
         ws_data_stream,
         ping_duration,
+        error_reporter,
+        executor.clone(),
     ));
-    listen_document_ws_state(&user_id, &doc_id, ws_manager.scribe_state());
-    ws_manager
+    listen_document_ws_state(&user_id, &doc_id, ws_manager.scribe_state(), &executor);
+    (ws_manager, conflict_controller)
+}
+
+/// Tells the UI that the document stopped syncing, the user resumes it or resets the document.
+struct DocumentSyncLoopObserver();
+impl SyncLoopObserver for DocumentSyncLoopObserver {
+    fn did_detect_sync_loop(&self, object_id: &str, _md5: &str) {
+        send_dart_notification(object_id, DocumentNotification::SyncLoopDetected).send();
+    }
 }
 
 #[allow(dead_code)]
-fn listen_document_ws_state(_user_id: &str, _doc_id: &str, mut subscriber: broadcast::Receiver<WSConnectState>) {
-    tokio::spawn(async move {
+fn listen_document_ws_state(
+    _user_id: &str,
+    _doc_id: &str,
+    mut subscriber: broadcast::Receiver<WSConnectState>,
+    executor: &Executor,
+) {
+    executor.spawn(async move {
         while let Ok(state) = subscriber.recv().await {
             match state {
                 WSConnectState::Init => {}
@@ -86,10 +109,8 @@ pub(crate) struct DocumentRevisionWSDataStream {
 
 impl DocumentRevisionWSDataStream {
     #[allow(dead_code)]
-    pub fn new(conflict_controller: DocumentConflictController) -> Self {
-        Self {
-            conflict_controller: Arc::new(conflict_controller),
-        }
+    pub fn new(conflict_controller: Arc<DocumentConflictController>) -> Self {
+        Self { conflict_controller }
     }
 }
 
@@ -121,10 +142,24 @@ impl RevisionWebSocketSink for DocumentWSDataSink {
         let sink_provider = self.0.clone();
         FutureResult::new(async move { sink_provider.next().await })
     }
+
+    fn did_send(&self, data: &ClientRevisionWSData) -> FutureResult<(), FlowyError> {
+        let sink_provider = self.0.clone();
+        let data = data.clone();
+        FutureResult::new(async move { sink_provider.did_send(&data).await })
+    }
+
+    fn did_fail_to_send(&self, data: &ClientRevisionWSData, error: &FlowyError) {
+        self.0.did_fail_to_send(data, error);
+    }
+
+    fn session(&self) -> Option<WSSession> {
+        self.0.session()
+    }
 }
 
-struct DocumentConflictResolver {
-    edit_cmd_tx: EditorCommandSender,
+pub(crate) struct DocumentConflictResolver {
+    pub(crate) edit_cmd_tx: EditorCommandSender,
 }
 
 impl ConflictResolver<DeltaDocumentResolveOperations> for DocumentConflictResolver {
@@ -0,0 +1,277 @@
+use flowy_revision::Executor;
+use lib_ot::text_delta::DeltaTextOperations;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_REVISION_GUARD_TIME_BUDGET: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuardDecision {
+    Allow,
+    /// Drops the change, the edit fails with `ErrorCode::RevisionRejected`.
+    Reject(String),
+    /// Saves the returned operations instead of the change. The operations must apply to the
+    /// same document as the change.
+    Redact(DeltaTextOperations),
+}
+
+/// Checks the local changes of the delta documents before they are saved as revisions, which
+/// keeps the content that must not be persisted or synced out of the document.
+pub trait RevisionGuard: Send + Sync {
+    /// The `operations` are the change and the `document` is the document before it, the text
+    /// that the change completes is in the document composed with the change.
+    fn check(&self, doc_id: &str, document: &DeltaTextOperations, operations: &DeltaTextOperations) -> GuardDecision;
+}
+
+/// The guards registered with the `DocumentConfig`. They run in the order of registration
+/// and each of them checks the operations redacted by the previous ones.
+#[derive(Clone)]
+pub struct RevisionGuards {
+    guards: Vec<Arc<dyn RevisionGuard>>,
+    time_budget: Duration,
+    fail_open: bool,
+}
+
+impl std::default::Default for RevisionGuards {
+    fn default() -> Self {
+        Self::new(DEFAULT_REVISION_GUARD_TIME_BUDGET, false)
+    }
+}
+
+impl Debug for RevisionGuards {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RevisionGuards")
+            .field("num_of_guards", &self.guards.len())
+            .field("time_budget", &self.time_budget)
+            .field("fail_open", &self.fail_open)
+            .finish()
+    }
+}
+
+impl RevisionGuards {
+    /// # Arguments
+    ///
+    /// * `time_budget`: the time that all the guards have to check one change.
+    /// * `fail_open`: allows the change if the guards exceed the time budget or panic. The
+    /// change is rejected otherwise.
+    ///
+    pub fn new(time_budget: Duration, fail_open: bool) -> Self {
+        Self {
+            guards: vec![],
+            time_budget,
+            fail_open,
+        }
+    }
+
+    pub fn with_guard(mut self, guard: Arc<dyn RevisionGuard>) -> Self {
+        self.guards.push(guard);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.guards.is_empty()
+    }
+
+    /// Runs the guards on the blocking threads of the `executor`. The guards that exceed the
+    /// time budget can't be interrupted, they keep running but their decision is ignored.
+    pub(crate) async fn check(
+        &self,
+        doc_id: &str,
+        document: &DeltaTextOperations,
+        operations: &DeltaTextOperations,
+        executor: &Executor,
+    ) -> GuardDecision {
+        if self.guards.is_empty() {
+            return GuardDecision::Allow;
+        }
+
+        let guards = self.guards.clone();
+        let object_id = doc_id.to_owned();
+        let document = document.clone();
+        let operations = operations.clone();
+        let task = executor.spawn_blocking(move || check_with_guards(&guards, &object_id, &document, operations));
+        match tokio::time::timeout(self.time_budget, task).await {
+            Ok(Ok(decision)) => decision,
+            Ok(Err(e)) => {
+                tracing::error!("Revision guard of {} failed: {:?}", doc_id, e);
+                self.decision_on_failure("The revision guard failed".to_owned())
+            }
+            Err(_) => {
+                tracing::warn!("Revision guards of {} exceeded {:?}", doc_id, self.time_budget);
+                self.decision_on_failure(format!("The revision guards exceeded {:?}", self.time_budget))
+            }
+        }
+    }
+
+    fn decision_on_failure(&self, reason: String) -> GuardDecision {
+        if self.fail_open {
+            GuardDecision::Allow
+        } else {
+            GuardDecision::Reject(reason)
+        }
+    }
+}
+
+fn check_with_guards(
+    guards: &[Arc<dyn RevisionGuard>],
+    doc_id: &str,
+    document: &DeltaTextOperations,
+    operations: DeltaTextOperations,
+) -> GuardDecision {
+    let mut redacted: Option<DeltaTextOperations> = None;
+    for guard in guards {
+        match guard.check(doc_id, document, redacted.as_ref().unwrap_or(&operations)) {
+            GuardDecision::Allow => {}
+            GuardDecision::Reject(reason) => return GuardDecision::Reject(reason),
+            GuardDecision::Redact(operations) => redacted = Some(operations),
+        }
+    }
+    match redacted {
+        None => GuardDecision::Allow,
+        Some(operations) => GuardDecision::Redact(operations),
+    }
+}
+
+/// Rejects or masks the text that matches the pattern, e.g. the credit card numbers. The
+/// pattern is matched in the lines that the change touches after it's applied, so the text
+/// typed one character at a time or pasted next to its other part is caught once it's whole.
+#[cfg(feature = "regex_guard")]
+pub struct RegexRevisionGuard {
+    pattern: regex::Regex,
+    redact: bool,
+}
+
+#[cfg(feature = "regex_guard")]
+impl RegexRevisionGuard {
+    /// Rejects the changes that complete a text matching the `pattern`.
+    pub fn reject(pattern: regex::Regex) -> Self {
+        Self { pattern, redact: false }
+    }
+
+    /// Replaces each character of the matched text with `*`, including the part of it that
+    /// was already in the document.
+    pub fn redact(pattern: regex::Regex) -> Self {
+        Self { pattern, redact: true }
+    }
+
+    /// Matches 13 to 16 digits that may be separated by spaces or dashes.
+    pub fn credit_card_pattern() -> regex::Regex {
+        regex::Regex::new(r"\b(?:\d[ -]?){12,15}\d\b").unwrap()
+    }
+
+    /// Returns the utf16 ranges of the matches in the lines of the `text` that the `edits`
+    /// touch. A match counts if an edit is in it or next to it.
+    fn matches_around_edits(&self, text: &str, edits: &[std::ops::Range<usize>]) -> Vec<std::ops::Range<usize>> {
+        let mut lines: Vec<std::ops::Range<usize>> = edits
+            .iter()
+            .map(|edit| {
+                let start = byte_offset(text, edit.start);
+                let end = byte_offset(text, edit.end);
+                let line_start = text[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+                let line_end = text[end..].find('\n').map(|i| end + i).unwrap_or(text.len());
+                line_start..line_end
+            })
+            .collect();
+        lines.sort_by_key(|line| line.start);
+        lines.dedup_by(|next, line| {
+            if next.start <= line.end {
+                line.end = line.end.max(next.end);
+                true
+            } else {
+                false
+            }
+        });
+
+        let mut matches = vec![];
+        for line in lines {
+            for found in self.pattern.find_iter(&text[line.clone()]) {
+                let start = utf16_offset(text, line.start + found.start());
+                let end = utf16_offset(text, line.start + found.end());
+                if edits.iter().any(|edit| start <= edit.end && edit.start <= end) {
+                    matches.push(start..end);
+                }
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(feature = "regex_guard")]
+impl RevisionGuard for RegexRevisionGuard {
+    fn check(&self, _doc_id: &str, document: &DeltaTextOperations, operations: &DeltaTextOperations) -> GuardDecision {
+        use lib_ot::core::{DeltaOperation, OperationTransform};
+        use lib_ot::text_delta::DeltaTextOperationBuilder;
+
+        let composed = match document.compose(operations) {
+            Ok(composed) => composed,
+            Err(e) => return GuardDecision::Reject(format!("The change doesn't apply to the document: {}", e)),
+        };
+        // The ranges of the composed document that the change inserted, or where it deleted.
+        let mut edits = vec![];
+        let mut offset = 0;
+        for op in operations.ops.iter() {
+            match op {
+                DeltaOperation::Retain(retain) => offset += retain.n,
+                DeltaOperation::Insert(insert) => {
+                    edits.push(offset..offset + insert.s.utf16_len());
+                    offset += insert.s.utf16_len();
+                }
+                DeltaOperation::Delete(_) => edits.push(offset..offset),
+            }
+        }
+        let text = composed
+            .ops
+            .iter()
+            .map(|op| op.get_data())
+            .collect::<Vec<&str>>()
+            .concat();
+        let matches = self.matches_around_edits(&text, &edits);
+        if matches.is_empty() {
+            return GuardDecision::Allow;
+        }
+        if !self.redact {
+            return GuardDecision::Reject(format!("The text matches {}", self.pattern));
+        }
+
+        // Masks the matches of the composed document, keeping the attributes of each part.
+        let mut builder = DeltaTextOperationBuilder::new();
+        let mut cursor = 0;
+        for found in matches.iter() {
+            builder = builder.retain(found.start - cursor);
+            let mut op_start = 0;
+            for op in composed.ops.iter() {
+                let op_end = op_start + op.len();
+                let start = op_start.max(found.start);
+                let end = op_end.min(found.end);
+                if start < end {
+                    builder = builder.insert_with_attributes(&"*".repeat(end - start), op.get_attributes());
+                }
+                op_start = op_end;
+            }
+            builder = builder.delete(found.len());
+            cursor = found.end;
+        }
+        match operations.compose(&builder.build()) {
+            Ok(redacted) => GuardDecision::Redact(redacted),
+            Err(e) => GuardDecision::Reject(format!("Redact the change failed: {}", e)),
+        }
+    }
+}
+
+#[cfg(feature = "regex_guard")]
+fn byte_offset(text: &str, utf16_offset: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in text.char_indices() {
+        if units >= utf16_offset {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+#[cfg(feature = "regex_guard")]
+fn utf16_offset(text: &str, byte_offset: usize) -> usize {
+    text[..byte_offset].encode_utf16().count()
+}
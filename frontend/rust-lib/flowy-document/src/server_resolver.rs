@@ -0,0 +1,90 @@
+use crate::DocumentCloudService;
+use dashmap::DashMap;
+use flowy_revision::RevisionWebSocket;
+use std::sync::Arc;
+
+/// The endpoint a document syncs with: the http service used to fetch the document
+/// and the web socket used to push/receive its revisions.
+#[derive(Clone)]
+pub struct DocumentServer {
+    /// Identifies the endpoint. Documents that resolve to the same endpoint share
+    /// the same web socket connection.
+    pub endpoint: String,
+    pub cloud_service: Arc<dyn DocumentCloudService>,
+    pub web_socket: Arc<dyn RevisionWebSocket>,
+}
+
+impl DocumentServer {
+    pub fn new(
+        endpoint: &str,
+        cloud_service: Arc<dyn DocumentCloudService>,
+        web_socket: Arc<dyn RevisionWebSocket>,
+    ) -> Self {
+        Self {
+            endpoint: endpoint.to_owned(),
+            cloud_service,
+            web_socket,
+        }
+    }
+}
+
+/// Resolves which server a document should sync with. It gets consulted when
+/// the document's editor is opened and when the web socket connection state of
+/// an endpoint changes.
+pub trait DocumentServerResolver: Send + Sync {
+    fn resolve(&self, doc_id: &str) -> DocumentServer;
+
+    /// Returns all the distinct endpoints the resolver knows about.
+    fn servers(&self) -> Vec<DocumentServer>;
+
+    /// Resolves the document to the `server` from now on, see `DocumentManager::set_document_server`.
+    fn set_server(&self, doc_id: &str, server: DocumentServer);
+
+    /// Removes the server set by `set_server`, the document resolves as before it was set.
+    fn remove_server(&self, doc_id: &str) -> Option<DocumentServer>;
+}
+
+pub const DEFAULT_DOCUMENT_ENDPOINT: &str = "default";
+
+/// Resolves every document to the default server unless an override was set for
+/// it, e.g. a document shared from another workspace that lives on a different server.
+pub struct DocumentServerTable {
+    default_server: DocumentServer,
+    overrides: DashMap<String, DocumentServer>,
+}
+
+impl DocumentServerTable {
+    pub fn new(default_server: DocumentServer) -> Self {
+        Self {
+            default_server,
+            overrides: DashMap::new(),
+        }
+    }
+}
+
+impl DocumentServerResolver for DocumentServerTable {
+    fn resolve(&self, doc_id: &str) -> DocumentServer {
+        match self.overrides.get(doc_id) {
+            None => self.default_server.clone(),
+            Some(server) => server.value().clone(),
+        }
+    }
+
+    fn servers(&self) -> Vec<DocumentServer> {
+        let mut servers = vec![self.default_server.clone()];
+        self.overrides.iter().for_each(|entry| {
+            if servers.iter().all(|server| server.endpoint != entry.value().endpoint) {
+                servers.push(entry.value().clone());
+            }
+        });
+        servers
+    }
+
+    fn set_server(&self, doc_id: &str, server: DocumentServer) {
+        self.overrides.insert(doc_id.to_owned(), server);
+    }
+
+    fn remove_server(&self, doc_id: &str) -> Option<DocumentServer> {
+        self.overrides.remove(doc_id).map(|(_, server)| server)
+    }
+}
@@ -0,0 +1,542 @@
+use crate::dart_notification::{send_dart_notification, DocumentNotification};
+use crate::services::rev_sqlite::{map_read_error, DeltaRevisionSql, SQLiteDeltaDocumentRevisionPersistence};
+use crate::DocumentCloudService;
+use flowy_database::{
+    dsl::sql,
+    prelude::*,
+    schema::{document_attachment, document_attachment::dsl},
+    sql_types::BigInt,
+    ConnectionPool,
+};
+use flowy_error::{ErrorCode, FlowyError, FlowyResult};
+use flowy_http_model::util::md5;
+use flowy_revision::{composable_revisions, Executor};
+use flowy_revision_persistence::RevisionDiskCache;
+use flowy_sync::util::make_operations_from_revisions;
+use lib_infra::util::timestamp;
+use lib_ot::core::AttributeHashMap;
+use lib_ot::text_delta::{attach_links, attachment_ids, DeltaTextOperations};
+use nanoid::nanoid;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// An attachment that no document refers to is kept this long before it's deleted, the
+/// revisions that refer to it again may still be on their way from another device.
+pub const DEFAULT_ATTACHMENT_GRACE_PERIOD: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// The documents refer to the attachment by this id, see `BuildInTextAttribute::Attachment`.
+    /// Renaming or re-uploading the attachment keeps its id, so the references stay valid.
+    pub id: String,
+    pub name: String,
+    pub data: Vec<u8>,
+    /// The time of the last upload or rename. When the attachment was changed on two devices,
+    /// the newer version is kept, see `AttachmentVersion`.
+    pub update_time: i64,
+}
+
+/// Tells which version of an attachment is kept when it was changed on two devices: the newer
+/// one, or the one with the greater digest if both were changed in the same second. Every device
+/// and the server keep the same version, so the renames made offline converge.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AttachmentVersion {
+    pub update_time: i64,
+    /// The md5 of the name and the data.
+    pub digest: String,
+}
+
+impl Attachment {
+    pub fn new(name: &str, data: Vec<u8>) -> Self {
+        Self {
+            id: nanoid!(10),
+            name: name.to_owned(),
+            data,
+            update_time: timestamp(),
+        }
+    }
+
+    pub fn version(&self) -> AttachmentVersion {
+        AttachmentVersion {
+            update_time: self.update_time,
+            digest: attachment_digest(&self.name, &self.data),
+        }
+    }
+}
+
+fn attachment_digest(name: &str, data: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(name.len() + 1 + data.len());
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(data);
+    md5(bytes)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttachmentReconcileSummary {
+    /// The attachments that at least one document refers to.
+    pub num_of_referenced: usize,
+    /// The links to the local files that were moved to attachments, see `attach_links`.
+    pub num_of_attached_links: usize,
+    /// The referenced attachments that are missing or older than the server's, they're
+    /// downloaded in the background.
+    pub downloads: Vec<String>,
+    /// The referenced attachments that the server doesn't have or has an older version of,
+    /// they're uploaded in the background.
+    pub uploads: Vec<String>,
+    /// The referenced attachments that neither this device nor the server has. The next
+    /// reconciliation looks for them again.
+    pub missing: Vec<String>,
+    /// The attachments deleted because no document referred to them for the grace period.
+    pub num_of_collected: usize,
+}
+
+/// Keeps the attachments of the delta documents in the `document_attachment` table. The
+/// documents only keep the ids of the attachments, the name and the data are read from here
+/// when the document is rendered or exported.
+#[derive(Clone)]
+pub struct AttachmentStore {
+    pool: Arc<ConnectionPool>,
+}
+
+impl AttachmentStore {
+    pub(crate) fn new(pool: Arc<ConnectionPool>) -> Self {
+        Self { pool }
+    }
+
+    /// Saves a new attachment. Insert a reference with its id to attach it to a document.
+    pub fn upload(&self, name: &str, data: Vec<u8>) -> FlowyResult<Attachment> {
+        let attachment = Attachment::new(name, data);
+        let conn = self.pool.get()?;
+        AttachmentSql::save(&attachment, &conn)?;
+        Ok(attachment)
+    }
+
+    /// Replaces the data of the attachment, the documents that refer to it get the new data.
+    pub fn reupload(&self, attachment_id: &str, data: Vec<u8>) -> FlowyResult<Attachment> {
+        self.update(attachment_id, |attachment| attachment.data = data)
+    }
+
+    pub fn rename(&self, attachment_id: &str, name: &str) -> FlowyResult<Attachment> {
+        self.update(attachment_id, |attachment| attachment.name = name.to_owned())
+    }
+
+    pub fn read(&self, attachment_id: &str) -> FlowyResult<Option<Attachment>> {
+        let conn = self.pool.get()?;
+        AttachmentSql::read(attachment_id, &conn)
+    }
+
+    /// Returns the version of the attachment, None if it's not in the store.
+    pub fn read_version(&self, attachment_id: &str) -> FlowyResult<Option<AttachmentVersion>> {
+        let conn = self.pool.get()?;
+        AttachmentSql::read_version(attachment_id, &conn)
+    }
+
+    /// Saves the attachment received from the server or another database, unless the local
+    /// version is the same or newer, see `AttachmentVersion`. Returns true if it was saved.
+    pub fn merge(&self, attachment: &Attachment) -> FlowyResult<bool> {
+        let conn = self.pool.get()?;
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            if let Some(local) = AttachmentSql::read_version(&attachment.id, &conn)? {
+                if local >= attachment.version() {
+                    return Ok(false);
+                }
+            }
+            AttachmentSql::save(attachment, &conn)?;
+            Ok(true)
+        })
+    }
+
+    /// Returns the ids that aren't in the store.
+    pub fn missing<'a, I>(&self, attachment_ids: I) -> FlowyResult<Vec<String>>
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let conn = self.pool.get()?;
+        let mut missing = vec![];
+        for attachment_id in attachment_ids {
+            if !AttachmentSql::exists(attachment_id, &conn)? {
+                missing.push(attachment_id.clone());
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Marks the attachments that aren't `referenced` as orphaned, and deletes the ones that
+    /// have been orphaned for the `grace_period`. The referenced attachments are unmarked.
+    /// Returns the number of the deleted attachments.
+    pub(crate) fn collect_orphans(
+        &self,
+        referenced: &BTreeSet<String>,
+        now: i64,
+        grace_period: Duration,
+    ) -> FlowyResult<usize> {
+        let conn = self.pool.get()?;
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let ids = dsl::document_attachment
+                .select((dsl::id, dsl::orphaned_time))
+                .load::<(String, i64)>(&*conn)?;
+            let expired_time = now - grace_period.as_secs() as i64;
+            let mut num_of_collected = 0;
+            for (id, orphaned_time) in ids {
+                let row = || dsl::document_attachment.filter(dsl::id.eq(&id));
+                if referenced.contains(&id) {
+                    if orphaned_time != 0 {
+                        let _ = diesel::update(row()).set(dsl::orphaned_time.eq(0)).execute(&*conn)?;
+                    }
+                    continue;
+                }
+
+                let orphaned_time = if orphaned_time == 0 {
+                    let _ = diesel::update(row()).set(dsl::orphaned_time.eq(now)).execute(&*conn)?;
+                    now
+                } else {
+                    orphaned_time
+                };
+                if orphaned_time <= expired_time {
+                    let _ = diesel::delete(row()).execute(&*conn)?;
+                    num_of_collected += 1;
+                }
+            }
+            Ok(num_of_collected)
+        })
+    }
+
+    /// The dry run of `collect_orphans`, returns the bytes of the attachments it would delete.
+    /// Nothing is marked or deleted.
+    pub(crate) fn collectable_orphan_bytes(
+        &self,
+        referenced: &BTreeSet<String>,
+        now: i64,
+        grace_period: Duration,
+    ) -> FlowyResult<u64> {
+        let conn = self.pool.get()?;
+        let rows = dsl::document_attachment
+            .select((dsl::id, dsl::orphaned_time, dsl::data))
+            .load::<(String, i64, Vec<u8>)>(&*conn)?;
+        let expired_time = now - grace_period.as_secs() as i64;
+        let bytes = rows
+            .into_iter()
+            .filter(|(id, orphaned_time, _)| {
+                // The attachment that isn't marked yet would be marked as orphaned `now`.
+                let orphaned_time = if *orphaned_time == 0 { now } else { *orphaned_time };
+                !referenced.contains(id) && orphaned_time <= expired_time
+            })
+            .map(|(_, _, data)| data.len() as u64)
+            .sum();
+        Ok(bytes)
+    }
+
+    fn update<F>(&self, attachment_id: &str, f: F) -> FlowyResult<Attachment>
+    where
+        F: FnOnce(&mut Attachment),
+    {
+        let conn = self.pool.get()?;
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let mut attachment = AttachmentSql::read(attachment_id, &conn)?.ok_or_else(|| {
+                FlowyError::record_not_found().context(format!("Can't find the attachment: {}", attachment_id))
+            })?;
+            f(&mut attachment);
+            // Newer than the version it was changed from, even within the same second.
+            attachment.update_time = timestamp().max(attachment.update_time + 1);
+            AttachmentSql::save(&attachment, &conn)?;
+            Ok(attachment)
+        })
+    }
+}
+
+/// The attachments referenced by the delta documents, each with the first document that
+/// refers to it.
+pub(crate) struct AttachmentReferences {
+    pub(crate) doc_id_by_attachment: BTreeMap<String, String>,
+    /// The documents that link to local files, the links are moved to attachments by
+    /// `attach_links`. The documents written before the attachments had ids refer to them so.
+    pub(crate) linked_doc_ids: Vec<String>,
+    /// The documents whose revisions couldn't be composed, their references are unknown.
+    pub(crate) unreadable_doc_ids: Vec<String>,
+}
+
+/// Reads the references of all the delta documents from their saved revisions. The folder and
+/// the custom dictionaries are stored with the documents but never refer to attachments.
+pub(crate) fn read_attachment_references(
+    user_id: &str,
+    pool: Arc<ConnectionPool>,
+) -> FlowyResult<AttachmentReferences> {
+    let doc_ids = {
+        let conn = pool.get()?;
+        DeltaRevisionSql::read_doc_ids(&conn)?
+    };
+
+    let disk_cache = SQLiteDeltaDocumentRevisionPersistence::new(user_id, pool);
+    let mut references = AttachmentReferences {
+        doc_id_by_attachment: BTreeMap::new(),
+        linked_doc_ids: vec![],
+        unreadable_doc_ids: vec![],
+    };
+    for doc_id in doc_ids {
+        let operations = disk_cache.read_revision_records(&doc_id, None).and_then(|records| {
+            Ok(make_operations_from_revisions::<AttributeHashMap>(
+                composable_revisions(&records),
+            )?)
+        });
+        match operations {
+            Ok(operations) => {
+                for attachment_id in attachment_ids(&operations) {
+                    references
+                        .doc_id_by_attachment
+                        .entry(attachment_id)
+                        .or_insert_with(|| doc_id.clone());
+                }
+                if attach_links(&operations, |link| local_file_path(link).map(|_| String::new())).is_some() {
+                    references.linked_doc_ids.push(doc_id);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Read the attachments of {} failed: {:?}", doc_id, e);
+                references.unreadable_doc_ids.push(doc_id);
+            }
+        }
+    }
+    Ok(references)
+}
+
+/// Returns the path of the local file that the link points to, None if it's not a link to an
+/// existing file, e.g. a web page.
+pub(crate) fn local_file_path(link: &str) -> Option<PathBuf> {
+    let path = Path::new(link.strip_prefix("file://").unwrap_or(link));
+    if path.is_absolute() && path.is_file() {
+        Some(path.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Moves the links to the local files of the `operations` to attachments, the files are saved
+/// in the `store`. A file linked more than once is saved once. Returns the operations to
+/// compose and the ids of the saved attachments, None if there's no link to a local file.
+pub(crate) fn attach_local_links(
+    operations: &DeltaTextOperations,
+    store: &AttachmentStore,
+) -> FlowyResult<Option<(DeltaTextOperations, Vec<String>)>> {
+    let mut attachment_by_path = BTreeMap::<PathBuf, String>::new();
+    let mut error = None;
+    let changes = attach_links(operations, |link| {
+        let path = local_file_path(link)?;
+        if let Some(attachment_id) = attachment_by_path.get(&path) {
+            return Some(attachment_id.clone());
+        }
+        let name = path.file_name()?.to_string_lossy().into_owned();
+        let attachment = std::fs::read(&path)
+            .map_err(|e| FlowyError::internal().context(format!("Read {:?} failed: {}", path, e)))
+            .and_then(|data| store.upload(&name, data));
+        match attachment {
+            Ok(attachment) => {
+                attachment_by_path.insert(path, attachment.id.clone());
+                Some(attachment.id)
+            }
+            Err(e) => {
+                error.get_or_insert(e);
+                None
+            }
+        }
+    });
+    if let Some(e) = error {
+        return Err(e);
+    }
+    Ok(changes.map(|changes| (changes, attachment_by_path.into_values().collect())))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AttachmentTransfer {
+    Download,
+    Upload,
+}
+
+/// Downloads and uploads the attachments in the background, see
+/// `DocumentManager::reconcile_attachments`. An attachment that is queued again before its
+/// transfer is done is transferred once.
+#[derive(Clone, Default)]
+pub(crate) struct AttachmentTransfers {
+    pending: Arc<Mutex<HashSet<String>>>,
+    idle: Arc<Notify>,
+}
+
+impl AttachmentTransfers {
+    pub(crate) fn queue(
+        &self,
+        executor: &Executor,
+        store: AttachmentStore,
+        cloud_service: Arc<dyn DocumentCloudService>,
+        token: String,
+        attachment_id: String,
+        transfer: AttachmentTransfer,
+    ) {
+        if !self.pending().insert(attachment_id.clone()) {
+            return;
+        }
+        let transfers = self.clone();
+        executor.spawn(async move {
+            let result = match transfer {
+                AttachmentTransfer::Download => {
+                    download_attachment(&store, &*cloud_service, &token, &attachment_id).await
+                }
+                AttachmentTransfer::Upload => upload_attachment(&store, &*cloud_service, &token, &attachment_id).await,
+            };
+            if let Err(e) = result {
+                tracing::error!("{:?} the attachment {} failed: {:?}", transfer, attachment_id, e);
+            }
+            let mut pending = transfers.pending();
+            pending.remove(&attachment_id);
+            if pending.is_empty() {
+                transfers.idle.notify_waiters();
+            }
+        });
+    }
+
+    /// Waits until the queued transfers are done.
+    pub(crate) async fn wait(&self) {
+        loop {
+            let idle = self.idle.notified();
+            if self.pending().is_empty() {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    fn pending(&self) -> std::sync::MutexGuard<HashSet<String>> {
+        // The set stays valid if a transfer panicked while holding the lock.
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+async fn download_attachment(
+    store: &AttachmentStore,
+    cloud_service: &dyn DocumentCloudService,
+    token: &str,
+    attachment_id: &str,
+) -> FlowyResult<()> {
+    match cloud_service.fetch_attachment(token, attachment_id).await? {
+        Some(attachment) if attachment.id == attachment_id => {
+            if store.merge(&attachment)? {
+                send_dart_notification(attachment_id, DocumentNotification::DidDownloadAttachment).send();
+            }
+        }
+        _ => tracing::warn!("The server doesn't have the attachment {}", attachment_id),
+    }
+    Ok(())
+}
+
+async fn upload_attachment(
+    store: &AttachmentStore,
+    cloud_service: &dyn DocumentCloudService,
+    token: &str,
+    attachment_id: &str,
+) -> FlowyResult<()> {
+    match store.read(attachment_id)? {
+        None => tracing::warn!("The attachment {} was deleted before its upload", attachment_id),
+        Some(attachment) => cloud_service.upload_attachment(token, attachment).await?,
+    }
+    Ok(())
+}
+
+/// Wakes up the reconciliation of the attachments when the remote changes of a document refer
+/// to attachments, see `DocumentManager::schedule_attachment_reconcile`.
+#[derive(Clone)]
+pub(crate) struct AttachmentObserver(Arc<Notify>);
+
+impl AttachmentObserver {
+    pub(crate) fn new(notify: Arc<Notify>) -> Self {
+        Self(notify)
+    }
+
+    pub(crate) fn did_receive_remote_operations(&self, operations: &DeltaTextOperations) {
+        if !attachment_ids(operations).is_empty() {
+            self.0.notify_one();
+        }
+    }
+}
+
+pub(crate) struct AttachmentSql {}
+
+impl AttachmentSql {
+    /// Saves the attachment, the one with the same id is replaced and is no longer orphaned.
+    pub(crate) fn save(attachment: &Attachment, conn: &SqliteConnection) -> FlowyResult<()> {
+        let record = (
+            dsl::id.eq(&attachment.id),
+            dsl::name.eq(&attachment.name),
+            dsl::data.eq(attachment.data.as_slice()),
+            dsl::update_time.eq(attachment.update_time),
+            dsl::orphaned_time.eq(0),
+            dsl::digest.eq(attachment.version().digest),
+        );
+        let _ = replace_into(document_attachment::table).values(record).execute(conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn read(attachment_id: &str, conn: &SqliteConnection) -> FlowyResult<Option<Attachment>> {
+        let attachment = dsl::document_attachment
+            .filter(dsl::id.eq(attachment_id))
+            .select((dsl::id, dsl::name, dsl::data, dsl::update_time))
+            .first::<(String, String, Vec<u8>, i64)>(conn)
+            .optional()?
+            .map(|(id, name, data, update_time)| Attachment {
+                id,
+                name,
+                data,
+                update_time,
+            });
+        Ok(attachment)
+    }
+
+    /// The digest is computed once for the attachments saved before it was kept in the table.
+    pub(crate) fn read_version(attachment_id: &str, conn: &SqliteConnection) -> FlowyResult<Option<AttachmentVersion>> {
+        let row = dsl::document_attachment
+            .filter(dsl::id.eq(attachment_id))
+            .select((dsl::update_time, dsl::digest))
+            .first::<(i64, String)>(conn)
+            .optional()?;
+        match row {
+            None => Ok(None),
+            Some((update_time, digest)) if !digest.is_empty() => Ok(Some(AttachmentVersion { update_time, digest })),
+            Some(_) => match Self::read(attachment_id, conn)? {
+                None => Ok(None),
+                Some(attachment) => {
+                    let version = attachment.version();
+                    let _ = diesel::update(dsl::document_attachment.filter(dsl::id.eq(attachment_id)))
+                        .set(dsl::digest.eq(&version.digest))
+                        .execute(conn)?;
+                    Ok(Some(version))
+                }
+            },
+        }
+    }
+
+    /// Returns the id and the size of the data of each attachment without reading the data. There
+    /// are none if the database was written by a version of the app that didn't have the
+    /// attachments yet.
+    pub(crate) fn read_sizes(conn: &SqliteConnection) -> FlowyResult<Vec<(String, i64)>> {
+        let rows = dsl::document_attachment
+            .select((dsl::id, sql::<BigInt>("length(data)")))
+            .order(dsl::id.asc())
+            .load::<(String, i64)>(conn)
+            .map_err(map_read_error);
+        match rows {
+            Ok(rows) => Ok(rows),
+            Err(e) if e.code == ErrorCode::RecordNotFound.value() => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(crate) fn exists(attachment_id: &str, conn: &SqliteConnection) -> FlowyResult<bool> {
+        let count = dsl::document_attachment
+            .filter(dsl::id.eq(attachment_id))
+            .count()
+            .get_result::<i64>(conn)?;
+        Ok(count > 0)
+    }
+}
@@ -0,0 +1,753 @@
+use crate::services::rev_sqlite::{DeltaRevisionSql, RevisionTimestampSql};
+use crate::services::{backup_database, DocMetaSql, DocPreference};
+use flowy_database::{
+    dsl::sql,
+    prelude::*,
+    schema::{document_backup_audit, document_backup_audit::dsl, document_rev_table, rev_table},
+    sql_types::Text,
+    DB_NAME, STAGED_DB_NAME,
+};
+use flowy_error::{FlowyError, FlowyResult};
+use flowy_http_model::revision::Revision;
+use lib_infra::util::timestamp;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The folder under the user's folder that keeps the backups, one folder per backup.
+pub(crate) const BACKUPS_DIR: &str = "backups";
+
+/// The folder in the backup that keeps the copies of the blob directories.
+const BLOBS_DIR: &str = "blobs";
+
+/// The file in each backup that describes it, see `BackupManifest`. The backups made before
+/// the incremental backups don't have one, they're full backups.
+const BACKUP_MANIFEST_FILE: &str = "manifest.json";
+
+/// The file in an incremental backup that keeps the rows written since its parent backup.
+const BACKUP_DELTA_FILE: &str = "delta.json";
+
+/// The tables that every backup of the documents has.
+const REQUIRED_TABLES: [&str; 2] = ["rev_table", "document_rev_table"];
+
+/// Describes a backup. A full backup has a copy of the database, an incremental backup only has
+/// the rows written since its parent backup and is restored on top of the chain of backups that
+/// leads to its full backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BackupManifest {
+    /// The name of the backup's folder.
+    pub(crate) id: String,
+    /// The full backup that the incremental backup is restored on top of, None for a full backup.
+    pub(crate) base_id: Option<String>,
+    /// The backup that the incremental backup was made after, None for a full backup.
+    pub(crate) parent_id: Option<String>,
+    /// The time that the latest revision in the backup was written at, in seconds.
+    pub(crate) rev_watermark: i64,
+    /// The time that the latest preference in the `doc_meta` table was set at, in milliseconds.
+    pub(crate) meta_watermark: i64,
+    /// In seconds.
+    pub(crate) created_at: i64,
+}
+
+impl BackupManifest {
+    fn new(id: &str, parent: Option<&BackupManifest>, conn: &SqliteConnection) -> FlowyResult<Self> {
+        let rev_watermark = RevisionTimestampSql::read_latest(conn)?.unwrap_or(0);
+        let meta_watermark = DocMetaSql::read_rows(conn)?
+            .iter()
+            .map(|(_, preferences)| latest_preference_update(preferences))
+            .max()
+            .unwrap_or(0);
+        Ok(Self {
+            id: id.to_owned(),
+            base_id: parent.map(|parent| parent.base_id().to_owned()),
+            parent_id: parent.map(|parent| parent.id.clone()),
+            rev_watermark,
+            meta_watermark,
+            created_at: timestamp(),
+        })
+    }
+
+    pub(crate) fn base_id(&self) -> &str {
+        self.base_id.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// A revision of an incremental backup with its state and the time it was written at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BackupRevision {
+    pub(crate) revision: Revision,
+    /// See `TextRevisionState`.
+    pub(crate) state: i32,
+    /// In seconds.
+    pub(crate) create_time: i64,
+}
+
+/// The rows of an object in an incremental backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupObject {
+    object_id: String,
+    /// The rev_id and the state of each revision of the object at the time of the backup, the
+    /// revisions that aren't here were deleted since the parent backup.
+    rev_states: Vec<(i64, i32)>,
+    /// The revisions written since the parent backup.
+    revisions: Vec<BackupRevision>,
+}
+
+/// The rows of an incremental backup, only the ones written since the parent backup are kept
+/// with the ids of all the rows, which tell the ones that were deleted since then.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupDelta {
+    /// The ids of all the objects at the time of the backup, the ones that aren't here were
+    /// deleted since the parent backup.
+    object_ids: Vec<String>,
+    /// The objects that have a revision written since the parent backup.
+    objects: Vec<BackupObject>,
+    /// The ids of all the `doc_meta` rows at the time of the backup.
+    doc_meta_ids: Vec<String>,
+    /// The doc_id and the preferences' JSON of the `doc_meta` rows changed since the parent
+    /// backup.
+    doc_meta: Vec<(String, String)>,
+}
+
+impl BackupDelta {
+    /// The times have a resolution of a second, so the rows written in the same second as the
+    /// latest rows of the parent backup are saved again.
+    fn read(parent: &BackupManifest, conn: &SqliteConnection) -> FlowyResult<Self> {
+        let object_ids = DeltaRevisionSql::read_object_ids(conn)?;
+        let changed_ids = RevisionTimestampSql::read_object_ids_since(parent.rev_watermark, conn)?
+            .into_iter()
+            .collect::<HashSet<String>>();
+        let mut objects = vec![];
+        for object_id in object_ids.iter().filter(|object_id| changed_ids.contains(*object_id)) {
+            objects.push(BackupObject {
+                object_id: object_id.clone(),
+                rev_states: DeltaRevisionSql::read_rev_states(object_id, conn)?,
+                revisions: DeltaRevisionSql::read_backup_revisions(object_id, parent.rev_watermark, conn)?,
+            });
+        }
+        let doc_meta_rows = DocMetaSql::read_rows(conn)?;
+        let doc_meta_ids = doc_meta_rows.iter().map(|(doc_id, _)| doc_id.clone()).collect();
+        let doc_meta = doc_meta_rows
+            .into_iter()
+            .filter(|(_, preferences)| latest_preference_update(preferences) >= parent.meta_watermark)
+            .collect();
+        Ok(Self {
+            object_ids,
+            objects,
+            doc_meta_ids,
+            doc_meta,
+        })
+    }
+
+    /// Fails if the rows don't match the ids of the backup, i.e. the backup was modified.
+    fn validate(&self) -> Result<(), String> {
+        let object_ids = self.object_ids.iter().collect::<HashSet<&String>>();
+        for object in &self.objects {
+            if !object_ids.contains(&object.object_id) {
+                return Err(format!("the object {} isn't in the backup", object.object_id));
+            }
+            let rev_ids = object
+                .rev_states
+                .iter()
+                .map(|(rev_id, _)| *rev_id)
+                .collect::<HashSet<i64>>();
+            if let Some(revision) = object
+                .revisions
+                .iter()
+                .find(|revision| !rev_ids.contains(&revision.revision.rev_id))
+            {
+                return Err(format!(
+                    "the revision {} of {} isn't in the backup",
+                    revision.revision.rev_id, object.object_id
+                ));
+            }
+        }
+        let doc_meta_ids = self.doc_meta_ids.iter().collect::<HashSet<&String>>();
+        if let Some((doc_id, _)) = self.doc_meta.iter().find(|(doc_id, _)| !doc_meta_ids.contains(doc_id)) {
+            return Err(format!("the preferences of {} aren't in the backup", doc_id));
+        }
+        Ok(())
+    }
+
+    /// Runs on the connection of the caller, which should be in a transaction.
+    fn apply(self, conn: &SqliteConnection) -> FlowyResult<()> {
+        let object_ids = self.object_ids.into_iter().collect::<HashSet<String>>();
+        for object_id in DeltaRevisionSql::read_object_ids(conn)? {
+            if !object_ids.contains(&object_id) {
+                DeltaRevisionSql::delete(&object_id, None, conn)?;
+            }
+        }
+        for object in self.objects {
+            DeltaRevisionSql::apply_backup_revisions(&object.object_id, &object.rev_states, object.revisions, conn)?;
+        }
+
+        let doc_meta_ids = self.doc_meta_ids.into_iter().collect::<HashSet<String>>();
+        for (doc_id, _) in DocMetaSql::read_rows(conn)? {
+            if !doc_meta_ids.contains(&doc_id) {
+                DocMetaSql::delete_row(&doc_id, conn)?;
+            }
+        }
+        for (doc_id, preferences) in self.doc_meta {
+            DocMetaSql::replace_row(&doc_id, &preferences, conn)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the time that the latest preference of the `doc_meta` row was set at.
+fn latest_preference_update(preferences: &str) -> i64 {
+    serde_json::from_str::<BTreeMap<String, DocPreference>>(preferences)
+        .ok()
+        .and_then(|preferences| preferences.values().map(|preference| preference.updated_at).max())
+        .unwrap_or(0)
+}
+
+/// Writes a new full backup into a folder named after the current time under `backups_dir`.
+/// The database and the `blob_dirs` are copied into a hidden folder first, it's renamed to the
+/// backup once everything is copied. A failed copy removes the hidden folder, so there are no
+/// partial backups.
+pub(crate) fn write_backup(conn: &SqliteConnection, backups_dir: &Path, blob_dirs: &[PathBuf]) -> FlowyResult<PathBuf> {
+    write_backup_dir(backups_dir, |name, dir| {
+        // The watermarks are read before the copy, the rows written during the copy are saved
+        // again by the next incremental backup.
+        let manifest = BackupManifest::new(name, None, conn)?;
+        copy_into(conn, dir, blob_dirs)?;
+        write_json(&dir.join(BACKUP_MANIFEST_FILE), &manifest)
+    })
+}
+
+/// Writes a new incremental backup after the backup of the `parent` manifest, see
+/// `write_backup`. It has the revisions of the objects written since the parent backup, the
+/// `doc_meta` rows changed since then and the files of the `blob_dirs` modified since then.
+pub(crate) fn write_incremental_backup(
+    conn: &SqliteConnection,
+    backups_dir: &Path,
+    parent: &BackupManifest,
+    blob_dirs: &[PathBuf],
+) -> FlowyResult<PathBuf> {
+    write_backup_dir(backups_dir, |name, dir| {
+        let manifest = BackupManifest::new(name, Some(parent), conn)?;
+        let delta = BackupDelta::read(parent, conn)?;
+        std::fs::create_dir_all(dir).map_err(|e| FlowyError::internal().context(e))?;
+        write_json(&dir.join(BACKUP_DELTA_FILE), &delta)?;
+        let modified_since = UNIX_EPOCH + Duration::from_secs(parent.created_at.max(0) as u64);
+        copy_blob_dirs(dir, blob_dirs, Some(modified_since))?;
+        write_json(&dir.join(BACKUP_MANIFEST_FILE), &manifest)
+    })
+}
+
+fn write_backup_dir<F>(backups_dir: &Path, write: F) -> FlowyResult<PathBuf>
+where
+    F: FnOnce(&str, &Path) -> FlowyResult<()>,
+{
+    std::fs::create_dir_all(backups_dir).map_err(|e| FlowyError::internal().context(e))?;
+    let name = unique_backup_name(backups_dir);
+    let partial_dir = backups_dir.join(format!(".{}.partial", name));
+    let backup_dir = backups_dir.join(&name);
+
+    let result = write(&name, &partial_dir)
+        .and_then(|_| std::fs::rename(&partial_dir, &backup_dir).map_err(|e| FlowyError::internal().context(e)));
+    if let Err(e) = result {
+        if partial_dir.exists() {
+            if let Err(remove_error) = std::fs::remove_dir_all(&partial_dir) {
+                tracing::error!("Remove the partial backup {:?} failed: {:?}", partial_dir, remove_error);
+            }
+        }
+        return Err(e);
+    }
+    Ok(backup_dir)
+}
+
+fn copy_into(conn: &SqliteConnection, dir: &Path, blob_dirs: &[PathBuf]) -> FlowyResult<()> {
+    let dir_str = dir
+        .to_str()
+        .ok_or_else(|| FlowyError::invalid_data().context(format!("Invalid backup path: {:?}", dir)))?;
+    let _ = backup_database(conn, dir_str)?;
+    copy_blob_dirs(dir, blob_dirs, None)
+}
+
+/// Copies the `blob_dirs` into the backup at `dir`, only the files modified at or after
+/// `modified_since` if it's set.
+fn copy_blob_dirs(dir: &Path, blob_dirs: &[PathBuf], modified_since: Option<SystemTime>) -> FlowyResult<()> {
+    for blob_dir in blob_dirs {
+        let name = blob_dir
+            .file_name()
+            .ok_or_else(|| FlowyError::invalid_data().context(format!("Invalid blob directory: {:?}", blob_dir)))?;
+        copy_dir(blob_dir, &dir.join(BLOBS_DIR).join(name), modified_since)?;
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path, modified_since: Option<SystemTime>) -> FlowyResult<()> {
+    copy_dir_all(from, to, modified_since)
+        .map_err(|e| FlowyError::internal().context(format!("Copy {:?} failed: {}", from, e)))
+}
+
+fn copy_dir_all(from: &Path, to: &Path, modified_since: Option<SystemTime>) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &to.join(entry.file_name()), modified_since)?;
+            continue;
+        }
+        if let Some(modified_since) = modified_since {
+            if std::fs::metadata(entry.path())?.modified()? < modified_since {
+                continue;
+            }
+        }
+        std::fs::copy(entry.path(), to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> FlowyResult<()> {
+    let bytes = serde_json::to_vec(value)?;
+    std::fs::write(path, bytes).map_err(|e| FlowyError::internal().context(format!("Write {:?} failed: {}", path, e)))
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> FlowyResult<T> {
+    let bytes =
+        std::fs::read(path).map_err(|e| FlowyError::internal().context(format!("Read {:?} failed: {}", path, e)))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| FlowyError::invalid_data().context(format!("{:?} is invalid: {}", path, e)))
+}
+
+/// Returns None if the backup has no manifest, i.e. it's a full backup made before the
+/// incremental backups.
+pub(crate) fn read_manifest(backup_dir: &Path) -> FlowyResult<Option<BackupManifest>> {
+    let path = backup_dir.join(BACKUP_MANIFEST_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    read_json(&path).map(Some)
+}
+
+/// Returns the manifest of the newest backup under `backups_dir` if the next backup can be an
+/// incremental backup after it: its full backup is still there and fewer than
+/// `max_incremental_backups` incremental backups were made after that full backup.
+pub(crate) fn incremental_backup_parent(
+    backups_dir: &Path,
+    max_incremental_backups: usize,
+) -> FlowyResult<Option<BackupManifest>> {
+    if max_incremental_backups == 0 {
+        return Ok(None);
+    }
+    let manifests = list_backups(backups_dir)?
+        .iter()
+        .flat_map(|backup| read_manifest(backup).ok().flatten())
+        .collect::<Vec<BackupManifest>>();
+    let latest = match manifests.last() {
+        None => return Ok(None),
+        Some(latest) => latest.clone(),
+    };
+    if !backups_dir.join(latest.base_id()).join(DB_NAME).is_file() {
+        return Ok(None);
+    }
+    let num_of_incremental_backups = manifests
+        .iter()
+        .filter(|manifest| manifest.base_id.as_deref() == Some(latest.base_id()))
+        .count();
+    if num_of_incremental_backups >= max_incremental_backups {
+        return Ok(None);
+    }
+    Ok(Some(latest))
+}
+
+/// Returns the backups that restore the backup at `backup_dir`, its full backup first and the
+/// backup itself last. Fails if a backup of the chain is missing or doesn't belong to it.
+pub(crate) fn resolve_backup_chain(backup_dir: &Path) -> FlowyResult<Vec<PathBuf>> {
+    let incomplete_chain = |reason: String| {
+        FlowyError::invalid_data().context(format!("The backup {:?} can't be restored: {}", backup_dir, reason))
+    };
+    let mut chain = vec![backup_dir.to_path_buf()];
+    let mut manifest = match read_manifest(backup_dir)? {
+        None => return Ok(chain),
+        Some(manifest) => manifest,
+    };
+    let backups_dir = backup_dir
+        .parent()
+        .ok_or_else(|| FlowyError::invalid_data().context(format!("Invalid backup path: {:?}", backup_dir)))?;
+    let mut visited_ids = HashSet::from([manifest.id.clone()]);
+    while let Some(parent_id) = manifest.parent_id.clone() {
+        let current_dir = chain.last().unwrap();
+        if !current_dir.join(BACKUP_DELTA_FILE).is_file() {
+            return Err(incomplete_chain(format!("the rows of {:?} are missing", current_dir)));
+        }
+        if !visited_ids.insert(parent_id.clone()) {
+            return Err(incomplete_chain(format!("the backup {} depends on itself", parent_id)));
+        }
+        let parent_dir = backups_dir.join(&parent_id);
+        let parent = read_manifest(&parent_dir)?
+            .ok_or_else(|| incomplete_chain(format!("the backup {} it depends on is missing", parent_id)))?;
+        if parent.base_id() != manifest.base_id() {
+            return Err(incomplete_chain(format!(
+                "the backup {} belongs to another full backup",
+                parent_id
+            )));
+        }
+        chain.push(parent_dir);
+        manifest = parent;
+    }
+    if manifest.base_id.is_some() {
+        return Err(incomplete_chain(format!(
+            "the full backup {} is missing",
+            manifest.base_id()
+        )));
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Copies the database of a full backup next to the database in `user_dir` and applies the
+/// incremental backups to the copy in order. Returns the path of the copy, which is removed if
+/// any incremental backup fails to apply.
+pub(crate) fn layer_backup_chain(
+    db_path: &Path,
+    incremental_backups: &[PathBuf],
+    user_dir: &str,
+) -> FlowyResult<PathBuf> {
+    let layered_path = Path::new(user_dir).join(format!("{}.layered", STAGED_DB_NAME));
+    let result = std::fs::copy(db_path, &layered_path)
+        .map_err(|e| FlowyError::internal().context(format!("Copy the backup {:?} failed: {}", db_path, e)))
+        .and_then(|_| apply_incremental_backups(&layered_path, incremental_backups));
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&layered_path);
+        return Err(e);
+    }
+    Ok(layered_path)
+}
+
+fn apply_incremental_backups(db_path: &Path, incremental_backups: &[PathBuf]) -> FlowyResult<()> {
+    let db_path_str = db_path
+        .to_str()
+        .ok_or_else(|| FlowyError::invalid_data().context(format!("Invalid backup path: {:?}", db_path)))?;
+    let conn = SqliteConnection::establish(db_path_str).map_err(|e| FlowyError::internal().context(e))?;
+    for backup_dir in incremental_backups {
+        let delta = read_json::<BackupDelta>(&backup_dir.join(BACKUP_DELTA_FILE))?;
+        conn.immediate_transaction::<_, FlowyError, _>(|| delta.apply(&conn))?;
+    }
+    Ok(())
+}
+
+/// The backups are named after the time they were made, so their names sort from the oldest
+/// to the newest.
+fn unique_backup_name(backups_dir: &Path) -> String {
+    let name = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+    let mut unique_name = name.clone();
+    let mut n = 1;
+    while backups_dir.join(&unique_name).exists() {
+        unique_name = format!("{}-{}", name, n);
+        n += 1;
+    }
+    unique_name
+}
+
+/// Returns the backups under `backups_dir`, the oldest first.
+pub(crate) fn list_backups(backups_dir: &Path) -> FlowyResult<Vec<PathBuf>> {
+    if !backups_dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut backups = vec![];
+    for entry in std::fs::read_dir(backups_dir).map_err(|e| FlowyError::internal().context(e))? {
+        let entry = entry.map_err(|e| FlowyError::internal().context(e))?;
+        let is_partial = entry.file_name().to_string_lossy().starts_with('.');
+        if entry.path().is_dir() && !is_partial {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Removes the oldest backups until at most `max_backups` are left. A full backup is removed
+/// along with the incremental backups made after it, so no incremental backup is left without
+/// its full backup, and the newest full backup is always kept. Returns the removed ones.
+pub(crate) fn rotate_backups(backups_dir: &Path, max_backups: usize) -> FlowyResult<Vec<PathBuf>> {
+    let backups = list_backups(backups_dir)?;
+    let mut num_of_backups = backups.len();
+    let mut chains: Vec<(String, Vec<PathBuf>)> = vec![];
+    for backup in backups {
+        let base_id = match read_manifest(&backup) {
+            Ok(Some(manifest)) => manifest.base_id().to_owned(),
+            _ => backup.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        };
+        match chains.iter_mut().find(|(id, _)| *id == base_id) {
+            Some((_, chain)) => chain.push(backup),
+            None => chains.push((base_id, vec![backup])),
+        }
+    }
+
+    let mut removed = vec![];
+    let num_of_old_chains = chains.len().saturating_sub(1);
+    for (_, chain) in chains.into_iter().take(num_of_old_chains) {
+        if num_of_backups <= max_backups {
+            break;
+        }
+        num_of_backups -= chain.len();
+        // The incremental backups go first, so an interrupted rotation doesn't leave any of them
+        // without its full backup.
+        for backup in chain.iter().rev() {
+            std::fs::remove_dir_all(backup).map_err(|e| FlowyError::internal().context(e))?;
+        }
+        removed.extend(chain);
+    }
+    Ok(removed)
+}
+
+/// Checks that the backup at `backup_dir` can replace the database of `conn`: its database
+/// passes the integrity check, has the tables of the documents and wasn't migrated by a newer
+/// version of the app. Returns the path of its database.
+pub(crate) fn validate_backup(backup_dir: &Path, conn: &SqliteConnection) -> FlowyResult<PathBuf> {
+    let db_path = backup_dir.join(DB_NAME);
+    if !db_path.is_file() {
+        return Err(FlowyError::record_not_found().context(format!("There is no backup at {:?}", backup_dir)));
+    }
+    let db_path_str = db_path
+        .to_str()
+        .ok_or_else(|| FlowyError::invalid_data().context(format!("Invalid backup path: {:?}", db_path)))?;
+    let invalid_backup = |e: &dyn std::fmt::Display| {
+        FlowyError::invalid_data().context(format!("The backup {:?} is invalid: {}", backup_dir, e))
+    };
+
+    let backup_conn = SqliteConnection::establish(db_path_str).map_err(|e| invalid_backup(&e))?;
+    let integrity = sql::<Text>("PRAGMA integrity_check")
+        .load::<String>(&backup_conn)
+        .map_err(|e| invalid_backup(&e))?;
+    if integrity.len() != 1 || integrity[0] != "ok" {
+        return Err(invalid_backup(&integrity.join(", ")));
+    }
+
+    let tables = sql::<Text>("SELECT name FROM sqlite_master WHERE type = 'table'")
+        .load::<String>(&backup_conn)
+        .map_err(|e| invalid_backup(&e))?;
+    if let Some(table) = REQUIRED_TABLES
+        .iter()
+        .find(|table| !tables.iter().any(|name| name.as_str() == **table))
+    {
+        return Err(invalid_backup(&format!("the table {} is missing", table)));
+    }
+
+    let known_migrations = migration_versions(conn)?.into_iter().collect::<HashSet<String>>();
+    let backup_migrations = migration_versions(&backup_conn).map_err(|e| invalid_backup(&e))?;
+    if let Some(version) = backup_migrations
+        .iter()
+        .find(|version| !known_migrations.contains(*version))
+    {
+        return Err(invalid_backup(&format!(
+            "it's migrated by a newer version ({})",
+            version
+        )));
+    }
+    Ok(db_path)
+}
+
+/// Checks that the incremental backup at `backup_dir` can be applied: its rows can be read and
+/// match the ids it keeps.
+pub(crate) fn validate_incremental_backup(backup_dir: &Path) -> FlowyResult<()> {
+    let delta = read_json::<BackupDelta>(&backup_dir.join(BACKUP_DELTA_FILE))?;
+    delta
+        .validate()
+        .map_err(|e| FlowyError::invalid_data().context(format!("The backup {:?} is invalid: {}", backup_dir, e)))
+}
+
+/// Copies the blob directories kept by the backups of the `chain` into the `blob_dirs`, the
+/// full backup first, so the files of the newer backups win. The files added after the backup
+/// are kept.
+pub(crate) fn restore_blob_dirs(chain: &[PathBuf], blob_dirs: &[PathBuf]) -> FlowyResult<()> {
+    for backup_dir in chain {
+        for blob_dir in blob_dirs {
+            let name = blob_dir
+                .file_name()
+                .ok_or_else(|| FlowyError::invalid_data().context(format!("Invalid blob directory: {:?}", blob_dir)))?;
+            let backup_blob_dir = backup_dir.join(BLOBS_DIR).join(name);
+            if backup_blob_dir.is_dir() {
+                copy_dir(&backup_blob_dir, blob_dir, None)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn migration_versions(conn: &SqliteConnection) -> FlowyResult<Vec<String>> {
+    let versions = sql::<Text>("SELECT version FROM __diesel_schema_migrations").load::<String>(conn)?;
+    Ok(versions)
+}
+
+/// Returns the number of the document revisions that aren't acked by the server yet.
+pub(crate) fn count_unsynced_revisions(conn: &SqliteConnection) -> FlowyResult<i64> {
+    // The state 0 is the `Sync` state of both tables, see `TextRevisionState`.
+    let text_revisions = rev_table::dsl::rev_table
+        .filter(rev_table::dsl::state.eq(0))
+        .count()
+        .get_result::<i64>(conn)?;
+    let document_revisions = document_rev_table::dsl::document_rev_table
+        .filter(document_rev_table::dsl::state.eq(0))
+        .count()
+        .get_result::<i64>(conn)?;
+    Ok(text_revisions + document_revisions)
+}
+
+/// Copies the database at `db_path` next to the database in `user_dir`, it replaces that
+/// database the next time it's opened.
+pub(crate) fn stage_database(db_path: &Path, user_dir: &str) -> FlowyResult<PathBuf> {
+    let staged_path = Path::new(user_dir).join(STAGED_DB_NAME);
+    let partial_path = Path::new(user_dir).join(format!("{}.partial", STAGED_DB_NAME));
+    let result = std::fs::copy(db_path, &partial_path).and_then(|_| std::fs::rename(&partial_path, &staged_path));
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial_path);
+        return Err(FlowyError::internal().context(format!("Stage the backup {:?} failed: {}", db_path, e)));
+    }
+    Ok(staged_path)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupKind {
+    Backup,
+    Restore,
+    /// The revisions of a document copied before they're replaced by its snapshot, the path is
+    /// the id of the document.
+    Revisions,
+    /// The snapshot of a document that couldn't be confirmed, kept as a version of the document.
+    /// The path is the id of the document.
+    RecoveredSnapshot,
+}
+
+impl BackupKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BackupKind::Backup => "backup",
+            BackupKind::Restore => "restore",
+            BackupKind::Revisions => "revisions",
+            BackupKind::RecoveredSnapshot => "recovered_snapshot",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupOutcome {
+    Succeeded,
+    Failed,
+}
+
+impl BackupOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BackupOutcome::Succeeded => "succeeded",
+            BackupOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// The record of one backup or restore, it's saved in the document_backup_audit table.
+#[derive(Debug, Clone)]
+pub struct BackupAuditEntry {
+    pub kind: BackupKind,
+    /// The backup, or the backups folder if the backup failed before it got a name.
+    pub path: String,
+    pub outcome: BackupOutcome,
+    /// The error of the failed run.
+    pub detail: String,
+    pub timestamp: i64,
+}
+
+impl BackupAuditEntry {
+    pub(crate) fn new<T>(kind: BackupKind, path: &Path, result: &Result<T, FlowyError>) -> Self {
+        let (outcome, detail) = match result {
+            Ok(_) => (BackupOutcome::Succeeded, String::new()),
+            Err(e) => (BackupOutcome::Failed, e.msg.clone()),
+        };
+        Self {
+            kind,
+            path: path.to_string_lossy().into_owned(),
+            outcome,
+            detail,
+            timestamp: timestamp(),
+        }
+    }
+
+    pub(crate) fn revisions(object_id: &str, num_of_revisions: usize) -> Self {
+        Self {
+            kind: BackupKind::Revisions,
+            path: object_id.to_owned(),
+            outcome: BackupOutcome::Succeeded,
+            detail: format!("{} revisions", num_of_revisions),
+            timestamp: timestamp(),
+        }
+    }
+
+    pub(crate) fn recovered_snapshot(object_id: &str, rev_id: i64, name: &str) -> Self {
+        Self {
+            kind: BackupKind::RecoveredSnapshot,
+            path: object_id.to_owned(),
+            outcome: BackupOutcome::Succeeded,
+            detail: format!("the snapshot {} is kept as \"{}\"", rev_id, name),
+            timestamp: timestamp(),
+        }
+    }
+}
+
+pub(crate) fn write_backup_audit(entry: &BackupAuditEntry, conn: &SqliteConnection) -> FlowyResult<()> {
+    let record = (
+        dsl::kind.eq(entry.kind.as_str()),
+        dsl::path.eq(&entry.path),
+        dsl::outcome.eq(entry.outcome.as_str()),
+        dsl::detail.eq(&entry.detail),
+        dsl::timestamp.eq(entry.timestamp),
+    );
+    let _ = insert_into(dsl::document_backup_audit).values(record).execute(conn)?;
+    Ok(())
+}
+
+/// Returns the time of the last succeeded backup in seconds, None if no backup succeeded yet.
+pub(crate) fn read_last_backup_timestamp(conn: &SqliteConnection) -> FlowyResult<Option<i64>> {
+    let last = dsl::document_backup_audit
+        .filter(dsl::kind.eq(BackupKind::Backup.as_str()))
+        .filter(dsl::outcome.eq(BackupOutcome::Succeeded.as_str()))
+        .select(diesel::dsl::max(dsl::timestamp))
+        .first::<Option<i64>>(conn)?;
+    Ok(last)
+}
+
+/// Returns the audit entries of the backups and restores, the oldest first.
+pub fn read_backup_audit(conn: &SqliteConnection) -> FlowyResult<Vec<BackupAuditEntry>> {
+    let records = dsl::document_backup_audit
+        .order(dsl::id.asc())
+        .load::<BackupAuditRecord>(conn)?;
+    Ok(records.into_iter().map(BackupAuditEntry::from).collect())
+}
+
+#[derive(PartialEq, Clone, Debug, Queryable, Identifiable, Insertable)]
+#[table_name = "document_backup_audit"]
+struct BackupAuditRecord {
+    id: i32,
+    kind: String,
+    path: String,
+    outcome: String,
+    detail: String,
+    timestamp: i64,
+}
+
+impl std::convert::From<BackupAuditRecord> for BackupAuditEntry {
+    fn from(record: BackupAuditRecord) -> Self {
+        let kind = match record.kind.as_str() {
+            "restore" => BackupKind::Restore,
+            "revisions" => BackupKind::Revisions,
+            "recovered_snapshot" => BackupKind::RecoveredSnapshot,
+            _ => BackupKind::Backup,
+        };
+        let outcome = match record.outcome.as_str() {
+            "succeeded" => BackupOutcome::Succeeded,
+            _ => BackupOutcome::Failed,
+        };
+        Self {
+            kind,
+            path: record.path,
+            outcome,
+            detail: record.detail,
+            timestamp: record.timestamp,
+        }
+    }
+}
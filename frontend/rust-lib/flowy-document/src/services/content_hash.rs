@@ -0,0 +1,118 @@
+use crate::services::rev_sqlite::{RevTableType, DELETE_REVS_CHUNK_SIZE};
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+use flowy_database::{
+    prelude::*,
+    schema::{document_content_hash, document_content_hash::dsl, rev_table},
+    sql_query,
+};
+use flowy_error::{FlowyError, FlowyResult};
+use std::collections::HashMap;
+
+/// The hash of the document's content at the revision `rev_id`, see `content_hash`. The hash
+/// only changes with the text and the formatting of the document, so the other apps can compare
+/// it to tell whether the document changed, or whether two documents are the same.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentContentHash {
+    pub doc_id: String,
+    pub rev_id: i64,
+    pub hash: String,
+}
+
+#[derive(QueryableByName)]
+struct LatestRevId {
+    #[sql_type = "Nullable<BigInt>"]
+    rev_id: Option<i64>,
+}
+
+/// Keeps the hash of each closed document, so it's only computed again after the document
+/// changed.
+pub(crate) struct ContentHashSql {}
+
+impl ContentHashSql {
+    pub(crate) fn read(doc_id: &str, conn: &SqliteConnection) -> FlowyResult<Option<DocumentContentHash>> {
+        let content_hash = dsl::document_content_hash
+            .filter(dsl::doc_id.eq(doc_id))
+            .select((dsl::rev_id, dsl::hash))
+            .first::<(i64, String)>(conn)
+            .optional()?;
+        Ok(content_hash.map(|(rev_id, hash)| DocumentContentHash {
+            doc_id: doc_id.to_owned(),
+            rev_id,
+            hash,
+        }))
+    }
+
+    pub(crate) fn write(content_hash: &DocumentContentHash, conn: &SqliteConnection) -> FlowyResult<()> {
+        let record = (
+            dsl::doc_id.eq(&content_hash.doc_id),
+            dsl::rev_id.eq(content_hash.rev_id),
+            dsl::hash.eq(&content_hash.hash),
+        );
+        let _ = replace_into(document_content_hash::table)
+            .values(record)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Same as `read` for each of the documents, keyed by their doc_ids.
+    pub(crate) fn read_all(
+        doc_ids: &[String],
+        conn: &SqliteConnection,
+    ) -> FlowyResult<HashMap<String, DocumentContentHash>> {
+        let mut content_hashes = HashMap::with_capacity(doc_ids.len());
+        for chunk in doc_ids.chunks(DELETE_REVS_CHUNK_SIZE) {
+            let rows = dsl::document_content_hash
+                .filter(dsl::doc_id.eq_any(chunk))
+                .select((dsl::doc_id, dsl::rev_id, dsl::hash))
+                .load::<(String, i64, String)>(conn)?;
+            for (doc_id, rev_id, hash) in rows {
+                let content_hash = DocumentContentHash {
+                    doc_id: doc_id.clone(),
+                    rev_id,
+                    hash,
+                };
+                content_hashes.insert(doc_id, content_hash);
+            }
+        }
+        Ok(content_hashes)
+    }
+
+    pub(crate) fn write_all(content_hashes: &[DocumentContentHash], conn: &SqliteConnection) -> FlowyResult<()> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            for content_hash in content_hashes {
+                Self::write(content_hash, conn)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Same as `read_latest_rev_id` for each of the documents, the documents without revisions
+    /// are left out.
+    pub(crate) fn read_latest_rev_ids(
+        doc_ids: &[String],
+        conn: &SqliteConnection,
+    ) -> FlowyResult<HashMap<String, i64>> {
+        let mut latest_rev_ids: HashMap<String, i64> = HashMap::with_capacity(doc_ids.len());
+        for chunk in doc_ids.chunks(DELETE_REVS_CHUNK_SIZE) {
+            let rows = rev_table::dsl::rev_table
+                .filter(rev_table::dsl::doc_id.eq_any(chunk))
+                .filter(rev_table::dsl::ty.ne(RevTableType::Quarantined))
+                .select((rev_table::dsl::doc_id, rev_table::dsl::rev_id))
+                .load::<(String, i64)>(conn)?;
+            for (doc_id, rev_id) in rows {
+                let latest = latest_rev_ids.entry(doc_id).or_insert(rev_id);
+                *latest = (*latest).max(rev_id);
+            }
+        }
+        Ok(latest_rev_ids)
+    }
+
+    /// Returns the rev_id of the document's last saved revision, None if it has no revisions.
+    pub(crate) fn read_latest_rev_id(doc_id: &str, conn: &SqliteConnection) -> FlowyResult<Option<i64>> {
+        let latest = sql_query("SELECT MAX(rev_id) AS rev_id FROM rev_table WHERE doc_id = ? AND ty != ?")
+            .bind::<Text, _>(doc_id)
+            .bind::<Integer, _>(RevTableType::Quarantined)
+            .get_result::<LatestRevId>(conn)?;
+        Ok(latest.rev_id)
+    }
+}
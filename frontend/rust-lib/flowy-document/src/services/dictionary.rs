@@ -0,0 +1,172 @@
+use crate::dart_notification::{send_anonymous_dart_notification, DocumentNotification};
+use crate::entities::CustomDictionaryPB;
+use flowy_database::{
+    prelude::*,
+    schema::{custom_dictionary, custom_dictionary::dsl},
+    ConnectionPool,
+};
+use flowy_error::{FlowyError, FlowyResult};
+use lib_ot::core::Interval;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// The custom dictionary of a workspace is kept in a delta document with one word per line, so
+/// it syncs between the devices like any other document and the words added on different
+/// devices merge when their revisions are transformed.
+const CUSTOM_DICTIONARY_PREFIX: &str = "custom_dictionary:";
+
+pub fn custom_dictionary_doc_id(workspace_id: &str) -> String {
+    format!("{}{}", CUSTOM_DICTIONARY_PREFIX, workspace_id)
+}
+
+/// Returns the workspace of the document if it's the custom dictionary of that workspace.
+pub(crate) fn custom_dictionary_workspace_id(doc_id: &str) -> Option<&str> {
+    doc_id.strip_prefix(CUSTOM_DICTIONARY_PREFIX)
+}
+
+/// Returns the trimmed word. A word can't be empty or contain whitespace, it would be split
+/// into several lines or words when the dictionary is read back.
+pub(crate) fn validate_dictionary_word(word: &str) -> FlowyResult<&str> {
+    let word = word.trim();
+    if word.is_empty() || word.chars().any(char::is_whitespace) {
+        return Err(FlowyError::invalid_data().context(format!("Invalid dictionary word: {:?}", word)));
+    }
+    Ok(word)
+}
+
+/// Returns the words of the dictionary's content, sorted and without duplicates. The same word
+/// added on two devices is on two lines after they merge.
+pub(crate) fn dictionary_words(content: &str) -> Vec<String> {
+    let mut words = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_owned())
+        .collect::<Vec<String>>();
+    words.sort();
+    words.dedup();
+    words
+}
+
+/// Merges the words of the dictionary as sets, see `ContentMerge`. The words added and removed
+/// since the last sync are applied to the server's words, so the words added on the other
+/// devices are kept and a word removed here doesn't come back. Returns one word per line.
+pub(crate) fn merge_dictionary_content(base: &str, local: &str, server: &str) -> String {
+    let base = dictionary_words(base).into_iter().collect::<BTreeSet<String>>();
+    let local = dictionary_words(local).into_iter().collect::<BTreeSet<String>>();
+    let mut words = dictionary_words(server).into_iter().collect::<BTreeSet<String>>();
+    words.extend(local.difference(&base).cloned());
+    for word in base.difference(&local) {
+        words.remove(word);
+    }
+    // The document ends with a newline like the dictionary the words are inserted into.
+    let mut content = words.iter().map(|word| format!("{}\n", word)).collect::<String>();
+    content.push('\n');
+    content
+}
+
+/// Returns the intervals of the lines of the `word` including their newlines, the last line
+/// first, so they can be deleted one after another. The newline that ends the document is kept.
+pub(crate) fn dictionary_word_lines(content: &str, word: &str) -> Vec<Interval> {
+    matching_lines(content, |text| text.trim() == word)
+}
+
+/// Returns the intervals of the lines whose text `is_match`, like `dictionary_word_lines`.
+pub(crate) fn matching_lines<F>(content: &str, is_match: F) -> Vec<Interval>
+where
+    F: Fn(&str) -> bool,
+{
+    let content_len = content.encode_utf16().count();
+    let mut intervals = vec![];
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let len = line.encode_utf16().count();
+        let text = line.trim_end_matches('\n');
+        if is_match(text) {
+            let end = if offset + len == content_len {
+                offset + text.encode_utf16().count()
+            } else {
+                offset + len
+            };
+            intervals.push(Interval::new(offset, end));
+        }
+        offset += len;
+    }
+    intervals.reverse();
+    intervals
+}
+
+pub(crate) struct CustomDictionarySql {}
+
+impl CustomDictionarySql {
+    /// Returns the words of the workspace's dictionary in alphabetical order.
+    pub(crate) fn read_all(workspace_id: &str, conn: &SqliteConnection) -> FlowyResult<Vec<String>> {
+        let words = dsl::custom_dictionary
+            .filter(dsl::workspace_id.eq(workspace_id))
+            .select(dsl::word)
+            .order(dsl::word.asc())
+            .load::<String>(conn)?;
+        Ok(words)
+    }
+
+    /// Replaces the words of the workspace's dictionary with `words`. Returns false if the
+    /// dictionary already had these words.
+    pub(crate) fn replace_all(workspace_id: &str, words: &[String], conn: &SqliteConnection) -> FlowyResult<bool> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            if Self::read_all(workspace_id, conn)? == words {
+                return Ok(false);
+            }
+            let _ = diesel::delete(dsl::custom_dictionary.filter(dsl::workspace_id.eq(workspace_id))).execute(conn)?;
+            let records = words
+                .iter()
+                .map(|word| (dsl::workspace_id.eq(workspace_id), dsl::word.eq(word)))
+                .collect::<Vec<_>>();
+            let _ = insert_or_ignore_into(dsl::custom_dictionary)
+                .values(&records)
+                .execute(conn)?;
+            Ok(true)
+        })
+    }
+}
+
+/// Keeps the `custom_dictionary` table up to date with the dictionary document when the
+/// remote changes are applied to it, and tells the editor with the `DidUpdateCustomDictionary`
+/// notification.
+#[derive(Clone)]
+pub(crate) struct CustomDictionaryObserver {
+    workspace_id: String,
+    pool: Arc<ConnectionPool>,
+}
+
+impl CustomDictionaryObserver {
+    pub(crate) fn new(workspace_id: &str, pool: Arc<ConnectionPool>) -> Self {
+        Self {
+            workspace_id: workspace_id.to_owned(),
+            pool,
+        }
+    }
+
+    /// Saves the words of the `content`. Returns the words if they changed.
+    pub(crate) fn save(&self, content: &str) -> FlowyResult<Option<Vec<String>>> {
+        let words = dictionary_words(content);
+        let conn = self.pool.get()?;
+        if CustomDictionarySql::replace_all(&self.workspace_id, &words, &conn)? {
+            Ok(Some(words))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub(crate) fn did_receive_remote_change(&self, content: &str) {
+        match self.save(content) {
+            Ok(Some(words)) => send_anonymous_dart_notification(DocumentNotification::DidUpdateCustomDictionary)
+                .payload(CustomDictionaryPB {
+                    workspace_id: self.workspace_id.clone(),
+                    words,
+                })
+                .send(),
+            Ok(None) => {}
+            Err(e) => tracing::error!("Save the custom dictionary of {} failed: {:?}", self.workspace_id, e),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use lib_infra::chunked::ChunkedReader;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The size of the chunks the json of a document is written into, see
+/// `DocumentEditor::export_chunked`.
+pub const DOCUMENT_JSON_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The documents whose json is larger are opened through a reader instead of being returned
+/// in one buffer, see `DocumentConfig::streamed_open_threshold`.
+pub const DEFAULT_STREAMED_OPEN_THRESHOLD: usize = 4 * 1024 * 1024;
+
+/// What the open returns of the full document.
+#[derive(Debug)]
+pub enum DocumentContent {
+    /// The json of the document.
+    Inline(String),
+    /// The json is read by chunks with `DocumentManager::read_document_chunk`.
+    Streamed { handle: i64, len: usize },
+}
+
+/// The readers that aren't read for this long are dropped, e.g. the ones whose reading was
+/// abandoned without closing them, see `DocumentConfig::document_reader_timeout`.
+pub const DEFAULT_DOCUMENT_READER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The readers of the documents opened with `DocumentManager::open_document_reader`, by
+/// their handle. They're read from the FFI, which doesn't run on the runtime of the documents.
+pub(crate) struct DocumentReaders {
+    next_handle: AtomicI64,
+    readers: Mutex<HashMap<i64, (ChunkedReader, Instant)>>,
+    timeout: Duration,
+}
+
+impl DocumentReaders {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            next_handle: AtomicI64::new(0),
+            readers: Mutex::new(HashMap::new()),
+            timeout,
+        }
+    }
+
+    pub(crate) fn insert(&self, reader: ChunkedReader) -> i64 {
+        let handle = self.next_handle.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut readers = self.readers.lock().unwrap();
+        self.remove_expired(&mut readers);
+        readers.insert(handle, (reader, Instant::now()));
+        handle
+    }
+
+    /// Returns None if there is no reader with the `handle`, e.g. it expired. The reader is
+    /// dropped by the read that returns 0, i.e. the first one after the end.
+    pub(crate) fn read_chunk(&self, handle: i64, buf: &mut [u8]) -> Option<usize> {
+        let mut readers = self.readers.lock().unwrap();
+        self.remove_expired(&mut readers);
+        let (reader, last_read) = readers.get_mut(&handle)?;
+        let written = reader.read_chunk(buf);
+        *last_read = Instant::now();
+        if written == 0 && !buf.is_empty() {
+            readers.remove(&handle);
+        }
+        Some(written)
+    }
+
+    pub(crate) fn remove(&self, handle: i64) -> bool {
+        self.readers.lock().unwrap().remove(&handle).is_some()
+    }
+
+    fn remove_expired(&self, readers: &mut HashMap<i64, (ChunkedReader, Instant)>) {
+        readers.retain(|handle, (_, last_read)| {
+            let is_expired = last_read.elapsed() > self.timeout;
+            if is_expired {
+                tracing::warn!(
+                    "Drop the document reader {} that wasn't read for {:?}",
+                    handle,
+                    self.timeout
+                );
+            }
+            !is_expired
+        });
+    }
+}
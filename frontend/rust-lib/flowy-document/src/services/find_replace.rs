@@ -0,0 +1,185 @@
+use crate::services::rev_sqlite::FOLDER_OBJECT_SUFFIX;
+use crate::services::{custom_dictionary_workspace_id, doc_preferences_owner_id};
+use lib_ot::core::OperationTransform;
+use lib_ot::text_delta::{
+    content_hash, find_snippets, find_text, replace_matches, DeltaTextOperations, FindOptions, SearchSnippet,
+};
+
+/// The chars of the line on each side of a match that the preview shows.
+pub const FIND_REPLACE_SNIPPET_RADIUS: usize = 24;
+
+/// The documents that `DocumentManager::find_replace` looks into. The folder resolves a
+/// workspace or an app to the ids of its documents, see `DocumentScopeResolver`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FindReplaceScope {
+    /// Every document saved on this device.
+    All,
+    /// The documents of the views of the workspace with this id.
+    Workspace(String),
+    /// The documents of the views of the app with this id.
+    App(String),
+    DocIds(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindReplaceQuery {
+    pub pattern: String,
+    pub replacement: String,
+    pub options: FindOptions,
+}
+
+/// Why a document that has matches isn't changed by the replace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindReplaceSkip {
+    /// The folder, the custom dictionary of a workspace or the preferences of a document, their
+    /// content is only written by their own API.
+    ReadOnly,
+    /// The document stopped syncing after a sync loop, see `DocumentManager::resume_sync`.
+    Locked,
+}
+
+impl FindReplaceSkip {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            FindReplaceSkip::ReadOnly => "The document is read-only",
+            FindReplaceSkip::Locked => "The document is locked until its sync is resumed",
+        }
+    }
+}
+
+/// The matches of one document when the preview was made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FindReplaceDocPreview {
+    pub doc_id: String,
+    /// The `content_hash` of the document the matches were found in. The replace is skipped if
+    /// the document changed since.
+    pub content_hash: String,
+    /// The `content_hash` of the document once the matches are replaced. A document that has
+    /// this hash was already replaced by an interrupted apply.
+    pub replaced_hash: String,
+    pub snippets: Vec<SearchSnippet>,
+    pub skip: Option<FindReplaceSkip>,
+}
+
+impl FindReplaceDocPreview {
+    pub fn num_of_matches(&self) -> usize {
+        self.snippets.len()
+    }
+}
+
+/// What `DocumentManager::find_replace` returns, it's passed back as is to
+/// `DocumentManager::apply_find_replace`. The documents without any match are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindReplacePreview {
+    pub query: FindReplaceQuery,
+    pub documents: Vec<FindReplaceDocPreview>,
+}
+
+impl FindReplacePreview {
+    pub fn num_of_matches(&self) -> usize {
+        self.documents.iter().map(|document| document.num_of_matches()).sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FindReplaceOutcome {
+    /// The matches were replaced with one revision.
+    Replaced(usize),
+    /// The document was replaced by an apply of the same preview before, e.g. one that got
+    /// interrupted.
+    AlreadyReplaced,
+    /// The document changed since the preview, it must be previewed again.
+    Stale,
+    Skipped(FindReplaceSkip),
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FindReplaceDocReport {
+    pub doc_id: String,
+    pub outcome: FindReplaceOutcome,
+}
+
+/// The outcome of each document of the preview, in the order of the preview.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindReplaceReport {
+    pub documents: Vec<FindReplaceDocReport>,
+}
+
+impl FindReplaceReport {
+    pub fn outcome(&self, doc_id: &str) -> Option<&FindReplaceOutcome> {
+        self.documents
+            .iter()
+            .find(|report| report.doc_id == doc_id)
+            .map(|report| &report.outcome)
+    }
+
+    pub fn num_of_replaced(&self) -> usize {
+        self.documents
+            .iter()
+            .map(|report| match report.outcome {
+                FindReplaceOutcome::Replaced(n) => n,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    pub fn stale_doc_ids(&self) -> Vec<String> {
+        self.documents
+            .iter()
+            .filter(|report| report.outcome == FindReplaceOutcome::Stale)
+            .map(|report| report.doc_id.clone())
+            .collect()
+    }
+}
+
+/// Returns the reason to skip the document whatever its state, None if it can be replaced.
+pub(crate) fn read_only_skip(doc_id: &str) -> Option<FindReplaceSkip> {
+    if doc_id.ends_with(FOLDER_OBJECT_SUFFIX)
+        || custom_dictionary_workspace_id(doc_id).is_some()
+        || doc_preferences_owner_id(doc_id).is_some()
+    {
+        Some(FindReplaceSkip::ReadOnly)
+    } else {
+        None
+    }
+}
+
+/// Returns the preview of the document, None if the `query` doesn't match its text.
+pub(crate) fn preview_document(
+    doc_id: &str,
+    operations: &DeltaTextOperations,
+    query: &FindReplaceQuery,
+    skip: Option<FindReplaceSkip>,
+) -> Option<FindReplaceDocPreview> {
+    let snippets = find_snippets(operations, &query.pattern, query.options, FIND_REPLACE_SNIPPET_RADIUS);
+    if snippets.is_empty() {
+        return None;
+    }
+    // A replacement that can't be composed fails again in the apply, so the hash is only
+    // compared to a document that can't have it.
+    let replaced_hash = replacement_operations(operations, query)
+        .and_then(|(_, replacement)| operations.compose(&replacement).ok())
+        .map(|replaced| content_hash(&replaced))
+        .unwrap_or_default();
+    Some(FindReplaceDocPreview {
+        doc_id: doc_id.to_owned(),
+        content_hash: content_hash(operations),
+        replaced_hash,
+        snippets,
+        skip,
+    })
+}
+
+/// Returns the number of the matches of the `query` in the document with the operations that
+/// replace all of them in one change, None if there is no match.
+pub(crate) fn replacement_operations(
+    operations: &DeltaTextOperations,
+    query: &FindReplaceQuery,
+) -> Option<(usize, DeltaTextOperations)> {
+    let matches = find_text(operations, &query.pattern, query.options);
+    if matches.is_empty() {
+        return None;
+    }
+    Some((matches.len(), replace_matches(operations, &matches, &query.replacement)))
+}
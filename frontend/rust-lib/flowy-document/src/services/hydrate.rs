@@ -0,0 +1,238 @@
+use crate::services::rev_sqlite::DocumentChunkSql;
+use crate::DocumentCloudService;
+use flowy_database::ConnectionPool;
+use flowy_error::{internal_error, FlowyError, FlowyResult};
+use flowy_http_model::document::DocumentId;
+use flowy_http_model::revision::Revision;
+use flowy_http_model::util::md5;
+use flowy_revision_persistence::{RevisionState, SyncRecord};
+use flowy_sync::util::{cal_diff, make_operations_from_revisions};
+use lib_ot::core::{AttributeHashMap, OperationTransform};
+use lib_ot::text_delta::{DeltaTextOperationBuilder, DeltaTextOperations};
+use std::sync::Arc;
+
+/// Fetches the delta document from the server chunk by chunk and saves it as the document's
+/// only revision, see `stage_document_chunks`. The local revisions that the server doesn't
+/// have are kept, the document isn't hydrated then.
+///
+/// Returns false if the server doesn't stream the documents.
+pub(crate) async fn hydrate_document_in_chunks(
+    server: &Arc<dyn DocumentCloudService>,
+    token: &str,
+    doc_id: &str,
+    pool: Arc<ConnectionPool>,
+) -> FlowyResult<bool> {
+    match stage_document_chunks(server, token, doc_id, &pool).await? {
+        None => Ok(false),
+        Some(document) => {
+            document.compose_revision(doc_id, true, &pool)?;
+            Ok(true)
+        }
+    }
+}
+
+/// The server's document fetched by `DocumentManager::hydrate_document`.
+pub(crate) enum ServerDocument {
+    /// The document streamed in chunks, they're staged on the disk until `compose_revision`
+    /// or `clear` is called.
+    Staged {
+        base_rev_id: i64,
+        rev_id: i64,
+    },
+    Fetched(Vec<Revision>),
+}
+
+impl ServerDocument {
+    pub(crate) fn rev_id(&self) -> i64 {
+        match self {
+            ServerDocument::Staged { rev_id, .. } => *rev_id,
+            ServerDocument::Fetched(revisions) => revisions.last().map(|revision| revision.rev_id).unwrap_or(0),
+        }
+    }
+
+    /// Returns the revisions of the document. The staged document is read into memory, only
+    /// the merge with the local revisions needs it.
+    pub(crate) fn read_revisions(&self, doc_id: &str, pool: &Arc<ConnectionPool>) -> FlowyResult<Vec<Revision>> {
+        match self {
+            ServerDocument::Staged { base_rev_id, rev_id } => {
+                let bytes = DocumentChunkSql::read(doc_id, &*pool.get()?)?;
+                let doc_md5 = md5(&bytes);
+                Ok(vec![Revision::new(doc_id, *base_rev_id, *rev_id, bytes, doc_md5)])
+            }
+            ServerDocument::Fetched(revisions) => Ok(revisions.clone()),
+        }
+    }
+
+    /// Replaces the revisions of the staged document with one revision made of its chunks.
+    /// With `keep_unsynced`, it fails if the document got a revision that the server doesn't
+    /// have in the meantime, e.g. it was opened and edited.
+    pub(crate) fn compose_revision(
+        &self,
+        doc_id: &str,
+        keep_unsynced: bool,
+        pool: &Arc<ConnectionPool>,
+    ) -> FlowyResult<()> {
+        if let ServerDocument::Staged { base_rev_id, rev_id } = self {
+            let conn = pool.get()?;
+            let result = DocumentChunkSql::compose_revision(doc_id, *base_rev_id, *rev_id, keep_unsynced, &conn);
+            if result.is_err() {
+                DocumentChunkSql::clear(doc_id, &conn)?;
+            }
+            result?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn clear(&self, doc_id: &str, pool: &Arc<ConnectionPool>) -> FlowyResult<()> {
+        if let ServerDocument::Staged { .. } = self {
+            DocumentChunkSql::clear(doc_id, &*pool.get()?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fetches the delta document from the server chunk by chunk and stages it on the disk. Each
+/// chunk is written once it's received, so the memory used is bounded by the size of a chunk
+/// instead of the size of the document.
+///
+/// Returns None if the server doesn't stream the documents. Nothing is left staged if it fails.
+pub(crate) async fn stage_document_chunks(
+    server: &Arc<dyn DocumentCloudService>,
+    token: &str,
+    doc_id: &str,
+    pool: &Arc<ConnectionPool>,
+) -> FlowyResult<Option<ServerDocument>> {
+    match write_document_chunks(server, token, doc_id, pool).await {
+        Ok(rev_ids) => Ok(rev_ids.map(|(base_rev_id, rev_id)| ServerDocument::Staged { base_rev_id, rev_id })),
+        Err(e) => {
+            DocumentChunkSql::clear(doc_id, &*pool.get()?)?;
+            Err(e)
+        }
+    }
+}
+
+/// Returns the base_rev_id and the rev_id of the streamed document, or None if the server
+/// doesn't stream the documents.
+async fn write_document_chunks(
+    server: &Arc<dyn DocumentCloudService>,
+    token: &str,
+    doc_id: &str,
+    pool: &Arc<ConnectionPool>,
+) -> FlowyResult<Option<(i64, i64)>> {
+    DocumentChunkSql::clear(doc_id, &*pool.get()?)?;
+    let mut rev_ids: Option<(i64, i64)> = None;
+    let mut chunk_index = 0;
+    loop {
+        let params: DocumentId = doc_id.to_string().into();
+        let chunk = match server.fetch_document_chunk(token, params, chunk_index).await? {
+            Some(chunk) => chunk,
+            None if chunk_index == 0 => return Ok(None),
+            None => {
+                return Err(FlowyError::internal().context(format!(
+                    "The server stopped streaming the document:{} at the chunk:{}",
+                    doc_id, chunk_index
+                )))
+            }
+        };
+        if chunk.doc_id != doc_id {
+            return Err(FlowyError::invalid_data().context(format!(
+                "Expect the chunk of the document:{}, but receive the chunk of the document:{}",
+                doc_id, chunk.doc_id
+            )));
+        }
+        match rev_ids {
+            None => rev_ids = Some((chunk.base_rev_id, chunk.rev_id)),
+            Some((_, rev_id)) if rev_id != chunk.rev_id => {
+                return Err(FlowyError::internal().context(format!(
+                    "The document:{} changed from the revision:{} to the revision:{} while it was fetched",
+                    doc_id, rev_id, chunk.rev_id
+                )));
+            }
+            Some(_) => {}
+        }
+
+        let operations = chunk_operations(&chunk.data)?;
+        // An empty chunk would leave an empty element in the concatenated json.
+        if !operations.is_empty() {
+            DocumentChunkSql::write(doc_id, chunk_index as i32, operations, &*pool.get()?)?;
+        }
+        if chunk.is_last {
+            return Ok(rev_ids);
+        }
+        chunk_index += 1;
+    }
+}
+
+/// Checks that the chunk is made of inserts, and returns the json of its operations without
+/// the surrounding brackets.
+fn chunk_operations(data: &[u8]) -> FlowyResult<&str> {
+    let operations = DeltaTextOperations::from_bytes(data)?;
+    if !operations.ops.iter().all(|operation| operation.is_insert()) {
+        return Err(FlowyError::invalid_data().context("The chunk of the document must only contain inserts"));
+    }
+    let json = std::str::from_utf8(data).map_err(internal_error)?.trim();
+    json.strip_prefix('[')
+        .and_then(|json| json.strip_suffix(']'))
+        .map(str::trim)
+        .ok_or_else(|| FlowyError::invalid_data().context("The chunk of the document must be a json array"))
+}
+
+/// Returns the rev_id of the first revision of the closed document that the server doesn't
+/// have, the server's document is at `server_rev_id`.
+pub(crate) fn first_unsynced_rev_id(records: &[SyncRecord], server_rev_id: i64) -> Option<i64> {
+    records
+        .iter()
+        .find(|record| record.state == RevisionState::Sync && record.revision.rev_id > server_rev_id)
+        .map(|record| record.revision.rev_id)
+}
+
+/// Returns the local changes starting from `next_sync_rev_id` rebased onto the server's
+/// document.
+///
+/// The server only provides the content, so the server's changes are the diff between the
+/// synced local content and the server's content. The local changes are transformed against
+/// them like the revisions that conflict with the server.
+pub(crate) fn rebase_unsynced_revisions(
+    local_revisions: Vec<Revision>,
+    next_sync_rev_id: i64,
+    server_operations: &DeltaTextOperations,
+) -> FlowyResult<DeltaTextOperations> {
+    let (synced, unsynced): (Vec<Revision>, Vec<Revision>) = local_revisions
+        .into_iter()
+        .partition(|revision| revision.rev_id < next_sync_rev_id);
+    let synced_operations: DeltaTextOperations = make_operations_from_revisions(synced)?;
+    let unsynced_operations: DeltaTextOperations = make_operations_from_revisions(unsynced)?;
+    let server_changes = cal_diff::<AttributeHashMap>(synced_operations.content()?, server_operations.content()?)
+        .unwrap_or_else(|| {
+            DeltaTextOperationBuilder::new()
+                .retain(synced_operations.utf16_target_len)
+                .build()
+        });
+    let (_, rebased_operations) = server_changes.transform(&unsynced_operations)?;
+    Ok(rebased_operations)
+}
+
+/// Returns the server's revisions followed by the revision of the unsynced local changes
+/// rebased onto them, see `rebase_unsynced_revisions`. The closed document is reset with the
+/// returned revisions, so the local changes get pushed once it's opened.
+pub(crate) fn merge_with_server_revisions(
+    doc_id: &str,
+    local_revisions: Vec<Revision>,
+    next_sync_rev_id: i64,
+    mut server_revisions: Vec<Revision>,
+) -> FlowyResult<Vec<Revision>> {
+    let server_operations: DeltaTextOperations = make_operations_from_revisions(server_revisions.clone())?;
+    let rebased_operations = rebase_unsynced_revisions(local_revisions, next_sync_rev_id, &server_operations)?;
+    if !rebased_operations.is_noop() {
+        let server_rev_id = server_revisions.last().map(|revision| revision.rev_id).unwrap_or(0);
+        let document_md5 = md5(&server_operations.compose(&rebased_operations)?.json_bytes());
+        server_revisions.push(Revision::new(
+            doc_id,
+            server_rev_id,
+            server_rev_id + 1,
+            rebased_operations.json_bytes(),
+            document_md5,
+        ));
+    }
+    Ok(server_revisions)
+}
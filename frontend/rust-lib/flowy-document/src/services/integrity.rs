@@ -0,0 +1,136 @@
+use flowy_database::{
+    prelude::*,
+    schema::{document_repair_audit, document_repair_audit::dsl},
+};
+use flowy_error::FlowyResult;
+use lib_infra::util::timestamp;
+use lib_ot::errors::{OTError, OTErrorCode};
+
+/// Why the payload of a stored revision can't be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadIssue {
+    InvalidUtf8,
+    /// The payload is valid UTF-8, but it isn't the JSON of a delta.
+    UndecodableJson,
+}
+
+impl PayloadIssue {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PayloadIssue::InvalidUtf8 => "invalid_utf8",
+            PayloadIssue::UndecodableJson => "undecodable_json",
+        }
+    }
+}
+
+impl std::convert::From<&OTError> for PayloadIssue {
+    fn from(error: &OTError) -> Self {
+        match error.code {
+            OTErrorCode::InvalidUtf8 => PayloadIssue::InvalidUtf8,
+            _ => PayloadIssue::UndecodableJson,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidRevision {
+    pub doc_id: String,
+    pub rev_id: i64,
+    pub issue: PayloadIssue,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepairOutcome {
+    /// The invalid bytes were replaced with U+FFFD and the payload could be parsed.
+    Recovered,
+    /// The revision is kept as it is but skipped when its document is read.
+    Quarantined,
+}
+
+impl RepairOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RepairOutcome::Recovered => "recovered",
+            RepairOutcome::Quarantined => "quarantined",
+        }
+    }
+}
+
+/// The record of one repaired revision, it's saved in the document_repair_audit table.
+#[derive(Debug, Clone)]
+pub struct RepairAuditEntry {
+    pub doc_id: String,
+    pub rev_id: i64,
+    pub issue: PayloadIssue,
+    pub outcome: RepairOutcome,
+    pub detail: String,
+    pub timestamp: i64,
+}
+
+impl RepairAuditEntry {
+    pub(crate) fn new(invalid_revision: InvalidRevision, outcome: RepairOutcome) -> Self {
+        Self {
+            doc_id: invalid_revision.doc_id,
+            rev_id: invalid_revision.rev_id,
+            issue: invalid_revision.issue,
+            outcome,
+            detail: invalid_revision.error,
+            timestamp: timestamp(),
+        }
+    }
+}
+
+pub(crate) fn write_repair_audit(entry: &RepairAuditEntry, conn: &SqliteConnection) -> FlowyResult<()> {
+    let record = (
+        dsl::object_id.eq(&entry.doc_id),
+        dsl::rev_id.eq(entry.rev_id),
+        dsl::issue.eq(entry.issue.as_str()),
+        dsl::outcome.eq(entry.outcome.as_str()),
+        dsl::detail.eq(&entry.detail),
+        dsl::timestamp.eq(entry.timestamp),
+    );
+    let _ = insert_into(dsl::document_repair_audit).values(record).execute(conn)?;
+    Ok(())
+}
+
+/// Returns the audit entries of the repairs, the oldest first.
+pub fn read_repair_audit(conn: &SqliteConnection) -> FlowyResult<Vec<RepairAuditEntry>> {
+    let records = dsl::document_repair_audit
+        .order(dsl::id.asc())
+        .load::<RepairAuditRecord>(conn)?;
+    Ok(records.into_iter().map(RepairAuditEntry::from).collect())
+}
+
+#[derive(PartialEq, Clone, Debug, Queryable, Identifiable, Insertable)]
+#[table_name = "document_repair_audit"]
+struct RepairAuditRecord {
+    id: i32,
+    object_id: String,
+    rev_id: i64,
+    issue: String,
+    outcome: String,
+    detail: String,
+    timestamp: i64,
+}
+
+impl std::convert::From<RepairAuditRecord> for RepairAuditEntry {
+    fn from(record: RepairAuditRecord) -> Self {
+        let issue = match record.issue.as_str() {
+            "invalid_utf8" => PayloadIssue::InvalidUtf8,
+            _ => PayloadIssue::UndecodableJson,
+        };
+        let outcome = match record.outcome.as_str() {
+            "recovered" => RepairOutcome::Recovered,
+            _ => RepairOutcome::Quarantined,
+        };
+        Self {
+            doc_id: record.object_id,
+            rev_id: record.rev_id,
+            issue,
+            outcome,
+            detail: record.detail,
+            timestamp: record.timestamp,
+        }
+    }
+}
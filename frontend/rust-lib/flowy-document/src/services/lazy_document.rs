@@ -0,0 +1,235 @@
+use flowy_error::{FlowyError, FlowyResult};
+use flowy_http_model::revision::Revision;
+use lib_ot::core::{DeltaOperation, Interval, OperationTransform};
+use lib_ot::text_delta::DeltaTextOperations;
+use std::cmp::{max, min};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// The number of the composed regions a `LazyDocument` keeps, the least recently used one is
+/// dropped past it.
+const REGION_CACHE_CAPACITY: usize = 16;
+
+/// A document that is never composed as a whole. It keeps the operations of its revisions and
+/// composes the regions that are asked for, see `DocumentManager::lazy_document`.
+///
+/// A region is composed by following it back through the revisions: each revision tells which
+/// part of the document before it ends up in the region, so only that part is composed with the
+/// operations of the revision that touch the region.
+pub struct LazyDocument {
+    /// The document the revisions are composed onto, i.e. the last snapshot or nothing.
+    base: DeltaTextOperations,
+    revisions: Vec<LazyRevision>,
+    len: usize,
+    /// The composed regions by their interval, the most recently used last.
+    regions: Mutex<VecDeque<(Interval, DeltaTextOperations)>>,
+}
+
+struct LazyRevision {
+    operations: DeltaTextOperations,
+    /// The length of the document the revision is composed onto. The operations may stop before
+    /// its end, the rest is retained.
+    base_len: usize,
+}
+
+impl LazyDocument {
+    pub fn new(base: DeltaTextOperations, revisions: Vec<Revision>) -> FlowyResult<Self> {
+        let mut len = base.utf16_target_len;
+        let mut lazy_revisions = Vec::with_capacity(revisions.len());
+        for revision in revisions {
+            if revision.bytes.is_empty() {
+                return Err(FlowyError::internal().context(format!("The revision:{} is empty", revision.rev_id)));
+            }
+            let operations = DeltaTextOperations::from_bytes(&revision.bytes)?;
+            let base_len = len;
+            len = operations.utf16_target_len + base_len.saturating_sub(operations.utf16_base_len);
+            lazy_revisions.push(LazyRevision { operations, base_len });
+        }
+        Ok(Self {
+            base,
+            revisions: lazy_revisions,
+            len,
+            regions: Mutex::new(VecDeque::with_capacity(REGION_CACHE_CAPACITY)),
+        })
+    }
+
+    /// The length of the document in utf16 code units.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the operations of the document in `[start, start + len)`, cut the same way as
+    /// `DeltaOperations::slice` cuts the fully composed document. The region is clamped to the
+    /// end of the document. The last composed regions are cached, a region inside a cached one
+    /// is sliced from it.
+    pub fn range(&self, start: usize, len: usize) -> FlowyResult<DeltaTextOperations> {
+        let start = min(start, self.len);
+        let end = min(start.saturating_add(len), self.len);
+        let interval = Interval::new(start, end);
+        if interval.is_empty() {
+            return Ok(DeltaTextOperations::default());
+        }
+
+        if let Some(region) = self.cached_region(interval) {
+            return Ok(region);
+        }
+        let region = self.compose_region(interval)?;
+        let mut regions = self.regions.lock().unwrap();
+        if regions.len() >= REGION_CACHE_CAPACITY {
+            regions.pop_front();
+        }
+        regions.push_back((interval, region.clone()));
+        Ok(region)
+    }
+
+    fn cached_region(&self, interval: Interval) -> Option<DeltaTextOperations> {
+        let mut regions = self.regions.lock().unwrap();
+        let index = regions
+            .iter()
+            .rposition(|(cached, _)| cached.start <= interval.start && interval.end <= cached.end)?;
+        let (cached, region) = regions.remove(index)?;
+        let sliced = if cached == interval {
+            region.clone()
+        } else {
+            region.slice(Interval::new(
+                interval.start - cached.start,
+                interval.end - cached.start,
+            ))
+        };
+        regions.push_back((cached, region));
+        Some(sliced)
+    }
+
+    #[cfg(test)]
+    fn num_of_cached_regions(&self) -> usize {
+        self.regions.lock().unwrap().len()
+    }
+
+    fn compose_region(&self, interval: Interval) -> FlowyResult<DeltaTextOperations> {
+        let mut interval = interval;
+        let mut restricted_operations = vec![];
+        for revision in self.revisions.iter().rev() {
+            if interval.is_empty() {
+                break;
+            }
+            let (base_interval, operations) = restrict(revision, interval);
+            restricted_operations.push(operations);
+            interval = base_interval;
+        }
+
+        let mut region = if interval.is_empty() {
+            DeltaTextOperations::default()
+        } else {
+            self.base.slice(interval)
+        };
+        for operations in restricted_operations.iter().rev() {
+            region = region.compose(operations)?;
+        }
+        Ok(region)
+    }
+}
+
+/// Returns the operations of the `revision` that produce the `interval` of the document after
+/// it, with the interval of the document before it they're composed onto.
+fn restrict(revision: &LazyRevision, interval: Interval) -> (Interval, DeltaTextOperations) {
+    let trailing_retain = revision
+        .base_len
+        .checked_sub(revision.operations.utf16_base_len)
+        .filter(|n| *n > 0)
+        .map(DeltaOperation::retain);
+
+    let mut restricted = DeltaTextOperations::default();
+    let mut base_interval: Option<Interval> = None;
+    let (mut offset, mut base_offset) = (0, 0);
+    for op in revision.operations.ops.iter().chain(trailing_retain.iter()) {
+        if offset >= interval.end {
+            break;
+        }
+        let len = op.len();
+        if let DeltaOperation::Delete(n) = op {
+            // The deletes before the region don't matter, the ones inside it remove the text
+            // between the parts of the region.
+            if let Some(base_interval) = base_interval.as_mut() {
+                restricted.delete(*n);
+                base_interval.end = base_offset + n;
+            }
+            base_offset += n;
+            continue;
+        }
+
+        let from = max(offset, interval.start);
+        let to = min(offset + len, interval.end);
+        if from < to {
+            let shrink = Interval::new(from - offset, to - offset);
+            if let Some(op) = op.shrink(shrink) {
+                restricted.add(op);
+            }
+            let consumed = if op.is_retain() {
+                Interval::new(base_offset + shrink.start, base_offset + shrink.end)
+            } else {
+                Interval::new(base_offset, base_offset)
+            };
+            base_interval = Some(match base_interval {
+                None => consumed,
+                Some(base_interval) => Interval::new(base_interval.start, consumed.end),
+            });
+        }
+        offset += len;
+        if op.is_retain() {
+            base_offset += len;
+        }
+    }
+    (base_interval.unwrap_or_else(|| Interval::new(0, 0)), restricted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use lib_ot::core::AttributeHashMap;
+    use lib_ot::text_delta::{BuildInTextAttribute, DeltaTextOperationBuilder};
+
+    #[test]
+    fn lazy_document_delete_inside_region_test() {
+        let mut bold = AttributeHashMap::new();
+        bold.insert_entry(BuildInTextAttribute::Bold(true));
+        let first = DeltaTextOperationBuilder::new().insert("abcXYZdef\n").build();
+        let second = DeltaTextOperationBuilder::new()
+            .retain(3)
+            .delete(3)
+            .insert("Q")
+            .retain_with_attributes(2, bold)
+            .build();
+        let full = first.compose(&second).unwrap();
+        let revisions = vec![
+            Revision::new("doc", 0, 1, Bytes::from(first.json_str()), ""),
+            Revision::new("doc", 1, 2, Bytes::from(second.json_str()), ""),
+        ];
+        let document = LazyDocument::new(DeltaTextOperations::default(), revisions).unwrap();
+        assert_eq!(document.len(), full.utf16_target_len);
+        for &(start, end) in [(2, 5), (3, 4), (0, 8), (4, 8), (6, 20)].iter() {
+            let expected = full.slice(Interval::new(start, min(end, full.utf16_target_len)));
+            assert_eq!(document.range(start, end - start).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn lazy_document_region_cache_is_bounded_test() {
+        let text = "abcdefghij".repeat(10);
+        let operations = DeltaTextOperationBuilder::new().insert(&text).build();
+        let revisions = vec![Revision::new("doc", 0, 1, Bytes::from(operations.json_str()), "")];
+        let document = LazyDocument::new(DeltaTextOperations::default(), revisions).unwrap();
+        for start in 0..REGION_CACHE_CAPACITY * 2 {
+            document.range(start, 3).unwrap();
+        }
+        assert_eq!(document.num_of_cached_regions(), REGION_CACHE_CAPACITY);
+
+        // The regions are still composed right once they're dropped from the cache.
+        assert_eq!(document.range(0, 3).unwrap(), operations.slice(Interval::new(0, 3)));
+        assert_eq!(document.num_of_cached_regions(), REGION_CACHE_CAPACITY);
+    }
+}
@@ -0,0 +1,225 @@
+use flowy_error::FlowyResult;
+use std::time::Duration;
+
+/// The tasks of `DocumentManager::run_maintenance`, combined with `union`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MaintenanceTasks(pub u32);
+
+impl MaintenanceTasks {
+    pub const NONE: MaintenanceTasks = MaintenanceTasks(0);
+    pub const FLUSH: MaintenanceTasks = MaintenanceTasks(1 << 0);
+    pub const COMPACTION: MaintenanceTasks = MaintenanceTasks(1 << 1);
+    pub const PURGE: MaintenanceTasks = MaintenanceTasks(1 << 2);
+    pub const BLOB_GC: MaintenanceTasks = MaintenanceTasks(1 << 3);
+    pub const VACUUM: MaintenanceTasks = MaintenanceTasks(1 << 4);
+    pub const SNAPSHOT_REFRESH: MaintenanceTasks = MaintenanceTasks(1 << 5);
+    pub const ALL: MaintenanceTasks = MaintenanceTasks(
+        Self::FLUSH.0
+            | Self::COMPACTION.0
+            | Self::PURGE.0
+            | Self::BLOB_GC.0
+            | Self::VACUUM.0
+            | Self::SNAPSHOT_REFRESH.0,
+    );
+
+    pub fn contains(&self, other: MaintenanceTasks) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn union(&self, other: MaintenanceTasks) -> MaintenanceTasks {
+        MaintenanceTasks(self.0 | other.0)
+    }
+
+    /// Returns the selected tasks in the order they run.
+    pub fn tasks(&self) -> Vec<MaintenanceTask> {
+        MaintenanceTask::ORDER
+            .iter()
+            .copied()
+            .filter(|task| self.contains(task.flag()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaintenanceTask {
+    /// Writes the deferred saves of the opened documents to disk.
+    Flush,
+    /// Merges the runs of small acked revisions of each document, see
+    /// `RevisionManager::compact_small_revisions`.
+    Compaction,
+    /// Merges the expired revisions of each document, see
+    /// `RevisionManager::sweep_expired_revisions`.
+    Purge,
+    /// Deletes the attachments that no document has referred to for the grace period.
+    BlobGc,
+    /// Rebuilds the database file without the pages freed by the tasks before it.
+    Vacuum,
+    /// Generates a new snapshot of each document.
+    SnapshotRefresh,
+}
+
+impl MaintenanceTask {
+    /// The tasks that rewrite the revisions go after the flush, so they see every revision, and
+    /// the vacuum goes after all the tasks that delete rows.
+    pub const ORDER: [MaintenanceTask; 6] = [
+        MaintenanceTask::Flush,
+        MaintenanceTask::Compaction,
+        MaintenanceTask::Purge,
+        MaintenanceTask::BlobGc,
+        MaintenanceTask::Vacuum,
+        MaintenanceTask::SnapshotRefresh,
+    ];
+
+    pub fn flag(&self) -> MaintenanceTasks {
+        match self {
+            MaintenanceTask::Flush => MaintenanceTasks::FLUSH,
+            MaintenanceTask::Compaction => MaintenanceTasks::COMPACTION,
+            MaintenanceTask::Purge => MaintenanceTasks::PURGE,
+            MaintenanceTask::BlobGc => MaintenanceTasks::BLOB_GC,
+            MaintenanceTask::Vacuum => MaintenanceTasks::VACUUM,
+            MaintenanceTask::SnapshotRefresh => MaintenanceTasks::SNAPSHOT_REFRESH,
+        }
+    }
+
+    /// The task that must not have failed for this task to run. The tasks that read the
+    /// revisions from disk would miss the deferred saves if the flush failed. The vacuum and the
+    /// snapshots are still worth running whatever happened before them.
+    pub fn dependency(&self) -> Option<MaintenanceTask> {
+        match self {
+            MaintenanceTask::Compaction | MaintenanceTask::Purge | MaintenanceTask::BlobGc => {
+                Some(MaintenanceTask::Flush)
+            }
+            MaintenanceTask::Flush | MaintenanceTask::Vacuum | MaintenanceTask::SnapshotRefresh => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaintenanceStatus {
+    /// The task ran. In a dry run, it only estimated what it would reclaim.
+    Done,
+    /// The task has no dry-run mode, so it didn't run in the dry run.
+    NoDryRun,
+    /// The task didn't run because the task it depends on failed.
+    Skipped(MaintenanceTask),
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct MaintenanceTaskReport {
+    pub task: MaintenanceTask,
+    pub status: MaintenanceStatus,
+    pub duration: Duration,
+    /// The bytes of the database the task freed, or would free in a dry run.
+    pub reclaimed_bytes: u64,
+}
+
+/// The outcome of each selected task of `DocumentManager::run_maintenance`, in the order they
+/// ran. A failed task doesn't stop the tasks after it unless they depend on it.
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceReport {
+    pub dry_run: bool,
+    pub tasks: Vec<MaintenanceTaskReport>,
+}
+
+impl MaintenanceReport {
+    pub fn new(dry_run: bool) -> Self {
+        Self { dry_run, tasks: vec![] }
+    }
+
+    pub fn get(&self, task: MaintenanceTask) -> Option<&MaintenanceTaskReport> {
+        self.tasks.iter().find(|report| report.task == task)
+    }
+
+    pub fn total_duration(&self) -> Duration {
+        self.tasks.iter().map(|report| report.duration).sum()
+    }
+
+    pub fn total_reclaimed_bytes(&self) -> u64 {
+        self.tasks.iter().map(|report| report.reclaimed_bytes).sum()
+    }
+
+    pub fn has_failure(&self) -> bool {
+        self.tasks
+            .iter()
+            .any(|report| matches!(report.status, MaintenanceStatus::Failed(_)))
+    }
+
+    /// Returns the failed or skipped task that the `task` depends on, the `task` must be
+    /// skipped then.
+    pub(crate) fn blocking_dependency(&self, task: MaintenanceTask) -> Option<MaintenanceTask> {
+        let dependency = task.dependency()?;
+        match self.get(dependency)?.status {
+            MaintenanceStatus::Failed(_) | MaintenanceStatus::Skipped(_) => Some(dependency),
+            MaintenanceStatus::Done | MaintenanceStatus::NoDryRun => None,
+        }
+    }
+
+    pub(crate) fn skip(&mut self, task: MaintenanceTask, dependency: MaintenanceTask) {
+        tracing::warn!("[Maintenance]: skip {:?}, {:?} failed", task, dependency);
+        self.tasks.push(MaintenanceTaskReport {
+            task,
+            status: MaintenanceStatus::Skipped(dependency),
+            duration: Duration::ZERO,
+            reclaimed_bytes: 0,
+        });
+    }
+
+    /// Records the `result` of the task, None if the task has no dry-run mode.
+    pub(crate) fn add(&mut self, task: MaintenanceTask, duration: Duration, result: FlowyResult<Option<u64>>) {
+        let (status, reclaimed_bytes) = match result {
+            Ok(Some(reclaimed_bytes)) => (MaintenanceStatus::Done, reclaimed_bytes),
+            Ok(None) => (MaintenanceStatus::NoDryRun, 0),
+            Err(e) => {
+                tracing::error!("[Maintenance]: {:?} failed: {:?}", task, e);
+                (MaintenanceStatus::Failed(format!("{:?}", e)), 0)
+            }
+        };
+        self.tasks.push(MaintenanceTaskReport {
+            task,
+            status,
+            duration,
+            reclaimed_bytes,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flowy_error::FlowyError;
+
+    #[test]
+    fn maintenance_tasks_order_test() {
+        let tasks = MaintenanceTasks::SNAPSHOT_REFRESH
+            .union(MaintenanceTasks::FLUSH)
+            .union(MaintenanceTasks::VACUUM);
+        assert_eq!(
+            tasks.tasks(),
+            vec![
+                MaintenanceTask::Flush,
+                MaintenanceTask::Vacuum,
+                MaintenanceTask::SnapshotRefresh
+            ]
+        );
+        assert_eq!(MaintenanceTasks::ALL.tasks(), MaintenanceTask::ORDER.to_vec());
+        assert!(MaintenanceTasks::NONE.tasks().is_empty());
+    }
+
+    #[test]
+    fn maintenance_failed_dependency_test() {
+        let mut report = MaintenanceReport::new(false);
+        report.add(MaintenanceTask::Flush, Duration::ZERO, Err(FlowyError::internal()));
+        assert_eq!(
+            report.blocking_dependency(MaintenanceTask::Compaction),
+            Some(MaintenanceTask::Flush)
+        );
+        assert_eq!(report.blocking_dependency(MaintenanceTask::Vacuum), None);
+        assert_eq!(report.blocking_dependency(MaintenanceTask::SnapshotRefresh), None);
+
+        // The flush that didn't run in the dry run doesn't block the tasks after it.
+        let mut report = MaintenanceReport::new(true);
+        report.add(MaintenanceTask::Flush, Duration::ZERO, Ok(None));
+        assert_eq!(report.blocking_dependency(MaintenanceTask::BlobGc), None);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::services::rev_sqlite::{DeltaRevisionSql, PinnedRevisionSql};
+use crate::services::{validate_backup, Attachment, AttachmentSql};
+use flowy_database::{
+    insert_or_ignore_into,
+    prelude::*,
+    schema::{document_merge, document_merge::dsl},
+    ConnectionPool, DB_NAME,
+};
+use flowy_error::{ErrorCode, FlowyError, FlowyResult};
+use flowy_http_model::{revision::Revision, util::md5};
+use lib_ot::text_delta::{remap_attachments, remap_document_links, DeltaTextOperations};
+use nanoid::nanoid;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatabaseMergeSummary {
+    /// The id of each document of the other database mapped to the id of its copy. A merge that
+    /// is run again maps the documents to the same ids.
+    pub doc_ids: BTreeMap<String, String>,
+    /// The documents copied by this run. The others were copied by an earlier run, or failed.
+    pub num_of_merged: usize,
+    /// The attachments saved by this run.
+    pub num_of_attachments: usize,
+    /// The attachments whose data was already stored, the copies refer to the stored ones.
+    pub num_of_deduped_attachments: usize,
+    /// The documents that couldn't be copied, running the merge again retries them.
+    pub failed: Vec<String>,
+}
+
+/// Copies the delta documents of the database under `source_dir`, e.g. the database of another
+/// account on this device, into the database of the `pool`. Each document gets a new id, the
+/// links between the copied documents are rewritten to the new ids and the revisions keep their
+/// rev_ids. The attachments are copied once, by their data.
+///
+/// Each document is copied in its own transaction and recorded in the `document_merge` table, so
+/// a merge that stopped continues with the documents that weren't copied yet.
+pub(crate) fn merge_database(
+    source_dir: &Path,
+    user_dir: &str,
+    pool: Arc<ConnectionPool>,
+) -> FlowyResult<DatabaseMergeSummary> {
+    let conn = pool.get()?;
+    let source_path = validate_backup(source_dir, &conn)?;
+    let source = canonical_path(&source_path)?;
+    if source == canonical_path(&Path::new(user_dir).join(DB_NAME))? {
+        return Err(FlowyError::invalid_data().context("Can't merge the database into itself"));
+    }
+    let source_conn = SqliteConnection::establish(&source)
+        .map_err(|e| FlowyError::internal().context(format!("Open {} failed: {}", source, e)))?;
+
+    let source_doc_ids = DeltaRevisionSql::read_doc_ids(&source_conn)?;
+    let merges = conn.immediate_transaction::<_, FlowyError, _>(|| {
+        DocumentMergeSql::assign_doc_ids(&source, &source_doc_ids, &conn)
+    })?;
+    let doc_ids = merges
+        .iter()
+        .map(|(source_doc_id, (doc_id, _))| (source_doc_id.clone(), doc_id.clone()))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut summary = DatabaseMergeSummary::default();
+    let attachment_ids = merge_attachments(&source_conn, &conn, &mut summary)?;
+    for (source_doc_id, (doc_id, merged)) in merges {
+        if merged {
+            continue;
+        }
+        let result = merge_document(
+            &source,
+            &source_doc_id,
+            &doc_id,
+            &doc_ids,
+            &attachment_ids,
+            &source_conn,
+            &conn,
+        );
+        match result {
+            Ok(_) => summary.num_of_merged += 1,
+            Err(e) => {
+                tracing::error!("Merge the document {} of {} failed: {:?}", source_doc_id, source, e);
+                summary.failed.push(source_doc_id);
+            }
+        }
+    }
+    summary.doc_ids = doc_ids;
+    Ok(summary)
+}
+
+fn merge_document(
+    source: &str,
+    source_doc_id: &str,
+    doc_id: &str,
+    doc_ids: &BTreeMap<String, String>,
+    attachment_ids: &HashMap<String, String>,
+    source_conn: &SqliteConnection,
+    conn: &SqliteConnection,
+) -> FlowyResult<()> {
+    let revisions = DeltaRevisionSql::read_revisions(source_doc_id, source_conn)?
+        .into_iter()
+        .map(|revision| remap_revision(revision, doc_id, doc_ids, attachment_ids))
+        .collect::<Vec<_>>();
+    // The databases of the versions without the tags have no table of the tags.
+    let tags = match PinnedRevisionSql::read_tags(source_doc_id, source_conn) {
+        Err(e) if e.code == ErrorCode::RecordNotFound.value() => vec![],
+        result => result?,
+    };
+    conn.immediate_transaction::<_, FlowyError, _>(|| {
+        DeltaRevisionSql::write_revisions(revisions, conn)?;
+        for (tag, rev_id) in tags.iter() {
+            PinnedRevisionSql::insert_tag(doc_id, *rev_id, tag, conn)?;
+        }
+        DocumentMergeSql::set_merged(source, source_doc_id, conn)
+    })
+}
+
+/// Moves the revision to the document `doc_id` and points its links and attachment references
+/// to the copies. The revision that can't be parsed is copied as it is, the integrity check of
+/// this database reports it like it did in the other one.
+fn remap_revision(
+    revision: Revision,
+    doc_id: &str,
+    doc_ids: &BTreeMap<String, String>,
+    attachment_ids: &HashMap<String, String>,
+) -> Revision {
+    let remapped = DeltaTextOperations::from_bytes(&revision.bytes)
+        .ok()
+        .and_then(|operations| {
+            let linked = remap_document_links(&operations, |id| doc_ids.get(id).cloned());
+            let attached = remap_attachments(linked.as_ref().unwrap_or(&operations), |id| {
+                attachment_ids.get(id).cloned()
+            });
+            attached.or(linked)
+        });
+    match remapped {
+        None => Revision {
+            object_id: doc_id.to_owned(),
+            ..revision
+        },
+        Some(operations) => {
+            let bytes = operations.json_bytes();
+            let md5 = md5(&bytes);
+            Revision::new(doc_id, revision.base_rev_id, revision.rev_id, bytes, md5)
+        }
+    }
+}
+
+/// Saves the attachments of the other database. The attachment whose data is already stored is
+/// not saved again, and the attachment whose id is taken by other data gets a new id. Returns the
+/// id that each attachment of the other database is stored under, if it isn't its own id.
+///
+/// The attachments are read one at a time, and only the stored attachments with the same size
+/// are read to compare their data.
+fn merge_attachments(
+    source_conn: &SqliteConnection,
+    conn: &SqliteConnection,
+    summary: &mut DatabaseMergeSummary,
+) -> FlowyResult<HashMap<String, String>> {
+    let source_sizes = AttachmentSql::read_sizes(source_conn)?;
+    if source_sizes.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    conn.immediate_transaction::<_, FlowyError, _>(|| {
+        let mut stored = StoredAttachments::new(AttachmentSql::read_sizes(conn)?);
+        let mut attachment_ids = HashMap::new();
+        for (source_id, _) in source_sizes {
+            let attachment = match AttachmentSql::read(&source_id, source_conn)? {
+                None => continue,
+                Some(attachment) => attachment,
+            };
+            let size = attachment.data.len() as i64;
+            let hash = md5(&attachment.data);
+            if let Some(id) = stored.find(size, &hash, conn)? {
+                if id != attachment.id {
+                    attachment_ids.insert(attachment.id, id);
+                }
+                summary.num_of_deduped_attachments += 1;
+                continue;
+            }
+
+            let attachment = if AttachmentSql::exists(&attachment.id, conn)? {
+                let id = nanoid!(10);
+                attachment_ids.insert(attachment.id.clone(), id.clone());
+                Attachment { id, ..attachment }
+            } else {
+                attachment
+            };
+            AttachmentSql::save(&attachment, conn)?;
+            stored.insert(size, attachment.id, hash);
+            summary.num_of_attachments += 1;
+        }
+        Ok(attachment_ids)
+    })
+}
+
+/// The attachments of this database by the size of their data. The hash of the data of an
+/// attachment is computed when an attachment of the same size is looked up.
+struct StoredAttachments {
+    by_size: HashMap<i64, Vec<(String, Option<String>)>>,
+}
+
+impl StoredAttachments {
+    fn new(sizes: Vec<(String, i64)>) -> Self {
+        let mut by_size: HashMap<i64, Vec<(String, Option<String>)>> = HashMap::new();
+        for (id, size) in sizes {
+            by_size.entry(size).or_default().push((id, None));
+        }
+        Self { by_size }
+    }
+
+    /// Returns the id of the stored attachment whose data has the `hash`.
+    fn find(&mut self, size: i64, hash: &str, conn: &SqliteConnection) -> FlowyResult<Option<String>> {
+        let attachments = match self.by_size.get_mut(&size) {
+            None => return Ok(None),
+            Some(attachments) => attachments,
+        };
+        for (id, stored_hash) in attachments.iter_mut() {
+            if stored_hash.is_none() {
+                *stored_hash = AttachmentSql::read(id, conn)?.map(|attachment| md5(&attachment.data));
+            }
+            if stored_hash.as_deref() == Some(hash) {
+                return Ok(Some(id.clone()));
+            }
+        }
+        Ok(None)
+    }
+
+    fn insert(&mut self, size: i64, id: String, hash: String) {
+        self.by_size.entry(size).or_default().push((id, Some(hash)));
+    }
+}
+
+fn canonical_path(path: &Path) -> FlowyResult<String> {
+    let path = path
+        .canonicalize()
+        .map_err(|e| FlowyError::internal().context(format!("Resolve {:?} failed: {}", path, e)))?;
+    path.to_str()
+        .map(|path| path.to_owned())
+        .ok_or_else(|| FlowyError::invalid_data().context(format!("Invalid database path: {:?}", path)))
+}
+
+/// Reads and writes the `document_merge` table, which maps the documents of each merged database
+/// to their copies.
+struct DocumentMergeSql {}
+
+impl DocumentMergeSql {
+    /// Returns the id of the copy of each document and whether it's copied already. The documents
+    /// that weren't merged before get new ids, they're saved before any document is copied so the
+    /// links of a resumed merge point to the same copies.
+    fn assign_doc_ids(
+        source: &str,
+        source_doc_ids: &[String],
+        conn: &SqliteConnection,
+    ) -> FlowyResult<BTreeMap<String, (String, bool)>> {
+        for source_doc_id in source_doc_ids {
+            let record = (
+                dsl::source.eq(source),
+                dsl::source_doc_id.eq(source_doc_id),
+                dsl::doc_id.eq(nanoid!(10)),
+                dsl::merged.eq(false),
+            );
+            let _ = insert_or_ignore_into(document_merge::table)
+                .values(record)
+                .execute(conn)?;
+        }
+
+        let source_doc_ids = source_doc_ids.iter().collect::<HashSet<_>>();
+        let merges = dsl::document_merge
+            .filter(dsl::source.eq(source))
+            .select((dsl::source_doc_id, dsl::doc_id, dsl::merged))
+            .load::<(String, String, bool)>(conn)?
+            .into_iter()
+            .filter(|(source_doc_id, _, _)| source_doc_ids.contains(source_doc_id))
+            .map(|(source_doc_id, doc_id, merged)| (source_doc_id, (doc_id, merged)))
+            .collect();
+        Ok(merges)
+    }
+
+    fn set_merged(source: &str, source_doc_id: &str, conn: &SqliteConnection) -> FlowyResult<()> {
+        let row = dsl::document_merge
+            .filter(dsl::source.eq(source))
+            .filter(dsl::source_doc_id.eq(source_doc_id));
+        let _ = diesel::update(row).set(dsl::merged.eq(true)).execute(conn)?;
+        Ok(())
+    }
+}
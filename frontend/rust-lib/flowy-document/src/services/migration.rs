@@ -1,16 +1,22 @@
+use crate::dart_notification::{send_anonymous_dart_notification, DocumentNotification};
+use crate::entities::TableMigrationProgressPB;
 use crate::services::delta_migration::DeltaRevisionMigration;
 use crate::services::rev_sqlite::{DeltaRevisionSql, SQLiteDocumentRevisionPersistence};
+use crate::services::MigrationOutcome;
 use crate::DocumentDatabase;
 use bytes::Bytes;
 use flowy_database::kv::KV;
-use flowy_error::FlowyResult;
+use flowy_database::table_migration::{CopyTableMigration, MigrationStep};
+use flowy_database::SqliteConnection;
+use flowy_error::{FlowyError, FlowyResult};
 use flowy_http_model::revision::Revision;
 use flowy_http_model::util::md5;
 use flowy_revision_persistence::{RevisionDiskCache, SyncRecord};
 use flowy_sync::util::make_operations_from_revisions;
 use std::sync::Arc;
 
-const V1_MIGRATION: &str = "DOCUMENT_V1_MIGRATION";
+pub(crate) const V1_MIGRATION: &str = "DOCUMENT_V1_MIGRATION";
+
 pub(crate) struct DocumentMigration {
     user_id: String,
     database: Arc<dyn DocumentDatabase>,
@@ -22,10 +28,11 @@ impl DocumentMigration {
         Self { user_id, database }
     }
 
-    pub fn run_v1_migration(&self) -> FlowyResult<()> {
+    /// Returns false if the migration was completed before.
+    pub fn run_v1_migration(&self) -> FlowyResult<bool> {
         let key = migration_flag_key(&self.user_id, V1_MIGRATION);
         if KV::get_bool(&key) {
-            return Ok(());
+            return Ok(false);
         }
 
         let pool = self.database.db_pool()?;
@@ -66,9 +73,76 @@ impl DocumentMigration {
 
         KV::set_bool(&key, true);
         tracing::debug!("Run document v1 migration");
-        Ok(())
+        Ok(true)
+    }
+
+    /// Runs the copy-table migrations of the document tables batch by batch. The connection is
+    /// released between the batches, so the migration doesn't block the other database users.
+    /// A failed migration doesn't stop the ones after it, its error is returned in its outcome.
+    pub fn run_table_migrations(&self, migrations: &[CopyTableMigration]) -> FlowyResult<Vec<MigrationOutcome>> {
+        let pool = self.database.db_pool()?;
+        let mut outcomes = vec![];
+        for migration in migrations {
+            let mut outcome = MigrationOutcome::new(&migration.name);
+            loop {
+                let conn = pool.get()?;
+                let step = match migration.step(&*conn) {
+                    Ok(step) => step,
+                    Err(e) => {
+                        tracing::error!("[Document Migration]: {} failed: {:?}", migration.name, e);
+                        outcome.error = Some(format!("{:?}", e));
+                        break;
+                    }
+                };
+                match step {
+                    MigrationStep::Copied(progress) => {
+                        outcome.ran = true;
+                        tracing::trace!(
+                            "[Document Migration]: {} copied {}/{} rows",
+                            progress.name,
+                            progress.copied,
+                            progress.total
+                        );
+                        send_anonymous_dart_notification(DocumentNotification::DidUpdateTableMigration)
+                            .payload(TableMigrationProgressPB::from(progress))
+                            .send();
+                    }
+                    MigrationStep::Swapped => {
+                        tracing::debug!("[Document Migration]: {} completed", migration.name);
+                        outcome.ran = true;
+                        break;
+                    }
+                    MigrationStep::Done => break,
+                }
+                drop(conn);
+                std::thread::yield_now();
+            }
+            outcomes.push(outcome);
+        }
+        Ok(outcomes)
     }
 }
+
+/// The copy-table migrations of the document tables in the order they get applied. The name
+/// of a migration must not change once it's released, it's the key of its checkpoint.
+pub(crate) fn document_table_migrations() -> Vec<CopyTableMigration> {
+    vec![]
+}
+
+/// Returns an error if one of the `migrations` hasn't completed. The rows that change during the
+/// copy aren't tracked, so no document may be opened before its tables are migrated.
+pub(crate) fn check_table_migrations(conn: &SqliteConnection, migrations: &[CopyTableMigration]) -> FlowyResult<()> {
+    for migration in migrations {
+        if !migration.is_done(conn)? {
+            return Err(FlowyError::internal().context(format!(
+                "The table migration {} must complete before the documents are opened",
+                migration.name
+            )));
+        }
+    }
+    Ok(())
+}
+
 fn migration_flag_key(user_id: &str, version: &str) -> String {
     md5(format!("{}{}", user_id, version,))
 }
@@ -1,4 +1,46 @@
+mod attachment;
+mod backup;
+mod content_hash;
+mod dictionary;
+mod doc_reader;
+mod find_replace;
+mod hydrate;
+mod integrity;
+mod lazy_document;
+mod maintenance;
+mod merge;
 mod migration;
 mod persistence;
+mod portable;
+mod preference;
+mod preview;
+mod recovery;
+mod reexport;
+mod rev_graph;
+mod search_index;
+mod snippet;
+mod startup_report;
+mod storage;
 
+pub use attachment::*;
+pub use backup::*;
+pub use content_hash::*;
+pub use dictionary::*;
+pub use doc_reader::*;
+pub use find_replace::*;
+pub(crate) use hydrate::*;
+pub use integrity::*;
+pub use lazy_document::*;
+pub use maintenance::*;
+pub use merge::*;
 pub use persistence::*;
+pub use portable::*;
+pub use preference::*;
+pub use preview::*;
+pub use recovery::*;
+pub(crate) use reexport::*;
+pub use rev_graph::*;
+pub use search_index::*;
+pub use snippet::*;
+pub use startup_report::*;
+pub use storage::*;
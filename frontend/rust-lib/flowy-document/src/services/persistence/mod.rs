@@ -1,9 +1,14 @@
 pub mod delta_migration;
 pub mod rev_sqlite;
 
-use crate::services::migration::DocumentMigration;
+use crate::entities::DocumentVersionPB;
+use crate::services::migration::{check_table_migrations, document_table_migrations, DocumentMigration, V1_MIGRATION};
+use crate::services::rev_sqlite::{DeltaRevisionSql, DocumentRevisionSql};
+use crate::services::{scan_documents, DocumentStartupReport, MigrationOutcome};
 use crate::DocumentDatabase;
-use flowy_error::FlowyResult;
+use flowy_database::prelude::*;
+use flowy_error::{FlowyError, FlowyResult};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub struct DocumentPersistence {
@@ -15,12 +20,218 @@ impl DocumentPersistence {
         Self { database }
     }
 
-    #[tracing::instrument(level = "trace", skip_all, err)]
-    pub fn initialize(&self, user_id: &str) -> FlowyResult<()> {
+    /// Runs the migrations and scans the documents, it must complete before any document is
+    /// opened, see `check_table_migrations`. A failed phase doesn't stop the ones after it, all
+    /// of them are recorded in the returned report with the error of the first failed phase or
+    /// migration.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn initialize(&self, user_id: &str, version: &DocumentVersionPB) -> (DocumentStartupReport, FlowyResult<()>) {
+        let mut report = DocumentStartupReport::default();
+        let mut first_error = None;
         let migration = DocumentMigration::new(user_id, self.database.clone());
-        if let Err(e) = migration.run_v1_migration() {
-            tracing::error!("[Document Migration]: run v1 migration failed: {:?}", e);
+        let mut v1_outcome = MigrationOutcome::new(V1_MIGRATION);
+        match report.run_phase("v1_migration", || migration.run_v1_migration()) {
+            Ok(ran) => v1_outcome.ran = ran,
+            Err(e) => {
+                v1_outcome.error = Some(format!("{:?}", e));
+                first_error.get_or_insert(e);
+            }
         }
-        Ok(())
+        report.migrations.push(v1_outcome);
+
+        match report.run_phase("table_migrations", || {
+            migration.run_table_migrations(&document_table_migrations())
+        }) {
+            Ok(outcomes) => {
+                for outcome in outcomes.iter() {
+                    if let Some(error) = outcome.error.as_ref() {
+                        first_error.get_or_insert(
+                            FlowyError::internal().context(format!("{} failed: {}", outcome.name, error)),
+                        );
+                    }
+                }
+                report.migrations.extend(outcomes);
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+
+        let database = self.database.clone();
+        let scan = report.run_phase("scan_documents", || {
+            let pool = database.db_pool()?;
+            let conn = pool.get()?;
+            scan_documents(version, &*conn)
+        });
+        match scan {
+            Ok(scan) => {
+                report.num_of_documents = scan.num_of_documents;
+                report.num_of_unsynced_revisions = scan.num_of_unsynced_revisions;
+                report.warnings = scan.warnings;
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+
+        let result = match first_error {
+            None => Ok(()),
+            Some(error) => Err(error),
+        };
+        (report, result)
+    }
+
+    /// Returns an error if the table migrations haven't completed yet, e.g. the document is opened
+    /// before `initialize` or after a failed migration.
+    pub fn check_table_migrations(&self) -> FlowyResult<()> {
+        let migrations = document_table_migrations();
+        if migrations.is_empty() {
+            return Ok(());
+        }
+        let conn = self.database.db_pool()?.get()?;
+        check_table_migrations(&conn, &migrations)
+    }
+
+    /// Returns the latest rev_id of each document in one query, e.g. for the folder to show the
+    /// documents on launch without reading them one by one.
+    pub fn read_all_heads(&self, version: &DocumentVersionPB) -> FlowyResult<HashMap<String, i64>> {
+        let conn = self.database.db_pool()?.get()?;
+        read_document_heads(version, &conn)
+    }
+}
+
+fn read_document_heads(version: &DocumentVersionPB, conn: &SqliteConnection) -> FlowyResult<HashMap<String, i64>> {
+    let heads = match version {
+        DocumentVersionPB::V0 => DeltaRevisionSql::read_heads(conn)?,
+        DocumentVersionPB::V1 => DocumentRevisionSql::read_heads(conn)?,
+    };
+    Ok(heads.into_iter().collect())
+}
+
+/// The database of the documents in the tests.
+#[cfg(any(test, feature = "flowy_unit_test"))]
+pub struct DocumentDatabaseMock(pub Arc<flowy_database::ConnectionPool>);
+
+#[cfg(any(test, feature = "flowy_unit_test"))]
+impl DocumentDatabase for DocumentDatabaseMock {
+    fn db_pool(&self) -> Result<Arc<flowy_database::ConnectionPool>, flowy_error::FlowyError> {
+        Ok(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::DocumentVersionPB;
+    use crate::services::migration::{check_table_migrations, DocumentMigration};
+    use crate::services::rev_sqlite::{SQLiteDeltaDocumentRevisionPersistence, SQLiteDocumentRevisionPersistence};
+    use crate::services::{
+        custom_dictionary_doc_id, doc_preferences_doc_id, DocumentDatabaseMock, DocumentPersistence,
+    };
+    use bytes::Bytes;
+    use flowy_database::table_migration::CopyTableMigration;
+    use flowy_database::test_util::TempDatabase;
+    use flowy_http_model::revision::Revision;
+    use flowy_revision_persistence::{RevisionDiskCache, SyncRecord};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn records(doc_id: &str, rev_ids: &[i64]) -> Vec<SyncRecord> {
+        rev_ids
+            .iter()
+            .map(|rev_id| SyncRecord::new(Revision::new(doc_id, rev_id - 1, *rev_id, Bytes::from("[]"), "")))
+            .collect()
+    }
+
+    #[test]
+    fn read_all_heads_test() {
+        let database = TempDatabase::new("document_heads");
+        let persistence = DocumentPersistence::new(Arc::new(DocumentDatabaseMock(database.get_pool())));
+        assert!(persistence.read_all_heads(&DocumentVersionPB::V0).unwrap().is_empty());
+
+        let delta_disk_cache = SQLiteDeltaDocumentRevisionPersistence::new("user", database.get_pool());
+        let disk_cache = SQLiteDocumentRevisionPersistence::new("user", database.get_pool());
+        for (doc_id, rev_ids) in [("doc_1", vec![1, 2, 3]), ("doc_2", vec![1]), ("doc_3", vec![1, 2])] {
+            delta_disk_cache
+                .create_revision_records(records(doc_id, &rev_ids))
+                .unwrap();
+            disk_cache.create_revision_records(records(doc_id, &rev_ids)).unwrap();
+        }
+
+        // The folder and the data of the app kept in documents aren't documents of the user.
+        for doc_id in [
+            "user:folder".to_owned(),
+            custom_dictionary_doc_id("workspace"),
+            doc_preferences_doc_id("doc_1"),
+        ] {
+            delta_disk_cache
+                .create_revision_records(records(&doc_id, &[1]))
+                .unwrap();
+        }
+
+        let expected = HashMap::from([
+            ("doc_1".to_owned(), 3),
+            ("doc_2".to_owned(), 1),
+            ("doc_3".to_owned(), 2),
+        ]);
+        assert_eq!(persistence.read_all_heads(&DocumentVersionPB::V0).unwrap(), expected);
+        assert_eq!(persistence.read_all_heads(&DocumentVersionPB::V1).unwrap(), expected);
+    }
+
+    fn created_at_migration() -> CopyTableMigration {
+        let columns = ["id", "document_id", "base_rev_id", "rev_id", "data", "state"];
+        let mut copy_columns = columns
+            .iter()
+            .map(|column| (column.to_string(), column.to_string()))
+            .collect::<Vec<(String, String)>>();
+        copy_columns.push(("created_at".to_owned(), "0".to_owned()));
+
+        CopyTableMigration {
+            name: "document_rev_table_created_at".to_owned(),
+            table: "document_rev_table".to_owned(),
+            columns: "id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT, \
+                      document_id TEXT NOT NULL DEFAULT '', \
+                      base_rev_id BIGINT NOT NULL DEFAULT 0, \
+                      rev_id BIGINT NOT NULL DEFAULT 0, \
+                      data BLOB NOT NULL DEFAULT (x''), \
+                      state INTEGER NOT NULL DEFAULT 0, \
+                      created_at BIGINT NOT NULL DEFAULT 0"
+                .to_owned(),
+            copy_columns,
+            after_swap: vec![],
+            batch_size: 2,
+        }
+    }
+
+    #[test]
+    fn run_table_migrations_before_open_test() {
+        let database = TempDatabase::new("document_table_migrations");
+        let delta_disk_cache = SQLiteDeltaDocumentRevisionPersistence::new("user", database.get_pool());
+        delta_disk_cache
+            .create_revision_records(records("doc_1", &[1, 2, 3, 4, 5]))
+            .unwrap();
+
+        let migrations = vec![created_at_migration()];
+        let conn = database.get_connection().unwrap();
+        assert!(check_table_migrations(&conn, &migrations).is_err());
+        drop(conn);
+
+        let migration = DocumentMigration::new("user", Arc::new(DocumentDatabaseMock(database.get_pool())));
+        let outcomes = migration.run_table_migrations(&migrations).unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].ran);
+        assert!(outcomes[0].error.is_none());
+
+        let conn = database.get_connection().unwrap();
+        assert!(check_table_migrations(&conn, &migrations).is_ok());
+        drop(conn);
+        let persistence = DocumentPersistence::new(Arc::new(DocumentDatabaseMock(database.get_pool())));
+        assert_eq!(
+            persistence.read_all_heads(&DocumentVersionPB::V0).unwrap(),
+            HashMap::from([("doc_1".to_owned(), 5)])
+        );
+
+        // Running them again is a no-op.
+        let outcomes = migration.run_table_migrations(&migrations).unwrap();
+        assert!(!outcomes[0].ran);
     }
 }
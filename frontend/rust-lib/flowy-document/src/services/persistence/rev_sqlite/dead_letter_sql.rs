@@ -0,0 +1,34 @@
+use crate::services::rev_sqlite::map_read_error;
+use flowy_database::{prelude::*, schema::rev_dead_letter::dsl};
+use flowy_error::FlowyError;
+
+/// Reads and writes the `rev_dead_letter` table, which keeps the revisions whose push was given
+/// up on. The table is shared by the revision tables of both document versions.
+pub(crate) struct DeadLetterSql {}
+
+impl DeadLetterSql {
+    /// Replaces the dead letters of the object with the `rev_ids`.
+    pub(crate) fn write(object_id: &str, rev_ids: &[i64], conn: &SqliteConnection) -> Result<(), FlowyError> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let _ = diesel::delete(dsl::rev_dead_letter.filter(dsl::object_id.eq(object_id))).execute(conn)?;
+            let records = rev_ids
+                .iter()
+                .map(|rev_id| (dsl::object_id.eq(object_id), dsl::rev_id.eq(*rev_id)))
+                .collect::<Vec<_>>();
+            let _ = diesel::insert_into(dsl::rev_dead_letter)
+                .values(&records)
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    pub(crate) fn read(object_id: &str, conn: &SqliteConnection) -> Result<Vec<i64>, FlowyError> {
+        let rev_ids = dsl::rev_dead_letter
+            .filter(dsl::object_id.eq(object_id))
+            .select(dsl::rev_id)
+            .order(dsl::rev_id.asc())
+            .load::<i64>(conn)
+            .map_err(map_read_error)?;
+        Ok(rev_ids)
+    }
+}
@@ -0,0 +1,91 @@
+use crate::services::rev_sqlite::{DeltaRevisionSql, RevTableType, TextRevisionState};
+use diesel::sql_types::{BigInt, Integer, Text};
+use flowy_database::{
+    prelude::*,
+    schema::{document_chunk, document_chunk::dsl, rev_table},
+    sql_query,
+};
+use flowy_error::FlowyError;
+
+/// Stages the chunks of a document streamed from the server in the `document_chunk` table.
+/// The chunks are concatenated into the document's revision by SQLite, so the document is
+/// never loaded into memory while it's fetched.
+pub(crate) struct DocumentChunkSql {}
+
+impl DocumentChunkSql {
+    /// Saves the operations of one chunk. The `operations` are the json of the chunk's
+    /// operations without the surrounding brackets.
+    pub(crate) fn write(
+        doc_id: &str,
+        chunk_index: i32,
+        operations: &str,
+        conn: &SqliteConnection,
+    ) -> Result<(), FlowyError> {
+        let record = (
+            dsl::doc_id.eq(doc_id),
+            dsl::chunk_index.eq(chunk_index),
+            dsl::data.eq(operations),
+        );
+        let _ = replace_into(document_chunk::table).values(record).execute(conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn clear(doc_id: &str, conn: &SqliteConnection) -> Result<(), FlowyError> {
+        let _ = diesel::delete(dsl::document_chunk.filter(dsl::doc_id.eq(doc_id))).execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the json of the document made of the staged chunks.
+    pub(crate) fn read(doc_id: &str, conn: &SqliteConnection) -> Result<Vec<u8>, FlowyError> {
+        let chunks = dsl::document_chunk
+            .filter(dsl::doc_id.eq(doc_id))
+            .order(dsl::chunk_index.asc())
+            .select(dsl::data)
+            .load::<String>(conn)?;
+        Ok(format!("[{}]", chunks.join(",")).into_bytes())
+    }
+
+    /// Replaces the revisions of the document with one revision that is made of the staged
+    /// chunks, and clears the chunks. The revision came from the server, so it's acked.
+    ///
+    /// With `keep_unsynced`, nothing is replaced if the document has a revision that isn't
+    /// synced and is newer than the server's `rev_id`.
+    pub(crate) fn compose_revision(
+        doc_id: &str,
+        base_rev_id: i64,
+        rev_id: i64,
+        keep_unsynced: bool,
+        conn: &SqliteConnection,
+    ) -> Result<(), FlowyError> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            if keep_unsynced {
+                let num_of_unsynced: i64 = rev_table::dsl::rev_table
+                    .filter(rev_table::dsl::doc_id.eq(doc_id))
+                    .filter(rev_table::dsl::state.eq(TextRevisionState::Sync))
+                    .filter(rev_table::dsl::rev_id.gt(rev_id))
+                    .count()
+                    .get_result(conn)?;
+                if num_of_unsynced > 0 {
+                    return Err(FlowyError::internal().context(format!(
+                        "The document:{} has {} unsynced revisions, it's not replaced with the server's document",
+                        doc_id, num_of_unsynced
+                    )));
+                }
+            }
+            DeltaRevisionSql::delete(doc_id, None, conn)?;
+            let _ = sql_query(
+                "INSERT INTO rev_table (doc_id, base_rev_id, rev_id, data, state, ty) \
+                 SELECT ?, ?, ?, CAST('[' || IFNULL(group_concat(data, ','), '') || ']' AS BLOB), ?, ? \
+                 FROM (SELECT data FROM document_chunk WHERE doc_id = ? ORDER BY chunk_index)",
+            )
+            .bind::<Text, _>(doc_id)
+            .bind::<BigInt, _>(base_rev_id)
+            .bind::<BigInt, _>(rev_id)
+            .bind::<Integer, _>(TextRevisionState::Ack)
+            .bind::<Integer, _>(RevTableType::Remote)
+            .bind::<Text, _>(doc_id)
+            .execute(conn)?;
+            Self::clear(doc_id, conn)
+        })
+    }
+}
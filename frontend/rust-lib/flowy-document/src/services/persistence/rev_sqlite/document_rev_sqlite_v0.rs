@@ -1,5 +1,18 @@
+use crate::services::rev_sqlite::{
+    map_read_error, with_synchronous_full, DeadLetterSql, PayloadDedupeSummary, PinnedRevisionSql, RevBackupSql,
+    RevIdMapSql, RevisionPayloadSql, RevisionTimestampSql, DELETE_REVS_CHUNK_SIZE, MIN_SHARED_PAYLOAD_LEN,
+};
+use crate::services::{
+    custom_dictionary_workspace_id, doc_preferences_owner_id, write_repair_audit, BackupRevision, InvalidRevision,
+    PayloadIssue, RepairAuditEntry, RepairOutcome, RevGraphAuthor, RevGraphNode, RECOVERED_MARKER,
+    RECOVERED_UNREADABLE,
+};
 use bytes::Bytes;
-use diesel::{sql_types::Integer, update, SqliteConnection};
+use diesel::{
+    dsl::sql,
+    sql_types::{BigInt, Bool, Integer},
+    update, SqliteConnection,
+};
 use flowy_database::{
     impl_sql_integer_expression, insert_or_ignore_into,
     prelude::*,
@@ -12,12 +25,17 @@ use flowy_http_model::{
     util::md5,
 };
 use flowy_revision_persistence::{RevisionChangeset, RevisionDiskCache, RevisionState, SyncRecord};
-use std::collections::HashMap;
+use lib_infra::util::timestamp;
+use lib_ot::core::{DeltaOperation, OperationAttributes};
+use lib_ot::text_delta::{DeltaTextOperationBuilder, DeltaTextOperations};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
 use std::sync::Arc;
 
 pub struct SQLiteDeltaDocumentRevisionPersistence {
     user_id: String,
     pub(crate) pool: Arc<ConnectionPool>,
+    share_payloads: bool,
 }
 
 impl RevisionDiskCache<Arc<ConnectionPool>> for SQLiteDeltaDocumentRevisionPersistence {
@@ -25,10 +43,22 @@ impl RevisionDiskCache<Arc<ConnectionPool>> for SQLiteDeltaDocumentRevisionPersi
 
     fn create_revision_records(&self, revision_records: Vec<SyncRecord>) -> Result<(), Self::Error> {
         let conn = self.pool.get().map_err(internal_error)?;
-        DeltaRevisionSql::create(revision_records, &conn)?;
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            DeltaRevisionSql::create(revision_records, self.share_payloads, &conn)
+        })?;
         Ok(())
     }
 
+    /// The commit is fsynced before it returns, see `with_synchronous_full`.
+    fn create_durable_revision_records(&self, revision_records: Vec<SyncRecord>) -> Result<(), Self::Error> {
+        let conn = self.pool.get().map_err(internal_error)?;
+        with_synchronous_full(&conn, || {
+            conn.immediate_transaction::<_, FlowyError, _>(|| {
+                DeltaRevisionSql::create(revision_records, self.share_payloads, &conn)
+            })
+        })
+    }
+
     fn get_connection(&self) -> Result<Arc<ConnectionPool>, Self::Error> {
         Ok(self.pool.clone())
     }
@@ -66,8 +96,17 @@ impl RevisionDiskCache<Arc<ConnectionPool>> for SQLiteDeltaDocumentRevisionPersi
 
     fn delete_revision_records(&self, object_id: &str, rev_ids: Option<Vec<i64>>) -> Result<(), Self::Error> {
         let conn = &*self.pool.get().map_err(internal_error)?;
-        DeltaRevisionSql::delete(object_id, rev_ids, conn)?;
-        Ok(())
+        conn.immediate_transaction::<_, FlowyError, _>(|| DeltaRevisionSql::delete(object_id, rev_ids, conn))
+    }
+
+    fn count_revision_records(&self, object_id: &str) -> Result<u64, Self::Error> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        DeltaRevisionSql::count(object_id, conn)
+    }
+
+    fn delete_revs(&self, object_id: &str, rev_ids: &[i64]) -> Result<usize, Self::Error> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        conn.immediate_transaction::<_, FlowyError, _>(|| DeltaRevisionSql::delete_revs(object_id, rev_ids, conn))
     }
 
     fn delete_and_insert_records(
@@ -78,11 +117,97 @@ impl RevisionDiskCache<Arc<ConnectionPool>> for SQLiteDeltaDocumentRevisionPersi
     ) -> Result<(), Self::Error> {
         let conn = self.pool.get().map_err(internal_error)?;
         conn.immediate_transaction::<_, FlowyError, _>(|| {
+            // The inserted revisions replace the deleted ones, e.g. they're merged from them, so
+            // they keep the time that the newest of them was written at.
+            let create_time = match deleted_rev_ids.as_deref() {
+                Some(rev_ids) if !inserted_records.is_empty() => {
+                    RevisionTimestampSql::read_newest(object_id, rev_ids, &conn)?
+                }
+                _ => None,
+            };
             DeltaRevisionSql::delete(object_id, deleted_rev_ids, &conn)?;
-            DeltaRevisionSql::create(inserted_records, &conn)?;
+            if let Some(create_time) = create_time {
+                // The times are written first, `create` keeps them.
+                let rev_ids = inserted_records
+                    .iter()
+                    .map(|record| record.revision.rev_id)
+                    .collect::<Vec<i64>>();
+                RevisionTimestampSql::write(object_id, &rev_ids, create_time, &conn)?;
+            }
+            DeltaRevisionSql::create(inserted_records, self.share_payloads, &conn)?;
             Ok(())
         })
     }
+
+    fn pin_revision(&self, object_id: &str, rev_id: i64) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        PinnedRevisionSql::pin(object_id, rev_id, conn)
+    }
+
+    fn unpin_revision(&self, object_id: &str, rev_id: i64) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        PinnedRevisionSql::unpin(object_id, rev_id, conn)
+    }
+
+    fn read_pinned_rev_ids(&self, object_id: &str) -> FlowyResult<Vec<i64>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        PinnedRevisionSql::read(object_id, conn)
+    }
+
+    fn tag_revision(&self, object_id: &str, rev_id: i64, tag: &str) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        PinnedRevisionSql::tag(object_id, rev_id, tag, conn)
+    }
+
+    fn read_revision_tags(&self, object_id: &str) -> FlowyResult<Vec<(String, i64)>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        PinnedRevisionSql::read_tags(object_id, conn)
+    }
+
+    fn read_revision_timestamps(&self, object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevisionTimestampSql::read(object_id, conn)
+    }
+
+    fn set_revision_expiry(&self, object_id: &str, rev_ids: &[i64], expires_at: Option<i64>) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevisionTimestampSql::expire(object_id, rev_ids, expires_at, conn)
+    }
+
+    fn read_revision_expiries(&self, object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevisionTimestampSql::read_expiries(object_id, conn)
+    }
+
+    fn write_dead_letters(&self, object_id: &str, rev_ids: &[i64]) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        DeadLetterSql::write(object_id, rev_ids, conn)
+    }
+
+    fn read_dead_letters(&self, object_id: &str) -> FlowyResult<Vec<i64>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        DeadLetterSql::read(object_id, conn)
+    }
+
+    fn write_rev_id_map(&self, object_id: &str, rev_id_map: &[(i64, i64)]) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevIdMapSql::write(object_id, rev_id_map, conn)
+    }
+
+    fn read_rev_id_map(&self, object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevIdMapSql::read(object_id, conn)
+    }
+
+    fn write_revision_backup(&self, object_id: &str, revisions: &[Revision]) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevBackupSql::write(object_id, revisions, conn)
+    }
+
+    fn read_revision_backup(&self, object_id: &str) -> FlowyResult<Vec<Revision>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevBackupSql::read(object_id, conn)
+    }
 }
 
 impl SQLiteDeltaDocumentRevisionPersistence {
@@ -90,36 +215,67 @@ impl SQLiteDeltaDocumentRevisionPersistence {
         Self {
             user_id: user_id.to_owned(),
             pool,
+            share_payloads: false,
         }
     }
+
+    /// Saves the payloads of the new revisions once in the `rev_payload` table, the revisions
+    /// with the same payload refer to it by its hash. The revisions saved before keep their own
+    /// payload until `DeltaRevisionSql::share_all_payloads` runs.
+    pub fn with_shared_payloads(mut self, share_payloads: bool) -> Self {
+        self.share_payloads = share_payloads;
+        self
+    }
 }
 
 pub struct DeltaRevisionSql {}
 
 impl DeltaRevisionSql {
-    fn create(revision_records: Vec<SyncRecord>, conn: &SqliteConnection) -> Result<(), FlowyError> {
-        // Batch insert: https://diesel.rs/guides/all-about-inserts.html
-        let records = revision_records
-            .into_iter()
-            .map(|record| {
-                tracing::trace!(
-                    "[TextRevisionSql] create revision: {}:{:?}",
-                    record.revision.object_id,
-                    record.revision.rev_id
-                );
-                let rev_state: TextRevisionState = record.state.into();
-                (
-                    dsl::doc_id.eq(record.revision.object_id),
-                    dsl::base_rev_id.eq(record.revision.base_rev_id),
-                    dsl::rev_id.eq(record.revision.rev_id),
-                    dsl::data.eq(record.revision.bytes),
-                    dsl::state.eq(rev_state),
-                    dsl::ty.eq(RevTableType::Local),
-                )
-            })
-            .collect::<Vec<_>>();
+    fn create(
+        revision_records: Vec<SyncRecord>,
+        share_payloads: bool,
+        conn: &SqliteConnection,
+    ) -> Result<(), FlowyError> {
+        let mut records = vec![];
+        let mut rev_ids: HashMap<String, Vec<i64>> = HashMap::new();
+        for record in revision_records {
+            rev_ids
+                .entry(record.revision.object_id.clone())
+                .or_default()
+                .push(record.revision.rev_id);
+            tracing::trace!(
+                "[TextRevisionSql] create revision: {}:{:?}",
+                record.revision.object_id,
+                record.revision.rev_id
+            );
+            let rev_state: TextRevisionState = record.state.into();
+            let (data, ty) = match encode_append_revision(&record.revision.bytes) {
+                None => (record.revision.bytes, RevTableType::Local),
+                Some(data) => (data, RevTableType::Append),
+            };
+            let (data, ty) = if share_payloads && data.len() >= MIN_SHARED_PAYLOAD_LEN {
+                let hash = RevisionPayloadSql::hash(&data, ty);
+                RevisionPayloadSql::retain(&hash, &data, ty, conn)?;
+                (hash.into_bytes(), RevTableType::Shared)
+            } else {
+                (data, ty)
+            };
+            records.push((
+                dsl::doc_id.eq(record.revision.object_id),
+                dsl::base_rev_id.eq(record.revision.base_rev_id),
+                dsl::rev_id.eq(record.revision.rev_id),
+                dsl::data.eq(data),
+                dsl::state.eq(rev_state),
+                dsl::ty.eq(ty),
+            ));
+        }
 
+        // Batch insert: https://diesel.rs/guides/all-about-inserts.html
         let _ = insert_or_ignore_into(dsl::rev_table).values(&records).execute(conn)?;
+        let create_time = timestamp();
+        for (object_id, rev_ids) in rev_ids {
+            RevisionTimestampSql::write(&object_id, &rev_ids, create_time, conn)?;
+        }
         Ok(())
     }
 
@@ -143,12 +299,18 @@ impl DeltaRevisionSql {
         rev_ids: Option<Vec<i64>>,
         conn: &SqliteConnection,
     ) -> Result<Vec<SyncRecord>, FlowyError> {
-        let mut sql = dsl::rev_table.filter(dsl::doc_id.eq(object_id)).into_boxed();
+        let mut sql = dsl::rev_table
+            .filter(dsl::doc_id.eq(object_id))
+            .filter(dsl::ty.ne(RevTableType::Quarantined))
+            .into_boxed();
         if let Some(rev_ids) = rev_ids {
             sql = sql.filter(dsl::rev_id.eq_any(rev_ids));
         }
-        let rows = sql.order(dsl::rev_id.asc()).load::<RevisionTable>(conn)?;
-        let records = rows
+        let rows = sql
+            .order(dsl::rev_id.asc())
+            .load::<RevisionTable>(conn)
+            .map_err(map_read_error)?;
+        let records = resolve_shared_payloads(rows, conn)?
             .into_iter()
             .map(|row| mk_revision_record_from_table(user_id, row))
             .collect::<Vec<_>>();
@@ -156,6 +318,16 @@ impl DeltaRevisionSql {
         Ok(records)
     }
 
+    fn count(object_id: &str, conn: &SqliteConnection) -> Result<u64, FlowyError> {
+        let count = dsl::rev_table
+            .filter(dsl::doc_id.eq(object_id))
+            .filter(dsl::ty.ne(RevTableType::Quarantined))
+            .count()
+            .get_result::<i64>(conn)
+            .map_err(map_read_error)?;
+        Ok(count as u64)
+    }
+
     fn read_with_range(
         user_id: &str,
         object_id: &str,
@@ -166,17 +338,27 @@ impl DeltaRevisionSql {
             .filter(dsl::rev_id.ge(range.start))
             .filter(dsl::rev_id.le(range.end))
             .filter(dsl::doc_id.eq(object_id))
+            .filter(dsl::ty.ne(RevTableType::Quarantined))
             .order(dsl::rev_id.asc())
-            .load::<RevisionTable>(conn)?;
+            .load::<RevisionTable>(conn)
+            .map_err(map_read_error)?;
 
-        let revisions = rev_tables
+        let revisions = resolve_shared_payloads(rev_tables, conn)?
             .into_iter()
             .map(|table| mk_revision_record_from_table(user_id, table))
             .collect::<Vec<_>>();
         Ok(revisions)
     }
 
-    fn delete(object_id: &str, rev_ids: Option<Vec<i64>>, conn: &SqliteConnection) -> Result<(), FlowyError> {
+    /// Deletes the revisions with their timestamps and releases their shared payloads. It runs
+    /// on the connection of the caller, which should be in a transaction.
+    pub(crate) fn delete(
+        object_id: &str,
+        rev_ids: Option<Vec<i64>>,
+        conn: &SqliteConnection,
+    ) -> Result<(), FlowyError> {
+        let hashes = read_shared_hashes(object_id, rev_ids.as_deref(), conn)?;
+        RevisionTimestampSql::delete(object_id, rev_ids.as_deref(), conn)?;
         let mut sql = diesel::delete(dsl::rev_table).into_boxed();
         sql = sql.filter(dsl::doc_id.eq(object_id));
 
@@ -187,13 +369,207 @@ impl DeltaRevisionSql {
 
         let affected_row = sql.execute(conn)?;
         tracing::trace!("[TextRevisionSql] Delete {} rows", affected_row);
+        RevisionPayloadSql::release(&hashes, conn)
+    }
+
+    fn delete_revs(object_id: &str, rev_ids: &[i64], conn: &SqliteConnection) -> Result<usize, FlowyError> {
+        let mut affected_row = 0;
+        for chunk in rev_ids.chunks(DELETE_REVS_CHUNK_SIZE) {
+            let hashes = read_shared_hashes(object_id, Some(chunk), conn)?;
+            let filter = dsl::rev_table
+                .filter(dsl::doc_id.eq(object_id))
+                .filter(dsl::rev_id.eq_any(chunk));
+            affected_row += diesel::delete(filter).execute(conn)?;
+            RevisionTimestampSql::delete(object_id, Some(chunk), conn)?;
+            RevisionPayloadSql::release(&hashes, conn)?;
+        }
+        tracing::trace!(
+            "[TextRevisionSql] Delete {} of {} revisions",
+            affected_row,
+            rev_ids.len()
+        );
+        Ok(affected_row)
+    }
+
+    /// Returns the ids of the delta documents that have revisions which aren't quarantined. The
+    /// folder, the custom dictionaries and the preferences of the documents are saved in the same
+    /// table, they are left out.
+    pub(crate) fn read_doc_ids(conn: &SqliteConnection) -> Result<Vec<String>, FlowyError> {
+        let doc_ids = dsl::rev_table
+            .filter(dsl::ty.ne(RevTableType::Quarantined))
+            .select(dsl::doc_id)
+            .distinct()
+            .order(dsl::doc_id.asc())
+            .load::<String>(conn)
+            .map_err(map_read_error)?;
+        let doc_ids = doc_ids
+            .into_iter()
+            .filter(|doc_id| is_delta_document_id(doc_id))
+            .collect();
+        Ok(doc_ids)
+    }
+
+    /// Returns the latest rev_id of each delta document, the documents are the ones of
+    /// `read_doc_ids`. The quarantined revisions are skipped when the document is read, so they
+    /// don't count for its head.
+    pub(crate) fn read_heads(conn: &SqliteConnection) -> Result<Vec<(String, i64)>, FlowyError> {
+        let heads = dsl::rev_table
+            .filter(dsl::ty.ne(RevTableType::Quarantined))
+            .group_by(dsl::doc_id)
+            .select((dsl::doc_id, sql::<BigInt>("MAX(rev_id)")))
+            .load::<(String, i64)>(conn)
+            .map_err(map_read_error)?;
+        let heads = heads
+            .into_iter()
+            .filter(|(doc_id, _)| is_delta_document_id(doc_id))
+            .collect();
+        Ok(heads)
+    }
+
+    /// Reads the revisions of the document, e.g. to copy them into another database.
+    pub(crate) fn read_revisions(object_id: &str, conn: &SqliteConnection) -> Result<Vec<Revision>, FlowyError> {
+        let revisions = Self::read("", object_id, None, conn)?
+            .into_iter()
+            .map(|record| record.revision)
+            .collect();
+        Ok(revisions)
+    }
+
+    /// Saves the revisions copied from another database. They aren't synced with the server of
+    /// this database yet.
+    pub(crate) fn write_revisions(revisions: Vec<Revision>, conn: &SqliteConnection) -> Result<(), FlowyError> {
+        let records = revisions.into_iter().map(SyncRecord::new).collect();
+        Self::create(records, false, conn)
+    }
+
+    /// Returns the ids of all the objects in the table, including the folder and the objects
+    /// whose revisions are all quarantined.
+    pub(crate) fn read_object_ids(conn: &SqliteConnection) -> Result<Vec<String>, FlowyError> {
+        let object_ids = dsl::rev_table
+            .select(dsl::doc_id)
+            .distinct()
+            .order(dsl::doc_id.asc())
+            .load::<String>(conn)
+            .map_err(map_read_error)?;
+        Ok(object_ids)
+    }
+
+    /// Returns the rev_id and the state of each revision of the object, including the
+    /// quarantined ones, see `write_incremental_backup`.
+    pub(crate) fn read_rev_states(object_id: &str, conn: &SqliteConnection) -> Result<Vec<(i64, i32)>, FlowyError> {
+        let rev_states = dsl::rev_table
+            .filter(dsl::doc_id.eq(object_id))
+            .select((dsl::rev_id, dsl::state))
+            .order(dsl::rev_id.asc())
+            .load::<(i64, TextRevisionState)>(conn)
+            .map_err(map_read_error)?;
+        Ok(rev_states
+            .into_iter()
+            .map(|(rev_id, state)| (rev_id, state as i32))
+            .collect())
+    }
+
+    /// Reads the revisions of the object written at or after `since` with their state and the
+    /// time they were written at, see `write_incremental_backup`.
+    pub(crate) fn read_backup_revisions(
+        object_id: &str,
+        since: i64,
+        conn: &SqliteConnection,
+    ) -> Result<Vec<BackupRevision>, FlowyError> {
+        let create_times = RevisionTimestampSql::read(object_id, conn)?
+            .into_iter()
+            .filter(|(_, create_time)| *create_time >= since)
+            .collect::<HashMap<i64, i64>>();
+        if create_times.is_empty() {
+            return Ok(vec![]);
+        }
+        let rev_ids = create_times.keys().copied().collect::<Vec<i64>>();
+        let revisions = Self::read("", object_id, Some(rev_ids), conn)?
+            .into_iter()
+            .map(|record| {
+                let state: TextRevisionState = record.state.into();
+                BackupRevision {
+                    create_time: create_times.get(&record.revision.rev_id).copied().unwrap_or(0),
+                    state: state as i32,
+                    revision: record.revision,
+                }
+            })
+            .collect();
+        Ok(revisions)
+    }
+
+    /// Applies the rows of the object read by `read_rev_states` and `read_backup_revisions`:
+    /// the revisions that aren't in the `rev_states` are deleted, the `revisions` replace the
+    /// ones with the same rev_ids and keep the time they were written at, and the others get
+    /// their state from the `rev_states`.
+    pub(crate) fn apply_backup_revisions(
+        object_id: &str,
+        rev_states: &[(i64, i32)],
+        revisions: Vec<BackupRevision>,
+        conn: &SqliteConnection,
+    ) -> Result<(), FlowyError> {
+        let kept_rev_ids = rev_states.iter().map(|(rev_id, _)| *rev_id).collect::<HashSet<i64>>();
+        let written_rev_ids = revisions
+            .iter()
+            .map(|revision| revision.revision.rev_id)
+            .collect::<HashSet<i64>>();
+        let deleted_rev_ids = dsl::rev_table
+            .filter(dsl::doc_id.eq(object_id))
+            .select(dsl::rev_id)
+            .load::<i64>(conn)
+            .map_err(map_read_error)?
+            .into_iter()
+            .filter(|rev_id| !kept_rev_ids.contains(rev_id) || written_rev_ids.contains(rev_id))
+            .collect::<Vec<i64>>();
+        if !deleted_rev_ids.is_empty() {
+            Self::delete(object_id, Some(deleted_rev_ids), conn)?;
+        }
+
+        // The times are written first, `create` keeps them.
+        let create_times = revisions
+            .iter()
+            .map(|revision| (revision.revision.rev_id, revision.create_time))
+            .collect::<Vec<_>>();
+        if !create_times.is_empty() {
+            RevisionTimestampSql::write_create_times(object_id, &create_times, conn)?;
+        }
+        let mut records = vec![];
+        for revision in revisions {
+            let state: TextRevisionState = revision.state.into();
+            records.push(SyncRecord {
+                revision: revision.revision,
+                state: state.into(),
+                write_to_disk: true,
+            });
+        }
+        Self::create(records, false, conn)?;
+
+        let mut rev_ids_by_state: HashMap<i32, Vec<i64>> = HashMap::new();
+        for (rev_id, state) in rev_states
+            .iter()
+            .filter(|(rev_id, _)| !written_rev_ids.contains(rev_id))
+        {
+            rev_ids_by_state.entry(*state).or_insert_with(Vec::new).push(*rev_id);
+        }
+        for (state, rev_ids) in rev_ids_by_state {
+            let state: TextRevisionState = state.into();
+            for chunk in rev_ids.chunks(DELETE_REVS_CHUNK_SIZE) {
+                let filter = dsl::rev_table
+                    .filter(dsl::doc_id.eq(object_id))
+                    .filter(dsl::rev_id.eq_any(chunk));
+                let _ = update(filter).set(dsl::state.eq(state)).execute(conn)?;
+            }
+        }
         Ok(())
     }
 
     pub fn read_all_documents(user_id: &str, conn: &SqliteConnection) -> Result<Vec<Vec<Revision>>, FlowyError> {
-        let rev_tables = dsl::rev_table.order(dsl::rev_id.asc()).load::<RevisionTable>(conn)?;
+        let rev_tables = dsl::rev_table
+            .filter(dsl::ty.ne(RevTableType::Quarantined))
+            .order(dsl::rev_id.asc())
+            .load::<RevisionTable>(conn)?;
         let mut document_map = HashMap::new();
-        for rev_table in rev_tables {
+        for rev_table in resolve_shared_payloads(rev_tables, conn)? {
             document_map
                 .entry(rev_table.doc_id.clone())
                 .or_insert_with(Vec::new)
@@ -213,6 +589,457 @@ impl DeltaRevisionSql {
 
         Ok(documents)
     }
+
+    /// Parses the payload of each revision without composing them. Returns the revisions whose
+    /// payload can't be read, the quarantined revisions are skipped.
+    pub fn validate_all(conn: &SqliteConnection) -> Result<Vec<InvalidRevision>, FlowyError> {
+        let invalid_revisions = resolve_shared_payloads(load_unquarantined(conn)?, conn)?
+            .iter()
+            .filter_map(check_payload)
+            .collect::<Vec<_>>();
+        Ok(invalid_revisions)
+    }
+
+    /// Repairs the revisions whose payload can't be read. The invalid UTF-8 is replaced with
+    /// U+FFFD, and the revision is quarantined if its payload still can't be parsed after that.
+    /// Each repair writes an audit entry in the same transaction.
+    ///
+    /// The opened documents keep their content, the repairs apply when they're opened again.
+    pub fn repair_all(conn: &SqliteConnection) -> Result<Vec<RepairAuditEntry>, FlowyError> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let mut entries = vec![];
+            let tables = load_unquarantined(conn)?;
+            let shared_hashes = tables
+                .iter()
+                .filter(|table| table.ty == RevTableType::Shared)
+                .map(|table| (table.id, shared_hash(table)))
+                .collect::<HashMap<_, _>>();
+            for table in resolve_shared_payloads(tables, conn)? {
+                let invalid_revision = match check_payload(&table) {
+                    None => continue,
+                    Some(invalid_revision) => invalid_revision,
+                };
+                // The repaired revision keeps its own payload, the other revisions that share the
+                // payload are repaired on their own.
+                let filter = dsl::rev_table.filter(dsl::id.eq(table.id));
+                let outcome = match recover_payload(&table) {
+                    Some(data) => {
+                        let _ = update(filter)
+                            .set((dsl::data.eq(data), dsl::ty.eq(table.ty)))
+                            .execute(conn)?;
+                        RepairOutcome::Recovered
+                    }
+                    None => {
+                        let _ = update(filter)
+                            .set((dsl::data.eq(&table.data), dsl::ty.eq(RevTableType::Quarantined)))
+                            .execute(conn)?;
+                        RepairOutcome::Quarantined
+                    }
+                };
+                if let Some(hash) = shared_hashes.get(&table.id) {
+                    RevisionPayloadSql::release(&[hash.clone()], conn)?;
+                }
+                tracing::warn!(
+                    "[TextRevisionSql] {:?} revision {}:{}, {:?}",
+                    outcome,
+                    invalid_revision.doc_id,
+                    invalid_revision.rev_id,
+                    invalid_revision.issue
+                );
+                let entry = RepairAuditEntry::new(invalid_revision, outcome);
+                write_repair_audit(&entry, conn)?;
+                entries.push(entry);
+            }
+            Ok(entries)
+        })
+    }
+
+    /// Moves the payloads of the revisions saved before the payloads were shared to the
+    /// `rev_payload` table, the revisions with the same payload end up referring to one copy.
+    pub fn share_all_payloads(conn: &SqliteConnection) -> Result<PayloadDedupeSummary, FlowyError> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let mut summary = PayloadDedupeSummary::default();
+            let mut hashes = HashSet::new();
+            // The rows are read by pages, only the ones whose payload is large enough to share.
+            let mut last_id = 0;
+            loop {
+                let page = dsl::rev_table
+                    .filter(dsl::id.gt(last_id))
+                    .filter(dsl::ty.ne(RevTableType::Quarantined))
+                    .filter(dsl::ty.ne(RevTableType::Shared))
+                    .filter(sql::<Bool>(&format!("length(data) >= {}", MIN_SHARED_PAYLOAD_LEN)))
+                    .order(dsl::id.asc())
+                    .limit(SHARE_PAYLOADS_PAGE_SIZE)
+                    .load::<RevisionTable>(conn)
+                    .map_err(map_read_error)?;
+                let table = match page.last() {
+                    None => break,
+                    Some(table) => table,
+                };
+                last_id = table.id;
+                for table in page
+                    .iter()
+                    .filter(|table| !table.doc_id.ends_with(FOLDER_OBJECT_SUFFIX))
+                {
+                    let hash = RevisionPayloadSql::hash(&table.data, table.ty);
+                    RevisionPayloadSql::retain(&hash, &table.data, table.ty, conn)?;
+                    let filter = dsl::rev_table.filter(dsl::id.eq(table.id));
+                    let _ = update(filter)
+                        .set((dsl::data.eq(hash.as_bytes()), dsl::ty.eq(RevTableType::Shared)))
+                        .execute(conn)?;
+                    hashes.insert(hash);
+                    summary.num_of_revisions += 1;
+                }
+            }
+            summary.num_of_payloads = hashes.len();
+            tracing::debug!("[TextRevisionSql] {:?}", summary);
+            Ok(summary)
+        })
+    }
+
+    /// Deletes the acked revisions of the document that don't change it, i.e. the ones made of
+    /// retains without attributes that don't reach past the end of the document. The revisions
+    /// based on a deleted revision are rebased onto its base. Returns the number of the deleted
+    /// revisions.
+    ///
+    /// The pinned and tagged revisions and the rev_ids shared by more than one row are kept. The
+    /// length of the document is unknown after a revision that can't be parsed, the revisions
+    /// after it are only rebased.
+    pub fn collapse_noops(object_id: &str, conn: &SqliteConnection) -> Result<usize, FlowyError> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let rows = dsl::rev_table
+                .filter(dsl::doc_id.eq(object_id))
+                .filter(dsl::ty.ne(RevTableType::Quarantined))
+                .order((dsl::rev_id.asc(), dsl::id.asc()))
+                .load::<RevisionTable>(conn)
+                .map_err(map_read_error)?;
+            let pinned_rev_ids = PinnedRevisionSql::read(object_id, conn)?
+                .into_iter()
+                .collect::<HashSet<i64>>();
+            let mut num_of_rows = HashMap::<i64, usize>::new();
+            for row in rows.iter() {
+                *num_of_rows.entry(row.rev_id).or_default() += 1;
+            }
+
+            let mut noop_rev_ids = vec![];
+            // The base of each deleted revision, after its own base was rebased.
+            let mut rebased_base_rev_ids = HashMap::<i64, i64>::new();
+            let mut len = Some(0);
+            for table in resolve_shared_payloads(rows, conn)? {
+                let (id, rev_id, state) = (table.id, table.rev_id, table.state);
+                let base_rev_id = rebased_base_rev_ids
+                    .get(&table.base_rev_id)
+                    .copied()
+                    .unwrap_or(table.base_rev_id);
+                if base_rev_id != table.base_rev_id {
+                    let filter = dsl::rev_table.filter(dsl::id.eq(id));
+                    let _ = update(filter).set(dsl::base_rev_id.eq(base_rev_id)).execute(conn)?;
+                }
+                // The resolved revisions aren't composed, they don't change the length.
+                if state == TextRevisionState::Resolved {
+                    continue;
+                }
+
+                let revision = mk_revision_record_from_table("", table).revision;
+                let operations = match (len, DeltaTextOperations::from_bytes(&revision.bytes)) {
+                    (Some(_), Ok(operations)) => operations,
+                    _ => {
+                        len = None;
+                        continue;
+                    }
+                };
+                let doc_len = len.unwrap_or_default();
+                let is_noop = operations.is_noop()
+                    && !operations.ops.iter().any(|op| op.has_attribute())
+                    && operations.utf16_base_len <= doc_len;
+                if is_noop
+                    && state == TextRevisionState::Ack
+                    && !pinned_rev_ids.contains(&rev_id)
+                    && num_of_rows[&rev_id] == 1
+                {
+                    noop_rev_ids.push(rev_id);
+                    rebased_base_rev_ids.insert(rev_id, base_rev_id);
+                    continue;
+                }
+                len = Some(operations.utf16_target_len + doc_len.saturating_sub(operations.utf16_base_len));
+            }
+
+            let num_of_deleted = Self::delete_revs(object_id, &noop_rev_ids, conn)?;
+            tracing::debug!(
+                "[TextRevisionSql] Collapse {} no-op revisions of {}",
+                num_of_deleted,
+                object_id
+            );
+            Ok(num_of_deleted)
+        })
+    }
+
+    /// Extracts the inserted text of each revision of the document in rev_id order, including
+    /// the quarantined ones, without composing them. The text of each revision follows a
+    /// `RECOVERED_MARKER` line, and the revisions whose payload can't be parsed only have the
+    /// marker. It only reads, so it works even if the revisions can't be composed.
+    pub fn recover_text(object_id: &str, conn: &SqliteConnection) -> Result<String, FlowyError> {
+        let rows = dsl::rev_table
+            .filter(dsl::doc_id.eq(object_id))
+            .order(dsl::rev_id.asc())
+            .load::<RevisionTable>(conn)
+            .map_err(map_read_error)?;
+        if rows.is_empty() {
+            return Err(FlowyError::record_not_found().context(format!("The document:{} has no revisions", object_id)));
+        }
+
+        let mut text = String::new();
+        for table in resolve_shared_payloads(rows, conn)? {
+            let data = match table.ty {
+                RevTableType::Append => decode_append_revision(&table.data),
+                _ => Some(table.data),
+            };
+            match data.and_then(|data| DeltaTextOperations::from_bytes(data).ok()) {
+                None => {
+                    tracing::warn!(
+                        "[TextRevisionSql] Skip the unreadable revision {}:{}",
+                        object_id,
+                        table.rev_id
+                    );
+                    text.push_str(&format!(
+                        "{} {} {}\n",
+                        RECOVERED_MARKER, table.rev_id, RECOVERED_UNREADABLE
+                    ));
+                }
+                Some(operations) => {
+                    text.push_str(&format!("{} {}\n", RECOVERED_MARKER, table.rev_id));
+                    for op in operations.ops.iter() {
+                        if let DeltaOperation::Insert(insert) = op {
+                            text.push_str(insert.s.as_str());
+                        }
+                    }
+                    if !text.ends_with('\n') {
+                        text.push('\n');
+                    }
+                }
+            }
+        }
+        Ok(text)
+    }
+
+    /// Reads the rev_id and the base_rev_id of every revision of the document in rev_id order,
+    /// including the quarantined ones and the rows that share a rev_id.
+    pub(crate) fn read_rev_links(object_id: &str, conn: &SqliteConnection) -> Result<Vec<(i64, i64)>, FlowyError> {
+        let links = dsl::rev_table
+            .filter(dsl::doc_id.eq(object_id))
+            .order((dsl::rev_id.asc(), dsl::id.asc()))
+            .select((dsl::rev_id, dsl::base_rev_id))
+            .load::<(i64, i64)>(conn)
+            .map_err(map_read_error)?;
+        if links.is_empty() {
+            return Err(FlowyError::record_not_found().context(format!("The document:{} has no revisions", object_id)));
+        }
+        Ok(links)
+    }
+
+    /// Reads the metadata of the last `tail` revisions of the document, or of all of them if it's
+    /// None, in the order of `read_rev_links`. Only the length of the payloads is read.
+    pub(crate) fn read_rev_graph_nodes(
+        object_id: &str,
+        tail: Option<usize>,
+        conn: &SqliteConnection,
+    ) -> Result<Vec<RevGraphNode>, FlowyError> {
+        let mut query = dsl::rev_table
+            .filter(dsl::doc_id.eq(object_id))
+            .order((dsl::rev_id.desc(), dsl::id.desc()))
+            .select((
+                dsl::id,
+                dsl::rev_id,
+                dsl::base_rev_id,
+                dsl::state,
+                dsl::ty,
+                sql::<BigInt>("length(data)"),
+            ))
+            .into_boxed();
+        if let Some(tail) = tail {
+            query = query.limit(tail as i64);
+        }
+        let mut rows = query
+            .load::<(i32, i64, i64, TextRevisionState, RevTableType, i64)>(conn)
+            .map_err(map_read_error)?;
+        rows.reverse();
+
+        // The row of a shared payload keeps its hash, the length and the type are the payload's.
+        let shared_ids = rows
+            .iter()
+            .filter(|(_, _, _, _, ty, _)| *ty == RevTableType::Shared)
+            .map(|(id, _, _, _, _, _)| *id)
+            .collect::<Vec<_>>();
+        let mut hashes = HashMap::new();
+        for chunk in shared_ids.chunks(DELETE_REVS_CHUNK_SIZE) {
+            let chunk_hashes = dsl::rev_table
+                .filter(dsl::id.eq_any(chunk))
+                .select((dsl::id, dsl::data))
+                .load::<(i32, Vec<u8>)>(conn)
+                .map_err(map_read_error)?;
+            hashes.extend(
+                chunk_hashes
+                    .into_iter()
+                    .map(|(id, data)| (id, String::from_utf8_lossy(&data).into_owned())),
+            );
+        }
+        let payloads = RevisionPayloadSql::read_lengths(&hashes.values().cloned().collect::<Vec<_>>(), conn)?;
+
+        let nodes = rows
+            .into_iter()
+            .map(|(id, rev_id, base_rev_id, state, mut ty, mut size)| {
+                if let Some(hash) = hashes.get(&id) {
+                    match payloads.get(hash) {
+                        None => tracing::error!(
+                            "[TextRevisionSql] The shared payload of revision {}:{} is missing",
+                            object_id,
+                            rev_id
+                        ),
+                        Some((payload_size, payload_ty)) => {
+                            size = *payload_size;
+                            ty = *payload_ty;
+                        }
+                    }
+                }
+                RevGraphNode {
+                    rev_id,
+                    base_rev_id,
+                    state: state.into(),
+                    author: match ty {
+                        RevTableType::Local | RevTableType::Append => RevGraphAuthor::Local,
+                        RevTableType::Remote => RevGraphAuthor::Remote,
+                        RevTableType::Quarantined | RevTableType::Shared => RevGraphAuthor::Unknown,
+                    },
+                    size: size as usize,
+                    quarantined: ty == RevTableType::Quarantined,
+                }
+            })
+            .collect();
+        Ok(nodes)
+    }
+}
+
+/// The folder of each user saves its revisions in the `rev_table` too, with an object id that
+/// ends with this suffix. Its rows never refer to a shared payload.
+pub(crate) const FOLDER_OBJECT_SUFFIX: &str = ":folder";
+
+/// The folder, the custom dictionaries and the preferences of the documents are saved in the
+/// `rev_table` too, they aren't delta documents.
+fn is_delta_document_id(doc_id: &str) -> bool {
+    !doc_id.ends_with(FOLDER_OBJECT_SUFFIX)
+        && custom_dictionary_workspace_id(doc_id).is_none()
+        && doc_preferences_owner_id(doc_id).is_none()
+}
+
+/// The number of rows `share_all_payloads` reads at a time.
+const SHARE_PAYLOADS_PAGE_SIZE: i64 = 200;
+
+fn load_unquarantined(conn: &SqliteConnection) -> Result<Vec<RevisionTable>, FlowyError> {
+    let rev_tables = dsl::rev_table
+        .filter(dsl::ty.ne(RevTableType::Quarantined))
+        .order(dsl::rev_id.asc())
+        .load::<RevisionTable>(conn)
+        .map_err(map_read_error)?;
+    Ok(rev_tables)
+}
+
+/// Replaces the hash of each revision that refers to a shared payload with the payload and its
+/// type. A revision whose payload is missing keeps the hash, `validate_all` reports it.
+fn resolve_shared_payloads(
+    mut tables: Vec<RevisionTable>,
+    conn: &SqliteConnection,
+) -> Result<Vec<RevisionTable>, FlowyError> {
+    let hashes = tables
+        .iter()
+        .filter(|table| table.ty == RevTableType::Shared)
+        .map(shared_hash)
+        .collect::<Vec<_>>();
+    if hashes.is_empty() {
+        return Ok(tables);
+    }
+
+    let payloads = RevisionPayloadSql::read(&hashes, conn)?;
+    for table in tables.iter_mut().filter(|table| table.ty == RevTableType::Shared) {
+        match payloads.get(&shared_hash(table)) {
+            None => tracing::error!(
+                "[TextRevisionSql] The shared payload of revision {}:{} is missing",
+                table.doc_id,
+                table.rev_id
+            ),
+            Some((data, ty)) => {
+                table.data = data.clone();
+                table.ty = *ty;
+            }
+        }
+    }
+    Ok(tables)
+}
+
+fn shared_hash(table: &RevisionTable) -> String {
+    String::from_utf8_lossy(&table.data).into_owned()
+}
+
+/// Returns the hashes of the shared payloads that the revisions refer to, or that all the
+/// revisions of the object refer to if `rev_ids` is None.
+fn read_shared_hashes(
+    object_id: &str,
+    rev_ids: Option<&[i64]>,
+    conn: &SqliteConnection,
+) -> Result<Vec<String>, FlowyError> {
+    let mut sql = dsl::rev_table
+        .filter(dsl::doc_id.eq(object_id))
+        .filter(dsl::ty.eq(RevTableType::Shared))
+        .select(dsl::data)
+        .into_boxed();
+    if let Some(rev_ids) = rev_ids {
+        sql = sql.filter(dsl::rev_id.eq_any(rev_ids));
+    }
+    let hashes = sql
+        .load::<Vec<u8>>(conn)?
+        .into_iter()
+        .map(|data| String::from_utf8_lossy(&data).into_owned())
+        .collect::<Vec<_>>();
+    Ok(hashes)
+}
+
+/// Returns None if the payload of the revision can be parsed.
+fn check_payload(table: &RevisionTable) -> Option<InvalidRevision> {
+    let (issue, error) = match table.ty {
+        RevTableType::Append => match table.data.get(8..) {
+            None => (PayloadIssue::UndecodableJson, "Truncated append revision".to_owned()),
+            Some(s) => match std::str::from_utf8(s) {
+                Ok(_) => return None,
+                Err(e) => (PayloadIssue::InvalidUtf8, e.to_string()),
+            },
+        },
+        _ => match DeltaTextOperations::from_bytes(&table.data) {
+            Ok(_) => return None,
+            Err(e) => (PayloadIssue::from(&e), e.msg),
+        },
+    };
+    Some(InvalidRevision {
+        doc_id: table.doc_id.clone(),
+        rev_id: table.rev_id,
+        issue,
+        error,
+    })
+}
+
+/// Returns the payload with the invalid UTF-8 replaced, or None if it still can't be parsed.
+fn recover_payload(table: &RevisionTable) -> Option<Vec<u8>> {
+    match table.ty {
+        RevTableType::Append => {
+            let s = table.data.get(8..)?;
+            let mut data = table.data[..8].to_vec();
+            data.extend_from_slice(String::from_utf8_lossy(s).as_bytes());
+            Some(data)
+        }
+        _ => {
+            let json = String::from_utf8_lossy(&table.data);
+            let _ = DeltaTextOperations::from_json(&json).ok()?;
+            Some(json.into_owned().into_bytes())
+        }
+    }
 }
 
 #[derive(PartialEq, Clone, Debug, Queryable, Identifiable, Insertable, Associations)]
@@ -224,15 +1051,16 @@ struct RevisionTable {
     rev_id: i64,
     data: Vec<u8>,
     state: TextRevisionState,
-    ty: RevTableType, // Deprecated
+    ty: RevTableType,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, FromSqlRow, AsExpression)]
 #[repr(i32)]
 #[sql_type = "Integer"]
-enum TextRevisionState {
+pub(crate) enum TextRevisionState {
     Sync = 0,
     Ack = 1,
+    Resolved = 2,
 }
 impl_sql_integer_expression!(TextRevisionState);
 impl_rev_state_map!(TextRevisionState);
@@ -244,14 +1072,15 @@ impl std::default::Default for TextRevisionState {
 }
 
 fn mk_revision_record_from_table(_user_id: &str, table: RevisionTable) -> SyncRecord {
-    let md5 = md5(&table.data);
-    let revision = Revision::new(
-        &table.doc_id,
-        table.base_rev_id,
-        table.rev_id,
-        Bytes::from(table.data),
-        md5,
-    );
+    let data = match table.ty {
+        RevTableType::Append => decode_append_revision(&table.data).unwrap_or_else(|| {
+            tracing::error!("[TextRevisionSql] Decode append revision: {} failed", table.rev_id);
+            table.data
+        }),
+        _ => table.data,
+    };
+    let md5 = md5(&data);
+    let revision = Revision::new(&table.doc_id, table.base_rev_id, table.rev_id, Bytes::from(data), md5);
     SyncRecord {
         revision,
         state: table.state.into(),
@@ -259,12 +1088,56 @@ fn mk_revision_record_from_table(_user_id: &str, table: RevisionTable) -> SyncRe
     }
 }
 
+/// Most of the revisions of a journal-like document only append text to its end. Such a revision
+/// is saved as the utf16 length it retains followed by the appended text instead of the delta's
+/// JSON. Returns None if the revision is not a plain append.
+fn encode_append_revision(bytes: &[u8]) -> Option<Vec<u8>> {
+    let operations = DeltaTextOperations::from_bytes(bytes).ok()?;
+    let (n, s) = match operations.ops.as_slice() {
+        [DeltaOperation::Insert(insert)] if insert.attributes.is_empty() => (0, insert.s.as_str()),
+        [DeltaOperation::Retain(retain), DeltaOperation::Insert(insert)]
+            if retain.attributes.is_empty() && insert.attributes.is_empty() =>
+        {
+            (retain.n, insert.s.as_str())
+        }
+        _ => return None,
+    };
+
+    let mut data = (n as u64).to_be_bytes().to_vec();
+    data.extend_from_slice(s.as_bytes());
+    // The revision must be read back byte for byte, its md5 is computed from its data.
+    match decode_append_revision(&data) {
+        Some(decoded) if decoded == bytes => Some(data),
+        _ => None,
+    }
+}
+
+/// Rebuilds the delta of a row of the `RevTableType::Append` type, which is the utf16 length the
+/// revision retains followed by the appended text.
+fn decode_append_revision(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < 8 {
+        return None;
+    }
+    let (n, s) = data.split_at(8);
+    let n = u64::from_be_bytes(n.try_into().ok()?) as usize;
+    let s = std::str::from_utf8(s).ok()?;
+    let operations = DeltaTextOperationBuilder::new().retain(n).insert(s).build();
+    Some(operations.json_bytes().to_vec())
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, FromSqlRow, AsExpression)]
 #[repr(i32)]
 #[sql_type = "Integer"]
 pub enum RevTableType {
     Local = 0,
     Remote = 1,
+    /// The data is encoded by `encode_append_revision`
+    Append = 2,
+    /// The data can't be read, the revision is skipped when reading the document.
+    Quarantined = 3,
+    /// The data is the hash of the payload saved in the `rev_payload` table, see
+    /// `RevisionPayloadSql`.
+    Shared = 4,
 }
 impl_sql_integer_expression!(RevTableType);
 
@@ -279,6 +1152,9 @@ impl std::convert::From<i32> for RevTableType {
         match value {
             0 => RevTableType::Local,
             1 => RevTableType::Remote,
+            2 => RevTableType::Append,
+            3 => RevTableType::Quarantined,
+            4 => RevTableType::Shared,
             o => {
                 tracing::error!("Unsupported rev type {}, fallback to RevTableType::Local", o);
                 RevTableType::Local
@@ -286,3 +1162,334 @@ impl std::convert::From<i32> for RevTableType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{RevTableType, TextRevisionState};
+    use crate::services::rev_sqlite::{DeltaRevisionSql, RevisionTimestampSql, SQLiteDeltaDocumentRevisionPersistence};
+    use crate::services::{read_repair_audit, PayloadIssue, RepairOutcome};
+    use flowy_database::prelude::*;
+    use flowy_database::schema::{rev_payload, rev_table::dsl};
+    use flowy_database::test_util::TempDatabase;
+    use flowy_error::ErrorCode;
+    use flowy_http_model::revision::Revision;
+    use flowy_revision_persistence::{RevisionDiskCache, SyncRecord};
+    use lib_ot::text_delta::DeltaTextOperationBuilder;
+
+    #[test]
+    fn append_revisions_test() {
+        let database = TempDatabase::new("append_revisions");
+        let persistence = SQLiteDeltaDocumentRevisionPersistence::new("user", database.get_pool());
+
+        let mut len = 0;
+        let mut revisions = (1..=1000)
+            .map(|rev_id| {
+                let s = format!("line {}\n", rev_id);
+                let operations = DeltaTextOperationBuilder::new().retain(len).insert(&s).build();
+                len += s.len();
+                Revision::new("doc", rev_id - 1, rev_id, operations.json_bytes(), "")
+            })
+            .collect::<Vec<Revision>>();
+        // Mixed edits are saved as they are.
+        let operations = DeltaTextOperationBuilder::new().retain(5).delete(5).build();
+        revisions.push(Revision::new("doc", 1000, 1001, operations.json_bytes(), ""));
+        let records = revisions.iter().cloned().map(SyncRecord::new).collect::<Vec<_>>();
+        persistence.create_revision_records(records).unwrap();
+
+        let conn = database.get_connection().unwrap();
+        let rows = dsl::rev_table
+            .select((dsl::data, dsl::ty))
+            .order(dsl::rev_id.asc())
+            .load::<(Vec<u8>, RevTableType)>(&*conn)
+            .unwrap();
+        let types = rows.iter().map(|(_, ty)| *ty).collect::<Vec<RevTableType>>();
+        assert!(types[..1000].iter().all(|ty| *ty == RevTableType::Append));
+        assert_eq!(types[1000], RevTableType::Local);
+        let saved_len = rows.iter().map(|(data, _)| data.len()).sum::<usize>();
+        let json_len = revisions.iter().map(|revision| revision.bytes.len()).sum::<usize>();
+        assert!(saved_len * 2 < json_len, "saved: {}, json: {}", saved_len, json_len);
+
+        let read_revisions = persistence
+            .read_revision_records("doc", None)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.revision)
+            .collect::<Vec<Revision>>();
+        assert_eq!(read_revisions.len(), revisions.len());
+        for (read_revision, revision) in read_revisions.iter().zip(revisions.iter()) {
+            assert_eq!(read_revision.bytes, revision.bytes);
+        }
+    }
+
+    #[test]
+    fn delete_and_insert_records_test() {
+        let database = TempDatabase::new("delete_and_insert");
+        let persistence = SQLiteDeltaDocumentRevisionPersistence::new("user", database.get_pool());
+        let revision = |rev_id: i64, s: &str| {
+            let operations = DeltaTextOperationBuilder::new().insert(s).build();
+            Revision::new("doc", rev_id - 1, rev_id, operations.json_bytes(), "")
+        };
+        let records = (1..=3)
+            .map(|rev_id| SyncRecord::new(revision(rev_id, "abc")))
+            .collect::<Vec<_>>();
+        // The times are written first, `create` keeps them.
+        let create_times = vec![(1, 100), (2, 200), (3, 300)];
+        RevisionTimestampSql::write_create_times("doc", &create_times, &*database.get_pool().get().unwrap()).unwrap();
+        persistence.create_revision_records(records).unwrap();
+
+        // The delete runs in the transaction of the replace, it doesn't open its own.
+        let merged = revision(2, "merged");
+        persistence
+            .delete_and_insert_records("doc", Some(vec![2, 3]), vec![SyncRecord::new(merged.clone())])
+            .unwrap();
+        // The merged revision keeps the time of the newest revision it replaced.
+        assert_eq!(
+            persistence.read_revision_timestamps("doc").unwrap(),
+            vec![(1, 100), (2, 300)]
+        );
+        let read_revisions = persistence
+            .read_revision_records("doc", None)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.revision)
+            .collect::<Vec<Revision>>();
+        assert_eq!(read_revisions.len(), 2);
+        assert_eq!(read_revisions[1].bytes, merged.bytes);
+
+        persistence.delete_revision_records("doc", None).unwrap();
+        assert!(persistence.read_revision_records("doc", None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_revisions_without_table_test() {
+        let database = TempDatabase::new("read_without_table");
+        // The table is missing before the migrations run on a fresh install.
+        let conn = database.get_connection().unwrap();
+        sql_query("DROP TABLE rev_table").execute(&*conn).unwrap();
+
+        let persistence = SQLiteDeltaDocumentRevisionPersistence::new("user", database.get_pool());
+        let error = persistence.read_revision_records("doc", None).unwrap_err();
+        assert_eq!(error.code, ErrorCode::RecordNotFound.value());
+    }
+
+    fn stored_len(conn: &SqliteConnection) -> usize {
+        let rev_len = dsl::rev_table
+            .select(dsl::data)
+            .load::<Vec<u8>>(conn)
+            .unwrap()
+            .iter()
+            .map(|data| data.len())
+            .sum::<usize>();
+        let payload_len = rev_payload::table
+            .select(rev_payload::data)
+            .load::<Vec<u8>>(conn)
+            .unwrap()
+            .iter()
+            .map(|data| data.len())
+            .sum::<usize>();
+        rev_len + payload_len
+    }
+
+    /// Returns the revisions of ten copies of a large document, each copy has a small edit of its own.
+    fn duplicated_documents() -> Vec<Vec<Revision>> {
+        let text = (0..2000).map(|i| format!("line {}\n", i)).collect::<String>();
+        let baseline = DeltaTextOperationBuilder::new().insert(&text).build();
+        (0..10)
+            .map(|i| {
+                let doc_id = format!("doc_{}", i);
+                let edit = DeltaTextOperationBuilder::new()
+                    .retain(text.len())
+                    .insert(&format!("copy {}\n", i))
+                    .build();
+                vec![
+                    Revision::new(&doc_id, 0, 1, baseline.json_bytes(), ""),
+                    Revision::new(&doc_id, 1, 2, edit.json_bytes(), ""),
+                ]
+            })
+            .collect()
+    }
+
+    fn assert_read_documents(persistence: &SQLiteDeltaDocumentRevisionPersistence, documents: &[Vec<Revision>]) {
+        for revisions in documents {
+            let read_revisions = persistence
+                .read_revision_records(&revisions[0].object_id, None)
+                .unwrap()
+                .into_iter()
+                .map(|record| record.revision)
+                .collect::<Vec<Revision>>();
+            assert_eq!(read_revisions.len(), revisions.len());
+            for (read_revision, revision) in read_revisions.iter().zip(revisions.iter()) {
+                assert_eq!(read_revision.bytes, revision.bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn shared_payloads_test() {
+        let database = TempDatabase::new("shared_payloads");
+        let persistence =
+            SQLiteDeltaDocumentRevisionPersistence::new("user", database.get_pool()).with_shared_payloads(true);
+        let conn = database.get_connection().unwrap();
+
+        let documents = duplicated_documents();
+        persistence
+            .create_revision_records(documents[0].iter().cloned().map(SyncRecord::new).collect())
+            .unwrap();
+        let one_copy_len = stored_len(&conn);
+        for revisions in &documents[1..] {
+            persistence
+                .create_revision_records(revisions.iter().cloned().map(SyncRecord::new).collect())
+                .unwrap();
+        }
+        let ten_copies_len = stored_len(&conn);
+        assert!(
+            ten_copies_len < one_copy_len * 2,
+            "one copy: {}, ten copies: {}",
+            one_copy_len,
+            ten_copies_len
+        );
+        assert_read_documents(&persistence, &documents);
+
+        // The payload is deleted with the last revision that refers to it.
+        for revisions in &documents[..9] {
+            persistence
+                .delete_revision_records(&revisions[0].object_id, None)
+                .unwrap();
+        }
+        let ref_counts = rev_payload::table
+            .select(rev_payload::ref_count)
+            .load::<i64>(&*conn)
+            .unwrap();
+        assert_eq!(ref_counts, vec![1]);
+        assert_read_documents(&persistence, &documents[9..]);
+        persistence
+            .delete_revision_records(&documents[9][0].object_id, None)
+            .unwrap();
+        assert_eq!(rev_payload::table.count().get_result::<i64>(&*conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn share_all_payloads_test() {
+        let database = TempDatabase::new("share_all_payloads");
+        // The documents were saved before the payloads were shared.
+        let persistence = SQLiteDeltaDocumentRevisionPersistence::new("user", database.get_pool());
+        let conn = database.get_connection().unwrap();
+        let documents = duplicated_documents();
+        for revisions in &documents {
+            persistence
+                .create_revision_records(revisions.iter().cloned().map(SyncRecord::new).collect())
+                .unwrap();
+        }
+        let inline_len = stored_len(&conn);
+
+        let summary = DeltaRevisionSql::share_all_payloads(&conn).unwrap();
+        assert_eq!(summary.num_of_revisions, 10);
+        assert_eq!(summary.num_of_payloads, 1);
+        assert!(stored_len(&conn) * 5 < inline_len);
+        assert_read_documents(&persistence, &documents);
+        assert!(DeltaRevisionSql::validate_all(&conn).unwrap().is_empty());
+
+        // Running it again finds nothing left to share.
+        let summary = DeltaRevisionSql::share_all_payloads(&conn).unwrap();
+        assert_eq!(summary.num_of_revisions, 0);
+    }
+
+    fn insert_row(rev_id: i64, data: Vec<u8>, ty: RevTableType, conn: &SqliteConnection) {
+        let record = (
+            dsl::doc_id.eq("doc"),
+            dsl::base_rev_id.eq(rev_id - 1),
+            dsl::rev_id.eq(rev_id),
+            dsl::data.eq(data),
+            dsl::state.eq(TextRevisionState::Ack),
+            dsl::ty.eq(ty),
+        );
+        insert_into(dsl::rev_table).values(record).execute(conn).unwrap();
+    }
+
+    fn append_data(n: u64, s: &[u8]) -> Vec<u8> {
+        let mut data = n.to_be_bytes().to_vec();
+        data.extend_from_slice(s);
+        data
+    }
+
+    #[test]
+    fn validate_and_repair_invalid_payloads_test() {
+        let database = TempDatabase::new("invalid_payloads");
+        let conn = database.get_connection().unwrap();
+        insert_row(1, br#"[{"insert":"123"}]"#.to_vec(), RevTableType::Local, &conn);
+        // A flipped bit turned the "4" into an invalid byte.
+        insert_row(
+            2,
+            b"[{\"retain\":3},{\"insert\":\"\xb45\"}]".to_vec(),
+            RevTableType::Local,
+            &conn,
+        );
+        insert_row(3, append_data(5, b"6\xf7"), RevTableType::Append, &conn);
+        // The invalid byte is part of the JSON syntax, the payload can't be recovered.
+        insert_row(
+            4,
+            b"[{\"retain\":7}\xdd{\"insert\":\"8\"}]".to_vec(),
+            RevTableType::Local,
+            &conn,
+        );
+        insert_row(5, br#"[{"retain":7},{"insert""#.to_vec(), RevTableType::Local, &conn);
+
+        let invalid_revisions = DeltaRevisionSql::validate_all(&conn).unwrap();
+        let issues = invalid_revisions
+            .iter()
+            .map(|revision| (revision.rev_id, revision.issue))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (2, PayloadIssue::InvalidUtf8),
+                (3, PayloadIssue::InvalidUtf8),
+                (4, PayloadIssue::InvalidUtf8),
+                (5, PayloadIssue::UndecodableJson),
+            ]
+        );
+
+        let entries = DeltaRevisionSql::repair_all(&conn).unwrap();
+        let outcomes = entries
+            .iter()
+            .map(|entry| (entry.rev_id, entry.outcome))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                (2, RepairOutcome::Recovered),
+                (3, RepairOutcome::Recovered),
+                (4, RepairOutcome::Quarantined),
+                (5, RepairOutcome::Quarantined),
+            ]
+        );
+        let audit = read_repair_audit(&conn).unwrap();
+        assert_eq!(audit.len(), entries.len());
+        assert!(audit
+            .iter()
+            .zip(entries.iter())
+            .all(|(a, b)| a.rev_id == b.rev_id && a.outcome == b.outcome));
+        assert!(DeltaRevisionSql::validate_all(&conn).unwrap().is_empty());
+
+        // The quarantined revisions are skipped when reading the document.
+        let persistence = SQLiteDeltaDocumentRevisionPersistence::new("user", database.get_pool());
+        let revisions = persistence
+            .read_revision_records("doc", None)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.revision)
+            .collect::<Vec<Revision>>();
+        assert_eq!(
+            revisions.iter().map(|revision| revision.rev_id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            String::from_utf8(revisions[1].bytes.clone()).unwrap(),
+            "[{\"retain\":3},{\"insert\":\"\u{fffd}5\"}]"
+        );
+        assert_eq!(
+            String::from_utf8(revisions[2].bytes.clone()).unwrap(),
+            "[{\"retain\":5},{\"insert\":\"6\u{fffd}\"}]"
+        );
+    }
+}
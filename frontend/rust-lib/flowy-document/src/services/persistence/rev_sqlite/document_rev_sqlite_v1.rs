@@ -1,5 +1,13 @@
+use crate::services::rev_sqlite::{
+    map_read_error, with_synchronous_full, DeadLetterSql, PinnedRevisionSql, RevBackupSql, RevIdMapSql,
+    DELETE_REVS_CHUNK_SIZE,
+};
 use bytes::Bytes;
-use diesel::{sql_types::Integer, update, SqliteConnection};
+use diesel::{
+    dsl::sql,
+    sql_types::{BigInt, Integer},
+    update, SqliteConnection,
+};
 use flowy_database::{
     impl_sql_integer_expression, insert_or_ignore_into,
     prelude::*,
@@ -28,6 +36,14 @@ impl RevisionDiskCache<Arc<ConnectionPool>> for SQLiteDocumentRevisionPersistenc
         Ok(())
     }
 
+    /// The commit is fsynced before it returns, see `with_synchronous_full`.
+    fn create_durable_revision_records(&self, revision_records: Vec<SyncRecord>) -> Result<(), Self::Error> {
+        let conn = self.pool.get().map_err(internal_error)?;
+        with_synchronous_full(&conn, || {
+            conn.immediate_transaction::<_, FlowyError, _>(|| DocumentRevisionSql::create(revision_records, &conn))
+        })
+    }
+
     fn get_connection(&self) -> Result<Arc<ConnectionPool>, Self::Error> {
         Ok(self.pool.clone())
     }
@@ -69,6 +85,16 @@ impl RevisionDiskCache<Arc<ConnectionPool>> for SQLiteDocumentRevisionPersistenc
         Ok(())
     }
 
+    fn count_revision_records(&self, object_id: &str) -> Result<u64, Self::Error> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        DocumentRevisionSql::count(object_id, conn)
+    }
+
+    fn delete_revs(&self, object_id: &str, rev_ids: &[i64]) -> Result<usize, Self::Error> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        conn.immediate_transaction::<_, FlowyError, _>(|| DocumentRevisionSql::delete_revs(object_id, rev_ids, conn))
+    }
+
     fn delete_and_insert_records(
         &self,
         object_id: &str,
@@ -82,6 +108,61 @@ impl RevisionDiskCache<Arc<ConnectionPool>> for SQLiteDocumentRevisionPersistenc
             Ok(())
         })
     }
+
+    fn pin_revision(&self, object_id: &str, rev_id: i64) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        PinnedRevisionSql::pin(object_id, rev_id, conn)
+    }
+
+    fn unpin_revision(&self, object_id: &str, rev_id: i64) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        PinnedRevisionSql::unpin(object_id, rev_id, conn)
+    }
+
+    fn read_pinned_rev_ids(&self, object_id: &str) -> FlowyResult<Vec<i64>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        PinnedRevisionSql::read(object_id, conn)
+    }
+
+    fn tag_revision(&self, object_id: &str, rev_id: i64, tag: &str) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        PinnedRevisionSql::tag(object_id, rev_id, tag, conn)
+    }
+
+    fn read_revision_tags(&self, object_id: &str) -> FlowyResult<Vec<(String, i64)>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        PinnedRevisionSql::read_tags(object_id, conn)
+    }
+
+    fn write_dead_letters(&self, object_id: &str, rev_ids: &[i64]) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        DeadLetterSql::write(object_id, rev_ids, conn)
+    }
+
+    fn read_dead_letters(&self, object_id: &str) -> FlowyResult<Vec<i64>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        DeadLetterSql::read(object_id, conn)
+    }
+
+    fn write_rev_id_map(&self, object_id: &str, rev_id_map: &[(i64, i64)]) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevIdMapSql::write(object_id, rev_id_map, conn)
+    }
+
+    fn read_rev_id_map(&self, object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevIdMapSql::read(object_id, conn)
+    }
+
+    fn write_revision_backup(&self, object_id: &str, revisions: &[Revision]) -> FlowyResult<()> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevBackupSql::write(object_id, revisions, conn)
+    }
+
+    fn read_revision_backup(&self, object_id: &str) -> FlowyResult<Vec<Revision>> {
+        let conn = &*self.pool.get().map_err(internal_error)?;
+        RevBackupSql::read(object_id, conn)
+    }
 }
 
 impl SQLiteDocumentRevisionPersistence {
@@ -93,9 +174,28 @@ impl SQLiteDocumentRevisionPersistence {
     }
 }
 
-struct DocumentRevisionSql {}
+pub(crate) struct DocumentRevisionSql {}
 
 impl DocumentRevisionSql {
+    /// Returns the ids of the documents that have revisions.
+    pub(crate) fn read_doc_ids(conn: &SqliteConnection) -> Result<Vec<String>, FlowyError> {
+        dsl::document_rev_table
+            .select(dsl::document_id)
+            .distinct()
+            .order(dsl::document_id.asc())
+            .load::<String>(conn)
+            .map_err(map_read_error)
+    }
+
+    /// Returns the latest rev_id of each document.
+    pub(crate) fn read_heads(conn: &SqliteConnection) -> Result<Vec<(String, i64)>, FlowyError> {
+        dsl::document_rev_table
+            .group_by(dsl::document_id)
+            .select((dsl::document_id, sql::<BigInt>("MAX(rev_id)")))
+            .load::<(String, i64)>(conn)
+            .map_err(map_read_error)
+    }
+
     fn create(revision_records: Vec<SyncRecord>, conn: &SqliteConnection) -> Result<(), FlowyError> {
         // Batch insert: https://diesel.rs/guides/all-about-inserts.html
         let records = revision_records
@@ -149,7 +249,10 @@ impl DocumentRevisionSql {
         if let Some(rev_ids) = rev_ids {
             sql = sql.filter(dsl::rev_id.eq_any(rev_ids));
         }
-        let rows = sql.order(dsl::rev_id.asc()).load::<DocumentRevisionTable>(conn)?;
+        let rows = sql
+            .order(dsl::rev_id.asc())
+            .load::<DocumentRevisionTable>(conn)
+            .map_err(map_read_error)?;
         let records = rows
             .into_iter()
             .map(|row| mk_revision_record_from_table(user_id, row))
@@ -158,6 +261,15 @@ impl DocumentRevisionSql {
         Ok(records)
     }
 
+    fn count(object_id: &str, conn: &SqliteConnection) -> Result<u64, FlowyError> {
+        let count = dsl::document_rev_table
+            .filter(dsl::document_id.eq(object_id))
+            .count()
+            .get_result::<i64>(conn)
+            .map_err(map_read_error)?;
+        Ok(count as u64)
+    }
+
     fn read_with_range(
         user_id: &str,
         object_id: &str,
@@ -169,7 +281,8 @@ impl DocumentRevisionSql {
             .filter(dsl::rev_id.le(range.end))
             .filter(dsl::document_id.eq(object_id))
             .order(dsl::rev_id.asc())
-            .load::<DocumentRevisionTable>(conn)?;
+            .load::<DocumentRevisionTable>(conn)
+            .map_err(map_read_error)?;
 
         let revisions = rev_tables
             .into_iter()
@@ -191,6 +304,22 @@ impl DocumentRevisionSql {
         tracing::trace!("[DocumentRevisionSql] Delete {} rows", affected_row);
         Ok(())
     }
+
+    fn delete_revs(object_id: &str, rev_ids: &[i64], conn: &SqliteConnection) -> Result<usize, FlowyError> {
+        let mut affected_row = 0;
+        for chunk in rev_ids.chunks(DELETE_REVS_CHUNK_SIZE) {
+            let filter = dsl::document_rev_table
+                .filter(dsl::document_id.eq(object_id))
+                .filter(dsl::rev_id.eq_any(chunk));
+            affected_row += diesel::delete(filter).execute(conn)?;
+        }
+        tracing::trace!(
+            "[DocumentRevisionSql] Delete {} of {} revisions",
+            affected_row,
+            rev_ids.len()
+        );
+        Ok(affected_row)
+    }
 }
 
 #[derive(PartialEq, Clone, Debug, Queryable, Identifiable, Insertable, Associations)]
@@ -210,6 +339,7 @@ struct DocumentRevisionTable {
 enum DocumentRevisionState {
     Sync = 0,
     Ack = 1,
+    Resolved = 2,
 }
 impl_sql_integer_expression!(DocumentRevisionState);
 impl_rev_state_map!(DocumentRevisionState);
@@ -235,3 +365,120 @@ fn mk_revision_record_from_table(_user_id: &str, table: DocumentRevisionTable) -
         write_to_disk: false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::services::rev_sqlite::SQLiteDocumentRevisionPersistence;
+    use bytes::Bytes;
+    use flowy_database::test_util::TempDatabase;
+    use flowy_http_model::revision::Revision;
+    use flowy_revision_persistence::{RevisionDiskCache, SyncRecord};
+
+    #[test]
+    fn delete_revs_test() {
+        let database = TempDatabase::new("delete_revs");
+        let persistence = SQLiteDocumentRevisionPersistence::new("user", database.get_pool());
+        for object_id in ["doc_1", "doc_2"] {
+            let records = (1..=2000)
+                .map(|rev_id| {
+                    let revision = Revision::new(object_id, rev_id - 1, rev_id, Bytes::from("123"), "");
+                    SyncRecord::new(revision)
+                })
+                .collect::<Vec<SyncRecord>>();
+            for chunk in records.chunks(100) {
+                persistence.create_revision_records(chunk.to_vec()).unwrap();
+            }
+        }
+
+        // The number of the rev_ids exceeds the number of the parameters SQLite allows in one statement.
+        let rev_ids = (1..=1500).collect::<Vec<i64>>();
+        assert_eq!(persistence.delete_revs("doc_1", &rev_ids).unwrap(), 1500);
+
+        let records = persistence.read_revision_records("doc_1", None).unwrap();
+        assert_eq!(records.len(), 500);
+        assert!(records.iter().all(|record| record.revision.rev_id > 1500));
+
+        // The revisions of the other documents are untouched.
+        let records = persistence.read_revision_records("doc_2", None).unwrap();
+        assert_eq!(records.len(), 2000);
+    }
+
+    #[test]
+    fn durable_revision_records_test() {
+        let database = TempDatabase::new("durable_revisions");
+        let persistence = SQLiteDocumentRevisionPersistence::new("user", database.get_pool());
+        let records = (1..=3)
+            .map(|rev_id| SyncRecord::new(Revision::new("doc", rev_id - 1, rev_id, Bytes::from("123"), "")))
+            .collect::<Vec<SyncRecord>>();
+        persistence.create_durable_revision_records(records).unwrap();
+
+        let records = persistence.read_revision_records("doc", None).unwrap();
+        let rev_ids = records
+            .iter()
+            .map(|record| record.revision.rev_id)
+            .collect::<Vec<i64>>();
+        assert_eq!(rev_ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn pin_revisions_test() {
+        let database = TempDatabase::new("pin_revs");
+        let persistence = SQLiteDocumentRevisionPersistence::new("user", database.get_pool());
+        persistence.pin_revision("doc_1", 5).unwrap();
+        persistence.pin_revision("doc_1", 2).unwrap();
+        // Pinning the same revision twice keeps one row.
+        persistence.pin_revision("doc_1", 5).unwrap();
+        persistence.pin_revision("doc_2", 3).unwrap();
+        assert_eq!(persistence.read_pinned_rev_ids("doc_1").unwrap(), vec![2, 5]);
+
+        persistence.unpin_revision("doc_1", 5).unwrap();
+        assert_eq!(persistence.read_pinned_rev_ids("doc_1").unwrap(), vec![2]);
+        assert_eq!(persistence.read_pinned_rev_ids("doc_2").unwrap(), vec![3]);
+    }
+
+    #[test]
+    fn dead_letters_test() {
+        let database = TempDatabase::new("dead_letters");
+        let persistence = SQLiteDocumentRevisionPersistence::new("user", database.get_pool());
+        persistence.write_dead_letters("doc_1", &[4, 2]).unwrap();
+        persistence.write_dead_letters("doc_2", &[3]).unwrap();
+        assert_eq!(persistence.read_dead_letters("doc_1").unwrap(), vec![2, 4]);
+
+        // The dead letters replace the ones written before.
+        persistence.write_dead_letters("doc_1", &[4]).unwrap();
+        assert_eq!(persistence.read_dead_letters("doc_1").unwrap(), vec![4]);
+        persistence.write_dead_letters("doc_1", &[]).unwrap();
+        assert!(persistence.read_dead_letters("doc_1").unwrap().is_empty());
+        assert_eq!(persistence.read_dead_letters("doc_2").unwrap(), vec![3]);
+    }
+
+    #[test]
+    fn tag_revisions_test() {
+        let database = TempDatabase::new("tag_revs");
+        let persistence = SQLiteDocumentRevisionPersistence::new("user", database.get_pool());
+        persistence.pin_revision("doc_1", 1).unwrap();
+        persistence.tag_revision("doc_1", 4, "v2").unwrap();
+        persistence.tag_revision("doc_1", 2, "v1").unwrap();
+        assert_eq!(
+            persistence.read_revision_tags("doc_1").unwrap(),
+            vec![("v1".to_owned(), 2), ("v2".to_owned(), 4)]
+        );
+        // The tagged revisions are pinned.
+        assert_eq!(persistence.read_pinned_rev_ids("doc_1").unwrap(), vec![1, 2, 4]);
+
+        // Moving the tag keeps the revision that had it pinned.
+        persistence.tag_revision("doc_1", 6, "v2").unwrap();
+        assert_eq!(
+            persistence.read_revision_tags("doc_1").unwrap(),
+            vec![("v1".to_owned(), 2), ("v2".to_owned(), 6)]
+        );
+        assert_eq!(persistence.read_pinned_rev_ids("doc_1").unwrap(), vec![1, 2, 4, 6]);
+
+        persistence.unpin_revision("doc_1", 2).unwrap();
+        assert_eq!(
+            persistence.read_revision_tags("doc_1").unwrap(),
+            vec![("v2".to_owned(), 6)]
+        );
+        assert!(persistence.read_revision_tags("doc_2").unwrap().is_empty());
+    }
+}
@@ -1,10 +1,12 @@
+use crate::dart_notification::{send_dart_notification, DocumentNotification};
+use crate::services::{write_backup_audit, BackupAuditEntry};
 use bytes::Bytes;
 use flowy_database::{
     prelude::*,
-    schema::{document_rev_snapshot, document_rev_snapshot::dsl},
+    schema::{document_recovered_version, document_rev_snapshot, document_rev_snapshot::dsl},
     ConnectionPool,
 };
-use flowy_error::{internal_error, FlowyResult};
+use flowy_error::{internal_error, FlowyError, FlowyResult};
 use flowy_revision::{RevisionSnapshot, RevisionSnapshotDiskCache};
 use lib_infra::util::timestamp;
 use std::sync::Arc;
@@ -70,6 +72,75 @@ impl RevisionSnapshotDiskCache for SQLiteDocumentRevisionSnapshotPersistence {
             .first::<DocumentSnapshotRecord>(&*conn)?;
         Ok(Some(latest_record.into()))
     }
+
+    /// The snapshot is moved to the `document_recovered_version` table with its audit entry in
+    /// one transaction, then the user is notified with `DidRecoverSnapshot`.
+    fn demote_snapshot(&self, snapshot: &RevisionSnapshot, name: &str) -> FlowyResult<()> {
+        let conn = self.pool.get().map_err(internal_error)?;
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let record = (
+                document_recovered_version::object_id.eq(&self.object_id),
+                document_recovered_version::rev_id.eq(snapshot.rev_id),
+                document_recovered_version::name.eq(name),
+                document_recovered_version::timestamp.eq(snapshot.timestamp),
+                document_recovered_version::data.eq(snapshot.data.as_ref()),
+            );
+            let _ = replace_into(document_recovered_version::table)
+                .values(record)
+                .execute(&*conn)?;
+            let _ = diesel::delete(
+                dsl::document_rev_snapshot
+                    .filter(dsl::object_id.eq(&self.object_id))
+                    .filter(dsl::rev_id.eq(snapshot.rev_id)),
+            )
+            .execute(&*conn)?;
+            write_backup_audit(
+                &BackupAuditEntry::recovered_snapshot(&self.object_id, snapshot.rev_id, name),
+                &*conn,
+            )?;
+            Ok(())
+        })?;
+        send_dart_notification(&self.object_id, DocumentNotification::DidRecoverSnapshot).send();
+        Ok(())
+    }
+
+    fn read_demoted_snapshots(&self) -> FlowyResult<Vec<(String, RevisionSnapshot)>> {
+        let conn = self.pool.get().map_err(internal_error)?;
+        let rows = document_recovered_version::table
+            .filter(document_recovered_version::object_id.eq(&self.object_id))
+            .order(document_recovered_version::rev_id.asc())
+            .select((
+                document_recovered_version::name,
+                document_recovered_version::rev_id,
+                document_recovered_version::timestamp,
+                document_recovered_version::data,
+            ))
+            .load::<(String, i64, i64, Vec<u8>)>(&*conn)?;
+        let snapshots = rows
+            .into_iter()
+            .map(|(name, rev_id, timestamp, data)| {
+                let snapshot = RevisionSnapshot {
+                    rev_id,
+                    base_rev_id: rev_id,
+                    timestamp,
+                    data: Bytes::from(data),
+                };
+                (name, snapshot)
+            })
+            .collect();
+        Ok(snapshots)
+    }
+}
+
+/// Returns the rev_id of the last snapshot of the object, None if it has no snapshot.
+pub(crate) fn read_last_snapshot_rev_id(object_id: &str, conn: &SqliteConnection) -> FlowyResult<Option<i64>> {
+    let rev_id = dsl::document_rev_snapshot
+        .filter(dsl::object_id.eq(object_id))
+        .order(dsl::timestamp.desc())
+        .select(dsl::rev_id)
+        .first::<i64>(conn)
+        .optional()?;
+    Ok(rev_id)
 }
 
 #[derive(PartialEq, Clone, Debug, Queryable, Identifiable, Insertable, Associations)]
@@ -1,7 +1,86 @@
+mod dead_letter_sql;
+mod document_chunk_sql;
 mod document_rev_sqlite_v0;
 mod document_rev_sqlite_v1;
 mod document_snapshot;
+mod pinned_revision_sql;
+mod rev_backup_sql;
+mod rev_id_map_sql;
+mod revision_payload_sql;
+mod revision_timestamp_sql;
 
+use diesel::{dsl::sql, sql_types::Integer, SqliteConnection};
+use flowy_database::prelude::*;
+use flowy_error::{FlowyError, FlowyResult};
+
+pub(crate) use dead_letter_sql::*;
+pub(crate) use document_chunk_sql::*;
 pub use document_rev_sqlite_v0::*;
 pub use document_rev_sqlite_v1::*;
 pub use document_snapshot::*;
+pub(crate) use pinned_revision_sql::*;
+pub(crate) use rev_backup_sql::*;
+pub(crate) use rev_id_map_sql::*;
+pub use revision_payload_sql::*;
+pub(crate) use revision_timestamp_sql::*;
+
+/// The number of rev_ids bound to one delete statement. SQLite limits the number of the
+/// parameters of a statement, which is 999 before version 3.32.0.
+pub(crate) const DELETE_REVS_CHUNK_SIZE: usize = 500;
+
+/// Maps the error of reading the revision tables. The tables don't exist before the migrations
+/// run on a fresh install, the document is considered as not existing yet instead of failing
+/// with an internal error.
+pub(crate) fn map_read_error(error: flowy_database::Error) -> FlowyError {
+    match &error {
+        flowy_database::Error::DatabaseError(_, info) if info.message().starts_with("no such table") => {
+            FlowyError::record_not_found().context(error)
+        }
+        _ => error.into(),
+    }
+}
+
+/// Runs the `f` with `PRAGMA synchronous = FULL` on the `conn`, so the transaction it commits is
+/// fsynced before it returns. The pool's connections commit with `PRAGMA synchronous = NORMAL`,
+/// the commit is only fsynced at the next checkpoint of the WAL. The previous value is restored
+/// afterwards.
+pub(crate) fn with_synchronous_full<T, F>(conn: &SqliteConnection, f: F) -> FlowyResult<T>
+where
+    F: FnOnce() -> FlowyResult<T>,
+{
+    let synchronous = sql::<Integer>("PRAGMA synchronous").get_result::<i32>(conn)?;
+    let _ = sql_query("PRAGMA synchronous = FULL").execute(conn)?;
+    let result = f();
+    let _ = sql_query(format!("PRAGMA synchronous = {}", synchronous)).execute(conn)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::with_synchronous_full;
+    use diesel::{dsl::sql, sql_types::Integer};
+    use flowy_database::prelude::*;
+    use flowy_database::test_util::TempDatabase;
+    use flowy_error::FlowyError;
+
+    /// The value of `PRAGMA synchronous = FULL`.
+    const SYNCHRONOUS_FULL: i32 = 2;
+
+    #[test]
+    fn with_synchronous_full_test() {
+        let database = TempDatabase::new("synchronous_full");
+        let conn = database.get_connection().unwrap();
+        let read_synchronous = || sql::<Integer>("PRAGMA synchronous").get_result::<i32>(&*conn).unwrap();
+        let synchronous = read_synchronous();
+        assert_ne!(synchronous, SYNCHRONOUS_FULL);
+
+        let value = with_synchronous_full(&conn, || Ok(read_synchronous())).unwrap();
+        assert_eq!(value, SYNCHRONOUS_FULL);
+        assert_eq!(read_synchronous(), synchronous);
+
+        // The previous value is restored when the `f` fails too.
+        let result = with_synchronous_full::<(), _>(&conn, || Err(FlowyError::internal()));
+        assert!(result.is_err());
+        assert_eq!(read_synchronous(), synchronous);
+    }
+}
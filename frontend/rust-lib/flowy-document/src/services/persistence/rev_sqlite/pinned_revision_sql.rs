@@ -0,0 +1,72 @@
+use crate::services::rev_sqlite::map_read_error;
+use flowy_database::{insert_or_ignore_into, prelude::*, schema::pinned_revisions::dsl};
+use flowy_error::FlowyError;
+
+/// Reads and writes the `pinned_revisions` table. The table is shared by the revision tables of
+/// both document versions, each row pins one revision of one object.
+pub(crate) struct PinnedRevisionSql {}
+
+impl PinnedRevisionSql {
+    pub(crate) fn pin(object_id: &str, rev_id: i64, conn: &SqliteConnection) -> Result<(), FlowyError> {
+        let record = (dsl::object_id.eq(object_id), dsl::rev_id.eq(rev_id));
+        let _ = insert_or_ignore_into(dsl::pinned_revisions)
+            .values(record)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn unpin(object_id: &str, rev_id: i64, conn: &SqliteConnection) -> Result<(), FlowyError> {
+        let filter = dsl::pinned_revisions
+            .filter(dsl::object_id.eq(object_id))
+            .filter(dsl::rev_id.eq(rev_id));
+        let _ = diesel::delete(filter).execute(conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn read(object_id: &str, conn: &SqliteConnection) -> Result<Vec<i64>, FlowyError> {
+        let rev_ids = dsl::pinned_revisions
+            .filter(dsl::object_id.eq(object_id))
+            .select(dsl::rev_id)
+            .order(dsl::rev_id.asc())
+            .load::<i64>(conn)
+            .map_err(map_read_error)?;
+        Ok(rev_ids)
+    }
+
+    /// Pins the revision and names it `tag`. The tag is moved from the revision that had it
+    /// before, that revision stays pinned.
+    pub(crate) fn tag(object_id: &str, rev_id: i64, tag: &str, conn: &SqliteConnection) -> Result<(), FlowyError> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| Self::insert_tag(object_id, rev_id, tag, conn))
+    }
+
+    /// Like `tag`, but runs on the connection of the caller, which should be in a transaction.
+    pub(crate) fn insert_tag(
+        object_id: &str,
+        rev_id: i64,
+        tag: &str,
+        conn: &SqliteConnection,
+    ) -> Result<(), FlowyError> {
+        let tagged = dsl::pinned_revisions
+            .filter(dsl::object_id.eq(object_id))
+            .filter(dsl::tag.eq(tag));
+        let _ = diesel::update(tagged).set(dsl::tag.eq("")).execute(conn)?;
+        Self::pin(object_id, rev_id, conn)?;
+        let pinned = dsl::pinned_revisions
+            .filter(dsl::object_id.eq(object_id))
+            .filter(dsl::rev_id.eq(rev_id));
+        let _ = diesel::update(pinned).set(dsl::tag.eq(tag)).execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the tags and the rev_ids of the tagged revisions in ascending order of the rev_id
+    pub(crate) fn read_tags(object_id: &str, conn: &SqliteConnection) -> Result<Vec<(String, i64)>, FlowyError> {
+        let tags = dsl::pinned_revisions
+            .filter(dsl::object_id.eq(object_id))
+            .filter(dsl::tag.ne(""))
+            .select((dsl::tag, dsl::rev_id))
+            .order(dsl::rev_id.asc())
+            .load::<(String, i64)>(conn)
+            .map_err(map_read_error)?;
+        Ok(tags)
+    }
+}
@@ -0,0 +1,60 @@
+use crate::services::rev_sqlite::map_read_error;
+use crate::services::{write_backup_audit, BackupAuditEntry};
+use flowy_database::{prelude::*, schema::rev_backup::dsl};
+use flowy_error::FlowyError;
+use flowy_http_model::revision::Revision;
+
+/// The number of revisions inserted by one statement, each revision binds 5 parameters.
+const INSERT_CHUNK_SIZE: usize = 100;
+
+/// Reads and writes the `rev_backup` table, which keeps the copy of the revisions of an object
+/// that were replaced the last time it was restored from its snapshot. The table is shared by
+/// the revision tables of both document versions.
+pub(crate) struct RevBackupSql {}
+
+impl RevBackupSql {
+    /// Replaces the backup of the object with the `revisions`, and writes the audit entry of the
+    /// backup in the same transaction.
+    pub(crate) fn write(object_id: &str, revisions: &[Revision], conn: &SqliteConnection) -> Result<(), FlowyError> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let _ = diesel::delete(dsl::rev_backup.filter(dsl::object_id.eq(object_id))).execute(conn)?;
+            for chunk in revisions.chunks(INSERT_CHUNK_SIZE) {
+                let records = chunk
+                    .iter()
+                    .map(|revision| {
+                        (
+                            dsl::object_id.eq(object_id),
+                            dsl::rev_id.eq(revision.rev_id),
+                            dsl::base_rev_id.eq(revision.base_rev_id),
+                            dsl::data.eq(&revision.bytes),
+                            dsl::md5.eq(&revision.md5),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let _ = diesel::insert_into(dsl::rev_backup).values(&records).execute(conn)?;
+            }
+            write_backup_audit(&BackupAuditEntry::revisions(object_id, revisions.len()), conn)?;
+            Ok(())
+        })
+    }
+
+    pub(crate) fn read(object_id: &str, conn: &SqliteConnection) -> Result<Vec<Revision>, FlowyError> {
+        let rows = dsl::rev_backup
+            .filter(dsl::object_id.eq(object_id))
+            .select((dsl::rev_id, dsl::base_rev_id, dsl::data, dsl::md5))
+            .order(dsl::rev_id.asc())
+            .load::<(i64, i64, Vec<u8>, String)>(conn)
+            .map_err(map_read_error)?;
+        let revisions = rows
+            .into_iter()
+            .map(|(rev_id, base_rev_id, bytes, md5)| Revision {
+                base_rev_id,
+                rev_id,
+                bytes,
+                md5,
+                object_id: object_id.to_owned(),
+            })
+            .collect();
+        Ok(revisions)
+    }
+}
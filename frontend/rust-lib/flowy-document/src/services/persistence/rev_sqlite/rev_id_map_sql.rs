@@ -0,0 +1,39 @@
+use crate::services::rev_sqlite::map_read_error;
+use flowy_database::{prelude::*, schema::rev_id_map::dsl};
+use flowy_error::FlowyError;
+
+/// Reads and writes the `rev_id_map` table, which keeps the rev_ids assigned by the client mapped
+/// to the rev_ids the server acked the revisions with. The table is shared by the revision tables
+/// of both document versions.
+pub(crate) struct RevIdMapSql {}
+
+impl RevIdMapSql {
+    /// Replaces the map of the object with the `rev_id_map`.
+    pub(crate) fn write(object_id: &str, rev_id_map: &[(i64, i64)], conn: &SqliteConnection) -> Result<(), FlowyError> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let _ = diesel::delete(dsl::rev_id_map.filter(dsl::object_id.eq(object_id))).execute(conn)?;
+            let records = rev_id_map
+                .iter()
+                .map(|(rev_id, canonical_rev_id)| {
+                    (
+                        dsl::object_id.eq(object_id),
+                        dsl::rev_id.eq(*rev_id),
+                        dsl::canonical_rev_id.eq(*canonical_rev_id),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let _ = diesel::insert_into(dsl::rev_id_map).values(&records).execute(conn)?;
+            Ok(())
+        })
+    }
+
+    pub(crate) fn read(object_id: &str, conn: &SqliteConnection) -> Result<Vec<(i64, i64)>, FlowyError> {
+        let rev_id_map = dsl::rev_id_map
+            .filter(dsl::object_id.eq(object_id))
+            .select((dsl::rev_id, dsl::canonical_rev_id))
+            .order(dsl::rev_id.asc())
+            .load::<(i64, i64)>(conn)
+            .map_err(map_read_error)?;
+        Ok(rev_id_map)
+    }
+}
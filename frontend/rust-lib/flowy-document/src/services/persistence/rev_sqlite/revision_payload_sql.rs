@@ -0,0 +1,98 @@
+use crate::services::rev_sqlite::{map_read_error, RevTableType, DELETE_REVS_CHUNK_SIZE};
+use diesel::dsl::sql;
+use diesel::sql_types::{BigInt, Binary, Integer, Text};
+use flowy_database::{prelude::*, schema::rev_payload::dsl, sql_query};
+use flowy_error::FlowyError;
+use flowy_http_model::util::md5;
+use std::collections::HashMap;
+
+/// The payloads shorter than this stay in the row of their revision. Sharing them saves less
+/// than the hash that the row would refer to them with.
+pub(crate) const MIN_SHARED_PAYLOAD_LEN: usize = 256;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PayloadDedupeSummary {
+    /// The revisions that refer to a shared payload instead of keeping their own.
+    pub num_of_revisions: usize,
+    /// The distinct payloads the revisions refer to.
+    pub num_of_payloads: usize,
+}
+
+/// Reads and writes the `rev_payload` table. A revision of the `rev_table` whose type is
+/// `RevTableType::Shared` keeps the hash of its payload instead of the payload. The payload
+/// is saved once in this table with the number of the revisions that refer to it.
+pub(crate) struct RevisionPayloadSql {}
+
+impl RevisionPayloadSql {
+    /// The type is part of the hash, the same bytes are decoded differently by each type.
+    pub(crate) fn hash(data: &[u8], ty: RevTableType) -> String {
+        format!("{}:{}", ty as i32, md5(data))
+    }
+
+    /// Adds a reference to the payload, it's saved if nothing refers to it yet.
+    pub(crate) fn retain(hash: &str, data: &[u8], ty: RevTableType, conn: &SqliteConnection) -> Result<(), FlowyError> {
+        let _ = sql_query(
+            "INSERT INTO rev_payload (hash, data, ty, ref_count) VALUES (?, ?, ?, 1) \
+             ON CONFLICT(hash) DO UPDATE SET ref_count = ref_count + 1",
+        )
+        .bind::<Text, _>(hash)
+        .bind::<Binary, _>(data)
+        .bind::<Integer, _>(ty)
+        .execute(conn)?;
+        Ok(())
+    }
+
+    /// Drops one reference for each of the `hashes`, a hash that's passed twice drops two. The
+    /// payloads that nothing refers to anymore are deleted.
+    pub(crate) fn release(hashes: &[String], conn: &SqliteConnection) -> Result<(), FlowyError> {
+        if hashes.is_empty() {
+            return Ok(());
+        }
+        for hash in hashes {
+            let filter = dsl::rev_payload.filter(dsl::hash.eq(hash));
+            let _ = diesel::update(filter)
+                .set(dsl::ref_count.eq(dsl::ref_count - 1))
+                .execute(conn)?;
+        }
+        let _ = diesel::delete(dsl::rev_payload.filter(dsl::ref_count.le(0))).execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the payloads and their types by hash. The missing hashes are left out.
+    pub(crate) fn read(
+        hashes: &[String],
+        conn: &SqliteConnection,
+    ) -> Result<HashMap<String, (Vec<u8>, RevTableType)>, FlowyError> {
+        let mut payloads = HashMap::new();
+        for chunk in hashes.chunks(DELETE_REVS_CHUNK_SIZE) {
+            let rows = dsl::rev_payload
+                .filter(dsl::hash.eq_any(chunk))
+                .select((dsl::hash, dsl::data, dsl::ty))
+                .load::<(String, Vec<u8>, RevTableType)>(conn)
+                .map_err(map_read_error)?;
+            for (hash, data, ty) in rows {
+                payloads.insert(hash, (data, ty));
+            }
+        }
+        Ok(payloads)
+    }
+
+    /// Like `read` but only reads the length of each payload, not the payload itself.
+    pub(crate) fn read_lengths(
+        hashes: &[String],
+        conn: &SqliteConnection,
+    ) -> Result<HashMap<String, (i64, RevTableType)>, FlowyError> {
+        let mut lengths = HashMap::new();
+        for chunk in hashes.chunks(DELETE_REVS_CHUNK_SIZE) {
+            let rows = dsl::rev_payload
+                .filter(dsl::hash.eq_any(chunk))
+                .select((dsl::hash, sql::<BigInt>("length(data)"), dsl::ty))
+                .load::<(String, i64, RevTableType)>(conn)
+                .map_err(map_read_error)?;
+            for (hash, length, ty) in rows {
+                lengths.insert(hash, (length, ty));
+            }
+        }
+        Ok(lengths)
+    }
+}
@@ -0,0 +1,156 @@
+use crate::services::rev_sqlite::{map_read_error, DELETE_REVS_CHUNK_SIZE};
+use flowy_database::{insert_or_ignore_into, prelude::*, schema::rev_timestamp::dsl};
+use flowy_error::FlowyError;
+
+/// Reads and writes the `rev_timestamp` table, which keeps the time that each revision was
+/// written at and the time it expires at. A revision that is written again, e.g. merged into
+/// another one, gets a new time.
+pub(crate) struct RevisionTimestampSql {}
+
+impl RevisionTimestampSql {
+    pub(crate) fn write(
+        object_id: &str,
+        rev_ids: &[i64],
+        create_time: i64,
+        conn: &SqliteConnection,
+    ) -> Result<(), FlowyError> {
+        let records = rev_ids
+            .iter()
+            .map(|rev_id| {
+                (
+                    dsl::object_id.eq(object_id),
+                    dsl::rev_id.eq(*rev_id),
+                    dsl::create_time.eq(create_time),
+                )
+            })
+            .collect::<Vec<_>>();
+        let _ = insert_or_ignore_into(dsl::rev_timestamp)
+            .values(&records)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Same as `write` with the time that each revision was written at.
+    pub(crate) fn write_create_times(
+        object_id: &str,
+        create_times: &[(i64, i64)],
+        conn: &SqliteConnection,
+    ) -> Result<(), FlowyError> {
+        let records = create_times
+            .iter()
+            .map(|(rev_id, create_time)| {
+                (
+                    dsl::object_id.eq(object_id),
+                    dsl::rev_id.eq(*rev_id),
+                    dsl::create_time.eq(*create_time),
+                )
+            })
+            .collect::<Vec<_>>();
+        let _ = insert_or_ignore_into(dsl::rev_timestamp)
+            .values(&records)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns the time that the newest of the revisions was written at, in seconds.
+    pub(crate) fn read_newest(
+        object_id: &str,
+        rev_ids: &[i64],
+        conn: &SqliteConnection,
+    ) -> Result<Option<i64>, FlowyError> {
+        let mut newest = None;
+        for chunk in rev_ids.chunks(DELETE_REVS_CHUNK_SIZE) {
+            let create_time = dsl::rev_timestamp
+                .filter(dsl::object_id.eq(object_id))
+                .filter(dsl::rev_id.eq_any(chunk))
+                .select(diesel::dsl::max(dsl::create_time))
+                .first::<Option<i64>>(conn)
+                .map_err(map_read_error)?;
+            newest = newest.max(create_time);
+        }
+        Ok(newest)
+    }
+
+    /// Returns the rev_ids with the time each revision was written at, in seconds.
+    pub(crate) fn read(object_id: &str, conn: &SqliteConnection) -> Result<Vec<(i64, i64)>, FlowyError> {
+        let timestamps = dsl::rev_timestamp
+            .filter(dsl::object_id.eq(object_id))
+            .select((dsl::rev_id, dsl::create_time))
+            .order(dsl::rev_id.asc())
+            .load::<(i64, i64)>(conn)
+            .map_err(map_read_error)?;
+        Ok(timestamps)
+    }
+
+    /// Returns the time that the latest revision of any object was written at, in seconds.
+    pub(crate) fn read_latest(conn: &SqliteConnection) -> Result<Option<i64>, FlowyError> {
+        let latest = dsl::rev_timestamp
+            .select(diesel::dsl::max(dsl::create_time))
+            .first::<Option<i64>>(conn)
+            .map_err(map_read_error)?;
+        Ok(latest)
+    }
+
+    /// Returns the ids of the objects that have a revision written at or after `create_time`.
+    pub(crate) fn read_object_ids_since(create_time: i64, conn: &SqliteConnection) -> Result<Vec<String>, FlowyError> {
+        let object_ids = dsl::rev_timestamp
+            .filter(dsl::create_time.ge(create_time))
+            .select(dsl::object_id)
+            .distinct()
+            .load::<String>(conn)
+            .map_err(map_read_error)?;
+        Ok(object_ids)
+    }
+
+    /// Sets the time that the revisions expire at, in seconds. None keeps them for good. The
+    /// revisions written before their time was kept can't expire.
+    pub(crate) fn expire(
+        object_id: &str,
+        rev_ids: &[i64],
+        expires_at: Option<i64>,
+        conn: &SqliteConnection,
+    ) -> Result<(), FlowyError> {
+        for chunk in rev_ids.chunks(DELETE_REVS_CHUNK_SIZE) {
+            let filter = dsl::rev_timestamp
+                .filter(dsl::object_id.eq(object_id))
+                .filter(dsl::rev_id.eq_any(chunk));
+            let _ = diesel::update(filter)
+                .set(dsl::expires_at.eq(expires_at))
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the rev_ids of the revisions that expire with the time they expire at.
+    pub(crate) fn read_expiries(object_id: &str, conn: &SqliteConnection) -> Result<Vec<(i64, i64)>, FlowyError> {
+        let expiries = dsl::rev_timestamp
+            .filter(dsl::object_id.eq(object_id))
+            .filter(dsl::expires_at.is_not_null())
+            .select((dsl::rev_id, dsl::expires_at))
+            .order(dsl::rev_id.asc())
+            .load::<(i64, Option<i64>)>(conn)
+            .map_err(map_read_error)?;
+        Ok(expiries
+            .into_iter()
+            .flat_map(|(rev_id, expires_at)| expires_at.map(|expires_at| (rev_id, expires_at)))
+            .collect())
+    }
+
+    /// Deletes the times of the revisions, or of all the revisions if the `rev_ids` is None.
+    pub(crate) fn delete(object_id: &str, rev_ids: Option<&[i64]>, conn: &SqliteConnection) -> Result<(), FlowyError> {
+        match rev_ids {
+            None => {
+                let _ = diesel::delete(dsl::rev_timestamp.filter(dsl::object_id.eq(object_id))).execute(conn)?;
+            }
+            Some(rev_ids) => {
+                for chunk in rev_ids.chunks(DELETE_REVS_CHUNK_SIZE) {
+                    let filter = dsl::rev_timestamp
+                        .filter(dsl::object_id.eq(object_id))
+                        .filter(dsl::rev_id.eq_any(chunk));
+                    let _ = diesel::delete(filter).execute(conn)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+use crate::services::rev_sqlite::{DeltaRevisionSql, PinnedRevisionSql};
+use crate::services::{doc_preferences_content, doc_preferences_doc_id, Attachment, DocMetaSql, DocPreference};
+use flowy_database::prelude::*;
+use flowy_error::{FlowyError, FlowyResult};
+use flowy_http_model::revision::Revision;
+use lib_infra::util::timestamp;
+use lib_ot::text_delta::{attachment_ids, DeltaTextOperationBuilder, DeltaTextOperations};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// The extension of the files written by `DocumentManager::export_doc_portable`.
+pub const PORTABLE_DOCUMENT_EXTENSION: &str = "affdoc";
+
+/// The version of the format of the portable documents. It's bumped whenever the older
+/// versions can't read the files of the new format, they refuse those files instead of
+/// importing a part of them.
+///
+/// 2: the data of the revisions and the attachments is written as base64 instead of an array
+/// of numbers, the files of the version 1 are still read.
+pub const PORTABLE_DOCUMENT_FORMAT_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortableDocumentMeta {
+    /// The id of the exported document, the imported document gets a new id.
+    pub doc_id: String,
+    pub rev_id: i64,
+    pub exported_time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortableRevision {
+    pub base_rev_id: i64,
+    pub rev_id: i64,
+    #[serde(with = "base64_bytes")]
+    pub bytes: Vec<u8>,
+    pub md5: String,
+}
+
+impl std::convert::From<Revision> for PortableRevision {
+    fn from(revision: Revision) -> Self {
+        Self {
+            base_rev_id: revision.base_rev_id,
+            rev_id: revision.rev_id,
+            bytes: revision.bytes,
+            md5: revision.md5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortableAttachment {
+    pub id: String,
+    pub name: String,
+    #[serde(with = "base64_bytes")]
+    pub data: Vec<u8>,
+    pub update_time: i64,
+}
+
+impl std::convert::From<Attachment> for PortableAttachment {
+    fn from(attachment: Attachment) -> Self {
+        Self {
+            id: attachment.id,
+            name: attachment.name,
+            data: attachment.data,
+            update_time: attachment.update_time,
+        }
+    }
+}
+
+impl std::convert::From<PortableAttachment> for Attachment {
+    fn from(attachment: PortableAttachment) -> Self {
+        Self {
+            id: attachment.id,
+            name: attachment.name,
+            data: attachment.data,
+            update_time: attachment.update_time,
+        }
+    }
+}
+
+/// A delta document with its whole history, its named versions, its preferences and the
+/// attachments that any of its revisions refers to. It's written as a single file, so one
+/// document can be sent to another user without sharing the workspace.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortableDocument {
+    /// Read before the rest of the file, see `PORTABLE_DOCUMENT_FORMAT_VERSION`.
+    pub format_version: u32,
+    pub meta: PortableDocumentMeta,
+    pub revisions: Vec<PortableRevision>,
+    /// The tags of the revisions, see `DocumentManager::tag_revision`.
+    pub tags: Vec<(String, i64)>,
+    pub attachments: Vec<PortableAttachment>,
+    /// The preferences of the document, see `DocumentManager::set_doc_preference`. The files of
+    /// the version 1 have none.
+    #[serde(default)]
+    pub preferences: BTreeMap<String, DocPreference>,
+}
+
+#[derive(Deserialize)]
+struct PortableDocumentHeader {
+    format_version: u32,
+}
+
+impl PortableDocument {
+    pub(crate) fn new(
+        doc_id: &str,
+        rev_id: i64,
+        revisions: Vec<Revision>,
+        tags: Vec<(String, i64)>,
+        attachments: Vec<Attachment>,
+        preferences: BTreeMap<String, DocPreference>,
+    ) -> Self {
+        Self {
+            format_version: PORTABLE_DOCUMENT_FORMAT_VERSION,
+            meta: PortableDocumentMeta {
+                doc_id: doc_id.to_owned(),
+                rev_id,
+                exported_time: timestamp(),
+            },
+            revisions: revisions.into_iter().map(PortableRevision::from).collect(),
+            tags,
+            attachments: attachments.into_iter().map(PortableAttachment::from).collect(),
+            preferences,
+        }
+    }
+
+    /// Writes the document into a hidden file next to the `path` first and renames it, so a
+    /// failed export doesn't leave a partial file behind. It blocks, run it on the blocking
+    /// threads.
+    pub(crate) fn write_to(&self, path: &Path) -> FlowyResult<()> {
+        let bytes = serde_json::to_vec(self).map_err(|e| FlowyError::serde().context(e))?;
+        let partial_path = partial_path(path)?;
+        let result = std::fs::write(&partial_path, bytes).and_then(|_| std::fs::rename(&partial_path, path));
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&partial_path);
+            return Err(FlowyError::internal().context(format!("Write {:?} failed: {}", path, e)));
+        }
+        Ok(())
+    }
+
+    /// It blocks like `write_to`.
+    pub(crate) fn read_from(path: &Path) -> FlowyResult<Self> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| FlowyError::internal().context(format!("Read {:?} failed: {}", path, e)))?;
+        let invalid = |e: serde_json::Error| {
+            FlowyError::invalid_data().context(format!("{:?} isn't a portable document: {}", path, e))
+        };
+        let header = serde_json::from_slice::<PortableDocumentHeader>(&bytes).map_err(invalid)?;
+        if header.format_version > PORTABLE_DOCUMENT_FORMAT_VERSION {
+            return Err(FlowyError::unsupported_format_version().context(format!(
+                "{:?} was exported by a newer version of AppFlowy with the format {}, this version reads up to {}",
+                path, header.format_version, PORTABLE_DOCUMENT_FORMAT_VERSION
+            )));
+        }
+        serde_json::from_slice(&bytes).map_err(invalid)
+    }
+
+    /// Returns the history of the document with its revisions moved to the document `doc_id`.
+    pub(crate) fn revisions_of(&self, doc_id: &str) -> Vec<Revision> {
+        self.revisions
+            .iter()
+            .map(|revision| Revision {
+                base_rev_id: revision.base_rev_id,
+                rev_id: revision.rev_id,
+                bytes: revision.bytes.clone(),
+                md5: revision.md5.clone(),
+                object_id: doc_id.to_owned(),
+            })
+            .collect()
+    }
+
+    /// Saves the history, the tags and the preferences as the ones of the new document `doc_id`
+    /// in one transaction, so a failed import leaves nothing of the document behind. The
+    /// revisions aren't synced yet, they're pushed once the document is opened.
+    pub(crate) fn save_as(&self, doc_id: &str, conn: &SqliteConnection) -> FlowyResult<()> {
+        let mut revisions = self.revisions_of(doc_id);
+        if !self.preferences.is_empty() {
+            // The preferences are kept in a document of their own, the `doc_meta` table keeps
+            // a copy of them.
+            let operations = DeltaTextOperationBuilder::new()
+                .insert(&doc_preferences_content(&self.preferences))
+                .build();
+            revisions.push(Revision::initial_revision(
+                &doc_preferences_doc_id(doc_id),
+                operations.json_bytes(),
+            ));
+        }
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            DeltaRevisionSql::write_revisions(revisions, conn)?;
+            for (tag, rev_id) in self.tags.iter() {
+                PinnedRevisionSql::insert_tag(doc_id, *rev_id, tag, conn)?;
+            }
+            if !self.preferences.is_empty() {
+                DocMetaSql::replace_row(doc_id, &serde_json::to_string(&self.preferences)?, conn)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Returns the ids of the attachments that any revision refers to, so the older versions of the
+/// document still render after it's imported.
+pub(crate) fn referenced_attachment_ids(revisions: &[Revision]) -> FlowyResult<BTreeSet<String>> {
+    let mut ids = BTreeSet::new();
+    for revision in revisions {
+        let operations = DeltaTextOperations::from_bytes(&revision.bytes)?;
+        ids.extend(attachment_ids(&operations));
+    }
+    Ok(ids)
+}
+
+fn partial_path(path: &Path) -> FlowyResult<PathBuf> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| FlowyError::invalid_data().context(format!("Invalid export path: {:?}", path)))?;
+    Ok(path.with_file_name(format!(".{}.partial", name)))
+}
+
+/// Writes the bytes as a base64 string. The files of the version 1 have them as an array of
+/// numbers, they're read as well.
+mod base64_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum EncodedBytes {
+            Base64(String),
+            Numbers(Vec<u8>),
+        }
+        match EncodedBytes::deserialize(deserializer)? {
+            EncodedBytes::Base64(encoded) => base64::decode(encoded).map_err(serde::de::Error::custom),
+            EncodedBytes::Numbers(bytes) => Ok(bytes),
+        }
+    }
+}
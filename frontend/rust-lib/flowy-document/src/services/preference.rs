@@ -0,0 +1,283 @@
+use crate::dart_notification::{send_dart_notification, DocumentNotification};
+use crate::entities::DocPreferencesPB;
+use crate::services::{custom_dictionary_workspace_id, matching_lines, CustomDictionaryObserver, DocumentMeta};
+use flowy_database::{
+    insert_or_ignore_into,
+    prelude::*,
+    schema::{doc_meta, doc_meta::dsl},
+    ConnectionPool,
+};
+use flowy_error::{FlowyError, FlowyResult};
+use lib_ot::core::Interval;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The preferences of a document, e.g. its font size or its width, are kept in a delta document
+/// of their own with one entry per line. They sync between the devices like the custom
+/// dictionary without adding revisions to the document itself, and the latest entry of each key
+/// wins after the entries of two devices merge.
+const DOC_PREFERENCES_PREFIX: &str = "doc_preferences:";
+
+pub fn doc_preferences_doc_id(doc_id: &str) -> String {
+    format!("{}{}", DOC_PREFERENCES_PREFIX, doc_id)
+}
+
+/// Returns the document whose preferences are kept in the document `doc_id`, if any.
+pub(crate) fn doc_preferences_owner_id(doc_id: &str) -> Option<&str> {
+    doc_id.strip_prefix(DOC_PREFERENCES_PREFIX)
+}
+
+/// An entry of the preferences document. The `value` is JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocPreference {
+    pub key: String,
+    pub value: String,
+    /// In milliseconds.
+    pub updated_at: i64,
+}
+
+impl DocPreference {
+    /// The later entry of a key wins. The entries set at the same millisecond are ordered by
+    /// their value, so every device picks the same one whichever entry it got first.
+    fn supersedes(&self, other: &DocPreference) -> bool {
+        (self.updated_at, &self.value) > (other.updated_at, &other.value)
+    }
+
+    pub(crate) fn line(&self) -> FlowyResult<String> {
+        Ok(format!("{}\n", serde_json::to_string(self)?))
+    }
+}
+
+/// Returns the trimmed key. A key can't be empty, the value must be JSON.
+pub(crate) fn validate_doc_preference<'a>(key: &'a str, value: &str) -> FlowyResult<&'a str> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(FlowyError::invalid_data().context("The preference key is empty"));
+    }
+    if let Err(e) = serde_json::from_str::<serde_json::Value>(value) {
+        return Err(FlowyError::invalid_data().context(format!("The value of {} isn't JSON: {}", key, e)));
+    }
+    Ok(key)
+}
+
+/// Returns the winning entry of each key of the preferences' content. The lines that aren't
+/// entries are skipped.
+pub(crate) fn doc_preferences(content: &str) -> BTreeMap<String, DocPreference> {
+    let mut preferences: BTreeMap<String, DocPreference> = BTreeMap::new();
+    for preference in content
+        .lines()
+        .flat_map(|line| serde_json::from_str::<DocPreference>(line).ok())
+    {
+        match preferences.get(&preference.key) {
+            Some(latest) if !preference.supersedes(latest) => {}
+            _ => {
+                preferences.insert(preference.key.clone(), preference);
+            }
+        }
+    }
+    preferences
+}
+
+/// Merges the entries of the preferences per key, see `ContentMerge`. The latest entry of each
+/// key wins whichever device set it, so a value set here isn't lost to an older value of the
+/// server and the other way round. Returns one entry per line.
+pub(crate) fn merge_doc_preferences_content(_base: &str, local: &str, server: &str) -> String {
+    let mut preferences = doc_preferences(server);
+    for (key, preference) in doc_preferences(local) {
+        match preferences.get(&key) {
+            Some(latest) if !preference.supersedes(latest) => {}
+            _ => {
+                preferences.insert(key, preference);
+            }
+        }
+    }
+    doc_preferences_content(&preferences)
+}
+
+/// Returns the content of the preferences document with one entry per line.
+pub(crate) fn doc_preferences_content(preferences: &BTreeMap<String, DocPreference>) -> String {
+    // The document ends with a newline like the preferences the entries are inserted into.
+    let mut content = preferences
+        .values()
+        .flat_map(|preference| preference.line().ok())
+        .collect::<String>();
+    content.push('\n');
+    content
+}
+
+/// Returns the intervals of the lines of the `key`'s entries, see `dictionary_word_lines`. There
+/// is more than one after the entries of two devices merge.
+pub(crate) fn doc_preference_lines(content: &str, key: &str) -> Vec<Interval> {
+    matching_lines(
+        content,
+        |text| matches!(serde_json::from_str::<DocPreference>(text), Ok(preference) if preference.key == key),
+    )
+}
+
+/// Returns the JSON value of each key.
+pub(crate) fn doc_preference_values(preferences: &BTreeMap<String, DocPreference>) -> BTreeMap<String, String> {
+    preferences
+        .iter()
+        .map(|(key, preference)| (key.clone(), preference.value.clone()))
+        .collect()
+}
+
+/// Keeps the winning entries of each document's preferences in the `preferences` column of the
+/// `doc_meta` table, so they're read without opening the preferences document.
+pub(crate) struct DocMetaSql {}
+
+impl DocMetaSql {
+    pub(crate) fn read_preferences(
+        doc_id: &str,
+        conn: &SqliteConnection,
+    ) -> FlowyResult<BTreeMap<String, DocPreference>> {
+        let preferences = dsl::doc_meta
+            .filter(dsl::doc_id.eq(doc_id))
+            .select(dsl::preferences)
+            .first::<String>(conn)
+            .optional()?;
+        match preferences {
+            None => Ok(BTreeMap::new()),
+            Some(json) => Ok(serde_json::from_str(&json)?),
+        }
+    }
+
+    /// Returns false if the document already had these preferences.
+    pub(crate) fn replace_preferences(
+        doc_id: &str,
+        preferences: &BTreeMap<String, DocPreference>,
+        conn: &SqliteConnection,
+    ) -> FlowyResult<bool> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            if &Self::read_preferences(doc_id, conn)? == preferences {
+                return Ok(false);
+            }
+            Self::replace_row(doc_id, &serde_json::to_string(preferences)?, conn)?;
+            Ok(true)
+        })
+    }
+
+    /// Returns the meta that the document had when it was last closed, with its current
+    /// preferences. None if the document was never closed on this device.
+    pub(crate) fn read_meta(doc_id: &str, conn: &SqliteConnection) -> FlowyResult<Option<DocumentMeta>> {
+        let row = dsl::doc_meta
+            .filter(dsl::doc_id.eq(doc_id))
+            .select((dsl::skeleton, dsl::preferences))
+            .first::<(String, String)>(conn)
+            .optional()?;
+        match row {
+            Some((skeleton, preferences)) if !skeleton.is_empty() => {
+                let mut meta = serde_json::from_str::<DocumentMeta>(&skeleton)?;
+                let preferences = serde_json::from_str::<BTreeMap<String, DocPreference>>(&preferences)?;
+                meta.preferences = doc_preference_values(&preferences);
+                Ok(Some(meta))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Saves the `meta` without its preferences, they're kept in their own column.
+    pub(crate) fn write_skeleton(doc_id: &str, meta: &DocumentMeta, conn: &SqliteConnection) -> FlowyResult<()> {
+        let skeleton = serde_json::to_string(meta)?;
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let _ = insert_or_ignore_into(doc_meta::table)
+                .values(dsl::doc_id.eq(doc_id))
+                .execute(conn)?;
+            let _ = diesel::update(dsl::doc_meta.filter(dsl::doc_id.eq(doc_id)))
+                .set(dsl::skeleton.eq(skeleton))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Returns the doc_id and the preferences' JSON of each row.
+    pub(crate) fn read_rows(conn: &SqliteConnection) -> FlowyResult<Vec<(String, String)>> {
+        let rows = dsl::doc_meta
+            .select((dsl::doc_id, dsl::preferences))
+            .order(dsl::doc_id.asc())
+            .load::<(String, String)>(conn)?;
+        Ok(rows)
+    }
+
+    /// Replaces the preferences of the row, the skeleton of the document is kept.
+    pub(crate) fn replace_row(doc_id: &str, preferences: &str, conn: &SqliteConnection) -> FlowyResult<()> {
+        let _ = insert_or_ignore_into(doc_meta::table)
+            .values(dsl::doc_id.eq(doc_id))
+            .execute(conn)?;
+        let _ = diesel::update(dsl::doc_meta.filter(dsl::doc_id.eq(doc_id)))
+            .set(dsl::preferences.eq(preferences))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn delete_row(doc_id: &str, conn: &SqliteConnection) -> FlowyResult<()> {
+        let _ = diesel::delete(dsl::doc_meta.filter(dsl::doc_id.eq(doc_id))).execute(conn)?;
+        Ok(())
+    }
+}
+
+/// Keeps the `doc_meta` table up to date with the preferences document and tells the editor of
+/// the document with the `DidUpdateDocPreferences` notification.
+#[derive(Clone)]
+pub(crate) struct DocPreferencesObserver {
+    doc_id: String,
+    pool: Arc<ConnectionPool>,
+}
+
+impl DocPreferencesObserver {
+    pub(crate) fn new(doc_id: &str, pool: Arc<ConnectionPool>) -> Self {
+        Self {
+            doc_id: doc_id.to_owned(),
+            pool,
+        }
+    }
+
+    /// Saves the preferences of the `content` and sends the notification if they changed.
+    /// Returns the values of the preferences.
+    pub(crate) fn save(&self, content: &str) -> FlowyResult<BTreeMap<String, String>> {
+        let preferences = doc_preferences(content);
+        let conn = self.pool.get()?;
+        let values = doc_preference_values(&preferences);
+        if DocMetaSql::replace_preferences(&self.doc_id, &preferences, &conn)? {
+            send_dart_notification(&self.doc_id, DocumentNotification::DidUpdateDocPreferences)
+                .payload(DocPreferencesPB::new(&self.doc_id, &values))
+                .send();
+        }
+        Ok(values)
+    }
+
+    pub(crate) fn did_receive_remote_change(&self, content: &str) {
+        if let Err(e) = self.save(content) {
+            tracing::error!("Save the preferences of {} failed: {:?}", self.doc_id, e);
+        }
+    }
+}
+
+/// Set if the document keeps the data of the app rather than the text of the user, the table
+/// of the data is updated after the remote changes are applied to the document.
+#[derive(Clone)]
+pub(crate) enum ContentObserver {
+    CustomDictionary(CustomDictionaryObserver),
+    DocPreferences(DocPreferencesObserver),
+}
+
+impl ContentObserver {
+    pub(crate) fn from_doc_id(doc_id: &str, pool: Arc<ConnectionPool>) -> Option<Self> {
+        if let Some(workspace_id) = custom_dictionary_workspace_id(doc_id) {
+            return Some(Self::CustomDictionary(CustomDictionaryObserver::new(
+                workspace_id,
+                pool,
+            )));
+        }
+        doc_preferences_owner_id(doc_id)
+            .map(|owner_id| Self::DocPreferences(DocPreferencesObserver::new(owner_id, pool)))
+    }
+
+    pub(crate) fn did_receive_remote_change(&self, content: &str) {
+        match self {
+            ContentObserver::CustomDictionary(observer) => observer.did_receive_remote_change(content),
+            ContentObserver::DocPreferences(observer) => observer.did_receive_remote_change(content),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::services::LazyDocument;
+use flowy_error::{FlowyError, FlowyResult};
+use lib_ot::core::DeltaOperation;
+use lib_ot::text_delta::{is_archived, word_count, DeltaTextOperations, WordCounter};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The number of the graphemes of the preview that the open event returns for
+/// `DocumentFieldPB::Preview`.
+pub const DEFAULT_PREVIEW_LEN: usize = 200;
+
+/// The bytes of the visible text that are read for each grapheme of the preview. A grapheme
+/// that is longer, e.g. a char with dozens of combining marks, may be cut.
+const MAX_PREVIEW_GRAPHEME_LEN: usize = 32;
+
+/// The utf16 length of the regions of a closed document that `lazy_document_meta` counts the
+/// words of one by one.
+const META_REGION_LEN: usize = 64 * 1024;
+
+/// The beginning of the visible text of a document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentPreview {
+    pub text: String,
+    /// True if the document has more visible text than the preview.
+    pub truncated: bool,
+}
+
+/// The size of a document and its revision, without its content.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentMeta {
+    pub rev_id: i64,
+    /// The oldest revision that isn't synced yet, None if all the revisions are synced.
+    pub next_sync_rev_id: Option<i64>,
+    /// The utf16 length of the document, including the archived text.
+    pub num_of_chars: usize,
+    /// The words of the visible text, see `word_count`.
+    pub num_of_words: usize,
+    /// The JSON value of each preference, see `DocumentManager::set_doc_preference`. They're
+    /// saved on their own, so they're left out of the serialized meta.
+    #[serde(skip)]
+    pub preferences: BTreeMap<String, String>,
+}
+
+/// What `DocumentManager::open_latest_available` returns without waiting behind the background
+/// work on the disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AvailableDocument {
+    /// The document was already opened, with its JSON.
+    Ready(String),
+    /// The document is being read, it's sent with the `DidOpenDocument` notification once it's
+    /// opened. The meta is the one the document had when it was last closed on this device,
+    /// with its current preferences.
+    Loading(Option<DocumentMeta>),
+}
+
+/// Returns the first `max_len` graphemes of the document's text. The archived text is skipped
+/// and the rest of the document is never read, so the preview of a large document costs about
+/// as much as the one of a small document.
+pub fn document_preview(operations: &DeltaTextOperations, max_len: usize) -> DocumentPreview {
+    let max_bytes = max_len.saturating_mul(MAX_PREVIEW_GRAPHEME_LEN);
+    let mut text = String::new();
+    let mut has_more = false;
+    for op in operations.ops.iter() {
+        let insert = match op {
+            DeltaOperation::Insert(insert) => insert,
+            _ => continue,
+        };
+        if is_archived(&insert.attributes) || insert.s.is_empty() {
+            continue;
+        }
+        let rest = max_bytes - text.len();
+        if rest == 0 {
+            has_more = true;
+            break;
+        }
+        let s = insert.s.as_str();
+        if s.len() <= rest {
+            text.push_str(s);
+            continue;
+        }
+        let mut end = rest;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.push_str(&s[..end]);
+        has_more = true;
+        break;
+    }
+
+    // Read one grapheme past the preview to tell if it's followed by more text. The last
+    // grapheme of the text that was read may be incomplete if there is more text after it.
+    let mut graphemes = text.grapheme_indices(true);
+    let end = match graphemes.nth(max_len) {
+        Some((end, _)) => Some(end),
+        None if has_more => text.grapheme_indices(true).last().map(|(end, _)| end),
+        None => None,
+    };
+    match end {
+        None => DocumentPreview { text, truncated: false },
+        Some(end) => {
+            text.truncate(end);
+            DocumentPreview { text, truncated: true }
+        }
+    }
+}
+
+/// Same as `document_preview`, but only the beginning of the closed document is composed. The
+/// region is doubled until it has the whole preview, e.g. past a long archived intro.
+pub(crate) fn lazy_document_preview(document: &LazyDocument, max_len: usize) -> FlowyResult<DocumentPreview> {
+    let mut region_len = max_len.saturating_mul(MAX_PREVIEW_GRAPHEME_LEN).max(1);
+    loop {
+        let preview = document_preview(&document.range(0, region_len)?, max_len);
+        if preview.truncated || region_len >= document.len() {
+            return Ok(preview);
+        }
+        region_len = region_len.saturating_mul(2);
+    }
+}
+
+/// Same as `document_meta`, but the closed document is never composed as a whole. Its length is
+/// known without composing it, and its words are counted one region at a time.
+pub(crate) fn lazy_document_meta(
+    document: &LazyDocument,
+    rev_id: i64,
+    next_sync_rev_id: Option<i64>,
+) -> FlowyResult<DocumentMeta> {
+    let mut words = WordCounter::new(false);
+    let mut start = 0;
+    while start < document.len() {
+        words.add(&document.range(start, META_REGION_LEN)?);
+        start += META_REGION_LEN;
+    }
+    Ok(DocumentMeta {
+        rev_id,
+        next_sync_rev_id,
+        num_of_chars: document.len(),
+        num_of_words: words.count(),
+        preferences: BTreeMap::new(),
+    })
+}
+
+/// Returns the `byte_len` bytes of the UTF-8 `content` from `byte_start`, for the protocols that
+/// address the text by byte offsets rather than by chars. Both ends of the range must fall on
+/// char boundaries, a char is never split.
+pub fn content_byte_range(content: &str, byte_start: usize, byte_len: usize) -> FlowyResult<&[u8]> {
+    let byte_end = byte_start
+        .checked_add(byte_len)
+        .filter(|byte_end| *byte_end <= content.len())
+        .ok_or_else(|| {
+            FlowyError::out_of_bounds().context(format!(
+                "The {} bytes from {} are out of the {} bytes of the content",
+                byte_len,
+                byte_start,
+                content.len()
+            ))
+        })?;
+    if !content.is_char_boundary(byte_start) || !content.is_char_boundary(byte_end) {
+        return Err(FlowyError::invalid_boundary().context(format!(
+            "The bytes {}..{} split a char of the content",
+            byte_start, byte_end
+        )));
+    }
+    Ok(&content.as_bytes()[byte_start..byte_end])
+}
+
+pub(crate) fn document_meta(
+    operations: &DeltaTextOperations,
+    rev_id: i64,
+    next_sync_rev_id: Option<i64>,
+) -> DocumentMeta {
+    DocumentMeta {
+        rev_id,
+        next_sync_rev_id,
+        num_of_chars: operations.utf16_target_len,
+        num_of_words: word_count(operations, false),
+        preferences: BTreeMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flowy_error::ErrorCode;
+    use lib_ot::core::{AttributeHashMap, DeltaOperationBuilder};
+    use lib_ot::text_delta::BuildInTextAttribute;
+
+    #[test]
+    fn document_preview_skips_archived_text() {
+        let mut archived = AttributeHashMap::new();
+        archived.insert_entry(BuildInTextAttribute::Archived(true));
+        let operations = DeltaOperationBuilder::new()
+            .insert("ab")
+            .insert_with_attributes("cd", archived)
+            .insert("ef\n")
+            .build();
+        assert_eq!(
+            document_preview(&operations, 3),
+            DocumentPreview {
+                text: "abe".to_owned(),
+                truncated: true
+            }
+        );
+        assert_eq!(document_preview(&operations, 5).text, "abef\n");
+        assert!(!document_preview(&operations, 5).truncated);
+    }
+
+    #[test]
+    fn document_preview_keeps_graphemes_whole() {
+        // The family emoji is a single grapheme of 7 chars, it's either kept or dropped whole.
+        let operations = DeltaOperationBuilder::new().insert("a👨‍👩‍👧‍👦b\n").build();
+        assert_eq!(document_preview(&operations, 1).text, "a");
+        assert_eq!(document_preview(&operations, 2).text, "a👨‍👩‍👧‍👦");
+        assert_eq!(document_preview(&operations, 3).text, "a👨‍👩‍👧‍👦b");
+    }
+
+    #[test]
+    fn content_byte_range_test() {
+        // "é" is 2 bytes and "👋" is 4 bytes.
+        let content = "aé👋b\n";
+        assert_eq!(content_byte_range(content, 0, 1).unwrap(), b"a");
+        assert_eq!(content_byte_range(content, 1, 6).unwrap(), "é👋".as_bytes());
+        assert_eq!(content_byte_range(content, 7, 2).unwrap(), b"b\n");
+        assert_eq!(content_byte_range(content, 9, 0).unwrap(), b"");
+
+        let error = content_byte_range(content, 2, 1).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidBoundary.value());
+        let error = content_byte_range(content, 1, 4).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidBoundary.value());
+        let error = content_byte_range(content, 7, 3).unwrap_err();
+        assert_eq!(error.code, ErrorCode::OutOfBounds.value());
+        let error = content_byte_range(content, usize::MAX, 2).unwrap_err();
+        assert_eq!(error.code, ErrorCode::OutOfBounds.value());
+    }
+}
@@ -0,0 +1,30 @@
+use flowy_error::{FlowyError, FlowyResult};
+use std::path::{Path, PathBuf};
+
+/// The folder under the user's folder that keeps the text recovered by `recover_text`.
+pub(crate) const RECOVERED_TEXT_DIR: &str = "recovered";
+
+/// Starts the line before the text of each revision in the recovered text, followed by the
+/// rev_id. The revisions that can't be read are marked with `RECOVERED_UNREADABLE` after it.
+pub const RECOVERED_MARKER: &str = "--- revision";
+
+pub const RECOVERED_UNREADABLE: &str = "(unreadable)";
+
+/// Writes the recovered text of the document into a new file under `dir`, named after the
+/// document and the current time, so the earlier recoveries are kept.
+pub(crate) fn write_recovered_text(dir: &Path, doc_id: &str, text: &str) -> FlowyResult<PathBuf> {
+    std::fs::create_dir_all(dir).map_err(|e| FlowyError::internal().context(e))?;
+    let doc_name = doc_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect::<String>();
+    let time = chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+    let mut path = dir.join(format!("{}-{}.txt", doc_name, time));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}-{}.txt", doc_name, time, n));
+        n += 1;
+    }
+    std::fs::write(&path, text).map_err(|e| FlowyError::internal().context(e))?;
+    Ok(path)
+}
@@ -0,0 +1,219 @@
+use crate::services::rev_sqlite::{RevTableType, SQLiteDeltaDocumentRevisionPersistence};
+use crate::services::{custom_dictionary_workspace_id, doc_preferences_owner_id, AttachmentStore};
+use crate::{DocumentExportTargets, ReexportSummary};
+use diesel::sql_types::{BigInt, Integer, Text};
+use flowy_database::{
+    prelude::*,
+    schema::{document_export_stamp, document_export_stamp::dsl},
+    sql_query, ConnectionPool,
+};
+use flowy_error::{internal_error, FlowyResult};
+use flowy_revision::{composable_revisions, Executor, PriorityScheduler, TaskPriority};
+use flowy_revision_persistence::RevisionDiskCache;
+use flowy_sync::util::make_operations_from_revisions;
+use lib_ot::codec::markdown::markdown_list::encode_markdown_list_with_custom_attributes;
+use lib_ot::core::AttributeHashMap;
+use lib_ot::text_delta::{
+    resolve_attachments, without_archived, BuildInTextAttributeKey, CustomAttributes, DeltaTextOperations,
+};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The number of the documents read from the database at a time.
+const REEXPORT_PAGE_SIZE: i64 = 50;
+
+/// Regenerates the exports of the delta documents that were exported by an older exporter
+/// version or changed since. Each regenerated document is stamped with the exporter version
+/// and its rev_id, so the next run skips it.
+pub(crate) struct DocumentReexport {
+    user_id: String,
+    pool: Arc<ConnectionPool>,
+    targets: DocumentExportTargets,
+    custom_attributes: CustomAttributes,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl DocumentReexport {
+    pub(crate) fn new(
+        user_id: &str,
+        pool: Arc<ConnectionPool>,
+        targets: DocumentExportTargets,
+        custom_attributes: CustomAttributes,
+        cancelled: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            user_id: user_id.to_owned(),
+            pool,
+            targets,
+            custom_attributes,
+            cancelled,
+        }
+    }
+
+    /// Runs the re-export page by page on the blocking threads of the `executor`, each page is a
+    /// background task of the `scheduler`, so the reads of the focused document run between the
+    /// pages. The `progress` is called with the summary so far after each page.
+    pub(crate) async fn run<F>(
+        self,
+        executor: &Executor,
+        scheduler: &PriorityScheduler,
+        progress: F,
+    ) -> FlowyResult<ReexportSummary>
+    where
+        F: Fn(&ReexportSummary),
+    {
+        let reexport = Arc::new(self);
+        let mut summary = ReexportSummary::default();
+        let mut after = String::new();
+        loop {
+            let cloned_reexport = reexport.clone();
+            let (next, page_summary) = scheduler
+                .spawn_blocking(executor, TaskPriority::Background, move || {
+                    let mut summary = summary;
+                    let next = cloned_reexport.run_page(&after, &mut summary);
+                    (next, summary)
+                })
+                .await
+                .map_err(internal_error)?;
+            summary = page_summary;
+            progress(&summary);
+            match next? {
+                None => return Ok(summary),
+                Some(last_doc_id) => after = last_doc_id,
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// Regenerates the stale documents of the page after the `after` doc_id. Returns the last
+    /// doc_id of the page, or None if there are no more documents.
+    fn run_page(&self, after: &str, summary: &mut ReexportSummary) -> FlowyResult<Option<String>> {
+        let conn = self.pool.get()?;
+        let page = ExportStampSql::read_document_page(after, REEXPORT_PAGE_SIZE, &conn)?;
+        for document in page.iter() {
+            if self.cancelled.load(Ordering::SeqCst) {
+                summary.cancelled = true;
+                return Ok(None);
+            }
+            // The custom dictionaries and the preferences are documents too, but they aren't exported.
+            if custom_dictionary_workspace_id(&document.doc_id).is_some()
+                || doc_preferences_owner_id(&document.doc_id).is_some()
+            {
+                summary.num_of_skipped += 1;
+                continue;
+            }
+            let stamp = ExportStampSql::read(&document.doc_id, &conn)?;
+            if stamp == Some((self.targets.exporter_version(), document.rev_id)) {
+                summary.num_of_skipped += 1;
+                continue;
+            }
+            match self.export_document(&document.doc_id) {
+                Ok(_) => {
+                    ExportStampSql::write(
+                        &document.doc_id,
+                        self.targets.exporter_version(),
+                        document.rev_id,
+                        &conn,
+                    )?;
+                    summary.num_of_regenerated += 1;
+                }
+                Err(e) => {
+                    tracing::error!("Re-export {} failed: {:?}", document.doc_id, e);
+                    summary.num_of_failed += 1;
+                }
+            }
+        }
+        Ok(page.last().map(|document| document.doc_id.clone()))
+    }
+
+    fn export_document(&self, doc_id: &str) -> FlowyResult<()> {
+        let disk_cache = SQLiteDeltaDocumentRevisionPersistence::new(&self.user_id, self.pool.clone());
+        let revisions = composable_revisions(&disk_cache.read_revision_records(doc_id, None)?);
+        let operations = make_operations_from_revisions::<AttributeHashMap>(revisions)?;
+        warn_unknown_attributes(doc_id, &operations, &self.custom_attributes);
+        // The references to the attachments are exported with the current names of the
+        // attachments, they may have been renamed since the reference was inserted.
+        let attachments = AttachmentStore::new(self.pool.clone());
+        let operations = resolve_attachments(&without_archived(&operations), |attachment_id| {
+            attachments
+                .read(attachment_id)
+                .ok()
+                .flatten()
+                .map(|attachment| attachment.name)
+        });
+        let markdown = encode_markdown_list_with_custom_attributes(
+            &operations,
+            self.targets.wrap_width(),
+            &self.custom_attributes,
+        );
+        self.targets.write(doc_id, &markdown)
+    }
+}
+
+/// The registered custom attributes are exported with the text, the keys that are neither
+/// built-in nor registered are left out of the export.
+fn warn_unknown_attributes(doc_id: &str, operations: &DeltaTextOperations, custom_attributes: &CustomAttributes) {
+    let unknown_keys = operations
+        .ops
+        .iter()
+        .flat_map(|operation| operation.get_attributes().keys().cloned().collect::<Vec<String>>())
+        .filter(|key| BuildInTextAttributeKey::from_str(key).is_err() && custom_attributes.semantics(key).is_none())
+        .collect::<BTreeSet<String>>();
+    if !unknown_keys.is_empty() {
+        tracing::warn!(
+            "The export of {} ignores the unknown attributes {:?}",
+            doc_id,
+            unknown_keys
+        );
+    }
+}
+
+#[derive(QueryableByName)]
+struct DocumentRevId {
+    #[sql_type = "Text"]
+    doc_id: String,
+    #[sql_type = "BigInt"]
+    rev_id: i64,
+}
+
+struct ExportStampSql {}
+
+impl ExportStampSql {
+    /// Returns the documents whose doc_id is greater than `after` with their latest rev_id,
+    /// ordered by the doc_id.
+    fn read_document_page(after: &str, limit: i64, conn: &SqliteConnection) -> FlowyResult<Vec<DocumentRevId>> {
+        let page = sql_query(
+            "SELECT doc_id, MAX(rev_id) AS rev_id FROM rev_table \
+             WHERE doc_id > ? AND ty != ? GROUP BY doc_id ORDER BY doc_id LIMIT ?",
+        )
+        .bind::<Text, _>(after)
+        .bind::<Integer, _>(RevTableType::Quarantined)
+        .bind::<BigInt, _>(limit)
+        .load::<DocumentRevId>(conn)?;
+        Ok(page)
+    }
+
+    /// Returns the exporter version and the rev_id of the document's last export.
+    fn read(doc_id: &str, conn: &SqliteConnection) -> FlowyResult<Option<(i64, i64)>> {
+        let stamp = dsl::document_export_stamp
+            .filter(dsl::doc_id.eq(doc_id))
+            .select((dsl::exporter_version, dsl::rev_id))
+            .first::<(i64, i64)>(conn)
+            .optional()?;
+        Ok(stamp)
+    }
+
+    fn write(doc_id: &str, exporter_version: i64, rev_id: i64, conn: &SqliteConnection) -> FlowyResult<()> {
+        let record = (
+            dsl::doc_id.eq(doc_id),
+            dsl::exporter_version.eq(exporter_version),
+            dsl::rev_id.eq(rev_id),
+        );
+        let _ = replace_into(document_export_stamp::table)
+            .values(record)
+            .execute(conn)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use flowy_revision_persistence::RevisionState;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// Where the revision was made, as far as the revision table tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevGraphAuthor {
+    Local,
+    Remote,
+    /// The quarantined revisions and the ones whose shared payload is missing lost their type.
+    Unknown,
+}
+
+impl RevGraphAuthor {
+    fn label(&self) -> &'static str {
+        match self {
+            RevGraphAuthor::Local => "local",
+            RevGraphAuthor::Remote => "remote",
+            RevGraphAuthor::Unknown => "unknown",
+        }
+    }
+}
+
+/// The metadata of one row of the revision table, the payload itself is never read into it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevGraphNode {
+    pub rev_id: i64,
+    pub base_rev_id: i64,
+    pub state: RevisionState,
+    pub author: RevGraphAuthor,
+    /// The number of bytes the payload takes in the table.
+    pub size: usize,
+    pub quarantined: bool,
+}
+
+/// The revision chain of a document in rev_id order, see `DocumentManager::export_rev_graph`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RevGraph {
+    pub doc_id: String,
+    /// The rev_id and the base_rev_id of every revision of the chain.
+    pub links: Vec<(i64, i64)>,
+    /// The metadata of the last revisions of the chain, the earlier ones are only in `links`.
+    pub nodes: Vec<RevGraphNode>,
+    /// The rev_id the last snapshot of the document was taken at.
+    pub snapshot_rev_id: Option<i64>,
+}
+
+impl RevGraph {
+    /// Renders the chain as a Graphviz DOT digraph. Each revision points to the revisions based
+    /// on it, so a fork shows as a revision with more than one edge out. The revisions with the
+    /// same rev_id, the quarantined ones, the missing bases and the snapshot are highlighted.
+    ///
+    /// Only the `nodes` are drawn. The gaps and the forks are still found in the whole chain of
+    /// the `links`, the revisions left out are drawn as one node.
+    pub fn to_dot(&self) -> String {
+        let rev_ids = self.links.iter().map(|(rev_id, _)| *rev_id).collect::<HashSet<i64>>();
+        let mut children = HashMap::<i64, HashSet<i64>>::new();
+        let mut num_of_duplicates = HashMap::<i64, usize>::new();
+        for (rev_id, base_rev_id) in self.links.iter() {
+            children.entry(*base_rev_id).or_default().insert(*rev_id);
+            *num_of_duplicates.entry(*rev_id).or_default() += 1;
+        }
+
+        let skip = self.links.len().saturating_sub(self.nodes.len());
+        let nodes = &self.nodes;
+        let drawn_rev_ids = nodes.iter().map(|node| node.rev_id).collect::<HashSet<i64>>();
+
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph \"{}\" {{", escape(&self.doc_id));
+        let _ = writeln!(dot, "  rankdir=LR;");
+        let _ = writeln!(dot, "  node [shape=box, fontname=\"monospace\"];");
+        if skip > 0 {
+            let _ = writeln!(
+                dot,
+                "  truncated [label=\"{} earlier revisions\", shape=plaintext];",
+                skip
+            );
+        }
+
+        let mut seen = HashMap::<i64, usize>::new();
+        let mut missing = vec![];
+        for node in nodes {
+            let index = seen.entry(node.rev_id).or_default();
+            let id = node_id(node.rev_id, *index);
+            *index += 1;
+
+            let mut labels = vec![
+                node.rev_id.to_string(),
+                format!("{} {} {}B", state_label(&node.state), node.author.label(), node.size),
+            ];
+            let mut attributes = vec![];
+            if node.quarantined {
+                labels.push("quarantined".to_owned());
+                attributes.push("style=\"filled,dashed\"".to_owned());
+                attributes.push("fillcolor=gray80".to_owned());
+            } else if num_of_duplicates.get(&node.rev_id).map_or(0, |num| *num) > 1 {
+                labels.push("duplicate".to_owned());
+                attributes.push("style=filled".to_owned());
+                attributes.push("fillcolor=orange".to_owned());
+            }
+            if children.get(&node.rev_id).map_or(0, |children| children.len()) > 1 {
+                labels.push("fork".to_owned());
+                attributes.push("color=purple".to_owned());
+            }
+            if self.snapshot_rev_id == Some(node.rev_id) {
+                labels.push("snapshot".to_owned());
+                attributes.push("peripheries=2".to_owned());
+            }
+            let _ = writeln!(dot, "  {} [label=\"{}\"{}];", id, labels.join("\\n"), join(&attributes));
+
+            if node.base_rev_id <= 0 {
+                continue;
+            }
+            if drawn_rev_ids.contains(&node.base_rev_id) {
+                let _ = writeln!(dot, "  {} -> {};", node_id(node.base_rev_id, 0), id);
+            } else if rev_ids.contains(&node.base_rev_id) {
+                let _ = writeln!(dot, "  truncated -> {} [style=dotted];", id);
+            } else {
+                if !missing.contains(&node.base_rev_id) {
+                    missing.push(node.base_rev_id);
+                }
+                let _ = writeln!(
+                    dot,
+                    "  {} -> {} [style=dashed, color=red];",
+                    node_id(node.base_rev_id, 0),
+                    id
+                );
+            }
+        }
+
+        for rev_id in missing {
+            let _ = writeln!(
+                dot,
+                "  {} [label=\"{}\\nmissing\", style=dashed, color=red];",
+                node_id(rev_id, 0),
+                rev_id
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// The id of the node of the `index`th revision with the `rev_id`, the first one is `r{rev_id}`.
+fn node_id(rev_id: i64, index: usize) -> String {
+    if index == 0 {
+        format!("r{}", rev_id)
+    } else {
+        format!("r{}_{}", rev_id, index)
+    }
+}
+
+fn state_label(state: &RevisionState) -> &'static str {
+    match state {
+        RevisionState::Sync => "sync",
+        RevisionState::Ack => "ack",
+        RevisionState::Resolved => "resolved",
+    }
+}
+
+fn join(attributes: &[String]) -> String {
+    attributes.iter().map(|attribute| format!(", {}", attribute)).collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(rev_id: i64, base_rev_id: i64) -> RevGraphNode {
+        RevGraphNode {
+            rev_id,
+            base_rev_id,
+            state: RevisionState::Ack,
+            author: RevGraphAuthor::Local,
+            size: 10,
+            quarantined: false,
+        }
+    }
+
+    // Keeps the last `tail` of the nodes like `DeltaRevisionSql::read_rev_graph_nodes`.
+    fn graph(nodes: Vec<RevGraphNode>, tail: Option<usize>, snapshot_rev_id: Option<i64>) -> RevGraph {
+        let links = nodes.iter().map(|node| (node.rev_id, node.base_rev_id)).collect();
+        let skip = tail.map_or(0, |tail| nodes.len().saturating_sub(tail));
+        RevGraph {
+            doc_id: "doc".to_owned(),
+            links,
+            nodes: nodes[skip..].to_vec(),
+            snapshot_rev_id,
+        }
+    }
+
+    #[test]
+    fn rev_graph_duplicate_and_snapshot_test() {
+        let graph = graph(vec![node(1, 0), node(2, 1), node(2, 1), node(3, 2)], None, Some(3));
+        let dot = graph.to_dot();
+        assert!(dot.contains("  r2 [label=\"2\\nack local 10B\\nduplicate\", style=filled, fillcolor=orange];"));
+        assert!(dot.contains("  r2_1 [label=\"2\\nack local 10B\\nduplicate\", style=filled, fillcolor=orange];"));
+        assert!(dot.contains("  r1 -> r2_1;"));
+        assert!(dot.contains("  r3 [label=\"3\\nack local 10B\\nsnapshot\", peripheries=2];"));
+    }
+
+    #[test]
+    fn rev_graph_tail_test() {
+        let graph = graph(vec![node(1, 0), node(2, 1), node(3, 2), node(4, 3)], Some(2), None);
+        let dot = graph.to_dot();
+        assert!(dot.contains("  truncated [label=\"2 earlier revisions\", shape=plaintext];"));
+        assert!(dot.contains("  truncated -> r3 [style=dotted];"));
+        assert!(dot.contains("  r3 -> r4;"));
+        assert!(!dot.contains("r1"));
+        assert!(!dot.contains("missing"));
+    }
+
+    #[test]
+    fn rev_graph_fork_before_tail_test() {
+        // The revisions 2 and 3 are both based on 1, only 1 and 3 are drawn.
+        let graph = graph(vec![node(1, 0), node(2, 1), node(3, 1)], Some(1), None);
+        let dot = graph.to_dot();
+        assert!(dot.contains("  truncated [label=\"2 earlier revisions\", shape=plaintext];"));
+        assert!(dot.contains("  truncated -> r3 [style=dotted];"));
+        assert!(!dot.contains("missing"));
+    }
+}
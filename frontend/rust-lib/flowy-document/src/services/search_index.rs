@@ -0,0 +1,78 @@
+use crate::services::rev_sqlite::DELETE_REVS_CHUNK_SIZE;
+use flowy_database::{
+    prelude::*,
+    schema::{document_search_index, document_search_index::dsl},
+};
+use flowy_error::{FlowyError, FlowyResult};
+use lib_ot::text_delta::{plain_text, DeltaTextOperations};
+use std::collections::HashMap;
+
+/// The text of the document at the revision `rev_id` that is searched by
+/// `DocumentManager::search_documents`. The archived text is left out, see `without_archived`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchIndexEntry {
+    pub doc_id: String,
+    pub rev_id: i64,
+    pub text: String,
+}
+
+impl SearchIndexEntry {
+    pub fn new(doc_id: &str, rev_id: i64, operations: &DeltaTextOperations) -> Self {
+        Self {
+            doc_id: doc_id.to_owned(),
+            rev_id,
+            text: plain_text(operations, false),
+        }
+    }
+}
+
+/// Keeps the searched text of each closed document, so the documents don't need to be composed
+/// for each search. An entry is only composed again after its document changed.
+pub(crate) struct SearchIndexSql {}
+
+impl SearchIndexSql {
+    pub(crate) fn write(entry: &SearchIndexEntry, conn: &SqliteConnection) -> FlowyResult<()> {
+        let record = (
+            dsl::doc_id.eq(&entry.doc_id),
+            dsl::rev_id.eq(entry.rev_id),
+            dsl::text.eq(&entry.text),
+        );
+        let _ = replace_into(document_search_index::table)
+            .values(record)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn write_all(entries: &[SearchIndexEntry], conn: &SqliteConnection) -> FlowyResult<()> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            for entry in entries {
+                Self::write(entry, conn)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns the entries of the documents, keyed by their doc_ids. The documents that were never
+    /// indexed are left out.
+    pub(crate) fn read_all(
+        doc_ids: &[String],
+        conn: &SqliteConnection,
+    ) -> FlowyResult<HashMap<String, SearchIndexEntry>> {
+        let mut entries = HashMap::with_capacity(doc_ids.len());
+        for chunk in doc_ids.chunks(DELETE_REVS_CHUNK_SIZE) {
+            let rows = dsl::document_search_index
+                .filter(dsl::doc_id.eq_any(chunk))
+                .select((dsl::doc_id, dsl::rev_id, dsl::text))
+                .load::<(String, i64, String)>(conn)?;
+            for (doc_id, rev_id, text) in rows {
+                let entry = SearchIndexEntry {
+                    doc_id: doc_id.clone(),
+                    rev_id,
+                    text,
+                };
+                entries.insert(doc_id, entry);
+            }
+        }
+        Ok(entries)
+    }
+}
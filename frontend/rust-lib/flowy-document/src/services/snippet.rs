@@ -0,0 +1,118 @@
+use flowy_database::{
+    prelude::*,
+    schema::{document_snippet, document_snippet::dsl},
+};
+use flowy_error::{FlowyError, FlowyResult};
+use lib_infra::util::timestamp;
+use lib_ot::text_delta::DeltaTextOperations;
+use nanoid::nanoid;
+use std::convert::TryFrom;
+
+/// A named fragment of a delta document, e.g. the skeleton of a meeting agenda, that can be
+/// inserted into any document. Its operations are inserts that keep their attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub id: String,
+    pub name: String,
+    pub operations: DeltaTextOperations,
+    pub create_time: i64,
+}
+
+impl Snippet {
+    pub fn new(name: &str, operations: DeltaTextOperations) -> Self {
+        Self {
+            id: nanoid!(10),
+            name: name.to_owned(),
+            operations,
+            create_time: timestamp(),
+        }
+    }
+
+    /// Returns the plain text of the snippet for the picker. The lines are joined with spaces,
+    /// and the text is cut to `max_chars` characters followed by an ellipsis if it's longer.
+    pub fn preview(&self, max_chars: usize) -> String {
+        let content = self.operations.content().unwrap_or_default();
+        let text = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        if text.chars().count() <= max_chars {
+            return text;
+        }
+        let mut preview = text.chars().take(max_chars).collect::<String>();
+        preview.push('…');
+        preview
+    }
+}
+
+pub(crate) struct SnippetSql {}
+
+impl SnippetSql {
+    /// Saves the snippet, the snippet with the same id is replaced.
+    pub(crate) fn save(snippet: &Snippet, conn: &SqliteConnection) -> FlowyResult<()> {
+        let record = (
+            dsl::id.eq(&snippet.id),
+            dsl::name.eq(&snippet.name),
+            dsl::data.eq(snippet.operations.json_bytes().to_vec()),
+            dsl::create_time.eq(snippet.create_time),
+        );
+        let _ = replace_into(dsl::document_snippet).values(record).execute(conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn read(snippet_id: &str, conn: &SqliteConnection) -> FlowyResult<Snippet> {
+        let record = dsl::document_snippet
+            .filter(dsl::id.eq(snippet_id))
+            .first::<SnippetRecord>(conn)
+            .optional()?
+            .ok_or_else(|| FlowyError::record_not_found().context(format!("Can't find the snippet: {}", snippet_id)))?;
+        Snippet::try_from(record)
+    }
+
+    /// Returns all the snippets, the oldest first. The snippets created in the same second are
+    /// ordered by id, so the order is the same after importing them.
+    pub(crate) fn read_all(conn: &SqliteConnection) -> FlowyResult<Vec<Snippet>> {
+        dsl::document_snippet
+            .order((dsl::create_time.asc(), dsl::id.asc()))
+            .load::<SnippetRecord>(conn)?
+            .into_iter()
+            .map(Snippet::try_from)
+            .collect()
+    }
+
+    /// Saves the snippets in one transaction. Returns the number of the snippets.
+    pub(crate) fn import(snippets: &[Snippet], conn: &SqliteConnection) -> FlowyResult<usize> {
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            for snippet in snippets {
+                Self::save(snippet, conn)?;
+            }
+            Ok(())
+        })?;
+        Ok(snippets.len())
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Queryable, Identifiable, Insertable)]
+#[table_name = "document_snippet"]
+struct SnippetRecord {
+    id: String,
+    name: String,
+    data: Vec<u8>,
+    create_time: i64,
+}
+
+impl std::convert::TryFrom<SnippetRecord> for Snippet {
+    type Error = FlowyError;
+
+    fn try_from(record: SnippetRecord) -> Result<Self, Self::Error> {
+        let operations = DeltaTextOperations::from_bytes(&record.data)?;
+        Ok(Self {
+            id: record.id,
+            name: record.name,
+            operations,
+            create_time: record.create_time,
+        })
+    }
+}
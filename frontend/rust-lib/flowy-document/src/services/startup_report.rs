@@ -0,0 +1,221 @@
+use crate::entities::DocumentVersionPB;
+use flowy_database::prelude::*;
+use flowy_database::sql_types::{BigInt, Text};
+use flowy_error::FlowyResult;
+use std::time::{Duration, Instant};
+
+/// Describes what happened while the document module was initializing. It's assembled once
+/// per sign in, the phases that failed are recorded instead of aborting the initialization.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentStartupReport {
+    pub migrations: Vec<MigrationOutcome>,
+    pub num_of_documents: usize,
+    /// The number of the revisions that haven't been acked by the server yet.
+    pub num_of_unsynced_revisions: i64,
+    pub warnings: Vec<String>,
+    pub phases: Vec<StartupPhase>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationOutcome {
+    pub name: String,
+    /// False if the migration was completed in a previous launch.
+    pub ran: bool,
+    pub error: Option<String>,
+}
+
+impl MigrationOutcome {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            ran: false,
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StartupPhase {
+    pub name: String,
+    pub duration: Duration,
+    pub error: Option<String>,
+}
+
+impl DocumentStartupReport {
+    /// Runs the phase and records how long it took and its error if it failed.
+    pub(crate) fn run_phase<T, F>(&mut self, name: &str, f: F) -> FlowyResult<T>
+    where
+        F: FnOnce() -> FlowyResult<T>,
+    {
+        let start = Instant::now();
+        let result = f();
+        let error = result.as_ref().err().map(|e| {
+            tracing::error!("[Document Startup]: {} failed: {:?}", name, e);
+            format!("{:?}", e)
+        });
+        self.add_phase(name, start.elapsed(), error);
+        result
+    }
+
+    pub(crate) fn add_phase(&mut self, name: &str, duration: Duration, error: Option<String>) {
+        self.phases.push(StartupPhase {
+            name: name.to_owned(),
+            duration,
+            error,
+        });
+    }
+
+    pub fn total_duration(&self) -> Duration {
+        self.phases.iter().map(|phase| phase.duration).sum()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct DocumentScan {
+    pub(crate) num_of_documents: usize,
+    pub(crate) num_of_unsynced_revisions: i64,
+    pub(crate) warnings: Vec<String>,
+}
+
+#[derive(QueryableByName)]
+struct DocumentRevisionStats {
+    #[sql_type = "Text"]
+    document_id: String,
+    #[sql_type = "BigInt"]
+    num_of_unsynced: i64,
+    #[sql_type = "BigInt"]
+    num_of_duplicated: i64,
+    #[sql_type = "BigInt"]
+    num_of_empty: i64,
+}
+
+/// Counts the documents and their unsynced revisions, and warns about the documents whose
+/// revisions can't be composed as they are.
+pub(crate) fn scan_documents(version: &DocumentVersionPB, conn: &SqliteConnection) -> FlowyResult<DocumentScan> {
+    let (table, id_column) = match version {
+        DocumentVersionPB::V0 => ("rev_table", "doc_id"),
+        DocumentVersionPB::V1 => ("document_rev_table", "document_id"),
+    };
+    // The state of the unsynced revisions is 0 in both tables.
+    let sql = format!(
+        "SELECT {id} AS document_id, \
+         SUM(CASE WHEN state = 0 THEN 1 ELSE 0 END) AS num_of_unsynced, \
+         COUNT(*) - COUNT(DISTINCT rev_id) AS num_of_duplicated, \
+         SUM(CASE WHEN length(data) = 0 THEN 1 ELSE 0 END) AS num_of_empty \
+         FROM {table} GROUP BY {id} ORDER BY {id}",
+        id = id_column,
+        table = table
+    );
+    let rows = sql_query(sql).load::<DocumentRevisionStats>(conn)?;
+    let mut scan = DocumentScan {
+        num_of_documents: rows.len(),
+        ..Default::default()
+    };
+    for row in rows {
+        scan.num_of_unsynced_revisions += row.num_of_unsynced;
+        if row.num_of_duplicated > 0 {
+            scan.warnings.push(format!(
+                "{} has {} revisions with duplicated rev_id",
+                row.document_id, row.num_of_duplicated
+            ));
+        }
+        if row.num_of_empty > 0 {
+            scan.warnings
+                .push(format!("{} has {} empty revisions", row.document_id, row.num_of_empty));
+        }
+    }
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::DocumentVersionPB;
+    use crate::services::rev_sqlite::SQLiteDocumentRevisionPersistence;
+    use crate::services::{DocumentDatabaseMock, DocumentPersistence};
+    use crate::DocumentDatabase;
+    use bytes::Bytes;
+    use flowy_database::test_util::TempDatabase;
+    use flowy_database::ConnectionPool;
+    use flowy_error::FlowyError;
+    use flowy_http_model::revision::Revision;
+    use flowy_revision_persistence::{RevisionDiskCache, SyncRecord};
+    use std::sync::Arc;
+
+    fn record(document_id: &str, rev_id: i64, data: &'static str, is_acked: bool) -> SyncRecord {
+        let revision = Revision::new(document_id, rev_id - 1, rev_id, Bytes::from(data), "");
+        let mut record = SyncRecord::new(revision);
+        if is_acked {
+            record.ack();
+        }
+        record
+    }
+
+    #[test]
+    fn startup_report_test() {
+        let database = TempDatabase::new("startup_report");
+        let disk_cache = SQLiteDocumentRevisionPersistence::new("user", database.get_pool());
+        disk_cache
+            .create_revision_records(vec![
+                record("doc_1", 1, "1", true),
+                record("doc_1", 2, "2", true),
+                record("doc_1", 3, "3", true),
+                // doc_2 has two unsynced revisions, and its second revision was saved twice.
+                record("doc_2", 1, "1", true),
+                record("doc_2", 2, "2", false),
+                record("doc_2", 2, "2", false),
+                record("doc_2", 3, "3", false),
+                // doc_3 has an empty revision.
+                record("doc_3", 1, "", true),
+            ])
+            .unwrap();
+
+        let persistence = DocumentPersistence::new(Arc::new(DocumentDatabaseMock(database.get_pool())));
+        let (report, result) = persistence.initialize("user", &DocumentVersionPB::V1);
+        assert!(result.is_ok());
+        assert_eq!(report.num_of_documents, 3);
+        assert_eq!(report.num_of_unsynced_revisions, 3);
+        assert_eq!(
+            report.warnings,
+            vec![
+                "doc_2 has 1 revisions with duplicated rev_id".to_owned(),
+                "doc_3 has 1 empty revisions".to_owned(),
+            ]
+        );
+        assert!(report.migrations.iter().all(|outcome| outcome.error.is_none()));
+
+        let phases = report
+            .phases
+            .iter()
+            .map(|phase| phase.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(phases, vec!["v1_migration", "table_migrations", "scan_documents"]);
+        assert!(report.phases.iter().all(|phase| phase.error.is_none()));
+    }
+
+    struct UnavailableDatabaseMock();
+    impl DocumentDatabase for UnavailableDatabaseMock {
+        fn db_pool(&self) -> Result<Arc<ConnectionPool>, FlowyError> {
+            Err(FlowyError::internal().context("The database is unavailable"))
+        }
+    }
+
+    #[test]
+    fn startup_report_failed_phases_test() {
+        let persistence = DocumentPersistence::new(Arc::new(UnavailableDatabaseMock()));
+        let (report, result) = persistence.initialize("unavailable_user", &DocumentVersionPB::V1);
+        assert!(result.is_err());
+
+        // The phases after the failed one still run and record their own errors.
+        let failed_phases = report
+            .phases
+            .iter()
+            .filter(|phase| phase.error.is_some())
+            .map(|phase| phase.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            failed_phases,
+            vec!["v1_migration", "table_migrations", "scan_documents"]
+        );
+        assert_eq!(report.num_of_documents, 0);
+    }
+}
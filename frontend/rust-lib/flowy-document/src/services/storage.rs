@@ -0,0 +1,132 @@
+use flowy_database::{
+    dsl::sql,
+    sql_query,
+    sql_types::{BigInt, Text},
+    RunQueryDsl, SqliteConnection, DB_NAME,
+};
+use flowy_error::{FlowyError, FlowyResult};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoragePathKind {
+    Database,
+    /// The `-wal` file, it holds the latest changes until they're checkpointed into the database.
+    WriteAheadLog,
+    /// The `-shm` file, the index of the write-ahead log.
+    SharedMemory,
+    /// The `-journal` file of the rollback journal modes.
+    Journal,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoragePath {
+    pub kind: StoragePathKind,
+    pub path: PathBuf,
+    /// Whether copying the file as is while the app is running gives a usable copy.
+    pub safe_to_copy_while_running: bool,
+}
+
+/// Returns the files that hold the documents of the user. The files are listed even if they
+/// don't exist yet, SQLite creates the journal files on demand.
+///
+/// None of them is safe to copy while running. In WAL mode, the database file misses the
+/// changes that haven't been checkpointed yet and the `-wal` file keeps growing during the copy.
+/// In the rollback journal modes, the database file is written in place. Use
+/// `backup_database` to get a consistent copy instead.
+pub fn query_storage_paths(user_dir: &str, conn: &SqliteConnection) -> FlowyResult<Vec<StoragePath>> {
+    let db_path = Path::new(user_dir).join(DB_NAME);
+    let with_suffix = |suffix: &str| {
+        let mut path = db_path.clone().into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    };
+
+    let mut paths = vec![StoragePath {
+        kind: StoragePathKind::Database,
+        path: db_path.clone(),
+        safe_to_copy_while_running: false,
+    }];
+    if is_wal_mode(conn)? {
+        paths.push(StoragePath {
+            kind: StoragePathKind::WriteAheadLog,
+            path: with_suffix("-wal"),
+            safe_to_copy_while_running: false,
+        });
+        paths.push(StoragePath {
+            kind: StoragePathKind::SharedMemory,
+            path: with_suffix("-shm"),
+            safe_to_copy_while_running: false,
+        });
+    } else {
+        paths.push(StoragePath {
+            kind: StoragePathKind::Journal,
+            path: with_suffix("-journal"),
+            safe_to_copy_while_running: false,
+        });
+    }
+    Ok(paths)
+}
+
+/// Writes a consistent snapshot of the database into `dir`. The snapshot is read in one
+/// transaction, so the writes made meanwhile are either all in it or not at all. Returns the
+/// path of the copied database, it doesn't need the journal files to be opened.
+pub fn backup_database(conn: &SqliteConnection, dir: &str) -> FlowyResult<PathBuf> {
+    std::fs::create_dir_all(dir).map_err(|e| FlowyError::internal().context(e))?;
+    let path = Path::new(dir).join(DB_NAME);
+    if path.exists() {
+        return Err(FlowyError::invalid_data().context(format!("The backup: {:?} already exists", path)));
+    }
+
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| FlowyError::invalid_data().context(format!("Invalid backup path: {:?}", path)))?;
+    sql_query("VACUUM INTO ?").bind::<Text, _>(path_str).execute(conn)?;
+    Ok(path)
+}
+
+/// The pages of the database file. The rows deleted from the database leave their pages on the
+/// free list, the file only shrinks when it's vacuumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatabasePages {
+    pub page_size: u64,
+    pub page_count: u64,
+    pub freelist_count: u64,
+}
+
+impl DatabasePages {
+    pub fn file_bytes(&self) -> u64 {
+        self.page_size * self.page_count
+    }
+
+    pub fn used_bytes(&self) -> u64 {
+        self.page_size * self.page_count.saturating_sub(self.freelist_count)
+    }
+
+    pub fn free_bytes(&self) -> u64 {
+        self.page_size * self.freelist_count
+    }
+}
+
+pub fn read_database_pages(conn: &SqliteConnection) -> FlowyResult<DatabasePages> {
+    let pragma = |name: &str| -> FlowyResult<u64> {
+        let value = sql::<BigInt>(&format!("PRAGMA {}", name)).get_result::<i64>(conn)?;
+        Ok(value.max(0) as u64)
+    };
+    Ok(DatabasePages {
+        page_size: pragma("page_size")?,
+        page_count: pragma("page_count")?,
+        freelist_count: pragma("freelist_count")?,
+    })
+}
+
+/// Rebuilds the database file without its free pages. It fails if another connection is
+/// writing meanwhile.
+pub fn vacuum_database(conn: &SqliteConnection) -> FlowyResult<()> {
+    sql_query("VACUUM").execute(conn)?;
+    Ok(())
+}
+
+fn is_wal_mode(conn: &SqliteConnection) -> FlowyResult<bool> {
+    let journal_mode = sql::<Text>("PRAGMA journal_mode").get_result::<String>(conn)?;
+    Ok(journal_mode.eq_ignore_ascii_case("wal"))
+}
@@ -0,0 +1,64 @@
+use flowy_sync::client_document::{attribute_histogram, ClientDocument, NewlineDocument};
+use lib_ot::core::Interval;
+use lib_ot::text_delta::BuildInTextAttribute;
+use std::collections::HashMap;
+
+fn histogram(pairs: &[(&str, usize)]) -> HashMap<String, usize> {
+    pairs.iter().map(|(key, len)| (key.to_string(), *len)).collect()
+}
+
+#[test]
+fn attribute_histogram_plain_text_test() {
+    let mut document = ClientDocument::new::<NewlineDocument>();
+    document.insert(0, "hello world").unwrap();
+    assert!(attribute_histogram(document.get_operations()).is_empty());
+}
+
+#[test]
+fn attribute_histogram_bold_italic_test() {
+    let mut document = ClientDocument::new::<NewlineDocument>();
+    document.insert(0, "hello world").unwrap();
+    document
+        .format(Interval::new(0, 5), BuildInTextAttribute::Bold(true))
+        .unwrap();
+    document
+        .format(Interval::new(3, 8), BuildInTextAttribute::Italic(true))
+        .unwrap();
+    document
+        .format(Interval::new(9, 11), BuildInTextAttribute::Bold(true))
+        .unwrap();
+    assert_eq!(
+        attribute_histogram(document.get_operations()),
+        histogram(&[("bold", 7), ("italic", 5)])
+    );
+}
+
+#[test]
+fn attribute_histogram_removed_format_test() {
+    let mut document = ClientDocument::new::<NewlineDocument>();
+    document.insert(0, "hello world").unwrap();
+    document
+        .format(Interval::new(0, 11), BuildInTextAttribute::Bold(true))
+        .unwrap();
+    // Leaves "bold":false on the removed part.
+    document
+        .format(Interval::new(0, 6), BuildInTextAttribute::Bold(false))
+        .unwrap();
+    assert_eq!(
+        attribute_histogram(document.get_operations()),
+        histogram(&[("bold", 5)])
+    );
+}
+
+#[test]
+fn attribute_histogram_utf16_len_test() {
+    let mut document = ClientDocument::new::<NewlineDocument>();
+    document.insert(0, "👋 hi").unwrap();
+    document
+        .format(Interval::new(0, 2), BuildInTextAttribute::Italic(true))
+        .unwrap();
+    assert_eq!(
+        attribute_histogram(document.get_operations()),
+        histogram(&[("italic", 2)])
+    );
+}
@@ -4,6 +4,7 @@ use flowy_sync::client_document::{NewlineDocument, EmptyDocument};
 use lib_ot::core::{Interval, OperationTransform, NEW_LINE, WHITESPACE, OTString};
 use unicode_segmentation::UnicodeSegmentation;
 use lib_ot::text_delta::DeltaTextOperations;
+use lib_ot::codec::markdown::markdown_list::{decode_markdown_list, encode_markdown_list};
 
 #[test]
 fn attributes_bold_added() {
@@ -798,3 +799,74 @@ fn delta_compose() {
 
     TestBuilder::new().run_scripts::<NewlineDocument>(ops);
 }
+
+#[test]
+fn attributes_indent_nested_bullet_list() {
+    let ops = vec![
+        Insert(0, "a", 0),
+        Bullet(0, Interval::new(0, 1), true),
+        Insert(0, NEW_LINE, 1),
+        Insert(0, "b", 2),
+        Insert(0, NEW_LINE, 3),
+        Insert(0, "c", 4),
+        Indent(0, Interval::new(2, 5)),
+        Indent(0, Interval::new(4, 5)),
+        AssertDocJson(
+            0,
+            r#"[{"insert":"a"},{"insert":"\n","attributes":{"list":"bullet"}},{"insert":"b"},{"insert":"\n","attributes":{"list":"bullet","indent":1}},{"insert":"c"},{"insert":"\n","attributes":{"list":"bullet","indent":2}}]"#,
+        ),
+        AssertMarkdown(0, "* a\n  * b\n    * c\n"),
+    ];
+
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn attributes_outdent_mixed_depths() {
+    let ops = vec![
+        Insert(0, "a", 0),
+        Bullet(0, Interval::new(0, 1), true),
+        Insert(0, NEW_LINE, 1),
+        Insert(0, "b", 2),
+        Insert(0, NEW_LINE, 3),
+        Insert(0, "c", 4),
+        Indent(0, Interval::new(2, 5)),
+        Indent(0, Interval::new(4, 5)),
+        Outdent(0, Interval::new(0, 5)),
+        AssertDocJson(
+            0,
+            r#"[{"insert":"a"},{"insert":"\n","attributes":{"list":"bullet"}},{"insert":"b"},{"insert":"\n","attributes":{"list":"bullet"}},{"insert":"c"},{"insert":"\n","attributes":{"list":"bullet","indent":1}}]"#,
+        ),
+        AssertMarkdown(0, "* a\n* b\n  * c\n"),
+    ];
+
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn attributes_indent_skip_non_list_line() {
+    let ops = vec![
+        Insert(0, "a", 0),
+        Insert(0, NEW_LINE, 1),
+        Insert(0, "b", 2),
+        Bullet(0, Interval::new(2, 3), true),
+        Indent(0, Interval::new(0, 3)),
+        AssertDocJson(
+            0,
+            r#"[{"insert":"a\nb"},{"insert":"\n","attributes":{"list":"bullet","indent":1}}]"#,
+        ),
+    ];
+
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn attributes_nested_ordered_list_markdown_round_trip() {
+    let markdown = "1. a\n   1. b\n      1. c\n";
+    let operations = decode_markdown_list(markdown);
+    assert_eq!(
+        operations.json_str(),
+        r#"[{"insert":"a"},{"insert":"\n","attributes":{"list":"ordered"}},{"insert":"b"},{"insert":"\n","attributes":{"list":"ordered","indent":1}},{"insert":"c"},{"insert":"\n","attributes":{"list":"ordered","indent":2}}]"#
+    );
+    assert_eq!(encode_markdown_list(&operations), markdown);
+}
@@ -1,11 +1,15 @@
 #![allow(clippy::module_inception)]
+mod attribute_histogram_test;
 mod attribute_test;
 mod op_test;
+mod redline_test;
+mod selection_test;
 mod serde_test;
 mod undo_redo_test;
 
 use derive_more::Display;
 use flowy_sync::client_document::{ClientDocument, InitialDocument};
+use lib_ot::codec::markdown::markdown_list::{decode_markdown_list, encode_markdown_list};
 use lib_ot::{
     core::*,
     text_delta::{BuildInTextAttribute, DeltaTextOperations},
@@ -32,6 +36,10 @@ pub enum TestOp {
     #[display(fmt = "Replace")]
     Replace(usize, Interval, &'static str),
 
+    // delta_i, len
+    #[display(fmt = "TrimLeading")]
+    TrimLeading(usize, usize),
+
     #[display(fmt = "Italic")]
     Italic(usize, Interval, bool),
 
@@ -44,6 +52,12 @@ pub enum TestOp {
     #[display(fmt = "Bullet")]
     Bullet(usize, Interval, bool),
 
+    #[display(fmt = "Indent")]
+    Indent(usize, Interval),
+
+    #[display(fmt = "Outdent")]
+    Outdent(usize, Interval),
+
     #[display(fmt = "Transform")]
     Transform(usize, usize),
 
@@ -60,6 +74,12 @@ pub enum TestOp {
     #[display(fmt = "Redo")]
     Redo(usize),
 
+    #[display(fmt = "BeginUndoGroup")]
+    BeginUndoGroup(usize),
+
+    #[display(fmt = "EndUndoGroup")]
+    EndUndoGroup(usize),
+
     #[display(fmt = "Wait")]
     Wait(usize),
 
@@ -69,6 +89,10 @@ pub enum TestOp {
     #[display(fmt = "AssertDocJson")]
     AssertDocJson(usize, &'static str),
 
+    // Exports the document to markdown and imports it back
+    #[display(fmt = "AssertMarkdown")]
+    AssertMarkdown(usize, &'static str),
+
     #[display(fmt = "AssertPrimeJson")]
     AssertPrimeJson(usize, &'static str),
 
@@ -117,6 +141,12 @@ impl TestBuilder {
                 tracing::trace!("Delete delta: {}", delta.json_str());
                 self.deltas.insert(*delta_i, Some(delta));
             }
+            TestOp::TrimLeading(delta_i, len) => {
+                let document = &mut self.documents[*delta_i];
+                let delta = document.trim_leading(*len).unwrap();
+                tracing::trace!("Trim leading delta: {}", delta.json_str());
+                self.deltas.insert(*delta_i, Some(delta));
+            }
             TestOp::Replace(delta_i, iv, s) => {
                 let document = &mut self.documents[*delta_i];
                 let delta = document.replace(*iv, s).unwrap();
@@ -167,6 +197,18 @@ impl TestBuilder {
 
                 self.deltas.insert(*delta_i, Some(delta));
             }
+            TestOp::Indent(delta_i, iv) => {
+                let document = &mut self.documents[*delta_i];
+                let delta = document.indent(*iv).unwrap();
+                tracing::debug!("Indent delta: {}", delta.json_str());
+                self.deltas.insert(*delta_i, Some(delta));
+            }
+            TestOp::Outdent(delta_i, iv) => {
+                let document = &mut self.documents[*delta_i];
+                let delta = document.outdent(*iv).unwrap();
+                tracing::debug!("Outdent delta: {}", delta.json_str());
+                self.deltas.insert(*delta_i, Some(delta));
+            }
             TestOp::Transform(delta_a_i, delta_b_i) => {
                 let (a_prime, b_prime) = self.documents[*delta_a_i]
                     .get_operations()
@@ -217,6 +259,12 @@ impl TestBuilder {
             TestOp::Redo(delta_i) => {
                 self.documents[*delta_i].redo().unwrap();
             }
+            TestOp::BeginUndoGroup(delta_i) => {
+                self.documents[*delta_i].begin_undo_group();
+            }
+            TestOp::EndUndoGroup(delta_i) => {
+                self.documents[*delta_i].end_undo_group();
+            }
             TestOp::Wait(mills_sec) => {
                 std::thread::sleep(Duration::from_millis(*mills_sec as u64));
             }
@@ -236,6 +284,13 @@ impl TestBuilder {
                 assert_eq!(target_delta, expected_delta);
             }
 
+            TestOp::AssertMarkdown(delta_i, expected) => {
+                let operations = self.documents[*delta_i].get_operations();
+                let markdown = encode_markdown_list(operations);
+                assert_eq!(&markdown, expected);
+                assert_eq!(&decode_markdown_list(&markdown), operations);
+            }
+
             TestOp::AssertPrimeJson(doc_i, expected) => {
                 let prime_json = self.primes[*doc_i].as_ref().unwrap().json_str();
                 let expected_prime: DeltaTextOperations = serde_json::from_str(expected).unwrap();
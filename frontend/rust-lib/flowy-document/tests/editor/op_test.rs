@@ -1,7 +1,9 @@
 #![allow(clippy::all)]
 use crate::editor::{Rng, TestBuilder, TestOp::*};
+use flowy_http_model::revision::Revision;
 use flowy_sync::client_document::{EmptyDocument, NewlineDocument};
-use lib_ot::text_delta::DeltaTextOperationBuilder;
+use flowy_sync::util::make_operations_from_revisions;
+use lib_ot::text_delta::{detect_script, DeltaTextOperationBuilder, Script, SCRIPT_SAMPLE_LEN};
 use lib_ot::{core::Interval, core::*, text_delta::DeltaTextOperations};
 
 #[test]
@@ -577,6 +579,53 @@ fn transform_two_conflict_non_seq_delta() {
     TestBuilder::new().run_scripts::<EmptyDocument>(ops);
 }
 
+#[test]
+fn transform_with_trace_concurrent_insert_test() {
+    let left = DeltaTextOperationBuilder::new().retain(1).insert("x").retain(2).build();
+    let right = DeltaTextOperationBuilder::new().retain(1).insert("y").retain(2).build();
+    let (left_prime, right_prime, steps) = left.transform_with_trace(&right).unwrap();
+    assert_eq!(left_prime.json_str(), r#"[{"retain":1},{"insert":"x"},{"retain":3}]"#);
+    assert_eq!(right_prime.json_str(), r#"[{"retain":2},{"insert":"y"},{"retain":2}]"#);
+
+    // The insert of the left delta goes first when both deltas insert at the same index.
+    let steps = steps.iter().map(|step| step.to_string()).collect::<Vec<String>>();
+    assert_eq!(
+        steps,
+        vec![
+            "{retain: 1} | {retain: 1} => RetainBoth 1 (advance Both)",
+            "{insert: x} | {insert: y} => InsertLeft 1 (advance Left)",
+            "{retain: 2} | {insert: y} => InsertRight 1 (advance Right)",
+            "{retain: 2} | {retain: 2} => RetainBoth 2 (advance Both)",
+        ]
+    );
+
+    // Swapping the deltas swaps the order of the inserts.
+    let (_, _, steps) = right.transform_with_trace(&left).unwrap();
+    assert_eq!(steps[1].action, TransformAction::InsertLeft);
+    assert_eq!(steps[1].left.as_deref(), Some("{insert: y}"));
+    assert_eq!(left.transform(&right).unwrap(), (left_prime, right_prime));
+}
+
+#[test]
+fn transform_with_trace_split_delete_test() {
+    let left = DeltaTextOperationBuilder::new().delete(3).build();
+    let right = DeltaTextOperationBuilder::new().retain(1).delete(2).build();
+    let (left_prime, right_prime, steps) = left.transform_with_trace(&right).unwrap();
+    assert_eq!(left_prime.json_str(), r#"[{"delete":1}]"#);
+    assert!(right_prime.is_empty());
+    assert_eq!(
+        steps
+            .iter()
+            .map(|step| (step.action, step.len, step.advance))
+            .collect::<Vec<_>>(),
+        vec![
+            (TransformAction::DeleteLeft, 1, TransformAdvance::Right),
+            (TransformAction::DeleteBoth, 2, TransformAdvance::Both),
+        ]
+    );
+    assert_eq!(steps[1].left.as_deref(), Some("{delete: 2}"));
+}
+
 #[test]
 fn delta_invert_no_attribute_delta() {
     let mut delta = DeltaTextOperations::default();
@@ -593,6 +642,67 @@ fn delta_invert_no_attribute_delta() {
     assert_eq!(delta_after_undo, delta);
 }
 
+#[test]
+fn delta_redacted_test() {
+    let attributes = AttributeBuilder::new().insert("bold", true).build();
+    let delta = DeltaTextOperationBuilder::new()
+        .insert("hello ")
+        .insert_with_attributes("世界😀", attributes.clone())
+        .insert("\n")
+        .build();
+    let redacted = delta.redacted();
+
+    assert_eq!(redacted.ops.len(), delta.ops.len());
+    assert_eq!(redacted.utf16_base_len, delta.utf16_base_len);
+    assert_eq!(redacted.utf16_target_len, delta.utf16_target_len);
+    for (redacted_op, op) in redacted.ops.iter().zip(delta.ops.iter()) {
+        assert_eq!(redacted_op.len(), op.len());
+        assert_eq!(redacted_op.get_attributes(), op.get_attributes());
+    }
+
+    let content = redacted.content().unwrap();
+    assert!(!content.contains("hello"));
+    assert!(!content.contains("世界"));
+    assert_eq!(redacted.ops[1].get_attributes(), attributes);
+}
+
+#[test]
+fn delta_detect_latin_script() {
+    let delta = DeltaTextOperationBuilder::new()
+        .insert("Hello, ")
+        .insert_with_attributes("AppFlowy", AttributeBuilder::new().insert("bold", true).build())
+        .insert("!\n")
+        .build();
+    assert_eq!(detect_script(&delta), Script::Latin);
+}
+
+#[test]
+fn delta_detect_cjk_script() {
+    let delta = DeltaTextOperationBuilder::new().insert("你好，世界。こんにちは\n").build();
+    assert_eq!(detect_script(&delta), Script::Cjk);
+}
+
+#[test]
+fn delta_detect_mixed_script() {
+    let delta = DeltaTextOperationBuilder::new()
+        .insert("AppFlowy 是一个开源的笔记应用\n")
+        .build();
+    assert_eq!(detect_script(&delta), Script::Cjk);
+
+    let delta = DeltaTextOperationBuilder::new().insert("Say 你好 to AppFlowy\n").build();
+    assert_eq!(detect_script(&delta), Script::Latin);
+
+    let delta = DeltaTextOperationBuilder::new().insert("123 ...\n").build();
+    assert_eq!(detect_script(&delta), Script::Unknown);
+}
+
+#[test]
+fn delta_detect_script_with_bounded_prefix() {
+    let text = format!("{}{}", "a".repeat(SCRIPT_SAMPLE_LEN), "世界".repeat(SCRIPT_SAMPLE_LEN));
+    let delta = DeltaTextOperationBuilder::new().insert(&text).build();
+    assert_eq!(detect_script(&delta), Script::Latin);
+}
+
 #[test]
 fn delta_invert_no_attribute_delta2() {
     let ops = vec![
@@ -747,3 +857,197 @@ fn delta_compose_with_missing_delta() {
     ];
     TestBuilder::new().run_scripts::<NewlineDocument>(ops);
 }
+
+#[test]
+fn delta_delete_prefix_test() {
+    let delta = DeltaTextOperations::delete_prefix(2, 5);
+    assert_eq!(delta.json_str(), r#"[{"delete":2},{"retain":3}]"#);
+
+    let document = DeltaTextOperationBuilder::new().insert("12345").build();
+    assert_eq!(document.compose(&delta).unwrap().json_str(), r#"[{"insert":"345"}]"#);
+}
+
+#[test]
+fn delta_delete_prefix_exceeding_len_test() {
+    let delta = DeltaTextOperations::delete_prefix(10, 5);
+    assert_eq!(delta.json_str(), r#"[{"delete":5}]"#);
+
+    let document = DeltaTextOperationBuilder::new().insert("12345").build();
+    assert!(document.compose(&delta).unwrap().is_empty());
+}
+
+#[test]
+fn delta_trim_leading_test() {
+    let ops = vec![
+        Insert(0, "123", 0),
+        Insert(0, "456", 3),
+        TrimLeading(0, 4),
+        AssertDocJson(0, r#"[{"insert":"56\n"}]"#),
+    ];
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn delta_trim_leading_formatted_text_test() {
+    let ops = vec![
+        InsertBold(0, "123", Interval::new(0, 3)),
+        TrimLeading(0, 2),
+        AssertDocJson(0, r#"[{"insert":"3","attributes":{"bold":true}},{"insert":"\n"}]"#),
+    ];
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn delta_trim_leading_exceeding_len_test() {
+    let ops = vec![
+        Insert(0, "123", 0),
+        TrimLeading(0, 100),
+        AssertDocJson(0, r#"[{"insert":"\n"}]"#),
+    ];
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn delta_trim_leading_mid_surrogate_pair_test() {
+    // The emoji takes two utf16 code units, trimming one of them keeps the emoji.
+    let ops = vec![
+        Insert(0, "😀1", 0),
+        TrimLeading(0, 1),
+        AssertDocJson(0, r#"[{"insert":"😀1\n"}]"#),
+        TrimLeading(0, 3),
+        AssertDocJson(0, r#"[{"insert":"\n"}]"#),
+    ];
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn delta_normalize_mixed_line_endings_test() {
+    let delta = DeltaTextOperationBuilder::new()
+        .insert("line1\r\nline2\rline3\nline4\r\r\n")
+        .build();
+    let normalized = delta.normalize_line_endings();
+    assert_eq!(normalized.content().unwrap(), "line1\nline2\nline3\nline4\n\n");
+    assert_eq!(normalized.utf16_target_len, 25);
+    assert!(!normalized.content().unwrap().contains('\r'));
+}
+
+#[test]
+fn delta_normalize_line_endings_keeps_attributes_test() {
+    let bold = AttributeBuilder::new().insert("bold", true).build();
+    let header = AttributeBuilder::new().insert("header", 1).build();
+    // The "\r\n" between the plain text and the header is split between two operations.
+    let delta = DeltaTextOperationBuilder::new()
+        .insert_with_attributes("bold\r\n", bold)
+        .insert("plain\r")
+        .insert_with_attributes("\nheader", header)
+        .build();
+    let normalized = delta.normalize_line_endings();
+    assert_eq!(
+        normalized.json_str(),
+        r#"[{"insert":"bold\n","attributes":{"bold":true}},{"insert":"plain"},{"insert":"\nheader","attributes":{"header":1}}]"#
+    );
+    assert_eq!(normalized.utf16_target_len, 17);
+}
+
+#[test]
+fn delta_normalize_line_endings_compose_test() {
+    let delta = DeltaTextOperationBuilder::new()
+        .insert("123\r\n456\r")
+        .build()
+        .normalize_line_endings();
+    let change = DeltaTextOperationBuilder::new()
+        .retain(delta.utf16_target_len)
+        .insert("\r\n789\r\n")
+        .build()
+        .normalize_line_endings();
+    assert_eq!(change.utf16_base_len, 8);
+
+    let composed = delta.compose(&change).unwrap();
+    assert_eq!(composed.content().unwrap(), "123\n456\n\n789\n");
+}
+
+#[test]
+fn delta_assert_eq_ignores_attribute_order_test() {
+    let left = DeltaTextOperations::from_json(
+        r#"[{"insert":"12"},{"insert":"3","attributes":{"bold":true,"italic":true}},{"insert":"\n"}]"#,
+    )
+    .unwrap();
+    let right = DeltaTextOperations::from_json(
+        r#"[{"insert":"1"},{"insert":"2"},{"insert":"3","attributes":{"italic":true,"bold":true}},{"insert":"\n"}]"#,
+    )
+    .unwrap();
+    assert_ne!(left.json_str(), right.json_str());
+    assert!(left.assert_eq(&right).is_ok());
+}
+
+#[test]
+fn delta_assert_eq_diff_test() {
+    let bold = AttributeBuilder::new().insert("bold", true).build();
+    let italic = AttributeBuilder::new().insert("italic", true).build();
+    let left = DeltaTextOperationBuilder::new()
+        .insert("123")
+        .insert_with_attributes("456", bold.clone())
+        .insert("789")
+        .insert_with_attributes("abc", bold.clone())
+        .build();
+    let right = DeltaTextOperationBuilder::new()
+        .insert("123")
+        .insert_with_attributes("456", bold.clone())
+        .insert("789")
+        .insert_with_attributes("abc", italic.clone())
+        .build();
+
+    let diff = left.assert_eq(&right).unwrap_err();
+    assert_eq!(diff.index, 3);
+    assert_eq!(diff.offset, 9);
+    assert_eq!(diff.left, Some(DeltaOperation::insert_with_attributes("abc", bold)));
+    assert_eq!(diff.right, Some(DeltaOperation::insert_with_attributes("abc", italic)));
+
+    // The extra operation of the longer delta is the difference.
+    let mut longer = left.clone();
+    longer.insert("\n", AttributeHashMap::default());
+    let diff = left.assert_eq(&longer).unwrap_err();
+    assert_eq!(diff.index, 4);
+    assert_eq!(diff.offset, 12);
+    assert_eq!(diff.left, None);
+    assert_eq!(diff.right, Some(DeltaOperation::insert("\n")));
+}
+
+fn make_revisions(operations: Vec<DeltaTextOperations>) -> Vec<Revision> {
+    operations
+        .into_iter()
+        .enumerate()
+        .map(|(index, operations)| Revision::new("1", index as i64, index as i64 + 1, operations.json_bytes(), ""))
+        .collect()
+}
+
+#[test]
+fn delta_normalized_snapshot_test() {
+    // The appended text with an unset attribute isn't merged with the text around it,
+    // although it looks the same.
+    let unset_bold = AttributeBuilder::new().delete("bold").build();
+    let mut operations = vec![
+        DeltaTextOperationBuilder::new().insert("abc\n").build(),
+        DeltaTextOperationBuilder::new()
+            .retain(4)
+            .insert_with_attributes("def", unset_bold)
+            .build(),
+        DeltaTextOperationBuilder::new().retain(7).insert("\n").build(),
+    ];
+    let naive = make_operations_from_revisions::<AttributeHashMap>(make_revisions(operations.clone())).unwrap();
+    let normalized = naive.normalized();
+    assert_eq!(naive.ops.len(), 3);
+    assert_eq!(normalized.ops.len(), 1);
+    assert_eq!(normalized.json_str(), r#"[{"insert":"abc\ndef\n"}]"#);
+    assert!(naive.assert_eq(&normalized).is_ok());
+    assert_eq!(naive.content().unwrap(), normalized.content().unwrap());
+
+    // The revision that retains past the end of the document leaves a retain at the end.
+    operations.push(DeltaTextOperationBuilder::new().retain(9).build());
+    let naive = make_operations_from_revisions::<AttributeHashMap>(make_revisions(operations)).unwrap();
+    let normalized = naive.normalized();
+    assert_eq!(naive.ops.len(), 2);
+    assert!(naive.ops.last().unwrap().is_retain());
+    assert_eq!(normalized.ops, vec![DeltaOperation::insert("abc\ndef\n")]);
+    assert_eq!(naive.content().unwrap(), normalized.content().unwrap());
+}
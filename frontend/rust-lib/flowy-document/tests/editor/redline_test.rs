@@ -0,0 +1,117 @@
+use flowy_sync::client_document::{make_attribute_diff, make_redline};
+use lib_ot::core::{AttributeBuilder, OperationTransform};
+use lib_ot::text_delta::{DeltaTextOperationBuilder, DeltaTextOperations};
+
+fn assert_redline(from: DeltaTextOperations, to: DeltaTextOperations, expected: &str) {
+    let redline = make_redline(&from, &to).unwrap();
+    assert_eq!(redline.json_str(), expected);
+}
+
+fn text(s: &str) -> DeltaTextOperations {
+    DeltaTextOperationBuilder::new().insert(s).build()
+}
+
+#[test]
+fn redline_unchanged_test() {
+    assert_redline(text("abc\n"), text("abc\n"), r#"[{"insert":"abc\n"}]"#);
+}
+
+#[test]
+fn redline_insert_test() {
+    assert_redline(
+        text("abc\n"),
+        text("abXc\n"),
+        r#"[{"insert":"ab"},{"insert":"X","attributes":{"insertion":true}},{"insert":"c\n"}]"#,
+    );
+}
+
+#[test]
+fn redline_delete_test() {
+    assert_redline(
+        text("abc\n"),
+        text("ac\n"),
+        r#"[{"insert":"a"},{"insert":"b","attributes":{"deletion":true}},{"insert":"c\n"}]"#,
+    );
+}
+
+#[test]
+fn redline_replace_test() {
+    assert_redline(
+        text("abc\n"),
+        text("aXc\n"),
+        r#"[{"insert":"a"},{"insert":"b","attributes":{"deletion":true}},{"insert":"X","attributes":{"insertion":true}},{"insert":"c\n"}]"#,
+    );
+}
+
+#[test]
+fn redline_format_changed_test() {
+    let to = DeltaTextOperationBuilder::new()
+        .insert_with_attributes("abc", AttributeBuilder::new().insert("bold", true).build())
+        .insert("\n")
+        .build();
+    assert_redline(
+        text("abc\n"),
+        to,
+        r#"[{"insert":"abc","attributes":{"bold":true,"format_changed":true}},{"insert":"\n"}]"#,
+    );
+}
+
+#[test]
+fn redline_delete_formatted_text_test() {
+    let from = DeltaTextOperationBuilder::new()
+        .insert("a")
+        .insert_with_attributes("b", AttributeBuilder::new().insert("bold", true).build())
+        .insert("c\n")
+        .build();
+    assert_redline(
+        from,
+        text("ac\n"),
+        r#"[{"insert":"a"},{"insert":"b","attributes":{"bold":true,"deletion":true}},{"insert":"c\n"}]"#,
+    );
+}
+
+fn bold() -> lib_ot::core::AttributeHashMap {
+    AttributeBuilder::new().insert("bold", true).build()
+}
+
+#[test]
+fn attribute_diff_bold_only_revision_test() {
+    let from = text("abcd\n");
+    // The revision only bolds "bc", the text doesn't change.
+    let revision = DeltaTextOperationBuilder::new()
+        .retain(1)
+        .retain_with_attributes(2, bold())
+        .build();
+    let to = from.compose(&revision).unwrap();
+    let diff = make_attribute_diff(&from, &to).unwrap();
+    assert_eq!(
+        diff.json_str(),
+        r#"[{"retain":1},{"retain":2,"attributes":{"bold":true}}]"#
+    );
+    assert_eq!(from.compose(&diff).unwrap(), to);
+}
+
+#[test]
+fn attribute_diff_skips_text_edits_test() {
+    let from = DeltaTextOperationBuilder::new()
+        .insert("ab")
+        .insert_with_attributes("cd", bold())
+        .insert("ef\n")
+        .build();
+    // "X" is inserted, "b" is deleted and "cd" is unbolded.
+    let to = DeltaTextOperationBuilder::new().insert("aXcdef\n").build();
+    let diff = make_attribute_diff(&from, &to).unwrap();
+    assert_eq!(
+        diff.json_str(),
+        r#"[{"retain":2},{"retain":2,"attributes":{"bold":null}}]"#
+    );
+    // The diff keeps the text of the older version.
+    let formatted = from.compose(&diff).unwrap();
+    assert_eq!(formatted.content().unwrap(), "abcdef\n");
+}
+
+#[test]
+fn attribute_diff_unchanged_test() {
+    let diff = make_attribute_diff(&text("abc\n"), &text("abXc\n")).unwrap();
+    assert!(diff.is_empty());
+}
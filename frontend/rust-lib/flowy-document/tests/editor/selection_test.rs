@@ -0,0 +1,146 @@
+use flowy_sync::client_document::transform_selection;
+use lib_ot::core::{AttributeBuilder, Interval};
+use lib_ot::text_delta::{DeltaTextOperationBuilder, DeltaTextOperations};
+
+// The document is "0123456789\n" and the selection is 3..6 unless stated otherwise.
+
+fn insert_at(index: usize, s: &str) -> DeltaTextOperations {
+    DeltaTextOperationBuilder::new()
+        .retain(index)
+        .insert(s)
+        .retain(11 - index)
+        .build()
+}
+
+fn delete_at(index: usize, len: usize) -> DeltaTextOperations {
+    DeltaTextOperationBuilder::new()
+        .retain(index)
+        .delete(len)
+        .retain(11 - index - len)
+        .build()
+}
+
+fn assert_selection(selection: &[(usize, usize)], operations: DeltaTextOperations, expected: &[(usize, usize)]) {
+    let selection = selection
+        .iter()
+        .map(|(start, end)| Interval::new(*start, *end))
+        .collect::<Vec<Interval>>();
+    let transformed = transform_selection(&selection, &operations)
+        .into_iter()
+        .map(|range| (range.start, range.end))
+        .collect::<Vec<(usize, usize)>>();
+    assert_eq!(transformed, expected);
+}
+
+#[test]
+fn selection_insert_before_test() {
+    assert_selection(&[(3, 6)], insert_at(1, "ab"), &[(5, 8)]);
+}
+
+#[test]
+fn selection_insert_at_start_test() {
+    assert_selection(&[(3, 6)], insert_at(3, "ab"), &[(5, 8)]);
+}
+
+#[test]
+fn selection_insert_inside_test() {
+    assert_selection(&[(3, 6)], insert_at(4, "ab"), &[(3, 8)]);
+}
+
+#[test]
+fn selection_insert_at_end_test() {
+    assert_selection(&[(3, 6)], insert_at(6, "ab"), &[(3, 6)]);
+}
+
+#[test]
+fn selection_insert_after_test() {
+    assert_selection(&[(3, 6)], insert_at(8, "ab"), &[(3, 6)]);
+}
+
+#[test]
+fn cursor_insert_at_cursor_test() {
+    assert_selection(&[(3, 3)], insert_at(3, "ab"), &[(5, 5)]);
+}
+
+#[test]
+fn cursor_insert_utf16_test() {
+    // The emoji is two utf16 code units.
+    assert_selection(&[(3, 3)], insert_at(0, "😀"), &[(5, 5)]);
+}
+
+#[test]
+fn selection_delete_before_test() {
+    assert_selection(&[(3, 6)], delete_at(0, 2), &[(1, 4)]);
+}
+
+#[test]
+fn selection_delete_ending_at_start_test() {
+    assert_selection(&[(3, 6)], delete_at(1, 2), &[(1, 4)]);
+}
+
+#[test]
+fn selection_delete_overlapping_start_test() {
+    assert_selection(&[(3, 6)], delete_at(2, 2), &[(2, 4)]);
+}
+
+#[test]
+fn selection_delete_inside_test() {
+    assert_selection(&[(3, 6)], delete_at(4, 1), &[(3, 5)]);
+}
+
+#[test]
+fn selection_delete_exact_range_test() {
+    assert_selection(&[(3, 6)], delete_at(3, 3), &[(3, 3)]);
+}
+
+#[test]
+fn selection_delete_spanning_range_test() {
+    assert_selection(&[(3, 6)], delete_at(1, 7), &[(1, 1)]);
+}
+
+#[test]
+fn selection_delete_overlapping_end_test() {
+    assert_selection(&[(3, 6)], delete_at(5, 3), &[(3, 5)]);
+}
+
+#[test]
+fn selection_delete_starting_at_end_test() {
+    assert_selection(&[(3, 6)], delete_at(6, 2), &[(3, 6)]);
+}
+
+#[test]
+fn selection_delete_after_test() {
+    assert_selection(&[(3, 6)], delete_at(8, 2), &[(3, 6)]);
+}
+
+#[test]
+fn cursor_delete_spanning_cursor_test() {
+    assert_selection(&[(5, 5)], delete_at(3, 4), &[(3, 3)]);
+}
+
+#[test]
+fn selection_format_test() {
+    let operations = DeltaTextOperationBuilder::new()
+        .retain(2)
+        .retain_with_attributes(5, AttributeBuilder::new().insert("bold", true).build())
+        .retain(4)
+        .build();
+    assert_selection(&[(3, 6)], operations, &[(3, 6)]);
+}
+
+#[test]
+fn multi_range_selection_test() {
+    // Deletes "12" and inserts "ab" between "7" and "8".
+    let operations = DeltaTextOperationBuilder::new()
+        .retain(1)
+        .delete(2)
+        .retain(5)
+        .insert("ab")
+        .retain(3)
+        .build();
+    assert_selection(
+        &[(0, 1), (2, 4), (5, 5), (8, 10)],
+        operations,
+        &[(0, 1), (1, 2), (3, 3), (8, 10)],
+    );
+}
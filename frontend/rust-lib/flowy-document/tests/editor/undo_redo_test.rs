@@ -1,6 +1,7 @@
 use crate::editor::{TestBuilder, TestOp::*};
-use flowy_sync::client_document::{EmptyDocument, NewlineDocument, RECORD_THRESHOLD};
+use flowy_sync::client_document::{ClientDocument, EmptyDocument, NewlineDocument, RECORD_THRESHOLD};
 use lib_ot::core::{Interval, NEW_LINE, WHITESPACE};
+use lib_ot::text_delta::DeltaTextOperationBuilder;
 
 #[test]
 fn history_insert_undo() {
@@ -371,3 +372,102 @@ fn history_undo_attribute_on_merge_between_line() {
 
     TestBuilder::new().run_scripts::<NewlineDocument>(ops);
 }
+
+#[test]
+fn history_undo_group_split_test() {
+    // Splits the line into two lines in two steps: removes the text from the source line and
+    // inserts it as a new line.
+    let ops = vec![
+        Insert(0, "123456", 0),
+        Wait(RECORD_THRESHOLD),
+        BeginUndoGroup(0),
+        Delete(0, Interval::new(3, 6)),
+        Wait(RECORD_THRESHOLD),
+        Insert(0, "\n456", 3),
+        EndUndoGroup(0),
+        AssertDocJson(0, r#"[{"insert":"123\n456\n"}]"#),
+        Undo(0),
+        AssertDocJson(0, r#"[{"insert":"123456\n"}]"#),
+        Redo(0),
+        AssertDocJson(0, r#"[{"insert":"123\n456\n"}]"#),
+        Undo(0),
+        Undo(0),
+        AssertDocJson(0, r#"[{"insert":"\n"}]"#),
+    ];
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn history_undo_after_group_test() {
+    let ops = vec![
+        BeginUndoGroup(0),
+        Insert(0, "123", 0),
+        Insert(0, "456", 3),
+        EndUndoGroup(0),
+        // Typed right after the group, but it's not part of the group.
+        Insert(0, "7", 6),
+        Undo(0),
+        AssertDocJson(0, r#"[{"insert":"123456\n"}]"#),
+        Undo(0),
+        AssertDocJson(0, r#"[{"insert":"\n"}]"#),
+    ];
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn history_undo_in_middle_of_group_test() {
+    let ops = vec![
+        Insert(0, "123", 0),
+        Wait(RECORD_THRESHOLD),
+        BeginUndoGroup(0),
+        Insert(0, "456", 3),
+        Undo(0),
+        AssertDocJson(0, r#"[{"insert":"123\n"}]"#),
+        // The change after the undo isn't composed into the undo entry of "123".
+        Insert(0, "789", 3),
+        EndUndoGroup(0),
+        AssertDocJson(0, r#"[{"insert":"123789\n"}]"#),
+        Undo(0),
+        AssertDocJson(0, r#"[{"insert":"123\n"}]"#),
+        Undo(0),
+        AssertDocJson(0, r#"[{"insert":"\n"}]"#),
+    ];
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn history_undo_nested_group_test() {
+    let ops = vec![
+        BeginUndoGroup(0),
+        Insert(0, "123", 0),
+        BeginUndoGroup(0),
+        Insert(0, "456", 3),
+        EndUndoGroup(0),
+        Wait(RECORD_THRESHOLD),
+        Insert(0, "789", 6),
+        EndUndoGroup(0),
+        Undo(0),
+        AssertDocJson(0, r#"[{"insert":"\n"}]"#),
+    ];
+    TestBuilder::new().run_scripts::<NewlineDocument>(ops);
+}
+
+#[test]
+fn history_undo_group_with_remote_change_test() {
+    let mut document = ClientDocument::new::<NewlineDocument>();
+    document.insert(0, "123").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(RECORD_THRESHOLD as u64));
+
+    document.begin_undo_group();
+    document.insert(3, "456").unwrap();
+    // The remote change arrives in the middle of the group.
+    let remote_operations = DeltaTextOperationBuilder::new().insert("abc").retain(7).build();
+    document.compose_remote_operations(remote_operations).unwrap();
+    document.insert(9, "789").unwrap();
+    document.end_undo_group();
+    assert_eq!(document.get_operations_json(), r#"[{"insert":"abc123456789\n"}]"#);
+
+    // Undoing the group keeps the remote change.
+    document.undo().unwrap();
+    assert_eq!(document.get_operations_json(), r#"[{"insert":"abc123\n"}]"#);
+}
@@ -1,3 +1,4 @@
 mod editor;
 mod new_document;
 mod old_document;
+mod server_resolver;
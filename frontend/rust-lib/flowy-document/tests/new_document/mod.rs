@@ -1,3 +1,4 @@
 mod document_compose_test;
+mod scratch_document_test;
 mod script;
 mod test;
@@ -0,0 +1,48 @@
+use flowy_document::editor::AppFlowyDocumentEditor;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_test::helper::ViewTest;
+use flowy_test::FlowySDKTest;
+use lib_ot::core::{NodeDataBuilder, NodeOperation, Transaction};
+use lib_ot::text_delta::DeltaTextOperationBuilder;
+use std::sync::Arc;
+
+async fn open_editor(sdk: &FlowySDKTest, doc_id: &str) -> Arc<AppFlowyDocumentEditor> {
+    let editor = sdk.document_manager.open_document_editor(doc_id).await.unwrap();
+    editor
+        .as_any()
+        .downcast_ref::<Arc<AppFlowyDocumentEditor>>()
+        .unwrap()
+        .clone()
+}
+
+#[tokio::test]
+async fn scratch_document_convert_test() {
+    let sdk = FlowySDKTest::new(DocumentVersionPB::V1);
+    let _ = sdk.init_user().await;
+    let view = ViewTest::new_scratch_document_view(&sdk).await.view;
+    assert!(view.is_scratch);
+    assert!(sdk.folder_manager.is_scratch_view(&view.id).await.unwrap());
+    assert!(sdk.document_manager.is_scratch_document(&view.id).await.unwrap());
+
+    let editor = open_editor(&sdk, &view.id).await;
+    let delta = DeltaTextOperationBuilder::new().insert("Hello world").build();
+    let operation = NodeOperation::Insert {
+        path: vec![0, 0].into(),
+        nodes: vec![NodeDataBuilder::new("text").insert_delta(delta).build()],
+    };
+    editor
+        .apply_transaction(Transaction::from_operations(vec![operation]))
+        .await
+        .unwrap();
+    let content = editor.get_content(false).await.unwrap();
+
+    // The editor must be closed before converting the document.
+    assert!(sdk.document_manager.convert_scratch_document(&view.id).await.is_err());
+    sdk.document_manager.close_document_editor(&view.id).await.unwrap();
+    sdk.document_manager.convert_scratch_document(&view.id).await.unwrap();
+    assert!(!sdk.document_manager.is_scratch_document(&view.id).await.unwrap());
+    assert!(!sdk.folder_manager.is_scratch_view(&view.id).await.unwrap());
+
+    let editor = open_editor(&sdk, &view.id).await;
+    assert_eq!(editor.get_content(false).await.unwrap(), content);
+}
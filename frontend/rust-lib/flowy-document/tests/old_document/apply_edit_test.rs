@@ -0,0 +1,114 @@
+use crate::old_document::mock::{make_document_manager, DocumentCloudServiceMock};
+use bytes::Bytes;
+use flowy_document::entities::{DocumentVersionPB, EditParams};
+use flowy_document::errors::ErrorCode;
+use flowy_document::{DocumentConfig, DocumentManager};
+use flowy_http_model::revision::Revision;
+use lib_ot::text_delta::{attachment_ids, DeltaTextOperationBuilder, DeltaTextOperations};
+use std::sync::Arc;
+
+const DOC_ID: &str = "apply_edit_doc";
+
+#[tokio::test]
+async fn apply_edit_test() {
+    let manager = make_manager().await;
+    let operations = DeltaTextOperationBuilder::new().retain(3).insert("d").retain(1).build();
+    manager
+        .apply_edit(EditParams {
+            doc_id: DOC_ID.to_owned(),
+            operations: operations.json_str(),
+            strip_all_attributes: false,
+        })
+        .await
+        .unwrap();
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"abcd\n"}]"#);
+}
+
+#[tokio::test]
+async fn apply_edit_with_mismatched_base_test() {
+    let manager = make_manager().await;
+    // The client made the edit against "ab\n", but the document is "abc\n".
+    let operations = DeltaTextOperationBuilder::new().retain(2).insert("d").retain(1).build();
+    let error = manager
+        .apply_edit(EditParams {
+            doc_id: DOC_ID.to_owned(),
+            operations: operations.json_str(),
+            strip_all_attributes: false,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code, ErrorCode::BaseMismatch.value());
+    assert_eq!(error.msg, "expected base length: 4, actual base length: 3");
+
+    // The document isn't changed.
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"abc\n"}]"#);
+}
+
+/// The clipboard of a formatted selection: a bold word, an italic link, an attachment and a header.
+const CLIPBOARD: &str = r#"[{"retain":3},{"insert":"see ","attributes":{"bold":true}},{"insert":"this","attributes":{"link":"https://appflowy.io","italic":true}},{"insert":"photo","attributes":{"attachment":"a1"}},{"insert":"\n","attributes":{"header":1}}]"#;
+
+#[tokio::test]
+async fn apply_edit_without_formatting_test() {
+    let manager = make_manager().await;
+    manager
+        .apply_edit(EditParams {
+            doc_id: DOC_ID.to_owned(),
+            operations: CLIPBOARD.to_owned(),
+            strip_all_attributes: true,
+        })
+        .await
+        .unwrap();
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(
+        editor.export().await.unwrap(),
+        r#"[{"insert":"abcsee thisphoto"},{"insert":"\n","attributes":{"header":1}},{"insert":"\n"}]"#
+    );
+}
+
+#[tokio::test]
+async fn apply_edit_with_formatting_test() {
+    let manager = make_manager().await;
+    manager
+        .apply_edit(EditParams {
+            doc_id: DOC_ID.to_owned(),
+            operations: CLIPBOARD.to_owned(),
+            strip_all_attributes: false,
+        })
+        .await
+        .unwrap();
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    let document = DeltaTextOperations::from_json(&editor.export().await.unwrap()).unwrap();
+    let attributes = document
+        .ops
+        .iter()
+        .map(|op| op.get_attributes())
+        .filter(|attributes| !attributes.is_empty())
+        .count();
+    assert_eq!(attributes, 4);
+    assert_eq!(
+        attachment_ids(&document).into_iter().collect::<Vec<_>>(),
+        vec!["a1".to_owned()]
+    );
+}
+
+async fn make_manager() -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        ..Default::default()
+    };
+    let manager = make_document_manager(Arc::new(DocumentCloudServiceMock()), config);
+    manager
+        .create_document(
+            DOC_ID,
+            vec![Revision::initial_revision(
+                DOC_ID,
+                Bytes::from(r#"[{"insert":"abc\n"}]"#),
+            )],
+        )
+        .await
+        .unwrap();
+    let _ = manager.open_document_editor(DOC_ID).await.unwrap();
+    manager
+}
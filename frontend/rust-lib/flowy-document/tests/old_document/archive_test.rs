@@ -0,0 +1,83 @@
+use crate::old_document::mock::{create_delta_editor, make_delta_document_manager, open_delta_editor};
+use lib_ot::core::Interval;
+
+const DOC_ID: &str = "archive_doc";
+const CONTENT: &str = r#"[{"insert":"abc def\nghi\n"}]"#;
+
+#[tokio::test]
+async fn archive_part_of_line_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    editor.archive_range(Interval::new(3, 7)).await.unwrap();
+
+    // The archived text stays in the document.
+    assert_eq!(
+        editor.export().await.unwrap(),
+        r#"[{"insert":"abc"},{"insert":" def","attributes":{"archived":true}},{"insert":"\nghi\n"}]"#
+    );
+    assert_eq!(editor.plain_text(false).await.unwrap(), "abc\nghi\n");
+    assert_eq!(editor.plain_text(true).await.unwrap(), "abc def\nghi\n");
+    assert_eq!(editor.word_count(false).await.unwrap(), 2);
+    assert_eq!(editor.word_count(true).await.unwrap(), 3);
+
+    assert!(editor.search("def", false).await.unwrap().is_empty());
+    assert_eq!(editor.search("def", true).await.unwrap(), vec![Interval::new(4, 7)]);
+    assert_eq!(editor.search("ghi", false).await.unwrap(), vec![Interval::new(8, 11)]);
+
+    assert_eq!(
+        manager.export_document(DOC_ID, false).await.unwrap(),
+        r#"[{"insert":"abc\nghi\n"}]"#
+    );
+    assert_eq!(
+        manager.export_document(DOC_ID, true).await.unwrap(),
+        editor.export().await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn archive_whole_line_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    editor.archive_range(Interval::new(0, 7)).await.unwrap();
+
+    // The line is left out with its newline.
+    assert_eq!(editor.plain_text(false).await.unwrap(), "ghi\n");
+    assert_eq!(
+        manager.export_document(DOC_ID, false).await.unwrap(),
+        r#"[{"insert":"ghi\n"}]"#
+    );
+}
+
+#[tokio::test]
+async fn unarchive_range_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    editor.archive_range(Interval::new(3, 7)).await.unwrap();
+    editor.unarchive_range(Interval::new(3, 7)).await.unwrap();
+
+    assert_eq!(editor.export().await.unwrap(), CONTENT);
+    assert_eq!(manager.export_document(DOC_ID, false).await.unwrap(), CONTENT);
+    assert_eq!(editor.search("def", false).await.unwrap(), vec![Interval::new(4, 7)]);
+}
+
+#[tokio::test]
+async fn search_documents_skips_archived_text_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    editor.archive_range(Interval::new(3, 7)).await.unwrap();
+
+    // The opened document is searched as it's edited.
+    assert!(manager.search_documents("def").await.unwrap().is_empty());
+    assert_eq!(manager.search_documents("ghi").await.unwrap(), vec![DOC_ID.to_owned()]);
+
+    // The closed document is searched through its saved text.
+    manager.close_document_editor(DOC_ID).await.unwrap();
+    assert!(manager.search_documents("def").await.unwrap().is_empty());
+    assert_eq!(manager.search_documents("ghi").await.unwrap(), vec![DOC_ID.to_owned()]);
+
+    let editor = open_delta_editor(&manager, DOC_ID).await;
+    editor.unarchive_range(Interval::new(3, 7)).await.unwrap();
+    manager.close_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(manager.search_documents("def").await.unwrap(), vec![DOC_ID.to_owned()]);
+    assert!(manager.search_documents("").await.is_err());
+}
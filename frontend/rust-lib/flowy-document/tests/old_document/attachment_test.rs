@@ -0,0 +1,336 @@
+use crate::old_document::mock::{make_document_manager, make_document_manager_at, make_temp_dir, open_delta_editor};
+use bytes::Bytes;
+use flowy_database::{sql_query, RunQueryDsl};
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::FlowyError;
+use flowy_document::{
+    Attachment, AttachmentReconcileSummary, AttachmentVersion, DocumentCloudService, DocumentConfig, DocumentManager,
+};
+use flowy_http_model::document::{CreateDocumentParams, DocumentId, DocumentPayload, ResetDocumentParams};
+use flowy_http_model::revision::Revision;
+use lib_infra::future::FutureResult;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const DOC_ID: &str = "attachment_doc";
+
+#[tokio::test]
+async fn reconcile_attachments_of_two_devices_test() {
+    let server = Arc::new(AttachmentServerMock::default());
+    let device_a = make_manager(server.clone(), Duration::from_secs(60));
+    let device_b = make_manager(server.clone(), Duration::from_secs(60));
+
+    // Both devices have the attachment.
+    let photo = device_a
+        .attachment_store()
+        .unwrap()
+        .upload("photo.png", vec![1, 2, 3])
+        .unwrap();
+    server.upload(photo.clone());
+    device_b.attachment_store().unwrap().merge(&photo).unwrap();
+
+    // Offline, both devices rename it, and device B uploads a new version and refers to it.
+    device_a
+        .attachment_store()
+        .unwrap()
+        .rename(&photo.id, "photo_a.png")
+        .unwrap();
+    device_b
+        .attachment_store()
+        .unwrap()
+        .rename(&photo.id, "photo_b.png")
+        .unwrap();
+    let new_photo = device_b
+        .attachment_store()
+        .unwrap()
+        .upload("photo_v2.png", vec![4, 5, 6])
+        .unwrap();
+    server.upload(new_photo.clone());
+
+    // The synced document refers to both attachments on both devices.
+    let content = format!(
+        r#"[{},{{"insert":" "}},{},{{"insert":"\n"}}]"#,
+        reference("photo.png", &photo.id),
+        reference("photo_v2.png", &new_photo.id)
+    );
+    create_document(&device_a, &content).await;
+    create_document(&device_b, &content).await;
+
+    let summary = reconcile(&device_a).await;
+    assert_eq!(summary.num_of_referenced, 2);
+    assert_eq!(summary.downloads, vec![new_photo.id.clone()]);
+    assert_eq!(summary.uploads, vec![photo.id.clone()]);
+    assert!(summary.missing.is_empty());
+
+    let summary = reconcile(&device_b).await;
+    assert!(summary.downloads.is_empty() || summary.downloads == vec![photo.id.clone()]);
+    assert!(summary.missing.is_empty());
+    let _ = reconcile(&device_a).await;
+
+    // No reference is left dangling, and both devices and the server keep the same rename.
+    let server_name = server.read(&photo.id).unwrap().name;
+    assert!(server_name == "photo_a.png" || server_name == "photo_b.png");
+    for device in [&device_a, &device_b] {
+        let store = device.attachment_store().unwrap();
+        assert!(store.missing([&photo.id, &new_photo.id]).unwrap().is_empty());
+        assert_eq!(store.read(&new_photo.id).unwrap().unwrap().data, vec![4, 5, 6]);
+        assert_eq!(store.read(&photo.id).unwrap().unwrap().name, server_name);
+    }
+}
+
+#[tokio::test]
+async fn upload_attachment_missing_on_the_server_test() {
+    let server = Arc::new(AttachmentServerMock::default());
+    let manager = make_manager(server.clone(), Duration::from_secs(60));
+    let photo = manager
+        .attachment_store()
+        .unwrap()
+        .upload("photo.png", vec![1, 2, 3])
+        .unwrap();
+    let content = format!(r#"[{},{{"insert":"\n"}}]"#, reference("photo.png", &photo.id));
+    create_document(&manager, &content).await;
+
+    let summary = reconcile(&manager).await;
+    assert_eq!(summary.uploads, vec![photo.id.clone()]);
+    assert_eq!(server.read(&photo.id), Some(photo));
+
+    // Nothing is transferred once both sides have the same version.
+    let summary = reconcile(&manager).await;
+    assert!(summary.uploads.is_empty());
+    assert!(summary.downloads.is_empty());
+}
+
+#[tokio::test]
+async fn move_local_file_links_to_attachments_test() {
+    let server = Arc::new(AttachmentServerMock::default());
+    let manager = make_manager(server.clone(), Duration::from_secs(60));
+    let path = std::path::Path::new(&make_temp_dir()).join("report.pdf");
+    std::fs::write(&path, [1, 2, 3]).unwrap();
+    // Written before the attachments had ids, the document links to the file twice.
+    let link = format!(
+        r#"{{"insert":"report","attributes":{{"link":"file://{}"}}}}"#,
+        path.display()
+    );
+    let content = format!(r#"[{},{{"insert":" "}},{},{{"insert":"\n"}}]"#, link, link);
+    create_document(&manager, &content).await;
+
+    let summary = reconcile(&manager).await;
+    assert_eq!(summary.num_of_attached_links, 1);
+    assert_eq!(summary.num_of_referenced, 1);
+    assert_eq!(summary.uploads.len(), 1);
+
+    let attachment_id = &summary.uploads[0];
+    let attachment = manager
+        .attachment_store()
+        .unwrap()
+        .read(attachment_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(attachment.name, "report.pdf");
+    assert_eq!(attachment.data, vec![1, 2, 3]);
+    assert_eq!(server.read(attachment_id), Some(attachment));
+
+    let editor = open_delta_editor(&manager, DOC_ID).await;
+    let json = editor.document_operations().await.unwrap().json_str();
+    assert!(!json.contains("link"));
+    assert_eq!(json.matches(attachment_id.as_str()).count(), 2);
+
+    // The links are moved once.
+    let summary = reconcile(&manager).await;
+    assert_eq!(summary.num_of_attached_links, 0);
+}
+
+#[tokio::test]
+async fn reconcile_attachment_missing_on_the_server_test() {
+    let server = Arc::new(AttachmentServerMock::default());
+    let manager = make_manager(server.clone(), Duration::from_secs(60));
+    let content = format!(r#"[{},{{"insert":"\n"}}]"#, reference("lost.png", "lost"));
+    create_document(&manager, &content).await;
+
+    let summary = reconcile(&manager).await;
+    assert_eq!(summary.missing, vec!["lost".to_owned()]);
+
+    // The next reconciliation downloads it once the server has it.
+    server.upload(Attachment {
+        id: "lost".to_owned(),
+        name: "lost.png".to_owned(),
+        data: vec![7],
+        update_time: 0,
+    });
+    let summary = reconcile(&manager).await;
+    assert_eq!(summary.downloads, vec!["lost".to_owned()]);
+    assert!(summary.missing.is_empty());
+    assert!(manager.attachment_store().unwrap().read("lost").unwrap().is_some());
+}
+
+#[tokio::test]
+async fn collect_orphaned_attachments_test() {
+    let server = Arc::new(AttachmentServerMock::default());
+    let manager = make_manager(server.clone(), Duration::from_secs(60));
+    let orphan = manager
+        .attachment_store()
+        .unwrap()
+        .upload("orphan.png", vec![1])
+        .unwrap();
+
+    // The orphan is kept during the grace period.
+    let summary = manager.reconcile_attachments().await.unwrap();
+    assert_eq!(summary.num_of_collected, 0);
+    assert!(manager.attachment_store().unwrap().read(&orphan.id).unwrap().is_some());
+
+    let manager = make_manager(server, Duration::from_secs(0));
+    let orphan = manager
+        .attachment_store()
+        .unwrap()
+        .upload("orphan.png", vec![1])
+        .unwrap();
+    let referenced = manager
+        .attachment_store()
+        .unwrap()
+        .upload("photo.png", vec![2])
+        .unwrap();
+    let content = format!(r#"[{},{{"insert":"\n"}}]"#, reference("photo.png", &referenced.id));
+    create_document(&manager, &content).await;
+
+    let summary = manager.reconcile_attachments().await.unwrap();
+    assert_eq!(summary.num_of_collected, 1);
+    let store = manager.attachment_store().unwrap();
+    assert!(store.read(&orphan.id).unwrap().is_none());
+    assert!(store.read(&referenced.id).unwrap().is_some());
+}
+
+#[tokio::test]
+async fn collect_attachments_referenced_by_resolved_revisions_test() {
+    let dir = make_temp_dir();
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        attachment_grace_period: Duration::from_secs(0),
+        ..Default::default()
+    };
+    let manager = make_document_manager_at(&dir, Arc::new(AttachmentServerMock::default()), config);
+    let superseded = manager
+        .attachment_store()
+        .unwrap()
+        .upload("superseded.png", vec![1])
+        .unwrap();
+    let revisions = vec![
+        Revision::initial_revision(DOC_ID, Bytes::from(r#"[{"insert":"\n"}]"#)),
+        Revision::new(
+            DOC_ID,
+            1,
+            2,
+            Bytes::from(format!("[{}]", reference("superseded.png", &superseded.id))),
+            "",
+        ),
+    ];
+    manager.create_document(DOC_ID, revisions).await.unwrap();
+
+    // The revision 2 was superseded by resolving a conflict, so nothing refers to the attachment.
+    let database = flowy_database::init(&dir).unwrap();
+    let conn = database.get_connection().unwrap();
+    sql_query("UPDATE rev_table SET state = 2 WHERE rev_id = 2")
+        .execute(&*conn)
+        .unwrap();
+
+    let summary = manager.reconcile_attachments().await.unwrap();
+    assert_eq!(summary.num_of_collected, 1);
+    assert!(manager
+        .attachment_store()
+        .unwrap()
+        .read(&superseded.id)
+        .unwrap()
+        .is_none());
+}
+
+async fn reconcile(manager: &DocumentManager) -> AttachmentReconcileSummary {
+    let summary = manager.reconcile_attachments().await.unwrap();
+    manager.wait_for_attachment_transfers().await;
+    summary
+}
+
+fn reference(label: &str, attachment_id: &str) -> String {
+    format!(
+        r#"{{"insert":"{}","attributes":{{"attachment":"{}"}}}}"#,
+        label, attachment_id
+    )
+}
+
+fn make_manager(server: Arc<AttachmentServerMock>, grace_period: Duration) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        attachment_grace_period: grace_period,
+        ..Default::default()
+    };
+    make_document_manager(server, config)
+}
+
+async fn create_document(manager: &DocumentManager, content: &str) {
+    manager
+        .create_document(
+            DOC_ID,
+            vec![Revision::initial_revision(DOC_ID, Bytes::from(content.to_owned()))],
+        )
+        .await
+        .unwrap();
+}
+
+/// The server that keeps the attachments uploaded by the devices.
+#[derive(Default)]
+struct AttachmentServerMock {
+    attachments: Mutex<HashMap<String, Attachment>>,
+}
+
+impl AttachmentServerMock {
+    /// Keeps the newer version, like the devices.
+    fn upload(&self, attachment: Attachment) {
+        let mut attachments = self.attachments.lock().unwrap();
+        match attachments.get(&attachment.id) {
+            Some(existing) if existing.version() >= attachment.version() => {}
+            _ => {
+                attachments.insert(attachment.id.clone(), attachment);
+            }
+        }
+    }
+
+    fn read(&self, attachment_id: &str) -> Option<Attachment> {
+        self.attachments.lock().unwrap().get(attachment_id).cloned()
+    }
+}
+
+impl DocumentCloudService for AttachmentServerMock {
+    fn create_document(&self, _token: &str, _params: CreateDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    fn fetch_document(&self, _token: &str, _params: DocumentId) -> FutureResult<Option<DocumentPayload>, FlowyError> {
+        FutureResult::new(async { Ok(None) })
+    }
+
+    fn update_document_content(&self, _token: &str, _params: ResetDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    fn fetch_attachment(&self, _token: &str, attachment_id: &str) -> FutureResult<Option<Attachment>, FlowyError> {
+        let attachment = self.attachments.lock().unwrap().get(attachment_id).cloned();
+        FutureResult::new(async move { Ok(attachment) })
+    }
+
+    fn fetch_attachment_versions(
+        &self,
+        _token: &str,
+        attachment_ids: Vec<String>,
+    ) -> FutureResult<Option<HashMap<String, AttachmentVersion>>, FlowyError> {
+        let attachments = self.attachments.lock().unwrap();
+        let versions = attachment_ids
+            .into_iter()
+            .filter_map(|id| Some((id.clone(), attachments.get(&id)?.version())))
+            .collect();
+        FutureResult::new(async move { Ok(Some(versions)) })
+    }
+
+    fn upload_attachment(&self, _token: &str, attachment: Attachment) -> FutureResult<(), FlowyError> {
+        self.upload(attachment);
+        FutureResult::new(async { Ok(()) })
+    }
+}
@@ -0,0 +1,358 @@
+use crate::old_document::mock::{
+    create_delta_editor, make_document_manager_at, make_temp_dir, DocumentCloudServiceMock,
+};
+use bytes::Bytes;
+use flowy_database::schema::document_rev_snapshot;
+use flowy_database::{sql_query, ExpressionMethods, QueryDsl, RunQueryDsl};
+use flowy_document::editor::initial_document_content;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::ErrorCode;
+use flowy_document::{BackupKind, BackupOutcome, DocumentBackupConfiguration, DocumentConfig, DocumentManager};
+use flowy_http_model::revision::Revision;
+use flowy_revision::RECOVERED_BACKUP_VERSION;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const DOC_ID: &str = "scheduled_backup_doc";
+const CONTENT: &str = r#"[{"insert":"a\n"}]"#;
+
+#[tokio::test]
+async fn backup_rotation_test() {
+    let dir = make_temp_dir();
+    let blob_dir = make_blob_dir(&dir);
+    let manager = make_manager(&dir, vec![blob_dir]);
+    let _ = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+
+    let first = manager.backup_now().await.unwrap();
+    let second = manager.backup_now().await.unwrap();
+    let third = manager.backup_now().await.unwrap();
+
+    // Only the two newest backups are kept.
+    assert_eq!(manager.backups().unwrap(), vec![second, third.clone()]);
+    assert!(!first.exists());
+    assert!(third.join(flowy_database::DB_NAME).exists());
+    assert_eq!(
+        std::fs::read_to_string(third.join("blobs").join("images").join("a.png")).unwrap(),
+        "image"
+    );
+
+    let audit = manager.backup_audit().unwrap();
+    assert_eq!(audit.len(), 3);
+    assert!(audit.iter().all(|entry| entry.outcome == BackupOutcome::Succeeded));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn backup_failed_copy_test() {
+    let dir = make_temp_dir();
+    let blob_dir = make_blob_dir(&dir);
+    // The database gets copied, then copying the dangling link fails.
+    std::os::unix::fs::symlink(Path::new(&dir).join("missing.png"), blob_dir.join("b.png")).unwrap();
+    let manager = make_manager(&dir, vec![blob_dir]);
+    let _ = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+
+    assert!(manager.backup_now().await.is_err());
+
+    // Neither the backup nor the partial copy is left behind.
+    let backups_dir = Path::new(&dir).join("backups");
+    assert_eq!(std::fs::read_dir(&backups_dir).unwrap().count(), 0);
+    let audit = manager.backup_audit().unwrap();
+    assert_eq!(audit.len(), 1);
+    assert_eq!(audit[0].outcome, BackupOutcome::Failed);
+    assert!(!audit[0].detail.is_empty());
+}
+
+#[tokio::test]
+async fn restore_from_backup_test() {
+    let dir = make_temp_dir();
+    let manager = make_manager(&dir, vec![]);
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    let backup = manager.backup_now().await.unwrap();
+    editor.insert(1, "b").await.unwrap();
+
+    // The revisions aren't synced, the mock server never acks them.
+    let backup_str = backup.to_str().unwrap();
+    assert!(manager.restore_from_backup(backup_str, false).await.is_err());
+    assert!(!Path::new(&dir).join(flowy_database::STAGED_DB_NAME).exists());
+
+    manager.restore_from_backup(backup_str, true).await.unwrap();
+    assert!(Path::new(&dir).join(flowy_database::STAGED_DB_NAME).exists());
+    // The edits would be lost by the swap, the document is closed until the restart.
+    let error = manager.open_document_editor(DOC_ID).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::ModuleNotReady.value());
+
+    // The staged backup replaces the database once it's opened again.
+    let restored_manager = make_manager(&dir, vec![]);
+    assert!(!Path::new(&dir).join(flowy_database::STAGED_DB_NAME).exists());
+    let restored_editor = restored_manager.open_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(restored_editor.export().await.unwrap(), r#"[{"insert":"a\n"}]"#);
+}
+
+#[tokio::test]
+async fn restore_from_invalid_backup_test() {
+    let dir = make_temp_dir();
+    let manager = make_manager(&dir, vec![]);
+
+    let missing_backup = Path::new(&dir).join("backups").join("missing");
+    assert!(manager
+        .restore_from_backup(missing_backup.to_str().unwrap(), true)
+        .await
+        .is_err());
+
+    let corrupted_backup = Path::new(&dir).join("backups").join("corrupted");
+    std::fs::create_dir_all(&corrupted_backup).unwrap();
+    std::fs::write(
+        corrupted_backup.join(flowy_database::DB_NAME),
+        "not a database, just some text that is long enough to be read as a header",
+    )
+    .unwrap();
+    assert!(manager
+        .restore_from_backup(corrupted_backup.to_str().unwrap(), true)
+        .await
+        .is_err());
+
+    assert!(!Path::new(&dir).join(flowy_database::STAGED_DB_NAME).exists());
+    let audit = manager.backup_audit().unwrap();
+    assert_eq!(audit.len(), 2);
+    assert!(audit
+        .iter()
+        .all(|entry| entry.kind == BackupKind::Restore && entry.outcome == BackupOutcome::Failed));
+}
+
+#[tokio::test]
+async fn restore_incremental_backup_chain_test() {
+    let dir = make_temp_dir();
+    let manager = make_incremental_manager(&dir, 10, 2);
+    let editor_a = create_delta_editor(&manager, "doc_a", CONTENT).await;
+    let editor_b = create_delta_editor(&manager, "doc_b", CONTENT).await;
+    let base = manager.backup_now().await.unwrap();
+
+    editor_a.insert(1, "1").await.unwrap();
+    manager.set_doc_preference("doc_a", "font_size", &16).await.unwrap();
+    let first = manager.backup_now().await.unwrap();
+    editor_b.insert(1, "2").await.unwrap();
+    let editor_c = create_delta_editor(&manager, "doc_c", CONTENT).await;
+    editor_c.insert(0, "3").await.unwrap();
+    let second = manager.backup_now().await.unwrap();
+
+    // Only the full backup has a copy of the database.
+    assert_eq!(
+        manager.backups().unwrap(),
+        vec![base.clone(), first.clone(), second.clone()]
+    );
+    assert!(base.join(flowy_database::DB_NAME).exists());
+    assert!(!first.join(flowy_database::DB_NAME).exists());
+    assert!(!second.join(flowy_database::DB_NAME).exists());
+
+    let mut expected = vec![];
+    for editor in [&editor_a, &editor_b, &editor_c] {
+        expected.push(editor.export().await.unwrap());
+    }
+    for doc_id in ["doc_a", "doc_b", "doc_c"] {
+        manager.close_document_editor(doc_id).await.unwrap();
+    }
+    drop(manager);
+    remove_database(&dir);
+
+    // The new database has no unsynced revisions, the restore doesn't need to be forced.
+    let empty_manager = make_incremental_manager(&dir, 10, 2);
+    empty_manager
+        .restore_from_backup(second.to_str().unwrap(), false)
+        .await
+        .unwrap();
+    assert!(!Path::new(&dir)
+        .join(format!("{}.layered", flowy_database::STAGED_DB_NAME))
+        .exists());
+
+    let restored_manager = make_incremental_manager(&dir, 10, 2);
+    for (doc_id, expected) in ["doc_a", "doc_b", "doc_c"].iter().zip(expected) {
+        let editor = restored_manager.open_document_editor(doc_id).await.unwrap();
+        assert_eq!(editor.export().await.unwrap(), expected);
+    }
+    assert_eq!(
+        restored_manager
+            .get_doc_preference::<i32>("doc_a", "font_size")
+            .unwrap(),
+        Some(16)
+    );
+}
+
+#[tokio::test]
+async fn restore_incremental_backup_blobs_test() {
+    let dir = make_temp_dir();
+    let blob_dir = make_blob_dir(&dir);
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        backup: Some(DocumentBackupConfiguration {
+            max_backups: 10,
+            max_incremental_backups: 2,
+            blob_dirs: vec![blob_dir.clone()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let manager = make_document_manager_at(&dir, Arc::new(DocumentCloudServiceMock()), config);
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    let _ = manager.backup_now().await.unwrap();
+    editor.insert(1, "b").await.unwrap();
+    std::fs::write(blob_dir.join("b.png"), "new image").unwrap();
+    let first = manager.backup_now().await.unwrap();
+
+    std::fs::remove_dir_all(&blob_dir).unwrap();
+    manager
+        .restore_from_backup(first.to_str().unwrap(), true)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(blob_dir.join("a.png")).unwrap(), "image");
+    assert_eq!(std::fs::read_to_string(blob_dir.join("b.png")).unwrap(), "new image");
+}
+
+#[tokio::test]
+async fn restore_modified_incremental_backup_test() {
+    let dir = make_temp_dir();
+    let manager = make_incremental_manager(&dir, 10, 2);
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    let _ = manager.backup_now().await.unwrap();
+    editor.insert(1, "b").await.unwrap();
+    let first = manager.backup_now().await.unwrap();
+    editor.insert(2, "c").await.unwrap();
+    let second = manager.backup_now().await.unwrap();
+
+    // Every incremental backup of the chain is validated, not only the one that's restored.
+    let delta_path = first.join("delta.json");
+    let mut delta: serde_json::Value = serde_json::from_slice(&std::fs::read(&delta_path).unwrap()).unwrap();
+    delta["objects"][0]["rev_states"] = serde_json::json!([]);
+    std::fs::write(&delta_path, serde_json::to_vec(&delta).unwrap()).unwrap();
+    assert!(manager
+        .restore_from_backup(second.to_str().unwrap(), true)
+        .await
+        .is_err());
+    assert!(!Path::new(&dir).join(flowy_database::STAGED_DB_NAME).exists());
+}
+
+#[tokio::test]
+async fn restore_incomplete_backup_chain_test() {
+    let dir = make_temp_dir();
+    let manager = make_incremental_manager(&dir, 10, 2);
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    let _ = manager.backup_now().await.unwrap();
+    editor.insert(1, "b").await.unwrap();
+    let first = manager.backup_now().await.unwrap();
+    editor.insert(2, "c").await.unwrap();
+    let second = manager.backup_now().await.unwrap();
+
+    std::fs::remove_dir_all(&first).unwrap();
+    assert!(manager
+        .restore_from_backup(second.to_str().unwrap(), true)
+        .await
+        .is_err());
+    assert!(!Path::new(&dir).join(flowy_database::STAGED_DB_NAME).exists());
+}
+
+#[tokio::test]
+async fn incremental_backup_rotation_test() {
+    let dir = make_temp_dir();
+    let manager = make_incremental_manager(&dir, 2, 2);
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+
+    let base = manager.backup_now().await.unwrap();
+    editor.insert(1, "b").await.unwrap();
+    let first = manager.backup_now().await.unwrap();
+    editor.insert(2, "c").await.unwrap();
+    let second = manager.backup_now().await.unwrap();
+
+    // The incremental backups still need their full backup, none of them is removed.
+    assert_eq!(manager.backups().unwrap(), vec![base.clone(), first, second]);
+
+    // The next backup is a full backup, the older chain is removed as a whole.
+    let next_base = manager.backup_now().await.unwrap();
+    assert!(next_base.join(flowy_database::DB_NAME).exists());
+    assert_eq!(manager.backups().unwrap(), vec![next_base]);
+    assert!(!base.exists());
+}
+
+#[tokio::test]
+async fn unconfirmed_snapshot_kept_as_recovered_backup_test() {
+    let dir = make_temp_dir();
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V1,
+        ..Default::default()
+    };
+    let manager = make_document_manager_at(&dir, Arc::new(DocumentCloudServiceMock()), config);
+    let content = initial_document_content();
+    let revision = Revision::initial_revision(DOC_ID, Bytes::from(content.clone()));
+    manager.create_document(DOC_ID, vec![revision]).await.unwrap();
+
+    // The snapshot of the revision 5 is left over from a newer database, the older one was
+    // restored over it.
+    let database = flowy_database::init(&dir).unwrap();
+    let conn = database.get_connection().unwrap();
+    sql_query(format!(
+        "INSERT INTO document_rev_snapshot VALUES ('{0}:5', '{0}', 5, 5, 1, CAST('{1}' AS BLOB))",
+        DOC_ID, content
+    ))
+    .execute(&*conn)
+    .unwrap();
+
+    // The server doesn't know the revision 5, the snapshot is moved out of the snapshots.
+    let _ = manager.open_document_editor(DOC_ID).await.unwrap();
+    let versions = manager.recovered_versions(DOC_ID).unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].0, RECOVERED_BACKUP_VERSION);
+    assert_eq!(versions[0].1.rev_id, 5);
+    assert_eq!(versions[0].1.data, Bytes::from(content));
+    let num_of_snapshots = document_rev_snapshot::table
+        .filter(document_rev_snapshot::rev_id.eq(5))
+        .count()
+        .get_result::<i64>(&*conn)
+        .unwrap();
+    assert_eq!(num_of_snapshots, 0);
+
+    let audit = manager.backup_audit().unwrap();
+    assert_eq!(audit.len(), 1);
+    assert_eq!(audit[0].kind, BackupKind::RecoveredSnapshot);
+    assert_eq!(audit[0].path, DOC_ID);
+}
+
+fn make_manager(dir: &str, blob_dirs: Vec<PathBuf>) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        backup: Some(DocumentBackupConfiguration {
+            max_backups: 2,
+            blob_dirs,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    make_document_manager_at(dir, Arc::new(DocumentCloudServiceMock()), config)
+}
+
+fn make_incremental_manager(dir: &str, max_backups: usize, max_incremental_backups: usize) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        backup: Some(DocumentBackupConfiguration {
+            max_backups,
+            max_incremental_backups,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    make_document_manager_at(dir, Arc::new(DocumentCloudServiceMock()), config)
+}
+
+/// Removes the database and its journal files, like a lost or corrupted database.
+fn remove_database(dir: &str) {
+    for suffix in ["", "-wal", "-shm", "-journal"] {
+        let path = Path::new(dir).join(format!("{}{}", flowy_database::DB_NAME, suffix));
+        if path.exists() {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
+
+fn make_blob_dir(dir: &str) -> PathBuf {
+    let blob_dir = Path::new(dir).join("images");
+    std::fs::create_dir_all(&blob_dir).unwrap();
+    std::fs::write(blob_dir.join("a.png"), "image").unwrap();
+    blob_dir
+}
@@ -0,0 +1,44 @@
+use crate::old_document::mock::{create_delta_editor, make_delta_document_manager};
+use lib_ot::core::Interval;
+use lib_ot::text_delta::BuildInTextAttribute;
+
+const DOC_ID: &str = "blame_doc";
+
+#[tokio::test]
+async fn blame_span_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"abc\n"}]"#).await;
+    editor.insert(3, "def").await.unwrap();
+    let def_rev_id = editor.rev_manager().rev_id();
+    editor.insert(1, "X").await.unwrap();
+    let x_rev_id = editor.rev_manager().rev_id();
+    // The "b" is deleted, the text after it moves forward.
+    editor.delete(Interval::new(2, 3)).await.unwrap();
+    // Formatting the text doesn't change who inserted it.
+    editor
+        .format(Interval::new(3, 5), BuildInTextAttribute::Bold(true))
+        .await
+        .unwrap();
+    assert_eq!(editor.plain_text(true).await.unwrap(), "aXcdef\n");
+
+    assert_eq!(
+        manager.blame(DOC_ID, Interval::new(0, 6)).await.unwrap(),
+        vec![
+            (Interval::new(0, 1), 0),
+            (Interval::new(1, 2), x_rev_id),
+            (Interval::new(2, 3), 0),
+            (Interval::new(3, 6), def_rev_id),
+        ]
+    );
+    assert_eq!(
+        manager.blame(DOC_ID, Interval::new(4, 5)).await.unwrap(),
+        vec![(Interval::new(4, 5), def_rev_id)]
+    );
+}
+
+#[tokio::test]
+async fn blame_out_of_bound_test() {
+    let manager = make_delta_document_manager();
+    let _ = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"abc\n"}]"#).await;
+    assert!(manager.blame(DOC_ID, Interval::new(2, 10)).await.is_err());
+}
@@ -0,0 +1,213 @@
+use crate::old_document::mock::{make_document_manager, DocumentCloudServiceMock};
+use bytes::Bytes;
+use flowy_document::entities::{
+    DocumentSnapshotPB, DocumentVersionPB, ExportDataPB, ExportPayloadPB, ExportType, OpenDocumentContextPB,
+};
+use flowy_document::errors::{ErrorCode, FlowyError};
+use flowy_document::event_map::{init, DocumentEvent};
+use flowy_document::{DocumentConfig, DocumentManager};
+use flowy_http_model::revision::Revision;
+use flowy_test::FlowySDKTest;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::tokio_default_runtime;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+/// The capability that each document event requires.
+const DOCUMENT_EVENT_CAPABILITIES: [(DocumentEvent, AFPluginCapability); 15] = [
+    (DocumentEvent::GetDocument, AFPluginCapability::Read),
+    (DocumentEvent::ApplyEdit, AFPluginCapability::Write),
+    (DocumentEvent::ExportDocument, AFPluginCapability::Read),
+    (DocumentEvent::GetStartupReport, AFPluginCapability::Read),
+    (DocumentEvent::GetRedline, AFPluginCapability::Read),
+    (DocumentEvent::QueryStoragePaths, AFPluginCapability::Maintenance),
+    (DocumentEvent::ValidateAllDocs, AFPluginCapability::Maintenance),
+    (DocumentEvent::SaveSelectionAsSnippet, AFPluginCapability::Write),
+    (DocumentEvent::GetSnippets, AFPluginCapability::Read),
+    (DocumentEvent::InsertSnippet, AFPluginCapability::Write),
+    (DocumentEvent::ResumeSync, AFPluginCapability::Maintenance),
+    (DocumentEvent::UpdateSelection, AFPluginCapability::Write),
+    (DocumentEvent::ReexportDocuments, AFPluginCapability::Export),
+    (DocumentEvent::CancelReexport, AFPluginCapability::Export),
+    (DocumentEvent::RestoreFromBackup, AFPluginCapability::Maintenance),
+];
+
+#[test]
+fn document_event_declares_capability_test() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let _guard = runtime.enter();
+    let manager = make_document_manager(Arc::new(DocumentCloudServiceMock()), DocumentConfig::default());
+    let plugin = init(Arc::new(manager));
+    for (event, capability) in DOCUMENT_EVENT_CAPABILITIES {
+        assert_eq!(plugin.required_capability(&event.into()), Some(capability));
+    }
+}
+
+#[test]
+fn document_event_rejects_missing_capability_test() {
+    let (_test, dispatcher) = make_dispatcher();
+    for (event, capability) in DOCUMENT_EVENT_CAPABILITIES {
+        // The request without a token is rejected.
+        let response = AFPluginDispatcher::sync_send(dispatcher.clone(), AFPluginRequest::new(event));
+        assert!(is_permission_denied(&response), "{} without a token", event);
+
+        // So is the request whose token grants all the other capabilities.
+        let others = AFPluginCapability::ALL
+            .iter()
+            .cloned()
+            .filter(|other| *other != capability)
+            .collect::<Vec<AFPluginCapability>>();
+        let token = dispatcher.mint_capability_token(&others);
+        let request = AFPluginRequest::new(event).capability_token(token);
+        let response = AFPluginDispatcher::sync_send(dispatcher.clone(), request);
+        assert!(is_permission_denied(&response), "{} without {:?}", event, capability);
+
+        // The request that carries the capability reaches the handler.
+        let token = dispatcher.mint_capability_token(&[capability]);
+        let request = AFPluginRequest::new(event).capability_token(token);
+        let response = AFPluginDispatcher::sync_send(dispatcher.clone(), request);
+        assert!(!is_permission_denied(&response), "{} with {:?}", event, capability);
+    }
+}
+
+#[test]
+fn read_only_token_opens_and_exports_document_test() {
+    let (_test, dispatcher) = make_dispatcher();
+    let token = dispatcher.mint_capability_token(&[AFPluginCapability::Read]);
+
+    let payload = OpenDocumentContextPB {
+        document_id: "doc".to_owned(),
+        document_version: DocumentVersionPB::V0,
+    };
+    let request = AFPluginRequest::new(DocumentEvent::GetDocument)
+        .payload(payload.into_bytes().unwrap())
+        .capability_token(token.clone());
+    let snapshot = AFPluginDispatcher::sync_send(dispatcher.clone(), request)
+        .parse::<DocumentSnapshotPB, FlowyError>()
+        .unwrap()
+        .unwrap();
+    assert!(snapshot.snapshot.contains("abc"));
+
+    let payload = ExportPayloadPB {
+        view_id: "doc".to_owned(),
+        export_type: ExportType::Text,
+        document_version: DocumentVersionPB::V0,
+        include_archived: false,
+    };
+    let request = AFPluginRequest::new(DocumentEvent::ExportDocument)
+        .payload(payload.into_bytes().unwrap())
+        .capability_token(token.clone());
+    let export = AFPluginDispatcher::sync_send(dispatcher.clone(), request)
+        .parse::<ExportDataPB, FlowyError>()
+        .unwrap()
+        .unwrap();
+    assert!(export.data.contains("abc"));
+
+    // The read-only token can't edit the document.
+    let request = AFPluginRequest::new(DocumentEvent::ApplyEdit).capability_token(token);
+    let response = AFPluginDispatcher::sync_send(dispatcher, request);
+    assert!(is_permission_denied(&response));
+}
+
+#[test]
+fn token_of_other_dispatcher_is_rejected_test() {
+    let (_test, dispatcher) = make_dispatcher();
+    let (_other_test, other_dispatcher) = make_dispatcher();
+    let token = other_dispatcher.mint_capability_token(&AFPluginCapability::ALL);
+    let request = AFPluginRequest::new(DocumentEvent::GetStartupReport).capability_token(token);
+    let response = AFPluginDispatcher::sync_send(dispatcher, request);
+    assert!(is_permission_denied(&response));
+}
+
+#[tokio::test]
+async fn ffi_token_grants_all_capabilities_test() {
+    let sdk = FlowySDKTest::default();
+    // The document events are held until the user signs in.
+    let _ = sdk.init_user().await;
+
+    // The app sends the maintenance events too, e.g. ResumeSync after a sync loop.
+    for event in [
+        DocumentEvent::ValidateAllDocs,
+        DocumentEvent::ResumeSync,
+        DocumentEvent::QueryStoragePaths,
+        DocumentEvent::GetStartupReport,
+        DocumentEvent::ApplyEdit,
+    ] {
+        let request = AFPluginRequest::new(event).capability_token(sdk.ffi_capability_token.clone());
+        let response = AFPluginDispatcher::async_send(sdk.dispatcher(), request).await;
+        assert!(!is_permission_denied(&response), "{} with the FFI token", event);
+    }
+}
+
+#[tokio::test]
+async fn restricted_ffi_token_is_scoped_test() {
+    // The `RESTRICTED_FFI_CAPABILITIES` of flowy-core.
+    let capabilities = [
+        AFPluginCapability::Read,
+        AFPluginCapability::Write,
+        AFPluginCapability::Export,
+    ];
+    let sdk = FlowySDKTest::new_with_ffi_capabilities(DocumentVersionPB::V0, &capabilities);
+    let _ = sdk.init_user().await;
+
+    // The restricted token of the FFI can't run the maintenance events, the in-process host
+    // token can.
+    let request =
+        AFPluginRequest::new(DocumentEvent::ValidateAllDocs).capability_token(sdk.ffi_capability_token.clone());
+    let response = AFPluginDispatcher::async_send(sdk.dispatcher(), request).await;
+    assert!(is_permission_denied(&response));
+
+    let request = AFPluginRequest::new(DocumentEvent::ValidateAllDocs).capability_token(sdk.capability_token.clone());
+    let response = AFPluginDispatcher::async_send(sdk.dispatcher(), request).await;
+    assert!(!is_permission_denied(&response));
+
+    // It can run the events of the app.
+    for event in [
+        DocumentEvent::GetStartupReport,
+        DocumentEvent::ApplyEdit,
+        DocumentEvent::CancelReexport,
+    ] {
+        let request = AFPluginRequest::new(event).capability_token(sdk.ffi_capability_token.clone());
+        let response = AFPluginDispatcher::async_send(sdk.dispatcher(), request).await;
+        assert!(!is_permission_denied(&response), "{} with the FFI token", event);
+    }
+}
+
+/// Holds the runtime that the document "doc" was created with.
+struct CapabilityTest {
+    _runtime: tokio::runtime::Runtime,
+}
+
+/// Makes the dispatcher of the document plugin, whose manager has the document "doc".
+fn make_dispatcher() -> (CapabilityTest, Arc<AFPluginDispatcher>) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let manager = runtime.block_on(async {
+        let manager = make_document_manager(Arc::new(DocumentCloudServiceMock()), DocumentConfig::default());
+        create_document(&manager, "doc").await;
+        // The manager isn't initialized, so the events would be held until it is.
+        manager.startup_gate().open().await;
+        Arc::new(manager)
+    });
+    let dispatcher = Arc::new(AFPluginDispatcher::construct(tokio_default_runtime().unwrap(), || {
+        vec![init(manager)]
+    }));
+    (CapabilityTest { _runtime: runtime }, dispatcher)
+}
+
+async fn create_document(manager: &DocumentManager, doc_id: &str) {
+    let json = r#"[{"insert":"abc\n"}]"#;
+    manager
+        .create_document(doc_id, vec![Revision::initial_revision(doc_id, Bytes::from(json))])
+        .await
+        .unwrap();
+}
+
+fn is_permission_denied(response: &AFPluginEventResponse) -> bool {
+    if response.status_code != StatusCode::Err {
+        return false;
+    }
+    match <AFPluginData<FlowyError>>::try_from(response.payload.clone()) {
+        Ok(error) => error.into_inner().code == ErrorCode::PermissionDenied.value(),
+        Err(_) => false,
+    }
+}
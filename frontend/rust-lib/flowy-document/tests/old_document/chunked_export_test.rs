@@ -0,0 +1,145 @@
+use crate::old_document::mock::{make_document_manager, DocumentCloudServiceMock};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::ErrorCode;
+use flowy_document::{DocumentConfig, DocumentContent, DocumentManager, DOCUMENT_JSON_CHUNK_SIZE};
+use flowy_http_model::revision::Revision;
+use lib_ot::core::AttributeHashMap;
+use lib_ot::text_delta::{BuildInTextAttribute, DeltaTextOperationBuilder};
+use std::sync::Arc;
+use std::time::Duration;
+
+const DOC_ID: &str = "chunked_export_doc";
+
+#[tokio::test]
+async fn chunked_export_matches_export_test() {
+    let manager = make_manager(usize::MAX);
+    create_large_document(&manager).await;
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    let legacy = editor.export().await.unwrap().into_bytes();
+    assert!(legacy.len() > 3 * 1024 * 1024);
+
+    let chunked = editor.export_chunked(DOCUMENT_JSON_CHUNK_SIZE).await.unwrap();
+    assert_eq!(chunked.len(), legacy.len());
+    assert!(chunked
+        .chunks()
+        .iter()
+        .all(|chunk| chunk.len() <= DOCUMENT_JSON_CHUNK_SIZE));
+    assert_eq!(chunked.to_vec(), legacy);
+
+    // A buffer size that doesn't divide the chunk size cuts the chunks and the chars anywhere.
+    let (handle, len) = manager.open_document_reader(DOC_ID).await.unwrap();
+    assert_eq!(len, legacy.len());
+    assert_eq!(read_to_end(&manager, handle, 7919), legacy);
+
+    // The reader is closed once it was read to the end.
+    let error = manager.read_document_chunk(handle, &mut [0; 16]).err().unwrap();
+    assert_eq!(error.code, ErrorCode::RecordNotFound.value());
+}
+
+#[tokio::test]
+async fn read_document_content_threshold_test() {
+    let manager = make_manager(usize::MAX);
+    create_large_document(&manager).await;
+    let legacy = manager
+        .open_document_editor(DOC_ID)
+        .await
+        .unwrap()
+        .export()
+        .await
+        .unwrap();
+    match manager.read_document_content(DOC_ID).await.unwrap() {
+        DocumentContent::Inline(json) => assert_eq!(json, legacy),
+        content => panic!("Expected the inline json, but receive: {:?}", content),
+    }
+
+    let manager = make_manager(1024 * 1024);
+    create_large_document(&manager).await;
+    let (handle, len) = match manager.read_document_content(DOC_ID).await.unwrap() {
+        DocumentContent::Streamed { handle, len } => (handle, len),
+        content => panic!("Expected a reader, but receive: {:?}", content),
+    };
+    assert_eq!(len, legacy.len());
+    assert_eq!(
+        read_to_end(&manager, handle, DOCUMENT_JSON_CHUNK_SIZE),
+        legacy.into_bytes()
+    );
+}
+
+#[tokio::test]
+async fn close_document_reader_test() {
+    let manager = make_manager(usize::MAX);
+    create_large_document(&manager).await;
+    let (handle, _) = manager.open_document_reader(DOC_ID).await.unwrap();
+    assert_eq!(manager.read_document_chunk(handle, &mut [0; 16]).unwrap(), 16);
+    assert!(manager.close_document_reader(handle));
+    assert!(!manager.close_document_reader(handle));
+    assert!(manager.read_document_chunk(handle, &mut [0; 16]).is_err());
+}
+
+#[tokio::test]
+async fn abandoned_document_reader_expires_test() {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        document_reader_timeout: Duration::from_millis(200),
+        ..Default::default()
+    };
+    let manager = make_document_manager(Arc::new(DocumentCloudServiceMock()), config);
+    create_large_document(&manager).await;
+    let (abandoned, _) = manager.open_document_reader(DOC_ID).await.unwrap();
+    assert_eq!(manager.read_document_chunk(abandoned, &mut [0; 16]).unwrap(), 16);
+
+    // The reader that isn't read anymore is dropped, the one that is read is kept.
+    let (handle, _) = manager.open_document_reader(DOC_ID).await.unwrap();
+    for _ in 0..4 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(manager.read_document_chunk(handle, &mut [0; 16]).unwrap(), 16);
+    }
+    assert!(manager.read_document_chunk(abandoned, &mut [0; 16]).is_err());
+    assert!(!manager.close_document_reader(abandoned));
+    assert!(manager.close_document_reader(handle));
+}
+
+fn make_manager(streamed_open_threshold: usize) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        streamed_open_threshold,
+        ..Default::default()
+    };
+    make_document_manager(Arc::new(DocumentCloudServiceMock()), config)
+}
+
+/// More than 3MB of lines with multi-byte chars, escaped quotes and alternating attributes.
+async fn create_large_document(manager: &DocumentManager) {
+    let mut bold = AttributeHashMap::new();
+    bold.insert_entry(BuildInTextAttribute::Bold(true));
+    let mut builder = DeltaTextOperationBuilder::new();
+    for i in 0..60_000 {
+        let line = format!("line {} \"Ünïcödé\" 👨‍👩‍👧‍👦 \\ text\n", i);
+        builder = if i % 2 == 0 {
+            builder.insert(&line)
+        } else {
+            builder.insert_with_attributes(&line, bold.clone())
+        };
+    }
+    let operations = builder.build();
+    manager
+        .create_document(
+            DOC_ID,
+            vec![Revision::initial_revision(DOC_ID, Bytes::from(operations.json_str()))],
+        )
+        .await
+        .unwrap();
+}
+
+fn read_to_end(manager: &DocumentManager, handle: i64, buf_len: usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut buf = vec![0; buf_len];
+    loop {
+        let written = manager.read_document_chunk(handle, &mut buf).unwrap();
+        if written == 0 {
+            return bytes;
+        }
+        bytes.extend_from_slice(&buf[..written]);
+    }
+}
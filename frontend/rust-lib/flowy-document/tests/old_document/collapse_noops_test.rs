@@ -0,0 +1,78 @@
+use crate::old_document::mock::{
+    make_delta_document_manager, make_document_manager, open_delta_editor, DocumentCloudServiceMock,
+};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::ErrorCode;
+use flowy_document::DocumentConfig;
+use flowy_http_model::revision::Revision;
+use std::sync::Arc;
+
+const DOC_ID: &str = "collapse_noops_doc";
+
+#[tokio::test]
+async fn collapse_noops_test() {
+    let manager = make_delta_document_manager();
+    // The revisions of a scratch document are acked as they're saved.
+    manager.set_scratch_document(DOC_ID, true);
+    manager.create_document(DOC_ID, revisions(DOC_ID)).await.unwrap();
+    let editor = open_delta_editor(&manager, DOC_ID).await;
+    editor.rev_manager().pin_revision(7).await.unwrap();
+    let json = editor.export().await.unwrap();
+    drop(editor);
+
+    // The revisions 2, 4, 5 and 9 only retain, the pinned revision 7 is kept.
+    assert_eq!(manager.collapse_noops(DOC_ID).await.unwrap(), 4);
+    let editor = open_delta_editor(&manager, DOC_ID).await;
+    assert_eq!(editor.export().await.unwrap(), json);
+    assert_eq!(editor.plain_text(true).await.unwrap(), "hellodear world\n");
+
+    let dot = manager.export_rev_graph(DOC_ID, None).await.unwrap();
+    for rev_id in [2, 4, 5, 9].iter() {
+        assert!(!dot.contains(&format!("  r{} ", rev_id)));
+    }
+    assert!(dot.contains("  r1 -> r3;"));
+    assert!(dot.contains("  r3 -> r6;"));
+    assert!(dot.contains("  r6 -> r7;"));
+    assert!(dot.contains("  r7 -> r8;"));
+    assert!(!dot.contains("missing"));
+
+    // Nothing is left to collapse.
+    assert_eq!(manager.collapse_noops(DOC_ID).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn collapse_noops_keeps_unacked_revisions_test() {
+    let doc_id = "collapse_noops_unacked_doc";
+    let manager = make_delta_document_manager();
+    manager.create_document(doc_id, revisions(doc_id)).await.unwrap();
+    assert_eq!(manager.collapse_noops(doc_id).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn collapse_noops_node_document_test() {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V1,
+        ..Default::default()
+    };
+    let manager = make_document_manager(Arc::new(DocumentCloudServiceMock()), config);
+    let error = manager.collapse_noops(DOC_ID).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::Internal.value());
+}
+
+fn revisions(doc_id: &str) -> Vec<Revision> {
+    vec![
+        (0, 1, r#"[{"insert":"hello world\n"}]"#),
+        (1, 2, r#"[{"retain":12}]"#),
+        (2, 3, r#"[{"retain":5},{"insert":", dear"}]"#),
+        (3, 4, r#"[{"retain":18}]"#),
+        (4, 5, r#"[{"retain":5}]"#),
+        (5, 6, r#"[{"retain":3,"attributes":{"bold":true}}]"#),
+        (6, 7, r#"[{"retain":18}]"#),
+        (7, 8, r#"[{"retain":5},{"delete":2}]"#),
+        (8, 9, r#"[{"retain":16}]"#),
+    ]
+    .into_iter()
+    .map(|(base_rev_id, rev_id, json)| Revision::new(doc_id, base_rev_id, rev_id, Bytes::from(json), ""))
+    .collect()
+}
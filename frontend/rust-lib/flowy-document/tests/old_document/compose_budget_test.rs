@@ -0,0 +1,63 @@
+use crate::old_document::mock::{create_delta_editor, make_delta_document_manager};
+use flowy_sync::util::ComposeResult;
+use std::time::Duration;
+
+const DOC_ID: &str = "compose_budget_doc";
+
+#[tokio::test]
+async fn compose_with_tiny_budget_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"a\n"}]"#).await;
+    editor.insert(1, "b").await.unwrap();
+    let b_rev_id = editor.rev_manager().rev_id();
+    editor.insert(2, "c").await.unwrap();
+    editor.insert(3, "d").await.unwrap();
+
+    // Only the initial revision is composed, the partial document is the document at its rev_id.
+    let (operations, rev_id) = match manager.compose_document_budgeted(DOC_ID, Duration::ZERO).await.unwrap() {
+        ComposeResult::Partial { operations, rev_id } => (operations, rev_id),
+        ComposeResult::Complete(_) => panic!("Expect a partial result"),
+    };
+    assert_eq!(rev_id, 0);
+    assert_eq!(operations.json_str(), r#"[{"insert":"a\n"}]"#);
+
+    let (operations, rev_id) = match manager
+        .continue_compose_budgeted(DOC_ID, operations, rev_id, Duration::ZERO)
+        .await
+        .unwrap()
+    {
+        ComposeResult::Partial { operations, rev_id } => (operations, rev_id),
+        ComposeResult::Complete(_) => panic!("Expect a partial result"),
+    };
+    assert_eq!(rev_id, b_rev_id);
+    assert_eq!(operations.json_str(), r#"[{"insert":"ab\n"}]"#);
+
+    // Each call composes at least one revision until the document is complete.
+    let mut result = ComposeResult::Partial { operations, rev_id };
+    while let ComposeResult::Partial { operations, rev_id } = result {
+        result = manager
+            .continue_compose_budgeted(DOC_ID, operations, rev_id, Duration::ZERO)
+            .await
+            .unwrap();
+    }
+    match result {
+        ComposeResult::Complete(operations) => assert_eq!(operations.json_str(), editor.export().await.unwrap()),
+        ComposeResult::Partial { .. } => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn compose_within_budget_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"a\n"}]"#).await;
+    editor.insert(1, "bc").await.unwrap();
+
+    match manager
+        .compose_document_budgeted(DOC_ID, Duration::from_secs(60))
+        .await
+        .unwrap()
+    {
+        ComposeResult::Complete(operations) => assert_eq!(operations.json_str(), r#"[{"insert":"abc\n"}]"#),
+        ComposeResult::Partial { .. } => panic!("Expect the complete document"),
+    }
+}
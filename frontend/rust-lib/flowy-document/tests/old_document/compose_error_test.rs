@@ -0,0 +1,64 @@
+use crate::old_document::mock::{make_document_manager, DocumentCloudServiceMock};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::FlowyError;
+use flowy_document::{DocumentConfig, DocumentManager};
+use flowy_http_model::revision::Revision;
+use flowy_revision::ComposeErrorObserver;
+use std::sync::{Arc, Mutex};
+
+const DOC_ID: &str = "compose_error_doc";
+
+#[derive(Default)]
+struct ComposeErrorRecorder(Mutex<Vec<(i64, Vec<u8>)>>);
+
+impl ComposeErrorObserver for ComposeErrorRecorder {
+    fn did_fail(&self, rev_id: i64, bytes: &[u8], _error: &FlowyError) {
+        self.0.lock().unwrap().push((rev_id, bytes.to_vec()));
+    }
+}
+
+#[tokio::test]
+async fn compose_error_observer_receives_undecodable_revision_test() {
+    let bad_bytes = b"[{\"insert\":\"12\xff\"}]".to_vec();
+    let recorder = Arc::new(ComposeErrorRecorder::default());
+    let manager = make_manager(recorder.clone());
+    open_with_revisions(
+        &manager,
+        vec![Bytes::from(r#"[{"insert":"\n"}]"#), Bytes::from(bad_bytes.clone())],
+    )
+    .await;
+    assert_eq!(*recorder.0.lock().unwrap(), vec![(2, bad_bytes)]);
+}
+
+#[tokio::test]
+async fn compose_error_observer_receives_uncomposable_revision_test() {
+    // The revision retains more than the length of the document.
+    let bad_bytes = br#"[{"retain":100},{"insert":"1"}]"#.to_vec();
+    let recorder = Arc::new(ComposeErrorRecorder::default());
+    let manager = make_manager(recorder.clone());
+    open_with_revisions(
+        &manager,
+        vec![Bytes::from(r#"[{"insert":"\n"}]"#), Bytes::from(bad_bytes.clone())],
+    )
+    .await;
+    assert_eq!(*recorder.0.lock().unwrap(), vec![(2, bad_bytes)]);
+}
+
+fn make_manager(recorder: Arc<ComposeErrorRecorder>) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        ..Default::default()
+    };
+    make_document_manager(Arc::new(DocumentCloudServiceMock()), config).with_compose_error_observer(recorder)
+}
+
+async fn open_with_revisions(manager: &DocumentManager, data: Vec<Bytes>) {
+    let revisions = data
+        .into_iter()
+        .enumerate()
+        .map(|(index, bytes)| Revision::new(DOC_ID, index as i64, index as i64 + 1, bytes, ""))
+        .collect::<Vec<_>>();
+    manager.create_document(DOC_ID, revisions).await.unwrap();
+    assert!(manager.open_document_editor(DOC_ID).await.is_err());
+}
@@ -0,0 +1,100 @@
+use crate::old_document::mock::{create_delta_editor, make_delta_document_manager};
+use flowy_document::old_editor::editor::DeltaDocumentEditor;
+use lib_ot::core::Interval;
+use lib_ot::text_delta::{content_hash, BuildInTextAttribute, DeltaTextOperations};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::sync::Arc;
+
+const DOC_ID: &str = "content_hash_doc";
+const CONTENT: &str = r#"[{"insert":"abc\n"}]"#;
+
+#[tokio::test]
+async fn content_hash_incremental_equals_from_scratch_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..60 {
+        let len = document(&editor).await.utf16_target_len - 1;
+        let start = rng.gen_range(0..=len);
+        let end = (start + rng.gen_range(0..=4)).min(len);
+        let interval = Interval::new(start, end);
+        match rng.gen_range(0..5) {
+            0 => editor.insert(start, "xyz").await.unwrap(),
+            1 if start < end => editor.delete(interval).await.unwrap(),
+            2 if start < end => editor.format(interval, BuildInTextAttribute::Bold(true)).await.unwrap(),
+            3 if start < end => editor
+                .format(interval, BuildInTextAttribute::Italic(true))
+                .await
+                .unwrap(),
+            4 if start < end => editor
+                .format(interval, BuildInTextAttribute::Bold(false))
+                .await
+                .unwrap(),
+            _ => editor.insert(start, "a").await.unwrap(),
+        }
+
+        let incremental = manager.doc_content_hash(DOC_ID).await.unwrap();
+        assert_eq!(incremental.rev_id, editor.rev_manager().rev_id());
+        assert_eq!(incremental.hash, content_hash(&document(&editor).await));
+    }
+
+    // The closed document is composed from its revisions.
+    let opened = manager.doc_content_hash(DOC_ID).await.unwrap();
+    manager.close_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(manager.doc_content_hash(DOC_ID).await.unwrap(), opened);
+}
+
+#[tokio::test]
+async fn content_hash_formatting_only_change_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    let before = manager.doc_content_hash(DOC_ID).await.unwrap();
+
+    editor
+        .format(Interval::new(0, 3), BuildInTextAttribute::Bold(true))
+        .await
+        .unwrap();
+    let bold = manager.doc_content_hash(DOC_ID).await.unwrap();
+    assert_ne!(bold.hash, before.hash);
+
+    // Removing the formatting brings the document, and its hash, back.
+    editor
+        .format(Interval::new(0, 3), BuildInTextAttribute::Bold(false))
+        .await
+        .unwrap();
+    let after = manager.doc_content_hash(DOC_ID).await.unwrap();
+    assert_eq!(after.hash, before.hash);
+    assert!(after.rev_id > before.rev_id);
+}
+
+#[tokio::test]
+async fn content_hash_batch_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    editor.insert(3, "d").await.unwrap();
+    manager.close_document_editor(DOC_ID).await.unwrap();
+
+    let doc_ids = vec![DOC_ID.to_owned(), "unknown_doc".to_owned()];
+    let content_hashes = manager.doc_content_hashes(&doc_ids).await.unwrap();
+    assert_eq!(content_hashes.len(), 1);
+    assert_eq!(content_hashes[0].doc_id, DOC_ID);
+    let expected = DeltaTextOperations::from_json(r#"[{"insert":"abcd\n"}]"#).unwrap();
+    assert_eq!(content_hashes[0].hash, content_hash(&expected));
+
+    // The saved hash is read back, the opened document hashes its changes.
+    assert_eq!(manager.doc_content_hashes(&doc_ids).await.unwrap(), content_hashes);
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    let editor = editor
+        .as_any()
+        .downcast_ref::<Arc<DeltaDocumentEditor>>()
+        .unwrap()
+        .clone();
+    editor.insert(4, "e").await.unwrap();
+    let content_hashes = manager.doc_content_hashes(&doc_ids).await.unwrap();
+    assert_eq!(content_hashes[0].hash, content_hash(&document(&editor).await));
+    assert_ne!(content_hashes[0].hash, content_hash(&expected));
+}
+
+async fn document(editor: &Arc<DeltaDocumentEditor>) -> DeltaTextOperations {
+    DeltaTextOperations::from_json(&editor.export().await.unwrap()).unwrap()
+}
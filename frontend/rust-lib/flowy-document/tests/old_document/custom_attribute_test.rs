@@ -0,0 +1,165 @@
+use crate::old_document::mock::{create_delta_editor, make_delta_document_manager, make_temp_dir};
+use crate::old_document::reexport_test::{make_manager, ExportTargetMock};
+use bytes::Bytes;
+use flowy_document::errors::ErrorCode;
+use flowy_http_model::revision::Revision;
+use flowy_sync::client_document::ClientDocument;
+use lib_ot::core::{AttributeEntry, Interval};
+use lib_ot::text_delta::{CustomAttributeSemantics, CustomAttributes, DeltaTextOperations};
+use std::sync::Arc;
+
+const DOC_ID: &str = "custom_attribute_doc";
+const CONTENT: &str = r#"[{"insert":"abc\n"}]"#;
+
+#[tokio::test]
+async fn custom_attribute_survives_persist_and_export_test() {
+    let dir = make_temp_dir();
+    let target = Arc::new(ExportTargetMock::default());
+    let manager = make_manager(&dir, 1, target.clone());
+    manager
+        .register_custom_attribute("comment_id", CustomAttributeSemantics::String)
+        .await
+        .unwrap();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    editor
+        .format(Interval::new(0, 3), AttributeEntry::new("comment_id", "c1"))
+        .await
+        .unwrap();
+
+    // Reopening reads the document back from the disk.
+    manager.close_document_editor(DOC_ID).await.unwrap();
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(
+        editor.export().await.unwrap(),
+        r#"[{"insert":"abc","attributes":{"comment_id":"c1"}},{"insert":"\n"}]"#
+    );
+
+    // The markdown exporter keeps the registered attribute.
+    let _ = manager.reexport_documents().await.unwrap();
+    assert_eq!(target.markdown_of(DOC_ID), "<span data-comment_id=\"c1\">abc</span>\n");
+}
+
+#[tokio::test]
+async fn custom_attribute_boolean_test() {
+    let manager = make_delta_document_manager();
+    manager
+        .register_custom_attribute("reviewed", CustomAttributeSemantics::Boolean)
+        .await
+        .unwrap();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    editor
+        .format(Interval::new(0, 3), AttributeEntry::new("reviewed", true))
+        .await
+        .unwrap();
+    editor
+        .format(Interval::new(1, 3), AttributeEntry::new("reviewed", false))
+        .await
+        .unwrap();
+    assert_eq!(
+        editor.export().await.unwrap(),
+        r#"[{"insert":"a","attributes":{"reviewed":true}},{"insert":"bc\n"}]"#
+    );
+
+    // The value doesn't match the semantics of the attribute.
+    assert!(editor
+        .format(Interval::new(0, 3), AttributeEntry::new("reviewed", "yes"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn custom_attribute_registered_per_manager_test() {
+    let manager = make_delta_document_manager();
+    manager
+        .register_custom_attribute("comment_id", CustomAttributeSemantics::String)
+        .await
+        .unwrap();
+    let other_manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    let other_editor = create_delta_editor(&other_manager, DOC_ID, CONTENT).await;
+
+    editor
+        .format(Interval::new(0, 3), AttributeEntry::new("comment_id", "c1"))
+        .await
+        .unwrap();
+    // The other manager didn't register the attribute.
+    assert!(other_editor
+        .format(Interval::new(0, 3), AttributeEntry::new("comment_id", "c1"))
+        .await
+        .is_err());
+    assert_eq!(other_editor.export().await.unwrap(), CONTENT);
+}
+
+#[tokio::test]
+async fn custom_attribute_invalid_stored_value_test() {
+    let manager = make_delta_document_manager();
+    manager
+        .register_custom_attribute("reviewed", CustomAttributeSemantics::Boolean)
+        .await
+        .unwrap();
+    // E.g. the revision was written by a client that registered the attribute differently.
+    let json = r#"[{"insert":"abc","attributes":{"reviewed":"yes"}},{"insert":"\n"}]"#;
+    let revision = Revision::initial_revision(DOC_ID, Bytes::from(json));
+    manager.create_document(DOC_ID, vec![revision]).await.unwrap();
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"abc"},{"insert":"\n"}]"#);
+}
+
+#[test]
+fn custom_attribute_remote_operations_sanitized_test() {
+    let custom_attributes = CustomAttributes::default();
+    custom_attributes
+        .register("reviewed", CustomAttributeSemantics::Boolean)
+        .unwrap();
+    let mut document = ClientDocument::from_json(CONTENT).unwrap();
+    document.set_custom_attributes(custom_attributes);
+
+    let remote_operations = DeltaTextOperations::from_json(
+        r#"[{"retain":1,"attributes":{"reviewed":true}},{"retain":2,"attributes":{"reviewed":"yes"}}]"#,
+    )
+    .unwrap();
+    let composed_operations = document.compose_remote_operations(remote_operations).unwrap();
+    assert_eq!(
+        composed_operations.json_str(),
+        r#"[{"retain":1,"attributes":{"reviewed":true}},{"retain":2}]"#
+    );
+    assert_eq!(
+        document.get_operations_json(),
+        r#"[{"insert":"a","attributes":{"reviewed":true}},{"insert":"bc\n"}]"#
+    );
+}
+
+#[tokio::test]
+async fn register_custom_attribute_after_open_test() {
+    let manager = make_delta_document_manager();
+    let _ = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    let error = manager
+        .register_custom_attribute("late_key", CustomAttributeSemantics::Numeric)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code, ErrorCode::AttributeRegistrationClosed.value());
+}
+
+#[tokio::test]
+async fn register_invalid_custom_attribute_test() {
+    let manager = make_delta_document_manager();
+    // The built-in attributes can't be registered.
+    let error = manager
+        .register_custom_attribute("bold", CustomAttributeSemantics::Boolean)
+        .await
+        .unwrap_err();
+    assert_eq!(error.code, ErrorCode::InvalidAttribute.value());
+
+    manager
+        .register_custom_attribute("word_count", CustomAttributeSemantics::Numeric)
+        .await
+        .unwrap();
+    manager
+        .register_custom_attribute("word_count", CustomAttributeSemantics::Numeric)
+        .await
+        .unwrap();
+    assert!(manager
+        .register_custom_attribute("word_count", CustomAttributeSemantics::String)
+        .await
+        .is_err());
+}
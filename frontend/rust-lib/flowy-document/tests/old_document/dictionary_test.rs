@@ -0,0 +1,138 @@
+use crate::old_document::mock::{make_document_manager, DocumentCloudServiceMock, DocumentServerMock};
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::{DocumentCloudService, DocumentConfig, DocumentManager};
+use std::sync::Arc;
+
+const WORKSPACE_ID: &str = "workspace";
+
+#[tokio::test]
+async fn custom_dictionary_add_and_remove_test() {
+    let manager = make_manager(Arc::new(DocumentCloudServiceMock()));
+    manager.add_custom_dictionary_word(WORKSPACE_ID, "tokio").await.unwrap();
+    manager
+        .add_custom_dictionary_word(WORKSPACE_ID, " serde ")
+        .await
+        .unwrap();
+    manager.add_custom_dictionary_word(WORKSPACE_ID, "tokio").await.unwrap();
+    assert_eq!(manager.custom_dictionary(WORKSPACE_ID).unwrap(), vec!["serde", "tokio"]);
+    assert!(manager.custom_dictionary("other_workspace").unwrap().is_empty());
+
+    manager
+        .remove_custom_dictionary_word(WORKSPACE_ID, "tokio")
+        .await
+        .unwrap();
+    assert_eq!(manager.custom_dictionary(WORKSPACE_ID).unwrap(), vec!["serde"]);
+
+    assert!(manager
+        .add_custom_dictionary_word(WORKSPACE_ID, "two words")
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn custom_dictionary_export_and_import_test() {
+    let manager = make_manager(Arc::new(DocumentCloudServiceMock()));
+    manager.add_custom_dictionary_word(WORKSPACE_ID, "tokio").await.unwrap();
+    manager.add_custom_dictionary_word(WORKSPACE_ID, "serde").await.unwrap();
+    let words = manager.custom_dictionary(WORKSPACE_ID).unwrap();
+
+    let other_manager = make_manager(Arc::new(DocumentCloudServiceMock()));
+    other_manager
+        .add_custom_dictionary_word(WORKSPACE_ID, "tokio")
+        .await
+        .unwrap();
+    assert_eq!(
+        other_manager
+            .import_custom_dictionary(WORKSPACE_ID, &words)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        other_manager.custom_dictionary(WORKSPACE_ID).unwrap(),
+        vec!["serde", "tokio"]
+    );
+}
+
+#[tokio::test]
+async fn custom_dictionary_merge_offline_devices_test() {
+    let server = Arc::new(DocumentServerMock::default());
+    let device_a = make_manager(server.clone());
+    let device_b = make_manager(server.clone());
+
+    // Both devices add a word while they're offline.
+    device_a
+        .add_custom_dictionary_word(WORKSPACE_ID, "tokio")
+        .await
+        .unwrap();
+    device_b
+        .add_custom_dictionary_word(WORKSPACE_ID, "serde")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        device_a.sync_custom_dictionary(WORKSPACE_ID).await.unwrap(),
+        vec!["tokio"]
+    );
+    // The second device merges its word with the one on the server.
+    assert_eq!(
+        device_b.sync_custom_dictionary(WORKSPACE_ID).await.unwrap(),
+        vec!["serde", "tokio"]
+    );
+    assert_eq!(
+        device_a.sync_custom_dictionary(WORKSPACE_ID).await.unwrap(),
+        vec!["serde", "tokio"]
+    );
+
+    assert_eq!(
+        device_a.custom_dictionary(WORKSPACE_ID).unwrap(),
+        vec!["serde", "tokio"]
+    );
+    assert_eq!(
+        device_b.custom_dictionary(WORKSPACE_ID).unwrap(),
+        vec!["serde", "tokio"]
+    );
+}
+
+#[tokio::test]
+async fn custom_dictionary_merge_removed_word_test() {
+    let server = Arc::new(DocumentServerMock::default());
+    let device_a = make_manager(server.clone());
+    let device_b = make_manager(server.clone());
+    device_a
+        .add_custom_dictionary_word(WORKSPACE_ID, "tokio")
+        .await
+        .unwrap();
+    device_a.sync_custom_dictionary(WORKSPACE_ID).await.unwrap();
+    device_b.sync_custom_dictionary(WORKSPACE_ID).await.unwrap();
+
+    // The first device removes the word while the second one adds the same word and another.
+    device_a
+        .remove_custom_dictionary_word(WORKSPACE_ID, "tokio")
+        .await
+        .unwrap();
+    device_b
+        .add_custom_dictionary_word(WORKSPACE_ID, "serde")
+        .await
+        .unwrap();
+    assert_eq!(
+        device_a.sync_custom_dictionary(WORKSPACE_ID).await.unwrap(),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        device_b.sync_custom_dictionary(WORKSPACE_ID).await.unwrap(),
+        vec!["serde"]
+    );
+    assert_eq!(
+        device_a.sync_custom_dictionary(WORKSPACE_ID).await.unwrap(),
+        vec!["serde"]
+    );
+}
+
+fn make_manager(cloud_service: Arc<dyn DocumentCloudService>) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        ..Default::default()
+    };
+    make_document_manager(cloud_service, config)
+}
@@ -0,0 +1,145 @@
+use crate::old_document::mock::{make_document_manager, DocumentCloudServiceMock, DocumentServerMock};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::{doc_preferences_doc_id, DocumentCloudService, DocumentConfig, DocumentManager};
+use flowy_http_model::revision::Revision;
+use lib_ot::text_delta::DeltaTextOperationBuilder;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DOC_ID: &str = "doc_preference_doc";
+
+#[tokio::test]
+async fn doc_preference_set_and_get_test() {
+    let manager = make_manager(Arc::new(DocumentCloudServiceMock()));
+    let operations = DeltaTextOperationBuilder::new().insert("hello world\n").build();
+    manager
+        .create_document(
+            DOC_ID,
+            vec![Revision::initial_revision(DOC_ID, Bytes::from(operations.json_str()))],
+        )
+        .await
+        .unwrap();
+    let rev_id = manager.document_meta(DOC_ID).await.unwrap().rev_id;
+
+    manager.set_doc_preference(DOC_ID, "font_size", &14).await.unwrap();
+    manager.set_doc_preference(DOC_ID, "font_size", &16).await.unwrap();
+    manager.set_doc_preference(DOC_ID, "width", &"wide").await.unwrap();
+    manager.set_doc_preference(DOC_ID, "focus_mode", &true).await.unwrap();
+    assert_eq!(
+        manager.get_doc_preference::<i32>(DOC_ID, "font_size").unwrap(),
+        Some(16)
+    );
+    assert_eq!(
+        manager.get_doc_preference::<String>(DOC_ID, "width").unwrap(),
+        Some("wide".to_owned())
+    );
+    assert_eq!(
+        manager.get_doc_preference::<bool>(DOC_ID, "focus_mode").unwrap(),
+        Some(true)
+    );
+    assert_eq!(manager.get_doc_preference::<i32>(DOC_ID, "zoom").unwrap(), None);
+    assert!(manager
+        .set_doc_preference_json(DOC_ID, "zoom", "not json".to_owned())
+        .await
+        .is_err());
+
+    // The preferences are in the meta of the document, the document has no new revision.
+    let meta = manager.document_meta(DOC_ID).await.unwrap();
+    assert_eq!(meta.rev_id, rev_id);
+    assert_eq!(meta.preferences.len(), 3);
+    assert_eq!(meta.preferences["font_size"], "16");
+}
+
+#[tokio::test]
+async fn doc_preference_set_same_value_test() {
+    let manager = make_manager(Arc::new(DocumentCloudServiceMock()));
+    let preferences_doc_id = doc_preferences_doc_id(DOC_ID);
+    manager.set_doc_preference(DOC_ID, "font_size", &14).await.unwrap();
+    manager.set_doc_preference(DOC_ID, "width", &"wide").await.unwrap();
+    let rev_id = manager.document_meta(&preferences_doc_id).await.unwrap().rev_id;
+
+    // Setting a value adds one revision, setting the same value again adds none.
+    manager.set_doc_preference(DOC_ID, "font_size", &16).await.unwrap();
+    assert_eq!(
+        manager.document_meta(&preferences_doc_id).await.unwrap().rev_id,
+        rev_id + 1
+    );
+    manager.set_doc_preference(DOC_ID, "font_size", &16).await.unwrap();
+    manager.set_doc_preference(DOC_ID, "width", &"wide").await.unwrap();
+    assert_eq!(
+        manager.document_meta(&preferences_doc_id).await.unwrap().rev_id,
+        rev_id + 1
+    );
+    assert_eq!(manager.doc_preferences(DOC_ID).unwrap().len(), 2);
+    assert_eq!(
+        manager.get_doc_preference::<i32>(DOC_ID, "font_size").unwrap(),
+        Some(16)
+    );
+}
+
+#[tokio::test]
+async fn doc_preference_merge_offline_devices_test() {
+    // The devices sync in either order, they end up with the same preferences.
+    for device_a_syncs_first in [true, false] {
+        let server = Arc::new(DocumentServerMock::default());
+        let device_a = make_manager(server.clone());
+        let device_b = make_manager(server.clone());
+
+        // Both devices set the font size while they're offline, the second device sets it last.
+        device_a.set_doc_preference(DOC_ID, "font_size", &14).await.unwrap();
+        device_a.set_doc_preference(DOC_ID, "width", &"wide").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        device_b.set_doc_preference(DOC_ID, "font_size", &18).await.unwrap();
+        device_b.set_doc_preference(DOC_ID, "focus_mode", &true).await.unwrap();
+
+        let (first, second) = if device_a_syncs_first {
+            (&device_a, &device_b)
+        } else {
+            (&device_b, &device_a)
+        };
+        let _ = first.sync_doc_preferences(DOC_ID).await.unwrap();
+        let merged = second.sync_doc_preferences(DOC_ID).await.unwrap();
+        assert_eq!(first.sync_doc_preferences(DOC_ID).await.unwrap(), merged);
+
+        for device in [&device_a, &device_b] {
+            assert_eq!(device.doc_preferences(DOC_ID).unwrap(), merged);
+            assert_eq!(device.get_doc_preference::<i32>(DOC_ID, "font_size").unwrap(), Some(18));
+            assert_eq!(
+                device.get_doc_preference::<String>(DOC_ID, "width").unwrap(),
+                Some("wide".to_owned())
+            );
+            assert_eq!(
+                device.get_doc_preference::<bool>(DOC_ID, "focus_mode").unwrap(),
+                Some(true)
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn doc_preference_set_after_merge_test() {
+    let server = Arc::new(DocumentServerMock::default());
+    let device_a = make_manager(server.clone());
+    let device_b = make_manager(server.clone());
+    device_a.set_doc_preference(DOC_ID, "font_size", &14).await.unwrap();
+    let _ = device_a.sync_doc_preferences(DOC_ID).await.unwrap();
+    let _ = device_b.sync_doc_preferences(DOC_ID).await.unwrap();
+
+    // The value set after the merge wins over the merged one, and replaces it on the other
+    // device with the next sync.
+    device_b.set_doc_preference(DOC_ID, "font_size", &20).await.unwrap();
+    let _ = device_b.sync_doc_preferences(DOC_ID).await.unwrap();
+    let _ = device_a.sync_doc_preferences(DOC_ID).await.unwrap();
+    for device in [&device_a, &device_b] {
+        assert_eq!(device.get_doc_preference::<i32>(DOC_ID, "font_size").unwrap(), Some(20));
+    }
+}
+
+fn make_manager(cloud_service: Arc<dyn DocumentCloudService>) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        ..Default::default()
+    };
+    make_document_manager(cloud_service, config)
+}
@@ -0,0 +1,91 @@
+use crate::old_document::mock::{create_delta_editor, make_delta_document_manager};
+use bytes::Bytes;
+use flowy_http_model::revision::Revision;
+use flowy_http_model::util::md5;
+use flowy_sync::util::explain_transform;
+use lib_ot::core::{AttributeHashMap, TransformAction};
+
+const DOC_ID: &str = "explain_transform_doc";
+
+#[tokio::test]
+async fn explain_transform_concurrent_insert_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"a\n"}]"#).await;
+    editor.insert(1, "x").await.unwrap();
+    let local_rev_id = editor.rev_manager().rev_id();
+    editor.insert(1, "y").await.unwrap();
+    let remote_rev_id = editor.rev_manager().rev_id();
+
+    // The remote revision is rebased, so both revisions insert at the same index.
+    let explanation = manager
+        .explain_transform(DOC_ID, local_rev_id, remote_rev_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        explanation.local.json_str(),
+        r#"[{"retain":1},{"insert":"x"},{"retain":1}]"#
+    );
+    assert_eq!(
+        explanation.remote.json_str(),
+        r#"[{"retain":1},{"insert":"y"},{"retain":1}]"#
+    );
+
+    let local_first = explanation.local_first;
+    assert_eq!(
+        local_first
+            .steps
+            .iter()
+            .map(|step| step.to_string())
+            .collect::<Vec<String>>(),
+        vec![
+            "{retain: 1} | {retain: 1} => RetainBoth 1 (advance Both)",
+            "{insert: x} | {insert: y} => InsertLeft 1 (advance Left)",
+            "{retain: 1} | {insert: y} => InsertRight 1 (advance Right)",
+            "{retain: 1} | {retain: 1} => RetainBoth 1 (advance Both)",
+        ]
+    );
+    assert_eq!(
+        local_first.left_prime.json_str(),
+        r#"[{"retain":1},{"insert":"x"},{"retain":2}]"#
+    );
+    assert_eq!(
+        local_first.right_prime.json_str(),
+        r#"[{"retain":2},{"insert":"y"},{"retain":1}]"#
+    );
+
+    // The remote insert goes first when the remote revision is the left delta.
+    let remote_first = explanation.remote_first;
+    assert_eq!(remote_first.steps[1].action, TransformAction::InsertLeft);
+    assert_eq!(remote_first.steps[1].left.as_deref(), Some("{insert: y}"));
+
+    // The document itself doesn't change.
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"ayx\n"}]"#);
+}
+
+#[tokio::test]
+async fn explain_transform_unknown_revision_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"a\n"}]"#).await;
+    editor.insert(1, "x").await.unwrap();
+    let rev_id = editor.rev_manager().rev_id();
+    let error = manager.explain_transform(DOC_ID, rev_id, rev_id + 1).await.unwrap_err();
+    assert!(error.msg.contains("There is no revision"));
+}
+
+#[test]
+fn explain_transform_compacted_revision_test() {
+    // The revision 1 was merged into the revision 2.
+    let revisions = vec![
+        make_revision(0, r#"[{"insert":"a\n"}]"#),
+        make_revision(2, r#"[{"retain":1},{"insert":"xy"}]"#),
+        make_revision(3, r#"[{"retain":3},{"insert":"z"}]"#),
+    ];
+    let error = explain_transform::<AttributeHashMap>(revisions, 1, 3).unwrap_err();
+    assert!(error.msg.contains("has been compacted away"));
+}
+
+fn make_revision(rev_id: i64, json: &str) -> Revision {
+    let bytes = Bytes::from(json.to_owned());
+    let md5 = md5(&bytes);
+    Revision::new(DOC_ID, rev_id - 1, rev_id, bytes, md5)
+}
@@ -0,0 +1,154 @@
+use crate::old_document::mock::make_document_manager;
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::FlowyError;
+use flowy_document::{DocumentCloudService, DocumentConfig, DocumentEditor, DocumentFetchGuard, DocumentManager};
+use flowy_http_model::document::{CreateDocumentParams, DocumentId, DocumentPayload, ResetDocumentParams};
+use flowy_http_model::revision::Revision;
+use futures::future::join_all;
+use lib_infra::future::FutureResult;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::time::{sleep, Duration};
+
+const DOC_ID: &str = "fetch_guard_doc";
+const SERVER_DOCUMENT: &str = r#"[{"insert":"1234\n"}]"#;
+
+#[tokio::test]
+async fn fetch_guard_concurrent_hydrations_share_one_fetch_test() {
+    let cloud_service = Arc::new(GatedDocumentCloudService::new(false));
+    let manager = make_manager(cloud_service.clone(), true).await;
+    let results = fetch_concurrently(&manager, &cloud_service).await;
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert_eq!(cloud_service.num_of_fetches(), 1);
+
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(editor.export().await.unwrap(), SERVER_DOCUMENT);
+}
+
+#[tokio::test]
+async fn fetch_guard_concurrent_hydrations_share_one_error_test() {
+    let cloud_service = Arc::new(GatedDocumentCloudService::new(true));
+    let manager = make_manager(cloud_service.clone(), true).await;
+    let results = fetch_concurrently(&manager, &cloud_service).await;
+    assert!(results.iter().all(|result| result.is_err()));
+    assert_eq!(cloud_service.num_of_fetches(), 1);
+}
+
+#[tokio::test]
+async fn fetch_guard_disabled_test() {
+    let cloud_service = Arc::new(GatedDocumentCloudService::new(false));
+    let manager = make_manager(cloud_service.clone(), false).await;
+    let _ = fetch_concurrently(&manager, &cloud_service).await;
+    assert_eq!(cloud_service.num_of_fetches(), 10);
+}
+
+#[tokio::test]
+async fn fetch_guard_fetches_again_after_completed_test() {
+    let cloud_service = Arc::new(GatedDocumentCloudService::new(false));
+    cloud_service.respond();
+    let guard = Arc::new(DocumentFetchGuard::new());
+    let guarded = guard.guard(cloud_service.clone());
+    for _ in 0..2 {
+        let payload = guarded.fetch_document("", DOC_ID.to_owned().into()).await.unwrap();
+        assert_eq!(payload.unwrap().rev_id, 5);
+    }
+    assert_eq!(cloud_service.num_of_fetches(), 2);
+}
+
+#[tokio::test]
+async fn fetch_guard_cancelled_fetch_test() {
+    let cloud_service = Arc::new(GatedDocumentCloudService::new(false));
+    let guard = Arc::new(DocumentFetchGuard::new());
+    let guarded = guard.guard(cloud_service.clone());
+    let fetch = guarded.fetch_document("", DOC_ID.to_owned().into());
+    assert!(tokio::time::timeout(Duration::from_millis(50), fetch).await.is_err());
+
+    // Nothing waits on the cancelled fetch, so the next one starts over.
+    assert_eq!(guard.num_in_flight(), 0);
+    cloud_service.respond();
+    let payload = guarded.fetch_document("", DOC_ID.to_owned().into()).await.unwrap();
+    assert_eq!(payload.unwrap().rev_id, 5);
+    assert_eq!(cloud_service.num_of_fetches(), 2);
+    assert_eq!(guard.num_in_flight(), 0);
+}
+
+async fn make_manager(cloud_service: Arc<GatedDocumentCloudService>, single_flight_fetch: bool) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        single_flight_fetch,
+        ..Default::default()
+    };
+    let manager = make_document_manager(cloud_service, config);
+    let revision = Revision::new(DOC_ID, 0, 1, Bytes::from(r#"[{"insert":"123\n"}]"#), "");
+    manager.create_document(DOC_ID, vec![revision]).await.unwrap();
+    manager
+}
+
+/// Hydrates the document ten times at once, the server responds once all of them are waiting.
+async fn fetch_concurrently(
+    manager: &DocumentManager,
+    cloud_service: &GatedDocumentCloudService,
+) -> Vec<Result<(), FlowyError>> {
+    let hydrations = join_all((0..10).map(|_| manager.hydrate_document(DOC_ID)));
+    let respond = async {
+        sleep(Duration::from_millis(100)).await;
+        cloud_service.respond();
+    };
+    let (results, _) = tokio::join!(hydrations, respond);
+    results
+}
+
+/// Holds each fetch until `respond` is called and counts the fetches that reach it.
+struct GatedDocumentCloudService {
+    gate: Arc<Semaphore>,
+    num_of_fetches: Arc<AtomicUsize>,
+    fails: bool,
+}
+
+impl GatedDocumentCloudService {
+    fn new(fails: bool) -> Self {
+        Self {
+            gate: Arc::new(Semaphore::new(0)),
+            num_of_fetches: Arc::new(AtomicUsize::new(0)),
+            fails,
+        }
+    }
+
+    fn respond(&self) {
+        self.gate.add_permits(100);
+    }
+
+    fn num_of_fetches(&self) -> usize {
+        self.num_of_fetches.load(Ordering::SeqCst)
+    }
+}
+
+impl DocumentCloudService for GatedDocumentCloudService {
+    fn create_document(&self, _token: &str, _params: CreateDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    fn fetch_document(&self, _token: &str, params: DocumentId) -> FutureResult<Option<DocumentPayload>, FlowyError> {
+        self.num_of_fetches.fetch_add(1, Ordering::SeqCst);
+        let gate = self.gate.clone();
+        let fails = self.fails;
+        FutureResult::new(async move {
+            let _permit = gate.acquire().await.unwrap();
+            if fails {
+                return Err(FlowyError::connection());
+            }
+            Ok(Some(DocumentPayload {
+                doc_id: params.value,
+                data: SERVER_DOCUMENT.as_bytes().to_vec(),
+                rev_id: 5,
+                base_rev_id: 4,
+            }))
+        })
+    }
+
+    fn update_document_content(&self, _token: &str, _params: ResetDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+}
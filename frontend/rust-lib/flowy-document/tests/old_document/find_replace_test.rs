@@ -0,0 +1,177 @@
+use crate::old_document::mock::{make_delta_document_manager, open_delta_editor};
+use bytes::Bytes;
+use flowy_document::{
+    doc_preferences_doc_id, DocumentManager, DocumentScopeResolver, FindReplaceOutcome, FindReplaceQuery,
+    FindReplaceScope, FindReplaceSkip,
+};
+use flowy_error::FlowyError;
+use flowy_http_model::revision::Revision;
+use lib_infra::future::FutureResult;
+use lib_ot::core::{AttributeHashMap, Interval};
+use lib_ot::text_delta::{BuildInTextAttribute, DeltaTextOperationBuilder, DeltaTextOperations, FindOptions};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn find_replace_multi_match_lines_test() {
+    let manager = make_delta_document_manager();
+    create_document(&manager, "doc_1", r#"[{"insert":"Foo bar foo\nfood foo\n"}]"#).await;
+    create_document(&manager, "doc_2", r#"[{"insert":"nothing here\n"}]"#).await;
+
+    let query = FindReplaceQuery {
+        pattern: "foo".to_owned(),
+        replacement: "baz".to_owned(),
+        options: FindOptions {
+            case_sensitive: false,
+            whole_word: true,
+        },
+    };
+    let preview = manager.find_replace(FindReplaceScope::All, query).await.unwrap();
+    assert_eq!(preview.documents.len(), 1);
+    let document = &preview.documents[0];
+    assert_eq!(document.doc_id, "doc_1");
+    assert_eq!(document.num_of_matches(), 3);
+    let intervals = document
+        .snippets
+        .iter()
+        .map(|snippet| snippet.interval)
+        .collect::<Vec<Interval>>();
+    assert_eq!(
+        intervals,
+        vec![Interval::new(0, 3), Interval::new(8, 11), Interval::new(17, 20)]
+    );
+    assert_eq!(document.skip, None);
+
+    let report = manager.apply_find_replace(&preview).await.unwrap();
+    assert_eq!(report.outcome("doc_1"), Some(&FindReplaceOutcome::Replaced(3)));
+    let editor = open_delta_editor(&manager, "doc_1").await;
+    assert_eq!(
+        editor.export().await.unwrap(),
+        r#"[{"insert":"baz bar baz\nfood baz\n"}]"#
+    );
+    // All the matches of the document are replaced with one revision.
+    assert_eq!(editor.rev_manager().rev_id(), 2);
+}
+
+#[tokio::test]
+async fn find_replace_across_attributes_test() {
+    let manager = make_delta_document_manager();
+    let operations = DeltaTextOperationBuilder::new()
+        .insert("say ")
+        .insert_with_attributes("hel", AttributeHashMap::from(BuildInTextAttribute::Bold(true)))
+        .insert("lo world\n")
+        .build();
+    create_document(&manager, "doc_1", &operations.json_str()).await;
+
+    let query = FindReplaceQuery {
+        pattern: "hello".to_owned(),
+        replacement: "bye".to_owned(),
+        options: FindOptions::default(),
+    };
+    let preview = manager
+        .find_replace(FindReplaceScope::DocIds(vec!["doc_1".to_owned()]), query)
+        .await
+        .unwrap();
+    assert_eq!(preview.documents[0].snippets[0].interval, Interval::new(4, 9));
+    assert_eq!(preview.documents[0].snippets[0].text, "say hello world");
+
+    let report = manager.apply_find_replace(&preview).await.unwrap();
+    assert_eq!(report.num_of_replaced(), 1);
+    // The replacement takes the attributes of the first char of the match.
+    let editor = open_delta_editor(&manager, "doc_1").await;
+    assert_eq!(
+        editor.export().await.unwrap(),
+        r#"[{"insert":"say "},{"insert":"bye","attributes":{"bold":true}},{"insert":" world\n"}]"#
+    );
+}
+
+#[tokio::test]
+async fn find_replace_skips_stale_document_test() {
+    let manager = make_delta_document_manager();
+    create_document(&manager, "doc_1", r#"[{"insert":"one foo\n"}]"#).await;
+    create_document(&manager, "doc_2", r#"[{"insert":"two foo\n"}]"#).await;
+    let query = FindReplaceQuery {
+        pattern: "foo".to_owned(),
+        replacement: "bar".to_owned(),
+        options: FindOptions::default(),
+    };
+    let preview = manager.find_replace(FindReplaceScope::All, query).await.unwrap();
+    assert_eq!(preview.num_of_matches(), 2);
+
+    // The document is edited after the preview.
+    let editor = open_delta_editor(&manager, "doc_2").await;
+    editor.insert(0, "new ").await.unwrap();
+
+    let report = manager.apply_find_replace(&preview).await.unwrap();
+    assert_eq!(report.outcome("doc_1"), Some(&FindReplaceOutcome::Replaced(1)));
+    assert_eq!(report.stale_doc_ids(), vec!["doc_2".to_owned()]);
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"new two foo\n"}]"#);
+
+    // Running the apply again, e.g. after it was interrupted, doesn't replace twice.
+    let report = manager.apply_find_replace(&preview).await.unwrap();
+    assert_eq!(report.outcome("doc_1"), Some(&FindReplaceOutcome::AlreadyReplaced));
+    assert_eq!(report.outcome("doc_2"), Some(&FindReplaceOutcome::Stale));
+    let editor = open_delta_editor(&manager, "doc_1").await;
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"one bar\n"}]"#);
+    assert_eq!(editor.rev_manager().rev_id(), 2);
+}
+
+#[tokio::test]
+async fn find_replace_skips_read_only_document_test() {
+    let manager = make_delta_document_manager();
+    let preferences_doc_id = doc_preferences_doc_id("doc_1");
+    create_document(&manager, &preferences_doc_id, r#"[{"insert":"{\"key\":\"foo\"}\n"}]"#).await;
+    let query = FindReplaceQuery {
+        pattern: "foo".to_owned(),
+        replacement: "bar".to_owned(),
+        options: FindOptions::default(),
+    };
+    let preview = manager.find_replace(FindReplaceScope::All, query).await.unwrap();
+    assert_eq!(preview.documents[0].skip, Some(FindReplaceSkip::ReadOnly));
+
+    let report = manager.apply_find_replace(&preview).await.unwrap();
+    assert_eq!(
+        report.outcome(&preferences_doc_id),
+        Some(&FindReplaceOutcome::Skipped(FindReplaceSkip::ReadOnly))
+    );
+    assert_eq!(report.num_of_replaced(), 0);
+}
+
+#[tokio::test]
+async fn find_replace_workspace_scope_test() {
+    let manager = make_delta_document_manager();
+    manager.set_scope_resolver(Arc::new(ScopeResolverMock())).await;
+    create_document(&manager, "doc_1", r#"[{"insert":"foo\n"}]"#).await;
+    create_document(&manager, "doc_2", r#"[{"insert":"foo\n"}]"#).await;
+    let query = FindReplaceQuery {
+        pattern: "foo".to_owned(),
+        replacement: "bar".to_owned(),
+        options: FindOptions::default(),
+    };
+    let preview = manager
+        .find_replace(FindReplaceScope::Workspace("workspace_1".to_owned()), query)
+        .await
+        .unwrap();
+    let doc_ids = preview
+        .documents
+        .iter()
+        .map(|document| document.doc_id.clone())
+        .collect::<Vec<String>>();
+    assert_eq!(doc_ids, vec!["doc_1".to_owned()]);
+}
+
+struct ScopeResolverMock();
+impl DocumentScopeResolver for ScopeResolverMock {
+    fn workspace_doc_ids(&self, _workspace_id: &str) -> FutureResult<Vec<String>, FlowyError> {
+        FutureResult::new(async { Ok(vec!["doc_1".to_owned()]) })
+    }
+
+    fn app_doc_ids(&self, _app_id: &str) -> FutureResult<Vec<String>, FlowyError> {
+        FutureResult::new(async { Ok(vec![]) })
+    }
+}
+
+async fn create_document(manager: &DocumentManager, doc_id: &str, json: &str) {
+    let operations = DeltaTextOperations::from_json(json).unwrap();
+    let revision = Revision::initial_revision(doc_id, Bytes::from(operations.json_str()));
+    manager.create_document(doc_id, vec![revision]).await.unwrap();
+}
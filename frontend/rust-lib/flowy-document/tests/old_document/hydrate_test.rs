@@ -0,0 +1,122 @@
+use crate::old_document::mock::{
+    chunk_letter, make_document_manager, StreamingDocumentCloudService, CHUNK_LEN, NUM_OF_CHUNKS,
+};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::old_editor::editor::DeltaDocumentEditor;
+use flowy_document::{DocumentConfig, DocumentEditor, DocumentManager, FetchOverwritePolicy};
+use flowy_http_model::revision::Revision;
+use lib_ot::text_delta::DeltaTextOperations;
+use std::sync::Arc;
+
+const DOC_ID: &str = "hydrate_doc";
+const LOCAL_DOCUMENT: &str = r#"[{"insert":"123\n"}]"#;
+
+#[tokio::test]
+async fn hydrate_document_without_streaming_test() {
+    let manager = make_manager(StreamingDocumentCloudService {
+        streaming: false,
+        ..StreamingDocumentCloudService::new()
+    })
+    .await;
+    manager.hydrate_document(DOC_ID).await.unwrap();
+    assert_eq!(document_content(&manager).await, "1234\n");
+}
+
+#[tokio::test]
+async fn hydrate_document_changed_while_streaming_test() {
+    let manager = make_manager(StreamingDocumentCloudService {
+        changed_at_chunk: Some(3),
+        ..StreamingDocumentCloudService::new()
+    })
+    .await;
+    assert!(manager.hydrate_document(DOC_ID).await.is_err());
+
+    // The local document is kept.
+    assert_eq!(document_content(&manager).await, "123\n");
+}
+
+#[tokio::test]
+async fn hydrate_opened_document_test() {
+    let manager = make_manager(StreamingDocumentCloudService::new()).await;
+    let _editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    assert!(manager.hydrate_document(DOC_ID).await.is_err());
+}
+
+#[tokio::test]
+async fn hydrate_preserve_unsynced_document_test() {
+    let manager = make_manager_with_policy(StreamingDocumentCloudService::new(), FetchOverwritePolicy::Preserve).await;
+    insert_unsynced(&manager, "0").await;
+    assert!(manager.hydrate_document(DOC_ID).await.is_err());
+    assert_eq!(document_content(&manager).await, "0123\n");
+}
+
+#[tokio::test]
+async fn hydrate_merge_unsynced_document_test() {
+    let manager = make_manager_with_policy(StreamingDocumentCloudService::new(), FetchOverwritePolicy::Merge).await;
+    insert_unsynced(&manager, "0").await;
+    manager.hydrate_document(DOC_ID).await.unwrap();
+    // The local change is rebased onto the server's document.
+    assert_eq!(document_content(&manager).await, "01234\n");
+}
+
+#[tokio::test]
+async fn open_document_streamed_from_server_test() {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        ..Default::default()
+    };
+    let manager = make_document_manager(Arc::new(StreamingDocumentCloudService::new()), config);
+    // The document isn't on the disk, it's streamed instead of fetched whole.
+    let content = document_content(&manager).await;
+    assert_eq!(content.len(), CHUNK_LEN * NUM_OF_CHUNKS + 1);
+    for (chunk_index, chunk) in content.trim_end_matches('\n').as_bytes().chunks(CHUNK_LEN).enumerate() {
+        assert!(chunk.iter().all(|c| *c == chunk_letter(chunk_index) as u8));
+    }
+}
+
+#[tokio::test]
+async fn hydrate_overwrite_unsynced_document_test() {
+    let manager = make_manager_with_policy(StreamingDocumentCloudService::new(), FetchOverwritePolicy::Overwrite).await;
+    insert_unsynced(&manager, "0").await;
+    manager.hydrate_document(DOC_ID).await.unwrap();
+    assert_eq!(document_content(&manager).await.len(), CHUNK_LEN * NUM_OF_CHUNKS + 1);
+}
+
+async fn make_manager(cloud_service: StreamingDocumentCloudService) -> DocumentManager {
+    make_manager_with_policy(cloud_service, FetchOverwritePolicy::default()).await
+}
+
+async fn make_manager_with_policy(
+    cloud_service: StreamingDocumentCloudService,
+    fetch_overwrite_policy: FetchOverwritePolicy,
+) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        fetch_overwrite_policy,
+        ..Default::default()
+    };
+    let manager = make_document_manager(Arc::new(cloud_service), config);
+    let revision = Revision::new(DOC_ID, 0, 1, Bytes::from(LOCAL_DOCUMENT), "");
+    manager.create_document(DOC_ID, vec![revision]).await.unwrap();
+    manager
+}
+
+/// Inserts the `text` at the beginning of the document, the revision isn't synced.
+async fn insert_unsynced(manager: &DocumentManager, text: &str) {
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    let editor = editor
+        .as_any()
+        .downcast_ref::<Arc<DeltaDocumentEditor>>()
+        .unwrap()
+        .clone();
+    editor.insert(0, text).await.unwrap();
+    manager.close_document_editor(DOC_ID).await.unwrap();
+}
+
+async fn document_content(manager: &DocumentManager) -> String {
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    let json = editor.export().await.unwrap();
+    manager.close_document_editor(DOC_ID).await.unwrap();
+    DeltaTextOperations::from_json(&json).unwrap().content().unwrap()
+}
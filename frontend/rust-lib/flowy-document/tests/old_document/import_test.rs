@@ -0,0 +1,35 @@
+use crate::old_document::mock::{make_document_manager, DocumentCloudServiceMock};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::DocumentConfig;
+use flowy_http_model::revision::Revision;
+use std::sync::Arc;
+
+const DOC_ID: &str = "imported_doc";
+
+#[tokio::test]
+async fn import_normalize_line_endings_test() {
+    let json = import_document(true, r#"[{"insert":"123\r\n456\r789\n"}]"#).await;
+    assert_eq!(json, r#"[{"insert":"123\n456\n789\n"}]"#);
+}
+
+#[tokio::test]
+async fn import_keep_line_endings_test() {
+    let json = import_document(false, r#"[{"insert":"123\r\n456\n"}]"#).await;
+    assert_eq!(json, r#"[{"insert":"123\r\n456\n"}]"#);
+}
+
+async fn import_document(normalize_line_endings: bool, json: &'static str) -> String {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        normalize_line_endings,
+        ..Default::default()
+    };
+    let manager = make_document_manager(Arc::new(DocumentCloudServiceMock()), config);
+    manager
+        .create_document(DOC_ID, vec![Revision::initial_revision(DOC_ID, Bytes::from(json))])
+        .await
+        .unwrap();
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    editor.export().await.unwrap()
+}
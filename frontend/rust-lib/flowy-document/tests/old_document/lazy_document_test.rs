@@ -0,0 +1,88 @@
+use crate::old_document::mock::make_delta_document_manager;
+use bytes::Bytes;
+use flowy_http_model::revision::Revision;
+use flowy_sync::util::make_operations_from_revisions;
+use lib_ot::core::{AttributeHashMap, Interval};
+use lib_ot::text_delta::{BuildInTextAttribute, DeltaTextOperationBuilder, DeltaTextOperations};
+
+const DOC_ID: &str = "lazy_document_doc";
+
+#[tokio::test]
+async fn lazy_document_regions_match_full_compose_test() {
+    let manager = make_delta_document_manager();
+    let revisions = large_document_revisions();
+    let full: DeltaTextOperations = make_operations_from_revisions(revisions.clone()).unwrap();
+    manager.create_document(DOC_ID, revisions).await.unwrap();
+
+    let document = manager.lazy_document(DOC_ID).await.unwrap();
+    assert_eq!(document.len(), full.utf16_target_len);
+
+    // The first region crosses the deleted lines and the bold ones, the second one reaches the
+    // lines appended by the last revision.
+    let first = Interval::new(1_000, 41_000);
+    let second = Interval::new(full.utf16_target_len - 30_000, full.utf16_target_len - 10);
+    for interval in [first, second].iter() {
+        let region = document.range(interval.start, interval.size()).unwrap();
+        assert_eq!(region, full.slice(*interval));
+        assert_eq!(region.utf16_target_len, interval.size());
+        // Cached, and a region inside it is sliced from it.
+        assert_eq!(document.range(interval.start, interval.size()).unwrap(), region);
+        assert_eq!(
+            document.range(interval.start + 7, 100).unwrap(),
+            full.slice(Interval::new(interval.start + 7, interval.start + 107))
+        );
+    }
+
+    // The region is clamped to the end of the document.
+    assert_eq!(
+        document.range(full.utf16_target_len - 5, 100).unwrap(),
+        full.slice(Interval::new(full.utf16_target_len - 5, full.utf16_target_len))
+    );
+    assert!(document.range(full.utf16_target_len, 100).unwrap().is_empty());
+}
+
+/// About 1MB of lines, then revisions that delete lines in the middle, make lines bold and
+/// append lines without retaining the end of the document.
+fn large_document_revisions() -> Vec<Revision> {
+    let line = |i: usize| format!("line {:06} with some text to make it longer\n", i);
+    let line_len = line(0).len();
+    let mut builder = DeltaTextOperationBuilder::new();
+    for i in 0..20_000 {
+        builder = builder.insert(&line(i));
+    }
+    let mut revisions = vec![Revision::initial_revision(
+        DOC_ID,
+        Bytes::from(builder.build().json_str()),
+    )];
+
+    let mut bold = AttributeHashMap::new();
+    bold.insert_entry(BuildInTextAttribute::Bold(true));
+    let changes = vec![
+        DeltaTextOperationBuilder::new()
+            .retain(line_len * 100)
+            .delete(line_len * 50)
+            .insert("replaced lines\n")
+            .build(),
+        DeltaTextOperationBuilder::new()
+            .retain(line_len * 120 + 5)
+            .retain_with_attributes(line_len * 300, bold)
+            .delete(12)
+            .build(),
+        DeltaTextOperationBuilder::new()
+            .insert("title\n")
+            .retain(line_len * 19_000)
+            .insert(&line(20_000))
+            .build(),
+    ];
+    for (index, operations) in changes.into_iter().enumerate() {
+        let rev_id = index as i64 + 2;
+        revisions.push(Revision::new(
+            DOC_ID,
+            rev_id - 1,
+            rev_id,
+            Bytes::from(operations.json_str()),
+            "",
+        ));
+    }
+    revisions
+}
@@ -0,0 +1,107 @@
+use crate::old_document::mock::{create_delta_editor, make_delta_document_manager};
+use flowy_document::old_editor::editor::SEARCH_SNIPPET_RADIUS;
+use lib_ot::codec::markdown::markdown_list::{
+    encode_markdown_list, encode_markdown_list_with_wrap, write_markdown_list,
+};
+use lib_ot::text_delta::{
+    line_segments, write_plain_text, CustomAttributes, DeltaTextOperationBuilder, DeltaTextOperations,
+    MAX_LINE_SEGMENT_LEN,
+};
+use std::fmt::{self, Write};
+
+const DOC_ID: &str = "long_line_doc";
+const NUM_OF_ITEMS: usize = 150_000;
+
+/// A minified JSON of about 3MB on a single line, e.g. pasted from a web page.
+fn long_line() -> String {
+    let mut line = String::from("[");
+    for i in 0..NUM_OF_ITEMS {
+        if i == NUM_OF_ITEMS / 2 {
+            line.push_str("{\"needle\":\"found\"},");
+        }
+        line.push_str(&format!("{{\"key_{}\":\"value {}\"}},", i, i));
+    }
+    line.push(']');
+    line
+}
+
+#[tokio::test]
+async fn long_line_document_test() {
+    let line = long_line();
+    assert!(line.len() > 2 * 1024 * 1024);
+    let operations = DeltaTextOperationBuilder::new().insert(&format!("{}\n", line)).build();
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, &operations.json_str()).await;
+
+    // The only spaces are the ones in the values of the items.
+    assert_eq!(editor.word_count(false).await.unwrap(), NUM_OF_ITEMS + 1);
+
+    let snippets = editor.search_snippets("needle", false).await.unwrap();
+    assert_eq!(snippets.len(), 1);
+    let snippet = &snippets[0].text;
+    assert!(snippet.starts_with('…') && snippet.ends_with('…'), "{}", snippet);
+    assert_eq!(snippet.chars().count(), 2 * SEARCH_SNIPPET_RADIUS + "needle".len() + 2);
+
+    let operations = DeltaTextOperations::from_json(&editor.export().await.unwrap()).unwrap();
+    let segments = line_segments(&operations).collect::<Vec<_>>();
+    assert!(segments.len() > 1);
+    assert!(segments
+        .iter()
+        .all(|segment| segment.text.len() <= MAX_LINE_SEGMENT_LEN));
+    assert!(segments.last().unwrap().ends_line);
+    assert_eq!(segments.iter().map(|segment| segment.text).collect::<String>(), line);
+
+    // The line is exported verbatim unless the export wraps it.
+    assert_eq!(encode_markdown_list(&operations), format!("{}\n", line));
+    let wrapped = encode_markdown_list_with_wrap(&operations, Some(100));
+    assert!(wrapped.lines().all(|wrapped_line| wrapped_line.chars().count() <= 100));
+    assert_eq!(wrapped.replace('\n', ""), line);
+}
+
+/// Keeps the length of the longest piece written to it instead of the text.
+#[derive(Default)]
+struct LongestWrite {
+    len: usize,
+    total_len: usize,
+}
+
+impl Write for LongestWrite {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.len = self.len.max(s.len());
+        self.total_len += s.len();
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn long_line_streaming_export_test() {
+    let line = long_line();
+    let operations = DeltaTextOperationBuilder::new().insert(&format!("{}\n", line)).build();
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, &operations.json_str()).await;
+    let operations = DeltaTextOperations::from_json(&editor.export().await.unwrap()).unwrap();
+
+    // The line is written segment by segment instead of being buffered.
+    for wrap_width in [None, Some(100)] {
+        let mut markdown = LongestWrite::default();
+        write_markdown_list(&operations, wrap_width, &CustomAttributes::default(), &mut markdown).unwrap();
+        assert!(markdown.len <= MAX_LINE_SEGMENT_LEN);
+        assert!(markdown.total_len > line.len());
+    }
+
+    let mut text = LongestWrite::default();
+    write_plain_text(&operations, false, &mut text).unwrap();
+    assert!(text.len <= MAX_LINE_SEGMENT_LEN);
+    assert_eq!(text.total_len, line.len() + 1);
+}
+
+#[test]
+fn wrap_long_list_line_test() {
+    let json = r#"[{"insert":"abcdefgh"},{"insert":"\n","attributes":{"list":"bullet"}}]"#;
+    let operations = DeltaTextOperations::from_json(json).unwrap();
+    assert_eq!(
+        encode_markdown_list_with_wrap(&operations, Some(3)),
+        "* abc\n  def\n  gh\n"
+    );
+    assert_eq!(encode_markdown_list_with_wrap(&operations, None), "* abcdefgh\n");
+}
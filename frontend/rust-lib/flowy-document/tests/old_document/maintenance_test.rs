@@ -0,0 +1,120 @@
+use crate::old_document::mock::{make_document_manager, DocumentCloudServiceMock};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::{
+    DocumentConfig, DocumentManager, MaintenanceStatus, MaintenanceTask, MaintenanceTaskReport, MaintenanceTasks,
+};
+use flowy_http_model::revision::Revision;
+use lib_ot::text_delta::DeltaTextOperationBuilder;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DOC_ID: &str = "maintenance_doc";
+const ORPHAN_LEN: usize = 256 * 1024;
+
+#[tokio::test]
+async fn run_maintenance_test() {
+    let manager = make_manager(DocumentVersionPB::V0).await;
+    let _ = manager.open_document_editor(DOC_ID).await.unwrap();
+    let orphan = manager
+        .attachment_store()
+        .unwrap()
+        .upload("orphan.bin", vec![7; ORPHAN_LEN])
+        .unwrap();
+
+    // The dry run only runs the tasks that can estimate what they'd reclaim.
+    let report = manager.run_maintenance(MaintenanceTasks::ALL, true).await.unwrap();
+    assert!(report.dry_run);
+    assert_eq!(tasks_of(&report.tasks), MaintenanceTask::ORDER.to_vec());
+    for report in &report.tasks {
+        match report.task {
+            MaintenanceTask::Compaction | MaintenanceTask::BlobGc | MaintenanceTask::Vacuum => {
+                assert_eq!(report.status, MaintenanceStatus::Done)
+            }
+            _ => assert_eq!(report.status, MaintenanceStatus::NoDryRun),
+        }
+    }
+    assert_eq!(
+        report.get(MaintenanceTask::BlobGc).unwrap().reclaimed_bytes,
+        ORPHAN_LEN as u64
+    );
+    assert!(manager.attachment_store().unwrap().read(&orphan.id).unwrap().is_some());
+
+    let report = manager.run_maintenance(MaintenanceTasks::ALL, false).await.unwrap();
+    assert_eq!(tasks_of(&report.tasks), MaintenanceTask::ORDER.to_vec());
+    assert!(!report.has_failure());
+    assert!(report
+        .tasks
+        .iter()
+        .all(|report| report.status == MaintenanceStatus::Done));
+    // The attachment's pages are freed by the blob GC, then given back by the vacuum.
+    assert!(report.get(MaintenanceTask::BlobGc).unwrap().reclaimed_bytes >= ORPHAN_LEN as u64);
+    assert!(report.get(MaintenanceTask::Vacuum).unwrap().reclaimed_bytes >= ORPHAN_LEN as u64);
+    assert_eq!(
+        report.total_reclaimed_bytes(),
+        report.tasks.iter().map(|report| report.reclaimed_bytes).sum::<u64>()
+    );
+    assert_eq!(
+        report.total_duration(),
+        report.tasks.iter().map(|report| report.duration).sum::<Duration>()
+    );
+    assert!(manager.attachment_store().unwrap().read(&orphan.id).unwrap().is_none());
+
+    // The document is still readable after the maintenance.
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"hello world\n"}]"#);
+}
+
+#[tokio::test]
+async fn run_selected_maintenance_tasks_test() {
+    let manager = make_manager(DocumentVersionPB::V0).await;
+    let tasks = MaintenanceTasks::SNAPSHOT_REFRESH.union(MaintenanceTasks::FLUSH);
+    let report = manager.run_maintenance(tasks, false).await.unwrap();
+    assert_eq!(
+        tasks_of(&report.tasks),
+        vec![MaintenanceTask::Flush, MaintenanceTask::SnapshotRefresh]
+    );
+
+    let report = manager.run_maintenance(MaintenanceTasks::NONE, false).await.unwrap();
+    assert!(report.tasks.is_empty());
+}
+
+#[tokio::test]
+async fn run_maintenance_partial_failure_test() {
+    // The attachments only support the delta documents, so the blob GC fails.
+    let manager = make_manager(DocumentVersionPB::V1).await;
+    let report = manager.run_maintenance(MaintenanceTasks::ALL, false).await.unwrap();
+    assert!(report.has_failure());
+    assert_eq!(tasks_of(&report.tasks), MaintenanceTask::ORDER.to_vec());
+    for report in &report.tasks {
+        match report.task {
+            MaintenanceTask::BlobGc => assert!(matches!(report.status, MaintenanceStatus::Failed(_))),
+            // The tasks after the failed one still run.
+            _ => assert_eq!(report.status, MaintenanceStatus::Done),
+        }
+    }
+}
+
+fn tasks_of(reports: &[MaintenanceTaskReport]) -> Vec<MaintenanceTask> {
+    reports.iter().map(|report| report.task).collect()
+}
+
+async fn make_manager(version: DocumentVersionPB) -> DocumentManager {
+    let config = DocumentConfig {
+        version: version.clone(),
+        attachment_grace_period: Duration::from_secs(0),
+        ..Default::default()
+    };
+    let manager = make_document_manager(Arc::new(DocumentCloudServiceMock()), config);
+    if version == DocumentVersionPB::V0 {
+        let operations = DeltaTextOperationBuilder::new().insert("hello world\n").build();
+        manager
+            .create_document(
+                DOC_ID,
+                vec![Revision::initial_revision(DOC_ID, Bytes::from(operations.json_str()))],
+            )
+            .await
+            .unwrap();
+    }
+    manager
+}
@@ -0,0 +1,141 @@
+use crate::old_document::mock::{make_delta_document_manager_at, make_temp_dir};
+use bytes::Bytes;
+use flowy_document::{DocumentEditor, DocumentManager};
+use flowy_http_model::revision::Revision;
+use lib_ot::core::DeltaOperation;
+use lib_ot::text_delta::{attachment_ids, document_link, linked_doc_id, DeltaTextOperations};
+
+#[tokio::test]
+async fn merge_database_test() {
+    let (_, target) = make_manager();
+    let photo = target
+        .attachment_store()
+        .unwrap()
+        .upload("photo.png", vec![1, 2, 3])
+        .unwrap();
+    create_document(&target, "a1", vec![r#"[{"insert":"mine\n"}]"#.to_owned()]).await;
+
+    let (source_dir, source) = make_manager();
+    let same_photo = source
+        .attachment_store()
+        .unwrap()
+        .upload("photo.png", vec![1, 2, 3])
+        .unwrap();
+    let other_photo = source
+        .attachment_store()
+        .unwrap()
+        .upload("other.png", vec![4, 5, 6])
+        .unwrap();
+    create_document(
+        &source,
+        "b1",
+        vec![
+            r#"[{"insert":"hello\n"}]"#.to_owned(),
+            r#"[{"retain":5},{"insert":" world"}]"#.to_owned(),
+        ],
+    )
+    .await;
+    source.tag_revision("b1", 1, "draft").await.unwrap();
+    let linking = format!(
+        r#"[{{"insert":"see","attributes":{{"link":"{}"}}}},{{"insert":"photo","attributes":{{"attachment":"{}"}}}},{{"insert":"other","attributes":{{"attachment":"{}"}}}},{{"insert":"\n"}}]"#,
+        document_link("b1"),
+        same_photo.id,
+        other_photo.id
+    );
+    create_document(&source, "b2", vec![linking]).await;
+
+    let summary = target.merge_local_database(&source_dir).await.unwrap();
+    assert_eq!(summary.num_of_merged, 2);
+    assert!(summary.failed.is_empty());
+    assert_eq!(summary.num_of_attachments, 1);
+    assert_eq!(summary.num_of_deduped_attachments, 1);
+    let b1 = summary.doc_ids["b1"].clone();
+    let b2 = summary.doc_ids["b2"].clone();
+    assert_ne!(b1, "b1");
+    assert_ne!(b2, "b2");
+
+    // Both documents open, the history and the tags are kept.
+    assert_eq!(content(&target, &b1).await.content().unwrap(), "hello world\n");
+    let versions = target.tagged_versions(&b1).await.unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].0, "draft");
+    assert_eq!(versions[0].1.content().unwrap(), "hello\n");
+
+    // The link points to the copy, the photo to the stored one and the other photo is copied.
+    let linking = content(&target, &b2).await;
+    let link = linking
+        .ops
+        .iter()
+        .find_map(|op| match op {
+            DeltaOperation::Insert(insert) => linked_doc_id(&insert.attributes),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(link, b1);
+    let attachment_ids = attachment_ids(&linking);
+    assert!(attachment_ids.contains(&photo.id));
+    assert!(attachment_ids.contains(&other_photo.id));
+    let store = target.attachment_store().unwrap();
+    assert_eq!(store.read(&other_photo.id).unwrap().unwrap().data, vec![4, 5, 6]);
+
+    // The local document is untouched.
+    assert_eq!(content(&target, "a1").await.content().unwrap(), "mine\n");
+}
+
+#[tokio::test]
+async fn merge_database_again_test() {
+    let (_, target) = make_manager();
+    let (source_dir, source) = make_manager();
+    create_document(&source, "b1", vec![r#"[{"insert":"hello\n"}]"#.to_owned()]).await;
+
+    let first = target.merge_local_database(&source_dir).await.unwrap();
+    assert_eq!(first.num_of_merged, 1);
+
+    // The merge that is run again finds the copies, nothing is copied twice.
+    let second = target.merge_local_database(&source_dir).await.unwrap();
+    assert_eq!(second.num_of_merged, 0);
+    assert_eq!(second.doc_ids, first.doc_ids);
+    assert_eq!(
+        content(&target, &first.doc_ids["b1"]).await.content().unwrap(),
+        "hello\n"
+    );
+
+    // A document added since is merged by the next run.
+    create_document(&source, "b2", vec![r#"[{"insert":"later\n"}]"#.to_owned()]).await;
+    let third = target.merge_local_database(&source_dir).await.unwrap();
+    assert_eq!(third.num_of_merged, 1);
+    assert_eq!(third.doc_ids["b1"], first.doc_ids["b1"]);
+    assert_eq!(
+        content(&target, &third.doc_ids["b2"]).await.content().unwrap(),
+        "later\n"
+    );
+}
+
+#[tokio::test]
+async fn merge_database_into_itself_test() {
+    let (dir, manager) = make_manager();
+    create_document(&manager, "a1", vec![r#"[{"insert":"mine\n"}]"#.to_owned()]).await;
+    assert!(manager.merge_local_database(&dir).await.is_err());
+    assert!(manager.merge_local_database(&make_temp_dir()).await.is_err());
+}
+
+fn make_manager() -> (String, DocumentManager) {
+    let dir = make_temp_dir();
+    let manager = make_delta_document_manager_at(&dir);
+    (dir, manager)
+}
+
+/// Creates the document with a revision for each of the `deltas`.
+async fn create_document(manager: &DocumentManager, doc_id: &str, deltas: Vec<String>) {
+    let revisions = deltas
+        .into_iter()
+        .enumerate()
+        .map(|(i, delta)| Revision::new(doc_id, i as i64, i as i64 + 1, Bytes::from(delta), ""))
+        .collect();
+    manager.create_document(doc_id, revisions).await.unwrap();
+}
+
+async fn content(manager: &DocumentManager, doc_id: &str) -> DeltaTextOperations {
+    let editor = manager.open_document_editor(doc_id).await.unwrap();
+    DeltaTextOperations::from_json(&editor.export().await.unwrap()).unwrap()
+}
@@ -0,0 +1,260 @@
+use bytes::Bytes;
+use flowy_database::ConnectionPool;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::FlowyError;
+use flowy_document::old_editor::editor::DeltaDocumentEditor;
+use flowy_document::{
+    DocumentChunk, DocumentCloudService, DocumentConfig, DocumentDatabase, DocumentDatabaseMock, DocumentManager,
+    DocumentUser,
+};
+use flowy_http_model::document::{CreateDocumentParams, DocumentId, DocumentPayload, ResetDocumentParams};
+use flowy_http_model::revision::Revision;
+use flowy_http_model::ws_data::ClientRevisionWSData;
+use flowy_revision::{RevisionWebSocket, WSStateReceiver};
+use futures_util::future::BoxFuture;
+use lib_infra::future::{BoxResultFuture, FutureResult};
+use lib_ws::WSConnectState;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+/// Makes a `DocumentManager` that stores its documents in a new database under the temp dir.
+pub fn make_document_manager(cloud_service: Arc<dyn DocumentCloudService>, config: DocumentConfig) -> DocumentManager {
+    make_document_manager_at(&make_temp_dir(), cloud_service, config)
+}
+
+/// Makes a `DocumentManager` that stores its documents in the database under the `dir`.
+pub fn make_document_manager_at(
+    dir: &str,
+    cloud_service: Arc<dyn DocumentCloudService>,
+    config: DocumentConfig,
+) -> DocumentManager {
+    make_document_manager_with_server(dir, cloud_service, Arc::new(RevisionWebSocketMock::new()), config)
+}
+
+/// Same as `make_document_manager_at`, but the documents sync with the `web_socket` by default.
+pub fn make_document_manager_with_server(
+    dir: &str,
+    cloud_service: Arc<dyn DocumentCloudService>,
+    web_socket: Arc<dyn RevisionWebSocket>,
+    config: DocumentConfig,
+) -> DocumentManager {
+    let database = flowy_database::init(dir).unwrap();
+    DocumentManager::new(
+        cloud_service,
+        Arc::new(DocumentUserMock(dir.to_owned())),
+        Arc::new(DocumentDatabaseMock(database.get_pool())),
+        web_socket,
+        config,
+    )
+}
+
+/// Makes a `DocumentManager` whose database can't be opened, so its `initialize` fails.
+pub fn make_unavailable_document_manager() -> DocumentManager {
+    DocumentManager::new(
+        Arc::new(DocumentCloudServiceMock()),
+        Arc::new(DocumentUserMock(make_temp_dir())),
+        Arc::new(UnavailableDatabaseMock()),
+        Arc::new(RevisionWebSocketMock::new()),
+        DocumentConfig::default(),
+    )
+}
+
+/// Makes a `DocumentManager` of the delta documents with the default configuration.
+pub fn make_delta_document_manager() -> DocumentManager {
+    make_delta_document_manager_at(&make_temp_dir())
+}
+
+/// Same as `make_delta_document_manager`, but the database is under the `dir`.
+pub fn make_delta_document_manager_at(dir: &str) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        ..Default::default()
+    };
+    make_document_manager_at(dir, Arc::new(DocumentCloudServiceMock()), config)
+}
+
+/// Creates the delta document with the `json` as its initial revision and opens it.
+pub async fn create_delta_editor(manager: &DocumentManager, doc_id: &str, json: &str) -> Arc<DeltaDocumentEditor> {
+    let revision = Revision::initial_revision(doc_id, Bytes::from(json.to_owned()));
+    manager.create_document(doc_id, vec![revision]).await.unwrap();
+    open_delta_editor(manager, doc_id).await
+}
+
+pub async fn open_delta_editor(manager: &DocumentManager, doc_id: &str) -> Arc<DeltaDocumentEditor> {
+    let editor = manager.open_document_editor(doc_id).await.unwrap();
+    editor
+        .as_any()
+        .downcast_ref::<Arc<DeltaDocumentEditor>>()
+        .unwrap()
+        .clone()
+}
+
+pub fn make_temp_dir() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let dir = std::env::temp_dir().join(format!("flowy_document_{}", nanos));
+    dir.to_str().unwrap().to_owned()
+}
+
+/// The server that keeps the latest content of each document uploaded by the devices.
+#[derive(Default)]
+pub struct DocumentServerMock {
+    documents: Mutex<HashMap<String, DocumentPayload>>,
+}
+
+impl DocumentCloudService for DocumentServerMock {
+    fn create_document(&self, _token: &str, _params: CreateDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    fn fetch_document(&self, _token: &str, params: DocumentId) -> FutureResult<Option<DocumentPayload>, FlowyError> {
+        let payload = self.documents.lock().unwrap().get(&params.value).cloned();
+        FutureResult::new(async move { Ok(payload) })
+    }
+
+    fn update_document_content(&self, _token: &str, params: ResetDocumentParams) -> FutureResult<(), FlowyError> {
+        if let Some(revision) = params.revisions.last() {
+            let payload = DocumentPayload {
+                doc_id: params.doc_id.clone(),
+                data: revision.bytes.to_vec(),
+                rev_id: revision.rev_id,
+                base_rev_id: revision.base_rev_id,
+            };
+            self.documents.lock().unwrap().insert(params.doc_id, payload);
+        }
+        FutureResult::new(async { Ok(()) })
+    }
+}
+
+/// The server that doesn't have any document.
+pub struct DocumentCloudServiceMock();
+impl DocumentCloudService for DocumentCloudServiceMock {
+    fn create_document(&self, _token: &str, _params: CreateDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    fn fetch_document(&self, _token: &str, _params: DocumentId) -> FutureResult<Option<DocumentPayload>, FlowyError> {
+        FutureResult::new(async { Ok(None) })
+    }
+
+    fn update_document_content(&self, _token: &str, _params: ResetDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+}
+
+struct DocumentUserMock(String);
+impl DocumentUser for DocumentUserMock {
+    fn user_dir(&self) -> Result<String, FlowyError> {
+        Ok(self.0.clone())
+    }
+
+    fn user_id(&self) -> Result<String, FlowyError> {
+        Ok("user".to_owned())
+    }
+
+    fn token(&self) -> Result<String, FlowyError> {
+        Ok("".to_owned())
+    }
+}
+
+struct UnavailableDatabaseMock();
+impl DocumentDatabase for UnavailableDatabaseMock {
+    fn db_pool(&self) -> Result<Arc<ConnectionPool>, FlowyError> {
+        Err(FlowyError::internal().context("The database is unavailable"))
+    }
+}
+
+struct RevisionWebSocketMock {
+    state_sender: broadcast::Sender<WSConnectState>,
+}
+
+impl RevisionWebSocketMock {
+    fn new() -> Self {
+        let (state_sender, _) = broadcast::channel(2);
+        Self { state_sender }
+    }
+}
+
+impl RevisionWebSocket for RevisionWebSocketMock {
+    fn send(&self, _data: ClientRevisionWSData) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn subscribe_state_changed(&self) -> BoxFuture<WSStateReceiver> {
+        let receiver = self.state_sender.subscribe();
+        Box::pin(async move { receiver })
+    }
+}
+
+/// The size of each chunk streamed by `StreamingDocumentCloudService`.
+pub const CHUNK_LEN: usize = 16 * 1024;
+pub const NUM_OF_CHUNKS: usize = 256;
+
+pub fn chunk_letter(chunk_index: usize) -> char {
+    (b'a' + (chunk_index % 26) as u8) as char
+}
+
+/// Generates each chunk when it's requested, so the server never holds the whole document.
+pub struct StreamingDocumentCloudService {
+    pub streaming: bool,
+    pub changed_at_chunk: Option<usize>,
+}
+
+impl StreamingDocumentCloudService {
+    pub fn new() -> Self {
+        Self {
+            streaming: true,
+            changed_at_chunk: None,
+        }
+    }
+}
+
+impl DocumentCloudService for StreamingDocumentCloudService {
+    fn create_document(&self, _token: &str, _params: CreateDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    fn fetch_document(&self, _token: &str, params: DocumentId) -> FutureResult<Option<DocumentPayload>, FlowyError> {
+        FutureResult::new(async move {
+            Ok(Some(DocumentPayload {
+                doc_id: params.value,
+                data: r#"[{"insert":"1234\n"}]"#.as_bytes().to_vec(),
+                rev_id: 5,
+                base_rev_id: 4,
+            }))
+        })
+    }
+
+    fn update_document_content(&self, _token: &str, _params: ResetDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    fn fetch_document_chunk(
+        &self,
+        _token: &str,
+        params: DocumentId,
+        chunk_index: usize,
+    ) -> FutureResult<Option<DocumentChunk>, FlowyError> {
+        if !self.streaming {
+            return FutureResult::new(async { Ok(None) });
+        }
+        let is_last = chunk_index + 1 == NUM_OF_CHUNKS;
+        let mut text = chunk_letter(chunk_index).to_string().repeat(CHUNK_LEN);
+        if is_last {
+            text.push('\n');
+        }
+        let rev_id = match self.changed_at_chunk {
+            Some(changed_at_chunk) if chunk_index >= changed_at_chunk => 6,
+            _ => 5,
+        };
+        let chunk = DocumentChunk {
+            doc_id: params.value,
+            data: format!(r#"[{{"insert":"{}"}}]"#, text.replace('\n', "\\n")).into_bytes(),
+            rev_id,
+            base_rev_id: 4,
+            is_last,
+        };
+        FutureResult::new(async move { Ok(Some(chunk)) })
+    }
+}
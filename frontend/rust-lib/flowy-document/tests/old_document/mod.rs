@@ -1,2 +1,41 @@
+mod apply_edit_test;
+mod archive_test;
+mod attachment_test;
+mod backup_test;
+mod blame_test;
+mod capability_test;
+mod chunked_export_test;
+mod collapse_noops_test;
+mod compose_budget_test;
+mod compose_error_test;
+mod content_hash_test;
+mod custom_attribute_test;
+mod dictionary_test;
+mod doc_preference_test;
+mod explain_transform_test;
+mod fetch_guard_test;
+mod find_replace_test;
+mod hydrate_test;
+mod import_test;
+mod lazy_document_test;
+mod long_line_test;
+mod maintenance_test;
+mod merge_database_test;
+pub mod mock;
 mod old_document_test;
+mod open_latest_test;
+mod portable_test;
+mod preview_test;
+mod recover_text_test;
+mod redline_test;
+mod reexport_test;
+mod rev_graph_test;
+mod revalidate_test;
+mod revision_guard_test;
 mod script;
+mod snippet_test;
+mod split_test;
+mod startup_gate_test;
+mod storage_test;
+mod tag_test;
+mod touched_ranges_test;
@@ -103,3 +103,30 @@ async fn text_block_sync_replace_test() {
     ];
     DeltaDocumentEditorTest::new().await.run_scripts(scripts).await;
 }
+
+#[tokio::test]
+async fn text_block_sync_rollback_test() {
+    let scripts = vec![
+        InsertText("1", 0),
+        InsertText("2", 1),
+        InsertText("3", 2),
+        AssertJson(r#"[{"insert":"123\n"}]"#),
+        Rollback(2),
+        AssertJson(r#"[{"insert":"1\n"}]"#),
+        AssertCurrentRevId(4),
+    ];
+    DeltaDocumentEditorTest::new().await.run_scripts(scripts).await;
+}
+
+#[tokio::test]
+async fn text_block_sync_trim_leading_test() {
+    let scripts = vec![
+        InsertText("1", 0),
+        InsertText("2", 1),
+        InsertText("3", 2),
+        TrimLeading(2),
+        AssertJson(r#"[{"insert":"3\n"}]"#),
+        AssertCurrentRevId(4),
+    ];
+    DeltaDocumentEditorTest::new().await.run_scripts(scripts).await;
+}
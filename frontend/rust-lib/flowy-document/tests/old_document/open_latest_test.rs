@@ -0,0 +1,131 @@
+use crate::old_document::mock::{make_document_manager_at, make_temp_dir, DocumentCloudServiceMock};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::{AvailableDocument, DocumentConfig, DocumentManager};
+use flowy_http_model::revision::Revision;
+use flowy_revision::{Executor, PriorityConfiguration, PriorityScheduler, TaskPriority};
+use lib_ot::text_delta::DeltaTextOperationBuilder;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const DOC_ID: &str = "open_latest_doc";
+const BUSY_TASK_DURATION: Duration = Duration::from_millis(300);
+
+#[tokio::test]
+async fn open_latest_available_while_disk_is_busy_test() {
+    let scheduler = PriorityScheduler::new(PriorityConfiguration {
+        max_concurrency: 1,
+        ..Default::default()
+    });
+    let manager = Arc::new(make_manager(scheduler.clone()));
+    let operations = DeltaTextOperationBuilder::new().insert("hello world\n").build();
+    manager
+        .create_document(
+            DOC_ID,
+            vec![Revision::initial_revision(DOC_ID, Bytes::from(operations.json_str()))],
+        )
+        .await
+        .unwrap();
+    let _ = manager.open_document_editor(DOC_ID).await.unwrap();
+    manager.close_document_editor(DOC_ID).await.unwrap();
+
+    // Keeps the only worker of the disk busy with the background work. The first task holds it
+    // until the gate is released, so the disk is busy for sure while the skeleton is read.
+    let gate = Arc::new(RwLock::new(()));
+    let closed_gate = gate.write().unwrap();
+    for _ in 0..5 {
+        let scheduler = scheduler.clone();
+        let gate = gate.clone();
+        tokio::spawn(async move {
+            scheduler
+                .spawn_blocking(&Executor::Current, TaskPriority::Background, move || {
+                    let _gate = gate.read().unwrap();
+                    std::thread::sleep(BUSY_TASK_DURATION)
+                })
+                .await
+        });
+    }
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // The skeleton comes back without waiting for the worker.
+    match manager.open_latest_available(DOC_ID).await.unwrap() {
+        AvailableDocument::Loading(Some(meta)) => assert_eq!(meta.num_of_chars, 12),
+        other => panic!("Expect the loading document with its meta, got {:?}", other),
+    }
+    drop(closed_gate);
+
+    // The document goes before the rest of the background work once it's opened.
+    let json = tokio::time::timeout(5 * BUSY_TASK_DURATION, async {
+        loop {
+            match manager.open_latest_available(DOC_ID).await.unwrap() {
+                AvailableDocument::Ready(json) => break json,
+                AvailableDocument::Loading(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(json, r#"[{"insert":"hello world\n"}]"#);
+}
+
+#[tokio::test]
+async fn open_latest_available_unknown_meta_test() {
+    let manager = Arc::new(make_manager(PriorityScheduler::default()));
+    let operations = DeltaTextOperationBuilder::new().insert("hello\n").build();
+    manager
+        .create_document(
+            DOC_ID,
+            vec![Revision::initial_revision(DOC_ID, Bytes::from(operations.json_str()))],
+        )
+        .await
+        .unwrap();
+
+    // The document was never closed, so its meta isn't known yet.
+    let available = manager.open_latest_available(DOC_ID).await.unwrap();
+    assert_eq!(available, AvailableDocument::Loading(None));
+}
+
+#[tokio::test]
+async fn open_latest_available_after_restart_test() {
+    let dir = make_temp_dir();
+    let manager = make_manager_at(&dir, PriorityScheduler::default());
+    let operations = DeltaTextOperationBuilder::new().insert("hello world\n").build();
+    manager
+        .create_document(
+            DOC_ID,
+            vec![Revision::initial_revision(DOC_ID, Bytes::from(operations.json_str()))],
+        )
+        .await
+        .unwrap();
+    manager.set_doc_preference(DOC_ID, "font_size", &14).await.unwrap();
+    let _ = manager.open_document_editor(DOC_ID).await.unwrap();
+    manager.close_document_editor(DOC_ID).await.unwrap();
+    drop(manager);
+
+    // The meta is saved with the document, the new manager returns it with the preferences.
+    let manager = Arc::new(make_manager_at(&dir, PriorityScheduler::default()));
+    match manager.open_latest_available(DOC_ID).await.unwrap() {
+        AvailableDocument::Loading(Some(meta)) => {
+            assert_eq!(meta.num_of_chars, 12);
+            assert_eq!(meta.num_of_words, 2);
+            assert_eq!(
+                meta.preferences.get("font_size").map(|value| value.as_str()),
+                Some("14")
+            );
+        }
+        other => panic!("Expect the loading document with its meta, got {:?}", other),
+    }
+}
+
+fn make_manager(priority_scheduler: PriorityScheduler) -> DocumentManager {
+    make_manager_at(&make_temp_dir(), priority_scheduler)
+}
+
+fn make_manager_at(dir: &str, priority_scheduler: PriorityScheduler) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        priority_scheduler,
+        ..Default::default()
+    };
+    make_document_manager_at(dir, Arc::new(DocumentCloudServiceMock()), config)
+}
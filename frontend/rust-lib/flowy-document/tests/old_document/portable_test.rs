@@ -0,0 +1,154 @@
+use crate::old_document::mock::{make_document_manager, make_temp_dir, DocumentCloudServiceMock};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::ErrorCode;
+use flowy_document::old_editor::editor::DeltaDocumentEditor;
+use flowy_document::{DocumentConfig, DocumentEditor, DocumentManager, PORTABLE_DOCUMENT_EXTENSION};
+use flowy_http_model::revision::Revision;
+use flowy_http_model::util::md5;
+use lib_ot::text_delta::resolve_attachments;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const DOC_ID: &str = "portable_doc";
+
+#[tokio::test]
+async fn portable_document_round_trip_test() {
+    let source = make_manager();
+    let photo = source
+        .attachment_store()
+        .unwrap()
+        .upload("photo.png", vec![1, 2, 3])
+        .unwrap();
+    let content = format!(
+        r#"[{{"insert":"a"}},{{"insert":"photo","attributes":{{"attachment":"{}"}}}},{{"insert":"\n"}}]"#,
+        photo.id
+    );
+    let editor = open_editor(&source, DOC_ID, Some(content)).await;
+    source.tag_revision(DOC_ID, 1, "draft").await.unwrap();
+    editor.insert(1, "b").await.unwrap();
+    editor.insert(2, "c").await.unwrap();
+    let rev_id = editor.rev_manager().rev_id();
+    source.tag_revision(DOC_ID, rev_id, "review").await.unwrap();
+    editor.insert(0, "0").await.unwrap();
+    source.set_doc_preference(DOC_ID, "font_size", &18).await.unwrap();
+
+    let path = portable_path();
+    source.export_doc_portable(DOC_ID, &path).await.unwrap();
+
+    let target = make_manager();
+    let doc_id = target.import_doc_portable(&path).await.unwrap();
+    assert_ne!(doc_id, DOC_ID);
+    let imported = open_editor(&target, &doc_id, None).await;
+    assert_eq!(imported.export().await.unwrap(), editor.export().await.unwrap());
+
+    // The whole history is replayed under the new id.
+    let rev_ids = |revisions: Vec<Revision>| revisions.iter().map(|revision| revision.rev_id).collect::<Vec<i64>>();
+    editor.rev_manager().flush().await.unwrap();
+    let source_revisions = editor.rev_manager().load_revisions().await.unwrap();
+    let imported_revisions = imported.rev_manager().load_revisions().await.unwrap();
+    assert!(source_revisions.len() > 1);
+    assert_eq!(rev_ids(imported_revisions.clone()), rev_ids(source_revisions));
+    assert!(imported_revisions.iter().all(|revision| revision.object_id == doc_id));
+
+    // The named versions are kept.
+    let imported_versions = tagged_versions(&target, &doc_id).await;
+    assert_eq!(imported_versions.len(), 2);
+    assert_eq!(imported_versions, tagged_versions(&source, DOC_ID).await);
+
+    // The attachment renders with its name on the other side.
+    let store = target.attachment_store().unwrap();
+    assert_eq!(store.read(&photo.id).unwrap().unwrap().data, vec![1, 2, 3]);
+    let operations = imported.document_operations().await.unwrap();
+    let rendered = resolve_attachments(&operations, |attachment_id| {
+        store
+            .read(attachment_id)
+            .ok()
+            .flatten()
+            .map(|attachment| attachment.name)
+    });
+    assert_eq!(rendered.content().unwrap(), "0abcphoto.png\n");
+
+    // The preferences are imported into the preferences document too, setting another one
+    // keeps them.
+    target.set_doc_preference(&doc_id, "width", &"wide").await.unwrap();
+    let preferences = target.doc_preferences(&doc_id).unwrap();
+    assert_eq!(preferences.get("font_size").unwrap(), "18");
+    assert_eq!(preferences.get("width").unwrap(), "\"wide\"");
+
+    // The data is written as base64 rather than an array of numbers.
+    let json = std::fs::read_to_string(&path).unwrap();
+    assert!(json.contains(r#""format_version":2"#));
+    assert!(!json.contains(r#""bytes":["#));
+    assert!(!json.contains(r#""data":["#));
+}
+
+#[tokio::test]
+async fn portable_document_of_first_format_test() {
+    let bytes = br#"[{"insert":"v1\n"}]"#.to_vec();
+    let path = portable_path();
+    std::fs::write(
+        &path,
+        format!(
+            r#"{{"format_version":1,"meta":{{"doc_id":"v1","rev_id":1,"exported_time":0}},"revisions":[{{"base_rev_id":0,"rev_id":1,"bytes":{:?},"md5":"{}","object_id":"v1"}}],"tags":[],"attachments":[]}}"#,
+            bytes,
+            md5(&bytes)
+        ),
+    )
+    .unwrap();
+    let manager = make_manager();
+    let doc_id = manager.import_doc_portable(&path).await.unwrap();
+    let editor = open_editor(&manager, &doc_id, None).await;
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"v1\n"}]"#);
+}
+
+#[tokio::test]
+async fn portable_document_of_newer_format_test() {
+    let path = portable_path();
+    std::fs::write(
+        &path,
+        r#"{"format_version":99,"meta":{},"revisions":[],"tags":[],"attachments":[],"pages":[]}"#,
+    )
+    .unwrap();
+    let error = make_manager().import_doc_portable(&path).await.unwrap_err();
+    assert_eq!(error.code, ErrorCode::UnsupportedFormatVersion.value());
+}
+
+fn make_manager() -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        ..Default::default()
+    };
+    make_document_manager(Arc::new(DocumentCloudServiceMock()), config)
+}
+
+async fn tagged_versions(manager: &DocumentManager, doc_id: &str) -> Vec<(String, String)> {
+    manager
+        .tagged_versions(doc_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(tag, operations)| (tag, operations.json_str()))
+        .collect()
+}
+
+fn portable_path() -> PathBuf {
+    let dir = PathBuf::from(make_temp_dir());
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(format!("{}.{}", DOC_ID, PORTABLE_DOCUMENT_EXTENSION))
+}
+
+async fn open_editor(manager: &DocumentManager, doc_id: &str, content: Option<String>) -> Arc<DeltaDocumentEditor> {
+    if let Some(content) = content {
+        manager
+            .create_document(doc_id, vec![Revision::initial_revision(doc_id, Bytes::from(content))])
+            .await
+            .unwrap();
+    }
+    let editor = manager.open_document_editor(doc_id).await.unwrap();
+    editor
+        .as_any()
+        .downcast_ref::<Arc<DeltaDocumentEditor>>()
+        .unwrap()
+        .clone()
+}
@@ -0,0 +1,87 @@
+use crate::old_document::mock::{create_delta_editor, make_delta_document_manager, open_delta_editor};
+use flowy_document::errors::ErrorCode;
+use lib_ot::core::{AttributeHashMap, Interval};
+use lib_ot::text_delta::{BuildInTextAttribute, DeltaTextOperationBuilder};
+
+const DOC_ID: &str = "preview_doc";
+
+#[tokio::test]
+async fn preview_without_middle_revision_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"a\n"}]"#).await;
+    editor.insert(1, "b").await.unwrap();
+    let b_rev_id = editor.rev_manager().rev_id();
+    editor.insert(2, "c").await.unwrap();
+    let c_rev_id = editor.rev_manager().rev_id();
+    editor.insert(0, "0").await.unwrap();
+
+    // The "c" moves to where it would have been inserted without the "b".
+    let preview = manager.preview_without(DOC_ID, b_rev_id).await.unwrap();
+    assert_eq!(preview.json_str(), r#"[{"insert":"0ac\n"}]"#);
+
+    let preview = manager.preview_without(DOC_ID, c_rev_id).await.unwrap();
+    assert_eq!(preview.json_str(), r#"[{"insert":"0ab\n"}]"#);
+
+    // The document itself doesn't change.
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"0abc\n"}]"#);
+}
+
+#[tokio::test]
+async fn preview_without_revision_edited_later_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"a\n"}]"#).await;
+    editor.insert(1, "bc").await.unwrap();
+    let rev_id = editor.rev_manager().rev_id();
+    // Deletes the text inserted by the removed revision, there's nothing left to delete.
+    editor.delete(Interval::new(1, 3)).await.unwrap();
+    editor.insert(1, "d").await.unwrap();
+
+    let preview = manager.preview_without(DOC_ID, rev_id).await.unwrap();
+    assert_eq!(preview.json_str(), r#"[{"insert":"ad\n"}]"#);
+}
+
+#[tokio::test]
+async fn preview_without_unknown_revision_test() {
+    let manager = make_delta_document_manager();
+    let _ = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"a\n"}]"#).await;
+    assert!(manager.preview_without(DOC_ID, 10).await.is_err());
+}
+
+#[tokio::test]
+async fn content_bytes_test() {
+    let manager = make_delta_document_manager();
+    let _ = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"héllo 👋\n"}]"#).await;
+    assert_eq!(manager.content_bytes(DOC_ID, 0, 3).await.unwrap(), "hé".as_bytes());
+    assert_eq!(manager.content_bytes(DOC_ID, 7, 5).await.unwrap(), "👋\n".as_bytes());
+
+    // The range that starts in the middle of the "é" is refused.
+    let error = manager.content_bytes(DOC_ID, 2, 2).await.unwrap_err();
+    assert_eq!(error.code, ErrorCode::InvalidBoundary.value());
+}
+
+#[tokio::test]
+async fn closed_document_preview_and_meta_test() {
+    let mut archived = AttributeHashMap::new();
+    archived.insert_entry(BuildInTextAttribute::Archived(true));
+    let operations = DeltaTextOperationBuilder::new()
+        .insert_with_attributes(&"archived intro ".repeat(20), archived)
+        .insert("hello world\n")
+        .build();
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, &operations.json_str()).await;
+    editor.insert(300, "👋 ").await.unwrap();
+    editor.insert(303, "the ").await.unwrap();
+    drop(editor);
+    manager.close_document_editor(DOC_ID).await.unwrap();
+
+    // The closed document is read past the archived intro without opening it.
+    let closed_preview = manager.document_preview(DOC_ID, 3).await.unwrap();
+    let closed_meta = manager.document_meta(DOC_ID).await.unwrap();
+    assert_eq!(closed_preview.text, "👋 t");
+    assert!(closed_preview.truncated);
+
+    let _editor = open_delta_editor(&manager, DOC_ID).await;
+    assert_eq!(manager.document_preview(DOC_ID, 3).await.unwrap(), closed_preview);
+    assert_eq!(manager.document_meta(DOC_ID).await.unwrap(), closed_meta);
+    assert_eq!(closed_meta.num_of_words, 4);
+}
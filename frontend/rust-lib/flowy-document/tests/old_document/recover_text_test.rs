@@ -0,0 +1,101 @@
+use crate::old_document::mock::{make_delta_document_manager, make_delta_document_manager_at, make_temp_dir};
+use bytes::Bytes;
+use flowy_database::{sql_query, RunQueryDsl};
+use flowy_document::errors::ErrorCode;
+use flowy_document::{DocumentManager, RECOVERED_MARKER, RECOVERED_UNREADABLE};
+use flowy_http_model::revision::Revision;
+
+const DOC_ID: &str = "recover_text_doc";
+
+#[tokio::test]
+async fn recover_text_from_broken_chain_test() {
+    let manager = make_delta_document_manager();
+    create_broken_document(&manager).await;
+    let error = manager.open_document_editor(DOC_ID).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::DocumentUnreadable.value());
+
+    let path = manager.recover_text(DOC_ID).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), expected_text());
+}
+
+#[tokio::test]
+async fn recover_text_includes_quarantined_revisions_test() {
+    let manager = make_delta_document_manager();
+    create_broken_document(&manager).await;
+    let entries = manager.repair_all_documents().unwrap();
+    assert_eq!(entries.len(), 1);
+
+    // The quarantined revision is still marked in the recovered text.
+    let path = manager.recover_text(DOC_ID).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), expected_text());
+}
+
+#[tokio::test]
+async fn recover_text_leaves_database_unchanged_test() {
+    let manager = make_delta_document_manager();
+    create_broken_document(&manager).await;
+    assert_eq!(invalid_rev_ids(&manager), vec![3]);
+
+    let first_path = manager.recover_text(DOC_ID).await.unwrap();
+    let second_path = manager.recover_text(DOC_ID).await.unwrap();
+    assert_ne!(first_path, second_path);
+    assert_eq!(
+        std::fs::read_to_string(&first_path).unwrap(),
+        std::fs::read_to_string(&second_path).unwrap()
+    );
+    assert_eq!(invalid_rev_ids(&manager), vec![3]);
+    assert!(manager.repair_audit().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn recover_text_unknown_document_test() {
+    let manager = make_delta_document_manager();
+    let error = manager.recover_text("unknown_doc").await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::RecordNotFound.value());
+}
+
+#[tokio::test]
+async fn open_document_without_rev_table_test() {
+    let dir = make_temp_dir();
+    let manager = make_delta_document_manager_at(&dir);
+    let revision = Revision::initial_revision(DOC_ID, Bytes::from(r#"[{"insert":"first line\n"}]"#));
+    manager.create_document(DOC_ID, vec![revision]).await.unwrap();
+    let database = flowy_database::init(&dir).unwrap();
+    let conn = database.get_connection().unwrap();
+    sql_query("DROP TABLE rev_table").execute(&*conn).unwrap();
+
+    // The missing revision table is taken as a document that doesn't exist yet, not as an
+    // unreadable document or an internal error.
+    let error = manager.open_document_editor(DOC_ID).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::RecordNotFound.value());
+}
+
+/// The second revision retains more than the length of the document, so the chain can't be
+/// composed, and the third one can't be parsed even after repairing it.
+async fn create_broken_document(manager: &DocumentManager) {
+    let data = vec![
+        Bytes::from(r#"[{"insert":"first line\n"}]"#),
+        Bytes::from(r#"[{"retain":100},{"insert":"second line"}]"#),
+        Bytes::from(b"[{\"insert\":\"12\xff".to_vec()),
+        Bytes::from(r#"[{"retain":11},{"insert":"third line\n"}]"#),
+    ];
+    let revisions = data
+        .into_iter()
+        .enumerate()
+        .map(|(index, bytes)| Revision::new(DOC_ID, index as i64, index as i64 + 1, bytes, ""))
+        .collect::<Vec<_>>();
+    manager.create_document(DOC_ID, revisions).await.unwrap();
+}
+
+fn expected_text() -> String {
+    format!(
+        "{marker} 1\nfirst line\n{marker} 2\nsecond line\n{marker} 3 {unreadable}\n{marker} 4\nthird line\n",
+        marker = RECOVERED_MARKER,
+        unreadable = RECOVERED_UNREADABLE
+    )
+}
+
+fn invalid_rev_ids(manager: &DocumentManager) -> Vec<i64> {
+    let invalid_revisions = manager.validate_all_documents().unwrap();
+    invalid_revisions.iter().map(|revision| revision.rev_id).collect()
+}
@@ -0,0 +1,130 @@
+use crate::old_document::mock::{
+    make_delta_document_manager, make_document_manager, open_delta_editor, DocumentCloudServiceMock,
+};
+use bytes::Bytes;
+use flowy_document::entities::{DocumentVersionPB, RedlinePB, RedlinePayloadPB};
+use flowy_document::errors::{ErrorCode, FlowyError};
+use flowy_document::event_map::{init, DocumentEvent};
+use flowy_document::{DocumentConfig, DocumentManager};
+use flowy_http_model::revision::Revision;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::tokio_default_runtime;
+use std::sync::Arc;
+
+const DOC_ID: &str = "redline_doc";
+const INSERT_REDLINE: &str = r#"[{"insert":"ab"},{"insert":"X","attributes":{"insertion":true}},{"insert":"c\n"}]"#;
+const LATEST: &str = r#"[{"insert":"abX","attributes":{"bold":true}},{"insert":"c\n"}]"#;
+
+#[tokio::test]
+async fn redline_between_revisions_test() {
+    let manager = make_delta_document_manager();
+    create_document(&manager).await;
+
+    let redline = manager.redline(DOC_ID, 1, 2).await.unwrap();
+    assert_eq!(redline.json_str(), INSERT_REDLINE);
+    let redline = manager.redline(DOC_ID, 2, 3).await.unwrap();
+    assert_eq!(
+        redline.json_str(),
+        r#"[{"insert":"abX","attributes":{"bold":true,"format_changed":true}},{"insert":"c\n"}]"#
+    );
+    let redline = manager.redline(DOC_ID, 2, 2).await.unwrap();
+    assert_eq!(redline.json_str(), r#"[{"insert":"abXc\n"}]"#);
+
+    // The redline is never applied to the document.
+    let editor = open_delta_editor(&manager, DOC_ID).await;
+    assert_eq!(editor.document_operations().await.unwrap().json_str(), LATEST);
+}
+
+#[tokio::test]
+async fn attribute_diff_between_revisions_test() {
+    let manager = make_delta_document_manager();
+    create_document(&manager).await;
+
+    let diff = manager.attribute_diff(DOC_ID, 2, 3).await.unwrap();
+    assert_eq!(diff.json_str(), r#"[{"retain":3,"attributes":{"bold":true}}]"#);
+    // The inserted text isn't a formatting change.
+    assert!(manager.attribute_diff(DOC_ID, 1, 2).await.unwrap().is_empty());
+
+    let editor = open_delta_editor(&manager, DOC_ID).await;
+    assert_eq!(editor.document_operations().await.unwrap().json_str(), LATEST);
+}
+
+#[tokio::test]
+async fn redline_error_test() {
+    let manager = make_delta_document_manager();
+    create_document(&manager).await;
+
+    let error = manager.redline(DOC_ID, 3, 2).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::InvalidData.value());
+    let error = manager.attribute_diff(DOC_ID, 3, 2).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::InvalidData.value());
+
+    let error = manager.redline(DOC_ID, 1, 4).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::RecordNotFound.value());
+    let error = manager.attribute_diff(DOC_ID, 1, 4).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::RecordNotFound.value());
+
+    // The documents in the node format don't keep their versions as deltas.
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V1,
+        ..Default::default()
+    };
+    let manager = make_document_manager(Arc::new(DocumentCloudServiceMock()), config);
+    let error = manager.redline(DOC_ID, 1, 2).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::Internal.value());
+    let error = manager.attribute_diff(DOC_ID, 1, 2).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::Internal.value());
+}
+
+#[test]
+fn get_redline_event_test() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let manager = runtime.block_on(async {
+        let manager = make_delta_document_manager();
+        create_document(&manager).await;
+        // The manager isn't initialized, so the events would be held until it is.
+        manager.startup_gate().open().await;
+        Arc::new(manager)
+    });
+    let dispatcher = Arc::new(AFPluginDispatcher::construct(tokio_default_runtime().unwrap(), || {
+        vec![init(manager)]
+    }));
+    let token = dispatcher.mint_capability_token(&[AFPluginCapability::Read]);
+
+    let send = |from_rev_id: i64, to_rev_id: i64| {
+        let payload = RedlinePayloadPB {
+            doc_id: DOC_ID.to_owned(),
+            from_rev_id,
+            to_rev_id,
+        };
+        let request = AFPluginRequest::new(DocumentEvent::GetRedline)
+            .payload(payload.into_bytes().unwrap())
+            .capability_token(token.clone());
+        AFPluginDispatcher::sync_send(dispatcher.clone(), request)
+            .parse::<RedlinePB, FlowyError>()
+            .unwrap()
+    };
+
+    let redline = send(1, 2).unwrap();
+    assert_eq!(redline.doc_id, DOC_ID);
+    assert_eq!(redline.operations, INSERT_REDLINE);
+
+    let error = send(3, 2).err().unwrap();
+    assert_eq!(error.code, ErrorCode::InvalidData.value());
+}
+
+/// Creates the document with three revisions: the text "abc", the "X" inserted after "ab" and
+/// the "abX" bolded.
+async fn create_document(manager: &DocumentManager) {
+    let data = vec![
+        Bytes::from(r#"[{"insert":"abc\n"}]"#),
+        Bytes::from(r#"[{"retain":2},{"insert":"X"}]"#),
+        Bytes::from(r#"[{"retain":3,"attributes":{"bold":true}}]"#),
+    ];
+    let revisions = data
+        .into_iter()
+        .enumerate()
+        .map(|(index, bytes)| Revision::new(DOC_ID, index as i64, index as i64 + 1, bytes, ""))
+        .collect::<Vec<_>>();
+    manager.create_document(DOC_ID, revisions).await.unwrap();
+}
@@ -0,0 +1,210 @@
+use crate::old_document::mock::{make_document_manager_at, make_temp_dir, DocumentCloudServiceMock};
+use bytes::Bytes;
+use flowy_database::{sql_query, RunQueryDsl};
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::FlowyError;
+use flowy_document::{
+    DocumentConfig, DocumentExportTarget, DocumentExportTargets, DocumentManager, MarkdownMirror, ReexportSummary,
+    MARKDOWN_MIRROR_DIR,
+};
+use flowy_http_model::revision::Revision;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn reexport_skips_unchanged_documents_test() {
+    let dir = make_temp_dir();
+    let target = Arc::new(ExportTargetMock::default());
+    let manager = make_manager(&dir, 1, target.clone());
+    create_document(&manager, "doc_a", r#"[{"insert":"a\n"}]"#).await;
+    create_document(&manager, "doc_b", r#"[{"insert":"b\n"}]"#).await;
+
+    let summary = manager.reexport_documents().await.unwrap();
+    assert_eq!(summary, make_summary(2, 0, 0, false));
+    assert_eq!(target.written_doc_ids(), vec!["doc_a", "doc_b"]);
+    assert!(target.markdown_of("doc_a").contains('a'));
+
+    // Only the new document is exported by the second run.
+    create_document(&manager, "doc_c", r#"[{"insert":"c\n"}]"#).await;
+    let summary = manager.reexport_documents().await.unwrap();
+    assert_eq!(summary, make_summary(1, 2, 0, false));
+    assert_eq!(target.written_doc_ids(), vec!["doc_a", "doc_b", "doc_c"]);
+}
+
+#[tokio::test]
+async fn reexport_regenerates_markdown_mirror_test() {
+    let dir = make_temp_dir();
+    let manager = make_manager(&dir, 1, Arc::new(ExportTargetMock::default()));
+    create_document(
+        &manager,
+        "doc_a",
+        r#"[{"insert":"a"},{"insert":"\n","attributes":{"list":"bullet"}}]"#,
+    )
+    .await;
+    let _ = manager.reexport_documents().await.unwrap();
+    let mirror = MarkdownMirror::new(Path::new(&dir).join(MARKDOWN_MIRROR_DIR));
+    assert_eq!(std::fs::read_to_string(mirror.path_of("doc_a")).unwrap(), "* a\n");
+    drop(manager);
+
+    // The stale mirror file is rewritten by the re-export of the new exporter version.
+    std::fs::write(mirror.path_of("doc_a"), "stale").unwrap();
+    let manager = make_manager(&dir, 2, Arc::new(ExportTargetMock::default()));
+    let _ = manager.reexport_documents().await.unwrap();
+    assert_eq!(std::fs::read_to_string(mirror.path_of("doc_a")).unwrap(), "* a\n");
+}
+
+#[tokio::test]
+async fn reexport_without_markdown_mirror_test() {
+    let dir = make_temp_dir();
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        export_targets: DocumentExportTargets::default().with_markdown_mirror(false),
+        ..Default::default()
+    };
+    let manager = make_document_manager_at(&dir, Arc::new(DocumentCloudServiceMock()), config);
+    create_document(&manager, "doc_a", r#"[{"insert":"a\n"}]"#).await;
+    let summary = manager.reexport_documents().await.unwrap();
+    assert_eq!(summary, make_summary(1, 0, 0, false));
+    assert!(!Path::new(&dir).join(MARKDOWN_MIRROR_DIR).exists());
+}
+
+#[tokio::test]
+async fn reexport_after_exporter_version_bump_test() {
+    let dir = make_temp_dir();
+    let target = Arc::new(ExportTargetMock::default());
+    let manager = make_manager(&dir, 1, target.clone());
+    for doc_id in ["doc_a", "doc_b", "doc_c"] {
+        create_document(&manager, doc_id, r#"[{"insert":"abc\n"}]"#).await;
+    }
+    let _ = manager.reexport_documents().await.unwrap();
+    drop(manager);
+
+    // The re-export is cancelled after the first document of the new version.
+    let target = Arc::new(ExportTargetMock::default());
+    let manager = Arc::new(make_manager(&dir, 2, target.clone()));
+    *target.cancel_after_write.lock().unwrap() = Some(manager.clone());
+    let summary = manager.reexport_documents().await.unwrap();
+    assert_eq!(summary, make_summary(1, 0, 0, true));
+    assert_eq!(target.written_doc_ids(), vec!["doc_a"]);
+
+    // Running it again only regenerates the documents that still have the old version.
+    let summary = manager.reexport_documents().await.unwrap();
+    assert_eq!(summary, make_summary(2, 1, 0, false));
+    assert_eq!(target.written_doc_ids(), vec!["doc_a", "doc_b", "doc_c"]);
+
+    let summary = manager.reexport_documents().await.unwrap();
+    assert_eq!(summary, make_summary(0, 3, 0, false));
+}
+
+#[tokio::test]
+async fn reexport_retries_failed_documents_test() {
+    let dir = make_temp_dir();
+    let target = Arc::new(ExportTargetMock::default());
+    *target.failing_doc_id.lock().unwrap() = Some("doc_b".to_owned());
+    let manager = make_manager(&dir, 1, target.clone());
+    create_document(&manager, "doc_a", r#"[{"insert":"a\n"}]"#).await;
+    create_document(&manager, "doc_b", r#"[{"insert":"b\n"}]"#).await;
+
+    let summary = manager.reexport_documents().await.unwrap();
+    assert_eq!(summary, make_summary(1, 0, 1, false));
+
+    // The failed document isn't stamped, so it's exported by the next run.
+    *target.failing_doc_id.lock().unwrap() = None;
+    let summary = manager.reexport_documents().await.unwrap();
+    assert_eq!(summary, make_summary(1, 1, 0, false));
+    assert_eq!(target.written_doc_ids(), vec!["doc_a", "doc_b"]);
+}
+
+#[tokio::test]
+async fn reexport_skips_resolved_revisions_test() {
+    let dir = make_temp_dir();
+    let target = Arc::new(ExportTargetMock::default());
+    let manager = make_manager(&dir, 1, target.clone());
+    let revisions = vec![
+        Revision::initial_revision("doc_a", Bytes::from(r#"[{"insert":"a\n"}]"#)),
+        Revision::new("doc_a", 1, 2, Bytes::from(r#"[{"insert":"b"}]"#), ""),
+    ];
+    manager.create_document("doc_a", revisions).await.unwrap();
+
+    // The revision 2 was superseded by resolving a conflict, it isn't part of the document.
+    let database = flowy_database::init(&dir).unwrap();
+    let conn = database.get_connection().unwrap();
+    sql_query("UPDATE rev_table SET state = 2 WHERE rev_id = 2")
+        .execute(&*conn)
+        .unwrap();
+
+    let _ = manager.reexport_documents().await.unwrap();
+    assert!(!target.markdown_of("doc_a").contains('b'));
+}
+
+#[derive(Default)]
+pub struct ExportTargetMock {
+    written: Mutex<Vec<(String, String)>>,
+    failing_doc_id: Mutex<Option<String>>,
+    cancel_after_write: Mutex<Option<Arc<DocumentManager>>>,
+}
+
+impl ExportTargetMock {
+    fn written_doc_ids(&self) -> Vec<String> {
+        self.written
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(doc_id, _)| doc_id.clone())
+            .collect()
+    }
+
+    pub fn markdown_of(&self, doc_id: &str) -> String {
+        let written = self.written.lock().unwrap();
+        let (_, markdown) = written.iter().find(|(other, _)| other == doc_id).unwrap();
+        markdown.clone()
+    }
+}
+
+impl DocumentExportTarget for ExportTargetMock {
+    fn write(&self, doc_id: &str, markdown: &str) -> Result<(), FlowyError> {
+        if self.failing_doc_id.lock().unwrap().as_deref() == Some(doc_id) {
+            return Err(FlowyError::internal().context("The disk is full"));
+        }
+        self.written
+            .lock()
+            .unwrap()
+            .push((doc_id.to_owned(), markdown.to_owned()));
+        if let Some(manager) = self.cancel_after_write.lock().unwrap().take() {
+            manager.cancel_reexport();
+        }
+        Ok(())
+    }
+}
+
+pub fn make_manager(dir: &str, exporter_version: i64, target: Arc<ExportTargetMock>) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        export_targets: DocumentExportTargets::default()
+            .with_target(target)
+            .with_exporter_version(exporter_version),
+        ..Default::default()
+    };
+    make_document_manager_at(dir, Arc::new(DocumentCloudServiceMock()), config)
+}
+
+fn make_summary(
+    num_of_regenerated: usize,
+    num_of_skipped: usize,
+    num_of_failed: usize,
+    cancelled: bool,
+) -> ReexportSummary {
+    ReexportSummary {
+        num_of_regenerated,
+        num_of_skipped,
+        num_of_failed,
+        cancelled,
+    }
+}
+
+async fn create_document(manager: &DocumentManager, doc_id: &str, json: &'static str) {
+    manager
+        .create_document(doc_id, vec![Revision::initial_revision(doc_id, Bytes::from(json))])
+        .await
+        .unwrap();
+}
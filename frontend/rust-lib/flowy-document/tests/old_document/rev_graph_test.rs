@@ -0,0 +1,88 @@
+use crate::old_document::mock::make_delta_document_manager;
+use bytes::Bytes;
+use flowy_document::errors::ErrorCode;
+use flowy_document::DocumentManager;
+use flowy_http_model::revision::Revision;
+
+const DOC_ID: &str = "rev_graph_doc";
+
+#[tokio::test]
+async fn rev_graph_fork_gap_and_quarantine_test() {
+    let manager = make_delta_document_manager();
+    create_forked_document(&manager).await;
+    assert_eq!(manager.repair_all_documents().unwrap().len(), 1);
+
+    let dot = manager.export_rev_graph(DOC_ID, None).await.unwrap();
+    assert!(dot.starts_with("digraph \"rev_graph_doc\" {"));
+
+    // The revisions 2 and 3 are both based on the revision 1.
+    assert!(dot.contains("  r1 -> r2;"));
+    assert!(dot.contains("  r1 -> r3;"));
+    assert!(node_line(&dot, "r1").contains("\\nfork\", color=purple"));
+    assert!(!node_line(&dot, "r2").contains("fork"));
+
+    // The revision 4 that the revision 5 is based on doesn't exist.
+    assert!(dot.contains("  r4 -> r5 [style=dashed, color=red];"));
+    assert!(dot.contains("  r4 [label=\"4\\nmissing\", style=dashed, color=red];"));
+
+    let quarantined = node_line(&dot, "r6");
+    assert!(quarantined.contains("unknown"));
+    assert!(quarantined.contains("\\nquarantined\", style=\"filled,dashed\", fillcolor=gray80"));
+    assert!(dot.contains("  r5 -> r6;"));
+}
+
+#[tokio::test]
+async fn rev_graph_tail_test() {
+    let manager = make_delta_document_manager();
+    create_forked_document(&manager).await;
+
+    let dot = manager.export_rev_graph(DOC_ID, Some(2)).await.unwrap();
+    assert!(dot.contains("  truncated [label=\"3 earlier revisions\", shape=plaintext];"));
+    assert!(!dot.contains("r1"));
+    // The gap is still found without the revisions before it.
+    assert!(dot.contains("  r4 -> r5 [style=dashed, color=red];"));
+    assert!(dot.contains("  r5 -> r6;"));
+}
+
+#[tokio::test]
+async fn rev_graph_shared_payload_test() {
+    let manager = make_delta_document_manager();
+    let content = "a".repeat(300);
+    let bytes = Bytes::from(format!(r#"[{{"insert":"{}\n"}}]"#, content));
+    let size = bytes.len();
+    let revision = Revision::new(DOC_ID, 0, 1, bytes, "");
+    manager.create_document(DOC_ID, vec![revision]).await.unwrap();
+    assert_eq!(manager.dedupe_revision_payloads().unwrap().num_of_revisions, 1);
+
+    // The length and the type are the ones of the shared payload, not of the hash.
+    let dot = manager.export_rev_graph(DOC_ID, None).await.unwrap();
+    assert!(node_line(&dot, "r1").contains(&format!(" {}B", size)));
+}
+
+#[tokio::test]
+async fn rev_graph_unknown_document_test() {
+    let manager = make_delta_document_manager();
+    let error = manager.export_rev_graph("unknown_doc", None).await.err().unwrap();
+    assert_eq!(error.code, ErrorCode::RecordNotFound.value());
+}
+
+/// Two devices edited the revision 1, the revision 4 is missing and the revision 6 can't be
+/// parsed, so it gets quarantined by the repair.
+async fn create_forked_document(manager: &DocumentManager) {
+    let revisions = vec![
+        (0, 1, Bytes::from(r#"[{"insert":"first line\n"}]"#)),
+        (1, 2, Bytes::from(r#"[{"retain":11},{"insert":"device a\n"}]"#)),
+        (1, 3, Bytes::from(r#"[{"retain":11},{"insert":"device b\n"}]"#)),
+        (4, 5, Bytes::from(r#"[{"retain":20},{"insert":"after the gap\n"}]"#)),
+        (5, 6, Bytes::from(b"[{\"insert\":\"12\xff".to_vec())),
+    ]
+    .into_iter()
+    .map(|(base_rev_id, rev_id, bytes)| Revision::new(DOC_ID, base_rev_id, rev_id, bytes, ""))
+    .collect::<Vec<_>>();
+    manager.create_document(DOC_ID, revisions).await.unwrap();
+}
+
+fn node_line<'a>(dot: &'a str, id: &str) -> &'a str {
+    let prefix = format!("  {} [", id);
+    dot.lines().find(|line| line.starts_with(&prefix)).unwrap()
+}
@@ -0,0 +1,185 @@
+use crate::old_document::mock::make_document_manager;
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::FlowyError;
+use flowy_document::old_editor::editor::DeltaDocumentEditor;
+use flowy_document::{DocumentCloudService, DocumentConfig, DocumentEditor, DocumentManager, FetchOverwritePolicy};
+use flowy_http_model::document::{CreateDocumentParams, DocumentId, DocumentPayload, ResetDocumentParams};
+use flowy_http_model::revision::Revision;
+use lib_infra::future::FutureResult;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::time::{sleep, Duration};
+
+const DOC_ID: &str = "revalidate_doc";
+const LOCAL_DOCUMENT: &str = r#"[{"insert":"123\n"}]"#;
+const SERVER_DOCUMENT: &str = r#"[{"insert":"1234\n"}]"#;
+
+#[tokio::test]
+async fn revalidate_open_with_local_document_test() {
+    let test = RevalidateTest::new(FetchOverwritePolicy::default()).await;
+    let editor = test.open_editor().await;
+    // The server hasn't responded yet.
+    assert_eq!(editor.export().await.unwrap(), LOCAL_DOCUMENT);
+
+    test.cloud_service.respond();
+    test.wait_until_exported(&editor, SERVER_DOCUMENT).await;
+    assert_eq!(editor.rev_manager().rev_id(), 5);
+    // The document is fetched once.
+    assert_eq!(test.cloud_service.num_of_responses(), 1);
+}
+
+#[tokio::test]
+async fn revalidate_cancelled_on_close_test() {
+    let test = RevalidateTest::new(FetchOverwritePolicy::default()).await;
+    let editor = test.open_editor().await;
+    test.manager.close_document_editor(DOC_ID).await.unwrap();
+
+    test.cloud_service.respond();
+    sleep(Duration::from_millis(300)).await;
+    assert_eq!(test.cloud_service.num_of_responses(), 0);
+    assert_eq!(editor.export().await.unwrap(), LOCAL_DOCUMENT);
+}
+
+#[tokio::test]
+async fn revalidate_preserve_unsynced_document_test() {
+    let test = RevalidateTest::new(FetchOverwritePolicy::Preserve).await;
+    let editor = test.open_editor().await;
+    editor.insert(0, "0").await.unwrap();
+
+    test.cloud_service.respond();
+    test.wait_until_responded(1).await;
+    sleep(Duration::from_millis(300)).await;
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"0123\n"}]"#);
+    assert_eq!(editor.rev_manager().rev_id(), 2);
+    assert_eq!(editor.rev_manager().next_sync_rev_id().await, Some(2));
+}
+
+#[tokio::test]
+async fn revalidate_merge_unsynced_document_test() {
+    let test = RevalidateTest::new(FetchOverwritePolicy::Merge).await;
+    let editor = test.open_editor().await;
+    editor.insert(0, "0").await.unwrap();
+
+    test.cloud_service.respond();
+    test.wait_until_exported(&editor, r#"[{"insert":"01234\n"}]"#).await;
+    // The local change is saved on top of the server's revision and waits to be synced.
+    assert_eq!(editor.rev_manager().rev_id(), 6);
+    assert_eq!(editor.rev_manager().next_sync_rev_id().await, Some(6));
+}
+
+#[tokio::test]
+async fn revalidate_overwrite_unsynced_document_test() {
+    let test = RevalidateTest::new(FetchOverwritePolicy::Overwrite).await;
+    let editor = test.open_editor().await;
+    editor.insert(0, "0").await.unwrap();
+
+    test.cloud_service.respond();
+    test.wait_until_exported(&editor, SERVER_DOCUMENT).await;
+    assert_eq!(editor.rev_manager().rev_id(), 5);
+    assert_eq!(editor.rev_manager().next_sync_rev_id().await, None);
+}
+
+struct RevalidateTest {
+    manager: DocumentManager,
+    cloud_service: Arc<DelayedDocumentCloudService>,
+}
+
+impl RevalidateTest {
+    async fn new(fetch_overwrite_policy: FetchOverwritePolicy) -> Self {
+        let cloud_service = Arc::new(DelayedDocumentCloudService::default());
+        let config = DocumentConfig {
+            version: DocumentVersionPB::V0,
+            revalidate_on_open: true,
+            fetch_overwrite_policy,
+            ..Default::default()
+        };
+        let manager = make_document_manager(cloud_service.clone(), config);
+        let revision = Revision::new(DOC_ID, 0, 1, Bytes::from(LOCAL_DOCUMENT), "");
+        manager.create_document(DOC_ID, vec![revision]).await.unwrap();
+        Self { manager, cloud_service }
+    }
+
+    /// Opens the document whose local revision was synced before.
+    async fn open_editor(&self) -> Arc<DeltaDocumentEditor> {
+        let editor = self.manager.open_document_editor(DOC_ID).await.unwrap();
+        let editor = editor
+            .as_any()
+            .downcast_ref::<Arc<DeltaDocumentEditor>>()
+            .unwrap()
+            .clone();
+        editor.rev_manager().ack_revision(1).await.unwrap();
+        editor
+    }
+
+    async fn wait_until_exported(&self, editor: &Arc<DeltaDocumentEditor>, expected: &str) {
+        for _ in 0..50 {
+            if editor.export().await.unwrap() == expected {
+                return;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        panic!("The document wasn't refreshed with {}", expected);
+    }
+
+    async fn wait_until_responded(&self, num_of_responses: usize) {
+        for _ in 0..50 {
+            if self.cloud_service.num_of_responses() >= num_of_responses {
+                return;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        panic!("The server didn't respond");
+    }
+}
+
+/// Holds the responses of the `fetch_document` until `respond` gets called.
+struct DelayedDocumentCloudService {
+    gate: Arc<Semaphore>,
+    num_of_responses: Arc<AtomicUsize>,
+}
+
+impl std::default::Default for DelayedDocumentCloudService {
+    fn default() -> Self {
+        Self {
+            gate: Arc::new(Semaphore::new(0)),
+            num_of_responses: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+impl DelayedDocumentCloudService {
+    fn respond(&self) {
+        self.gate.add_permits(10);
+    }
+
+    fn num_of_responses(&self) -> usize {
+        self.num_of_responses.load(Ordering::SeqCst)
+    }
+}
+
+impl DocumentCloudService for DelayedDocumentCloudService {
+    fn create_document(&self, _token: &str, _params: CreateDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    fn fetch_document(&self, _token: &str, params: DocumentId) -> FutureResult<Option<DocumentPayload>, FlowyError> {
+        let gate = self.gate.clone();
+        let num_of_responses = self.num_of_responses.clone();
+        FutureResult::new(async move {
+            let _permit = gate.acquire().await.unwrap();
+            num_of_responses.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(DocumentPayload {
+                doc_id: params.value,
+                data: SERVER_DOCUMENT.as_bytes().to_vec(),
+                rev_id: 5,
+                base_rev_id: 4,
+            }))
+        })
+    }
+
+    fn update_document_content(&self, _token: &str, _params: ResetDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+}
@@ -0,0 +1,152 @@
+use crate::old_document::mock::{make_document_manager, DocumentCloudServiceMock};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::ErrorCode;
+use flowy_document::old_editor::editor::DeltaDocumentEditor;
+use flowy_document::{
+    DocumentConfig, DocumentEditor, DocumentManager, GuardDecision, RegexRevisionGuard, RevisionGuard, RevisionGuards,
+};
+use flowy_http_model::revision::Revision;
+use lib_ot::core::Interval;
+use lib_ot::text_delta::DeltaTextOperations;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DOC_ID: &str = "guarded_doc";
+const CARD_NUMBER: &str = "4111 1111 1111 1111";
+
+#[tokio::test]
+async fn revision_guard_allow_test() {
+    let guards = RevisionGuards::default().with_guard(Arc::new(RegexRevisionGuard::reject(
+        RegexRevisionGuard::credit_card_pattern(),
+    )));
+    let test = RevisionGuardTest::new(guards).await;
+    test.editor.insert(0, "abc").await.unwrap();
+    assert_eq!(test.export().await, r#"[{"insert":"abc\n"}]"#);
+    assert_eq!(test.editor.rev_manager().rev_id(), 2);
+}
+
+#[tokio::test]
+async fn revision_guard_reject_test() {
+    let guards = RevisionGuards::default().with_guard(Arc::new(RegexRevisionGuard::reject(
+        RegexRevisionGuard::credit_card_pattern(),
+    )));
+    let test = RevisionGuardTest::new(guards).await;
+    let error = test.editor.insert(0, CARD_NUMBER).await.unwrap_err();
+    assert_eq!(error.code, ErrorCode::RevisionRejected.value());
+
+    // The rejected change is neither applied nor saved.
+    assert_eq!(test.export().await, r#"[{"insert":"\n"}]"#);
+    assert_eq!(test.editor.rev_manager().rev_id(), 1);
+    assert!(!test.editor.can_undo().await);
+}
+
+#[tokio::test]
+async fn revision_guard_reject_typed_in_parts_test() {
+    let guards = RevisionGuards::default().with_guard(Arc::new(RegexRevisionGuard::reject(
+        RegexRevisionGuard::credit_card_pattern(),
+    )));
+    let test = RevisionGuardTest::new(guards).await;
+    for (index, c) in "4111 1111 1111".chars().enumerate() {
+        test.editor.insert(index, c).await.unwrap();
+    }
+
+    // The change that completes the number is rejected, though it doesn't match by itself.
+    let error = test.editor.insert(14, " 1111").await.unwrap_err();
+    assert_eq!(error.code, ErrorCode::RevisionRejected.value());
+    assert_eq!(test.export().await, r#"[{"insert":"4111 1111 1111\n"}]"#);
+
+    // So is the change that joins its parts.
+    test.editor.insert(14, "x1111").await.unwrap();
+    let error = test.editor.replace(Interval::new(14, 15), " ").await.unwrap_err();
+    assert_eq!(error.code, ErrorCode::RevisionRejected.value());
+}
+
+#[tokio::test]
+async fn revision_guard_redact_completed_test() {
+    let guards = RevisionGuards::default().with_guard(Arc::new(RegexRevisionGuard::redact(
+        RegexRevisionGuard::credit_card_pattern(),
+    )));
+    let test = RevisionGuardTest::new(guards).await;
+    test.editor.insert(0, "card 4111 1111").await.unwrap();
+    test.editor.insert(14, " 1111 1111").await.unwrap();
+
+    // The part that was already in the document is masked too.
+    assert_eq!(test.export().await, r#"[{"insert":"card *******************\n"}]"#);
+}
+
+#[tokio::test]
+async fn revision_guard_redact_test() {
+    let guards = RevisionGuards::default().with_guard(Arc::new(RegexRevisionGuard::redact(
+        RegexRevisionGuard::credit_card_pattern(),
+    )));
+    let test = RevisionGuardTest::new(guards).await;
+    test.editor.insert(0, format!("card {}", CARD_NUMBER)).await.unwrap();
+    assert_eq!(test.export().await, r#"[{"insert":"card *******************\n"}]"#);
+
+    test.editor.rev_manager().flush().await.unwrap();
+    let revisions = test.editor.rev_manager().load_revisions().await.unwrap();
+    let saved = revisions.last().unwrap();
+    assert_eq!(saved.bytes, Bytes::from(r#"[{"insert":"card *******************"}]"#));
+}
+
+#[tokio::test]
+async fn revision_guard_time_budget_fail_closed_test() {
+    let guards = RevisionGuards::new(Duration::from_millis(20), false).with_guard(Arc::new(SlowRevisionGuard()));
+    let test = RevisionGuardTest::new(guards).await;
+    let error = test.editor.insert(0, "abc").await.unwrap_err();
+    assert_eq!(error.code, ErrorCode::RevisionRejected.value());
+    assert_eq!(test.export().await, r#"[{"insert":"\n"}]"#);
+}
+
+#[tokio::test]
+async fn revision_guard_time_budget_fail_open_test() {
+    let guards = RevisionGuards::new(Duration::from_millis(20), true).with_guard(Arc::new(SlowRevisionGuard()));
+    let test = RevisionGuardTest::new(guards).await;
+    test.editor.insert(0, "abc").await.unwrap();
+    assert_eq!(test.export().await, r#"[{"insert":"abc\n"}]"#);
+}
+
+struct RevisionGuardTest {
+    #[allow(dead_code)]
+    manager: DocumentManager,
+    editor: Arc<DeltaDocumentEditor>,
+}
+
+impl RevisionGuardTest {
+    async fn new(revision_guards: RevisionGuards) -> Self {
+        let config = DocumentConfig {
+            version: DocumentVersionPB::V0,
+            revision_guards,
+            ..Default::default()
+        };
+        let manager = make_document_manager(Arc::new(DocumentCloudServiceMock()), config);
+        let revision = Revision::new(DOC_ID, 0, 1, Bytes::from(r#"[{"insert":"\n"}]"#), "");
+        manager.create_document(DOC_ID, vec![revision]).await.unwrap();
+        let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+        let editor = editor
+            .as_any()
+            .downcast_ref::<Arc<DeltaDocumentEditor>>()
+            .unwrap()
+            .clone();
+        Self { manager, editor }
+    }
+
+    async fn export(&self) -> String {
+        self.editor.export().await.unwrap()
+    }
+}
+
+/// Takes longer than the time budget of the tests.
+struct SlowRevisionGuard();
+impl RevisionGuard for SlowRevisionGuard {
+    fn check(
+        &self,
+        _doc_id: &str,
+        _document: &DeltaTextOperations,
+        _operations: &DeltaTextOperations,
+    ) -> GuardDecision {
+        std::thread::sleep(Duration::from_millis(200));
+        GuardDecision::Allow
+    }
+}
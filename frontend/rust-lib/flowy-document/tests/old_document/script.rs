@@ -1,5 +1,5 @@
 use flowy_document::old_editor::editor::DeltaDocumentEditor;
-use flowy_document::TEXT_BLOCK_SYNC_INTERVAL_IN_MILLIS;
+use flowy_document::{DocumentEditor, TEXT_BLOCK_SYNC_INTERVAL_IN_MILLIS};
 use flowy_revision_persistence::RevisionState;
 use flowy_test::{helper::ViewTest, FlowySDKTest};
 use lib_ot::{core::Interval, text_delta::DeltaTextOperations};
@@ -10,6 +10,9 @@ pub enum EditorScript {
     InsertText(&'static str, usize),
     Delete(Interval),
     Replace(Interval, &'static str),
+    /// Composes the operations that undo the last `n` revisions.
+    Rollback(usize),
+    TrimLeading(usize),
 
     AssertRevisionState(i64, RevisionState),
     AssertNextSyncRevId(Option<i64>),
@@ -56,6 +59,17 @@ impl DeltaDocumentEditorTest {
             EditorScript::Replace(interval, s) => {
                 self.editor.replace(interval, s).await.unwrap();
             }
+            EditorScript::Rollback(n) => {
+                let operations = self.editor.rollback_operations(n).await.unwrap();
+                self.editor
+                    .compose_local_operations(operations.json_bytes())
+                    .await
+                    .unwrap();
+            }
+            EditorScript::TrimLeading(len) => {
+                let revision = self.editor.trim_leading(len).await.unwrap();
+                assert_eq!(revision.rev_id, self.editor.rev_manager().rev_id());
+            }
             EditorScript::AssertRevisionState(rev_id, state) => {
                 let record = cache.get(rev_id).await.unwrap();
                 assert_eq!(record.state, state);
@@ -0,0 +1,103 @@
+use crate::old_document::mock::make_delta_document_manager;
+use bytes::Bytes;
+use flowy_document::entities::RepeatedSnippetPB;
+use flowy_document::{DocumentManager, Snippet};
+use flowy_http_model::revision::Revision;
+use lib_ot::core::Interval;
+use std::convert::TryInto;
+
+const SOURCE_DOC_ID: &str = "snippet_source_doc";
+const TARGET_DOC_ID: &str = "snippet_target_doc";
+
+#[tokio::test]
+async fn snippet_insert_keeps_attributes_test() {
+    let manager = make_delta_document_manager();
+    create_document(
+        &manager,
+        SOURCE_DOC_ID,
+        r#"[{"insert":"Hello "},{"insert":"world","attributes":{"bold":true}},{"insert":"\n"}]"#,
+    )
+    .await;
+    create_document(&manager, TARGET_DOC_ID, r#"[{"insert":"abc\n"}]"#).await;
+
+    let snippet = manager
+        .save_selection_as_snippet(SOURCE_DOC_ID, Interval::new(4, 11), "greeting")
+        .await
+        .unwrap();
+    assert_eq!(snippet.preview(80), "o world");
+    assert_eq!(snippet.preview(3), "o w…");
+
+    manager.insert_snippet(TARGET_DOC_ID, 3, &snippet.id).await.unwrap();
+    let editor = manager.open_document_editor(TARGET_DOC_ID).await.unwrap();
+    assert_eq!(
+        editor.export().await.unwrap(),
+        r#"[{"insert":"abco "},{"insert":"world","attributes":{"bold":true}},{"insert":"\n"}]"#
+    );
+}
+
+#[tokio::test]
+async fn snippet_empty_selection_test() {
+    let manager = make_delta_document_manager();
+    create_document(&manager, SOURCE_DOC_ID, r#"[{"insert":"abc\n"}]"#).await;
+    assert!(manager
+        .save_selection_as_snippet(SOURCE_DOC_ID, Interval::new(1, 1), "empty")
+        .await
+        .is_err());
+    assert!(manager.snippets().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn snippet_insert_out_of_bounds_test() {
+    let manager = make_delta_document_manager();
+    create_document(&manager, SOURCE_DOC_ID, r#"[{"insert":"abc\n"}]"#).await;
+    let snippet = manager
+        .save_selection_as_snippet(SOURCE_DOC_ID, Interval::new(0, 2), "ab")
+        .await
+        .unwrap();
+    assert!(manager.insert_snippet(SOURCE_DOC_ID, 10, &snippet.id).await.is_err());
+}
+
+#[tokio::test]
+async fn snippet_export_and_import_test() {
+    let manager = make_delta_document_manager();
+    create_document(
+        &manager,
+        SOURCE_DOC_ID,
+        r#"[{"insert":"line one\n"},{"insert":"line two","attributes":{"italic":true}},{"insert":"\n"}]"#,
+    )
+    .await;
+    let _ = manager
+        .save_selection_as_snippet(SOURCE_DOC_ID, Interval::new(0, 9), "first")
+        .await
+        .unwrap();
+    let _ = manager
+        .save_selection_as_snippet(SOURCE_DOC_ID, Interval::new(0, 17), "both")
+        .await
+        .unwrap();
+    let snippets = manager.snippets().unwrap();
+    assert_eq!(snippets.len(), 2);
+    let both = snippets.iter().find(|snippet| snippet.name == "both").unwrap();
+    assert_eq!(both.preview(80), "line one line two");
+
+    // The export is the `GetSnippets` payload, the snippets are parsed back from it.
+    let exported = RepeatedSnippetPB::from(snippets.clone());
+    let imported = exported
+        .items
+        .into_iter()
+        .map(|snippet| snippet.try_into().unwrap())
+        .collect::<Vec<Snippet>>();
+    let other_manager = make_delta_document_manager();
+    assert_eq!(other_manager.import_snippets(&imported).unwrap(), 2);
+    assert_eq!(other_manager.snippets().unwrap(), snippets);
+
+    // Importing the same snippets again replaces them instead of adding duplicates.
+    assert_eq!(other_manager.import_snippets(&imported).unwrap(), 2);
+    assert_eq!(other_manager.snippets().unwrap().len(), 2);
+}
+
+async fn create_document(manager: &DocumentManager, doc_id: &str, json: &'static str) {
+    manager
+        .create_document(doc_id, vec![Revision::initial_revision(doc_id, Bytes::from(json))])
+        .await
+        .unwrap();
+}
@@ -0,0 +1,102 @@
+use crate::old_document::mock::{
+    create_delta_editor, make_delta_document_manager, make_delta_document_manager_at, make_temp_dir, open_delta_editor,
+};
+use bytes::Bytes;
+use flowy_document::old_editor::editor::DeltaDocumentEditor;
+use flowy_http_model::revision::Revision;
+
+const DOC_ID: &str = "split_doc";
+const NEW_DOC_ID: &str = "split_new_doc";
+const CONTENT: &str = r#"[{"insert":"abc\n"},{"insert":"def","attributes":{"bold":true}},{"insert":"\n"}]"#;
+
+#[tokio::test]
+async fn split_document_in_the_middle_of_line_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    manager.split_document(DOC_ID, 2, NEW_DOC_ID).await.unwrap();
+
+    assert_eq!(json_of(&editor).await, r#"[{"insert":"ab\n"}]"#);
+    let new_editor = open_delta_editor(&manager, NEW_DOC_ID).await;
+    assert_eq!(
+        json_of(&new_editor).await,
+        r#"[{"insert":"c\n"},{"insert":"def","attributes":{"bold":true}},{"insert":"\n"}]"#
+    );
+}
+
+#[tokio::test]
+async fn split_document_at_line_start_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    manager.split_document(DOC_ID, 4, NEW_DOC_ID).await.unwrap();
+
+    // The newline before the index stays in the original, so there's no empty line left behind.
+    assert_eq!(json_of(&editor).await, r#"[{"insert":"abc\n"}]"#);
+    let new_editor = open_delta_editor(&manager, NEW_DOC_ID).await;
+    assert_eq!(
+        json_of(&new_editor).await,
+        r#"[{"insert":"def","attributes":{"bold":true}},{"insert":"\n"}]"#
+    );
+}
+
+#[tokio::test]
+async fn split_document_at_boundaries_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    manager.split_document(DOC_ID, 7, NEW_DOC_ID).await.unwrap();
+    assert_eq!(json_of(&editor).await, CONTENT);
+    let new_editor = open_delta_editor(&manager, NEW_DOC_ID).await;
+    assert_eq!(json_of(&new_editor).await, r#"[{"insert":"\n"}]"#);
+
+    manager.split_document(DOC_ID, 0, "split_third_doc").await.unwrap();
+    assert_eq!(json_of(&editor).await, r#"[{"insert":"\n"}]"#);
+    let third_editor = open_delta_editor(&manager, "split_third_doc").await;
+    assert_eq!(json_of(&third_editor).await, CONTENT);
+}
+
+#[tokio::test]
+async fn split_document_saves_both_documents_test() {
+    let dir = make_temp_dir();
+    let manager = make_delta_document_manager_at(&dir);
+    let _editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    manager.split_document(DOC_ID, 2, NEW_DOC_ID).await.unwrap();
+
+    // Both documents are on disk once the split returns, the revision that removed the text
+    // doesn't wait for the deferred save.
+    let reopened_manager = make_delta_document_manager_at(&dir);
+    let editor = open_delta_editor(&reopened_manager, DOC_ID).await;
+    assert_eq!(json_of(&editor).await, r#"[{"insert":"ab\n"}]"#);
+    let new_editor = open_delta_editor(&reopened_manager, NEW_DOC_ID).await;
+    assert_eq!(
+        json_of(&new_editor).await,
+        r#"[{"insert":"c\n"},{"insert":"def","attributes":{"bold":true}},{"insert":"\n"}]"#
+    );
+}
+
+#[tokio::test]
+async fn split_document_error_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, CONTENT).await;
+    assert!(manager.split_document(DOC_ID, 8, NEW_DOC_ID).await.is_err());
+    assert!(manager.split_document(DOC_ID, 2, DOC_ID).await.is_err());
+
+    manager
+        .create_document(
+            NEW_DOC_ID,
+            vec![Revision::initial_revision(
+                NEW_DOC_ID,
+                Bytes::from(r#"[{"insert":"x\n"}]"#),
+            )],
+        )
+        .await
+        .unwrap();
+    assert!(manager.split_document(DOC_ID, 2, NEW_DOC_ID).await.is_err());
+
+    // Neither document changed.
+    assert_eq!(json_of(&editor).await, CONTENT);
+    let new_editor = open_delta_editor(&manager, NEW_DOC_ID).await;
+    assert_eq!(json_of(&new_editor).await, r#"[{"insert":"x\n"}]"#);
+}
+
+async fn json_of(editor: &DeltaDocumentEditor) -> String {
+    editor.document_operations().await.unwrap().json_str()
+}
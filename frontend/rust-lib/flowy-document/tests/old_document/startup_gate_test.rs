@@ -0,0 +1,82 @@
+use crate::old_document::mock::make_unavailable_document_manager;
+use flowy_document::errors::{ErrorCode, FlowyError};
+use flowy_document::event_map::{init, DocumentEvent};
+use flowy_test::FlowySDKTest;
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::tokio_default_runtime;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn startup_gate_held_events_test() {
+    let sdk = FlowySDKTest::default();
+    let startup_gate = sdk.document_manager.startup_gate();
+    assert!(!startup_gate.is_ready());
+
+    // The events sent before the user signs in are held, like the ones of the FFI.
+    let mut responses = vec![];
+    for generation in [1, 2] {
+        let request = AFPluginRequest::new(DocumentEvent::GetStartupReport)
+            .capability_token(sdk.ffi_capability_token.clone())
+            .generation(generation);
+        responses.push(tokio::spawn(AFPluginDispatcher::async_send(sdk.dispatcher(), request)));
+        while startup_gate.num_of_queued() < generation as usize {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+    sdk.dispatcher().advance_generation(2);
+
+    let _ = sdk.init_user().await;
+    let current = responses.pop().unwrap().await.unwrap();
+    let stale = responses.pop().unwrap().await.unwrap();
+    assert_eq!(current.status_code, StatusCode::Ok);
+    assert_eq!(error_code(&stale), Some(ErrorCode::StaleRequest.value()));
+    assert!(startup_gate.is_ready());
+}
+
+#[tokio::test]
+async fn startup_gate_after_failed_initialize_test() {
+    let manager = Arc::new(make_unavailable_document_manager());
+    let plugin_manager = manager.clone();
+    let dispatcher = Arc::new(AFPluginDispatcher::construct(
+        tokio_default_runtime().unwrap(),
+        move || vec![init(plugin_manager)],
+    ));
+    let token = dispatcher.mint_capability_token(&AFPluginCapability::ALL);
+    let startup_gate = manager.startup_gate();
+
+    let mut responses = vec![];
+    for num_of_queued in 1..=3 {
+        let request = AFPluginRequest::new(DocumentEvent::GetStartupReport).capability_token(token.clone());
+        responses.push(tokio::spawn(AFPluginDispatcher::async_send(
+            dispatcher.clone(),
+            request,
+        )));
+        while startup_gate.num_of_queued() < num_of_queued {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    // The failed initialize opens the gate, then the host opens it again in its error arm. The
+    // held events are replayed once.
+    assert!(manager.initialize("user").await.is_err());
+    manager.open_startup_gate();
+    for response in responses {
+        assert_eq!(response.await.unwrap().status_code, StatusCode::Ok);
+    }
+    assert!(startup_gate.is_ready());
+    assert!(!startup_gate.open().await);
+
+    // Dropping the dispatcher's runtime inside the test's runtime panics.
+    std::mem::forget(dispatcher);
+}
+
+fn error_code(response: &AFPluginEventResponse) -> Option<i32> {
+    if response.status_code != StatusCode::Err {
+        return None;
+    }
+    <AFPluginData<FlowyError>>::try_from(response.payload.clone())
+        .ok()
+        .map(|error| error.into_inner().code)
+}
@@ -0,0 +1,95 @@
+use crate::old_document::mock::{make_delta_document_manager, make_delta_document_manager_at, make_temp_dir};
+use bytes::Bytes;
+use flowy_document::entities::{StoragePathKindPB, StoragePathsPB};
+use flowy_document::old_editor::editor::DeltaDocumentEditor;
+use flowy_http_model::revision::Revision;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DOC_ID: &str = "backup_doc";
+
+#[tokio::test]
+async fn storage_paths_test() {
+    let dir = make_temp_dir();
+    let manager = make_delta_document_manager_at(&dir);
+    let paths: StoragePathsPB = manager.storage_paths().unwrap().into();
+
+    let database = paths
+        .items
+        .iter()
+        .find(|path| path.kind == StoragePathKindPB::Database)
+        .unwrap();
+    assert_eq!(Path::new(&database.path), Path::new(&dir).join(flowy_database::DB_NAME));
+    assert!(Path::new(&database.path).exists());
+
+    // The database comes with either the write-ahead log or the rollback journal.
+    let has_wal = paths
+        .items
+        .iter()
+        .any(|path| path.kind == StoragePathKindPB::WriteAheadLog);
+    let has_journal = paths.items.iter().any(|path| path.kind == StoragePathKindPB::Journal);
+    assert_ne!(has_wal, has_journal);
+    assert!(paths.items.iter().all(|path| !path.safe_to_copy_while_running));
+}
+
+#[tokio::test]
+async fn safe_backup_to_test() {
+    let manager = make_delta_document_manager();
+    manager
+        .create_document(
+            DOC_ID,
+            vec![Revision::new(DOC_ID, 0, 1, Bytes::from(r#"[{"insert":"\n"}]"#), "")],
+        )
+        .await
+        .unwrap();
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    let delta_editor = editor.as_any().downcast_ref::<Arc<DeltaDocumentEditor>>().unwrap();
+    delta_editor.insert(0, "123").await.unwrap();
+    delta_editor.insert(3, "456").await.unwrap();
+
+    // The document stays open, its unsaved revisions must be in the backup too.
+    let backup_dir = make_temp_dir();
+    manager.safe_backup_to(&backup_dir).await.unwrap();
+    delta_editor.insert(6, "789").await.unwrap();
+
+    let backup_manager = make_delta_document_manager_at(&backup_dir);
+    let backup_editor = backup_manager.open_document_editor(DOC_ID).await.unwrap();
+    assert_eq!(backup_editor.export().await.unwrap(), r#"[{"insert":"123456\n"}]"#);
+}
+
+#[tokio::test]
+async fn pause_document_test() {
+    let manager = make_delta_document_manager();
+    manager
+        .create_document(
+            DOC_ID,
+            vec![Revision::new(DOC_ID, 0, 1, Bytes::from(r#"[{"insert":"\n"}]"#), "")],
+        )
+        .await
+        .unwrap();
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    let delta_editor = editor
+        .as_any()
+        .downcast_ref::<Arc<DeltaDocumentEditor>>()
+        .unwrap()
+        .clone();
+
+    // The edit sent during the pause waits until the pause is dropped.
+    let pause = editor.pause().await.unwrap();
+    let mut insert = tokio::spawn(async move { delta_editor.insert(0, "123").await });
+    assert!(tokio::time::timeout(Duration::from_millis(100), &mut insert)
+        .await
+        .is_err());
+    drop(pause);
+    insert.await.unwrap().unwrap();
+    assert_eq!(editor.export().await.unwrap(), r#"[{"insert":"123\n"}]"#);
+}
+
+#[tokio::test]
+async fn safe_backup_to_existing_backup_test() {
+    let manager = make_delta_document_manager();
+    let backup_dir = make_temp_dir();
+    manager.safe_backup_to(&backup_dir).await.unwrap();
+    assert!(manager.safe_backup_to(&backup_dir).await.is_err());
+}
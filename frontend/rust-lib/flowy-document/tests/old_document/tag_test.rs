@@ -0,0 +1,62 @@
+use crate::old_document::mock::{create_delta_editor, make_delta_document_manager};
+
+const DOC_ID: &str = "tag_doc";
+
+#[tokio::test]
+async fn tagged_versions_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"a\n"}]"#).await;
+    manager.tag_revision(DOC_ID, 1, "draft").await.unwrap();
+
+    editor.insert(1, "b").await.unwrap();
+    editor.insert(2, "c").await.unwrap();
+    let rev_id = editor.rev_manager().rev_id();
+    manager.tag_revision(DOC_ID, rev_id, "review").await.unwrap();
+
+    editor.insert(0, "0").await.unwrap();
+    let rev_id = editor.rev_manager().rev_id();
+    manager.tag_revision(DOC_ID, rev_id, "published").await.unwrap();
+
+    // The edits after the last tag aren't in any of the versions.
+    editor.insert(4, "d").await.unwrap();
+
+    let versions = manager
+        .tagged_versions(DOC_ID)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(tag, operations)| (tag, operations.json_str()))
+        .collect::<Vec<(String, String)>>();
+    assert_eq!(
+        versions,
+        vec![
+            ("draft".to_owned(), r#"[{"insert":"a\n"}]"#.to_owned()),
+            ("review".to_owned(), r#"[{"insert":"abc\n"}]"#.to_owned()),
+            ("published".to_owned(), r#"[{"insert":"0abc\n"}]"#.to_owned()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn tagged_versions_move_tag_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"a\n"}]"#).await;
+    manager.tag_revision(DOC_ID, 1, "latest").await.unwrap();
+    editor.insert(1, "b").await.unwrap();
+    let rev_id = editor.rev_manager().rev_id();
+    manager.tag_revision(DOC_ID, rev_id, "latest").await.unwrap();
+
+    let versions = manager.tagged_versions(DOC_ID).await.unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].0, "latest");
+    assert_eq!(versions[0].1.json_str(), r#"[{"insert":"ab\n"}]"#);
+}
+
+#[tokio::test]
+async fn tag_unknown_revision_test() {
+    let manager = make_delta_document_manager();
+    let _ = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"a\n"}]"#).await;
+    assert!(manager.tag_revision(DOC_ID, 10, "draft").await.is_err());
+    assert!(manager.tag_revision(DOC_ID, 1, "").await.is_err());
+    assert!(manager.tagged_versions(DOC_ID).await.unwrap().is_empty());
+}
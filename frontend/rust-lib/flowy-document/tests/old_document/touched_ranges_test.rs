@@ -0,0 +1,54 @@
+use crate::old_document::mock::{create_delta_editor, make_delta_document_manager};
+use lib_ot::core::Interval;
+use lib_ot::text_delta::BuildInTextAttribute;
+
+const DOC_ID: &str = "touched_ranges_doc";
+
+#[tokio::test]
+async fn touched_ranges_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"abc\n"}]"#).await;
+    editor.insert(3, "def").await.unwrap();
+    let def_rev_id = editor.rev_manager().rev_id();
+    editor.delete(Interval::new(1, 2)).await.unwrap();
+    let delete_b_rev_id = editor.rev_manager().rev_id();
+    // The "X" splits the "def" inserted before.
+    editor.insert(3, "X").await.unwrap();
+    let x_rev_id = editor.rev_manager().rev_id();
+    editor.delete(Interval::new(0, 1)).await.unwrap();
+    editor
+        .format(Interval::new(0, 2), BuildInTextAttribute::Bold(true))
+        .await
+        .unwrap();
+    let format_rev_id = editor.rev_manager().rev_id();
+    assert_eq!(editor.plain_text(true).await.unwrap(), "cdXef\n");
+
+    assert_eq!(
+        manager.touched_ranges(DOC_ID, def_rev_id).await.unwrap(),
+        vec![Interval::new(1, 2), Interval::new(3, 5)]
+    );
+    // The deleted "b" is marked where it was, the "a" before it is deleted too.
+    assert_eq!(
+        manager.touched_ranges(DOC_ID, delete_b_rev_id).await.unwrap(),
+        vec![Interval::new(0, 0)]
+    );
+    assert_eq!(
+        manager.touched_ranges(DOC_ID, x_rev_id).await.unwrap(),
+        vec![Interval::new(2, 3)]
+    );
+    // Formatting isn't a touch.
+    assert!(manager.touched_ranges(DOC_ID, format_rev_id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn touched_ranges_of_deleted_text_test() {
+    let manager = make_delta_document_manager();
+    let editor = create_delta_editor(&manager, DOC_ID, r#"[{"insert":"abc\n"}]"#).await;
+    editor.insert(1, "xyz").await.unwrap();
+    let xyz_rev_id = editor.rev_manager().rev_id();
+    editor.delete(Interval::new(0, 5)).await.unwrap();
+    assert_eq!(editor.plain_text(true).await.unwrap(), "c\n");
+
+    assert!(manager.touched_ranges(DOC_ID, xyz_rev_id).await.unwrap().is_empty());
+    assert!(manager.touched_ranges(DOC_ID, xyz_rev_id + 100).await.is_err());
+}
@@ -0,0 +1,59 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Counts the bytes allocated by each thread. The hydration runs on the thread of the test,
+/// so its peak memory isn't affected by the other tests.
+struct CountingAllocator;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    static PEAK_ALLOCATED: Cell<isize> = const { Cell::new(0) };
+}
+
+fn record_allocated(size: isize) {
+    let _ = ALLOCATED.try_with(|allocated| {
+        let value = allocated.get() + size;
+        allocated.set(value);
+        let _ = PEAK_ALLOCATED.try_with(|peak| {
+            if value > peak.get() {
+                peak.set(value);
+            }
+        });
+    });
+}
+
+pub fn reset_peak_allocated() -> isize {
+    let allocated = ALLOCATED.with(Cell::get);
+    PEAK_ALLOCATED.with(|peak| peak.set(allocated));
+    allocated
+}
+
+pub fn peak_allocated_since(start: isize) -> usize {
+    (PEAK_ALLOCATED.with(Cell::get) - start).max(0) as usize
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_allocated(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record_allocated(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_allocated(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
@@ -0,0 +1,73 @@
+use crate::allocator::{peak_allocated_since, reset_peak_allocated};
+use crate::mock::make_delta_document_manager;
+use bytes::Bytes;
+use flowy_document::entities::DocumentSnapshotPB;
+use flowy_document::DEFAULT_PREVIEW_LEN;
+use flowy_http_model::revision::Revision;
+use lib_ot::core::AttributeHashMap;
+use lib_ot::text_delta::{BuildInTextAttribute, DeltaTextOperationBuilder};
+use std::convert::TryInto;
+use unicode_segmentation::UnicodeSegmentation;
+
+const DOC_ID: &str = "document_fields_doc";
+
+#[tokio::test]
+async fn open_document_preview_of_large_document_test() {
+    let mut archived = AttributeHashMap::new();
+    archived.insert_entry(BuildInTextAttribute::Archived(true));
+    let line = format!("{}\n", "Ünïcödé 👨‍👩‍👧‍👦 text ".repeat(20));
+    let num_of_lines = 5 * 1024 * 1024 / line.len() + 1;
+    let body = line.repeat(num_of_lines);
+    let operations = DeltaTextOperationBuilder::new()
+        .insert_with_attributes("archived intro\n", archived)
+        .insert(&body)
+        .build();
+    let manager = make_delta_document_manager();
+    manager
+        .create_document(
+            DOC_ID,
+            vec![Revision::initial_revision(DOC_ID, Bytes::from(operations.json_str()))],
+        )
+        .await
+        .unwrap();
+    // The document isn't opened, so the preview and the meta are computed from its revisions.
+    let start = reset_peak_allocated();
+    let preview = manager.document_preview(DOC_ID, DEFAULT_PREVIEW_LEN).await.unwrap();
+    let preview_text = preview.text.clone();
+    let preview = DocumentSnapshotPB {
+        doc_id: DOC_ID.to_owned(),
+        preview: Some(preview.into()),
+        ..Default::default()
+    };
+    let preview: Bytes = preview.try_into().unwrap();
+    let preview_allocated = peak_allocated_since(start);
+
+    let start = reset_peak_allocated();
+    let meta = manager.document_meta(DOC_ID).await.unwrap();
+    let meta_allocated = peak_allocated_since(start);
+
+    let start = reset_peak_allocated();
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    let full = DocumentSnapshotPB {
+        doc_id: DOC_ID.to_owned(),
+        snapshot: editor.export().await.unwrap(),
+        ..Default::default()
+    };
+    let full: Bytes = full.try_into().unwrap();
+    let full_allocated = peak_allocated_since(start);
+
+    // The preview skips the archived intro and ends between two graphemes.
+    assert!(body.starts_with(&preview_text));
+    assert_eq!(preview_text.graphemes(true).count(), DEFAULT_PREVIEW_LEN);
+    assert!(full.len() > 5 * 1024 * 1024);
+    assert!(preview.len() * 1000 < full.len(), "{} vs {}", preview.len(), full.len());
+    // Both still read the stored revision, but neither composes the whole document: the preview
+    // composes a prefix of it and the meta counts its words region by region.
+    for allocated in [preview_allocated, meta_allocated] {
+        assert!(allocated < full_allocated, "{} vs {}", allocated, full_allocated);
+        assert!(allocated < 3 * body.len(), "{} vs {}", allocated, body.len());
+    }
+
+    assert_eq!(meta.num_of_chars, operations.utf16_target_len);
+    assert_eq!(meta.num_of_words, 60 * num_of_lines);
+}
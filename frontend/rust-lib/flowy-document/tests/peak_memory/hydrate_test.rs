@@ -0,0 +1,52 @@
+use crate::allocator::{peak_allocated_since, reset_peak_allocated};
+use crate::mock::{chunk_letter, make_document_manager, StreamingDocumentCloudService, CHUNK_LEN, NUM_OF_CHUNKS};
+use bytes::Bytes;
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::{DocumentConfig, DocumentEditor, DocumentManager};
+use flowy_http_model::revision::Revision;
+use lib_ot::text_delta::DeltaTextOperations;
+use std::sync::Arc;
+
+const DOC_ID: &str = "hydrate_doc";
+const LOCAL_DOCUMENT: &str = r#"[{"insert":"123\n"}]"#;
+
+#[tokio::test]
+async fn hydrate_large_document_in_chunks_test() {
+    let manager = make_manager(StreamingDocumentCloudService::new()).await;
+    let start = reset_peak_allocated();
+    manager.hydrate_document(DOC_ID).await.unwrap();
+    let peak = peak_allocated_since(start);
+
+    // The document is 4MB, only a few chunks are allowed to be in memory at the same time.
+    let document_len = CHUNK_LEN * NUM_OF_CHUNKS;
+    assert!(
+        peak < document_len / 8,
+        "The peak memory of the hydration is {} bytes, the document is {} bytes",
+        peak,
+        document_len
+    );
+
+    let content = document_content(&manager).await;
+    assert_eq!(content.len(), document_len + 1);
+    for (chunk_index, chunk) in content.trim_end_matches('\n').as_bytes().chunks(CHUNK_LEN).enumerate() {
+        assert!(chunk.iter().all(|c| *c == chunk_letter(chunk_index) as u8));
+    }
+}
+
+async fn make_manager(cloud_service: StreamingDocumentCloudService) -> DocumentManager {
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        ..Default::default()
+    };
+    let manager = make_document_manager(Arc::new(cloud_service), config);
+    let revision = Revision::new(DOC_ID, 0, 1, Bytes::from(LOCAL_DOCUMENT), "");
+    manager.create_document(DOC_ID, vec![revision]).await.unwrap();
+    manager
+}
+
+async fn document_content(manager: &DocumentManager) -> String {
+    let editor = manager.open_document_editor(DOC_ID).await.unwrap();
+    let json = editor.export().await.unwrap();
+    manager.close_document_editor(DOC_ID).await.unwrap();
+    DeltaTextOperations::from_json(&json).unwrap().content().unwrap()
+}
@@ -0,0 +1,8 @@
+//! The tests that measure the peak memory with a counting `#[global_allocator]`, it applies
+//! to the whole test binary so they're kept apart from the other tests.
+mod allocator;
+mod document_fields_test;
+mod hydrate_test;
+#[allow(dead_code)]
+#[path = "../old_document/mock.rs"]
+mod mock;
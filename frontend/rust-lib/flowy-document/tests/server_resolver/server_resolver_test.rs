@@ -0,0 +1,235 @@
+use crate::old_document::mock::{
+    create_delta_editor, make_document_manager_with_server, make_temp_dir, open_delta_editor,
+};
+use flowy_document::entities::DocumentVersionPB;
+use flowy_document::errors::FlowyError;
+use flowy_document::{
+    DocumentCloudService, DocumentConfig, DocumentServer, DocumentServerResolver, DocumentServerTable,
+    DEFAULT_DOCUMENT_ENDPOINT,
+};
+use flowy_http_model::document::{CreateDocumentParams, DocumentId, DocumentPayload, ResetDocumentParams};
+use flowy_http_model::ws_data::ClientRevisionWSData;
+use flowy_revision::{RevisionWebSocket, WSStateReceiver};
+use futures_util::future::BoxFuture;
+use lib_infra::future::{BoxResultFuture, FutureResult};
+use lib_ws::WSConnectState;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::{sleep, timeout};
+
+#[tokio::test]
+async fn server_resolver_default_server_test() {
+    let default_server = MockDocumentServer::new(DEFAULT_DOCUMENT_ENDPOINT);
+    let table = DocumentServerTable::new(default_server.server());
+
+    let server = table.resolve("doc_1");
+    assert_eq!(server.endpoint, DEFAULT_DOCUMENT_ENDPOINT);
+    assert_eq!(table.servers().len(), 1);
+}
+
+#[tokio::test]
+async fn server_resolver_override_server_test() {
+    let default_server = MockDocumentServer::new(DEFAULT_DOCUMENT_ENDPOINT);
+    let shared_server = MockDocumentServer::new("shared_workspace");
+    let table = DocumentServerTable::new(default_server.server());
+    table.set_server("shared_doc", shared_server.server());
+
+    let server = table.resolve("shared_doc");
+    server
+        .cloud_service
+        .fetch_document("", "shared_doc".to_owned().into())
+        .await
+        .unwrap();
+    let server = table.resolve("local_doc");
+    server
+        .cloud_service
+        .fetch_document("", "local_doc".to_owned().into())
+        .await
+        .unwrap();
+
+    assert_eq!(shared_server.cloud_service.fetched_doc_ids(), vec!["shared_doc"]);
+    assert_eq!(default_server.cloud_service.fetched_doc_ids(), vec!["local_doc"]);
+}
+
+#[tokio::test]
+async fn server_resolver_remove_override_test() {
+    let default_server = MockDocumentServer::new(DEFAULT_DOCUMENT_ENDPOINT);
+    let shared_server = MockDocumentServer::new("shared_workspace");
+    let table = DocumentServerTable::new(default_server.server());
+    table.set_server("shared_doc", shared_server.server());
+    assert_eq!(table.servers().len(), 2);
+
+    let removed = table.remove_server("shared_doc").unwrap();
+    assert_eq!(removed.endpoint, "shared_workspace");
+    assert_eq!(table.resolve("shared_doc").endpoint, DEFAULT_DOCUMENT_ENDPOINT);
+    assert_eq!(table.servers().len(), 1);
+}
+
+#[tokio::test]
+async fn server_resolver_distinct_endpoints_test() {
+    let default_server = MockDocumentServer::new(DEFAULT_DOCUMENT_ENDPOINT);
+    let shared_server = MockDocumentServer::new("shared_workspace");
+    let table = DocumentServerTable::new(default_server.server());
+    table.set_server("shared_doc_1", shared_server.server());
+    table.set_server("shared_doc_2", shared_server.server());
+
+    let mut endpoints = table
+        .servers()
+        .into_iter()
+        .map(|server| server.endpoint)
+        .collect::<Vec<String>>();
+    endpoints.sort();
+    assert_eq!(endpoints, vec![DEFAULT_DOCUMENT_ENDPOINT, "shared_workspace"]);
+}
+
+#[tokio::test]
+async fn server_resolver_web_socket_per_endpoint_test() {
+    let default_server = MockDocumentServer::new(DEFAULT_DOCUMENT_ENDPOINT);
+    let shared_server = MockDocumentServer::new("shared_workspace");
+    let table = DocumentServerTable::new(default_server.server());
+    table.set_server("shared_doc", shared_server.server());
+
+    let mut shared_state = table.resolve("shared_doc").web_socket.subscribe_state_changed().await;
+    let mut default_state = table.resolve("local_doc").web_socket.subscribe_state_changed().await;
+    shared_server.web_socket.set_state(WSConnectState::Connected);
+
+    assert!(shared_state.recv().await.unwrap() == WSConnectState::Connected);
+    assert!(default_state.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn server_resolver_documents_sync_with_their_endpoints_test() {
+    let default_server = MockDocumentServer::new(DEFAULT_DOCUMENT_ENDPOINT);
+    let shared_server = MockDocumentServer::new("shared_workspace");
+    let config = DocumentConfig {
+        version: DocumentVersionPB::V0,
+        ..Default::default()
+    };
+    let manager = make_document_manager_with_server(
+        &make_temp_dir(),
+        default_server.cloud_service.clone(),
+        default_server.web_socket.clone(),
+        config,
+    );
+    manager
+        .set_document_server("shared_doc", shared_server.server())
+        .await
+        .unwrap();
+    let local_editor = create_delta_editor(&manager, "local_doc", r#"[{"insert":"\n"}]"#).await;
+    let shared_editor = create_delta_editor(&manager, "shared_doc", r#"[{"insert":"\n"}]"#).await;
+    local_editor.insert(0, "local").await.unwrap();
+    shared_editor.insert(0, "shared").await.unwrap();
+
+    // Both documents sync at the same time, each one with its own endpoint.
+    let synced = async {
+        while default_server.web_socket.sent_object_ids().is_empty()
+            || shared_server.web_socket.sent_object_ids().is_empty()
+        {
+            sleep(Duration::from_millis(50)).await;
+        }
+    };
+    timeout(Duration::from_secs(10), synced).await.unwrap();
+    assert_eq!(default_server.web_socket.sent_object_ids(), vec!["local_doc"]);
+    assert_eq!(shared_server.web_socket.sent_object_ids(), vec!["shared_doc"]);
+
+    // Without the override the reopened document syncs with the default endpoint.
+    manager.remove_document_server("shared_doc").await.unwrap();
+    drop(shared_editor);
+    let shared_editor = open_delta_editor(&manager, "shared_doc").await;
+    shared_editor.insert(0, "default").await.unwrap();
+    let synced = async {
+        while !default_server
+            .web_socket
+            .sent_object_ids()
+            .contains(&"shared_doc".to_owned())
+        {
+            sleep(Duration::from_millis(50)).await;
+        }
+    };
+    timeout(Duration::from_secs(10), synced).await.unwrap();
+}
+
+struct MockDocumentServer {
+    endpoint: String,
+    cloud_service: Arc<MockDocumentCloudService>,
+    web_socket: Arc<MockRevisionWebSocket>,
+}
+
+impl MockDocumentServer {
+    fn new(endpoint: &str) -> Self {
+        Self {
+            endpoint: endpoint.to_owned(),
+            cloud_service: Arc::new(MockDocumentCloudService::default()),
+            web_socket: Arc::new(MockRevisionWebSocket::new()),
+        }
+    }
+
+    fn server(&self) -> DocumentServer {
+        DocumentServer::new(&self.endpoint, self.cloud_service.clone(), self.web_socket.clone())
+    }
+}
+
+#[derive(Default)]
+struct MockDocumentCloudService {
+    fetched_doc_ids: Mutex<Vec<String>>,
+}
+
+impl MockDocumentCloudService {
+    fn fetched_doc_ids(&self) -> Vec<String> {
+        self.fetched_doc_ids.lock().unwrap().clone()
+    }
+}
+
+impl DocumentCloudService for MockDocumentCloudService {
+    fn create_document(&self, _token: &str, _params: CreateDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    fn fetch_document(&self, _token: &str, params: DocumentId) -> FutureResult<Option<DocumentPayload>, FlowyError> {
+        self.fetched_doc_ids.lock().unwrap().push(params.value);
+        FutureResult::new(async { Ok(None) })
+    }
+
+    fn update_document_content(&self, _token: &str, _params: ResetDocumentParams) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+}
+
+struct MockRevisionWebSocket {
+    state_sender: broadcast::Sender<WSConnectState>,
+    sent_object_ids: Mutex<Vec<String>>,
+}
+
+impl MockRevisionWebSocket {
+    fn new() -> Self {
+        let (state_sender, _) = broadcast::channel(2);
+        Self {
+            state_sender,
+            sent_object_ids: Mutex::new(vec![]),
+        }
+    }
+
+    fn sent_object_ids(&self) -> Vec<String> {
+        let mut object_ids = self.sent_object_ids.lock().unwrap().clone();
+        object_ids.sort();
+        object_ids.dedup();
+        object_ids
+    }
+
+    fn set_state(&self, state: WSConnectState) {
+        let _ = self.state_sender.send(state);
+    }
+}
+
+impl RevisionWebSocket for MockRevisionWebSocket {
+    fn send(&self, data: ClientRevisionWSData) -> BoxResultFuture<(), FlowyError> {
+        self.sent_object_ids.lock().unwrap().push(data.object_id);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn subscribe_state_changed(&self) -> BoxFuture<WSStateReceiver> {
+        let receiver = self.state_sender.subscribe();
+        Box::pin(async move { receiver })
+    }
+}
@@ -160,6 +160,39 @@ pub enum ErrorCode {
 
     #[error("Sort id is empty")]
     SortIdIsEmpty = 53,
+
+    #[error("The change was rejected by the revision guard")]
+    RevisionRejected = 54,
+
+    #[error("The request lacks the capability that the event requires")]
+    PermissionDenied = 55,
+
+    #[error("The module is still initializing, try again later")]
+    ModuleNotReady = 56,
+
+    #[error("The request was dropped because it's stale")]
+    StaleRequest = 57,
+
+    #[error("The operations aren't based on the current document")]
+    BaseMismatch = 58,
+
+    #[error("The file was written by a newer version of the app")]
+    UnsupportedFormatVersion = 59,
+
+    #[error("The document can't be read, its text can still be recovered")]
+    DocumentUnreadable = 60,
+
+    #[error("The byte range doesn't fall on the character boundaries")]
+    InvalidBoundary = 61,
+
+    #[error("The sync is paused, the changes are sent once it's resumed")]
+    SyncPaused = 62,
+
+    #[error("The attribute can't be registered or its value doesn't match its semantics")]
+    InvalidAttribute = 63,
+
+    #[error("The custom attributes must be registered before the documents are opened")]
+    AttributeRegistrationClosed = 64,
 }
 
 impl ErrorCode {
@@ -2,7 +2,9 @@ use crate::ErrorCode;
 use anyhow::Result;
 use bytes::Bytes;
 use flowy_derive::ProtoBuf;
-use lib_dispatch::prelude::{AFPluginEventResponse, ResponseBuilder};
+use lib_dispatch::prelude::{
+    AFPluginEventResponse, AFPluginNotReady, AFPluginNotReadyReason, AFPluginPermissionDenied, ResponseBuilder,
+};
 use std::{convert::TryInto, fmt::Debug};
 use thiserror::Error;
 
@@ -72,6 +74,16 @@ impl FlowyError {
     static_flowy_error!(out_of_bounds, ErrorCode::OutOfBounds);
     static_flowy_error!(serde, ErrorCode::Serde);
     static_flowy_error!(field_record_not_found, ErrorCode::FieldRecordNotFound);
+    static_flowy_error!(revision_rejected, ErrorCode::RevisionRejected);
+    static_flowy_error!(permission_denied, ErrorCode::PermissionDenied);
+    static_flowy_error!(module_not_ready, ErrorCode::ModuleNotReady);
+    static_flowy_error!(stale_request, ErrorCode::StaleRequest);
+    static_flowy_error!(unsupported_format_version, ErrorCode::UnsupportedFormatVersion);
+    static_flowy_error!(document_unreadable, ErrorCode::DocumentUnreadable);
+    static_flowy_error!(invalid_boundary, ErrorCode::InvalidBoundary);
+    static_flowy_error!(sync_paused, ErrorCode::SyncPaused);
+    static_flowy_error!(invalid_attribute, ErrorCode::InvalidAttribute);
+    static_flowy_error!(attribute_registration_closed, ErrorCode::AttributeRegistrationClosed);
 }
 
 impl std::convert::From<ErrorCode> for FlowyError {
@@ -106,6 +118,21 @@ impl lib_dispatch::Error for FlowyError {
     }
 }
 
+impl std::convert::From<AFPluginPermissionDenied> for FlowyError {
+    fn from(error: AFPluginPermissionDenied) -> Self {
+        FlowyError::permission_denied().context(error)
+    }
+}
+
+impl std::convert::From<AFPluginNotReady> for FlowyError {
+    fn from(error: AFPluginNotReady) -> Self {
+        match error.reason {
+            AFPluginNotReadyReason::QueueFull => FlowyError::module_not_ready().context(error),
+            AFPluginNotReadyReason::Stale => FlowyError::stale_request().context(error),
+        }
+    }
+}
+
 impl std::convert::From<std::io::Error> for FlowyError {
     fn from(error: std::io::Error) -> Self {
         FlowyError::internal().context(error)
@@ -6,6 +6,9 @@ impl std::convert::From<flowy_sync::errors::CollaborateError> for FlowyError {
     fn from(error: flowy_sync::errors::CollaborateError) -> Self {
         match error.code {
             ErrorCode::RecordNotFound => FlowyError::record_not_found().context(error.msg),
+            ErrorCode::RevisionRejected => FlowyError::revision_rejected().context(error.msg),
+            // The message has both lengths, it's kept as is so the client can read them.
+            ErrorCode::BaseMismatch => FlowyError::new(crate::ErrorCode::BaseMismatch, &error.msg),
             _ => FlowyError::internal().context(error.msg),
         }
     }
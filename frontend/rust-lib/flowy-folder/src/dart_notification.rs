@@ -16,6 +16,7 @@ pub(crate) enum FolderNotification {
     ViewDeleted = 32,
     ViewRestored = 33,
     ViewMoveToTrash = 34,
+    DuplicatedViewsFound = 35,
     UserUnauthorized = 100,
     TrashUpdated = 1000,
 }
@@ -32,6 +32,9 @@ pub struct ViewPB {
 
     #[pb(index = 7)]
     pub layout: ViewLayoutTypePB,
+
+    #[pb(index = 8)]
+    pub is_scratch: bool,
 }
 
 impl std::convert::From<ViewRevision> for ViewPB {
@@ -44,6 +47,7 @@ impl std::convert::From<ViewRevision> for ViewPB {
             modified_time: rev.modified_time,
             create_time: rev.create_time,
             layout: rev.layout.into(),
+            is_scratch: rev.is_scratch,
         }
     }
 }
@@ -159,6 +163,15 @@ pub struct CreateViewPayloadPB {
 
     #[pb(index = 7)]
     pub view_content_data: Vec<u8>,
+
+    /// A scratch view is kept on this device only, it never syncs to the server.
+    #[pb(index = 8)]
+    pub is_scratch: bool,
+
+    /// Generated by the client for each "create" action. Creating a view with a key that was
+    /// used recently returns the view created the first time instead of a new one.
+    #[pb(index = 9, one_of)]
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -171,6 +184,8 @@ pub struct CreateViewParams {
     pub layout: ViewLayoutTypePB,
     pub view_id: String,
     pub view_content_data: Vec<u8>,
+    pub is_scratch: bool,
+    pub idempotency_key: Option<String>,
 }
 
 impl TryInto<CreateViewParams> for CreateViewPayloadPB {
@@ -194,6 +209,40 @@ impl TryInto<CreateViewParams> for CreateViewPayloadPB {
             thumbnail,
             view_id,
             view_content_data: self.view_content_data,
+            is_scratch: self.is_scratch,
+            idempotency_key: self.idempotency_key,
+        })
+    }
+}
+
+#[derive(Default, ProtoBuf)]
+pub struct ImportPortableViewPayloadPB {
+    #[pb(index = 1)]
+    pub belong_to_id: String,
+
+    #[pb(index = 2)]
+    pub name: String,
+
+    /// The file exported by `DocumentEvent::ExportPortable`.
+    #[pb(index = 3)]
+    pub path: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportPortableViewParams {
+    pub belong_to_id: String,
+    pub name: String,
+    pub path: String,
+}
+
+impl TryInto<ImportPortableViewParams> for ImportPortableViewPayloadPB {
+    type Error = ErrorCode;
+
+    fn try_into(self) -> Result<ImportPortableViewParams, Self::Error> {
+        Ok(ImportPortableViewParams {
+            belong_to_id: AppIdentify::parse(self.belong_to_id)?.0,
+            name: ViewName::parse(self.name)?.0,
+            path: self.path,
         })
     }
 }
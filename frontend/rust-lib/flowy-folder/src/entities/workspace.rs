@@ -119,6 +119,13 @@ pub struct UpdateWorkspaceParams {
     pub desc: Option<String>,
 }
 
+#[derive(ProtoBuf, Default)]
+pub struct MergeLocalDatabasePayloadPB {
+    /// The folder of the other account's database, i.e. its user folder.
+    #[pb(index = 1)]
+    pub path: String,
+}
+
 impl TryInto<UpdateWorkspaceParams> for UpdateWorkspacePayloadPB {
     type Error = ErrorCode;
 
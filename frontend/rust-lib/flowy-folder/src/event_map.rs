@@ -49,7 +49,8 @@ pub fn init(folder: Arc<FolderManager>) -> AFPlugin {
         .event(FolderEvent::ReadCurrentWorkspace, read_cur_workspace_handler)
         .event(FolderEvent::ReadWorkspaces, read_workspaces_handler)
         .event(FolderEvent::OpenWorkspace, open_workspace_handler)
-        .event(FolderEvent::ReadWorkspaceApps, read_workspace_apps_handler);
+        .event(FolderEvent::ReadWorkspaceApps, read_workspace_apps_handler)
+        .event(FolderEvent::MergeLocalDatabase, merge_local_database_handler);
 
     // App
     plugin = plugin
@@ -68,7 +69,9 @@ pub fn init(folder: Arc<FolderManager>) -> AFPlugin {
         .event(FolderEvent::DuplicateView, duplicate_view_handler)
         .event(FolderEvent::SetLatestView, set_latest_view_handler)
         .event(FolderEvent::CloseView, close_view_handler)
-        .event(FolderEvent::MoveFolderItem, move_item_handler);
+        .event(FolderEvent::MoveFolderItem, move_item_handler)
+        .event(FolderEvent::ReadDuplicatedViews, read_duplicated_views_handler)
+        .event(FolderEvent::ImportPortableView, import_portable_view_handler);
 
     // Trash
     plugin = plugin
@@ -102,6 +105,12 @@ pub enum FolderEvent {
     #[event(input = "WorkspaceIdPB", output = "RepeatedAppPB")]
     ReadWorkspaceApps = 5,
 
+    /// A maintenance event that merges the database of another account on this device into the
+    /// current workspace, see `FolderManager::merge_local_database`. Returns the apps that got
+    /// the merged views.
+    #[event(input = "MergeLocalDatabasePayloadPB", output = "RepeatedAppPB")]
+    MergeLocalDatabase = 6,
+
     #[event(input = "CreateAppPayloadPB", output = "AppPB")]
     CreateApp = 101,
 
@@ -135,6 +144,15 @@ pub enum FolderEvent {
     #[event(input = "ViewIdPB", output = "ViewInfoPB")]
     ReadViewInfo = 207,
 
+    /// Returns the untouched views of the app that were created along with an identical
+    /// sibling, e.g. by double clicking the "New page" button.
+    #[event(input = "AppIdPB", output = "RepeatedViewPB")]
+    ReadDuplicatedViews = 208,
+
+    /// Imports a document exported by another user with its history as a new view of the app.
+    #[event(input = "ImportPortableViewPayloadPB", output = "ViewPB")]
+    ImportPortableView = 209,
+
     #[event()]
     CopyLink = 220,
 
@@ -7,26 +7,36 @@ use crate::{
     errors::FlowyResult,
     event_map::{FolderCouldServiceV1, WorkspaceDatabase, WorkspaceUser},
     services::{
-        folder_editor::FolderEditor, persistence::FolderPersistence, set_current_workspace, AppController,
-        TrashController, ViewController, WorkspaceController,
+        folder_editor::FolderEditor,
+        get_current_workspace,
+        persistence::{FolderPersistence, FolderPersistenceTransaction},
+        set_current_workspace, AppController, TrashController, ViewController, WorkspaceController,
     },
 };
 use bytes::Bytes;
+use diesel::{Connection, SqliteConnection};
+use flowy_database::DB_NAME;
 use flowy_document::editor::initial_read_me;
 use flowy_error::FlowyError;
 use flowy_revision::{RevisionManager, RevisionPersistence, RevisionPersistenceConfiguration, RevisionWebSocket};
-use folder_rev_model::user_default;
+use folder_rev_model::{gen_app_id, user_default, AppRevision, ViewDataFormatRevision, ViewRevision};
 use lazy_static::lazy_static;
 use lib_infra::future::FutureResult;
 
+use crate::entities::app::{AppIdPB, AppPB, RepeatedAppPB};
 use crate::services::clear_current_workspace;
 use crate::services::persistence::rev_sqlite::{
-    SQLiteFolderRevisionPersistence, SQLiteFolderRevisionSnapshotPersistence,
+    FolderRevisionSql, SQLiteFolderRevisionPersistence, SQLiteFolderRevisionSnapshotPersistence,
 };
 use flowy_http_model::ws_data::ServerRevisionWSData;
 use flowy_sync::client_folder::FolderPad;
 use std::convert::TryFrom;
-use std::{collections::HashMap, fmt::Formatter, sync::Arc};
+use std::path::Path;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Formatter,
+    sync::Arc,
+};
 use tokio::sync::RwLock as TokioRwLock;
 lazy_static! {
     static ref INIT_FOLDER_FLAG: TokioRwLock<HashMap<String, bool>> = TokioRwLock::new(HashMap::new());
@@ -175,7 +185,7 @@ impl FolderManager {
         let rev_compactor = FolderRevisionMergeable();
 
         let snapshot_object_id = format!("folder:{}", object_id);
-        let snapshot_persistence = SQLiteFolderRevisionSnapshotPersistence::new(&snapshot_object_id, pool);
+        let snapshot_persistence = SQLiteFolderRevisionSnapshotPersistence::new(&snapshot_object_id, pool.clone());
         let rev_manager = RevisionManager::new(
             user_id,
             folder_id.as_ref(),
@@ -184,7 +194,8 @@ impl FolderManager {
             snapshot_persistence,
         );
 
-        let folder_editor = FolderEditor::new(user_id, &folder_id, token, rev_manager, self.web_socket.clone()).await?;
+        let folder_editor =
+            FolderEditor::new(user_id, &folder_id, token, rev_manager, self.web_socket.clone(), pool).await?;
         *self.folder_editor.write().await = Some(Arc::new(folder_editor));
 
         self.app_controller.initialize()?;
@@ -217,6 +228,224 @@ impl FolderManager {
         clear_current_workspace(user_id);
         *self.folder_editor.write().await = None;
     }
+
+    /// Returns the id of the workspace the user opened last.
+    pub fn current_workspace_id(&self) -> FlowyResult<String> {
+        let user_id = self.user.user_id()?;
+        get_current_workspace(&user_id)
+    }
+
+    /// Merges the workspaces of the database under `dir`, e.g. of another account on this device,
+    /// into the current workspace. The documents are copied under new ids, see
+    /// `ViewDataProcessor::merge_local_database`, and their views are added to the app with the
+    /// same name, or to a new app. A view whose name is taken in its app gets a suffix. The views
+    /// of the other data formats and the trashed views aren't merged.
+    ///
+    /// The merge that stopped midway adds the views that are still missing when it's run again.
+    /// Returns the apps that got the merged views.
+    pub async fn merge_local_database(&self, dir: &str) -> FlowyResult<Vec<AppRevision>> {
+        let doc_ids = self.view_controller.merge_local_database(dir).await?;
+        let source = match read_local_folder(dir)? {
+            None => return Ok(vec![]),
+            Some(source) => source,
+        };
+        let trash_ids = source
+            .read_trash(None)?
+            .into_iter()
+            .map(|trash| trash.id)
+            .collect::<HashSet<_>>();
+        let workspace_id = self.current_workspace_id()?;
+        let editor =
+            self.folder_editor.read().await.clone().ok_or_else(|| {
+                FlowyError::internal().context("FolderEditor should be initialized after user login in.")
+            })?;
+        // The apps and the views are created in one revision, a merge that fails midway doesn't
+        // leave some of them behind.
+        let merged_app_ids = editor.apply_changes(|folder| {
+            let mut changes = vec![];
+            let current_trash_ids = folder
+                .read_trash(None)?
+                .into_iter()
+                .map(|trash| trash.id)
+                .collect::<HashSet<_>>();
+            let mut apps = folder
+                .read_workspaces(Some(workspace_id.clone()))?
+                .into_iter()
+                .flat_map(|workspace| workspace.apps)
+                .filter(|app| !current_trash_ids.contains(&app.id))
+                .collect::<Vec<_>>();
+            let mut merged_app_ids = vec![];
+            for workspace in source.read_workspaces(None)? {
+                for source_app in workspace.apps {
+                    if trash_ids.contains(&source_app.id) {
+                        continue;
+                    }
+                    let views = source_app
+                        .belongings
+                        .iter()
+                        .filter(|view| !trash_ids.contains(&view.id))
+                        .filter_map(|view| match doc_ids.get(&view.id) {
+                            None => {
+                                tracing::warn!("The view {} of {:?} isn't merged", view.id, view.data_format);
+                                None
+                            }
+                            Some(doc_id) => Some((doc_id.clone(), view)),
+                        })
+                        .collect::<Vec<_>>();
+                    if views.is_empty() {
+                        continue;
+                    }
+
+                    let app_id = match apps.iter().find(|app| app.name == source_app.name) {
+                        Some(app) => app.id.clone(),
+                        None => {
+                            let app = AppRevision {
+                                id: gen_app_id(),
+                                workspace_id: workspace_id.clone(),
+                                belongings: vec![],
+                                ..source_app.clone()
+                            };
+                            changes.extend(folder.create_app(app.clone())?);
+                            apps.push(app.clone());
+                            app.id
+                        }
+                    };
+                    let mut names = folder
+                        .read_views(&app_id)?
+                        .into_iter()
+                        .filter(|view| !current_trash_ids.contains(&view.id))
+                        .map(|view| view.name)
+                        .collect::<HashSet<_>>();
+                    for (view_id, view) in views {
+                        if folder.read_view(&view_id).is_ok() {
+                            continue;
+                        }
+                        let name = unique_view_name(&view.name, &names);
+                        names.insert(name.clone());
+                        let view_rev = ViewRevision {
+                            id: view_id,
+                            app_id: app_id.clone(),
+                            name,
+                            belongings: vec![],
+                            ..view.clone()
+                        };
+                        changes.extend(folder.create_view(view_rev)?);
+                    }
+                    if !merged_app_ids.contains(&app_id) {
+                        merged_app_ids.push(app_id);
+                    }
+                }
+            }
+            Ok((merged_app_ids, changes))
+        })?;
+
+        let mut merged_apps = vec![];
+        for app_id in merged_app_ids {
+            let app = self.app_controller.read_app(AppIdPB::new(&app_id)).await?;
+            send_dart_notification(&app_id, FolderNotification::AppUpdated)
+                .payload(AppPB::from(app.clone()))
+                .send();
+            merged_apps.push(app);
+        }
+        let items = self
+            .workspace_controller
+            .read_current_workspace_apps()
+            .await?
+            .into_iter()
+            .map(|app_rev| app_rev.into())
+            .collect();
+        send_dart_notification(&workspace_id, FolderNotification::WorkspaceAppsChanged)
+            .payload(RepeatedAppPB { items })
+            .send();
+        Ok(merged_apps)
+    }
+
+    /// Returns the ids of the delta documents of the views of the workspace, the trashed views
+    /// and apps are left out.
+    pub async fn workspace_document_ids(&self, workspace_id: &str) -> FlowyResult<Vec<String>> {
+        self.persistence
+            .begin_transaction(|transaction| {
+                let trash_ids = self.read_trash_id_set(&transaction)?;
+                let mut doc_ids = vec![];
+                for app in transaction.read_workspace_apps(workspace_id)? {
+                    if !trash_ids.contains(&app.id) {
+                        collect_document_ids(&app.belongings, &trash_ids, &mut doc_ids);
+                    }
+                }
+                Ok(doc_ids)
+            })
+            .await
+    }
+
+    /// Returns the ids of the delta documents of the views of the app, the trashed views are
+    /// left out.
+    pub async fn app_document_ids(&self, app_id: &str) -> FlowyResult<Vec<String>> {
+        self.persistence
+            .begin_transaction(|transaction| {
+                let trash_ids = self.read_trash_id_set(&transaction)?;
+                let mut doc_ids = vec![];
+                let app = transaction.read_app(app_id)?;
+                if !trash_ids.contains(&app.id) {
+                    collect_document_ids(&app.belongings, &trash_ids, &mut doc_ids);
+                }
+                Ok(doc_ids)
+            })
+            .await
+    }
+
+    /// Returns true if the view is a scratch view, see `ViewRevision::is_scratch`.
+    pub async fn is_scratch_view(&self, view_id: &str) -> FlowyResult<bool> {
+        let view_rev = self
+            .persistence
+            .begin_transaction(|transaction| transaction.read_view(view_id))
+            .await?;
+        Ok(view_rev.is_scratch)
+    }
+
+    pub async fn set_view_scratch(&self, view_id: &str, is_scratch: bool) -> FlowyResult<()> {
+        self.view_controller.set_view_scratch(view_id, is_scratch).await
+    }
+
+    fn read_trash_id_set<'a>(
+        &self,
+        transaction: &'a (dyn FolderPersistenceTransaction + 'a),
+    ) -> FlowyResult<HashSet<String>> {
+        Ok(self.trash_controller.read_trash_ids(transaction)?.into_iter().collect())
+    }
+}
+
+fn collect_document_ids(views: &[ViewRevision], trash_ids: &HashSet<String>, doc_ids: &mut Vec<String>) {
+    for view in views.iter().filter(|view| !trash_ids.contains(&view.id)) {
+        if view.data_format == ViewDataFormatRevision::DeltaFormat {
+            doc_ids.push(view.id.clone());
+        }
+        collect_document_ids(&view.belongings, trash_ids, doc_ids);
+    }
+}
+
+/// Reads the folder of the database under `dir`, None if it has no folder.
+fn read_local_folder(dir: &str) -> FlowyResult<Option<FolderPad>> {
+    let db_path = Path::new(dir).join(DB_NAME);
+    let db_path = db_path
+        .to_str()
+        .ok_or_else(|| FlowyError::invalid_data().context(format!("Invalid database path: {:?}", db_path)))?;
+    let conn = SqliteConnection::establish(db_path)
+        .map_err(|e| FlowyError::internal().context(format!("Open {} failed: {}", db_path, e)))?;
+    match FolderRevisionSql::read_folder(&conn)? {
+        None => Ok(None),
+        Some(revisions) => Ok(Some(FolderPad::from_revisions(revisions)?)),
+    }
+}
+
+/// Returns the `name`, or the `name` with the first number suffix that isn't `taken`.
+fn unique_view_name(name: &str, taken: &HashSet<String>) -> String {
+    if !taken.contains(name) {
+        return name.to_owned();
+    }
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|name| !taken.contains(name))
+        .unwrap()
 }
 
 struct DefaultFolderBuilder();
@@ -293,6 +522,30 @@ pub trait ViewDataProcessor {
     ) -> FutureResult<Bytes, FlowyError>;
 
     fn data_types(&self) -> Vec<ViewDataFormatPB>;
+
+    /// Marks the data of the view as the data of a scratch view, which never syncs to the
+    /// server, before it's created. Processors whose data always stays local can ignore it.
+    fn set_view_scratch(&self, _view_id: &str, _is_scratch: bool) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    /// Returns true if nothing was written into the view since it was created.
+    fn is_view_untouched(&self, _view_id: &str) -> FutureResult<bool, FlowyError> {
+        FutureResult::new(async { Ok(false) })
+    }
+
+    /// Creates the data of a new view from the portable file exported by another user, and
+    /// returns the id of the view.
+    fn import_view(&self, _path: &str) -> FutureResult<String, FlowyError> {
+        FutureResult::new(async { Err(FlowyError::internal().context("The view can't be imported")) })
+    }
+
+    /// Copies the data of the views from the database under `dir` and returns the id of each
+    /// copied view mapped to the id of its copy. The copies of a merge that is run again keep
+    /// their ids.
+    fn merge_local_database(&self, _dir: &str) -> FutureResult<HashMap<String, String>, FlowyError> {
+        FutureResult::new(async { Err(FlowyError::internal().context("The views can't be merged")) })
+    }
 }
 
 pub type ViewDataProcessorMap = Arc<HashMap<ViewDataFormatPB, Arc<dyn ViewDataProcessor + Send + Sync>>>;
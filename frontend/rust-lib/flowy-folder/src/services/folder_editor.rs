@@ -12,7 +12,7 @@ use flowy_sync::client_folder::{FolderChangeset, FolderPad};
 use flowy_sync::server_folder::FolderOperations;
 use flowy_sync::util::{make_operations_from_revisions, recover_operation_from_revisions};
 use lib_infra::future::FutureResult;
-use lib_ot::core::EmptyAttributes;
+use lib_ot::core::{EmptyAttributes, OperationTransform};
 use parking_lot::RwLock;
 use std::sync::Arc;
 
@@ -22,6 +22,8 @@ pub struct FolderEditor {
     #[allow(dead_code)]
     folder_id: FolderId,
     pub(crate) folder: Arc<RwLock<FolderPad>>,
+    /// The database of the scratch views, which are kept out of the `folder`.
+    pub(crate) pool: Arc<ConnectionPool>,
     rev_manager: Arc<RevisionManager<Arc<ConnectionPool>>>,
     #[cfg(feature = "sync")]
     ws_manager: Arc<flowy_revision::RevisionWebSocketManager>,
@@ -35,6 +37,7 @@ impl FolderEditor {
         token: &str,
         mut rev_manager: RevisionManager<Arc<ConnectionPool>>,
         web_socket: Arc<dyn RevisionWebSocket>,
+        pool: Arc<ConnectionPool>,
     ) -> FlowyResult<Self> {
         let cloud = Arc::new(FolderRevisionCloudService {
             token: token.to_string(),
@@ -60,6 +63,7 @@ impl FolderEditor {
             user_id,
             folder_id,
             folder,
+            pool,
             rev_manager,
             #[cfg(feature = "sync")]
             ws_manager,
@@ -91,6 +95,32 @@ impl FolderEditor {
         Ok(())
     }
 
+    /// Runs `f` on a copy of the folder and saves the changes it returns as one revision, e.g. the
+    /// apps and views that are created together. The folder is left as it was if `f` fails.
+    pub(crate) fn apply_changes<F, O>(&self, f: F) -> FlowyResult<O>
+    where
+        F: FnOnce(&mut FolderPad) -> FlowyResult<(O, Vec<FolderChangeset>)>,
+    {
+        let mut folder = self.folder.write();
+        let mut pad = folder.clone();
+        let (output, changes) = f(&mut pad)?;
+        let mut changes = changes.into_iter();
+        let mut change = match changes.next() {
+            None => return Ok(output),
+            Some(change) => change,
+        };
+        for next in changes {
+            change = FolderChangeset {
+                operations: change.operations.compose(&next.operations)?,
+                md5: next.md5,
+            };
+        }
+        *folder = pad;
+        drop(folder);
+        self.apply_change(change)?;
+        Ok(output)
+    }
+
     #[allow(dead_code)]
     pub fn folder_json(&self) -> FlowyResult<String> {
         let json = self.folder.read().to_json()?;
@@ -131,6 +161,11 @@ impl RevisionMergeable for FolderRevisionMergeable {
     fn combine_revisions(&self, revisions: Vec<Revision>) -> FlowyResult<Bytes> {
         FolderRevisionSerde::combine_revisions(revisions)
     }
+
+    fn combine_snapshot(&self, revisions: Vec<Revision>) -> FlowyResult<Bytes> {
+        let operations = make_operations_from_revisions::<EmptyAttributes>(revisions)?;
+        Ok(operations.normalized().json_bytes())
+    }
 }
 
 struct FolderRevisionCloudService {
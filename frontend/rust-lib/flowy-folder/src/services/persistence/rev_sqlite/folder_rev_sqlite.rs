@@ -91,9 +91,31 @@ impl SQLiteFolderRevisionPersistence {
     }
 }
 
-struct FolderRevisionSql {}
+pub(crate) struct FolderRevisionSql {}
 
 impl FolderRevisionSql {
+    /// Returns the revisions of the folder saved in the database, e.g. the database of another
+    /// account that is merged, see `FolderManager::merge_local_database`. None if there is no
+    /// folder in the database.
+    pub(crate) fn read_folder(conn: &SqliteConnection) -> Result<Option<Vec<Revision>>, FlowyError> {
+        // The id of the folder is the id of its user followed by ":folder", see `FolderId`.
+        let folder_id = dsl::rev_table
+            .filter(dsl::doc_id.like("%:folder"))
+            .select(dsl::doc_id)
+            .first::<String>(conn)
+            .optional()?;
+        match folder_id {
+            None => Ok(None),
+            Some(folder_id) => {
+                let revisions = Self::read("", &folder_id, None, conn)?
+                    .into_iter()
+                    .map(|record| record.revision)
+                    .collect();
+                Ok(Some(revisions))
+            }
+        }
+    }
+
     fn create(revision_records: Vec<SyncRecord>, conn: &SqliteConnection) -> Result<(), FlowyError> {
         // Batch insert: https://diesel.rs/guides/all-about-inserts.html
 
@@ -207,6 +229,7 @@ struct RevisionTable {
 enum TextRevisionState {
     Sync = 0,
     Ack = 1,
+    Resolved = 2,
 }
 impl_sql_integer_expression!(TextRevisionState);
 impl_rev_state_map!(TextRevisionState);
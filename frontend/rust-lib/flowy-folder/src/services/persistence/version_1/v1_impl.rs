@@ -84,6 +84,10 @@ impl<'a> FolderPersistenceTransaction for V1Transaction<'a> {
         Ok(())
     }
 
+    fn set_view_scratch(&self, _view_id: &str, _is_scratch: bool) -> FlowyResult<()> {
+        Ok(())
+    }
+
     fn delete_view(&self, view_id: &str) -> FlowyResult<ViewRevision> {
         let view_revision: ViewRevision = ViewTableSql::read_view(view_id, self.0)?.into();
         ViewTableSql::delete_view(view_id, self.0)?;
@@ -183,6 +187,10 @@ where
         (**self).update_view(changeset)
     }
 
+    fn set_view_scratch(&self, view_id: &str, is_scratch: bool) -> FlowyResult<()> {
+        (**self).set_view_scratch(view_id, is_scratch)
+    }
+
     fn delete_view(&self, view_id: &str) -> FlowyResult<ViewRevision> {
         (**self).delete_view(view_id)
     }
@@ -131,6 +131,7 @@ impl std::convert::From<ViewTable> for ViewRevision {
             // Store the view in ViewTable was deprecated since v0.0.2.
             // No need to worry about layout.
             layout: ViewLayoutTypeRevision::Document,
+            is_scratch: false,
         }
     }
 }
@@ -1 +1,2 @@
+mod scratch_view_sql;
 pub mod v2_impl;
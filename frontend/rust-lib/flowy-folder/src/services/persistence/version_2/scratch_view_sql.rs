@@ -0,0 +1,102 @@
+use flowy_database::{prelude::*, schema::folder_scratch_view::dsl, SqliteConnection};
+use flowy_error::{internal_error, FlowyResult};
+use folder_rev_model::{TrashRevision, ViewRevision};
+
+/// The scratch views are kept in this table instead of the folder, so neither the views nor
+/// their trash reach the server with the folder's revisions. They're merged into the apps of
+/// the folder when it's read, see `FolderEditor`.
+pub(crate) struct ScratchViewSql();
+
+impl ScratchViewSql {
+    pub(crate) fn create_view(view_rev: &ViewRevision, conn: &SqliteConnection) -> FlowyResult<()> {
+        let data = serde_json::to_string(view_rev).map_err(internal_error)?;
+        let record = (
+            dsl::view_id.eq(&view_rev.id),
+            dsl::app_id.eq(&view_rev.app_id),
+            dsl::create_time.eq(view_rev.create_time),
+            dsl::data.eq(data),
+        );
+        let _ = diesel::replace_into(dsl::folder_scratch_view)
+            .values(record)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn update_view(view_rev: &ViewRevision, conn: &SqliteConnection) -> FlowyResult<()> {
+        let data = serde_json::to_string(view_rev).map_err(internal_error)?;
+        let _ = diesel::update(dsl::folder_scratch_view.filter(dsl::view_id.eq(&view_rev.id)))
+            .set(dsl::data.eq(data))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn read_view(view_id: &str, conn: &SqliteConnection) -> FlowyResult<Option<ViewRevision>> {
+        let data = dsl::folder_scratch_view
+            .filter(dsl::view_id.eq(view_id))
+            .select(dsl::data)
+            .first::<String>(conn)
+            .optional()?;
+        match data {
+            None => Ok(None),
+            Some(data) => Ok(Some(serde_json::from_str(&data).map_err(internal_error)?)),
+        }
+    }
+
+    /// Returns the scratch views of the app in the order they were created.
+    pub(crate) fn read_views(app_id: &str, conn: &SqliteConnection) -> FlowyResult<Vec<ViewRevision>> {
+        let rows = dsl::folder_scratch_view
+            .filter(dsl::app_id.eq(app_id))
+            .order(dsl::create_time.asc())
+            .select(dsl::data)
+            .load::<String>(conn)?;
+        rows.iter()
+            .map(|data| serde_json::from_str(data).map_err(internal_error))
+            .collect()
+    }
+
+    /// Returns false if the view isn't a scratch view.
+    pub(crate) fn delete_view(view_id: &str, conn: &SqliteConnection) -> FlowyResult<bool> {
+        let num = diesel::delete(dsl::folder_scratch_view.filter(dsl::view_id.eq(view_id))).execute(conn)?;
+        Ok(num > 0)
+    }
+
+    pub(crate) fn delete_app_views(app_id: &str, conn: &SqliteConnection) -> FlowyResult<()> {
+        let _ = diesel::delete(dsl::folder_scratch_view.filter(dsl::app_id.eq(app_id))).execute(conn)?;
+        Ok(())
+    }
+
+    /// Moves the scratch view to the trash, or puts it back if `trash` is None. Returns false if
+    /// the view isn't a scratch view.
+    pub(crate) fn set_trash(
+        view_id: &str,
+        trash: Option<&TrashRevision>,
+        conn: &SqliteConnection,
+    ) -> FlowyResult<bool> {
+        let trash = match trash {
+            None => "".to_owned(),
+            Some(trash) => serde_json::to_string(trash).map_err(internal_error)?,
+        };
+        let num = diesel::update(dsl::folder_scratch_view.filter(dsl::view_id.eq(view_id)))
+            .set(dsl::trash.eq(trash))
+            .execute(conn)?;
+        Ok(num > 0)
+    }
+
+    pub(crate) fn read_trash(conn: &SqliteConnection) -> FlowyResult<Vec<TrashRevision>> {
+        let rows = dsl::folder_scratch_view
+            .filter(dsl::trash.ne(""))
+            .order(dsl::create_time.asc())
+            .select(dsl::trash)
+            .load::<String>(conn)?;
+        rows.iter()
+            .map(|trash| serde_json::from_str(trash).map_err(internal_error))
+            .collect()
+    }
+
+    pub(crate) fn clear_trash(conn: &SqliteConnection) -> FlowyResult<()> {
+        let _ = diesel::update(dsl::folder_scratch_view.filter(dsl::trash.ne("")))
+            .set(dsl::trash.eq(""))
+            .execute(conn)?;
+        Ok(())
+    }
+}
@@ -1,11 +1,30 @@
 use crate::services::{
     folder_editor::FolderEditor,
-    persistence::{AppChangeset, FolderPersistenceTransaction, ViewChangeset, WorkspaceChangeset},
+    persistence::{
+        version_2::scratch_view_sql::ScratchViewSql, AppChangeset, FolderPersistenceTransaction, ViewChangeset,
+        WorkspaceChangeset,
+    },
 };
-use flowy_error::{FlowyError, FlowyResult};
+use flowy_database::DBConnection;
+use flowy_error::{internal_error, FlowyError, FlowyResult};
 use folder_rev_model::{AppRevision, TrashRevision, ViewRevision, WorkspaceRevision};
 use std::sync::Arc;
 
+impl FolderEditor {
+    fn db_connection(&self) -> FlowyResult<DBConnection> {
+        self.pool.get().map_err(internal_error)
+    }
+
+    /// Adds the scratch views, which aren't part of the folder, to the views of the `apps`.
+    fn with_scratch_views(&self, mut apps: Vec<AppRevision>) -> FlowyResult<Vec<AppRevision>> {
+        let conn = self.db_connection()?;
+        for app in apps.iter_mut() {
+            app.belongings.extend(ScratchViewSql::read_views(&app.id, &conn)?);
+        }
+        Ok(apps)
+    }
+}
+
 impl FolderPersistenceTransaction for FolderEditor {
     fn create_workspace(&self, _user_id: &str, workspace_rev: WorkspaceRevision) -> FlowyResult<()> {
         if let Some(change) = self.folder.write().create_workspace(workspace_rev)? {
@@ -15,7 +34,10 @@ impl FolderPersistenceTransaction for FolderEditor {
     }
 
     fn read_workspaces(&self, _user_id: &str, workspace_id: Option<String>) -> FlowyResult<Vec<WorkspaceRevision>> {
-        let workspaces = self.folder.read().read_workspaces(workspace_id)?;
+        let mut workspaces = self.folder.read().read_workspaces(workspace_id)?;
+        for workspace in workspaces.iter_mut() {
+            workspace.apps = self.with_scratch_views(std::mem::take(&mut workspace.apps))?;
+        }
         Ok(workspaces)
     }
 
@@ -57,7 +79,7 @@ impl FolderPersistenceTransaction for FolderEditor {
 
     fn read_app(&self, app_id: &str) -> FlowyResult<AppRevision> {
         let app = self.folder.read().read_app(app_id)?;
-        Ok(app)
+        Ok(self.with_scratch_views(vec![app])?.pop().unwrap())
     }
 
     fn read_workspace_apps(&self, workspace_id: &str) -> FlowyResult<Vec<AppRevision>> {
@@ -66,12 +88,13 @@ impl FolderPersistenceTransaction for FolderEditor {
             None => {
                 Err(FlowyError::record_not_found().context(format!("can't find workspace with id {}", workspace_id)))
             }
-            Some(workspace) => Ok(workspace.apps.clone()),
+            Some(workspace) => self.with_scratch_views(workspace.apps.clone()),
         }
     }
 
     fn delete_app(&self, app_id: &str) -> FlowyResult<AppRevision> {
-        let app = self.folder.read().read_app(app_id)?;
+        let app = self.read_app(app_id)?;
+        ScratchViewSql::delete_app_views(app_id, &*self.db_connection()?)?;
         if let Some(change) = self.folder.write().delete_app(app_id)? {
             self.apply_change(change)?;
         }
@@ -86,6 +109,9 @@ impl FolderPersistenceTransaction for FolderEditor {
     }
 
     fn create_view(&self, view_rev: ViewRevision) -> FlowyResult<()> {
+        if view_rev.is_scratch {
+            return ScratchViewSql::create_view(&view_rev, &*self.db_connection()?);
+        }
         if let Some(change) = self.folder.write().create_view(view_rev)? {
             self.apply_change(change)?;
         }
@@ -93,16 +119,31 @@ impl FolderPersistenceTransaction for FolderEditor {
     }
 
     fn read_view(&self, view_id: &str) -> FlowyResult<ViewRevision> {
+        if let Some(view) = ScratchViewSql::read_view(view_id, &*self.db_connection()?)? {
+            return Ok(view);
+        }
         let view = self.folder.read().read_view(view_id)?;
         Ok(view)
     }
 
     fn read_views(&self, belong_to_id: &str) -> FlowyResult<Vec<ViewRevision>> {
-        let views = self.folder.read().read_views(belong_to_id)?;
+        let mut views = self.folder.read().read_views(belong_to_id)?;
+        views.extend(ScratchViewSql::read_views(belong_to_id, &*self.db_connection()?)?);
         Ok(views)
     }
 
     fn update_view(&self, changeset: ViewChangeset) -> FlowyResult<()> {
+        let conn = self.db_connection()?;
+        if let Some(mut view) = ScratchViewSql::read_view(&changeset.id, &conn)? {
+            if let Some(name) = changeset.name {
+                view.name = name;
+            }
+            if let Some(desc) = changeset.desc {
+                view.desc = desc;
+            }
+            view.modified_time = changeset.modified_time;
+            return ScratchViewSql::update_view(&view, &conn);
+        }
         if let Some(change) =
             self.folder
                 .write()
@@ -113,7 +154,39 @@ impl FolderPersistenceTransaction for FolderEditor {
         Ok(())
     }
 
+    /// Moves the view between the folder and the scratch views.
+    fn set_view_scratch(&self, view_id: &str, is_scratch: bool) -> FlowyResult<()> {
+        let conn = self.db_connection()?;
+        match ScratchViewSql::read_view(view_id, &conn)? {
+            Some(mut view) => {
+                if !is_scratch {
+                    view.is_scratch = false;
+                    if let Some(change) = self.folder.write().create_view(view)? {
+                        self.apply_change(change)?;
+                    }
+                    ScratchViewSql::delete_view(view_id, &conn)?;
+                }
+            }
+            None => {
+                if is_scratch {
+                    let mut view = self.folder.read().read_view(view_id)?;
+                    if let Some(change) = self.folder.write().delete_view(&view.app_id, view_id)? {
+                        self.apply_change(change)?;
+                    }
+                    view.is_scratch = true;
+                    ScratchViewSql::create_view(&view, &conn)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn delete_view(&self, view_id: &str) -> FlowyResult<ViewRevision> {
+        let conn = self.db_connection()?;
+        if let Some(view) = ScratchViewSql::read_view(view_id, &conn)? {
+            ScratchViewSql::delete_view(view_id, &conn)?;
+            return Ok(view);
+        }
         let view = self.folder.read().read_view(view_id)?;
         if let Some(change) = self.folder.write().delete_view(&view.app_id, view_id)? {
             self.apply_change(change)?;
@@ -122,6 +195,10 @@ impl FolderPersistenceTransaction for FolderEditor {
     }
 
     fn move_view(&self, view_id: &str, from: usize, to: usize) -> FlowyResult<()> {
+        // The scratch views stay after the views of the folder.
+        if ScratchViewSql::read_view(view_id, &*self.db_connection()?)?.is_some() {
+            return Ok(());
+        }
         if let Some(change) = self.folder.write().move_view(view_id, from, to)? {
             self.apply_change(change)?;
         }
@@ -129,18 +206,53 @@ impl FolderPersistenceTransaction for FolderEditor {
     }
 
     fn create_trash(&self, trashes: Vec<TrashRevision>) -> FlowyResult<()> {
-        if let Some(change) = self.folder.write().create_trash(trashes)? {
+        let conn = self.db_connection()?;
+        let mut folder_trashes = vec![];
+        for trash in trashes {
+            if !ScratchViewSql::set_trash(&trash.id, Some(&trash), &conn)? {
+                folder_trashes.push(trash);
+            }
+        }
+        if folder_trashes.is_empty() {
+            return Ok(());
+        }
+        if let Some(change) = self.folder.write().create_trash(folder_trashes)? {
             self.apply_change(change)?;
         }
         Ok(())
     }
 
     fn read_trash(&self, trash_id: Option<String>) -> FlowyResult<Vec<TrashRevision>> {
-        let trash = self.folder.read().read_trash(trash_id)?;
+        let mut trash = self.folder.read().read_trash(trash_id.clone())?;
+        let scratch_trash = ScratchViewSql::read_trash(&*self.db_connection()?)?;
+        trash.extend(
+            scratch_trash
+                .into_iter()
+                .filter(|trash| trash_id.as_ref().map(|id| id == &trash.id).unwrap_or(true)),
+        );
         Ok(trash)
     }
 
     fn delete_trash(&self, trash_ids: Option<Vec<String>>) -> FlowyResult<()> {
+        let conn = self.db_connection()?;
+        let trash_ids = match trash_ids {
+            None => {
+                ScratchViewSql::clear_trash(&conn)?;
+                None
+            }
+            Some(trash_ids) => {
+                let mut folder_trash_ids = vec![];
+                for trash_id in trash_ids {
+                    if !ScratchViewSql::set_trash(&trash_id, None, &conn)? {
+                        folder_trash_ids.push(trash_id);
+                    }
+                }
+                if folder_trash_ids.is_empty() {
+                    return Ok(());
+                }
+                Some(folder_trash_ids)
+            }
+        };
         if let Some(change) = self.folder.write().delete_trash(trash_ids)? {
             self.apply_change(change)?;
         }
@@ -208,6 +320,10 @@ where
         (**self).update_view(changeset)
     }
 
+    fn set_view_scratch(&self, view_id: &str, is_scratch: bool) -> FlowyResult<()> {
+        (**self).set_view_scratch(view_id, is_scratch)
+    }
+
     fn delete_view(&self, view_id: &str) -> FlowyResult<ViewRevision> {
         (**self).delete_view(view_id)
     }
@@ -169,7 +169,17 @@ impl TrashController {
         self.persistence
             .begin_transaction(|transaction| {
                 transaction.create_trash(trash_revs.clone())?;
-                let _ = self.create_trash_on_server(trash_revs);
+                // The scratch views never reach the server, nor does their trash.
+                let server_trash_revs = trash_revs
+                    .into_iter()
+                    .filter(|trash| match transaction.read_view(&trash.id) {
+                        Ok(view) => !view.is_scratch,
+                        Err(_) => true,
+                    })
+                    .collect::<Vec<TrashRevision>>();
+                if !server_trash_revs.is_empty() {
+                    let _ = self.create_trash_on_server(server_trash_revs);
+                }
 
                 notify_trash_changed(transaction.read_trash(None)?);
                 Ok(())
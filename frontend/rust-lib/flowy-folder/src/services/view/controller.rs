@@ -5,12 +5,13 @@ use crate::{
     dart_notification::{send_dart_notification, FolderNotification},
     entities::{
         trash::{RepeatedTrashIdPB, TrashType},
-        view::{CreateViewParams, RepeatedViewPB, UpdateViewParams, ViewIdPB, ViewPB},
+        view::{CreateViewParams, ImportPortableViewParams, RepeatedViewPB, UpdateViewParams, ViewIdPB, ViewPB},
     },
     errors::{FlowyError, FlowyResult},
     event_map::{FolderCouldServiceV1, WorkspaceUser},
     services::{
         persistence::{FolderPersistence, FolderPersistenceTransaction, ViewChangeset},
+        view::idempotency::{group_duplicated_views, read_view_id_with_idempotency_key, save_idempotency_key},
         TrashController, TrashEvent,
     },
 };
@@ -19,7 +20,12 @@ use flowy_database::kv::KV;
 use flowy_http_model::document::DocumentId;
 use folder_rev_model::{gen_view_id, ViewRevision};
 use futures::{FutureExt, StreamExt};
-use std::{collections::HashSet, sync::Arc};
+use lib_infra::util::timestamp;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use tokio::sync::Mutex;
 
 const LATEST_VIEW_ID: &str = "latest_view_id";
 
@@ -29,6 +35,9 @@ pub(crate) struct ViewController {
     persistence: Arc<FolderPersistence>,
     trash_controller: Arc<TrashController>,
     data_processors: ViewDataProcessorMap,
+    // Serializes the creations that carry an idempotency key, so a repeated key always finds
+    // the view created by the first one.
+    idempotency_lock: Mutex<()>,
 }
 
 impl ViewController {
@@ -45,6 +54,7 @@ impl ViewController {
             persistence,
             trash_controller,
             data_processors,
+            idempotency_lock: Mutex::new(()),
         }
     }
 
@@ -58,8 +68,29 @@ impl ViewController {
         &self,
         mut params: CreateViewParams,
     ) -> Result<ViewRevision, FlowyError> {
-        let processor = self.get_data_processor(params.data_format.clone())?;
         let user_id = self.user.user_id()?;
+        let _idempotency_guard = match params.idempotency_key.as_ref() {
+            None => None,
+            Some(key) => {
+                let guard = self.idempotency_lock.lock().await;
+                if let Some(view_id) = read_view_id_with_idempotency_key(&user_id, key) {
+                    match self.read_view(&view_id).await {
+                        Ok(view_rev) => {
+                            tracing::debug!("The view:{} was created with the same key", view_id);
+                            return Ok(view_rev);
+                        }
+                        Err(e) => tracing::warn!("Read the view:{} created with the same key failed: {}", view_id, e),
+                    }
+                }
+                Some(guard)
+            }
+        };
+
+        let processor = self.get_data_processor(params.data_format.clone())?;
+        if params.is_scratch {
+            processor.set_view_scratch(&params.view_id, true).await?;
+        }
+
         if params.view_content_data.is_empty() {
             tracing::trace!("Create view with build-in data");
             let view_data = processor
@@ -90,11 +121,55 @@ impl ViewController {
             .await?;
         };
 
-        let view_rev = self.create_view_on_server(params).await?;
+        let idempotency_key = params.idempotency_key.clone();
+        let view_rev = if params.is_scratch {
+            make_scratch_view_rev(params)
+        } else {
+            self.create_view_on_server(params).await?
+        };
         self.create_view_on_local(view_rev.clone()).await?;
+        if let Some(key) = idempotency_key {
+            save_idempotency_key(&user_id, &key, &view_rev.id);
+        }
         Ok(view_rev)
     }
 
+    /// Returns the views of the app that look like leftovers of a repeated create action: they
+    /// have an identical sibling created a few seconds apart and nothing was written into them.
+    /// The first created view of each group is kept if none of the group was edited.
+    #[tracing::instrument(level = "trace", skip(self), err)]
+    pub(crate) async fn read_duplicated_views(&self, app_id: &str) -> FlowyResult<Vec<ViewRevision>> {
+        let view_revs = self.read_views_belong_to(app_id).await?;
+        let mut duplicated_views = vec![];
+        for group in group_duplicated_views(view_revs) {
+            let num_of_views = group.len();
+            let mut untouched_views = vec![];
+            for view_rev in group {
+                let processor = self.get_data_processor(view_rev.data_format.clone())?;
+                if processor.is_view_untouched(&view_rev.id).await? {
+                    untouched_views.push(view_rev);
+                }
+            }
+            if untouched_views.len() == num_of_views {
+                untouched_views.remove(0);
+            }
+            duplicated_views.extend(untouched_views);
+        }
+        Ok(duplicated_views)
+    }
+
+    /// Sends the duplicated views of the app to the client, which offers the user to delete them.
+    pub(crate) async fn notify_duplicated_views(&self, app_id: &str) -> FlowyResult<()> {
+        let duplicated_views = self.read_duplicated_views(app_id).await?;
+        if !duplicated_views.is_empty() {
+            let items = duplicated_views.into_iter().map(|view_rev| view_rev.into()).collect();
+            send_dart_notification(app_id, FolderNotification::DuplicatedViewsFound)
+                .payload(RepeatedViewPB { items })
+                .send();
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(level = "debug", skip(self, view_id, view_data), err)]
     pub(crate) async fn create_view(
         &self,
@@ -265,12 +340,43 @@ impl ViewController {
             layout: view_rev.layout.into(),
             view_content_data: view_data.to_vec(),
             view_id: gen_view_id(),
+            is_scratch: false,
+            idempotency_key: None,
         };
 
         let _ = self.create_view_from_params(duplicate_params).await?;
         Ok(())
     }
 
+    /// Imports the document exported by `DocumentEvent::ExportPortable` as a new view of the app.
+    /// The document gets a new id, the view takes it.
+    #[tracing::instrument(level = "debug", skip(self, params), err)]
+    pub(crate) async fn import_portable_view(&self, params: ImportPortableViewParams) -> FlowyResult<ViewRevision> {
+        let processor = self.get_data_processor(ViewDataFormatPB::DeltaFormat)?;
+        let view_id = processor.import_view(&params.path).await?;
+        let create_params = CreateViewParams {
+            belong_to_id: params.belong_to_id,
+            name: params.name,
+            desc: "".to_owned(),
+            thumbnail: "".to_owned(),
+            data_format: ViewDataFormatPB::DeltaFormat,
+            layout: ViewLayoutTypePB::Document,
+            view_content_data: vec![],
+            view_id,
+            is_scratch: false,
+            idempotency_key: None,
+        };
+        let view_rev = self.create_view_on_server(create_params).await?;
+        self.create_view_on_local(view_rev.clone()).await?;
+        Ok(view_rev)
+    }
+
+    /// Copies the documents of the database under `dir`, see `FolderManager::merge_local_database`.
+    pub(crate) async fn merge_local_database(&self, dir: &str) -> FlowyResult<HashMap<String, String>> {
+        let processor = self.get_data_processor(ViewDataFormatPB::DeltaFormat)?;
+        processor.merge_local_database(dir).await
+    }
+
     // belong_to_id will be the app_id or view_id.
     #[tracing::instrument(level = "trace", skip(self), err)]
     pub(crate) async fn read_views_belong_to(&self, belong_to_id: &str) -> Result<Vec<ViewRevision>, FlowyError> {
@@ -299,10 +405,43 @@ impl ViewController {
             })
             .await?;
 
-        let _ = self.update_view_on_server(params);
+        if !view_rev.is_scratch {
+            let _ = self.update_view_on_server(params);
+        }
         Ok(view_rev)
     }
 
+    /// Marks the view as a scratch view or a normal one. A scratch view that becomes a normal
+    /// view is added to the folder and created on the server. A normal view that becomes a
+    /// scratch view is removed from the folder, but it's left on the server as it is.
+    #[tracing::instrument(level = "debug", skip(self), err)]
+    pub(crate) async fn set_view_scratch(&self, view_id: &str, is_scratch: bool) -> FlowyResult<()> {
+        let view_rev = self
+            .persistence
+            .begin_transaction(|transaction| {
+                let view_rev = transaction.read_view(view_id)?;
+                transaction.set_view_scratch(view_id, is_scratch)?;
+                Ok(view_rev)
+            })
+            .await?;
+        if view_rev.is_scratch && !is_scratch {
+            let params = CreateViewParams {
+                belong_to_id: view_rev.app_id,
+                name: view_rev.name,
+                desc: view_rev.desc,
+                thumbnail: view_rev.thumbnail,
+                data_format: view_rev.data_format.into(),
+                layout: view_rev.layout.into(),
+                view_id: view_rev.id,
+                view_content_data: vec![],
+                is_scratch: false,
+                idempotency_key: None,
+            };
+            let _ = self.create_view_on_server(params).await?;
+        }
+        Ok(())
+    }
+
     pub(crate) async fn latest_visit_view(&self) -> FlowyResult<Option<ViewRevision>> {
         match KV::get_str(LATEST_VIEW_ID) {
             None => Ok(None),
@@ -506,6 +645,26 @@ fn get_data_processor(
     }
 }
 
+/// Makes the view of the scratch view's `params` on this device, the server never sees it.
+fn make_scratch_view_rev(params: CreateViewParams) -> ViewRevision {
+    let time = timestamp();
+    ViewRevision {
+        id: params.view_id,
+        app_id: params.belong_to_id,
+        name: params.name,
+        desc: params.desc,
+        data_format: params.data_format.into(),
+        version: 0,
+        belongings: vec![],
+        modified_time: time,
+        create_time: time,
+        ext_data: "".to_string(),
+        thumbnail: params.thumbnail,
+        layout: params.layout.into(),
+        is_scratch: true,
+    }
+}
+
 fn read_local_views_with_transaction<'a>(
     identifiers: RepeatedTrashIdPB,
     transaction: &'a (dyn FolderPersistenceTransaction + 'a),
@@ -1,3 +1,4 @@
+use crate::entities::app::AppIdPB;
 use crate::entities::view::{MoveFolderItemParams, MoveFolderItemPayloadPB, MoveFolderItemType};
 use crate::entities::ViewInfoPB;
 use crate::manager::FolderManager;
@@ -6,8 +7,8 @@ use crate::{
     entities::{
         trash::TrashPB,
         view::{
-            CreateViewParams, CreateViewPayloadPB, RepeatedViewIdPB, UpdateViewParams, UpdateViewPayloadPB, ViewIdPB,
-            ViewPB,
+            CreateViewParams, CreateViewPayloadPB, ImportPortableViewParams, ImportPortableViewPayloadPB,
+            RepeatedViewIdPB, RepeatedViewPB, UpdateViewParams, UpdateViewPayloadPB, ViewIdPB, ViewPB,
         },
     },
     errors::FlowyError,
@@ -23,9 +24,35 @@ pub(crate) async fn create_view_handler(
 ) -> DataResult<ViewPB, FlowyError> {
     let params: CreateViewParams = data.into_inner().try_into()?;
     let view_rev = controller.create_view_from_params(params).await?;
+    if let Err(e) = controller.notify_duplicated_views(&view_rev.app_id).await {
+        tracing::error!("Read the duplicated views failed: {:?}", e);
+    }
+    data_result(view_rev.into())
+}
+
+pub(crate) async fn import_portable_view_handler(
+    data: AFPluginData<ImportPortableViewPayloadPB>,
+    controller: AFPluginState<Arc<ViewController>>,
+) -> DataResult<ViewPB, FlowyError> {
+    let params: ImportPortableViewParams = data.into_inner().try_into()?;
+    let view_rev = controller.import_portable_view(params).await?;
     data_result(view_rev.into())
 }
 
+pub(crate) async fn read_duplicated_views_handler(
+    data: AFPluginData<AppIdPB>,
+    controller: AFPluginState<Arc<ViewController>>,
+) -> DataResult<RepeatedViewPB, FlowyError> {
+    let app_id: AppIdPB = data.into_inner();
+    let items = controller
+        .read_duplicated_views(&app_id.value)
+        .await?
+        .into_iter()
+        .map(|view_rev| view_rev.into())
+        .collect();
+    data_result(RepeatedViewPB { items })
+}
+
 pub(crate) async fn read_view_handler(
     data: AFPluginData<ViewIdPB>,
     controller: AFPluginState<Arc<ViewController>>,
@@ -0,0 +1,92 @@
+use flowy_database::kv::KV;
+use folder_rev_model::ViewRevision;
+use lib_infra::util::timestamp;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A repeated create event is only recognized within this period. The client generates a new
+/// key for each "create" action, so it only needs to outlive the retries of that action.
+pub const VIEW_IDEMPOTENCY_KEY_EXPIRED_IN_SECS: i64 = 60;
+
+/// Views created within this period in the same app with the same name and layout are
+/// considered to be created by the same action.
+pub const DUPLICATED_VIEW_WINDOW_IN_SECS: i64 = 5;
+
+const RECENT_VIEW_IDEMPOTENCY_KEYS: &str = "recent_view_idempotency_keys";
+
+#[derive(Serialize, Deserialize, Default)]
+struct RecentIdempotencyKeys(HashMap<String, RecentIdempotencyKey>);
+
+#[derive(Serialize, Deserialize)]
+struct RecentIdempotencyKey {
+    view_id: String,
+    timestamp: i64,
+}
+
+/// Returns the id of the view that was created with the key if the key hasn't expired.
+pub(crate) fn read_view_id_with_idempotency_key(user_id: &str, key: &str) -> Option<String> {
+    let keys = read_recent_keys();
+    let recent_key = keys.0.get(&idempotency_key(user_id, key))?;
+    if timestamp() - recent_key.timestamp > VIEW_IDEMPOTENCY_KEY_EXPIRED_IN_SECS {
+        return None;
+    }
+    Some(recent_key.view_id.clone())
+}
+
+/// Remembers the view created with the key. The expired keys are removed at the same time.
+pub(crate) fn save_idempotency_key(user_id: &str, key: &str, view_id: &str) {
+    let now = timestamp();
+    let mut keys = read_recent_keys();
+    keys.0
+        .retain(|_, recent_key| now - recent_key.timestamp <= VIEW_IDEMPOTENCY_KEY_EXPIRED_IN_SECS);
+    keys.0.insert(
+        idempotency_key(user_id, key),
+        RecentIdempotencyKey {
+            view_id: view_id.to_owned(),
+            timestamp: now,
+        },
+    );
+    match serde_json::to_string(&keys) {
+        Ok(s) => KV::set_str(RECENT_VIEW_IDEMPOTENCY_KEYS, s),
+        Err(e) => tracing::error!("Serialize the idempotency keys failed: {:?}", e),
+    }
+}
+
+fn read_recent_keys() -> RecentIdempotencyKeys {
+    KV::get_str(RECENT_VIEW_IDEMPOTENCY_KEYS)
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn idempotency_key(user_id: &str, key: &str) -> String {
+    format!("{}:{}", user_id, key)
+}
+
+/// Groups the views that look like they were created by the same action: they have the same
+/// name and layout, and each of them was created within `DUPLICATED_VIEW_WINDOW_IN_SECS` after
+/// the previous one. Each group is sorted by the create time, groups of one view are skipped.
+pub(crate) fn group_duplicated_views(mut view_revs: Vec<ViewRevision>) -> Vec<Vec<ViewRevision>> {
+    view_revs.sort_by(|a, b| {
+        a.name
+            .cmp(&b.name)
+            .then((a.layout.clone() as u8).cmp(&(b.layout.clone() as u8)))
+            .then(a.create_time.cmp(&b.create_time))
+    });
+
+    let mut groups: Vec<Vec<ViewRevision>> = vec![];
+    for view_rev in view_revs {
+        match groups.last_mut() {
+            Some(group) if is_duplicated(group.last().unwrap(), &view_rev) => group.push(view_rev),
+            _ => groups.push(vec![view_rev]),
+        }
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+fn is_duplicated(previous: &ViewRevision, view_rev: &ViewRevision) -> bool {
+    previous.name == view_rev.name
+        && previous.layout == view_rev.layout
+        && previous.data_format == view_rev.data_format
+        && view_rev.create_time - previous.create_time <= DUPLICATED_VIEW_WINDOW_IN_SECS
+}
@@ -43,7 +43,10 @@ pub(crate) async fn make_folder_ws_manager(
     web_socket: Arc<dyn RevisionWebSocket>,
     folder_pad: Arc<RwLock<FolderPad>>,
 ) -> Arc<RevisionWebSocketManager> {
-    let ws_data_provider = Arc::new(WSDataProvider::new(folder_id, Arc::new(rev_manager.clone())));
+    let executor = rev_manager.executor().clone();
+    let error_reporter = rev_manager.error_reporter();
+    let ws_data_provider =
+        Arc::new(WSDataProvider::new(folder_id, Arc::new(rev_manager.clone())).with_session(WSSession::default()));
     let resolver = Arc::new(FolderConflictResolver { folder_pad });
     let conflict_controller =
         FolderConflictController::new(user_id, resolver, Arc::new(ws_data_provider.clone()), rev_manager);
@@ -57,6 +60,8 @@ pub(crate) async fn make_folder_ws_manager(
         ws_data_sink,
         ws_data_stream,
         ping_duration,
+        error_reporter,
+        executor,
     ))
 }
 
@@ -66,6 +71,20 @@ impl RevisionWebSocketSink for FolderWSDataSink {
         let sink_provider = self.0.clone();
         FutureResult::new(async move { sink_provider.next().await })
     }
+
+    fn did_send(&self, data: &ClientRevisionWSData) -> FutureResult<(), FlowyError> {
+        let sink_provider = self.0.clone();
+        let data = data.clone();
+        FutureResult::new(async move { sink_provider.did_send(&data).await })
+    }
+
+    fn did_fail_to_send(&self, data: &ClientRevisionWSData, error: &FlowyError) {
+        self.0.did_fail_to_send(data, error);
+    }
+
+    fn session(&self) -> Option<WSSession> {
+        self.0.session()
+    }
 }
 
 struct FolderConflictResolver {
@@ -159,3 +159,16 @@ fn read_workspaces_on_server(
 
     Ok(())
 }
+
+#[tracing::instrument(level = "debug", skip(data, folder), err)]
+pub(crate) async fn merge_local_database_handler(
+    data: AFPluginData<MergeLocalDatabasePayloadPB>,
+    folder: AFPluginState<Arc<FolderManager>>,
+) -> DataResult<RepeatedAppPB, FlowyError> {
+    let payload: MergeLocalDatabasePayloadPB = data.into_inner();
+    let apps = folder.merge_local_database(&payload.path).await?;
+    let repeated_app = RepeatedAppPB {
+        items: apps.into_iter().map(|app_rev| app_rev.into()).collect(),
+    };
+    data_result(repeated_app)
+}
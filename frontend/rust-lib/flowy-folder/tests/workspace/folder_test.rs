@@ -1,8 +1,15 @@
-use crate::script::{invalid_workspace_name_test_case, FolderScript::*, FolderTest};
+use crate::script::{
+    create_scratch_view, create_view_with_idempotency_key, delete_view, invalid_workspace_name_test_case,
+    merge_local_database, read_app, read_duplicated_views, read_trash, update_view, FolderScript::*, FolderTest,
+};
+use flowy_folder::entities::app::AppPB;
 use flowy_folder::entities::view::ViewDataFormatPB;
 use flowy_folder::entities::workspace::CreateWorkspacePayloadPB;
+use flowy_revision::REVISION_WRITE_INTERVAL_IN_MILLIS;
 use flowy_revision_persistence::RevisionState;
 use flowy_test::{event_builder::*, FlowySDKTest};
+use std::time::Duration;
+use tokio::time::sleep;
 
 #[tokio::test]
 async fn workspace_read_all() {
@@ -150,6 +157,82 @@ async fn app_create_with_view() {
     assert_eq!(app.belongings[2].name, "Grid")
 }
 
+#[tokio::test]
+async fn view_create_with_same_idempotency_key() {
+    let test = FolderTest::new().await;
+    let app_id = test.app.id.clone();
+    let (view_a, view_b) = tokio::join!(
+        create_view_with_idempotency_key(&test.sdk, &app_id, "Untitled", "new_page_1"),
+        create_view_with_idempotency_key(&test.sdk, &app_id, "Untitled", "new_page_1"),
+    );
+    assert_eq!(view_a.id, view_b.id);
+
+    let app = read_app(&test.sdk, &app_id).await;
+    let views = app
+        .belongings
+        .items
+        .iter()
+        .filter(|view| view.name == "Untitled")
+        .collect::<Vec<_>>();
+    assert_eq!(views.len(), 1);
+
+    // A new click generates a new key.
+    let view_c = create_view_with_idempotency_key(&test.sdk, &app_id, "Untitled", "new_page_2").await;
+    assert_ne!(view_a.id, view_c.id);
+}
+
+#[tokio::test]
+async fn view_read_duplicated_views() {
+    let test = FolderTest::new().await;
+    let app_id = test.app.id.clone();
+    let view_a = create_view_with_idempotency_key(&test.sdk, &app_id, "Untitled", "new_page_1").await;
+    let view_b = create_view_with_idempotency_key(&test.sdk, &app_id, "Untitled", "new_page_2").await;
+    let _ = create_view_with_idempotency_key(&test.sdk, &app_id, "Another page", "new_page_3").await;
+
+    // Both views are untouched, the first created one is kept.
+    let duplicated_views = read_duplicated_views(&test.sdk, &app_id).await;
+    assert_eq!(duplicated_views.items.len(), 1);
+    assert_eq!(duplicated_views.items[0].id, view_b.id);
+    assert_ne!(duplicated_views.items[0].id, view_a.id);
+}
+
+#[tokio::test]
+async fn scratch_view_not_in_folder_revisions_test() {
+    let test = FolderTest::new().await;
+    let app_id = test.app.id.clone();
+    let view = create_scratch_view(&test.sdk, &app_id, "Scratch view").await;
+    assert!(view.is_scratch);
+    update_view(&test.sdk, &view.id, Some("Renamed scratch view".to_owned()), None).await;
+
+    // The scratch view is merged into the views of its app when the folder is read.
+    let app = read_app(&test.sdk, &app_id).await;
+    let scratch_view = app.belongings.items.iter().find(|item| item.id == view.id).unwrap();
+    assert_eq!(scratch_view.name, "Renamed scratch view");
+
+    delete_view(&test.sdk, vec![view.id.clone()]).await;
+    let trash = read_trash(&test.sdk).await;
+    assert!(trash.items.iter().any(|item| item.id == view.id));
+    let app = read_app(&test.sdk, &app_id).await;
+    assert!(app.belongings.items.iter().all(|item| item.id != view.id));
+
+    sleep(Duration::from_millis(2 * REVISION_WRITE_INTERVAL_IN_MILLIS)).await;
+    let revisions = test
+        .sdk
+        .folder_manager
+        .folder_editor()
+        .await
+        .rev_manager()
+        .load_revisions()
+        .await
+        .unwrap();
+    assert!(!revisions.is_empty());
+    for revision in revisions {
+        let data = String::from_utf8_lossy(&revision.bytes);
+        assert!(!data.contains(&view.id));
+        assert!(!data.contains("Scratch view") && !data.contains("Renamed scratch view"));
+    }
+}
+
 #[tokio::test]
 async fn view_update() {
     let mut test = FolderTest::new().await;
@@ -317,6 +400,41 @@ async fn folder_sync_revision_seq() {
 //     test.run_scripts(vec![ReadApp(app.id.clone()), AssertApp(app)]).await;
 // }
 
+#[tokio::test]
+async fn merge_local_database_test() {
+    let source = FolderTest::new().await;
+    // The folder revisions are written in the background.
+    sleep(Duration::from_millis(2 * REVISION_WRITE_INTERVAL_IN_MILLIS)).await;
+    let source_dir = source.sdk.user_session.user_dir().unwrap();
+
+    let test = FolderTest::new().await;
+    let merged_views = |apps: &[AppPB]| {
+        apps.iter()
+            .flat_map(|app| app.belongings.items.iter())
+            .filter(|view| view.name.starts_with(&source.view.name) && view.id != test.view.id)
+            .map(|view| view.id.clone())
+            .collect::<Vec<_>>()
+    };
+    let apps = merge_local_database(&test.sdk, &source_dir).await;
+    let view_ids = merged_views(&apps);
+    assert_eq!(view_ids.len(), 1);
+    assert_ne!(view_ids[0], source.view.id);
+
+    // The apps and their views are created together, the app that is read back has the view.
+    let merged_app = apps
+        .iter()
+        .find(|app| app.belongings.items.iter().any(|view| view.id == view_ids[0]))
+        .unwrap();
+    let app = read_app(&test.sdk, &merged_app.id).await;
+    assert!(app.belongings.items.iter().any(|view| view.id == view_ids[0]));
+
+    // Merging again doesn't add the views again.
+    let apps = merge_local_database(&test.sdk, &source_dir).await;
+    let app = read_app(&test.sdk, &merged_app.id).await;
+    assert_eq!(merged_views(&apps), view_ids);
+    assert_eq!(merged_views(&[app]), view_ids);
+}
+
 // #[tokio::test]
 // async fn folder_sync_revision_with_new_view() {
 //     let mut test = FolderTest::new().await;
@@ -4,7 +4,7 @@ use flowy_folder::entities::{
     app::{AppIdPB, CreateAppPayloadPB, UpdateAppPayloadPB},
     trash::{RepeatedTrashPB, TrashIdPB, TrashType},
     view::{CreateViewPayloadPB, UpdateViewPayloadPB},
-    workspace::{CreateWorkspacePayloadPB, RepeatedWorkspacePB},
+    workspace::{CreateWorkspacePayloadPB, MergeLocalDatabasePayloadPB, RepeatedWorkspacePB},
     ViewLayoutTypePB,
 };
 use flowy_folder::entities::{
@@ -291,6 +291,17 @@ pub async fn read_workspace(sdk: &FlowySDKTest, workspace_id: Option<String>) ->
     workspaces
 }
 
+pub async fn merge_local_database(sdk: &FlowySDKTest, path: &str) -> Vec<AppPB> {
+    let request = MergeLocalDatabasePayloadPB { path: path.to_owned() };
+    FolderEventBuilder::new(sdk.clone())
+        .event(MergeLocalDatabase)
+        .payload(request)
+        .async_send()
+        .await
+        .parse::<RepeatedAppPB>()
+        .items
+}
+
 pub async fn create_app(sdk: &FlowySDKTest, workspace_id: &str, name: &str, desc: &str) -> AppPB {
     let create_app_request = CreateAppPayloadPB {
         workspace_id: workspace_id.to_owned(),
@@ -364,6 +375,30 @@ pub async fn create_view(
         data_format: data_type,
         layout,
         view_content_data: vec![],
+        is_scratch: false,
+        idempotency_key: None,
+    };
+    FolderEventBuilder::new(sdk.clone())
+        .event(CreateView)
+        .payload(request)
+        .async_send()
+        .await
+        .parse::<ViewPB>()
+}
+
+/// Creates a document view like the "New page" button, which generates the `idempotency_key`
+/// for each click.
+pub async fn create_view_with_idempotency_key(sdk: &FlowySDKTest, app_id: &str, name: &str, key: &str) -> ViewPB {
+    let request = CreateViewPayloadPB {
+        belong_to_id: app_id.to_string(),
+        name: name.to_string(),
+        desc: "".to_string(),
+        thumbnail: None,
+        data_format: ViewDataFormatPB::DeltaFormat,
+        layout: ViewLayoutTypePB::Document,
+        view_content_data: vec![],
+        is_scratch: false,
+        idempotency_key: Some(key.to_string()),
     };
     FolderEventBuilder::new(sdk.clone())
         .event(CreateView)
@@ -373,6 +408,39 @@ pub async fn create_view(
         .parse::<ViewPB>()
 }
 
+/// Creates a scratch document view, which is kept on this device only.
+pub async fn create_scratch_view(sdk: &FlowySDKTest, app_id: &str, name: &str) -> ViewPB {
+    let request = CreateViewPayloadPB {
+        belong_to_id: app_id.to_string(),
+        name: name.to_string(),
+        desc: "".to_string(),
+        thumbnail: None,
+        data_format: ViewDataFormatPB::DeltaFormat,
+        layout: ViewLayoutTypePB::Document,
+        view_content_data: vec![],
+        is_scratch: true,
+        idempotency_key: None,
+    };
+    FolderEventBuilder::new(sdk.clone())
+        .event(CreateView)
+        .payload(request)
+        .async_send()
+        .await
+        .parse::<ViewPB>()
+}
+
+pub async fn read_duplicated_views(sdk: &FlowySDKTest, app_id: &str) -> RepeatedViewPB {
+    let request = AppIdPB {
+        value: app_id.to_string(),
+    };
+    FolderEventBuilder::new(sdk.clone())
+        .event(ReadDuplicatedViews)
+        .payload(request)
+        .async_send()
+        .await
+        .parse::<RepeatedViewPB>()
+}
+
 pub async fn read_view(sdk: &FlowySDKTest, view_id: &str) -> ViewPB {
     let view_id: ViewIdPB = view_id.into();
     FolderEventBuilder::new(sdk.clone())
@@ -208,6 +208,7 @@ struct GridBlockRevisionTable {
 pub enum GridBlockRevisionState {
     Sync = 0,
     Ack = 1,
+    Resolved = 2,
 }
 impl_sql_integer_expression!(GridBlockRevisionState);
 impl_rev_state_map!(GridBlockRevisionState);
@@ -205,6 +205,7 @@ struct GridRevisionTable {
 pub enum GridRevisionState {
     Sync = 0,
     Ack = 1,
+    Resolved = 2,
 }
 impl_sql_integer_expression!(GridRevisionState);
 impl_rev_state_map!(GridRevisionState);
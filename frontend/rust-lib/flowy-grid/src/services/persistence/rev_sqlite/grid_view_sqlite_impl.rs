@@ -207,6 +207,7 @@ struct GridViewRevisionTable {
 pub enum GridViewRevisionState {
     Sync = 0,
     Ack = 1,
+    Resolved = 2,
 }
 impl_sql_integer_expression!(GridViewRevisionState);
 impl_rev_state_map!(GridViewRevisionState);
@@ -73,6 +73,10 @@ impl ClientServerConfiguration {
         format!("{}/api/doc", self.base_url())
     }
 
+    pub fn attachment_url(&self) -> String {
+        format!("{}/api/doc/attachment", self.base_url())
+    }
+
     pub fn trash_url(&self) -> String {
         format!("{}/api/trash", self.base_url())
     }
@@ -0,0 +1,26 @@
+use dart_notify::DartNotifyBuilder;
+use flowy_derive::ProtoBuf_Enum;
+const OBSERVABLE_CATEGORY: &str = "Network";
+
+#[derive(ProtoBuf_Enum, Debug)]
+pub(crate) enum NetworkNotification {
+    Unknown = 0,
+    DidExceedSyncUsageCap = 1,
+}
+
+impl std::default::Default for NetworkNotification {
+    fn default() -> Self {
+        NetworkNotification::Unknown
+    }
+}
+
+impl std::convert::From<NetworkNotification> for i32 {
+    fn from(notification: NetworkNotification) -> Self {
+        notification as i32
+    }
+}
+
+#[tracing::instrument(level = "trace")]
+pub(crate) fn send_anonymous_dart_notification(ty: NetworkNotification) -> DartNotifyBuilder {
+    DartNotifyBuilder::new("", ty, OBSERVABLE_CATEGORY)
+}
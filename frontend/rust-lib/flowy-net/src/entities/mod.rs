@@ -1,2 +1,6 @@
 mod network_state;
+mod power_state;
+mod sync_usage;
 pub use network_state::*;
+pub use power_state::*;
+pub use sync_usage::*;
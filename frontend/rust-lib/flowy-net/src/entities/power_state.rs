@@ -0,0 +1,21 @@
+use flowy_derive::{ProtoBuf, ProtoBuf_Enum};
+
+/// The power source of the device, the app updates it when it changes.
+#[derive(ProtoBuf_Enum, Debug, Clone, Eq, PartialEq)]
+pub enum PowerType {
+    UnknownPowerType = 0,
+    OnBattery = 1,
+    PluggedIn = 2,
+}
+
+impl std::default::Default for PowerType {
+    fn default() -> Self {
+        PowerType::UnknownPowerType
+    }
+}
+
+#[derive(ProtoBuf, Debug, Default, Clone)]
+pub struct PowerStatePB {
+    #[pb(index = 1)]
+    pub ty: PowerType,
+}
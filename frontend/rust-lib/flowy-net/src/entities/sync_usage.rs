@@ -0,0 +1,107 @@
+use crate::sync_usage::{SyncUsageDay, SyncUsageSetting};
+use flowy_derive::ProtoBuf;
+
+#[derive(ProtoBuf, Default, Debug, Clone)]
+pub struct QuerySyncUsagePB {
+    /// The number of the days up to today.
+    #[pb(index = 1)]
+    pub days: i64,
+}
+
+#[derive(ProtoBuf, Default, Debug, Clone)]
+pub struct SyncUsagePB {
+    /// The day in the format of `YYYY-MM-DD`
+    #[pb(index = 1)]
+    pub day: String,
+
+    #[pb(index = 2)]
+    pub revision_sent: i64,
+
+    #[pb(index = 3)]
+    pub revision_received: i64,
+
+    #[pb(index = 4)]
+    pub presence_sent: i64,
+
+    #[pb(index = 5)]
+    pub presence_received: i64,
+
+    #[pb(index = 6)]
+    pub control_sent: i64,
+
+    #[pb(index = 7)]
+    pub control_received: i64,
+
+    #[pb(index = 8)]
+    pub total: i64,
+}
+
+impl std::convert::From<SyncUsageDay> for SyncUsagePB {
+    fn from(day: SyncUsageDay) -> Self {
+        SyncUsagePB {
+            day: day.day,
+            total: day.usage.total(),
+            revision_sent: day.usage.revision_sent,
+            revision_received: day.usage.revision_received,
+            presence_sent: day.usage.presence_sent,
+            presence_received: day.usage.presence_received,
+            control_sent: day.usage.control_sent,
+            control_received: day.usage.control_received,
+        }
+    }
+}
+
+#[derive(ProtoBuf, Default, Debug, Clone)]
+pub struct RepeatedSyncUsagePB {
+    #[pb(index = 1)]
+    pub items: Vec<SyncUsagePB>,
+}
+
+#[derive(ProtoBuf, Default, Debug, Clone)]
+pub struct SyncUsageSettingPB {
+    /// The soft cap of the bytes a month. There's no cap if it's 0.
+    #[pb(index = 1)]
+    pub monthly_cap: i64,
+
+    #[pb(index = 2)]
+    pub auto_pause: bool,
+}
+
+impl std::convert::From<SyncUsageSetting> for SyncUsageSettingPB {
+    fn from(setting: SyncUsageSetting) -> Self {
+        SyncUsageSettingPB {
+            monthly_cap: setting.monthly_cap,
+            auto_pause: setting.auto_pause,
+        }
+    }
+}
+
+impl std::convert::From<SyncUsageSettingPB> for SyncUsageSetting {
+    fn from(pb: SyncUsageSettingPB) -> Self {
+        SyncUsageSetting {
+            monthly_cap: pb.monthly_cap,
+            auto_pause: pb.auto_pause,
+        }
+    }
+}
+
+#[derive(ProtoBuf, Default, Debug, Clone)]
+pub struct BucketTimezonePB {
+    /// The IANA name of the timezone the daily usage of the workspace is bucketed by, e.g.
+    /// `America/New_York`.
+    #[pb(index = 1)]
+    pub timezone: String,
+}
+
+#[derive(ProtoBuf, Default, Debug, Clone)]
+pub struct SyncUsageCapExceededPB {
+    /// The bytes used in this month
+    #[pb(index = 1)]
+    pub used: i64,
+
+    #[pb(index = 2)]
+    pub monthly_cap: i64,
+
+    #[pb(index = 3)]
+    pub sync_paused: bool,
+}
@@ -1,14 +1,23 @@
+use crate::sync_usage::SyncUsageManager;
 use crate::{handlers::*, ws::connection::FlowyWebSocketConnect};
 use flowy_derive::{Flowy_Event, ProtoBuf_Enum};
 use lib_dispatch::prelude::*;
 use std::sync::Arc;
 use strum_macros::Display;
 
-pub fn init(ws_conn: Arc<FlowyWebSocketConnect>) -> AFPlugin {
+pub fn init(ws_conn: Arc<FlowyWebSocketConnect>, sync_usage: Arc<SyncUsageManager>) -> AFPlugin {
     AFPlugin::new()
         .name("Flowy-Network")
         .state(ws_conn)
+        .state(sync_usage)
         .event(NetworkEvent::UpdateNetworkType, update_network_ty)
+        .event(NetworkEvent::QuerySyncUsage, query_sync_usage_handler)
+        .event(NetworkEvent::GetSyncUsageSetting, get_sync_usage_setting_handler)
+        .event(NetworkEvent::UpdateSyncUsageSetting, update_sync_usage_setting_handler)
+        .event(NetworkEvent::ResumeSync, resume_sync_handler)
+        .event(NetworkEvent::GetBucketTimezone, get_bucket_timezone_handler)
+        .event(NetworkEvent::UpdateBucketTimezone, update_bucket_timezone_handler)
+        .event(NetworkEvent::UpdatePowerType, update_power_ty)
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Hash, ProtoBuf_Enum, Flowy_Event)]
@@ -16,4 +25,30 @@ pub fn init(ws_conn: Arc<FlowyWebSocketConnect>) -> AFPlugin {
 pub enum NetworkEvent {
     #[event(input = "NetworkState")]
     UpdateNetworkType = 0,
+
+    /// Returns the daily sync usage of the current workspace for the settings chart
+    #[event(input = "QuerySyncUsagePB", output = "RepeatedSyncUsagePB")]
+    QuerySyncUsage = 1,
+
+    #[event(output = "SyncUsageSettingPB")]
+    GetSyncUsageSetting = 2,
+
+    #[event(input = "SyncUsageSettingPB")]
+    UpdateSyncUsageSetting = 3,
+
+    /// Resumes the sync paused by exceeding the monthly cap of the sync usage
+    #[event()]
+    ResumeSync = 4,
+
+    /// Returns the timezone of the current workspace that the daily sync usage is bucketed by
+    #[event(output = "BucketTimezonePB")]
+    GetBucketTimezone = 5,
+
+    /// Sets the timezone of the current workspace, the saved daily usage is moved to its days
+    #[event(input = "BucketTimezonePB")]
+    UpdateBucketTimezone = 6,
+
+    /// Tells whether the device is on battery, the local changes are written less often then
+    #[event(input = "PowerStatePB")]
+    UpdatePowerType = 7,
 }
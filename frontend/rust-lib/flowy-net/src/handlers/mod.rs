@@ -1,6 +1,8 @@
+use crate::entities::{BucketTimezonePB, PowerStatePB, QuerySyncUsagePB, RepeatedSyncUsagePB, SyncUsageSettingPB};
+use crate::sync_usage::SyncUsageManager;
 use crate::{entities::NetworkState, ws::connection::FlowyWebSocketConnect};
 use flowy_error::FlowyError;
-use lib_dispatch::prelude::{AFPluginData, AFPluginState};
+use lib_dispatch::prelude::{data_result, AFPluginData, AFPluginState, DataResult};
 use std::sync::Arc;
 
 #[tracing::instrument(level = "debug", skip(data, ws_manager))]
@@ -12,3 +14,63 @@ pub async fn update_network_ty(
     ws_manager.update_network_type(&network_state.ty);
     Ok(())
 }
+
+#[tracing::instrument(level = "debug", skip(data, ws_manager))]
+pub async fn update_power_ty(
+    data: AFPluginData<PowerStatePB>,
+    ws_manager: AFPluginState<Arc<FlowyWebSocketConnect>>,
+) -> Result<(), FlowyError> {
+    ws_manager.update_power_type(data.into_inner().ty);
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug", skip(data, manager), err)]
+pub async fn query_sync_usage_handler(
+    data: AFPluginData<QuerySyncUsagePB>,
+    manager: AFPluginState<Arc<SyncUsageManager>>,
+) -> DataResult<RepeatedSyncUsagePB, FlowyError> {
+    let days = data.into_inner().days;
+    if days <= 0 {
+        return Err(FlowyError::invalid_data().context("The days should be positive"));
+    }
+    let items = manager
+        .query(days as usize)?
+        .into_iter()
+        .map(|day| day.into())
+        .collect();
+    data_result(RepeatedSyncUsagePB { items })
+}
+
+pub async fn get_sync_usage_setting_handler(
+    manager: AFPluginState<Arc<SyncUsageManager>>,
+) -> DataResult<SyncUsageSettingPB, FlowyError> {
+    data_result(manager.setting().into())
+}
+
+#[tracing::instrument(level = "debug", skip(data, manager), err)]
+pub async fn update_sync_usage_setting_handler(
+    data: AFPluginData<SyncUsageSettingPB>,
+    manager: AFPluginState<Arc<SyncUsageManager>>,
+) -> Result<(), FlowyError> {
+    manager.update_setting(data.into_inner().into())
+}
+
+pub async fn get_bucket_timezone_handler(
+    manager: AFPluginState<Arc<SyncUsageManager>>,
+) -> DataResult<BucketTimezonePB, FlowyError> {
+    let timezone = manager.bucket_clock()?.timezone().to_owned();
+    data_result(BucketTimezonePB { timezone })
+}
+
+#[tracing::instrument(level = "debug", skip(data, manager), err)]
+pub async fn update_bucket_timezone_handler(
+    data: AFPluginData<BucketTimezonePB>,
+    manager: AFPluginState<Arc<SyncUsageManager>>,
+) -> Result<(), FlowyError> {
+    manager.update_timezone(&data.into_inner().timezone)
+}
+
+pub async fn resume_sync_handler(manager: AFPluginState<Arc<SyncUsageManager>>) -> Result<(), FlowyError> {
+    manager.resume_sync();
+    Ok(())
+}
@@ -2,12 +2,13 @@ use crate::{
     configuration::*,
     request::{HttpRequestBuilder, ResponseMiddleware},
 };
-use flowy_document::DocumentCloudService;
+use flowy_document::{Attachment, AttachmentVersion, DocumentCloudService};
 use flowy_error::FlowyError;
 use flowy_http_model::document::{CreateDocumentParams, DocumentId, DocumentPayload, ResetDocumentParams};
 use http_flowy::response::FlowyResponse;
 use lazy_static::lazy_static;
 use lib_infra::future::FutureResult;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 pub struct DocumentCloudServiceImpl {
@@ -38,6 +39,34 @@ impl DocumentCloudService for DocumentCloudServiceImpl {
         let url = self.config.doc_url();
         FutureResult::new(async move { reset_doc_request(&token, params, &url).await })
     }
+
+    fn fetch_document_rev_id(&self, token: &str, params: DocumentId) -> FutureResult<Option<i64>, FlowyError> {
+        let token = token.to_owned();
+        let url = format!("{}/rev_id", self.config.doc_url());
+        FutureResult::new(async move { read_document_rev_id_request(&token, params, &url).await })
+    }
+
+    fn fetch_attachment(&self, token: &str, attachment_id: &str) -> FutureResult<Option<Attachment>, FlowyError> {
+        let token = token.to_owned();
+        let url = format!("{}/{}", self.config.attachment_url(), attachment_id);
+        FutureResult::new(async move { read_attachment_request(&token, &url).await })
+    }
+
+    fn fetch_attachment_versions(
+        &self,
+        token: &str,
+        attachment_ids: Vec<String>,
+    ) -> FutureResult<Option<HashMap<String, AttachmentVersion>>, FlowyError> {
+        let token = token.to_owned();
+        let url = format!("{}/versions", self.config.attachment_url());
+        FutureResult::new(async move { read_attachment_versions_request(&token, attachment_ids, &url).await })
+    }
+
+    fn upload_attachment(&self, token: &str, attachment: Attachment) -> FutureResult<(), FlowyError> {
+        let token = token.to_owned();
+        let url = self.config.attachment_url();
+        FutureResult::new(async move { upload_attachment_request(&token, attachment, &url).await })
+    }
 }
 
 pub async fn create_document_request(token: &str, params: CreateDocumentParams, url: &str) -> Result<(), FlowyError> {
@@ -65,6 +94,21 @@ pub async fn read_document_request(
     Ok(doc)
 }
 
+pub async fn read_document_rev_id_request(
+    token: &str,
+    params: DocumentId,
+    url: &str,
+) -> Result<Option<i64>, FlowyError> {
+    let rev_id = request_builder()
+        .get(url)
+        .header(HEADER_TOKEN, token)
+        .json(params)?
+        .option_json_response()
+        .await?;
+
+    Ok(rev_id)
+}
+
 pub async fn reset_doc_request(token: &str, params: ResetDocumentParams, url: &str) -> Result<(), FlowyError> {
     request_builder()
         .patch(url)
@@ -75,6 +119,41 @@ pub async fn reset_doc_request(token: &str, params: ResetDocumentParams, url: &s
     Ok(())
 }
 
+pub async fn read_attachment_request(token: &str, url: &str) -> Result<Option<Attachment>, FlowyError> {
+    let attachment = request_builder()
+        .get(url)
+        .header(HEADER_TOKEN, token)
+        .option_json_response()
+        .await?;
+
+    Ok(attachment)
+}
+
+pub async fn read_attachment_versions_request(
+    token: &str,
+    attachment_ids: Vec<String>,
+    url: &str,
+) -> Result<Option<HashMap<String, AttachmentVersion>>, FlowyError> {
+    let versions = request_builder()
+        .post(url)
+        .header(HEADER_TOKEN, token)
+        .json(attachment_ids)?
+        .option_json_response()
+        .await?;
+
+    Ok(versions)
+}
+
+pub async fn upload_attachment_request(token: &str, attachment: Attachment, url: &str) -> Result<(), FlowyError> {
+    request_builder()
+        .post(url)
+        .header(HEADER_TOKEN, token)
+        .json(attachment)?
+        .send()
+        .await?;
+    Ok(())
+}
+
 fn request_builder() -> HttpRequestBuilder {
     HttpRequestBuilder::new().middleware(MIDDLEWARE.clone())
 }
@@ -1,4 +1,5 @@
 mod configuration;
+mod dart_notification;
 pub mod entities;
 pub mod event_map;
 mod handlers;
@@ -6,6 +7,7 @@ pub mod http_server;
 pub mod local_server;
 pub mod protobuf;
 mod request;
+pub mod sync_usage;
 pub mod ws;
 
 pub use crate::configuration::{get_client_server_configuration, ClientServerConfiguration};
@@ -109,6 +109,7 @@ impl LocalWebSocketRunner {
     async fn handle_message(&self, message: WebSocketRawMessage) -> Result<(), FlowyError> {
         let bytes = Bytes::from(message.data);
         let client_data = ClientRevisionWSData::try_from(bytes).map_err(internal_error)?;
+        self.reply_handshake(&message.channel, &client_data)?;
         match message.channel {
             WSChannel::Document => {
                 self.handle_document_client_data(client_data, "".to_owned()).await?;
@@ -124,6 +125,23 @@ impl LocalWebSocketRunner {
         }
     }
 
+    /// Answers the handshake that comes with the first ping of the client, the local server
+    /// supports all the capabilities.
+    fn reply_handshake(&self, channel: &WSChannel, client_data: &ClientRevisionWSData) -> Result<(), FlowyError> {
+        if client_data.handshake.is_none() {
+            return Ok(());
+        }
+        let handshake = WSHandshake::new(WSCapabilities::ALL);
+        let data = ServerRevisionWSDataBuilder::build_handshake_message(&client_data.object_id, handshake);
+        let bytes: Bytes = data.try_into().map_err(internal_error)?;
+        let msg = WebSocketRawMessage {
+            channel: channel.clone(),
+            data: bytes.to_vec(),
+        };
+        self.client_ws_sender.send(msg).map_err(internal_error)?;
+        Ok(())
+    }
+
     pub async fn handle_folder_client_data(
         &self,
         client_data: ClientRevisionWSData,
@@ -149,6 +167,9 @@ impl LocalWebSocketRunner {
             ClientRevisionWSDataType::ClientPing => {
                 self.folder_manager.handle_client_ping(user, client_data).await?;
             }
+            ClientRevisionWSDataType::ClientUnsupported => {
+                tracing::warn!("[LocalFolderServer] client dropped {:?}", client_data.unsupported);
+            }
         }
         Ok(())
     }
@@ -178,6 +199,9 @@ impl LocalWebSocketRunner {
             ClientRevisionWSDataType::ClientPing => {
                 self.doc_manager.handle_client_ping(user, client_data).await?;
             }
+            ClientRevisionWSDataType::ClientUnsupported => {
+                tracing::warn!("[LocalDocumentServer] client dropped {:?}", client_data.unsupported);
+            }
         }
         Ok(())
     }
@@ -244,7 +268,9 @@ use flowy_folder::entities::{
     workspace::{CreateWorkspaceParams, UpdateWorkspaceParams, WorkspaceIdPB},
 };
 use flowy_http_model::document::{CreateDocumentParams, DocumentId, DocumentPayload, ResetDocumentParams};
-use flowy_http_model::ws_data::{ClientRevisionWSData, ClientRevisionWSDataType};
+use flowy_http_model::ws_data::{
+    ClientRevisionWSData, ClientRevisionWSDataType, ServerRevisionWSDataBuilder, WSCapabilities, WSHandshake,
+};
 use flowy_user::entities::{
     SignInParams, SignInResponse, SignUpParams, SignUpResponse, UpdateUserProfileParams, UserProfilePB,
 };
@@ -300,6 +326,7 @@ impl FolderCouldServiceV1 for LocalServer {
             ext_data: "".to_string(),
             thumbnail: params.thumbnail,
             layout: params.layout.into(),
+            is_scratch: false,
         };
         FutureResult::new(async { Ok(view) })
     }
@@ -407,6 +434,10 @@ impl DocumentCloudService for LocalServer {
         FutureResult::new(async { Ok(None) })
     }
 
+    fn fetch_document_rev_id(&self, _token: &str, _params: DocumentId) -> FutureResult<Option<i64>, FlowyError> {
+        FutureResult::new(async { Ok(None) })
+    }
+
     fn update_document_content(&self, _token: &str, _params: ResetDocumentParams) -> FutureResult<(), FlowyError> {
         FutureResult::new(async { Ok(()) })
     }
@@ -0,0 +1,794 @@
+use crate::dart_notification::{send_anonymous_dart_notification, NetworkNotification};
+use crate::entities::SyncUsageCapExceededPB;
+use crate::sync_usage::persistence::SyncUsageSql;
+use crate::ws::connection::FlowyWebSocketConnect;
+use crate::ws::usage::SyncUsage;
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDate};
+use flowy_database::{kv::KV, ConnectionPool};
+use flowy_error::{FlowyError, FlowyResult};
+use lib_infra::bucket_clock::BucketClock;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+const SYNC_USAGE_SETTING: &str = "sync_usage_setting";
+const BUCKET_TIMEZONE_SETTING: &str = "bucket_timezone";
+
+/// The longest series `SyncUsageManager::query` returns.
+pub const MAX_SYNC_USAGE_DAYS: usize = 366;
+
+pub trait SyncUsageUser: Send + Sync {
+    /// The usage is saved for the current workspace.
+    fn workspace_id(&self) -> FlowyResult<String>;
+    fn db_pool(&self) -> FlowyResult<Arc<ConnectionPool>>;
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SyncUsageSetting {
+    /// The soft cap of the bytes a month. There's no cap if it's 0.
+    pub monthly_cap: i64,
+    /// Pauses the sync once the monthly cap is exceeded.
+    pub auto_pause: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SyncUsageDay {
+    /// The day in the format of `YYYY-MM-DD`
+    pub day: String,
+    pub usage: SyncUsage,
+}
+
+/// Saves the sync usage counted by the websocket as the daily usage of the current workspace,
+/// and checks it against the monthly cap of the `SyncUsageSetting`. Exceeding the cap sends the
+/// `DidExceedSyncUsageCap` notification once a month, and pauses the sync if `auto_pause` is on.
+/// The sync stays paused until it's resumed.
+pub struct SyncUsageManager {
+    ws_conn: Arc<FlowyWebSocketConnect>,
+    user: Arc<dyn SyncUsageUser>,
+    setting: RwLock<SyncUsageSetting>,
+    /// The month, e.g. `2023-01`, whose cap is exceeded. The cap is checked again after the
+    /// setting is updated.
+    exceeded_month: RwLock<Option<String>>,
+    /// The clocks of the workspaces by their id, read from the KV once.
+    bucket_clocks: RwLock<HashMap<String, BucketClock>>,
+}
+
+impl SyncUsageManager {
+    pub fn new(ws_conn: Arc<FlowyWebSocketConnect>, user: Arc<dyn SyncUsageUser>) -> Self {
+        let setting = KV::get_str(SYNC_USAGE_SETTING)
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            ws_conn,
+            user,
+            setting: RwLock::new(setting),
+            exceeded_month: RwLock::new(None),
+            bucket_clocks: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Flushes the usage every `interval` until the manager is dropped.
+    pub fn run(self: &Arc<Self>, interval: Duration) {
+        let weak_manager = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match weak_manager.upgrade() {
+                    None => break,
+                    Some(manager) => {
+                        if let Err(e) = manager.flush() {
+                            tracing::error!("Save the sync usage failed: {:?}", e);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Adds the usage counted since the last flush to today's usage.
+    pub fn flush(&self) -> FlowyResult<()> {
+        self.flush_at(self.bucket_clock()?.today())
+    }
+
+    /// Returns the daily usage of the last `days` days, from the oldest day to today. The days
+    /// without any usage are included with zero usage.
+    pub fn query(&self, days: usize) -> FlowyResult<Vec<SyncUsageDay>> {
+        self.flush()?;
+        self.query_at(self.bucket_clock()?.today(), days)
+    }
+
+    pub fn setting(&self) -> SyncUsageSetting {
+        self.setting.read().clone()
+    }
+
+    pub fn update_setting(&self, setting: SyncUsageSetting) -> FlowyResult<()> {
+        if setting.monthly_cap < 0 {
+            return Err(FlowyError::invalid_data().context("The monthly cap should not be negative"));
+        }
+        KV::set_str(SYNC_USAGE_SETTING, serde_json::to_string(&setting)?);
+        *self.setting.write() = setting;
+        *self.exceeded_month.write() = None;
+        Ok(())
+    }
+
+    /// The clock that buckets the usage of the current workspace into the days of its timezone.
+    /// It's UTC until the timezone is set with `update_timezone`.
+    pub fn bucket_clock(&self) -> FlowyResult<BucketClock> {
+        let workspace_id = self.user.workspace_id()?;
+        if let Some(clock) = self.bucket_clocks.read().get(&workspace_id) {
+            return Ok(*clock);
+        }
+        let clock = KV::get_str(&bucket_timezone_key(&workspace_id))
+            .and_then(|timezone| BucketClock::parse(&timezone))
+            .unwrap_or_default();
+        self.bucket_clocks.write().insert(workspace_id, clock);
+        Ok(clock)
+    }
+
+    /// Sets the timezone of the current workspace, e.g. `America/New_York`. The saved daily
+    /// usage is moved to the days of the new timezone, see `rebucket_usage`. The usage is spread
+    /// over the new days evenly, so changing the timezone back doesn't restore the old days.
+    pub fn update_timezone(&self, timezone: &str) -> FlowyResult<()> {
+        let clock = BucketClock::parse(timezone)
+            .ok_or_else(|| FlowyError::invalid_data().context(format!("Unknown timezone: {}", timezone)))?;
+        // The usage counted so far belongs to the days of the old timezone.
+        self.flush()?;
+        let old_clock = self.bucket_clock()?;
+        if old_clock == clock {
+            return Ok(());
+        }
+
+        let workspace_id = self.user.workspace_id()?;
+        let conn = self.user.db_pool()?.get()?;
+        conn.immediate_transaction::<_, FlowyError, _>(|| {
+            let days = SyncUsageSql::read_since(&workspace_id, "", &conn)?;
+            SyncUsageSql::replace_all(&workspace_id, &rebucket_usage(days, &old_clock, &clock), &conn)
+        })?;
+        KV::set_str(&bucket_timezone_key(&workspace_id), clock.timezone().to_owned());
+        // The month whose cap was exceeded is kept, so the sync resumed in this month isn't
+        // paused again because its usage moved to other days.
+        self.bucket_clocks.write().insert(workspace_id, clock);
+        Ok(())
+    }
+
+    /// Resumes the sync paused by exceeding the cap. It's not paused again in this month.
+    pub fn resume_sync(&self) {
+        self.ws_conn.resume_sync();
+    }
+
+    fn flush_at(&self, today: NaiveDate) -> FlowyResult<()> {
+        let counter = self.ws_conn.sync_usage_counter();
+        let usage = counter.take();
+        if usage.is_empty() {
+            return Ok(());
+        }
+        let saved = self.user.workspace_id().and_then(|workspace_id| {
+            let conn = self.user.db_pool()?.get()?;
+            SyncUsageSql::add(&workspace_id, &day_key(today), &usage, &conn)?;
+            Ok(workspace_id)
+        });
+        match saved {
+            Ok(workspace_id) => self.check_monthly_cap(&workspace_id, today),
+            Err(e) => {
+                counter.restore(&usage);
+                Err(e)
+            }
+        }
+    }
+
+    fn query_at(&self, today: NaiveDate, days: usize) -> FlowyResult<Vec<SyncUsageDay>> {
+        if days == 0 || days > MAX_SYNC_USAGE_DAYS {
+            return Err(FlowyError::invalid_data().context(format!(
+                "The days should be in the range of 1..={}",
+                MAX_SYNC_USAGE_DAYS
+            )));
+        }
+        let workspace_id = self.user.workspace_id()?;
+        let conn = self.user.db_pool()?.get()?;
+        let first_day = today - ChronoDuration::days(days as i64 - 1);
+        let mut rows = SyncUsageSql::read_since(&workspace_id, &day_key(first_day), &conn)?
+            .into_iter()
+            .peekable();
+        let series = (0..days)
+            .map(|offset| {
+                let day = day_key(first_day + ChronoDuration::days(offset as i64));
+                let usage = match rows.next_if(|(row_day, _)| row_day == &day) {
+                    None => SyncUsage::default(),
+                    Some((_, usage)) => usage,
+                };
+                SyncUsageDay { day, usage }
+            })
+            .collect();
+        Ok(series)
+    }
+
+    fn check_monthly_cap(&self, workspace_id: &str, today: NaiveDate) -> FlowyResult<()> {
+        let setting = self.setting();
+        let month = format!("{:04}-{:02}", today.year(), today.month());
+        if setting.monthly_cap == 0 || self.exceeded_month.read().as_ref() == Some(&month) {
+            return Ok(());
+        }
+
+        let conn = self.user.db_pool()?.get()?;
+        let first_day = NaiveDate::from_ymd(today.year(), today.month(), 1);
+        let mut used = SyncUsage::default();
+        for (_, usage) in SyncUsageSql::read_since(workspace_id, &day_key(first_day), &conn)? {
+            used.add(&usage);
+        }
+        if used.total() <= setting.monthly_cap {
+            return Ok(());
+        }
+
+        tracing::warn!(
+            "The sync usage {} exceeds the monthly cap {}",
+            used.total(),
+            setting.monthly_cap
+        );
+        *self.exceeded_month.write() = Some(month);
+        if setting.auto_pause {
+            self.ws_conn.pause_sync();
+        }
+        send_anonymous_dart_notification(NetworkNotification::DidExceedSyncUsageCap)
+            .payload(SyncUsageCapExceededPB {
+                used: used.total(),
+                monthly_cap: setting.monthly_cap,
+                sync_paused: setting.auto_pause,
+            })
+            .send();
+        Ok(())
+    }
+}
+
+fn bucket_timezone_key(workspace_id: &str) -> String {
+    format!("{}:{}", BUCKET_TIMEZONE_SETTING, workspace_id)
+}
+
+/// Moves the daily usage bucketed by the `from` clock to the days of the `to` clock. The usage
+/// of a day is only known for the whole day, so it's spread over the new days by the time they
+/// overlap with it. Each count is split into whole bytes that add up to it, nothing is dropped
+/// or counted twice.
+fn rebucket_usage(days: Vec<(String, SyncUsage)>, from: &BucketClock, to: &BucketClock) -> Vec<(String, SyncUsage)> {
+    let mut rebucketed = BTreeMap::<String, SyncUsage>::new();
+    for (day, usage) in days {
+        let date = match NaiveDate::parse_from_str(&day, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                rebucketed.entry(day).or_default().add(&usage);
+                continue;
+            }
+        };
+        let parts = to.rebucket(from, date);
+        let weights = parts
+            .iter()
+            .map(|(_, duration)| duration.num_seconds())
+            .collect::<Vec<i64>>();
+        let revision_sent = split_count(usage.revision_sent, &weights);
+        let revision_received = split_count(usage.revision_received, &weights);
+        let presence_sent = split_count(usage.presence_sent, &weights);
+        let presence_received = split_count(usage.presence_received, &weights);
+        let control_sent = split_count(usage.control_sent, &weights);
+        let control_received = split_count(usage.control_received, &weights);
+        for (index, (new_date, _)) in parts.iter().enumerate() {
+            let part = SyncUsage {
+                revision_sent: revision_sent[index],
+                revision_received: revision_received[index],
+                presence_sent: presence_sent[index],
+                presence_received: presence_received[index],
+                control_sent: control_sent[index],
+                control_received: control_received[index],
+            };
+            if !part.is_empty() {
+                rebucketed.entry(day_key(*new_date)).or_default().add(&part);
+            }
+        }
+    }
+    rebucketed.into_iter().collect()
+}
+
+/// Splits the `count` in proportion to the `weights`. The rounding is given to the parts with
+/// the largest remainders, so the parts add up to the `count`.
+fn split_count(count: i64, weights: &[i64]) -> Vec<i64> {
+    let sum = weights.iter().sum::<i64>() as i128;
+    if sum == 0 {
+        return weights.iter().map(|_| 0).collect();
+    }
+    let mut parts = weights
+        .iter()
+        .map(|weight| (count as i128 * *weight as i128 / sum) as i64)
+        .collect::<Vec<i64>>();
+    let mut remainders = weights
+        .iter()
+        .enumerate()
+        .map(|(index, weight)| (count as i128 * *weight as i128 % sum, index))
+        .collect::<Vec<(i128, usize)>>();
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    let left = count - parts.iter().sum::<i64>();
+    for (_, index) in remainders.into_iter().take(left.max(0) as usize) {
+        parts[index] += 1;
+    }
+    parts
+}
+
+fn day_key(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ws::connection::{FlowyRawWebSocket, FlowyWebSocket};
+    use crate::ws::usage::classify_message;
+    use crate::ws::usage::SyncTrafficDirection::{Received, Sent};
+    use crate::ws::usage::SyncTrafficKind;
+    use bytes::Bytes;
+    use chrono::{DateTime, TimeZone, Utc};
+    use flowy_database::test_util::TempDatabase;
+    use flowy_error::ErrorCode;
+    use flowy_http_model::revision::Revision;
+    use flowy_http_model::ws_data::{ClientRevisionWSData, NewDocumentUser, ServerRevisionWSData, WSRevisionPayload};
+    use futures_util::future::BoxFuture;
+    use lib_infra::future::FutureResult;
+    use lib_ws::{WSChannel, WSConnectState, WSMessageReceiver, WebSocketRawMessage};
+    use std::convert::TryInto;
+    use tokio::sync::broadcast;
+
+    #[tokio::test]
+    async fn sync_usage_split_by_kind_test() {
+        let test = SyncUsageTest::new();
+        let push = client_message(ClientRevisionWSData::from_revisions("doc", vec![revision(1, "abc")]));
+        let ping = client_message(ClientRevisionWSData::ping("doc", 1));
+        test.send(push.clone()).await;
+        test.send(push.clone()).await;
+        test.send(ping.clone()).await;
+
+        let server_push = server_message(WSRevisionPayload::ServerPushRev {
+            revisions: vec![revision(2, "def")],
+        });
+        let ack = server_message(WSRevisionPayload::ServerAck { rev_id: 1 });
+        let user_connect = server_message(WSRevisionPayload::UserConnect {
+            user: NewDocumentUser::default(),
+        });
+        test.transport.receive(server_push.clone());
+        test.transport.receive(ack.clone());
+        test.transport.receive(user_connect.clone());
+
+        test.manager.flush_at(day(2023, 1, 10)).unwrap();
+        let series = test.manager.query_at(day(2023, 1, 10), 1).unwrap();
+        assert_eq!(
+            series,
+            vec![SyncUsageDay {
+                day: "2023-01-10".to_owned(),
+                usage: SyncUsage {
+                    revision_sent: 2 * sent_len(&push),
+                    revision_received: received_len(&server_push),
+                    presence_sent: 0,
+                    presence_received: received_len(&user_connect),
+                    control_sent: sent_len(&ping),
+                    control_received: received_len(&ack),
+                },
+            }]
+        );
+        // The messages are delivered to the receivers and the transport.
+        assert_eq!(test.transport.num_of_sent(), 3);
+        assert_eq!(test.receiver.num_of_received(), 3);
+    }
+
+    #[tokio::test]
+    async fn sync_usage_wire_bytes_test() {
+        let test = SyncUsageTest::new();
+        // `{"channel":1,"data":[7,42,255]}` in a masked frame with the 2 bytes header.
+        let tiny = WebSocketRawMessage {
+            channel: WSChannel::Folder,
+            data: vec![7, 42, 255],
+        };
+        test.send(tiny).await;
+        test.manager.flush_at(day(2023, 1, 10)).unwrap();
+        let usage = &test.manager.query_at(day(2023, 1, 10), 1).unwrap()[0].usage;
+        assert_eq!(usage.total(), 31 + 2 + 4);
+
+        // The frames of the larger payloads carry their length in 2 or 8 more bytes. The frames
+        // received from the server aren't masked.
+        let medium = client_message(ClientRevisionWSData::from_revisions(
+            "doc",
+            vec![revision(1, &"a".repeat(1000))],
+        ));
+        let large = client_message(ClientRevisionWSData::from_revisions(
+            "doc",
+            vec![revision(2, &"b".repeat(100_000))],
+        ));
+        test.send(medium).await;
+        test.send(large.clone()).await;
+        test.transport.receive(large);
+        test.manager.flush_at(day(2023, 1, 11)).unwrap();
+
+        let payload_lens = test.transport.sent_payload_lens();
+        assert_eq!(payload_lens[0], 31);
+        assert!(payload_lens[1] > 125 && payload_lens[1] <= 65535);
+        assert!(payload_lens[2] > 65535);
+        let usage = &test.manager.query_at(day(2023, 1, 11), 1).unwrap()[0].usage;
+        assert_eq!(
+            usage.total() as usize,
+            (payload_lens[1] + 2 + 2 + 4) + (payload_lens[2] + 2 + 8 + 4) + (payload_lens[2] + 2 + 8)
+        );
+    }
+
+    #[test]
+    fn classify_message_test() {
+        let push = client_message(ClientRevisionWSData::from_revisions(
+            r#"doc "1","ty":1"#,
+            vec![revision(1, r#""ty":1"#)],
+        ));
+        let ping = client_message(ClientRevisionWSData::ping(r#"doc "1","ty":0"#, 1));
+        assert_eq!(classify_message(&push, Sent), SyncTrafficKind::Revision);
+        assert_eq!(classify_message(&ping, Sent), SyncTrafficKind::Control);
+
+        let user_connect = server_message(WSRevisionPayload::UserConnect {
+            user: NewDocumentUser::default(),
+        });
+        assert_eq!(classify_message(&user_connect, Received), SyncTrafficKind::Presence);
+
+        // The frames that can't be recognized are counted as control.
+        let garbage = WebSocketRawMessage {
+            channel: WSChannel::Document,
+            data: br#"{"object_id":"doc""#.to_vec(),
+        };
+        assert_eq!(classify_message(&garbage, Sent), SyncTrafficKind::Control);
+        assert_eq!(classify_message(&garbage, Received), SyncTrafficKind::Control);
+    }
+
+    #[tokio::test]
+    async fn sync_usage_daily_series_test() {
+        let test = SyncUsageTest::new();
+        let push = client_message(ClientRevisionWSData::from_revisions("doc", vec![revision(1, "abc")]));
+        test.send(push.clone()).await;
+        test.manager.flush_at(day(2023, 1, 8)).unwrap();
+        test.send(push.clone()).await;
+        test.manager.flush_at(day(2023, 1, 10)).unwrap();
+        test.send(push.clone()).await;
+        test.manager.flush_at(day(2023, 1, 10)).unwrap();
+
+        let series = test
+            .manager
+            .query_at(day(2023, 1, 10), 4)
+            .unwrap()
+            .into_iter()
+            .map(|day| (day.day, day.usage.revision_sent))
+            .collect::<Vec<(String, i64)>>();
+        let len = sent_len(&push);
+        assert_eq!(
+            series,
+            vec![
+                ("2023-01-07".to_owned(), 0),
+                ("2023-01-08".to_owned(), len),
+                ("2023-01-09".to_owned(), 0),
+                ("2023-01-10".to_owned(), 2 * len),
+            ]
+        );
+
+        // The usage of the other workspaces isn't included.
+        *test.user.workspace_id.write() = "workspace_2".to_owned();
+        let series = test.manager.query_at(day(2023, 1, 10), 4).unwrap();
+        assert!(series.iter().all(|day| day.usage.is_empty()));
+        assert!(test.manager.query_at(day(2023, 1, 10), 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn sync_usage_monthly_cap_test() {
+        let test = SyncUsageTest::new();
+        // The timezone is saved by the workspace, the other tests keep theirs in UTC.
+        *test.user.workspace_id.write() = "monthly_cap_workspace".to_owned();
+        let push = client_message(ClientRevisionWSData::from_revisions("doc", vec![revision(1, "abc")]));
+        let len = sent_len(&push);
+        test.manager
+            .update_setting(SyncUsageSetting {
+                monthly_cap: 2 * len,
+                auto_pause: true,
+            })
+            .unwrap();
+
+        // The usage of the last month isn't counted.
+        test.send(push.clone()).await;
+        test.send(push.clone()).await;
+        test.manager.flush_at(day(2022, 12, 31)).unwrap();
+        test.send(push.clone()).await;
+        test.send(push.clone()).await;
+        test.manager.flush_at(day(2023, 1, 1)).unwrap();
+        assert!(!test.ws_conn.is_sync_paused());
+
+        test.send(push.clone()).await;
+        test.manager.flush_at(day(2023, 1, 2)).unwrap();
+        assert!(test.ws_conn.is_sync_paused());
+        let error = test.ws_conn.web_socket().await.err().unwrap();
+        assert_eq!(error.code, ErrorCode::SyncPaused.value());
+
+        // It's not paused again in the same month after it's resumed.
+        test.manager.resume_sync();
+        test.send(push.clone()).await;
+        test.manager.flush_at(day(2023, 1, 3)).unwrap();
+        assert!(!test.ws_conn.is_sync_paused());
+
+        // Nor after the timezone changes.
+        test.manager.update_timezone("America/New_York").unwrap();
+        test.send(push.clone()).await;
+        test.manager.flush_at(day(2023, 1, 4)).unwrap();
+        assert!(!test.ws_conn.is_sync_paused());
+        assert_eq!(*test.manager.exceeded_month.read(), Some("2023-01".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn sync_usage_monthly_cap_without_auto_pause_test() {
+        let test = SyncUsageTest::new();
+        let push = client_message(ClientRevisionWSData::from_revisions("doc", vec![revision(1, "abc")]));
+        test.manager
+            .update_setting(SyncUsageSetting {
+                monthly_cap: 1,
+                auto_pause: false,
+            })
+            .unwrap();
+        test.send(push).await;
+        test.manager.flush_at(day(2023, 1, 2)).unwrap();
+        assert!(!test.ws_conn.is_sync_paused());
+        assert_eq!(*test.manager.exceeded_month.read(), Some("2023-01".to_owned()));
+    }
+
+    #[test]
+    fn bucket_clock_dst_day_test() {
+        let clock = BucketClock::parse("America/New_York").unwrap();
+        assert_eq!(clock.day_len(day(2023, 3, 12)), ChronoDuration::hours(23));
+        assert_eq!(clock.day_len(day(2023, 11, 5)), ChronoDuration::hours(25));
+        assert_eq!(clock.day_len(day(2023, 11, 6)), ChronoDuration::hours(24));
+
+        // Both 01:30 of the day the DST ends, in EDT and then in EST, are in the same day.
+        assert_eq!(clock.day_of(instant(2023, 11, 5, 5, 30)), day(2023, 11, 5));
+        assert_eq!(clock.day_of(instant(2023, 11, 5, 6, 30)), day(2023, 11, 5));
+        assert_eq!(clock.day_of(instant(2023, 11, 6, 4, 59)), day(2023, 11, 5));
+        assert_eq!(clock.day_of(instant(2023, 11, 6, 5, 0)), day(2023, 11, 6));
+        assert_eq!(clock.day_of(instant(2023, 11, 5, 3, 59)), day(2023, 11, 4));
+
+        // The days split an interval without a gap or an overlap.
+        let parts = clock.split(instant(2023, 11, 4, 12, 0), instant(2023, 11, 6, 12, 0));
+        assert_eq!(
+            parts,
+            vec![
+                (day(2023, 11, 4), ChronoDuration::hours(16)),
+                (day(2023, 11, 5), ChronoDuration::hours(25)),
+                (day(2023, 11, 6), ChronoDuration::hours(7)),
+            ]
+        );
+        assert!(BucketClock::parse("Mars/Olympus_Mons").is_none());
+        assert_eq!(BucketClock::default().timezone(), "UTC");
+    }
+
+    #[tokio::test]
+    async fn sync_usage_rebucket_on_timezone_change_test() {
+        let test = SyncUsageTest::new();
+        *test.user.workspace_id.write() = "rebucket_workspace".to_owned();
+        {
+            let conn = test.user.database.get_connection().unwrap();
+            for fixture_day in ["2023-11-04", "2023-11-05"].iter() {
+                let usage = SyncUsage {
+                    revision_sent: 2400,
+                    control_sent: 7,
+                    ..Default::default()
+                };
+                SyncUsageSql::add("rebucket_workspace", fixture_day, &usage, &conn).unwrap();
+            }
+        }
+        assert_eq!(test.manager.bucket_clock().unwrap().timezone(), "UTC");
+
+        // Each UTC day overlaps 4 hours of the day before in New York. The 2023-11-05 of New York
+        // is 25 hours long, the usage of the UTC days only covers 20 hours of it.
+        test.manager.update_timezone("America/New_York").unwrap();
+        assert_eq!(test.manager.bucket_clock().unwrap().timezone(), "America/New_York");
+        let series = test.manager.query_at(day(2023, 11, 6), 4).unwrap();
+        let sent = |f: fn(&SyncUsage) -> i64| series.iter().map(|day| f(&day.usage)).collect::<Vec<i64>>();
+        assert_eq!(sent(|usage| usage.revision_sent), vec![400, 2400, 2000, 0]);
+        assert_eq!(sent(|usage| usage.control_sent), vec![1, 7, 6, 0]);
+
+        // Nothing is dropped or counted twice going back.
+        test.manager.update_timezone("UTC").unwrap();
+        let series = test.manager.query_at(day(2023, 11, 6), 5).unwrap();
+        let total = series.iter().map(|day| day.usage.total()).sum::<i64>();
+        assert_eq!(total, 2 * 2400 + 2 * 7);
+
+        assert!(test.manager.update_timezone("Not/A_Timezone").is_err());
+        assert_eq!(test.manager.bucket_clock().unwrap().timezone(), "UTC");
+    }
+
+    #[test]
+    fn split_count_test() {
+        assert_eq!(split_count(7, &[4, 20]), vec![1, 6]);
+        assert_eq!(split_count(10, &[1, 1, 1]), vec![4, 3, 3]);
+        assert_eq!(split_count(5, &[0, 0]), vec![0, 0]);
+        assert_eq!(split_count(0, &[3, 5]), vec![0, 0]);
+    }
+
+    struct SyncUsageTest {
+        ws_conn: Arc<FlowyWebSocketConnect>,
+        transport: Arc<MockTransport>,
+        receiver: Arc<MockReceiver>,
+        user: Arc<MockUser>,
+        manager: SyncUsageManager,
+    }
+
+    impl SyncUsageTest {
+        fn new() -> Self {
+            let transport = Arc::new(MockTransport::default());
+            let ws_conn = Arc::new(FlowyWebSocketConnect::from_local(
+                "ws://localhost".to_owned(),
+                transport.clone(),
+            ));
+            let receiver = Arc::new(MockReceiver::default());
+            ws_conn.add_ws_message_receiver(receiver.clone()).unwrap();
+            let user = Arc::new(MockUser::new());
+            let manager = SyncUsageManager::new(ws_conn.clone(), user.clone());
+            Self {
+                ws_conn,
+                transport,
+                receiver,
+                user,
+                manager,
+            }
+        }
+
+        async fn send(&self, msg: WebSocketRawMessage) {
+            let sender = self.ws_conn.web_socket().await.unwrap().unwrap();
+            sender.send(msg).unwrap();
+        }
+    }
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(year, month, day)
+    }
+
+    fn instant(year: i32, month: u32, day: u32, hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.ymd(year, month, day).and_hms(hour, min, 0)
+    }
+
+    fn revision(rev_id: i64, data: &str) -> Revision {
+        Revision::new("doc", rev_id - 1, rev_id, Bytes::from(data.to_owned()), "")
+    }
+
+    fn client_message(data: ClientRevisionWSData) -> WebSocketRawMessage {
+        let bytes: Bytes = data.try_into().unwrap();
+        WebSocketRawMessage {
+            channel: WSChannel::Document,
+            data: bytes.to_vec(),
+        }
+    }
+
+    fn server_message(payload: WSRevisionPayload) -> WebSocketRawMessage {
+        let data = ServerRevisionWSData {
+            object_id: "doc".to_owned(),
+            payload,
+        };
+        let bytes: Bytes = data.try_into().unwrap();
+        WebSocketRawMessage {
+            channel: WSChannel::Document,
+            data: bytes.to_vec(),
+        }
+    }
+
+    fn sent_len(msg: &WebSocketRawMessage) -> i64 {
+        crate::ws::usage::wire_len(msg, crate::ws::usage::SyncTrafficDirection::Sent) as i64
+    }
+
+    fn received_len(msg: &WebSocketRawMessage) -> i64 {
+        crate::ws::usage::wire_len(msg, crate::ws::usage::SyncTrafficDirection::Received) as i64
+    }
+
+    struct MockUser {
+        workspace_id: RwLock<String>,
+        database: TempDatabase,
+    }
+
+    impl MockUser {
+        fn new() -> Self {
+            Self {
+                workspace_id: RwLock::new("workspace_1".to_owned()),
+                database: TempDatabase::new("sync_usage"),
+            }
+        }
+    }
+
+    impl SyncUsageUser for MockUser {
+        fn workspace_id(&self) -> FlowyResult<String> {
+            Ok(self.workspace_id.read().clone())
+        }
+
+        fn db_pool(&self) -> FlowyResult<Arc<ConnectionPool>> {
+            Ok(self.database.get_pool())
+        }
+    }
+
+    /// Records the messages sent, and delivers the messages received to the receivers.
+    #[derive(Default)]
+    struct MockTransport {
+        sent: Arc<RwLock<Vec<WebSocketRawMessage>>>,
+        receivers: RwLock<Vec<Arc<dyn WSMessageReceiver>>>,
+    }
+
+    impl MockTransport {
+        fn receive(&self, msg: WebSocketRawMessage) {
+            for receiver in self.receivers.read().iter() {
+                receiver.receive_message(msg.clone());
+            }
+        }
+
+        fn num_of_sent(&self) -> usize {
+            self.sent.read().len()
+        }
+
+        /// The lengths of the payloads of the binary frames sent, see `WebSocketRawMessage::to_bytes`.
+        fn sent_payload_lens(&self) -> Vec<usize> {
+            self.sent.read().iter().map(|msg| msg.to_bytes().len()).collect()
+        }
+    }
+
+    impl FlowyRawWebSocket for MockTransport {
+        fn initialize(&self) -> FutureResult<(), FlowyError> {
+            FutureResult::new(async { Ok(()) })
+        }
+
+        fn start_connect(&self, _addr: String, _user_id: String) -> FutureResult<(), FlowyError> {
+            FutureResult::new(async { Ok(()) })
+        }
+
+        fn stop_connect(&self) -> FutureResult<(), FlowyError> {
+            FutureResult::new(async { Ok(()) })
+        }
+
+        fn subscribe_connect_state(&self) -> BoxFuture<broadcast::Receiver<WSConnectState>> {
+            let (sender, receiver) = broadcast::channel(1);
+            drop(sender);
+            Box::pin(async move { receiver })
+        }
+
+        fn reconnect(&self, _count: usize) -> FutureResult<(), FlowyError> {
+            FutureResult::new(async { Ok(()) })
+        }
+
+        fn add_msg_receiver(&self, receiver: Arc<dyn WSMessageReceiver>) -> Result<(), FlowyError> {
+            self.receivers.write().push(receiver);
+            Ok(())
+        }
+
+        fn ws_msg_sender(&self) -> FutureResult<Option<Arc<dyn FlowyWebSocket>>, FlowyError> {
+            let sender: Arc<dyn FlowyWebSocket> = Arc::new(MockSender(self.sent.clone()));
+            FutureResult::new(async move { Ok(Some(sender)) })
+        }
+    }
+
+    struct MockSender(Arc<RwLock<Vec<WebSocketRawMessage>>>);
+
+    impl FlowyWebSocket for MockSender {
+        fn send(&self, msg: WebSocketRawMessage) -> Result<(), FlowyError> {
+            self.0.write().push(msg);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct MockReceiver {
+        received: RwLock<Vec<WebSocketRawMessage>>,
+    }
+
+    impl MockReceiver {
+        fn num_of_received(&self) -> usize {
+            self.received.read().len()
+        }
+    }
+
+    impl WSMessageReceiver for MockReceiver {
+        fn source(&self) -> WSChannel {
+            WSChannel::Document
+        }
+
+        fn receive_message(&self, msg: WebSocketRawMessage) {
+            self.received.write().push(msg);
+        }
+    }
+}
@@ -0,0 +1,4 @@
+mod manager;
+mod persistence;
+
+pub use manager::*;
@@ -0,0 +1,96 @@
+use crate::ws::usage::SyncUsage;
+use diesel::sql_types::{BigInt, Text};
+use flowy_database::{prelude::*, schema::sync_usage::dsl, sql_query};
+use flowy_error::FlowyResult;
+
+/// Reads and writes the `sync_usage` table, each row is the usage of a workspace in a day.
+pub(crate) struct SyncUsageSql {}
+
+impl SyncUsageSql {
+    /// Adds the `usage` to the day's usage of the workspace.
+    pub(crate) fn add(workspace_id: &str, day: &str, usage: &SyncUsage, conn: &SqliteConnection) -> FlowyResult<()> {
+        let _ = sql_query(
+            "INSERT INTO sync_usage (workspace_id, day, revision_sent, revision_received, presence_sent, \
+             presence_received, control_sent, control_received) VALUES (?, ?, ?, ?, ?, ?, ?, ?) \
+             ON CONFLICT(workspace_id, day) DO UPDATE SET \
+             revision_sent = revision_sent + excluded.revision_sent, \
+             revision_received = revision_received + excluded.revision_received, \
+             presence_sent = presence_sent + excluded.presence_sent, \
+             presence_received = presence_received + excluded.presence_received, \
+             control_sent = control_sent + excluded.control_sent, \
+             control_received = control_received + excluded.control_received",
+        )
+        .bind::<Text, _>(workspace_id)
+        .bind::<Text, _>(day)
+        .bind::<BigInt, _>(usage.revision_sent)
+        .bind::<BigInt, _>(usage.revision_received)
+        .bind::<BigInt, _>(usage.presence_sent)
+        .bind::<BigInt, _>(usage.presence_received)
+        .bind::<BigInt, _>(usage.control_sent)
+        .bind::<BigInt, _>(usage.control_received)
+        .execute(conn)?;
+        Ok(())
+    }
+
+    /// Replaces all the daily usage of the workspace with the `days`.
+    pub(crate) fn replace_all(
+        workspace_id: &str,
+        days: &[(String, SyncUsage)],
+        conn: &SqliteConnection,
+    ) -> FlowyResult<()> {
+        let _ = diesel::delete(dsl::sync_usage.filter(dsl::workspace_id.eq(workspace_id))).execute(conn)?;
+        for (day, usage) in days {
+            Self::add(workspace_id, day, usage, conn)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the usage of the workspace from the `from_day` on, ordered by the day. The days
+    /// without any usage are missing.
+    pub(crate) fn read_since(
+        workspace_id: &str,
+        from_day: &str,
+        conn: &SqliteConnection,
+    ) -> FlowyResult<Vec<(String, SyncUsage)>> {
+        let rows = dsl::sync_usage
+            .filter(dsl::workspace_id.eq(workspace_id))
+            .filter(dsl::day.ge(from_day))
+            .select((
+                dsl::day,
+                dsl::revision_sent,
+                dsl::revision_received,
+                dsl::presence_sent,
+                dsl::presence_received,
+                dsl::control_sent,
+                dsl::control_received,
+            ))
+            .order(dsl::day.asc())
+            .load::<SyncUsageRow>(conn)?;
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Queryable)]
+struct SyncUsageRow {
+    day: String,
+    revision_sent: i64,
+    revision_received: i64,
+    presence_sent: i64,
+    presence_received: i64,
+    control_sent: i64,
+    control_received: i64,
+}
+
+impl std::convert::From<SyncUsageRow> for (String, SyncUsage) {
+    fn from(row: SyncUsageRow) -> Self {
+        let usage = SyncUsage {
+            revision_sent: row.revision_sent,
+            revision_received: row.revision_received,
+            presence_sent: row.presence_sent,
+            presence_received: row.presence_received,
+            control_sent: row.control_sent,
+            control_received: row.control_received,
+        };
+        (row.day, usage)
+    }
+}
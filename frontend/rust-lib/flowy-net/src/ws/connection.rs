@@ -1,4 +1,5 @@
-use crate::entities::NetworkType;
+use crate::entities::{NetworkType, PowerType};
+use crate::ws::usage::{MeteredReceiver, MeteredWebSocket, SyncUsageCounter};
 
 pub use flowy_error::FlowyError;
 use lib_infra::future::FutureResult;
@@ -7,6 +8,7 @@ pub use lib_ws::{WSConnectState, WSMessageReceiver, WebSocketRawMessage};
 use futures_util::future::BoxFuture;
 use lib_ws::WSController;
 use parking_lot::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 
@@ -28,28 +30,39 @@ pub struct FlowyWebSocketConnect {
     inner: Arc<dyn FlowyRawWebSocket>,
     connect_type: RwLock<NetworkType>,
     status_notifier: broadcast::Sender<NetworkType>,
+    power_notifier: broadcast::Sender<PowerType>,
     addr: String,
+    sync_usage: Arc<SyncUsageCounter>,
+    sync_paused: AtomicBool,
 }
 
 impl FlowyWebSocketConnect {
     pub fn new(addr: String) -> Self {
         let ws = Arc::new(Arc::new(WSController::new()));
         let (status_notifier, _) = broadcast::channel(10);
+        let (power_notifier, _) = broadcast::channel(10);
         FlowyWebSocketConnect {
             inner: ws,
             connect_type: RwLock::new(NetworkType::default()),
             status_notifier,
+            power_notifier,
             addr,
+            sync_usage: Arc::new(SyncUsageCounter::default()),
+            sync_paused: AtomicBool::new(false),
         }
     }
 
     pub fn from_local(addr: String, ws: Arc<dyn FlowyRawWebSocket>) -> Self {
         let (status_notifier, _) = broadcast::channel(10);
+        let (power_notifier, _) = broadcast::channel(10);
         FlowyWebSocketConnect {
             inner: ws,
             connect_type: RwLock::new(NetworkType::default()),
             status_notifier,
+            power_notifier,
             addr,
+            sync_usage: Arc::new(SyncUsageCounter::default()),
+            sync_paused: AtomicBool::new(false),
         }
     }
 
@@ -101,13 +114,54 @@ impl FlowyWebSocketConnect {
         self.status_notifier.subscribe()
     }
 
+    pub fn update_power_type(&self, new_type: PowerType) {
+        tracing::debug!("Power new state: {:?}", new_type);
+        let _ = self.power_notifier.send(new_type);
+    }
+
+    pub fn subscribe_power_ty(&self) -> broadcast::Receiver<PowerType> {
+        self.power_notifier.subscribe()
+    }
+
     pub fn add_ws_message_receiver(&self, receiver: Arc<dyn WSMessageReceiver>) -> Result<(), FlowyError> {
+        let receiver = Arc::new(MeteredReceiver::new(receiver, self.sync_usage.clone()));
         self.inner.add_msg_receiver(receiver)?;
         Ok(())
     }
 
+    /// Returns the `SyncPaused` error if the sync is paused instead of a sender, so the message
+    /// isn't taken as sent. The unsynced revisions are sent after the sync is resumed.
     pub async fn web_socket(&self) -> Result<Option<Arc<dyn FlowyWebSocket>>, FlowyError> {
-        self.inner.ws_msg_sender().await
+        if self.is_sync_paused() {
+            return Err(FlowyError::sync_paused());
+        }
+        match self.inner.ws_msg_sender().await? {
+            None => Ok(None),
+            Some(sender) => {
+                let sender: Arc<dyn FlowyWebSocket> = Arc::new(MeteredWebSocket::new(sender, self.sync_usage.clone()));
+                Ok(Some(sender))
+            }
+        }
+    }
+
+    /// Counts the wire bytes of the messages sent and received by the websocket.
+    pub fn sync_usage_counter(&self) -> Arc<SyncUsageCounter> {
+        self.sync_usage.clone()
+    }
+
+    /// Stops sending the messages, the messages from the server are still received.
+    pub fn pause_sync(&self) {
+        tracing::info!("Pause the sync");
+        self.sync_paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume_sync(&self) {
+        tracing::info!("Resume the sync");
+        self.sync_paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_sync_paused(&self) -> bool {
+        self.sync_paused.load(Ordering::SeqCst)
     }
 }
 
@@ -0,0 +1,230 @@
+use crate::ws::connection::FlowyWebSocket;
+use flowy_error::FlowyError;
+use flowy_http_model::ws_data::ClientRevisionWSDataType;
+use lib_ws::{WSChannel, WSMessageReceiver, WebSocketRawMessage};
+use serde::de::IgnoredAny;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SyncTrafficKind {
+    /// The frames that carry the revisions, i.e. pushing and receiving the changes.
+    Revision,
+    /// The frames that tell other users are connected to the object.
+    Presence,
+    /// The pings, acks and pull requests that drive the sync.
+    Control,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SyncTrafficDirection {
+    Sent,
+    Received,
+}
+
+/// The wire bytes of the sync traffic, split by the kind of the frames.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SyncUsage {
+    pub revision_sent: i64,
+    pub revision_received: i64,
+    pub presence_sent: i64,
+    pub presence_received: i64,
+    pub control_sent: i64,
+    pub control_received: i64,
+}
+
+impl SyncUsage {
+    pub fn total(&self) -> i64 {
+        self.revision_sent
+            + self.revision_received
+            + self.presence_sent
+            + self.presence_received
+            + self.control_sent
+            + self.control_received
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    pub fn add(&mut self, other: &SyncUsage) {
+        self.revision_sent += other.revision_sent;
+        self.revision_received += other.revision_received;
+        self.presence_sent += other.presence_sent;
+        self.presence_received += other.presence_received;
+        self.control_sent += other.control_sent;
+        self.control_received += other.control_received;
+    }
+}
+
+/// Counts the wire bytes of the messages sent and received by the websocket. The counters are
+/// taken by the `SyncUsageManager`, which adds them to the daily usage.
+#[derive(Default)]
+pub struct SyncUsageCounter {
+    revision_sent: AtomicI64,
+    revision_received: AtomicI64,
+    presence_sent: AtomicI64,
+    presence_received: AtomicI64,
+    control_sent: AtomicI64,
+    control_received: AtomicI64,
+}
+
+impl SyncUsageCounter {
+    pub fn record(&self, kind: SyncTrafficKind, direction: SyncTrafficDirection, bytes: usize) {
+        let counter = match (kind, direction) {
+            (SyncTrafficKind::Revision, SyncTrafficDirection::Sent) => &self.revision_sent,
+            (SyncTrafficKind::Revision, SyncTrafficDirection::Received) => &self.revision_received,
+            (SyncTrafficKind::Presence, SyncTrafficDirection::Sent) => &self.presence_sent,
+            (SyncTrafficKind::Presence, SyncTrafficDirection::Received) => &self.presence_received,
+            (SyncTrafficKind::Control, SyncTrafficDirection::Sent) => &self.control_sent,
+            (SyncTrafficKind::Control, SyncTrafficDirection::Received) => &self.control_received,
+        };
+        counter.fetch_add(bytes as i64, Ordering::SeqCst);
+    }
+
+    /// Counts the message with its wire bytes, see `wire_len`.
+    pub fn record_message(&self, msg: &WebSocketRawMessage, direction: SyncTrafficDirection) {
+        self.record(classify_message(msg, direction), direction, wire_len(msg, direction));
+    }
+
+    /// Returns the bytes counted since the last call and resets the counters.
+    pub fn take(&self) -> SyncUsage {
+        SyncUsage {
+            revision_sent: self.revision_sent.swap(0, Ordering::SeqCst),
+            revision_received: self.revision_received.swap(0, Ordering::SeqCst),
+            presence_sent: self.presence_sent.swap(0, Ordering::SeqCst),
+            presence_received: self.presence_received.swap(0, Ordering::SeqCst),
+            control_sent: self.control_sent.swap(0, Ordering::SeqCst),
+            control_received: self.control_received.swap(0, Ordering::SeqCst),
+        }
+    }
+
+    /// Puts back the usage returned by `take`, e.g. it failed to be saved.
+    pub fn restore(&self, usage: &SyncUsage) {
+        self.revision_sent.fetch_add(usage.revision_sent, Ordering::SeqCst);
+        self.revision_received
+            .fetch_add(usage.revision_received, Ordering::SeqCst);
+        self.presence_sent.fetch_add(usage.presence_sent, Ordering::SeqCst);
+        self.presence_received
+            .fetch_add(usage.presence_received, Ordering::SeqCst);
+        self.control_sent.fetch_add(usage.control_sent, Ordering::SeqCst);
+        self.control_received
+            .fetch_add(usage.control_received, Ordering::SeqCst);
+    }
+}
+
+/// The frames of all the channels share the same format. The frame that can't be recognized is
+/// counted as control.
+///
+/// Only the fields in front of the revisions are read, i.e. the `object_id` and then the `ty` of
+/// the client's frame or the name of the server's payload, so the revisions are never parsed.
+pub fn classify_message(msg: &WebSocketRawMessage, direction: SyncTrafficDirection) -> SyncTrafficKind {
+    match direction {
+        SyncTrafficDirection::Sent => {
+            let ty = read_frame_tag(&msg.data, "ty").and_then(|tag| {
+                let mut ty = serde_json::Deserializer::from_slice(tag).into_iter::<ClientRevisionWSDataType>();
+                ty.next()?.ok()
+            });
+            match ty {
+                Some(ClientRevisionWSDataType::ClientPushRev) => SyncTrafficKind::Revision,
+                _ => SyncTrafficKind::Control,
+            }
+        }
+        SyncTrafficDirection::Received => match read_frame_tag(&msg.data, "payload") {
+            Some(tag) if tag.starts_with(br#"{"ServerPushRev""#) => SyncTrafficKind::Revision,
+            Some(tag) if tag.starts_with(br#"{"UserConnect""#) => SyncTrafficKind::Presence,
+            _ => SyncTrafficKind::Control,
+        },
+    }
+}
+
+/// Returns the bytes that follow the `key` of the frame, e.g. `0,"revisions":..` of the key
+/// `ty`. The `key` is expected right after the `object_id`, which is the field order of both the
+/// `ClientRevisionWSData` and the `ServerRevisionWSData`.
+fn read_frame_tag<'a>(data: &'a [u8], key: &str) -> Option<&'a [u8]> {
+    let rest = data.strip_prefix(br#"{"object_id":"#)?;
+    let mut object_id = serde_json::Deserializer::from_slice(rest).into_iter::<IgnoredAny>();
+    object_id.next()?.ok()?;
+    let rest = rest[object_id.byte_offset()..].strip_prefix(b",")?;
+    rest.strip_prefix(format!(r#""{}":"#, key).as_bytes())
+}
+
+/// Returns the size of the websocket frame that carries the message. The message is serialized
+/// as the payload of a binary frame, the client doesn't negotiate the permessage-deflate
+/// extension, so the payload goes over the wire as it is. The frames sent by the client are
+/// masked.
+///
+/// The size of the serialized message is computed from its data instead of serializing it again,
+/// see `serialized_len`.
+pub fn wire_len(msg: &WebSocketRawMessage, direction: SyncTrafficDirection) -> usize {
+    let payload_len = serialized_len(msg);
+    let extended_len = match payload_len {
+        0..=125 => 0,
+        126..=65535 => 2,
+        _ => 8,
+    };
+    let mask_len = match direction {
+        SyncTrafficDirection::Sent => 4,
+        SyncTrafficDirection::Received => 0,
+    };
+    2 + extended_len + mask_len + payload_len
+}
+
+/// The length of `WebSocketRawMessage::to_bytes`, i.e. `{"channel":0,"data":[123,4,56]}`. The
+/// data is serialized as an array of the decimal bytes.
+fn serialized_len(msg: &WebSocketRawMessage) -> usize {
+    let channel_len = decimal_len(msg.channel.clone() as u8);
+    let data_len = msg.data.iter().map(|byte| decimal_len(*byte)).sum::<usize>() + msg.data.len().saturating_sub(1);
+    r#"{"channel":,"data":[]}"#.len() + channel_len + data_len
+}
+
+fn decimal_len(value: u8) -> usize {
+    match value {
+        0..=9 => 1,
+        10..=99 => 2,
+        _ => 3,
+    }
+}
+
+pub(crate) struct MeteredWebSocket {
+    inner: Arc<dyn FlowyWebSocket>,
+    counter: Arc<SyncUsageCounter>,
+}
+
+impl MeteredWebSocket {
+    pub(crate) fn new(inner: Arc<dyn FlowyWebSocket>, counter: Arc<SyncUsageCounter>) -> Self {
+        Self { inner, counter }
+    }
+}
+
+impl FlowyWebSocket for MeteredWebSocket {
+    fn send(&self, msg: WebSocketRawMessage) -> Result<(), FlowyError> {
+        let kind = classify_message(&msg, SyncTrafficDirection::Sent);
+        let len = wire_len(&msg, SyncTrafficDirection::Sent);
+        self.inner.send(msg)?;
+        self.counter.record(kind, SyncTrafficDirection::Sent, len);
+        Ok(())
+    }
+}
+
+pub(crate) struct MeteredReceiver {
+    inner: Arc<dyn WSMessageReceiver>,
+    counter: Arc<SyncUsageCounter>,
+}
+
+impl MeteredReceiver {
+    pub(crate) fn new(inner: Arc<dyn WSMessageReceiver>, counter: Arc<SyncUsageCounter>) -> Self {
+        Self { inner, counter }
+    }
+}
+
+impl WSMessageReceiver for MeteredReceiver {
+    fn source(&self) -> WSChannel {
+        self.inner.source()
+    }
+
+    fn receive_message(&self, msg: WebSocketRawMessage) {
+        self.counter.record_message(&msg, SyncTrafficDirection::Received);
+        self.inner.receive_message(msg)
+    }
+}
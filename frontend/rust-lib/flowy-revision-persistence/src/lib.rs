@@ -9,6 +9,13 @@ pub trait RevisionDiskCache<Connection>: Sync + Send {
     type Error: Debug;
     fn create_revision_records(&self, revision_records: Vec<SyncRecord>) -> Result<(), Self::Error>;
 
+    // Same as `create_revision_records`, but the records are on the disk when it returns, so a
+    // power loss right after it doesn't lose them. The disk caches that leave the syncing to the
+    // OS, e.g. the sqlite ones with `PRAGMA synchronous = NORMAL`, should override it
+    fn create_durable_revision_records(&self, revision_records: Vec<SyncRecord>) -> Result<(), Self::Error> {
+        self.create_revision_records(revision_records)
+    }
+
     fn get_connection(&self) -> Result<Connection, Self::Error>;
 
     // Read all the records if the rev_ids is None
@@ -27,6 +34,19 @@ pub trait RevisionDiskCache<Connection>: Sync + Send {
     // Delete all the records if the rev_ids is None
     fn delete_revision_records(&self, object_id: &str, rev_ids: Option<Vec<i64>>) -> Result<(), Self::Error>;
 
+    // Count the records of the object. The disk caches that can count without reading the
+    // records, e.g. with `SELECT COUNT(*)`, should override it
+    fn count_revision_records(&self, object_id: &str) -> Result<u64, Self::Error> {
+        Ok(self.read_revision_records(object_id, None)?.len() as u64)
+    }
+
+    // Delete the records with the rev_ids in one transaction. Returns the number of the deleted records
+    fn delete_revs(&self, object_id: &str, rev_ids: &[i64]) -> Result<usize, Self::Error> {
+        let deleted = self.read_revision_records(object_id, Some(rev_ids.to_vec()))?.len();
+        self.delete_revision_records(object_id, Some(rev_ids.to_vec()))?;
+        Ok(deleted)
+    }
+
     // Delete and insert will be executed in the same transaction.
     // It deletes all the records if the deleted_rev_ids is None and then insert the new records
     fn delete_and_insert_records(
@@ -35,6 +55,84 @@ pub trait RevisionDiskCache<Connection>: Sync + Send {
         deleted_rev_ids: Option<Vec<i64>>,
         inserted_records: Vec<SyncRecord>,
     ) -> Result<(), Self::Error>;
+
+    // Pin the revision, the pinned revisions are never merged or deleted by the compaction
+    fn pin_revision(&self, _object_id: &str, _rev_id: i64) -> FlowyResult<()> {
+        Err(FlowyError::internal().context("The disk cache doesn't support pinning revisions"))
+    }
+
+    fn unpin_revision(&self, _object_id: &str, _rev_id: i64) -> FlowyResult<()> {
+        Err(FlowyError::internal().context("The disk cache doesn't support pinning revisions"))
+    }
+
+    // Read the rev_ids of the pinned revisions in ascending order
+    fn read_pinned_rev_ids(&self, _object_id: &str) -> FlowyResult<Vec<i64>> {
+        Ok(vec![])
+    }
+
+    // Pin the revision and name it with the tag. The tag is unique within the object, tagging
+    // another revision with it moves the tag
+    fn tag_revision(&self, _object_id: &str, _rev_id: i64, _tag: &str) -> FlowyResult<()> {
+        Err(FlowyError::internal().context("The disk cache doesn't support tagging revisions"))
+    }
+
+    // Read the tags and the rev_ids of the tagged revisions in ascending order of the rev_id
+    fn read_revision_tags(&self, _object_id: &str) -> FlowyResult<Vec<(String, i64)>> {
+        Ok(vec![])
+    }
+
+    // Read the rev_ids of the revisions with the time in seconds that each one was written at. The
+    // revisions written before the disk cache kept the time are left out
+    fn read_revision_timestamps(&self, _object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        Err(FlowyError::internal().context("The disk cache doesn't keep the timestamps of the revisions"))
+    }
+
+    // Set the time in seconds that the revisions expire at, None keeps them for good. The expired
+    // revisions are pruned by `RevisionManager::sweep_expired_revisions`
+    fn set_revision_expiry(&self, _object_id: &str, _rev_ids: &[i64], _expires_at: Option<i64>) -> FlowyResult<()> {
+        Err(FlowyError::internal().context("The disk cache doesn't support the expiry of the revisions"))
+    }
+
+    // Read the rev_ids of the revisions that expire with the time in seconds they expire at, in
+    // ascending order of the rev_id
+    fn read_revision_expiries(&self, _object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        Ok(vec![])
+    }
+
+    // Keep the rev_ids of the revisions whose push was given up on, they replace the ones kept
+    // before. The disk cache that doesn't keep them syncs them again after the object reopens
+    fn write_dead_letters(&self, _object_id: &str, _rev_ids: &[i64]) -> FlowyResult<()> {
+        Ok(())
+    }
+
+    // Read the rev_ids of the revisions whose push was given up on in ascending order
+    fn read_dead_letters(&self, _object_id: &str) -> FlowyResult<Vec<i64>> {
+        Ok(vec![])
+    }
+
+    // Keep the rev_ids assigned by the client mapped to the rev_ids assigned by the server, they
+    // replace the ones kept before. The disk cache that doesn't keep them resolves the rev_ids
+    // assigned by the client until the object reopens
+    fn write_rev_id_map(&self, _object_id: &str, _rev_id_map: &[(i64, i64)]) -> FlowyResult<()> {
+        Ok(())
+    }
+
+    // Read the rev_ids assigned by the client with the rev_ids assigned by the server in
+    // ascending order of the former
+    fn read_rev_id_map(&self, _object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        Ok(vec![])
+    }
+
+    // Keep a copy of the revisions before they're replaced, e.g. by a snapshot, it replaces the
+    // copy kept before. The disk cache that doesn't keep it can't recover the replaced revisions
+    fn write_revision_backup(&self, _object_id: &str, _revisions: &[Revision]) -> FlowyResult<()> {
+        Ok(())
+    }
+
+    // Read the copy kept by `write_revision_backup` in ascending order of the rev_ids
+    fn read_revision_backup(&self, _object_id: &str) -> FlowyResult<Vec<Revision>> {
+        Ok(vec![])
+    }
 }
 
 impl<T, Connection> RevisionDiskCache<Connection> for Arc<T>
@@ -47,6 +145,10 @@ where
         (**self).create_revision_records(revision_records)
     }
 
+    fn create_durable_revision_records(&self, revision_records: Vec<SyncRecord>) -> Result<(), Self::Error> {
+        (**self).create_durable_revision_records(revision_records)
+    }
+
     fn get_connection(&self) -> Result<Connection, Self::Error> {
         (**self).get_connection()
     }
@@ -75,6 +177,14 @@ where
         (**self).delete_revision_records(object_id, rev_ids)
     }
 
+    fn count_revision_records(&self, object_id: &str) -> Result<u64, Self::Error> {
+        (**self).count_revision_records(object_id)
+    }
+
+    fn delete_revs(&self, object_id: &str, rev_ids: &[i64]) -> Result<usize, Self::Error> {
+        (**self).delete_revs(object_id, rev_ids)
+    }
+
     fn delete_and_insert_records(
         &self,
         object_id: &str,
@@ -83,6 +193,62 @@ where
     ) -> Result<(), Self::Error> {
         (**self).delete_and_insert_records(object_id, deleted_rev_ids, inserted_records)
     }
+
+    fn pin_revision(&self, object_id: &str, rev_id: i64) -> FlowyResult<()> {
+        (**self).pin_revision(object_id, rev_id)
+    }
+
+    fn unpin_revision(&self, object_id: &str, rev_id: i64) -> FlowyResult<()> {
+        (**self).unpin_revision(object_id, rev_id)
+    }
+
+    fn read_pinned_rev_ids(&self, object_id: &str) -> FlowyResult<Vec<i64>> {
+        (**self).read_pinned_rev_ids(object_id)
+    }
+
+    fn tag_revision(&self, object_id: &str, rev_id: i64, tag: &str) -> FlowyResult<()> {
+        (**self).tag_revision(object_id, rev_id, tag)
+    }
+
+    fn read_revision_tags(&self, object_id: &str) -> FlowyResult<Vec<(String, i64)>> {
+        (**self).read_revision_tags(object_id)
+    }
+
+    fn read_revision_timestamps(&self, object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        (**self).read_revision_timestamps(object_id)
+    }
+
+    fn set_revision_expiry(&self, object_id: &str, rev_ids: &[i64], expires_at: Option<i64>) -> FlowyResult<()> {
+        (**self).set_revision_expiry(object_id, rev_ids, expires_at)
+    }
+
+    fn read_revision_expiries(&self, object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        (**self).read_revision_expiries(object_id)
+    }
+
+    fn write_dead_letters(&self, object_id: &str, rev_ids: &[i64]) -> FlowyResult<()> {
+        (**self).write_dead_letters(object_id, rev_ids)
+    }
+
+    fn read_dead_letters(&self, object_id: &str) -> FlowyResult<Vec<i64>> {
+        (**self).read_dead_letters(object_id)
+    }
+
+    fn write_rev_id_map(&self, object_id: &str, rev_id_map: &[(i64, i64)]) -> FlowyResult<()> {
+        (**self).write_rev_id_map(object_id, rev_id_map)
+    }
+
+    fn read_rev_id_map(&self, object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        (**self).read_rev_id_map(object_id)
+    }
+
+    fn write_revision_backup(&self, object_id: &str, revisions: &[Revision]) -> FlowyResult<()> {
+        (**self).write_revision_backup(object_id, revisions)
+    }
+
+    fn read_revision_backup(&self, object_id: &str) -> FlowyResult<Vec<Revision>> {
+        (**self).read_revision_backup(object_id)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -114,10 +280,13 @@ pub struct RevisionChangeset {
 
 /// Sync: revision is not synced to the server
 /// Ack: revision is synced to the server
+/// Resolved: revision was superseded by the revision that resolved its conflict. It's kept in
+/// the history, but it's neither synced nor composed into the object.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum RevisionState {
     Sync = 0,
     Ack = 1,
+    Resolved = 2,
 }
 
 impl RevisionState {
@@ -125,6 +294,7 @@ impl RevisionState {
         match self {
             RevisionState::Sync => true,
             RevisionState::Ack => false,
+            RevisionState::Resolved => false,
         }
     }
 }
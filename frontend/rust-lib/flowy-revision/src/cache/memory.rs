@@ -1,14 +1,16 @@
-use crate::REVISION_WRITE_INTERVAL_IN_MILLIS;
+use crate::{ErrorReporter, Executor, PriorityScheduler, SaveDebounceConfiguration, SaveScheduler};
 use dashmap::DashMap;
-use flowy_error::{FlowyError, FlowyResult};
+use flowy_error::{internal_error, FlowyError, FlowyResult};
 use flowy_http_model::revision::RevisionRange;
 use flowy_revision_persistence::SyncRecord;
-use std::{borrow::Cow, sync::Arc, time::Duration};
+use std::sync::Mutex;
+use std::{borrow::Cow, sync::Arc};
+use tokio::time::Instant;
 use tokio::{sync::RwLock, task::JoinHandle};
 
 pub(crate) trait RevisionMemoryCacheDelegate: Send + Sync {
     fn send_sync(&self, records: Vec<SyncRecord>) -> FlowyResult<()>;
-    fn receive_ack(&self, object_id: &str, rev_id: i64);
+    fn receive_ack(&self, object_id: &str, rev_id: i64) -> FlowyResult<()>;
 }
 
 pub(crate) struct RevisionMemoryCache {
@@ -17,16 +19,32 @@ pub(crate) struct RevisionMemoryCache {
     delegate: Arc<dyn RevisionMemoryCacheDelegate>,
     defer_write_revs: Arc<RwLock<Vec<i64>>>,
     defer_save: RwLock<Option<JoinHandle<()>>>,
+    /// Only locked while holding the `defer_write_revs`, so it agrees with the pending revisions.
+    save_scheduler: Arc<Mutex<SaveScheduler>>,
+    error_reporter: Option<Arc<dyn ErrorReporter>>,
+    executor: Executor,
+    priority_scheduler: Option<PriorityScheduler>,
 }
 
 impl RevisionMemoryCache {
-    pub(crate) fn new(object_id: &str, delegate: Arc<dyn RevisionMemoryCacheDelegate>) -> Self {
+    pub(crate) fn new(
+        object_id: &str,
+        delegate: Arc<dyn RevisionMemoryCacheDelegate>,
+        save_debounce: SaveDebounceConfiguration,
+        error_reporter: Option<Arc<dyn ErrorReporter>>,
+        executor: Executor,
+        priority_scheduler: Option<PriorityScheduler>,
+    ) -> Self {
         RevisionMemoryCache {
             object_id: object_id.to_owned(),
             revs_map: Arc::new(DashMap::new()),
             delegate,
             defer_write_revs: Arc::new(RwLock::new(vec![])),
             defer_save: RwLock::new(None),
+            save_scheduler: Arc::new(Mutex::new(SaveScheduler::new(save_debounce))),
+            error_reporter,
+            executor,
+            priority_scheduler,
         }
     }
 
@@ -41,11 +59,16 @@ impl RevisionMemoryCache {
         };
 
         let rev_id = record.revision.rev_id;
+        let num_of_bytes = record.revision.bytes.len();
         self.revs_map.insert(rev_id, record);
 
         let mut write_guard = self.defer_write_revs.write().await;
         if !write_guard.contains(&rev_id) {
             write_guard.push(rev_id);
+            self.save_scheduler
+                .lock()
+                .unwrap()
+                .record_change(Instant::now(), num_of_bytes);
             drop(write_guard);
             self.tick_checkpoint().await;
         }
@@ -62,14 +85,62 @@ impl RevisionMemoryCache {
         } else {
             // The revision must be saved on disk if the pending_write_revs
             // doesn't contains the rev_id.
-            self.delegate.receive_ack(&self.object_id, *rev_id);
+            if let Err(e) = self.delegate.receive_ack(&self.object_id, *rev_id) {
+                tracing::error!("{}", e);
+            }
+        }
+    }
+
+    /// Acks the revision and writes the ack to disk before returning instead of waiting for the
+    /// next checkpoint.
+    pub(crate) async fn ack_and_persist(&self, rev_id: &i64) -> FlowyResult<()> {
+        let record = self.revs_map.get_mut(rev_id).map(|mut record| {
+            record.ack();
+            record.value().clone()
+        });
+
+        let mut write_guard = self.defer_write_revs.write().await;
+        let index = write_guard.iter().position(|pending_rev_id| pending_rev_id == rev_id);
+        match (index, record) {
+            (Some(index), Some(record)) if record.write_to_disk => {
+                // The revision is not on disk yet, write it with the ack state.
+                save_records(
+                    &self.delegate,
+                    self.priority_scheduler.as_ref(),
+                    &self.executor,
+                    &self.object_id,
+                    vec![record],
+                )
+                .await?;
+                write_guard.remove(index);
+                if write_guard.is_empty() {
+                    self.save_scheduler.lock().unwrap().did_save();
+                }
+            }
+            _ => self.delegate.receive_ack(&self.object_id, *rev_id)?,
         }
+        Ok(())
+    }
+
+    /// Replaces the record of `rev_id` with the `record` that carries the rev_id assigned by
+    /// the server. The caller is responsible for writing the record to disk.
+    pub(crate) async fn remap(&self, rev_id: &i64, record: SyncRecord) {
+        let _ = self.revs_map.remove(rev_id);
+        self.defer_write_revs
+            .write()
+            .await
+            .retain(|pending_rev_id| pending_rev_id != rev_id);
+        self.revs_map.insert(record.revision.rev_id, record);
     }
 
     pub(crate) async fn get(&self, rev_id: &i64) -> Option<SyncRecord> {
         self.revs_map.get(rev_id).map(|r| r.value().clone())
     }
 
+    pub(crate) fn records(&self) -> Vec<SyncRecord> {
+        self.revs_map.iter().map(|record| record.value().clone()).collect()
+    }
+
     pub(crate) fn remove(&self, rev_id: &i64) {
         let _ = self.revs_map.remove(rev_id);
     }
@@ -88,10 +159,39 @@ impl RevisionMemoryCache {
         Ok(revs)
     }
 
+    /// Writes the pending records to disk right away instead of waiting for the deferred save.
+    pub(crate) async fn flush(&self) -> FlowyResult<()> {
+        if let Some(handler) = self.defer_save.write().await.take() {
+            handler.abort();
+        }
+
+        let mut write_guard = self.defer_write_revs.write().await;
+        let records = write_guard
+            .iter()
+            .flat_map(|rev_id| self.revs_map.get(rev_id).map(|record| record.value().clone()))
+            .collect::<Vec<SyncRecord>>();
+        save_records(
+            &self.delegate,
+            self.priority_scheduler.as_ref(),
+            &self.executor,
+            &self.object_id,
+            records,
+        )
+        .await?;
+        write_guard.clear();
+        self.save_scheduler.lock().unwrap().did_save();
+        Ok(())
+    }
+
     pub(crate) fn number_of_sync_records(&self) -> usize {
         self.revs_map.len()
     }
 
+    /// Returns the number of the records waiting for the deferred save.
+    pub(crate) async fn number_of_pending_records(&self) -> usize {
+        self.defer_write_revs.read().await.len()
+    }
+
     pub(crate) async fn reset_with_revisions(&self, revision_records: Vec<SyncRecord>) {
         self.revs_map.clear();
         if let Some(handler) = self.defer_save.write().await.take() {
@@ -100,10 +200,49 @@ impl RevisionMemoryCache {
 
         let mut write_guard = self.defer_write_revs.write().await;
         write_guard.clear();
+        let mut save_scheduler = self.save_scheduler.lock().unwrap();
+        save_scheduler.did_save();
+        let now = Instant::now();
         for record in revision_records {
+            save_scheduler.record_change(now, record.revision.bytes.len());
             write_guard.push(record.revision.rev_id);
             self.revs_map.insert(record.revision.rev_id, record);
         }
+        drop(save_scheduler);
+        drop(write_guard);
+
+        self.tick_checkpoint().await;
+    }
+
+    /// Returns the records and the rev_ids of the ones waiting for the deferred save. The records
+    /// are read while the pending rev_ids are locked, so every pending rev_id has its record.
+    pub(crate) async fn capture(&self) -> (Vec<SyncRecord>, Vec<i64>) {
+        let read_guard = self.defer_write_revs.read().await;
+        let mut records = self.records();
+        records.sort_by_key(|record| record.revision.rev_id);
+        (records, read_guard.clone())
+    }
+
+    /// Replaces the records with the captured ones, the `pending_rev_ids` are saved by the next
+    /// checkpoint like the records that were added.
+    pub(crate) async fn restore(&self, records: Vec<SyncRecord>, pending_rev_ids: Vec<i64>) {
+        if let Some(handler) = self.defer_save.write().await.take() {
+            handler.abort();
+        }
+
+        let mut write_guard = self.defer_write_revs.write().await;
+        self.revs_map.clear();
+        let mut save_scheduler = self.save_scheduler.lock().unwrap();
+        save_scheduler.did_save();
+        let now = Instant::now();
+        for record in records {
+            if pending_rev_ids.contains(&record.revision.rev_id) {
+                save_scheduler.record_change(now, record.revision.bytes.len());
+            }
+            self.revs_map.insert(record.revision.rev_id, record);
+        }
+        *write_guard = pending_rev_ids;
+        drop(save_scheduler);
         drop(write_guard);
 
         self.tick_checkpoint().await;
@@ -115,16 +254,29 @@ impl RevisionMemoryCache {
             handler.abort();
         }
 
-        if self.defer_write_revs.read().await.is_empty() {
+        let read_guard = self.defer_write_revs.read().await;
+        if read_guard.is_empty() {
             return;
         }
+        let save_at = self
+            .save_scheduler
+            .lock()
+            .unwrap()
+            .next_save()
+            .unwrap_or_else(Instant::now);
+        drop(read_guard);
 
         let rev_map = self.revs_map.clone();
         let pending_write_revs = self.defer_write_revs.clone();
+        let save_scheduler = self.save_scheduler.clone();
         let delegate = self.delegate.clone();
+        let object_id = self.object_id.clone();
+        let error_reporter = self.error_reporter.clone();
+        let executor = self.executor.clone();
+        let priority_scheduler = self.priority_scheduler.clone();
 
-        *self.defer_save.write().await = Some(tokio::spawn(async move {
-            tokio::time::sleep(Duration::from_millis(REVISION_WRITE_INTERVAL_IN_MILLIS)).await;
+        *self.defer_save.write().await = Some(self.executor.spawn(async move {
+            tokio::time::sleep_until(save_at).await;
             let mut revs_write_guard = pending_write_revs.write().await;
             // It may cause performance issues because we hold the write lock of the
             // rev_order and the lock will be released after the checkpoint has been written
@@ -140,10 +292,50 @@ impl RevisionMemoryCache {
                 }
             });
 
-            if delegate.send_sync(save_records).is_ok() {
-                revs_write_guard.clear();
-                drop(revs_write_guard);
+            let result = save_records(
+                &delegate,
+                priority_scheduler.as_ref(),
+                &executor,
+                &object_id,
+                save_records,
+            )
+            .await;
+            match result {
+                Ok(_) => {
+                    revs_write_guard.clear();
+                    save_scheduler.lock().unwrap().did_save();
+                    drop(revs_write_guard);
+                }
+                Err(e) => {
+                    // The revisions stay pending, the next checkpoint or flush saves them again.
+                    tracing::error!("Save the revisions of {} failed: {:?}", object_id, e);
+                    if let Some(error_reporter) = error_reporter {
+                        error_reporter.report(&format!("save revisions of {}", object_id), &e);
+                    }
+                }
             }
         }));
     }
 }
+
+/// Saves the records with the `priority_scheduler` if it's set, so the saves of the focused object
+/// go before the background work. Otherwise they're saved on the current task.
+async fn save_records(
+    delegate: &Arc<dyn RevisionMemoryCacheDelegate>,
+    priority_scheduler: Option<&PriorityScheduler>,
+    executor: &Executor,
+    object_id: &str,
+    records: Vec<SyncRecord>,
+) -> FlowyResult<()> {
+    match priority_scheduler {
+        None => delegate.send_sync(records),
+        Some(scheduler) => {
+            let delegate = delegate.clone();
+            let priority = scheduler.priority_of(object_id);
+            scheduler
+                .spawn_blocking(executor, priority, move || delegate.send_sync(records))
+                .await
+                .map_err(internal_error)?
+        }
+    }
+}
@@ -1,2 +1,3 @@
 pub(crate) mod memory;
+pub(crate) mod read;
 pub mod reset;
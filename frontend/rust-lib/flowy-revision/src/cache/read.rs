@@ -0,0 +1,276 @@
+use flowy_error::{FlowyError, FlowyResult};
+use flowy_http_model::revision::{Revision, RevisionRange};
+use flowy_revision_persistence::{RevisionChangeset, RevisionDiskCache, SyncRecord};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Keeps the recently read revisions of the object in front of the disk cache, so reading the
+/// same historical revision again doesn't query the disk. At most `capacity` revisions are
+/// kept, the least recently used one is evicted first.
+///
+/// Every write that goes through it invalidates the revisions it touches. The writes that go
+/// to the disk cache directly aren't seen, so the `RevisionPersistence` must be the only
+/// writer of the object's revisions.
+pub(crate) struct RevisionReadCache<Connection> {
+    object_id: String,
+    disk_cache: Arc<dyn RevisionDiskCache<Connection, Error = FlowyError>>,
+    capacity: usize,
+    inner: Mutex<ReadCacheInner>,
+}
+
+#[derive(Default)]
+struct ReadCacheInner {
+    records: HashMap<i64, SyncRecord>,
+    /// The rev_ids from the least to the most recently used.
+    lru: VecDeque<i64>,
+    /// Bumped by each invalidation. The records read from the disk are only kept if nothing
+    /// was invalidated while reading them.
+    generation: u64,
+}
+
+impl<Connection> RevisionReadCache<Connection> {
+    pub(crate) fn new(
+        object_id: &str,
+        disk_cache: Arc<dyn RevisionDiskCache<Connection, Error = FlowyError>>,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            object_id: object_id.to_owned(),
+            disk_cache,
+            capacity,
+            inner: Mutex::new(ReadCacheInner::default()),
+        }
+    }
+
+    /// Returns the records of the `rev_ids` in ascending order if all of them are cached.
+    fn get_all(&self, object_id: &str, rev_ids: &[i64]) -> Option<Vec<SyncRecord>> {
+        if self.capacity == 0 || object_id != self.object_id || rev_ids.is_empty() {
+            return None;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if !rev_ids.iter().all(|rev_id| inner.records.contains_key(rev_id)) {
+            return None;
+        }
+        let mut records = Vec::with_capacity(rev_ids.len());
+        for rev_id in rev_ids {
+            inner.touch(*rev_id);
+            records.push(inner.records[rev_id].clone());
+        }
+        records.sort_by_key(|record| record.revision.rev_id);
+        Some(records)
+    }
+
+    fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    fn put(&self, object_id: &str, generation: u64, records: &[SyncRecord]) {
+        if self.capacity == 0 || object_id != self.object_id {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if inner.generation != generation {
+            return;
+        }
+        for record in records {
+            let rev_id = record.revision.rev_id;
+            inner.records.insert(rev_id, record.clone());
+            inner.touch(rev_id);
+        }
+        while inner.lru.len() > self.capacity {
+            if let Some(rev_id) = inner.lru.pop_front() {
+                inner.records.remove(&rev_id);
+            }
+        }
+    }
+
+    /// Removes the records of the `rev_ids`, or all the records if it's None.
+    fn invalidate(&self, rev_ids: Option<&[i64]>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.generation += 1;
+        match rev_ids {
+            None => {
+                inner.records.clear();
+                inner.lru.clear();
+            }
+            Some(rev_ids) => {
+                for rev_id in rev_ids {
+                    inner.records.remove(rev_id);
+                }
+                inner.lru.retain(|rev_id| !rev_ids.contains(rev_id));
+            }
+        }
+    }
+}
+
+impl ReadCacheInner {
+    fn touch(&mut self, rev_id: i64) {
+        if let Some(index) = self.lru.iter().position(|other| *other == rev_id) {
+            self.lru.remove(index);
+        }
+        self.lru.push_back(rev_id);
+    }
+}
+
+impl<Connection> RevisionDiskCache<Connection> for RevisionReadCache<Connection> {
+    type Error = FlowyError;
+
+    fn create_revision_records(&self, revision_records: Vec<SyncRecord>) -> Result<(), Self::Error> {
+        let rev_ids = rev_ids_of(&revision_records);
+        let result = self.disk_cache.create_revision_records(revision_records);
+        self.invalidate(Some(&rev_ids));
+        result
+    }
+
+    fn create_durable_revision_records(&self, revision_records: Vec<SyncRecord>) -> Result<(), Self::Error> {
+        let rev_ids = rev_ids_of(&revision_records);
+        let result = self.disk_cache.create_durable_revision_records(revision_records);
+        self.invalidate(Some(&rev_ids));
+        result
+    }
+
+    fn get_connection(&self) -> Result<Connection, Self::Error> {
+        self.disk_cache.get_connection()
+    }
+
+    fn read_revision_records(
+        &self,
+        object_id: &str,
+        rev_ids: Option<Vec<i64>>,
+    ) -> Result<Vec<SyncRecord>, Self::Error> {
+        match rev_ids {
+            // Reading all the records would evict the hot ones, so they aren't cached.
+            None => self.disk_cache.read_revision_records(object_id, None),
+            Some(rev_ids) => {
+                if let Some(records) = self.get_all(object_id, &rev_ids) {
+                    return Ok(records);
+                }
+                let generation = self.generation();
+                let records = self.disk_cache.read_revision_records(object_id, Some(rev_ids))?;
+                self.put(object_id, generation, &records);
+                Ok(records)
+            }
+        }
+    }
+
+    fn read_revision_records_with_range(
+        &self,
+        object_id: &str,
+        range: &RevisionRange,
+    ) -> Result<Vec<SyncRecord>, Self::Error> {
+        if range.len() as usize <= self.capacity {
+            if let Some(records) = self.get_all(object_id, &range.to_rev_ids()) {
+                return Ok(records);
+            }
+        }
+        let generation = self.generation();
+        let records = self.disk_cache.read_revision_records_with_range(object_id, range)?;
+        if records.len() <= self.capacity {
+            self.put(object_id, generation, &records);
+        }
+        Ok(records)
+    }
+
+    fn update_revision_record(&self, changesets: Vec<RevisionChangeset>) -> FlowyResult<()> {
+        let rev_ids = changesets
+            .iter()
+            .map(|changeset| changeset.rev_id)
+            .collect::<Vec<i64>>();
+        let result = self.disk_cache.update_revision_record(changesets);
+        self.invalidate(Some(&rev_ids));
+        result
+    }
+
+    fn delete_revision_records(&self, object_id: &str, rev_ids: Option<Vec<i64>>) -> Result<(), Self::Error> {
+        let result = self.disk_cache.delete_revision_records(object_id, rev_ids.clone());
+        self.invalidate(rev_ids.as_deref());
+        result
+    }
+
+    fn count_revision_records(&self, object_id: &str) -> Result<u64, Self::Error> {
+        self.disk_cache.count_revision_records(object_id)
+    }
+
+    fn delete_revs(&self, object_id: &str, rev_ids: &[i64]) -> Result<usize, Self::Error> {
+        let result = self.disk_cache.delete_revs(object_id, rev_ids);
+        self.invalidate(Some(rev_ids));
+        result
+    }
+
+    fn delete_and_insert_records(
+        &self,
+        object_id: &str,
+        deleted_rev_ids: Option<Vec<i64>>,
+        inserted_records: Vec<SyncRecord>,
+    ) -> Result<(), Self::Error> {
+        let invalidated_rev_ids = deleted_rev_ids.clone().map(|mut rev_ids| {
+            rev_ids.extend(rev_ids_of(&inserted_records));
+            rev_ids
+        });
+        let result = self
+            .disk_cache
+            .delete_and_insert_records(object_id, deleted_rev_ids, inserted_records);
+        self.invalidate(invalidated_rev_ids.as_deref());
+        result
+    }
+
+    fn pin_revision(&self, object_id: &str, rev_id: i64) -> FlowyResult<()> {
+        self.disk_cache.pin_revision(object_id, rev_id)
+    }
+
+    fn unpin_revision(&self, object_id: &str, rev_id: i64) -> FlowyResult<()> {
+        self.disk_cache.unpin_revision(object_id, rev_id)
+    }
+
+    fn read_pinned_rev_ids(&self, object_id: &str) -> FlowyResult<Vec<i64>> {
+        self.disk_cache.read_pinned_rev_ids(object_id)
+    }
+
+    fn tag_revision(&self, object_id: &str, rev_id: i64, tag: &str) -> FlowyResult<()> {
+        self.disk_cache.tag_revision(object_id, rev_id, tag)
+    }
+
+    fn read_revision_tags(&self, object_id: &str) -> FlowyResult<Vec<(String, i64)>> {
+        self.disk_cache.read_revision_tags(object_id)
+    }
+
+    fn read_revision_timestamps(&self, object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        self.disk_cache.read_revision_timestamps(object_id)
+    }
+
+    fn set_revision_expiry(&self, object_id: &str, rev_ids: &[i64], expires_at: Option<i64>) -> FlowyResult<()> {
+        self.disk_cache.set_revision_expiry(object_id, rev_ids, expires_at)
+    }
+
+    fn read_revision_expiries(&self, object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        self.disk_cache.read_revision_expiries(object_id)
+    }
+
+    fn write_dead_letters(&self, object_id: &str, rev_ids: &[i64]) -> FlowyResult<()> {
+        self.disk_cache.write_dead_letters(object_id, rev_ids)
+    }
+
+    fn read_dead_letters(&self, object_id: &str) -> FlowyResult<Vec<i64>> {
+        self.disk_cache.read_dead_letters(object_id)
+    }
+
+    fn write_rev_id_map(&self, object_id: &str, rev_id_map: &[(i64, i64)]) -> FlowyResult<()> {
+        self.disk_cache.write_rev_id_map(object_id, rev_id_map)
+    }
+
+    fn read_rev_id_map(&self, object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        self.disk_cache.read_rev_id_map(object_id)
+    }
+
+    fn write_revision_backup(&self, object_id: &str, revisions: &[Revision]) -> FlowyResult<()> {
+        self.disk_cache.write_revision_backup(object_id, revisions)
+    }
+
+    fn read_revision_backup(&self, object_id: &str) -> FlowyResult<Vec<Revision>> {
+        self.disk_cache.read_revision_backup(object_id)
+    }
+}
+
+fn rev_ids_of(records: &[SyncRecord]) -> Vec<i64> {
+    records.iter().map(|record| record.revision.rev_id).collect()
+}
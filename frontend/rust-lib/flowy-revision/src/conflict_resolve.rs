@@ -1,9 +1,9 @@
-use crate::{RevisionMD5, RevisionManager};
+use crate::{RevisionAuthor, RevisionMD5, RevisionManager, SyncLoopConfiguration, SyncLoopDetector, SyncLoopObserver};
 use bytes::Bytes;
 use flowy_error::{FlowyError, FlowyResult};
 use flowy_http_model::revision::{Revision, RevisionRange};
 use lib_infra::future::BoxResultFuture;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 pub struct TransformOperations<Operations> {
     pub client_operations: Operations,
@@ -18,6 +18,25 @@ pub trait OperationsSerializer: Send + Sync {
     fn serialize_operations(&self) -> Bytes;
 }
 
+/// Decides what happens to the unsynced local revisions of an object when the newer version
+/// fetched from the server would replace it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchOverwritePolicy {
+    /// Keeps the local object and ignores the fetched version. The unsynced revisions get
+    /// merged with the server once they're synced.
+    Preserve,
+    /// Applies the fetched version and rebases the unsynced local changes onto it.
+    Merge,
+    /// Applies the fetched version, the unsynced local changes are dropped.
+    Overwrite,
+}
+
+impl std::default::Default for FetchOverwritePolicy {
+    fn default() -> Self {
+        FetchOverwritePolicy::Preserve
+    }
+}
+
 pub struct ConflictOperations<T>(T);
 pub trait ConflictResolver<Operations>
 where
@@ -44,6 +63,14 @@ where
     resolver: Arc<dyn ConflictResolver<Operations> + Send + Sync>,
     rev_sink: Arc<dyn ConflictRevisionSink>,
     rev_manager: Arc<RevisionManager<Connection>>,
+    sync_loop: Mutex<SyncLoopDetector>,
+    sync_loop_observer: Option<Arc<dyn SyncLoopObserver>>,
+    /// The revision that wasn't sent because it tripped the sync loop detector.
+    unsent_revisions: Mutex<Vec<Revision>>,
+    /// The revisions pushed by the server while the sync loop detector is tripped, they're
+    /// applied once the sync resumes.
+    queued_revisions: Mutex<Vec<Vec<Revision>>>,
+    overwrite_policy: FetchOverwritePolicy,
 }
 
 impl<Operations, Connection> ConflictController<Operations, Connection>
@@ -63,7 +90,52 @@ where
             resolver,
             rev_sink,
             rev_manager,
+            sync_loop: Mutex::new(SyncLoopDetector::new(SyncLoopConfiguration::default())),
+            sync_loop_observer: None,
+            unsent_revisions: Mutex::new(vec![]),
+            queued_revisions: Mutex::new(vec![]),
+            overwrite_policy: FetchOverwritePolicy::Overwrite,
+        }
+    }
+
+    /// Decides whether the server's revisions that replace the object drop its unsynced
+    /// revisions, they're dropped by default.
+    pub fn with_overwrite_policy(mut self, policy: FetchOverwritePolicy) -> Self {
+        self.overwrite_policy = policy;
+        self
+    }
+
+    pub fn with_sync_loop_configuration(self, configuration: SyncLoopConfiguration) -> Self {
+        *self.sync_loop.lock().unwrap() = SyncLoopDetector::new(configuration);
+        self
+    }
+
+    pub fn with_sync_loop_observer(mut self, observer: Arc<dyn SyncLoopObserver>) -> Self {
+        self.sync_loop_observer = Some(observer);
+        self
+    }
+
+    /// Returns true if a sync loop was detected. The revisions pushed by the server are queued
+    /// and nothing is sent back until `resume_sync` is called.
+    pub fn is_sync_loop_detected(&self) -> bool {
+        self.sync_loop.lock().unwrap().is_tripped()
+    }
+
+    /// Returns false if the sync loop detector is tripped, the revision must not be applied or
+    /// sent then.
+    fn record_sync_revision(&self, revision: &Revision, author: RevisionAuthor) -> bool {
+        let mut sync_loop = self.sync_loop.lock().unwrap();
+        if sync_loop.record(&revision.md5, author) {
+            tracing::error!(
+                "Detect the sync loop of {} at the revision:{}, stop sending its revisions",
+                revision.object_id,
+                revision.rev_id
+            );
+            if let Some(observer) = self.sync_loop_observer.as_ref() {
+                observer.did_detect_sync_loop(&revision.object_id, &revision.md5);
+            }
         }
+        !sync_loop.is_tripped()
     }
 }
 
@@ -77,15 +149,40 @@ where
             return Ok(());
         }
 
+        if !self.record_sync_revision(revisions.last().unwrap(), RevisionAuthor::Remote) {
+            self.queued_revisions.lock().unwrap().push(revisions);
+            return Ok(());
+        }
         match self.handle_revision(revisions).await? {
             None => {}
             Some(server_revision) => {
-                self.rev_sink.send(vec![server_revision]).await?;
+                if self.record_sync_revision(&server_revision, RevisionAuthor::Local) {
+                    self.rev_sink.send(vec![server_revision]).await?;
+                } else {
+                    // It's already applied, only sending it is held back.
+                    self.unsent_revisions.lock().unwrap().push(server_revision);
+                }
             }
         }
         Ok(())
     }
 
+    /// Resumes the sync after a sync loop was detected. The revision that tripped the detector
+    /// is sent, then the revisions that the server pushed meanwhile are applied in order. They
+    /// trip the detector again if the loop goes on.
+    pub async fn resume_sync(&self) -> FlowyResult<()> {
+        self.sync_loop.lock().unwrap().resume();
+        let unsent_revisions = std::mem::take(&mut *self.unsent_revisions.lock().unwrap());
+        if !unsent_revisions.is_empty() {
+            self.rev_sink.send(unsent_revisions).await?;
+        }
+        let queued_revisions = std::mem::take(&mut *self.queued_revisions.lock().unwrap());
+        for revisions in queued_revisions {
+            self.receive_revisions(revisions).await?;
+        }
+        Ok(())
+    }
+
     pub async fn ack_revision(&self, rev_id: i64) -> FlowyResult<()> {
         self.rev_sink.ack(rev_id).await?;
         Ok(())
@@ -121,6 +218,19 @@ where
             None => {
                 // The server_prime is None means the client local revisions conflict with the
                 // // server, and it needs to override the client delta.
+                if let Some(rev_id) = self.rev_manager.next_sync_rev_id().await {
+                    // The local object is empty, so there is nothing to rebase onto the server's
+                    // revisions. Unless they're overwritten, the unsynced revisions are kept and
+                    // the server merges them once they're pushed.
+                    if self.overwrite_policy != FetchOverwritePolicy::Overwrite {
+                        tracing::warn!(
+                            "Keep {} with the unsynced revision: {}, ignore the server's revisions",
+                            self.rev_manager.object_id,
+                            rev_id
+                        );
+                        return Ok(None);
+                    }
+                }
                 let md5 = self.resolver.reset_operations(client_operations).await?;
                 debug_assert!(md5.is_equal(&revisions.last().unwrap().md5));
                 self.rev_manager.reset_object(revisions).await?;
@@ -0,0 +1,44 @@
+use std::future::Future;
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+
+/// Runs the background tasks of the revisions, e.g. the queue of the local revisions, the
+/// deferred save and the web socket sink.
+///
+/// The host application that embeds the core passes `Executor::Handle` to keep these tasks on
+/// its own runtime. Shutting down that runtime drops the tasks that are still running.
+#[derive(Clone, Debug, Default)]
+pub enum Executor {
+    /// Spawns the tasks with `tokio::spawn`, it must be called within a tokio runtime.
+    #[default]
+    Current,
+    Handle(Handle),
+}
+
+impl Executor {
+    pub fn from_handle(handle: Handle) -> Self {
+        Executor::Handle(handle)
+    }
+
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self {
+            Executor::Current => tokio::spawn(future),
+            Executor::Handle(handle) => handle.spawn(future),
+        }
+    }
+
+    pub fn spawn_blocking<F, R>(&self, f: F) -> JoinHandle<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        match self {
+            Executor::Current => tokio::task::spawn_blocking(f),
+            Executor::Handle(handle) => handle.spawn_blocking(f),
+        }
+    }
+}
@@ -1,14 +1,30 @@
 mod cache;
 mod conflict_resolve;
+mod executor;
+mod priority;
+mod rev_lifecycle;
 mod rev_manager;
 mod rev_persistence;
 mod rev_queue;
 mod rev_snapshot;
+mod rev_state;
+mod save_debounce;
+mod sync_loop;
+mod sync_plan;
 mod ws_manager;
+mod ws_session;
 
 pub use cache::*;
 pub use conflict_resolve::*;
+pub use executor::*;
+pub use priority::*;
+pub use rev_lifecycle::*;
 pub use rev_manager::*;
 pub use rev_persistence::*;
 pub use rev_snapshot::*;
+pub use rev_state::*;
+pub use save_debounce::*;
+pub use sync_loop::*;
+pub use sync_plan::*;
 pub use ws_manager::*;
+pub use ws_session::*;
@@ -0,0 +1,255 @@
+use crate::Executor;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinError;
+use tokio::time::Instant;
+
+/// Decides which of the waiting blocking tasks runs first, see `PriorityScheduler`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskPriority {
+    /// The reads and writes of the focused object, the user waits for them.
+    Foreground,
+    /// The work that nobody waits for, e.g. the exports and the objects that aren't focused.
+    Background,
+}
+
+#[derive(Debug, Clone)]
+pub struct PriorityConfiguration {
+    /// The number of the blocking tasks that run at a time.
+    pub max_concurrency: usize,
+    /// The share of `max_concurrency` that the background tasks may use while a foreground task
+    /// is waiting or running. At least one background task runs.
+    pub background_share: f32,
+    /// A background task that has waited this long runs before the foreground tasks that come
+    /// after it, so a stream of foreground tasks can't starve it.
+    pub max_background_wait: Duration,
+}
+
+impl std::default::Default for PriorityConfiguration {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 4,
+            background_share: 0.25,
+            max_background_wait: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    focused: Option<String>,
+    running: usize,
+    running_background: usize,
+    running_foreground: usize,
+    waiting_foreground: usize,
+    /// The background tasks that have waited for `max_background_wait`.
+    waiting_aged: usize,
+}
+
+struct SchedulerInner {
+    configuration: PriorityConfiguration,
+    state: Mutex<SchedulerState>,
+    released: Notify,
+}
+
+impl SchedulerInner {
+    /// The state is only counters that are updated without panicking in between, so it's still
+    /// consistent if a thread panicked while holding the lock.
+    fn state(&self) -> MutexGuard<'_, SchedulerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Runs the blocking tasks of the objects, e.g. the reads and the writes of the revisions, in two
+/// levels of priority. The tasks of the focused object are foreground tasks: they run before the waiting
+/// background tasks, and the background tasks are capped to `background_share` while any of
+/// them is pending. A long background task, e.g. an export, runs each of its batches as a task
+/// of its own, which lets the foreground tasks in between the batches.
+///
+/// The scheduler is cloned to share it, all the clones run their tasks within the same
+/// `max_concurrency`.
+#[derive(Clone)]
+pub struct PriorityScheduler {
+    inner: Arc<SchedulerInner>,
+}
+
+impl std::fmt::Debug for PriorityScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PriorityScheduler")
+            .field("configuration", &self.inner.configuration)
+            .field("focused", &self.focused())
+            .finish()
+    }
+}
+
+impl std::default::Default for PriorityScheduler {
+    fn default() -> Self {
+        Self::new(PriorityConfiguration::default())
+    }
+}
+
+impl PriorityScheduler {
+    pub fn new(configuration: PriorityConfiguration) -> Self {
+        Self {
+            inner: Arc::new(SchedulerInner {
+                configuration,
+                state: Mutex::new(SchedulerState::default()),
+                released: Notify::new(),
+            }),
+        }
+    }
+
+    /// Makes the tasks of the object foreground tasks, the tasks of the previously focused
+    /// object become background tasks. Pass None when no object is focused.
+    pub fn set_focused(&self, object_id: Option<&str>) {
+        self.inner.state().focused = object_id.map(|object_id| object_id.to_owned());
+    }
+
+    pub fn focused(&self) -> Option<String> {
+        self.inner.state().focused.clone()
+    }
+
+    pub fn priority_of(&self, object_id: &str) -> TaskPriority {
+        match self.inner.state().focused.as_deref() {
+            Some(focused) if focused == object_id => TaskPriority::Foreground,
+            _ => TaskPriority::Background,
+        }
+    }
+
+    /// Runs `f` on the blocking threads of the `executor` once the scheduler lets a task of the
+    /// `priority` run, e.g. the reads and the writes of the revisions.
+    pub async fn spawn_blocking<F, R>(&self, executor: &Executor, priority: TaskPriority, f: F) -> Result<R, JoinError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let permit = self.acquire(priority).await;
+        executor
+            .spawn_blocking(move || {
+                let _permit = permit;
+                f()
+            })
+            .await
+    }
+
+    /// Waits until a task of the `priority` may run. The task runs until the permit is dropped.
+    pub async fn acquire(&self, priority: TaskPriority) -> PriorityPermit {
+        let start = Instant::now();
+        let mut waiting = WaitingGuard {
+            inner: &self.inner,
+            foreground: priority == TaskPriority::Foreground,
+            aged: false,
+        };
+        if waiting.foreground {
+            self.inner.state().waiting_foreground += 1;
+        }
+        let max_wait = self.inner.configuration.max_background_wait;
+        loop {
+            // Created before the check, so a release right after the check isn't missed.
+            let released = self.inner.released.notified();
+            {
+                let mut state = self.inner.state();
+                if !waiting.foreground && !waiting.aged && start.elapsed() >= max_wait {
+                    waiting.aged = true;
+                    state.waiting_aged += 1;
+                }
+                if self.try_acquire(&mut state, priority, waiting.aged) {
+                    drop(state);
+                    drop(waiting);
+                    return PriorityPermit {
+                        inner: self.inner.clone(),
+                        priority,
+                    };
+                }
+            }
+
+            let rest = max_wait.saturating_sub(start.elapsed());
+            if waiting.foreground || waiting.aged || rest.is_zero() {
+                released.await;
+            } else {
+                let _ = tokio::time::timeout(rest, released).await;
+            }
+        }
+    }
+
+    fn try_acquire(&self, state: &mut SchedulerState, priority: TaskPriority, aged: bool) -> bool {
+        let max_concurrency = self.inner.configuration.max_concurrency.max(1);
+        if state.running >= max_concurrency {
+            return false;
+        }
+        match priority {
+            TaskPriority::Foreground => {
+                // The aged background tasks go first, the foreground task takes the next slot.
+                if state.waiting_aged > 0 {
+                    return false;
+                }
+                state.running_foreground += 1;
+            }
+            TaskPriority::Background => {
+                if !aged {
+                    let free = max_concurrency - state.running;
+                    if state.waiting_foreground >= free || state.running_background >= self.background_limit(state) {
+                        return false;
+                    }
+                }
+                state.running_background += 1;
+            }
+        }
+        state.running += 1;
+        true
+    }
+
+    fn background_limit(&self, state: &SchedulerState) -> usize {
+        let configuration = &self.inner.configuration;
+        let max_concurrency = configuration.max_concurrency.max(1);
+        if state.waiting_foreground + state.running_foreground > 0 {
+            ((max_concurrency as f32 * configuration.background_share) as usize).max(1)
+        } else {
+            // Keeps a slot free for the next foreground task, it doesn't wait for a background
+            // task to finish then.
+            (max_concurrency - 1).max(1)
+        }
+    }
+}
+
+/// Removes the task from the waiting ones when it gets a permit or stops waiting.
+struct WaitingGuard<'a> {
+    inner: &'a SchedulerInner,
+    foreground: bool,
+    aged: bool,
+}
+
+impl<'a> Drop for WaitingGuard<'a> {
+    fn drop(&mut self) {
+        let mut state = self.inner.state();
+        if self.foreground {
+            state.waiting_foreground -= 1;
+        }
+        if self.aged {
+            state.waiting_aged -= 1;
+        }
+        drop(state);
+        // The background tasks that were held back by this task may run now.
+        self.inner.released.notify_waiters();
+    }
+}
+
+/// Lets a task run until it's dropped, see `PriorityScheduler::acquire`.
+pub struct PriorityPermit {
+    inner: Arc<SchedulerInner>,
+    priority: TaskPriority,
+}
+
+impl Drop for PriorityPermit {
+    fn drop(&mut self) {
+        let mut state = self.inner.state();
+        state.running -= 1;
+        match self.priority {
+            TaskPriority::Foreground => state.running_foreground -= 1,
+            TaskPriority::Background => state.running_background -= 1,
+        }
+        drop(state);
+        self.inner.released.notify_waiters();
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// The number of revisions whose lifecycle is kept, the oldest revision is forgotten first.
+pub const MAX_LIFECYCLE_REVISIONS: usize = 128;
+
+/// The number of events kept for each revision, e.g. a revision that fails to send again and
+/// again only keeps its latest events.
+pub const MAX_LIFECYCLE_EVENTS: usize = 16;
+
+/// A transition of a local revision on its way to the server, see
+/// `RevisionManager::revision_lifecycle`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevLifecycleEvent {
+    /// The revision was made from a local change.
+    Created,
+    /// The revision was saved and waits to be pushed.
+    Pending,
+    /// The revision was merged into the revision `into` before it was pushed.
+    Merged {
+        into: i64,
+    },
+    /// The revision was handed to the web socket.
+    Pushed,
+    /// Sending the revision failed, it stays in the sync sequence and is sent again.
+    Failed(String),
+    /// The revision wasn't acked after it was sent `attempts` times. It was taken out of the
+    /// sync sequence but stays unacked.
+    GaveUp {
+        attempts: usize,
+    },
+    /// The revision didn't compose onto its base, it was taken out of the sync sequence
+    /// without being sent. See `RevisionPersistenceConfiguration::with_verify_before_push`.
+    Invalid(String),
+    Acked,
+}
+
+/// Keeps the recent transitions of the local revisions in memory for debugging the sync.
+/// Nothing is written to disk, the lifecycle is gone once the object is closed.
+#[derive(Default)]
+pub(crate) struct RevisionLifecycle {
+    events: Mutex<BTreeMap<i64, VecDeque<(Instant, RevLifecycleEvent)>>>,
+}
+
+impl RevisionLifecycle {
+    pub(crate) fn record(&self, rev_id: i64, event: RevLifecycleEvent) {
+        tracing::trace!("Revision {} lifecycle: {:?}", rev_id, event);
+        let mut events = self.events.lock().unwrap();
+        let rev_events = events.entry(rev_id).or_default();
+        if rev_events.len() >= MAX_LIFECYCLE_EVENTS {
+            rev_events.pop_front();
+        }
+        rev_events.push_back((Instant::now(), event));
+
+        while events.len() > MAX_LIFECYCLE_REVISIONS {
+            let oldest_rev_id = *events.keys().next().unwrap();
+            events.remove(&oldest_rev_id);
+        }
+    }
+
+    pub(crate) fn get(&self, rev_id: i64) -> Option<Vec<(Instant, RevLifecycleEvent)>> {
+        let events = self.events.lock().unwrap();
+        events
+            .get(&rev_id)
+            .map(|rev_events| rev_events.iter().cloned().collect())
+    }
+}
@@ -1,17 +1,25 @@
 use crate::rev_queue::{RevCommand, RevCommandSender, RevQueue};
+use crate::sync_plan::make_sync_plan;
 use crate::{
-    RevisionPersistence, RevisionSnapshot, RevisionSnapshotController, RevisionSnapshotDiskCache,
-    WSDataProviderDataSource,
+    CompactionEstimate, ErrorReporter, Executor, RevLifecycleEvent, RevisionCacheState, RevisionHeadState,
+    RevisionPersistence, RevisionSnapshot, RevisionSnapshotController, RevisionSnapshotDiskCache, SyncPlan,
+    WSDataProviderDataSource, RECOVERED_BACKUP_VERSION,
 };
 use bytes::Bytes;
 use flowy_error::{internal_error, FlowyError, FlowyResult};
 use flowy_http_model::revision::{Revision, RevisionRange};
 use flowy_http_model::util::md5;
+use flowy_revision_persistence::{RevisionState, SyncRecord};
 use lib_infra::future::FutureResult;
+use std::convert::TryFrom;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::Ordering::SeqCst;
-use std::sync::Arc;
-use tokio::sync::{mpsc, oneshot};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+/// Composing the revisions longer than this suggests generating a snapshot of the object.
+pub const COMPOSE_SNAPSHOT_THRESHOLD_IN_MILLIS: u64 = 200;
 
 pub trait RevisionCloudService: Send + Sync {
     /// Read the object's revision from remote
@@ -22,6 +30,12 @@ pub trait RevisionCloudService: Send + Sync {
     /// * `object_id`: the id of the object
     ///
     fn fetch_object(&self, user_id: &str, object_id: &str) -> FutureResult<Vec<Revision>, FlowyError>;
+
+    /// Read the rev_id of the object's latest revision from remote
+    /// Returns None if the remote doesn't know the object or can't be reached
+    fn fetch_object_rev_id(&self, _user_id: &str, _object_id: &str) -> FutureResult<Option<i64>, FlowyError> {
+        FutureResult::new(async { Ok(None) })
+    }
 }
 
 pub trait RevisionObjectDeserializer: Send + Sync {
@@ -35,9 +49,26 @@ pub trait RevisionObjectDeserializer: Send + Sync {
     ///
     fn deserialize_revisions(object_id: &str, revisions: Vec<Revision>) -> FlowyResult<Self::Output>;
 
+    /// Same as `deserialize_revisions`, and passes the revision that fails to deserialize or
+    /// compose to the `observer`. The objects that can't tell which revision failed don't
+    /// override it and never call the `observer`.
+    fn deserialize_revisions_with_observer(
+        object_id: &str,
+        revisions: Vec<Revision>,
+        _observer: &dyn ComposeErrorObserver,
+    ) -> FlowyResult<Self::Output> {
+        Self::deserialize_revisions(object_id, revisions)
+    }
+
     fn recover_operations_from_revisions(revisions: Vec<Revision>) -> Option<Self::Output>;
 }
 
+/// Receives the raw bytes of the revision that fails to deserialize or compose when the object
+/// is opened, e.g. for a repair tool to export them. It doesn't change how the failure is handled.
+pub trait ComposeErrorObserver: Send + Sync {
+    fn did_fail(&self, rev_id: i64, bytes: &[u8], error: &FlowyError);
+}
+
 pub trait RevisionObjectSerializer: Send + Sync {
     /// Serialize a list of revisions into one in `Bytes` format
     ///
@@ -68,6 +99,44 @@ pub trait RevisionMergeable: Send + Sync {
     }
 
     fn combine_revisions(&self, revisions: Vec<Revision>) -> FlowyResult<Bytes>;
+
+    /// Combines all the revisions of the object into the data of a snapshot. The snapshot is
+    /// kept as is, so it's worth making it as small as possible, e.g. by merging the operations
+    /// of different revisions that can be merged.
+    fn combine_snapshot(&self, revisions: Vec<Revision>) -> FlowyResult<Bytes> {
+        self.combine_revisions(revisions)
+    }
+
+    /// Checks that the `revision` applies to the object built from the `base_revisions`, i.e.
+    /// the revisions up to its `base_rev_id`. Called before the revision is pushed if
+    /// `RevisionPersistenceConfiguration::with_verify_before_push` is set. Every revision
+    /// passes by default.
+    fn verify_revision(&self, _base_revisions: Vec<Revision>, _revision: &Revision) -> FlowyResult<()> {
+        Ok(())
+    }
+}
+
+/// Measures the last time the object was built by composing all its local revisions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComposeStats {
+    pub duration: Duration,
+    pub num_of_revisions: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum RevisionManagerEvent {
+    /// Composing the revisions took longer than the compose threshold, a snapshot would
+    /// make opening the object faster.
+    SnapshotSuggested { object_id: String, stats: ComposeStats },
+    /// The revision wasn't acked after `max_push_attempts`, the revisions behind it are held
+    /// back with it. See `RevisionManager::retry_dead_letters`.
+    PushGaveUp { object_id: String, rev_id: i64 },
+    /// The revision didn't compose onto its base, the push stopped at it. See
+    /// `RevisionPersistenceConfiguration::with_verify_before_push`.
+    InvalidRevision { object_id: String, rev_id: i64 },
+    /// The snapshot newer than the revisions couldn't be confirmed by the server, it was moved
+    /// to the `RECOVERED_BACKUP_VERSION` and the object was built from its revisions instead.
+    SnapshotDemoted { object_id: String, rev_id: i64 },
 }
 
 pub struct RevisionManager<Connection> {
@@ -80,6 +149,13 @@ pub struct RevisionManager<Connection> {
     #[cfg(feature = "flowy_unit_test")]
     rev_ack_notifier: tokio::sync::broadcast::Sender<i64>,
     rev_queue: RevCommandSender,
+    compose_threshold: Duration,
+    last_compose_stats: Option<ComposeStats>,
+    event_notifier: broadcast::Sender<RevisionManagerEvent>,
+    compose_error_observer: Option<Arc<dyn ComposeErrorObserver>>,
+    /// The object composed by the last `capture_state` or restored by `restore_state`, it's
+    /// reused while the rev_id doesn't change.
+    composed_head: tokio::sync::RwLock<Option<RevisionHeadState>>,
 }
 
 impl<Connection: 'static> RevisionManager<Connection> {
@@ -113,45 +189,127 @@ impl<Connection: 'static> RevisionManager<Connection> {
             rev_compress.clone(),
             receiver,
         );
-        tokio::spawn(queue.run());
+        rev_persistence.executor().spawn(queue.run());
+        let rev_snapshot = Arc::new(rev_snapshot);
+        if let Some(interval) = rev_persistence.expiry_sweep_interval() {
+            rev_persistence.executor().spawn(run_expiry_sweep(
+                interval,
+                Arc::downgrade(&rev_persistence),
+                Arc::downgrade(&rev_snapshot),
+                rev_compress.clone(),
+                rev_id_counter.clone(),
+            ));
+        }
         Self {
             object_id: object_id.to_string(),
             user_id: user_id.to_owned(),
             rev_id_counter,
             rev_persistence,
-            rev_snapshot: Arc::new(rev_snapshot),
+            rev_snapshot,
             rev_compress,
             #[cfg(feature = "flowy_unit_test")]
             rev_ack_notifier: tokio::sync::broadcast::channel(1).0,
             rev_queue,
+            compose_threshold: Duration::from_millis(COMPOSE_SNAPSHOT_THRESHOLD_IN_MILLIS),
+            last_compose_stats: None,
+            event_notifier: broadcast::channel(10).0,
+            compose_error_observer: None,
+            composed_head: tokio::sync::RwLock::new(None),
         }
     }
 
+    pub fn with_compose_threshold(mut self, threshold: Duration) -> Self {
+        self.compose_threshold = threshold;
+        self
+    }
+
+    pub fn with_compose_error_observer(mut self, observer: Arc<dyn ComposeErrorObserver>) -> Self {
+        self.compose_error_observer = Some(observer);
+        self
+    }
+
+    pub fn subscribe_event(&self) -> broadcast::Receiver<RevisionManagerEvent> {
+        self.event_notifier.subscribe()
+    }
+
+    /// Returns the stats of the last time the object was composed from its local revisions.
+    pub fn last_compose_stats(&self) -> Option<ComposeStats> {
+        self.last_compose_stats.clone()
+    }
+
     #[tracing::instrument(name = "revision_manager_initialize", level = "info", skip_all, fields(deserializer, object_id, deserialize_revisions) err)]
-    pub async fn initialize<B>(&mut self, _cloud: Option<Arc<dyn RevisionCloudService>>) -> FlowyResult<B::Output>
+    pub async fn initialize<B>(&mut self, cloud: Option<Arc<dyn RevisionCloudService>>) -> FlowyResult<B::Output>
     where
         B: RevisionObjectDeserializer,
     {
         let revision_records = self.rev_persistence.load_all_records(&self.object_id)?;
         tracing::Span::current().record("object_id", &self.object_id.as_str());
         tracing::Span::current().record("deserializer", &std::any::type_name::<B>());
-        let revisions: Vec<Revision> = revision_records.iter().map(|record| record.revision.clone()).collect();
+        let revisions = composable_revisions(&revision_records);
         tracing::Span::current().record("deserialize_revisions", &revisions.len());
         let current_rev_id = revisions.last().as_ref().map(|revision| revision.rev_id).unwrap_or(0);
-        match B::deserialize_revisions(&self.object_id, revisions.clone()) {
+
+        // The snapshot can't be newer than the revisions unless the revisions were restored from
+        // an old backup. Only trust the snapshot if the server confirms its rev_id, otherwise it's
+        // demoted to a version the user can still restore.
+        let mut use_snapshot = true;
+        if let Ok(Some(snapshot)) = self.rev_snapshot.read_last_snapshot() {
+            if snapshot.rev_id > current_rev_id {
+                if self.is_snapshot_confirmed(cloud.as_ref(), snapshot.rev_id).await {
+                    if let Some((object, snapshot_rev)) = self.rev_snapshot.restore_from_snapshot::<B>(snapshot.rev_id)
+                    {
+                        self.reset_to_snapshot(&revisions, snapshot_rev).await?;
+                        self.rev_id_counter.set(snapshot.rev_id);
+                        return Ok(object);
+                    }
+                } else {
+                    tracing::warn!(
+                        "Ignore the snapshot: {} of {}, rebuild from the revisions: {}",
+                        snapshot.rev_id,
+                        self.object_id,
+                        current_rev_id
+                    );
+                    self.demote_snapshot(&snapshot);
+                    use_snapshot = false;
+                }
+            }
+        }
+
+        let num_of_revisions = revisions.len();
+        let compose_start = Instant::now();
+        let result = match &self.compose_error_observer {
+            None => B::deserialize_revisions(&self.object_id, revisions.clone()),
+            Some(observer) => {
+                B::deserialize_revisions_with_observer(&self.object_id, revisions.clone(), observer.as_ref())
+            }
+        };
+        self.record_compose_stats(ComposeStats {
+            duration: compose_start.elapsed(),
+            num_of_revisions,
+        });
+
+        match result {
             Ok(object) => {
-                self.rev_persistence.sync_revision_records(&revision_records).await?;
+                if let Some(rev_id) = self.rev_persistence.sync_revision_records(&revision_records).await? {
+                    let _ = self.event_notifier.send(RevisionManagerEvent::InvalidRevision {
+                        object_id: self.object_id.clone(),
+                        rev_id,
+                    });
+                }
                 self.rev_id_counter.set(current_rev_id);
                 Ok(object)
             }
-            Err(e) => match self.rev_snapshot.restore_from_snapshot::<B>(current_rev_id) {
+            Err(e) => match use_snapshot
+                .then(|| self.rev_snapshot.restore_from_snapshot::<B>(current_rev_id))
+                .flatten()
+            {
                 None => {
                     tracing::info!("Restore object from validation revisions");
                     B::recover_operations_from_revisions(revisions).ok_or(e)
                 }
                 Some((object, snapshot_rev)) => {
                     let snapshot_rev_id = snapshot_rev.rev_id;
-                    let _ = self.rev_persistence.reset(vec![snapshot_rev]).await;
+                    self.reset_to_snapshot(&revisions, snapshot_rev).await?;
                     // revision_records.retain(|record| record.revision.rev_id <= snapshot_rev_id);
                     // let _ = self.rev_persistence.sync_revision_records(&revision_records).await?;
                     self.rev_id_counter.set(snapshot_rev_id);
@@ -161,12 +319,113 @@ impl<Connection: 'static> RevisionManager<Connection> {
         }
     }
 
+    /// Replaces the revisions of the object with the `snapshot_rev`. The replaced `revisions` are
+    /// backed up first, nothing is replaced if the backup fails.
+    async fn reset_to_snapshot(&self, revisions: &[Revision], snapshot_rev: Revision) -> FlowyResult<()> {
+        tracing::warn!(
+            "Reset {} to the snapshot: {}, back up {} revisions",
+            self.object_id,
+            snapshot_rev.rev_id,
+            revisions.len()
+        );
+        self.rev_persistence.backup(revisions)?;
+        self.rev_persistence.reset(vec![snapshot_rev]).await
+    }
+
+    fn demote_snapshot(&self, snapshot: &RevisionSnapshot) {
+        match self.rev_snapshot.demote_snapshot(snapshot, RECOVERED_BACKUP_VERSION) {
+            Ok(_) => {
+                let _ = self.event_notifier.send(RevisionManagerEvent::SnapshotDemoted {
+                    object_id: self.object_id.clone(),
+                    rev_id: snapshot.rev_id,
+                });
+            }
+            Err(e) => tracing::error!(
+                "Demote the snapshot: {} of {} failed: {}",
+                snapshot.rev_id,
+                self.object_id,
+                e
+            ),
+        }
+    }
+
+    fn record_compose_stats(&mut self, stats: ComposeStats) {
+        if stats.duration > self.compose_threshold {
+            tracing::info!(
+                "Composing {} revisions of {} took {:?}, suggest generating a snapshot",
+                stats.num_of_revisions,
+                self.object_id,
+                stats.duration
+            );
+            let _ = self.event_notifier.send(RevisionManagerEvent::SnapshotSuggested {
+                object_id: self.object_id.clone(),
+                stats: stats.clone(),
+            });
+        }
+        self.last_compose_stats = Some(stats);
+    }
+
+    async fn is_snapshot_confirmed(&self, cloud: Option<&Arc<dyn RevisionCloudService>>, snapshot_rev_id: i64) -> bool {
+        let cloud = match cloud {
+            None => {
+                tracing::warn!(
+                    "Can't confirm the snapshot: {} of {} offline",
+                    snapshot_rev_id,
+                    self.object_id
+                );
+                return false;
+            }
+            Some(cloud) => cloud,
+        };
+
+        match cloud.fetch_object_rev_id(&self.user_id, &self.object_id).await {
+            Ok(Some(server_rev_id)) if server_rev_id >= snapshot_rev_id => {
+                tracing::info!(
+                    "The server confirms the snapshot: {} of {}",
+                    snapshot_rev_id,
+                    self.object_id
+                );
+                true
+            }
+            Ok(server_rev_id) => {
+                tracing::warn!(
+                    "The server rejects the snapshot: {} of {}, server rev_id: {:?}",
+                    snapshot_rev_id,
+                    self.object_id,
+                    server_rev_id
+                );
+                false
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Can't confirm the snapshot: {} of {}: {}",
+                    snapshot_rev_id,
+                    self.object_id,
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    /// Writes the pending revisions to disk, so nothing is lost if the runtime of the executor
+    /// shuts down right after closing.
     pub async fn close(&self) {
+        if let Err(e) = self.flush().await {
+            tracing::error!("{} flush revisions failed: {:?}", self.object_id, e);
+        }
         let _ = self.rev_persistence.compact_lagging_revisions(&self.rev_compress).await;
     }
 
+    /// Writes the revisions that are waiting for the deferred save to disk right away.
+    pub async fn flush(&self) -> FlowyResult<()> {
+        self.rev_persistence.flush().await
+    }
+
     pub async fn generate_snapshot(&self) {
-        self.rev_snapshot.generate_snapshot().await;
+        if let Err(e) = self.rev_snapshot.generate_snapshot().await {
+            tracing::error!("{} generate snapshot failed: {:?}", self.object_id, e);
+        }
     }
 
     pub async fn read_snapshot(&self, rev_id: Option<i64>) -> FlowyResult<Option<RevisionSnapshot>> {
@@ -196,6 +455,64 @@ impl<Connection: 'static> RevisionManager<Connection> {
         Ok(())
     }
 
+    /// Captures the revisions in memory, the ones that aren't written to disk yet and the ones
+    /// that wait to be synced, e.g. before the app gets suspended. The object composed from the
+    /// revisions is captured with them, it's composed only if the rev_id changed since the last
+    /// capture. See `restore_state`.
+    pub async fn capture_state(&self) -> FlowyResult<RevisionCacheState> {
+        let mut state = self.rev_persistence.capture_state(self.rev_id()).await;
+        state.head = Some(self.compose_head(state.rev_id).await?);
+        Ok(state)
+    }
+
+    async fn compose_head(&self, rev_id: i64) -> FlowyResult<RevisionHeadState> {
+        let mut composed_head = self.composed_head.write().await;
+        if let Some(head) = composed_head.as_ref().filter(|head| head.rev_id == rev_id) {
+            return Ok(head.clone());
+        }
+
+        // The revisions added after the rev_id was read aren't part of the state.
+        let revisions = composable_revisions(&self.rev_persistence.current_records()?)
+            .into_iter()
+            .filter(|revision| revision.rev_id <= rev_id)
+            .collect::<Vec<Revision>>();
+        let head = RevisionHeadState {
+            rev_id,
+            bytes: self.rev_compress.combine_snapshot(revisions)?.to_vec(),
+        };
+        *composed_head = Some(head.clone());
+        Ok(head)
+    }
+
+    /// Builds the object from the head of the state captured by `capture_state` instead of
+    /// composing its revisions, then restores the state. It's called instead of `initialize`.
+    #[tracing::instrument(level = "debug", skip(self, state), err)]
+    pub async fn initialize_from_state<B>(&mut self, state: RevisionCacheState) -> FlowyResult<B::Output>
+    where
+        B: RevisionObjectDeserializer,
+    {
+        let head = state
+            .head
+            .clone()
+            .ok_or_else(|| FlowyError::invalid_data().context("Invalid revision state: it has no head"))?;
+        let revision = Revision::new(&self.object_id, 0, head.rev_id, Bytes::from(head.bytes), "".to_owned());
+        let object = B::deserialize_revisions(&self.object_id, vec![revision])?;
+        self.restore_state(state).await?;
+        Ok(object)
+    }
+
+    /// Restores the state captured by `capture_state` of the same object, the restored revisions
+    /// are read from memory instead of disk. The state is validated before anything is changed.
+    #[tracing::instrument(level = "debug", skip(self, state), err)]
+    pub async fn restore_state(&self, state: RevisionCacheState) -> FlowyResult<()> {
+        let rev_id = state.rev_id();
+        let head = state.head.clone();
+        self.rev_persistence.restore_state(state).await?;
+        self.rev_id_counter.set(rev_id);
+        *self.composed_head.write().await = head;
+        Ok(())
+    }
+
     #[tracing::instrument(level = "debug", skip(self, revision), err)]
     pub async fn add_remote_revision(&self, revision: &Revision) -> Result<(), FlowyError> {
         if revision.bytes.is_empty() {
@@ -222,6 +539,31 @@ impl<Connection: 'static> RevisionManager<Connection> {
         rx.await.map_err(internal_error)?
     }
 
+    /// Same as `add_local_revision`, but the revision is written to disk with the `records` of
+    /// other objects in one transaction, so either all of them are saved or none is.
+    pub async fn add_local_revision_with_records(
+        &self,
+        data: Bytes,
+        object_md5: String,
+        records: Vec<SyncRecord>,
+    ) -> Result<i64, FlowyError> {
+        if data.is_empty() {
+            return Err(FlowyError::internal().context("The data of the revisions is empty"));
+        }
+        self.rev_snapshot.generate_snapshot_if_need();
+        let (ret, rx) = oneshot::channel();
+        self.rev_queue
+            .send(RevCommand::RevisionDataWithRecords {
+                data,
+                object_md5,
+                records,
+                ret,
+            })
+            .await
+            .map_err(internal_error)?;
+        rx.await.map_err(internal_error)?
+    }
+
     #[tracing::instrument(level = "debug", skip(self), err)]
     pub async fn ack_revision(&self, rev_id: i64) -> Result<(), FlowyError> {
         if self.rev_persistence.ack_revision(rev_id).await.is_ok() {
@@ -231,6 +573,197 @@ impl<Connection: 'static> RevisionManager<Connection> {
         Ok(())
     }
 
+    /// Runs the `callback` once the revision is acked, or right away if it's acked already. It
+    /// runs on the blocking threads of the executor, so a slow callback doesn't hold up the
+    /// acks. The callback of a revision that is merged into another before it's acked never runs.
+    pub async fn on_ack<F>(&self, rev_id: i64, callback: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.rev_persistence.on_ack(rev_id, Box::new(callback)).await
+    }
+
+    /// Same as `ack_revision` but the ack is written to disk before returning, so it
+    /// survives a crash right after the call.
+    #[tracing::instrument(level = "debug", skip(self), err)]
+    pub async fn ack_and_persist(&self, rev_id: i64) -> FlowyResult<()> {
+        self.rev_persistence.ack_and_persist(rev_id).await?;
+        #[cfg(feature = "flowy_unit_test")]
+        let _ = self.rev_ack_notifier.send(rev_id);
+        Ok(())
+    }
+
+    /// Acks the revision with the rev_id that the server assigned to it. The revision is stored
+    /// and read back with the `canonical_rev_id` afterwards.
+    ///
+    /// Returns an error if a revision after it is still unacked, that revision is based on the
+    /// local rev_id that the server doesn't know.
+    #[tracing::instrument(level = "debug", skip(self), err)]
+    pub async fn ack_revision_with_canonical_id(&self, rev_id: i64, canonical_rev_id: i64) -> FlowyResult<()> {
+        self.rev_persistence
+            .ack_with_canonical_rev_id(rev_id, canonical_rev_id)
+            .await?;
+        // Nothing is unacked after it, so the acked revision is the latest one and the new local
+        // revisions are based on the rev_id assigned by the server.
+        self.rev_id_counter.set(canonical_rev_id);
+        #[cfg(feature = "flowy_unit_test")]
+        let _ = self.rev_ack_notifier.send(canonical_rev_id);
+        Ok(())
+    }
+
+    /// Replaces the `superseded` revisions with a new revision that contains the `resolved` data.
+    /// The new revision is based on the common ancestor of the `superseded` revisions, which
+    /// are marked as resolved so that they're neither synced nor composed anymore.
+    ///
+    /// Every revision after the common ancestor must be in the `superseded`, otherwise composing
+    /// the new revision on the ancestor would drop them. None of the `superseded` may be acked,
+    /// the server already has the acked revisions on top of the ancestor.
+    #[tracing::instrument(level = "debug", skip(self, resolved), err)]
+    pub async fn resolve_conflict(
+        &self,
+        resolved: Bytes,
+        object_md5: String,
+        superseded: &[i64],
+    ) -> FlowyResult<Revision> {
+        if superseded.is_empty() {
+            return Err(FlowyError::internal().context("Can't resolve the conflict without the superseded revisions"));
+        }
+
+        let mut base_rev_id = i64::MAX;
+        for rev_id in superseded {
+            match self.rev_persistence.get(*rev_id).await {
+                None => {
+                    return Err(FlowyError::record_not_found().context(format!("Can't find the revision: {}", rev_id)));
+                }
+                Some(record) if record.state == RevisionState::Resolved => {
+                    return Err(FlowyError::internal().context(format!("The revision: {} was resolved", rev_id)));
+                }
+                Some(record) if record.state == RevisionState::Ack => {
+                    return Err(FlowyError::internal().context(format!("The revision: {} was acked", rev_id)));
+                }
+                Some(record) => base_rev_id = base_rev_id.min(record.revision.base_rev_id),
+            }
+        }
+
+        for rev_id in (base_rev_id + 1)..=self.rev_id() {
+            if superseded.contains(&rev_id) {
+                continue;
+            }
+            if let Some(record) = self.rev_persistence.get(rev_id).await {
+                if record.state != RevisionState::Resolved {
+                    return Err(FlowyError::internal().context(format!(
+                        "The revision: {} is after the common ancestor: {} but not superseded",
+                        rev_id, base_rev_id
+                    )));
+                }
+            }
+        }
+
+        let (_, rev_id) = self.next_rev_id_pair();
+        let revision = Revision::new(&self.object_id, base_rev_id, rev_id, resolved, object_md5);
+        self.rev_persistence
+            .resolve_conflict(revision.clone(), superseded)
+            .await?;
+        Ok(revision)
+    }
+
+    /// Pins the revision, so it's never merged into another revision or deleted by the
+    /// compaction and the history limit. The revisions before it may still be merged with each
+    /// other, composing them still gives the object at the pinned revision.
+    ///
+    /// Resetting the object, e.g. restoring it from a snapshot, replaces the pinned revisions too.
+    pub async fn pin_revision(&self, rev_id: i64) -> FlowyResult<()> {
+        self.rev_persistence.pin(rev_id).await
+    }
+
+    pub async fn unpin_revision(&self, rev_id: i64) -> FlowyResult<()> {
+        self.rev_persistence.unpin(rev_id).await
+    }
+
+    /// Returns the rev_ids of the pinned revisions in ascending order
+    pub fn pinned_rev_ids(&self) -> FlowyResult<Vec<i64>> {
+        self.rev_persistence.pinned_rev_ids()
+    }
+
+    /// Names the revision with the `tag`, e.g. a release or a published version. The tagged
+    /// revision is pinned, see `pin_revision`. Tagging another revision with the same tag moves
+    /// the tag, unpinning the revision removes its tag.
+    pub async fn tag_revision(&self, rev_id: i64, tag: &str) -> FlowyResult<()> {
+        if tag.is_empty() {
+            return Err(FlowyError::invalid_data().context("The tag should not be empty"));
+        }
+        self.rev_persistence.tag(rev_id, tag).await
+    }
+
+    /// Returns the revisions that were replaced the last time the object was restored from its
+    /// snapshot, in ascending order of the rev_ids. They're kept until the next restore.
+    pub fn read_revision_backup(&self) -> FlowyResult<Vec<Revision>> {
+        self.rev_persistence.revision_backup()
+    }
+
+    /// Returns the snapshots that were demoted because the server couldn't confirm them, see
+    /// `RevisionSnapshotDiskCache::demote_snapshot`.
+    pub fn read_demoted_snapshots(&self) -> FlowyResult<Vec<(String, RevisionSnapshot)>> {
+        self.rev_snapshot.read_demoted_snapshots()
+    }
+
+    /// Returns the tags and the rev_ids of the tagged revisions in ascending order of the rev_id
+    pub fn revision_tags(&self) -> FlowyResult<Vec<(String, i64)>> {
+        self.rev_persistence.revision_tags()
+    }
+
+    /// Removes the history that is older than the `age`, e.g. to follow a retention policy. The
+    /// revisions written before the cutoff are merged into one revision, which holds the object
+    /// as of the cutoff, so composing the revisions still gives the current object. The purge
+    /// stops at the first revision that isn't synced or is pinned, it and the revisions after it
+    /// are kept. Returns the number of the deleted revisions.
+    ///
+    /// It fails if the disk cache doesn't keep the time of the revisions, see
+    /// `RevisionDiskCache::read_revision_timestamps`.
+    pub async fn purge_older_than(&self, age: Duration) -> FlowyResult<usize> {
+        let age = i64::try_from(age.as_secs()).unwrap_or(i64::MAX);
+        let cutoff = self.rev_persistence.now().saturating_sub(age);
+        self.rev_persistence.purge_before(cutoff, &self.rev_compress).await
+    }
+
+    /// Sets the time in seconds that the revision expires at, e.g. for the revisions of an
+    /// ephemeral session. None keeps it for good. See `sweep_expired_revisions`.
+    pub async fn set_revision_expiry(&self, rev_id: i64, expires_at: Option<i64>) -> FlowyResult<()> {
+        self.rev_persistence.set_expiry(rev_id, expires_at).await
+    }
+
+    /// Merges the expired revisions into one, so their history is gone but the content is kept.
+    /// A snapshot of the object is generated first unless the last one is up to date. Like
+    /// `purge_older_than`, it stops at the first revision that isn't expired, synced or pinned.
+    /// Returns the number of the deleted revisions.
+    ///
+    /// It runs periodically if the object is configured `with_expiry_sweep`.
+    pub async fn sweep_expired_revisions(&self) -> FlowyResult<usize> {
+        sweep_expired_revisions(
+            &self.rev_persistence,
+            &self.rev_snapshot,
+            &self.rev_compress,
+            self.rev_id(),
+        )
+        .await
+    }
+
+    /// Returns the rev_id assigned by the server for the local rev_id
+    pub fn canonical_rev_id(&self, rev_id: i64) -> i64 {
+        self.rev_persistence.canonical_rev_id(rev_id)
+    }
+
+    /// The executor of the object's background tasks, the editors spawn their own tasks with it.
+    pub fn executor(&self) -> &Executor {
+        self.rev_persistence.executor()
+    }
+
+    /// Receives the failures of the object's background tasks, the web socket of the object
+    /// reports its failures to it too.
+    pub fn error_reporter(&self) -> Option<Arc<dyn ErrorReporter>> {
+        self.rev_persistence.error_reporter()
+    }
+
     /// Returns the current revision id
     pub fn rev_id(&self) -> i64 {
         self.rev_id_counter.value()
@@ -250,10 +783,34 @@ impl<Connection: 'static> RevisionManager<Connection> {
         self.rev_persistence.number_of_sync_records()
     }
 
+    /// Returns the records of the revisions on the disk with their sync state.
+    pub fn get_all_revision_records(&self) -> FlowyResult<Vec<flowy_revision_persistence::SyncRecord>> {
+        self.rev_persistence.load_all_records(&self.object_id)
+    }
+
     pub fn number_of_revisions_in_disk(&self) -> usize {
         self.rev_persistence.number_of_records_in_disk()
     }
 
+    /// Returns the number of the object's revisions, including the ones that aren't written to
+    /// disk yet. The data of the revisions isn't read.
+    pub async fn revision_count(&self) -> FlowyResult<u64> {
+        self.rev_persistence.count_revisions().await
+    }
+
+    /// Estimates how many rows and bytes compacting the small revisions would remove. The
+    /// revisions that haven't been written to disk yet are not counted.
+    pub fn compaction_estimate(&self) -> FlowyResult<CompactionEstimate> {
+        self.rev_persistence.compaction_estimate()
+    }
+
+    /// Merges the runs of small acked revisions that `compaction_estimate` counts, so the
+    /// history has fewer rows but composing the revisions still gives the same object. Returns
+    /// the number of the deleted revisions.
+    pub async fn compact_small_revisions(&self) -> FlowyResult<usize> {
+        self.rev_persistence.compact_small_revisions(&self.rev_compress).await
+    }
+
     pub async fn get_revisions_in_range(&self, range: RevisionRange) -> Result<Vec<Revision>, FlowyError> {
         let revisions = self.rev_persistence.revisions_in_range(&range).await?;
         Ok(revisions)
@@ -263,15 +820,111 @@ impl<Connection: 'static> RevisionManager<Connection> {
         self.rev_persistence.next_sync_revision().await
     }
 
+    /// Returns the next revision to push if the push window isn't full, see
+    /// `RevisionPersistenceConfiguration::with_push_window`. The window is capped to
+    /// `max_push_window`, e.g. the server only takes one revision at a time.
+    ///
+    /// If the `verify_before_push` is set, the push stops at the revision that fails its
+    /// verification with an `InvalidRevision` event. The revisions behind it are based on it,
+    /// so they're held back too until the object is reset.
+    pub async fn next_push_revision(&self, max_push_window: usize) -> FlowyResult<Option<Revision>> {
+        let revision = match self.rev_persistence.next_push_revision(max_push_window).await? {
+            None => return Ok(None),
+            Some(revision) => revision,
+        };
+        if !self.rev_persistence.verify_before_push() {
+            return Ok(Some(revision));
+        }
+        match self.verify_revision(&revision).await {
+            Ok(_) => Ok(Some(revision)),
+            Err(err) => {
+                tracing::error!(
+                    "The revision {} of {} is invalid: {}",
+                    revision.rev_id,
+                    self.object_id,
+                    err
+                );
+                self.rev_persistence
+                    .halt_invalid(revision.rev_id, &err.to_string())
+                    .await;
+                let _ = self.event_notifier.send(RevisionManagerEvent::InvalidRevision {
+                    object_id: self.object_id.clone(),
+                    rev_id: revision.rev_id,
+                });
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns the rev_id of the revision the push stopped at, see `next_push_revision`.
+    pub async fn invalid_rev_id(&self) -> Option<i64> {
+        self.rev_persistence.halted_rev_id().await
+    }
+
+    async fn verify_revision(&self, revision: &Revision) -> FlowyResult<()> {
+        let base_revisions = self
+            .load_revisions()
+            .await?
+            .into_iter()
+            .filter(|base_revision| base_revision.rev_id <= revision.base_rev_id)
+            .collect::<Vec<Revision>>();
+        self.rev_compress.verify_revision(base_revisions, revision)
+    }
+
+    /// Returns the oldest revision that isn't acked to send it again. The revision that reached
+    /// the `max_push_attempts` is given up on with a `PushGaveUp` event. The revisions behind it
+    /// aren't returned either, they're based on it.
+    pub async fn next_resend_revision(&self) -> FlowyResult<Option<Revision>> {
+        let (revision, gave_up_rev_ids) = self.rev_persistence.next_resend_revision().await?;
+        for rev_id in gave_up_rev_ids {
+            tracing::warn!("Give up pushing the revision {} of {}", rev_id, self.object_id);
+            let _ = self.event_notifier.send(RevisionManagerEvent::PushGaveUp {
+                object_id: self.object_id.clone(),
+                rev_id,
+            });
+        }
+        Ok(revision)
+    }
+
+    /// Returns the revisions that were given up on. They're saved and unacked, they're kept as
+    /// dead letters after the object reopens until `retry_dead_letters` is called.
+    pub async fn dead_letters(&self) -> Vec<i64> {
+        self.rev_persistence.dead_letters().await
+    }
+
+    /// Syncs the revisions that were given up on again. Returns their rev_ids.
+    pub async fn retry_dead_letters(&self) -> Vec<i64> {
+        self.rev_persistence.retry_dead_letters().await
+    }
+
+    /// Compares the local history with the one of the server and returns the revisions that
+    /// syncing would push and pull. Neither history is changed, it's a dry run of the sync.
+    pub async fn sync_plan(&self, cloud: &Arc<dyn RevisionCloudService>) -> FlowyResult<SyncPlan> {
+        let remote = cloud.fetch_object(&self.user_id, &self.object_id).await?;
+        let local = self.rev_persistence.current_records()?;
+        Ok(make_sync_plan(&local, &remote))
+    }
+
     pub async fn get_revision(&self, rev_id: i64) -> Option<Revision> {
         self.rev_persistence.get(rev_id).await.map(|record| record.revision)
     }
+
+    /// Returns the transitions of the local revision from its creation to its ack in the order
+    /// they happened. Only the recent revisions are kept, see `MAX_LIFECYCLE_REVISIONS`.
+    pub fn revision_lifecycle(&self, rev_id: i64) -> Option<Vec<(Instant, RevLifecycleEvent)>> {
+        self.rev_persistence.lifecycle().get(rev_id)
+    }
 }
 
 impl<Connection: 'static> WSDataProviderDataSource for Arc<RevisionManager<Connection>> {
     fn next_revision(&self) -> FutureResult<Option<Revision>, FlowyError> {
         let rev_manager = self.clone();
-        FutureResult::new(async move { rev_manager.next_sync_revision().await })
+        FutureResult::new(async move { rev_manager.next_resend_revision().await })
+    }
+
+    fn next_push_revision(&self, max_push_window: usize) -> FutureResult<Option<Revision>, FlowyError> {
+        let rev_manager = self.clone();
+        FutureResult::new(async move { rev_manager.next_push_revision(max_push_window).await })
     }
 
     fn ack_revision(&self, rev_id: i64) -> FutureResult<(), FlowyError> {
@@ -279,6 +932,20 @@ impl<Connection: 'static> WSDataProviderDataSource for Arc<RevisionManager<Conne
         FutureResult::new(async move { (*rev_manager).ack_revision(rev_id).await })
     }
 
+    fn did_send(&self, rev_id: i64) -> FutureResult<(), FlowyError> {
+        let rev_manager = self.clone();
+        FutureResult::new(async move {
+            rev_manager.rev_persistence.did_send(rev_id).await;
+            Ok(())
+        })
+    }
+
+    fn did_fail_to_send(&self, rev_id: i64, error: &FlowyError) {
+        self.rev_persistence
+            .lifecycle()
+            .record(rev_id, RevLifecycleEvent::Failed(error.to_string()));
+    }
+
     fn current_rev_id(&self) -> i64 {
         self.rev_id()
     }
@@ -292,9 +959,6 @@ impl<Connection: 'static> RevisionManager<Connection> {
     pub fn ack_notify(&self) -> tokio::sync::broadcast::Receiver<i64> {
         self.rev_ack_notifier.subscribe()
     }
-    pub fn get_all_revision_records(&self) -> FlowyResult<Vec<flowy_revision_persistence::SyncRecord>> {
-        self.rev_persistence.load_all_records(&self.object_id)
-    }
 }
 
 pub struct RevisionLoader<Connection> {
@@ -307,11 +971,20 @@ pub struct RevisionLoader<Connection> {
 impl<Connection: 'static> RevisionLoader<Connection> {
     pub async fn load_revisions(&self) -> Result<Vec<Revision>, FlowyError> {
         let records = self.rev_persistence.load_all_records(&self.object_id)?;
-        let revisions = records.into_iter().map(|record| record.revision).collect::<_>();
-        Ok(revisions)
+        Ok(composable_revisions(&records))
     }
 }
 
+/// Returns the revisions that build the object, the resolved revisions are skipped. The raw
+/// records must go through it before they're composed.
+pub fn composable_revisions(records: &[SyncRecord]) -> Vec<Revision> {
+    records
+        .iter()
+        .filter(|record| record.state != RevisionState::Resolved)
+        .map(|record| record.revision.clone())
+        .collect()
+}
+
 /// Represents as the md5 of the revision object after applying the
 /// revision. For example, RevisionMD5 will be the md5 of the document
 /// content.
@@ -380,6 +1053,51 @@ fn pair_rev_id_from_revisions(revisions: &[Revision]) -> (i64, i64) {
     }
 }
 
+async fn sweep_expired_revisions<Connection: 'static>(
+    rev_persistence: &RevisionPersistence<Connection>,
+    rev_snapshot: &RevisionSnapshotController<Connection>,
+    rev_compress: &Arc<dyn RevisionMergeable>,
+    rev_id: i64,
+) -> FlowyResult<usize> {
+    let now = rev_persistence.now();
+    if !rev_persistence.has_expired(now)? {
+        return Ok(0);
+    }
+    // The rev_id is 0 if the object wasn't initialized, its snapshot can't be told current then.
+    let is_snapshot_current =
+        rev_id > 0 && matches!(rev_snapshot.read_last_snapshot(), Ok(Some(snapshot)) if snapshot.rev_id >= rev_id);
+    if !is_snapshot_current {
+        // The expired revisions are only purged once the snapshot keeps their content.
+        rev_persistence.flush().await?;
+        rev_snapshot.generate_snapshot().await?;
+    }
+    rev_persistence.purge_expired(now, rev_compress).await
+}
+
+/// Sweeps the expired revisions every `interval` until the `RevisionManager` is dropped.
+async fn run_expiry_sweep<Connection: 'static>(
+    interval: Duration,
+    rev_persistence: Weak<RevisionPersistence<Connection>>,
+    rev_snapshot: Weak<RevisionSnapshotController<Connection>>,
+    rev_compress: Arc<dyn RevisionMergeable>,
+    rev_id_counter: Arc<RevIdCounter>,
+) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let (rev_persistence, rev_snapshot) = match (rev_persistence.upgrade(), rev_snapshot.upgrade()) {
+            (Some(rev_persistence), Some(rev_snapshot)) => (rev_persistence, rev_snapshot),
+            _ => break,
+        };
+        let rev_id = rev_id_counter.value();
+        if let Err(e) = sweep_expired_revisions(&rev_persistence, &rev_snapshot, &rev_compress, rev_id).await {
+            tracing::error!("Sweep the expired revisions failed: {:?}", e);
+            if let Some(error_reporter) = rev_persistence.error_reporter() {
+                error_reporter.report("sweep expired revisions", &e);
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RevIdCounter(pub AtomicI64);
 
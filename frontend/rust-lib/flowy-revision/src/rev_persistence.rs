@@ -1,17 +1,39 @@
 use crate::cache::memory::RevisionMemoryCacheDelegate;
 use crate::memory::RevisionMemoryCache;
-use crate::RevisionMergeable;
+use crate::read::RevisionReadCache;
+use crate::rev_lifecycle::RevisionLifecycle;
+use crate::{
+    Executor, PriorityScheduler, RevLifecycleEvent, RevisionCacheState, RevisionMergeable, SaveDebounceConfiguration,
+};
+use dashmap::DashMap;
 use flowy_error::{internal_error, FlowyError, FlowyResult};
 use flowy_http_model::revision::{Revision, RevisionRange};
 use flowy_revision_persistence::{RevisionChangeset, RevisionDiskCache, RevisionState, SyncRecord};
-use std::collections::{HashMap, VecDeque};
-
+use futures::FutureExt;
+use lib_infra::util::timestamp;
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::sync::Mutex;
+use std::time::Duration;
 use std::{borrow::Cow, sync::Arc};
 use tokio::sync::RwLock;
-use tokio::task::spawn_blocking;
 
 pub const REVISION_WRITE_INTERVAL_IN_MILLIS: u64 = 600;
 
+/// The maximum number of the revisions that can be pushed to the server without being acked.
+pub const MAX_PUSH_WINDOW: usize = 16;
+
+/// The number of the revisions read from disk that are kept in memory by default.
+pub const DEFAULT_READ_CACHE_CAPACITY: usize = 64;
+
+/// The number of the acks that can wait for the acks of the revisions in front of them. An
+/// early ack beyond it is dropped, its revision stays pushed and gets acked again.
+pub const MAX_EARLY_ACKS: usize = 32;
+
+/// Runs once the revision it's registered for is acked, see `RevisionManager::on_ack`.
+pub type AckCallback = Box<dyn FnOnce() + Send + 'static>;
+
 #[derive(Clone)]
 pub struct RevisionPersistenceConfiguration {
     // If the number of revisions that didn't sync to the server greater than the merge_threshold
@@ -21,6 +43,52 @@ pub struct RevisionPersistenceConfiguration {
     /// Indicates that the revisions that didn't sync to the server can be merged into one when
     /// `compact_lagging_revisions` get called.
     merge_lagging: bool,
+
+    /// Indicates that the revisions never sync to the server. Each revision is acked once it's
+    /// added, and only the last `n` revisions are kept, the older ones get merged into one.
+    local_only_history_limit: Option<usize>,
+
+    /// Receives the revisions after they were written to disk, see `RevisionMirror`.
+    mirror: Option<Arc<dyn RevisionMirror>>,
+
+    /// Decides when the revisions that wait in memory get written to disk.
+    save_debounce: SaveDebounceConfiguration,
+
+    /// The number of the revisions that can be pushed to the server before the first of them
+    /// gets acked. The acks are still processed in order.
+    push_window: usize,
+
+    /// The number of the recently read revisions that are kept in front of the disk cache. It's
+    /// disabled if it's zero.
+    read_cache_capacity: usize,
+
+    /// Receives the failures of the background tasks, e.g. the deferred save.
+    error_reporter: Option<Arc<dyn ErrorReporter>>,
+
+    /// The number of times a revision is sent before it's given up on, see
+    /// `with_max_push_attempts`. The revision is sent until it's acked if it's None.
+    max_push_attempts: Option<usize>,
+
+    /// Saves the revisions with `create_durable_revision_records`, see `with_durable_writes`.
+    durable_writes: bool,
+
+    executor: Executor,
+
+    /// Runs the reads and the writes of the revisions by the priority of the object, see
+    /// `with_priority_scheduler`.
+    priority_scheduler: Option<PriorityScheduler>,
+
+    /// Sweeps the expired revisions at this interval, see `with_expiry_sweep`.
+    expiry_sweep_interval: Option<Duration>,
+
+    /// What happens to the revision whose ack panicked, see `AckPanicFallback`.
+    ack_panic_fallback: AckPanicFallback,
+
+    /// Checks each revision against its base before it's pushed, see `with_verify_before_push`.
+    verify_before_push: bool,
+
+    /// Tells the time that the revisions are expired and purged by, see `with_clock`.
+    clock: Option<Arc<dyn RevisionClock>>,
 }
 
 impl RevisionPersistenceConfiguration {
@@ -30,14 +98,186 @@ impl RevisionPersistenceConfiguration {
             Self {
                 merge_threshold,
                 merge_lagging,
+                local_only_history_limit: None,
+                mirror: None,
+                save_debounce: SaveDebounceConfiguration::default(),
+                push_window: 1,
+                read_cache_capacity: DEFAULT_READ_CACHE_CAPACITY,
+                error_reporter: None,
+                max_push_attempts: None,
+                durable_writes: false,
+                executor: Executor::default(),
+                priority_scheduler: None,
+                expiry_sweep_interval: None,
+                ack_panic_fallback: AckPanicFallback::Reconcile,
+                verify_before_push: false,
+                clock: None,
             }
         } else {
             Self {
                 merge_threshold: 100,
                 merge_lagging,
+                local_only_history_limit: None,
+                mirror: None,
+                save_debounce: SaveDebounceConfiguration::default(),
+                push_window: 1,
+                read_cache_capacity: DEFAULT_READ_CACHE_CAPACITY,
+                error_reporter: None,
+                max_push_attempts: None,
+                durable_writes: false,
+                executor: Executor::default(),
+                priority_scheduler: None,
+                expiry_sweep_interval: None,
+                ack_panic_fallback: AckPanicFallback::Reconcile,
+                verify_before_push: false,
+                clock: None,
             }
         }
     }
+
+    pub fn with_local_only(mut self, history_limit: usize) -> Self {
+        debug_assert!(history_limit > 0);
+        self.local_only_history_limit = Some(history_limit.max(1));
+        self
+    }
+
+    pub fn is_local_only(&self) -> bool {
+        self.local_only_history_limit.is_some()
+    }
+
+    pub fn with_mirror(mut self, mirror: Arc<dyn RevisionMirror>) -> Self {
+        self.mirror = Some(mirror);
+        self
+    }
+
+    pub fn with_save_debounce(mut self, save_debounce: SaveDebounceConfiguration) -> Self {
+        self.save_debounce = save_debounce;
+        self
+    }
+
+    /// Allows up to `push_window` revisions in flight, which saves the round trips on the
+    /// high-latency links. The window is capped at `MAX_PUSH_WINDOW`.
+    pub fn with_push_window(mut self, push_window: usize) -> Self {
+        debug_assert!(push_window > 0);
+        self.push_window = push_window.clamp(1, MAX_PUSH_WINDOW);
+        self
+    }
+
+    pub fn push_window(&self) -> usize {
+        self.push_window
+    }
+
+    /// Keeps up to `capacity` revisions read from disk in memory, so the historical revisions
+    /// that are read repeatedly don't query the disk each time. Pass zero to disable it.
+    pub fn with_read_cache(mut self, capacity: usize) -> Self {
+        self.read_cache_capacity = capacity;
+        self
+    }
+
+    /// Runs the background tasks of the object with the `executor` instead of `tokio::spawn`.
+    pub fn with_executor(mut self, executor: Executor) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Runs the reads of the revisions from disk and the saves of the pending revisions with the
+    /// `priority_scheduler`, they go first while the object is focused. Without it, the reads run
+    /// right away on the `executor` and the saves run on the task that saves them.
+    pub fn with_priority_scheduler(mut self, priority_scheduler: PriorityScheduler) -> Self {
+        self.priority_scheduler = Some(priority_scheduler);
+        self
+    }
+
+    pub fn with_error_reporter(mut self, error_reporter: Arc<dyn ErrorReporter>) -> Self {
+        self.error_reporter = Some(error_reporter);
+        self
+    }
+
+    /// Gives up on the revision that wasn't acked after it was sent `max_push_attempts` times,
+    /// e.g. the one the server keeps rejecting, so it isn't sent forever. Only the sends that
+    /// reached the web socket count. The revisions behind it are based on it, they're held back
+    /// with it. It stays saved and unacked, see `RevisionManager::retry_dead_letters`.
+    pub fn with_max_push_attempts(mut self, max_push_attempts: usize) -> Self {
+        debug_assert!(max_push_attempts > 0);
+        self.max_push_attempts = Some(max_push_attempts.max(1));
+        self
+    }
+
+    /// Waits for each save to reach the disk before it's done, so a power loss doesn't lose the
+    /// revisions that were just saved. Each save takes an fsync, which slows down the typing
+    /// on slow disks, so it's meant for the deployments that can't lose any revision.
+    pub fn with_durable_writes(mut self) -> Self {
+        self.durable_writes = true;
+        self
+    }
+
+    /// Checks that each revision composes onto the revisions up to its `base_rev_id` before it's
+    /// pushed, see `RevisionMergeable::verify_revision`. The push stops at the revision that
+    /// fails with an `InvalidRevision` event, neither it nor the revisions based on it reach the
+    /// server. It costs loading and composing the revisions of the object up to the base of each
+    /// pushed revision, so the push slows down as the history grows. Leave it off for the large
+    /// objects unless a broken revision reaching the server is worse than the slower sync.
+    pub fn with_verify_before_push(mut self) -> Self {
+        self.verify_before_push = true;
+        self
+    }
+
+    /// Prunes the expired revisions of the object every `interval` while it's open, see
+    /// `RevisionManager::sweep_expired_revisions`. The revisions expire only if they're given
+    /// an expiry, see `RevisionManager::set_revision_expiry`.
+    pub fn with_expiry_sweep(mut self, interval: Duration) -> Self {
+        self.expiry_sweep_interval = Some(interval);
+        self
+    }
+
+    pub fn with_ack_panic_fallback(mut self, ack_panic_fallback: AckPanicFallback) -> Self {
+        self.ack_panic_fallback = ack_panic_fallback;
+        self
+    }
+
+    /// Replaces the system time that the expiry and the retention of the revisions are checked
+    /// against, e.g. with a fake one in the tests.
+    pub fn with_clock(mut self, clock: Arc<dyn RevisionClock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+}
+
+/// The ack of a revision can panic, e.g. on a poisoned lock of the disk cache. The panic is
+/// caught, logged with the rev_id and passed to the `ErrorReporter` instead of taking down the
+/// task that received the ack. The revision was already taken out of the sync sequence, so
+/// without the fallback it would stay in the `Sync` state on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckPanicFallback {
+    /// The ack is applied again the next time the revisions are accessed.
+    Reconcile,
+    /// The panic is only reported, the revision is acked again after the object is reopened
+    /// and the server acks it once more.
+    ReportOnly,
+}
+
+/// Copies the revisions to an external store, e.g. Redis, so that the other processes that
+/// share the objects can observe them.
+///
+/// It's called by the deferred save of the `RevisionPersistence` after the revisions were
+/// written to disk, each saved revision is mirrored once. The error is logged, it never fails
+/// the local write.
+pub trait RevisionMirror: Send + Sync {
+    fn mirror(&self, revision: &Revision) -> FlowyResult<()>;
+}
+
+/// Receives the errors of the detached tasks of an object, e.g. to forward them to an error
+/// tracking service. Nobody awaits these tasks, so their errors would only end up in the logs.
+/// The errors are still logged.
+///
+/// The `context` tells which task failed and for which object.
+pub trait ErrorReporter: Send + Sync {
+    fn report(&self, context: &str, error: &FlowyError);
+}
+
+/// Returns the current time in seconds, see `RevisionPersistenceConfiguration::with_clock`.
+pub trait RevisionClock: Send + Sync {
+    fn now(&self) -> i64;
 }
 
 impl std::default::Default for RevisionPersistenceConfiguration {
@@ -45,10 +285,36 @@ impl std::default::Default for RevisionPersistenceConfiguration {
         Self {
             merge_threshold: 100,
             merge_lagging: false,
+            local_only_history_limit: None,
+            mirror: None,
+            save_debounce: SaveDebounceConfiguration::default(),
+            push_window: 1,
+            read_cache_capacity: DEFAULT_READ_CACHE_CAPACITY,
+            error_reporter: None,
+            max_push_attempts: None,
+            durable_writes: false,
+            executor: Executor::default(),
+            priority_scheduler: None,
+            expiry_sweep_interval: None,
+            ack_panic_fallback: AckPanicFallback::Reconcile,
+            verify_before_push: false,
+            clock: None,
         }
     }
 }
 
+/// The revisions whose data is smaller than this are worth merging with their neighbours.
+pub const COMPACTION_REVISION_SIZE_LIMIT: usize = 1024;
+
+/// Estimates what compacting the small revisions would save. Each run of small consecutive
+/// revisions with the same state is merged into one revision.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactionEstimate {
+    pub num_of_runs: usize,
+    pub num_of_removed_rows: usize,
+    pub num_of_removed_bytes: usize,
+}
+
 /// Represents as the persistence of revisions including memory or disk cache.
 /// The generic parameter, `Connection`, represents as the disk backend's connection.
 /// If the backend is SQLite, then the Connect will be SQLiteConnect.
@@ -58,6 +324,14 @@ pub struct RevisionPersistence<Connection> {
     disk_cache: Arc<dyn RevisionDiskCache<Connection, Error = FlowyError>>,
     memory_cache: Arc<RevisionMemoryCache>,
     sync_seq: RwLock<DeferSyncSequence>,
+    /// Maps the rev_id assigned by the client to the rev_id assigned by the server. It's kept on
+    /// disk too, so the rev_ids assigned by the client still resolve after the object reopens.
+    rev_id_map: DashMap<i64, i64>,
+    lifecycle: RevisionLifecycle,
+    /// The callbacks that wait for the acks of the revisions, see `on_ack`.
+    ack_callbacks: DashMap<i64, Vec<AckCallback>>,
+    /// The revisions whose ack panicked, see `AckPanicFallback::Reconcile`.
+    unreconciled_acks: Mutex<BTreeSet<i64>>,
     configuration: RevisionPersistenceConfiguration,
 }
 
@@ -86,18 +360,74 @@ where
     ) -> RevisionPersistence<Connection> {
         let object_id = object_id.to_owned();
         let user_id = user_id.to_owned();
+        let disk_cache = Arc::new(RevisionReadCache::new(
+            &object_id,
+            disk_cache,
+            configuration.read_cache_capacity,
+        )) as Arc<dyn RevisionDiskCache<Connection, Error = FlowyError>>;
         let sync_seq = RwLock::new(DeferSyncSequence::new());
-        let memory_cache = Arc::new(RevisionMemoryCache::new(&object_id, Arc::new(disk_cache.clone())));
+        let delegate = RevisionDiskCacheDelegate {
+            disk_cache: disk_cache.clone(),
+            mirror: configuration.mirror.clone(),
+            durable_writes: configuration.durable_writes,
+        };
+        let memory_cache = Arc::new(RevisionMemoryCache::new(
+            &object_id,
+            Arc::new(delegate),
+            configuration.save_debounce.clone(),
+            configuration.error_reporter.clone(),
+            configuration.executor.clone(),
+            configuration.priority_scheduler.clone(),
+        ));
+        let rev_id_map = match disk_cache.read_rev_id_map(&object_id) {
+            Ok(rev_id_map) => rev_id_map.into_iter().collect(),
+            Err(e) => {
+                tracing::error!("Read the rev_id map of {} failed: {}", object_id, e);
+                DashMap::new()
+            }
+        };
         Self {
             user_id,
             object_id,
             disk_cache,
             memory_cache,
             sync_seq,
+            rev_id_map,
+            lifecycle: RevisionLifecycle::default(),
+            ack_callbacks: DashMap::new(),
+            unreconciled_acks: Mutex::new(BTreeSet::new()),
             configuration,
         }
     }
 
+    pub fn executor(&self) -> &Executor {
+        &self.configuration.executor
+    }
+
+    pub fn error_reporter(&self) -> Option<Arc<dyn ErrorReporter>> {
+        self.configuration.error_reporter.clone()
+    }
+
+    /// The current time in seconds, see `RevisionClock`.
+    pub(crate) fn now(&self) -> i64 {
+        match self.configuration.clock.as_ref() {
+            None => timestamp(),
+            Some(clock) => clock.now(),
+        }
+    }
+
+    pub(crate) fn expiry_sweep_interval(&self) -> Option<Duration> {
+        self.configuration.expiry_sweep_interval
+    }
+
+    pub(crate) fn verify_before_push(&self) -> bool {
+        self.configuration.verify_before_push
+    }
+
+    pub(crate) fn lifecycle(&self) -> &RevisionLifecycle {
+        &self.lifecycle
+    }
+
     /// Save the revision that comes from remote to disk.
     #[tracing::instrument(level = "trace", skip(self, revision), fields(rev_id, object_id=%self.object_id), err)]
     pub(crate) async fn add_ack_revision(&self, revision: &Revision) -> FlowyResult<()> {
@@ -115,7 +445,8 @@ where
         }
 
         let mut sync_seq = self.sync_seq.write().await;
-        let compact_seq = sync_seq.compact();
+        let mut compact_seq = sync_seq.compact();
+        self.exclude_pinned(&mut sync_seq, &mut compact_seq)?;
         if !compact_seq.is_empty() {
             let range = RevisionRange {
                 start: *compact_seq.front().unwrap(),
@@ -142,15 +473,53 @@ where
 
     /// Sync the each records' revisions to remote if its state is `RevisionState::Sync`.
     ///
-    pub(crate) async fn sync_revision_records(&self, records: &[SyncRecord]) -> FlowyResult<()> {
+    /// The records are read from disk in the order they were stored, which isn't always the
+    /// order of their rev_ids, e.g. after a crash. The sync sequence is rebuilt in the order of
+    /// the rev_ids, otherwise the revisions would be sent out of order and rejected by the
+    /// server. The sink picks them up on its next tick, no new edit is needed to resume.
+    ///
+    /// The push stops at the first revision that isn't based on the revision before it, like at
+    /// a revision that fails its verification, see `halt_invalid`. The revisions in front of it
+    /// are still pushed. Returns its rev_id, the object has to be reset from the server to sync
+    /// the revisions from there on.
+    pub(crate) async fn sync_revision_records(&self, records: &[SyncRecord]) -> FlowyResult<Option<i64>> {
+        let mut records = records.iter().collect::<Vec<&SyncRecord>>();
+        records.sort_by_key(|record| record.revision.rev_id);
+        let discontinuity = first_discontinuous_record(&records).map(|record| {
+            let error = FlowyError::invalid_data().context(format!(
+                "The revision:{} of {} is based on {}, which isn't the revision before it",
+                record.revision.rev_id, self.object_id, record.revision.base_rev_id
+            ));
+            tracing::error!("{}", error);
+            if let Some(error_reporter) = self.configuration.error_reporter.as_ref() {
+                error_reporter.report(&format!("rebuild the sync sequence of {}", self.object_id), &error);
+            }
+            (record.revision.rev_id, error)
+        });
+
+        let dead_letters = self.read_dead_letters();
         let mut sync_seq = self.sync_seq.write().await;
         for record in records {
             if record.state == RevisionState::Sync {
                 self.add(record.revision.clone(), RevisionState::Sync, false).await?;
-                sync_seq.recv(record.revision.rev_id)?; // Sync the records if their state is RevisionState::Sync.
+                if dead_letters.contains(&record.revision.rev_id) {
+                    sync_seq.dead_letters.insert(record.revision.rev_id);
+                } else {
+                    sync_seq.recv(record.revision.rev_id)?; // Sync the records if their state is RevisionState::Sync.
+                }
+            }
+        }
+
+        match discontinuity {
+            None => Ok(None),
+            Some((rev_id, error)) => {
+                sync_seq.halt(rev_id);
+                drop(sync_seq);
+                self.lifecycle
+                    .record(rev_id, RevLifecycleEvent::Invalid(error.msg.clone()));
+                Ok(Some(rev_id))
             }
         }
-        Ok(())
     }
 
     /// Save the revision to disk and append it to the end of the sync sequence.
@@ -162,7 +531,14 @@ where
         new_revision: Revision,
         rev_compress: &Arc<dyn RevisionMergeable + 'a>,
     ) -> FlowyResult<i64> {
+        if let Some(history_limit) = self.configuration.local_only_history_limit {
+            return self
+                .add_local_only_revision(new_revision, history_limit, rev_compress)
+                .await;
+        }
+
         let mut sync_seq = self.sync_seq.write().await;
+        let new_rev_id = new_revision.rev_id;
 
         // Before the new_revision is pushed into the sync_seq, we check if the current `compact_length` of the
         // sync_seq is less equal to or greater than the merge threshold. If yes, it's needs to merged
@@ -171,6 +547,7 @@ where
         // tracing::info!("{}", compact_seq)
         if sync_seq.compact_length >= self.configuration.merge_threshold - 1 {
             compact_seq.extend(sync_seq.compact());
+            self.exclude_pinned(&mut sync_seq, &mut compact_seq)?;
         }
         if !compact_seq.is_empty() {
             let range = RevisionRange {
@@ -189,6 +566,12 @@ where
             let rev_id = merged_revision.rev_id;
             tracing::Span::current().record("rev_id", &merged_revision.rev_id);
             sync_seq.recv(merged_revision.rev_id)?;
+            for merged_rev_id in range.to_rev_ids().into_iter().chain(Some(new_rev_id)) {
+                if merged_rev_id != rev_id {
+                    self.lifecycle
+                        .record(merged_rev_id, RevLifecycleEvent::Merged { into: rev_id });
+                }
+            }
 
             // replace the revisions in range with compact revision
             self.compact(&range, merged_revision).await?;
@@ -198,18 +581,462 @@ where
             tracing::Span::current().record("rev_id", &rev_id);
             self.add(new_revision, RevisionState::Sync, true).await?;
             sync_seq.merge_recv(rev_id)?;
+            self.lifecycle.record(rev_id, RevLifecycleEvent::Pending);
             Ok(rev_id)
         }
     }
 
-    /// Remove the revision with rev_id from the sync sequence.
+    /// Adds the local revision and writes it to disk with the `records` of other objects in one
+    /// transaction, e.g. the first revision of a document split off from this one, so either
+    /// all of them are saved or none is. The pending revisions are flushed first, so the disk
+    /// never has the new revision without the ones before it. The revision isn't merged with
+    /// the revisions before it.
+    pub(crate) async fn add_local_revision_with_records(
+        &self,
+        new_revision: Revision,
+        records: Vec<SyncRecord>,
+    ) -> FlowyResult<i64> {
+        let mut sync_seq = self.sync_seq.write().await;
+        self.memory_cache.flush().await?;
+        let rev_id = new_revision.rev_id;
+        tracing::Span::current().record("rev_id", &rev_id);
+        // The revision of a local-only object has nothing to sync with.
+        let is_local_only = self.configuration.is_local_only();
+        let record = SyncRecord {
+            revision: new_revision.clone(),
+            state: if is_local_only {
+                RevisionState::Ack
+            } else {
+                RevisionState::Sync
+            },
+            write_to_disk: false,
+        };
+        let records = Some(record).into_iter().chain(records).collect::<Vec<SyncRecord>>();
+        if self.configuration.durable_writes {
+            self.disk_cache.create_durable_revision_records(records)?;
+        } else {
+            self.disk_cache.create_revision_records(records)?;
+        }
+
+        if !is_local_only {
+            self.add(new_revision, RevisionState::Sync, false).await?;
+            sync_seq.recv(rev_id)?;
+            self.lifecycle.record(rev_id, RevLifecycleEvent::Pending);
+        }
+        Ok(rev_id)
+    }
+
+    /// The revision of the local-only object is written to disk with the ack state right away.
+    /// The revisions beyond the `history_limit` get merged into the oldest one. The pinned
+    /// revisions are kept, so the revisions between them are merged separately and the history
+    /// may exceed the limit.
+    async fn add_local_only_revision<'a>(
+        &'a self,
+        new_revision: Revision,
+        history_limit: usize,
+        rev_compress: &Arc<dyn RevisionMergeable + 'a>,
+    ) -> FlowyResult<i64> {
+        let _sync_seq = self.sync_seq.write().await;
+        let rev_id = new_revision.rev_id;
+        tracing::Span::current().record("rev_id", &rev_id);
+        let record = SyncRecord {
+            revision: new_revision,
+            state: RevisionState::Ack,
+            write_to_disk: true,
+        };
+        if self.configuration.durable_writes {
+            self.disk_cache.create_durable_revision_records(vec![record])?;
+        } else {
+            self.disk_cache.create_revision_records(vec![record])?;
+        }
+
+        let mut records = self.disk_cache.read_revision_records(&self.object_id, None)?;
+        if records.len() > history_limit {
+            records.sort_by_key(|record| record.revision.rev_id);
+            let num_of_merged = records.len() - history_limit + 1;
+            let pinned_rev_ids = self.pinned_rev_ids()?;
+            let mut runs: Vec<Vec<Revision>> = vec![vec![]];
+            for record in records.into_iter().take(num_of_merged) {
+                if pinned_rev_ids.contains(&record.revision.rev_id) {
+                    runs.push(vec![]);
+                } else {
+                    runs.last_mut().unwrap().push(record.revision);
+                }
+            }
+
+            for revisions in runs.into_iter().filter(|run| run.len() > 1) {
+                let rev_ids = revisions.iter().map(|revision| revision.rev_id).collect::<Vec<i64>>();
+                let merged_revision = rev_compress.merge_revisions(&self.user_id, &self.object_id, revisions)?;
+                let record = SyncRecord {
+                    revision: merged_revision,
+                    state: RevisionState::Ack,
+                    write_to_disk: true,
+                };
+                self.disk_cache
+                    .delete_and_insert_records(&self.object_id, Some(rev_ids), vec![record])?;
+            }
+        }
+        Ok(rev_id)
+    }
+
+    /// Pins the revision, see `RevisionManager::pin_revision`.
+    pub(crate) async fn pin(&self, rev_id: i64) -> FlowyResult<()> {
+        // Waits for the merge that is in progress, it may be merging the revision.
+        let _sync_seq = self.sync_seq.write().await;
+        let rev_id = self.canonical_rev_id(rev_id);
+        if self.get(rev_id).await.is_none() {
+            return Err(FlowyError::record_not_found().context(format!("Can't find the revision: {}", rev_id)));
+        }
+        self.disk_cache.pin_revision(&self.object_id, rev_id)
+    }
+
+    pub(crate) async fn unpin(&self, rev_id: i64) -> FlowyResult<()> {
+        let _sync_seq = self.sync_seq.write().await;
+        self.disk_cache
+            .unpin_revision(&self.object_id, self.canonical_rev_id(rev_id))
+    }
+
+    pub(crate) fn pinned_rev_ids(&self) -> FlowyResult<Vec<i64>> {
+        self.disk_cache.read_pinned_rev_ids(&self.object_id)
+    }
+
+    /// Tags the revision, see `RevisionManager::tag_revision`.
+    pub(crate) async fn tag(&self, rev_id: i64, tag: &str) -> FlowyResult<()> {
+        let _sync_seq = self.sync_seq.write().await;
+        let rev_id = self.canonical_rev_id(rev_id);
+        if self.get(rev_id).await.is_none() {
+            return Err(FlowyError::record_not_found().context(format!("Can't find the revision: {}", rev_id)));
+        }
+        self.disk_cache.tag_revision(&self.object_id, rev_id, tag)
+    }
+
+    pub(crate) fn revision_tags(&self) -> FlowyResult<Vec<(String, i64)>> {
+        self.disk_cache.read_revision_tags(&self.object_id)
+    }
+
+    /// Keeps a copy of the `revisions` before `reset` replaces them, see
+    /// `RevisionManager::read_revision_backup`.
+    pub(crate) fn backup(&self, revisions: &[Revision]) -> FlowyResult<()> {
+        self.disk_cache.write_revision_backup(&self.object_id, revisions)
+    }
+
+    pub(crate) fn revision_backup(&self) -> FlowyResult<Vec<Revision>> {
+        self.disk_cache.read_revision_backup(&self.object_id)
+    }
+
+    /// Merges the revisions written before the `cutoff`, in seconds, into one revision, see
+    /// `RevisionManager::purge_older_than`. Returns the number of the deleted revisions.
+    pub(crate) async fn purge_before<'a>(
+        &'a self,
+        cutoff: i64,
+        rev_compress: &Arc<dyn RevisionMergeable + 'a>,
+    ) -> FlowyResult<usize> {
+        // Waits for the merge that is in progress, it may be merging the old revisions.
+        let _sync_seq = self.sync_seq.write().await;
+        let timestamps = self
+            .disk_cache
+            .read_revision_timestamps(&self.object_id)?
+            .into_iter()
+            .collect::<HashMap<i64, i64>>();
+        let is_old = |rev_id: i64| matches!(timestamps.get(&rev_id), Some(timestamp) if *timestamp < cutoff);
+        let num_of_purged = self.purge_leading(is_old, rev_compress).await?;
+        if num_of_purged > 0 {
+            tracing::info!(
+                "Purged {} revisions of {} written before {}",
+                num_of_purged,
+                self.object_id,
+                cutoff
+            );
+        }
+        Ok(num_of_purged)
+    }
+
+    /// Sets the time that the revision expires at, in seconds, see
+    /// `RevisionManager::set_revision_expiry`. The revision is written to disk first if it's
+    /// waiting for the deferred save.
+    pub(crate) async fn set_expiry(&self, rev_id: i64, expires_at: Option<i64>) -> FlowyResult<()> {
+        let rev_id = self.canonical_rev_id(rev_id);
+        self.memory_cache.flush().await?;
+        self.disk_cache
+            .set_revision_expiry(&self.object_id, &[rev_id], expires_at)
+    }
+
+    /// Returns true if any revision expired at the time `now`, in seconds.
+    pub(crate) fn has_expired(&self, now: i64) -> FlowyResult<bool> {
+        let expiries = self.disk_cache.read_revision_expiries(&self.object_id)?;
+        Ok(expiries.iter().any(|(_, expires_at)| *expires_at <= now))
+    }
+
+    /// Merges the revisions that expired at the time `now`, in seconds, into one revision, see
+    /// `RevisionManager::sweep_expired_revisions`. Returns the number of the deleted revisions.
+    pub(crate) async fn purge_expired<'a>(
+        &'a self,
+        now: i64,
+        rev_compress: &Arc<dyn RevisionMergeable + 'a>,
+    ) -> FlowyResult<usize> {
+        let _sync_seq = self.sync_seq.write().await;
+        let expiries = self
+            .disk_cache
+            .read_revision_expiries(&self.object_id)?
+            .into_iter()
+            .collect::<HashMap<i64, i64>>();
+        let is_expired = |rev_id: i64| matches!(expiries.get(&rev_id), Some(expires_at) if *expires_at <= now);
+        let num_of_purged = self.purge_leading(is_expired, rev_compress).await?;
+        if num_of_purged > 0 {
+            tracing::info!("Swept {} expired revisions of {}", num_of_purged, self.object_id);
+        }
+        Ok(num_of_purged)
+    }
+
+    /// Merges the leading revisions that `is_purged` into one revision. It stops at the first
+    /// revision that isn't purged, isn't synced or is pinned, the revisions after it can only be
+    /// composed on top of it. The caller holds the `sync_seq` so no merge runs in between.
+    async fn purge_leading<'a, F>(
+        &'a self,
+        is_purged: F,
+        rev_compress: &Arc<dyn RevisionMergeable + 'a>,
+    ) -> FlowyResult<usize>
+    where
+        F: Fn(i64) -> bool,
+    {
+        self.memory_cache.flush().await?;
+        let pinned_rev_ids = self.pinned_rev_ids()?;
+        let mut records = self.disk_cache.read_revision_records(&self.object_id, None)?;
+        records.sort_by_key(|record| record.revision.rev_id);
+
+        let mut purged_rev_ids = vec![];
+        let mut merged_revisions = vec![];
+        for record in records {
+            let rev_id = record.revision.rev_id;
+            if !is_purged(rev_id) || pinned_rev_ids.contains(&rev_id) {
+                break;
+            }
+            match record.state {
+                // The revision isn't sent yet, it's sent as it is.
+                RevisionState::Sync => break,
+                RevisionState::Ack => merged_revisions.push(record.revision),
+                // The resolved revision isn't composed into the object, it's only deleted.
+                RevisionState::Resolved => {}
+            }
+            purged_rev_ids.push(rev_id);
+        }
+
+        let inserted_records = match merged_revisions.len() {
+            0 => vec![],
+            // The only purged revision is the content as of itself already.
+            1 => {
+                let rev_id = merged_revisions[0].rev_id;
+                purged_rev_ids.retain(|purged_rev_id| *purged_rev_id != rev_id);
+                vec![]
+            }
+            _ => {
+                let merged_revision = rev_compress.merge_revisions(&self.user_id, &self.object_id, merged_revisions)?;
+                vec![SyncRecord {
+                    revision: merged_revision,
+                    state: RevisionState::Ack,
+                    write_to_disk: true,
+                }]
+            }
+        };
+        if purged_rev_ids.is_empty() {
+            return Ok(0);
+        }
+
+        for rev_id in purged_rev_ids.iter() {
+            self.memory_cache.remove(rev_id);
+        }
+        self.disk_cache
+            .delete_and_insert_records(&self.object_id, Some(purged_rev_ids.clone()), inserted_records)?;
+        Ok(purged_rev_ids.len())
+    }
+
+    /// Moves the rev_ids up to the last pinned one from the `compact_seq` back to the `sync_seq`.
+    /// They're kept as they are, only the revisions after the last pinned one get merged.
+    fn exclude_pinned(&self, sync_seq: &mut DeferSyncSequence, compact_seq: &mut VecDeque<i64>) -> FlowyResult<()> {
+        if compact_seq.is_empty() {
+            return Ok(());
+        }
+        let pinned_rev_ids = self.pinned_rev_ids()?;
+        if let Some(last_pinned) = compact_seq.iter().rposition(|rev_id| pinned_rev_ids.contains(rev_id)) {
+            for rev_id in compact_seq.drain(..=last_pinned) {
+                sync_seq.recv(rev_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the revision with rev_id from the sync sequence. The ack of a revision that isn't
+    /// the next one to sync is held until the revisions in front of it are acked.
     pub(crate) async fn ack_revision(&self, rev_id: i64) -> FlowyResult<()> {
-        if self.sync_seq.write().await.ack(&rev_id).is_ok() {
+        self.reconcile_acks().await;
+        let acked_rev_ids = {
+            let mut sync_seq = self.sync_seq.write().await;
+            let is_dead_letter = sync_seq.dead_letters.contains(&rev_id);
+            let acked_rev_ids = sync_seq.ack_in_order(rev_id);
+            if is_dead_letter {
+                self.write_dead_letters(&sync_seq);
+            }
+            acked_rev_ids
+        };
+        for rev_id in acked_rev_ids {
+            self.apply_ack(rev_id).await;
+        }
+        Ok(())
+    }
+
+    /// Moves the acked revision to the `Ack` state. A panic is caught and reported, see
+    /// `AckPanicFallback`.
+    async fn apply_ack(&self, rev_id: i64) {
+        let ack = async {
             self.memory_cache.ack(&rev_id).await;
+            self.lifecycle.record(rev_id, RevLifecycleEvent::Acked);
+            self.run_ack_callbacks(rev_id);
+        };
+        if let Err(panic) = AssertUnwindSafe(ack).catch_unwind().await {
+            let error = FlowyError::internal().context(format!(
+                "The ack of revision {} panicked: {}",
+                rev_id,
+                panic_message(&*panic)
+            ));
+            tracing::error!("[RevisionPersistence]: {} {}", self.object_id, error);
+            if let Some(error_reporter) = self.configuration.error_reporter.as_ref() {
+                error_reporter.report(&format!("ack revision {} of {}", rev_id, self.object_id), &error);
+            }
+            if self.configuration.ack_panic_fallback == AckPanicFallback::Reconcile {
+                self.unreconciled_acks.lock().unwrap().insert(rev_id);
+            }
         }
+    }
+
+    /// Applies the acks that panicked again. The ack that panics again is kept for the next
+    /// access.
+    async fn reconcile_acks(&self) {
+        let rev_ids = std::mem::take(&mut *self.unreconciled_acks.lock().unwrap());
+        for rev_id in rev_ids {
+            tracing::debug!(
+                "[RevisionPersistence]: {} reconciles the ack of {}",
+                self.object_id,
+                rev_id
+            );
+            self.apply_ack(rev_id).await;
+        }
+    }
+
+    /// Remove the revision with rev_id from the sync sequence and write its state to disk.
+    pub(crate) async fn ack_and_persist(&self, rev_id: i64) -> FlowyResult<()> {
+        self.reconcile_acks().await;
+        self.sync_seq.write().await.ack(&rev_id)?;
+        self.lifecycle.record(rev_id, RevLifecycleEvent::Acked);
+        self.memory_cache.ack_and_persist(&rev_id).await?;
+        self.run_ack_callbacks(rev_id);
         Ok(())
     }
 
+    /// Runs the `callback` once the revision is acked, or right away if it's acked already.
+    pub(crate) async fn on_ack(&self, rev_id: i64, callback: AckCallback) {
+        // The callback is registered before checking the state of the revision, so the ack
+        // that lands in between still runs it. Whoever takes it out of the map runs it.
+        self.ack_callbacks.entry(rev_id).or_default().push(callback);
+        let is_acked = self
+            .get(rev_id)
+            .await
+            .map(|record| record.state == RevisionState::Ack)
+            .unwrap_or(false);
+        if is_acked {
+            self.run_ack_callbacks(rev_id);
+        }
+    }
+
+    /// The callbacks run on the blocking threads, so a slow callback doesn't hold up the acks.
+    fn run_ack_callbacks(&self, rev_id: i64) {
+        if let Some((_, callbacks)) = self.ack_callbacks.remove(&rev_id) {
+            for callback in callbacks {
+                let _ = self.executor().spawn_blocking(callback);
+            }
+        }
+    }
+
+    /// Remove the revision with rev_id from the sync sequence. The record is stored under the
+    /// `canonical_rev_id` if the server assigned the revision a different id.
+    ///
+    /// The revisions after it are based on its local rev_id, so the remap is refused while any
+    /// of them is still unacked.
+    pub(crate) async fn ack_with_canonical_rev_id(&self, rev_id: i64, canonical_rev_id: i64) -> FlowyResult<()> {
+        if rev_id == canonical_rev_id {
+            return self.ack_and_persist(rev_id).await;
+        }
+
+        let mut sync_seq = self.sync_seq.write().await;
+        if let Some(pending_rev_id) = sync_seq.first_unacked_after(rev_id) {
+            return Err(FlowyError::internal().context(format!(
+                "Can't remap the revision {} to {}, the revision {} after it is unacked",
+                rev_id, canonical_rev_id, pending_rev_id
+            )));
+        }
+        sync_seq.ack(&rev_id)?;
+        self.lifecycle.record(rev_id, RevLifecycleEvent::Acked);
+        let mut record = self
+            .get(rev_id)
+            .await
+            .ok_or_else(|| FlowyError::record_not_found().context(format!("Can't find the revision: {}", rev_id)))?;
+        record.revision.rev_id = canonical_rev_id;
+        record.ack();
+        record.write_to_disk = true;
+
+        self.memory_cache.remap(&rev_id, record.clone()).await;
+        self.disk_cache
+            .delete_and_insert_records(&self.object_id, Some(vec![rev_id]), vec![record])?;
+        self.rev_id_map.insert(rev_id, canonical_rev_id);
+        self.write_rev_id_map();
+        self.run_ack_callbacks(rev_id);
+        if self.pinned_rev_ids()?.contains(&rev_id) {
+            let tag = self
+                .revision_tags()?
+                .into_iter()
+                .find(|(_, tagged_rev_id)| *tagged_rev_id == rev_id);
+            self.disk_cache.unpin_revision(&self.object_id, rev_id)?;
+            match tag {
+                None => self.disk_cache.pin_revision(&self.object_id, canonical_rev_id)?,
+                Some((tag, _)) => self.disk_cache.tag_revision(&self.object_id, canonical_rev_id, &tag)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the `superseded` revisions as resolved and saves the `revision` that resolved their
+    /// conflict. The resolved revisions are removed from the sync sequence, the new revision is
+    /// appended to it.
+    pub(crate) async fn resolve_conflict(&self, revision: Revision, superseded: &[i64]) -> FlowyResult<()> {
+        let mut sync_seq = self.sync_seq.write().await;
+        let mut records = vec![];
+        for rev_id in superseded {
+            let mut record = self.get(*rev_id).await.ok_or_else(|| {
+                FlowyError::record_not_found().context(format!("Can't find the revision: {}", rev_id))
+            })?;
+            record.state = RevisionState::Resolved;
+            record.write_to_disk = true;
+            self.memory_cache.remove(rev_id);
+            records.push(record);
+        }
+        sync_seq.remove(superseded);
+        self.disk_cache
+            .delete_and_insert_records(&self.object_id, Some(superseded.to_vec()), records)?;
+
+        let rev_id = revision.rev_id;
+        self.add(revision, RevisionState::Sync, true).await?;
+        sync_seq.recv(rev_id)?;
+        Ok(())
+    }
+
+    /// Returns the rev_id assigned by the server if the revision was acked with a different id,
+    /// otherwise returns the passed-in rev_id.
+    pub(crate) fn canonical_rev_id(&self, rev_id: i64) -> i64 {
+        self.rev_id_map
+            .get(&rev_id)
+            .map(|canonical_rev_id| *canonical_rev_id.value())
+            .unwrap_or(rev_id)
+    }
+
     pub(crate) async fn next_sync_revision(&self) -> FlowyResult<Option<Revision>> {
         match self.sync_seq.read().await.next_rev_id() {
             None => Ok(None),
@@ -217,32 +1044,215 @@ where
         }
     }
 
+    /// Returns the next revision that hasn't been pushed to the server if the push window isn't
+    /// full. The revision counts as in flight until it gets acked.
+    pub(crate) async fn next_push_revision(&self, max_push_window: usize) -> FlowyResult<Option<Revision>> {
+        let rev_id = {
+            let mut sync_seq = self.sync_seq.write().await;
+            let push_window = self.configuration.push_window.min(max_push_window.max(1));
+            sync_seq.push(push_window)
+        };
+        match rev_id {
+            None => Ok(None),
+            Some(rev_id) => {
+                self.lifecycle.record(rev_id, RevLifecycleEvent::Pushed);
+                Ok(self.get(rev_id).await.map(|record| record.revision))
+            }
+        }
+    }
+
+    /// Returns the oldest revision that isn't acked to send it again. The revision that was
+    /// already sent `max_push_attempts` times is moved to the dead letters instead, the revisions
+    /// behind it are based on it, so none of them is returned until it's retried. Returns the
+    /// rev_ids that were given up on too.
+    pub(crate) async fn next_resend_revision(&self) -> FlowyResult<(Option<Revision>, Vec<i64>)> {
+        let max_push_attempts = match self.configuration.max_push_attempts {
+            None => return Ok((self.next_sync_revision().await?, vec![])),
+            Some(max_push_attempts) => max_push_attempts,
+        };
+
+        let mut gave_up_rev_ids = vec![];
+        let rev_id = {
+            let mut sync_seq = self.sync_seq.write().await;
+            loop {
+                let rev_id = match sync_seq.next_rev_id() {
+                    None => break None,
+                    Some(rev_id) if sync_seq.is_blocked(rev_id) => break None,
+                    Some(rev_id) => rev_id,
+                };
+                // The revision that was retried may not be pushed yet.
+                if sync_seq.num_of_pushed == 0 && sync_seq.push(self.configuration.push_window).is_some() {
+                    self.lifecycle.record(rev_id, RevLifecycleEvent::Pushed);
+                }
+                if sync_seq.attempts(rev_id) < max_push_attempts {
+                    break Some(rev_id);
+                }
+                sync_seq.dead_letter(rev_id);
+                self.lifecycle.record(
+                    rev_id,
+                    RevLifecycleEvent::GaveUp {
+                        attempts: max_push_attempts,
+                    },
+                );
+                gave_up_rev_ids.push(rev_id);
+            }
+        };
+        if !gave_up_rev_ids.is_empty() {
+            self.write_dead_letters(&*self.sync_seq.read().await);
+        }
+        let revision = match rev_id {
+            None => None,
+            Some(rev_id) => self.get(rev_id).await.map(|record| record.revision),
+        };
+        Ok((revision, gave_up_rev_ids))
+    }
+
+    /// Counts an attempt to send the revision, i.e. it was handed to the web socket without an
+    /// error. A revision that fails to send doesn't use up its `max_push_attempts`.
+    pub(crate) async fn did_send(&self, rev_id: i64) {
+        self.sync_seq.write().await.count_attempt(rev_id);
+    }
+
+    /// Returns the rev_ids that were given up on, see `next_resend_revision`.
+    pub(crate) async fn dead_letters(&self) -> Vec<i64> {
+        self.sync_seq.read().await.dead_letters.iter().cloned().collect()
+    }
+
+    /// Stops pushing at the revision that failed its verification. The revisions behind it are
+    /// based on it, so they aren't pushed either until the object is reset. See
+    /// `with_verify_before_push`.
+    pub(crate) async fn halt_invalid(&self, rev_id: i64, reason: &str) {
+        self.sync_seq.write().await.halt(rev_id);
+        self.lifecycle
+            .record(rev_id, RevLifecycleEvent::Invalid(reason.to_owned()));
+    }
+
+    /// Returns the rev_id that failed its verification, see `halt_invalid`.
+    pub(crate) async fn halted_rev_id(&self) -> Option<i64> {
+        self.sync_seq.read().await.halted_rev_id
+    }
+
+    /// Moves the rev_ids that were given up on back to the sync sequence, each of them gets
+    /// `max_push_attempts` again. Returns the moved rev_ids.
+    pub(crate) async fn retry_dead_letters(&self) -> Vec<i64> {
+        let rev_ids = {
+            let mut sync_seq = self.sync_seq.write().await;
+            let rev_ids = sync_seq.retry_dead_letters();
+            self.write_dead_letters(&sync_seq);
+            rev_ids
+        };
+        for rev_id in rev_ids.iter() {
+            self.lifecycle.record(*rev_id, RevLifecycleEvent::Pending);
+        }
+        rev_ids
+    }
+
     pub(crate) async fn next_sync_rev_id(&self) -> Option<i64> {
         self.sync_seq.read().await.next_rev_id()
     }
 
+    /// Writes the revisions that are waiting for the deferred save to disk.
+    pub(crate) async fn flush(&self) -> FlowyResult<()> {
+        self.memory_cache.flush().await
+    }
+
     pub(crate) fn number_of_sync_records(&self) -> usize {
         self.memory_cache.number_of_sync_records()
     }
 
     pub(crate) fn number_of_records_in_disk(&self) -> usize {
-        match self.disk_cache.read_revision_records(&self.object_id, None) {
-            Ok(records) => records.len(),
+        match self.disk_cache.count_revision_records(&self.object_id) {
+            Ok(count) => count as usize,
             Err(e) => {
-                tracing::error!("Read revision records failed: {:?}", e);
+                tracing::error!("Count revision records failed: {:?}", e);
                 0
             }
         }
     }
 
+    /// Counts the revisions in disk and the ones waiting for the deferred save, without reading
+    /// their data.
+    pub(crate) async fn count_revisions(&self) -> FlowyResult<u64> {
+        // The pending revisions are counted first, a deferred save that completes in between
+        // is then counted in disk instead of being missed.
+        let num_of_pending = self.memory_cache.number_of_pending_records().await as u64;
+        let num_of_saved = self.disk_cache.count_revision_records(&self.object_id)?;
+        Ok(num_of_saved + num_of_pending)
+    }
+
+    /// Scans the revisions in disk without merging them, see `estimate_compaction`.
+    pub(crate) fn compaction_estimate(&self) -> FlowyResult<CompactionEstimate> {
+        let mut records = self.load_all_records(&self.object_id)?;
+        records.sort_by_key(|record| record.revision.rev_id);
+        Ok(estimate_compaction(&records, &self.kept_rev_ids()?))
+    }
+
+    /// Merges each run of small acked revisions into one revision, see `small_revision_runs`.
+    /// The merged revision keeps the rev_id of the last revision of its run, so the revisions
+    /// after the run are still based on it. Returns the number of the deleted revisions, which
+    /// is the `num_of_removed_rows` of the `compaction_estimate` made right before.
+    pub(crate) async fn compact_small_revisions<'a>(
+        &'a self,
+        rev_compress: &Arc<dyn RevisionMergeable + 'a>,
+    ) -> FlowyResult<usize> {
+        // Waits for the merge that is in progress, the runs must not change while they're merged.
+        let _sync_seq = self.sync_seq.write().await;
+        self.memory_cache.flush().await?;
+        let mut records = self.disk_cache.read_revision_records(&self.object_id, None)?;
+        records.sort_by_key(|record| record.revision.rev_id);
+
+        let mut num_of_removed = 0;
+        for run in small_revision_runs(&records, &self.kept_rev_ids()?) {
+            let (first, last) = (&run[0].revision, &run[run.len() - 1].revision);
+            let rev_ids = run.iter().map(|record| record.revision.rev_id).collect::<Vec<i64>>();
+            let revisions = run.iter().map(|record| record.revision.clone()).collect();
+            let merged_revision = rev_compress.merge_revisions(&self.user_id, &self.object_id, revisions)?;
+            let merged_revision = Revision {
+                base_rev_id: first.base_rev_id,
+                rev_id: last.rev_id,
+                ..merged_revision
+            };
+            for rev_id in rev_ids.iter() {
+                self.memory_cache.remove(rev_id);
+            }
+            num_of_removed += rev_ids.len() - 1;
+            self.disk_cache.delete_and_insert_records(
+                &self.object_id,
+                Some(rev_ids),
+                vec![SyncRecord {
+                    revision: merged_revision,
+                    state: RevisionState::Ack,
+                    write_to_disk: true,
+                }],
+            )?;
+        }
+        if num_of_removed > 0 {
+            tracing::info!("Compacted {} revisions of {}", num_of_removed, self.object_id);
+        }
+        Ok(num_of_removed)
+    }
+
+    /// The pinned and the tagged revisions, they're never merged with the others.
+    fn kept_rev_ids(&self) -> FlowyResult<HashSet<i64>> {
+        let mut rev_ids = self.pinned_rev_ids()?.into_iter().collect::<HashSet<i64>>();
+        rev_ids.extend(self.revision_tags()?.into_iter().map(|(_, rev_id)| rev_id));
+        Ok(rev_ids)
+    }
+
     /// The cache gets reset while it conflicts with the remote revisions.
     #[tracing::instrument(level = "trace", skip(self, revisions), err)]
     pub(crate) async fn reset(&self, revisions: Vec<Revision>) -> FlowyResult<()> {
+        // The revisions of a local-only object have nothing to sync with.
+        let state = if self.configuration.is_local_only() {
+            RevisionState::Ack
+        } else {
+            RevisionState::Sync
+        };
         let records = revisions
             .into_iter()
             .map(|revision| SyncRecord {
                 revision,
-                state: RevisionState::Sync,
+                state: state.clone(),
                 write_to_disk: false,
             })
             .collect::<Vec<_>>();
@@ -250,7 +1260,111 @@ where
         self.disk_cache
             .delete_and_insert_records(&self.object_id, None, records.clone())?;
         self.memory_cache.reset_with_revisions(records).await;
-        self.sync_seq.write().await.clear();
+        let mut sync_seq = self.sync_seq.write().await;
+        let had_dead_letters = !sync_seq.dead_letters.is_empty();
+        sync_seq.clear();
+        if had_dead_letters {
+            self.write_dead_letters(&sync_seq);
+        }
+        // The revisions acked with another rev_id were replaced.
+        if !self.rev_id_map.is_empty() {
+            self.rev_id_map.clear();
+            self.write_rev_id_map();
+        }
+        Ok(())
+    }
+
+    /// Keeps the dead letters on disk, they stay dead letters after the object reopens. A failed
+    /// write is only logged, the dead letters are synced again after the object reopens then.
+    fn write_dead_letters(&self, sync_seq: &DeferSyncSequence) {
+        let rev_ids = sync_seq.dead_letters.iter().cloned().collect::<Vec<i64>>();
+        if let Err(e) = self.disk_cache.write_dead_letters(&self.object_id, &rev_ids) {
+            tracing::error!("Save the dead letters of {} failed: {}", self.object_id, e);
+        }
+    }
+
+    /// Keeps the rev_id map on disk. A failed write is only logged, the rev_ids assigned by the
+    /// client don't resolve after the object reopens then.
+    fn write_rev_id_map(&self) {
+        let mut rev_id_map = self
+            .rev_id_map
+            .iter()
+            .map(|entry| (*entry.key(), *entry.value()))
+            .collect::<Vec<(i64, i64)>>();
+        rev_id_map.sort_unstable();
+        if let Err(e) = self.disk_cache.write_rev_id_map(&self.object_id, &rev_id_map) {
+            tracing::error!("Save the rev_id map of {} failed: {}", self.object_id, e);
+        }
+    }
+
+    fn read_dead_letters(&self) -> Vec<i64> {
+        match self.disk_cache.read_dead_letters(&self.object_id) {
+            Ok(rev_ids) => rev_ids,
+            Err(e) => {
+                tracing::error!("Read the dead letters of {} failed: {}", self.object_id, e);
+                vec![]
+            }
+        }
+    }
+
+    /// Captures the records in memory and the revisions that wait to be synced. The sync sequence
+    /// is locked while the records are read, so the state agrees with itself.
+    pub(crate) async fn capture_state(&self, rev_id: i64) -> RevisionCacheState {
+        let sync_seq = self.sync_seq.read().await;
+        let (records, pending_rev_ids) = self.memory_cache.capture().await;
+        // The dead letters are unacked too, they're kept apart again by the ones on disk.
+        let mut sync_rev_ids = sync_seq
+            .rev_ids
+            .iter()
+            .chain(sync_seq.dead_letters.iter())
+            .cloned()
+            .collect::<Vec<i64>>();
+        sync_rev_ids.sort_unstable();
+        sync_rev_ids.dedup();
+        let rev_id_map = self
+            .rev_id_map
+            .iter()
+            .map(|entry| (*entry.key(), *entry.value()))
+            .collect::<Vec<(i64, i64)>>();
+        RevisionCacheState {
+            object_id: self.object_id.clone(),
+            rev_id,
+            records: RevisionCacheState::records_from(records),
+            pending_rev_ids,
+            sync_rev_ids,
+            rev_id_map,
+            head: None,
+        }
+    }
+
+    /// Replaces the records in memory and the sync sequence with the captured ones. It fails
+    /// without changing anything if the state is invalid or if there are revisions that aren't
+    /// written to disk yet, they would be lost.
+    pub(crate) async fn restore_state(&self, state: RevisionCacheState) -> FlowyResult<()> {
+        let records = state.validate(&self.object_id)?;
+        let mut sync_seq = self.sync_seq.write().await;
+        if self.memory_cache.number_of_pending_records().await > 0 {
+            return Err(FlowyError::internal().context(format!(
+                "Restore the state of {} failed: its revisions aren't saved yet",
+                self.object_id
+            )));
+        }
+
+        let dead_letters = self.read_dead_letters();
+        sync_seq.clear();
+        for rev_id in state.sync_rev_ids {
+            if dead_letters.contains(&rev_id) {
+                sync_seq.dead_letters.insert(rev_id);
+            } else {
+                sync_seq.rev_ids.push_back(rev_id);
+            }
+        }
+        self.memory_cache.restore(records, state.pending_rev_ids).await;
+        self.rev_id_map.clear();
+        for (rev_id, canonical_rev_id) in state.rev_id_map {
+            self.rev_id_map.insert(rev_id, canonical_rev_id);
+        }
+        self.write_rev_id_map();
         Ok(())
     }
 
@@ -272,13 +1386,14 @@ where
     async fn compact(&self, range: &RevisionRange, new_revision: Revision) -> FlowyResult<()> {
         self.memory_cache.remove_with_range(range);
         let rev_ids = range.to_rev_ids();
-        self.disk_cache
-            .delete_revision_records(&self.object_id, Some(rev_ids))?;
+        self.disk_cache.delete_revs(&self.object_id, &rev_ids)?;
         self.add(new_revision, RevisionState::Sync, true).await?;
         Ok(())
     }
 
     pub async fn get(&self, rev_id: i64) -> Option<SyncRecord> {
+        self.reconcile_acks().await;
+        let rev_id = self.canonical_rev_id(rev_id);
         match self.memory_cache.get(&rev_id).await {
             None => match self
                 .disk_cache
@@ -311,6 +1426,20 @@ where
         Ok(records)
     }
 
+    /// Returns the records of the object in the order of their rev_ids, including the ones that
+    /// aren't written to disk yet.
+    pub(crate) fn current_records(&self) -> FlowyResult<Vec<SyncRecord>> {
+        let mut records = self
+            .load_all_records(&self.object_id)?
+            .into_iter()
+            .map(|record| (record.revision.rev_id, record))
+            .collect::<BTreeMap<i64, SyncRecord>>();
+        for record in self.memory_cache.records() {
+            records.insert(record.revision.rev_id, record);
+        }
+        Ok(records.into_values().collect())
+    }
+
     // Read the revision which rev_id >= range.start && rev_id <= range.end
     pub async fn revisions_in_range(&self, range: &RevisionRange) -> FlowyResult<Vec<Revision>> {
         let range = range.clone();
@@ -319,9 +1448,15 @@ where
         if records.len() != range_len {
             let disk_cache = self.disk_cache.clone();
             let object_id = self.object_id.clone();
-            records = spawn_blocking(move || disk_cache.read_revision_records_with_range(&object_id, &range))
-                .await
-                .map_err(internal_error)??;
+            let read = move || disk_cache.read_revision_records_with_range(&object_id, &range);
+            records = match self.configuration.priority_scheduler.as_ref() {
+                None => self.executor().spawn_blocking(read).await,
+                Some(scheduler) => {
+                    let priority = scheduler.priority_of(&self.object_id);
+                    scheduler.spawn_blocking(self.executor(), priority, read).await
+                }
+            }
+            .map_err(internal_error)??;
 
             if records.len() != range_len {
                 tracing::error!("Expect revision len {},but receive {}", range_len, records.len());
@@ -333,15 +1468,67 @@ where
             .collect::<Vec<Revision>>())
     }
 
+    /// Deletes the revisions in the range except the pinned ones.
     #[allow(dead_code)]
     pub fn delete_revisions_from_range(&self, range: RevisionRange) -> FlowyResult<()> {
-        self.disk_cache
-            .delete_revision_records(&self.object_id, Some(range.to_rev_ids()))?;
+        let pinned_rev_ids = self.pinned_rev_ids()?;
+        let mut rev_ids = range.to_rev_ids();
+        rev_ids.retain(|rev_id| !pinned_rev_ids.contains(rev_id));
+        self.disk_cache.delete_revs(&self.object_id, &rev_ids)?;
         Ok(())
     }
 }
 
-impl<C> RevisionMemoryCacheDelegate for Arc<dyn RevisionDiskCache<C, Error = FlowyError>> {
+/// The merged revision is assumed to be as large as the largest revision of its run. It holds
+/// for the typing revisions that make up most of the small runs, each of them repeats the
+/// same retain and only inserts a few characters.
+fn estimate_compaction(records: &[SyncRecord], kept_rev_ids: &HashSet<i64>) -> CompactionEstimate {
+    let mut estimate = CompactionEstimate::default();
+    for run in small_revision_runs(records, kept_rev_ids) {
+        let sizes = run.iter().map(|record| record_size(record)).collect::<Vec<usize>>();
+        estimate.num_of_runs += 1;
+        estimate.num_of_removed_rows += run.len() - 1;
+        estimate.num_of_removed_bytes += sizes.iter().sum::<usize>() - sizes.iter().max().unwrap();
+    }
+    estimate
+}
+
+/// Returns the runs of at least two small consecutive acked revisions, the `records` are sorted
+/// by rev_id. The revisions that aren't synced yet are merged by the `sync_seq` instead, and the
+/// resolved, pinned and tagged ones are kept for the history, so they end the run.
+fn small_revision_runs<'a>(records: &'a [SyncRecord], kept_rev_ids: &HashSet<i64>) -> Vec<Vec<&'a SyncRecord>> {
+    let mut runs = vec![];
+    let mut run: Vec<&SyncRecord> = vec![];
+    for record in records {
+        let is_small = record.revision.bytes.len() < COMPACTION_REVISION_SIZE_LIMIT;
+        if !is_small || record.state != RevisionState::Ack || kept_rev_ids.contains(&record.revision.rev_id) {
+            if run.len() > 1 {
+                runs.push(std::mem::take(&mut run));
+            }
+            run.clear();
+            continue;
+        }
+        run.push(record);
+    }
+    if run.len() > 1 {
+        runs.push(run);
+    }
+    runs
+}
+
+/// The size of the record's row: its data, md5, object_id and the rev_id, base_rev_id and state.
+fn record_size(record: &SyncRecord) -> usize {
+    let revision = &record.revision;
+    revision.bytes.len() + revision.md5.len() + revision.object_id.len() + 3 * std::mem::size_of::<i64>()
+}
+
+struct RevisionDiskCacheDelegate<C> {
+    disk_cache: Arc<dyn RevisionDiskCache<C, Error = FlowyError>>,
+    mirror: Option<Arc<dyn RevisionMirror>>,
+    durable_writes: bool,
+}
+
+impl<C> RevisionMemoryCacheDelegate for RevisionDiskCacheDelegate<C> {
     fn send_sync(&self, mut records: Vec<SyncRecord>) -> FlowyResult<()> {
         records.retain(|record| record.write_to_disk);
         if !records.is_empty() {
@@ -349,22 +1536,64 @@ impl<C> RevisionMemoryCacheDelegate for Arc<dyn RevisionDiskCache<C, Error = Flo
                 "checkpoint_result",
                 &format!("{} records were saved", records.len()).as_str(),
             );
-            self.create_revision_records(records)?;
+            let revisions = match self.mirror {
+                None => vec![],
+                Some(_) => records.iter().map(|record| record.revision.clone()).collect(),
+            };
+            if self.durable_writes {
+                self.disk_cache.create_durable_revision_records(records)?;
+            } else {
+                self.disk_cache.create_revision_records(records)?;
+            }
+            if let Some(mirror) = self.mirror.as_ref() {
+                for revision in revisions {
+                    if let Err(e) = mirror.mirror(&revision) {
+                        tracing::error!("Mirror revision {} failed: {:?}", revision.rev_id, e);
+                    }
+                }
+            }
         }
         Ok(())
     }
 
-    fn receive_ack(&self, object_id: &str, rev_id: i64) {
+    fn receive_ack(&self, object_id: &str, rev_id: i64) -> FlowyResult<()> {
         let changeset = RevisionChangeset {
             object_id: object_id.to_string(),
             rev_id,
             state: RevisionState::Ack,
         };
-        match self.update_revision_record(vec![changeset]) {
-            Ok(_) => {}
-            Err(e) => tracing::error!("{}", e),
+        self.disk_cache.update_revision_record(vec![changeset])
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => panic
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| "unknown panic".to_owned()),
+    }
+}
+
+/// Returns the first revision waiting to be synced that isn't based on the revision before it,
+/// the `records` are in the order of their rev_ids. The resolved revisions are skipped, the
+/// revision that replaces them is based on the revision before them.
+fn first_discontinuous_record<'a>(records: &[&'a SyncRecord]) -> Option<&'a SyncRecord> {
+    let mut previous: Option<&SyncRecord> = None;
+    for record in records
+        .iter()
+        .copied()
+        .filter(|record| record.state != RevisionState::Resolved)
+    {
+        if let Some(previous) = previous {
+            if record.state == RevisionState::Sync && record.revision.base_rev_id != previous.revision.rev_id {
+                return Some(record);
+            }
         }
+        previous = Some(record);
     }
+    None
 }
 
 #[derive(Default)]
@@ -372,6 +1601,18 @@ struct DeferSyncSequence {
     rev_ids: VecDeque<i64>,
     compact_index: Option<usize>,
     compact_length: usize,
+    /// The first `num_of_pushed` rev_ids were pushed to the server and wait for their acks.
+    num_of_pushed: usize,
+    /// The acks that arrived before the acks of the rev_ids in front of them.
+    early_acks: BTreeSet<i64>,
+    /// The number of times each rev_id was handed out to be sent.
+    push_attempts: HashMap<i64, usize>,
+    /// The rev_ids that were taken out of the list after too many attempts. They're still
+    /// unacked, see `dead_letter`.
+    dead_letters: BTreeSet<i64>,
+    /// The rev_id that failed its verification. Neither it nor the rev_ids behind it are pushed,
+    /// they're based on it. See `halt`. The rev_ids behind a dead letter aren't pushed either.
+    halted_rev_id: Option<i64>,
 }
 
 impl DeferSyncSequence {
@@ -406,6 +1647,16 @@ impl DeferSyncSequence {
         Ok(())
     }
 
+    /// Returns the first unacked rev_id that is greater than the rev_id, including the dead letters.
+    fn first_unacked_after(&self, rev_id: i64) -> Option<i64> {
+        self.rev_ids
+            .iter()
+            .chain(self.dead_letters.iter())
+            .filter(|pending_rev_id| **pending_rev_id > rev_id)
+            .min()
+            .cloned()
+    }
+
     /// Removes the rev_id from the list
     fn ack(&mut self, rev_id: &i64) -> FlowyResult<()> {
         let cur_rev_id = self.rev_ids.front().cloned();
@@ -424,6 +1675,8 @@ impl DeferSyncSequence {
             }
 
             let pop_rev_id = self.rev_ids.pop_front();
+            self.num_of_pushed = self.num_of_pushed.saturating_sub(1);
+            self.push_attempts.remove(rev_id);
             if let (Some(compact_rev_id), Some(pop_rev_id)) = (compact_rev_id, pop_rev_id) {
                 if compact_rev_id <= pop_rev_id && self.compact_length > 0 {
                     self.compact_length -= 1;
@@ -433,20 +1686,158 @@ impl DeferSyncSequence {
         Ok(())
     }
 
+    /// Acks the rev_id if it's at the front, followed by the rev_ids whose acks arrived early
+    /// and that come to the front after it. The ack of a rev_id that's further back is held
+    /// until the rev_id reaches the front. Returns the acked rev_ids in order.
+    fn ack_in_order(&mut self, rev_id: i64) -> Vec<i64> {
+        // The server accepted the rev_id that was given up on after all.
+        if self.dead_letters.remove(&rev_id) {
+            return vec![rev_id];
+        }
+
+        let mut acked_rev_ids = vec![];
+        if self.next_rev_id() != Some(rev_id) {
+            if !self.rev_ids.contains(&rev_id) {
+                tracing::warn!("Ignore the ack of {}, it's not in the sync sequence", rev_id);
+            } else if self.early_acks.len() >= MAX_EARLY_ACKS {
+                tracing::warn!("Drop the early ack of {}, {} acks are waiting", rev_id, MAX_EARLY_ACKS);
+            } else {
+                tracing::trace!("Hold the ack of {} until {:?} is acked", rev_id, self.next_rev_id());
+                self.early_acks.insert(rev_id);
+            }
+            return acked_rev_ids;
+        }
+
+        let mut next_rev_id = Some(rev_id);
+        while let Some(rev_id) = next_rev_id {
+            if self.ack(&rev_id).is_err() {
+                break;
+            }
+            acked_rev_ids.push(rev_id);
+            next_rev_id = self.next_rev_id().filter(|rev_id| self.early_acks.remove(rev_id));
+        }
+        acked_rev_ids
+    }
+
     fn next_rev_id(&self) -> Option<i64> {
         self.rev_ids.front().cloned()
     }
 
+    /// Marks the first rev_id that wasn't pushed as pushed if there are less than `push_window`
+    /// rev_ids in flight.
+    fn push(&mut self, push_window: usize) -> Option<i64> {
+        if self.num_of_pushed >= push_window {
+            return None;
+        }
+        let rev_id = self.rev_ids.get(self.num_of_pushed).cloned()?;
+        if self.is_blocked(rev_id) {
+            return None;
+        }
+        self.num_of_pushed += 1;
+        Some(rev_id)
+    }
+
+    /// Removes the rev_ids wherever they are in the list. The pending compaction is dropped
+    /// because the removed rev_ids may be part of it.
+    fn remove(&mut self, rev_ids: &[i64]) {
+        let num_of_removed_pushed = self
+            .rev_ids
+            .iter()
+            .take(self.num_of_pushed)
+            .filter(|rev_id| rev_ids.contains(rev_id))
+            .count();
+        self.num_of_pushed -= num_of_removed_pushed;
+        self.rev_ids.retain(|rev_id| !rev_ids.contains(rev_id));
+        self.early_acks.retain(|rev_id| !rev_ids.contains(rev_id));
+        self.push_attempts.retain(|rev_id, _| !rev_ids.contains(rev_id));
+        if self.halted_rev_id.map_or(false, |rev_id| rev_ids.contains(&rev_id)) {
+            self.halted_rev_id = None;
+        }
+        self.compact_index = None;
+        self.compact_length = 0;
+    }
+
     fn clear(&mut self) {
         self.compact_index = None;
         self.compact_length = 0;
+        self.num_of_pushed = 0;
         self.rev_ids.clear();
+        self.early_acks.clear();
+        self.push_attempts.clear();
+        self.dead_letters.clear();
+        self.halted_rev_id = None;
+    }
+
+    /// Counts an attempt to send the rev_id if it's in the list.
+    fn count_attempt(&mut self, rev_id: i64) {
+        if self.rev_ids.contains(&rev_id) {
+            *self.push_attempts.entry(rev_id).or_insert(0) += 1;
+        }
+    }
+
+    /// Returns the number of the times the rev_id was sent.
+    fn attempts(&self, rev_id: i64) -> usize {
+        self.push_attempts.get(&rev_id).cloned().unwrap_or(0)
+    }
+
+    /// Takes the rev_id out of the list without acking it. The rev_ids behind it are based on
+    /// it, they're held back until it's retried, see `is_blocked`.
+    fn dead_letter(&mut self, rev_id: i64) {
+        self.remove(&[rev_id]);
+        self.dead_letters.insert(rev_id);
+    }
+
+    /// Stops pushing at the rev_id, it's kept in the list unpushed. The rev_ids in front of it
+    /// are still pushed and acked.
+    fn halt(&mut self, rev_id: i64) {
+        if let Some(index) = self.rev_ids.iter().position(|other| *other == rev_id) {
+            self.num_of_pushed = self.num_of_pushed.min(index);
+        }
+        self.push_attempts.remove(&rev_id);
+        self.halted_rev_id = Some(
+            self.halted_rev_id
+                .map_or(rev_id, |halted_rev_id| halted_rev_id.min(rev_id)),
+        );
+    }
+
+    /// Returns true if the rev_id is based on the revision that failed its verification or that
+    /// was given up on.
+    fn is_blocked(&self, rev_id: i64) -> bool {
+        self.halted_rev_id
+            .map_or(false, |halted_rev_id| rev_id >= halted_rev_id)
+            || self
+                .dead_letters
+                .iter()
+                .next()
+                .map_or(false, |dead_letter| rev_id > *dead_letter)
+    }
+
+    /// Puts the dead letters back in the list in the order of the rev_ids. The rev_ids in flight
+    /// behind them are pushed again after them.
+    fn retry_dead_letters(&mut self) -> Vec<i64> {
+        let rev_ids = std::mem::take(&mut self.dead_letters).into_iter().collect::<Vec<i64>>();
+        for rev_id in rev_ids.iter() {
+            let index = self
+                .rev_ids
+                .iter()
+                .position(|other| other > rev_id)
+                .unwrap_or(self.rev_ids.len());
+            self.rev_ids.insert(index, *rev_id);
+            self.num_of_pushed = self.num_of_pushed.min(index);
+        }
+        if !rev_ids.is_empty() {
+            self.compact_index = None;
+            self.compact_length = 0;
+        }
+        rev_ids
     }
 
-    // Compact the rev_ids into one except the current synchronizing rev_id.
+    // Compact the rev_ids into one except the current synchronizing rev_id. The pushed rev_ids
+    // are never compacted, the server acks them with their own rev_ids.
     fn compact(&mut self) -> VecDeque<i64> {
         let mut compact_seq = VecDeque::with_capacity(self.rev_ids.len());
         if let Some(start) = self.compact_index {
+            let start = start.max(self.num_of_pushed);
             if start < self.rev_ids.len() {
                 let seq = self.rev_ids.split_off(start);
                 compact_seq.extend(seq);
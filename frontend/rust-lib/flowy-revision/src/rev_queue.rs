@@ -1,8 +1,9 @@
-use crate::{RevIdCounter, RevisionMergeable, RevisionPersistence};
+use crate::{RevIdCounter, RevLifecycleEvent, RevisionMergeable, RevisionPersistence};
 use async_stream::stream;
 use bytes::Bytes;
 use flowy_error::FlowyError;
 use flowy_http_model::revision::Revision;
+use flowy_revision_persistence::SyncRecord;
 use futures::stream::StreamExt;
 use std::sync::Arc;
 use tokio::sync::mpsc::{Receiver, Sender};
@@ -15,6 +16,14 @@ pub(crate) enum RevCommand {
         object_md5: String,
         ret: Ret<i64>,
     },
+    /// Same as `RevisionData`, but the revision is written with the `records` of other objects
+    /// in one transaction.
+    RevisionDataWithRecords {
+        data: Bytes,
+        object_md5: String,
+        records: Vec<SyncRecord>,
+        ret: Ret<i64>,
+    },
 }
 
 pub(crate) struct RevQueue<Connection> {
@@ -79,6 +88,9 @@ where
                 let base_rev_id = self.rev_id_counter.value();
                 let rev_id = self.rev_id_counter.next_id();
                 let revision = Revision::new(&self.object_id, base_rev_id, rev_id, data, data_md5);
+                self.rev_persistence
+                    .lifecycle()
+                    .record(rev_id, RevLifecycleEvent::Created);
 
                 let new_rev_id = self
                     .rev_persistence
@@ -88,6 +100,35 @@ where
                 self.rev_id_counter.set(new_rev_id);
                 let _ = ret.send(Ok(new_rev_id));
             }
+            RevCommand::RevisionDataWithRecords {
+                data,
+                object_md5,
+                records,
+                ret,
+            } => {
+                let base_rev_id = self.rev_id_counter.value();
+                let rev_id = self.rev_id_counter.next_id();
+                let revision = Revision::new(&self.object_id, base_rev_id, rev_id, data, object_md5);
+                self.rev_persistence
+                    .lifecycle()
+                    .record(rev_id, RevLifecycleEvent::Created);
+
+                match self
+                    .rev_persistence
+                    .add_local_revision_with_records(revision, records)
+                    .await
+                {
+                    Ok(rev_id) => {
+                        self.rev_id_counter.set(rev_id);
+                        let _ = ret.send(Ok(rev_id));
+                    }
+                    Err(e) => {
+                        // Nothing was saved, so the rev_id is given to the next revision.
+                        self.rev_id_counter.set(base_rev_id);
+                        let _ = ret.send(Err(e));
+                    }
+                }
+            }
         }
         Ok(())
     }
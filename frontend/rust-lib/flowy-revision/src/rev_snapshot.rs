@@ -1,7 +1,7 @@
 #![allow(clippy::all)]
 #![allow(dead_code)]
 #![allow(unused_variables)]
-use crate::{RevIdCounter, RevisionMergeable, RevisionObjectDeserializer, RevisionPersistence};
+use crate::{composable_revisions, RevIdCounter, RevisionMergeable, RevisionObjectDeserializer, RevisionPersistence};
 use bytes::Bytes;
 use flowy_error::FlowyResult;
 use flowy_http_model::revision::Revision;
@@ -19,8 +19,25 @@ pub trait RevisionSnapshotDiskCache: Send + Sync {
     fn read_snapshot(&self, rev_id: i64) -> FlowyResult<Option<RevisionSnapshot>>;
 
     fn read_last_snapshot(&self) -> FlowyResult<Option<RevisionSnapshot>>;
+
+    /// Moves the `snapshot` out of the snapshots into a version named `name`, so it's never
+    /// restored on its own again but the user can still get its content back. The audit entry
+    /// of the move is written with it. The snapshot is kept as it is by default.
+    fn demote_snapshot(&self, snapshot: &RevisionSnapshot, name: &str) -> FlowyResult<()> {
+        Ok(())
+    }
+
+    /// Returns the names and the snapshots moved by `demote_snapshot`, in ascending order of the
+    /// rev_id.
+    fn read_demoted_snapshots(&self) -> FlowyResult<Vec<(String, RevisionSnapshot)>> {
+        Ok(vec![])
+    }
 }
 
+/// The name of the version that keeps the snapshot that couldn't be confirmed, see
+/// `RevisionManager::initialize`.
+pub const RECOVERED_BACKUP_VERSION: &str = "recovered backup";
+
 /// Do nothing but just used to clam the rust compiler about the generic parameter `SP` of `RevisionManager`
 ///  
 pub struct PhantomSnapshotPersistence();
@@ -78,12 +95,13 @@ where
         }
     }
 
-    pub async fn generate_snapshot(&self) {
-        if let Some((rev_id, bytes)) = self.generate_snapshot_data() {
-            if let Err(e) = self.rev_snapshot_persistence.write_snapshot(rev_id, bytes.to_vec()) {
-                tracing::error!("Save snapshot failed: {}", e);
-            }
+    /// Writes the snapshot of the object's current revisions. Nothing is written if the object
+    /// has no revisions.
+    pub async fn generate_snapshot(&self) -> FlowyResult<()> {
+        if let Some((rev_id, bytes)) = self.generate_snapshot_data()? {
+            self.rev_snapshot_persistence.write_snapshot(rev_id, bytes.to_vec())?;
         }
+        Ok(())
     }
 
     /// Find the nearest revision base on the passed-in rev_id
@@ -127,35 +145,34 @@ where
             .rev_snapshot_persistence
             .should_generate_snapshot_from_range(start_rev_id, current_rev_id)
         {
-            if let Some((rev_id, bytes)) = self.generate_snapshot_data() {
-                let disk_cache = self.rev_snapshot_persistence.clone();
-                tokio::spawn(async move {
-                    let _ = disk_cache.write_snapshot(rev_id, bytes.to_vec());
-                });
+            match self.generate_snapshot_data() {
+                Ok(Some((rev_id, bytes))) => {
+                    let disk_cache = self.rev_snapshot_persistence.clone();
+                    self.rev_persistence.executor().spawn(async move {
+                        if let Err(e) = disk_cache.write_snapshot(rev_id, bytes.to_vec()) {
+                            tracing::error!("Save snapshot failed: {}", e);
+                        }
+                    });
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Generate snapshot failed: {}", e),
             }
             self.set_start_rev_id(current_rev_id);
         }
     }
 
-    fn generate_snapshot_data(&self) -> Option<(i64, Bytes)> {
-        let revisions = self
-            .rev_persistence
-            .load_all_records(&self.object_id)
-            .map(|records| {
-                records
-                    .into_iter()
-                    .map(|record| record.revision)
-                    .collect::<Vec<Revision>>()
-            })
-            .ok()?;
+    fn generate_snapshot_data(&self) -> FlowyResult<Option<(i64, Bytes)>> {
+        let revisions = composable_revisions(&self.rev_persistence.load_all_records(&self.object_id)?);
 
         if revisions.is_empty() {
-            return None;
+            return Ok(None);
         }
 
-        let data = self.rev_compress.combine_revisions(revisions).ok()?;
-        let rev_id = self.rev_id_counter.value();
-        Some((rev_id, data))
+        // The counter is 0 if the object wasn't initialized, e.g. for a closed document.
+        let last_rev_id = revisions.iter().map(|revision| revision.rev_id).max().unwrap_or(0);
+        let rev_id = self.rev_id_counter.value().max(last_rev_id);
+        let data = self.rev_compress.combine_snapshot(revisions)?;
+        Ok(Some((rev_id, data)))
     }
 
     fn get_start_rev_id(&self) -> i64 {
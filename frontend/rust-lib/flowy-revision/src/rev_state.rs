@@ -0,0 +1,149 @@
+use flowy_error::{FlowyError, FlowyResult};
+use flowy_http_model::revision::Revision;
+use flowy_revision_persistence::{RevisionState, SyncRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The in-memory state of an object's revisions, see `RevisionManager::capture_state`. It's
+/// restored into a new `RevisionManager` of the same object, e.g. when the app resumes, without
+/// reading the revisions from disk again.
+///
+/// The revisions that were pushed but not acked yet are pushed again after the restore, and the
+/// pending compaction of the revisions is dropped. The object composed from the revisions is
+/// kept as its head, so `RevisionManager::initialize_from_state` doesn't compose it again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevisionCacheState {
+    pub(crate) object_id: String,
+    pub(crate) rev_id: i64,
+    pub(crate) records: Vec<RevisionRecordState>,
+    /// The rev_ids of the records that aren't written to disk yet.
+    pub(crate) pending_rev_ids: Vec<i64>,
+    /// The rev_ids of the records that wait to be synced, in the order they're synced.
+    pub(crate) sync_rev_ids: Vec<i64>,
+    /// The rev_ids assigned by the client mapped to the rev_ids assigned by the server.
+    pub(crate) rev_id_map: Vec<(i64, i64)>,
+    /// The object composed from the revisions up to the `rev_id`. The states captured before the
+    /// head was kept don't have it.
+    #[serde(default)]
+    pub(crate) head: Option<RevisionHeadState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RevisionHeadState {
+    pub(crate) rev_id: i64,
+    pub(crate) bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RevisionRecordState {
+    revision: Revision,
+    state: i32,
+    write_to_disk: bool,
+}
+
+impl RevisionCacheState {
+    pub fn object_id(&self) -> &str {
+        &self.object_id
+    }
+
+    pub fn rev_id(&self) -> i64 {
+        self.rev_id
+    }
+
+    pub fn to_bytes(&self) -> FlowyResult<Vec<u8>> {
+        serde_json::to_vec(self).map_err(|e| FlowyError::serde().context(e))
+    }
+
+    /// Reads the state from the bytes of `to_bytes`. The state is validated when it's restored.
+    pub fn from_bytes(bytes: &[u8]) -> FlowyResult<Self> {
+        serde_json::from_slice(bytes).map_err(|e| FlowyError::invalid_data().context(e))
+    }
+
+    pub(crate) fn records_from(records: Vec<SyncRecord>) -> Vec<RevisionRecordState> {
+        records
+            .into_iter()
+            .map(|record| RevisionRecordState {
+                revision: record.revision,
+                state: record.state as i32,
+                write_to_disk: record.write_to_disk,
+            })
+            .collect()
+    }
+
+    /// Checks that the state belongs to the object `object_id` and agrees with itself, then
+    /// returns its records.
+    pub(crate) fn validate(&self, object_id: &str) -> FlowyResult<Vec<SyncRecord>> {
+        if self.object_id != object_id {
+            return Err(invalid_state(format!(
+                "it belongs to {} instead of {}",
+                self.object_id, object_id
+            )));
+        }
+
+        let mut rev_ids = HashSet::new();
+        let mut records = Vec::with_capacity(self.records.len());
+        for record in self.records.iter() {
+            let revision = &record.revision;
+            if revision.object_id != object_id {
+                return Err(invalid_state(format!(
+                    "the revision {} belongs to {}",
+                    revision.rev_id, revision.object_id
+                )));
+            }
+            if revision.rev_id > self.rev_id {
+                return Err(invalid_state(format!(
+                    "the revision {} is newer than the rev_id {}",
+                    revision.rev_id, self.rev_id
+                )));
+            }
+            if !rev_ids.insert(revision.rev_id) {
+                return Err(invalid_state(format!("the revision {} is duplicated", revision.rev_id)));
+            }
+            let state = match record.state {
+                0 => RevisionState::Sync,
+                1 => RevisionState::Ack,
+                2 => RevisionState::Resolved,
+                state => {
+                    return Err(invalid_state(format!(
+                        "the revision {} has the unknown state {}",
+                        revision.rev_id, state
+                    )))
+                }
+            };
+            records.push(SyncRecord {
+                revision: revision.clone(),
+                state,
+                write_to_disk: record.write_to_disk,
+            });
+        }
+
+        if let Some(rev_id) = self.pending_rev_ids.iter().find(|rev_id| !rev_ids.contains(rev_id)) {
+            return Err(invalid_state(format!("the pending revision {} is missing", rev_id)));
+        }
+        for rev_id in self.sync_rev_ids.iter() {
+            match records.iter().find(|record| record.revision.rev_id == *rev_id) {
+                None => return Err(invalid_state(format!("the revision {} to sync is missing", rev_id))),
+                Some(record) if record.state != RevisionState::Sync => {
+                    return Err(invalid_state(format!("the revision {} to sync is synced", rev_id)))
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(head) = self.head.as_ref() {
+            if head.rev_id != self.rev_id {
+                return Err(invalid_state(format!(
+                    "the head {} isn't at the rev_id {}",
+                    head.rev_id, self.rev_id
+                )));
+            }
+        }
+        if self.sync_rev_ids.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(invalid_state("the revisions to sync are out of order".to_owned()));
+        }
+        Ok(records)
+    }
+}
+
+fn invalid_state(reason: String) -> FlowyError {
+    FlowyError::invalid_data().context(format!("Invalid revision state: {}", reason))
+}
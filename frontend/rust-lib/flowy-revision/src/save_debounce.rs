@@ -0,0 +1,130 @@
+use crate::REVISION_WRITE_INTERVAL_IN_MILLIS;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// The power source of the device, the host application updates it when it changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    /// The host didn't tell. Treated as plugged in.
+    Unknown = 0,
+    OnBattery = 1,
+    PluggedIn = 2,
+}
+
+impl PowerState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => PowerState::OnBattery,
+            2 => PowerState::PluggedIn,
+            _ => PowerState::Unknown,
+        }
+    }
+}
+
+/// Decides when the revisions that are waiting in memory get written to disk.
+///
+/// The revisions are saved once the user pauses typing. During sustained typing, they're saved
+/// at the latest when the oldest unsaved revision has waited for the window, which is longer
+/// on battery to write less often. They're saved right away if they get too large to lose.
+///
+/// The clones share the power state, so setting it applies to every object that was
+/// configured with this debounce.
+#[derive(Debug, Clone)]
+pub struct SaveDebounceConfiguration {
+    /// Saves once no revision was added for this long.
+    pub pause: Duration,
+    /// The longest time a revision waits in memory, it's the window on battery.
+    pub max_window: Duration,
+    /// The window when plugged in. It's clamped to the `max_window`.
+    pub plugged_in_window: Duration,
+    /// Saves right away once the unsaved revisions reach this many bytes.
+    pub max_unsaved_bytes: usize,
+    power_state: Arc<AtomicU8>,
+}
+
+impl std::default::Default for SaveDebounceConfiguration {
+    fn default() -> Self {
+        Self {
+            pause: Duration::from_millis(REVISION_WRITE_INTERVAL_IN_MILLIS),
+            max_window: Duration::from_secs(2),
+            plugged_in_window: Duration::from_secs(1),
+            max_unsaved_bytes: 64 * 1024,
+            power_state: Arc::new(AtomicU8::new(PowerState::Unknown as u8)),
+        }
+    }
+}
+
+impl SaveDebounceConfiguration {
+    pub fn power_state(&self) -> PowerState {
+        PowerState::from_u8(self.power_state.load(Ordering::SeqCst))
+    }
+
+    /// The new state applies to the revisions that are added after the call.
+    pub fn set_power_state(&self, power_state: PowerState) {
+        self.power_state.store(power_state as u8, Ordering::SeqCst);
+    }
+
+    /// The longest time a revision waits in memory with the current power state.
+    pub fn window(&self) -> Duration {
+        match self.power_state() {
+            PowerState::OnBattery => self.max_window,
+            PowerState::PluggedIn | PowerState::Unknown => self.plugged_in_window.min(self.max_window),
+        }
+    }
+}
+
+/// Tracks the unsaved revisions of one object and tells when to save them. The time is passed
+/// in, so the schedule can be checked without waiting.
+#[derive(Debug)]
+pub struct SaveScheduler {
+    configuration: SaveDebounceConfiguration,
+    first_unsaved_at: Option<Instant>,
+    last_change_at: Option<Instant>,
+    unsaved_bytes: usize,
+}
+
+impl SaveScheduler {
+    pub fn new(configuration: SaveDebounceConfiguration) -> Self {
+        Self {
+            configuration,
+            first_unsaved_at: None,
+            last_change_at: None,
+            unsaved_bytes: 0,
+        }
+    }
+
+    /// Records the revision of `num_of_bytes` that was added at `now`. Returns when to save.
+    pub fn record_change(&mut self, now: Instant, num_of_bytes: usize) -> Instant {
+        if self.first_unsaved_at.is_none() {
+            self.first_unsaved_at = Some(now);
+        }
+        self.last_change_at = Some(now);
+        self.unsaved_bytes += num_of_bytes;
+        self.next_save().unwrap_or(now)
+    }
+
+    /// Returns when to save the unsaved revisions, or None if there is nothing to save.
+    pub fn next_save(&self) -> Option<Instant> {
+        let first_unsaved_at = self.first_unsaved_at?;
+        let last_change_at = self.last_change_at.unwrap_or(first_unsaved_at);
+        if self.unsaved_bytes >= self.configuration.max_unsaved_bytes {
+            return Some(last_change_at);
+        }
+        let after_pause = last_change_at + self.configuration.pause;
+        let window_end = first_unsaved_at + self.configuration.window();
+        Some(after_pause.min(window_end))
+    }
+
+    /// Called after the unsaved revisions were written to disk.
+    pub fn did_save(&mut self) {
+        self.first_unsaved_at = None;
+        self.last_change_at = None;
+        self.unsaved_bytes = 0;
+    }
+
+    pub fn unsaved_bytes(&self) -> usize {
+        self.unsaved_bytes
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Where the revision that was applied to the object came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevisionAuthor {
+    /// The revision was sent to the server by this client.
+    Local,
+    /// The revision was pushed by the server.
+    Remote,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncLoopConfiguration {
+    /// The loop is detected when the same content goes back and forth between the authors
+    /// more than this many times.
+    pub max_repeats: usize,
+    /// Only the revisions applied within the window are compared.
+    pub window: Duration,
+    /// The history is cleared if no revision is applied for this long.
+    pub quiet_period: Duration,
+    /// The number of the revisions kept in the history.
+    pub history_len: usize,
+}
+
+impl std::default::Default for SyncLoopConfiguration {
+    fn default() -> Self {
+        Self {
+            max_repeats: 5,
+            window: Duration::from_secs(10),
+            quiet_period: Duration::from_secs(30),
+            history_len: 32,
+        }
+    }
+}
+
+/// Receives the sync loops detected by the `ConflictController`.
+pub trait SyncLoopObserver: Send + Sync {
+    /// Called once when the loop is detected. The object stops sending its revisions until
+    /// `ConflictController::resume_sync` is called.
+    fn did_detect_sync_loop(&self, object_id: &str, md5: &str);
+}
+
+/// Detects two clients that keep re-sending each other's revisions. The content of the
+/// object doesn't change in such a loop, so the same md5 shows up again and again in a row
+/// with the author alternating between local and remote.
+pub struct SyncLoopDetector {
+    configuration: SyncLoopConfiguration,
+    history: VecDeque<(String, RevisionAuthor, Instant)>,
+    tripped: bool,
+}
+
+impl SyncLoopDetector {
+    pub fn new(configuration: SyncLoopConfiguration) -> Self {
+        Self {
+            configuration,
+            history: VecDeque::new(),
+            tripped: false,
+        }
+    }
+
+    /// Records the md5 of the applied revision. Returns true if the revision trips the
+    /// detector, the detector stays tripped until `resume` is called.
+    pub fn record(&mut self, md5: &str, author: RevisionAuthor) -> bool {
+        let now = Instant::now();
+        if let Some((_, _, last)) = self.history.back() {
+            if now.duration_since(*last) >= self.configuration.quiet_period {
+                self.history.clear();
+            }
+        }
+        while let Some((_, _, time)) = self.history.front() {
+            if now.duration_since(*time) < self.configuration.window {
+                break;
+            }
+            self.history.pop_front();
+        }
+        self.history.push_back((md5.to_owned(), author, now));
+        while self.history.len() > self.configuration.history_len.max(1) {
+            self.history.pop_front();
+        }
+
+        if self.tripped {
+            return false;
+        }
+        if self.number_of_repeats(md5) > self.configuration.max_repeats {
+            self.tripped = true;
+            return true;
+        }
+        false
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.tripped
+    }
+
+    pub fn resume(&mut self) {
+        self.tripped = false;
+        self.history.clear();
+    }
+
+    /// Returns the number of times the author changed in the latest run of the revisions with
+    /// the `md5`. Undoing and redoing an edit also repeats the md5, but there are the other
+    /// revisions in between.
+    fn number_of_repeats(&self, md5: &str) -> usize {
+        let mut repeats = 0;
+        let mut iter = self.history.iter().rev().take_while(|(other, _, _)| other == md5);
+        let mut last_author = match iter.next() {
+            None => return 0,
+            Some((_, author, _)) => *author,
+        };
+        for (_, author, _) in iter {
+            if *author == last_author {
+                break;
+            }
+            repeats += 1;
+            last_author = *author;
+        }
+        repeats
+    }
+}
@@ -0,0 +1,65 @@
+use flowy_http_model::revision::Revision;
+use flowy_revision_persistence::{RevisionState, SyncRecord};
+use std::collections::HashMap;
+
+/// What syncing the object would do, see `RevisionManager::sync_plan`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncPlan {
+    /// The latest revision that both the local and the remote history have, 0 if they have
+    /// none in common.
+    pub common_rev_id: i64,
+    /// The local revisions that the server doesn't have, in the order they would be pushed.
+    pub pushes: Vec<i64>,
+    /// The remote revisions that aren't saved locally, in the order they would be pulled.
+    pub pulls: Vec<i64>,
+    /// Both histories moved on from the `common_rev_id`, the pushes must be transformed against
+    /// the pulls before they're pushed. Their rev_ids change then.
+    pub needs_rebase: bool,
+}
+
+/// Compares the histories by the rev_id and the md5 of their revisions. The same rev_id with
+/// another md5 means the histories diverged at that revision.
+///
+/// Only the unacked local revisions are pushed. An acked revision that the server doesn't
+/// return, e.g. because it was merged on the server, isn't pushed again.
+pub(crate) fn make_sync_plan(local: &[SyncRecord], remote: &[Revision]) -> SyncPlan {
+    let local = local
+        .iter()
+        .filter(|record| record.state != RevisionState::Resolved)
+        .collect::<Vec<&SyncRecord>>();
+    let local_md5s = local
+        .iter()
+        .map(|record| (record.revision.rev_id, record.revision.md5.as_str()))
+        .collect::<HashMap<i64, &str>>();
+    let remote_md5s = remote
+        .iter()
+        .map(|revision| (revision.rev_id, revision.md5.as_str()))
+        .collect::<HashMap<i64, &str>>();
+
+    let mut pushes = local
+        .iter()
+        .filter(|record| record.state == RevisionState::Sync)
+        .filter(|record| remote_md5s.get(&record.revision.rev_id) != Some(&record.revision.md5.as_str()))
+        .map(|record| record.revision.rev_id)
+        .collect::<Vec<i64>>();
+    pushes.sort_unstable();
+
+    let mut common_rev_id = 0;
+    let mut pulls = vec![];
+    for revision in remote {
+        if local_md5s.get(&revision.rev_id) == Some(&revision.md5.as_str()) {
+            common_rev_id = common_rev_id.max(revision.rev_id);
+        } else {
+            pulls.push(revision.rev_id);
+        }
+    }
+    pulls.sort_unstable();
+
+    let needs_rebase = !pushes.is_empty() && !pulls.is_empty();
+    SyncPlan {
+        common_rev_id,
+        pushes,
+        pulls,
+        needs_rebase,
+    }
+}
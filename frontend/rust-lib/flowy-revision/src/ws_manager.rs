@@ -1,20 +1,24 @@
-use crate::ConflictRevisionSink;
+use crate::{ConflictRevisionSink, ErrorReporter, Executor, WSSession, MAX_PUSH_WINDOW};
 use async_stream::stream;
 
-use flowy_error::{FlowyError, FlowyResult};
+use flowy_error::{ErrorCode, FlowyError, FlowyResult};
 use flowy_http_model::revision::{Revision, RevisionRange};
-use flowy_http_model::ws_data::{ClientRevisionWSData, NewDocumentUser, ServerRevisionWSData, WSRevisionPayload};
+use flowy_http_model::ws_data::{
+    ClientRevisionWSData, ClientRevisionWSDataType, NewDocumentUser, ServerRevisionWSData, WSCapabilities,
+    WSRevisionPayload, WSUnsupported,
+};
 use futures_util::{future::BoxFuture, stream::StreamExt};
 use lib_infra::future::{BoxResultFuture, FutureResult};
 use lib_ws::WSConnectState;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{collections::VecDeque, fmt::Formatter, sync::Arc};
 use tokio::{
     sync::{
         broadcast, mpsc,
         mpsc::{Receiver, Sender},
-        RwLock,
+        OwnedRwLockWriteGuard, RwLock,
     },
-    time::{interval, Duration},
+    time::{interval, Duration, MissedTickBehavior},
 };
 
 // The consumer consumes the messages pushed by the web socket.
@@ -29,6 +33,19 @@ pub trait RevisionWSDataStream: Send + Sync {
 // server.
 pub trait RevisionWebSocketSink: Send + Sync {
     fn next(&self) -> FutureResult<Option<ClientRevisionWSData>, FlowyError>;
+
+    /// Called when the `data` returned by `next` was sent.
+    fn did_send(&self, _data: &ClientRevisionWSData) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+
+    /// Called when the `data` returned by `next` fails to send.
+    fn did_fail_to_send(&self, _data: &ClientRevisionWSData, _error: &FlowyError) {}
+
+    /// The session whose handshake the sink sends, None if the sink never sends one.
+    fn session(&self) -> Option<WSSession> {
+        None
+    }
 }
 
 pub type WSStateReceiver = tokio::sync::broadcast::Receiver<WSConnectState>;
@@ -47,6 +64,11 @@ pub struct RevisionWebSocketManager {
     ws_passthrough_rx: Option<Receiver<ServerRevisionWSData>>,
     pub state_passthrough_tx: broadcast::Sender<WSConnectState>,
     stop_sync_tx: SinkStopTx,
+    remote_backlog: Arc<AtomicUsize>,
+    /// Held for writing while the sync is paused, see `pause`.
+    pause_lock: Arc<RwLock<()>>,
+    error_reporter: Option<Arc<dyn ErrorReporter>>,
+    executor: Executor,
 }
 
 impl std::fmt::Display for RevisionWebSocketManager {
@@ -62,6 +84,8 @@ impl RevisionWebSocketManager {
         ws_data_sink: Arc<dyn RevisionWebSocketSink>,
         ws_data_stream: Arc<dyn RevisionWSDataStream>,
         ping_duration: Duration,
+        error_reporter: Option<Arc<dyn ErrorReporter>>,
+        executor: Executor,
     ) -> Self {
         let (ws_passthrough_tx, ws_passthrough_rx) = mpsc::channel(1000);
         let (stop_sync_tx, _) = tokio::sync::broadcast::channel(2);
@@ -78,6 +102,10 @@ impl RevisionWebSocketManager {
             ws_passthrough_rx: Some(ws_passthrough_rx),
             state_passthrough_tx,
             stop_sync_tx,
+            remote_backlog: Arc::new(AtomicUsize::new(0)),
+            pause_lock: Arc::new(RwLock::new(())),
+            error_reporter,
+            executor,
         };
         manager.run(ping_duration);
         manager
@@ -85,29 +113,51 @@ impl RevisionWebSocketManager {
 
     fn run(&mut self, ping_duration: Duration) {
         let ws_passthrough_rx = self.ws_passthrough_rx.take().expect("Only take once");
-        let sink = RevisionWSSink::new(
+        let mut sink = RevisionWSSink::new(
             &self.object_id,
             &self.object_name,
             self.ws_data_sink.clone(),
             self.rev_web_socket.clone(),
             self.stop_sync_tx.subscribe(),
             ping_duration,
-        );
-        let stream = RevisionWSStream::new(
+        )
+        .with_pause_lock(self.pause_lock.clone());
+        let mut stream = RevisionWSStream::new(
             &self.object_name,
             &self.object_id,
             self.ws_data_stream.clone(),
             ws_passthrough_rx,
             self.stop_sync_tx.subscribe(),
-        );
-        tokio::spawn(sink.run());
-        tokio::spawn(stream.run());
+        )
+        .with_remote_backlog(self.remote_backlog.clone())
+        .with_pause_lock(self.pause_lock.clone());
+        if let Some(session) = self.ws_data_sink.session() {
+            stream = stream.with_session(session, self.rev_web_socket.clone());
+        }
+        if let Some(error_reporter) = self.error_reporter.as_ref() {
+            sink = sink.with_error_reporter(error_reporter.clone());
+            stream = stream.with_error_reporter(error_reporter.clone());
+        }
+        self.executor.spawn(sink.run());
+        self.executor.spawn(stream.run());
+    }
+
+    /// The number of the revisions pushed by the server that are waiting to be applied.
+    pub fn remote_backlog(&self) -> usize {
+        self.remote_backlog.load(Ordering::SeqCst)
     }
 
     pub fn scribe_state(&self) -> broadcast::Receiver<WSConnectState> {
         self.state_passthrough_tx.subscribe()
     }
 
+    /// Pauses the sync until the returned guard is dropped: nothing is sent to the server and
+    /// the messages of the server wait to be handled. It waits for the data being sent and the
+    /// messages being handled, so nothing of the sync writes to the disk once it returns.
+    pub async fn pause(&self) -> OwnedRwLockWriteGuard<()> {
+        self.pause_lock.clone().write_owned().await
+    }
+
     pub fn stop(&self) {
         if self.stop_sync_tx.send(()).is_ok() {
             tracing::trace!("{} stop sync", self.object_id)
@@ -124,6 +174,11 @@ impl RevisionWebSocketManager {
     }
 
     pub fn connect_state_changed(&self, state: WSConnectState) {
+        if state == WSConnectState::Disconnected {
+            if let Some(session) = self.ws_data_sink.session() {
+                session.reset();
+            }
+        }
         match self.state_passthrough_tx.send(state) {
             Ok(_) => {}
             Err(e) => tracing::error!("{}", e),
@@ -137,12 +192,42 @@ impl std::ops::Drop for RevisionWebSocketManager {
     }
 }
 
+/// Limits how often the revisions pushed by the server are applied. A collaborator importing
+/// a large document pushes hundreds of revisions per second, applying each of them would keep
+/// the document busy and delay the local edits.
+#[derive(Debug, Clone)]
+pub struct RemoteApplyRateLimit {
+    pub max_applies_per_second: u32,
+    /// The pushed revisions that are waiting are composed into one apply, at most this many.
+    pub max_revisions_per_apply: usize,
+}
+
+impl std::default::Default for RemoteApplyRateLimit {
+    fn default() -> Self {
+        Self {
+            max_applies_per_second: 20,
+            max_revisions_per_apply: 100,
+        }
+    }
+}
+
+impl RemoteApplyRateLimit {
+    fn apply_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.max_applies_per_second.max(1)
+    }
+}
+
 pub struct RevisionWSStream {
     object_name: String,
     object_id: String,
     consumer: Arc<dyn RevisionWSDataStream>,
     ws_msg_rx: Option<mpsc::Receiver<ServerRevisionWSData>>,
     stop_rx: Option<SinkStopRx>,
+    rate_limit: RemoteApplyRateLimit,
+    remote_backlog: Arc<AtomicUsize>,
+    error_reporter: Option<Arc<dyn ErrorReporter>>,
+    session: Option<(WSSession, Arc<dyn RevisionWebSocket>)>,
+    pause_lock: Arc<RwLock<()>>,
 }
 
 impl std::fmt::Display for RevisionWSStream {
@@ -171,44 +256,142 @@ impl RevisionWSStream {
             consumer,
             ws_msg_rx: Some(ws_msg_rx),
             stop_rx: Some(stop_rx),
+            rate_limit: RemoteApplyRateLimit::default(),
+            remote_backlog: Arc::new(AtomicUsize::new(0)),
+            error_reporter: None,
+            session: None,
+            pause_lock: Arc::new(RwLock::new(())),
         }
     }
 
+    /// Negotiates the `session` with the handshake of the server. The frames that can't be
+    /// handled are answered through the `web_socket` if the server supports it.
+    pub fn with_session(mut self, session: WSSession, web_socket: Arc<dyn RevisionWebSocket>) -> Self {
+        self.session = Some((session, web_socket));
+        self
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: RemoteApplyRateLimit) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    /// Shares the number of the pushed revisions that are waiting to be applied.
+    pub fn with_remote_backlog(mut self, remote_backlog: Arc<AtomicUsize>) -> Self {
+        self.remote_backlog = remote_backlog;
+        self
+    }
+
+    /// The messages aren't handled while the `pause_lock` is held for writing.
+    pub fn with_pause_lock(mut self, pause_lock: Arc<RwLock<()>>) -> Self {
+        self.pause_lock = pause_lock;
+        self
+    }
+
+    pub fn with_error_reporter(mut self, error_reporter: Arc<dyn ErrorReporter>) -> Self {
+        self.error_reporter = Some(error_reporter);
+        self
+    }
+
+    fn report_error(&self, context: &str, error: &FlowyError) {
+        tracing::error!("[{}]:{} error: {}", self, self.object_id, error);
+        if let Some(error_reporter) = self.error_reporter.as_ref() {
+            error_reporter.report(&format!("{} of {}", context, self.object_id), error);
+        }
+    }
+
+    /// The messages are handled in the order they're received. The pushed revisions are applied
+    /// in batches, one batch per interval of the rate limit, so the messages behind them, e.g.
+    /// the acks and the pulls, wait in the backlog with them. The backlog is flushed when the
+    /// stream stops, the revisions that the server pushed aren't dropped.
     pub async fn run(mut self) {
         let mut receiver = self.ws_msg_rx.take().expect("Only take once");
         let mut stop_rx = self.stop_rx.take().expect("Only take once");
-        let object_id = self.object_id.clone();
-        let name = format!("{}", &self);
-        let stream = stream! {
-            loop {
-                tokio::select! {
-                    result = receiver.recv() => {
-                        match result {
-                            Some(msg) => {
-                                yield msg
-                            },
-                            None => {
-                                tracing::debug!("[{}]:{} loop exit", name, object_id);
-                                break;
-                            },
-                        }
-                    },
-                    _ = stop_rx.recv() => {
-                        tracing::debug!("[{}]:{} loop exit", name, object_id);
-                        break
-                    },
-                };
+        let mut backlog: VecDeque<ServerRevisionWSData> = VecDeque::new();
+        let mut ticker = interval(self.rate_limit.apply_interval());
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                biased;
+                _ = stop_rx.recv() => break,
+                _ = ticker.tick(), if !backlog.is_empty() => {
+                    let _pause = self.pause_lock.read().await;
+                    self.apply_next_batch(&mut backlog).await;
+                    self.update_remote_backlog(&backlog);
+                },
+                result = receiver.recv() => {
+                    match result {
+                        Some(msg) => {
+                            backlog.push_back(msg);
+                            // The message is handled right away unless it's behind pushed revisions.
+                            let _pause = self.pause_lock.read().await;
+                            self.handle_until_push(&mut backlog).await;
+                            self.update_remote_backlog(&backlog);
+                        },
+                        None => break,
+                    }
+                },
+            };
+        }
+
+        receiver.close();
+        while let Ok(msg) = receiver.try_recv() {
+            backlog.push_back(msg);
+        }
+        let _pause = self.pause_lock.read().await;
+        while !backlog.is_empty() {
+            self.apply_next_batch(&mut backlog).await;
+        }
+        self.update_remote_backlog(&backlog);
+        tracing::debug!("[{}]:{} loop exit", self, self.object_id);
+    }
+
+    /// Applies the pushed revisions at the front of the backlog composed into one batch, then
+    /// handles the messages behind them up to the next pushed revisions.
+    async fn apply_next_batch(&self, backlog: &mut VecDeque<ServerRevisionWSData>) {
+        let max_revisions = self.rate_limit.max_revisions_per_apply.max(1);
+        let mut revisions = vec![];
+        while revisions.len() < max_revisions {
+            let pushed = match backlog.front_mut().map(|msg| &mut msg.payload) {
+                Some(WSRevisionPayload::ServerPushRev { revisions }) => revisions,
+                _ => break,
+            };
+            let n = pushed.len().min(max_revisions - revisions.len());
+            revisions.extend(pushed.drain(..n));
+            if pushed.is_empty() {
+                backlog.pop_front();
             }
-        };
+        }
+        if !revisions.is_empty() {
+            if let Err(e) = self.consumer.receive_push_revision(revisions).await {
+                self.report_error("apply pushed revisions", &e);
+            }
+        }
+        self.handle_until_push(backlog).await;
+    }
 
-        stream
-            .for_each(|msg| async {
-                match self.handle_message(msg).await {
-                    Ok(_) => {}
-                    Err(e) => tracing::error!("[{}]:{} error: {}", &self, self.object_id, e),
-                }
+    /// Handles the messages at the front of the backlog until the pushed revisions.
+    async fn handle_until_push(&self, backlog: &mut VecDeque<ServerRevisionWSData>) {
+        while let Some(msg) = backlog.front() {
+            if matches!(msg.payload, WSRevisionPayload::ServerPushRev { .. }) {
+                break;
+            }
+            let msg = backlog.pop_front().unwrap();
+            if let Err(e) = self.handle_message(msg).await {
+                self.report_error("handle server message", &e);
+            }
+        }
+    }
+
+    fn update_remote_backlog(&self, backlog: &VecDeque<ServerRevisionWSData>) {
+        let num_of_revisions = backlog
+            .iter()
+            .map(|msg| match &msg.payload {
+                WSRevisionPayload::ServerPushRev { revisions } => revisions.len(),
+                _ => 0,
             })
-            .await;
+            .sum();
+        self.remote_backlog.store(num_of_revisions, Ordering::SeqCst);
     }
 
     async fn handle_message(&self, msg: ServerRevisionWSData) -> FlowyResult<()> {
@@ -229,13 +412,60 @@ impl RevisionWSStream {
             WSRevisionPayload::UserConnect { user } => {
                 let _ = self.consumer.receive_new_user_connect(user).await;
             }
+            WSRevisionPayload::ServerHandshake { handshake } => match self.session.as_ref() {
+                None => self.reply_unsupported(&object_id, "ServerHandshake").await?,
+                Some((session, _)) => {
+                    let negotiated = session.negotiate(&handshake);
+                    tracing::debug!("[{}]: {} negotiated {:?}", self, object_id, negotiated);
+                }
+            },
+            WSRevisionPayload::ServerUnsupported { unsupported } => {
+                tracing::warn!("[{}]: {} server dropped {:?}", self, object_id, unsupported);
+            }
+            WSRevisionPayload::Unknown { ty } => self.reply_unsupported(&object_id, &ty).await?,
         }
         Ok(())
     }
+
+    /// Tells the server that the frame of type `ty` was dropped. The legacy server doesn't
+    /// know the unsupported frame, the frame is only logged then.
+    async fn reply_unsupported(&self, object_id: &str, ty: &str) -> FlowyResult<()> {
+        tracing::warn!("[{}]: {} drops the unsupported {} frame", self, object_id, ty);
+        match self.session.as_ref() {
+            Some((session, web_socket)) if session.supports(WSCapabilities::UNSUPPORTED_FRAME) => {
+                let unsupported = WSUnsupported {
+                    ty: ty.to_owned(),
+                    reason: "The frame was not negotiated".to_owned(),
+                };
+                web_socket
+                    .send(ClientRevisionWSData::unsupported(object_id, 0, unsupported))
+                    .await
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 type SinkStopRx = broadcast::Receiver<()>;
 type SinkStopTx = broadcast::Sender<()>;
+
+/// The outcome of one step of the `RevisionWSSink`.
+#[derive(Debug, Clone)]
+pub enum RevisionWSSinkStep {
+    /// The data was sent for the first time.
+    Send(ClientRevisionWSData),
+    /// The data is the same as the last sent data, the server hasn't acked it yet.
+    Resend(ClientRevisionWSData),
+    /// There was nothing to send in this step.
+    Idle,
+}
+
+/// Sends the data provided by the `RevisionWebSocketSink` to the server, one data per tick.
+/// The provider returns the data that isn't acked again once its push window is full, so the
+/// unacked data gets resent.
+///
+/// The sink doesn't own any timer, `run` spawns a ticker with the `ping_duration`. Call `step`
+/// to drive the sink manually.
 pub struct RevisionWSSink {
     object_id: String,
     object_name: String,
@@ -243,6 +473,10 @@ pub struct RevisionWSSink {
     rev_web_socket: Arc<dyn RevisionWebSocket>,
     stop_rx: Option<SinkStopRx>,
     ping_duration: Duration,
+    /// The recently sent data, any of them may be in flight.
+    recent_sent: RwLock<VecDeque<(i64, ClientRevisionWSDataType)>>,
+    error_reporter: Option<Arc<dyn ErrorReporter>>,
+    pause_lock: Arc<RwLock<()>>,
 }
 
 impl RevisionWSSink {
@@ -261,19 +495,40 @@ impl RevisionWSSink {
             rev_web_socket,
             stop_rx: Some(stop_rx),
             ping_duration,
+            recent_sent: RwLock::new(VecDeque::with_capacity(MAX_PUSH_WINDOW)),
+            error_reporter: None,
+            pause_lock: Arc::new(RwLock::new(())),
         }
     }
 
-    pub async fn run(mut self) {
-        let (tx, mut rx) = mpsc::channel(1);
+    pub fn with_error_reporter(mut self, error_reporter: Arc<dyn ErrorReporter>) -> Self {
+        self.error_reporter = Some(error_reporter);
+        self
+    }
+
+    /// Nothing is sent while the `pause_lock` is held for writing.
+    pub fn with_pause_lock(mut self, pause_lock: Arc<RwLock<()>>) -> Self {
+        self.pause_lock = pause_lock;
+        self
+    }
+
+    pub async fn run(self) {
+        let (tx, rx) = mpsc::channel(1);
+        // The ticker stops once the sink stopped and dropped the receiver.
+        let ticker = tick(tx, self.ping_duration);
+        tokio::join!(ticker, self.run_with_ticker(rx));
+    }
+
+    /// Runs a step whenever the `ticker` receives a tick until the ticker gets closed or
+    /// the sink gets stopped.
+    pub async fn run_with_ticker(mut self, mut ticker: mpsc::Receiver<()>) {
         let mut stop_rx = self.stop_rx.take().expect("Only take once");
         let object_id = self.object_id.clone();
-        tokio::spawn(tick(tx, self.ping_duration));
         let name = format!("{}", self);
         let stream = stream! {
             loop {
                 tokio::select! {
-                    result = rx.recv() => {
+                    result = ticker.recv() => {
                         match result {
                             Some(msg) => yield msg,
                             None => break,
@@ -288,23 +543,53 @@ impl RevisionWSSink {
         };
         stream
             .for_each(|_| async {
-                match self.send_next_revision().await {
-                    Ok(_) => {}
-                    Err(e) => tracing::error!("[{}] send failed, {:?}", self, e),
+                if let Err(e) = self.step().await {
+                    // The data is sent again once the sync is resumed.
+                    if e.code == ErrorCode::SyncPaused.value() {
+                        tracing::trace!("[{}] the sync is paused", self);
+                        return;
+                    }
+                    tracing::error!("[{}] send failed, {:?}", self, e);
+                    if let Some(error_reporter) = self.error_reporter.as_ref() {
+                        error_reporter.report(&format!("send revisions of {}", self.object_id), &e);
+                    }
                 }
             })
             .await;
     }
 
-    async fn send_next_revision(&self) -> FlowyResult<()> {
+    /// Sends the next data of the provider if there is one.
+    pub async fn step(&self) -> FlowyResult<RevisionWSSinkStep> {
+        let _pause = self.pause_lock.read().await;
         match self.provider.next().await? {
             None => {
                 tracing::trace!("[{}]: Finish synchronizing revisions", self);
-                Ok(())
+                Ok(RevisionWSSinkStep::Idle)
             }
             Some(data) => {
                 tracing::trace!("[{}]: send {}:{}-{:?}", self, data.object_id, data.rev_id, data.ty);
-                self.rev_web_socket.send(data).await
+                let is_resend = {
+                    let mut recent_sent = self.recent_sent.write().await;
+                    let sent = (data.rev_id, data.ty.clone());
+                    let is_resend = data.ty == ClientRevisionWSDataType::ClientPushRev && recent_sent.contains(&sent);
+                    if !is_resend {
+                        if recent_sent.len() >= MAX_PUSH_WINDOW {
+                            recent_sent.pop_front();
+                        }
+                        recent_sent.push_back(sent);
+                    }
+                    is_resend
+                };
+                if let Err(e) = self.rev_web_socket.send(data.clone()).await {
+                    self.provider.did_fail_to_send(&data, &e);
+                    return Err(e);
+                }
+                self.provider.did_send(&data).await?;
+                if is_resend {
+                    Ok(RevisionWSSinkStep::Resend(data))
+                } else {
+                    Ok(RevisionWSSinkStep::Send(data))
+                }
             }
         }
     }
@@ -336,9 +621,19 @@ enum Source {
 }
 
 pub trait WSDataProviderDataSource: Send + Sync {
+    /// Returns the oldest revision that isn't acked.
     fn next_revision(&self) -> FutureResult<Option<Revision>, FlowyError>;
+    /// Returns the next revision that wasn't pushed if there is room for it in the push window,
+    /// the window is capped to `max_push_window`.
+    fn next_push_revision(&self, max_push_window: usize) -> FutureResult<Option<Revision>, FlowyError>;
     fn ack_revision(&self, rev_id: i64) -> FutureResult<(), FlowyError>;
     fn current_rev_id(&self) -> i64;
+    /// Called when the revision returned by `next_revision` or `next_push_revision` was sent.
+    fn did_send(&self, _rev_id: i64) -> FutureResult<(), FlowyError> {
+        FutureResult::new(async { Ok(()) })
+    }
+    /// Called when the revision returned by `next_revision` or `next_push_revision` fails to send.
+    fn did_fail_to_send(&self, _rev_id: i64, _error: &FlowyError) {}
 }
 
 #[derive(Clone)]
@@ -347,6 +642,7 @@ pub struct WSDataProvider {
     rev_ws_data_list: Arc<RwLock<VecDeque<ClientRevisionWSData>>>,
     data_source: Arc<dyn WSDataProviderDataSource>,
     current_source: Arc<RwLock<Source>>,
+    session: Option<WSSession>,
 }
 
 impl WSDataProvider {
@@ -356,14 +652,34 @@ impl WSDataProvider {
             rev_ws_data_list: Arc::new(RwLock::new(VecDeque::new())),
             data_source,
             current_source: Arc::new(RwLock::new(Source::Custom)),
+            session: None,
         }
     }
 
+    /// Sends the handshake of the `session` first on each connection. The revisions are pushed
+    /// one at a time until the server negotiates `WSCapabilities::FLOW_CONTROL`.
+    pub fn with_session(mut self, session: WSSession) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    pub fn session(&self) -> Option<WSSession> {
+        self.session.clone()
+    }
+
     pub async fn push_data(&self, data: ClientRevisionWSData) {
         self.rev_ws_data_list.write().await.push_back(data);
     }
 
     pub async fn next(&self) -> FlowyResult<Option<ClientRevisionWSData>> {
+        if let Some(handshake) = self.session.as_ref().and_then(|session| session.take_handshake()) {
+            return Ok(Some(ClientRevisionWSData::handshake(
+                &self.object_id,
+                self.data_source.current_rev_id(),
+                handshake,
+            )));
+        }
+
         let source = self.current_source.read().await.clone();
         let data = match source {
             Source::Custom => match self.rev_ws_data_list.read().await.front() {
@@ -379,6 +695,15 @@ impl WSDataProvider {
                     return Ok(None);
                 }
 
+                // Push the next revision while the window has room, otherwise resend the oldest
+                // revision that isn't acked.
+                let max_push_window = match self.session.as_ref() {
+                    None => MAX_PUSH_WINDOW,
+                    Some(session) => session.max_push_window(),
+                };
+                if let Some(rev) = self.data_source.next_push_revision(max_push_window).await? {
+                    return Ok(Some(ClientRevisionWSData::from_revisions(&self.object_id, vec![rev])));
+                }
                 match self.data_source.next_revision().await? {
                     Some(rev) => Ok(Some(ClientRevisionWSData::from_revisions(&self.object_id, vec![rev]))),
                     None => Ok(Some(ClientRevisionWSData::ping(
@@ -391,6 +716,32 @@ impl WSDataProvider {
         data
     }
 
+    /// Tells the data source which revisions of the `data` were sent.
+    pub async fn did_send(&self, data: &ClientRevisionWSData) -> FlowyResult<()> {
+        if data.ty != ClientRevisionWSDataType::ClientPushRev {
+            return Ok(());
+        }
+        for revision in &data.revisions {
+            self.data_source.did_send(revision.rev_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Tells the data source which revisions of the `data` failed to send.
+    pub fn did_fail_to_send(&self, data: &ClientRevisionWSData, error: &FlowyError) {
+        if data.handshake.is_some() {
+            if let Some(session) = self.session.as_ref() {
+                session.reset();
+            }
+        }
+        if data.ty != ClientRevisionWSDataType::ClientPushRev {
+            return;
+        }
+        for revision in &data.revisions {
+            self.data_source.did_fail_to_send(revision.rev_id, error);
+        }
+    }
+
     pub async fn ack_data(&self, rev_id: i64) -> FlowyResult<()> {
         let source = self.current_source.read().await.clone();
         match source {
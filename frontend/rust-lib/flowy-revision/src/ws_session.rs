@@ -0,0 +1,91 @@
+use crate::MAX_PUSH_WINDOW;
+use flowy_http_model::ws_data::{WSCapabilities, WSHandshake, WS_PROTOCOL_VERSION};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+struct WSSessionState {
+    local: WSCapabilities,
+    negotiated: AtomicU32,
+    handshake_sent: AtomicBool,
+}
+
+/// The capabilities negotiated with the server on the current connection. The session starts
+/// with `WSCapabilities::LEGACY`, so a server that never answers the handshake is talked to the
+/// way the older clients did. Each feature that isn't legacy checks `supports` before it's used.
+///
+/// The session is reset when the connection is lost, the handshake is sent again once it's back.
+#[derive(Clone)]
+pub struct WSSession {
+    inner: Arc<WSSessionState>,
+}
+
+impl std::default::Default for WSSession {
+    fn default() -> Self {
+        Self::new(WSCapabilities::ALL)
+    }
+}
+
+impl std::fmt::Debug for WSSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WSSession")
+            .field("local", &self.inner.local)
+            .field("negotiated", &self.negotiated())
+            .finish()
+    }
+}
+
+impl WSSession {
+    /// `local` is the capabilities that the client advertises.
+    pub fn new(local: WSCapabilities) -> Self {
+        Self {
+            inner: Arc::new(WSSessionState {
+                local,
+                negotiated: AtomicU32::new(WSCapabilities::LEGACY.0),
+                handshake_sent: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    pub fn negotiated(&self) -> WSCapabilities {
+        WSCapabilities(self.inner.negotiated.load(Ordering::SeqCst))
+    }
+
+    pub fn supports(&self, capability: WSCapabilities) -> bool {
+        self.negotiated().contains(capability)
+    }
+
+    /// Returns the handshake if it wasn't sent on this connection yet.
+    pub fn take_handshake(&self) -> Option<WSHandshake> {
+        match self.inner.handshake_sent.swap(true, Ordering::SeqCst) {
+            true => None,
+            false => Some(WSHandshake::new(self.inner.local)),
+        }
+    }
+
+    /// Keeps the capabilities that both sides support. The server of an older version keeps the
+    /// session legacy.
+    pub fn negotiate(&self, remote: &WSHandshake) -> WSCapabilities {
+        let negotiated = if remote.version < WS_PROTOCOL_VERSION {
+            WSCapabilities::LEGACY
+        } else {
+            self.inner.local.intersection(remote.capabilities)
+        };
+        self.inner.negotiated.store(negotiated.0, Ordering::SeqCst);
+        negotiated
+    }
+
+    /// Goes back to legacy until the handshake of the next connection is answered.
+    pub fn reset(&self) {
+        self.inner.negotiated.store(WSCapabilities::LEGACY.0, Ordering::SeqCst);
+        self.inner.handshake_sent.store(false, Ordering::SeqCst);
+    }
+
+    /// The number of the revisions that may be in flight, the legacy server gets one at a time.
+    pub fn max_push_window(&self) -> usize {
+        if self.supports(WSCapabilities::FLOW_CONTROL) {
+            MAX_PUSH_WINDOW
+        } else {
+            1
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use crate::revision_test::script::{RevisionScript::*, RevisionTest};
+use crate::revision_test::script::{RevisionObjectMock, RevisionScript::*, RevisionTest};
+use bytes::Bytes;
+use flowy_http_model::util::md5;
+use flowy_revision::{CompactionEstimate, COMPACTION_REVISION_SIZE_LIMIT};
+use flowy_revision_persistence::RevisionState;
 
 #[tokio::test]
 async fn revision_sync_test() {
@@ -253,3 +257,380 @@ async fn revision_compress_revision_while_recv_ack_test() {
     ])
     .await;
 }
+
+#[tokio::test]
+async fn revision_local_only_history_limit_test() {
+    let test = RevisionTest::new_with_local_only(3).await;
+    for content in ["1", "2", "3", "4", "5"] {
+        test.run_script(AddLocalRevision {
+            content: content.to_string(),
+        })
+        .await;
+    }
+
+    // The revisions never sync and the oldest revisions are merged into one.
+    test.run_scripts(vec![
+        AssertNextSyncRevisionId { rev_id: None },
+        AssertNumberOfRevisionsInDisk { num: 3 },
+        AssertObjectContent {
+            expected: "12345".to_string(),
+        },
+    ])
+    .await;
+    assert_eq!(test.rev_id(), 5);
+}
+
+#[tokio::test]
+async fn revision_local_only_history_limit_keeps_pinned_revision_test() {
+    let test = RevisionTest::new_with_local_only(2).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+        PinRevision { rev_id: 2 },
+    ])
+    .await;
+    for content in ["3", "4", "5", "6"] {
+        test.run_script(AddLocalRevision {
+            content: content.to_string(),
+        })
+        .await;
+    }
+
+    // The revisions 3, 4 and 5 are merged into the revision 3. The revision 1 is kept to rebuild
+    // the object at the revision 2.
+    test.run_scripts(vec![
+        AssertPinnedRevisionIds { rev_ids: vec![2] },
+        AssertNumberOfRevisionsInDisk { num: 4 },
+        AssertRevision {
+            rev_id: 2,
+            expected: (2, "2".to_string()),
+        },
+        AssertObjectContentAtRevision {
+            rev_id: 2,
+            expected: "12".to_string(),
+        },
+        AssertObjectContent {
+            expected: "123456".to_string(),
+        },
+    ])
+    .await;
+
+    // Once unpinned, the revision is merged like the others.
+    test.run_scripts(vec![
+        UnpinRevision { rev_id: 2 },
+        AddLocalRevision {
+            content: "7".to_string(),
+        },
+        AssertPinnedRevisionIds { rev_ids: vec![] },
+        AssertNumberOfRevisionsInDisk { num: 2 },
+        AssertObjectContent {
+            expected: "1234567".to_string(),
+        },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_compress_keeps_pinned_revision_test() {
+    let test = RevisionTest::new_with_configuration(2).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        PinRevision { rev_id: 1 },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+        AddLocalRevision {
+            content: "3".to_string(),
+        },
+        AddLocalRevision {
+            content: "4".to_string(),
+        },
+        AddLocalRevision {
+            content: "5".to_string(),
+        },
+    ])
+    .await;
+
+    // Without the pin, the revision 2 would be merged into the revision 1.
+    test.run_scripts(vec![
+        AssertNumberOfSyncRevisions { num: 3 },
+        AssertRevision {
+            rev_id: 1,
+            expected: (1, "1".to_string()),
+        },
+        AssertRevision {
+            rev_id: 2,
+            expected: (2, "23".to_string()),
+        },
+        AssertRevision {
+            rev_id: 3,
+            expected: (3, "45".to_string()),
+        },
+        AssertNextSyncRevisionId { rev_id: Some(1) },
+        AckRevision { rev_id: 1 },
+        AssertNextSyncRevisionContent {
+            expected: "23".to_string(),
+        },
+        AssertObjectContent {
+            expected: "12345".to_string(),
+        },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_resolve_conflict_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AckRevisionAndPersist { rev_id: 1 },
+        // The revision 2 and 3 conflict with the server, the user merges them into "23".
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+        AddLocalRevision {
+            content: "3".to_string(),
+        },
+        WaitWhenWriteToDisk,
+        ResolveConflict {
+            content: "23".to_string(),
+            superseded: vec![2, 3],
+        },
+        AssertNextSyncRevisionId { rev_id: Some(4) },
+        AssertNextSyncRevisionContent {
+            expected: "23".to_string(),
+        },
+        WaitWhenWriteToDisk,
+        AssertRevisionState {
+            rev_id: 2,
+            state: RevisionState::Resolved,
+        },
+        AssertRevisionState {
+            rev_id: 3,
+            state: RevisionState::Resolved,
+        },
+        AssertRevisionState {
+            rev_id: 4,
+            state: RevisionState::Sync,
+        },
+        AssertObjectContent {
+            expected: "123".to_string(),
+        },
+    ])
+    .await;
+
+    // The resolved revisions are neither synced nor composed after reopening.
+    let test = RevisionTest::new_with_other(test).await;
+    test.run_scripts(vec![
+        AssertNextSyncRevisionId { rev_id: Some(4) },
+        AssertObjectContent {
+            expected: "123".to_string(),
+        },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_snapshot_after_resolve_conflict_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AckRevisionAndPersist { rev_id: 1 },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+        AddLocalRevision {
+            content: "3".to_string(),
+        },
+        WaitWhenWriteToDisk,
+        ResolveConflict {
+            content: "23".to_string(),
+            superseded: vec![2, 3],
+        },
+        WaitWhenWriteToDisk,
+    ])
+    .await;
+
+    // The snapshot has the resolved content only once.
+    test.rev_manager().generate_snapshot().await;
+    let snapshot = test.rev_manager().read_snapshot(None).await.unwrap().unwrap();
+    assert_eq!(snapshot.rev_id, 4);
+    assert_eq!(RevisionObjectMock::from_bytes(&snapshot.data).unwrap().content(), "123");
+}
+
+#[tokio::test]
+async fn revision_resolve_conflict_with_acked_revision_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+        AddLocalRevision {
+            content: "3".to_string(),
+        },
+        // The server already has the revision 2 of one branch.
+        AckRevisionAndPersist { rev_id: 1 },
+        AckRevisionAndPersist { rev_id: 2 },
+        WaitWhenWriteToDisk,
+    ])
+    .await;
+
+    let bytes = RevisionObjectMock::new("23").to_bytes();
+    let md5 = md5(&bytes);
+    assert!(test
+        .rev_manager()
+        .resolve_conflict(Bytes::from(bytes), md5, &[2, 3])
+        .await
+        .is_err());
+
+    // Nothing is resolved, the revision 3 is still synced.
+    test.run_scripts(vec![
+        AssertNextSyncRevisionId { rev_id: Some(3) },
+        AssertRevisionState {
+            rev_id: 2,
+            state: RevisionState::Ack,
+        },
+        AssertRevisionState {
+            rev_id: 3,
+            state: RevisionState::Sync,
+        },
+        AssertObjectContent {
+            expected: "123".to_string(),
+        },
+    ])
+    .await;
+    assert_eq!(test.rev_id(), 3);
+}
+
+#[tokio::test]
+async fn revision_compaction_estimate_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    let large_content = "a".repeat(COMPACTION_REVISION_SIZE_LIMIT);
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+        AddLocalRevision {
+            content: "3".to_string(),
+        },
+        AckRevisionAndPersist { rev_id: 1 },
+        AckRevisionAndPersist { rev_id: 2 },
+        AddLocalRevision {
+            content: large_content.clone(),
+        },
+        AddLocalRevision {
+            content: "5".to_string(),
+        },
+        AddLocalRevision {
+            content: "6".to_string(),
+        },
+        AddLocalRevision { content: large_content },
+        AddLocalRevision {
+            content: "8".to_string(),
+        },
+        WaitWhenWriteToDisk,
+        // The only run is [1,2]. The revisions from 3 on aren't acked, the `sync_seq` merges them.
+        // Each small revision takes 77 bytes: 15 bytes of data, 32 bytes of md5, 6 bytes of
+        // object_id and 24 bytes of the ids and the state.
+        AssertCompactionEstimate {
+            expected: CompactionEstimate {
+                num_of_runs: 1,
+                num_of_removed_rows: 1,
+                num_of_removed_bytes: 77,
+            },
+        },
+        AssertNumberOfRevisionsInDisk { num: 8 },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_compact_small_revisions_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    let large_content = "a".repeat(COMPACTION_REVISION_SIZE_LIMIT);
+    for content in ["1", "2", "3", large_content.as_str(), "5", "6", "7", "8"] {
+        test.run_script(AddLocalRevision {
+            content: content.to_string(),
+        })
+        .await;
+    }
+    for rev_id in 1..=8 {
+        test.run_script(AckRevisionAndPersist { rev_id }).await;
+    }
+    // The large revision 4 ends the run [1,2,3], and the pinned revision 6 keeps 5 on its own.
+    test.run_scripts(vec![PinRevision { rev_id: 6 }, WaitWhenWriteToDisk])
+        .await;
+    let estimate = test.rev_manager().compaction_estimate().unwrap();
+    assert_eq!(estimate.num_of_runs, 2);
+    assert_eq!(estimate.num_of_removed_rows, 3);
+
+    let num_of_removed = test.rev_manager().compact_small_revisions().await.unwrap();
+    assert_eq!(num_of_removed, estimate.num_of_removed_rows);
+    test.run_scripts(vec![
+        // [1,2,3] is merged into 3 and [7,8] into 8, the revisions after them are still based on them.
+        AssertNumberOfRevisionsInDisk { num: 5 },
+        AssertRevision {
+            rev_id: 3,
+            expected: (3, "123".to_string()),
+        },
+        AssertRevision {
+            rev_id: 8,
+            expected: (8, "78".to_string()),
+        },
+        AssertObjectContent {
+            expected: format!("123{}5678", large_content),
+        },
+        AssertCompactionEstimate {
+            expected: CompactionEstimate::default(),
+        },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_ack_out_of_order_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    for content in ["1", "2", "3"] {
+        test.run_script(AddLocalRevision {
+            content: content.to_string(),
+        })
+        .await;
+    }
+
+    test.run_scripts(vec![
+        AckRevision { rev_id: 1 },
+        // The ack of the third revision arrives before the ack of the second one.
+        AckRevision { rev_id: 3 },
+        AssertNumberOfSyncRevisions { num: 2 },
+        AssertNextSyncRevisionId { rev_id: Some(2) },
+        AckRevision { rev_id: 2 },
+        AssertNumberOfSyncRevisions { num: 0 },
+        AssertNextSyncRevisionId { rev_id: None },
+        WaitWhenWriteToDisk,
+        AssertRevisionState {
+            rev_id: 2,
+            state: RevisionState::Ack,
+        },
+        AssertRevisionState {
+            rev_id: 3,
+            state: RevisionState::Ack,
+        },
+    ])
+    .await;
+}
@@ -1,3 +1,19 @@
 mod local_revision_test;
+mod revision_ack_callback_test;
+mod revision_ack_panic_test;
 mod revision_disk_test;
+mod revision_error_reporter_test;
+mod revision_expiry_test;
+mod revision_lifecycle_test;
+mod revision_priority_test;
+mod revision_purge_test;
+mod revision_read_cache_test;
+mod revision_snapshot_test;
+mod revision_state_test;
+mod revision_sync_loop_test;
+mod revision_sync_plan_test;
+mod revision_ws_handshake_test;
+mod revision_ws_sink_test;
+mod revision_ws_stream_test;
+mod save_debounce_test;
 mod script;
@@ -0,0 +1,66 @@
+use crate::revision_test::script::{RevisionScript::*, RevisionTest};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+async fn add_local_revisions(test: &RevisionTest, contents: &[&str]) {
+    for content in contents {
+        test.run_script(AddLocalRevision {
+            content: content.to_string(),
+        })
+        .await;
+    }
+}
+
+async fn register_on_ack(test: &RevisionTest, rev_id: i64) -> oneshot::Receiver<i64> {
+    let (tx, rx) = oneshot::channel();
+    test.rev_manager()
+        .on_ack(rev_id, move || {
+            let _ = tx.send(rev_id);
+        })
+        .await;
+    rx
+}
+
+async fn assert_fired(rx: oneshot::Receiver<i64>, rev_id: i64) {
+    let fired = tokio::time::timeout(Duration::from_secs(1), rx).await.unwrap().unwrap();
+    assert_eq!(fired, rev_id);
+}
+
+async fn assert_not_fired(rx: &mut oneshot::Receiver<i64>) {
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn revision_ack_callback_fires_on_ack_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    add_local_revisions(&test, &["a"]).await;
+
+    let mut rx = register_on_ack(&test, 1).await;
+    assert_not_fired(&mut rx).await;
+    test.run_script(AckRevision { rev_id: 1 }).await;
+    assert_fired(rx, 1).await;
+}
+
+#[tokio::test]
+async fn revision_ack_callback_of_acked_revision_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    add_local_revisions(&test, &["a"]).await;
+    test.run_script(AckRevisionAndPersist { rev_id: 1 }).await;
+
+    let rx = register_on_ack(&test, 1).await;
+    assert_fired(rx, 1).await;
+}
+
+#[tokio::test]
+async fn revision_ack_callback_waits_for_held_ack_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    add_local_revisions(&test, &["a", "b"]).await;
+
+    // The ack of the second revision is held until the first one is acked.
+    let mut rx = register_on_ack(&test, 2).await;
+    test.run_script(AckRevision { rev_id: 2 }).await;
+    assert_not_fired(&mut rx).await;
+    test.run_script(AckRevision { rev_id: 1 }).await;
+    assert_fired(rx, 2).await;
+}
@@ -0,0 +1,88 @@
+use crate::revision_test::script::RevisionScript::*;
+use crate::revision_test::script::{ErrorReporterMock, RevisionTest};
+use flowy_revision::AckPanicFallback;
+use flowy_revision_persistence::RevisionState;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn revision_ack_panic_is_reconciled_test() {
+    let error_reporter = Arc::new(ErrorReporterMock::default());
+    let (test, disk_cache) = RevisionTest::builder()
+        .configure(|configuration| {
+            configuration
+                .with_error_reporter(error_reporter.clone())
+                .with_ack_panic_fallback(AckPanicFallback::Reconcile)
+        })
+        .build()
+        .await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        WaitWhenWriteToDisk,
+    ])
+    .await;
+
+    // The revision is on disk already, so its ack updates its state on disk and panics.
+    disk_cache.panic_on_next_update();
+    test.run_scripts(vec![
+        AckRevision { rev_id: 1 },
+        AssertNextSyncRevisionId { rev_id: None },
+        AssertRevisionState {
+            rev_id: 1,
+            state: RevisionState::Sync,
+        },
+    ])
+    .await;
+    let reports = error_reporter.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].0, format!("ack revision 1 of {}", test.object_id()));
+    assert!(reports[0].1.contains("The lock of the disk cache is poisoned"));
+
+    // The next access applies the ack again.
+    assert!(test.rev_manager().get_revision(1).await.is_some());
+    test.run_scripts(vec![
+        AssertRevisionState {
+            rev_id: 1,
+            state: RevisionState::Ack,
+        },
+        AddLocalRevision {
+            content: "456".to_string(),
+        },
+        AssertNextSyncRevisionId { rev_id: Some(2) },
+        AckRevision { rev_id: 2 },
+        AssertNextSyncRevisionId { rev_id: None },
+    ])
+    .await;
+    assert_eq!(error_reporter.reports().len(), 1);
+}
+
+#[tokio::test]
+async fn revision_ack_panic_report_only_test() {
+    let error_reporter = Arc::new(ErrorReporterMock::default());
+    let (test, disk_cache) = RevisionTest::builder()
+        .configure(|configuration| {
+            configuration
+                .with_error_reporter(error_reporter.clone())
+                .with_ack_panic_fallback(AckPanicFallback::ReportOnly)
+        })
+        .build()
+        .await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        WaitWhenWriteToDisk,
+    ])
+    .await;
+
+    disk_cache.panic_on_next_update();
+    test.run_scripts(vec![AckRevision { rev_id: 1 }]).await;
+    assert!(test.rev_manager().get_revision(1).await.is_some());
+    test.run_scripts(vec![AssertRevisionState {
+        rev_id: 1,
+        state: RevisionState::Sync,
+    }])
+    .await;
+    assert_eq!(error_reporter.reports().len(), 1);
+}
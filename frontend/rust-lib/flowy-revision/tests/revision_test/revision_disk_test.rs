@@ -1,5 +1,10 @@
 use crate::revision_test::script::RevisionScript::*;
-use crate::revision_test::script::{InvalidRevisionObject, RevisionTest};
+use crate::revision_test::script::{InvalidRevisionObject, RevisionMirrorMock, RevisionTest};
+use flowy_http_model::revision::RevisionRange;
+use flowy_revision::{Executor, RevisionWSSinkStep};
+use flowy_revision_persistence::RevisionState;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[tokio::test]
 async fn revision_write_to_disk_test() {
@@ -36,6 +41,33 @@ async fn revision_write_to_disk_with_merge_test() {
     .await;
 }
 
+#[tokio::test]
+async fn revision_count_with_pending_revisions_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    for i in 0..3 {
+        test.run_script(AddLocalRevision {
+            content: format!("{}", i),
+        })
+        .await;
+    }
+    test.run_scripts(vec![
+        AssertNumberOfRevisionsInDisk { num: 0 },
+        AssertRevisionCount { num: 3 },
+        WaitWhenWriteToDisk,
+        AssertNumberOfRevisionsInDisk { num: 3 },
+        AssertRevisionCount { num: 3 },
+        AddLocalRevision {
+            content: "3".to_string(),
+        },
+        AddLocalRevision {
+            content: "4".to_string(),
+        },
+        AssertNumberOfRevisionsInDisk { num: 3 },
+        AssertRevisionCount { num: 5 },
+    ])
+    .await;
+}
+
 #[tokio::test]
 async fn revision_read_from_disk_test() {
     let test = RevisionTest::new_with_configuration(2).await;
@@ -83,3 +115,314 @@ async fn revision_read_from_disk_with_invalid_record_test() {
     }])
     .await;
 }
+
+#[tokio::test]
+async fn revision_ack_and_persist_test() {
+    let test = RevisionTest::new_with_configuration(2).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        AssertNumberOfRevisionsInDisk { num: 0 },
+        AckRevisionAndPersist { rev_id: 1 },
+        AssertNumberOfRevisionsInDisk { num: 1 },
+        AssertNextSyncRevisionId { rev_id: None },
+    ])
+    .await;
+
+    // The revision was written with the ack state, so it doesn't need to sync again.
+    let test = RevisionTest::new_with_other(test).await;
+    test.run_scripts(vec![
+        AssertNumberOfRevisionsInDisk { num: 1 },
+        AssertNextSyncRevisionId { rev_id: None },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_ack_with_canonical_rev_id_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        AckRevisionWithCanonicalId {
+            rev_id: 1,
+            canonical: 10,
+        },
+        AssertNextSyncRevisionId { rev_id: None },
+        AssertCanonicalRevisionId {
+            rev_id: 1,
+            canonical: 10,
+        },
+        AssertNumberOfRevisionsInDisk { num: 1 },
+        // The local rev_id resolves to the canonical one.
+        AssertRevision {
+            rev_id: 1,
+            expected: (10, "123".to_string()),
+        },
+        AssertRevisionIdsInRange {
+            range: RevisionRange { start: 10, end: 10 },
+            ids: vec![10],
+        },
+        // The next local revisions are based on the canonical rev_id.
+        AddLocalRevision {
+            content: "456".to_string(),
+        },
+        AddLocalRevision {
+            content: "789".to_string(),
+        },
+        AssertRevisionBaseIds {
+            ids: vec![(10, 0), (11, 10), (12, 11)],
+        },
+        AssertObjectContent {
+            expected: "123456789".to_string(),
+        },
+        AssertNextSyncRevisionId { rev_id: Some(11) },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_ack_with_canonical_rev_id_with_unacked_revisions_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        AddLocalRevision {
+            content: "456".to_string(),
+        },
+    ])
+    .await;
+
+    // The revision 2 is based on the local rev_id 1, so the remap is refused and nothing changes.
+    assert!(test.rev_manager().ack_revision_with_canonical_id(1, 10).await.is_err());
+    test.run_scripts(vec![
+        AssertNextSyncRevisionId { rev_id: Some(1) },
+        AssertCanonicalRevisionId {
+            rev_id: 1,
+            canonical: 1,
+        },
+        AssertRevisionBaseIds {
+            ids: vec![(1, 0), (2, 1)],
+        },
+        AssertObjectContent {
+            expected: "123456".to_string(),
+        },
+    ])
+    .await;
+    assert_eq!(test.rev_id(), 2);
+}
+
+#[tokio::test]
+async fn revision_canonical_rev_id_after_reopen_test() {
+    let (test, disk_cache) = RevisionTest::builder().build().await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        AckRevisionWithCanonicalId {
+            rev_id: 1,
+            canonical: 10,
+        },
+    ])
+    .await;
+
+    test.rev_manager().flush().await.unwrap();
+
+    // The map is read back from the disk, the local rev_id still resolves to the canonical one.
+    let test = RevisionTest::reopen(test, disk_cache).await;
+    test.run_scripts(vec![
+        AssertCanonicalRevisionId {
+            rev_id: 1,
+            canonical: 10,
+        },
+        AssertRevision {
+            rev_id: 1,
+            expected: (10, "123".to_string()),
+        },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_mirror_after_write_to_disk_test() {
+    let mirror = Arc::new(RevisionMirrorMock::default());
+    let test = RevisionTest::new_with_mirror(mirror.clone()).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        AddLocalRevision {
+            content: "456".to_string(),
+        },
+    ])
+    .await;
+    assert!(mirror.mirrored_rev_ids().is_empty());
+
+    test.run_scripts(vec![WaitWhenWriteToDisk, AssertNumberOfRevisionsInDisk { num: 2 }])
+        .await;
+    assert_eq!(mirror.mirrored_rev_ids(), vec![1, 2]);
+
+    // Acking the revisions only updates their state, they are not mirrored again.
+    test.run_scripts(vec![
+        AckRevision { rev_id: 1 },
+        AckRevision { rev_id: 2 },
+        AddLocalRevision {
+            content: "789".to_string(),
+        },
+        WaitWhenWriteToDisk,
+    ])
+    .await;
+    assert_eq!(mirror.mirrored_rev_ids(), vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn revision_mirror_failed_test() {
+    let mirror = Arc::new(RevisionMirrorMock::new_failing());
+    let test = RevisionTest::new_with_mirror(mirror.clone()).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        WaitWhenWriteToDisk,
+        AssertNumberOfRevisionsInDisk { num: 1 },
+        AssertRevisionState {
+            rev_id: 1,
+            state: RevisionState::Sync,
+        },
+    ])
+    .await;
+    assert_eq!(mirror.mirrored_rev_ids(), vec![1]);
+}
+
+#[test]
+fn revision_close_with_executor_test() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let executor = Executor::from_handle(runtime.handle().clone());
+    runtime.block_on(async {
+        let test = RevisionTest::new_with_executor(executor).await;
+        test.run_scripts(vec![
+            AddLocalRevision {
+                content: "123".to_string(),
+            },
+            AssertNumberOfRevisionsInDisk { num: 0 },
+            // The deferred save hasn't run yet, closing writes the revision right away.
+            Close,
+            AssertNumberOfRevisionsInDisk { num: 1 },
+            AssertNextSyncRevisionId { rev_id: Some(1) },
+        ])
+        .await;
+    });
+
+    let start = Instant::now();
+    runtime.shutdown_timeout(Duration::from_secs(5));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn revision_resend_pending_in_rev_id_order_after_restart_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    for content in ["1", "2", "3", "4", "5"] {
+        test.run_scripts(vec![AddLocalRevision {
+            content: content.to_string(),
+        }])
+        .await;
+    }
+    test.run_scripts(vec![WaitWhenWriteToDisk, AssertNumberOfRevisionsInDisk { num: 5 }])
+        .await;
+
+    // The records are read back in another order than the rev_ids, e.g. after a crash.
+    let mut records = test.rev_manager().get_all_revision_records().unwrap();
+    records.reverse();
+    let test = RevisionTest::new_with_other_records(test, records).await;
+    test.run_scripts(vec![AssertNextSyncRevisionId { rev_id: Some(1) }])
+        .await;
+
+    // The sink sends them without waiting for a new edit.
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    for rev_id in 1..=5 {
+        assert!(matches!(sink.step().await, RevisionWSSinkStep::Send(_)));
+        sink.ack(rev_id).await;
+    }
+    assert_eq!(sink.sent_rev_ids(), vec![1, 2, 3, 4, 5]);
+}
+
+#[tokio::test]
+async fn revision_durable_writes_test() {
+    let (test, disk_cache) = RevisionTest::builder()
+        .configure(|configuration| configuration.with_durable_writes())
+        .build()
+        .await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        AddLocalRevision {
+            content: "456".to_string(),
+        },
+        WaitWhenWriteToDisk,
+        AssertNumberOfRevisionsInDisk { num: 2 },
+    ])
+    .await;
+    assert!(disk_cache.num_of_durable_writes() > 0);
+
+    // The flush saves the pending revisions durably too.
+    let num_of_durable_writes = disk_cache.num_of_durable_writes();
+    test.run_script(AddLocalRevision {
+        content: "789".to_string(),
+    })
+    .await;
+    test.rev_manager().flush().await.unwrap();
+    assert_eq!(disk_cache.num_of_durable_writes(), num_of_durable_writes + 1);
+    test.run_scripts(vec![AssertNumberOfRevisionsInDisk { num: 3 }]).await;
+}
+
+#[tokio::test]
+async fn revision_without_durable_writes_test() {
+    let (test, disk_cache) = RevisionTest::builder().build().await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        WaitWhenWriteToDisk,
+        AssertNumberOfRevisionsInDisk { num: 1 },
+    ])
+    .await;
+    assert_eq!(disk_cache.num_of_durable_writes(), 0);
+}
+
+#[tokio::test]
+async fn revision_halt_at_discontinuous_pending_revision_after_restart_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    for content in ["1", "2", "3", "4", "5"] {
+        test.run_scripts(vec![AddLocalRevision {
+            content: content.to_string(),
+        }])
+        .await;
+    }
+    test.run_scripts(vec![WaitWhenWriteToDisk, AssertNumberOfRevisionsInDisk { num: 5 }])
+        .await;
+
+    // The revision 4 isn't based on the revision 3.
+    let mut records = test.rev_manager().get_all_revision_records().unwrap();
+    records.sort_by_key(|record| record.revision.rev_id);
+    records[3].revision.base_rev_id = 1;
+    let test = RevisionTest::new_with_other_records(test, records).await;
+    assert_eq!(test.rev_manager().invalid_rev_id().await, Some(4));
+
+    // The revisions in front of it are still sent, the push stops at it.
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    for rev_id in 1..=3 {
+        assert!(matches!(sink.step().await, RevisionWSSinkStep::Send(_)));
+        sink.ack(rev_id).await;
+    }
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_eq!(sink.sent_rev_ids(), vec![1, 2, 3]);
+}
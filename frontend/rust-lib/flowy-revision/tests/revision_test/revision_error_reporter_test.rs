@@ -0,0 +1,50 @@
+use crate::revision_test::script::RevisionScript::*;
+use crate::revision_test::script::{ErrorReporterMock, RevisionTest};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn revision_save_failed_is_reported_test() {
+    let error_reporter = Arc::new(ErrorReporterMock::default());
+    let (test, disk_cache) = RevisionTest::builder()
+        .configure(|configuration| configuration.with_error_reporter(error_reporter.clone()))
+        .build()
+        .await;
+    disk_cache.set_fail_writes(true);
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        WaitWhenWriteToDisk,
+        AssertNumberOfRevisionsInDisk { num: 0 },
+    ])
+    .await;
+
+    let reports = error_reporter.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].0, format!("save revisions of {}", test.object_id()));
+    assert!(reports[0].1.contains("The disk is full"));
+
+    // The revision stays pending, so it's saved once the disk works again.
+    disk_cache.set_fail_writes(false);
+    test.rev_manager().flush().await.unwrap();
+    test.run_scripts(vec![AssertNumberOfRevisionsInDisk { num: 1 }]).await;
+    assert_eq!(error_reporter.reports().len(), 1);
+}
+
+#[tokio::test]
+async fn revision_save_succeeded_is_not_reported_test() {
+    let error_reporter = Arc::new(ErrorReporterMock::default());
+    let (test, _disk_cache) = RevisionTest::builder()
+        .configure(|configuration| configuration.with_error_reporter(error_reporter.clone()))
+        .build()
+        .await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        WaitWhenWriteToDisk,
+        AssertNumberOfRevisionsInDisk { num: 1 },
+    ])
+    .await;
+    assert!(error_reporter.reports().is_empty());
+}
@@ -0,0 +1,81 @@
+use crate::revision_test::script::{RevisionClockMock, RevisionObjectMock, RevisionScript::*, RevisionTest};
+use flowy_revision::RevisionClock;
+use std::time::Duration;
+
+#[tokio::test(start_paused = true)]
+async fn revision_expiry_sweep_test() {
+    let clock = RevisionClockMock::new();
+    let interval = Duration::from_millis(200);
+    let (test, _disk_cache) = RevisionTest::builder()
+        .contents(vec!["a", "b", "c", "d"])
+        .configure(|configuration| configuration.with_expiry_sweep(interval).with_clock(clock.clone()))
+        .build()
+        .await;
+    let expires_at = clock.now() + 2;
+    for rev_id in 1..=3 {
+        test.rev_manager()
+            .set_revision_expiry(rev_id, Some(expires_at))
+            .await
+            .unwrap();
+    }
+    test.run_scripts(vec![AssertNumberOfRevisionsInDisk { num: 4 }]).await;
+
+    // Nothing expired yet.
+    tokio::time::sleep(interval * 2).await;
+    test.run_scripts(vec![AssertNumberOfRevisionsInDisk { num: 4 }]).await;
+
+    // The sweeper merges the expired revisions once they expire, the last one doesn't expire.
+    clock.advance(3);
+    tokio::time::sleep(interval * 2).await;
+    test.run_scripts(vec![
+        AssertNumberOfRevisionsInDisk { num: 2 },
+        AssertObjectContent {
+            expected: "abcd".to_string(),
+        },
+    ])
+    .await;
+
+    // The snapshot taken before the sweep has the whole content.
+    let snapshot = test.rev_manager().read_snapshot(None).await.unwrap().unwrap();
+    assert_eq!(snapshot.rev_id, 4);
+    assert_eq!(
+        RevisionObjectMock::from_bytes(&snapshot.data).unwrap().content(),
+        "abcd"
+    );
+}
+
+#[tokio::test]
+async fn revision_expiry_keeps_unexpired_revisions_test() {
+    let clock = RevisionClockMock::new();
+    let (test, _disk_cache) = RevisionTest::builder()
+        .contents(vec!["a", "b", "c"])
+        .configure(|configuration| {
+            configuration
+                .with_expiry_sweep(Duration::from_secs(60))
+                .with_clock(clock.clone())
+        })
+        .build()
+        .await;
+    // The first revision doesn't expire, so the expired ones behind it can't be merged.
+    for rev_id in 2..=3 {
+        test.rev_manager()
+            .set_revision_expiry(rev_id, Some(clock.now() - 1))
+            .await
+            .unwrap();
+    }
+    assert_eq!(test.rev_manager().sweep_expired_revisions().await.unwrap(), 0);
+
+    test.rev_manager()
+        .set_revision_expiry(1, Some(clock.now() - 1))
+        .await
+        .unwrap();
+    assert_eq!(test.rev_manager().sweep_expired_revisions().await.unwrap(), 3);
+    test.run_scripts(vec![
+        AssertNumberOfRevisionsInDisk { num: 1 },
+        AssertObjectContent {
+            expected: "abc".to_string(),
+        },
+    ])
+    .await;
+    assert_eq!(test.rev_manager().sweep_expired_revisions().await.unwrap(), 0);
+}
@@ -0,0 +1,96 @@
+use crate::revision_test::script::{RevisionScript::*, RevisionTest};
+use flowy_revision::{RevLifecycleEvent, RevisionWSSinkStep};
+
+#[tokio::test]
+async fn revision_lifecycle_from_created_to_acked_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![AddLocalRevision {
+        content: "1".to_string(),
+    }])
+    .await;
+    assert_eq!(
+        lifecycle_events(&test, 1),
+        vec![RevLifecycleEvent::Created, RevLifecycleEvent::Pending]
+    );
+
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Send(_)));
+    sink.ack(1).await;
+    assert_eq!(
+        lifecycle_events(&test, 1),
+        vec![
+            RevLifecycleEvent::Created,
+            RevLifecycleEvent::Pending,
+            RevLifecycleEvent::Pushed,
+            RevLifecycleEvent::Acked,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn revision_lifecycle_failed_to_send_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![AddLocalRevision {
+        content: "1".to_string(),
+    }])
+    .await;
+
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    sink.set_offline(true);
+    assert!(sink.try_step().await.is_err());
+
+    // The revision is sent again once the web socket is back.
+    sink.set_offline(false);
+    let _ = sink.step().await;
+    sink.ack(1).await;
+
+    let events = lifecycle_events(&test, 1);
+    assert_eq!(events.len(), 5);
+    assert_eq!(
+        events[..3],
+        [
+            RevLifecycleEvent::Created,
+            RevLifecycleEvent::Pending,
+            RevLifecycleEvent::Pushed
+        ]
+    );
+    assert!(matches!(&events[3], RevLifecycleEvent::Failed(error) if error.contains("offline")));
+    assert_eq!(events[4], RevLifecycleEvent::Acked);
+}
+
+#[tokio::test]
+async fn revision_lifecycle_merged_test() {
+    let test = RevisionTest::new_with_configuration(2).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+    ])
+    .await;
+    assert_eq!(
+        lifecycle_events(&test, 2),
+        vec![RevLifecycleEvent::Created, RevLifecycleEvent::Merged { into: 1 }]
+    );
+    assert_eq!(
+        lifecycle_events(&test, 1),
+        vec![RevLifecycleEvent::Created, RevLifecycleEvent::Pending]
+    );
+}
+
+#[tokio::test]
+async fn revision_lifecycle_unknown_revision_test() {
+    let test = RevisionTest::new().await;
+    assert!(test.rev_manager().revision_lifecycle(1).is_none());
+}
+
+/// Returns the events of the revision, they must be recorded in the order they happened.
+fn lifecycle_events(test: &RevisionTest, rev_id: i64) -> Vec<RevLifecycleEvent> {
+    let lifecycle = test.rev_manager().revision_lifecycle(rev_id).unwrap_or_default();
+    assert!(lifecycle.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    lifecycle.into_iter().map(|(_, event)| event).collect()
+}
@@ -0,0 +1,140 @@
+use crate::revision_test::script::{RevisionScript::*, RevisionTest};
+use flowy_revision::{PriorityConfiguration, PriorityScheduler, TaskPriority};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+
+// Records the `name` once the task of the `priority` may run.
+fn spawn_acquire(
+    scheduler: &PriorityScheduler,
+    priority: TaskPriority,
+    name: &'static str,
+    order: &Arc<Mutex<Vec<&'static str>>>,
+) -> JoinHandle<()> {
+    let scheduler = scheduler.clone();
+    let order = order.clone();
+    tokio::spawn(async move {
+        let _permit = scheduler.acquire(priority).await;
+        order.lock().unwrap().push(name);
+    })
+}
+
+#[tokio::test]
+async fn revision_priority_focused_document_test() {
+    let scheduler = PriorityScheduler::default();
+    assert_eq!(scheduler.priority_of("a"), TaskPriority::Background);
+
+    scheduler.set_focused(Some("a"));
+    assert_eq!(scheduler.priority_of("a"), TaskPriority::Foreground);
+    assert_eq!(scheduler.priority_of("b"), TaskPriority::Background);
+
+    scheduler.set_focused(Some("b"));
+    assert_eq!(scheduler.priority_of("a"), TaskPriority::Background);
+    scheduler.set_focused(None);
+    assert_eq!(scheduler.priority_of("b"), TaskPriority::Background);
+}
+
+#[tokio::test(start_paused = true)]
+async fn revision_priority_foreground_before_waiting_background_test() {
+    let scheduler = PriorityScheduler::new(PriorityConfiguration {
+        max_concurrency: 1,
+        background_share: 0.5,
+        max_background_wait: Duration::from_secs(5),
+    });
+    let order = Arc::new(Mutex::new(vec![]));
+    let busy = scheduler.acquire(TaskPriority::Background).await;
+    let background = spawn_acquire(&scheduler, TaskPriority::Background, "background", &order);
+    sleep(Duration::from_millis(10)).await;
+    let foreground = spawn_acquire(&scheduler, TaskPriority::Foreground, "foreground", &order);
+    sleep(Duration::from_millis(10)).await;
+    assert!(order.lock().unwrap().is_empty());
+
+    // The foreground task goes first although the background task waited longer.
+    drop(busy);
+    background.await.unwrap();
+    foreground.await.unwrap();
+    assert_eq!(*order.lock().unwrap(), vec!["foreground", "background"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn revision_priority_background_share_test() {
+    let scheduler = PriorityScheduler::new(PriorityConfiguration {
+        max_concurrency: 4,
+        background_share: 0.25,
+        max_background_wait: Duration::from_secs(5),
+    });
+    let foreground = scheduler.acquire(TaskPriority::Foreground).await;
+    let _background = scheduler.acquire(TaskPriority::Background).await;
+
+    // Only one of the four slots is for the background tasks while a foreground task runs.
+    assert!(
+        timeout(Duration::from_millis(10), scheduler.acquire(TaskPriority::Background))
+            .await
+            .is_err()
+    );
+    drop(foreground);
+    assert!(
+        timeout(Duration::from_millis(10), scheduler.acquire(TaskPriority::Background))
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn revision_priority_background_task_ages_test() {
+    let scheduler = PriorityScheduler::new(PriorityConfiguration {
+        max_concurrency: 1,
+        background_share: 0.5,
+        max_background_wait: Duration::from_millis(100),
+    });
+    let order = Arc::new(Mutex::new(vec![]));
+    let busy = scheduler.acquire(TaskPriority::Foreground).await;
+    let background = spawn_acquire(&scheduler, TaskPriority::Background, "background", &order);
+    sleep(Duration::from_millis(10)).await;
+    let foreground = spawn_acquire(&scheduler, TaskPriority::Foreground, "foreground", &order);
+
+    // The background task waited for max_background_wait, it goes before the foreground task.
+    sleep(Duration::from_millis(150)).await;
+    drop(busy);
+    background.await.unwrap();
+    foreground.await.unwrap();
+    assert_eq!(*order.lock().unwrap(), vec!["background", "foreground"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn revision_priority_focused_object_saves_first_test() {
+    let scheduler = PriorityScheduler::new(PriorityConfiguration {
+        max_concurrency: 1,
+        ..Default::default()
+    });
+    let test = RevisionTest::new_with_priority_scheduler(scheduler.clone()).await;
+    scheduler.set_focused(Some(test.object_id()));
+    test.run_script(AddLocalRevision {
+        content: "123".to_string(),
+    })
+    .await;
+
+    // The disk is busy with the background work, another background task is waiting.
+    let busy = scheduler.acquire(TaskPriority::Background).await;
+    let background = {
+        let scheduler = scheduler.clone();
+        let rev_manager = test.rev_manager().clone();
+        tokio::spawn(async move {
+            let _permit = scheduler.acquire(TaskPriority::Background).await;
+            rev_manager.number_of_revisions_in_disk()
+        })
+    };
+    sleep(Duration::from_millis(10)).await;
+    let flush = {
+        let rev_manager = test.rev_manager().clone();
+        tokio::spawn(async move { rev_manager.flush().await })
+    };
+    sleep(Duration::from_millis(10)).await;
+    test.run_script(AssertNumberOfRevisionsInDisk { num: 0 }).await;
+
+    // The save of the focused object goes before the waiting background task.
+    drop(busy);
+    flush.await.unwrap().unwrap();
+    assert_eq!(background.await.unwrap(), 1);
+}
@@ -0,0 +1,118 @@
+use crate::revision_test::script::{RevisionScript::*, RevisionTest};
+use flowy_revision_persistence::RevisionDiskCache;
+use lib_infra::util::timestamp;
+use std::time::Duration;
+
+const DAY: i64 = 24 * 60 * 60;
+const RETENTION: Duration = Duration::from_secs(30 * DAY as u64);
+
+#[tokio::test]
+async fn revision_purge_older_than_test() {
+    let now = timestamp();
+    let (test, disk_cache) = RevisionTest::builder()
+        .timestamped_contents(vec![
+            ("a", now - 100 * DAY),
+            ("b", now - 90 * DAY),
+            ("c", now - 80 * DAY),
+            ("d", now - DAY),
+            ("e", now),
+        ])
+        .build()
+        .await;
+    let num_of_purged = test.rev_manager().purge_older_than(RETENTION).await.unwrap();
+    assert_eq!(num_of_purged, 3);
+    // The merged revision keeps the time of the newest revision merged into it.
+    let timestamps = disk_cache.read_revision_timestamps(test.object_id()).unwrap();
+    assert_eq!(timestamps[0].1, now - 80 * DAY);
+
+    // The old revisions are merged into one, so the content is kept.
+    test.run_scripts(vec![
+        AssertNumberOfRevisionsInDisk { num: 3 },
+        AssertObjectContent {
+            expected: "abcde".to_string(),
+        },
+    ])
+    .await;
+    let num_of_purged = test.rev_manager().purge_older_than(RETENTION).await.unwrap();
+    assert_eq!(num_of_purged, 0);
+}
+
+#[tokio::test]
+async fn revision_purge_nothing_older_than_test() {
+    let now = timestamp();
+    let (test, _disk_cache) = RevisionTest::builder()
+        .timestamped_contents(vec![("a", now - DAY), ("b", now)])
+        .build()
+        .await;
+    let num_of_purged = test.rev_manager().purge_older_than(RETENTION).await.unwrap();
+    assert_eq!(num_of_purged, 0);
+    test.run_scripts(vec![
+        AssertNumberOfRevisionsInDisk { num: 2 },
+        AssertObjectContent {
+            expected: "ab".to_string(),
+        },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_purge_keeps_unsynced_revisions_test() {
+    let now = timestamp();
+    let (test, disk_cache) = RevisionTest::builder()
+        .timestamped_contents(vec![("a", now - 100 * DAY), ("b", now - 90 * DAY)])
+        .build()
+        .await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "c".to_string(),
+        },
+        AddLocalRevision {
+            content: "d".to_string(),
+        },
+    ])
+    .await;
+    disk_cache.set_revision_timestamp(3, now - 80 * DAY);
+    disk_cache.set_revision_timestamp(4, now - 70 * DAY);
+
+    // The revisions 3 and 4 aren't sent yet, so only the acked ones are purged.
+    let num_of_purged = test.rev_manager().purge_older_than(RETENTION).await.unwrap();
+    assert_eq!(num_of_purged, 2);
+    test.run_scripts(vec![
+        AssertNumberOfRevisionsInDisk { num: 3 },
+        AssertNumberOfSyncRevisions { num: 2 },
+        AssertNextSyncRevisionId { rev_id: Some(3) },
+        AssertObjectContent {
+            expected: "abcd".to_string(),
+        },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_purge_stops_at_pinned_revision_test() {
+    let now = timestamp();
+    let (test, _disk_cache) = RevisionTest::builder()
+        .timestamped_contents(vec![
+            ("a", now - 100 * DAY),
+            ("b", now - 90 * DAY),
+            ("c", now - 80 * DAY),
+            ("d", now - 70 * DAY),
+        ])
+        .build()
+        .await;
+    test.run_scripts(vec![PinRevision { rev_id: 3 }]).await;
+    let num_of_purged = test.rev_manager().purge_older_than(RETENTION).await.unwrap();
+    assert_eq!(num_of_purged, 2);
+    test.run_scripts(vec![
+        AssertNumberOfRevisionsInDisk { num: 3 },
+        AssertPinnedRevisionIds { rev_ids: vec![3] },
+        AssertObjectContentAtRevision {
+            rev_id: 3,
+            expected: "abc".to_string(),
+        },
+        AssertObjectContent {
+            expected: "abcd".to_string(),
+        },
+    ])
+    .await;
+}
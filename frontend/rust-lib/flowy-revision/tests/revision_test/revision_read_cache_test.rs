@@ -0,0 +1,95 @@
+use crate::revision_test::script::RevisionTest;
+use flowy_http_model::revision::RevisionRange;
+
+#[tokio::test]
+async fn revision_read_cache_hits_disk_once_test() {
+    let (test, disk_cache) = RevisionTest::builder()
+        .contents(vec!["a", "b", "c"])
+        .configure(|configuration| configuration.with_read_cache(8))
+        .build()
+        .await;
+    let num_of_reads = disk_cache.num_of_reads();
+    for _ in 0..5 {
+        let revision = test.rev_manager().get_revision(2).await.unwrap();
+        assert_eq!(revision.rev_id, 2);
+    }
+    assert_eq!(disk_cache.num_of_reads(), num_of_reads + 1);
+}
+
+#[tokio::test]
+async fn revision_read_cache_range_test() {
+    let (test, disk_cache) = RevisionTest::builder()
+        .contents(vec!["a", "b", "c"])
+        .configure(|configuration| configuration.with_read_cache(8))
+        .build()
+        .await;
+    let num_of_reads = disk_cache.num_of_reads();
+    for _ in 0..3 {
+        let revisions = test
+            .rev_manager()
+            .get_revisions_in_range(RevisionRange { start: 1, end: 3 })
+            .await
+            .unwrap();
+        let rev_ids = revisions.iter().map(|revision| revision.rev_id).collect::<Vec<i64>>();
+        assert_eq!(rev_ids, vec![1, 2, 3]);
+    }
+    assert_eq!(disk_cache.num_of_reads(), num_of_reads + 1);
+
+    // The revisions of the range are cached, so reading one of them doesn't hit the disk.
+    let _ = test.rev_manager().get_revision(2).await.unwrap();
+    assert_eq!(disk_cache.num_of_reads(), num_of_reads + 1);
+}
+
+#[tokio::test]
+async fn revision_read_cache_evicts_least_recently_used_test() {
+    let (test, disk_cache) = RevisionTest::builder()
+        .contents(vec!["a", "b", "c"])
+        .configure(|configuration| configuration.with_read_cache(2))
+        .build()
+        .await;
+    let rev_manager = test.rev_manager();
+    let num_of_reads = disk_cache.num_of_reads();
+    let _ = rev_manager.get_revision(1).await.unwrap();
+    let _ = rev_manager.get_revision(2).await.unwrap();
+    let _ = rev_manager.get_revision(1).await.unwrap();
+    assert_eq!(disk_cache.num_of_reads(), num_of_reads + 2);
+
+    // Evicts the revision 2, the revision 1 was used more recently.
+    let _ = rev_manager.get_revision(3).await.unwrap();
+    let _ = rev_manager.get_revision(1).await.unwrap();
+    assert_eq!(disk_cache.num_of_reads(), num_of_reads + 3);
+    let _ = rev_manager.get_revision(2).await.unwrap();
+    assert_eq!(disk_cache.num_of_reads(), num_of_reads + 4);
+}
+
+#[tokio::test]
+async fn revision_read_cache_invalidated_on_delete_test() {
+    let (test, disk_cache) = RevisionTest::builder()
+        .contents(vec!["a", "b", "c"])
+        .configure(|configuration| configuration.with_read_cache(8))
+        .build()
+        .await;
+    let rev_manager = test.rev_manager();
+    let first_revision = rev_manager.get_revision(1).await.unwrap();
+    assert!(rev_manager.get_revision(3).await.is_some());
+
+    // Resetting the object deletes the revisions, the cached ones must not be returned.
+    rev_manager.reset_object(vec![first_revision]).await.unwrap();
+    let num_of_reads = disk_cache.num_of_reads();
+    assert!(rev_manager.get_revision(3).await.is_none());
+    assert_eq!(disk_cache.num_of_reads(), num_of_reads + 1);
+}
+
+#[tokio::test]
+async fn revision_read_cache_disabled_test() {
+    let (test, disk_cache) = RevisionTest::builder()
+        .contents(vec!["a", "b", "c"])
+        .configure(|configuration| configuration.with_read_cache(0))
+        .build()
+        .await;
+    let num_of_reads = disk_cache.num_of_reads();
+    for _ in 0..3 {
+        let _ = test.rev_manager().get_revision(2).await.unwrap();
+    }
+    assert_eq!(disk_cache.num_of_reads(), num_of_reads + 3);
+}
@@ -0,0 +1,132 @@
+use crate::revision_test::script::{OpenedRevisionTest, RevisionCloudServiceMock, RevisionObjectMock, RevisionTest};
+use bytes::Bytes;
+use flowy_revision::{RevisionManagerEvent, RevisionSnapshot, RECOVERED_BACKUP_VERSION};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+fn snapshot_newer_than_revisions() -> RevisionSnapshot {
+    RevisionSnapshot {
+        rev_id: 5,
+        base_rev_id: 4,
+        timestamp: 0,
+        data: Bytes::from(RevisionObjectMock::new("abcde").to_bytes()),
+    }
+}
+
+#[tokio::test]
+async fn revision_snapshot_newer_than_revisions_confirmed_by_server_test() {
+    let cloud = RevisionCloudServiceMock {
+        server_rev_id: Some(5),
+        ..Default::default()
+    };
+    let OpenedRevisionTest {
+        test,
+        object,
+        mut event_rx,
+        ..
+    } = RevisionTest::builder()
+        .contents(vec!["a", "b"])
+        .snapshot(snapshot_newer_than_revisions())
+        .cloud(Arc::new(cloud))
+        .open()
+        .await;
+    assert_eq!(object.content(), "abcde");
+    assert_eq!(test.rev_id(), 5);
+
+    // The revisions replaced by the snapshot are backed up, the snapshot is kept as it is.
+    let backup = test.revision_backup();
+    assert_eq!(
+        backup.iter().map(|revision| revision.rev_id).collect::<Vec<i64>>(),
+        vec![1, 2]
+    );
+    assert!(test.demoted_snapshots().is_empty());
+    assert!(event_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn revision_snapshot_newer_than_revisions_rejected_by_server_test() {
+    let cloud = RevisionCloudServiceMock {
+        server_rev_id: Some(2),
+        ..Default::default()
+    };
+    let OpenedRevisionTest {
+        test,
+        object,
+        mut event_rx,
+        ..
+    } = RevisionTest::builder()
+        .contents(vec!["a", "b"])
+        .snapshot(snapshot_newer_than_revisions())
+        .cloud(Arc::new(cloud))
+        .open()
+        .await;
+    assert_eq!(object.content(), "ab");
+    assert_eq!(test.rev_id(), 2);
+    assert!(test.revision_backup().is_empty());
+    assert_snapshot_demoted(&test, &mut event_rx).await;
+}
+
+#[tokio::test]
+async fn revision_snapshot_newer_than_revisions_offline_test() {
+    let OpenedRevisionTest {
+        test,
+        object,
+        mut event_rx,
+        ..
+    } = RevisionTest::builder()
+        .contents(vec!["a", "b"])
+        .snapshot(snapshot_newer_than_revisions())
+        .open()
+        .await;
+    assert_eq!(object.content(), "ab");
+    assert_eq!(test.rev_id(), 2);
+    assert!(test.revision_backup().is_empty());
+    assert_snapshot_demoted(&test, &mut event_rx).await;
+}
+
+/// The snapshot is moved to the recovered backup version and the demotion is sent.
+async fn assert_snapshot_demoted(test: &RevisionTest, event_rx: &mut broadcast::Receiver<RevisionManagerEvent>) {
+    assert_eq!(test.rev_manager().read_snapshot(None).await.unwrap(), None);
+    assert_eq!(
+        test.demoted_snapshots(),
+        vec![(RECOVERED_BACKUP_VERSION.to_owned(), snapshot_newer_than_revisions())]
+    );
+    match event_rx.try_recv().unwrap() {
+        RevisionManagerEvent::SnapshotDemoted { rev_id, .. } => assert_eq!(rev_id, 5),
+        event => panic!("Expected the snapshot to be demoted, but receive: {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn revision_compose_stats_test() {
+    let contents = (0..2000).map(|i| i.to_string()).collect::<Vec<String>>();
+    let contents = contents.iter().map(|content| content.as_str()).collect::<Vec<&str>>();
+    let OpenedRevisionTest { test, mut event_rx, .. } = RevisionTest::builder()
+        .contents(contents)
+        .compose_threshold(Duration::from_secs(3600))
+        .open()
+        .await;
+    let stats = test.last_compose_stats().unwrap();
+    assert_eq!(stats.num_of_revisions, 2000);
+    assert!(stats.duration > Duration::ZERO);
+    assert!(event_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn revision_compose_exceed_threshold_suggest_snapshot_test() {
+    let contents = (0..2000).map(|i| i.to_string()).collect::<Vec<String>>();
+    let contents = contents.iter().map(|content| content.as_str()).collect::<Vec<&str>>();
+    let OpenedRevisionTest { test, mut event_rx, .. } = RevisionTest::builder()
+        .contents(contents)
+        .compose_threshold(Duration::ZERO)
+        .open()
+        .await;
+    match event_rx.try_recv().unwrap() {
+        RevisionManagerEvent::SnapshotSuggested { stats, .. } => {
+            assert_eq!(stats.num_of_revisions, 2000);
+            assert_eq!(Some(stats), test.last_compose_stats());
+        }
+        event => panic!("Expected a snapshot suggestion, but receive: {:?}", event),
+    }
+}
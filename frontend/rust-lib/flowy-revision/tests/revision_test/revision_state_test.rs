@@ -0,0 +1,137 @@
+use crate::revision_test::script::{OpenedRevisionTest, RevisionScript::*, RevisionTest};
+use flowy_http_model::revision::RevisionRange;
+use flowy_revision::RevisionCacheState;
+
+#[tokio::test]
+async fn revision_restore_state_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        AddLocalRevision {
+            content: "456".to_string(),
+        },
+        AddLocalRevision {
+            content: "789".to_string(),
+        },
+        AckRevision { rev_id: 1 },
+    ])
+    .await;
+    let state = test.rev_manager().capture_state().await.unwrap();
+    let bytes = state.to_bytes().unwrap();
+
+    let (restored, disk_cache) = RevisionTest::builder().same_object(&test).build().await;
+    let state = RevisionCacheState::from_bytes(&bytes).unwrap();
+    restored.rev_manager().restore_state(state).await.unwrap();
+    assert_eq!(restored.rev_manager().rev_id(), test.rev_manager().rev_id());
+
+    // The revisions are read from memory, the empty disk isn't read or written.
+    restored
+        .run_scripts(vec![
+            AssertRevision {
+                rev_id: 2,
+                expected: (2, "456".to_string()),
+            },
+            AssertRevisionIdsInRange {
+                range: RevisionRange { start: 1, end: 3 },
+                ids: vec![1, 2, 3],
+            },
+            AssertNumberOfSyncRevisions { num: 3 },
+            AssertNextSyncRevisionId { rev_id: Some(2) },
+            AssertNextSyncRevisionContent {
+                expected: "456".to_string(),
+            },
+            AssertNumberOfRevisionsInDisk { num: 0 },
+        ])
+        .await;
+    assert_eq!(disk_cache.num_of_reads(), 0);
+
+    // The restored object carries on where the captured one left off.
+    restored
+        .run_scripts(vec![
+            AckRevision { rev_id: 2 },
+            AssertNextSyncRevisionId { rev_id: Some(3) },
+            AddLocalRevision {
+                content: "0".to_string(),
+            },
+            AssertRevision {
+                rev_id: 4,
+                expected: (4, "0".to_string()),
+            },
+        ])
+        .await;
+}
+
+#[tokio::test]
+async fn revision_initialize_from_state_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "123".to_string(),
+        },
+        AddLocalRevision {
+            content: "456".to_string(),
+        },
+        AckRevision { rev_id: 1 },
+    ])
+    .await;
+    let bytes = test.rev_manager().capture_state().await.unwrap().to_bytes().unwrap();
+
+    // The object is built from the captured head, the revisions aren't read or composed again.
+    let state = RevisionCacheState::from_bytes(&bytes).unwrap();
+    let OpenedRevisionTest {
+        test: restored,
+        disk_cache,
+        object,
+        ..
+    } = RevisionTest::builder().same_object(&test).state(state).open().await;
+    assert_eq!(object.content(), "123456");
+    assert_eq!(restored.rev_manager().rev_id(), 2);
+    assert_eq!(disk_cache.num_of_reads(), 0);
+    assert!(restored.last_compose_stats().is_none());
+
+    // The head is captured again without composing the revisions while nothing changed.
+    let state = restored.rev_manager().capture_state().await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&state.to_bytes().unwrap()).unwrap();
+    let captured: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(json["head"], captured["head"]);
+    assert_eq!(disk_cache.num_of_reads(), 0);
+
+    // The head has to be at the rev_id of the state.
+    let mut json = captured;
+    json["head"]["rev_id"] = serde_json::json!(1);
+    let invalid = RevisionCacheState::from_bytes(&serde_json::to_vec(&json).unwrap()).unwrap();
+    let (other, _disk_cache) = RevisionTest::builder().same_object(&test).build().await;
+    assert!(other.rev_manager().restore_state(invalid).await.is_err());
+}
+
+#[tokio::test]
+async fn revision_restore_invalid_state_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![AddLocalRevision {
+        content: "123".to_string(),
+    }])
+    .await;
+    let state = test.rev_manager().capture_state().await.unwrap();
+
+    // The state of another object.
+    let other = RevisionTest::new_with_configuration(100).await;
+    assert!(other.rev_manager().restore_state(state.clone()).await.is_err());
+
+    // The state whose revision to sync is missing.
+    let mut json: serde_json::Value = serde_json::from_slice(&state.to_bytes().unwrap()).unwrap();
+    json["sync_rev_ids"] = serde_json::json!([1, 2]);
+    let invalid = RevisionCacheState::from_bytes(&serde_json::to_vec(&json).unwrap()).unwrap();
+    let (restored, _disk_cache) = RevisionTest::builder().same_object(&test).build().await;
+    assert!(restored.rev_manager().restore_state(invalid).await.is_err());
+    restored
+        .run_scripts(vec![
+            AssertNumberOfSyncRevisions { num: 0 },
+            AssertNextSyncRevisionId { rev_id: None },
+        ])
+        .await;
+
+    // The object whose revisions aren't saved yet keeps them.
+    assert!(test.rev_manager().restore_state(state).await.is_err());
+}
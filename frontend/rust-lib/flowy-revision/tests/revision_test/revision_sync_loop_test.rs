@@ -0,0 +1,200 @@
+use crate::revision_test::script::{RevisionConnectionMock, RevisionTest};
+use bytes::Bytes;
+use flowy_error::{FlowyError, FlowyResult};
+use flowy_http_model::revision::Revision;
+use flowy_http_model::util::md5;
+use flowy_revision::{
+    ConflictController, ConflictResolver, ConflictRevisionSink, OperationsDeserializer, OperationsSerializer,
+    RevisionMD5, SyncLoopConfiguration, SyncLoopObserver, TransformOperations,
+};
+use lib_infra::future::BoxResultFuture;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[tokio::test]
+async fn sync_loop_ping_pong_trips_breaker_test() {
+    let test = SyncLoopTest::new().await;
+    for _ in 0..10 {
+        test.push("ping").await;
+    }
+    // The third push repeats the content more than 3 times, so it's queued without an echo.
+    assert_eq!(test.sent_contents(), vec!["ping", "ping"]);
+    assert!(test.controller.is_sync_loop_detected());
+    assert_eq!(test.observer.detected_object_ids(), vec![test.object_id.clone()]);
+}
+
+#[tokio::test]
+async fn sync_loop_resume_after_breaker_tripped_test() {
+    let test = SyncLoopTest::new().await;
+    for _ in 0..5 {
+        test.push("ping").await;
+    }
+    assert!(test.controller.is_sync_loop_detected());
+
+    // The queued pushes are applied on resume. They were pushed at the same rev_id, so only
+    // the first one is echoed, the others are already applied.
+    test.controller.resume_sync().await.unwrap();
+    test.push("ping hello").await;
+    test.push("ping hello world").await;
+    assert!(!test.controller.is_sync_loop_detected());
+    assert_eq!(
+        test.sent_contents(),
+        vec!["ping", "ping", "ping", "ping hello", "ping hello world"]
+    );
+    assert_eq!(test.observer.detected_object_ids().len(), 1);
+}
+
+#[tokio::test]
+async fn sync_loop_undo_and_redo_not_detected_test() {
+    let test = SyncLoopTest::new().await;
+    for _ in 0..10 {
+        test.push("a").await;
+        test.push("ab").await;
+    }
+    assert!(!test.controller.is_sync_loop_detected());
+    assert_eq!(test.sent_contents().len(), 20);
+}
+
+#[tokio::test(start_paused = true)]
+async fn sync_loop_reset_after_quiet_period_test() {
+    let test = SyncLoopTest::new().await;
+    for _ in 0..10 {
+        test.push("ping").await;
+        tokio::time::advance(Duration::from_secs(31)).await;
+    }
+    assert!(!test.controller.is_sync_loop_detected());
+    assert_eq!(test.sent_contents().len(), 10);
+}
+
+struct SyncLoopTest {
+    test: RevisionTest,
+    object_id: String,
+    controller: ConflictController<OperationsMock, RevisionConnectionMock>,
+    sink: Arc<RevisionSinkMock>,
+    observer: Arc<SyncLoopObserverMock>,
+}
+
+impl SyncLoopTest {
+    async fn new() -> Self {
+        let test = RevisionTest::new().await;
+        let object_id = test.object_id().to_owned();
+        let sink = Arc::new(RevisionSinkMock::default());
+        let observer = Arc::new(SyncLoopObserverMock::default());
+        let configuration = SyncLoopConfiguration {
+            max_repeats: 3,
+            ..Default::default()
+        };
+        let controller = test
+            .conflict_controller(Arc::new(EchoResolverMock()), sink.clone())
+            .with_sync_loop_configuration(configuration)
+            .with_sync_loop_observer(observer.clone());
+        Self {
+            test,
+            object_id,
+            controller,
+            sink,
+            observer,
+        }
+    }
+
+    /// Pushes the revision that turns the object into `content`.
+    async fn push(&self, content: &str) {
+        let rev_id = self.test.rev_id() + 1;
+        let revision = Revision::new(
+            &self.object_id,
+            rev_id - 1,
+            rev_id,
+            Bytes::from(content.to_owned()),
+            md5(content),
+        );
+        self.controller.receive_revisions(vec![revision]).await.unwrap();
+    }
+
+    fn sent_contents(&self) -> Vec<String> {
+        self.sink
+            .sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|revision| String::from_utf8(revision.bytes.clone()).unwrap())
+            .collect()
+    }
+}
+
+/// The content of the object. The operations replace the content instead of composing it.
+#[derive(Clone)]
+struct OperationsMock(String);
+
+impl OperationsDeserializer<OperationsMock> for OperationsMock {
+    fn deserialize_revisions(revisions: Vec<Revision>) -> FlowyResult<OperationsMock> {
+        let content = revisions
+            .last()
+            .map(|revision| String::from_utf8(revision.bytes.clone()).unwrap())
+            .unwrap_or_default();
+        Ok(OperationsMock(content))
+    }
+}
+
+impl OperationsSerializer for OperationsMock {
+    fn serialize_operations(&self) -> Bytes {
+        Bytes::from(self.0.clone())
+    }
+}
+
+/// Transforms each pushed revision into a revision that is sent back, like the client that
+/// re-sends the revisions of the other client.
+struct EchoResolverMock();
+impl ConflictResolver<OperationsMock> for EchoResolverMock {
+    fn compose_operations(&self, operations: OperationsMock) -> BoxResultFuture<RevisionMD5, FlowyError> {
+        Box::pin(async move { RevisionMD5::from_bytes(operations.0) })
+    }
+
+    fn transform_operations(
+        &self,
+        operations: OperationsMock,
+    ) -> BoxResultFuture<TransformOperations<OperationsMock>, FlowyError> {
+        Box::pin(async move {
+            Ok(TransformOperations {
+                client_operations: operations.clone(),
+                server_operations: Some(operations),
+            })
+        })
+    }
+
+    fn reset_operations(&self, operations: OperationsMock) -> BoxResultFuture<RevisionMD5, FlowyError> {
+        Box::pin(async move { RevisionMD5::from_bytes(operations.0) })
+    }
+}
+
+#[derive(Default)]
+struct RevisionSinkMock {
+    sent: Mutex<Vec<Revision>>,
+}
+
+impl ConflictRevisionSink for RevisionSinkMock {
+    fn send(&self, revisions: Vec<Revision>) -> BoxResultFuture<(), FlowyError> {
+        self.sent.lock().unwrap().extend(revisions);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn ack(&self, _rev_id: i64) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[derive(Default)]
+struct SyncLoopObserverMock {
+    detected: Mutex<Vec<String>>,
+}
+
+impl SyncLoopObserverMock {
+    fn detected_object_ids(&self) -> Vec<String> {
+        self.detected.lock().unwrap().clone()
+    }
+}
+
+impl SyncLoopObserver for SyncLoopObserverMock {
+    fn did_detect_sync_loop(&self, object_id: &str, _md5: &str) {
+        self.detected.lock().unwrap().push(object_id.to_owned());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::revision_test::script::{RevisionCloudServiceMock, RevisionObjectMock, RevisionScript::*, RevisionTest};
+use flowy_http_model::revision::Revision;
+use flowy_http_model::util::md5;
+use flowy_revision::{RevisionCloudService, SyncPlan};
+use std::sync::Arc;
+
+fn remote_revision(object_id: &str, rev_id: i64, content: &str) -> Revision {
+    let bytes = RevisionObjectMock::new(content).to_bytes();
+    let md5 = md5(&bytes);
+    Revision::new(object_id, rev_id - 1, rev_id, bytes.into(), md5)
+}
+
+// Saves the revisions 1 to 4 locally, the first two are acked.
+async fn test_with_local_history() -> RevisionTest {
+    let test = RevisionTest::new_with_configuration(100).await;
+    for content in ["a", "b", "c", "d"] {
+        test.run_script(AddLocalRevision {
+            content: content.to_string(),
+        })
+        .await;
+    }
+    test.run_scripts(vec![AckRevision { rev_id: 1 }, AckRevision { rev_id: 2 }])
+        .await;
+    test
+}
+
+async fn local_revisions(test: &RevisionTest, rev_ids: &[i64]) -> Vec<Revision> {
+    let mut revisions = vec![];
+    for rev_id in rev_ids {
+        revisions.push(test.rev_manager().get_revision(*rev_id).await.unwrap());
+    }
+    revisions
+}
+
+#[tokio::test]
+async fn revision_sync_plan_with_divergent_histories_test() {
+    let test = test_with_local_history().await;
+    let mut revisions = local_revisions(&test, &[1, 2]).await;
+    revisions.push(remote_revision(test.object_id(), 3, "x"));
+    revisions.push(remote_revision(test.object_id(), 4, "y"));
+    revisions.push(remote_revision(test.object_id(), 5, "z"));
+    let cloud: Arc<dyn RevisionCloudService> = Arc::new(RevisionCloudServiceMock {
+        revisions,
+        ..Default::default()
+    });
+
+    let plan = test.rev_manager().sync_plan(&cloud).await.unwrap();
+    assert_eq!(
+        plan,
+        SyncPlan {
+            common_rev_id: 2,
+            pushes: vec![3, 4],
+            pulls: vec![3, 4, 5],
+            needs_rebase: true,
+        }
+    );
+
+    // It's a dry run, the local revisions are still waiting to be pushed.
+    test.run_scripts(vec![
+        AssertNextSyncRevisionId { rev_id: Some(3) },
+        AssertRevision {
+            rev_id: 3,
+            expected: (3, "c".to_string()),
+        },
+    ])
+    .await;
+}
+
+#[tokio::test]
+async fn revision_sync_plan_with_local_changes_only_test() {
+    let test = test_with_local_history().await;
+    let revisions = local_revisions(&test, &[1, 2, 3]).await;
+    let cloud: Arc<dyn RevisionCloudService> = Arc::new(RevisionCloudServiceMock {
+        revisions,
+        ..Default::default()
+    });
+
+    // The server already has the revision 3, only its ack was lost.
+    let plan = test.rev_manager().sync_plan(&cloud).await.unwrap();
+    assert_eq!(
+        plan,
+        SyncPlan {
+            common_rev_id: 3,
+            pushes: vec![4],
+            pulls: vec![],
+            needs_rebase: false,
+        }
+    );
+}
+
+#[tokio::test]
+async fn revision_sync_plan_with_remote_changes_only_test() {
+    let test = test_with_local_history().await;
+    test.run_scripts(vec![AckRevision { rev_id: 3 }, AckRevision { rev_id: 4 }])
+        .await;
+    let mut revisions = local_revisions(&test, &[1, 2, 3, 4]).await;
+    revisions.push(remote_revision(test.object_id(), 5, "e"));
+    let cloud: Arc<dyn RevisionCloudService> = Arc::new(RevisionCloudServiceMock {
+        revisions,
+        ..Default::default()
+    });
+
+    let plan = test.rev_manager().sync_plan(&cloud).await.unwrap();
+    assert_eq!(
+        plan,
+        SyncPlan {
+            common_rev_id: 4,
+            pushes: vec![],
+            pulls: vec![5],
+            needs_rebase: false,
+        }
+    );
+}
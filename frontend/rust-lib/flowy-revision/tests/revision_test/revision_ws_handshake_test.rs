@@ -0,0 +1,188 @@
+use crate::revision_test::script::{RevisionScript::*, RevisionTest, RevisionWSSinkTest};
+use bytes::Bytes;
+use flowy_error::FlowyError;
+use flowy_http_model::revision::{Revision, RevisionRange};
+use flowy_http_model::ws_data::{
+    ClientRevisionWSDataType, NewDocumentUser, ServerRevisionWSData, ServerRevisionWSDataBuilder, WSCapabilities,
+    WSHandshake, WSRevisionPayload, LEGACY_WS_PROTOCOL_VERSION,
+};
+use flowy_revision::{RevisionWSDataStream, RevisionWSSinkStep, RevisionWSStream, WSSession};
+use lib_infra::future::BoxResultFuture;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+struct ObjectMock();
+impl RevisionWSDataStream for ObjectMock {
+    fn receive_push_revision(&self, _revisions: Vec<Revision>) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn receive_ack(&self, _rev_id: i64) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn receive_new_user_connect(&self, _new_user: NewDocumentUser) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn pull_revisions_in_range(&self, _range: RevisionRange) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Adds three revisions and returns a sink and a stream that share the session, like the ones
+/// of the `RevisionWebSocketManager`. The server messages are sent through the returned sender.
+async fn setup(session: WSSession) -> (RevisionTest, RevisionWSSinkTest, mpsc::Sender<ServerRevisionWSData>) {
+    let test = RevisionTest::new_with_push_window(100, 4).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+        AddLocalRevision {
+            content: "3".to_string(),
+        },
+    ])
+    .await;
+    let ws_sink = test.ws_sink_with_session(session.clone());
+    let (ws_msg_tx, ws_msg_rx) = mpsc::channel(10);
+    let (stop_tx, stop_rx) = broadcast::channel(1);
+    let stream = RevisionWSStream::new("Mock", test.object_id(), Arc::new(ObjectMock()), ws_msg_rx, stop_rx)
+        .with_session(session, ws_sink.web_socket());
+    tokio::spawn(async move {
+        let _stop_tx = stop_tx;
+        stream.run().await
+    });
+    (test, ws_sink, ws_msg_tx)
+}
+
+async fn receive(ws_msg_tx: &mpsc::Sender<ServerRevisionWSData>, json: &str) {
+    let msg = ServerRevisionWSData::try_from(Bytes::from(json.to_owned())).unwrap();
+    ws_msg_tx.send(msg).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+}
+
+/// Sends the handshake, the next step switches the provider to the revisions.
+async fn send_handshake(ws_sink: &RevisionWSSinkTest) {
+    match ws_sink.step().await {
+        RevisionWSSinkStep::Send(data) => assert!(data.handshake.is_some()),
+        step => panic!("Expected the handshake, but receive: {:?}", step),
+    }
+    assert!(matches!(ws_sink.step().await, RevisionWSSinkStep::Idle));
+}
+
+fn sent_rev_id(step: RevisionWSSinkStep) -> (i64, bool) {
+    match step {
+        RevisionWSSinkStep::Send(data) => (data.rev_id, false),
+        RevisionWSSinkStep::Resend(data) => (data.rev_id, true),
+        RevisionWSSinkStep::Idle => panic!("Expected sending a revision"),
+    }
+}
+
+#[tokio::test]
+async fn ws_handshake_is_a_ping_for_legacy_server_test() {
+    let (_test, ws_sink, _ws_msg_tx) = setup(WSSession::default()).await;
+    match ws_sink.step().await {
+        RevisionWSSinkStep::Send(data) => {
+            assert_eq!(data.ty, ClientRevisionWSDataType::ClientPing);
+            assert_eq!(data.handshake, Some(WSHandshake::new(WSCapabilities::ALL)));
+        }
+        step => panic!("Expected the handshake, but receive: {:?}", step),
+    }
+
+    // The legacy server reads the handshake as a plain ping, it ignores the unknown field.
+    let json = serde_json::to_value(&ws_sink.sent()[0]).unwrap();
+    assert_eq!(json["ty"], 1);
+    assert!(json["handshake"].is_object());
+    assert!(json.get("unsupported").is_none());
+}
+
+#[tokio::test]
+async fn ws_handshake_flow_control_fallback_test() {
+    let session = WSSession::default();
+    let (_test, ws_sink, _ws_msg_tx) = setup(session.clone()).await;
+    send_handshake(&ws_sink).await;
+
+    // The legacy server never answers the handshake, one revision is in flight at a time.
+    assert_eq!(session.negotiated(), WSCapabilities::LEGACY);
+    assert_eq!(sent_rev_id(ws_sink.step().await), (1, false));
+    assert_eq!(sent_rev_id(ws_sink.step().await), (1, true));
+    ws_sink.ack(1).await;
+    assert_eq!(sent_rev_id(ws_sink.step().await), (2, false));
+    assert_eq!(sent_rev_id(ws_sink.step().await), (2, true));
+}
+
+#[tokio::test]
+async fn ws_handshake_flow_control_negotiated_test() {
+    let session = WSSession::default();
+    let (_test, ws_sink, ws_msg_tx) = setup(session.clone()).await;
+    send_handshake(&ws_sink).await;
+    let msg = ServerRevisionWSDataBuilder::build_handshake_message("a", WSHandshake::new(WSCapabilities::ALL));
+    ws_msg_tx.send(msg).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    assert_eq!(session.negotiated(), WSCapabilities::ALL);
+    assert_eq!(sent_rev_id(ws_sink.step().await), (1, false));
+    assert_eq!(sent_rev_id(ws_sink.step().await), (2, false));
+    assert_eq!(sent_rev_id(ws_sink.step().await), (3, false));
+}
+
+#[tokio::test]
+async fn ws_handshake_unsupported_frame_fallback_test() {
+    let session = WSSession::default();
+    let (_test, ws_sink, ws_msg_tx) = setup(session.clone()).await;
+    send_handshake(&ws_sink).await;
+
+    // The frame of a newer feature is read as unknown instead of failing.
+    let json = r#"{"object_id":"a","payload":{"ServerReset":{"rev_id":1}}}"#;
+    let msg = ServerRevisionWSData::try_from(Bytes::from(json)).unwrap();
+    assert!(matches!(msg.payload, WSRevisionPayload::Unknown { ref ty } if ty == "ServerReset"));
+
+    // The legacy server doesn't know the unsupported frame, the unknown frame is only logged.
+    receive(&ws_msg_tx, json).await;
+    assert_eq!(ws_sink.sent().len(), 1);
+
+    let msg = ServerRevisionWSDataBuilder::build_handshake_message("a", WSHandshake::new(WSCapabilities::ALL));
+    ws_msg_tx.send(msg).await.unwrap();
+    receive(&ws_msg_tx, json).await;
+    let sent = ws_sink.sent();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[1].ty, ClientRevisionWSDataType::ClientUnsupported);
+    assert_eq!(sent[1].unsupported.as_ref().unwrap().ty, "ServerReset");
+}
+
+#[tokio::test]
+async fn ws_handshake_old_server_version_test() {
+    let session = WSSession::default();
+    let handshake = WSHandshake {
+        version: LEGACY_WS_PROTOCOL_VERSION,
+        capabilities: WSCapabilities::ALL,
+    };
+    assert_eq!(session.negotiate(&handshake), WSCapabilities::LEGACY);
+
+    // The server only advertises some of the capabilities.
+    let handshake = WSHandshake::new(WSCapabilities::FLOW_CONTROL);
+    assert_eq!(session.negotiate(&handshake), WSCapabilities::FLOW_CONTROL);
+    assert!(!session.supports(WSCapabilities::UNSUPPORTED_FRAME));
+}
+
+#[tokio::test]
+async fn ws_handshake_sent_again_after_reconnect_test() {
+    let session = WSSession::default();
+    let (_test, ws_sink, _ws_msg_tx) = setup(session.clone()).await;
+    send_handshake(&ws_sink).await;
+    session.negotiate(&WSHandshake::new(WSCapabilities::ALL));
+
+    session.reset();
+    assert_eq!(session.negotiated(), WSCapabilities::LEGACY);
+    assert_eq!(session.max_push_window(), 1);
+    match ws_sink.step().await {
+        RevisionWSSinkStep::Send(data) => assert!(data.handshake.is_some()),
+        step => panic!("Expected the handshake, but receive: {:?}", step),
+    }
+    assert_eq!(ws_sink.sent().len(), 2);
+}
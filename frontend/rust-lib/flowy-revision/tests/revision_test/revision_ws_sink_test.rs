@@ -0,0 +1,322 @@
+use crate::revision_test::script::{InvalidRevisionObject, RevisionScript::*, RevisionTest};
+use bytes::Bytes;
+use flowy_http_model::revision::Revision;
+use flowy_http_model::ws_data::{ClientRevisionWSData, ClientRevisionWSDataType};
+use flowy_revision::{RevisionManagerEvent, RevisionWSSinkStep};
+use flowy_revision_persistence::RevisionState;
+
+fn assert_send(step: RevisionWSSinkStep, rev_id: i64) {
+    match step {
+        RevisionWSSinkStep::Send(data) => {
+            assert_eq!(data.ty, ClientRevisionWSDataType::ClientPushRev);
+            assert_eq!(data.rev_id, rev_id);
+        }
+        step => panic!("Expected sending {}, but receive: {:?}", rev_id, step),
+    }
+}
+
+fn assert_resend(step: RevisionWSSinkStep, rev_id: i64) {
+    match step {
+        RevisionWSSinkStep::Resend(data) => assert_eq!(data.rev_id, rev_id),
+        step => panic!("Expected resending {}, but receive: {:?}", rev_id, step),
+    }
+}
+
+fn assert_ping(step: RevisionWSSinkStep, rev_id: i64) {
+    match step {
+        RevisionWSSinkStep::Send(data) => {
+            assert_eq!(data.ty, ClientRevisionWSDataType::ClientPing);
+            assert_eq!(data.rev_id, rev_id);
+        }
+        step => panic!("Expected ping {}, but receive: {:?}", rev_id, step),
+    }
+}
+
+#[tokio::test]
+async fn ws_sink_send_revisions_in_order_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+    ])
+    .await;
+
+    let sink = test.ws_sink();
+    // The sink sends the pushed data first, the provider switches to the revisions when
+    // there is no pushed data.
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_send(sink.step().await, 1);
+    sink.ack(1).await;
+    assert_send(sink.step().await, 2);
+    sink.ack(2).await;
+    // Keep the connection alive with the latest rev_id when all the revisions are acked.
+    assert_ping(sink.step().await, 2);
+    assert_ping(sink.step().await, 2);
+    assert_eq!(sink.sent_rev_ids(), vec![1, 2, 2, 2]);
+}
+
+#[tokio::test]
+async fn ws_sink_resend_until_acked_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+    ])
+    .await;
+
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_send(sink.step().await, 1);
+    assert_resend(sink.step().await, 1);
+    assert_resend(sink.step().await, 1);
+    sink.ack(1).await;
+    assert_send(sink.step().await, 2);
+    assert_eq!(sink.sent_rev_ids(), vec![1, 1, 1, 2]);
+}
+
+#[tokio::test]
+async fn ws_sink_ignore_unexpected_ack_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+    ])
+    .await;
+
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_send(sink.step().await, 1);
+    // The revision 1 isn't acked, so the ack of the revision 2 is ignored.
+    sink.ack(2).await;
+    assert_resend(sink.step().await, 1);
+    test.run_scripts(vec![AssertNextSyncRevisionId { rev_id: Some(1) }])
+        .await;
+    sink.ack(1).await;
+    assert_send(sink.step().await, 2);
+}
+
+#[tokio::test]
+async fn ws_sink_send_pushed_data_before_revisions_test() {
+    let test = RevisionTest::new_with_configuration(100).await;
+    test.run_scripts(vec![AddLocalRevision {
+        content: "1".to_string(),
+    }])
+    .await;
+
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_send(sink.step().await, 1);
+    sink.ack(1).await;
+
+    // The server asks for the revisions it missed.
+    let revision = Revision::new("", 9, 10, Bytes::new(), "");
+    sink.push_data(ClientRevisionWSData::from_revisions("", vec![revision]))
+        .await;
+    test.run_scripts(vec![AddLocalRevision {
+        content: "2".to_string(),
+    }])
+    .await;
+    // The provider switches back to the pushed data.
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_send(sink.step().await, 10);
+    // The pushed data is acked with another rev_id, it keeps being resent.
+    sink.ack(2).await;
+    assert_resend(sink.step().await, 10);
+    sink.ack(10).await;
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_send(sink.step().await, 2);
+    assert_eq!(sink.sent_rev_ids(), vec![1, 10, 10, 2]);
+}
+
+#[tokio::test]
+async fn ws_sink_push_window_test() {
+    let test = RevisionTest::new_with_push_window(100, 3).await;
+    for content in ["1", "2", "3", "4", "5"] {
+        test.run_scripts(vec![AddLocalRevision {
+            content: content.to_string(),
+        }])
+        .await;
+    }
+
+    // The server is slow to ack, so the sink keeps pushing until the window is full.
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_send(sink.step().await, 1);
+    assert_send(sink.step().await, 2);
+    assert_send(sink.step().await, 3);
+    assert_resend(sink.step().await, 1);
+
+    // Each ack makes room for one more revision.
+    sink.ack(1).await;
+    assert_send(sink.step().await, 4);
+    assert_resend(sink.step().await, 2);
+
+    // The acks are processed in order, the ack of the revision 3 is ignored before the
+    // revision 2 gets acked.
+    sink.ack(3).await;
+    test.run_scripts(vec![AssertNextSyncRevisionId { rev_id: Some(2) }])
+        .await;
+    sink.ack(2).await;
+    sink.ack(3).await;
+    assert_send(sink.step().await, 5);
+    sink.ack(4).await;
+    sink.ack(5).await;
+    assert_ping(sink.step().await, 5);
+    assert_eq!(sink.sent_rev_ids(), vec![1, 2, 3, 1, 4, 2, 5, 5]);
+}
+
+#[tokio::test]
+async fn ws_sink_push_window_keeps_pushed_revisions_test() {
+    let test = RevisionTest::new_with_push_window(2, 2).await;
+    let sink = test.ws_sink();
+    test.run_scripts(vec![AddLocalRevision {
+        content: "1".to_string(),
+    }])
+    .await;
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_send(sink.step().await, 1);
+    test.run_scripts(vec![AddLocalRevision {
+        content: "2".to_string(),
+    }])
+    .await;
+    assert_send(sink.step().await, 2);
+
+    // The revisions that are in flight are not merged with the new ones.
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "3".to_string(),
+        },
+        AddLocalRevision {
+            content: "4".to_string(),
+        },
+        AssertNumberOfSyncRevisions { num: 3 },
+        AssertNextSyncRevisionContent {
+            expected: "1".to_string(),
+        },
+    ])
+    .await;
+    sink.ack(1).await;
+    test.run_scripts(vec![AssertNextSyncRevisionContent {
+        expected: "2".to_string(),
+    }])
+    .await;
+    sink.ack(2).await;
+    test.run_scripts(vec![AssertNextSyncRevisionContent {
+        expected: "34".to_string(),
+    }])
+    .await;
+    assert_send(sink.step().await, 3);
+}
+
+#[tokio::test]
+async fn ws_sink_give_up_after_max_push_attempts_test() {
+    let (test, disk_cache) = RevisionTest::new_with_max_push_attempts(100, 3).await;
+    let mut event_rx = test.rev_manager().subscribe_event();
+    for content in ["1", "2", "3", "4"] {
+        test.run_scripts(vec![AddLocalRevision {
+            content: content.to_string(),
+        }])
+        .await;
+    }
+
+    // The server rejects the revision 2 whenever it's sent, and acks the others.
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_send(sink.step().await, 1);
+    sink.ack(1).await;
+    assert_send(sink.step().await, 2);
+    // The sends that fail don't count as attempts.
+    sink.set_offline(true);
+    assert!(sink.try_step().await.is_err());
+    assert!(sink.try_step().await.is_err());
+    sink.set_offline(false);
+    assert_resend(sink.step().await, 2);
+    assert_resend(sink.step().await, 2);
+
+    // The revision 2 was sent 3 times. The revisions 3 and 4 are based on it, they're held back.
+    assert_ping(sink.step().await, 4);
+    assert_eq!(sink.sent_rev_ids(), vec![1, 2, 2, 2, 4]);
+
+    let mut gave_up_rev_ids = vec![];
+    while let Ok(event) = event_rx.try_recv() {
+        if let RevisionManagerEvent::PushGaveUp { rev_id, .. } = event {
+            gave_up_rev_ids.push(rev_id);
+        }
+    }
+    assert_eq!(gave_up_rev_ids, vec![2]);
+
+    // The revision 2 is still saved and unacked, it stays given up on after the reopen.
+    assert_eq!(test.rev_manager().dead_letters().await, vec![2]);
+    let records = test.rev_manager().get_all_revision_records().unwrap();
+    let record = records.iter().find(|record| record.revision.rev_id == 2).unwrap();
+    assert_eq!(record.state, RevisionState::Sync);
+    test.rev_manager().flush().await.unwrap();
+    let test = RevisionTest::reopen(test, disk_cache).await;
+    assert_eq!(test.rev_manager().dead_letters().await, vec![2]);
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_ping(sink.step().await, 4);
+
+    // The retried revision is synced again, followed by the revisions behind it.
+    assert_eq!(test.rev_manager().retry_dead_letters().await, vec![2]);
+    test.run_scripts(vec![AssertNextSyncRevisionId { rev_id: Some(2) }])
+        .await;
+    assert_send(sink.step().await, 2);
+    sink.ack(2).await;
+    assert_send(sink.step().await, 3);
+    sink.ack(3).await;
+    assert_send(sink.step().await, 4);
+    sink.ack(4).await;
+    assert!(test.rev_manager().dead_letters().await.is_empty());
+}
+
+#[tokio::test]
+async fn ws_sink_stop_at_invalid_revision_when_verify_before_push_test() {
+    let test = RevisionTest::new_with_verify_before_push(100).await;
+    let mut event_rx = test.rev_manager().subscribe_event();
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AddInvalidLocalRevision {
+            bytes: InvalidRevisionObject::new().to_bytes(),
+        },
+        AddLocalRevision {
+            content: "3".to_string(),
+        },
+    ])
+    .await;
+
+    let sink = test.ws_sink();
+    assert!(matches!(sink.step().await, RevisionWSSinkStep::Idle));
+    assert_send(sink.step().await, 1);
+    sink.ack(1).await;
+    // The revision 2 doesn't compose onto the revision 1. The revision 3 is based on it, so
+    // neither of them is pushed.
+    assert_ping(sink.step().await, 3);
+    assert_ping(sink.step().await, 3);
+    assert!(!sink.sent_rev_ids().contains(&2));
+    assert!(!sink.sent_rev_ids().contains(&3));
+
+    let mut invalid_rev_ids = vec![];
+    while let Ok(event) = event_rx.try_recv() {
+        if let RevisionManagerEvent::InvalidRevision { rev_id, .. } = event {
+            invalid_rev_ids.push(rev_id);
+        }
+    }
+    assert_eq!(invalid_rev_ids, vec![2]);
+    assert_eq!(test.rev_manager().invalid_rev_id().await, Some(2));
+    assert!(test.rev_manager().dead_letters().await.is_empty());
+}
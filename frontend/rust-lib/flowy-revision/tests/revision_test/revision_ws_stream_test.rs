@@ -0,0 +1,312 @@
+use crate::revision_test::script::{RevisionConnectionMock, RevisionTest};
+use bytes::Bytes;
+use flowy_error::{FlowyError, FlowyResult};
+use flowy_http_model::revision::{Revision, RevisionRange};
+use flowy_http_model::util::md5;
+use flowy_http_model::ws_data::{NewDocumentUser, ServerRevisionWSData, ServerRevisionWSDataBuilder};
+use flowy_revision::{
+    ConflictController, ConflictResolver, ConflictRevisionSink, OperationsDeserializer, OperationsSerializer,
+    RemoteApplyRateLimit, RevisionMD5, RevisionWSDataStream, RevisionWSStream, TransformOperations,
+};
+use lib_infra::future::BoxResultFuture;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::time::{sleep, Instant};
+
+const OBJECT_ID: &str = "object";
+/// The time the document takes to compose one revision.
+const COMPOSE_COST: Duration = Duration::from_micros(100);
+
+#[derive(Default)]
+struct DocumentMock {
+    /// The remote rev_ids and the local edits in the order they were applied.
+    content: Mutex<Vec<String>>,
+    /// The time and the size of each apply of the pushed revisions.
+    applies: std::sync::Mutex<Vec<(Instant, usize)>>,
+}
+
+impl RevisionWSDataStream for DocumentMock {
+    fn receive_push_revision(&self, revisions: Vec<Revision>) -> BoxResultFuture<(), FlowyError> {
+        let content = &self.content;
+        let applies = &self.applies;
+        Box::pin(async move {
+            let mut content = content.lock().await;
+            applies.lock().unwrap().push((Instant::now(), revisions.len()));
+            sleep(COMPOSE_COST * revisions.len() as u32).await;
+            content.extend(revisions.iter().map(|revision| revision.rev_id.to_string()));
+            Ok(())
+        })
+    }
+
+    fn receive_ack(&self, _rev_id: i64) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn receive_new_user_connect(&self, _new_user: NewDocumentUser) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn pull_revisions_in_range(&self, _range: RevisionRange) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn ws_stream_rate_limit_remote_revisions_test() {
+    let rate_limit = RemoteApplyRateLimit::default();
+    let document = Arc::new(DocumentMock::default());
+    let remote_backlog = Arc::new(AtomicUsize::new(0));
+    let (ws_msg_tx, ws_msg_rx) = mpsc::channel(2000);
+    let (stop_tx, stop_rx) = broadcast::channel(1);
+    let stream = RevisionWSStream::new("Object", OBJECT_ID, document.clone(), ws_msg_rx, stop_rx)
+        .with_rate_limit(rate_limit.clone())
+        .with_remote_backlog(remote_backlog.clone());
+    tokio::spawn(stream.run());
+
+    // A collaborator imports a document, each revision is pushed on its own.
+    for rev_id in 1..=2000 {
+        let revision = Revision::new(OBJECT_ID, rev_id - 1, rev_id, Bytes::from(rev_id.to_string()), "");
+        let msg = ServerRevisionWSDataBuilder::build_push_message(OBJECT_ID, vec![revision]);
+        ws_msg_tx.send(msg).await.unwrap();
+    }
+    sleep(Duration::from_millis(1)).await;
+    assert!(remote_backlog.load(Ordering::SeqCst) > 0);
+
+    // The user keeps typing while the pushed revisions are applied.
+    let mut max_latency = Duration::default();
+    let mut num_of_local_edits = 0;
+    while document.applies.lock().unwrap().iter().map(|(_, n)| n).sum::<usize>() < 2000 {
+        let start = Instant::now();
+        document
+            .content
+            .lock()
+            .await
+            .push(format!("local {}", num_of_local_edits));
+        max_latency = max_latency.max(start.elapsed());
+        num_of_local_edits += 1;
+        sleep(Duration::from_millis(5)).await;
+    }
+    let _ = stop_tx.send(());
+
+    // A local edit waits for one apply at most.
+    let max_apply_cost = COMPOSE_COST * rate_limit.max_revisions_per_apply as u32;
+    assert!(max_latency <= max_apply_cost, "{:?}", max_latency);
+    assert!(num_of_local_edits > 50);
+    assert_eq!(remote_backlog.load(Ordering::SeqCst), 0);
+
+    let applies = document.applies.lock().unwrap().clone();
+    assert!(applies.len() <= 2000 / rate_limit.max_revisions_per_apply + 1);
+    assert!(applies.iter().all(|(_, n)| *n <= rate_limit.max_revisions_per_apply));
+    let apply_interval = Duration::from_secs(1) / rate_limit.max_applies_per_second;
+    for pair in applies.windows(2) {
+        assert!(pair[1].0 - pair[0].0 >= apply_interval);
+    }
+
+    // The local edits are interleaved, the pushed revisions keep their order.
+    let content = document.content.lock().await.clone();
+    let remote_rev_ids = content
+        .iter()
+        .filter(|s| !s.starts_with("local"))
+        .map(|s| s.parse::<i64>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(remote_rev_ids, (1..=2000).collect::<Vec<_>>());
+    let first_local_edit = content.iter().position(|s| s.starts_with("local")).unwrap();
+    assert!(first_local_edit < 2000 - rate_limit.max_revisions_per_apply);
+}
+
+#[tokio::test(start_paused = true)]
+async fn ws_stream_apply_remote_revisions_in_order_before_ack_test() {
+    let test = ConflictStreamTest::new().await;
+    let (ws_msg_tx, ws_msg_rx) = mpsc::channel(2000);
+    let (stop_tx, stop_rx) = broadcast::channel(1);
+    let stream = RevisionWSStream::new("Object", &test.object_id, test.stream.clone(), ws_msg_rx, stop_rx)
+        .with_remote_backlog(test.remote_backlog.clone());
+    let handle = tokio::spawn(stream.run());
+
+    for rev_id in 1..=2000 {
+        ws_msg_tx.send(test.push_message(rev_id)).await.unwrap();
+    }
+    // The server acks the local revision after the pushed revisions, the ack waits for them.
+    let ack = ServerRevisionWSDataBuilder::build_ack_message(&test.object_id, 2000);
+    ws_msg_tx.send(ack).await.unwrap();
+    sleep(Duration::from_millis(1)).await;
+    assert!(test.remote_backlog.load(Ordering::SeqCst) > 0);
+    assert!(test.log().iter().all(|entry| !entry.starts_with("ack")));
+
+    while test.remote_backlog.load(Ordering::SeqCst) > 0 {
+        sleep(Duration::from_millis(10)).await;
+    }
+    let _ = stop_tx.send(());
+    handle.await.unwrap();
+    test.assert_applied_in_order_then_acked();
+}
+
+#[tokio::test(start_paused = true)]
+async fn ws_stream_flush_backlog_on_stop_test() {
+    let test = ConflictStreamTest::new().await;
+    let (ws_msg_tx, ws_msg_rx) = mpsc::channel(2000);
+    let (stop_tx, stop_rx) = broadcast::channel(1);
+    let stream = RevisionWSStream::new("Object", &test.object_id, test.stream.clone(), ws_msg_rx, stop_rx)
+        .with_remote_backlog(test.remote_backlog.clone());
+    let handle = tokio::spawn(stream.run());
+
+    for rev_id in 1..=2000 {
+        ws_msg_tx.send(test.push_message(rev_id)).await.unwrap();
+    }
+    let ack = ServerRevisionWSDataBuilder::build_ack_message(&test.object_id, 2000);
+    ws_msg_tx.send(ack).await.unwrap();
+
+    // The stream stops before the backlog is applied, nothing that the server pushed is dropped.
+    let _ = stop_tx.send(());
+    handle.await.unwrap();
+    assert_eq!(test.remote_backlog.load(Ordering::SeqCst), 0);
+    test.assert_applied_in_order_then_acked();
+}
+
+/// Runs the stream on the path of the documents: the pushed revisions are applied by the
+/// conflict controller to the revisions of a `RevisionTest`.
+struct ConflictStreamTest {
+    test: RevisionTest,
+    object_id: String,
+    stream: Arc<ConflictStreamMock>,
+    remote_backlog: Arc<AtomicUsize>,
+    log: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl ConflictStreamTest {
+    async fn new() -> Self {
+        let test = RevisionTest::new().await;
+        let object_id = test.object_id().to_owned();
+        let log = Arc::new(std::sync::Mutex::new(vec![]));
+        let resolver = Arc::new(ResetResolverMock { log: log.clone() });
+        let sink = Arc::new(AckSinkMock { log: log.clone() });
+        let controller = test.conflict_controller(resolver, sink);
+        Self {
+            test,
+            object_id,
+            stream: Arc::new(ConflictStreamMock(Arc::new(controller))),
+            remote_backlog: Arc::new(AtomicUsize::new(0)),
+            log,
+        }
+    }
+
+    fn push_message(&self, rev_id: i64) -> ServerRevisionWSData {
+        let content = rev_id.to_string();
+        let revision = Revision::new(
+            &self.object_id,
+            rev_id - 1,
+            rev_id,
+            Bytes::from(content.clone()),
+            md5(&content),
+        );
+        ServerRevisionWSDataBuilder::build_push_message(&self.object_id, vec![revision])
+    }
+
+    fn log(&self) -> Vec<String> {
+        self.log.lock().unwrap().clone()
+    }
+
+    fn assert_applied_in_order_then_acked(&self) {
+        let log = self.log();
+        assert_eq!(log.last().unwrap(), "ack 2000");
+        let applied = log[..log.len() - 1]
+            .iter()
+            .map(|entry| entry.parse::<i64>().unwrap())
+            .collect::<Vec<_>>();
+        assert!(applied.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", applied);
+        assert_eq!(applied.last(), Some(&2000));
+        assert_eq!(self.test.rev_id(), 2000);
+    }
+}
+
+struct ConflictStreamMock(Arc<ConflictController<RevIdOperations, RevisionConnectionMock>>);
+
+impl RevisionWSDataStream for ConflictStreamMock {
+    fn receive_push_revision(&self, revisions: Vec<Revision>) -> BoxResultFuture<(), FlowyError> {
+        let controller = self.0.clone();
+        Box::pin(async move { controller.receive_revisions(revisions).await })
+    }
+
+    fn receive_ack(&self, rev_id: i64) -> BoxResultFuture<(), FlowyError> {
+        let controller = self.0.clone();
+        Box::pin(async move { controller.ack_revision(rev_id).await })
+    }
+
+    fn receive_new_user_connect(&self, _new_user: NewDocumentUser) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn pull_revisions_in_range(&self, range: RevisionRange) -> BoxResultFuture<(), FlowyError> {
+        let controller = self.0.clone();
+        Box::pin(async move { controller.send_revisions(range).await })
+    }
+}
+
+/// The rev_id of the last revision, the content of the pushed revision is its rev_id.
+#[derive(Clone)]
+struct RevIdOperations(String);
+
+impl OperationsDeserializer<RevIdOperations> for RevIdOperations {
+    fn deserialize_revisions(revisions: Vec<Revision>) -> FlowyResult<RevIdOperations> {
+        let content = revisions
+            .last()
+            .map(|revision| String::from_utf8(revision.bytes.clone()).unwrap())
+            .unwrap_or_default();
+        Ok(RevIdOperations(content))
+    }
+}
+
+impl OperationsSerializer for RevIdOperations {
+    fn serialize_operations(&self) -> Bytes {
+        Bytes::from(self.0.clone())
+    }
+}
+
+/// Replaces the document with the pushed revisions, the object has no local revisions.
+struct ResetResolverMock {
+    log: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl ConflictResolver<RevIdOperations> for ResetResolverMock {
+    fn compose_operations(&self, operations: RevIdOperations) -> BoxResultFuture<RevisionMD5, FlowyError> {
+        Box::pin(async move { RevisionMD5::from_bytes(operations.0) })
+    }
+
+    fn transform_operations(
+        &self,
+        operations: RevIdOperations,
+    ) -> BoxResultFuture<TransformOperations<RevIdOperations>, FlowyError> {
+        Box::pin(async move {
+            Ok(TransformOperations {
+                client_operations: operations,
+                server_operations: None,
+            })
+        })
+    }
+
+    fn reset_operations(&self, operations: RevIdOperations) -> BoxResultFuture<RevisionMD5, FlowyError> {
+        let log = self.log.clone();
+        Box::pin(async move {
+            sleep(COMPOSE_COST).await;
+            log.lock().unwrap().push(operations.0.clone());
+            RevisionMD5::from_bytes(operations.0)
+        })
+    }
+}
+
+struct AckSinkMock {
+    log: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl ConflictRevisionSink for AckSinkMock {
+    fn send(&self, _revisions: Vec<Revision>) -> BoxResultFuture<(), FlowyError> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn ack(&self, rev_id: i64) -> BoxResultFuture<(), FlowyError> {
+        self.log.lock().unwrap().push(format!("ack {}", rev_id));
+        Box::pin(async { Ok(()) })
+    }
+}
@@ -0,0 +1,203 @@
+use crate::revision_test::script::{RevisionScript::*, RevisionTest};
+use flowy_revision::{PowerState, SaveDebounceConfiguration, SaveScheduler};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+#[tokio::test(start_paused = true)]
+async fn save_debounce_sustained_typing_test() {
+    let test = RevisionTest::new_with_save_debounce(SaveDebounceConfiguration::default()).await;
+    // Typing every 300ms never pauses for 600ms, the revisions are saved when the 1s window
+    // of the first one ends.
+    for content in ["1", "2", "3", "4"] {
+        test.run_script(AddLocalRevision {
+            content: content.to_string(),
+        })
+        .await;
+        sleep(Duration::from_millis(300)).await;
+    }
+    test.run_script(AssertNumberOfRevisionsInDisk { num: 4 }).await;
+
+    for content in ["5", "6", "7", "8"] {
+        test.run_script(AddLocalRevision {
+            content: content.to_string(),
+        })
+        .await;
+        sleep(Duration::from_millis(300)).await;
+    }
+    test.run_script(AssertNumberOfRevisionsInDisk { num: 8 }).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn save_debounce_typing_pause_test() {
+    let test = RevisionTest::new_with_save_debounce(SaveDebounceConfiguration::default()).await;
+    test.run_scripts(vec![
+        AddLocalRevision {
+            content: "1".to_string(),
+        },
+        AddLocalRevision {
+            content: "2".to_string(),
+        },
+    ])
+    .await;
+    sleep(Duration::from_millis(500)).await;
+    test.run_script(AssertNumberOfRevisionsInDisk { num: 0 }).await;
+
+    sleep(Duration::from_millis(200)).await;
+    test.run_script(AssertNumberOfRevisionsInDisk { num: 2 }).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn save_debounce_on_battery_test() {
+    let save_debounce = SaveDebounceConfiguration::default();
+    save_debounce.set_power_state(PowerState::OnBattery);
+    let test = RevisionTest::new_with_save_debounce(save_debounce).await;
+    // The window is 2s on battery.
+    for content in ["1", "2", "3", "4", "5", "6", "7"] {
+        test.run_script(AddLocalRevision {
+            content: content.to_string(),
+        })
+        .await;
+        sleep(Duration::from_millis(300)).await;
+    }
+    // 2.1s after the first revision.
+    test.run_script(AssertNumberOfRevisionsInDisk { num: 7 }).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn save_debounce_on_battery_window_not_ended_test() {
+    let save_debounce = SaveDebounceConfiguration::default();
+    save_debounce.set_power_state(PowerState::OnBattery);
+    let test = RevisionTest::new_with_save_debounce(save_debounce).await;
+    for content in ["1", "2", "3", "4", "5", "6"] {
+        test.run_script(AddLocalRevision {
+            content: content.to_string(),
+        })
+        .await;
+        sleep(Duration::from_millis(300)).await;
+    }
+    // 1.8s after the first revision, it would have been saved if plugged in.
+    test.run_script(AssertNumberOfRevisionsInDisk { num: 0 }).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn save_debounce_exceed_unsaved_bytes_test() {
+    let save_debounce = SaveDebounceConfiguration {
+        max_unsaved_bytes: 1,
+        ..Default::default()
+    };
+    let test = RevisionTest::new_with_save_debounce(save_debounce).await;
+    test.run_script(AddLocalRevision {
+        content: "1".to_string(),
+    })
+    .await;
+    sleep(Duration::from_millis(1)).await;
+    test.run_script(AssertNumberOfRevisionsInDisk { num: 1 }).await;
+}
+
+#[test]
+fn save_scheduler_continuous_typing_test() {
+    for power_state in [PowerState::Unknown, PowerState::OnBattery, PowerState::PluggedIn] {
+        let configuration = SaveDebounceConfiguration::default();
+        configuration.set_power_state(power_state);
+        // Types a character every 100ms for 10s.
+        let changes = (0..100).map(|i| (i * 100, 10)).collect::<Vec<_>>();
+        let saves = simulate(&configuration, &changes);
+        assert!(saves.len() >= 5, "{:?}: {:?}", power_state, saves);
+        assert_saves_within(&configuration, &saves);
+    }
+}
+
+#[test]
+fn save_scheduler_typing_bursts_test() {
+    let configuration = SaveDebounceConfiguration::default();
+    // Three bursts of 5 characters separated by 1s pauses.
+    let changes = (0..15).map(|i| ((i / 5) * 1500 + (i % 5) * 100, 1)).collect::<Vec<_>>();
+    let saves = simulate(&configuration, &changes);
+    // Each burst is saved 600ms after its last character.
+    let save_times = saves.iter().map(|save| save.at).collect::<Vec<_>>();
+    assert_eq!(save_times, vec![1000, 2500, 4000]);
+    assert_saves_within(&configuration, &saves);
+}
+
+#[test]
+fn save_scheduler_large_change_test() {
+    let configuration = SaveDebounceConfiguration {
+        max_unsaved_bytes: 100,
+        ..Default::default()
+    };
+    // Pastes a large text in the middle of typing.
+    let changes = vec![(0, 10), (100, 10), (200, 500), (300, 10)];
+    let saves = simulate(&configuration, &changes);
+    assert_eq!(saves[0].at, 200);
+    assert_eq!(saves[0].num_of_bytes, 520);
+    assert_saves_within(&configuration, &saves);
+}
+
+#[test]
+fn save_scheduler_window_clamped_test() {
+    let configuration = SaveDebounceConfiguration {
+        plugged_in_window: Duration::from_secs(5),
+        max_window: Duration::from_secs(2),
+        ..Default::default()
+    };
+    configuration.set_power_state(PowerState::PluggedIn);
+    assert_eq!(configuration.window(), Duration::from_secs(2));
+
+    let changes = (0..50).map(|i| (i * 100, 1)).collect::<Vec<_>>();
+    assert_saves_within(&configuration, &simulate(&configuration, &changes));
+}
+
+#[derive(Debug)]
+struct Save {
+    /// Milliseconds since the start.
+    at: u64,
+    /// The time the oldest saved change waited.
+    waited: Duration,
+    num_of_bytes: usize,
+}
+
+/// Replays the `changes`, each is the milliseconds since the start and the number of bytes,
+/// and returns the saves the scheduler made.
+fn simulate(configuration: &SaveDebounceConfiguration, changes: &[(u64, usize)]) -> Vec<Save> {
+    let start = Instant::now();
+    let mut scheduler = SaveScheduler::new(configuration.clone());
+    let mut first_unsaved_at: Option<Instant> = None;
+    let mut saves = vec![];
+    let mut save = |scheduler: &mut SaveScheduler, first_unsaved_at: &mut Option<Instant>, at: Instant| {
+        saves.push(Save {
+            at: (at - start).as_millis() as u64,
+            waited: at - first_unsaved_at.take().unwrap(),
+            num_of_bytes: scheduler.unsaved_bytes(),
+        });
+        scheduler.did_save();
+    };
+
+    for (millis, num_of_bytes) in changes {
+        let now = start + Duration::from_millis(*millis);
+        if let Some(save_at) = scheduler.next_save() {
+            if save_at <= now {
+                save(&mut scheduler, &mut first_unsaved_at, save_at);
+            }
+        }
+        first_unsaved_at.get_or_insert(now);
+        let save_at = scheduler.record_change(now, *num_of_bytes);
+        if save_at <= now {
+            save(&mut scheduler, &mut first_unsaved_at, now);
+        }
+    }
+    if let Some(save_at) = scheduler.next_save() {
+        save(&mut scheduler, &mut first_unsaved_at, save_at);
+    }
+    saves
+}
+
+fn assert_saves_within(configuration: &SaveDebounceConfiguration, saves: &[Save]) {
+    for save in saves {
+        assert!(
+            save.waited <= configuration.max_window,
+            "The save at {}ms waited {:?}",
+            save.at,
+            save.waited
+        );
+    }
+}
@@ -1,28 +1,55 @@
 use bytes::Bytes;
 use flowy_error::{internal_error, FlowyError, FlowyResult};
 use flowy_revision::{
-    RevisionManager, RevisionMergeable, RevisionObjectDeserializer, RevisionPersistence,
-    RevisionPersistenceConfiguration, RevisionSnapshot, RevisionSnapshotDiskCache, REVISION_WRITE_INTERVAL_IN_MILLIS,
+    CompactionEstimate, ComposeStats, ConflictController, ConflictResolver, ConflictRevisionSink, ErrorReporter,
+    Executor, PriorityScheduler, RevisionCacheState, RevisionClock, RevisionCloudService, RevisionManager,
+    RevisionManagerEvent, RevisionMergeable, RevisionMirror, RevisionObjectDeserializer, RevisionPersistence,
+    RevisionPersistenceConfiguration, RevisionSnapshot, RevisionSnapshotDiskCache, RevisionWSSink, RevisionWSSinkStep,
+    RevisionWebSocket, RevisionWebSocketSink, SaveDebounceConfiguration, WSDataProvider, WSSession, WSStateReceiver,
+    REVISION_WRITE_INTERVAL_IN_MILLIS,
 };
-use flowy_revision_persistence::{RevisionChangeset, RevisionDiskCache, SyncRecord};
+use flowy_revision_persistence::{RevisionChangeset, RevisionDiskCache, RevisionState, SyncRecord};
 
 use flowy_http_model::revision::{Revision, RevisionRange};
 use flowy_http_model::util::md5;
+use flowy_http_model::ws_data::ClientRevisionWSData;
+use futures::future::BoxFuture;
+use lib_infra::future::{BoxResultFuture, FutureResult};
+use lib_infra::util::timestamp;
 use nanoid::nanoid;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::broadcast;
 
 pub enum RevisionScript {
     AddLocalRevision { content: String },
     AddLocalRevision2 { content: String },
     AddInvalidLocalRevision { bytes: Vec<u8> },
     AckRevision { rev_id: i64 },
+    AckRevisionAndPersist { rev_id: i64 },
+    AckRevisionWithCanonicalId { rev_id: i64, canonical: i64 },
+    AssertCanonicalRevisionId { rev_id: i64, canonical: i64 },
+    AssertRevision { rev_id: i64, expected: (i64, String) },
+    AssertRevisionIdsInRange { range: RevisionRange, ids: Vec<i64> },
+    AssertRevisionBaseIds { ids: Vec<(i64, i64)> },
     AssertNextSyncRevisionId { rev_id: Option<i64> },
     AssertNumberOfSyncRevisions { num: usize },
     AssertNumberOfRevisionsInDisk { num: usize },
+    AssertRevisionCount { num: u64 },
     AssertNextSyncRevisionContent { expected: String },
+    AssertObjectContent { expected: String },
+    ResolveConflict { content: String, superseded: Vec<i64> },
+    AssertRevisionState { rev_id: i64, state: RevisionState },
+    AssertCompactionEstimate { expected: CompactionEstimate },
+    PinRevision { rev_id: i64 },
+    UnpinRevision { rev_id: i64 },
+    AssertPinnedRevisionIds { rev_ids: Vec<i64> },
+    AssertObjectContentAtRevision { rev_id: i64, expected: String },
+    Close,
     WaitWhenWriteToDisk,
 }
 
@@ -33,52 +60,336 @@ pub struct RevisionTest {
     rev_manager: Arc<RevisionManager<RevisionConnectionMock>>,
 }
 
+/// Opens the object of a `RevisionTest`. Without any option, it's a new empty object whose
+/// revisions merge every 100 revisions.
+pub struct RevisionTestBuilder {
+    user_id: String,
+    object_id: String,
+    configuration: RevisionPersistenceConfiguration,
+    contents: Vec<(String, Option<i64>)>,
+    disk_cache: Option<Arc<RevisionDiskCacheMock>>,
+    snapshot: Option<RevisionSnapshot>,
+    cloud: Option<Arc<dyn RevisionCloudService>>,
+    state: Option<RevisionCacheState>,
+    compose_threshold: Option<Duration>,
+}
+
+/// The object opened by `RevisionTestBuilder::open`.
+pub struct OpenedRevisionTest {
+    pub test: RevisionTest,
+    pub disk_cache: Arc<RevisionDiskCacheMock>,
+    pub object: RevisionObjectMock,
+    /// Receives the events that were sent while opening the object.
+    pub event_rx: broadcast::Receiver<RevisionManagerEvent>,
+}
+
+impl RevisionTestBuilder {
+    fn new() -> Self {
+        Self {
+            user_id: nanoid!(10),
+            object_id: nanoid!(6),
+            configuration: RevisionPersistenceConfiguration::new(100, false),
+            contents: vec![],
+            disk_cache: None,
+            snapshot: None,
+            cloud: None,
+            state: None,
+            compose_threshold: None,
+        }
+    }
+
+    pub fn configuration(mut self, configuration: RevisionPersistenceConfiguration) -> Self {
+        self.configuration = configuration;
+        self
+    }
+
+    /// Changes the configuration, e.g. `|configuration| configuration.with_read_cache(8)`.
+    pub fn configure<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(RevisionPersistenceConfiguration) -> RevisionPersistenceConfiguration,
+    {
+        self.configuration = f(self.configuration);
+        self
+    }
+
+    /// The object consists of the `contents`, each content is saved as one acked revision.
+    pub fn contents(mut self, contents: Vec<&str>) -> Self {
+        self.contents = contents.into_iter().map(|content| (content.to_owned(), None)).collect();
+        self
+    }
+
+    /// Same as `contents`, but each revision was written at the time of its content.
+    pub fn timestamped_contents(mut self, contents: Vec<(&str, i64)>) -> Self {
+        self.contents = contents
+            .into_iter()
+            .map(|(content, create_time)| (content.to_owned(), Some(create_time)))
+            .collect();
+        self
+    }
+
+    /// Opens the object from the `disk_cache` instead of the `contents`.
+    pub fn disk_cache(mut self, disk_cache: Arc<RevisionDiskCacheMock>) -> Self {
+        self.disk_cache = Some(disk_cache);
+        self
+    }
+
+    /// Opens the object of the `old_test` with the same configuration.
+    pub fn same_object(mut self, old_test: &RevisionTest) -> Self {
+        self.user_id = old_test.user_id.clone();
+        self.object_id = old_test.object_id.clone();
+        self.configuration = old_test.configuration.clone();
+        self
+    }
+
+    pub fn snapshot(mut self, snapshot: RevisionSnapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    pub fn cloud(mut self, cloud: Arc<dyn RevisionCloudService>) -> Self {
+        self.cloud = Some(cloud);
+        self
+    }
+
+    /// Builds the object from the `state` instead of its revisions.
+    pub fn state(mut self, state: RevisionCacheState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn compose_threshold(mut self, compose_threshold: Duration) -> Self {
+        self.compose_threshold = Some(compose_threshold);
+        self
+    }
+
+    /// Returns the test and its disk cache.
+    pub async fn build(self) -> (RevisionTest, Arc<RevisionDiskCacheMock>) {
+        let opened = self.open().await;
+        (opened.test, opened.disk_cache)
+    }
+
+    pub async fn open(self) -> OpenedRevisionTest {
+        let disk_cache = match self.disk_cache {
+            Some(disk_cache) => disk_cache,
+            None => {
+                let (contents, timestamps): (Vec<String>, Vec<Option<i64>>) = self.contents.into_iter().unzip();
+                let contents = contents.iter().map(|content| content.as_str()).collect();
+                let disk_cache = Arc::new(RevisionDiskCacheMock::new(acked_records(&self.object_id, contents)));
+                for (index, create_time) in timestamps.into_iter().enumerate() {
+                    if let Some(create_time) = create_time {
+                        disk_cache.set_revision_timestamp(index as i64 + 1, create_time);
+                    }
+                }
+                disk_cache
+            }
+        };
+        let persistence = RevisionPersistence::new(
+            &self.user_id,
+            &self.object_id,
+            disk_cache.clone(),
+            self.configuration.clone(),
+        );
+        let compress = RevisionMergeableMock {};
+        let snapshot = RevisionSnapshotMock::new(self.snapshot);
+        let mut rev_manager = RevisionManager::new(&self.user_id, &self.object_id, persistence, compress, snapshot);
+        if let Some(compose_threshold) = self.compose_threshold {
+            rev_manager = rev_manager.with_compose_threshold(compose_threshold);
+        }
+        let event_rx = rev_manager.subscribe_event();
+        let object = match self.state {
+            None => rev_manager
+                .initialize::<RevisionObjectMockSerde>(self.cloud)
+                .await
+                .unwrap(),
+            Some(state) => rev_manager
+                .initialize_from_state::<RevisionObjectMockSerde>(state)
+                .await
+                .unwrap(),
+        };
+        let test = RevisionTest {
+            user_id: self.user_id,
+            object_id: self.object_id,
+            configuration: self.configuration,
+            rev_manager: Arc::new(rev_manager),
+        };
+        OpenedRevisionTest {
+            test,
+            disk_cache,
+            object,
+            event_rx,
+        }
+    }
+}
+
 impl RevisionTest {
     pub async fn new() -> Self {
         Self::new_with_configuration(2).await
     }
 
     pub async fn new_with_configuration(merge_threshold: i64) -> Self {
-        let user_id = nanoid!(10);
-        let object_id = nanoid!(6);
         let configuration = RevisionPersistenceConfiguration::new(merge_threshold as usize, false);
-        let disk_cache = RevisionDiskCacheMock::new(vec![]);
-        let persistence = RevisionPersistence::new(&user_id, &object_id, disk_cache, configuration.clone());
-        let compress = RevisionMergeableMock {};
-        let snapshot = RevisionSnapshotMock {};
-        let mut rev_manager = RevisionManager::new(&user_id, &object_id, persistence, compress, snapshot);
-        rev_manager.initialize::<RevisionObjectMockSerde>(None).await.unwrap();
-        Self {
-            user_id,
-            object_id,
-            configuration,
-            rev_manager: Arc::new(rev_manager),
-        }
+        Self::new_with(configuration).await
+    }
+
+    /// The revisions of the object never sync, only the last `history_limit` revisions are kept.
+    pub async fn new_with_local_only(history_limit: usize) -> Self {
+        let configuration = RevisionPersistenceConfiguration::default().with_local_only(history_limit);
+        Self::new_with(configuration).await
+    }
+
+    /// Each revision that is written to disk is mirrored to the `mirror`.
+    pub async fn new_with_mirror(mirror: Arc<RevisionMirrorMock>) -> Self {
+        let configuration = RevisionPersistenceConfiguration::new(100, false).with_mirror(mirror);
+        Self::new_with(configuration).await
+    }
+
+    /// The background tasks of the object run with the `executor`.
+    pub async fn new_with_executor(executor: Executor) -> Self {
+        let configuration = RevisionPersistenceConfiguration::new(2, false).with_executor(executor);
+        Self::new_with(configuration).await
+    }
+
+    /// The revisions are written to disk according to the `save_debounce`.
+    pub async fn new_with_save_debounce(save_debounce: SaveDebounceConfiguration) -> Self {
+        let configuration = RevisionPersistenceConfiguration::new(100, false).with_save_debounce(save_debounce);
+        Self::new_with(configuration).await
+    }
+
+    /// The revisions are read from and written to disk with the `priority_scheduler`.
+    pub async fn new_with_priority_scheduler(priority_scheduler: PriorityScheduler) -> Self {
+        let configuration =
+            RevisionPersistenceConfiguration::new(100, false).with_priority_scheduler(priority_scheduler);
+        Self::new_with(configuration).await
+    }
+
+    /// Up to `push_window` revisions are pushed to the server without being acked.
+    pub async fn new_with_push_window(merge_threshold: usize, push_window: usize) -> Self {
+        let configuration = RevisionPersistenceConfiguration::new(merge_threshold, false).with_push_window(push_window);
+        Self::new_with(configuration).await
+    }
+
+    /// Returns the test and its disk cache, see `reopen`.
+    pub async fn new_with_max_push_attempts(
+        merge_threshold: usize,
+        max_push_attempts: usize,
+    ) -> (Self, Arc<RevisionDiskCacheMock>) {
+        RevisionTest::builder()
+            .configuration(
+                RevisionPersistenceConfiguration::new(merge_threshold, false).with_max_push_attempts(max_push_attempts),
+            )
+            .build()
+            .await
+    }
+
+    /// Reopens the object of the `old_test` from its `disk_cache`.
+    pub async fn reopen(old_test: RevisionTest, disk_cache: Arc<RevisionDiskCacheMock>) -> Self {
+        RevisionTest::builder()
+            .same_object(&old_test)
+            .disk_cache(disk_cache)
+            .build()
+            .await
+            .0
+    }
+
+    /// Each revision is verified against its base before it's pushed.
+    pub async fn new_with_verify_before_push(merge_threshold: usize) -> Self {
+        let configuration = RevisionPersistenceConfiguration::new(merge_threshold, false).with_verify_before_push();
+        Self::new_with(configuration).await
+    }
+
+    async fn new_with(configuration: RevisionPersistenceConfiguration) -> Self {
+        RevisionTest::builder().configuration(configuration).build().await.0
     }
 
     pub async fn new_with_other(old_test: RevisionTest) -> Self {
         let records = old_test.rev_manager.get_all_revision_records().unwrap();
-        let disk_cache = RevisionDiskCacheMock::new(records);
-        let configuration = old_test.configuration;
-        let persistence = RevisionPersistence::new(
-            &old_test.user_id,
-            &old_test.object_id,
-            disk_cache,
-            configuration.clone(),
-        );
+        Self::new_with_other_records(old_test, records).await
+    }
 
-        let compress = RevisionMergeableMock {};
-        let snapshot = RevisionSnapshotMock {};
-        let mut rev_manager =
-            RevisionManager::new(&old_test.user_id, &old_test.object_id, persistence, compress, snapshot);
-        rev_manager.initialize::<RevisionObjectMockSerde>(None).await.unwrap();
-        Self {
-            user_id: old_test.user_id,
-            object_id: old_test.object_id,
-            configuration,
-            rev_manager: Arc::new(rev_manager),
+    /// Reopens the object of the `old_test` from the `records`, e.g. the records of the
+    /// `old_test` read back from disk in another order.
+    pub async fn new_with_other_records(old_test: RevisionTest, records: Vec<SyncRecord>) -> Self {
+        RevisionTest::builder()
+            .same_object(&old_test)
+            .disk_cache(Arc::new(RevisionDiskCacheMock::new(records)))
+            .build()
+            .await
+            .0
+    }
+
+    /// Returns the builder that opens the object of the test with the options, see
+    /// `RevisionTestBuilder`.
+    pub fn builder() -> RevisionTestBuilder {
+        RevisionTestBuilder::new()
+    }
+
+    pub fn rev_manager(&self) -> &Arc<RevisionManager<RevisionConnectionMock>> {
+        &self.rev_manager
+    }
+
+    pub fn last_compose_stats(&self) -> Option<ComposeStats> {
+        self.rev_manager.last_compose_stats()
+    }
+
+    pub fn rev_id(&self) -> i64 {
+        self.rev_manager.rev_id()
+    }
+
+    pub fn revision_backup(&self) -> Vec<Revision> {
+        self.rev_manager.read_revision_backup().unwrap()
+    }
+
+    pub fn demoted_snapshots(&self) -> Vec<(String, RevisionSnapshot)> {
+        self.rev_manager.read_demoted_snapshots().unwrap()
+    }
+
+    pub fn object_id(&self) -> &str {
+        &self.object_id
+    }
+
+    /// Returns a controller that applies the revisions pushed to this test's object.
+    pub fn conflict_controller<Operations>(
+        &self,
+        resolver: Arc<dyn ConflictResolver<Operations> + Send + Sync>,
+        rev_sink: Arc<dyn ConflictRevisionSink>,
+    ) -> ConflictController<Operations, RevisionConnectionMock>
+    where
+        Operations: Clone + Send + Sync,
+    {
+        ConflictController::new(&self.user_id, resolver, rev_sink, self.rev_manager.clone())
+    }
+
+    /// Returns a sink that sends the revisions of this test's object. The sink doesn't run
+    /// by itself, each `step` of the returned test sends at most one data.
+    pub fn ws_sink(&self) -> RevisionWSSinkTest {
+        self.make_ws_sink(WSDataProvider::new(&self.object_id, Arc::new(self.rev_manager.clone())))
+    }
+
+    /// Same as `ws_sink` but the sink sends the handshake of the `session` first.
+    pub fn ws_sink_with_session(&self, session: WSSession) -> RevisionWSSinkTest {
+        let provider = WSDataProvider::new(&self.object_id, Arc::new(self.rev_manager.clone())).with_session(session);
+        self.make_ws_sink(provider)
+    }
+
+    fn make_ws_sink(&self, provider: WSDataProvider) -> RevisionWSSinkTest {
+        let provider = Arc::new(provider);
+        let web_socket = Arc::new(RevisionWebSocketMock::default());
+        let (stop_tx, _) = broadcast::channel(1);
+        let sink = RevisionWSSink::new(
+            &self.object_id,
+            "Mock",
+            Arc::new(WSDataSinkMock(provider.clone())),
+            web_socket.clone(),
+            stop_tx.subscribe(),
+            Duration::from_millis(REVISION_WRITE_INTERVAL_IN_MILLIS),
+        );
+        RevisionWSSinkTest {
+            sink,
+            provider,
+            web_socket,
         }
     }
+
     pub async fn run_scripts(&self, scripts: Vec<RevisionScript>) {
         for script in scripts {
             self.run_script(script).await;
@@ -116,6 +427,33 @@ impl RevisionTest {
                 //
                 self.rev_manager.ack_revision(rev_id).await.unwrap()
             }
+            RevisionScript::AckRevisionAndPersist { rev_id } => self.rev_manager.ack_and_persist(rev_id).await.unwrap(),
+            RevisionScript::AckRevisionWithCanonicalId { rev_id, canonical } => self
+                .rev_manager
+                .ack_revision_with_canonical_id(rev_id, canonical)
+                .await
+                .unwrap(),
+            RevisionScript::AssertCanonicalRevisionId { rev_id, canonical } => {
+                assert_eq!(self.rev_manager.canonical_rev_id(rev_id), canonical)
+            }
+            RevisionScript::AssertRevision { rev_id, expected } => {
+                let revision = self.rev_manager.get_revision(rev_id).await.unwrap();
+                let object = RevisionObjectMock::from_bytes(&revision.bytes).unwrap();
+                assert_eq!((revision.rev_id, object.content), expected);
+            }
+            RevisionScript::AssertRevisionIdsInRange { range, ids } => {
+                let revisions = self.rev_manager.get_revisions_in_range(range).await.unwrap();
+                let rev_ids = revisions.iter().map(|revision| revision.rev_id).collect::<Vec<i64>>();
+                assert_eq!(rev_ids, ids);
+            }
+            RevisionScript::AssertRevisionBaseIds { ids } => {
+                let revisions = self.rev_manager.load_revisions().await.unwrap();
+                let base_ids = revisions
+                    .iter()
+                    .map(|revision| (revision.rev_id, revision.base_rev_id))
+                    .collect::<Vec<(i64, i64)>>();
+                assert_eq!(base_ids, ids);
+            }
             RevisionScript::AssertNextSyncRevisionId { rev_id } => {
                 assert_eq!(self.rev_manager.next_sync_rev_id().await, rev_id)
             }
@@ -125,6 +463,9 @@ impl RevisionTest {
             RevisionScript::AssertNumberOfRevisionsInDisk { num } => {
                 assert_eq!(self.rev_manager.number_of_revisions_in_disk(), num)
             }
+            RevisionScript::AssertRevisionCount { num } => {
+                assert_eq!(self.rev_manager.revision_count().await.unwrap(), num)
+            }
             RevisionScript::AssertNextSyncRevisionContent { expected } => {
                 //
                 let rev_id = self.rev_manager.next_sync_rev_id().await.unwrap();
@@ -132,6 +473,41 @@ impl RevisionTest {
                 let object = RevisionObjectMock::from_bytes(&revision.bytes).unwrap();
                 assert_eq!(object.content, expected);
             }
+            RevisionScript::AssertObjectContent { expected } => {
+                let revisions = self.rev_manager.load_revisions().await.unwrap();
+                let object = RevisionObjectMockSerde::deserialize_revisions(&self.object_id, revisions).unwrap();
+                assert_eq!(object.content, expected);
+            }
+            RevisionScript::ResolveConflict { content, superseded } => {
+                let bytes = RevisionObjectMock::new(&content).to_bytes();
+                let md5 = md5(&bytes);
+                self.rev_manager
+                    .resolve_conflict(Bytes::from(bytes), md5, &superseded)
+                    .await
+                    .unwrap();
+            }
+            RevisionScript::AssertRevisionState { rev_id, state } => {
+                let records = self.rev_manager.get_all_revision_records().unwrap();
+                let record = records.iter().find(|record| record.revision.rev_id == rev_id).unwrap();
+                assert_eq!(record.state, state);
+            }
+            RevisionScript::AssertCompactionEstimate { expected } => {
+                assert_eq!(self.rev_manager.compaction_estimate().unwrap(), expected);
+            }
+            RevisionScript::PinRevision { rev_id } => self.rev_manager.pin_revision(rev_id).await.unwrap(),
+            RevisionScript::UnpinRevision { rev_id } => self.rev_manager.unpin_revision(rev_id).await.unwrap(),
+            RevisionScript::AssertPinnedRevisionIds { rev_ids } => {
+                assert_eq!(self.rev_manager.pinned_rev_ids().unwrap(), rev_ids);
+            }
+            RevisionScript::AssertObjectContentAtRevision { rev_id, expected } => {
+                let mut revisions = self.rev_manager.load_revisions().await.unwrap();
+                revisions.retain(|revision| revision.rev_id <= rev_id);
+                let object = RevisionObjectMockSerde::deserialize_revisions(&self.object_id, revisions).unwrap();
+                assert_eq!(object.content, expected);
+            }
+            RevisionScript::Close => {
+                self.rev_manager.close().await;
+            }
             RevisionScript::WaitWhenWriteToDisk => {
                 let milliseconds = 2 * REVISION_WRITE_INTERVAL_IN_MILLIS;
                 tokio::time::sleep(Duration::from_millis(milliseconds)).await;
@@ -140,14 +516,193 @@ impl RevisionTest {
     }
 }
 
+pub struct RevisionWSSinkTest {
+    sink: RevisionWSSink,
+    provider: Arc<WSDataProvider>,
+    web_socket: Arc<RevisionWebSocketMock>,
+}
+
+impl RevisionWSSinkTest {
+    pub async fn step(&self) -> RevisionWSSinkStep {
+        self.sink.step().await.unwrap()
+    }
+
+    /// Same as `step` but returns the error of sending the data.
+    pub async fn try_step(&self) -> FlowyResult<RevisionWSSinkStep> {
+        self.sink.step().await
+    }
+
+    /// The data fails to send while the web socket is offline.
+    pub fn set_offline(&self, is_offline: bool) {
+        self.web_socket.is_offline.store(is_offline, Ordering::SeqCst);
+    }
+
+    /// Acks the data like the server acks it through the web socket.
+    pub async fn ack(&self, rev_id: i64) {
+        self.provider.ack_data(rev_id).await.unwrap();
+    }
+
+    /// Pushes the data that should be sent before the local revisions, e.g. the revisions
+    /// that were requested by the server.
+    pub async fn push_data(&self, data: ClientRevisionWSData) {
+        self.provider.push_data(data).await;
+    }
+
+    /// Returns the rev_id of each data that was received by the server.
+    pub fn sent_rev_ids(&self) -> Vec<i64> {
+        self.web_socket.sent.read().iter().map(|data| data.rev_id).collect()
+    }
+
+    pub fn sent(&self) -> Vec<ClientRevisionWSData> {
+        self.web_socket.sent.read().clone()
+    }
+
+    /// The web socket of the sink, the data sent through it is returned by `sent`.
+    pub fn web_socket(&self) -> Arc<dyn RevisionWebSocket> {
+        self.web_socket.clone()
+    }
+}
+
+struct WSDataSinkMock(Arc<WSDataProvider>);
+impl RevisionWebSocketSink for WSDataSinkMock {
+    fn next(&self) -> FutureResult<Option<ClientRevisionWSData>, FlowyError> {
+        let provider = self.0.clone();
+        FutureResult::new(async move { provider.next().await })
+    }
+
+    fn did_send(&self, data: &ClientRevisionWSData) -> FutureResult<(), FlowyError> {
+        let provider = self.0.clone();
+        let data = data.clone();
+        FutureResult::new(async move { provider.did_send(&data).await })
+    }
+
+    fn did_fail_to_send(&self, data: &ClientRevisionWSData, error: &FlowyError) {
+        self.0.did_fail_to_send(data, error);
+    }
+
+    fn session(&self) -> Option<WSSession> {
+        self.0.session()
+    }
+}
+
+#[derive(Default)]
+struct RevisionWebSocketMock {
+    sent: RwLock<Vec<ClientRevisionWSData>>,
+    is_offline: AtomicBool,
+}
+
+impl RevisionWebSocket for RevisionWebSocketMock {
+    fn send(&self, data: ClientRevisionWSData) -> BoxResultFuture<(), FlowyError> {
+        if self.is_offline.load(Ordering::SeqCst) {
+            return Box::pin(async { Err(FlowyError::internal().context("The web socket is offline")) });
+        }
+        self.sent.write().push(data);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn subscribe_state_changed(&self) -> BoxFuture<WSStateReceiver> {
+        let (tx, _) = broadcast::channel(1);
+        Box::pin(async move { tx.subscribe() })
+    }
+}
+
+/// Each content is saved as one acked revision, the rev_id starts from 1.
+fn acked_records(object_id: &str, contents: Vec<&str>) -> Vec<SyncRecord> {
+    contents
+        .into_iter()
+        .enumerate()
+        .map(|(index, content)| {
+            let bytes = Bytes::from(RevisionObjectMock::new(content).to_bytes());
+            let md5 = md5(&bytes);
+            let rev_id = index as i64 + 1;
+            let mut record = SyncRecord::new(Revision::new(object_id, rev_id - 1, rev_id, bytes, md5));
+            record.ack();
+            record
+        })
+        .collect::<Vec<SyncRecord>>()
+}
+
+/// The time in seconds that only changes when it's advanced.
+pub struct RevisionClockMock(AtomicI64);
+
+impl RevisionClockMock {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self(AtomicI64::new(timestamp())))
+    }
+
+    pub fn advance(&self, secs: i64) {
+        self.0.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl RevisionClock for RevisionClockMock {
+    fn now(&self) -> i64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
 pub struct RevisionDiskCacheMock {
     records: RwLock<Vec<SyncRecord>>,
+    pinned_rev_ids: RwLock<Vec<i64>>,
+    dead_letters: RwLock<Vec<i64>>,
+    rev_id_map: RwLock<Vec<(i64, i64)>>,
+    revision_backup: RwLock<Vec<Revision>>,
+    /// The time that each revision was written at, the initial records have none.
+    timestamps: RwLock<HashMap<i64, i64>>,
+    /// The time that each revision expires at.
+    expiries: RwLock<HashMap<i64, i64>>,
+    /// The number of the reads of the specific revisions.
+    num_of_reads: AtomicUsize,
+    /// Fails the writes of the new records while it's true.
+    fail_writes: AtomicBool,
+    /// Panics on the next update of the records' states, like a poisoned lock would.
+    panic_on_update: AtomicBool,
+    /// The number of the writes that were asked to reach the disk, i.e. to fsync.
+    num_of_durable_writes: AtomicUsize,
 }
 
 impl RevisionDiskCacheMock {
     pub fn new(records: Vec<SyncRecord>) -> Self {
         Self {
             records: RwLock::new(records),
+            pinned_rev_ids: RwLock::new(vec![]),
+            dead_letters: RwLock::new(vec![]),
+            rev_id_map: RwLock::new(vec![]),
+            revision_backup: RwLock::new(vec![]),
+            timestamps: RwLock::new(HashMap::new()),
+            expiries: RwLock::new(HashMap::new()),
+            num_of_reads: AtomicUsize::new(0),
+            fail_writes: AtomicBool::new(false),
+            panic_on_update: AtomicBool::new(false),
+            num_of_durable_writes: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn set_fail_writes(&self, fail_writes: bool) {
+        self.fail_writes.store(fail_writes, Ordering::SeqCst);
+    }
+
+    pub fn panic_on_next_update(&self) {
+        self.panic_on_update.store(true, Ordering::SeqCst);
+    }
+
+    pub fn num_of_reads(&self) -> usize {
+        self.num_of_reads.load(Ordering::SeqCst)
+    }
+
+    pub fn num_of_durable_writes(&self) -> usize {
+        self.num_of_durable_writes.load(Ordering::SeqCst)
+    }
+
+    pub fn set_revision_timestamp(&self, rev_id: i64, create_time: i64) {
+        self.timestamps.write().insert(rev_id, create_time);
+    }
+
+    fn write_timestamps(&self, records: &[SyncRecord]) {
+        let create_time = timestamp();
+        let mut timestamps = self.timestamps.write();
+        for record in records {
+            timestamps.entry(record.revision.rev_id).or_insert(create_time);
         }
     }
 }
@@ -156,10 +711,20 @@ impl RevisionDiskCache<RevisionConnectionMock> for RevisionDiskCacheMock {
     type Error = FlowyError;
 
     fn create_revision_records(&self, revision_records: Vec<SyncRecord>) -> Result<(), Self::Error> {
+        if self.fail_writes.load(Ordering::SeqCst) {
+            return Err(FlowyError::internal().context("The disk is full"));
+        }
+        self.write_timestamps(&revision_records);
         self.records.write().extend(revision_records);
         Ok(())
     }
 
+    fn create_durable_revision_records(&self, revision_records: Vec<SyncRecord>) -> Result<(), Self::Error> {
+        self.create_revision_records(revision_records)?;
+        self.num_of_durable_writes.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
     fn get_connection(&self) -> Result<RevisionConnectionMock, Self::Error> {
         todo!()
     }
@@ -171,13 +736,16 @@ impl RevisionDiskCache<RevisionConnectionMock> for RevisionDiskCacheMock {
     ) -> Result<Vec<SyncRecord>, Self::Error> {
         match rev_ids {
             None => Ok(self.records.read().clone()),
-            Some(rev_ids) => Ok(self
-                .records
-                .read()
-                .iter()
-                .filter(|record| rev_ids.contains(&record.revision.rev_id))
-                .cloned()
-                .collect::<Vec<SyncRecord>>()),
+            Some(rev_ids) => {
+                self.num_of_reads.fetch_add(1, Ordering::SeqCst);
+                Ok(self
+                    .records
+                    .read()
+                    .iter()
+                    .filter(|record| rev_ids.contains(&record.revision.rev_id))
+                    .cloned()
+                    .collect::<Vec<SyncRecord>>())
+            }
         }
     }
 
@@ -186,6 +754,7 @@ impl RevisionDiskCache<RevisionConnectionMock> for RevisionDiskCacheMock {
         _object_id: &str,
         range: &RevisionRange,
     ) -> Result<Vec<SyncRecord>, Self::Error> {
+        self.num_of_reads.fetch_add(1, Ordering::SeqCst);
         let read_guard = self.records.read();
         let records = range
             .iter()
@@ -200,6 +769,9 @@ impl RevisionDiskCache<RevisionConnectionMock> for RevisionDiskCacheMock {
     }
 
     fn update_revision_record(&self, changesets: Vec<RevisionChangeset>) -> FlowyResult<()> {
+        if self.panic_on_update.swap(false, Ordering::SeqCst) {
+            panic!("The lock of the disk cache is poisoned");
+        }
         for changeset in changesets {
             if let Some(record) = self
                 .records
@@ -225,6 +797,8 @@ impl RevisionDiskCache<RevisionConnectionMock> for RevisionDiskCacheMock {
                         .position(|record| record.revision.rev_id == rev_id)
                     {
                         self.records.write().remove(index);
+                        self.timestamps.write().remove(&rev_id);
+                        self.expiries.write().remove(&rev_id);
                     }
                 }
             }
@@ -232,29 +806,255 @@ impl RevisionDiskCache<RevisionConnectionMock> for RevisionDiskCacheMock {
         Ok(())
     }
 
+    fn delete_revs(&self, _object_id: &str, rev_ids: &[i64]) -> Result<usize, Self::Error> {
+        let mut records = self.records.write();
+        let len = records.len();
+        records.retain(|record| !rev_ids.contains(&record.revision.rev_id));
+        self.timestamps.write().retain(|rev_id, _| !rev_ids.contains(rev_id));
+        Ok(len - records.len())
+    }
+
     fn delete_and_insert_records(
         &self,
         _object_id: &str,
-        _deleted_rev_ids: Option<Vec<i64>>,
-        _inserted_records: Vec<SyncRecord>,
+        deleted_rev_ids: Option<Vec<i64>>,
+        inserted_records: Vec<SyncRecord>,
     ) -> Result<(), Self::Error> {
-        todo!()
+        let mut records = self.records.write();
+        let mut newest_create_time = None;
+        match deleted_rev_ids {
+            None => {
+                records.clear();
+                self.timestamps.write().clear();
+                self.expiries.write().clear();
+            }
+            Some(rev_ids) => {
+                records.retain(|record| !rev_ids.contains(&record.revision.rev_id));
+                self.timestamps.write().retain(|rev_id, create_time| {
+                    if rev_ids.contains(rev_id) {
+                        newest_create_time = newest_create_time.max(Some(*create_time));
+                        return false;
+                    }
+                    true
+                });
+                self.expiries.write().retain(|rev_id, _| !rev_ids.contains(rev_id));
+            }
+        }
+        // Same as the sqlite disk cache, the inserted revisions keep the time of the newest
+        // deleted one.
+        if let Some(create_time) = newest_create_time {
+            for record in inserted_records.iter() {
+                self.set_revision_timestamp(record.revision.rev_id, create_time);
+            }
+        }
+        self.write_timestamps(&inserted_records);
+        records.extend(inserted_records);
+        records.sort_by_key(|record| record.revision.rev_id);
+        Ok(())
+    }
+
+    fn pin_revision(&self, _object_id: &str, rev_id: i64) -> FlowyResult<()> {
+        let mut pinned_rev_ids = self.pinned_rev_ids.write();
+        if !pinned_rev_ids.contains(&rev_id) {
+            pinned_rev_ids.push(rev_id);
+            pinned_rev_ids.sort_unstable();
+        }
+        Ok(())
+    }
+
+    fn unpin_revision(&self, _object_id: &str, rev_id: i64) -> FlowyResult<()> {
+        self.pinned_rev_ids
+            .write()
+            .retain(|pinned_rev_id| *pinned_rev_id != rev_id);
+        Ok(())
+    }
+
+    fn read_pinned_rev_ids(&self, _object_id: &str) -> FlowyResult<Vec<i64>> {
+        Ok(self.pinned_rev_ids.read().clone())
+    }
+
+    fn read_revision_timestamps(&self, _object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        let mut timestamps = self
+            .timestamps
+            .read()
+            .iter()
+            .map(|(rev_id, create_time)| (*rev_id, *create_time))
+            .collect::<Vec<(i64, i64)>>();
+        timestamps.sort_unstable();
+        Ok(timestamps)
+    }
+
+    fn set_revision_expiry(&self, _object_id: &str, rev_ids: &[i64], expires_at: Option<i64>) -> FlowyResult<()> {
+        let mut expiries = self.expiries.write();
+        for rev_id in rev_ids {
+            match expires_at {
+                None => expiries.remove(rev_id),
+                Some(expires_at) => expiries.insert(*rev_id, expires_at),
+            };
+        }
+        Ok(())
+    }
+
+    fn read_revision_expiries(&self, _object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        let mut expiries = self
+            .expiries
+            .read()
+            .iter()
+            .map(|(rev_id, expires_at)| (*rev_id, *expires_at))
+            .collect::<Vec<(i64, i64)>>();
+        expiries.sort_unstable();
+        Ok(expiries)
+    }
+
+    fn write_dead_letters(&self, _object_id: &str, rev_ids: &[i64]) -> FlowyResult<()> {
+        let mut dead_letters = rev_ids.to_vec();
+        dead_letters.sort_unstable();
+        *self.dead_letters.write() = dead_letters;
+        Ok(())
+    }
+
+    fn read_dead_letters(&self, _object_id: &str) -> FlowyResult<Vec<i64>> {
+        Ok(self.dead_letters.read().clone())
+    }
+
+    fn write_rev_id_map(&self, _object_id: &str, rev_id_map: &[(i64, i64)]) -> FlowyResult<()> {
+        *self.rev_id_map.write() = rev_id_map.to_vec();
+        Ok(())
+    }
+
+    fn read_rev_id_map(&self, _object_id: &str) -> FlowyResult<Vec<(i64, i64)>> {
+        Ok(self.rev_id_map.read().clone())
+    }
+
+    fn write_revision_backup(&self, _object_id: &str, revisions: &[Revision]) -> FlowyResult<()> {
+        *self.revision_backup.write() = revisions.to_vec();
+        Ok(())
+    }
+
+    fn read_revision_backup(&self, _object_id: &str) -> FlowyResult<Vec<Revision>> {
+        Ok(self.revision_backup.read().clone())
+    }
+}
+
+/// Records the rev_id of the mirrored revisions. The mirror fails every time if `fail` is true.
+#[derive(Default)]
+pub struct RevisionMirrorMock {
+    fail: bool,
+    rev_ids: RwLock<Vec<i64>>,
+}
+
+impl RevisionMirrorMock {
+    pub fn new_failing() -> Self {
+        Self {
+            fail: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn mirrored_rev_ids(&self) -> Vec<i64> {
+        self.rev_ids.read().clone()
+    }
+}
+
+impl RevisionMirror for RevisionMirrorMock {
+    fn mirror(&self, revision: &Revision) -> FlowyResult<()> {
+        self.rev_ids.write().push(revision.rev_id);
+        if self.fail {
+            return Err(FlowyError::internal().context("The mirror is unavailable"));
+        }
+        Ok(())
+    }
+}
+
+/// Records the context and the message of each reported error.
+#[derive(Default)]
+pub struct ErrorReporterMock {
+    reports: RwLock<Vec<(String, String)>>,
+}
+
+impl ErrorReporterMock {
+    pub fn reports(&self) -> Vec<(String, String)> {
+        self.reports.read().clone()
+    }
+}
+
+impl ErrorReporter for ErrorReporterMock {
+    fn report(&self, context: &str, error: &FlowyError) {
+        self.reports.write().push((context.to_owned(), error.msg.clone()));
     }
 }
 
 pub struct RevisionConnectionMock {}
-pub struct RevisionSnapshotMock {}
+
+/// Keeps the last snapshot that was written, and the snapshots that were demoted.
+pub struct RevisionSnapshotMock {
+    snapshot: RwLock<Option<RevisionSnapshot>>,
+    demoted: RwLock<Vec<(String, RevisionSnapshot)>>,
+}
+
+impl RevisionSnapshotMock {
+    pub fn new(snapshot: Option<RevisionSnapshot>) -> Self {
+        Self {
+            snapshot: RwLock::new(snapshot),
+            demoted: RwLock::new(vec![]),
+        }
+    }
+}
+
 impl RevisionSnapshotDiskCache for RevisionSnapshotMock {
-    fn write_snapshot(&self, _rev_id: i64, _data: Vec<u8>) -> FlowyResult<()> {
-        todo!()
+    fn write_snapshot(&self, rev_id: i64, data: Vec<u8>) -> FlowyResult<()> {
+        *self.snapshot.write() = Some(RevisionSnapshot {
+            rev_id,
+            base_rev_id: rev_id,
+            timestamp: timestamp(),
+            data: Bytes::from(data),
+        });
+        Ok(())
     }
 
-    fn read_snapshot(&self, _rev_id: i64) -> FlowyResult<Option<RevisionSnapshot>> {
-        todo!()
+    fn read_snapshot(&self, rev_id: i64) -> FlowyResult<Option<RevisionSnapshot>> {
+        Ok(self
+            .snapshot
+            .read()
+            .clone()
+            .filter(|snapshot| snapshot.rev_id == rev_id))
     }
 
     fn read_last_snapshot(&self) -> FlowyResult<Option<RevisionSnapshot>> {
-        Ok(None)
+        Ok(self.snapshot.read().clone())
+    }
+
+    fn demote_snapshot(&self, snapshot: &RevisionSnapshot, name: &str) -> FlowyResult<()> {
+        let mut last_snapshot = self.snapshot.write();
+        if last_snapshot.as_ref() == Some(snapshot) {
+            *last_snapshot = None;
+        }
+        self.demoted.write().push((name.to_owned(), snapshot.clone()));
+        Ok(())
+    }
+
+    fn read_demoted_snapshots(&self) -> FlowyResult<Vec<(String, RevisionSnapshot)>> {
+        Ok(self.demoted.read().clone())
+    }
+}
+
+/// `server_rev_id` is the rev_id of the object's latest revision on the server, `revisions`
+/// are the revisions of the object on the server.
+#[derive(Default)]
+pub struct RevisionCloudServiceMock {
+    pub server_rev_id: Option<i64>,
+    pub revisions: Vec<Revision>,
+}
+
+impl RevisionCloudService for RevisionCloudServiceMock {
+    fn fetch_object(&self, _user_id: &str, _object_id: &str) -> FutureResult<Vec<Revision>, FlowyError> {
+        let revisions = self.revisions.clone();
+        FutureResult::new(async move { Ok(revisions) })
+    }
+
+    fn fetch_object_rev_id(&self, _user_id: &str, _object_id: &str) -> FutureResult<Option<i64>, FlowyError> {
+        let server_rev_id = self.server_rev_id;
+        FutureResult::new(async move { Ok(server_rev_id) })
     }
 }
 
@@ -270,6 +1070,11 @@ impl RevisionMergeable for RevisionMergeableMock {
         }
         Ok(Bytes::from(object.to_bytes()))
     }
+
+    fn verify_revision(&self, base_revisions: Vec<Revision>, revision: &Revision) -> FlowyResult<()> {
+        let mut object = RevisionObjectMock::from_bytes(&self.combine_revisions(base_revisions)?)?;
+        object.compose(RevisionObjectMock::from_bytes(&revision.bytes)?)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -300,6 +1105,10 @@ impl RevisionObjectMock {
         Self { content: s.to_owned() }
     }
 
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
     pub fn compose(&mut self, other: RevisionObjectMock) -> FlowyResult<()> {
         self.content.push_str(other.content.as_str());
         Ok(())
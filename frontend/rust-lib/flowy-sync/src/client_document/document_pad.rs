@@ -1,13 +1,16 @@
 use crate::{
     client_document::{
         history::{History, UndoResult},
+        resolve_inline_format,
         view::{ViewExtensions, RECORD_THRESHOLD},
     },
     errors::CollaborateError,
 };
 use bytes::Bytes;
 use flowy_http_model::util::md5;
-use lib_ot::text_delta::DeltaTextOperationBuilder;
+use lib_ot::text_delta::{
+    BuildInTextAttribute, BuildInTextAttributeKey, CustomAttributes, DeltaTextOperationBuilder, MAX_LIST_INDENT,
+};
 use lib_ot::{core::*, text_delta::DeltaTextOperations};
 use tokio::sync::mpsc;
 
@@ -39,6 +42,22 @@ pub struct ClientDocument {
     view: ViewExtensions,
     last_edit_time: usize,
     notify: Option<mpsc::UnboundedSender<()>>,
+    redact_logs: bool,
+    custom_attributes: CustomAttributes,
+}
+
+/// The state of the [ClientDocument] that [ClientDocument::restore] brings it back to.
+pub struct DocumentCheckpoint {
+    operations: DeltaTextOperations,
+    history: History,
+    last_edit_time: usize,
+}
+
+impl DocumentCheckpoint {
+    /// The operations of the document when the checkpoint was taken.
+    pub fn operations(&self) -> &DeltaTextOperations {
+        &self.operations
+    }
 }
 
 impl ClientDocument {
@@ -54,6 +73,8 @@ impl ClientDocument {
             view: ViewExtensions::new(),
             last_edit_time: 0,
             notify: None,
+            redact_logs: false,
+            custom_attributes: CustomAttributes::default(),
         }
     }
 
@@ -87,8 +108,57 @@ impl ClientDocument {
         self.notify = Some(notify);
     }
 
+    /// Mask the inserted text of the operations written to the logs. See [DeltaOperations::redacted].
+    pub fn set_redact_logs(&mut self, redact_logs: bool) {
+        self.redact_logs = redact_logs;
+    }
+
+    /// Formats the custom attributes of the `custom_attributes` like the built-in inline ones and
+    /// checks their values. The invalid values that the document already has are dropped.
+    pub fn set_custom_attributes(&mut self, custom_attributes: CustomAttributes) {
+        self.operations = custom_attributes.sanitize_operations(&self.operations);
+        self.custom_attributes = custom_attributes;
+    }
+
+    pub fn custom_attributes(&self) -> &CustomAttributes {
+        &self.custom_attributes
+    }
+
+    fn log_str(&self, operations: &DeltaTextOperations) -> String {
+        if self.redact_logs {
+            operations.redacted().json_str()
+        } else {
+            operations.json_str()
+        }
+    }
+
+    pub fn checkpoint(&self) -> DocumentCheckpoint {
+        DocumentCheckpoint {
+            operations: self.operations.clone(),
+            history: self.history.clone(),
+            last_edit_time: self.last_edit_time,
+        }
+    }
+
+    /// Discards the changes made after the `checkpoint`, including their undo history.
+    pub fn restore(&mut self, checkpoint: DocumentCheckpoint) {
+        self.history = checkpoint.history;
+        self.last_edit_time = checkpoint.last_edit_time;
+        self.set_operations(checkpoint.operations);
+    }
+
+    /// Returns the `base_mismatch` error if the `operations` of a client weren't made against
+    /// the current document, so the client can rebase them.
+    pub fn validate_base(&self, operations: &DeltaTextOperations) -> Result<(), CollaborateError> {
+        let expected = self.operations.utf16_target_len;
+        if operations.utf16_base_len != expected {
+            return Err(CollaborateError::base_mismatch(expected, operations.utf16_base_len));
+        }
+        Ok(())
+    }
+
     pub fn set_operations(&mut self, operations: DeltaTextOperations) {
-        tracing::trace!("document: {}", operations.json_str());
+        tracing::trace!("document: {}", self.log_str(&operations));
         self.operations = operations;
 
         match &self.notify {
@@ -100,16 +170,27 @@ impl ClientDocument {
     }
 
     pub fn compose_operations(&mut self, operations: DeltaTextOperations) -> Result<(), CollaborateError> {
-        tracing::trace!("{} compose {}", &self.operations.json_str(), operations.json_str());
+        tracing::trace!(
+            "{} compose {}",
+            self.log_str(&self.operations),
+            self.log_str(&operations)
+        );
         let composed_operations = self.operations.compose(&operations)?;
         let mut undo_operations = operations.invert(&self.operations);
 
         let now = chrono::Utc::now().timestamp_millis() as usize;
-        if now - self.last_edit_time < RECORD_THRESHOLD {
+        if self.history.is_in_group() {
+            // All the changes of the group are undone at once. The inverse of the group is the
+            // inverse of this change followed by the inverse of the previous changes.
+            if let Some(group_operations) = self.history.group_undo() {
+                *group_operations = undo_operations.compose(group_operations)?;
+                undo_operations = DeltaTextOperations::default();
+            }
+        } else if now - self.last_edit_time < RECORD_THRESHOLD {
             if let Some(last_operation) = self.history.undo() {
                 tracing::trace!("compose previous change");
-                tracing::trace!("current = {}", undo_operations);
-                tracing::trace!("previous = {}", last_operation);
+                tracing::trace!("current = {}", self.log_str(&undo_operations));
+                tracing::trace!("previous = {}", self.log_str(&last_operation));
                 undo_operations = undo_operations.compose(&last_operation)?;
             }
         } else {
@@ -117,7 +198,7 @@ impl ClientDocument {
         }
 
         if !undo_operations.is_empty() {
-            tracing::trace!("add history operations: {}", undo_operations);
+            tracing::trace!("add history operations: {}", self.log_str(&undo_operations));
             self.history.record(undo_operations);
         }
 
@@ -125,6 +206,46 @@ impl ClientDocument {
         Ok(())
     }
 
+    /// Composes the operations received from the server. The remote changes are not undoable, so
+    /// the undo entry of the current group is transformed to apply after them. The invalid values
+    /// of the custom attributes are dropped, the returned operations are the ones composed.
+    pub fn compose_remote_operations(
+        &mut self,
+        operations: DeltaTextOperations,
+    ) -> Result<DeltaTextOperations, CollaborateError> {
+        let operations = self.custom_attributes.sanitize_operations(&operations);
+        if !self.history.is_in_group() {
+            self.compose_operations(operations.clone())?;
+            return Ok(operations);
+        }
+
+        let composed_operations = self.operations.compose(&operations)?;
+        if let Some(group_operations) = self.history.group_undo() {
+            // The transform requires both operations to cover the whole document.
+            let len = self.operations.utf16_target_len;
+            let remote_operations = retain_to_len(operations.clone(), len);
+            let (_, transformed_operations) =
+                remote_operations.transform(&retain_to_len(group_operations.clone(), len))?;
+            *group_operations = transformed_operations;
+        }
+        self.set_operations(composed_operations);
+        Ok(operations)
+    }
+
+    /// Collects the changes made until [ClientDocument::end_undo_group] into one undo entry,
+    /// e.g. the changes of a programmatic operation that consists of several steps.
+    pub fn begin_undo_group(&mut self) {
+        self.history.begin_group();
+    }
+
+    pub fn end_undo_group(&mut self) {
+        self.history.end_group();
+        if !self.history.is_in_group() {
+            // The next change starts a new undo entry instead of joining the group.
+            self.last_edit_time = 0;
+        }
+    }
+
     pub fn insert<T: ToString>(&mut self, index: usize, data: T) -> Result<DeltaTextOperations, CollaborateError> {
         let text = data.to_string();
         let interval = Interval::new(index, index);
@@ -134,6 +255,21 @@ impl ClientDocument {
         Ok(operations)
     }
 
+    /// Inserts the `operations` at `index`, e.g. the saved fragment of another document. Unlike
+    /// `insert`, the inserts keep their attributes and the extensions don't apply.
+    pub fn insert_operations(
+        &mut self,
+        index: usize,
+        operations: DeltaTextOperations,
+    ) -> Result<DeltaTextOperations, CollaborateError> {
+        validate_interval(&self.operations, &Interval::new(index, index))?;
+        let mut inserted = DeltaTextOperations::default();
+        inserted.retain(index, AttributeHashMap::default());
+        inserted.extend(operations);
+        self.compose_operations(inserted.clone())?;
+        Ok(inserted)
+    }
+
     pub fn delete(&mut self, interval: Interval) -> Result<DeltaTextOperations, CollaborateError> {
         validate_interval(&self.operations, &interval)?;
         debug_assert!(!interval.is_empty());
@@ -144,6 +280,64 @@ impl ClientDocument {
         Ok(operations)
     }
 
+    /// Deletes the first `len` of the document. The `len` that lands in the middle of a character
+    /// is moved back to the start of the character. The trailing newline is never deleted, so
+    /// trimming past the end leaves the empty document.
+    pub fn trim_leading(&mut self, len: usize) -> Result<DeltaTextOperations, CollaborateError> {
+        let len = len.min(self.operations.utf16_target_len.saturating_sub(1));
+        let content = self.operations.content()?;
+        let mut boundary = 0;
+        for c in content.chars() {
+            if boundary + c.len_utf16() > len {
+                break;
+            }
+            boundary += c.len_utf16();
+        }
+        let operations = DeltaTextOperations::delete_prefix(boundary, self.operations.utf16_target_len);
+        self.compose_operations(operations.clone())?;
+        Ok(operations)
+    }
+
+    /// Removes the text from `index` to the end of the document. Returns the operations that
+    /// removed it and the removed text with its attributes, which ends with the newline of the
+    /// document. The `index` that lands in the middle of a character is moved back to the
+    /// start of the character.
+    ///
+    /// The document keeps its trailing newline unless the text before `index` already ends
+    /// with a newline, so splitting at the start of a line doesn't leave an empty line behind.
+    pub fn split_off(&mut self, index: usize) -> Result<(DeltaTextOperations, DeltaTextOperations), CollaborateError> {
+        let len = self.operations.utf16_target_len;
+        if len == 0 || index >= len {
+            return Err(CollaborateError::out_of_bound().context(format!("Can't split at {} of {}", index, len)));
+        }
+        let content = self.operations.content()?;
+        let mut boundary = 0;
+        let mut prev_char = None;
+        for c in content.chars() {
+            if boundary + c.len_utf16() > index {
+                break;
+            }
+            boundary += c.len_utf16();
+            prev_char = Some(c);
+        }
+
+        let tail = self.operations.slice(Interval::new(boundary, len));
+        let num_of_deleted = match prev_char {
+            Some('\n') => len - boundary,
+            _ => len - boundary - 1,
+        };
+        if num_of_deleted == 0 {
+            return Ok((DeltaTextOperations::default(), tail));
+        }
+        let operations = DeltaTextOperationBuilder::new()
+            .retain(boundary)
+            .delete(num_of_deleted)
+            .retain(len - boundary - num_of_deleted)
+            .build();
+        self.compose_operations(operations.clone())?;
+        Ok((operations, tail))
+    }
+
     pub fn format(
         &mut self,
         interval: Interval,
@@ -151,7 +345,15 @@ impl ClientDocument {
     ) -> Result<DeltaTextOperations, CollaborateError> {
         validate_interval(&self.operations, &interval)?;
         tracing::trace!("format {} with {:?}", interval, attribute);
-        let operations = self.view.format(&self.operations, attribute, interval).unwrap();
+        let attribute = self.custom_attributes.sanitize(attribute)?;
+        let operations = match self.custom_attributes.semantics(&attribute.key) {
+            None => self.view.format(&self.operations, attribute, interval)?,
+            Some(_) => {
+                let mut operations = resolve_inline_format(&self.operations, interval, &attribute);
+                trim(&mut operations);
+                operations
+            }
+        };
         self.compose_operations(operations.clone())?;
         Ok(operations)
     }
@@ -177,6 +379,17 @@ impl ClientDocument {
         Ok(operations)
     }
 
+    /// Increases the indent of each list line in the interval by one level. The lines without the
+    /// list attribute are left untouched.
+    pub fn indent(&mut self, interval: Interval) -> Result<DeltaTextOperations, CollaborateError> {
+        self.shift_list_indent(interval, 1)
+    }
+
+    /// Decreases the indent of each list line in the interval by one level.
+    pub fn outdent(&mut self, interval: Interval) -> Result<DeltaTextOperations, CollaborateError> {
+        self.shift_list_indent(interval, -1)
+    }
+
     pub fn can_undo(&self) -> bool {
         self.history.can_undo()
     }
@@ -220,6 +433,49 @@ impl ClientDocument {
 }
 
 impl ClientDocument {
+    fn shift_list_indent(&mut self, interval: Interval, offset: i64) -> Result<DeltaTextOperations, CollaborateError> {
+        validate_interval(&self.operations, &interval)?;
+        let mut builder = DeltaTextOperationBuilder::new();
+        let mut retained = 0;
+        let mut line_start = 0;
+        for (newline_index, attributes) in newline_attributes(&self.operations) {
+            if line_start > interval.end {
+                break;
+            }
+
+            let is_list = attributes
+                .get(BuildInTextAttributeKey::List.as_ref())
+                .map(|value| value.value.is_some())
+                .unwrap_or(false);
+
+            if newline_index >= interval.start && is_list {
+                // Each line is shifted relative to its own depth.
+                let indent = attributes
+                    .get(BuildInTextAttributeKey::Indent.as_ref())
+                    .and_then(|value| value.int_value())
+                    .unwrap_or(0);
+                let new_indent = (indent + offset).clamp(0, MAX_LIST_INDENT as i64);
+                if new_indent != indent {
+                    let entry = match new_indent {
+                        0 => AttributeEntry::new(BuildInTextAttributeKey::Indent.as_ref(), AttributeValue::none()),
+                        _ => BuildInTextAttribute::Indent(new_indent as usize),
+                    };
+                    builder = builder
+                        .retain(newline_index - retained)
+                        .retain_with_attributes(1, entry.into());
+                    retained = newline_index + 1;
+                }
+            }
+            line_start = newline_index + 1;
+        }
+
+        let operations = builder.build();
+        if !operations.is_empty() {
+            self.compose_operations(operations.clone())?;
+        }
+        Ok(operations)
+    }
+
     fn invert(
         &self,
         operations: &DeltaTextOperations,
@@ -233,6 +489,23 @@ impl ClientDocument {
     }
 }
 
+/// Returns the utf16 index and the attributes of each newline in the document.
+fn newline_attributes(operations: &DeltaTextOperations) -> Vec<(usize, AttributeHashMap)> {
+    let mut index = 0;
+    let mut newlines = vec![];
+    for operation in operations.ops.iter() {
+        if let DeltaOperation::Insert(insert) = operation {
+            for c in insert.s.chars() {
+                if c == '\n' {
+                    newlines.push((index, insert.attributes.clone()));
+                }
+                index += c.len_utf16();
+            }
+        }
+    }
+    newlines
+}
+
 fn validate_interval(operations: &DeltaTextOperations, interval: &Interval) -> Result<(), CollaborateError> {
     if operations.utf16_target_len < interval.end {
         log::error!(
@@ -244,3 +517,11 @@ fn validate_interval(operations: &DeltaTextOperations, interval: &Interval) -> R
     }
     Ok(())
 }
+
+/// Appends a retain to the operations whose base length is shorter than `len`.
+fn retain_to_len(mut operations: DeltaTextOperations, len: usize) -> DeltaTextOperations {
+    if operations.utf16_base_len < len {
+        operations.retain(len - operations.utf16_base_len, AttributeHashMap::default());
+    }
+    operations
+}
@@ -25,24 +25,34 @@ impl FormatExt for ResolveInlineFormat {
         if !is_inline(&attribute.key) {
             return None;
         }
-        let mut new_delta = DeltaOperationBuilder::new().retain(interval.start).build();
-        let mut iter = OperationIterator::from_offset(delta, interval.start);
-        let mut start = 0;
-        let end = interval.size();
+        Some(resolve_inline_format(delta, interval, attribute))
+    }
+}
 
-        while start < end && iter.has_next() {
-            let next_op = iter.next_op_with_len(end - start).unwrap();
-            match find_newline(next_op.get_data()) {
-                None => new_delta.retain(next_op.len(), attribute.clone().into()),
-                Some(_) => {
-                    let tmp_delta = line_break(&next_op, attribute, AttributeScope::Inline);
-                    new_delta.extend(tmp_delta);
-                }
-            }
+/// Applies the `attribute` to the text of the `interval` and leaves its newlines as they are.
+/// The custom attributes are applied with it too, see `ClientDocument::set_custom_attributes`.
+pub fn resolve_inline_format(
+    delta: &DeltaTextOperations,
+    interval: Interval,
+    attribute: &AttributeEntry,
+) -> DeltaTextOperations {
+    let mut new_delta = DeltaOperationBuilder::new().retain(interval.start).build();
+    let mut iter = OperationIterator::from_offset(delta, interval.start);
+    let mut start = 0;
+    let end = interval.size();
 
-            start += next_op.len();
+    while start < end && iter.has_next() {
+        let next_op = iter.next_op_with_len(end - start).unwrap();
+        match find_newline(next_op.get_data()) {
+            None => new_delta.retain(next_op.len(), attribute.clone().into()),
+            Some(_) => {
+                let tmp_delta = line_break(&next_op, attribute, AttributeScope::Inline);
+                new_delta.extend(tmp_delta);
+            }
         }
 
-        Some(new_delta)
+        start += next_op.len();
     }
+
+    new_delta
 }
@@ -0,0 +1,31 @@
+use lib_ot::core::{AttributeValue, DeltaOperation, ValueType};
+use lib_ot::text_delta::DeltaTextOperations;
+use std::collections::HashMap;
+
+/// Counts, per attribute key, the length of the text the attribute is applied to. The length
+/// is in utf16 code units, the same as the intervals of the document.
+///
+/// The `operations` should be the composed document that consists of insert operations. The
+/// attributes that don't apply any format, e.g. `"bold":false` left by removing the bold, are
+/// not counted.
+pub fn attribute_histogram(operations: &DeltaTextOperations) -> HashMap<String, usize> {
+    let mut histogram: HashMap<String, usize> = HashMap::new();
+    for op in operations.ops.iter() {
+        if let DeltaOperation::Insert(insert) = op {
+            for (key, value) in insert.attributes.iter() {
+                if is_applied(value) {
+                    *histogram.entry(key.clone()).or_insert(0) += op.len();
+                }
+            }
+        }
+    }
+    histogram
+}
+
+fn is_applied(value: &AttributeValue) -> bool {
+    match value.ty {
+        None => false,
+        Some(ValueType::BoolType) => value.bool_value().unwrap_or(false),
+        Some(_) => value.value.is_some(),
+    }
+}
@@ -14,6 +14,8 @@ pub struct History {
     undoes: Vec<DeltaTextOperations>,
     redoes: Vec<DeltaTextOperations>,
     capacity: usize,
+    group_depth: usize,
+    group_has_undo: bool,
 }
 
 impl std::default::Default for History {
@@ -23,6 +25,8 @@ impl std::default::Default for History {
             undoes: Vec::new(),
             redoes: Vec::new(),
             capacity: MAX_UNDOES,
+            group_depth: 0,
+            group_has_undo: false,
         }
     }
 }
@@ -55,17 +59,49 @@ impl History {
 
         self.redoes.clear();
         self.add_undo(delta);
+        if self.is_in_group() {
+            self.group_has_undo = true;
+        }
 
         if self.undoes.len() > self.capacity {
             self.undoes.remove(0);
         }
     }
 
+    /// Starts collecting the following changes into one undo entry. The groups can be nested,
+    /// the entry is closed when the outermost group ends.
+    pub fn begin_group(&mut self) {
+        if self.group_depth == 0 {
+            self.group_has_undo = false;
+        }
+        self.group_depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+    }
+
+    pub fn is_in_group(&self) -> bool {
+        self.group_depth > 0
+    }
+
+    /// Returns the undo entry of the current group if the group has recorded any change.
+    pub fn group_undo(&mut self) -> Option<&mut DeltaTextOperations> {
+        if self.is_in_group() && self.group_has_undo {
+            self.undoes.last_mut()
+        } else {
+            None
+        }
+    }
+
     pub fn undo(&mut self) -> Option<DeltaTextOperations> {
         if !self.can_undo() {
             return None;
         }
         let delta = self.undoes.pop().unwrap();
+        // The entry of the group may be the one popped, the next change of the group starts a
+        // new entry instead of joining an older one.
+        self.group_has_undo = false;
         Some(delta)
     }
 
@@ -75,6 +111,7 @@ impl History {
         }
 
         let delta = self.redoes.pop().unwrap();
+        self.group_has_undo = false;
         Some(delta)
     }
 }
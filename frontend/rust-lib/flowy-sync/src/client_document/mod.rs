@@ -2,9 +2,15 @@
 
 pub use document_pad::*;
 pub(crate) use extensions::*;
+pub use histogram::*;
+pub use redline::*;
+pub use selection::*;
 pub use view::*;
 
 mod document_pad;
 mod extensions;
+mod histogram;
 pub mod history;
+mod redline;
+mod selection;
 mod view;
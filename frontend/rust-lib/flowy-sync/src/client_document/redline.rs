@@ -0,0 +1,166 @@
+use crate::errors::CollaborateResult;
+use dissimilar::Chunk;
+use lib_ot::core::{AttributeHashMap, AttributeValue, DeltaIterator, OTString};
+use lib_ot::text_delta::{DeltaTextOperationBuilder, DeltaTextOperations};
+
+/// Marks the text that only exists in the newer version.
+pub const REDLINE_INSERTION: &str = "insertion";
+/// Marks the text that was removed since the older version.
+pub const REDLINE_DELETION: &str = "deletion";
+/// Marks the text that exists in both versions with different attributes.
+pub const REDLINE_FORMAT_CHANGED: &str = "format_changed";
+
+/// Annotates the changes between two versions of a document in one document: the text of both
+/// versions is kept and marked with one of the redline attributes.
+///
+/// The redline is for displaying only. It's a mix of the two versions, so it must not be
+/// composed into the document or submitted as a revision.
+///
+/// # Arguments
+///
+/// * `from`: the older version of the document, it should consist of insert operations.
+/// * `to`: the newer version of the document, it should consist of insert operations.
+///
+pub fn make_redline(from: &DeltaTextOperations, to: &DeltaTextOperations) -> CollaborateResult<DeltaTextOperations> {
+    let from_content = from.content()?;
+    let to_content = to.content()?;
+    let mut from_iter = DeltaIterator::new(from);
+    let mut to_iter = DeltaIterator::new(to);
+    let mut builder = DeltaTextOperationBuilder::new();
+    for chunk in dissimilar::diff(&from_content, &to_content) {
+        match chunk {
+            Chunk::Equal(s) => {
+                let mut len = OTString::from(s).utf16_len();
+                while len > 0 {
+                    let next_len = min_len(len, from_iter.next_op_len(), to_iter.next_op_len());
+                    if next_len == 0 {
+                        break;
+                    }
+                    let (from_op, to_op) =
+                        match (from_iter.next_op_with_len(next_len), to_iter.next_op_with_len(next_len)) {
+                            (Some(from_op), Some(to_op)) => (from_op, to_op),
+                            _ => break,
+                        };
+                    let mut attributes = to_op.get_attributes();
+                    if from_op.get_attributes() != attributes {
+                        attributes.insert(REDLINE_FORMAT_CHANGED, true);
+                    }
+                    builder = builder.insert_with_attributes(to_op.get_data(), attributes);
+                    len -= next_len;
+                }
+            }
+            Chunk::Delete(s) => {
+                builder = insert_marked(builder, &mut from_iter, OTString::from(s).utf16_len(), REDLINE_DELETION);
+            }
+            Chunk::Insert(s) => {
+                builder = insert_marked(builder, &mut to_iter, OTString::from(s).utf16_len(), REDLINE_INSERTION);
+            }
+        }
+    }
+    Ok(builder.build())
+}
+
+/// Returns the formatting changes between two versions of a document as the retain operations
+/// that apply to `from`, the text edits are left out. The text that exists in both versions is
+/// retained with the attributes that changed, the text that was removed is retained without any,
+/// and the inserted text isn't in the diff. Composing the diff into `from` changes its formatting
+/// to the one of `to`, its text stays the same.
+///
+/// # Arguments
+///
+/// * `from`: the older version of the document, it should consist of insert operations.
+/// * `to`: the newer version of the document, it should consist of insert operations.
+///
+pub fn make_attribute_diff(
+    from: &DeltaTextOperations,
+    to: &DeltaTextOperations,
+) -> CollaborateResult<DeltaTextOperations> {
+    let from_content = from.content()?;
+    let to_content = to.content()?;
+    let mut from_iter = DeltaIterator::new(from);
+    let mut to_iter = DeltaIterator::new(to);
+    let mut builder = DeltaTextOperationBuilder::new();
+    for chunk in dissimilar::diff(&from_content, &to_content) {
+        match chunk {
+            Chunk::Equal(s) => {
+                let mut len = OTString::from(s).utf16_len();
+                while len > 0 {
+                    let next_len = min_len(len, from_iter.next_op_len(), to_iter.next_op_len());
+                    if next_len == 0 {
+                        break;
+                    }
+                    let (from_op, to_op) =
+                        match (from_iter.next_op_with_len(next_len), to_iter.next_op_with_len(next_len)) {
+                            (Some(from_op), Some(to_op)) => (from_op, to_op),
+                            _ => break,
+                        };
+                    let attributes = diff_attributes(&from_op.get_attributes(), &to_op.get_attributes());
+                    builder = builder.retain_with_attributes(next_len, attributes);
+                    len -= next_len;
+                }
+            }
+            Chunk::Delete(s) => {
+                let len = OTString::from(s).utf16_len();
+                skip(&mut from_iter, len);
+                builder = builder.retain(len);
+            }
+            Chunk::Insert(s) => skip(&mut to_iter, OTString::from(s).utf16_len()),
+        }
+    }
+    Ok(builder.trim().build())
+}
+
+/// Returns the attributes that change `from` into `to`, the removed ones are set to null.
+fn diff_attributes(from: &AttributeHashMap, to: &AttributeHashMap) -> AttributeHashMap {
+    let mut attributes = AttributeHashMap::new();
+    for (key, value) in to.iter() {
+        if from.get(key) != Some(value) {
+            attributes.insert(key.clone(), value.clone());
+        }
+    }
+    for key in from.keys() {
+        if !to.contains_key(key) {
+            attributes.insert(key.clone(), AttributeValue::none());
+        }
+    }
+    attributes
+}
+
+/// Inserts the next `len` of the `iter` with its own attributes plus the `mark`.
+fn insert_marked(
+    mut builder: DeltaTextOperationBuilder,
+    iter: &mut DeltaIterator<AttributeHashMap>,
+    mut len: usize,
+    mark: &str,
+) -> DeltaTextOperationBuilder {
+    while len > 0 {
+        let next_len = iter.next_op_len().unwrap_or(0).min(len);
+        if next_len == 0 {
+            break;
+        }
+        match iter.next_op_with_len(next_len) {
+            Some(op) => {
+                let mut attributes = op.get_attributes();
+                attributes.insert(mark, true);
+                builder = builder.insert_with_attributes(op.get_data(), attributes);
+                len -= next_len;
+            }
+            None => break,
+        }
+    }
+    builder
+}
+
+fn skip(iter: &mut DeltaIterator<AttributeHashMap>, mut len: usize) {
+    while len > 0 {
+        let next_len = iter.next_op_len().unwrap_or(0).min(len);
+        if next_len == 0 || iter.next_op_with_len(next_len).is_none() {
+            break;
+        }
+        len -= next_len;
+    }
+}
+
+fn min_len(len: usize, from_len: Option<usize>, to_len: Option<usize>) -> usize {
+    len.min(from_len.unwrap_or(0)).min(to_len.unwrap_or(0))
+}
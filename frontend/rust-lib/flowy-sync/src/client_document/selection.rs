@@ -0,0 +1,53 @@
+use lib_ot::core::{DeltaOperation, Interval};
+use lib_ot::text_delta::DeltaTextOperations;
+
+/// Moves the ranges of the selection to where their text is after the `operations` are applied
+/// to the document. The `operations` should be the ones that are composed into the document,
+/// e.g. the transformed remote operations.
+///
+/// * The text inserted before a range shifts the range, and the text inserted inside a range
+///   extends it. The text inserted exactly at the start of a range is not selected, neither is
+///   the text inserted exactly at the end. A collapsed range, i.e. the cursor, moves after the
+///   text that is inserted at its position.
+/// * The text deleted before a range shifts the range back, and the text deleted inside a range
+///   shrinks it. A range that is deleted entirely collapses to the position of the deletion.
+///
+pub fn transform_selection(selection: &[Interval], operations: &DeltaTextOperations) -> Vec<Interval> {
+    selection
+        .iter()
+        .map(|range| {
+            if range.is_empty() {
+                let position = transform_position(range.start, operations, true);
+                return Interval::new(position, position);
+            }
+            let start = transform_position(range.start, operations, true);
+            let end = transform_position(range.end, operations, false);
+            Interval::new(start, end.max(start))
+        })
+        .collect()
+}
+
+/// Returns the position after the `operations` are applied. `after_insert` decides whether the
+/// position moves after the text inserted exactly at it.
+fn transform_position(position: usize, operations: &DeltaTextOperations, after_insert: bool) -> usize {
+    let mut index = 0;
+    let mut new_position = position;
+    for operation in operations.ops.iter() {
+        if index > position {
+            break;
+        }
+        match operation {
+            DeltaOperation::Retain(retain) => index += retain.n,
+            DeltaOperation::Insert(insert) => {
+                if index < position || after_insert {
+                    new_position += insert.utf16_size();
+                }
+            }
+            DeltaOperation::Delete(n) => {
+                new_position -= (*n).min(position - index);
+                index += n;
+            }
+        }
+    }
+    new_position
+}
@@ -41,8 +41,20 @@ impl CollaborateError {
     static_error!(out_of_bound, ErrorCode::OutOfBound);
     static_error!(record_not_found, ErrorCode::RecordNotFound);
     static_error!(revision_conflict, ErrorCode::RevisionConflict);
+    static_error!(revision_rejected, ErrorCode::RevisionRejected);
     static_error!(can_not_delete_primary_field, ErrorCode::CannotDeleteThePrimaryField);
     static_error!(unexpected_empty_revision, ErrorCode::UnexpectedEmptyRevision);
+
+    /// The operations are based on a document of `actual` length, but the document has
+    /// `expected` length. The client's copy of the document is out of date.
+    pub fn base_mismatch(expected: usize, actual: usize) -> CollaborateError {
+        CollaborateError::new(ErrorCode::BaseMismatch, &base_mismatch_msg(expected, actual))
+    }
+}
+
+/// The message of the `base_mismatch` error. Both lengths are in utf16 code units.
+pub fn base_mismatch_msg(expected: usize, actual: usize) -> String {
+    format!("expected base length: {}, actual base length: {}", expected, actual)
 }
 
 impl fmt::Display for CollaborateError {
@@ -59,6 +71,8 @@ pub enum ErrorCode {
     RedoFail = 201,
     OutOfBound = 202,
     RevisionConflict = 203,
+    RevisionRejected = 204,
+    BaseMismatch = 205,
     RecordNotFound = 300,
     CannotDeleteThePrimaryField = 301,
     UnexpectedEmptyRevision = 302,
@@ -4,12 +4,14 @@ use dissimilar::Chunk;
 use flowy_http_model::document::DocumentPayload;
 use flowy_http_model::folder::FolderInfo;
 use flowy_http_model::revision::Revision;
-use lib_ot::core::{DeltaOperationBuilder, OTString, OperationAttributes};
+use lib_ot::core::{DeltaOperation, DeltaOperationBuilder, Interval, OTString, OperationAttributes, TransformStep};
 use lib_ot::{
     core::{DeltaOperations, OperationTransform, NEW_LINE, WHITESPACE},
     text_delta::DeltaTextOperations,
 };
 use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
 #[inline]
 pub fn find_newline(s: &str) -> Option<usize> {
@@ -35,22 +37,474 @@ pub fn contain_newline(s: &str) -> bool {
 pub fn make_operations_from_revisions<T>(revisions: Vec<Revision>) -> CollaborateResult<DeltaOperations<T>>
 where
     T: OperationAttributes + DeserializeOwned + OperationAttributes + serde::Serialize,
+{
+    make_operations_from_revisions_observed(revisions, |_, _| {})
+}
+
+/// Same as `make_operations_from_revisions`. The revision that can't be deserialized or composed
+/// is passed to `on_error` with its error before the error is returned.
+pub fn make_operations_from_revisions_observed<T, F>(
+    revisions: Vec<Revision>,
+    mut on_error: F,
+) -> CollaborateResult<DeltaOperations<T>>
+where
+    T: OperationAttributes + DeserializeOwned + OperationAttributes + serde::Serialize,
+    F: FnMut(&Revision, &CollaborateError),
 {
     let mut new_operations = DeltaOperations::<T>::new();
     for revision in revisions {
-        if revision.bytes.is_empty() {
-            return Err(CollaborateError::unexpected_empty_revision().context("Unexpected Empty revision"));
+        if let Err(e) = compose_revision(&mut new_operations, &revision) {
+            on_error(&revision, &e);
+            return Err(e);
+        }
+    }
+    Ok(new_operations)
+}
+
+/// Composes the `revisions` onto the `base` and returns the operations at each of the `rev_ids`,
+/// which should be in ascending order. The revisions are composed in one pass, each version
+/// continues from the one before it. The version at a rev_id that isn't in the `revisions` is
+/// composed from the revisions before it.
+pub fn make_operations_at_rev_ids<T>(
+    base: DeltaOperations<T>,
+    revisions: Vec<Revision>,
+    rev_ids: &[i64],
+) -> CollaborateResult<Vec<DeltaOperations<T>>>
+where
+    T: OperationAttributes + DeserializeOwned + OperationAttributes + serde::Serialize,
+{
+    let mut new_operations = base;
+    let mut revisions = revisions.into_iter().peekable();
+    let mut versions = Vec::with_capacity(rev_ids.len());
+    for rev_id in rev_ids {
+        while let Some(revision) = revisions.next_if(|revision| revision.rev_id <= *rev_id) {
+            compose_revision(&mut new_operations, &revision)?;
         }
+        versions.push(new_operations.clone());
+    }
+    Ok(versions)
+}
+
+/// The result of `make_operations_within_budget`.
+#[derive(Debug, Clone)]
+pub enum ComposeResult<T: OperationAttributes> {
+    /// All the revisions were composed.
+    Complete(DeltaOperations<T>),
+    /// The budget ran out. The operations are the document at `rev_id`, the composing continues
+    /// from them with the revisions after `rev_id`.
+    Partial {
+        operations: DeltaOperations<T>,
+        rev_id: i64,
+    },
+}
+
+/// Composes the `revisions` onto the `base` until the `budget` runs out, e.g. to build a long
+/// history in slices without blocking the UI. At least one revision is composed each time, so
+/// the composing always moves forward however small the budget is.
+pub fn make_operations_within_budget<T>(
+    base: DeltaOperations<T>,
+    revisions: Vec<Revision>,
+    budget: Duration,
+) -> CollaborateResult<ComposeResult<T>>
+where
+    T: OperationAttributes + DeserializeOwned + OperationAttributes + serde::Serialize,
+{
+    let started_at = Instant::now();
+    let mut new_operations = base;
+    let mut revisions = revisions.into_iter().peekable();
+    while let Some(revision) = revisions.next() {
+        compose_revision(&mut new_operations, &revision)?;
+        if revisions.peek().is_some() && started_at.elapsed() >= budget {
+            return Ok(ComposeResult::Partial {
+                operations: new_operations,
+                rev_id: revision.rev_id,
+            });
+        }
+    }
+    Ok(ComposeResult::Complete(new_operations))
+}
+
+fn compose_revision<T>(new_operations: &mut DeltaOperations<T>, revision: &Revision) -> CollaborateResult<()>
+where
+    T: OperationAttributes + DeserializeOwned + OperationAttributes + serde::Serialize,
+{
+    if revision.bytes.is_empty() {
+        return Err(CollaborateError::unexpected_empty_revision().context("Unexpected Empty revision"));
+    }
+    let operations = DeltaOperations::<T>::from_bytes(&revision.bytes).map_err(|e| {
+        let err_msg = format!("Deserialize revision failed: {:?}", e);
+        CollaborateError::internal().context(err_msg)
+    })?;
+
+    // Appending to the end of the document, e.g. writing a journal, doesn't need to rebuild
+    // the whole document for each revision.
+    if !new_operations.compose_append(&operations) {
+        *new_operations = new_operations.compose(&operations)?;
+    }
+    Ok(())
+}
+
+/// Returns the operations that undo the last `n` revisions. Composing them with the operations of
+/// the `revisions` restores the state before the last `n` revisions.
+pub fn make_rollback_operations<T>(mut revisions: Vec<Revision>, n: usize) -> CollaborateResult<DeltaOperations<T>>
+where
+    T: OperationAttributes + DeserializeOwned + serde::Serialize,
+{
+    if n == 0 || n > revisions.len() {
+        return Err(CollaborateError::out_of_bound().context(format!(
+            "Can't roll back {} revisions out of {}",
+            n,
+            revisions.len()
+        )));
+    }
+    let rolled_back = revisions.split_off(revisions.len() - n);
+    let base = make_operations_from_revisions::<T>(revisions)?;
+    let mut composed: Option<DeltaOperations<T>> = None;
+    for revision in rolled_back {
         let operations = DeltaOperations::<T>::from_bytes(revision.bytes).map_err(|e| {
             let err_msg = format!("Deserialize revision failed: {:?}", e);
             CollaborateError::internal().context(err_msg)
         })?;
+        composed = Some(match composed {
+            None => operations,
+            Some(composed) => composed.compose(&operations)?,
+        });
+    }
+    Ok(composed.unwrap_or_default().invert(&base))
+}
 
-        new_operations = new_operations.compose(&operations)?;
+/// Returns the operations of the `revisions` as if the revision `rev_id` never happened. The
+/// revisions after it are rebased by transforming them against the inverse of the removed
+/// revision, so their changes are kept where they still apply.
+pub fn make_operations_without_revision<T>(
+    revisions: Vec<Revision>,
+    rev_id: i64,
+) -> CollaborateResult<DeltaOperations<T>>
+where
+    T: OperationAttributes + DeserializeOwned + OperationAttributes + serde::Serialize,
+{
+    let index = revisions
+        .iter()
+        .position(|revision| revision.rev_id == rev_id)
+        .ok_or_else(|| CollaborateError::record_not_found().context(format!("There is no revision:{}", rev_id)))?;
+    let mut revisions = revisions.into_iter();
+    let mut new_operations = make_operations_from_revisions::<T>(revisions.by_ref().take(index).collect())?;
+    let removed = revisions.next().unwrap();
+    let removed_operations = deserialize_revision::<T>(&removed)?;
+
+    // The undo applies to the document that has the removed revision. Each later revision is
+    // transformed against it, then the undo is moved past that revision.
+    let mut undo = removed_operations.invert(&new_operations);
+    for revision in revisions {
+        let operations = deserialize_revision::<T>(&revision)?;
+        let (undo_prime, operations_prime) = undo.transform(&operations)?;
+        new_operations = new_operations.compose(&operations_prime)?;
+        undo = undo_prime;
     }
     Ok(new_operations)
 }
 
+/// Returns the revision that inserted each part of the document in the `interval`, as runs of
+/// the utf16 interval and the rev_id. The revisions are walked forward and each character keeps
+/// the rev_id of the revision that inserted it, formatting the character doesn't change it. The
+/// text of the revisions that were merged into one is attributed to the merged revision.
+pub fn make_blame<T>(revisions: Vec<Revision>, interval: Interval) -> CollaborateResult<Vec<(Interval, i64)>>
+where
+    T: OperationAttributes + DeserializeOwned,
+{
+    // The document as the runs of the utf16 length and the rev_id that inserted them.
+    let mut runs: VecDeque<(usize, i64)> = VecDeque::new();
+    for revision in revisions {
+        let operations = deserialize_revision::<T>(&revision)?;
+        let mut new_runs = VecDeque::with_capacity(runs.len() + 1);
+        for op in operations.ops.iter() {
+            match op {
+                DeltaOperation::Retain(retain) => take_runs(&mut runs, retain.n, Some(&mut new_runs))?,
+                DeltaOperation::Delete(n) => take_runs(&mut runs, *n, None)?,
+                DeltaOperation::Insert(_) => push_run(&mut new_runs, op.len(), revision.rev_id),
+            }
+        }
+        // The rest of the document is retained.
+        for (len, rev_id) in runs.drain(..) {
+            push_run(&mut new_runs, len, rev_id);
+        }
+        runs = new_runs;
+    }
+
+    let document_len = runs.iter().map(|(len, _)| len).sum::<usize>();
+    if interval.end > document_len {
+        return Err(CollaborateError::out_of_bound().context(format!(
+            "The interval:{} is out of the document's length:{}",
+            interval, document_len
+        )));
+    }
+    let mut blame = vec![];
+    let mut start = 0;
+    for (len, rev_id) in runs {
+        let run = Interval::new(start, start + len).intersect(interval);
+        if !run.is_empty() {
+            blame.push((run, rev_id));
+        }
+        start += len;
+    }
+    Ok(blame)
+}
+
+fn push_run(runs: &mut VecDeque<(usize, i64)>, len: usize, rev_id: i64) {
+    if len == 0 {
+        return;
+    }
+    match runs.back_mut() {
+        Some((last_len, last_rev_id)) if *last_rev_id == rev_id => *last_len += len,
+        _ => runs.push_back((len, rev_id)),
+    }
+}
+
+/// Takes `n` utf16 code units from the front of the `runs`. They're moved to `to` unless it's
+/// None, i.e. they're deleted.
+fn take_runs(
+    runs: &mut VecDeque<(usize, i64)>,
+    mut n: usize,
+    mut to: Option<&mut VecDeque<(usize, i64)>>,
+) -> CollaborateResult<()> {
+    while n > 0 {
+        let (len, rev_id) = runs
+            .pop_front()
+            .ok_or_else(|| CollaborateError::internal().context("The revision is longer than the document"))?;
+        let taken = len.min(n);
+        if taken < len {
+            runs.push_front((len - taken, rev_id));
+        }
+        if let Some(to) = to.as_mut() {
+            push_run(to, taken, rev_id);
+        }
+        n -= taken;
+    }
+    Ok(())
+}
+
+/// Returns the ranges of the current document that the revision `rev_id` touched, in utf16
+/// code units. The text it inserted is mapped through the later revisions, the parts that were
+/// deleted since are left out and the text inserted into it later splits it. Each deletion of
+/// the revision is a zero-width range at the place of the deleted text. Formatting doesn't count
+/// as a touch.
+pub fn make_touched_ranges<T>(revisions: Vec<Revision>, rev_id: i64) -> CollaborateResult<Vec<Interval>>
+where
+    T: OperationAttributes + DeserializeOwned,
+{
+    // The document as the runs of the utf16 length and whether the revision touched them. A
+    // deletion is a touched run of zero length.
+    let mut runs: Option<VecDeque<(usize, bool)>> = None;
+    let mut document_len = 0;
+    for revision in revisions {
+        let operations = deserialize_revision::<T>(&revision)?;
+        let mut old_runs = match runs.take() {
+            None if revision.rev_id != rev_id => {
+                document_len = operations.utf16_target_len;
+                continue;
+            }
+            None => {
+                let mut old_runs = VecDeque::new();
+                push_touched_run(&mut old_runs, document_len, false);
+                old_runs
+            }
+            Some(runs) => runs,
+        };
+        let is_touched = revision.rev_id == rev_id;
+        let mut new_runs = VecDeque::with_capacity(old_runs.len() + 1);
+        for op in operations.ops.iter() {
+            match op {
+                DeltaOperation::Retain(retain) => take_touched_runs(&mut old_runs, retain.n, &mut new_runs, true)?,
+                DeltaOperation::Delete(n) => {
+                    take_touched_runs(&mut old_runs, *n, &mut new_runs, false)?;
+                    if is_touched {
+                        new_runs.push_back((0, true));
+                    }
+                }
+                DeltaOperation::Insert(_) => push_touched_run(&mut new_runs, op.len(), is_touched),
+            }
+        }
+        // The rest of the document is retained.
+        for (len, is_touched) in old_runs.drain(..) {
+            match len {
+                0 => new_runs.push_back((len, is_touched)),
+                _ => push_touched_run(&mut new_runs, len, is_touched),
+            }
+        }
+        runs = Some(new_runs);
+    }
+
+    let runs = runs
+        .ok_or_else(|| CollaborateError::record_not_found().context(format!("The revision:{} is not found", rev_id)))?;
+    let mut ranges: Vec<Interval> = vec![];
+    let mut start = 0;
+    for (len, is_touched) in runs {
+        let range = Interval::new(start, start + len);
+        start += len;
+        if !is_touched || ranges.last() == Some(&range) {
+            continue;
+        }
+        ranges.push(range);
+    }
+    Ok(ranges)
+}
+
+fn push_touched_run(runs: &mut VecDeque<(usize, bool)>, len: usize, is_touched: bool) {
+    if len == 0 {
+        return;
+    }
+    match runs.back_mut() {
+        Some((last_len, last_is_touched)) if *last_len > 0 && *last_is_touched == is_touched => *last_len += len,
+        _ => runs.push_back((len, is_touched)),
+    }
+}
+
+/// Takes `n` utf16 code units from the front of the `runs`. They're moved to `to` if `keep`,
+/// otherwise they're deleted. The zero-width runs are always moved, a deletion keeps its place
+/// when the text around it is deleted.
+fn take_touched_runs(
+    runs: &mut VecDeque<(usize, bool)>,
+    mut n: usize,
+    to: &mut VecDeque<(usize, bool)>,
+    keep: bool,
+) -> CollaborateResult<()> {
+    while n > 0 {
+        let (len, is_touched) = runs
+            .pop_front()
+            .ok_or_else(|| CollaborateError::internal().context("The revision is longer than the document"))?;
+        if len == 0 {
+            to.push_back((len, is_touched));
+            continue;
+        }
+        let taken = len.min(n);
+        if taken < len {
+            runs.push_front((len - taken, is_touched));
+        }
+        if keep {
+            push_touched_run(to, taken, is_touched);
+        }
+        n -= taken;
+    }
+    Ok(())
+}
+
+/// The transform of a pair of revisions with the decisions it made, see `explain_transform`.
+#[derive(Debug, Clone)]
+pub struct TransformExplanation<T: OperationAttributes> {
+    /// The operations of the local revision. The later one of the revisions is rebased so both
+    /// apply to the same document.
+    pub local: DeltaOperations<T>,
+    pub remote: DeltaOperations<T>,
+    /// The transform with the local revision as the left delta.
+    pub local_first: TracedTransform<T>,
+    /// The transform with the remote revision as the left delta.
+    pub remote_first: TracedTransform<T>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TracedTransform<T: OperationAttributes> {
+    pub left_prime: DeltaOperations<T>,
+    pub right_prime: DeltaOperations<T>,
+    pub steps: Vec<TransformStep>,
+}
+
+/// Replays the transform of the revisions `local_rev_id` and `remote_rev_id` of the `revisions`
+/// in both directions. The revisions in the history were applied one after another, so the later
+/// one is rebased onto the document before the earlier one by transforming it against the undo of
+/// each revision in between, starting from the last one.
+pub fn explain_transform<T>(
+    revisions: Vec<Revision>,
+    local_rev_id: i64,
+    remote_rev_id: i64,
+) -> CollaborateResult<TransformExplanation<T>>
+where
+    T: OperationAttributes + DeserializeOwned + serde::Serialize,
+{
+    if local_rev_id == remote_rev_id {
+        return Err(CollaborateError::internal()
+            .context(format!("Can't transform the revision:{} against itself", local_rev_id)));
+    }
+    let last_rev_id = revisions.last().map(|revision| revision.rev_id).unwrap_or(0);
+    let position = |rev_id: i64| {
+        revisions
+            .iter()
+            .position(|revision| revision.rev_id == rev_id)
+            .ok_or_else(|| {
+                let msg = if rev_id <= last_rev_id {
+                    format!("The revision:{} has been compacted away", rev_id)
+                } else {
+                    format!("There is no revision:{}", rev_id)
+                };
+                CollaborateError::record_not_found().context(msg)
+            })
+    };
+    let local_index = position(local_rev_id)?;
+    let remote_index = position(remote_rev_id)?;
+    let (earlier_index, later_index) = if local_index < remote_index {
+        (local_index, remote_index)
+    } else {
+        (remote_index, local_index)
+    };
+
+    // The document before each revision up to the later one. The revisions may leave out the
+    // retain at the end, it's added back so the revision applies to the whole document.
+    let mut documents = vec![DeltaOperations::<T>::default()];
+    let mut operations = vec![];
+    for revision in &revisions[..=later_index] {
+        let document = documents.last().unwrap();
+        let mut revision_operations = deserialize_revision::<T>(revision)?;
+        if revision_operations.utf16_base_len < document.utf16_target_len {
+            let len = document.utf16_target_len - revision_operations.utf16_base_len;
+            revision_operations.retain(len, T::default());
+        }
+        let next_document = document.compose(&revision_operations)?;
+        documents.push(next_document);
+        operations.push(revision_operations);
+    }
+
+    let earlier = operations[earlier_index].clone();
+    let mut later = operations[later_index].clone();
+    for index in (earlier_index..later_index).rev() {
+        let undo = operations[index].invert(&documents[index]);
+        let (_, later_prime) = undo.transform(&later)?;
+        later = later_prime;
+    }
+    let (local, remote) = if local_index < remote_index {
+        (earlier, later)
+    } else {
+        (later, earlier)
+    };
+
+    let (left_prime, right_prime, steps) = local.transform_with_trace(&remote)?;
+    let local_first = TracedTransform {
+        left_prime,
+        right_prime,
+        steps,
+    };
+    let (left_prime, right_prime, steps) = remote.transform_with_trace(&local)?;
+    let remote_first = TracedTransform {
+        left_prime,
+        right_prime,
+        steps,
+    };
+    Ok(TransformExplanation {
+        local,
+        remote,
+        local_first,
+        remote_first,
+    })
+}
+
+fn deserialize_revision<T>(revision: &Revision) -> CollaborateResult<DeltaOperations<T>>
+where
+    T: OperationAttributes + DeserializeOwned,
+{
+    DeltaOperations::<T>::from_bytes(&revision.bytes).map_err(|e| {
+        let err_msg = format!("Deserialize revision failed: {:?}", e);
+        CollaborateError::internal().context(err_msg)
+    })
+}
+
 pub fn recover_operation_from_revisions<T>(
     revisions: Vec<Revision>,
     validator: impl Fn(&DeltaOperations<T>) -> bool,
@@ -193,3 +647,73 @@ pub fn cal_diff<T: OperationAttributes>(old: String, new: String) -> Option<Delt
         Some(delta)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::util::make_operations_from_revisions;
+    use flowy_http_model::revision::Revision;
+    use lib_ot::core::OperationTransform;
+    use lib_ot::text_delta::{BuildInTextAttribute, DeltaTextOperationBuilder, DeltaTextOperations};
+
+    fn revisions_from(deltas: &[DeltaTextOperations]) -> Vec<Revision> {
+        deltas
+            .iter()
+            .enumerate()
+            .map(|(index, delta)| {
+                let rev_id = index as i64 + 1;
+                Revision::new("doc", rev_id - 1, rev_id, delta.json_bytes(), "")
+            })
+            .collect()
+    }
+
+    fn compose_all(deltas: &[DeltaTextOperations]) -> DeltaTextOperations {
+        let mut composed = DeltaTextOperations::new();
+        for delta in deltas {
+            composed = composed.compose(delta).unwrap();
+        }
+        composed
+    }
+
+    #[test]
+    fn compose_appends_test() {
+        let mut len = 0;
+        let deltas = (0..1000)
+            .map(|i| {
+                let s = format!("line {}\n", i);
+                let delta = DeltaTextOperationBuilder::new().retain(len).insert(&s).build();
+                len += s.len();
+                delta
+            })
+            .collect::<Vec<DeltaTextOperations>>();
+        assert!(deltas.iter().all(|delta| delta.is_append()));
+
+        let operations = make_operations_from_revisions(revisions_from(&deltas)).unwrap();
+        assert_eq!(operations, compose_all(&deltas));
+        assert_eq!(operations.ops.len(), 1);
+    }
+
+    #[test]
+    fn compose_appends_with_mixed_edits_test() {
+        let deltas = vec![
+            DeltaTextOperationBuilder::new().insert("123").build(),
+            DeltaTextOperationBuilder::new().retain(3).insert("456").build(),
+            // Bold the whole text
+            DeltaTextOperationBuilder::new()
+                .retain_with_attributes(6, BuildInTextAttribute::Bold(true).into())
+                .build(),
+            DeltaTextOperationBuilder::new().retain(6).insert("789").build(),
+            // Delete the "456"
+            DeltaTextOperationBuilder::new().retain(3).delete(3).retain(3).build(),
+            DeltaTextOperationBuilder::new()
+                .retain(6)
+                .insert_with_attributes("abc", BuildInTextAttribute::Italic(true).into())
+                .build(),
+        ];
+        assert!(!deltas[2].is_append());
+        assert!(!deltas[4].is_append());
+
+        let operations = make_operations_from_revisions(revisions_from(&deltas)).unwrap();
+        assert_eq!(operations, compose_all(&deltas));
+        assert_eq!(operations.content().unwrap(), "123789abc");
+    }
+}
@@ -62,7 +62,8 @@ where
     where
         Event: Eq + Hash + Debug + Clone + Display,
     {
-        self.context.request = Some(AFPluginRequest::new(event));
+        let request = AFPluginRequest::new(event).capability_token(self.context.sdk.capability_token.clone());
+        self.context.request = Some(request);
         self
     }
 
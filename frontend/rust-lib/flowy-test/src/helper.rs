@@ -30,11 +30,21 @@ impl ViewTest {
         data_format: ViewDataFormatPB,
         layout: ViewLayoutTypePB,
         data: Vec<u8>,
+    ) -> Self {
+        Self::new_with_scratch(sdk, data_format, layout, data, false).await
+    }
+
+    async fn new_with_scratch(
+        sdk: &FlowySDKTest,
+        data_format: ViewDataFormatPB,
+        layout: ViewLayoutTypePB,
+        data: Vec<u8>,
+        is_scratch: bool,
     ) -> Self {
         let workspace = create_workspace(sdk, "Workspace", "").await;
         open_workspace(sdk, &workspace.id).await;
         let app = create_app(sdk, "App", "AppFlowy GitHub Project", &workspace.id).await;
-        let view = create_view(sdk, &app.id, data_format, layout, data).await;
+        let view = create_view(sdk, &app.id, data_format, layout, data, is_scratch).await;
         Self {
             sdk: sdk.clone(),
             workspace,
@@ -56,11 +66,18 @@ impl ViewTest {
     }
 
     pub async fn new_document_view(sdk: &FlowySDKTest) -> Self {
-        let view_data_format = match sdk.document_version() {
-            DocumentVersionPB::V0 => ViewDataFormatPB::DeltaFormat,
-            DocumentVersionPB::V1 => ViewDataFormatPB::TreeFormat,
-        };
-        Self::new(sdk, view_data_format, ViewLayoutTypePB::Document, vec![]).await
+        Self::new(sdk, document_data_format(sdk), ViewLayoutTypePB::Document, vec![]).await
+    }
+
+    pub async fn new_scratch_document_view(sdk: &FlowySDKTest) -> Self {
+        Self::new_with_scratch(sdk, document_data_format(sdk), ViewLayoutTypePB::Document, vec![], true).await
+    }
+}
+
+fn document_data_format(sdk: &FlowySDKTest) -> ViewDataFormatPB {
+    match sdk.document_version() {
+        DocumentVersionPB::V0 => ViewDataFormatPB::DeltaFormat,
+        DocumentVersionPB::V1 => ViewDataFormatPB::TreeFormat,
     }
 }
 
@@ -111,6 +128,7 @@ async fn create_view(
     data_format: ViewDataFormatPB,
     layout: ViewLayoutTypePB,
     data: Vec<u8>,
+    is_scratch: bool,
 ) -> ViewPB {
     let request = CreateViewPayloadPB {
         belong_to_id: app_id.to_string(),
@@ -120,6 +138,8 @@ async fn create_view(
         data_format,
         layout,
         view_content_data: data,
+        is_scratch,
+        idempotency_key: None,
     };
 
     FolderEventBuilder::new(sdk.clone())
@@ -7,6 +7,7 @@ use flowy_core::{FlowySDK, FlowySDKConfig};
 use flowy_document::entities::DocumentVersionPB;
 use flowy_net::get_client_server_configuration;
 use flowy_user::entities::UserProfilePB;
+use lib_dispatch::prelude::AFPluginCapability;
 use nanoid::nanoid;
 
 pub mod prelude {
@@ -35,10 +36,22 @@ impl std::default::Default for FlowySDKTest {
 
 impl FlowySDKTest {
     pub fn new(document_version: DocumentVersionPB) -> Self {
+        Self::new_with_config(Self::config(document_version))
+    }
+
+    /// Same as `new` but the token of the FFI only grants the `capabilities`.
+    pub fn new_with_ffi_capabilities(document_version: DocumentVersionPB, capabilities: &[AFPluginCapability]) -> Self {
+        Self::new_with_config(Self::config(document_version).with_ffi_capabilities(capabilities))
+    }
+
+    fn config(document_version: DocumentVersionPB) -> FlowySDKConfig {
         let server_config = get_client_server_configuration().unwrap();
-        let config = FlowySDKConfig::new(&root_dir(), nanoid!(6), server_config)
+        FlowySDKConfig::new(&root_dir(), nanoid!(6), server_config)
             .with_document_version(document_version)
-            .log_filter("info");
+            .log_filter("info")
+    }
+
+    fn new_with_config(config: FlowySDKConfig) -> Self {
         let sdk = std::thread::spawn(|| FlowySDK::new(config)).join().unwrap();
         std::mem::forget(sdk.dispatcher());
         Self { inner: sdk }
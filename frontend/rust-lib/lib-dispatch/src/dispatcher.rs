@@ -1,7 +1,10 @@
 use crate::runtime::AFPluginRuntime;
 use crate::{
     errors::{DispatchError, Error, InternalError},
-    module::{as_plugin_map, AFPlugin, AFPluginMap, AFPluginRequest},
+    module::{
+        as_plugin_map, AFPlugin, AFPluginCapabilities, AFPluginCapability, AFPluginCapabilityAuthority,
+        AFPluginCapabilityToken, AFPluginMap, AFPluginRequest,
+    },
     response::AFPluginEventResponse,
     service::{AFPluginServiceFactory, Service},
 };
@@ -15,6 +18,7 @@ use tokio::macros::support::{Pin, Poll};
 pub struct AFPluginDispatcher {
     plugins: AFPluginMap,
     runtime: AFPluginRuntime,
+    capability_authority: Arc<AFPluginCapabilityAuthority>,
 }
 
 impl AFPluginDispatcher {
@@ -27,6 +31,21 @@ impl AFPluginDispatcher {
         AFPluginDispatcher {
             plugins: as_plugin_map(plugins),
             runtime,
+            capability_authority: Arc::new(AFPluginCapabilityAuthority::default()),
+        }
+    }
+
+    /// Mints the token that grants the `capabilities`. The requests carry it with
+    /// `AFPluginRequest::capability_token`.
+    pub fn mint_capability_token(&self, capabilities: &[AFPluginCapability]) -> AFPluginCapabilityToken {
+        self.capability_authority.mint(AFPluginCapabilities::from(capabilities))
+    }
+
+    /// Moves the startup gates of the plugins to the `generation`, the held requests that were
+    /// sent for an older generation are dropped. See `AFPluginRequest::generation`.
+    pub fn advance_generation(&self, generation: u64) {
+        for plugin in self.plugins.values() {
+            plugin.advance_generation(generation);
         }
     }
 
@@ -48,7 +67,11 @@ impl AFPluginDispatcher {
     {
         let request: AFPluginRequest = request.into();
         let plugins = dispatch.plugins.clone();
-        let service = Box::new(DispatchService { plugins });
+        let capability_authority = dispatch.capability_authority.clone();
+        let service = Box::new(DispatchService {
+            plugins,
+            capability_authority,
+        });
         tracing::trace!("Async event: {:?}", &request.event);
         let service_ctx = DispatchContext {
             request,
@@ -124,6 +147,7 @@ impl DispatchContext {
 
 pub(crate) struct DispatchService {
     pub(crate) plugins: AFPluginMap,
+    pub(crate) capability_authority: Arc<AFPluginCapabilityAuthority>,
 }
 
 impl Service<DispatchContext> for DispatchService {
@@ -137,7 +161,10 @@ impl Service<DispatchContext> for DispatchService {
     )]
     fn call(&self, ctx: DispatchContext) -> Self::Future {
         let module_map = self.plugins.clone();
-        let (request, callback) = ctx.into_parts();
+        let (mut request, callback) = ctx.into_parts();
+        request.capabilities = self
+            .capability_authority
+            .capabilities_of(request.capability_token.as_ref());
 
         Box::pin(async move {
             let result = {
@@ -0,0 +1,103 @@
+use crate::{
+    errors::Error,
+    module::AFPluginEvent,
+    response::{AFPluginEventResponse, ResponseBuilder},
+};
+use dashmap::DashMap;
+use nanoid::nanoid;
+use std::fmt;
+
+/// The capabilities an event can require. The host mints the tokens that grant them, see
+/// `AFPluginDispatcher::mint_capability_token`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum AFPluginCapability {
+    Read = 0,
+    Write = 1,
+    Maintenance = 2,
+    Export = 3,
+}
+
+impl AFPluginCapability {
+    pub const ALL: [AFPluginCapability; 4] = [
+        AFPluginCapability::Read,
+        AFPluginCapability::Write,
+        AFPluginCapability::Maintenance,
+        AFPluginCapability::Export,
+    ];
+
+    fn bit(&self) -> u8 {
+        1 << (*self as u8)
+    }
+}
+
+/// A set of `AFPluginCapability`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AFPluginCapabilities(u8);
+
+impl AFPluginCapabilities {
+    pub fn all() -> Self {
+        Self::from(&AFPluginCapability::ALL[..])
+    }
+
+    pub fn contains(&self, capability: AFPluginCapability) -> bool {
+        self.0 & capability.bit() != 0
+    }
+}
+
+impl std::convert::From<&[AFPluginCapability]> for AFPluginCapabilities {
+    fn from(capabilities: &[AFPluginCapability]) -> Self {
+        Self(capabilities.iter().fold(0, |bits, capability| bits | capability.bit()))
+    }
+}
+
+/// An opaque token that grants the capabilities it was minted with. It's only valid for the
+/// dispatcher that minted it.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct AFPluginCapabilityToken(String);
+
+impl fmt::Debug for AFPluginCapabilityToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The token is a secret, it must not end up in the logs.
+        f.write_str("AFPluginCapabilityToken(..)")
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct AFPluginCapabilityAuthority {
+    tokens: DashMap<String, AFPluginCapabilities>,
+}
+
+impl AFPluginCapabilityAuthority {
+    pub(crate) fn mint(&self, capabilities: AFPluginCapabilities) -> AFPluginCapabilityToken {
+        let token = nanoid!(32);
+        self.tokens.insert(token.clone(), capabilities);
+        AFPluginCapabilityToken(token)
+    }
+
+    /// Returns the capabilities granted by the token. The missing or unknown token grants none.
+    pub(crate) fn capabilities_of(&self, token: Option<&AFPluginCapabilityToken>) -> AFPluginCapabilities {
+        token
+            .and_then(|token| self.tokens.get(&token.0).map(|capabilities| *capabilities))
+            .unwrap_or_default()
+    }
+}
+
+/// The error of the event that was sent without the capability it requires. The plugin turns
+/// it into its own error type, see `AFPlugin::permission_error`.
+#[derive(Clone, Debug)]
+pub struct AFPluginPermissionDenied {
+    pub event: AFPluginEvent,
+    pub capability: AFPluginCapability,
+}
+
+impl fmt::Display for AFPluginPermissionDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} requires the {:?} capability", self.event, self.capability)
+    }
+}
+
+impl Error for AFPluginPermissionDenied {
+    fn as_response(&self) -> AFPluginEventResponse {
+        ResponseBuilder::Err().data(format!("{}", self).into_bytes()).build()
+    }
+}
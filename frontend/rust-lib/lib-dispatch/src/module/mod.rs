@@ -1,8 +1,12 @@
 #![allow(clippy::module_inception)]
+pub use capability::*;
 pub use container::*;
 pub use data::*;
 pub use module::*;
+pub use startup_gate::*;
 
+mod capability;
 mod container;
 mod data;
 mod module;
+mod startup_gate;
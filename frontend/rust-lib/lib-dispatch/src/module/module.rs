@@ -1,6 +1,10 @@
 use crate::{
-    errors::{DispatchError, InternalError},
-    module::{container::AFPluginStateMap, AFPluginState},
+    errors::{DispatchError, Error, InternalError},
+    module::{
+        container::AFPluginStateMap, AFPluginCapabilities, AFPluginCapability, AFPluginCapabilityToken,
+        AFPluginNotReady, AFPluginNotReadyReason, AFPluginPermissionDenied, AFPluginStartupGate, AFPluginState,
+        Admission, GateEntry,
+    },
     request::{payload::Payload, AFPluginEventRequest, FromAFPluginRequest},
     response::{AFPluginEventResponse, AFPluginResponder},
     service::{
@@ -24,6 +28,8 @@ use std::{
 };
 
 pub type AFPluginMap = Arc<HashMap<AFPluginEvent, Arc<AFPlugin>>>;
+type PermissionErrorFn = Arc<dyn Fn(AFPluginPermissionDenied) -> AFPluginEventResponse + Send + Sync>;
+type NotReadyErrorFn = Arc<dyn Fn(AFPluginNotReady) -> AFPluginEventResponse + Send + Sync>;
 pub(crate) fn as_plugin_map(plugins: Vec<AFPlugin>) -> AFPluginMap {
     let mut plugin_map = HashMap::new();
     plugins.into_iter().for_each(|m| {
@@ -62,6 +68,18 @@ pub struct AFPlugin {
     ///
     event_service_factory:
         Arc<HashMap<AFPluginEvent, BoxServiceFactory<(), ServiceRequest, ServiceResponse, DispatchError>>>,
+
+    /// The capabilities that the requests of the events must carry, see `event_with_capability`.
+    required_capabilities: Arc<HashMap<AFPluginEvent, AFPluginCapability>>,
+
+    /// Builds the response of the rejected requests.
+    permission_error: PermissionErrorFn,
+
+    /// Holds back the requests until the plugin is ready, see `startup_gate`.
+    startup_gate: Option<AFPluginStartupGate>,
+
+    /// Builds the response of the requests that the startup gate didn't let through.
+    not_ready_error: NotReadyErrorFn,
 }
 
 impl std::default::Default for AFPlugin {
@@ -70,6 +88,10 @@ impl std::default::Default for AFPlugin {
             name: "".to_owned(),
             states: Arc::new(AFPluginStateMap::new()),
             event_service_factory: Arc::new(HashMap::new()),
+            required_capabilities: Arc::new(HashMap::new()),
+            permission_error: Arc::new(|error| error.as_response()),
+            startup_gate: None,
+            not_ready_error: Arc::new(|error| error.as_response()),
         }
     }
 }
@@ -110,6 +132,63 @@ impl AFPlugin {
         self
     }
 
+    /// Registers the handler like `event`, the requests of the event are rejected before
+    /// reaching the handler unless their token grants the `capability`.
+    pub fn event_with_capability<E, H, T, R>(mut self, event: E, capability: AFPluginCapability, handler: H) -> Self
+    where
+        H: AFPluginHandler<T, R>,
+        T: FromAFPluginRequest + 'static + Send + Sync,
+        <T as FromAFPluginRequest>::Future: Sync + Send,
+        R: Future + 'static + Send + Sync,
+        R::Output: AFPluginResponder + 'static,
+        E: Eq + Hash + Debug + Clone + Display,
+    {
+        let plugin_event: AFPluginEvent = event.clone().into();
+        self = self.event(event, handler);
+        // Copies the map if a service was already made from the plugin instead of panicking, the
+        // services made before keep the capabilities they were made with.
+        Arc::make_mut(&mut self.required_capabilities).insert(plugin_event, capability);
+        self
+    }
+
+    /// Rejects the requests that lack the capability with the error `E`, so the caller can
+    /// parse the rejection like the other errors of the plugin.
+    pub fn permission_error<E>(mut self) -> Self
+    where
+        E: 'static + From<AFPluginPermissionDenied> + Error,
+    {
+        self.permission_error = Arc::new(|error| E::from(error).as_response());
+        self
+    }
+
+    /// Holds back the requests of the plugin's events until the `gate` opens. The plugin opens
+    /// it once it finishes initializing, the held requests are replayed in order then.
+    pub fn startup_gate(mut self, gate: AFPluginStartupGate) -> Self {
+        self.startup_gate = Some(gate);
+        self
+    }
+
+    /// Rejects the requests that the startup gate didn't let through with the error `E`.
+    pub fn not_ready_error<E>(mut self) -> Self
+    where
+        E: 'static + From<AFPluginNotReady> + Error,
+    {
+        self.not_ready_error = Arc::new(|error| E::from(error).as_response());
+        self
+    }
+
+    /// Moves the startup gate of the plugin to the `generation` if it has one, see
+    /// `AFPluginStartupGate::advance_generation`.
+    pub fn advance_generation(&self, generation: u64) {
+        if let Some(startup_gate) = &self.startup_gate {
+            startup_gate.advance_generation(generation);
+        }
+    }
+
+    pub fn required_capability(&self, event: &AFPluginEvent) -> Option<AFPluginCapability> {
+        self.required_capabilities.get(event).cloned()
+    }
+
     pub fn events(&self) -> Vec<AFPluginEvent> {
         self.event_service_factory.keys().cloned().collect::<Vec<_>>()
     }
@@ -124,6 +203,11 @@ pub struct AFPluginRequest {
     pub id: String,
     pub event: AFPluginEvent,
     pub(crate) payload: Payload,
+    pub(crate) capability_token: Option<AFPluginCapabilityToken>,
+    /// The capabilities granted by the `capability_token`, resolved by the dispatcher.
+    pub(crate) capabilities: AFPluginCapabilities,
+    /// The generation the request was sent for, see `AFPluginStartupGate::advance_generation`.
+    pub(crate) generation: Option<u64>,
 }
 
 impl AFPluginRequest {
@@ -135,9 +219,22 @@ impl AFPluginRequest {
             id: nanoid!(6),
             event: event.into(),
             payload: Payload::None,
+            capability_token: None,
+            capabilities: AFPluginCapabilities::default(),
+            generation: None,
         }
     }
 
+    pub fn capability_token(mut self, token: AFPluginCapabilityToken) -> Self {
+        self.capability_token = Some(token);
+        self
+    }
+
+    pub fn generation(mut self, generation: u64) -> Self {
+        self.generation = Some(generation);
+        self
+    }
+
     pub fn payload<P>(mut self, payload: P) -> Self
     where
         P: Into<Payload>,
@@ -163,8 +260,19 @@ impl AFPluginServiceFactory<AFPluginRequest> for AFPlugin {
     fn new_service(&self, _cfg: Self::Context) -> Self::Future {
         let services = self.event_service_factory.clone();
         let states = self.states.clone();
+        let required_capabilities = self.required_capabilities.clone();
+        let permission_error = self.permission_error.clone();
+        let startup_gate = self.startup_gate.clone();
+        let not_ready_error = self.not_ready_error.clone();
         Box::pin(async move {
-            let service = AFPluginService { services, states };
+            let service = AFPluginService {
+                services,
+                states,
+                required_capabilities,
+                permission_error,
+                startup_gate,
+                not_ready_error,
+            };
             Ok(Box::new(service) as Self::Service)
         })
     }
@@ -173,15 +281,19 @@ impl AFPluginServiceFactory<AFPluginRequest> for AFPlugin {
 pub struct AFPluginService {
     services: Arc<HashMap<AFPluginEvent, BoxServiceFactory<(), ServiceRequest, ServiceResponse, DispatchError>>>,
     states: Arc<AFPluginStateMap>,
+    required_capabilities: Arc<HashMap<AFPluginEvent, AFPluginCapability>>,
+    permission_error: PermissionErrorFn,
+    startup_gate: Option<AFPluginStartupGate>,
+    not_ready_error: NotReadyErrorFn,
 }
 
-impl Service<AFPluginRequest> for AFPluginService {
-    type Response = AFPluginEventResponse;
-    type Error = DispatchError;
-    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
-
-    fn call(&self, request: AFPluginRequest) -> Self::Future {
-        let AFPluginRequest { id, event, payload } = request;
+impl AFPluginService {
+    fn handle(
+        &self,
+        id: String,
+        event: AFPluginEvent,
+        payload: Payload,
+    ) -> BoxFuture<'static, Result<AFPluginEventResponse, DispatchError>> {
         let states = self.states.clone();
         let request = AFPluginEventRequest::new(id, event, states);
 
@@ -205,6 +317,63 @@ impl Service<AFPluginRequest> for AFPluginService {
     }
 }
 
+impl Service<AFPluginRequest> for AFPluginService {
+    type Response = AFPluginEventResponse;
+    type Error = DispatchError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn call(&self, request: AFPluginRequest) -> Self::Future {
+        let AFPluginRequest {
+            id,
+            event,
+            payload,
+            capabilities,
+            generation,
+            ..
+        } = request;
+        if let Some(capability) = self.required_capabilities.get(&event) {
+            if !capabilities.contains(*capability) {
+                tracing::warn!("Reject {:?}, it lacks the {:?} capability", event, capability);
+                let response = (self.permission_error)(AFPluginPermissionDenied {
+                    event,
+                    capability: *capability,
+                });
+                return Box::pin(async move { Ok(response) });
+            }
+        }
+        let gate_entry = match &self.startup_gate {
+            None => GateEntry::Open,
+            Some(startup_gate) => startup_gate.enter(generation),
+        };
+        match gate_entry {
+            GateEntry::Open => self.handle(id, event, payload),
+            GateEntry::Rejected => {
+                tracing::warn!("Reject {:?}, the plugin isn't ready and holds too many requests", event);
+                let response = (self.not_ready_error)(AFPluginNotReady {
+                    event,
+                    reason: AFPluginNotReadyReason::QueueFull,
+                });
+                Box::pin(async move { Ok(response) })
+            }
+            GateEntry::Queued(admission) => {
+                tracing::trace!("Hold {:?} until the plugin is ready", event);
+                let handle = self.handle(id, event.clone(), payload);
+                let not_ready_error = self.not_ready_error.clone();
+                Box::pin(async move {
+                    match admission.await {
+                        // The gate replays the next request after this one is handled.
+                        Ok(Admission::Run(_done)) => handle.await,
+                        Ok(Admission::Stale) | Err(_) => Ok(not_ready_error(AFPluginNotReady {
+                            event,
+                            reason: AFPluginNotReadyReason::Stale,
+                        })),
+                    }
+                })
+            }
+        }
+    }
+}
+
 #[pin_project]
 pub struct AFPluginServiceFuture {
     #[pin]
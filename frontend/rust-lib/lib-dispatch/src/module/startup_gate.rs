@@ -0,0 +1,156 @@
+use crate::{
+    errors::Error,
+    module::AFPluginEvent,
+    response::{AFPluginEventResponse, ResponseBuilder},
+};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Holds back the requests of a plugin until the plugin reports that it's ready with `open`,
+/// see `AFPlugin::startup_gate`. At most `capacity` requests are held, the requests after them
+/// are rejected with `AFPluginNotReadyReason::QueueFull` and can be sent again later.
+#[derive(Clone)]
+pub struct AFPluginStartupGate {
+    capacity: usize,
+    inner: Arc<Mutex<StartupGateInner>>,
+}
+
+#[derive(Default)]
+struct StartupGateInner {
+    is_ready: bool,
+    /// Set while `open` replays the held requests, so a second `open` doesn't replay them too.
+    is_opening: bool,
+    generation: u64,
+    queue: VecDeque<QueuedRequest>,
+}
+
+struct QueuedRequest {
+    generation: Option<u64>,
+    admission: oneshot::Sender<Admission>,
+}
+
+pub(crate) enum Admission {
+    /// The request is handled now. The gate replays the next request once it's dropped.
+    Run(oneshot::Sender<()>),
+    /// The request was sent for an older generation, it's dropped without being handled.
+    Stale,
+}
+
+pub(crate) enum GateEntry {
+    Open,
+    Queued(oneshot::Receiver<Admission>),
+    Rejected,
+}
+
+impl AFPluginStartupGate {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Arc::new(Mutex::new(StartupGateInner::default())),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.inner.lock().unwrap().is_ready
+    }
+
+    /// Returns the number of the requests waiting for the gate to open.
+    pub fn num_of_queued(&self) -> usize {
+        self.inner.lock().unwrap().queue.len()
+    }
+
+    /// Moves the gate to the `generation`. The held requests sent for an older generation are
+    /// dropped instead of replayed, e.g. the request to open a view that the user already left.
+    pub fn advance_generation(&self, generation: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.generation = inner.generation.max(generation);
+    }
+
+    /// Replays the held requests one by one in the order they arrived, then lets the requests
+    /// through. The requests that arrive while replaying are held and replayed too.
+    ///
+    /// Returns false right away if the gate is already open or another `open` is replaying.
+    pub async fn open(&self) -> bool {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.is_ready || inner.is_opening {
+                return false;
+            }
+            inner.is_opening = true;
+        }
+
+        loop {
+            let (request, is_stale) = {
+                let mut inner = self.inner.lock().unwrap();
+                match inner.queue.pop_front() {
+                    None => {
+                        inner.is_ready = true;
+                        inner.is_opening = false;
+                        return true;
+                    }
+                    Some(request) => {
+                        let is_stale = matches!(request.generation, Some(generation) if generation < inner.generation);
+                        (request, is_stale)
+                    }
+                }
+            };
+
+            if is_stale {
+                let _ = request.admission.send(Admission::Stale);
+                continue;
+            }
+            let (done_tx, done_rx) = oneshot::channel();
+            if request.admission.send(Admission::Run(done_tx)).is_ok() {
+                let _ = done_rx.await;
+            }
+        }
+    }
+
+    pub(crate) fn enter(&self, generation: Option<u64>) -> GateEntry {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.is_ready {
+            return GateEntry::Open;
+        }
+        if inner.queue.len() >= self.capacity {
+            return GateEntry::Rejected;
+        }
+        let (admission, rx) = oneshot::channel();
+        inner.queue.push_back(QueuedRequest { generation, admission });
+        GateEntry::Queued(rx)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AFPluginNotReadyReason {
+    /// The plugin isn't ready and already holds as many requests as it can. The request can be
+    /// sent again later.
+    QueueFull,
+    /// The request was held until the plugin got ready, then dropped because it was sent for an
+    /// older generation.
+    Stale,
+}
+
+/// The error of the request that the startup gate didn't let through. The plugin turns it into
+/// its own error type, see `AFPlugin::not_ready_error`.
+#[derive(Clone, Debug)]
+pub struct AFPluginNotReady {
+    pub event: AFPluginEvent,
+    pub reason: AFPluginNotReadyReason,
+}
+
+impl fmt::Display for AFPluginNotReady {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            AFPluginNotReadyReason::QueueFull => write!(f, "{:?} was sent before the plugin is ready", self.event),
+            AFPluginNotReadyReason::Stale => write!(f, "{:?} is stale", self.event),
+        }
+    }
+}
+
+impl Error for AFPluginNotReady {
+    fn as_response(&self) -> AFPluginEventResponse {
+        ResponseBuilder::Err().data(format!("{}", self).into_bytes()).build()
+    }
+}
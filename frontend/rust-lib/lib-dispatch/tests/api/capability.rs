@@ -0,0 +1,44 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::tokio_default_runtime;
+use std::sync::Arc;
+
+pub async fn purge() -> String {
+    "purged".to_string()
+}
+
+pub async fn hello() -> String {
+    "say hello".to_string()
+}
+
+fn make_dispatcher() -> Arc<AFPluginDispatcher> {
+    let runtime = tokio_default_runtime().unwrap();
+    Arc::new(AFPluginDispatcher::construct(runtime, || {
+        vec![AFPlugin::new()
+            .event_with_capability("purge", AFPluginCapability::Maintenance, purge)
+            .event("hello", hello)]
+    }))
+}
+
+#[test]
+fn capability_required_test() {
+    let dispatcher = make_dispatcher();
+    let response = AFPluginDispatcher::sync_send(dispatcher.clone(), AFPluginRequest::new("purge"));
+    assert_eq!(response.status_code, StatusCode::Err);
+
+    let token = dispatcher.mint_capability_token(&[AFPluginCapability::Read, AFPluginCapability::Write]);
+    let request = AFPluginRequest::new("purge").capability_token(token);
+    let response = AFPluginDispatcher::sync_send(dispatcher.clone(), request);
+    assert_eq!(response.status_code, StatusCode::Err);
+
+    let token = dispatcher.mint_capability_token(&[AFPluginCapability::Maintenance]);
+    let request = AFPluginRequest::new("purge").capability_token(token);
+    let response = AFPluginDispatcher::sync_send(dispatcher, request);
+    assert_eq!(response.status_code, StatusCode::Ok);
+}
+
+#[test]
+fn capability_not_required_test() {
+    let dispatcher = make_dispatcher();
+    let response = AFPluginDispatcher::sync_send(dispatcher, AFPluginRequest::new("hello"));
+    assert_eq!(response.status_code, StatusCode::Ok);
+}
@@ -1 +1,3 @@
+mod capability;
 mod module;
+mod startup_gate;
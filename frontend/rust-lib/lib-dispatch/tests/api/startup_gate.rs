@@ -0,0 +1,156 @@
+use lib_dispatch::prelude::*;
+use lib_dispatch::runtime::tokio_default_runtime;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type EventLog = Arc<Mutex<Vec<&'static str>>>;
+
+pub async fn open(log: AFPluginState<EventLog>) -> String {
+    log.lock().unwrap().push("open");
+    "opened".to_string()
+}
+
+pub async fn edit(log: AFPluginState<EventLog>) -> String {
+    log.lock().unwrap().push("edit");
+    "edited".to_string()
+}
+
+pub async fn slow_edit(log: AFPluginState<EventLog>) -> String {
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    log.lock().unwrap().push("slow_edit");
+    "edited".to_string()
+}
+
+pub async fn close(log: AFPluginState<EventLog>) -> String {
+    log.lock().unwrap().push("close");
+    "closed".to_string()
+}
+
+fn make_dispatcher(gate: &AFPluginStartupGate, log: &EventLog) -> Arc<AFPluginDispatcher> {
+    let runtime = tokio_default_runtime().unwrap();
+    let gate = gate.clone();
+    let log = log.clone();
+    Arc::new(AFPluginDispatcher::construct(runtime, || {
+        vec![AFPlugin::new()
+            .state(log)
+            .startup_gate(gate)
+            .event("open", open)
+            .event("edit", edit)
+            .event("slow_edit", slow_edit)
+            .event("close", close)]
+    }))
+}
+
+/// Sends the request and waits until the gate holds it, so the requests are held in the order
+/// they're sent.
+async fn send_held(
+    dispatcher: &Arc<AFPluginDispatcher>,
+    gate: &AFPluginStartupGate,
+    request: AFPluginRequest,
+) -> tokio::task::JoinHandle<AFPluginEventResponse> {
+    let num_of_queued = gate.num_of_queued();
+    let response = tokio::spawn(AFPluginDispatcher::async_send(dispatcher.clone(), request));
+    while gate.num_of_queued() == num_of_queued {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    response
+}
+
+#[tokio::test]
+async fn startup_gate_replay_in_order_test() {
+    let gate = AFPluginStartupGate::new(10);
+    let log = EventLog::default();
+    let dispatcher = make_dispatcher(&gate, &log);
+
+    let mut responses = vec![];
+    for event in ["open", "edit", "close"] {
+        responses.push(send_held(&dispatcher, &gate, AFPluginRequest::new(event)).await);
+    }
+    // The slow initialization, nothing is handled until the gate opens.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(log.lock().unwrap().is_empty());
+
+    gate.open().await;
+    assert!(gate.is_ready());
+    for response in responses {
+        assert_eq!(response.await.unwrap().status_code, StatusCode::Ok);
+    }
+    assert_eq!(*log.lock().unwrap(), vec!["open", "edit", "close"]);
+
+    // The requests after the gate opened are handled right away.
+    let response = AFPluginDispatcher::async_send(dispatcher.clone(), AFPluginRequest::new("edit")).await;
+    assert_eq!(response.status_code, StatusCode::Ok);
+    assert_eq!(log.lock().unwrap().len(), 4);
+
+    // Dropping the dispatcher's runtime inside the test's runtime panics.
+    std::mem::forget(dispatcher);
+}
+
+#[tokio::test]
+async fn startup_gate_open_twice_test() {
+    let gate = AFPluginStartupGate::new(10);
+    let log = EventLog::default();
+    let dispatcher = make_dispatcher(&gate, &log);
+
+    let mut responses = vec![];
+    for event in ["slow_edit", "edit", "close"] {
+        responses.push(send_held(&dispatcher, &gate, AFPluginRequest::new(event)).await);
+    }
+
+    // The second open doesn't replay the held requests alongside the first one.
+    let first = tokio::spawn({
+        let gate = gate.clone();
+        async move { gate.open().await }
+    });
+    while gate.num_of_queued() == 3 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(!gate.open().await);
+    assert!(first.await.unwrap());
+    for response in responses {
+        assert_eq!(response.await.unwrap().status_code, StatusCode::Ok);
+    }
+    assert_eq!(*log.lock().unwrap(), vec!["slow_edit", "edit", "close"]);
+    assert!(!gate.open().await);
+    std::mem::forget(dispatcher);
+}
+
+#[tokio::test]
+async fn startup_gate_overflow_test() {
+    let gate = AFPluginStartupGate::new(2);
+    let log = EventLog::default();
+    let dispatcher = make_dispatcher(&gate, &log);
+
+    let first = send_held(&dispatcher, &gate, AFPluginRequest::new("open")).await;
+    let second = send_held(&dispatcher, &gate, AFPluginRequest::new("edit")).await;
+
+    // The gate is full, the request is rejected without waiting for the gate.
+    let response = AFPluginDispatcher::async_send(dispatcher.clone(), AFPluginRequest::new("close")).await;
+    assert_eq!(response.status_code, StatusCode::Err);
+    assert_eq!(gate.num_of_queued(), 2);
+
+    gate.open().await;
+    assert_eq!(first.await.unwrap().status_code, StatusCode::Ok);
+    assert_eq!(second.await.unwrap().status_code, StatusCode::Ok);
+    assert_eq!(*log.lock().unwrap(), vec!["open", "edit"]);
+    std::mem::forget(dispatcher);
+}
+
+#[tokio::test]
+async fn startup_gate_drop_stale_test() {
+    let gate = AFPluginStartupGate::new(10);
+    let log = EventLog::default();
+    let dispatcher = make_dispatcher(&gate, &log);
+
+    let stale = send_held(&dispatcher, &gate, AFPluginRequest::new("open").generation(1)).await;
+    let current = send_held(&dispatcher, &gate, AFPluginRequest::new("edit").generation(2)).await;
+    let untracked = send_held(&dispatcher, &gate, AFPluginRequest::new("close")).await;
+    gate.advance_generation(2);
+
+    gate.open().await;
+    assert_eq!(stale.await.unwrap().status_code, StatusCode::Err);
+    assert_eq!(current.await.unwrap().status_code, StatusCode::Ok);
+    assert_eq!(untracked.await.unwrap().status_code, StatusCode::Ok);
+    assert_eq!(*log.lock().unwrap(), vec!["edit", "close"]);
+    std::mem::forget(dispatcher);
+}
@@ -3,11 +3,70 @@ use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use serde_repr::*;
 
+/// The version of the protocol that the client and the server advertise in their handshakes.
+pub const WS_PROTOCOL_VERSION: u32 = 2;
+
+/// The version of the peers that don't send a handshake. They only know the frames of
+/// `WSCapabilities::LEGACY`.
+pub const LEGACY_WS_PROTOCOL_VERSION: u32 = 1;
+
+/// The optional features of the protocol. Each side advertises the features it supports in its
+/// handshake, a feature is only used when both sides support it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[serde(transparent)]
+pub struct WSCapabilities(pub u32);
+
+impl WSCapabilities {
+    /// The features of the peers that don't send a handshake.
+    pub const LEGACY: WSCapabilities = WSCapabilities(0);
+    /// More than one revision may be in flight, see `ClientRevisionWSData::from_revisions`.
+    /// Without it, the next revision is only pushed after the previous one was acked.
+    pub const FLOW_CONTROL: WSCapabilities = WSCapabilities(1 << 0);
+    /// The frames that can't be handled are answered with an unsupported frame, see `WSUnsupported`.
+    pub const UNSUPPORTED_FRAME: WSCapabilities = WSCapabilities(1 << 1);
+    pub const ALL: WSCapabilities = WSCapabilities(Self::FLOW_CONTROL.0 | Self::UNSUPPORTED_FRAME.0);
+
+    pub fn contains(&self, other: WSCapabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersection(&self, other: WSCapabilities) -> WSCapabilities {
+        WSCapabilities(self.0 & other.0)
+    }
+}
+
+/// Sent by the client with its first ping on each connection, the server that knows it answers
+/// with its own handshake. The older servers ignore the unknown field and answer the ping.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct WSHandshake {
+    pub version: u32,
+    pub capabilities: WSCapabilities,
+}
+
+impl WSHandshake {
+    pub fn new(capabilities: WSCapabilities) -> Self {
+        Self {
+            version: WS_PROTOCOL_VERSION,
+            capabilities,
+        }
+    }
+}
+
+/// Tells the peer that a frame it sent was dropped, instead of closing the connection.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct WSUnsupported {
+    /// The type of the dropped frame.
+    pub ty: String,
+    pub reason: String,
+}
+
 #[derive(Debug, Clone, Serialize_repr, Deserialize_repr, Eq, PartialEq, Hash)]
 #[repr(u8)]
 pub enum ClientRevisionWSDataType {
     ClientPushRev = 0,
     ClientPing = 1,
+    /// Only sent when `WSCapabilities::UNSUPPORTED_FRAME` was negotiated.
+    ClientUnsupported = 2,
 }
 
 impl Default for ClientRevisionWSDataType {
@@ -22,6 +81,10 @@ pub struct ClientRevisionWSData {
     pub ty: ClientRevisionWSDataType,
     pub revisions: Vec<Revision>,
     pub rev_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handshake: Option<WSHandshake>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unsupported: Option<WSUnsupported>,
 }
 
 impl ClientRevisionWSData {
@@ -36,6 +99,8 @@ impl ClientRevisionWSData {
             ty: ClientRevisionWSDataType::ClientPushRev,
             revisions,
             rev_id,
+            handshake: None,
+            unsupported: None,
         }
     }
 
@@ -45,6 +110,27 @@ impl ClientRevisionWSData {
             ty: ClientRevisionWSDataType::ClientPing,
             revisions: vec![],
             rev_id,
+            handshake: None,
+            unsupported: None,
+        }
+    }
+
+    /// A ping that carries the handshake, see `WSHandshake`.
+    pub fn handshake(object_id: &str, rev_id: i64, handshake: WSHandshake) -> Self {
+        Self {
+            handshake: Some(handshake),
+            ..Self::ping(object_id, rev_id)
+        }
+    }
+
+    pub fn unsupported(object_id: &str, rev_id: i64, unsupported: WSUnsupported) -> Self {
+        Self {
+            object_id: object_id.to_owned(),
+            ty: ClientRevisionWSDataType::ClientUnsupported,
+            revisions: vec![],
+            rev_id,
+            handshake: None,
+            unsupported: Some(unsupported),
         }
     }
 }
@@ -67,10 +153,30 @@ impl std::convert::TryFrom<ClientRevisionWSData> for Bytes {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WSRevisionPayload {
-    ServerAck { rev_id: i64 },
-    ServerPushRev { revisions: Vec<Revision> },
-    ServerPullRev { range: RevisionRange },
-    UserConnect { user: NewDocumentUser },
+    ServerAck {
+        rev_id: i64,
+    },
+    ServerPushRev {
+        revisions: Vec<Revision>,
+    },
+    ServerPullRev {
+        range: RevisionRange,
+    },
+    UserConnect {
+        user: NewDocumentUser,
+    },
+    ServerHandshake {
+        handshake: WSHandshake,
+    },
+    /// Only sent when `WSCapabilities::UNSUPPORTED_FRAME` was negotiated.
+    ServerUnsupported {
+        unsupported: WSUnsupported,
+    },
+    /// The payload that this version doesn't know, it's never sent.
+    #[serde(skip)]
+    Unknown {
+        ty: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -79,11 +185,29 @@ pub struct ServerRevisionWSData {
     pub payload: WSRevisionPayload,
 }
 
+/// Reads the payload of an unknown type as `WSRevisionPayload::Unknown`, so the frames of the
+/// newer servers are answered instead of failing the connection.
 impl std::convert::TryFrom<Bytes> for ServerRevisionWSData {
     type Error = serde_json::Error;
 
     fn try_from(bytes: Bytes) -> Result<Self, Self::Error> {
-        serde_json::from_slice(&bytes)
+        #[derive(Deserialize)]
+        struct UnknownServerRevisionWSData {
+            object_id: String,
+            payload: serde_json::Map<String, serde_json::Value>,
+        }
+
+        serde_json::from_slice(&bytes).or_else(|error| {
+            match serde_json::from_slice::<UnknownServerRevisionWSData>(&bytes) {
+                Ok(data) if data.payload.len() == 1 => Ok(ServerRevisionWSData {
+                    object_id: data.object_id,
+                    payload: WSRevisionPayload::Unknown {
+                        ty: data.payload.keys().next().cloned().unwrap_or_default(),
+                    },
+                }),
+                _ => Err(error),
+            }
+        })
     }
 }
 
@@ -117,6 +241,13 @@ impl ServerRevisionWSDataBuilder {
             payload: WSRevisionPayload::ServerAck { rev_id },
         }
     }
+
+    pub fn build_handshake_message(object_id: &str, handshake: WSHandshake) -> ServerRevisionWSData {
+        ServerRevisionWSData {
+            object_id: object_id.to_string(),
+            payload: WSRevisionPayload::ServerHandshake { handshake },
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
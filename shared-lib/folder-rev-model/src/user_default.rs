@@ -58,5 +58,6 @@ fn create_default_view(app_id: String, time: chrono::DateTime<Utc>) -> ViewRevis
         ext_data: "".to_string(),
         thumbnail: "".to_string(),
         layout: ViewLayoutTypeRevision::Document,
+        is_scratch: false,
     }
 }
@@ -39,6 +39,11 @@ pub struct ViewRevision {
     #[serde(default = "DEFAULT_PLUGIN_TYPE")]
     #[serde(rename = "plugin_type")]
     pub layout: ViewLayoutTypeRevision,
+
+    /// A scratch view is kept on this device only, neither the view nor its data is sent to
+    /// the server.
+    #[serde(default)]
+    pub is_scratch: bool,
 }
 const DEFAULT_PLUGIN_TYPE: fn() -> ViewLayoutTypeRevision = || ViewLayoutTypeRevision::Document;
 
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// The timezone the analytics are bucketed into days with when none is set.
+pub const DEFAULT_BUCKET_TIMEZONE: &str = "UTC";
+
+/// Maps instants to the days of a timezone, so that the daily aggregates follow the days of the
+/// user instead of the UTC days. A day isn't always 24 hours long: the day the DST starts is 23
+/// hours and the day it ends is 25 hours in most timezones, `day_bounds` returns the real bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BucketClock {
+    tz: Tz,
+}
+
+impl std::default::Default for BucketClock {
+    fn default() -> Self {
+        Self { tz: Tz::UTC }
+    }
+}
+
+impl BucketClock {
+    /// Returns None if the `timezone` isn't an IANA name, e.g. `Europe/Berlin`.
+    pub fn parse(timezone: &str) -> Option<Self> {
+        timezone.parse::<Tz>().ok().map(|tz| Self { tz })
+    }
+
+    /// The IANA name of the timezone.
+    pub fn timezone(&self) -> &'static str {
+        self.tz.name()
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.day_of(Utc::now())
+    }
+
+    /// The day the `instant` falls in.
+    pub fn day_of(&self, instant: DateTime<Utc>) -> NaiveDate {
+        instant.with_timezone(&self.tz).naive_local().date()
+    }
+
+    /// The first instant of the `day` and the first instant of the day after it.
+    pub fn day_bounds(&self, day: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+        (self.start_of(day), self.start_of(day.succ()))
+    }
+
+    pub fn day_len(&self, day: NaiveDate) -> Duration {
+        let (start, end) = self.day_bounds(day);
+        end - start
+    }
+
+    /// Splits the `[start, end)` interval by the days it overlaps, in ascending order. The
+    /// durations add up to the length of the interval.
+    pub fn split(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<(NaiveDate, Duration)> {
+        let mut parts = vec![];
+        let mut day = self.day_of(start);
+        let mut from = start;
+        while from < end {
+            let to = self.start_of(day.succ()).min(end);
+            if to > from {
+                parts.push((day, to - from));
+            }
+            from = to;
+            day = day.succ();
+        }
+        parts
+    }
+
+    /// Splits the `day` of the `from` clock by the days of this clock, see `split`.
+    ///
+    /// The value counted for a whole day, e.g. its usage, can only be spread over the new days
+    /// evenly by the hours they overlap with it, when it happened within the day is unknown. So
+    /// rebucketing loses the shape of the days: rebucketing back to the `from` clock doesn't
+    /// restore the original days, and rebucketing back and forth blurs them a bit more each
+    /// time. The totals are kept.
+    pub fn rebucket(&self, from: &BucketClock, day: NaiveDate) -> Vec<(NaiveDate, Duration)> {
+        let (start, end) = from.day_bounds(day);
+        self.split(start, end)
+    }
+
+    /// The midnight of the `day`. If the clocks skip the midnight, which some timezones do when
+    /// the DST starts, the day starts at the first local time after the gap. If the midnight
+    /// happens twice, the day starts at the first one.
+    fn start_of(&self, day: NaiveDate) -> DateTime<Utc> {
+        let midnight = day.and_hms(0, 0, 0);
+        for minutes in (0..=180).step_by(15) {
+            let local = midnight + Duration::minutes(minutes);
+            match self.tz.from_local_datetime(&local) {
+                LocalResult::Single(start) | LocalResult::Ambiguous(start, _) => return start.with_timezone(&Utc),
+                LocalResult::None => continue,
+            }
+        }
+        // No timezone skips more than three hours.
+        Utc.from_utc_datetime(&midnight)
+    }
+}
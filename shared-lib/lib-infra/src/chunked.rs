@@ -0,0 +1,122 @@
+use std::io;
+
+/// A byte buffer made of chunks of at most `chunk_size` bytes. Writing megabytes into it never
+/// reallocates and copies what was written before, unlike a `Vec<u8>` that keeps doubling.
+#[derive(Debug, Clone)]
+pub struct ChunkedBuffer {
+    chunk_size: usize,
+    chunks: Vec<Vec<u8>>,
+    len: usize,
+}
+
+impl ChunkedBuffer {
+    pub fn new(chunk_size: usize) -> Self {
+        debug_assert!(chunk_size > 0);
+        Self {
+            chunk_size: chunk_size.max(1),
+            chunks: vec![],
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn chunks(&self) -> &[Vec<u8>] {
+        &self.chunks
+    }
+
+    /// Copies the chunks into one buffer.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.len);
+        for chunk in self.chunks.iter() {
+            bytes.extend_from_slice(chunk);
+        }
+        bytes
+    }
+
+    pub fn into_reader(self) -> ChunkedReader {
+        ChunkedReader {
+            remaining: self.len,
+            chunks: self.chunks,
+            chunk_index: 0,
+            offset: 0,
+        }
+    }
+}
+
+impl io::Write for ChunkedBuffer {
+    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize> {
+        let written = buf.len();
+        while !buf.is_empty() {
+            let chunk = match self.chunks.last_mut() {
+                Some(chunk) if chunk.len() < self.chunk_size => chunk,
+                _ => {
+                    self.chunks.push(Vec::with_capacity(self.chunk_size));
+                    self.chunks.last_mut().unwrap()
+                }
+            };
+            let n = (self.chunk_size - chunk.len()).min(buf.len());
+            chunk.extend_from_slice(&buf[..n]);
+            buf = &buf[n..];
+        }
+        self.len += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads a `ChunkedBuffer` from the start into the buffers of the caller, e.g. across the FFI
+/// where the caller owns the memory.
+#[derive(Debug)]
+pub struct ChunkedReader {
+    chunks: Vec<Vec<u8>>,
+    chunk_index: usize,
+    offset: usize,
+    remaining: usize,
+}
+
+impl ChunkedReader {
+    /// Copies the next bytes into `buf`. Returns the number of the copied bytes, which is less
+    /// than the length of `buf` only at the end. Returns 0 once everything was read.
+    pub fn read_chunk(&mut self, buf: &mut [u8]) -> usize {
+        let mut written = 0;
+        while written < buf.len() {
+            let chunk = match self.chunks.get(self.chunk_index) {
+                None => break,
+                Some(chunk) => chunk,
+            };
+            let n = (chunk.len() - self.offset).min(buf.len() - written);
+            buf[written..written + n].copy_from_slice(&chunk[self.offset..self.offset + n]);
+            written += n;
+            self.offset += n;
+            if self.offset == chunk.len() {
+                // The chunk is freed as soon as it's read.
+                self.chunks[self.chunk_index] = vec![];
+                self.chunk_index += 1;
+                self.offset = 0;
+            }
+        }
+        self.remaining -= written;
+        written
+    }
+
+    /// The number of the bytes that weren't read yet.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl io::Read for ChunkedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.read_chunk(buf))
+    }
+}
@@ -1,3 +1,5 @@
+pub mod bucket_clock;
+pub mod chunked;
 pub mod future;
 pub mod ref_map;
 pub mod retry;
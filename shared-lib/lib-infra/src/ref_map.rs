@@ -42,6 +42,10 @@ where
         self.0.get(key).map(|handler| handler.inner.clone())
     }
 
+    pub fn keys(&self) -> Vec<String> {
+        self.0.keys().cloned().collect::<Vec<String>>()
+    }
+
     pub fn values(&self) -> Vec<T> {
         self.0.values().map(|value| value.inner.clone()).collect::<Vec<T>>()
     }
@@ -0,0 +1,264 @@
+use crate::core::AttributeHashMap;
+use crate::text_delta::{
+    line_segments, BuildInTextAttribute, BuildInTextAttributeKey, CustomAttributes, DeltaTextOperationBuilder,
+    DeltaTextOperations, MAX_LIST_INDENT,
+};
+use std::fmt::{self, Write};
+
+/// Encodes the lines of the [DeltaTextOperations] to markdown, keeping the nesting of the list
+/// lines. Each nested level is indented by the width of its parent's list marker, that is two
+/// spaces for bullets and check lists and three spaces for ordered lists.
+///
+/// Only the block structure is encoded, the inline attributes are dropped.
+///
+/// # Examples
+///
+/// ```
+///  use lib_ot::codec::markdown::markdown_list::encode_markdown_list;
+///  use lib_ot::text_delta::DeltaTextOperations;
+///  let json = r#"[{"insert":"a"},{"insert":"\n","attributes":{"list":"bullet"}},{"insert":"b"},{"insert":"\n","attributes":{"list":"bullet","indent":1}}]"#;
+///  let operations = DeltaTextOperations::from_json(json).unwrap();
+///  assert_eq!(encode_markdown_list(&operations), "* a\n  * b\n");
+/// ```
+pub fn encode_markdown_list(operations: &DeltaTextOperations) -> String {
+    encode_markdown_list_with_wrap(operations, None)
+}
+
+/// Same as [encode_markdown_list], but the lines longer than `wrap_width` chars are hard-wrapped
+/// if it's not None. The wrapped part of a list line is indented to the text of its first line.
+/// The lines are written verbatim otherwise, however long they are.
+pub fn encode_markdown_list_with_wrap(operations: &DeltaTextOperations, wrap_width: Option<usize>) -> String {
+    encode_markdown_list_with_custom_attributes(operations, wrap_width, &CustomAttributes::default())
+}
+
+/// Same as [encode_markdown_list_with_wrap], but the text that has the registered custom
+/// attributes is put in a `span` that keeps their values, e.g.
+/// `<span data-comment_id="c1">abc</span>`. The lines are never wrapped inside the tags.
+pub fn encode_markdown_list_with_custom_attributes(
+    operations: &DeltaTextOperations,
+    wrap_width: Option<usize>,
+    custom_attributes: &CustomAttributes,
+) -> String {
+    let mut markdown = String::new();
+    // Writing to a String never fails.
+    let _ = write_markdown_list(operations, wrap_width, custom_attributes, &mut markdown);
+    markdown
+}
+
+/// Same as [encode_markdown_list_with_custom_attributes], but the markdown is written to the
+/// `markdown` as the lines are read. The text is written segment by segment, so a long line is
+/// never held in memory, see [line_segments].
+pub fn write_markdown_list<W: Write>(
+    operations: &DeltaTextOperations,
+    wrap_width: Option<usize>,
+    custom_attributes: &CustomAttributes,
+    markdown: &mut W,
+) -> fmt::Result {
+    let mut marker_widths: Vec<usize> = vec![];
+    let mut segments = line_segments(operations).peekable();
+    while segments.peek().is_some() {
+        // The list marker is decided by the attributes of the newline, so they're looked up
+        // ahead before the text of the line is written. The last line may have no newline.
+        let column = match segments.clone().find(|segment| segment.ends_line) {
+            None => 0,
+            Some(newline) => write_list_marker(markdown, newline.attributes, &mut marker_widths)?,
+        };
+
+        let mut line = MarkdownLine::new(column, wrap_width);
+        for segment in segments.by_ref() {
+            line.write(
+                markdown,
+                segment.text,
+                custom_span(segment.attributes, custom_attributes),
+            )?;
+            if segment.ends_line {
+                break;
+            }
+        }
+        line.close_span(markdown)?;
+        markdown.write_char('\n')?;
+    }
+    Ok(())
+}
+
+/// Writes the text of a line, it's broken every `wrap_width` chars if it's not None and each
+/// break is followed by `column` spaces. The chars of the tags aren't counted and the text isn't
+/// broken inside them.
+struct MarkdownLine {
+    column: usize,
+    wrap_width: Option<usize>,
+    num_of_chars: usize,
+    open_span: Option<String>,
+}
+
+impl MarkdownLine {
+    fn new(column: usize, wrap_width: Option<usize>) -> Self {
+        Self {
+            column,
+            wrap_width: wrap_width.filter(|wrap_width| *wrap_width > 0),
+            num_of_chars: 0,
+            open_span: None,
+        }
+    }
+
+    fn write<W: Write>(&mut self, markdown: &mut W, text: &str, span: Option<String>) -> fmt::Result {
+        if text.is_empty() {
+            return Ok(());
+        }
+        if span != self.open_span {
+            self.close_span(markdown)?;
+            if let Some(span) = span {
+                markdown.write_str(&span)?;
+                self.open_span = Some(span);
+            }
+        }
+        self.write_text(markdown, text)
+    }
+
+    fn write_text<W: Write>(&mut self, markdown: &mut W, text: &str) -> fmt::Result {
+        let wrap_width = match self.wrap_width {
+            None => return markdown.write_str(text),
+            Some(wrap_width) => wrap_width,
+        };
+
+        let mut start = 0;
+        for (offset, _) in text.char_indices() {
+            if self.num_of_chars > 0 && self.num_of_chars % wrap_width == 0 {
+                markdown.write_str(&text[start..offset])?;
+                write!(markdown, "\n{:width$}", "", width = self.column)?;
+                start = offset;
+            }
+            self.num_of_chars += 1;
+        }
+        markdown.write_str(&text[start..])
+    }
+
+    fn close_span<W: Write>(&mut self, markdown: &mut W) -> fmt::Result {
+        if self.open_span.take().is_some() {
+            markdown.write_str("</span>")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the opening tag of the span of the registered custom attributes, None if the
+/// attributes have none of them.
+fn custom_span(attributes: &AttributeHashMap, custom_attributes: &CustomAttributes) -> Option<String> {
+    if custom_attributes.is_empty() {
+        return None;
+    }
+    let mut span = String::new();
+    for (key, value) in attributes.iter() {
+        if let (Some(_), Some(value)) = (custom_attributes.semantics(key), value.value.as_ref()) {
+            span.push_str(&format!(" data-{}=\"{}\"", key, escape_attribute_value(value)));
+        }
+    }
+    if span.is_empty() {
+        None
+    } else {
+        Some(format!("<span{}>", span))
+    }
+}
+
+fn escape_attribute_value(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Decodes the markdown generated by [encode_markdown_list]. The nesting level of each list line
+/// is stored in the [BuildInTextAttribute::Indent] attribute of its newline.
+pub fn decode_markdown_list(markdown: &str) -> DeltaTextOperations {
+    let mut builder = DeltaTextOperationBuilder::new();
+    let mut columns: Vec<usize> = vec![];
+    for line in markdown.lines() {
+        let trimmed_line = line.trim_start_matches(' ');
+        let column = line.len() - trimmed_line.len();
+        match parse_list_marker(trimmed_line) {
+            None => {
+                columns.clear();
+                builder = builder.insert(line).insert("\n");
+            }
+            Some((list, text)) => {
+                while matches!(columns.last(), Some(last) if *last > column) {
+                    columns.pop();
+                }
+                if columns.last() != Some(&column) {
+                    columns.push(column);
+                }
+
+                let mut attributes = AttributeHashMap::from(BuildInTextAttribute::List(list));
+                let indent = (columns.len() - 1).min(MAX_LIST_INDENT);
+                if indent > 0 {
+                    attributes.insert_entry(BuildInTextAttribute::Indent(indent));
+                }
+                builder = builder.insert(text).insert_with_attributes("\n", attributes);
+            }
+        }
+    }
+    builder.build()
+}
+
+/// Writes the marker of the line if it's a list line and returns the column of its text.
+fn write_list_marker<W: Write>(
+    markdown: &mut W,
+    attributes: &AttributeHashMap,
+    marker_widths: &mut Vec<usize>,
+) -> Result<usize, fmt::Error> {
+    let list = attributes
+        .get(BuildInTextAttributeKey::List.as_ref())
+        .and_then(|value| value.str_value());
+
+    match list {
+        None => {
+            marker_widths.clear();
+            Ok(0)
+        }
+        Some(list) => {
+            let indent = attributes
+                .get(BuildInTextAttributeKey::Indent.as_ref())
+                .and_then(|value| value.int_value())
+                .unwrap_or(0)
+                .clamp(0, MAX_LIST_INDENT as i64) as usize;
+
+            // Missing parent levels are treated as bullets.
+            marker_widths.resize(indent, 2);
+            let marker = list_marker(&list);
+            let parent_width: usize = marker_widths.iter().sum();
+            write!(markdown, "{:width$}{}", "", marker, width = parent_width)?;
+            marker_widths.push(if marker.starts_with("1.") { 3 } else { 2 });
+            Ok(marker_widths.iter().sum())
+        }
+    }
+}
+
+fn list_marker(list: &str) -> &'static str {
+    match list {
+        "ordered" => "1. ",
+        "checked" => "- [x] ",
+        "unchecked" => "- [ ] ",
+        _ => "* ",
+    }
+}
+
+fn parse_list_marker(s: &str) -> Option<(&'static str, &str)> {
+    if let Some(text) = s.strip_prefix("- [x] ") {
+        return Some(("checked", text));
+    }
+    if let Some(text) = s.strip_prefix("- [ ] ") {
+        return Some(("unchecked", text));
+    }
+    if let Some(text) = s.strip_prefix("* ").or_else(|| s.strip_prefix("- ")) {
+        return Some(("bullet", text));
+    }
+
+    let digits = s.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        if let Some(text) = s[digits..].strip_prefix(". ") {
+            return Some(("ordered", text));
+        }
+    }
+    None
+}
@@ -1 +1,2 @@
 // pub mod markdown_encoder;
+pub mod markdown_list;
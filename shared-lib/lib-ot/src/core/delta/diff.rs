@@ -0,0 +1,100 @@
+use crate::core::delta::operation::{DeltaOperation, OperationAttributes};
+use crate::core::delta::{trim, DeltaOperations};
+use std::fmt;
+
+/// The first difference between two deltas, see [DeltaOperations::assert_eq].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeltaDiff<T: OperationAttributes> {
+    /// The index of the differing operation in the normalized deltas.
+    pub index: usize,
+
+    /// The utf16 offset where the differing operation starts. The operations before it are
+    /// equal, so the offset is the same in both deltas.
+    pub offset: usize,
+
+    /// The operation of the left delta, None if the left delta has fewer operations.
+    pub left: Option<DeltaOperation<T>>,
+
+    /// The operation of the right delta, None if the right delta has fewer operations.
+    pub right: Option<DeltaOperation<T>>,
+}
+
+impl<T> fmt::Display for DeltaDiff<T>
+where
+    T: OperationAttributes,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format_op = |op: &Option<DeltaOperation<T>>| match op {
+            None => "None".to_owned(),
+            Some(op) => op.to_string(),
+        };
+        f.write_fmt(format_args!(
+            "The deltas differ at the operation {} (offset {}): {} != {}",
+            self.index,
+            self.offset,
+            format_op(&self.left),
+            format_op(&self.right)
+        ))
+    }
+}
+
+impl<T> DeltaOperations<T>
+where
+    T: OperationAttributes,
+{
+    /// Compares the deltas operation by operation. Unlike comparing their json, the order of
+    /// the attributes doesn't matter, and the adjacent inserts with the same attributes are
+    /// compared as one insert. Returns the first operation that differs otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///  use lib_ot::core::DeltaBuilder;
+    ///  let left = DeltaBuilder::new().insert("12").insert("3").build();
+    ///  let right = DeltaBuilder::new().insert("123").build();
+    ///  assert!(left.assert_eq(&right).is_ok());
+    ///
+    ///  let right = DeltaBuilder::new().insert("124").build();
+    ///  let diff = left.assert_eq(&right).unwrap_err();
+    ///  assert_eq!(diff.index, 0);
+    /// ```
+    pub fn assert_eq(&self, other: &Self) -> Result<(), DeltaDiff<T>> {
+        let left = self.normalized();
+        let right = other.normalized();
+        let mut offset = 0;
+        for index in 0..left.ops.len().max(right.ops.len()) {
+            let left_op = left.ops.get(index);
+            let right_op = right.ops.get(index);
+            if left_op != right_op {
+                return Err(DeltaDiff {
+                    index,
+                    offset,
+                    left: left_op.cloned(),
+                    right: right_op.cloned(),
+                });
+            }
+            offset += left_op.map(|op| op.len()).unwrap_or(0);
+        }
+        Ok(())
+    }
+
+    /// Returns the minimal delta that is equal to this one. The unset attributes of the inserts
+    /// are removed, an unset attribute of an insert is the same as no attribute. The operations
+    /// are added again so the adjacent ones get merged, and the trailing retain without
+    /// attributes is dropped since it doesn't change anything.
+    pub fn normalized(&self) -> Self {
+        let mut normalized: Self = self
+            .ops
+            .iter()
+            .cloned()
+            .map(|mut op| {
+                if let DeltaOperation::Insert(insert) = &mut op {
+                    insert.attributes.remove();
+                }
+                op
+            })
+            .collect();
+        trim(&mut normalized);
+        normalized
+    }
+}
@@ -1,15 +1,19 @@
 #![allow(clippy::module_inception)]
 mod builder;
 mod cursor;
+mod diff;
 mod iterator;
 pub mod operation;
 mod ops;
 mod ops_serde;
+mod transform_trace;
 
 pub use builder::*;
 pub use cursor::*;
+pub use diff::*;
 pub use iterator::*;
 pub use ops::*;
+pub use transform_trace::*;
 
 pub const NEW_LINE: &str = "\n";
 pub const WHITESPACE: &str = " ";
@@ -1,5 +1,6 @@
 use crate::core::delta::operation::{DeltaOperation, EmptyAttributes, OperationAttributes, OperationTransform};
-use crate::core::delta::{OperationIterator, MAX_IV_LEN};
+use crate::core::delta::transform_trace::{is_transform_traced, push_transform_step, trace_transform};
+use crate::core::delta::{OperationIterator, TransformAction, TransformAdvance, TransformStep, MAX_IV_LEN};
 use crate::core::interval::Interval;
 use crate::core::ot_str::OTString;
 use crate::core::DeltaOperationBuilder;
@@ -118,6 +119,14 @@ where
         }
     }
 
+    /// Returns the operations that delete the first `len` of the delta whose length is
+    /// `delta_len`: `delete(len)` followed by `retain(delta_len - len)`. The `len` exceeding
+    /// the delta deletes the whole delta.
+    pub fn delete_prefix(len: usize, delta_len: usize) -> Self {
+        let len = len.min(delta_len);
+        DeltaOperationBuilder::new().delete(len).retain(delta_len - len).build()
+    }
+
     /// Creating a [Insert] operation with string, [s].
     pub fn insert(&mut self, s: &str, attributes: T) {
         let s: OTString = s.into();
@@ -294,12 +303,151 @@ where
         other.ops.into_iter().for_each(|op| self.add(op));
     }
 
+    /// Return true if the delta only inserts at the end of the text it applies to, e.g.
+    /// `[retain(n), insert(s)]` or `[insert(s)]`. The [Retain] must not carry attributes.
+    pub fn is_append(&self) -> bool {
+        let inserts = match self.ops.as_slice() {
+            [DeltaOperation::Retain(retain), inserts @ ..] if retain.attributes.is_empty() => inserts,
+            inserts => inserts,
+        };
+        !inserts.is_empty() && inserts.iter().all(|op| op.is_insert())
+    }
+
+    /// Composes the [other] in place if it only appends to the end of this delta, which is
+    /// equivalent to `compose` without rebuilding the delta. Returns false and leaves the delta
+    /// untouched if the [other] is not an append.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///  use lib_ot::core::{DeltaBuilder, OperationTransform};
+    ///  let mut delta = DeltaBuilder::new().insert("hello").build();
+    ///  let append = DeltaBuilder::new().retain(5).insert(" world").build();
+    ///  let composed = delta.compose(&append).unwrap();
+    ///  assert!(delta.compose_append(&append));
+    ///  assert_eq!(delta, composed);
+    ///
+    ///  let replace = DeltaBuilder::new().delete(5).insert("hi").build();
+    ///  assert!(!delta.compose_append(&replace));
+    /// ```
+    pub fn compose_append(&mut self, other: &Self) -> bool {
+        if other.utf16_base_len != self.utf16_target_len || !other.is_append() {
+            return false;
+        }
+
+        for op in &other.ops {
+            if let DeltaOperation::Insert(insert) = op {
+                self.insert(&insert.s, insert.attributes.clone());
+            }
+        }
+        true
+    }
+
     /// Get the content that the [Delta] represents.
     pub fn content(&self) -> Result<String, OTError> {
         self.apply("")
     }
+
+    /// Returns a copy of the [Delta] that replaces the text of each [Insert] operation with a mask
+    /// of the same utf16 length. The operations, their attributes and the newlines are kept, so the
+    /// redacted delta can be logged to show the shape of a change without leaking its content.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///  use lib_ot::core::DeltaBuilder;
+    ///  let delta = DeltaBuilder::new().retain(3).insert("secret\n").delete(2).build();
+    ///  let redacted = delta.redacted();
+    ///  assert_eq!(redacted.ops.len(), delta.ops.len());
+    ///  assert_eq!(redacted.utf16_target_len, delta.utf16_target_len);
+    ///  assert_eq!(redacted.ops[1].get_data(), "******\n");
+    /// ```
+    pub fn redacted(&self) -> Self {
+        let ops = self
+            .ops
+            .iter()
+            .map(|op| match op {
+                DeltaOperation::Insert(insert) => {
+                    let mask = insert
+                        .s
+                        .chars()
+                        .map(|c| match c {
+                            '\n' => "\n".to_owned(),
+                            _ => REDACTED_MASK.repeat(c.len_utf16()),
+                        })
+                        .collect::<String>();
+                    DeltaOperation::insert_with_attributes(&mask, insert.attributes.clone())
+                }
+                _ => op.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            ops,
+            utf16_base_len: self.utf16_base_len,
+            utf16_target_len: self.utf16_target_len,
+        }
+    }
+
+    /// Returns a copy of the [Delta] that converts the `\r\n` and `\r` line endings of the [Insert]
+    /// operations to `\n`. The attributes are kept and the lengths are recomputed. A `\r\n` that
+    /// is split between two [Insert] operations becomes the `\n` of the second one, so the block
+    /// attributes that come with the newline stay on it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///  use lib_ot::core::DeltaBuilder;
+    ///  let delta = DeltaBuilder::new().insert("a\r\nb\rc\r").insert("\nd").build();
+    ///  let normalized = delta.normalize_line_endings();
+    ///  assert_eq!(normalized.content().unwrap(), "a\nb\nc\nd");
+    ///  assert_eq!(normalized.utf16_target_len, 8);
+    /// ```
+    pub fn normalize_line_endings(&self) -> Self {
+        let mut normalized = DeltaOperations::with_capacity(self.ops.len());
+        for (index, op) in self.ops.iter().enumerate() {
+            match op {
+                DeltaOperation::Insert(insert) => {
+                    let next_starts_with_newline = matches!(
+                        self.ops.get(index + 1),
+                        Some(DeltaOperation::Insert(next)) if next.s.starts_with('\n')
+                    );
+                    let mut s = insert.s.as_str();
+                    if next_starts_with_newline {
+                        s = s.strip_suffix('\r').unwrap_or(s);
+                    }
+                    let s = s.replace("\r\n", "\n").replace('\r', "\n");
+                    normalized.insert(&s, insert.attributes.clone());
+                }
+                DeltaOperation::Retain(retain) => normalized.retain(retain.n, retain.attributes.clone()),
+                DeltaOperation::Delete(n) => normalized.delete(*n),
+            }
+        }
+        normalized
+    }
+
+    /// Returns the operations in the `interval`, which is measured in utf16 code units. The
+    /// operations keep their attributes, an operation that crosses the bounds of the `interval`
+    /// is cut.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///  use lib_ot::core::{DeltaBuilder, Interval};
+    ///  let delta = DeltaBuilder::new().insert("123").insert("456").build();
+    ///  let slice = delta.slice(Interval::new(2, 4));
+    ///  assert_eq!(slice.content().unwrap(), "34");
+    /// ```
+    pub fn slice(&self, interval: Interval) -> Self {
+        OperationIterator::from_interval(self, interval)
+            .ops()
+            .into_iter()
+            .collect()
+    }
 }
 
+const REDACTED_MASK: &str = "*";
+
 impl<T> OperationTransform for DeltaOperations<T>
 where
     T: OperationAttributes,
@@ -387,12 +535,28 @@ where
                 (None, None) => break,
                 (Some(DeltaOperation::Insert(insert)), _) => {
                     // let composed_attrs = transform_attributes(&next_op1, &next_op2, true);
+                    record_step(&next_op1, &next_op2, || {
+                        (
+                            TransformAction::InsertLeft,
+                            insert.utf16_size(),
+                            TransformAdvance::Left,
+                            insert.attributes.clone(),
+                        )
+                    });
                     a_prime.insert(&insert.s, insert.attributes.clone());
                     b_prime.retain(insert.utf16_size(), insert.attributes.clone());
                     next_op1 = ops1.next();
                 }
                 (_, Some(DeltaOperation::Insert(o_insert))) => {
                     let composed_attrs = transform_op_attribute(&next_op1, &next_op2)?;
+                    record_step(&next_op1, &next_op2, || {
+                        (
+                            TransformAction::InsertRight,
+                            o_insert.utf16_size(),
+                            TransformAdvance::Right,
+                            composed_attrs.clone(),
+                        )
+                    });
                     a_prime.retain(o_insert.utf16_size(), composed_attrs.clone());
                     b_prime.insert(&o_insert.s, composed_attrs);
                     next_op2 = ops2.next();
@@ -405,6 +569,15 @@ where
                 }
                 (Some(DeltaOperation::Retain(retain)), Some(DeltaOperation::Retain(o_retain))) => {
                     let composed_attrs = transform_op_attribute(&next_op1, &next_op2)?;
+                    let len = min(retain.n, o_retain.n);
+                    record_step(&next_op1, &next_op2, || {
+                        (
+                            TransformAction::RetainBoth,
+                            len,
+                            advance_of(retain.cmp(o_retain)),
+                            composed_attrs.clone(),
+                        )
+                    });
                     match retain.cmp(o_retain) {
                         Ordering::Less => {
                             a_prime.retain(retain.n, composed_attrs.clone());
@@ -426,21 +599,39 @@ where
                         }
                     };
                 }
-                (Some(DeltaOperation::Delete(i)), Some(DeltaOperation::Delete(j))) => match i.cmp(j) {
-                    Ordering::Less => {
-                        next_op2 = Some(DeltaOperation::delete(*j - *i));
-                        next_op1 = ops1.next();
-                    }
-                    Ordering::Equal => {
-                        next_op1 = ops1.next();
-                        next_op2 = ops2.next();
-                    }
-                    Ordering::Greater => {
-                        next_op1 = Some(DeltaOperation::delete(*i - *j));
-                        next_op2 = ops2.next();
+                (Some(DeltaOperation::Delete(i)), Some(DeltaOperation::Delete(j))) => {
+                    record_step(&next_op1, &next_op2, || {
+                        (
+                            TransformAction::DeleteBoth,
+                            min(*i, *j),
+                            advance_of(i.cmp(j)),
+                            T::default(),
+                        )
+                    });
+                    match i.cmp(j) {
+                        Ordering::Less => {
+                            next_op2 = Some(DeltaOperation::delete(*j - *i));
+                            next_op1 = ops1.next();
+                        }
+                        Ordering::Equal => {
+                            next_op1 = ops1.next();
+                            next_op2 = ops2.next();
+                        }
+                        Ordering::Greater => {
+                            next_op1 = Some(DeltaOperation::delete(*i - *j));
+                            next_op2 = ops2.next();
+                        }
                     }
-                },
+                }
                 (Some(DeltaOperation::Delete(i)), Some(DeltaOperation::Retain(o_retain))) => {
+                    record_step(&next_op1, &next_op2, || {
+                        (
+                            TransformAction::DeleteLeft,
+                            min(*i, o_retain.n),
+                            advance_of(i.cmp(&o_retain.n)),
+                            T::default(),
+                        )
+                    });
                     match i.cmp(o_retain) {
                         Ordering::Less => {
                             a_prime.delete(*i);
@@ -460,6 +651,14 @@ where
                     };
                 }
                 (Some(DeltaOperation::Retain(retain)), Some(DeltaOperation::Delete(j))) => {
+                    record_step(&next_op1, &next_op2, || {
+                        (
+                            TransformAction::DeleteRight,
+                            min(retain.n, *j),
+                            advance_of(retain.n.cmp(j)),
+                            T::default(),
+                        )
+                    });
                     match retain.cmp(j) {
                         Ordering::Less => {
                             b_prime.delete(retain.n);
@@ -514,6 +713,66 @@ where
     }
 }
 
+impl<T> DeltaOperations<T>
+where
+    T: OperationAttributes,
+{
+    /// Same as `transform`, but also returns each decision the transform made, e.g. to replay a
+    /// mangled merge reported by a user. `self` is the left delta of the steps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lib_ot::core::{DeltaBuilder, TransformAction};
+    /// let left = DeltaBuilder::new().insert("a").build();
+    /// let right = DeltaBuilder::new().insert("b").build();
+    /// let (left_prime, right_prime, steps) = left.transform_with_trace(&right).unwrap();
+    /// assert_eq!(steps[0].action, TransformAction::InsertLeft);
+    /// assert_eq!(steps[1].action, TransformAction::InsertRight);
+    /// assert_eq!(left_prime.utf16_base_len, 1);
+    /// assert_eq!(right_prime.utf16_base_len, 1);
+    /// ```
+    pub fn transform_with_trace(&self, other: &Self) -> Result<(Self, Self, Vec<TransformStep>), OTError> {
+        let (result, steps) = trace_transform(|| self.transform(other));
+        let (a_prime, b_prime) = result?;
+        Ok((a_prime, b_prime, steps))
+    }
+}
+
+/// The left operation is used up if it's shorter than the right one, and the other way round.
+fn advance_of(ordering: Ordering) -> TransformAdvance {
+    match ordering {
+        Ordering::Less => TransformAdvance::Left,
+        Ordering::Equal => TransformAdvance::Both,
+        Ordering::Greater => TransformAdvance::Right,
+    }
+}
+
+/// Adds the step made of the current pair of operations to the trace if the transform is traced,
+/// see `trace_transform`. The step is only built when it is.
+fn record_step<T, F>(left: &Option<DeltaOperation<T>>, right: &Option<DeltaOperation<T>>, f: F)
+where
+    T: OperationAttributes,
+    F: FnOnce() -> (TransformAction, usize, TransformAdvance, T),
+{
+    if !is_transform_traced() {
+        return;
+    }
+    let (action, len, advance, attributes) = f();
+    push_transform_step(TransformStep {
+        left: left.as_ref().map(|op| op.to_string()),
+        right: right.as_ref().map(|op| op.to_string()),
+        action,
+        len,
+        advance,
+        attributes: if attributes.is_empty() {
+            None
+        } else {
+            Some(attributes.to_string())
+        },
+    });
+}
+
 /// Removes trailing retain operation with empty attributes, if present.
 pub fn trim<T>(delta: &mut DeltaOperations<T>)
 where
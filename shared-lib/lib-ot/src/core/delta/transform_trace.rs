@@ -0,0 +1,97 @@
+use std::cell::RefCell;
+use std::fmt;
+
+/// What the transform did with the pair of operations of a [TransformStep].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformAction {
+    /// The insert of the left delta goes first, the right delta retains it.
+    InsertLeft,
+    /// The insert of the right delta, the left delta retains it.
+    InsertRight,
+    /// Both deltas retain the text, their attributes are merged.
+    RetainBoth,
+    /// Both deltas delete the text, it's deleted only once.
+    DeleteBoth,
+    /// The left delta deletes the text that the right delta retains.
+    DeleteLeft,
+    /// The right delta deletes the text that the left delta retains.
+    DeleteRight,
+}
+
+/// Which of the operations of a [TransformStep] was used up. The rest of the other one is
+/// compared with the next operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformAdvance {
+    Left,
+    Right,
+    Both,
+}
+
+/// One decision of the transform of two deltas, see `DeltaOperations::transform_with_trace`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransformStep {
+    /// The operation of the left delta, or its rest if it was split by the previous step.
+    pub left: Option<String>,
+    /// The operation of the right delta, or its rest if it was split by the previous step.
+    pub right: Option<String>,
+    pub action: TransformAction,
+    /// The utf16 length consumed by the step.
+    pub len: usize,
+    pub advance: TransformAdvance,
+    /// The merged attributes of the retained or inserted text, None if there are none.
+    pub attributes: Option<String>,
+}
+
+impl fmt::Display for TransformStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} | {} => {:?} {} (advance {:?})",
+            self.left.as_deref().unwrap_or("-"),
+            self.right.as_deref().unwrap_or("-"),
+            self.action,
+            self.len,
+            self.advance
+        )?;
+        if let Some(attributes) = &self.attributes {
+            write!(f, " attributes: {}", attributes)?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// The steps of the transform that runs on this thread, it's Some while the transform is
+    /// traced, see `trace_transform`.
+    static TRANSFORM_TRACE: RefCell<Option<Vec<TransformStep>>> = RefCell::new(None);
+}
+
+/// Runs the `f` and returns the steps of the transforms it ran on this thread. The transform
+/// records its steps only while it's traced, so the untraced ones don't pay for it.
+pub(crate) fn trace_transform<R, F: FnOnce() -> R>(f: F) -> (R, Vec<TransformStep>) {
+    /// Stops the tracing even if the `f` panics.
+    struct TraceGuard;
+    impl Drop for TraceGuard {
+        fn drop(&mut self) {
+            TRANSFORM_TRACE.with(|trace| trace.borrow_mut().take());
+        }
+    }
+
+    TRANSFORM_TRACE.with(|trace| *trace.borrow_mut() = Some(vec![]));
+    let _guard = TraceGuard;
+    let result = f();
+    let steps = TRANSFORM_TRACE.with(|trace| trace.borrow_mut().take().unwrap_or_default());
+    (result, steps)
+}
+
+pub(crate) fn is_transform_traced() -> bool {
+    TRANSFORM_TRACE.with(|trace| trace.borrow().is_some())
+}
+
+pub(crate) fn push_transform_step(step: TransformStep) {
+    TRANSFORM_TRACE.with(|trace| {
+        if let Some(steps) = trace.borrow_mut().as_mut() {
+            steps.push(step);
+        }
+    });
+}
@@ -41,6 +41,8 @@ impl OTError {
     static_ot_error!(path_not_found, OTErrorCode::PathNotFound);
     static_ot_error!(compose, OTErrorCode::ComposeOperationFail);
     static_ot_error!(record_not_found, OTErrorCode::RecordNotFound);
+    static_ot_error!(invalid_utf8, OTErrorCode::InvalidUtf8);
+    static_ot_error!(invalid_attribute, OTErrorCode::InvalidAttribute);
 }
 
 impl fmt::Display for OTError {
@@ -57,7 +59,7 @@ impl std::convert::From<serde_json::Error> for OTError {
 
 impl std::convert::From<Utf8Error> for OTError {
     fn from(error: Utf8Error) -> Self {
-        ErrorBuilder::new(OTErrorCode::SerdeError).error(error).build()
+        ErrorBuilder::new(OTErrorCode::InvalidUtf8).error(error).build()
     }
 }
 
@@ -79,6 +81,10 @@ pub enum OTErrorCode {
     PathIsEmpty,
     InvalidPath,
     RecordNotFound,
+    /// The bytes of a serialized value aren't valid UTF-8, e.g. the stored data was corrupted.
+    InvalidUtf8,
+    /// The custom attribute can't be registered, or its value doesn't match its semantics.
+    InvalidAttribute,
 }
 
 pub struct ErrorBuilder {
@@ -0,0 +1,366 @@
+use crate::core::{AttributeHashMap, DeltaOperation, Interval};
+use crate::text_delta::{BuildInTextAttributeKey, DeltaTextOperations};
+use std::fmt::{self, Write};
+
+/// Returns true if the text with these attributes is archived, see [BuildInTextAttribute::Archived].
+///
+/// [BuildInTextAttribute::Archived]: crate::text_delta::BuildInTextAttribute::Archived
+pub fn is_archived(attributes: &AttributeHashMap) -> bool {
+    attributes
+        .get(BuildInTextAttributeKey::Archived.as_ref())
+        .and_then(|value| value.bool_value())
+        .unwrap_or(false)
+}
+
+/// Returns the document without its archived text. The newline of a line that still has
+/// visible text is kept even if it's archived, otherwise the visible part of the line would be
+/// joined with the next line. A line that has only archived text is dropped with its newline.
+pub fn without_archived(operations: &DeltaTextOperations) -> DeltaTextOperations {
+    let mut visible = DeltaTextOperations::default();
+    let mut line_has_visible_text = false;
+    let mut line_has_archived_text = false;
+    for op in operations.ops.iter() {
+        let insert = match op {
+            DeltaOperation::Insert(insert) => insert,
+            _ => continue,
+        };
+        let archived = is_archived(&insert.attributes);
+        let mut attributes = insert.attributes.clone();
+        attributes.remove_key(BuildInTextAttributeKey::Archived);
+
+        for segment in insert.s.as_str().split_inclusive('\n') {
+            let text = segment.trim_end_matches('\n');
+            if !text.is_empty() {
+                if archived {
+                    line_has_archived_text = true;
+                } else {
+                    visible.insert(text, attributes.clone());
+                    line_has_visible_text = true;
+                }
+            }
+
+            if segment.ends_with('\n') {
+                line_has_archived_text |= archived;
+                if line_has_visible_text || !line_has_archived_text {
+                    visible.insert("\n", attributes.clone());
+                }
+                line_has_visible_text = false;
+                line_has_archived_text = false;
+            }
+        }
+    }
+    visible
+}
+
+/// Returns the text of the document, the archived text is skipped unless `include_archived`.
+pub fn plain_text(operations: &DeltaTextOperations, include_archived: bool) -> String {
+    let mut text = String::new();
+    // Writing to a String never fails.
+    let _ = write_plain_text(operations, include_archived, &mut text);
+    text
+}
+
+/// Same as [plain_text], but the text is written to the `text` insert by insert. The archived
+/// lines are dropped the same way as [without_archived] does, without copying the document.
+pub fn write_plain_text<W: Write>(
+    operations: &DeltaTextOperations,
+    include_archived: bool,
+    text: &mut W,
+) -> fmt::Result {
+    let mut line_has_visible_text = false;
+    let mut line_has_archived_text = false;
+    for op in operations.ops.iter() {
+        let insert = match op {
+            DeltaOperation::Insert(insert) => insert,
+            _ => continue,
+        };
+        if include_archived {
+            text.write_str(insert.s.as_str())?;
+            continue;
+        }
+
+        let archived = is_archived(&insert.attributes);
+        for segment in insert.s.as_str().split_inclusive('\n') {
+            let segment_text = segment.trim_end_matches('\n');
+            if !segment_text.is_empty() {
+                if archived {
+                    line_has_archived_text = true;
+                } else {
+                    text.write_str(segment_text)?;
+                    line_has_visible_text = true;
+                }
+            }
+
+            if segment.ends_with('\n') {
+                line_has_archived_text |= archived;
+                if line_has_visible_text || !line_has_archived_text {
+                    text.write_char('\n')?;
+                }
+                line_has_visible_text = false;
+                line_has_archived_text = false;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the number of whitespace separated words of the document, see [plain_text]. The
+/// words are counted in place, the text isn't copied however long its lines are.
+pub fn word_count(operations: &DeltaTextOperations, include_archived: bool) -> usize {
+    let mut counter = WordCounter::new(include_archived);
+    counter.add(operations);
+    counter.count()
+}
+
+/// Counts the words of a document that is read in parts, see [word_count]. A word that goes on
+/// from one part to the next is counted once.
+#[derive(Debug, Clone, Default)]
+pub struct WordCounter {
+    include_archived: bool,
+    count: usize,
+    in_word: bool,
+}
+
+impl WordCounter {
+    pub fn new(include_archived: bool) -> Self {
+        Self {
+            include_archived,
+            count: 0,
+            in_word: false,
+        }
+    }
+
+    /// Counts the words of the next part of the document.
+    pub fn add(&mut self, operations: &DeltaTextOperations) {
+        for op in operations.ops.iter() {
+            let insert = match op {
+                DeltaOperation::Insert(insert) => insert,
+                _ => continue,
+            };
+            if !self.include_archived && is_archived(&insert.attributes) {
+                continue;
+            }
+            for c in insert.s.chars() {
+                if c.is_whitespace() {
+                    self.in_word = false;
+                } else if !self.in_word {
+                    self.in_word = true;
+                    self.count += 1;
+                }
+            }
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Returns the utf16 intervals of the document that match `query`. A match never spans archived
+/// text, so the words on both sides of an archived run aren't found as if they were adjacent.
+pub fn search_text(operations: &DeltaTextOperations, query: &str, include_archived: bool) -> Vec<Interval> {
+    let mut intervals = vec![];
+    find_matches(operations, query, include_archived, |_, _, interval| {
+        intervals.push(interval)
+    });
+    intervals
+}
+
+/// A match of [search_snippets] with the text around it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchSnippet {
+    /// The utf16 interval of the match in the document.
+    pub interval: Interval,
+    /// The match with up to `radius` chars of its line on each side. An ellipsis marks the side
+    /// that was cut before the line ends.
+    pub text: String,
+}
+
+/// Same as [search_text], but each match comes with a window of its line, so a match in a very
+/// long line doesn't return the whole line.
+pub fn search_snippets(
+    operations: &DeltaTextOperations,
+    query: &str,
+    include_archived: bool,
+    radius: usize,
+) -> Vec<SearchSnippet> {
+    let mut snippets = vec![];
+    find_matches(operations, query, include_archived, |run, byte_offset, interval| {
+        let text = snippet_text(run, byte_offset, byte_offset + query.len(), radius);
+        snippets.push(SearchSnippet { interval, text });
+    });
+    snippets
+}
+
+pub(crate) fn snippet_text(run: &str, start: usize, end: usize, radius: usize) -> String {
+    let mut from = start;
+    let mut cut_before = false;
+    for (n, (offset, c)) in run[..start].char_indices().rev().enumerate() {
+        if c == '\n' {
+            break;
+        }
+        if n == radius {
+            cut_before = true;
+            break;
+        }
+        from = offset;
+    }
+
+    let mut to = end;
+    let mut cut_after = false;
+    for (n, (offset, c)) in run[end..].char_indices().enumerate() {
+        if c == '\n' {
+            break;
+        }
+        if n == radius {
+            cut_after = true;
+            break;
+        }
+        to = end + offset + c.len_utf8();
+    }
+
+    let mut text = String::with_capacity(to - from + 6);
+    if cut_before {
+        text.push('…');
+    }
+    text.push_str(&run[from..to]);
+    if cut_after {
+        text.push('…');
+    }
+    text
+}
+
+/// Calls `f` with the run of the text, the byte offset in the run and the utf16 interval in the
+/// document of each match. The archived text splits the runs unless `include_archived`.
+fn find_matches<F>(operations: &DeltaTextOperations, query: &str, include_archived: bool, mut f: F)
+where
+    F: FnMut(&str, usize, Interval),
+{
+    if query.is_empty() {
+        return;
+    }
+
+    let query_len = query.encode_utf16().count();
+    for_each_run(operations, include_archived, |run, run_start| {
+        for (byte_offset, _) in run.match_indices(query) {
+            let start = run_start + run[..byte_offset].encode_utf16().count();
+            f(run, byte_offset, Interval::new(start, start + query_len));
+        }
+    });
+}
+
+/// Calls `f` with each run of the text and the utf16 offset of the run in the document. The
+/// archived text splits the runs unless `include_archived`, the attributes never do.
+pub(crate) fn for_each_run<F>(operations: &DeltaTextOperations, include_archived: bool, mut f: F)
+where
+    F: FnMut(&str, usize),
+{
+    let mut run = String::new();
+    let mut run_start = 0;
+    let mut offset = 0;
+    for op in operations.ops.iter() {
+        if !op.is_insert() {
+            continue;
+        }
+        let len = op.len();
+        if !include_archived && is_archived(&op.get_attributes()) {
+            f(&run, run_start);
+            run.clear();
+            run_start = offset + len;
+        } else {
+            run.push_str(op.get_data());
+        }
+        offset += len;
+    }
+    f(&run, run_start);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::DeltaOperationBuilder;
+    use crate::text_delta::BuildInTextAttribute;
+
+    fn archived() -> AttributeHashMap {
+        let mut attributes = AttributeHashMap::new();
+        attributes.insert_entry(BuildInTextAttribute::Archived(true));
+        attributes
+    }
+
+    #[test]
+    fn without_archived_keeps_the_newline_of_a_partially_archived_line() {
+        let operations = DeltaOperationBuilder::new()
+            .insert("abc")
+            .insert_with_attributes("def\n", archived())
+            .insert("ghi\n")
+            .build();
+        assert_eq!(plain_text(&operations, false), "abc\nghi\n");
+        assert_eq!(plain_text(&operations, true), "abcdef\nghi\n");
+    }
+
+    #[test]
+    fn without_archived_drops_a_whole_archived_line() {
+        let operations = DeltaOperationBuilder::new()
+            .insert("abc\n")
+            .insert_with_attributes("def\n", archived())
+            .insert("ghi\n")
+            .build();
+        assert_eq!(plain_text(&operations, false), "abc\nghi\n");
+        assert_eq!(word_count(&operations, false), 2);
+        assert_eq!(word_count(&operations, true), 3);
+    }
+
+    #[test]
+    fn without_archived_drops_a_line_with_only_archived_text() {
+        let operations = DeltaOperationBuilder::new()
+            .insert("abc\n")
+            .insert_with_attributes("def", archived())
+            .insert("\n\nghi\n")
+            .build();
+        assert_eq!(plain_text(&operations, false), "abc\n\nghi\n");
+    }
+
+    #[test]
+    fn word_counter_counts_a_word_split_between_parts_once() {
+        let operations = DeltaOperationBuilder::new()
+            .insert("ab cd")
+            .insert_with_attributes(" ef", archived())
+            .insert("gh ij\n")
+            .build();
+        let mut counter = WordCounter::new(false);
+        for start in (0..operations.utf16_target_len).step_by(4) {
+            let end = (start + 4).min(operations.utf16_target_len);
+            counter.add(&operations.slice(Interval::new(start, end)));
+        }
+        assert_eq!(counter.count(), word_count(&operations, false));
+        assert_eq!(counter.count(), 3);
+    }
+
+    #[test]
+    fn search_text_skips_archived_runs() {
+        let operations = DeltaOperationBuilder::new()
+            .insert("ab")
+            .insert_with_attributes("cd", archived())
+            .insert("ef ab\n")
+            .build();
+        assert_eq!(
+            search_text(&operations, "ab", false),
+            vec![Interval::new(0, 2), Interval::new(7, 9)]
+        );
+        assert!(search_text(&operations, "cd", false).is_empty());
+        assert!(search_text(&operations, "be", false).is_empty());
+        assert_eq!(search_text(&operations, "cd", true), vec![Interval::new(2, 4)]);
+    }
+
+    #[test]
+    fn search_snippets_window_around_the_match() {
+        let operations = DeltaOperationBuilder::new()
+            .insert("ab\n0123456789xy9876543210\ncd\n")
+            .build();
+        let snippets = search_snippets(&operations, "xy", false, 3);
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(snippets[0].interval, Interval::new(13, 15));
+        assert_eq!(snippets[0].text, "…789xy987…");
+        assert_eq!(search_snippets(&operations, "cd", false, 3)[0].text, "cd");
+    }
+}
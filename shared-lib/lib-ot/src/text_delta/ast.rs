@@ -0,0 +1,257 @@
+use crate::core::{AttributeHashMap, DeltaOperations};
+use crate::text_delta::{line_segments, BuildInTextAttributeKey, MAX_LIST_INDENT};
+use serde::Serialize;
+
+/// The document as a tree of blocks, see [DeltaOperations::to_document_ast].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DocumentAst {
+    pub blocks: Vec<BlockNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockNode {
+    Paragraph {
+        runs: Vec<InlineRun>,
+    },
+    Heading {
+        level: usize,
+        runs: Vec<InlineRun>,
+    },
+    /// The consecutive list lines of the same kind. The nested lists are the children of
+    /// their items.
+    List(ListBlock),
+    CodeBlock {
+        runs: Vec<InlineRun>,
+    },
+    BlockQuote {
+        runs: Vec<InlineRun>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListKind {
+    Bullet,
+    Ordered,
+    /// The items of a check list are either checked or unchecked, see [ListItem::checked].
+    Check,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ListBlock {
+    pub kind: ListKind,
+    pub items: Vec<ListItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ListItem {
+    pub runs: Vec<InlineRun>,
+    /// None unless the item is part of a check list.
+    pub checked: Option<bool>,
+    pub children: Vec<ListBlock>,
+}
+
+/// A piece of the text of a line with the same inline attributes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InlineRun {
+    pub text: String,
+    pub attributes: AttributeHashMap,
+}
+
+impl DeltaOperations<AttributeHashMap> {
+    /// Groups the lines of the document into blocks according to the attributes of their
+    /// newlines. The text of each line is split into runs wherever its inline attributes change.
+    ///
+    /// The list lines are nested by their [BuildInTextAttribute::Indent]. A line that is indented
+    /// more than one level deeper than the line before it is nested one level deeper only.
+    ///
+    /// [BuildInTextAttribute::Indent]: crate::text_delta::BuildInTextAttribute::Indent
+    pub fn to_document_ast(&self) -> DocumentAst {
+        let mut ast = DocumentAst::default();
+        let mut runs: Vec<InlineRun> = vec![];
+        for segment in line_segments(self) {
+            if !segment.text.is_empty() {
+                match runs.last_mut() {
+                    Some(run) if &run.attributes == segment.attributes => run.text.push_str(segment.text),
+                    _ => runs.push(InlineRun {
+                        text: segment.text.to_owned(),
+                        attributes: segment.attributes.clone(),
+                    }),
+                }
+            }
+            if segment.ends_line {
+                push_line(&mut ast.blocks, std::mem::take(&mut runs), segment.attributes);
+            }
+        }
+
+        // The document should end with a newline, the text after the last one is a paragraph.
+        if !runs.is_empty() {
+            ast.blocks.push(BlockNode::Paragraph { runs });
+        }
+        ast
+    }
+}
+
+fn push_line(blocks: &mut Vec<BlockNode>, runs: Vec<InlineRun>, attributes: &AttributeHashMap) {
+    let value = |key: BuildInTextAttributeKey| attributes.get(key.as_ref());
+    if let Some(list) = value(BuildInTextAttributeKey::List).and_then(|value| value.str_value()) {
+        let (kind, checked) = match list.as_str() {
+            "ordered" => (ListKind::Ordered, None),
+            "checked" => (ListKind::Check, Some(true)),
+            "unchecked" => (ListKind::Check, Some(false)),
+            _ => (ListKind::Bullet, None),
+        };
+        let depth = value(BuildInTextAttributeKey::Indent)
+            .and_then(|value| value.int_value())
+            .unwrap_or(0)
+            .clamp(0, MAX_LIST_INDENT as i64) as usize;
+        let item = ListItem {
+            runs,
+            checked,
+            children: vec![],
+        };
+        let item = match blocks.last_mut() {
+            Some(BlockNode::List(list)) => match push_list_item(list, kind, depth, item) {
+                None => return,
+                Some(item) => item,
+            },
+            _ => item,
+        };
+        blocks.push(BlockNode::List(ListBlock {
+            kind,
+            items: vec![item],
+        }));
+        return;
+    }
+
+    let block = if let Some(level) = value(BuildInTextAttributeKey::Header).and_then(|value| value.int_value()) {
+        BlockNode::Heading {
+            level: level.max(1) as usize,
+            runs,
+        }
+    } else if value(BuildInTextAttributeKey::CodeBlock).and_then(|value| value.bool_value()) == Some(true) {
+        BlockNode::CodeBlock { runs }
+    } else if value(BuildInTextAttributeKey::BlockQuote).and_then(|value| value.bool_value()) == Some(true) {
+        BlockNode::BlockQuote { runs }
+    } else {
+        BlockNode::Paragraph { runs }
+    };
+    blocks.push(block);
+}
+
+/// Adds the item to the list at the `depth` below it. Returns the item if it's at the top of the
+/// list but of another kind, it starts a new list then.
+fn push_list_item(list: &mut ListBlock, kind: ListKind, depth: usize, item: ListItem) -> Option<ListItem> {
+    if depth == 0 {
+        if list.kind != kind {
+            return Some(item);
+        }
+        list.items.push(item);
+        return None;
+    }
+
+    // A list always has an item, it's created with one.
+    let parent = list.items.last_mut()?;
+    let item = match parent.children.last_mut() {
+        None => item,
+        Some(child) => push_list_item(child, kind, depth - 1, item)?,
+    };
+    parent.children.push(ListBlock {
+        kind,
+        items: vec![item],
+    });
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_delta::DeltaTextOperations;
+
+    fn ast(json: &str) -> DocumentAst {
+        DeltaTextOperations::from_json(json).unwrap().to_document_ast()
+    }
+
+    fn plain(text: &str) -> InlineRun {
+        InlineRun {
+            text: text.to_owned(),
+            attributes: AttributeHashMap::default(),
+        }
+    }
+
+    fn item(text: &str, children: Vec<ListBlock>) -> ListItem {
+        ListItem {
+            runs: vec![plain(text)],
+            checked: None,
+            children,
+        }
+    }
+
+    #[test]
+    fn document_ast_headings_and_paragraphs() {
+        let ast = ast(
+            r#"[{"insert":"Title"},{"insert":"\n","attributes":{"header":1}},{"insert":"Body\n\nSub"},{"insert":"\n","attributes":{"header":2}}]"#,
+        );
+        assert_eq!(
+            ast.blocks,
+            vec![
+                BlockNode::Heading {
+                    level: 1,
+                    runs: vec![plain("Title")]
+                },
+                BlockNode::Paragraph {
+                    runs: vec![plain("Body")]
+                },
+                BlockNode::Paragraph { runs: vec![] },
+                BlockNode::Heading {
+                    level: 2,
+                    runs: vec![plain("Sub")]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn document_ast_nested_lists() {
+        let ast = ast(
+            r#"[{"insert":"a"},{"insert":"\n","attributes":{"list":"bullet"}},{"insert":"a1"},{"insert":"\n","attributes":{"list":"ordered","indent":1}},{"insert":"a2"},{"insert":"\n","attributes":{"list":"ordered","indent":1}},{"insert":"b"},{"insert":"\n","attributes":{"list":"bullet"}},{"insert":"c"},{"insert":"\n","attributes":{"list":"checked"}}]"#,
+        );
+        let nested = ListBlock {
+            kind: ListKind::Ordered,
+            items: vec![item("a1", vec![]), item("a2", vec![])],
+        };
+        assert_eq!(
+            ast.blocks,
+            vec![
+                BlockNode::List(ListBlock {
+                    kind: ListKind::Bullet,
+                    items: vec![item("a", vec![nested]), item("b", vec![])],
+                }),
+                BlockNode::List(ListBlock {
+                    kind: ListKind::Check,
+                    items: vec![ListItem {
+                        runs: vec![plain("c")],
+                        checked: Some(true),
+                        children: vec![],
+                    }],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn document_ast_inline_runs() {
+        let ast = ast(r#"[{"insert":"a"},{"insert":"bc","attributes":{"bold":true}},{"insert":"d\n"}]"#);
+        let runs = match &ast.blocks[..] {
+            [BlockNode::Paragraph { runs }] => runs.clone(),
+            blocks => panic!("{:?}", blocks),
+        };
+        assert_eq!(
+            runs.iter().map(|run| run.text.as_str()).collect::<Vec<_>>(),
+            vec!["a", "bc", "d"]
+        );
+        assert!(runs[1].attributes.contains_key("bold"));
+        assert!(runs[2].attributes.is_empty());
+    }
+}
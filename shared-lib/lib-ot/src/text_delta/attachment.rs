@@ -0,0 +1,177 @@
+use crate::core::{AttributeHashMap, DeltaOperation};
+use crate::text_delta::{BuildInTextAttributeKey, DeltaTextOperations};
+use std::collections::BTreeSet;
+
+/// Returns the id of the attachment that the text with these attributes refers to, see
+/// [BuildInTextAttribute::Attachment]. The text is only the label of the reference, the
+/// attachment is looked up by its id.
+///
+/// [BuildInTextAttribute::Attachment]: crate::text_delta::BuildInTextAttribute::Attachment
+pub fn attachment_id(attributes: &AttributeHashMap) -> Option<String> {
+    attributes
+        .get(BuildInTextAttributeKey::Attachment.as_ref())
+        .and_then(|value| value.str_value())
+        .filter(|id| !id.is_empty())
+}
+
+/// Returns the ids of the attachments that the document refers to.
+pub fn attachment_ids(operations: &DeltaTextOperations) -> BTreeSet<String> {
+    operations
+        .ops
+        .iter()
+        .filter_map(|op| match op {
+            DeltaOperation::Insert(insert) => attachment_id(&insert.attributes),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replaces the label of each attachment reference with the text returned by `resolve` for its
+/// id, e.g. the current name of the attachment. The references that `resolve` returns None for
+/// keep their label. The attributes of the references are kept.
+pub fn resolve_attachments<F>(operations: &DeltaTextOperations, resolve: F) -> DeltaTextOperations
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut resolved = DeltaTextOperations::default();
+    for op in operations.ops.iter() {
+        let insert = match op {
+            DeltaOperation::Insert(insert) => insert,
+            _ => continue,
+        };
+        match attachment_id(&insert.attributes).and_then(|id| resolve(&id)) {
+            Some(text) if !text.is_empty() => resolved.insert(&text, insert.attributes.clone()),
+            _ => resolved.insert(insert.s.as_str(), insert.attributes.clone()),
+        }
+    }
+    resolved
+}
+
+/// Points the attachment references to the ids returned by `remap`, e.g. when the attachments
+/// are copied from another database under new ids. The references that `remap` returns None for
+/// are kept. Returns None if no reference was remapped.
+pub fn remap_attachments<F>(operations: &DeltaTextOperations, remap: F) -> Option<DeltaTextOperations>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut remapped = operations.clone();
+    let mut changed = false;
+    for op in remapped.ops.iter_mut() {
+        let attributes = match op {
+            DeltaOperation::Insert(insert) => &mut insert.attributes,
+            DeltaOperation::Retain(retain) => &mut retain.attributes,
+            DeltaOperation::Delete(_) => continue,
+        };
+        if let Some(id) = attachment_id(attributes).and_then(|id| remap(&id)) {
+            attributes.insert(BuildInTextAttributeKey::Attachment.as_ref(), id);
+            changed = true;
+        }
+    }
+    if changed {
+        Some(remapped)
+    } else {
+        None
+    }
+}
+
+/// Returns the operations that move the links to the references of the attachments that `attach`
+/// returns an id for, e.g. the local files that were linked before the attachments had ids. The
+/// link of the text is removed and the attachment is set instead, the text is kept. Returns None
+/// if no link was moved.
+pub fn attach_links<F>(operations: &DeltaTextOperations, mut attach: F) -> Option<DeltaTextOperations>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut changes = DeltaTextOperations::default();
+    let mut changed = false;
+    for op in operations.ops.iter() {
+        let insert = match op {
+            DeltaOperation::Insert(insert) => insert,
+            _ => continue,
+        };
+        let attachment_id = insert
+            .attributes
+            .get(BuildInTextAttributeKey::Link.as_ref())
+            .and_then(|value| value.str_value())
+            .filter(|_| attachment_id(&insert.attributes).is_none())
+            .and_then(|link| attach(&link));
+        match attachment_id {
+            Some(attachment_id) => {
+                let mut attributes = AttributeHashMap::new();
+                attributes.remove_value(BuildInTextAttributeKey::Link.as_ref());
+                attributes.insert(BuildInTextAttributeKey::Attachment.as_ref(), attachment_id);
+                changes.retain(op.len(), attributes);
+                changed = true;
+            }
+            None => changes.retain(op.len(), AttributeHashMap::default()),
+        }
+    }
+    if changed {
+        Some(changes)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{DeltaOperationBuilder, OperationTransform};
+    use crate::text_delta::BuildInTextAttribute;
+
+    fn reference(id: &str) -> AttributeHashMap {
+        AttributeHashMap::from(BuildInTextAttribute::Attachment(id.to_owned()))
+    }
+
+    #[test]
+    fn resolve_attachments_by_id() {
+        let operations = DeltaOperationBuilder::new()
+            .insert("see ")
+            .insert_with_attributes("old.png", reference("a1"))
+            .insert(" and ")
+            .insert_with_attributes("gone.png", reference("a2"))
+            .insert("\n")
+            .build();
+        assert_eq!(
+            attachment_ids(&operations).into_iter().collect::<Vec<_>>(),
+            vec!["a1".to_owned(), "a2".to_owned()]
+        );
+
+        let resolved = resolve_attachments(&operations, |id| match id {
+            "a1" => Some("new.png".to_owned()),
+            _ => None,
+        });
+        assert_eq!(resolved.content().unwrap(), "see new.png and gone.png\n");
+        assert_eq!(attachment_ids(&resolved), attachment_ids(&operations));
+    }
+
+    #[test]
+    fn attach_local_links() {
+        let operations = DeltaOperationBuilder::new()
+            .insert("see ")
+            .insert_with_attributes(
+                "photo.png",
+                AttributeHashMap::from(BuildInTextAttribute::Link("/images/photo.png")),
+            )
+            .insert(" at ")
+            .insert_with_attributes(
+                "site",
+                AttributeHashMap::from(BuildInTextAttribute::Link("https://appflowy.io")),
+            )
+            .insert("\n")
+            .build();
+        let changes = attach_links(&operations, |link| match link {
+            "/images/photo.png" => Some("a1".to_owned()),
+            _ => None,
+        })
+        .unwrap();
+
+        let attached = operations.compose(&changes).unwrap();
+        assert_eq!(attached.content().unwrap(), "see photo.png at site\n");
+        assert_eq!(
+            attachment_ids(&attached).into_iter().collect::<Vec<_>>(),
+            vec!["a1".to_owned()]
+        );
+        assert!(attach_links(&attached, |_| None).is_none());
+    }
+}
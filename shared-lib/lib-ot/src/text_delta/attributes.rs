@@ -7,6 +7,9 @@ use std::str::FromStr;
 use std::{collections::HashSet, iter::FromIterator};
 use strum_macros::{AsRefStr, Display, EnumString};
 
+/// The maximum nesting level of a list line. See [BuildInTextAttribute::Indent].
+pub const MAX_LIST_INDENT: usize = 6;
+
 #[inline(always)]
 pub fn empty_attributes() -> AttributeHashMap {
     AttributeHashMap::default()
@@ -32,6 +35,8 @@ impl BuildInTextAttribute {
     inline_attribute_entry!(Size, usize);
     inline_attribute_entry!(Background, String);
     inline_attribute_entry!(InlineCode, bool);
+    inline_attribute_entry!(Archived, bool);
+    inline_attribute_entry!(Attachment, String);
 
     inline_attribute_entry!(Header, usize);
     inline_attribute_entry!(Indent, usize);
@@ -89,6 +94,10 @@ pub enum BuildInTextAttributeKey {
     Height,
     #[serde(rename = "header")]
     Header,
+    #[serde(rename = "archived")]
+    Archived,
+    #[serde(rename = "attachment")]
+    Attachment,
 }
 
 pub fn is_block(k: &AttributeKey) -> bool {
@@ -127,6 +136,8 @@ lazy_static! {
         BuildInTextAttributeKey::Size,
         BuildInTextAttributeKey::Background,
         BuildInTextAttributeKey::InlineCode,
+        BuildInTextAttributeKey::Archived,
+        BuildInTextAttributeKey::Attachment,
     ]);
     static ref INGORE_KEYS: HashSet<BuildInTextAttributeKey> =
         HashSet::from_iter(vec![BuildInTextAttributeKey::Width, BuildInTextAttributeKey::Height,]);
@@ -0,0 +1,170 @@
+use crate::core::{AttributeEntry, AttributeHashMap, AttributeKey, AttributeValue, DeltaOperation, ValueType};
+use crate::errors::OTError;
+use crate::text_delta::{BuildInTextAttributeKey, DeltaTextOperations};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Decides which values of a custom attribute are accepted. Like the built-in attributes, the
+/// later value of the attribute replaces the earlier one when they compose.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CustomAttributeSemantics {
+    /// `true` applies the attribute and `false` removes it.
+    Boolean,
+    /// Any string, the latest one wins. The empty string removes the attribute.
+    String,
+    /// An integer or a float.
+    Numeric,
+}
+
+/// The extra inline attributes of the documents, registered by the embedder with their
+/// [CustomAttributeSemantics]. The clones share the registered keys, each owner of the documents
+/// creates its own registry, so the keys of one don't leak into the documents of another.
+#[derive(Clone, Debug, Default)]
+pub struct CustomAttributes {
+    keys: Arc<DashMap<AttributeKey, CustomAttributeSemantics>>,
+}
+
+impl CustomAttributes {
+    /// Registers `key` as an extra inline attribute, so it can be formatted like the built-in
+    /// ones. Registering the key again with the same semantics does nothing. The keys of the
+    /// built-in attributes can't be registered.
+    pub fn register(&self, key: &str, semantics: CustomAttributeSemantics) -> Result<(), OTError> {
+        if key.is_empty() || BuildInTextAttributeKey::from_str(key).is_ok() {
+            return Err(OTError::invalid_attribute().context(format!("{} can't be a custom attribute", key)));
+        }
+        match self.keys.entry(key.to_owned()) {
+            Entry::Occupied(entry) if *entry.get() != semantics => {
+                Err(OTError::invalid_attribute().context(format!("{} is already registered as {:?}", key, entry.get())))
+            }
+            Entry::Occupied(_) => Ok(()),
+            Entry::Vacant(entry) => {
+                entry.insert(semantics);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the semantics of the registered custom attribute, None for the other keys.
+    pub fn semantics(&self, key: &str) -> Option<CustomAttributeSemantics> {
+        self.keys.get(key).map(|semantics| *semantics)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Checks the value of the custom attribute against its semantics, the `false` of a boolean
+    /// and the empty string become the removal of the attribute. The other attributes are
+    /// returned as they are.
+    pub fn sanitize(&self, mut entry: AttributeEntry) -> Result<AttributeEntry, OTError> {
+        let semantics = match self.semantics(&entry.key) {
+            None => return Ok(entry),
+            Some(semantics) => semantics,
+        };
+        match check_value(semantics, &entry.value) {
+            Some(value) => {
+                entry.value = value;
+                Ok(entry)
+            }
+            None => Err(OTError::invalid_attribute().context(format!(
+                "{} expects a {:?} value, found {:?}",
+                entry.key, semantics, entry.value
+            ))),
+        }
+    }
+
+    /// Returns the operations with the values of the custom attributes checked like
+    /// [CustomAttributes::sanitize], e.g. the operations of the remote revisions. An invalid value
+    /// is dropped, so the text keeps the value it had. The removals are dropped from the inserts,
+    /// the inserted text doesn't have the attribute anyway.
+    pub fn sanitize_operations(&self, operations: &DeltaTextOperations) -> DeltaTextOperations {
+        let mut operations = operations.clone();
+        if self.is_empty() {
+            return operations;
+        }
+        for operation in operations.ops.iter_mut() {
+            let (attributes, is_insert) = match operation {
+                DeltaOperation::Delete(_) => continue,
+                DeltaOperation::Retain(retain) => (&mut retain.attributes, false),
+                DeltaOperation::Insert(insert) => (&mut insert.attributes, true),
+            };
+            self.sanitize_attributes(attributes, is_insert);
+        }
+        operations
+    }
+
+    fn sanitize_attributes(&self, attributes: &mut AttributeHashMap, is_insert: bool) {
+        let keys = attributes
+            .keys()
+            .filter(|key| self.semantics(key).is_some())
+            .cloned()
+            .collect::<Vec<AttributeKey>>();
+        for key in keys {
+            let semantics = self.semantics(&key).unwrap();
+            let value = attributes.get(&key).cloned().unwrap_or_else(AttributeValue::none);
+            match check_value(semantics, &value) {
+                Some(value) if value.value.is_none() && is_insert => attributes.remove_key(&key),
+                Some(value) => attributes.insert(key, value),
+                None => {
+                    tracing::warn!("Drop the invalid value {:?} of the custom attribute {}", value, key);
+                    attributes.remove_key(&key);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the value that is applied for the value of an attribute with the `semantics`, None if
+/// the value doesn't match them.
+fn check_value(semantics: CustomAttributeSemantics, value: &AttributeValue) -> Option<AttributeValue> {
+    if value.value.is_none() {
+        return Some(AttributeValue::none());
+    }
+    match (semantics, &value.ty) {
+        (CustomAttributeSemantics::Boolean, Some(ValueType::BoolType)) => match value.bool_value() {
+            Some(false) => Some(AttributeValue::none()),
+            _ => Some(value.clone()),
+        },
+        (CustomAttributeSemantics::String, Some(ValueType::StrType)) => match value.value.as_deref() {
+            Some("") => Some(AttributeValue::none()),
+            _ => Some(value.clone()),
+        },
+        (CustomAttributeSemantics::Numeric, Some(ValueType::IntType | ValueType::FloatType)) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::DeltaOperationBuilder;
+
+    #[test]
+    fn custom_attributes_are_registered_per_registry() {
+        let custom_attributes = CustomAttributes::default();
+        custom_attributes
+            .register("comment_id", CustomAttributeSemantics::String)
+            .unwrap();
+        assert!(custom_attributes.clone().semantics("comment_id").is_some());
+        assert!(CustomAttributes::default().semantics("comment_id").is_none());
+    }
+
+    #[test]
+    fn sanitize_operations_drops_invalid_values() {
+        let custom_attributes = CustomAttributes::default();
+        custom_attributes
+            .register("reviewed", CustomAttributeSemantics::Boolean)
+            .unwrap();
+        let operations = DeltaOperationBuilder::new()
+            .insert_with_attributes("a", AttributeHashMap::from(AttributeEntry::new("reviewed", "yes")))
+            .insert_with_attributes("b", AttributeHashMap::from(AttributeEntry::new("reviewed", false)))
+            .insert_with_attributes("c\n", AttributeHashMap::from(AttributeEntry::new("reviewed", true)))
+            .build();
+        assert_eq!(
+            custom_attributes.sanitize_operations(&operations).json_str(),
+            r#"[{"insert":"a"},{"insert":"b"},{"insert":"c\n","attributes":{"reviewed":true}}]"#
+        );
+    }
+}
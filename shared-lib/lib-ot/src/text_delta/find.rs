@@ -0,0 +1,221 @@
+use crate::core::{AttributeHashMap, DeltaOperation, Interval};
+use crate::text_delta::{for_each_run, snippet_text, DeltaTextOperations, SearchSnippet};
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    pub case_sensitive: bool,
+    /// Only matches the pattern where it isn't preceded or followed by a letter, a digit or `_`.
+    pub whole_word: bool,
+}
+
+/// Returns the utf16 intervals of the visible text that match the `pattern`, from the start of
+/// the document and without overlapping. Unlike [search_text], the case can be ignored and the
+/// pattern can be matched as a whole word only. A match may span text with different
+/// attributes, but never archived text.
+///
+/// [search_text]: crate::text_delta::search_text
+pub fn find_text(operations: &DeltaTextOperations, pattern: &str, options: FindOptions) -> Vec<Interval> {
+    let mut intervals = vec![];
+    find_pattern(operations, pattern, options, |_, _, interval| intervals.push(interval));
+    intervals
+}
+
+/// Same as [find_text], but each match comes with up to `radius` chars of its line on each side.
+pub fn find_snippets(
+    operations: &DeltaTextOperations,
+    pattern: &str,
+    options: FindOptions,
+    radius: usize,
+) -> Vec<SearchSnippet> {
+    let mut snippets = vec![];
+    find_pattern(operations, pattern, options, |run, bytes, interval| {
+        let text = snippet_text(run, bytes.start, bytes.end, radius);
+        snippets.push(SearchSnippet { interval, text });
+    });
+    snippets
+}
+
+/// Returns the operations that replace each of the `matches` of the document with the
+/// `replacement`, to be composed with the document. The replacement takes the attributes of
+/// the first char of its match, so replacing a bold word that ends in plain text keeps it bold.
+/// The matches must be sorted and must not overlap, e.g. the ones of [find_text].
+pub fn replace_matches(
+    operations: &DeltaTextOperations,
+    matches: &[Interval],
+    replacement: &str,
+) -> DeltaTextOperations {
+    let mut replaced = DeltaTextOperations::default();
+    let mut offset = 0;
+    for interval in matches {
+        debug_assert!(interval.start >= offset && interval.end <= operations.utf16_target_len);
+        replaced.retain(interval.start - offset, AttributeHashMap::default());
+        replaced.insert(replacement, attributes_at(operations, interval.start));
+        replaced.delete(interval.size());
+        offset = interval.end;
+    }
+    replaced.retain(
+        operations.utf16_target_len.saturating_sub(offset),
+        AttributeHashMap::default(),
+    );
+    replaced
+}
+
+/// Returns the attributes of the char at the utf16 `index` of the document.
+fn attributes_at(operations: &DeltaTextOperations, index: usize) -> AttributeHashMap {
+    let mut offset = 0;
+    for op in operations.ops.iter() {
+        if let DeltaOperation::Insert(insert) = op {
+            offset += op.len();
+            if index < offset {
+                return insert.attributes.clone();
+            }
+        }
+    }
+    AttributeHashMap::default()
+}
+
+/// Calls `f` with the run of the text, the byte range in the run and the utf16 interval in the
+/// document of each match.
+fn find_pattern<F>(operations: &DeltaTextOperations, pattern: &str, options: FindOptions, mut f: F)
+where
+    F: FnMut(&str, Range<usize>, Interval),
+{
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    if pattern.is_empty() {
+        return;
+    }
+
+    for_each_run(operations, false, |run, run_start| {
+        let chars = run.char_indices().collect::<Vec<(usize, char)>>();
+        let mut utf16_offsets = Vec::with_capacity(chars.len() + 1);
+        let mut utf16_offset = run_start;
+        for (_, c) in chars.iter() {
+            utf16_offsets.push(utf16_offset);
+            utf16_offset += c.len_utf16();
+        }
+        utf16_offsets.push(utf16_offset);
+
+        let mut start = 0;
+        while start + pattern.len() <= chars.len() {
+            let end = start + pattern.len();
+            let is_match = chars[start..end]
+                .iter()
+                .zip(pattern.iter())
+                .all(|((_, c), p)| chars_eq(*c, *p, options.case_sensitive));
+            if is_match && (!options.whole_word || is_whole_word(&chars, start, end)) {
+                let byte_end = chars.get(end).map(|(byte_offset, _)| *byte_offset).unwrap_or(run.len());
+                f(
+                    run,
+                    chars[start].0..byte_end,
+                    Interval::new(utf16_offsets[start], utf16_offsets[end]),
+                );
+                start = end;
+            } else {
+                start += 1;
+            }
+        }
+    });
+}
+
+fn chars_eq(c: char, p: char, case_sensitive: bool) -> bool {
+    c == p || (!case_sensitive && c.to_lowercase().eq(p.to_lowercase()))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_whole_word(chars: &[(usize, char)], start: usize, end: usize) -> bool {
+    let before = start.checked_sub(1).map(|index| chars[index].1);
+    let after = chars.get(end).map(|(_, c)| *c);
+    !before.map_or(false, is_word_char) && !after.map_or(false, is_word_char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{DeltaOperationBuilder, OperationTransform};
+    use crate::text_delta::{plain_text, BuildInTextAttribute};
+
+    fn bold() -> AttributeHashMap {
+        AttributeHashMap::from(BuildInTextAttribute::Bold(true))
+    }
+
+    #[test]
+    fn find_text_options_test() {
+        let operations = DeltaOperationBuilder::new().insert("Cat cat concat cat_1\n").build();
+        // The case is ignored by default.
+        assert_eq!(
+            find_text(&operations, "cat", FindOptions::default()),
+            vec![
+                Interval::new(0, 3),
+                Interval::new(4, 7),
+                Interval::new(11, 14),
+                Interval::new(15, 18)
+            ]
+        );
+
+        let options = FindOptions {
+            case_sensitive: true,
+            whole_word: false,
+        };
+        assert_eq!(
+            find_text(&operations, "cat", options),
+            vec![Interval::new(4, 7), Interval::new(11, 14), Interval::new(15, 18)]
+        );
+
+        let options = FindOptions {
+            case_sensitive: false,
+            whole_word: true,
+        };
+        assert_eq!(
+            find_text(&operations, "CAT", options),
+            vec![Interval::new(0, 3), Interval::new(4, 7)]
+        );
+    }
+
+    #[test]
+    fn find_text_doesnt_overlap_test() {
+        let operations = DeltaOperationBuilder::new().insert("aaaa\n").build();
+        assert_eq!(
+            find_text(&operations, "aa", FindOptions::default()),
+            vec![Interval::new(0, 2), Interval::new(2, 4)]
+        );
+    }
+
+    #[test]
+    fn replace_matches_across_attributes_test() {
+        let operations = DeltaOperationBuilder::new()
+            .insert("a ")
+            .insert_with_attributes("fo", bold())
+            .insert("o b foo\n")
+            .build();
+        let matches = find_text(&operations, "foo", FindOptions::default());
+        assert_eq!(matches, vec![Interval::new(2, 5), Interval::new(8, 11)]);
+
+        let replaced = operations
+            .compose(&replace_matches(&operations, &matches, "bar"))
+            .unwrap();
+        assert_eq!(plain_text(&replaced, true), "a bar b bar\n");
+        let expected = DeltaOperationBuilder::new()
+            .insert("a ")
+            .insert_with_attributes("bar", bold())
+            .insert(" b bar\n")
+            .build();
+        assert_eq!(replaced, expected);
+    }
+
+    #[test]
+    fn find_snippets_test() {
+        let operations = DeltaOperationBuilder::new().insert("one Two three two\n").build();
+        let options = FindOptions {
+            case_sensitive: false,
+            whole_word: false,
+        };
+        let snippets = find_snippets(&operations, "two", options, 2);
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets[0].text, "…e Two t…");
+        assert_eq!(snippets[1].text, "…e two");
+    }
+}
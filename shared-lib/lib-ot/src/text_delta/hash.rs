@@ -0,0 +1,89 @@
+use crate::core::{AttributeHashMap, DeltaOperation};
+use crate::text_delta::DeltaTextOperations;
+use std::collections::BTreeMap;
+
+/// Returns the md5 of the canonical form of the document. The canonical form is the normalized
+/// document, see [DeltaOperations::normalized], with the attributes of each operation sorted
+/// by their keys. The documents with the same text and formatting have the same hash however
+/// their revisions built them, and the hash doesn't depend on the json of the attributes, so
+/// it's the same in every process.
+///
+/// [DeltaOperations::normalized]: crate::core::DeltaOperations::normalized
+pub fn content_hash(operations: &DeltaTextOperations) -> String {
+    let mut context = md5::Context::new();
+    for op in operations.normalized().ops.iter() {
+        match op {
+            DeltaOperation::Insert(insert) => {
+                write_field(&mut context, "insert");
+                write_field(&mut context, insert.s.as_str());
+                write_attributes(&mut context, &insert.attributes);
+            }
+            DeltaOperation::Retain(retain) => {
+                write_field(&mut context, "retain");
+                write_field(&mut context, &retain.n.to_string());
+                write_attributes(&mut context, &retain.attributes);
+            }
+            DeltaOperation::Delete(n) => {
+                write_field(&mut context, "delete");
+                write_field(&mut context, &n.to_string());
+            }
+        }
+    }
+    format!("{:x}", context.compute())
+}
+
+/// Each field is prefixed with its length, so the adjacent fields can't be read as others.
+fn write_field(context: &mut md5::Context, s: &str) {
+    context.consume((s.len() as u64).to_le_bytes());
+    context.consume(s.as_bytes());
+}
+
+fn write_attributes(context: &mut md5::Context, attributes: &AttributeHashMap) {
+    let sorted = attributes.iter().collect::<BTreeMap<_, _>>();
+    context.consume((sorted.len() as u64).to_le_bytes());
+    for (key, value) in sorted {
+        write_field(context, key);
+        let ty = value.ty.clone().map(|ty| ty as u8).unwrap_or(u8::MAX);
+        context.consume([ty]);
+        write_field(context, value.value.as_deref().unwrap_or_default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{AttributeBuilder, DeltaOperationBuilder};
+
+    #[test]
+    fn content_hash_ignores_attribute_order_test() {
+        let left = AttributeBuilder::new()
+            .insert("bold", true)
+            .insert("italic", true)
+            .build();
+        let right = AttributeBuilder::new()
+            .insert("italic", true)
+            .insert("bold", true)
+            .build();
+        assert_ne!(left.to_json().unwrap(), right.to_json().unwrap());
+
+        let left = DeltaOperationBuilder::new().insert_with_attributes("abc", left).build();
+        let right = DeltaOperationBuilder::new()
+            .insert_with_attributes("ab", right.clone())
+            .insert_with_attributes("c", right)
+            .build();
+        assert_eq!(content_hash(&left), content_hash(&right));
+    }
+
+    #[test]
+    fn content_hash_formatting_changes_hash_test() {
+        let plain = DeltaOperationBuilder::new().insert("abc").build();
+        let bold = DeltaOperationBuilder::new()
+            .insert_with_attributes("abc", AttributeBuilder::new().insert("bold", true).build())
+            .build();
+        let bold_string = DeltaOperationBuilder::new()
+            .insert_with_attributes("abc", AttributeBuilder::new().insert("bold", "true").build())
+            .build();
+        assert_ne!(content_hash(&plain), content_hash(&bold));
+        assert_ne!(content_hash(&bold), content_hash(&bold_string));
+    }
+}
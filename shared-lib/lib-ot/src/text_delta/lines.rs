@@ -0,0 +1,118 @@
+use crate::core::{AttributeHashMap, DeltaOperation};
+use crate::text_delta::DeltaTextOperations;
+use std::slice::Iter;
+
+/// The lines longer than this many bytes are split into several segments, e.g. a minified JSON
+/// pasted as a single line, so the consumers never need to hold the whole line.
+pub const MAX_LINE_SEGMENT_LEN: usize = 64 * 1024;
+
+/// A piece of a line of the document, borrowed from the insert it's part of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineSegment<'a> {
+    /// The text of the segment without the newline.
+    pub text: &'a str,
+    /// The attributes of the insert, they're the attributes of the line's newline if the segment
+    /// ends the line.
+    pub attributes: &'a AttributeHashMap,
+    pub ends_line: bool,
+}
+
+/// Iterates over the lines of the document as segments of at most `max_len` bytes. A line is
+/// split wherever one of its inserts ends, and the segment that ends a line may be empty.
+#[derive(Clone)]
+pub struct LineSegments<'a> {
+    ops: Iter<'a, DeltaOperation<AttributeHashMap>>,
+    rest: &'a str,
+    attributes: Option<&'a AttributeHashMap>,
+    max_len: usize,
+}
+
+/// Returns the segments of the lines of the document, see [LineSegments].
+pub fn line_segments(operations: &DeltaTextOperations) -> LineSegments<'_> {
+    line_segments_with_max_len(operations, MAX_LINE_SEGMENT_LEN)
+}
+
+pub fn line_segments_with_max_len(operations: &DeltaTextOperations, max_len: usize) -> LineSegments<'_> {
+    LineSegments {
+        ops: operations.ops.iter(),
+        rest: "",
+        attributes: None,
+        max_len: max_len.max(4),
+    }
+}
+
+impl<'a> Iterator for LineSegments<'a> {
+    type Item = LineSegment<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.rest.is_empty() {
+            match self.ops.next()? {
+                DeltaOperation::Insert(insert) => {
+                    self.rest = insert.s.as_str();
+                    self.attributes = Some(&insert.attributes);
+                }
+                _ => continue,
+            }
+        }
+        let attributes = self.attributes?;
+
+        // Only the first `max_len` bytes are scanned, the newline of a long line is found by
+        // the segment that reaches it.
+        let scan_len = self.rest.len().min(self.max_len + 1);
+        let segment = match self.rest.as_bytes()[..scan_len].iter().position(|b| *b == b'\n') {
+            Some(newline) if newline <= self.max_len => {
+                let text = &self.rest[..newline];
+                self.rest = &self.rest[newline + 1..];
+                LineSegment {
+                    text,
+                    attributes,
+                    ends_line: true,
+                }
+            }
+            _ => {
+                let mut end = self.rest.len().min(self.max_len);
+                while !self.rest.is_char_boundary(end) {
+                    end -= 1;
+                }
+                let text = &self.rest[..end];
+                self.rest = &self.rest[end..];
+                LineSegment {
+                    text,
+                    attributes,
+                    ends_line: false,
+                }
+            }
+        };
+        Some(segment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::DeltaOperationBuilder;
+
+    fn texts(operations: &DeltaTextOperations, max_len: usize) -> Vec<(&str, bool)> {
+        line_segments_with_max_len(operations, max_len)
+            .map(|segment| (segment.text, segment.ends_line))
+            .collect()
+    }
+
+    #[test]
+    fn line_segments_split_lines_at_inserts_and_newlines() {
+        let operations = DeltaOperationBuilder::new().insert("ab\ncd").insert("ef\n").build();
+        assert_eq!(
+            texts(&operations, MAX_LINE_SEGMENT_LEN),
+            vec![("ab", true), ("cd", false), ("ef", true)]
+        );
+    }
+
+    #[test]
+    fn line_segments_split_long_lines_at_char_boundaries() {
+        let operations = DeltaOperationBuilder::new().insert("abcédefgh\nk\n").build();
+        assert_eq!(
+            texts(&operations, 4),
+            vec![("abc", false), ("éde", false), ("fgh", true), ("k", true)]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use crate::core::{AttributeHashMap, DeltaOperation};
+use crate::text_delta::{BuildInTextAttributeKey, DeltaTextOperations};
+
+/// The links to other documents of the workspace are saved as this prefix followed by the id of
+/// the document, see [BuildInTextAttribute::Link].
+///
+/// [BuildInTextAttribute::Link]: crate::text_delta::BuildInTextAttribute::Link
+pub const DOCUMENT_LINK_PREFIX: &str = "appflowy://document/";
+
+pub fn document_link(doc_id: &str) -> String {
+    format!("{}{}", DOCUMENT_LINK_PREFIX, doc_id)
+}
+
+/// Returns the id of the document that the text with these attributes links to, None if it
+/// isn't a link or links to something else than a document.
+pub fn linked_doc_id(attributes: &AttributeHashMap) -> Option<String> {
+    attributes
+        .get(BuildInTextAttributeKey::Link.as_ref())
+        .and_then(|value| value.str_value())
+        .and_then(|link| link.strip_prefix(DOCUMENT_LINK_PREFIX).map(|doc_id| doc_id.to_owned()))
+        .filter(|doc_id| !doc_id.is_empty())
+}
+
+/// Points the document links to the ids returned by `remap` for the linked ids. The links that
+/// `remap` returns None for are kept. Both the inserts and the retains that format the text as a
+/// link are rewritten, so the operations of a revision can be remapped too. Returns None if no
+/// link was remapped.
+pub fn remap_document_links<F>(operations: &DeltaTextOperations, remap: F) -> Option<DeltaTextOperations>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut remapped = operations.clone();
+    let mut changed = false;
+    for op in remapped.ops.iter_mut() {
+        let attributes = match op {
+            DeltaOperation::Insert(insert) => &mut insert.attributes,
+            DeltaOperation::Retain(retain) => &mut retain.attributes,
+            DeltaOperation::Delete(_) => continue,
+        };
+        if let Some(doc_id) = linked_doc_id(attributes).and_then(|doc_id| remap(&doc_id)) {
+            attributes.insert(BuildInTextAttributeKey::Link.as_ref(), document_link(&doc_id));
+            changed = true;
+        }
+    }
+    if changed {
+        Some(remapped)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::DeltaOperationBuilder;
+    use crate::text_delta::BuildInTextAttribute;
+
+    fn link(url: &str) -> AttributeHashMap {
+        AttributeHashMap::from(BuildInTextAttribute::Link(url.to_owned()))
+    }
+
+    #[test]
+    fn remap_document_links_test() {
+        let operations = DeltaOperationBuilder::new()
+            .insert_with_attributes("a", link(&document_link("d1")))
+            .insert_with_attributes("b", link("https://appflowy.io"))
+            .retain_with_attributes(1, link(&document_link("d2")))
+            .build();
+        let remapped = remap_document_links(&operations, |doc_id| match doc_id {
+            "d1" => Some("n1".to_owned()),
+            "d2" => Some("n2".to_owned()),
+            _ => None,
+        })
+        .unwrap();
+        let links = remapped
+            .ops
+            .iter()
+            .map(|op| op.get_attributes().get("link").and_then(|value| value.str_value()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            links,
+            vec![
+                Some(document_link("n1")),
+                Some("https://appflowy.io".to_owned()),
+                Some(document_link("n2")),
+            ]
+        );
+        assert!(remap_document_links(&operations, |_| None).is_none());
+    }
+}
@@ -1,8 +1,28 @@
+mod archived;
+mod ast;
+mod attachment;
 mod attributes;
+mod custom_attributes;
 
 #[macro_use]
 mod macros;
 mod delta;
+mod find;
+mod hash;
+mod lines;
+mod link;
+mod paste;
+mod script;
 
+pub use archived::*;
+pub use ast::*;
+pub use attachment::*;
 pub use attributes::*;
+pub use custom_attributes::*;
 pub use delta::*;
+pub use find::*;
+pub use hash::*;
+pub use lines::*;
+pub use link::*;
+pub use paste::*;
+pub use script::*;
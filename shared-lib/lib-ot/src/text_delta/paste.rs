@@ -0,0 +1,56 @@
+use crate::core::{AttributeHashMap, DeltaOperation};
+use crate::text_delta::{is_block, DeltaTextOperations};
+
+/// Drops the inline attributes of the operations, e.g. for pasting without formatting. The
+/// block attributes of the inserted line breaks are kept, so the pasted lines keep their headers
+/// and lists. The attachment references and the links become their label as plain text, and
+/// the retains that would format the existing text only move over it.
+pub fn strip_all_attributes(operations: &DeltaTextOperations) -> DeltaTextOperations {
+    let mut stripped = DeltaTextOperations::default();
+    for op in operations.ops.iter() {
+        match op {
+            DeltaOperation::Insert(insert) => {
+                let mut attributes = insert.attributes.clone();
+                attributes.retain(|key, _| is_block(key));
+                stripped.insert(insert.s.as_str(), attributes)
+            }
+            DeltaOperation::Retain(retain) => stripped.retain(retain.n, AttributeHashMap::default()),
+            DeltaOperation::Delete(n) => stripped.delete(*n),
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{AttributeBuilder, DeltaOperationBuilder};
+    use crate::text_delta::{BuildInTextAttribute, DeltaTextOperationBuilder};
+
+    #[test]
+    fn strip_all_attributes_test() {
+        let operations = DeltaOperationBuilder::new()
+            .retain_with_attributes(2, AttributeHashMap::from(BuildInTextAttribute::Bold(true)))
+            .insert_with_attributes("ab", AttributeHashMap::from(BuildInTextAttribute::Italic(true)))
+            .insert_with_attributes(
+                "photo",
+                AttributeHashMap::from(BuildInTextAttribute::Attachment("a1".to_owned())),
+            )
+            .insert_with_attributes(
+                "\n",
+                AttributeBuilder::new()
+                    .insert_entry(BuildInTextAttribute::Header(1))
+                    .insert_entry(BuildInTextAttribute::Bold(true))
+                    .build(),
+            )
+            .delete(1)
+            .build();
+        let expected = DeltaTextOperationBuilder::new()
+            .retain(2)
+            .insert("abphoto")
+            .insert_with_attributes("\n", AttributeHashMap::from(BuildInTextAttribute::Header(1)))
+            .delete(1)
+            .build();
+        assert_eq!(strip_all_attributes(&operations), expected);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::core::DeltaOperation;
+use crate::text_delta::DeltaTextOperations;
+
+/// The number of chars sampled from the beginning of the document by [detect_script].
+pub const SCRIPT_SAMPLE_LEN: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    Latin,
+    Cjk,
+    Cyrillic,
+    Arabic,
+    Hebrew,
+    Greek,
+    Devanagari,
+    Thai,
+    Unknown,
+}
+
+impl Script {
+    pub fn from_char(c: char) -> Option<Script> {
+        let script = match c as u32 {
+            0x0041..=0x005A | 0x0061..=0x007A | 0x00C0..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+            0x0370..=0x03FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x0400..=0x052F => Script::Cyrillic,
+            0x0590..=0x05FF => Script::Hebrew,
+            0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Arabic,
+            0x0900..=0x097F => Script::Devanagari,
+            0x0E00..=0x0E7F => Script::Thai,
+            0x1100..=0x11FF
+            | 0x3040..=0x30FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xAC00..=0xD7AF
+            | 0xF900..=0xFAFF
+            | 0x20000..=0x2A6DF => Script::Cjk,
+            _ => return None,
+        };
+        Some(script)
+    }
+}
+
+/// Returns the dominant [Script] of the text that the operations insert. Only the first
+/// [SCRIPT_SAMPLE_LEN] chars are sampled, and the chars that don't belong to any script, such as
+/// digits, punctuation and whitespace, are ignored. Returns [Script::Unknown] if no char is
+/// classified.
+///
+/// # Examples
+///
+/// ```
+///  use lib_ot::text_delta::{detect_script, DeltaTextOperationBuilder, Script};
+///  let operations = DeltaTextOperationBuilder::new().insert("Привет, мир!\n").build();
+///  assert_eq!(detect_script(&operations), Script::Cyrillic);
+/// ```
+pub fn detect_script(operations: &DeltaTextOperations) -> Script {
+    let mut counts: Vec<(Script, usize)> = vec![];
+    operations
+        .ops
+        .iter()
+        .flat_map(|operation| match operation {
+            DeltaOperation::Insert(insert) => Some(insert.s.chars()),
+            _ => None,
+        })
+        .flatten()
+        .take(SCRIPT_SAMPLE_LEN)
+        .flat_map(Script::from_char)
+        .for_each(|script| match counts.iter_mut().find(|(s, _)| *s == script) {
+            None => counts.push((script, 1)),
+            Some((_, count)) => *count += 1,
+        });
+
+    // The script that appears first wins if the counts are equal.
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(script, _)| script)
+        .unwrap_or(Script::Unknown)
+}